@@ -194,17 +194,31 @@ impl DefinedFunction {
             }
         }
 
+        if let Some(ref mut tracer) = env.global_context.tracer {
+            let cost = env.global_context.cost_track.get_total();
+            let contract = &env.contract_context.contract_identifier;
+            tracer.report_enter(contract, &self.name, args, &cost);
+        }
+
         let result = eval(&self.body, env, &context);
 
         // if the error wasn't actually an error, but a function return,
         //    pull that out and return it.
-        match result {
+        let result = match result {
             Ok(r) => Ok(r),
             Err(e) => match e {
                 Error::ShortReturn(v) => Ok(v.into()),
                 _ => Err(e),
             },
+        };
+
+        if let Some(ref mut tracer) = env.global_context.tracer {
+            let cost = env.global_context.cost_track.get_total();
+            let contract = &env.contract_context.contract_identifier;
+            tracer.report_exit(contract, &self.name, &result, &cost);
         }
+
+        result
     }
 
     pub fn check_trait_expectations(
@@ -50,6 +50,7 @@ use crate::vm::costs::cost_functions::ClarityCostFunction;
 use serde::Serialize;
 
 use crate::vm::coverage::CoverageReporter;
+use crate::vm::tracing::ExecutionTracer;
 
 pub const MAX_CONTEXT_DEPTH: u16 = 256;
 
@@ -191,6 +192,8 @@ pub struct GlobalContext<'a> {
     pub cost_track: LimitedCostTracker,
     pub mainnet: bool,
     pub coverage_reporting: Option<CoverageReporter>,
+    /// If set, records entries and exits of user-defined functions.
+    pub tracer: Option<ExecutionTracer>,
     /// This is the epoch of the the block that this transaction is executing within.
     epoch_id: StacksEpochId,
 }
@@ -559,6 +562,14 @@ impl<'a> OwnedEnvironment<'a> {
         self.context.coverage_reporting.take()
     }
 
+    pub fn set_tracer(&mut self, tracer: ExecutionTracer) {
+        self.context.tracer = Some(tracer)
+    }
+
+    pub fn take_tracer(&mut self) -> Option<ExecutionTracer> {
+        self.context.tracer.take()
+    }
+
     pub fn new_free(
         mainnet: bool,
         database: ClarityDatabase<'a>,
@@ -1402,7 +1413,12 @@ impl<'a> GlobalContext<'a> {
             event_batches: Vec::new(),
             mainnet,
             epoch_id,
-            coverage_reporting: None,
+            coverage_reporting: if CoverageReporter::is_global_enabled() {
+                Some(CoverageReporter::new())
+            } else {
+                None
+            },
+            tracer: None,
         }
     }
 
@@ -1557,7 +1573,12 @@ impl<'a> GlobalContext<'a> {
     /// Destroys this context, returning ownership of its database reference.
     ///  If the context wasn't top-level (i.e., it had uncommitted data), return None,
     ///   because the database is not guaranteed to be in a sane state.
-    pub fn destruct(self) -> Option<(ClarityDatabase<'a>, LimitedCostTracker)> {
+    pub fn destruct(mut self) -> Option<(ClarityDatabase<'a>, LimitedCostTracker)> {
+        if let Some(reporter) = self.coverage_reporting.take() {
+            if CoverageReporter::is_global_enabled() {
+                reporter.report_global();
+            }
+        }
         if self.is_top_level() {
             Some((self.database, self.cost_track))
         } else {
@@ -1,7 +1,12 @@
 use std::{
     collections::{BTreeMap, HashMap, HashSet},
-    fs::File,
+    fs::{self, File},
     io::Write,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
 };
 
 use crate::vm::types::QualifiedContractIdentifier;
@@ -14,6 +19,27 @@ pub struct CoverageReporter {
     executed_lines: HashMap<QualifiedContractIdentifier, HashMap<u32, u64>>,
 }
 
+/// Coverage collected from every Clarity execution in this process, for the current run.
+struct GlobalCoverage {
+    reporter: CoverageReporter,
+    run: u64,
+    out_dir: Option<PathBuf>,
+}
+
+static GLOBAL_COVERAGE_ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref GLOBAL_COVERAGE: Mutex<Option<GlobalCoverage>> = Mutex::new(None);
+}
+
+/// Executed lines of each contract in a coverage run.  This is the format of the coverage files
+/// `produce_lcov()` reads.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverageDump {
+    pub run: u64,
+    pub coverage: HashMap<String, Vec<(u32, u64)>>,
+}
+
 #[derive(Serialize, Deserialize)]
 struct ContractFileInfo {
     contract: String,
@@ -70,8 +96,7 @@ impl CoverageReporter {
         }
     }
 
-    pub fn to_file<P: AsRef<std::path::Path> + Copy>(&self, filename: P) -> std::io::Result<()> {
-        let f = File::create(filename)?;
+    fn coverage_map(&self) -> HashMap<String, Vec<(u32, u64)>> {
         let mut coverage = HashMap::new();
         for (contract, execution_map) in self.executed_lines.iter() {
             let mut executed_lines = vec![];
@@ -82,6 +107,84 @@ impl CoverageReporter {
 
             coverage.insert(contract.to_string(), executed_lines);
         }
+        coverage
+    }
+
+    /// Add the line counts of `other` to this reporter's.
+    pub fn merge(&mut self, other: CoverageReporter) {
+        for (contract, execution_map) in other.executed_lines.into_iter() {
+            let contract_map = self
+                .executed_lines
+                .entry(contract)
+                .or_insert_with(HashMap::new);
+            for (line, count) in execution_map.into_iter() {
+                *contract_map.entry(line).or_insert(0) += count;
+            }
+        }
+    }
+
+    /// Start collecting coverage from every Clarity execution in this process.  Each
+    /// `GlobalContext` created from now on carries its own reporter, which is folded into the
+    /// process-wide coverage with `report_global()` when the context is destructed.  If
+    /// `out_dir` is given, `dump_global()` also writes each run's coverage there.
+    pub fn enable_global(out_dir: Option<PathBuf>) {
+        let mut global = GLOBAL_COVERAGE
+            .lock()
+            .expect("FATAL: coverage lock poisoned");
+        *global = Some(GlobalCoverage {
+            reporter: CoverageReporter::new(),
+            run: 0,
+            out_dir,
+        });
+        GLOBAL_COVERAGE_ENABLED.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_global_enabled() -> bool {
+        GLOBAL_COVERAGE_ENABLED.load(Ordering::SeqCst)
+    }
+
+    /// Fold this reporter's coverage into the process-wide coverage, if it is enabled.
+    pub fn report_global(self) {
+        let mut global = GLOBAL_COVERAGE
+            .lock()
+            .expect("FATAL: coverage lock poisoned");
+        if let Some(global) = global.as_mut() {
+            global.reporter.merge(self);
+        }
+    }
+
+    /// Get the process-wide coverage of the current run, writing it to
+    /// `<out_dir>/coverage-run-<run>.json` if an output directory was given.  If `reset` is set,
+    /// the run ends and a new one starts with no coverage.  Returns None if global coverage is
+    /// not enabled.
+    pub fn dump_global(reset: bool) -> std::io::Result<Option<CoverageDump>> {
+        let mut global = GLOBAL_COVERAGE
+            .lock()
+            .expect("FATAL: coverage lock poisoned");
+        let global = match global.as_mut() {
+            Some(global) => global,
+            None => return Ok(None),
+        };
+
+        let dump = CoverageDump {
+            run: global.run,
+            coverage: global.reporter.coverage_map(),
+        };
+        if let Some(out_dir) = global.out_dir.as_ref() {
+            fs::create_dir_all(out_dir)?;
+            let f = File::create(out_dir.join(format!("coverage-run-{}.json", global.run)))?;
+            serde_json::to_writer(f, &dump)?;
+        }
+        if reset {
+            global.reporter = CoverageReporter::new();
+            global.run += 1;
+        }
+        Ok(Some(dump))
+    }
+
+    pub fn to_file<P: AsRef<std::path::Path> + Copy>(&self, filename: P) -> std::io::Result<()> {
+        let f = File::create(filename)?;
+        let coverage = self.coverage_map();
 
         let out = CoverageFileInfo { coverage };
         if let Err(e) = serde_json::to_writer(f, &out) {
@@ -222,3 +325,37 @@ impl CoverageReporter {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage_merge() {
+        let contract_a = QualifiedContractIdentifier::local("a").unwrap();
+        let contract_b = QualifiedContractIdentifier::local("b").unwrap();
+
+        let mut left = CoverageReporter::new();
+        left.executed_lines
+            .insert(contract_a.clone(), [(1, 2), (3, 1)].into_iter().collect());
+
+        let mut right = CoverageReporter::new();
+        right
+            .executed_lines
+            .insert(contract_a.clone(), [(1, 1), (4, 5)].into_iter().collect());
+        right
+            .executed_lines
+            .insert(contract_b.clone(), [(7, 1)].into_iter().collect());
+
+        left.merge(right);
+        let coverage = left.coverage_map();
+        assert_eq!(
+            coverage.get(&contract_a.to_string()).unwrap(),
+            &vec![(1, 3), (3, 1), (4, 5)]
+        );
+        assert_eq!(
+            coverage.get(&contract_b.to_string()).unwrap(),
+            &vec![(7, 1)]
+        );
+    }
+}
@@ -941,6 +941,15 @@ impl<'a> ClarityDatabase<'a> {
         )
     }
 
+    /// The prefix shared by the keys of all the entries of a data map, for looking them up by
+    /// key prefix.  What follows it in each key is the serialized map key.
+    pub fn make_key_prefix_for_data_map(
+        contract_identifier: &QualifiedContractIdentifier,
+        map_name: &str,
+    ) -> String {
+        ClarityDatabase::make_key_for_data_map_entry_serialized(contract_identifier, map_name, "")
+    }
+
     fn make_key_for_data_map_entry_serialized(
         contract_identifier: &QualifiedContractIdentifier,
         map_name: &str,
@@ -155,6 +155,71 @@ impl SqliteConnection {
     pub fn has_entry(conn: &Connection, key: &str) -> bool {
         sqlite_has_entry(conn, key)
     }
+
+    /// Remember which key hashes to `key_hash`, so that keys found by hash can be resolved.
+    pub fn put_key_preimage(conn: &Connection, key_hash: &str, key: &str) {
+        let params: [&dyn ToSql; 2] = [&key_hash, &key];
+        if let Err(e) = conn.execute(
+            "INSERT OR IGNORE INTO key_preimages (key_hash, key) VALUES (?, ?)",
+            &params,
+        ) {
+            error!("Failed to insert preimage ({},{}): {:?}", key_hash, key, &e);
+            panic!("{}", SQL_FAIL_MESSAGE);
+        }
+    }
+
+    pub fn get_key_preimage(conn: &Connection, key_hash: &str) -> Option<String> {
+        match conn
+            .query_row(
+                "SELECT key FROM key_preimages WHERE key_hash = ?",
+                &[key_hash],
+                |row| row.get(0),
+            )
+            .optional()
+        {
+            Ok(x) => x,
+            Err(e) => {
+                error!("Failed to query preimage of '{}': {:?}", key_hash, &e);
+                panic!("{}", SQL_FAIL_MESSAGE);
+            }
+        }
+    }
+
+    /// Up to `limit` known keys that start with `prefix`, in ascending order, starting after
+    /// `start_after` if given.  The keys were seen on some fork, not necessarily on any
+    /// particular one.
+    pub fn get_key_preimages_with_prefix(
+        conn: &Connection,
+        prefix: &str,
+        start_after: Option<&str>,
+        limit: u32,
+    ) -> Vec<String> {
+        // keys are ASCII, so every key starting with `prefix` sorts below `prefix` followed by
+        // the highest code point
+        let upper_bound = format!("{}{}", prefix, char::MAX);
+        let (sql, lower_bound) = match start_after {
+            Some(start_after) if start_after >= prefix => (
+                "SELECT key FROM key_preimages WHERE key > ?1 AND key < ?2 ORDER BY key LIMIT ?3",
+                start_after,
+            ),
+            _ => (
+                "SELECT key FROM key_preimages WHERE key >= ?1 AND key < ?2 ORDER BY key LIMIT ?3",
+                prefix,
+            ),
+        };
+        let params: [&dyn ToSql; 3] = [&lower_bound, &upper_bound, &limit];
+        let res = conn.prepare(sql).and_then(|mut stmt| {
+            stmt.query_map(&params, |row| row.get(0))?
+                .collect::<std::result::Result<Vec<String>, _>>()
+        });
+        match res {
+            Ok(keys) => keys,
+            Err(e) => {
+                error!("Failed to query keys with prefix '{}': {:?}", prefix, &e);
+                panic!("{}", SQL_FAIL_MESSAGE);
+            }
+        }
+    }
 }
 
 impl SqliteConnection {
@@ -177,10 +242,45 @@ impl SqliteConnection {
         )
         .map_err(|x| InterpreterError::SqliteError(IncomparableError { err: x }))?;
 
+        Self::initialize_key_preimages(conn)?;
         Self::check_schema(conn)?;
 
         Ok(())
     }
+    /// Whether the table of key preimages exists.  It is missing from databases created before
+    /// it was introduced.
+    pub fn has_key_preimages(conn: &Connection) -> Result<bool> {
+        conn.query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'key_preimages'",
+            NO_PARAMS,
+            |_row| Ok(()),
+        )
+        .optional()
+        .map(|row| row.is_some())
+        .map_err(|x| InterpreterError::SqliteError(IncomparableError { err: x }).into())
+    }
+
+    /// Create the table of key preimages if it is missing.
+    pub fn initialize_key_preimages(conn: &Connection) -> Result<()> {
+        if Self::has_key_preimages(conn)? {
+            return Ok(());
+        }
+
+        conn.execute(
+            "CREATE TABLE key_preimages
+                      (key_hash TEXT PRIMARY KEY, key TEXT NOT NULL)",
+            NO_PARAMS,
+        )
+        .map_err(|x| InterpreterError::SqliteError(IncomparableError { err: x }))?;
+
+        conn.execute(
+            "CREATE INDEX index_key_preimages_key ON key_preimages(key)",
+            NO_PARAMS,
+        )
+        .map_err(|x| InterpreterError::SqliteError(IncomparableError { err: x }))?;
+
+        Ok(())
+    }
     pub fn memory() -> Result<Connection> {
         let contract_db = SqliteConnection::inner_open(":memory:")?;
         SqliteConnection::initialize_conn(&contract_db)?;
@@ -40,6 +40,8 @@ pub mod docs;
 
 pub mod coverage;
 
+pub mod tracing;
+
 pub mod events;
 
 #[cfg(test)]
@@ -81,6 +83,10 @@ use std::convert::{TryFrom, TryInto};
 
 pub const MAX_CALL_STACK_DEPTH: usize = 64;
 
+/// Version of the Clarity language this VM implements.  Every contract is interpreted as this
+/// version.
+pub const CLARITY_VERSION: u32 = 1;
+
 fn lookup_variable(name: &str, context: &LocalContext, env: &mut Environment) -> Result<Value> {
     if name.starts_with(char::is_numeric) || name.starts_with('\'') {
         Err(InterpreterError::BadSymbolicRepresentation(format!(
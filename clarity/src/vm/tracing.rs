@@ -0,0 +1,234 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::vm::costs::ExecutionCost;
+use crate::vm::errors::Error;
+use crate::vm::types::QualifiedContractIdentifier;
+use crate::vm::{ClarityName, Value};
+
+/// Maximum number of events an `ExecutionTracer` records before it stops recording and marks
+/// the trace as truncated.
+pub const MAX_TRACE_EVENTS: usize = 4096;
+
+/// One step of a traced execution.  Values are rendered in Clarity syntax.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TraceEvent {
+    /// A user-defined function was entered.  `cost` is the total cost consumed so far.
+    Enter {
+        depth: usize,
+        contract: String,
+        function: String,
+        args: Vec<String>,
+        cost: ExecutionCost,
+    },
+    /// A user-defined function returned `result`, or failed with `error`.  `cost` is the total
+    /// cost consumed so far, and `cost_delta` is the cost of this call (including its callees).
+    Exit {
+        depth: usize,
+        contract: String,
+        function: String,
+        result: Option<String>,
+        error: Option<String>,
+        cost: ExecutionCost,
+        cost_delta: ExecutionCost,
+    },
+}
+
+/// Structured trace of an execution, as produced by an `ExecutionTracer`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionTrace {
+    pub events: Vec<TraceEvent>,
+    /// True if the execution produced more than `MAX_TRACE_EVENTS` events, and later events
+    /// were not recorded.
+    pub truncated: bool,
+}
+
+/// VM hook that records function entries and exits.  Install it in a `GlobalContext` with
+/// `OwnedEnvironment::set_tracer()` (or by setting `GlobalContext::tracer`), and collect the
+/// trace with `into_trace()` once execution finishes.
+pub struct ExecutionTracer {
+    events: Vec<TraceEvent>,
+    /// Cost at entry of each function currently on the call stack.
+    entry_costs: Vec<ExecutionCost>,
+    truncated: bool,
+}
+
+impl ExecutionTracer {
+    pub fn new() -> ExecutionTracer {
+        ExecutionTracer {
+            events: vec![],
+            entry_costs: vec![],
+            truncated: false,
+        }
+    }
+
+    fn push(&mut self, event: TraceEvent) {
+        if self.events.len() >= MAX_TRACE_EVENTS {
+            self.truncated = true;
+        } else {
+            self.events.push(event);
+        }
+    }
+
+    pub fn report_enter(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+        function: &ClarityName,
+        args: &[Value],
+        cost: &ExecutionCost,
+    ) {
+        let depth = self.entry_costs.len();
+        self.entry_costs.push(cost.clone());
+        self.push(TraceEvent::Enter {
+            depth,
+            contract: contract.to_string(),
+            function: function.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            cost: cost.clone(),
+        });
+    }
+
+    pub fn report_exit(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+        function: &ClarityName,
+        result: &Result<Value, Error>,
+        cost: &ExecutionCost,
+    ) {
+        let mut cost_delta = cost.clone();
+        if let Some(entry_cost) = self.entry_costs.pop() {
+            if cost_delta.sub(&entry_cost).is_err() {
+                cost_delta = ExecutionCost::zero();
+            }
+        }
+        let (result, error) = match result {
+            Ok(value) => (Some(value.to_string()), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        self.push(TraceEvent::Exit {
+            depth: self.entry_costs.len(),
+            contract: contract.to_string(),
+            function: function.to_string(),
+            result,
+            error,
+            cost: cost.clone(),
+            cost_delta,
+        });
+    }
+
+    pub fn into_trace(self) -> ExecutionTrace {
+        ExecutionTrace {
+            events: self.events,
+            truncated: self.truncated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracer_enter_exit() {
+        let contract = QualifiedContractIdentifier::transient();
+        let outer: ClarityName = "outer".into();
+        let inner: ClarityName = "inner".into();
+
+        let mut tracer = ExecutionTracer::new();
+        tracer.report_enter(
+            &contract,
+            &outer,
+            &[Value::UInt(1)],
+            &ExecutionCost::runtime(10),
+        );
+        tracer.report_enter(&contract, &inner, &[], &ExecutionCost::runtime(15));
+        tracer.report_exit(
+            &contract,
+            &inner,
+            &Ok(Value::Bool(true)),
+            &ExecutionCost::runtime(20),
+        );
+        tracer.report_exit(
+            &contract,
+            &outer,
+            &Ok(Value::okay(Value::UInt(2)).unwrap()),
+            &ExecutionCost::runtime(30),
+        );
+
+        let trace = tracer.into_trace();
+        assert!(!trace.truncated);
+        assert_eq!(trace.events.len(), 4);
+        match &trace.events[0] {
+            TraceEvent::Enter {
+                depth,
+                function,
+                args,
+                ..
+            } => {
+                assert_eq!(*depth, 0);
+                assert_eq!(function, "outer");
+                assert_eq!(args, &vec!["u1".to_string()]);
+            }
+            _ => panic!("expected an enter event"),
+        }
+        match &trace.events[2] {
+            TraceEvent::Exit {
+                depth,
+                result,
+                cost_delta,
+                ..
+            } => {
+                assert_eq!(*depth, 1);
+                assert_eq!(result.as_deref(), Some("true"));
+                assert_eq!(cost_delta, &ExecutionCost::runtime(5));
+            }
+            _ => panic!("expected an exit event"),
+        }
+        match &trace.events[3] {
+            TraceEvent::Exit {
+                depth,
+                result,
+                cost_delta,
+                ..
+            } => {
+                assert_eq!(*depth, 0);
+                assert_eq!(result.as_deref(), Some("(ok u2)"));
+                assert_eq!(cost_delta, &ExecutionCost::runtime(20));
+            }
+            _ => panic!("expected an exit event"),
+        }
+    }
+
+    #[test]
+    fn test_tracer_truncates() {
+        let contract = QualifiedContractIdentifier::transient();
+        let function: ClarityName = "loop".into();
+        let mut tracer = ExecutionTracer::new();
+        for _ in 0..MAX_TRACE_EVENTS {
+            tracer.report_enter(&contract, &function, &[], &ExecutionCost::zero());
+            tracer.report_exit(
+                &contract,
+                &function,
+                &Ok(Value::none()),
+                &ExecutionCost::zero(),
+            );
+        }
+        let trace = tracer.into_trace();
+        assert!(trace.truncated);
+        assert_eq!(trace.events.len(), MAX_TRACE_EVENTS);
+    }
+}
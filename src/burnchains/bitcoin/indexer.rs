@@ -19,6 +19,7 @@ use std::cmp;
 use std::fs;
 use std::net;
 use std::net::Shutdown;
+use std::net::SocketAddr;
 use std::ops::Deref;
 use std::ops::DerefMut;
 use std::path;
@@ -40,6 +41,8 @@ use crate::burnchains::bitcoin::BitcoinNetworkType;
 use crate::burnchains::Error as burnchain_error;
 use crate::burnchains::MagicBytes;
 use crate::burnchains::BLOCKSTACK_MAGIC_MAINNET;
+use crate::net::socks;
+use crate::net::socks::SocksTarget;
 use crate::types::chainstate::BurnchainHeaderHash;
 
 use stacks_common::deps_common::bitcoin::blockdata::block::LoneBlockHeader;
@@ -117,6 +120,12 @@ pub struct BitcoinIndexerConfig {
     pub first_block: u64,
     pub magic_bytes: MagicBytes,
     pub epochs: Option<Vec<StacksEpoch>>,
+    /// connect to the bitcoin peer through this SOCKS5 proxy
+    pub socks_proxy: Option<SocketAddr>,
+    /// number of blocks to download at once when syncing, each over its own peer connection
+    pub download_workers: usize,
+    /// number of downloaded blocks to parse at once when syncing
+    pub parse_workers: usize,
 }
 
 #[derive(Debug)]
@@ -152,6 +161,9 @@ impl BitcoinIndexerConfig {
             first_block: 0,
             magic_bytes: BLOCKSTACK_MAGIC_MAINNET.clone(),
             epochs: None,
+            socks_proxy: None,
+            download_workers: 1,
+            parse_workers: 1,
         }
     }
 }
@@ -193,7 +205,17 @@ impl BitcoinIndexer {
     /// Bitcoin peer.  If we fail to connect, this method sets the socket
     /// to None.
     fn reconnect_peer(&mut self) -> Result<(), btc_error> {
-        match net::TcpStream::connect((self.config.peer_host.as_str(), self.config.peer_port)) {
+        let connect_res = match self.config.socks_proxy {
+            Some(ref proxy) => socks::connect_blocking(
+                proxy,
+                &SocksTarget::from_host(&self.config.peer_host, self.config.peer_port),
+                Duration::from_secs(self.config.timeout as u64),
+            ),
+            None => {
+                net::TcpStream::connect((self.config.peer_host.as_str(), self.config.peer_port))
+            }
+        };
+        match connect_res {
             Ok(s) => {
                 // Disable Nagle algorithm
                 s.set_nodelay(true).map_err(|_e| {
@@ -237,6 +259,12 @@ impl BitcoinIndexer {
         }
     }
 
+    /// Drop the connection to our configured peer, if any.  The next conversation with the
+    /// peer opens a new one.
+    pub fn disconnect(&mut self) {
+        self.runtime.sock = None;
+    }
+
     /// Run code with the socket
     pub fn with_socket<F, R>(&mut self, closure: F) -> Result<R, btc_error>
     where
@@ -1005,6 +1033,11 @@ impl BurnchainIndexer for BitcoinIndexer {
             .map_err(burnchain_error::Bitcoin)
     }
 
+    /// Every burn block is downloaded in full.  Skipping blocks with BIP157/158 compact block
+    /// filters is not an option: basic filters leave out OP_RETURN outputs, which carry every
+    /// Stacks burnchain operation, and operations like leader key registrations have no other
+    /// output a follower could know to match.  A filter therefore can't rule out that a block has
+    /// Stacks operations, and skipping one that does would fork the node's sortitions.
     fn downloader(&self) -> BitcoinBlockDownloader {
         BitcoinBlockDownloader::new(self.dup())
     }
@@ -1012,6 +1045,14 @@ impl BurnchainIndexer for BitcoinIndexer {
     fn parser(&self) -> BitcoinBlockParser {
         BitcoinBlockParser::new(self.runtime.network_id, self.config.magic_bytes)
     }
+
+    fn download_workers(&self) -> usize {
+        self.config.download_workers.max(1)
+    }
+
+    fn parse_workers(&self) -> usize {
+        self.config.parse_workers.max(1)
+    }
 }
 
 #[cfg(test)]
@@ -1432,6 +1473,9 @@ mod test {
             first_block: 0,
             magic_bytes: MagicBytes([105, 100]),
             epochs: None,
+            socks_proxy: None,
+            download_workers: 1,
+            parse_workers: 1,
         };
 
         if fs::metadata(&indexer_conf.spv_headers_path).is_ok() {
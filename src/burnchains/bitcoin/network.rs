@@ -354,6 +354,15 @@ impl BitcoinIndexer {
         self.send_message(payload)
     }
 
+    /// Send a SendHeaders message, so the remote peer announces new blocks with their headers
+    pub fn send_sendheaders(&mut self) -> Result<(), btc_error> {
+        debug!(
+            "Send SendHeaders to {}:{}",
+            self.config.peer_host, self.config.peer_port
+        );
+        self.send_message(btc_message::NetworkMessage::SendHeaders)
+    }
+
     /// Send a GetData message
     pub fn send_getdata(&mut self, block_hashes: &Vec<Sha256dHash>) -> Result<(), btc_error> {
         assert!(block_hashes.len() > 0);
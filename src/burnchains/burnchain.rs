@@ -22,7 +22,7 @@ use std::path::PathBuf;
 use std::sync::mpsc::sync_channel;
 use std::sync::{
     atomic::{AtomicBool, AtomicU64, Ordering},
-    Arc,
+    Arc, Mutex,
 };
 use std::thread;
 use std::time::{Duration, Instant};
@@ -61,7 +61,9 @@ use crate::core::NETWORK_ID_TESTNET;
 use crate::core::PEER_VERSION_MAINNET;
 use crate::core::PEER_VERSION_TESTNET;
 use crate::deps;
-use crate::monitoring::update_burnchain_height;
+use crate::monitoring::{
+    log_burnchain_block_synced, update_burnchain_height, update_burnchain_sync_target,
+};
 use crate::types::chainstate::StacksAddress;
 use crate::types::chainstate::TrieHash;
 use crate::util_lib::db::DBConn;
@@ -1248,6 +1250,9 @@ impl Burnchain {
             }
         }
 
+        update_burnchain_sync_target(self.first_block_height, end_block);
+        log_burnchain_block_synced(db_height);
+
         let mut start_block = sync_height;
         if db_height < start_block {
             start_block = db_height;
@@ -1311,68 +1316,114 @@ impl Burnchain {
 
         let total = sync_height - self.first_block_height;
         let progress = (end_block - self.first_block_height) as f32 / total as f32 * 100.;
-        info!(
+        debug!(
             "Syncing Bitcoin blocks: {:.1}% ({} to {} out of {})",
             progress, start_block, end_block, sync_height
         );
 
-        // synchronize
-        let (downloader_send, downloader_recv) = sync_channel(1);
-        let (parser_send, parser_recv) = sync_channel(1);
-        let (db_send, db_recv) = sync_channel(1);
+        // synchronize.  Blocks are downloaded and parsed by pools of workers, in whatever order
+        // they finish in, and the DB thread puts them back in order before it processes them.
+        let input_headers = indexer.read_headers(start_block + 1, end_block + 1)?;
+        let num_downloaders = indexer.download_workers().min(input_headers.len()).max(1);
+        let num_parsers = indexer.parse_workers().min(input_headers.len()).max(1);
 
-        let mut downloader = indexer.downloader();
-        let mut parser = indexer.parser();
+        let (downloader_send, downloader_recv) = sync_channel(num_downloaders);
+        let (parser_send, parser_recv) = sync_channel(num_parsers);
+        let (db_send, db_recv) = sync_channel(num_parsers);
+        let downloader_recv = Arc::new(Mutex::new(downloader_recv));
+        let parser_recv = Arc::new(Mutex::new(parser_recv));
+
+        // set by whichever thread fails first, so the others stop early
+        let pipeline_failed = Arc::new(AtomicBool::new(false));
 
         let myself = self.clone();
 
         // TODO: don't re-process blocks.  See if the block hash is already present in the burn db,
         // and if so, do nothing.
-        let download_thread: thread::JoinHandle<Result<(), burnchain_error>> =
-            thread::Builder::new()
-                .name("burnchain-downloader".to_string())
-                .spawn(move || {
-                    while let Ok(Some(ipc_header)) = downloader_recv.recv() {
-                        debug!("Try recv next header");
+        let mut download_threads: Vec<thread::JoinHandle<Result<(), burnchain_error>>> = vec![];
+        for worker_id in 0..num_downloaders {
+            let mut downloader = indexer.downloader();
+            let downloader_recv = downloader_recv.clone();
+            let parser_send = parser_send.clone();
+            let should_keep_running = should_keep_running.clone();
+            let pipeline_failed = pipeline_failed.clone();
+            let download_thread = thread::Builder::new()
+                .name(format!("burnchain-downloader-{}", worker_id))
+                .spawn(move || loop {
+                    debug!("Try recv next header");
 
-                        match should_keep_running {
-                            Some(ref should_keep_running)
-                                if !should_keep_running.load(Ordering::SeqCst) =>
-                            {
-                                return Err(burnchain_error::CoordinatorClosed);
-                            }
-                            _ => {}
-                        };
+                    // only hold the lock while taking the next header
+                    let next_header = downloader_recv
+                        .lock()
+                        .expect("FATAL: burnchain downloader lock poisoned")
+                        .recv();
+                    let (index, ipc_header) = match next_header {
+                        Ok(next_header) => next_header,
+                        Err(_) => return Ok(()),
+                    };
+                    if pipeline_failed.load(Ordering::SeqCst) {
+                        return Ok(());
+                    }
 
-                        let download_start = get_epoch_time_ms();
-                        let ipc_block = downloader.download(&ipc_header)?;
-                        let download_end = get_epoch_time_ms();
+                    match should_keep_running {
+                        Some(ref should_keep_running)
+                            if !should_keep_running.load(Ordering::SeqCst) =>
+                        {
+                            pipeline_failed.store(true, Ordering::SeqCst);
+                            return Err(burnchain_error::CoordinatorClosed);
+                        }
+                        _ => {}
+                    };
 
-                        debug!(
-                            "Downloaded block {} in {}ms",
-                            ipc_block.height(),
-                            download_end.saturating_sub(download_start)
-                        );
+                    let download_start = get_epoch_time_ms();
+                    let ipc_block = downloader.download(&ipc_header).map_err(|e| {
+                        pipeline_failed.store(true, Ordering::SeqCst);
+                        e
+                    })?;
+                    let download_end = get_epoch_time_ms();
+
+                    debug!(
+                        "Downloaded block {} in {}ms",
+                        ipc_block.height(),
+                        download_end.saturating_sub(download_start)
+                    );
 
-                        parser_send
-                            .send(Some(ipc_block))
-                            .map_err(|_e| burnchain_error::ThreadChannelError)?;
-                    }
                     parser_send
-                        .send(None)
+                        .send((index, ipc_block))
                         .map_err(|_e| burnchain_error::ThreadChannelError)?;
-                    Ok(())
                 })
                 .unwrap();
+            download_threads.push(download_thread);
+        }
 
-        let parse_thread: thread::JoinHandle<Result<(), burnchain_error>> = thread::Builder::new()
-            .name("burnchain-parser".to_string())
-            .spawn(move || {
-                while let Ok(Some(ipc_block)) = parser_recv.recv() {
+        let mut parse_threads: Vec<thread::JoinHandle<Result<(), burnchain_error>>> = vec![];
+        for worker_id in 0..num_parsers {
+            let mut parser = indexer.parser();
+            let parser_recv = parser_recv.clone();
+            let db_send = db_send.clone();
+            let pipeline_failed = pipeline_failed.clone();
+            let parse_thread = thread::Builder::new()
+                .name(format!("burnchain-parser-{}", worker_id))
+                .spawn(move || loop {
                     debug!("Try recv next block");
 
+                    let next_block = parser_recv
+                        .lock()
+                        .expect("FATAL: burnchain parser lock poisoned")
+                        .recv();
+                    let (index, ipc_block) = match next_block {
+                        Ok(next_block) => next_block,
+                        Err(_) => return Ok(()),
+                    };
+                    if pipeline_failed.load(Ordering::SeqCst) {
+                        return Ok(());
+                    }
+
                     let parse_start = get_epoch_time_ms();
-                    let burnchain_block = parser.parse(&ipc_block)?;
+                    let burnchain_block = parser.parse(&ipc_block).map_err(|e| {
+                        pipeline_failed.store(true, Ordering::SeqCst);
+                        e
+                    })?;
                     let parse_end = get_epoch_time_ms();
 
                     debug!(
@@ -1382,49 +1433,70 @@ impl Burnchain {
                     );
 
                     db_send
-                        .send(Some(burnchain_block))
+                        .send((index, burnchain_block))
                         .map_err(|_e| burnchain_error::ThreadChannelError)?;
-                }
-                db_send
-                    .send(None)
-                    .map_err(|_e| burnchain_error::ThreadChannelError)?;
-                Ok(())
-            })
-            .unwrap();
+                })
+                .unwrap();
+            parse_threads.push(parse_thread);
+        }
 
+        // the workers hold the only other ends of the channels, so that each stage sees its input
+        // close once the stage before it is done (or has failed)
+        drop(downloader_recv);
+        drop(parser_send);
+        drop(parser_recv);
+        drop(db_send);
+
+        let db_pipeline_failed = pipeline_failed.clone();
         let db_thread: thread::JoinHandle<Result<BurnchainBlockHeader, burnchain_error>> =
             thread::Builder::new()
                 .name("burnchain-db".to_string())
                 .spawn(move || {
                     let mut last_processed = burn_chain_tip;
-                    while let Ok(Some(burnchain_block)) = db_recv.recv() {
+                    // parsed blocks that arrived before a block that comes ahead of them
+                    let mut pending = HashMap::new();
+                    let mut next_index = 0;
+                    while let Ok((index, burnchain_block)) = db_recv.recv() {
                         debug!("Try recv next parsed block");
+                        pending.insert(index, burnchain_block);
 
-                        let block_height = burnchain_block.block_height();
-                        if block_height == 0 {
-                            continue;
-                        }
+                        while let Some(burnchain_block) = pending.remove(&next_index) {
+                            next_index += 1;
 
-                        let insert_start = get_epoch_time_ms();
-                        last_processed =
-                            Burnchain::process_block(&myself, &mut burnchain_db, &burnchain_block)?;
-                        if !coord_comm.announce_new_burn_block() {
-                            return Err(burnchain_error::CoordinatorClosed);
-                        }
-                        let insert_end = get_epoch_time_ms();
+                            let block_height = burnchain_block.block_height();
+                            if block_height == 0 {
+                                continue;
+                            }
 
-                        debug!(
-                            "Inserted block {} in {}ms",
-                            burnchain_block.block_height(),
-                            insert_end.saturating_sub(insert_start)
-                        );
+                            let insert_start = get_epoch_time_ms();
+                            last_processed = Burnchain::process_block(
+                                &myself,
+                                &mut burnchain_db,
+                                &burnchain_block,
+                            )
+                            .map_err(|e| {
+                                db_pipeline_failed.store(true, Ordering::SeqCst);
+                                e
+                            })?;
+                            if !coord_comm.announce_new_burn_block() {
+                                db_pipeline_failed.store(true, Ordering::SeqCst);
+                                return Err(burnchain_error::CoordinatorClosed);
+                            }
+                            log_burnchain_block_synced(block_height);
+                            let insert_end = get_epoch_time_ms();
+
+                            debug!(
+                                "Inserted block {} in {}ms",
+                                burnchain_block.block_height(),
+                                insert_end.saturating_sub(insert_start)
+                            );
+                        }
                     }
                     Ok(last_processed)
                 })
                 .unwrap();
 
         // feed the pipeline!
-        let input_headers = indexer.read_headers(start_block + 1, end_block + 1)?;
         let mut downloader_result: Result<(), burnchain_error> = Ok(());
         for i in 0..input_headers.len() {
             debug!(
@@ -1432,7 +1504,7 @@ impl Burnchain {
                 start_block + 1 + (i as u64),
                 end_block
             );
-            if let Err(e) = downloader_send.send(Some(input_headers[i].clone())) {
+            if let Err(e) = downloader_send.send((i, input_headers[i].clone())) {
                 info!(
                     "Failed to feed burnchain block header {}: {:?}",
                     start_block + 1 + (i as u64),
@@ -1443,16 +1515,16 @@ impl Burnchain {
             }
         }
 
-        if downloader_result.is_ok() {
-            if let Err(e) = downloader_send.send(None) {
-                info!("Failed to instruct downloader thread to finish: {:?}", &e);
-                downloader_result = Err(burnchain_error::TrySyncAgain);
-            }
-        }
+        // closing the channel tells the downloaders to finish
+        drop(downloader_send);
 
         // join up
-        let _ = download_thread.join().unwrap();
-        let _ = parse_thread.join().unwrap();
+        for download_thread in download_threads {
+            let _ = download_thread.join().unwrap();
+        }
+        for parse_thread in parse_threads {
+            let _ = parse_thread.join().unwrap();
+        }
         let block_header = match db_thread.join().unwrap() {
             Ok(x) => x,
             Err(e) => {
@@ -80,4 +80,8 @@ pub trait BurnchainIndexer {
 
     fn downloader(&self) -> <<Self as BurnchainIndexer>::P as BurnchainBlockParser>::D;
     fn parser(&self) -> Self::P;
+
+    /// How many downloaders and parsers to run at once when syncing
+    fn download_workers(&self) -> usize;
+    fn parse_workers(&self) -> usize;
 }
@@ -69,6 +69,7 @@ use crate::util_lib::db::tx_begin_immediate;
 use crate::util_lib::db::tx_busy_handler;
 use crate::util_lib::db::DBTx;
 use crate::util_lib::db::Error as db_error;
+use crate::util_lib::db::SqliteDBKind;
 use crate::util_lib::db::{
     db_mkdirs, query_count, query_row, query_row_columns, query_row_panic, query_rows, sql_pragma,
     u64_to_sql, DBConn, FromColumn, FromRow, IndexDBConn, IndexDBTx,
@@ -2051,7 +2052,8 @@ impl SortitionDB {
 
     fn open_index(index_path: &str) -> Result<MARF<SortitionId>, db_error> {
         test_debug!("Open index at {}", index_path);
-        let open_opts = MARFOpenOpts::default();
+        let mut open_opts = MARFOpenOpts::default();
+        open_opts.db_kind = SqliteDBKind::Sortition;
         let marf = MARF::from_path(index_path, open_opts).map_err(|_e| db_error::Corruption)?;
         sql_pragma(marf.sqlite_conn(), "foreign_keys", &true)?;
         Ok(marf)
@@ -2290,7 +2292,6 @@ impl SortitionDB {
     ) -> Result<(), db_error> {
         debug!("Instantiate sortition DB");
 
-        sql_pragma(self.conn(), "journal_mode", &"WAL")?;
         sql_pragma(self.conn(), "foreign_keys", &true)?;
 
         let mut db_tx = SortitionHandleTx::begin(self, &SortitionId::sentinel())?;
@@ -2368,7 +2369,6 @@ impl SortitionDB {
     ) -> Result<(), db_error> {
         debug!("Instantiate SortDB");
 
-        sql_pragma(self.conn(), "journal_mode", &"WAL")?;
         sql_pragma(self.conn(), "foreign_keys", &true)?;
 
         let mut db_tx = SortitionHandleTx::begin(self, &SortitionId::sentinel())?;
@@ -2481,7 +2481,7 @@ impl SortitionDB {
     }
 
     /// Get the database schema version, given a DB connection
-    fn get_schema_version(conn: &Connection) -> Result<Option<String>, db_error> {
+    pub fn get_schema_version(conn: &Connection) -> Result<Option<String>, db_error> {
         let version = conn
             .query_row(
                 "SELECT MAX(version) from db_config",
@@ -2566,23 +2566,25 @@ impl SortitionDB {
         &mut self,
         epochs: &[StacksEpoch],
     ) -> Result<(), db_error> {
+        let tx = self.tx_begin()?;
+        SortitionDB::apply_schema_migrations(&tx.deref(), epochs)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Apply all schema migrations up to the latest schema, given the set of system epochs.
+    pub fn apply_schema_migrations(tx: &DBTx, epochs: &[StacksEpoch]) -> Result<(), db_error> {
         let expected_version = SORTITION_DB_VERSION.to_string();
         loop {
-            match SortitionDB::get_schema_version(self.conn()) {
+            match SortitionDB::get_schema_version(tx) {
                 Ok(Some(version)) => {
                     if version == "1" {
-                        let tx = self.tx_begin()?;
-                        SortitionDB::apply_schema_2(&tx.deref(), epochs)?;
-                        tx.commit()?;
+                        SortitionDB::apply_schema_2(tx, epochs)?;
                     } else if version == "2" {
                         // add the tables of schema 3, but do not populate them.
-                        let tx = self.tx_begin()?;
-                        SortitionDB::apply_schema_3(&tx.deref())?;
-                        tx.commit()?;
+                        SortitionDB::apply_schema_3(tx)?;
                     } else if version == "3" {
-                        let tx = self.tx_begin()?;
-                        SortitionDB::apply_schema_4(&tx.deref())?;
-                        tx.commit()?;
+                        SortitionDB::apply_schema_4(tx)?;
                     } else if version == expected_version {
                         return Ok(());
                     } else {
@@ -60,6 +60,9 @@ pub struct CoordinatorChannels {
     stacks_blocks_processed: Arc<AtomicU64>,
     /// how many sortitions have been processed by this Coordinator thread since startup?
     sortitions_processed: Arc<AtomicU64>,
+    /// Held by the Coordinator while it processes an event, and by anyone
+    ///  who needs it to stop writing to the chainstate for a while
+    processing: Arc<Mutex<()>>,
 }
 
 /// Notification struct for communicating to
@@ -83,6 +86,8 @@ pub struct CoordinatorReceivers {
     signal_wakeup: Arc<Condvar>,
     pub stacks_blocks_processed: Arc<AtomicU64>,
     pub sortitions_processed: Arc<AtomicU64>,
+    /// Held while processing an event
+    processing: Arc<Mutex<()>>,
 }
 
 /// Static struct used to hold all the static methods
@@ -123,6 +128,16 @@ impl CoordinatorReceivers {
         }
         signal_bools.receive_signal()
     }
+
+    /// Blocks while the coordinator is paused.  Processing of an event
+    ///  must hold the returned guard until it is done.
+    pub fn begin_processing(&self) -> MutexGuard<'_, ()> {
+        // a thread that paused the coordinator and then panicked leaves
+        //  nothing inconsistent behind
+        self.processing
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
 }
 
 impl CoordinatorChannels {
@@ -147,6 +162,16 @@ impl CoordinatorChannels {
         false
     }
 
+    /// Waits for the coordinator to finish the event it is processing, and
+    ///  keeps it from processing another until the returned guard is dropped.
+    ///  While paused, the coordinator does not write to the sortition DB or
+    ///  the chainstate, so copies taken of them agree with each other.
+    pub fn pause_chains_coordinator(&self) -> MutexGuard<'_, ()> {
+        self.processing
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
     pub fn is_stopped(&self) -> bool {
         let bools = self.signal_bools.lock().unwrap();
         bools.stop.clone()
@@ -197,6 +222,7 @@ impl CoordinatorCommunication {
 
         let stacks_blocks_processed = Arc::new(AtomicU64::new(0));
         let sortitions_processed = Arc::new(AtomicU64::new(0));
+        let processing = Arc::new(Mutex::new(()));
 
         let senders = CoordinatorChannels {
             signal_bools: signal_bools.clone(),
@@ -204,6 +230,7 @@ impl CoordinatorCommunication {
             stacks_blocks_processed: stacks_blocks_processed.clone(),
 
             sortitions_processed: sortitions_processed.clone(),
+            processing: processing.clone(),
         };
 
         let rcvrs = CoordinatorReceivers {
@@ -211,6 +238,7 @@ impl CoordinatorCommunication {
             signal_wakeup: signal_wakeup,
             stacks_blocks_processed,
             sortitions_processed,
+            processing,
         };
 
         (rcvrs, senders)
@@ -35,8 +35,8 @@ use crate::chainstate::coordinator::comm::{
 use crate::chainstate::stacks::index::MarfTrieId;
 use crate::chainstate::stacks::{
     db::{
-        accounts::MinerReward, ChainStateBootData, ClarityTx, MinerRewardInfo, StacksChainState,
-        StacksHeaderInfo,
+        accounts::MinerReward, blocks::BlockValidationTelemetry, ChainStateBootData, ClarityTx,
+        MinerRewardInfo, StacksChainState, StacksHeaderInfo,
     },
     events::{StacksTransactionEvent, StacksTransactionReceipt, TransactionOrigin},
     Error as ChainstateError, StacksBlock, TransactionPayload,
@@ -44,6 +44,7 @@ use crate::chainstate::stacks::{
 use crate::core::StacksEpoch;
 use crate::monitoring::{
     increment_contract_calls_processed, increment_stx_blocks_processed_counter,
+    log_sortition_processed, log_stacks_block_synced, update_stacks_sync_target,
 };
 use crate::net::atlas::{AtlasConfig, AttachmentInstance};
 use crate::util_lib::db::Error as DBError;
@@ -145,6 +146,9 @@ pub trait BlockEventDispatcher {
     );
 
     fn dispatch_boot_receipts(&mut self, receipts: Vec<StacksTransactionReceipt>);
+
+    /// called once a processed block is committed, with how long its validation took
+    fn announce_block_telemetry(&self, telemetry: &BlockValidationTelemetry);
 }
 
 pub struct ChainsCoordinator<
@@ -312,12 +316,14 @@ impl<'a, T: BlockEventDispatcher, CE: CostEstimator + ?Sized, FE: FeeEstimator +
             match comms.wait_on() {
                 CoordinatorEvents::NEW_STACKS_BLOCK => {
                     debug!("Received new stacks block notice");
+                    let _processing = comms.begin_processing();
                     if let Err(e) = inst.handle_new_stacks_block() {
                         warn!("Error processing new stacks block: {:?}", e);
                     }
                 }
                 CoordinatorEvents::NEW_BURN_BLOCK => {
                     debug!("Received new burn block notice");
+                    let _processing = comms.begin_processing();
                     if let Err(e) = inst.handle_new_burnchain_block() {
                         warn!("Error processing new burn block: {:?}", e);
                     }
@@ -642,6 +648,23 @@ impl<
 
             let sortition_id = next_snapshot.sortition_id;
 
+            if next_snapshot.sortition {
+                log_sortition_processed(&next_snapshot.consensus_hash);
+                update_stacks_sync_target(
+                    self.burnchain.first_block_height,
+                    next_snapshot.block_height,
+                );
+            }
+            if let Ok(Some(stacks_tip_snapshot)) = SortitionDB::get_block_snapshot_consensus(
+                self.sortition_db.conn(),
+                &next_snapshot.canonical_stacks_tip_consensus_hash,
+            ) {
+                log_stacks_block_synced(
+                    next_snapshot.canonical_stacks_tip_height,
+                    stacks_tip_snapshot.block_height,
+                );
+            }
+
             self.notifier.notify_sortition_processed();
 
             debug!(
@@ -657,6 +680,11 @@ impl<
             self.canonical_sortition_tip = Some(sortition_id.clone());
             last_processed_ancestor = sortition_id;
 
+            // move a few more trie blobs out of the Clarity MARF's DB, if it is being migrated
+            if let Err(e) = self.chain_state_db.migrate_trie_blobs_step() {
+                warn!("ChainsCoordinator: failed to migrate trie blobs: {:?}", &e);
+            }
+
             if let Some(pox_anchor) = self.process_ready_blocks()? {
                 return self.process_new_pox_anchor(pox_anchor);
             }
@@ -733,6 +761,10 @@ impl<
                     debug!("Bump blocks processed");
                     self.notifier.notify_stacks_block_processed();
                     increment_stx_blocks_processed_counter();
+                    log_stacks_block_synced(
+                        block_receipt.header.stacks_block_height,
+                        block_receipt.header.burn_header_height as u64,
+                    );
 
                     let block_hash = block_receipt.header.anchored_header.block_hash();
 
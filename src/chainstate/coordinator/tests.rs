@@ -34,7 +34,8 @@ use crate::chainstate::burn::operations::*;
 use crate::chainstate::burn::*;
 use crate::chainstate::coordinator::{Error as CoordError, *};
 use crate::chainstate::stacks::db::{
-    accounts::MinerReward, ClarityTx, StacksChainState, StacksHeaderInfo,
+    accounts::MinerReward, blocks::BlockValidationTelemetry, ClarityTx, StacksChainState,
+    StacksHeaderInfo,
 };
 use crate::chainstate::stacks::*;
 use crate::clarity_vm::clarity::ClarityConnection;
@@ -333,6 +334,8 @@ impl BlockEventDispatcher for NullEventDispatcher {
     }
 
     fn dispatch_boot_receipts(&mut self, _receipts: Vec<StacksTransactionReceipt>) {}
+
+    fn announce_block_telemetry(&self, _telemetry: &BlockValidationTelemetry) {}
 }
 
 pub fn make_coordinator<'a>(
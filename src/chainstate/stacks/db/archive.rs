@@ -0,0 +1,271 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2022 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Export of chain history as content-addressed IPLD blocks, for archives pinned on
+//! IPFS/Filecoin.
+//!
+//! Every exported Stacks block is written as a `raw` IPLD block holding its consensus
+//! serialization, alongside the microblocks it confirmed. A DAG-CBOR index node per block ties
+//! these together and records the block's consensus hash, block hash, index block hash, and
+//! parent. Index nodes only depend on the block they describe, so the same block gets the same
+//! CID in every archive that contains it. The archive's root is a manifest listing the index
+//! nodes in height order.
+
+use std::io::Write;
+
+use crate::chainstate::stacks::db::*;
+use crate::chainstate::stacks::Error;
+use crate::chainstate::stacks::*;
+use crate::core::FIRST_BURNCHAIN_CONSENSUS_HASH;
+use crate::util_lib::car::{write_car_block, Cid, IpldValue, CODEC_DAG_CBOR, CODEC_RAW};
+use crate::util_lib::db::Error as db_error;
+
+fn io_error(e: std::io::Error) -> Error {
+    Error::DBError(db_error::IOError(e))
+}
+
+impl StacksChainState {
+    /// Write the blocks and confirmed microblocks of the Stacks chain ending at `tip`, from
+    /// height `start_height` to `tip`'s height, as CARv1 sections into `fd`. The boot block is
+    /// never exported.
+    ///
+    /// Returns the CID of the archive's manifest, which the caller must name as the root in the
+    /// CAR header (see `util_lib::car::write_car_header`) that precedes these sections.
+    pub fn export_car_blocks<W: Write>(
+        &self,
+        tip: StacksHeaderInfo,
+        start_height: u64,
+        fd: &mut W,
+    ) -> Result<Cid, Error> {
+        let end_height = tip.stacks_block_height;
+        let tip_index_block_hash = tip.index_block_hash();
+        let mut headers = StacksChainState::get_ancestors_headers(self.db(), tip, start_height)?;
+        headers.reverse();
+
+        let mut index_links = vec![];
+        for header in headers.into_iter() {
+            if header.consensus_hash == FIRST_BURNCHAIN_CONSENSUS_HASH {
+                continue;
+            }
+            let index_cid = self.export_car_block(&header, fd)?;
+            index_links.push(IpldValue::Link(index_cid));
+        }
+
+        let manifest = IpldValue::Map(vec![
+            ("blocks".to_string(), IpldValue::List(index_links)),
+            ("start_height".to_string(), IpldValue::Uint(start_height)),
+            ("end_height".to_string(), IpldValue::Uint(end_height)),
+            (
+                "tip".to_string(),
+                IpldValue::Bytes(tip_index_block_hash.as_bytes().to_vec()),
+            ),
+        ]);
+        write_car_block(fd, CODEC_DAG_CBOR, &manifest.to_dag_cbor()).map_err(io_error)
+    }
+
+    /// Write one block, the microblocks it confirms, and its index node. Returns the index
+    /// node's CID.
+    fn export_car_block<W: Write>(
+        &self,
+        header: &StacksHeaderInfo,
+        fd: &mut W,
+    ) -> Result<Cid, Error> {
+        let block_hash = header.anchored_header.block_hash();
+        let index_block_hash = header.index_block_hash();
+
+        let block_bytes = StacksChainState::load_block_bytes(
+            &self.blocks_path,
+            &header.consensus_hash,
+            &block_hash,
+        )?
+        .ok_or(Error::NoSuchBlockError)?;
+        let block_cid = write_car_block(fd, CODEC_RAW, &block_bytes).map_err(io_error)?;
+
+        let parent_block_id = StacksChainState::get_parent_block_id(self.db(), &index_block_hash)?
+            .ok_or(Error::NoSuchBlockError)?;
+
+        let mut microblock_links = vec![];
+        if header.anchored_header.has_microblock_parent() {
+            let parent = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                self.db(),
+                &parent_block_id,
+            )?
+            .ok_or(Error::NoSuchBlockError)?;
+            let microblocks = StacksChainState::load_processed_microblock_stream_fork(
+                self.db(),
+                &parent.consensus_hash,
+                &parent.anchored_header.block_hash(),
+                &header.anchored_header.parent_microblock,
+            )?
+            .ok_or(Error::NoSuchBlockError)?;
+            for microblock in microblocks.iter() {
+                let microblock_cid = write_car_block(fd, CODEC_RAW, &microblock.serialize_to_vec())
+                    .map_err(io_error)?;
+                microblock_links.push(IpldValue::Link(microblock_cid));
+            }
+        }
+
+        let index_node = IpldValue::Map(vec![
+            ("block".to_string(), IpldValue::Link(block_cid)),
+            ("microblocks".to_string(), IpldValue::List(microblock_links)),
+            (
+                "consensus_hash".to_string(),
+                IpldValue::Bytes(header.consensus_hash.as_bytes().to_vec()),
+            ),
+            (
+                "block_hash".to_string(),
+                IpldValue::Bytes(block_hash.as_bytes().to_vec()),
+            ),
+            (
+                "index_block_hash".to_string(),
+                IpldValue::Bytes(index_block_hash.as_bytes().to_vec()),
+            ),
+            (
+                "parent_block_id".to_string(),
+                IpldValue::Bytes(parent_block_id.as_bytes().to_vec()),
+            ),
+            (
+                "height".to_string(),
+                IpldValue::Uint(header.stacks_block_height),
+            ),
+        ]);
+        write_car_block(fd, CODEC_DAG_CBOR, &index_node.to_dag_cbor()).map_err(io_error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::chainstate::burn::db::sortdb::SortitionDB;
+    use crate::chainstate::stacks::miner::test::make_coinbase;
+    use crate::chainstate::stacks::miner::BlockBuilderSettings;
+    use crate::core::MemPoolDB;
+    use crate::net::test::*;
+    use crate::util_lib::car::read_car_block;
+    use stacks_common::util::hash::Hash160;
+
+    #[test]
+    fn test_export_car_blocks() {
+        let peer_config = TestPeerConfig::new("test_export_car_blocks", 4220, 4221);
+        let mut peer = TestPeer::new(peer_config);
+        let chainstate_path = peer.chainstate_path.clone();
+
+        let mut blocks = vec![];
+        for tenure_id in 0..3 {
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+                |ref mut miner,
+                 ref mut sortdb,
+                 ref mut chainstate,
+                 vrf_proof,
+                 ref parent_opt,
+                 ref _parent_microblock_header_opt| {
+                    let parent_tip = match parent_opt {
+                        None => StacksChainState::get_genesis_header_info(chainstate.db()).unwrap(),
+                        Some(block) => {
+                            let snapshot =
+                                SortitionDB::get_block_snapshot_for_winning_stacks_block(
+                                    &sortdb.index_conn(),
+                                    &tip.sortition_id,
+                                    &block.block_hash(),
+                                )
+                                .unwrap()
+                                .unwrap();
+                            StacksChainState::get_anchored_block_header_info(
+                                chainstate.db(),
+                                &snapshot.consensus_hash,
+                                &snapshot.winning_stacks_block_hash,
+                            )
+                            .unwrap()
+                            .unwrap()
+                        }
+                    };
+
+                    let mut mempool =
+                        MemPoolDB::open_test(false, 0x80000000, &chainstate_path).unwrap();
+                    let coinbase_tx = make_coinbase(miner, tenure_id);
+                    let anchored_block = StacksBlockBuilder::build_anchored_block(
+                        chainstate,
+                        &sortdb.index_conn(),
+                        &mut mempool,
+                        &parent_tip,
+                        tip.total_burn,
+                        vrf_proof,
+                        Hash160([tenure_id as u8; 20]),
+                        &coinbase_tx,
+                        BlockBuilderSettings::max_value(),
+                        None,
+                    )
+                    .unwrap();
+                    (anchored_block.0, vec![])
+                },
+            );
+            peer.next_burnchain_block(burn_ops.clone());
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+            blocks.push(stacks_block);
+        }
+
+        let (tip_consensus_hash, tip_block_hash) =
+            SortitionDB::get_canonical_stacks_chain_tip_hash(peer.sortdb.as_ref().unwrap().conn())
+                .unwrap();
+        let chainstate = peer.chainstate();
+        let tip = StacksChainState::get_anchored_block_header_info(
+            chainstate.db(),
+            &tip_consensus_hash,
+            &tip_block_hash,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(tip.stacks_block_height, 3);
+
+        let read_archive = |archive: &[u8]| {
+            let mut fd = archive;
+            let mut sections = vec![];
+            while let Some(section) = read_car_block(&mut fd).unwrap() {
+                sections.push(section);
+            }
+            sections
+        };
+
+        // exporting from the boot block skips it
+        let mut archive = vec![];
+        let root = chainstate
+            .export_car_blocks(tip.clone(), 0, &mut archive)
+            .unwrap();
+        let sections = read_archive(&archive);
+        // a block and its index node per block, then the manifest
+        assert_eq!(sections.len(), 2 * blocks.len() + 1);
+        for (i, block) in blocks.iter().enumerate() {
+            let (cid, data) = &sections[2 * i];
+            assert_eq!(cid.codec, CODEC_RAW);
+            assert_eq!(data, &block.serialize_to_vec());
+            assert_eq!(sections[2 * i + 1].0.codec, CODEC_DAG_CBOR);
+        }
+        assert_eq!(sections.last().unwrap().0, root);
+
+        // index nodes do not depend on the exported range
+        let mut partial_archive = vec![];
+        chainstate
+            .export_car_blocks(tip, 2, &mut partial_archive)
+            .unwrap();
+        let partial_sections = read_archive(&partial_archive);
+        assert_eq!(partial_sections.len(), 2 * 2 + 1);
+        assert_eq!(partial_sections[..4], sections[2..6]);
+    }
+}
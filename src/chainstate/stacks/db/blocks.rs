@@ -23,6 +23,7 @@ use std::io;
 use std::io::prelude::*;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 use clarity::vm::ast::ASTRules;
 use rand::thread_rng;
@@ -59,7 +60,7 @@ use crate::util_lib::db::u64_to_sql;
 use crate::util_lib::db::Error as db_error;
 use crate::util_lib::db::{
     query_count, query_int, query_row, query_row_columns, query_row_panic, query_rows,
-    tx_busy_handler, DBConn, FromColumn, FromRow,
+    sqlite_bytes_written, tx_busy_handler, DBConn, FromColumn, FromRow,
 };
 use crate::util_lib::strings::StacksString;
 pub use clarity::vm::analysis::errors::{CheckError, CheckErrors};
@@ -82,7 +83,10 @@ use crate::chainstate::coordinator::BlockEventDispatcher;
 use crate::chainstate::stacks::address::StacksAddressExtensions;
 use crate::chainstate::stacks::StacksBlockHeader;
 use crate::chainstate::stacks::StacksMicroblockHeader;
-use crate::monitoring::{set_last_block_transaction_count, set_last_execution_cost_observed};
+use crate::monitoring::{
+    log_block_received, log_block_validated, log_microblock_received,
+    set_last_block_transaction_count, set_last_execution_cost_observed,
+};
 use crate::util_lib::boot::boot_code_id;
 use crate::{types, util};
 use stacks_common::types::chainstate::BurnchainHeaderHash;
@@ -133,6 +137,9 @@ pub enum MemPoolRejection {
     DeserializationFailure(codec_error),
     FailedToValidate(Error),
     FeeTooLow(u64, u64),
+    /// The transaction's fee rate, per unit of the cost metric, is below the node's minimum
+    /// (actual, expected)
+    FeeRateTooLow(f64, f64),
     BadNonces(TransactionNonceMismatch),
     NotEnoughFunds(u128, u128),
     NoSuchContract,
@@ -152,12 +159,21 @@ pub enum MemPoolRejection {
         principal: PrincipalData,
         is_origin: bool,
     },
+    /// Together with the transactions it already has pending in the mempool, the transaction
+    /// would commit the principal to spend more STX than it has available
+    TooMuchPendingSpend {
+        principal: PrincipalData,
+        pending: u128,
+        spend: u128,
+        available: u128,
+    },
     BadTransactionVersion,
     TransferRecipientIsSender(PrincipalData),
     TransferAmountMustBePositive,
     DBError(db_error),
     EstimatorError(EstimatorError),
     TemporarilyBlacklisted,
+    WillAbort(String),
     Other(String),
 }
 
@@ -174,6 +190,62 @@ pub struct SetupBlockResult<'a, 'b> {
     pub applied_epoch_transition: bool,
 }
 
+/// What executing a valid proposed block found.  See `validate_block_proposal`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidatedBlockProposal {
+    pub block_hash: BlockHeaderHash,
+    pub parent_block_id: StacksBlockId,
+    pub stacks_height: u64,
+    pub block_size: u64,
+    /// Number of parent microblocks the block confirms
+    pub num_microblocks: usize,
+    pub microblocks_cost: ExecutionCost,
+    pub block_cost: ExecutionCost,
+    pub microblock_fees: u128,
+    pub anchored_fees: u128,
+    /// Receipts of the confirmed microblock transactions, of the burnchain operations, and of the
+    /// block's transactions, in that order
+    pub tx_receipts: Vec<StacksTransactionReceipt>,
+}
+
+/// A processed block, re-executed by `replay_block`, with everything that was announced to the
+/// event dispatcher when it was first processed.
+#[derive(Debug, Clone)]
+pub struct ReplayedBlock {
+    pub block: StacksBlock,
+    pub parent_block_id: StacksBlockId,
+    pub winning_block_txid: Txid,
+    pub epoch_receipt: StacksEpochReceipt,
+}
+
+/// How a processed block's validation went, as announced to the event dispatcher once the block
+/// is committed.  Times are wall-clock times, in microseconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockValidationTelemetry {
+    pub block_hash: BlockHeaderHash,
+    pub consensus_hash: ConsensusHash,
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+    pub block_size: u64,
+    /// Number of transactions in the block and the microblocks it confirms, and of burnchain
+    /// operations
+    pub tx_count: u64,
+    pub microblock_count: u64,
+    /// From loading the block out of the staging blocks to committing it
+    pub validation_time_us: u64,
+    /// Executing the confirmed microblocks' and the block's transactions, and computing the
+    /// block's state root
+    pub tx_processing_time_us: u64,
+    /// Writing the Clarity MARF and the chainstate to disk
+    pub marf_commit_time_us: u64,
+    /// Announcing the block to the event dispatcher
+    pub event_generation_time_us: u64,
+    /// Total cost of the block and the microblocks it confirms
+    pub execution_cost: ExecutionCost,
+    /// Bytes written to the chainstate and Clarity databases
+    pub sqlite_write_bytes: u64,
+}
+
 pub struct DummyEventDispatcher;
 
 impl BlockEventDispatcher for DummyEventDispatcher {
@@ -218,6 +290,13 @@ impl BlockEventDispatcher for DummyEventDispatcher {
             "We should never try to dispatch boot receipts to the dummy dispatcher"
         );
     }
+
+    fn announce_block_telemetry(&self, _telemetry: &BlockValidationTelemetry) {
+        assert!(
+            false,
+            "We should never try to announce to the dummy dispatcher"
+        );
+    }
 }
 
 impl MemPoolRejection {
@@ -256,6 +335,12 @@ impl MemPoolRejection {
                                                 "expected": expected,
                                                 "actual": actual})),
             ),
+            FeeRateTooLow(actual, expected) => (
+                "FeeRateTooLow",
+                Some(json!({
+                                                "expected": expected,
+                                                "actual": actual})),
+            ),
             TransferRecipientIsSender(recipient) => (
                 "TransferRecipientCannotEqualSender",
                 Some(json!({"recipient": recipient.to_string()})),
@@ -275,6 +360,21 @@ impl MemPoolRejection {
                      "principal": principal.to_string(),
                      "is_origin": is_origin})),
             ),
+            TooMuchPendingSpend {
+                principal,
+                pending,
+                spend,
+                available,
+            } => (
+                "TooMuchPendingSpend",
+                Some(json!({
+                    "message": "Pending transactions would spend more than the available balance",
+                    "principal": principal.to_string(),
+                    "pending": format!("0x{}", to_hex(&pending.to_be_bytes())),
+                    "spend": format!("0x{}", to_hex(&spend.to_be_bytes())),
+                    "available": format!("0x{}", to_hex(&available.to_be_bytes()))
+                })),
+            ),
             NotEnoughFunds(expected, actual) => (
                 "NotEnoughFunds",
                 Some(json!({
@@ -306,6 +406,7 @@ impl MemPoolRejection {
                 Some(json!({"message": e.to_string()})),
             ),
             TemporarilyBlacklisted => ("TemporarilyBlacklisted", None),
+            WillAbort(reason) => ("WillAbort", Some(json!({ "message": reason }))),
             Other(s) => ("ServerFailureOther", Some(json!({ "message": s }))),
         };
         let mut result = json!({
@@ -1687,6 +1788,113 @@ impl StacksChainState {
         }
     }
 
+    /// Find the index block hash of the anchored block with the given block hash that has
+    /// microblocks built on it.  If there are several (i.e. the same block was mined in several
+    /// sortitions), prefer one whose microblocks were confirmed.
+    fn get_microblock_parent_index_hash(
+        blocks_conn: &DBConn,
+        parent_block_hash: &BlockHeaderHash,
+    ) -> Result<Option<StacksBlockId>, Error> {
+        let sql = "SELECT index_block_hash FROM staging_microblocks WHERE anchored_block_hash = ?1 AND orphaned = 0 ORDER BY processed DESC LIMIT 1";
+        let args: &[&dyn ToSql] = &[parent_block_hash];
+        let mut rows =
+            query_row_columns::<StacksBlockId, _>(blocks_conn, sql, args, "index_block_hash")
+                .map_err(Error::DBError)?;
+        Ok(rows.pop())
+    }
+
+    /// Load up the microblock stream built on the anchored block with the given block hash,
+    /// processed or not.  If any of its microblocks were confirmed, then only the confirmed
+    /// microblocks are returned.  Otherwise, the longest non-forked stream is returned.
+    ///
+    /// DO NOT USE IN CONSENSUS CODE.
+    pub fn load_microblock_stream_by_parent(
+        blocks_conn: &DBConn,
+        parent_block_hash: &BlockHeaderHash,
+    ) -> Result<Option<Vec<StacksMicroblock>>, Error> {
+        let parent_index_block_hash = match StacksChainState::get_microblock_parent_index_hash(
+            blocks_conn,
+            parent_block_hash,
+        )? {
+            Some(index_block_hash) => index_block_hash,
+            None => {
+                return Ok(None);
+            }
+        };
+
+        let sql = "SELECT * FROM staging_microblocks WHERE index_block_hash = ?1 AND processed = 1 AND orphaned = 0 ORDER BY sequence ASC";
+        let args: &[&dyn ToSql] = &[&parent_index_block_hash];
+        let confirmed =
+            query_rows::<StagingMicroblock, _>(blocks_conn, sql, args).map_err(Error::DBError)?;
+        if confirmed.len() == 0 {
+            return StacksChainState::load_descendant_staging_microblock_stream(
+                blocks_conn,
+                &parent_index_block_hash,
+                0,
+                u16::MAX,
+            );
+        }
+
+        let mut ret = vec![];
+        for staging_microblock in confirmed.iter() {
+            match StacksChainState::load_staging_microblock_bytes(
+                blocks_conn,
+                &staging_microblock.microblock_hash,
+            )? {
+                Some(mblock_data) => {
+                    ret.push(StacksMicroblock::consensus_deserialize(
+                        &mut &mblock_data[..],
+                    )?);
+                }
+                None => {
+                    // data was deleted
+                    break;
+                }
+            }
+        }
+        Ok(Some(ret))
+    }
+
+    /// Load up the microblock with the given sequence number that was built on the anchored block
+    /// with the given block hash, processed or not.  If there are several, then prefer a
+    /// confirmed one.  Ties are broken arbitrarily.
+    ///
+    /// DO NOT USE IN CONSENSUS CODE.
+    pub fn load_microblock_by_parent_and_sequence(
+        blocks_conn: &DBConn,
+        parent_block_hash: &BlockHeaderHash,
+        sequence: u16,
+    ) -> Result<Option<StacksMicroblock>, Error> {
+        let parent_index_block_hash = match StacksChainState::get_microblock_parent_index_hash(
+            blocks_conn,
+            parent_block_hash,
+        )? {
+            Some(index_block_hash) => index_block_hash,
+            None => {
+                return Ok(None);
+            }
+        };
+
+        let sql = "SELECT * FROM staging_microblocks WHERE index_block_hash = ?1 AND sequence = ?2 AND orphaned = 0 ORDER BY processed DESC LIMIT 1";
+        let args: &[&dyn ToSql] = &[&parent_index_block_hash, &sequence];
+        let staging_microblock = match query_row::<StagingMicroblock, _>(blocks_conn, sql, args)? {
+            Some(staging_microblock) => staging_microblock,
+            None => {
+                return Ok(None);
+            }
+        };
+
+        match StacksChainState::load_staging_microblock_bytes(
+            blocks_conn,
+            &staging_microblock.microblock_hash,
+        )? {
+            Some(mblock_data) => Ok(Some(StacksMicroblock::consensus_deserialize(
+                &mut &mblock_data[..],
+            )?)),
+            None => Ok(None),
+        }
+    }
+
     /// Load up the next block in a microblock stream, assuming there is only one child.
     /// If there are zero children, or more than one child, then returns None.
     ///
@@ -3928,6 +4136,8 @@ impl StacksChainState {
 
         block_tx.commit()?;
 
+        log_block_received(consensus_hash, &block.block_hash());
+
         // ready to go
         Ok(true)
     }
@@ -4029,6 +4239,8 @@ impl StacksChainState {
 
         blocks_tx.commit()?;
 
+        log_microblock_received(parent_consensus_hash);
+
         Ok(true)
     }
 
@@ -5476,6 +5688,7 @@ impl StacksChainState {
         .expect("FATAL: failed to advance chain tip");
 
         chainstate_tx.log_transactions_processed(&new_tip.index_block_hash(), &tx_receipts);
+        chainstate_tx.log_account_events(&new_tip, &tx_receipts);
 
         set_last_block_transaction_count(block.txs.len() as u64);
         set_last_execution_cost_observed(&block_execution_cost, &block_limit);
@@ -5496,6 +5709,443 @@ impl StacksChainState {
         Ok((epoch_receipt, clarity_commit))
     }
 
+    /// Validate a block that a miner proposes to build on the processed block
+    /// `parent_consensus_hash`/`block.header.parent_block`, without storing or relaying it.  The
+    /// block is checked statically, then executed on top of its parent and the parent microblocks
+    /// it confirms, as the miner would have built it: against the canonical burnchain tip, before
+    /// the sortition that elects it.  Nothing is committed.
+    ///
+    /// `microblocks` is the parent microblock stream the block confirms; if None, the stream is
+    /// loaded from the staging microblocks.
+    ///
+    /// Returns Err(Error::InvalidStacksBlock(..)) with the reason if the block is invalid.
+    pub fn validate_block_proposal(
+        &mut self,
+        sort_ic: &SortitionDBConn,
+        parent_consensus_hash: &ConsensusHash,
+        block: &StacksBlock,
+        microblocks: Option<Vec<StacksMicroblock>>,
+    ) -> Result<ValidatedBlockProposal, Error> {
+        let block_hash = block.block_hash();
+        let reject = |msg: String| {
+            warn!("Rejected block proposal {}: {}", &block_hash, &msg);
+            Error::InvalidStacksBlock(msg)
+        };
+
+        let block_size = block.serialize_to_vec().len() as u64;
+        if block_size > MAX_EPOCH_SIZE.into() {
+            return Err(reject(format!(
+                "Block is {} bytes, more than the maximum of {}",
+                block_size, MAX_EPOCH_SIZE
+            )));
+        }
+        if !block.validate_transactions_static(self.mainnet, self.chain_id) {
+            return Err(reject("Transactions failed static checks".to_string()));
+        }
+
+        let (parent_consensus_hash, parent_block_hash) = if block.is_first_mined() {
+            (
+                FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+                FIRST_STACKS_BLOCK_HASH.clone(),
+            )
+        } else {
+            (
+                parent_consensus_hash.clone(),
+                block.header.parent_block.clone(),
+            )
+        };
+        let parent_block_id =
+            StacksBlockHeader::make_index_block_hash(&parent_consensus_hash, &parent_block_hash);
+        let parent_header_info = StacksChainState::get_anchored_block_header_info(
+            self.db(),
+            &parent_consensus_hash,
+            &parent_block_hash,
+        )?
+        .ok_or_else(|| {
+            reject(format!(
+                "Parent block {}/{} has not been processed",
+                &parent_consensus_hash, &parent_block_hash
+            ))
+        })?;
+        if parent_header_info.stacks_block_height + 1 != block.header.total_work.work {
+            return Err(reject(format!(
+                "Block height is {}, but its parent is at height {}",
+                block.header.total_work.work, parent_header_info.stacks_block_height
+            )));
+        }
+
+        let parent_microblocks = if !block.has_microblock_parent() {
+            vec![]
+        } else {
+            if StacksChainState::block_crosses_epoch_boundary(
+                self.db(),
+                &parent_consensus_hash,
+                &parent_block_hash,
+            )? {
+                return Err(reject(
+                    "Block confirms microblocks, but its parent crossed an epoch boundary"
+                        .to_string(),
+                ));
+            }
+            let mut microblocks = match microblocks {
+                Some(microblocks) => microblocks,
+                None => StacksChainState::load_microblock_stream_fork(
+                    self.db(),
+                    &parent_consensus_hash,
+                    &parent_block_hash,
+                    &block.header.parent_microblock,
+                )?
+                .ok_or_else(|| {
+                    reject(format!(
+                        "Parent microblock {} is not stored",
+                        &block.header.parent_microblock
+                    ))
+                })?,
+            };
+            microblocks.sort_by_key(|microblock| microblock.header.sequence);
+            if let Some(microblock) = microblocks.iter().find(|microblock| {
+                !microblock.validate_transactions_static(self.mainnet, self.chain_id)
+            }) {
+                return Err(reject(format!(
+                    "Transactions in microblock {} failed static checks",
+                    microblock.block_hash()
+                )));
+            }
+            match StacksChainState::validate_parent_microblock_stream(
+                &parent_header_info.anchored_header,
+                &block.header,
+                &microblocks,
+                true,
+            ) {
+                Some((end, None)) => {
+                    microblocks.truncate(end);
+                    microblocks
+                }
+                Some((_, Some(_))) => {
+                    return Err(reject(
+                        "Parent microblock stream contains a deliberate fork".to_string(),
+                    ))
+                }
+                None => {
+                    return Err(reject(format!(
+                    "Parent microblock stream does not connect to parent microblock {} (seq {})",
+                    &block.header.parent_microblock, block.header.parent_microblock_sequence
+                )))
+                }
+            }
+        };
+
+        let burn_tip = SortitionDB::get_canonical_chain_tip_bhh(sort_ic.conn())?;
+        let burn_tip_height =
+            SortitionDB::get_canonical_burn_chain_tip(sort_ic.conn())?.block_height as u32 + 1;
+        let ast_rules = SortitionDB::get_ast_rules(sort_ic.conn(), burn_tip_height.into())?;
+        let mainnet = self.mainnet;
+
+        let (mut chainstate_tx, clarity_instance) = self.chainstate_tx_begin()?;
+        let SetupBlockResult {
+            mut clarity_tx,
+            mut tx_receipts,
+            microblock_execution_cost,
+            microblock_fees,
+            microblock_txs_receipts,
+            matured_miner_rewards_opt,
+            ..
+        } = match StacksChainState::setup_block(
+            &mut chainstate_tx,
+            clarity_instance,
+            sort_ic,
+            sort_ic.conn(),
+            &parent_header_info,
+            burn_tip,
+            burn_tip_height,
+            parent_consensus_hash,
+            parent_block_hash,
+            &parent_microblocks,
+            mainnet,
+            None,
+        ) {
+            Ok(setup_result) => setup_result,
+            Err(Error::InvalidStacksMicroblock(msg, _)) | Err(Error::InvalidStacksBlock(msg)) => {
+                return Err(reject(msg));
+            }
+            Err(e) => return Err(e),
+        };
+
+        match StacksChainState::has_microblock_pubkey_hash(
+            &mut clarity_tx,
+            &block.header.microblock_pubkey_hash,
+        ) {
+            Ok(None) => {}
+            Ok(Some(height)) => {
+                clarity_tx.rollback_block();
+                return Err(reject(format!(
+                    "Microblock public key hash {} was already used at height {}",
+                    &block.header.microblock_pubkey_hash, height
+                )));
+            }
+            Err(e) => {
+                clarity_tx.rollback_block();
+                return Err(e);
+            }
+        }
+
+        let (anchored_fees, _, block_txs_receipts) =
+            match StacksChainState::process_block_transactions(
+                &mut clarity_tx,
+                block,
+                microblock_txs_receipts.len() as u32,
+                ast_rules,
+            ) {
+                Ok(result) => result,
+                Err(e) => {
+                    clarity_tx.rollback_block();
+                    return Err(reject(format!("Failed to process transactions: {:?}", &e)));
+                }
+            };
+        let block_cost = clarity_tx.cost_so_far();
+
+        let miner_payouts =
+            matured_miner_rewards_opt.map(|(miner_reward, user_rewards, parent_reward, _)| {
+                (miner_reward, user_rewards, parent_reward)
+            });
+        if let Err(e) = StacksChainState::finish_block(
+            &mut clarity_tx,
+            miner_payouts,
+            block.header.total_work.work as u32,
+            block.header.microblock_pubkey_hash,
+        ) {
+            clarity_tx.rollback_block();
+            return Err(match e {
+                Error::InvalidStacksBlock(msg) => reject(msg),
+                e => e,
+            });
+        }
+
+        let root_hash = clarity_tx.seal();
+        clarity_tx.rollback_block();
+        if root_hash != block.header.state_index_root {
+            return Err(reject(format!(
+                "State root mismatch: expected {}, got {}",
+                &block.header.state_index_root, &root_hash
+            )));
+        }
+
+        let mut receipts = microblock_txs_receipts;
+        receipts.append(&mut tx_receipts);
+        receipts.extend(block_txs_receipts.into_iter());
+
+        debug!("Validated block proposal {}", &block_hash;
+               "parent_block_id" => %parent_block_id,
+               "microblocks" => parent_microblocks.len(),
+               "block_cost" => %block_cost);
+
+        Ok(ValidatedBlockProposal {
+            block_hash,
+            parent_block_id,
+            stacks_height: block.header.total_work.work,
+            block_size,
+            num_microblocks: parent_microblocks.len(),
+            microblocks_cost: microblock_execution_cost,
+            block_cost,
+            microblock_fees,
+            anchored_fees,
+            tx_receipts: receipts,
+        })
+    }
+
+    /// Re-execute the processed block `index_block_hash` on top of its parent, exactly as
+    /// `append_block` did when the block was first processed, and return the receipts and
+    /// rewards that were announced for it.  The block must be on the canonical fork of `sort_ic`.
+    /// Nothing is committed.
+    ///
+    /// Returns Err(Error::NoSuchBlockError) if the block was never processed, or is the boot
+    /// block (which has no transactions to replay).
+    pub fn replay_block(
+        &mut self,
+        sort_ic: &SortitionDBConn,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<ReplayedBlock, Error> {
+        let header = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            self.db(),
+            index_block_hash,
+        )?
+        .ok_or(Error::NoSuchBlockError)?;
+        if header.stacks_block_height == 0 {
+            return Err(Error::NoSuchBlockError);
+        }
+        let block_hash = header.anchored_header.block_hash();
+        let block =
+            StacksChainState::load_block(&self.blocks_path, &header.consensus_hash, &block_hash)?
+                .ok_or(Error::NoSuchBlockError)?;
+        let parent_header_id = StacksChainState::get_parent_block_id(self.db(), index_block_hash)?
+            .ok_or(Error::NoSuchBlockError)?;
+        let parent_header = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            self.db(),
+            &parent_header_id,
+        )?
+        .ok_or(Error::NoSuchBlockError)?;
+
+        let (parent_consensus_hash, parent_block_hash) = if block.is_first_mined() {
+            (
+                FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+                FIRST_STACKS_BLOCK_HASH.clone(),
+            )
+        } else {
+            (
+                parent_header.consensus_hash.clone(),
+                parent_header.anchored_header.block_hash(),
+            )
+        };
+        // the parent that was announced, as recorded when the block was staged
+        let staging_block = StacksChainState::load_staging_block_info(self.db(), index_block_hash)?
+            .ok_or(Error::NoSuchBlockError)?;
+        let parent_block_id = StacksBlockId::new(
+            &staging_block.parent_consensus_hash,
+            &staging_block.parent_anchored_block_hash,
+        );
+        let microblocks = if block.has_microblock_parent() {
+            StacksChainState::load_processed_microblock_stream_fork(
+                self.db(),
+                &parent_consensus_hash,
+                &parent_block_hash,
+                &block.header.parent_microblock,
+            )?
+            .ok_or(Error::NoSuchBlockError)?
+        } else {
+            vec![]
+        };
+
+        let snapshot =
+            SortitionDB::get_block_snapshot_consensus(sort_ic.conn(), &header.consensus_hash)?
+                .ok_or(Error::NoSuchBlockError)?;
+        let (parent_burn_block_hash, parent_burn_block_height, parent_burn_block_timestamp) =
+            if block.is_first_mined() {
+                (BurnchainHeaderHash([0; 32]), 0, 0)
+            } else {
+                match SortitionDB::get_block_snapshot_consensus(
+                    sort_ic.conn(),
+                    &parent_consensus_hash,
+                )? {
+                    Some(sn) => (
+                        sn.burn_header_hash,
+                        sn.block_height as u32,
+                        sn.burn_header_timestamp,
+                    ),
+                    None => (BurnchainHeaderHash([0; 32]), 0, 0),
+                }
+            };
+        let ast_rules =
+            SortitionDB::get_ast_rules(sort_ic.conn(), header.burn_header_height.into())?;
+        let mainnet = self.mainnet;
+
+        let (mut chainstate_tx, clarity_instance) = self.chainstate_tx_begin()?;
+        let SetupBlockResult {
+            mut clarity_tx,
+            mut tx_receipts,
+            microblock_execution_cost,
+            microblock_txs_receipts,
+            matured_miner_rewards_opt,
+            evaluated_epoch,
+            ..
+        } = StacksChainState::setup_block(
+            &mut chainstate_tx,
+            clarity_instance,
+            sort_ic,
+            sort_ic.conn(),
+            &parent_header,
+            snapshot.parent_burn_header_hash.clone(),
+            header.burn_header_height,
+            parent_consensus_hash,
+            parent_block_hash,
+            &microblocks,
+            mainnet,
+            None,
+        )?;
+
+        let (_, _, block_txs_receipts) = match StacksChainState::process_block_transactions(
+            &mut clarity_tx,
+            &block,
+            microblock_txs_receipts.len() as u32,
+            ast_rules,
+        ) {
+            Ok(result) => result,
+            Err(e) => {
+                clarity_tx.rollback_block();
+                return Err(Error::InvalidStacksBlock(format!(
+                    "Failed to replay block {}: {:?}",
+                    index_block_hash, &e
+                )));
+            }
+        };
+        tx_receipts.extend(block_txs_receipts.into_iter());
+        let block_cost = clarity_tx.cost_so_far();
+
+        let (matured_rewards, matured_rewards_info, miner_payouts_opt) =
+            if let Some((miner_reward, mut user_rewards, parent_reward, reward_ptr)) =
+                matured_miner_rewards_opt
+            {
+                let mut ret = vec![miner_reward.clone()];
+                ret.append(&mut user_rewards);
+                ret.push(parent_reward.clone());
+                (
+                    ret,
+                    Some(reward_ptr),
+                    Some((miner_reward, user_rewards, parent_reward)),
+                )
+            } else {
+                (vec![], None, None)
+            };
+
+        let mut lockup_events = match StacksChainState::finish_block(
+            &mut clarity_tx,
+            miner_payouts_opt,
+            block.header.total_work.work as u32,
+            block.header.microblock_pubkey_hash,
+        ) {
+            Ok(lockup_events) => lockup_events,
+            Err(e) => {
+                clarity_tx.rollback_block();
+                return Err(e);
+            }
+        };
+        if lockup_events.len() > 0 {
+            if let Some(receipt) = tx_receipts.get_mut(0) {
+                if receipt.is_coinbase_tx() {
+                    receipt.events.append(&mut lockup_events);
+                }
+            }
+        }
+
+        let root_hash = clarity_tx.seal();
+        clarity_tx.rollback_block();
+        if root_hash != block.header.state_index_root {
+            return Err(Error::InvalidStacksBlock(format!(
+                "Replayed block {} state root mismatch: expected {}, got {}",
+                index_block_hash, &block.header.state_index_root, &root_hash
+            )));
+        }
+
+        tx_receipts.extend(microblock_txs_receipts.into_iter());
+
+        Ok(ReplayedBlock {
+            block,
+            parent_block_id,
+            winning_block_txid: snapshot.winning_block_txid,
+            epoch_receipt: StacksEpochReceipt {
+                header,
+                tx_receipts,
+                matured_rewards,
+                matured_rewards_info,
+                parent_microblocks_cost: microblock_execution_cost,
+                anchored_block_cost: block_cost,
+                parent_burn_block_hash,
+                parent_burn_block_height,
+                parent_burn_block_timestamp,
+                evaluated_epoch,
+            },
+        })
+    }
+
     /// Verify that a Stacks anchored block attaches to its parent anchored block.
     /// * checks .header.total_work.work
     /// * checks .header.parent_block
@@ -5640,6 +6290,8 @@ impl StacksChainState {
         dispatcher_opt: Option<&'a T>,
     ) -> Result<(Option<StacksEpochReceipt>, Option<TransactionPayload>), Error> {
         let blocks_path = self.blocks_path.clone();
+        let validation_start = Instant::now();
+        let bytes_written_before = self.sqlite_bytes_written();
         let (mut chainstate_tx, clarity_instance) = self.chainstate_tx_begin()?;
 
         // this is a transaction against both the headers and staging blocks databases!
@@ -5789,6 +6441,7 @@ impl StacksChainState {
         // attach the block to the chain state and calculate the next chain tip.
         // Execute the confirmed microblocks' transactions against the chain state, and then
         // execute the anchored block's transactions against the chain state.
+        let tx_processing_start = Instant::now();
         let (epoch_receipt, clarity_commit) = match StacksChainState::append_block(
             &mut chainstate_tx,
             clarity_instance,
@@ -5901,6 +6554,10 @@ impl StacksChainState {
             )?;
         }
 
+        let tx_processing_time = tx_processing_start.elapsed();
+        log_block_validated(&epoch_receipt.header.consensus_hash, &block.block_hash());
+
+        let event_generation_start = Instant::now();
         if let Some(dispatcher) = dispatcher_opt {
             let parent_id = StacksBlockId::new(
                 &next_staging_block.parent_consensus_hash,
@@ -5921,6 +6578,7 @@ impl StacksChainState {
                 &epoch_receipt.parent_microblocks_cost,
             );
         }
+        let event_generation_time = event_generation_start.elapsed();
 
         StacksChainState::set_block_processed(
             chainstate_tx.deref_mut(),
@@ -5932,6 +6590,7 @@ impl StacksChainState {
         )?;
 
         // this will panic if the Clarity commit fails.
+        let marf_commit_start = Instant::now();
         clarity_commit.commit();
         chainstate_tx.commit()
             .unwrap_or_else(|e| {
@@ -5939,10 +6598,48 @@ impl StacksChainState {
                        "error" => ?e);
                 panic!()
             });
+        let marf_commit_time = marf_commit_start.elapsed();
+
+        if let Some(dispatcher) = dispatcher_opt {
+            let mut execution_cost = epoch_receipt.anchored_block_cost.clone();
+            if execution_cost
+                .add(&epoch_receipt.parent_microblocks_cost)
+                .is_err()
+            {
+                execution_cost = ExecutionCost::max_value();
+            }
+            let telemetry = BlockValidationTelemetry {
+                block_hash: block.block_hash(),
+                consensus_hash: epoch_receipt.header.consensus_hash.clone(),
+                index_block_hash: epoch_receipt.header.index_block_hash(),
+                block_height: epoch_receipt.header.stacks_block_height,
+                block_size,
+                tx_count: epoch_receipt.tx_receipts.len() as u64,
+                microblock_count: next_microblocks.len() as u64,
+                validation_time_us: validation_start.elapsed().as_micros() as u64,
+                tx_processing_time_us: tx_processing_time.as_micros() as u64,
+                marf_commit_time_us: marf_commit_time.as_micros() as u64,
+                event_generation_time_us: event_generation_time.as_micros() as u64,
+                execution_cost,
+                sqlite_write_bytes: self
+                    .sqlite_bytes_written()
+                    .saturating_sub(bytes_written_before),
+            };
+            dispatcher.announce_block_telemetry(&telemetry);
+        }
 
         Ok((Some(epoch_receipt), None))
     }
 
+    /// Bytes written so far by this chainstate's connections to its chainstate and Clarity
+    /// databases
+    fn sqlite_bytes_written(&mut self) -> u64 {
+        let clarity_bytes = self
+            .clarity_state
+            .with_marf(|marf| sqlite_bytes_written(marf.sqlite_conn()));
+        sqlite_bytes_written(self.db()) + clarity_bytes
+    }
+
     /// Process staging blocks at the canonical chain tip,
     ///  this only needs to be used in contexts that aren't
     ///  PoX aware (i.e., unit tests, and old stacks-node loops),
@@ -6191,6 +6888,38 @@ impl StacksChainState {
         }
     }
 
+    /// Get the nonce and the available STX balance of each of `principals` at the given chain
+    /// tip, so the mempool can account for what their pending transactions spend.
+    pub fn get_mempool_admission_accounts(
+        &mut self,
+        current_consensus_hash: &ConsensusHash,
+        current_block: &BlockHeaderHash,
+        principals: &[PrincipalData],
+    ) -> Result<Vec<(u64, u128)>, MemPoolRejection> {
+        let current_tip =
+            StacksChainState::get_parent_index_block(current_consensus_hash, current_block);
+        self.with_read_only_clarity_tx(&NULL_BURN_STATE_DB, &current_tip, |conn| {
+            let block_height = conn
+                .with_clarity_db_readonly(|ref mut db| db.get_current_burnchain_block_height())
+                as u64;
+            principals
+                .iter()
+                .map(|principal| {
+                    let account = StacksChainState::get_account(conn, principal);
+                    (
+                        account.nonce,
+                        account
+                            .stx_balance
+                            .get_available_balance_at_burn_block(block_height),
+                    )
+                })
+                .collect()
+        })
+        .ok_or_else(|| {
+            MemPoolRejection::NoSuchChainTip(current_consensus_hash.clone(), current_block.clone())
+        })
+    }
+
     /// Given an outstanding clarity connection, can we append the tx to the chain state?
     /// Used when mining transactions.
     fn can_include_tx<T: ClarityConnection>(
@@ -10616,6 +11345,284 @@ pub mod test {
         }
     }
 
+    #[test]
+    fn test_validate_block_proposal() {
+        let peer_config = TestPeerConfig::new("test_validate_block_proposal", 21313, 21314);
+        let mut peer = TestPeer::new(peer_config);
+
+        let chainstate_path = peer.chainstate_path.clone();
+
+        let num_blocks = 3;
+        for tenure_id in 0..num_blocks {
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+                |ref mut miner,
+                 ref mut sortdb,
+                 ref mut chainstate,
+                 vrf_proof,
+                 ref parent_opt,
+                 ref parent_microblock_header_opt| {
+                    let parent_tip = match parent_opt {
+                        None => StacksChainState::get_genesis_header_info(chainstate.db()).unwrap(),
+                        Some(block) => {
+                            let ic = sortdb.index_conn();
+                            let snapshot =
+                                SortitionDB::get_block_snapshot_for_winning_stacks_block(
+                                    &ic,
+                                    &tip.sortition_id,
+                                    &block.block_hash(),
+                                )
+                                .unwrap()
+                                .unwrap(); // succeeds because we don't fork
+                            StacksChainState::get_anchored_block_header_info(
+                                chainstate.db(),
+                                &snapshot.consensus_hash,
+                                &snapshot.winning_stacks_block_hash,
+                            )
+                            .unwrap()
+                            .unwrap()
+                        }
+                    };
+
+                    let mut mempool =
+                        MemPoolDB::open_test(false, 0x80000000, &chainstate_path).unwrap();
+                    let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                    let (anchored_block, _, _) = StacksBlockBuilder::build_anchored_block(
+                        chainstate,
+                        &sortdb.index_conn(),
+                        &mut mempool,
+                        &parent_tip,
+                        tip.total_burn,
+                        vrf_proof,
+                        Hash160([tenure_id as u8; 20]),
+                        &coinbase_tx,
+                        BlockBuilderSettings::max_value(),
+                        None,
+                    )
+                    .unwrap();
+
+                    // the block the miner built is valid, and validating it changes nothing
+                    let validated = chainstate
+                        .validate_block_proposal(
+                            &sortdb.index_conn(),
+                            &parent_tip.consensus_hash,
+                            &anchored_block,
+                            None,
+                        )
+                        .unwrap();
+                    assert_eq!(validated.block_hash, anchored_block.block_hash());
+                    assert_eq!(validated.parent_block_id, parent_tip.index_block_hash());
+                    assert_eq!(validated.stacks_height, parent_tip.stacks_block_height + 1);
+                    assert_eq!(
+                        validated.block_size,
+                        anchored_block.serialize_to_vec().len() as u64
+                    );
+                    assert_eq!(validated.num_microblocks, 0);
+                    assert_eq!(validated.tx_receipts.len(), 1);
+                    assert!(validated.tx_receipts[0].is_coinbase_tx());
+
+                    let mut bad_block = anchored_block.clone();
+                    bad_block.header.state_index_root = TrieHash([0x01; 32]);
+                    match chainstate.validate_block_proposal(
+                        &sortdb.index_conn(),
+                        &parent_tip.consensus_hash,
+                        &bad_block,
+                        None,
+                    ) {
+                        Err(super::Error::InvalidStacksBlock(msg)) => {
+                            assert!(msg.starts_with("State root mismatch"), "{}", msg)
+                        }
+                        res => panic!("Accepted a block with a bad state root: {:?}", res),
+                    }
+
+                    let mut bad_block = anchored_block.clone();
+                    bad_block.header.total_work.work += 1;
+                    match chainstate.validate_block_proposal(
+                        &sortdb.index_conn(),
+                        &parent_tip.consensus_hash,
+                        &bad_block,
+                        None,
+                    ) {
+                        Err(super::Error::InvalidStacksBlock(msg)) => {
+                            assert!(msg.starts_with("Block height is"), "{}", msg)
+                        }
+                        res => panic!("Accepted a block at the wrong height: {:?}", res),
+                    }
+
+                    if parent_opt.is_some() {
+                        match chainstate.validate_block_proposal(
+                            &sortdb.index_conn(),
+                            &ConsensusHash([0x22; 20]),
+                            &anchored_block,
+                            None,
+                        ) {
+                            Err(super::Error::InvalidStacksBlock(msg)) => {
+                                assert!(msg.contains("has not been processed"), "{}", msg)
+                            }
+                            res => panic!("Accepted a block with an unknown parent: {:?}", res),
+                        }
+                    }
+
+                    (anchored_block, vec![])
+                },
+            );
+
+            peer.next_burnchain_block(burn_ops.clone());
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            // the proposal was never stored, so the block still processes
+            let tip_hash = SortitionDB::get_canonical_stacks_chain_tip_hash(
+                peer.sortdb.as_ref().unwrap().conn(),
+            )
+            .unwrap()
+            .1;
+            assert_eq!(tip_hash, stacks_block.block_hash());
+        }
+    }
+
+    #[test]
+    fn test_replay_block() {
+        let peer_config = TestPeerConfig::new("test_replay_block", 21315, 21316);
+        let observer = TestEventObserver::new();
+        let mut peer = TestPeer::new_with_observer(peer_config, Some(&observer));
+
+        let chainstate_path = peer.chainstate_path.clone();
+
+        let num_blocks = 3;
+        for tenure_id in 0..num_blocks {
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+                |ref mut miner,
+                 ref mut sortdb,
+                 ref mut chainstate,
+                 vrf_proof,
+                 ref parent_opt,
+                 ref parent_microblock_header_opt| {
+                    let parent_tip = match parent_opt {
+                        None => StacksChainState::get_genesis_header_info(chainstate.db()).unwrap(),
+                        Some(block) => {
+                            let ic = sortdb.index_conn();
+                            let snapshot =
+                                SortitionDB::get_block_snapshot_for_winning_stacks_block(
+                                    &ic,
+                                    &tip.sortition_id,
+                                    &block.block_hash(),
+                                )
+                                .unwrap()
+                                .unwrap(); // succeeds because we don't fork
+                            StacksChainState::get_anchored_block_header_info(
+                                chainstate.db(),
+                                &snapshot.consensus_hash,
+                                &snapshot.winning_stacks_block_hash,
+                            )
+                            .unwrap()
+                            .unwrap()
+                        }
+                    };
+
+                    let mut mempool =
+                        MemPoolDB::open_test(false, 0x80000000, &chainstate_path).unwrap();
+                    let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                    let (anchored_block, _, _) = StacksBlockBuilder::build_anchored_block(
+                        chainstate,
+                        &sortdb.index_conn(),
+                        &mut mempool,
+                        &parent_tip,
+                        tip.total_burn,
+                        vrf_proof,
+                        Hash160([tenure_id as u8; 20]),
+                        &coinbase_tx,
+                        BlockBuilderSettings::max_value(),
+                        None,
+                    )
+                    .unwrap();
+                    (anchored_block, vec![])
+                },
+            );
+
+            peer.next_burnchain_block(burn_ops.clone());
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+        }
+
+        let announced = observer.get_blocks();
+        assert_eq!(announced.len(), num_blocks);
+
+        // each announced block's validation was measured once it was committed
+        let telemetry = observer.get_block_telemetry();
+        assert_eq!(telemetry.len(), num_blocks);
+        for (announced_block, telemetry) in announced.iter().zip(telemetry.iter()) {
+            assert_eq!(telemetry.block_hash, announced_block.block.block_hash());
+            assert_eq!(
+                telemetry.index_block_hash,
+                announced_block.metadata.index_block_hash()
+            );
+            assert_eq!(telemetry.tx_count, announced_block.receipts.len() as u64);
+            assert!(
+                telemetry.tx_processing_time_us
+                    + telemetry.event_generation_time_us
+                    + telemetry.marf_commit_time_us
+                    <= telemetry.validation_time_us
+            );
+            assert!(telemetry.sqlite_write_bytes > 0);
+        }
+
+        let sortdb = peer.sortdb.take().unwrap();
+        let chainstate = peer.chainstate();
+        for announced_block in announced.iter() {
+            let replayed = chainstate
+                .replay_block(
+                    &sortdb.index_conn(),
+                    &announced_block.metadata.index_block_hash(),
+                )
+                .unwrap();
+            assert_eq!(replayed.block, announced_block.block);
+            assert_eq!(replayed.parent_block_id, announced_block.parent);
+            assert_eq!(replayed.winning_block_txid, announced_block.winner_txid);
+            assert_eq!(replayed.epoch_receipt.header, announced_block.metadata);
+            assert_eq!(replayed.epoch_receipt.tx_receipts, announced_block.receipts);
+            assert_eq!(
+                replayed.epoch_receipt.matured_rewards,
+                announced_block.matured_rewards
+            );
+            assert_eq!(
+                replayed.epoch_receipt.matured_rewards_info,
+                announced_block.matured_rewards_info
+            );
+        }
+
+        // replaying changes nothing, so blocks replay the same way twice
+        let last_block_id = announced[num_blocks - 1].metadata.index_block_hash();
+        let replayed = chainstate
+            .replay_block(&sortdb.index_conn(), &last_block_id)
+            .unwrap();
+        assert_eq!(
+            replayed.epoch_receipt.tx_receipts,
+            announced[num_blocks - 1].receipts
+        );
+
+        let genesis_block_id = StacksChainState::get_genesis_header_info(chainstate.db())
+            .unwrap()
+            .index_block_hash();
+        match chainstate.replay_block(&sortdb.index_conn(), &genesis_block_id) {
+            Err(super::Error::NoSuchBlockError) => {}
+            res => panic!("Replayed the boot block: {:?}", res.map(|r| r.block)),
+        }
+        match chainstate.replay_block(&sortdb.index_conn(), &StacksBlockId([0x11; 32])) {
+            Err(super::Error::NoSuchBlockError) => {}
+            res => panic!("Replayed an unknown block: {:?}", res.map(|r| r.block)),
+        }
+
+        peer.sortdb = Some(sortdb);
+    }
+
     #[test]
     fn test_get_parent_block_header() {
         let peer_config = TestPeerConfig::new("test_get_parent_block_header", 21313, 21314);
@@ -11063,6 +12070,102 @@ pub mod test {
         );
     }
 
+    #[test]
+    fn stacks_db_load_microblocks_by_parent() {
+        let mut chainstate =
+            instantiate_chainstate(false, 0x80000000, "stacks_db_load_microblocks_by_parent");
+        let privk = StacksPrivateKey::from_hex(
+            "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+        )
+        .unwrap();
+
+        let consensus_hash = ConsensusHash([0x02; 20]);
+        let anchored_block_hash = BlockHeaderHash([0x03; 32]);
+
+        // a stream that forks at sequence 5
+        let mblocks = make_sample_microblock_stream(&privk, &anchored_block_hash);
+        let mut fork = make_sample_microblock_stream_fork(&privk, &mblocks[4].block_hash(), 5);
+        fork.truncate(3);
+        for mblock in mblocks.iter().chain(fork.iter()) {
+            store_staging_microblock(
+                &mut chainstate,
+                &consensus_hash,
+                &anchored_block_hash,
+                mblock,
+            );
+        }
+
+        assert_eq!(
+            StacksChainState::load_microblock_stream_by_parent(
+                chainstate.db(),
+                &BlockHeaderHash([0x04; 32])
+            )
+            .unwrap(),
+            None
+        );
+
+        // nothing is confirmed yet, so only the stream up to the fork is loaded
+        assert_eq!(
+            StacksChainState::load_microblock_stream_by_parent(
+                chainstate.db(),
+                &anchored_block_hash
+            )
+            .unwrap()
+            .unwrap(),
+            mblocks[0..5].to_vec()
+        );
+
+        // confirm the first 10 microblocks of the main stream
+        let tx = chainstate.db_tx_begin().unwrap();
+        for mblock in mblocks[0..10].iter() {
+            tx.execute(
+                "UPDATE staging_microblocks SET processed = 1 WHERE microblock_hash = ?1",
+                &[&mblock.block_hash()],
+            )
+            .unwrap();
+        }
+        tx.commit().unwrap();
+
+        assert_eq!(
+            StacksChainState::load_microblock_stream_by_parent(
+                chainstate.db(),
+                &anchored_block_hash
+            )
+            .unwrap()
+            .unwrap(),
+            mblocks[0..10].to_vec()
+        );
+        assert_eq!(
+            StacksChainState::load_microblock_by_parent_and_sequence(
+                chainstate.db(),
+                &anchored_block_hash,
+                5
+            )
+            .unwrap()
+            .unwrap(),
+            mblocks[5]
+        );
+        assert_eq!(
+            StacksChainState::load_microblock_by_parent_and_sequence(
+                chainstate.db(),
+                &anchored_block_hash,
+                12
+            )
+            .unwrap()
+            .unwrap(),
+            mblocks[12]
+        );
+        assert_eq!(
+            StacksChainState::load_microblock_by_parent_and_sequence(
+                chainstate.db(),
+                &anchored_block_hash,
+                1000
+            )
+            .unwrap(),
+            None
+        );
+    }
+
     // TODO(test): test multiple anchored blocks confirming the same microblock stream (in the same
     // place, and different places, with/without orphans)
     // TODO(test): process_next_staging_block
@@ -0,0 +1,196 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2022 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Consistency hashes of the chain state, for comparing nodes.
+//!
+//! A consistency hash digests the metadata that pins down a node's view of the chain at a Stacks
+//! block: the block itself, the root of the chainstate MARF (which commits to every account and
+//! contract), and the sortition the block was chosen in along with the root of the sortition
+//! MARF. Two nodes that report the same hash at the same height agree on all of it, and a node
+//! whose hash differs from its peers' has diverged at or before that height.
+
+use crate::chainstate::burn::db::sortdb::SortitionDB;
+use crate::chainstate::stacks::db::*;
+use crate::chainstate::stacks::Error;
+use crate::types::chainstate::{SortitionId, StacksBlockId, TrieHash};
+use stacks_common::util::hash::Sha512Trunc256Sum;
+
+/// The consistency hash of the chain state at a Stacks block, and what it digests.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainstateConsistencyHash {
+    pub stacks_block_height: u64,
+    pub index_block_hash: StacksBlockId,
+    pub consensus_hash: ConsensusHash,
+    /// Root of the chainstate MARF as of this block
+    pub index_root: TrieHash,
+    pub sortition_id: SortitionId,
+    /// Root of the sortition MARF as of this block's sortition
+    pub sortition_index_root: TrieHash,
+    pub hash: Sha512Trunc256Sum,
+}
+
+impl ChainstateConsistencyHash {
+    pub fn new(
+        stacks_block_height: u64,
+        index_block_hash: StacksBlockId,
+        consensus_hash: ConsensusHash,
+        index_root: TrieHash,
+        sortition_id: SortitionId,
+        sortition_index_root: TrieHash,
+    ) -> ChainstateConsistencyHash {
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&stacks_block_height.to_be_bytes());
+        bytes.extend_from_slice(index_block_hash.as_bytes());
+        bytes.extend_from_slice(consensus_hash.as_bytes());
+        bytes.extend_from_slice(index_root.as_bytes());
+        bytes.extend_from_slice(sortition_id.as_bytes());
+        bytes.extend_from_slice(sortition_index_root.as_bytes());
+        let hash = Sha512Trunc256Sum::from_data(&bytes);
+
+        ChainstateConsistencyHash {
+            stacks_block_height,
+            index_block_hash,
+            consensus_hash,
+            index_root,
+            sortition_id,
+            sortition_index_root,
+            hash,
+        }
+    }
+}
+
+impl StacksChainState {
+    /// Compute the consistency hash of the chain state at the given processed Stacks block.
+    /// Returns None if the block or its sortition is not known.
+    pub fn get_consistency_hash(
+        &self,
+        sortdb: &SortitionDB,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<Option<ChainstateConsistencyHash>, Error> {
+        let header = match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            self.db(),
+            index_block_hash,
+        )? {
+            Some(header) => header,
+            None => {
+                return Ok(None);
+            }
+        };
+        let snapshot =
+            match SortitionDB::get_block_snapshot_consensus(sortdb.conn(), &header.consensus_hash)?
+            {
+                Some(snapshot) => snapshot,
+                None => {
+                    return Ok(None);
+                }
+            };
+
+        Ok(Some(ChainstateConsistencyHash::new(
+            header.stacks_block_height,
+            index_block_hash.clone(),
+            header.consensus_hash,
+            header.index_root,
+            snapshot.sortition_id,
+            snapshot.index_root,
+        )))
+    }
+
+    /// Compute the consistency hash of the chain state at the given height of the canonical Stacks
+    /// chain.  Returns None if the canonical chain is not that tall yet.
+    pub fn get_canonical_consistency_hash(
+        &self,
+        sortdb: &SortitionDB,
+        height: u64,
+    ) -> Result<Option<ChainstateConsistencyHash>, Error> {
+        let tip = match self.get_stacks_chain_tip(sortdb)? {
+            Some(tip) => StacksBlockHeader::make_index_block_hash(
+                &tip.consensus_hash,
+                &tip.anchored_block_hash,
+            ),
+            None => {
+                return Ok(None);
+            }
+        };
+        match self.index_conn()?.get_ancestor_block_hash(height, &tip)? {
+            Some(ancestor) => self.get_consistency_hash(sortdb, &ancestor),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::net::test::*;
+
+    #[test]
+    fn test_consistency_hash() {
+        let peer_config = TestPeerConfig::new("test_consistency_hash", 4222, 4223);
+        let mut peer = TestPeer::new(peer_config);
+
+        let mut coinbase_nonce = 0;
+        let mut tips = vec![];
+        for _ in 0..2 {
+            tips.push(peer.tenure_with_txs(&[], &mut coinbase_nonce));
+        }
+
+        let sortdb = peer.sortdb.take().unwrap();
+        let chainstate = peer.chainstate();
+
+        let mut hashes = vec![];
+        for (i, tip) in tips.iter().enumerate() {
+            let height = i as u64 + 1;
+            let consistency_hash = chainstate
+                .get_canonical_consistency_hash(&sortdb, height)
+                .unwrap()
+                .unwrap();
+            assert_eq!(consistency_hash.stacks_block_height, height);
+            assert_eq!(&consistency_hash.index_block_hash, tip);
+            assert_eq!(
+                chainstate.get_consistency_hash(&sortdb, tip).unwrap(),
+                Some(consistency_hash.clone())
+            );
+
+            // the hash commits to the state root
+            let mut tampered = consistency_hash.clone();
+            tampered.index_root = TrieHash([0xff; 32]);
+            assert_ne!(
+                ChainstateConsistencyHash::new(
+                    tampered.stacks_block_height,
+                    tampered.index_block_hash,
+                    tampered.consensus_hash,
+                    tampered.index_root,
+                    tampered.sortition_id,
+                    tampered.sortition_index_root,
+                )
+                .hash,
+                consistency_hash.hash
+            );
+            hashes.push(consistency_hash.hash);
+        }
+        assert_ne!(hashes[0], hashes[1]);
+
+        assert!(chainstate
+            .get_canonical_consistency_hash(&sortdb, 3)
+            .unwrap()
+            .is_none());
+        assert!(chainstate
+            .get_consistency_hash(&sortdb, &StacksBlockId([0x01; 32]))
+            .unwrap()
+            .is_none());
+    }
+}
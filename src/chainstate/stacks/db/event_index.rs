@@ -0,0 +1,636 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2022 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Index of the asset and contract events that each account and contract took part in, so that
+//! the RPC interface can serve their event history.
+//!
+//! The index is opt-in: it is only maintained if the node runs with
+//! `STACKS_ACCOUNT_EVENT_INDEX=1`, and only covers blocks processed while it was. Events are
+//! recorded for every block that gets processed, including blocks that later fall off the
+//! canonical fork, so queries filter out events that are not in the ancestry of the chain tip
+//! they are made against.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use rusqlite::types::ToSql;
+use rusqlite::{Connection, Row};
+
+use crate::chainstate::stacks::db::*;
+use crate::chainstate::stacks::events::{StacksTransactionEvent, StacksTransactionReceipt};
+use crate::chainstate::stacks::Error;
+use crate::util_lib::db::Error as db_error;
+use crate::util_lib::db::{query_rows, table_exists, u64_to_sql, DBTx, FromColumn, FromRow};
+use clarity::vm::events::{FTEventType, NFTEventType, STXEventType};
+use clarity::vm::types::PrincipalData;
+
+lazy_static! {
+    pub static ref ACCOUNT_EVENT_INDEX: bool =
+        std::env::var("STACKS_ACCOUNT_EVENT_INDEX") == Ok("1".into());
+}
+
+/// Event types that can be filtered on, as named in the events' JSON encoding.
+pub const ACCOUNT_EVENT_TYPES: &[&str] = &[
+    "contract_event",
+    "stx_transfer_event",
+    "stx_mint_event",
+    "stx_burn_event",
+    "stx_lock_event",
+    "nft_transfer_event",
+    "nft_mint_event",
+    "nft_burn_event",
+    "ft_transfer_event",
+    "ft_mint_event",
+    "ft_burn_event",
+];
+
+/// How many indexed events a single query will look at before handing back a cursor, even if it
+/// has not found a full page of canonical events yet.
+const MAX_ACCOUNT_EVENTS_SCAN: usize = 10_000;
+
+/// How many indexed events to load at a time while listing events.
+const ACCOUNT_EVENTS_BATCH_SIZE: usize = 256;
+
+const ACCOUNT_EVENTS_SCHEMA: &[&str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS account_events(
+        principal TEXT NOT NULL,
+        block_height INTEGER NOT NULL,
+        index_block_hash TEXT NOT NULL,
+        tx_index INTEGER NOT NULL,
+        event_index INTEGER NOT NULL,
+        event_type TEXT NOT NULL,
+        -- JSON-encoded event
+        event TEXT NOT NULL,
+        PRIMARY KEY(principal,index_block_hash,tx_index,event_index)
+    );"#,
+    "CREATE INDEX IF NOT EXISTS account_events_by_height ON account_events(principal,block_height,tx_index,event_index);",
+];
+
+/// Position of an event in the chain. Events are listed newest first, and a cursor continues
+/// the listing with the events that come before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AccountEventCursor {
+    pub block_height: u64,
+    pub tx_index: u32,
+    pub event_index: u32,
+}
+
+impl fmt::Display for AccountEventCursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}-{}",
+            self.block_height, self.tx_index, self.event_index
+        )
+    }
+}
+
+impl FromStr for AccountEventCursor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<AccountEventCursor, String> {
+        let parts: Vec<&str> = s.split('-').collect();
+        if parts.len() != 3 {
+            return Err(format!("Invalid event cursor '{}'", s));
+        }
+        let bad_cursor = |_| format!("Invalid event cursor '{}'", s);
+        Ok(AccountEventCursor {
+            block_height: parts[0].parse().map_err(bad_cursor)?,
+            tx_index: parts[1].parse().map_err(bad_cursor)?,
+            event_index: parts[2].parse().map_err(bad_cursor)?,
+        })
+    }
+}
+
+/// Which of an account's events to list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountEventsQuery {
+    /// Only list events from blocks above this height.
+    pub after_height: Option<u64>,
+    /// Only list events from blocks below this height.
+    pub before_height: Option<u64>,
+    /// Only list events of these types. All types are listed if this is empty.
+    pub event_types: Vec<String>,
+    /// Continue a previous listing.
+    pub cursor: Option<AccountEventCursor>,
+    /// Maximum number of events to list.
+    pub limit: u64,
+}
+
+struct AccountEventRow {
+    cursor: AccountEventCursor,
+    index_block_hash: StacksBlockId,
+    event: String,
+}
+
+impl FromRow<AccountEventRow> for AccountEventRow {
+    fn from_row<'a>(row: &'a Row) -> Result<AccountEventRow, db_error> {
+        let block_height = u64::from_column(row, "block_height")?;
+        let tx_index: i64 = row.get_unwrap("tx_index");
+        let event_index: i64 = row.get_unwrap("event_index");
+        let index_block_hash = StacksBlockId::from_column(row, "index_block_hash")?;
+        let event: String = row.get_unwrap("event");
+        Ok(AccountEventRow {
+            cursor: AccountEventCursor {
+                block_height,
+                tx_index: tx_index as u32,
+                event_index: event_index as u32,
+            },
+            index_block_hash,
+            event,
+        })
+    }
+}
+
+/// The accounts and contracts that took part in `event`.
+fn event_principals(event: &StacksTransactionEvent) -> Vec<PrincipalData> {
+    let principals = match event {
+        StacksTransactionEvent::SmartContractEvent(data) => {
+            vec![PrincipalData::Contract(data.key.0.clone())]
+        }
+        StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(data)) => {
+            vec![data.sender.clone(), data.recipient.clone()]
+        }
+        StacksTransactionEvent::STXEvent(STXEventType::STXMintEvent(data)) => {
+            vec![data.recipient.clone()]
+        }
+        StacksTransactionEvent::STXEvent(STXEventType::STXBurnEvent(data)) => {
+            vec![data.sender.clone()]
+        }
+        StacksTransactionEvent::STXEvent(STXEventType::STXLockEvent(data)) => {
+            vec![data.locked_address.clone()]
+        }
+        StacksTransactionEvent::NFTEvent(NFTEventType::NFTTransferEvent(data)) => vec![
+            PrincipalData::Contract(data.asset_identifier.contract_identifier.clone()),
+            data.sender.clone(),
+            data.recipient.clone(),
+        ],
+        StacksTransactionEvent::NFTEvent(NFTEventType::NFTMintEvent(data)) => vec![
+            PrincipalData::Contract(data.asset_identifier.contract_identifier.clone()),
+            data.recipient.clone(),
+        ],
+        StacksTransactionEvent::NFTEvent(NFTEventType::NFTBurnEvent(data)) => vec![
+            PrincipalData::Contract(data.asset_identifier.contract_identifier.clone()),
+            data.sender.clone(),
+        ],
+        StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(data)) => vec![
+            PrincipalData::Contract(data.asset_identifier.contract_identifier.clone()),
+            data.sender.clone(),
+            data.recipient.clone(),
+        ],
+        StacksTransactionEvent::FTEvent(FTEventType::FTMintEvent(data)) => vec![
+            PrincipalData::Contract(data.asset_identifier.contract_identifier.clone()),
+            data.recipient.clone(),
+        ],
+        StacksTransactionEvent::FTEvent(FTEventType::FTBurnEvent(data)) => vec![
+            PrincipalData::Contract(data.asset_identifier.contract_identifier.clone()),
+            data.sender.clone(),
+        ],
+    };
+    // e.g. a transfer to oneself
+    let mut unique_principals = vec![];
+    for principal in principals.into_iter() {
+        if !unique_principals.contains(&principal) {
+            unique_principals.push(principal);
+        }
+    }
+    unique_principals
+}
+
+/// List the events of `principal` that match `query`, newest first, from the rows of
+/// `account_events` for which `is_canonical(block_height, index_block_hash)` holds.
+/// Returns the events, and a cursor to continue from if there may be more of them.
+fn query_account_events<F>(
+    conn: &Connection,
+    principal: &PrincipalData,
+    query: &AccountEventsQuery,
+    tip_height: u64,
+    mut is_canonical: F,
+) -> Result<(Vec<serde_json::Value>, Option<AccountEventCursor>), Error>
+where
+    F: FnMut(u64, &StacksBlockId) -> Result<bool, Error>,
+{
+    // only list events that come before this position
+    let mut end = AccountEventCursor {
+        block_height: tip_height + 1,
+        tx_index: 0,
+        event_index: 0,
+    };
+    if let Some(before_height) = query.before_height {
+        end = end.min(AccountEventCursor {
+            block_height: before_height,
+            tx_index: 0,
+            event_index: 0,
+        });
+    }
+    if let Some(cursor) = query.cursor {
+        end = end.min(cursor);
+    }
+
+    let limit = query.limit as usize;
+    if limit == 0 {
+        return Ok((vec![], Some(end)));
+    }
+
+    let type_args: Vec<String> = (0..query.event_types.len())
+        .map(|i| format!("?{}", i + 7))
+        .collect();
+    let type_filter = if type_args.is_empty() {
+        "".to_string()
+    } else {
+        format!("AND event_type IN ({})", type_args.join(","))
+    };
+    let sql = format!(
+        "SELECT * FROM account_events WHERE principal = ?1 AND block_height > ?2 \
+         AND (block_height < ?3 OR (block_height = ?3 AND (tx_index < ?4 OR (tx_index = ?4 AND event_index < ?5)))) {} \
+         ORDER BY block_height DESC, tx_index DESC, event_index DESC LIMIT ?6",
+        type_filter
+    );
+
+    let principal_str = principal.to_string();
+    let after_height = match query.after_height {
+        Some(height) => u64_to_sql(height)?,
+        None => -1,
+    };
+    let batch_size = ACCOUNT_EVENTS_BATCH_SIZE as i64;
+
+    let mut events = vec![];
+    let mut num_scanned = 0;
+    loop {
+        let end_height = u64_to_sql(end.block_height)?;
+        let end_tx_index = end.tx_index as i64;
+        let end_event_index = end.event_index as i64;
+        let mut args: Vec<&dyn ToSql> = vec![
+            &principal_str,
+            &after_height,
+            &end_height,
+            &end_tx_index,
+            &end_event_index,
+            &batch_size,
+        ];
+        for event_type in query.event_types.iter() {
+            args.push(event_type);
+        }
+
+        let mut rows: Vec<AccountEventRow> = query_rows(conn, &sql, args.iter())?;
+        let exhausted = rows.len() < ACCOUNT_EVENTS_BATCH_SIZE;
+        if !exhausted {
+            // other forks can have events at the same position, and the batch may have cut them
+            // off, so leave the last position's events to the next batch
+            let last_cursor = rows[rows.len() - 1].cursor;
+            if rows[0].cursor != last_cursor {
+                rows.retain(|row| row.cursor != last_cursor);
+            }
+        }
+        for row in rows.into_iter() {
+            end = row.cursor;
+            num_scanned += 1;
+            if !is_canonical(row.cursor.block_height, &row.index_block_hash)? {
+                continue;
+            }
+            let event = serde_json::from_str(&row.event)
+                .map_err(|e| Error::DBError(db_error::SerializationError(e)))?;
+            events.push(event);
+            if events.len() >= limit {
+                return Ok((events, Some(end)));
+            }
+        }
+        if exhausted {
+            return Ok((events, None));
+        }
+        if num_scanned >= MAX_ACCOUNT_EVENTS_SCAN {
+            return Ok((events, Some(end)));
+        }
+    }
+}
+
+impl StacksChainState {
+    /// Create the account event index's table, if it does not exist yet.
+    pub fn add_account_event_index<'a>(tx: &DBTx<'a>) -> Result<(), Error> {
+        for cmd in ACCOUNT_EVENTS_SCHEMA {
+            tx.execute_batch(cmd)?;
+        }
+        Ok(())
+    }
+
+    /// Is the account event index available to query?  It is not if the node has never run
+    /// with it enabled.
+    pub fn has_account_event_index(&self) -> Result<bool, Error> {
+        Ok(table_exists(self.db(), "account_events")?)
+    }
+
+    /// Record the events of the transactions in the block with header `header`. Events of
+    /// transactions that were aborted by a post-condition are left out, since they never took
+    /// effect.
+    pub fn insert_account_events<'a>(
+        tx: &DBTx<'a>,
+        header: &StacksHeaderInfo,
+        receipts: &[StacksTransactionReceipt],
+    ) -> Result<(), Error> {
+        let insert = "INSERT OR REPLACE INTO account_events \
+                      (principal, block_height, index_block_hash, tx_index, event_index, event_type, event) \
+                      VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)";
+        let index_block_hash = header.index_block_hash();
+        let block_height = u64_to_sql(header.stacks_block_height)?;
+        for receipt in receipts.iter() {
+            if receipt.post_condition_aborted {
+                continue;
+            }
+            let txid = receipt.transaction.txid();
+            for (event_index, event) in receipt.events.iter().enumerate() {
+                let mut event_json = event.json_serialize(event_index, &txid, true);
+                let event_type = event_json["type"]
+                    .as_str()
+                    .expect("BUG: event JSON has no type")
+                    .to_string();
+                event_json["block_height"] = header.stacks_block_height.into();
+                event_json["index_block_hash"] = format!("0x{}", &index_block_hash).into();
+                event_json["tx_index"] = receipt.tx_index.into();
+                let event_str = event_json.to_string();
+
+                for principal in event_principals(event).into_iter() {
+                    let args: &[&dyn ToSql] = &[
+                        &principal.to_string(),
+                        &block_height,
+                        &index_block_hash,
+                        &receipt.tx_index,
+                        &(event_index as i64),
+                        &event_type,
+                        &event_str,
+                    ];
+                    tx.execute(insert, args)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// List the events of `principal` in the chain ending at `tip` that match `query`, newest
+    /// first. Returns the events, and a cursor to list more of them with, if there may be any.
+    /// The listing can end before `query.limit` events have been found if many of the
+    /// principal's events are not canonical; the cursor continues it.
+    pub fn get_account_events(
+        &self,
+        principal: &PrincipalData,
+        tip: &StacksBlockId,
+        query: &AccountEventsQuery,
+    ) -> Result<(Vec<serde_json::Value>, Option<AccountEventCursor>), Error> {
+        let tip_header =
+            StacksChainState::get_stacks_block_header_info_by_index_block_hash(self.db(), tip)?
+                .ok_or(Error::NoSuchBlockError)?;
+        let index_conn = self.index_conn()?;
+        let mut canonical_blocks: HashMap<u64, Option<StacksBlockId>> = HashMap::new();
+        query_account_events(
+            self.db(),
+            principal,
+            query,
+            tip_header.stacks_block_height,
+            |block_height, index_block_hash| {
+                let canonical_block = match canonical_blocks.get(&block_height) {
+                    Some(block) => *block,
+                    None => {
+                        let block = index_conn.get_ancestor_block_hash(block_height, tip)?;
+                        canonical_blocks.insert(block_height, block);
+                        block
+                    }
+                };
+                Ok(canonical_block.as_ref() == Some(index_block_hash))
+            },
+        )
+    }
+}
+
+impl<'a> ChainstateTx<'a> {
+    /// Add the events of a newly-processed block to the account event index, if it is enabled.
+    pub fn log_account_events(
+        &self,
+        header: &StacksHeaderInfo,
+        receipts: &[StacksTransactionReceipt],
+    ) {
+        if !*ACCOUNT_EVENT_INDEX {
+            return;
+        }
+        if let Err(e) = StacksChainState::insert_account_events(self.tx.tx(), header, receipts) {
+            warn!("Failed to index account events: {:?}", &e;
+                  "index_block_hash" => %header.index_block_hash());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::chainstate::stacks::db::test::instantiate_chainstate;
+    use crate::chainstate::stacks::events::TransactionOrigin;
+    use crate::chainstate::stacks::index::ClarityMarfTrieId;
+    use crate::chainstate::stacks::*;
+    use clarity::vm::costs::ExecutionCost;
+    use clarity::vm::events::{STXMintEventData, STXTransferEventData, SmartContractEventData};
+    use clarity::vm::types::{QualifiedContractIdentifier, StandardPrincipalData};
+    use clarity::vm::Value;
+
+    fn make_principal(byte: u8) -> PrincipalData {
+        PrincipalData::Standard(StandardPrincipalData(26, [byte; 20]))
+    }
+
+    fn make_receipt(
+        tx_index: u32,
+        events: Vec<StacksTransactionEvent>,
+        post_condition_aborted: bool,
+    ) -> StacksTransactionReceipt {
+        let tx = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(&StacksPrivateKey::new()).unwrap(),
+            TransactionPayload::Coinbase(CoinbasePayload([tx_index as u8; 32])),
+        );
+        StacksTransactionReceipt {
+            transaction: TransactionOrigin::Stacks(tx),
+            events,
+            post_condition_aborted,
+            result: Value::okay_true(),
+            stx_burned: 0,
+            contract_analysis: None,
+            execution_cost: ExecutionCost::zero(),
+            microblock_header: None,
+            tx_index,
+        }
+    }
+
+    fn make_header(block_height: u64, fork: u8) -> StacksHeaderInfo {
+        let mut header = StacksHeaderInfo::regtest_genesis();
+        header.stacks_block_height = block_height;
+        let mut consensus_hash = [fork; 20];
+        consensus_hash[19] = block_height as u8;
+        header.consensus_hash = ConsensusHash(consensus_hash);
+        header
+    }
+
+    fn transfer(sender: u8, recipient: u8, amount: u128) -> StacksTransactionEvent {
+        StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(STXTransferEventData {
+            sender: make_principal(sender),
+            recipient: make_principal(recipient),
+            amount,
+        }))
+    }
+
+    #[test]
+    fn test_account_event_index() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "test_account_event_index");
+        let alice = make_principal(1);
+        let contract_id =
+            QualifiedContractIdentifier::new(StandardPrincipalData(26, [2; 20]), "counter".into());
+        let contract = PrincipalData::Contract(contract_id.clone());
+
+        // alice receives 1..=5 uSTX in blocks 1 to 5 of one fork, and 100 uSTX in block 3 of
+        // another
+        let mut canonical_blocks = HashMap::new();
+        {
+            let tx = chainstate.db_tx_begin().unwrap();
+            StacksChainState::add_account_event_index(&tx).unwrap();
+            for height in 1..=5 {
+                let header = make_header(height, 1);
+                canonical_blocks.insert(height, header.index_block_hash());
+                let mut events = vec![transfer(3, 1, height as u128)];
+                if height == 4 {
+                    events.push(StacksTransactionEvent::STXEvent(
+                        STXEventType::STXMintEvent(STXMintEventData {
+                            recipient: alice.clone(),
+                            amount: 1000,
+                        }),
+                    ));
+                    events.push(StacksTransactionEvent::SmartContractEvent(
+                        SmartContractEventData {
+                            key: (contract_id.clone(), "print".into()),
+                            value: Value::UInt(1),
+                        },
+                    ));
+                }
+                let receipts = vec![
+                    make_receipt(0, events, false),
+                    // never took effect
+                    make_receipt(1, vec![transfer(1, 3, 7)], true),
+                ];
+                StacksChainState::insert_account_events(&tx, &header, &receipts).unwrap();
+            }
+            let header = make_header(3, 2);
+            StacksChainState::insert_account_events(
+                &tx,
+                &header,
+                &[make_receipt(0, vec![transfer(3, 1, 100)], false)],
+            )
+            .unwrap();
+            tx.commit().unwrap();
+        }
+
+        let list = |query: &AccountEventsQuery, principal: &PrincipalData| {
+            let (events, cursor) =
+                query_account_events(chainstate.db(), principal, query, 5, |height, block| {
+                    Ok(canonical_blocks.get(&height) == Some(block))
+                })
+                .unwrap();
+            let summary: Vec<(u64, String)> = events
+                .iter()
+                .map(|event| {
+                    (
+                        event["block_height"].as_u64().unwrap(),
+                        event["type"].as_str().unwrap().to_string(),
+                    )
+                })
+                .collect();
+            (summary, cursor)
+        };
+        let all = AccountEventsQuery {
+            after_height: None,
+            before_height: None,
+            event_types: vec![],
+            cursor: None,
+            limit: 10,
+        };
+
+        // newest first, without the other fork's or aborted events
+        let (events, cursor) = list(&all, &alice);
+        assert_eq!(
+            events,
+            vec![
+                (5, "stx_transfer_event".to_string()),
+                (4, "stx_mint_event".to_string()),
+                (4, "stx_transfer_event".to_string()),
+                (3, "stx_transfer_event".to_string()),
+                (2, "stx_transfer_event".to_string()),
+                (1, "stx_transfer_event".to_string()),
+            ]
+        );
+        assert_eq!(cursor, None);
+
+        // page through them
+        let (events, cursor) = list(
+            &AccountEventsQuery {
+                limit: 4,
+                ..all.clone()
+            },
+            &alice,
+        );
+        assert_eq!(events.len(), 4);
+        let cursor = cursor.unwrap();
+        assert_eq!(cursor.to_string(), "3-0-0");
+        assert_eq!(AccountEventCursor::from_str("3-0-0"), Ok(cursor));
+        let (events, cursor) = list(
+            &AccountEventsQuery {
+                limit: 4,
+                cursor: Some(cursor),
+                ..all.clone()
+            },
+            &alice,
+        );
+        assert_eq!(
+            events,
+            vec![
+                (2, "stx_transfer_event".to_string()),
+                (1, "stx_transfer_event".to_string()),
+            ]
+        );
+        assert_eq!(cursor, None);
+
+        // filter by height and type
+        let (events, _) = list(
+            &AccountEventsQuery {
+                after_height: Some(1),
+                before_height: Some(5),
+                event_types: vec!["stx_transfer_event".into()],
+                ..all.clone()
+            },
+            &alice,
+        );
+        assert_eq!(
+            events,
+            vec![
+                (4, "stx_transfer_event".to_string()),
+                (3, "stx_transfer_event".to_string()),
+                (2, "stx_transfer_event".to_string()),
+            ]
+        );
+
+        // contract events are listed under the contract
+        let (events, _) = list(&all, &contract);
+        assert_eq!(events, vec![(4, "contract_event".to_string())]);
+
+        assert!(AccountEventCursor::from_str("3-0").is_err());
+        assert!(AccountEventCursor::from_str("a-b-c").is_err());
+    }
+}
@@ -63,6 +63,7 @@ use crate::net::atlas::BNS_CHARS_REGEX;
 use crate::net::Error as net_error;
 use crate::net::MemPoolSyncData;
 use crate::util_lib::db::Error as db_error;
+use crate::util_lib::db::SqliteDBKind;
 use crate::util_lib::db::{
     query_count, query_row, tx_begin_immediate, tx_busy_handler, DBConn, DBTx, FromColumn, FromRow,
     IndexDBConn, IndexDBTx,
@@ -95,9 +96,13 @@ use crate::util_lib::boot::{boot_code_acc, boot_code_addr, boot_code_id, boot_co
 use clarity::vm::Value;
 use stacks_common::types::chainstate::{StacksAddress, StacksBlockId, TrieHash};
 pub mod accounts;
+pub mod archive;
 pub mod blocks;
+pub mod consistency;
 pub mod contracts;
+pub mod event_index;
 pub mod headers;
+pub mod transaction_log;
 pub mod transactions;
 pub mod unconfirmed;
 
@@ -469,6 +474,11 @@ impl<'a> ChainstateTx<'a> {
                     warn!("Failed to log TX: {}", e);
                 }
             }
+            if let Err(e) =
+                StacksChainState::insert_transaction_log_receipts(self.tx.tx(), block_id, events)
+            {
+                warn!("Failed to log TX receipts: {:?}", e);
+            }
         }
         for tx_event in events.iter() {
             let txid = tx_event.transaction.txid();
@@ -744,6 +754,7 @@ const CHAINSTATE_INDEXES: &'static [&'static str] = &[
     "CREATE INDEX IF NOT EXISTS staging_microblocks_index_hash_processed ON staging_microblocks(index_block_hash,processed);",
     "CREATE INDEX IF NOT EXISTS staging_microblocks_index_hash_orphaned ON staging_microblocks(index_block_hash,orphaned);",
     "CREATE INDEX IF NOT EXISTS staging_microblocks_microblock_hash ON staging_microblocks(microblock_hash);",
+    "CREATE INDEX IF NOT EXISTS staging_microblocks_anchored_sequence ON staging_microblocks(anchored_block_hash,sequence);",
     "CREATE INDEX IF NOT EXISTS processed_stacks_blocks ON staging_blocks(processed,anchored_block_hash,consensus_hash);",
     "CREATE INDEX IF NOT EXISTS orphaned_stacks_blocks ON staging_blocks(orphaned,anchored_block_hash,consensus_hash);",
     "CREATE INDEX IF NOT EXISTS parent_blocks ON staging_blocks(parent_anchored_block_hash);",
@@ -898,7 +909,7 @@ impl StacksChainState {
         StacksChainState::load_db_config(marf.sqlite_conn())
     }
 
-    fn load_db_config(conn: &DBConn) -> Result<DBConfig, db_error> {
+    pub fn load_db_config(conn: &DBConn) -> Result<DBConfig, db_error> {
         let config = query_row::<DBConfig, _>(
             conn,
             &"SELECT * FROM db_config LIMIT 1".to_string(),
@@ -907,7 +918,7 @@ impl StacksChainState {
         Ok(config.expect("BUG: no db_config installed"))
     }
 
-    fn apply_schema_migrations<'a>(
+    pub fn apply_schema_migrations<'a>(
         tx: &DBTx<'a>,
         mainnet: bool,
         chain_id: u32,
@@ -956,10 +967,16 @@ impl StacksChainState {
         Ok(())
     }
 
-    fn add_indexes<'a>(tx: &DBTx<'a>) -> Result<(), Error> {
+    pub fn add_indexes<'a>(tx: &DBTx<'a>) -> Result<(), Error> {
         for cmd in CHAINSTATE_INDEXES {
             tx.execute_batch(cmd)?;
         }
+        if *TRANSACTION_LOG {
+            StacksChainState::add_transaction_log_receipts(tx)?;
+        }
+        if *event_index::ACCOUNT_EVENT_INDEX {
+            StacksChainState::add_account_event_index(tx)?;
+        }
         Ok(())
     }
 
@@ -1007,6 +1024,7 @@ impl StacksChainState {
         test_debug!("Open MARF index at {}", marf_path);
         let mut open_opts = MARFOpenOpts::default();
         open_opts.external_blobs = true;
+        open_opts.db_kind = SqliteDBKind::Chainstate;
         let marf = MARF::from_path(marf_path, open_opts).map_err(|e| db_error::IndexError(e))?;
         Ok(marf)
     }
@@ -1525,6 +1543,53 @@ impl StacksChainState {
         StacksChainState::open_and_exec(mainnet, chain_id, path_str, None, marf_opts)
     }
 
+    /// Open an existing chainstate read-only, without instantiating, migrating or booting it
+    /// (i.e. to serve queries from another thread or process than the one processing blocks).
+    pub fn open_readonly(
+        mainnet: bool,
+        chain_id: u32,
+        path_str: &str,
+        marf_opts: Option<MARFOpenOpts>,
+    ) -> Result<StacksChainState, Error> {
+        let path = PathBuf::from(path_str);
+        let path_to_string = |path: PathBuf| {
+            path.to_str()
+                .map(|path| path.to_string())
+                .ok_or_else(|| Error::DBError(db_error::ParseError))
+        };
+        let blocks_path_root = path_to_string(StacksChainState::blocks_path(path.clone()))?;
+        let clarity_state_index_root =
+            path_to_string(StacksChainState::vm_state_index_root_path(path.clone()))?;
+        let clarity_state_index_marf =
+            path_to_string(StacksChainState::vm_state_index_marf_path(path.clone()))?;
+        let header_index_root =
+            path_to_string(StacksChainState::header_index_root_path(path.clone()))?;
+
+        let mut index_opts = MARFOpenOpts::default();
+        index_opts.external_blobs = true;
+        index_opts.db_kind = SqliteDBKind::Chainstate;
+        let state_index = MARF::from_storage(
+            TrieFileStorage::open_readonly(&header_index_root, index_opts)
+                .map_err(|e| Error::DBError(db_error::IndexError(e)))?,
+        );
+
+        let vm_state = MarfedKV::open_readonly(&clarity_state_index_root, marf_opts.clone())
+            .map_err(|e| Error::ClarityError(e.into()))?;
+
+        Ok(StacksChainState {
+            mainnet: mainnet,
+            chain_id: chain_id,
+            clarity_state: ClarityInstance::new(mainnet, vm_state),
+            state_index: state_index,
+            blocks_path: blocks_path_root,
+            clarity_state_index_path: clarity_state_index_marf,
+            clarity_state_index_root: clarity_state_index_root,
+            root_path: path_str.to_string(),
+            unconfirmed_state: None,
+            marf_opts: marf_opts,
+        })
+    }
+
     /// Re-open the chainstate -- i.e. to get a new handle to it using an existing chain state's
     /// parameters
     pub fn reopen(&self) -> Result<(StacksChainState, Vec<StacksTransactionReceipt>), Error> {
@@ -1833,6 +1898,37 @@ impl StacksChainState {
         self.clarity_state.with_marf(f)
     }
 
+    /// Move the next batch of the Clarity MARF's trie blobs out of its DB and into its blobs file,
+    /// if it was opened with an incremental blob migration underway (see
+    /// `MARFOpenOpts::blob_migration_batch`).  Returns true if there is nothing left to migrate.
+    pub fn migrate_trie_blobs_step(&mut self) -> Result<bool, Error> {
+        let batch = self
+            .marf_opts
+            .as_ref()
+            .map(|opts| opts.blob_migration_batch)
+            .unwrap_or(0);
+        if batch == 0 {
+            return Ok(true);
+        }
+        let done = self
+            .with_clarity_marf(|marf| marf.migrate_trie_blobs_step(batch))
+            .map_err(|e| Error::DBError(db_error::IndexError(e)))?;
+        Ok(done)
+    }
+
+    /// Open a read-only Clarity instance pinned to a snapshot of the committed Clarity state, for
+    /// answering queries (e.g. RPC reads) while this chainstate processes blocks.  It uses the
+    /// chainstate's MARF cache strategy for its own node cache.
+    pub fn reopen_clarity_snapshot(&self) -> Result<ClarityInstance, Error> {
+        let cache_strategy = self
+            .marf_opts
+            .as_ref()
+            .map(|opts| opts.cache_strategy.clone())
+            .unwrap_or_else(|| MARFOpenOpts::default().cache_strategy);
+        let snapshot = self.clarity_state.reopen_snapshot(&cache_strategy)?;
+        Ok(snapshot)
+    }
+
     fn begin_read_only_clarity_tx<'a>(
         &'a mut self,
         burn_dbconn: &'a dyn BurnStateDB,
@@ -2273,6 +2369,42 @@ pub mod test {
         }
     }
 
+    #[test]
+    fn test_open_chainstate_readonly() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "open-chainstate-readonly");
+        assert!(StacksChainState::open_readonly(
+            false,
+            0x80000000,
+            &chainstate_path("open-chainstate-readonly-missing"),
+            None
+        )
+        .is_err());
+
+        let mut readonly =
+            StacksChainState::open_readonly(false, 0x80000000, &chainstate.root_path, None)
+                .unwrap();
+        let genesis = StacksBlockHeader::make_index_block_hash(
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+        );
+        let pox_contract_id = boot_code_id("pox", false);
+        assert_eq!(
+            readonly.clarity_eval_read_only(
+                &TEST_BURN_STATE_DB,
+                &genesis,
+                &pox_contract_id,
+                "(get-pox-info)"
+            ),
+            chainstate.clarity_eval_read_only(
+                &TEST_BURN_STATE_DB,
+                &genesis,
+                &pox_contract_id,
+                "(get-pox-info)"
+            )
+        );
+        assert!(readonly.state_index.begin_tx().is_err());
+    }
+
     #[test]
     fn test_chainstate_sampled_genesis_consistency() {
         // Test root hash for the test chainstate data set
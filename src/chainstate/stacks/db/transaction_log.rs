@@ -0,0 +1,200 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2022 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Lookups in the transaction log, which records every transaction processed in a block if the
+//! node runs with `STACKS_TRANSACTION_LOG=1` (see `TRANSACTION_LOG`).
+//!
+//! The `transactions` table only holds each transaction's result as a Clarity expression. The
+//! log also keeps a receipt per transaction in `transaction_receipts`, with the result's
+//! consensus serialization and the transaction's events.
+
+use std::collections::HashMap;
+
+use rusqlite::types::ToSql;
+use rusqlite::Row;
+
+use crate::burnchains::Txid;
+use crate::chainstate::stacks::db::*;
+use crate::chainstate::stacks::events::StacksTransactionReceipt;
+use crate::chainstate::stacks::Error;
+use crate::codec::StacksMessageCodec;
+use crate::util_lib::db::Error as db_error;
+use crate::util_lib::db::{query_rows, table_exists, DBTx, FromColumn, FromRow};
+use stacks_common::util::hash::to_hex;
+
+const TRANSACTION_LOG_SCHEMA: &[&str] = &[r#"
+    CREATE TABLE IF NOT EXISTS transaction_receipts(
+        txid TEXT NOT NULL,
+        index_block_hash TEXT NOT NULL,
+        -- hex-encoded consensus serialization of the result
+        raw_result TEXT NOT NULL,
+        post_condition_aborted INTEGER NOT NULL,
+        -- JSON-encoded list of events
+        events TEXT NOT NULL,
+        PRIMARY KEY(txid,index_block_hash)
+    );"#];
+
+/// A transaction, as it was processed in a block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggedTransaction {
+    pub index_block_hash: StacksBlockId,
+    /// The result, as a Clarity expression
+    pub result: String,
+    /// The rest of the receipt, if the transaction was logged with one. Transactions logged by
+    /// nodes that did not record receipts yet do not have one.
+    pub receipt: Option<LoggedReceipt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggedReceipt {
+    /// Hex-encoded consensus serialization of the result
+    pub raw_result: String,
+    pub post_condition_aborted: bool,
+    /// Events, encoded as in the event observer interface
+    pub events: Vec<serde_json::Value>,
+}
+
+impl FromRow<LoggedTransaction> for LoggedTransaction {
+    fn from_row<'a>(row: &'a Row) -> Result<LoggedTransaction, db_error> {
+        let index_block_hash = StacksBlockId::from_column(row, "index_block_hash")?;
+        let result: String = row.get_unwrap("result");
+        let raw_result: Option<String> = row.get_unwrap("raw_result");
+        let receipt = match raw_result {
+            Some(raw_result) => {
+                let post_condition_aborted: bool = row.get_unwrap("post_condition_aborted");
+                let events: String = row.get_unwrap("events");
+                Some(LoggedReceipt {
+                    raw_result,
+                    post_condition_aborted,
+                    events: serde_json::from_str(&events).map_err(db_error::SerializationError)?,
+                })
+            }
+            None => None,
+        };
+        Ok(LoggedTransaction {
+            index_block_hash,
+            result,
+            receipt,
+        })
+    }
+}
+
+impl StacksChainState {
+    /// Create the transaction log's receipts table, if it does not exist yet.
+    pub fn add_transaction_log_receipts<'a>(tx: &DBTx<'a>) -> Result<(), Error> {
+        for cmd in TRANSACTION_LOG_SCHEMA {
+            tx.execute_batch(cmd)?;
+        }
+        Ok(())
+    }
+
+    /// Record the receipts of the transactions processed in the block `block_id`.
+    pub fn insert_transaction_log_receipts<'a>(
+        tx: &DBTx<'a>,
+        block_id: &StacksBlockId,
+        receipts: &[StacksTransactionReceipt],
+    ) -> Result<(), Error> {
+        let insert = "INSERT OR REPLACE INTO transaction_receipts \
+                      (txid, index_block_hash, raw_result, post_condition_aborted, events) \
+                      VALUES (?1, ?2, ?3, ?4, ?5)";
+        for receipt in receipts.iter() {
+            let txid = receipt.transaction.txid();
+            let raw_result = to_hex(&receipt.result.serialize_to_vec());
+            let events: Vec<_> = receipt
+                .events
+                .iter()
+                .enumerate()
+                .map(|(event_index, event)| {
+                    event.json_serialize(event_index, &txid, !receipt.post_condition_aborted)
+                })
+                .collect();
+            let events = serde_json::Value::Array(events).to_string();
+            let args: &[&dyn ToSql] = &[
+                &txid,
+                block_id,
+                &raw_result,
+                &receipt.post_condition_aborted,
+                &events,
+            ];
+            tx.execute(insert, args)?;
+        }
+        Ok(())
+    }
+
+    /// Find the block in the chain ending at `tip` that confirmed `txid`, according to the
+    /// transaction log. Returns None if the log has no record of the transaction in that chain,
+    /// which is always the case if the log has never been enabled.
+    pub fn get_logged_transaction(
+        &self,
+        txid: &Txid,
+        tip: &StacksBlockId,
+    ) -> Result<Option<(StacksHeaderInfo, LoggedTransaction)>, Error> {
+        let sql = if table_exists(self.db(), "transaction_receipts")? {
+            "SELECT t.index_block_hash, t.result, r.raw_result, r.post_condition_aborted, r.events \
+             FROM transactions t LEFT JOIN transaction_receipts r \
+             ON t.txid = r.txid AND t.index_block_hash = r.index_block_hash \
+             WHERE t.txid = ?1"
+        } else {
+            "SELECT index_block_hash, result, NULL AS raw_result, NULL AS post_condition_aborted, \
+             NULL AS events FROM transactions WHERE txid = ?1"
+        };
+        let args: &[&dyn ToSql] = &[txid];
+        let logged_txs: Vec<LoggedTransaction> = query_rows(self.db(), sql, args)?;
+
+        // the transaction may have been mined in several forks
+        let index_conn = self.index_conn()?;
+        for logged_tx in logged_txs.into_iter() {
+            let header = match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                self.db(),
+                &logged_tx.index_block_hash,
+            )? {
+                Some(header) => header,
+                None => continue,
+            };
+            let ancestor = index_conn.get_ancestor_block_hash(header.stacks_block_height, tip)?;
+            if ancestor.as_ref() == Some(&logged_tx.index_block_hash) {
+                return Ok(Some((header, logged_tx)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Get the transactions the transaction log recorded for the block `block_id`, keyed by
+    /// txid. Empty if the log was not enabled when the block was processed.
+    pub fn get_logged_block_transactions(
+        &self,
+        block_id: &StacksBlockId,
+    ) -> Result<HashMap<Txid, LoggedTransaction>, Error> {
+        let sql = if table_exists(self.db(), "transaction_receipts")? {
+            "SELECT t.txid, t.index_block_hash, t.result, r.raw_result, r.post_condition_aborted, r.events \
+             FROM transactions t LEFT JOIN transaction_receipts r \
+             ON t.txid = r.txid AND t.index_block_hash = r.index_block_hash \
+             WHERE t.index_block_hash = ?1"
+        } else {
+            "SELECT txid, index_block_hash, result, NULL AS raw_result, NULL AS post_condition_aborted, \
+             NULL AS events FROM transactions WHERE index_block_hash = ?1"
+        };
+        let args: &[&dyn ToSql] = &[block_id];
+        let mut stmt = self.db().prepare(sql)?;
+        let mut rows = stmt.query(args)?;
+        let mut logged_txs = HashMap::new();
+        while let Some(row) = rows.next()? {
+            let txid = Txid::from_column(row, "txid")?;
+            logged_txs.insert(txid, LoggedTransaction::from_row(row)?);
+        }
+        Ok(logged_txs)
+    }
+}
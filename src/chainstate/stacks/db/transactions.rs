@@ -1151,7 +1151,17 @@ impl StacksChainState {
         debug!("Process transaction {} ({})", tx.txid(), tx.payload.name());
 
         StacksChainState::process_transaction_precheck(&clarity_block.config, tx)?;
+        StacksChainState::process_checked_transaction(clarity_block, tx, quiet, ast_rules)
+    }
 
+    /// Process a transaction that has passed `process_transaction_precheck()` (or that is only
+    /// being dry-run).  Return the fee and the transaction receipt
+    fn process_checked_transaction(
+        clarity_block: &mut ClarityTx,
+        tx: &StacksTransaction,
+        quiet: bool,
+        ast_rules: ASTRules,
+    ) -> Result<(u64, StacksTransactionReceipt), Error> {
         let mut transaction = clarity_block.connection().start_transaction_processing();
         let (origin_account, payer_account) =
             StacksChainState::check_transaction_nonces(&mut transaction, tx, quiet)?;
@@ -1185,6 +1195,113 @@ impl StacksChainState {
 
         Ok((fee, tx_receipt))
     }
+
+    /// Execute `payload` as if `sender` had sent it, with its next nonce and no fee, in a block
+    /// built on the given parent block.  The transaction needs no signature, and all of its
+    /// effects are rolled back afterwards.  Return the transaction receipt, whose execution cost
+    /// and events are what the transaction would cost and emit if it were mined right after the
+    /// parent block.
+    pub fn dry_run_transaction(
+        &mut self,
+        burn_dbconn: &dyn BurnStateDB,
+        parent_consensus_hash: &ConsensusHash,
+        parent_block: &BlockHeaderHash,
+        sender: &StacksAddress,
+        payload: TransactionPayload,
+        ast_rules: ASTRules,
+    ) -> Result<StacksTransactionReceipt, Error> {
+        let version = if self.mainnet {
+            TransactionVersion::Mainnet
+        } else {
+            TransactionVersion::Testnet
+        };
+        let chain_id = self.chain_id;
+
+        self.with_simulated_block(
+            burn_dbconn,
+            parent_consensus_hash,
+            parent_block,
+            |clarity_tx| {
+                let nonce =
+                    StacksChainState::get_account(clarity_tx, &sender.to_account_principal()).nonce;
+                let spending_condition = match AddressHashMode::from_version(sender.version) {
+                    AddressHashMode::SerializeP2PKH => {
+                        TransactionSpendingCondition::Singlesig(SinglesigSpendingCondition {
+                            hash_mode: SinglesigHashMode::P2PKH,
+                            signer: sender.bytes.clone(),
+                            nonce,
+                            tx_fee: 0,
+                            key_encoding: TransactionPublicKeyEncoding::Compressed,
+                            signature: MessageSignature::empty(),
+                        })
+                    }
+                    _ => TransactionSpendingCondition::Multisig(MultisigSpendingCondition {
+                        hash_mode: MultisigHashMode::P2SH,
+                        signer: sender.bytes.clone(),
+                        nonce,
+                        tx_fee: 0,
+                        fields: vec![],
+                        signatures_required: 0,
+                    }),
+                };
+                let mut tx = StacksTransaction::new(
+                    version,
+                    TransactionAuth::Standard(spending_condition),
+                    payload,
+                );
+                tx.chain_id = chain_id;
+                tx.post_condition_mode = TransactionPostConditionMode::Allow;
+
+                StacksChainState::process_checked_transaction(clarity_tx, &tx, true, ast_rules)
+                    .map(|(_fee, receipt)| receipt)
+            },
+        )
+    }
+
+    /// Execute the signed transaction `tx` in a block built on the given parent block, and roll
+    /// back all of its effects.  Return the transaction receipt it would have if it were mined
+    /// right after the parent block, or the error that would keep it from being mined there.
+    pub fn simulate_transaction(
+        &mut self,
+        burn_dbconn: &dyn BurnStateDB,
+        parent_consensus_hash: &ConsensusHash,
+        parent_block: &BlockHeaderHash,
+        tx: &StacksTransaction,
+        ast_rules: ASTRules,
+    ) -> Result<StacksTransactionReceipt, Error> {
+        self.with_simulated_block(
+            burn_dbconn,
+            parent_consensus_hash,
+            parent_block,
+            |clarity_tx| {
+                StacksChainState::process_transaction(clarity_tx, tx, true, ast_rules)
+                    .map(|(_fee, receipt)| receipt)
+            },
+        )
+    }
+
+    /// Run `todo` in a block built on the given parent block, and discard the block afterwards.
+    fn with_simulated_block<F, R>(
+        &mut self,
+        burn_dbconn: &dyn BurnStateDB,
+        parent_consensus_hash: &ConsensusHash,
+        parent_block: &BlockHeaderHash,
+        todo: F,
+    ) -> R
+    where
+        F: FnOnce(&mut ClarityTx) -> R,
+    {
+        let mut clarity_tx = self.block_begin(
+            burn_dbconn,
+            parent_consensus_hash,
+            parent_block,
+            &MINER_BLOCK_CONSENSUS_HASH,
+            &MINER_BLOCK_HEADER_HASH,
+        );
+        let result = todo(&mut clarity_tx);
+        clarity_tx.rollback_block();
+        result
+    }
 }
 
 #[cfg(test)]
@@ -7736,4 +7853,61 @@ pub mod test {
 
         conn.commit_block();
     }
+
+    #[test]
+    fn dry_run_transaction() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "dry-run-transaction");
+
+        let privk = StacksPrivateKey::from_hex(
+            "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
+        )
+        .unwrap();
+        let addr = TransactionAuth::from_p2pkh(&privk)
+            .unwrap()
+            .origin()
+            .address_testnet();
+        let payload = TransactionPayload::new_smart_contract(
+            "hello-dry-run",
+            "(define-data-var bar int 1) (print (var-get bar))",
+        )
+        .unwrap();
+
+        // nothing is kept, so the same contract can be dry-run again
+        for _ in 0..2 {
+            let receipt = chainstate
+                .dry_run_transaction(
+                    &TEST_BURN_STATE_DB,
+                    &FIRST_BURNCHAIN_CONSENSUS_HASH,
+                    &FIRST_STACKS_BLOCK_HASH,
+                    &addr,
+                    payload.clone(),
+                    ASTRules::PrecheckSize,
+                )
+                .unwrap();
+            assert_eq!(receipt.result, Value::okay_true());
+            assert_eq!(receipt.events.len(), 1);
+            assert!(receipt.execution_cost.runtime > 0);
+        }
+
+        // the sender can't pay for a transfer
+        let transfer = TransactionPayload::TokenTransfer(
+            StacksAddress {
+                version: 1,
+                bytes: Hash160([0xff; 20]),
+            }
+            .into(),
+            123,
+            TokenTransferMemo([0u8; 34]),
+        );
+        assert!(chainstate
+            .dry_run_transaction(
+                &TEST_BURN_STATE_DB,
+                &FIRST_BURNCHAIN_CONSENSUS_HASH,
+                &FIRST_STACKS_BLOCK_HASH,
+                &addr,
+                transfer,
+                ASTRules::PrecheckSize,
+            )
+            .is_err());
+    }
 }
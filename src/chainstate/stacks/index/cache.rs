@@ -15,7 +15,7 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::char::from_digit;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::convert::{TryFrom, TryInto};
 use std::env;
 use std::fmt;
@@ -39,7 +39,7 @@ use rusqlite::{
 
 use crate::chainstate::stacks::index::bits::{
     get_node_byte_len, get_node_hash, read_block_identifier, read_hash_bytes, read_node_hash_bytes,
-    read_nodetype, read_root_hash, write_nodetype_bytes,
+    read_nodetype, read_nodetype_at_head, read_root_hash, write_nodetype_bytes,
 };
 use crate::chainstate::stacks::index::node::{
     clear_backptr, is_backptr, set_backptr, TrieNode, TrieNode16, TrieNode256, TrieNode4,
@@ -166,6 +166,478 @@ impl<T: MarfTrieId> TrieCacheState<T> {
     }
 }
 
+/// Default number of decoded nodes kept in the hot tier of the `tiered` strategy
+pub const TIERED_CACHE_DEFAULT_HOT_NODES: usize = 16_384;
+/// Default number of bytes of encoded nodes kept in the warm tier of the `tiered` strategy
+pub const TIERED_CACHE_DEFAULT_WARM_BYTES: usize = 256 * 1024 * 1024;
+
+/// A cached node and/or its hash.  Either may be missing, since nodes and hashes are read (and
+/// cached) separately.
+#[derive(Debug, Clone, PartialEq)]
+struct TieredCacheEntry {
+    node: Option<TrieNodeType>,
+    hash: Option<TrieHash>,
+}
+
+impl TieredCacheEntry {
+    /// Fold `other`'s node and hash into this entry
+    fn merge(&mut self, other: TieredCacheEntry) {
+        if other.node.is_some() {
+            self.node = other.node;
+        }
+        if other.hash.is_some() {
+            self.hash = other.hash;
+        }
+    }
+
+    /// Encode this entry for the warm tier.
+    /// Format: a flags byte, and then either the node ID and the node's wire encoding (with its
+    /// hash, or 0's if it is not known), or just the hash.  The whole thing is then
+    /// zero-run-length-encoded, since most of a node's child pointers are usually empty.
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Cursor::new(vec![]);
+        let mut flags = 0u8;
+        if self.hash.is_some() {
+            flags |= 0x01;
+        }
+        if self.node.is_some() {
+            flags |= 0x02;
+        }
+        bytes
+            .write_all(&[flags])
+            .expect("FATAL: failed to write to RAM");
+        match self.node {
+            Some(ref node) => {
+                bytes
+                    .write_all(&[node.id()])
+                    .expect("FATAL: failed to write to RAM");
+                write_nodetype_bytes(
+                    &mut bytes,
+                    node,
+                    self.hash
+                        .clone()
+                        .unwrap_or(TrieHash([0u8; TRIEHASH_ENCODED_SIZE])),
+                )
+                .expect("FATAL: failed to write to RAM");
+            }
+            None => {
+                let hash = self
+                    .hash
+                    .as_ref()
+                    .expect("FATAL: cached neither a node nor a hash");
+                bytes
+                    .write_all(hash.as_bytes())
+                    .expect("FATAL: failed to write to RAM");
+            }
+        }
+        zero_rle_encode(&bytes.into_inner())
+    }
+
+    /// Decode an entry encoded with `encode()`
+    fn decode(encoded: &[u8]) -> Result<TieredCacheEntry, Error> {
+        let mut bytes = Cursor::new(zero_rle_decode(encoded)?);
+        let mut flags = [0u8; 1];
+        bytes.read_exact(&mut flags)?;
+        let has_hash = flags[0] & 0x01 != 0;
+        if flags[0] & 0x02 != 0 {
+            let mut node_id = [0u8; 1];
+            bytes.read_exact(&mut node_id)?;
+            let (node, hash) = read_nodetype_at_head(&mut bytes, node_id[0])?;
+            Ok(TieredCacheEntry {
+                node: Some(node),
+                hash: if has_hash { Some(hash) } else { None },
+            })
+        } else {
+            Ok(TieredCacheEntry {
+                node: None,
+                hash: Some(TrieHash(read_hash_bytes(&mut bytes)?)),
+            })
+        }
+    }
+}
+
+/// Zero-run-length-encode `bytes`: each run of up to 255 0's becomes a 0 followed by the run
+/// length, and all other bytes are copied as-is.
+fn zero_rle_encode(bytes: &[u8]) -> Vec<u8> {
+    let mut ret = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != 0 {
+            ret.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        let mut run = 0;
+        while i < bytes.len() && bytes[i] == 0 && run < 255 {
+            run += 1;
+            i += 1;
+        }
+        ret.push(0);
+        ret.push(run as u8);
+    }
+    ret
+}
+
+/// Decode bytes encoded with `zero_rle_encode()`
+fn zero_rle_decode(encoded: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut ret = Vec::with_capacity(encoded.len() * 4);
+    let mut i = 0;
+    while i < encoded.len() {
+        if encoded[i] != 0 {
+            ret.push(encoded[i]);
+            i += 1;
+            continue;
+        }
+        let run = *encoded.get(i + 1).ok_or_else(|| {
+            Error::CorruptionError("Truncated zero run in cached node".to_string())
+        })?;
+        ret.extend(std::iter::repeat(0u8).take(run as usize));
+        i += 2;
+    }
+    Ok(ret)
+}
+
+/// Two-tier node cache.  The hot tier holds a bounded number of decoded nodes, and the warm tier
+/// holds a bounded number of bytes of encoded nodes, which take up a fraction of the space.
+/// Both tiers evict their least-recently-used entries.  Nodes evicted from the hot tier are
+/// demoted to the warm tier, and nodes loaded from the warm tier are promoted back to the hot
+/// tier.
+pub struct TieredNodeCache {
+    /// maximum number of entries in the hot tier
+    hot_capacity: usize,
+    /// maximum number of encoded bytes in the warm tier
+    warm_capacity: usize,
+    /// hot tier, and the tick at which each entry was last used
+    hot: HashMap<TrieNodeAddr, (TieredCacheEntry, u64)>,
+    /// hot tier entries, in order of last use
+    hot_lru: BTreeMap<u64, TrieNodeAddr>,
+    /// warm tier, and the tick at which each entry was last used
+    warm: HashMap<TrieNodeAddr, (Vec<u8>, u64)>,
+    /// warm tier entries, in order of last use
+    warm_lru: BTreeMap<u64, TrieNodeAddr>,
+    /// number of encoded bytes in the warm tier
+    warm_bytes: usize,
+    /// logical clock for ordering uses
+    tick: u64,
+}
+
+impl TieredNodeCache {
+    pub fn new(hot_capacity: usize, warm_capacity: usize) -> TieredNodeCache {
+        TieredNodeCache {
+            hot_capacity,
+            warm_capacity,
+            hot: HashMap::new(),
+            hot_lru: BTreeMap::new(),
+            warm: HashMap::new(),
+            warm_lru: BTreeMap::new(),
+            warm_bytes: 0,
+            tick: 0,
+        }
+    }
+
+    /// Parse the tier sizes out of a `tiered[:<hot nodes>:<warm bytes>]` strategy
+    fn parse(strategy: &str) -> Option<TieredNodeCache> {
+        let parts: Vec<_> = strategy.split(':').collect();
+        match parts.as_slice() {
+            ["tiered"] => Some(TieredNodeCache::new(
+                TIERED_CACHE_DEFAULT_HOT_NODES,
+                TIERED_CACHE_DEFAULT_WARM_BYTES,
+            )),
+            ["tiered", hot, warm] => {
+                Some(TieredNodeCache::new(hot.parse().ok()?, warm.parse().ok()?))
+            }
+            _ => None,
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// Number of nodes in the hot tier
+    pub fn hot_len(&self) -> usize {
+        self.hot.len()
+    }
+
+    /// Number of nodes in the warm tier
+    pub fn warm_len(&self) -> usize {
+        self.warm.len()
+    }
+
+    /// Number of encoded bytes in the warm tier
+    pub fn warm_bytes(&self) -> usize {
+        self.warm_bytes
+    }
+
+    /// Look up an entry, promoting it to the hot tier if it was in the warm tier.
+    fn load(&mut self, addr: &TrieNodeAddr) -> Option<&TieredCacheEntry> {
+        let tick = self.next_tick();
+        if let Some((_, last_used)) = self.hot.get_mut(addr) {
+            self.hot_lru.remove(last_used);
+            self.hot_lru.insert(tick, addr.clone());
+            *last_used = tick;
+        } else {
+            let entry = self.take_warm(addr)?;
+            self.insert_hot(addr.clone(), entry, tick);
+        }
+        self.hot.get(addr).map(|(entry, _)| entry)
+    }
+
+    /// Cache a node and/or hash in the hot tier, merging it with what's already cached
+    fn store(&mut self, addr: TrieNodeAddr, new_entry: TieredCacheEntry) {
+        let tick = self.next_tick();
+        let entry = if let Some((mut entry, last_used)) = self.hot.remove(&addr) {
+            self.hot_lru.remove(&last_used);
+            entry.merge(new_entry);
+            entry
+        } else if let Some(mut entry) = self.take_warm(&addr) {
+            entry.merge(new_entry);
+            entry
+        } else {
+            new_entry
+        };
+        self.insert_hot(addr, entry, tick);
+    }
+
+    /// Insert an entry into the hot tier, demoting the least-recently-used hot entries to the
+    /// warm tier if the hot tier is full.
+    fn insert_hot(&mut self, addr: TrieNodeAddr, entry: TieredCacheEntry, tick: u64) {
+        self.hot_lru.insert(tick, addr.clone());
+        self.hot.insert(addr, (entry, tick));
+        while self.hot.len() > self.hot_capacity {
+            let (oldest_tick, oldest_addr) = match self.hot_lru.iter().next() {
+                Some((t, a)) => (*t, a.clone()),
+                None => break,
+            };
+            self.hot_lru.remove(&oldest_tick);
+            if let Some((entry, _)) = self.hot.remove(&oldest_addr) {
+                self.insert_warm(oldest_addr, entry.encode(), oldest_tick);
+            }
+        }
+    }
+
+    /// Insert an encoded entry into the warm tier, evicting the least-recently-used warm
+    /// entries if the warm tier is full.
+    fn insert_warm(&mut self, addr: TrieNodeAddr, encoded: Vec<u8>, tick: u64) {
+        self.warm_bytes += encoded.len();
+        self.warm_lru.insert(tick, addr.clone());
+        self.warm.insert(addr, (encoded, tick));
+        while self.warm_bytes > self.warm_capacity {
+            let (oldest_tick, oldest_addr) = match self.warm_lru.iter().next() {
+                Some((t, a)) => (*t, a.clone()),
+                None => break,
+            };
+            self.warm_lru.remove(&oldest_tick);
+            if let Some((encoded, _)) = self.warm.remove(&oldest_addr) {
+                self.warm_bytes -= encoded.len();
+            }
+        }
+    }
+
+    /// Remove and decode an entry from the warm tier
+    fn take_warm(&mut self, addr: &TrieNodeAddr) -> Option<TieredCacheEntry> {
+        let (encoded, last_used) = self.warm.remove(addr)?;
+        self.warm_lru.remove(&last_used);
+        self.warm_bytes -= encoded.len();
+        match TieredCacheEntry::decode(&encoded) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                error!(
+                    "Failed to decode warm-cached trie node {:?}: {:?}",
+                    addr, &e
+                );
+                None
+            }
+        }
+    }
+
+    /// Obtain a possibly-cached node and its hash.
+    /// Only return data if we have *both* the node and hash
+    pub fn load_node_and_hash(
+        &mut self,
+        block_id: u32,
+        trieptr: &TriePtr,
+    ) -> Option<(TrieNodeType, TrieHash)> {
+        match self.load(&TrieNodeAddr(block_id, trieptr.clone())) {
+            Some(TieredCacheEntry {
+                node: Some(node),
+                hash: Some(hash),
+            }) => Some((node.clone(), hash.clone())),
+            _ => None,
+        }
+    }
+
+    /// Obtain a possibly-cached node
+    pub fn load_node(&mut self, block_id: u32, trieptr: &TriePtr) -> Option<TrieNodeType> {
+        self.load(&TrieNodeAddr(block_id, trieptr.clone()))
+            .and_then(|entry| entry.node.clone())
+    }
+
+    /// Obtain a possibly-cached node hash
+    pub fn load_node_hash(&mut self, block_id: u32, trieptr: &TriePtr) -> Option<TrieHash> {
+        self.load(&TrieNodeAddr(block_id, trieptr.clone()))
+            .and_then(|entry| entry.hash.clone())
+    }
+
+    /// Cache a node and hash
+    pub fn store_node_and_hash(
+        &mut self,
+        block_id: u32,
+        trieptr: TriePtr,
+        node: TrieNodeType,
+        hash: TrieHash,
+    ) {
+        self.store(
+            TrieNodeAddr(block_id, trieptr),
+            TieredCacheEntry {
+                node: Some(node),
+                hash: Some(hash),
+            },
+        )
+    }
+
+    /// Cache just a node
+    pub fn store_node(&mut self, block_id: u32, trieptr: TriePtr, node: TrieNodeType) {
+        self.store(
+            TrieNodeAddr(block_id, trieptr),
+            TieredCacheEntry {
+                node: Some(node),
+                hash: None,
+            },
+        )
+    }
+
+    /// Cache just a node hash
+    pub fn store_node_hash(&mut self, block_id: u32, trieptr: TriePtr, hash: TrieHash) {
+        self.store(
+            TrieNodeAddr(block_id, trieptr),
+            TieredCacheEntry {
+                node: None,
+                hash: Some(hash),
+            },
+        )
+    }
+}
+
+/// A trie's nodes and their hashes, keyed by the nodes' offsets in the trie
+pub type TrieNodeSet = HashMap<u32, (TrieNodeType, TrieHash)>;
+
+/// The complete node sets of the most recently committed tries, kept in RAM so that reads
+/// against recent chain tips never touch disk.  Unlike the node cache, a trie is either pinned in
+/// its entirety or not at all, and only the `capacity` tries with the highest block IDs (i.e. the
+/// most recently stored ones) are kept.
+pub struct PinnedTries {
+    /// how many tries to keep pinned
+    capacity: usize,
+    /// pinned tries, by block ID
+    tries: BTreeMap<u32, TrieNodeSet>,
+    /// tries stored in the open storage transaction, to be pinned once it commits
+    staged: Vec<(u32, TrieNodeSet)>,
+}
+
+impl PinnedTries {
+    pub fn new(capacity: usize) -> PinnedTries {
+        PinnedTries {
+            capacity,
+            tries: BTreeMap::new(),
+            staged: vec![],
+        }
+    }
+
+    /// Read every node in a trie, given its blob and the pointer to its root node
+    pub fn materialize(trie_blob: &[u8], root_ptr: TriePtr) -> Result<TrieNodeSet, Error> {
+        let mut cursor = Cursor::new(trie_blob);
+        let mut nodes = HashMap::new();
+        let mut frontier = vec![root_ptr];
+        while let Some(ptr) = frontier.pop() {
+            let (node, hash) = read_nodetype(&mut cursor, &ptr)?;
+            for child in node.ptrs() {
+                if child.id() != TrieNodeID::Empty as u8 && !is_backptr(child.id()) {
+                    frontier.push(child.clone());
+                }
+            }
+            nodes.insert(ptr.ptr(), (node, hash));
+        }
+        Ok(nodes)
+    }
+
+    /// How many tries are kept pinned.  If 0, nothing is ever pinned.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of pinned tries
+    pub fn len(&self) -> usize {
+        self.tries.len()
+    }
+
+    /// Is the trie with this block ID pinned?
+    pub fn is_pinned(&self, block_id: u32) -> bool {
+        self.tries.contains_key(&block_id)
+    }
+
+    /// Block ID of the most recently stored pinned trie
+    pub fn newest(&self) -> Option<u32> {
+        self.tries.keys().next_back().cloned()
+    }
+
+    /// Load a pinned node and its hash
+    pub fn load_node_and_hash(
+        &self,
+        block_id: u32,
+        trieptr: &TriePtr,
+    ) -> Option<(TrieNodeType, TrieHash)> {
+        self.tries
+            .get(&block_id)?
+            .get(&trieptr.ptr())
+            .map(|(node, hash)| (node.clone(), hash.clone()))
+    }
+
+    /// Load a pinned node's hash
+    pub fn load_node_hash(&self, block_id: u32, trieptr: &TriePtr) -> Option<TrieHash> {
+        self.tries
+            .get(&block_id)?
+            .get(&trieptr.ptr())
+            .map(|(_, hash)| hash.clone())
+    }
+
+    /// Pin a trie's nodes, unpinning the oldest tries if there are too many
+    pub fn pin(&mut self, block_id: u32, nodes: TrieNodeSet) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tries.insert(block_id, nodes);
+        while self.tries.len() > self.capacity {
+            let oldest = match self.tries.keys().next() {
+                Some(block_id) => *block_id,
+                None => break,
+            };
+            self.tries.remove(&oldest);
+        }
+    }
+
+    /// Remember a trie stored in the open storage transaction, to pin it once it commits
+    pub fn stage(&mut self, block_id: u32, nodes: TrieNodeSet) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.staged.push((block_id, nodes));
+    }
+
+    /// Pin the tries stored in the storage transaction that just committed
+    pub fn commit_staged(&mut self) {
+        for (block_id, nodes) in std::mem::replace(&mut self.staged, vec![]).into_iter() {
+            self.pin(block_id, nodes);
+        }
+    }
+
+    /// Forget the tries stored in a storage transaction that was rolled back
+    pub fn discard_staged(&mut self) {
+        self.staged.clear();
+    }
+}
+
 /// Trie node cache strategies
 pub enum TrieCache<T: MarfTrieId> {
     /// Do nothing
@@ -174,6 +646,8 @@ pub enum TrieCache<T: MarfTrieId> {
     Everything(TrieCacheState<T>),
     /// Cache only TrieNode256's
     Node256(TrieCacheState<T>),
+    /// Cache recently-used nodes in RAM, and less-recently-used nodes in RAM in encoded form
+    Tiered(TrieCacheState<T>, TieredNodeCache),
 }
 
 impl<T: MarfTrieId> TrieCache<T> {
@@ -188,14 +662,18 @@ impl<T: MarfTrieId> TrieCache<T> {
     }
 
     /// Make a new cache strategy.
-    /// `strategy` must be one of "noop", "everything", or "node256".
-    /// Any other option causes a runtime panic.
+    /// `strategy` must be one of "noop", "everything", "node256", or "tiered".  The sizes of the
+    /// "tiered" strategy's tiers can be given as "tiered:<hot nodes>:<warm bytes>".
+    /// Any other option falls back to the "noop" strategy.
     pub fn new(strategy: &str) -> TrieCache<T> {
         match strategy {
             "noop" => TrieCache::Noop(TrieCacheState::new()),
             "everything" => TrieCache::Everything(TrieCacheState::new()),
             "node256" => TrieCache::Node256(TrieCacheState::new()),
             _ => {
+                if let Some(tiers) = TieredNodeCache::parse(strategy) {
+                    return TrieCache::Tiered(TrieCacheState::new(), tiers);
+                }
                 error!(
                     "Unsupported trie node cache strategy '{}'; falling back to `Noop` strategy",
                     strategy
@@ -211,6 +689,7 @@ impl<T: MarfTrieId> TrieCache<T> {
             TrieCache::Noop(ref state) => state,
             TrieCache::Everything(ref state) => state,
             TrieCache::Node256(ref state) => state,
+            TrieCache::Tiered(ref state, _) => state,
         }
     }
 
@@ -220,15 +699,16 @@ impl<T: MarfTrieId> TrieCache<T> {
             TrieCache::Noop(ref mut state) => state,
             TrieCache::Everything(ref mut state) => state,
             TrieCache::Node256(ref mut state) => state,
+            TrieCache::Tiered(ref mut state, _) => state,
         }
     }
 
     /// Load a node from the cache, given its block ID and trie pointer within the block.
     pub fn load_node(&mut self, block_id: u32, trieptr: &TriePtr) -> Option<TrieNodeType> {
-        if let TrieCache::Noop(_) = self {
-            None
-        } else {
-            self.state_mut().load_node(block_id, trieptr)
+        match self {
+            TrieCache::Noop(_) => None,
+            TrieCache::Tiered(_, ref mut tiers) => tiers.load_node(block_id, trieptr),
+            _ => self.state_mut().load_node(block_id, trieptr),
         }
     }
 
@@ -239,19 +719,19 @@ impl<T: MarfTrieId> TrieCache<T> {
         block_id: u32,
         trieptr: &TriePtr,
     ) -> Option<(TrieNodeType, TrieHash)> {
-        if let TrieCache::Noop(_) = self {
-            None
-        } else {
-            self.state_mut().load_node_and_hash(block_id, trieptr)
+        match self {
+            TrieCache::Noop(_) => None,
+            TrieCache::Tiered(_, ref mut tiers) => tiers.load_node_and_hash(block_id, trieptr),
+            _ => self.state_mut().load_node_and_hash(block_id, trieptr),
         }
     }
 
     /// Load a node's hash, given its node's block ID and trie pointer within the block.
     pub fn load_node_hash(&mut self, block_id: u32, trieptr: &TriePtr) -> Option<TrieHash> {
-        if let TrieCache::Noop(_) = self {
-            None
-        } else {
-            self.state_mut().load_node_hash(block_id, trieptr)
+        match self {
+            TrieCache::Noop(_) => None,
+            TrieCache::Tiered(_, ref mut tiers) => tiers.load_node_hash(block_id, trieptr),
+            _ => self.state_mut().load_node_hash(block_id, trieptr),
         }
     }
 
@@ -275,6 +755,9 @@ impl<T: MarfTrieId> TrieCache<T> {
                 }
                 _ => {}
             },
+            TrieCache::Tiered(_, ref mut tiers) => {
+                tiers.store_node_and_hash(block_id, trieptr, node, hash);
+            }
         }
     }
 
@@ -290,6 +773,7 @@ impl<T: MarfTrieId> TrieCache<T> {
                 }
                 _ => {}
             },
+            TrieCache::Tiered(_, ref mut tiers) => tiers.store_node(block_id, trieptr, node),
         }
     }
 
@@ -314,6 +798,18 @@ impl<T: MarfTrieId> TrieCache<T> {
                 }
                 _ => {}
             },
+            TrieCache::Tiered(_, ref mut tiers) => {
+                tiers.store_node_hash(block_id, trieptr, hash);
+            }
+        }
+    }
+
+    /// Forget everything in the cache.  Needed when a storage transaction rolls back, since the
+    /// block IDs it handed out can be handed out again to other tries.
+    pub fn clear(&mut self) {
+        *self.state_mut() = TrieCacheState::new();
+        if let TrieCache::Tiered(_, ref mut tiers) = self {
+            *tiers = TieredNodeCache::new(tiers.hot_capacity, tiers.warm_capacity);
         }
     }
 
@@ -870,4 +1366,107 @@ pub mod test {
         );
         assert_eq!(root_hash, root_hash_batched);
     }
+
+    #[test]
+    fn test_marf_node_cache_tiered() {
+        let test_data = make_test_insert_data(128, 128);
+        let root_hash = test_marf_with_cache(
+            "test_marf_node_cache_tiered",
+            "noop",
+            TrieHashCalculationMode::Immediate,
+            &test_data,
+            None,
+        );
+        eprintln!("Final root hash is {}", root_hash);
+
+        let root_hash_batched = test_marf_with_cache(
+            "test_marf_node_cache_tiered",
+            "tiered",
+            TrieHashCalculationMode::Immediate,
+            &test_data,
+            Some(64),
+        );
+        assert_eq!(root_hash, root_hash_batched);
+
+        // small enough that nodes get demoted to and evicted from the warm tier
+        let root_hash_batched = test_marf_with_cache(
+            "test_marf_node_cache_tiered",
+            "tiered:64:16384",
+            TrieHashCalculationMode::Immediate,
+            &test_data,
+            Some(13),
+        );
+        assert_eq!(root_hash, root_hash_batched);
+
+        let root_hash_batched = test_marf_with_cache(
+            "test_marf_node_cache_tiered",
+            "tiered:64:16384",
+            TrieHashCalculationMode::Deferred,
+            &test_data,
+            Some(67),
+        );
+        assert_eq!(root_hash, root_hash_batched);
+    }
+
+    #[test]
+    fn test_tiered_node_cache_promotion() {
+        let mut tiers = TieredNodeCache::new(2, 1_000_000);
+
+        let mut node256 = TrieNode256::new(&vec![1, 2, 3]);
+        assert!(node256.insert(&TriePtr::new(TrieNodeID::Leaf as u8, 0x11, 1234)));
+        let nodes = vec![
+            TrieNodeType::Node256(Box::new(node256)),
+            TrieNodeType::Node4(TrieNode4::new(&vec![4, 5])),
+            TrieNodeType::Leaf(TrieLeaf::new(&vec![6, 7, 8], &[9u8; 40].to_vec())),
+        ];
+        for (i, node) in nodes.iter().enumerate() {
+            tiers.store_node_and_hash(
+                1,
+                TriePtr::new(node.id(), 0, i as u32),
+                node.clone(),
+                TrieHash([i as u8 + 1; 32]),
+            );
+        }
+
+        // the first node got demoted, and takes up far less space than its pointers
+        assert_eq!(tiers.hot_len(), 2);
+        assert_eq!(tiers.warm_len(), 1);
+        assert!(tiers.warm_bytes() < nodes[0].byte_len() / 4);
+
+        // loading it promotes it, and demotes the least-recently-used hot node
+        assert_eq!(
+            tiers.load_node_and_hash(1, &TriePtr::new(nodes[0].id(), 0, 0)),
+            Some((nodes[0].clone(), TrieHash([1; 32])))
+        );
+        assert_eq!(tiers.hot_len(), 2);
+        assert_eq!(tiers.warm_len(), 1);
+        assert_eq!(
+            tiers.load_node(1, &TriePtr::new(nodes[1].id(), 0, 1)),
+            Some(nodes[1].clone())
+        );
+
+        // hashes and nodes stored separately are merged, even across tiers
+        tiers.store_node(2, TriePtr::new(nodes[2].id(), 0, 0), nodes[2].clone());
+        tiers.store_node(2, TriePtr::new(nodes[1].id(), 0, 1), nodes[1].clone());
+        tiers.store_node(2, TriePtr::new(nodes[1].id(), 0, 2), nodes[1].clone());
+        assert_eq!(
+            tiers.load_node_and_hash(2, &TriePtr::new(nodes[2].id(), 0, 0)),
+            None
+        );
+        tiers.store_node_hash(2, TriePtr::new(nodes[2].id(), 0, 0), TrieHash([9; 32]));
+        assert_eq!(
+            tiers.load_node_and_hash(2, &TriePtr::new(nodes[2].id(), 0, 0)),
+            Some((nodes[2].clone(), TrieHash([9; 32])))
+        );
+
+        // the warm tier evicts once it's full
+        let mut tiers = TieredNodeCache::new(0, 1);
+        tiers.store_node_hash(1, TriePtr::new(nodes[1].id(), 0, 0), TrieHash([1; 32]));
+        assert_eq!(tiers.hot_len(), 0);
+        assert_eq!(tiers.warm_len(), 0);
+        assert_eq!(
+            tiers.load_node_hash(1, &TriePtr::new(nodes[1].id(), 0, 0)),
+            None
+        );
+    }
 }
@@ -15,7 +15,7 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::char::from_digit;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::convert::{TryFrom, TryInto};
 use std::env;
 use std::fmt;
@@ -29,7 +29,11 @@ use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 use std::os;
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 use std::{cmp, error};
 
 use rusqlite::{
@@ -68,11 +72,151 @@ use stacks_common::types::chainstate::{TrieHash, TRIEHASH_ENCODED_SIZE};
 /// Mapping between block IDs and trie offsets
 pub type TrieIdOffsets = HashMap<u32, u64>;
 
+/// Size of the pages of a trie blobs file held in its page cache
+pub const TRIE_FILE_PAGE_SIZE: u64 = 4096;
+
+/// LRU cache of fixed-size pages of a trie blobs file.  A page is keyed by the block ID of the
+/// trie it starts in and its offset within that trie, so walking a trie only ever looks up that
+/// trie's pages.  Stored tries never change, so pages don't go stale -- except for pages of tries
+/// whose transaction rolled back, which are dropped (see `discard_from()`).
+struct TrieFilePageCache {
+    /// maximum number of pages
+    capacity: usize,
+    /// pages, with the file offset each one starts at and the tick at which it was last used
+    pages: HashMap<(u32, u64), (Vec<u8>, u64, u64)>,
+    /// pages, in order of last use
+    lru: BTreeMap<u64, (u32, u64)>,
+    /// logical clock for ordering uses
+    tick: u64,
+}
+
+impl TrieFilePageCache {
+    fn new(capacity: usize) -> TrieFilePageCache {
+        TrieFilePageCache {
+            capacity,
+            pages: HashMap::new(),
+            lru: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    /// Mark a page as used.  Returns false if it isn't cached.
+    fn touch(&mut self, key: &(u32, u64)) -> bool {
+        match self.pages.get_mut(key) {
+            Some((_, _, last_used)) => {
+                self.lru.remove(&*last_used);
+                self.tick += 1;
+                *last_used = self.tick;
+                self.lru.insert(self.tick, *key);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn get(&self, key: &(u32, u64)) -> Option<&[u8]> {
+        self.pages.get(key).map(|(page, _, _)| page.as_slice())
+    }
+
+    /// Cache a page read from `file_offset`, evicting the least-recently-used page if full
+    fn insert(&mut self, key: (u32, u64), page: Vec<u8>, file_offset: u64) {
+        if self.pages.len() >= self.capacity {
+            let oldest = self.lru.keys().next().cloned();
+            if let Some(oldest) = oldest {
+                if let Some(oldest_key) = self.lru.remove(&oldest) {
+                    self.pages.remove(&oldest_key);
+                }
+            }
+        }
+        self.tick += 1;
+        if let Some((_, _, last_used)) = self.pages.insert(key, (page, file_offset, self.tick)) {
+            self.lru.remove(&last_used);
+        }
+        self.lru.insert(self.tick, key);
+    }
+
+    /// Drop every page that holds bytes at or after `file_offset`
+    fn discard_from(&mut self, file_offset: u64) {
+        let lru = &mut self.lru;
+        self.pages.retain(|_, (page, page_offset, last_used)| {
+            let keep = *page_offset + (page.len() as u64) <= file_offset;
+            if !keep {
+                lru.remove(&*last_used);
+            }
+            keep
+        });
+    }
+}
+
+/// When the tries appended to a trie blobs file are made durable
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrieFileSyncMode {
+    /// fsync after every appended trie, or once for all the tries buffered in a transaction
+    PerCommit,
+    /// fsync from a background thread at this interval.  Tries appended since the last fsync can
+    /// be lost in a crash, so this is only for deployments that can recover from a snapshot.
+    Periodic(Duration),
+    /// open the file with `O_DSYNC`, so that every write is durable once it returns
+    DSync,
+    /// never fsync.  Only for testing.
+    NoSync,
+}
+
+impl Default for TrieFileSyncMode {
+    fn default() -> TrieFileSyncMode {
+        TrieFileSyncMode::PerCommit
+    }
+}
+
+impl FromStr for TrieFileSyncMode {
+    type Err = String;
+
+    /// Parse one of `per-commit`, `periodic:<milliseconds>`, `dsync`, or `none`
+    fn from_str(mode: &str) -> Result<TrieFileSyncMode, String> {
+        match mode {
+            "per-commit" => Ok(TrieFileSyncMode::PerCommit),
+            "dsync" => Ok(TrieFileSyncMode::DSync),
+            "none" => Ok(TrieFileSyncMode::NoSync),
+            _ => {
+                let interval_ms = mode
+                    .strip_prefix("periodic:")
+                    .and_then(|interval_ms| interval_ms.parse::<u64>().ok())
+                    .filter(|interval_ms| *interval_ms > 0)
+                    .ok_or_else(|| {
+                        format!(
+                            "Invalid trie blob sync mode '{}': expected 'per-commit', 'periodic:<ms>', 'dsync', or 'none'",
+                            mode
+                        )
+                    })?;
+                Ok(TrieFileSyncMode::Periodic(Duration::from_millis(
+                    interval_ms,
+                )))
+            }
+        }
+    }
+}
+
 /// Handle to a flat file containing Trie blobs
 pub struct TrieFileDisk {
     fd: fs::File,
     path: String,
     trie_offsets: TrieIdOffsets,
+    /// When appended tries are fsync'ed
+    sync_mode: TrieFileSyncMode,
+    /// Tells the background fsync thread to stop, in `TrieFileSyncMode::Periodic`
+    syncer_stop: Option<Arc<AtomicBool>>,
+    /// Are writes being buffered in `pending` (see `TrieFile::begin_buffering()`)?
+    buffering: bool,
+    /// Bytes written while buffering, which belong at `pending_offset` in the file
+    pending: Vec<u8>,
+    pending_offset: u64,
+    /// Read/write position, which can be past the end of `fd` while there are pending bytes
+    pos: u64,
+    /// Cache of pages read from the tries, if enabled
+    page_cache: Option<TrieFilePageCache>,
+    /// Offset of the first trie appended since the last commit.  If the transaction that stored
+    /// it rolls back, its block ID can be reused for a different trie at the same offset.
+    uncommitted_offset: Option<u64>,
 }
 
 /// Handle to a flat in-memory buffer containing Trie blobs (used for testing)
@@ -93,18 +237,160 @@ pub enum TrieFile {
     Disk(TrieFileDisk),
 }
 
+impl TrieFileDisk {
+    /// Copy bytes of the trie with the given block ID and file offset into `buf`, starting at
+    /// `pos` within the trie, by way of the page that holds them.  The page is read in and
+    /// cached if need be.  Returns the number of bytes copied, which is 0 at the end of the file.
+    fn read_cached(
+        &mut self,
+        block_id: u32,
+        trie_offset: u64,
+        pos: u64,
+        buf: &mut [u8],
+    ) -> io::Result<usize> {
+        let page_offset = pos - pos % TRIE_FILE_PAGE_SIZE;
+        let key = (block_id, page_offset);
+        let cached = match self.page_cache.as_mut() {
+            Some(page_cache) => page_cache.touch(&key),
+            None => false,
+        };
+        if !cached {
+            let file_offset = trie_offset + page_offset;
+            let mut page = vec![0u8; TRIE_FILE_PAGE_SIZE as usize];
+            let mut len = 0;
+            self.seek(SeekFrom::Start(file_offset))?;
+            while len < page.len() {
+                let num_read = self.read(&mut page[len..])?;
+                if num_read == 0 {
+                    break;
+                }
+                len += num_read;
+            }
+            page.truncate(len);
+            if let Some(page_cache) = self.page_cache.as_mut() {
+                page_cache.insert(key, page, file_offset);
+            }
+        }
+        let page = match self
+            .page_cache
+            .as_ref()
+            .and_then(|page_cache| page_cache.get(&key))
+        {
+            Some(page) => page,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "trie blobs page cache is disabled",
+                ));
+            }
+        };
+        let start = (pos - page_offset) as usize;
+        if start >= page.len() {
+            return Ok(0);
+        }
+        let num_read = cmp::min(buf.len(), page.len() - start);
+        buf[..num_read].copy_from_slice(&page[start..(start + num_read)]);
+        Ok(num_read)
+    }
+
+    /// Make the tries appended so far durable, if the sync mode calls for it on commit
+    fn sync_appended(&mut self) -> Result<(), Error> {
+        match self.sync_mode {
+            TrieFileSyncMode::PerCommit => {
+                self.fd.sync_data()?;
+            }
+            TrieFileSyncMode::Periodic(_) | TrieFileSyncMode::DSync | TrieFileSyncMode::NoSync => {}
+        }
+        Ok(())
+    }
+
+    /// Start a thread that fsyncs `fd` every `interval` until told to stop, and once more when
+    /// it stops.
+    fn spawn_syncer(
+        fd: &fs::File,
+        path: &str,
+        interval: Duration,
+    ) -> Result<Arc<AtomicBool>, Error> {
+        let fd = fd.try_clone()?;
+        let path = path.to_string();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        thread::Builder::new()
+            .name("trie-blobs-sync".to_string())
+            .spawn(move || {
+                let mut last_sync = Instant::now();
+                loop {
+                    thread::sleep(cmp::min(interval, Duration::from_millis(100)));
+                    let stopping = thread_stop.load(Ordering::SeqCst);
+                    if stopping || last_sync.elapsed() >= interval {
+                        if let Err(e) = fd.sync_data() {
+                            warn!("Failed to fsync trie blobs {}: {:?}", &path, &e);
+                        }
+                        last_sync = Instant::now();
+                    }
+                    if stopping {
+                        break;
+                    }
+                }
+            })?;
+        Ok(stop)
+    }
+}
+
+impl Drop for TrieFileDisk {
+    fn drop(&mut self) {
+        if let Some(stop) = self.syncer_stop.as_ref() {
+            stop.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
 impl TrieFile {
     /// Make a new disk-backed TrieFile
-    fn new_disk(path: &str, readonly: bool) -> Result<TrieFile, Error> {
-        let fd = OpenOptions::new()
-            .read(true)
-            .write(!readonly)
-            .create(!readonly)
-            .open(path)?;
+    fn new_disk(
+        path: &str,
+        readonly: bool,
+        sync_mode: TrieFileSyncMode,
+        page_cache_size: usize,
+    ) -> Result<TrieFile, Error> {
+        let mut open_opts = OpenOptions::new();
+        open_opts.read(true).write(!readonly).create(!readonly);
+        if sync_mode == TrieFileSyncMode::DSync && !readonly {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                open_opts.custom_flags(libc::O_DSYNC);
+            }
+            #[cfg(not(unix))]
+            {
+                warn!(
+                    "O_DSYNC is not supported on this platform; trie blobs {} will not be synced",
+                    path
+                );
+            }
+        }
+        let fd = open_opts.open(path)?;
+        let syncer_stop = match sync_mode {
+            TrieFileSyncMode::Periodic(interval) if !readonly => {
+                Some(TrieFileDisk::spawn_syncer(&fd, path, interval)?)
+            }
+            _ => None,
+        };
         Ok(TrieFile::Disk(TrieFileDisk {
             fd,
             path: path.to_string(),
             trie_offsets: TrieIdOffsets::new(),
+            sync_mode,
+            syncer_stop,
+            buffering: false,
+            pending: vec![],
+            pending_offset: 0,
+            pos: 0,
+            page_cache: match (page_cache_size as u64) / TRIE_FILE_PAGE_SIZE {
+                0 => None,
+                num_pages => Some(TrieFilePageCache::new(num_pages as usize)),
+            },
+            uncommitted_offset: None,
         }))
     }
 
@@ -149,11 +435,22 @@ impl TrieFile {
     /// If path is ':memory:', then it'll be an in-RAM TrieFile.
     /// Otherwise, it'll be stored as `$db_path.blobs`.
     pub fn from_db_path(path: &str, readonly: bool) -> Result<TrieFile, Error> {
+        TrieFile::from_db_path_opts(path, readonly, TrieFileSyncMode::PerCommit, 0)
+    }
+
+    /// Instantiate a TrieFile, given the associated DB path, when to fsync appended tries, and
+    /// how many bytes of pages to cache when reading tries (none if less than a page).
+    pub fn from_db_path_opts(
+        path: &str,
+        readonly: bool,
+        sync_mode: TrieFileSyncMode,
+        page_cache_size: usize,
+    ) -> Result<TrieFile, Error> {
         if path == ":memory:" {
             Ok(TrieFile::new_ram(readonly))
         } else {
             let blob_path = format!("{}.blobs", path);
-            TrieFile::new_disk(&blob_path, readonly)
+            TrieFile::new_disk(&blob_path, readonly, sync_mode, page_cache_size)
         }
     }
 
@@ -181,10 +478,13 @@ impl TrieFile {
         Ok(trie_blob)
     }
 
-    /// Read a trie blob in its entirety from the blobs file
-    #[cfg(test)]
+    /// Read a trie blob in its entirety from the blobs file, or from the DB if it has not been
+    /// migrated yet
     pub fn read_trie_blob(&mut self, db: &Connection, block_id: u32) -> Result<Vec<u8>, Error> {
         let (offset, length) = trie_sql::get_external_trie_offset_length(db, block_id)?;
+        if length == 0 {
+            return TrieFile::read_trie_blob_from_db(db, block_id);
+        }
         self.seek(SeekFrom::Start(offset))?;
 
         let mut buf = vec![0u8; length as usize];
@@ -335,6 +635,68 @@ impl TrieFile {
         trie_sql::set_migrated(db).expect("FATAL: failed to mark DB as migrated");
         Ok(())
     }
+
+    /// Take one step of an incremental migration of trie blobs out of a sqlite3 DB and into this
+    /// file, as an alternative to `export_trie_blobs()` that doesn't stop the world.  Up to
+    /// `max_tries` confirmed tries are copied over, and the sqlite copies of the tries copied by
+    /// the previous step are cleared.  Readers that looked a trie up before its copy was recorded
+    /// read the sqlite copy, so it is only cleared a step later.
+    ///
+    /// `db` must be in a transaction, which must commit after this returns.  Returns true if the
+    /// migration is finished (or was never started).  The DB is not vacuumed afterwards, since
+    /// that would stop the world; sqlite reuses the freed pages for new data.
+    pub fn migrate_trie_blobs_step(
+        &mut self,
+        db: &Connection,
+        max_tries: u32,
+    ) -> Result<bool, Error> {
+        let (migrated_block_id, cleared_block_id) = match trie_sql::get_blob_migration_progress(db)?
+        {
+            Some(progress) => progress,
+            None => {
+                return Ok(true);
+            }
+        };
+
+        trie_sql::clear_migrated_trie_blobs(db, cleared_block_id, migrated_block_id)?;
+
+        let block_ids = trie_sql::get_unmigrated_block_ids(db, migrated_block_id, max_tries)?;
+        let last_block_id = match block_ids.last() {
+            Some(block_id) => *block_id,
+            None => {
+                info!(
+                    "Finished migrating tries to external blob storage at {}",
+                    &self.get_path()
+                );
+                trie_sql::finish_blob_migration(db)?;
+                return Ok(true);
+            }
+        };
+
+        // write all of this step's tries with one fsync
+        self.begin_buffering();
+        for block_id in block_ids.iter() {
+            let trie_blob = TrieFile::read_trie_blob_from_db(db, *block_id)?;
+            let offset = self.append_trie_blob(db, &trie_blob)?;
+            test_debug!("Migrated trie {} to offset {}", block_id, offset);
+            trie_sql::set_external_trie_blob_location(
+                db,
+                *block_id,
+                offset,
+                trie_blob.len() as u64,
+            )?;
+        }
+        self.flush_buffered()?;
+        trie_sql::set_blob_migration_progress(db, last_block_id, migrated_block_id)?;
+
+        info!(
+            "Migrated {} tries to external blob storage at {} ({} left)",
+            block_ids.len(),
+            &self.get_path(),
+            trie_sql::count_unmigrated_blocks(db)?
+        );
+        Ok(false)
+    }
 }
 
 /// NodeHashReader for TrieFile
@@ -356,7 +718,16 @@ impl<'a> TrieFileNodeHashReader<'a> {
 
 impl NodeHashReader for TrieFileNodeHashReader<'_> {
     fn read_node_hash_bytes<W: Write>(&mut self, ptr: &TriePtr, w: &mut W) -> Result<(), Error> {
-        let trie_offset = self.file.get_trie_offset(self.db, self.block_id)?;
+        let trie_offset = match self.file.find_trie_offset(self.db, self.block_id)? {
+            Some(offset) => offset,
+            None => {
+                return trie_sql::read_node_hash_bytes(self.db, w, self.block_id, ptr);
+            }
+        };
+        if let Some(mut reader) = self.file.paged_reader(self.block_id, trie_offset, ptr) {
+            let hash_buff = read_hash_bytes(&mut reader)?;
+            return w.write_all(&hash_buff).map_err(|e| e.into());
+        }
         self.file
             .seek(SeekFrom::Start(trie_offset + (ptr.ptr() as u64)))?;
         let hash_buff = read_hash_bytes(self.file)?;
@@ -364,23 +735,106 @@ impl NodeHashReader for TrieFileNodeHashReader<'_> {
     }
 }
 
+/// Reader over one trie in a disk-backed TrieFile, which reads through its page cache
+pub struct TrieFilePageReader<'a> {
+    disk: &'a mut TrieFileDisk,
+    block_id: u32,
+    trie_offset: u64,
+    /// read position, relative to the start of the trie
+    pos: u64,
+}
+
+impl Read for TrieFilePageReader<'_> {
+    /// Reads continue across page boundaries, so that (like a read from the file itself) a read
+    /// only comes up short at the end of the file.  Node decoding relies on this.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut total_read = 0;
+        while total_read < buf.len() {
+            let num_read = self.disk.read_cached(
+                self.block_id,
+                self.trie_offset,
+                self.pos,
+                &mut buf[total_read..],
+            )?;
+            if num_read == 0 {
+                break;
+            }
+            self.pos += num_read as u64;
+            total_read += num_read;
+        }
+        Ok(total_read)
+    }
+}
+
+impl Seek for TrieFilePageReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => checked_add_signed(self.pos, delta),
+            SeekFrom::End(_) => None,
+        }
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek within a trie blob",
+            )
+        })?;
+        self.pos = new_pos;
+        Ok(new_pos)
+    }
+}
+
 impl TrieFile {
+    /// Get a reader positioned at `ptr` within the trie with the given block ID, which is stored
+    /// at `trie_offset`, if this TrieFile has a page cache to read it through.
+    fn paged_reader(
+        &mut self,
+        block_id: u32,
+        trie_offset: u64,
+        ptr: &TriePtr,
+    ) -> Option<TrieFilePageReader<'_>> {
+        match self {
+            TrieFile::Disk(ref mut disk) if disk.page_cache.is_some() => Some(TrieFilePageReader {
+                disk,
+                block_id,
+                trie_offset,
+                pos: ptr.ptr() as u64,
+            }),
+            _ => None,
+        }
+    }
+
     /// Determine the file offset in the TrieFile where a serialized trie starts.
     /// The offsets are stored in the given DB, and are cached indefinitely once loaded.
     pub fn get_trie_offset(&mut self, db: &Connection, block_id: u32) -> Result<u64, Error> {
+        self.find_trie_offset(db, block_id)?
+            .ok_or(Error::NotFoundError)
+    }
+
+    /// Determine the file offset in the TrieFile where a serialized trie starts, or None if the
+    /// trie is still stored in the DB because an incremental migration hasn't reached it yet.
+    /// Only offsets are cached, since a trie can be migrated by another connection at any time.
+    pub fn find_trie_offset(
+        &mut self,
+        db: &Connection,
+        block_id: u32,
+    ) -> Result<Option<u64>, Error> {
         let offset_opt = match self {
             TrieFile::RAM(ref ram) => ram.trie_offsets.get(&block_id),
             TrieFile::Disk(ref disk) => disk.trie_offsets.get(&block_id),
         };
         match offset_opt {
-            Some(offset) => Ok(*offset),
+            Some(offset) => Ok(Some(*offset)),
             None => {
-                let (offset, _length) = trie_sql::get_external_trie_offset_length(db, block_id)?;
+                let (offset, length) = trie_sql::get_external_trie_offset_length(db, block_id)?;
+                if length == 0 {
+                    return Ok(None);
+                }
                 match self {
                     TrieFile::RAM(ref mut ram) => ram.trie_offsets.insert(block_id, offset),
                     TrieFile::Disk(ref mut disk) => disk.trie_offsets.insert(block_id, offset),
                 };
-                Ok(offset)
+                Ok(Some(offset))
             }
         }
     }
@@ -392,7 +846,15 @@ impl TrieFile {
         block_id: u32,
         ptr: &TriePtr,
     ) -> Result<TrieHash, Error> {
-        let offset = self.get_trie_offset(db, block_id)?;
+        let offset = match self.find_trie_offset(db, block_id)? {
+            Some(offset) => offset,
+            None => {
+                return trie_sql::get_node_hash_bytes(db, block_id, ptr);
+            }
+        };
+        if let Some(mut reader) = self.paged_reader(block_id, offset, ptr) {
+            return Ok(TrieHash(read_hash_bytes(&mut reader)?));
+        }
         self.seek(SeekFrom::Start(offset + (ptr.ptr() as u64)))?;
         let hash_buff = read_hash_bytes(self)?;
         Ok(TrieHash(hash_buff))
@@ -406,7 +868,15 @@ impl TrieFile {
         block_id: u32,
         ptr: &TriePtr,
     ) -> Result<(TrieNodeType, TrieHash), Error> {
-        let offset = self.get_trie_offset(db, block_id)?;
+        let offset = match self.find_trie_offset(db, block_id)? {
+            Some(offset) => offset,
+            None => {
+                return trie_sql::read_node_type(db, block_id, ptr);
+            }
+        };
+        if let Some(mut reader) = self.paged_reader(block_id, offset, ptr) {
+            return read_nodetype_at_head(&mut reader, ptr.id());
+        }
         self.seek(SeekFrom::Start(offset + (ptr.ptr() as u64)))?;
         read_nodetype_at_head(self, ptr.id())
     }
@@ -418,7 +888,15 @@ impl TrieFile {
         block_id: u32,
         ptr: &TriePtr,
     ) -> Result<TrieNodeType, Error> {
-        let offset = self.get_trie_offset(db, block_id)?;
+        let offset = match self.find_trie_offset(db, block_id)? {
+            Some(offset) => offset,
+            None => {
+                return trie_sql::read_node_type_nohash(db, block_id, ptr);
+            }
+        };
+        if let Some(mut reader) = self.paged_reader(block_id, offset, ptr) {
+            return read_nodetype_at_head_nohash(&mut reader, ptr.id());
+        }
         self.seek(SeekFrom::Start(offset + (ptr.ptr() as u64)))?;
         read_nodetype_at_head_nohash(self, ptr.id())
     }
@@ -468,6 +946,7 @@ impl TrieFile {
 
     /// Append a serialized trie to the TrieFile.
     /// Returns the offset at which it was appended.
+    /// While buffering, the trie is only kept in RAM until `flush_buffered()`.
     pub fn append_trie_blob(&mut self, db: &Connection, buf: &[u8]) -> Result<u64, Error> {
         let offset = trie_sql::get_external_blobs_length(db)?;
         test_debug!("Write trie of {} bytes at {}", buf.len(), offset);
@@ -477,18 +956,99 @@ impl TrieFile {
 
         match self {
             TrieFile::Disk(ref mut data) => {
-                data.fd.sync_data()?;
+                data.uncommitted_offset.get_or_insert(offset);
+                if !data.buffering {
+                    data.sync_appended()?;
+                }
             }
             _ => {}
         }
         Ok(offset)
     }
+
+    /// Start buffering appended tries in RAM, so that all the tries stored in a storage
+    /// transaction reach the file in one write and one fsync when it commits.  Buffered tries can
+    /// be read back as usual.  Anything left buffered from an earlier transaction that never
+    /// committed is discarded.  Does nothing for a RAM-backed TrieFile.
+    pub fn begin_buffering(&mut self) {
+        self.discard_buffered();
+        if let TrieFile::Disk(ref mut disk) = self {
+            disk.buffering = true;
+        }
+    }
+
+    /// Write out and fsync (per the sync mode) everything buffered since `begin_buffering()`, and
+    /// stop buffering.
+    /// This must happen before the transaction that recorded the tries' offsets commits.  On
+    /// error, the buffered tries are kept so that `discard_buffered()` can drop them.
+    pub fn flush_buffered(&mut self) -> Result<(), Error> {
+        if let TrieFile::Disk(ref mut disk) = self {
+            disk.buffering = false;
+            if !disk.pending.is_empty() {
+                test_debug!(
+                    "Write {} buffered trie bytes at {}",
+                    disk.pending.len(),
+                    disk.pending_offset
+                );
+                disk.fd.seek(SeekFrom::Start(disk.pending_offset))?;
+                disk.fd.write_all(&disk.pending)?;
+                disk.fd.flush()?;
+                disk.sync_appended()?;
+                disk.pos = disk.pending_offset + disk.pending.len() as u64;
+                disk.pending.clear();
+            }
+            disk.uncommitted_offset = None;
+        }
+        Ok(())
+    }
+
+    /// Drop everything buffered since `begin_buffering()` (i.e. because its transaction rolled
+    /// back), along with the cached offsets and pages of the tries in it, and stop buffering.
+    pub fn discard_buffered(&mut self) {
+        if let TrieFile::Disk(ref mut disk) = self {
+            disk.buffering = false;
+            if let Some(uncommitted_offset) = disk.uncommitted_offset.take() {
+                if let Some(page_cache) = disk.page_cache.as_mut() {
+                    page_cache.discard_from(uncommitted_offset);
+                }
+            }
+            if disk.pending.is_empty() {
+                return;
+            }
+            let pending_offset = disk.pending_offset;
+            disk.trie_offsets
+                .retain(|_, offset| *offset < pending_offset);
+            disk.pending.clear();
+            // the position may have been in the discarded bytes
+            if let Ok(pos) = disk.fd.seek(SeekFrom::Start(pending_offset)) {
+                disk.pos = pos;
+            }
+        }
+    }
 }
 
-/// Boilerplate Write implementation for TrieFileDisk.  Plumbs through to the inner fd.
+/// Write implementation for TrieFileDisk.  Plumbs through to the inner fd, unless buffering.
 impl Write for TrieFileDisk {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.fd.write(buf)
+        if self.buffering {
+            if self.pending.is_empty() {
+                self.pending_offset = self.pos;
+            }
+            if self.pos == self.pending_offset + self.pending.len() as u64 {
+                self.pending.extend_from_slice(buf);
+                self.pos += buf.len() as u64;
+                return Ok(buf.len());
+            }
+            // not an append to the buffered bytes, so they can't stay buffered
+            let pos = self.pos;
+            let pending = std::mem::replace(&mut self.pending, vec![]);
+            self.fd.seek(SeekFrom::Start(self.pending_offset))?;
+            self.fd.write_all(&pending)?;
+            self.fd.seek(SeekFrom::Start(pos))?;
+        }
+        let num_written = self.fd.write(buf)?;
+        self.pos += num_written as u64;
+        Ok(num_written)
     }
 
     fn flush(&mut self) -> io::Result<()> {
@@ -524,10 +1084,30 @@ impl Write for TrieFile {
     }
 }
 
-/// Boilerplate Read implementation for TrieFileDisk.  Plumbs through to the inner fd.
+/// Read implementation for TrieFileDisk.  Reads buffered bytes from RAM, and the rest from the
+/// inner fd.
 impl Read for TrieFileDisk {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.fd.read(buf)
+        if self.pending.is_empty() {
+            let num_read = self.fd.read(buf)?;
+            self.pos += num_read as u64;
+            return Ok(num_read);
+        }
+        if self.pos >= self.pending_offset {
+            let start = cmp::min(
+                (self.pos - self.pending_offset) as usize,
+                self.pending.len(),
+            );
+            let num_read = cmp::min(buf.len(), self.pending.len() - start);
+            buf[..num_read].copy_from_slice(&self.pending[start..(start + num_read)]);
+            self.pos += num_read as u64;
+            return Ok(num_read);
+        }
+        // don't read past the start of the buffered bytes
+        let limit = cmp::min(buf.len() as u64, self.pending_offset - self.pos) as usize;
+        let num_read = self.fd.read(&mut buf[..limit])?;
+        self.pos += num_read as u64;
+        Ok(num_read)
     }
 }
 
@@ -548,10 +1128,40 @@ impl Read for TrieFile {
     }
 }
 
-/// Boilerplate Seek implementation for TrieFileDisk.  Plumbs through to the inner fd
+/// Seek implementation for TrieFileDisk.  Plumbs through to the inner fd, treating any buffered
+/// bytes as if they were already at the end of it.
 impl Seek for TrieFileDisk {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
-        self.fd.seek(pos)
+        if self.pending.is_empty() {
+            self.pos = self.fd.seek(pos)?;
+            return Ok(self.pos);
+        }
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => checked_add_signed(self.pos, delta),
+            SeekFrom::End(delta) => {
+                checked_add_signed(self.pending_offset + self.pending.len() as u64, delta)
+            }
+        }
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        if new_pos < self.pending_offset {
+            self.fd.seek(SeekFrom::Start(new_pos))?;
+        }
+        self.pos = new_pos;
+        Ok(new_pos)
+    }
+}
+
+fn checked_add_signed(base: u64, delta: i64) -> Option<u64> {
+    if delta >= 0 {
+        base.checked_add(delta as u64)
+    } else {
+        base.checked_sub(delta.unsigned_abs())
     }
 }
 
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
 use std::error;
 use std::fmt;
 use std::fs;
@@ -26,6 +27,7 @@ use rusqlite::{Connection, Transaction};
 use sha2::Digest;
 
 use crate::chainstate::stacks::index::bits::{get_leaf_hash, get_node_hash, read_root_hash};
+use crate::chainstate::stacks::index::file::TrieFileSyncMode;
 use crate::chainstate::stacks::index::node::{
     clear_backptr, is_backptr, set_backptr, CursorError, TrieCursor, TrieNode, TrieNode16,
     TrieNode256, TrieNode4, TrieNode48, TrieNodeID, TrieNodeType, TriePath, TriePtr, TRIEPTR_SIZE,
@@ -37,7 +39,9 @@ use crate::chainstate::stacks::index::trie::Trie;
 use crate::chainstate::stacks::index::Error;
 use crate::chainstate::stacks::index::MARFValue;
 use crate::chainstate::stacks::index::MarfTrieId;
+use crate::codec::StacksMessageCodec;
 use crate::util_lib::db::Error as db_error;
+use crate::util_lib::db::SqliteDBKind;
 use stacks_common::util::hash::Sha512Trunc256Sum;
 use stacks_common::util::log;
 
@@ -79,6 +83,23 @@ pub struct MARFOpenOpts {
     pub external_blobs: bool,
     /// unconditionally do a DB migration (used for testing)
     pub force_db_migrate: bool,
+    /// which DB's sqlite settings to open the MARF with
+    pub db_kind: SqliteDBKind,
+    /// with external blobs, buffer the tries stored in a storage transaction in RAM and write
+    /// them out with a single fsync when it commits, instead of syncing each one
+    pub write_buffering: bool,
+    /// keep all the nodes of this many of the most recently stored tries in RAM, so reads against
+    /// recent chain tips never touch disk.  0 disables pinning.
+    pub pinned_tries: usize,
+    /// with external blobs, when to fsync the tries appended to the blobs file
+    pub blobs_sync_mode: TrieFileSyncMode,
+    /// when trie blobs need to be migrated out of the DB into the blobs file, move this many
+    /// tries per call to `MARF::migrate_trie_blobs_step()` instead of all of them when the MARF
+    /// is opened.  0 migrates everything on open.
+    pub blob_migration_batch: u32,
+    /// with external blobs, how many bytes of the blobs file to cache in pages for reading
+    /// nodes.  No pages are cached if this is smaller than `TRIE_FILE_PAGE_SIZE`.
+    pub blobs_page_cache_size: usize,
 }
 
 impl MARFOpenOpts {
@@ -88,6 +109,12 @@ impl MARFOpenOpts {
             cache_strategy: "noop".to_string(),
             external_blobs: false,
             force_db_migrate: false,
+            db_kind: SqliteDBKind::Marf,
+            write_buffering: false,
+            pinned_tries: 0,
+            blobs_sync_mode: TrieFileSyncMode::PerCommit,
+            blob_migration_batch: 0,
+            blobs_page_cache_size: 0,
         }
     }
 
@@ -101,6 +128,12 @@ impl MARFOpenOpts {
             cache_strategy: cache_strategy.to_string(),
             external_blobs,
             force_db_migrate: false,
+            db_kind: SqliteDBKind::Marf,
+            write_buffering: false,
+            pinned_tries: 0,
+            blobs_sync_mode: TrieFileSyncMode::PerCommit,
+            blob_migration_batch: 0,
+            blobs_page_cache_size: 0,
         }
     }
 
@@ -150,6 +183,15 @@ pub trait MarfConnection<T: MarfTrieId> {
         })
     }
 
+    /// Visit every key whose hash starts with `prefix` in the MARF at the given block.
+    /// See `MARF::scan_prefix()`.
+    fn scan_prefix<F>(&mut self, block_hash: &T, prefix: &[u8], visitor: F) -> Result<(), Error>
+    where
+        F: FnMut(TriePath, MARFValue) -> bool,
+    {
+        self.with_conn(|c| MARF::scan_prefix(c, block_hash, prefix, visitor))
+    }
+
     fn get_block_at_height(&mut self, height: u32, tip: &T) -> Result<Option<T>, Error> {
         self.with_conn(|c| MARF::get_block_at_height(c, height, tip))
     }
@@ -243,8 +285,7 @@ impl<'a, T: MarfTrieId> MarfTransaction<'a, T> {
         if let Some(_tip) = self.open_chain_tip.take() {
             self.storage.flush()?;
         }
-        self.storage.commit_tx();
-        Ok(())
+        self.storage.commit_tx()
     }
 
     /// Finish writing the next trie in the MARF, but change the hash of the current Trie's
@@ -258,7 +299,7 @@ impl<'a, T: MarfTrieId> MarfTransaction<'a, T> {
         }
         if let Some(_tip) = self.open_chain_tip.take() {
             self.storage.flush_to(real_bhh)?;
-            self.storage.commit_tx();
+            self.storage.commit_tx()?;
         }
         Ok(())
     }
@@ -276,7 +317,7 @@ impl<'a, T: MarfTrieId> MarfTransaction<'a, T> {
         }
         if let Some(_tip) = self.open_chain_tip.take() {
             self.storage.flush_mined(bhh)?;
-            self.storage.commit_tx();
+            self.storage.commit_tx()?;
         }
         Ok(())
     }
@@ -303,7 +344,9 @@ impl<'a, T: MarfTrieId> MarfTransaction<'a, T> {
 
     #[cfg(test)]
     fn commit_tx(self) {
-        self.storage.commit_tx()
+        self.storage
+            .commit_tx()
+            .expect("CORRUPTION: Failed to commit MARF")
     }
 
     pub fn sqlite_tx(&self) -> &Transaction<'a> {
@@ -560,7 +603,9 @@ impl<'a, T: MarfTrieId> MarfTransaction<'a, T> {
                 // Dropping unconfirmed state cannot be done with a tx rollback,
                 //   because the unconfirmed state may already have been written
                 //   to the sqlite table before this transaction began
-                self.storage.commit_tx()
+                self.storage
+                    .commit_tx()
+                    .expect("CORRUPTION: Failed to commit MARF")
             } else {
                 trace!("drop_unconfirmed() noop");
             }
@@ -1158,6 +1203,144 @@ impl<T: MarfTrieId> MARF<T> {
         result.map(|option_result| option_result.map(|leaf| leaf.data))
     }
 
+    /// Visit every leaf of the trie at `block_hash` whose path starts with `prefix`, in ascending
+    /// path order, passing its path and value to `visitor`.  The visitor returns `false` to stop
+    /// the scan early.  Leaf paths are the hashes of the inserted keys (see
+    /// `TriePath::from_key()`), so the prefix selects a range of key hashes, not of keys.  This
+    /// includes the MARF's own block height bookkeeping keys.
+    /// The storage connection is restored to the block it was open to beforehand.
+    pub fn scan_prefix<F>(
+        storage: &mut TrieStorageConnection<T>,
+        block_hash: &T,
+        prefix: &[u8],
+        mut visitor: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(TriePath, MARFValue) -> bool,
+    {
+        let (cur_block_hash, cur_block_id) = storage.get_cur_block_and_id();
+
+        let result = MARF::inner_scan_prefix(storage, block_hash, prefix, &mut visitor);
+
+        // restore
+        storage.open_block_maybe_id(&cur_block_hash, cur_block_id)?;
+
+        result
+    }
+
+    fn inner_scan_prefix<F>(
+        storage: &mut TrieStorageConnection<T>,
+        block_hash: &T,
+        prefix: &[u8],
+        visitor: &mut F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(TriePath, MARFValue) -> bool,
+    {
+        storage.open_block(block_hash)?;
+        let (_, block_id) = storage.get_cur_block_and_id();
+        let root = Trie::read_root_nohash(storage)?;
+
+        // nodes left to visit, as (block containing the node, its ID, ptr within that block,
+        // path bytes leading up to the node).  Children are pushed in descending order of their
+        // chr, so they get popped in ascending order.
+        let mut pending = vec![];
+        MARF::push_scan_children(
+            storage,
+            &root,
+            block_hash,
+            block_id,
+            root.path_bytes().clone(),
+            prefix,
+            &mut pending,
+        )?;
+
+        while let Some((node_block_hash, node_block_id, ptr, mut path)) = pending.pop() {
+            storage.open_block_maybe_id(&node_block_hash, node_block_id)?;
+            let node = storage.read_nodetype_nohash(&ptr)?;
+            path.extend_from_slice(node.path_bytes());
+
+            match node {
+                TrieNodeType::Leaf(leaf) => {
+                    if !path.starts_with(prefix) {
+                        continue;
+                    }
+                    let leaf_path = TriePath::from_bytes(&path).ok_or_else(|| {
+                        Error::CorruptionError(format!(
+                            "Leaf in {} has a {}-byte path",
+                            &node_block_hash,
+                            path.len()
+                        ))
+                    })?;
+                    if !visitor(leaf_path, leaf.data) {
+                        return Ok(());
+                    }
+                }
+                _ => {
+                    MARF::push_scan_children(
+                        storage,
+                        &node,
+                        &node_block_hash,
+                        node_block_id,
+                        path,
+                        prefix,
+                        &mut pending,
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Queue up the children of an intermediate node that can lead to a path starting with
+    /// `prefix`, resolving back-pointers to the block that holds the child.
+    fn push_scan_children(
+        storage: &mut TrieStorageConnection<T>,
+        node: &TrieNodeType,
+        node_block_hash: &T,
+        node_block_id: Option<u32>,
+        path: Vec<u8>,
+        prefix: &[u8],
+        pending: &mut Vec<(T, Option<u32>, TriePtr, Vec<u8>)>,
+    ) -> Result<(), Error> {
+        let overlaps_prefix = |path: &[u8]| {
+            let len = path.len().min(prefix.len());
+            path[..len] == prefix[..len]
+        };
+        if !overlaps_prefix(&path) {
+            return Ok(());
+        }
+
+        let mut children: Vec<TriePtr> = node
+            .ptrs()
+            .iter()
+            .filter(|ptr| ptr.id() != TrieNodeID::Empty as u8)
+            .cloned()
+            .collect();
+        children.sort_by_key(|ptr| ptr.chr());
+
+        for ptr in children.into_iter().rev() {
+            let mut child_path = path.clone();
+            child_path.push(ptr.chr());
+            if !overlaps_prefix(&child_path) {
+                continue;
+            }
+
+            if is_backptr(ptr.id()) {
+                let back_block_hash = storage.get_block_from_local_id(ptr.back_block())?.clone();
+                pending.push((
+                    back_block_hash,
+                    Some(ptr.back_block()),
+                    ptr.from_backptr(),
+                    child_path,
+                ));
+            } else {
+                pending.push((node_block_hash.clone(), node_block_id, ptr, child_path));
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_block_height_miner_tip(
         storage: &mut TrieStorageConnection<T>,
         block_hash: &T,
@@ -1353,8 +1536,7 @@ impl<T: MarfTrieId> MARF<T> {
 
         let mut tx = self.storage.transaction()?;
         MARF::inner_insert_batch(&mut tx, &block_hash, keys, values)?;
-        tx.commit_tx();
-        Ok(())
+        tx.commit_tx()
     }
 
     pub fn insert(&mut self, key: &str, value: MARFValue) -> Result<(), Error> {
@@ -1383,7 +1565,7 @@ impl<T: MarfTrieId> MARF<T> {
 
                 // restore
                 tx.open_block_maybe_id(&cur_block_hash, cur_block_id)?;
-                tx.commit_tx();
+                tx.commit_tx()?;
 
                 result
             }
@@ -1402,7 +1584,7 @@ impl<T: MarfTrieId> MARF<T> {
             self.open_chain_tip.take();
             tx.open_block(&T::sentinel())
                 .expect("BUG: should never fail to open the block sentinel");
-            tx.commit_tx();
+            tx.commit_tx().expect("CORRUPTION: Failed to commit MARF");
         }
     }
 
@@ -1417,7 +1599,7 @@ impl<T: MarfTrieId> MARF<T> {
                 tx.drop_unconfirmed_trie(&tip.block_hash);
                 tx.open_block(&T::sentinel())
                     .expect("BUG: should never fail to open the block sentinel");
-                tx.commit_tx();
+                tx.commit_tx().expect("CORRUPTION: Failed to commit MARF");
             }
         }
     }
@@ -1431,7 +1613,7 @@ impl<T: MarfTrieId> MARF<T> {
         if let Some(_tip) = self.open_chain_tip.take() {
             let mut tx = self.storage.transaction()?;
             tx.flush()?;
-            tx.commit_tx();
+            tx.commit_tx()?;
         }
         Ok(())
     }
@@ -1450,7 +1632,7 @@ impl<T: MarfTrieId> MARF<T> {
         if let Some(_tip) = self.open_chain_tip.take() {
             let mut tx = self.storage.transaction()?;
             tx.flush_mined(bhh)?;
-            tx.commit_tx();
+            tx.commit_tx()?;
         }
         Ok(())
     }
@@ -1467,7 +1649,7 @@ impl<T: MarfTrieId> MARF<T> {
         if let Some(_tip) = self.open_chain_tip.take() {
             let mut tx = self.storage.transaction()?;
             tx.flush_to(real_bhh)?;
-            tx.commit_tx();
+            tx.commit_tx()?;
         }
         Ok(())
     }
@@ -1509,6 +1691,13 @@ impl<T: MarfTrieId> MARF<T> {
         self.storage.transaction().unwrap()
     }
 
+    /// Move up to `max_tries` more trie blobs out of the DB and into the blobs file, if an
+    /// incremental migration is underway (see `MARFOpenOpts::blob_migration_batch`).
+    /// Returns true if there is nothing left to migrate.
+    pub fn migrate_trie_blobs_step(&mut self, max_tries: u32) -> Result<bool, Error> {
+        self.storage.migrate_trie_blobs_step(max_tries)
+    }
+
     /// Make a raw transaction to the underlying storage
     pub fn storage_tx<'a>(&'a mut self) -> Result<Transaction<'a>, db_error> {
         self.storage.sqlite_tx()
@@ -1536,8 +1725,58 @@ impl<T: MarfTrieId> MARF<T> {
         })
     }
 
+    /// Open an independent read-only MARF over the same storage, pinned to a snapshot of the
+    /// committed tries.  Any number of these can be open at once, each with its own connection
+    /// and node cache, and none of them blocks (or is blocked by) a writer on this MARF.
+    /// Call `refresh_snapshot()` to see blocks committed since.
+    pub fn reopen_snapshot(&self, cache_strategy: &str) -> Result<MARF<T>, Error> {
+        let ro_storage = self.storage.reopen_snapshot(cache_strategy)?;
+        Ok(MARF {
+            storage: ro_storage,
+            open_chain_tip: None,
+        })
+    }
+
+    /// Move this snapshot MARF up to the latest committed state.
+    pub fn refresh_snapshot(&mut self) -> Result<(), Error> {
+        self.storage.end_snapshot()?;
+        self.storage.begin_snapshot()
+    }
+
     /// Get the root trie hash at a particular block
     pub fn get_root_hash_at(&mut self, block_hash: &T) -> Result<TrieHash, Error> {
         self.storage.connection().get_root_hash_at(block_hash)
     }
 }
+
+/// Verify a consensus-serialized Merkle proof, such as the ones the RPC interface serves, that
+/// `key` maps to `value` in the MARF whose root hash at the queried block is `root_hash`.  `value`
+/// is the string stored under `key`, e.g. the hex serialization of a Clarity value.
+///
+/// If the value was written before the queried block, the proof runs through the tries of some of
+/// its ancestors, and `root_to_block` must map the root hashes of those tries to their blocks.
+/// Verifiers can build this map from the ancestors' headers.
+///
+/// Returns Ok(false) if the proof is well-formed but does not prove the mapping, and Err if the
+/// proof cannot be decoded.
+pub fn verify_proof<T: MarfTrieId>(
+    key: &str,
+    value: &str,
+    proof: &[u8],
+    root_hash: &TrieHash,
+    root_to_block: &HashMap<TrieHash, T>,
+) -> Result<bool, Error> {
+    let mut proof_bytes = proof;
+    let proof = TrieMerkleProof::<T>::consensus_deserialize(&mut proof_bytes)
+        .map_err(|e| Error::CorruptionError(format!("Failed to decode proof: {:?}", &e)))?;
+    if !proof_bytes.is_empty() {
+        return Err(Error::CorruptionError(format!(
+            "Failed to decode proof: {} trailing bytes",
+            proof_bytes.len()
+        )));
+    }
+
+    let path = TriePath::from_key(key);
+    let marf_value = MARFValue::from_value(value);
+    Ok(proof.verify(&path, &marf_value, root_hash, root_to_block))
+}
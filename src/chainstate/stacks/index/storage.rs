@@ -55,13 +55,11 @@ use crate::chainstate::stacks::index::trie::Trie;
 use crate::chainstate::stacks::index::Error;
 use crate::chainstate::stacks::index::TrieHasher;
 use crate::chainstate::stacks::index::{trie_sql, BlockMap, MarfTrieId};
-use crate::util_lib::db::sql_pragma;
-use crate::util_lib::db::sqlite_open;
+use crate::util_lib::db::sqlite_open_kind;
 use crate::util_lib::db::tx_begin_immediate;
 use crate::util_lib::db::tx_busy_handler;
 use crate::util_lib::db::Error as db_error;
-use crate::util_lib::db::SQLITE_MARF_PAGE_SIZE;
-use crate::util_lib::db::SQLITE_MMAP_SIZE;
+use crate::util_lib::db::SqliteDBKind;
 
 use stacks_common::util::hash::to_hex;
 use stacks_common::util::log;
@@ -98,7 +96,7 @@ impl<T: MarfTrieId> BlockMap for TrieFileStorage<T> {
     }
 
     fn get_block_id(&self, block_hash: &T) -> Result<u32, Error> {
-        trie_sql::get_block_identifier(&self.db, block_hash)
+        trie_sql::get_any_block_identifier(&self.db, block_hash, self.data.unconfirmed)
     }
 
     fn get_block_id_caching(&mut self, block_hash: &T) -> Result<u32, Error> {
@@ -137,7 +135,7 @@ impl<'a, T: MarfTrieId> BlockMap for TrieStorageConnection<'a, T> {
     }
 
     fn get_block_id(&self, block_hash: &T) -> Result<u32, Error> {
-        trie_sql::get_block_identifier(&self.db, block_hash)
+        trie_sql::get_any_block_identifier(&self.db, block_hash, self.data.unconfirmed)
     }
 
     fn get_block_id_caching(&mut self, block_hash: &T) -> Result<u32, Error> {
@@ -200,7 +198,7 @@ impl<T: MarfTrieId> BlockMap for TrieSqlHashMapCursor<'_, T> {
     }
 
     fn get_block_id(&self, block_hash: &T) -> Result<u32, Error> {
-        trie_sql::get_block_identifier(&self.db, block_hash)
+        trie_sql::get_any_block_identifier(&self.db, block_hash, self.unconfirmed)
     }
 
     fn get_block_id_caching(&mut self, block_hash: &T) -> Result<u32, Error> {
@@ -1231,8 +1229,10 @@ pub struct TrieStorageConnection<'a, T: MarfTrieId> {
     blobs: Option<&'a mut TrieFile>,
     data: &'a mut TrieStorageTransientData<T>,
     cache: &'a mut TrieCache<T>,
+    pinned: &'a mut PinnedTries,
     bench: &'a mut TrieBenchmark,
     pub hash_calculation_mode: TrieHashCalculationMode,
+    db_kind: SqliteDBKind,
 
     /// row ID of a trie that represents unconfirmed state (i.e. trie state that will never become
     /// part of the MARF, but nevertheless represents a persistent scratch space).  If this field
@@ -1297,8 +1297,13 @@ pub struct TrieFileStorage<T: MarfTrieId> {
     blobs: Option<TrieFile>,
     data: TrieStorageTransientData<T>,
     cache: TrieCache<T>,
+    /// Complete node sets of the most recently stored tries
+    pinned: PinnedTries,
     bench: TrieBenchmark,
     hash_calculation_mode: TrieHashCalculationMode,
+    db_kind: SqliteDBKind,
+    /// Buffer the tries stored in a transaction, and write them to the blobs file on commit
+    write_buffering: bool,
 
     // used in testing in order to short-circuit block-height lookups
     //   when the trie struct is tested outside of marf.rs usage
@@ -1307,14 +1312,22 @@ pub struct TrieFileStorage<T: MarfTrieId> {
 }
 
 /// Helper to open a MARF
+/// Path to the database that holds a MARF's unconfirmed tries, given the path to its main database
+fn unconfirmed_db_path(db_path: &str) -> String {
+    if db_path == ":memory:" {
+        db_path.to_string()
+    } else {
+        format!("{}.unconfirmed", db_path)
+    }
+}
+
 fn marf_sqlite_open<P: AsRef<Path>>(
     db_path: P,
     open_flags: OpenFlags,
     foreign_keys: bool,
+    db_kind: SqliteDBKind,
 ) -> Result<Connection, db_error> {
-    let db = sqlite_open(db_path, open_flags, foreign_keys)?;
-    sql_pragma(&db, "mmap_size", &SQLITE_MMAP_SIZE)?;
-    sql_pragma(&db, "page_size", &SQLITE_MARF_PAGE_SIZE)?;
+    let db = sqlite_open_kind(db_path, open_flags, foreign_keys, db_kind)?;
     Ok(db)
 }
 
@@ -1337,14 +1350,21 @@ impl<T: MarfTrieId> TrieStorageTransientData<T> {
 
 impl<T: MarfTrieId> TrieFileStorage<T> {
     pub fn connection<'a>(&'a mut self) -> TrieStorageConnection<'a, T> {
+        if let Some(blobs) = self.blobs.as_mut() {
+            // left over from a transaction that was dropped without committing
+            blobs.discard_buffered();
+        }
+        self.pinned.discard_staged();
         TrieStorageConnection {
             db: SqliteConnection::ConnRef(&self.db),
             db_path: &self.db_path,
             data: &mut self.data,
             blobs: self.blobs.as_mut(),
             cache: &mut self.cache,
+            pinned: &mut self.pinned,
             bench: &mut self.bench,
             hash_calculation_mode: self.hash_calculation_mode,
+            db_kind: self.db_kind,
             unconfirmed_block_id: None,
 
             #[cfg(test)]
@@ -1357,6 +1377,14 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
             return Err(Error::ReadOnlyError);
         }
         let tx = tx_begin_immediate(&mut self.db)?;
+        if let Some(blobs) = self.blobs.as_mut() {
+            if self.write_buffering {
+                blobs.begin_buffering();
+            } else {
+                blobs.discard_buffered();
+            }
+        }
+        self.pinned.discard_staged();
 
         Ok(TrieStorageTransaction(TrieStorageConnection {
             db: SqliteConnection::Tx(tx),
@@ -1364,8 +1392,10 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
             data: &mut self.data,
             blobs: self.blobs.as_mut(),
             cache: &mut self.cache,
+            pinned: &mut self.pinned,
             bench: &mut self.bench,
             hash_calculation_mode: self.hash_calculation_mode,
+            db_kind: self.db_kind,
             unconfirmed_block_id: None,
 
             #[cfg(test)]
@@ -1421,7 +1451,7 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
             }
         };
 
-        let mut db = marf_sqlite_open(db_path, open_flags, false)?;
+        let mut db = marf_sqlite_open(db_path, open_flags, false, marf_opts.db_kind)?;
         let db_path = db_path.to_string();
 
         if create_flag {
@@ -1429,7 +1459,12 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
         }
 
         let mut blobs = if marf_opts.external_blobs {
-            Some(TrieFile::from_db_path(&db_path, readonly)?)
+            Some(TrieFile::from_db_path_opts(
+                &db_path,
+                readonly,
+                marf_opts.blobs_sync_mode,
+                marf_opts.blobs_page_cache_size,
+            )?)
         } else {
             None
         };
@@ -1438,14 +1473,46 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
         if prev_schema_version != trie_sql::SQL_MARF_SCHEMA_VERSION || marf_opts.force_db_migrate {
             if let Some(blobs) = blobs.as_mut() {
                 if TrieFile::exists(&db_path)? {
-                    // migrate blobs out of the old DB
-                    blobs.export_trie_blobs::<T>(&db, &db_path)?;
+                    if marf_opts.blob_migration_batch > 0 && !readonly {
+                        // migrate blobs out of the old DB a few at a time (see
+                        // `migrate_trie_blobs_step()`)
+                        info!(
+                            "Begin incremental migration of tries to external blob storage at {}",
+                            &blobs.get_path()
+                        );
+                        trie_sql::begin_blob_migration(&db)?;
+                    } else {
+                        // migrate blobs out of the old DB
+                        blobs.export_trie_blobs::<T>(&db, &db_path)?;
+                    }
+                }
+            }
+        }
+        if marf_opts.blob_migration_batch == 0 && !readonly {
+            if let Some(blobs) = blobs.as_mut() {
+                if trie_sql::get_blob_migration_progress(&db)?.is_some() {
+                    // finish an incremental migration begun by an earlier run
+                    info!(
+                        "Finish incremental migration of tries to external blob storage at {}",
+                        &blobs.get_path()
+                    );
+                    loop {
+                        let tx = tx_begin_immediate(&mut db)?;
+                        let done = blobs.migrate_trie_blobs_step(&tx, u32::MAX)?;
+                        tx.commit()?;
+                        if done {
+                            break;
+                        }
+                    }
                 }
             }
         }
         if trie_sql::detect_partial_migration(&db)? {
             panic!("PARTIAL MIGRATION DETECTED! This is an irrecoverable error. You will need to restart your node from genesis.");
         }
+        if unconfirmed {
+            trie_sql::attach_unconfirmed_db(&db, &unconfirmed_db_path(&db_path), readonly)?;
+        }
 
         debug!(
             "Opened TrieFileStorage {}; external blobs: {}",
@@ -1455,13 +1522,16 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
 
         let cache = TrieCache::new(&marf_opts.cache_strategy);
 
-        let ret = TrieFileStorage {
+        let mut ret = TrieFileStorage {
             db_path,
             db,
             cache,
+            pinned: PinnedTries::new(marf_opts.pinned_tries),
             blobs,
             bench: TrieBenchmark::new(),
             hash_calculation_mode: marf_opts.hash_calculation_mode,
+            db_kind: marf_opts.db_kind,
+            write_buffering: marf_opts.write_buffering,
 
             data: TrieStorageTransientData {
                 uncommitted_writes: None,
@@ -1489,6 +1559,10 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
             test_genesis_block: None,
         };
 
+        if ret.pinned.capacity() > 0 {
+            ret.connection().pin_recent_tries()?;
+        }
+
         Ok(ret)
     }
 
@@ -1514,6 +1588,7 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
     ) -> Result<TrieFileStorage<T>, Error> {
         // no caching allowed for unconfirmed tries, since they can disappear
         marf_opts.cache_strategy = "noop".to_string();
+        marf_opts.pinned_tries = 0;
         TrieFileStorage::open_opts(db_path, false, true, marf_opts)
     }
 
@@ -1521,6 +1596,32 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
         self.data.readonly
     }
 
+    /// Take one step of an incremental migration of trie blobs into the blobs file, copying up
+    /// to `max_tries` tries (see `MARFOpenOpts::blob_migration_batch`).  Tries are served from
+    /// whichever of the DB and the blobs file holds them while the migration is underway.
+    /// Returns true if there is nothing left to migrate.
+    pub fn migrate_trie_blobs_step(&mut self, max_tries: u32) -> Result<bool, Error> {
+        if self.readonly() {
+            return Err(Error::ReadOnlyError);
+        }
+        let blobs = match self.blobs.as_mut() {
+            Some(blobs) => blobs,
+            None => {
+                return Ok(true);
+            }
+        };
+        let tx = tx_begin_immediate(&mut self.db)?;
+        let done = match blobs.migrate_trie_blobs_step(&tx, max_tries) {
+            Ok(done) => done,
+            Err(e) => {
+                blobs.discard_buffered();
+                return Err(e);
+            }
+        };
+        tx.commit()?;
+        Ok(done)
+    }
+
     /// Return true if this storage connection was opened with the intention of operating on an
     /// unconfirmed trie -- i.e. this is a storage connection for reading and writing a persisted
     /// scratch space trie, such as one for storing unconfirmed microblock transactions in the
@@ -1530,7 +1631,15 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
     }
 
     pub fn reopen_readonly(&self) -> Result<TrieFileStorage<T>, Error> {
-        let db = marf_sqlite_open(&self.db_path, OpenFlags::SQLITE_OPEN_READ_ONLY, false)?;
+        let db = marf_sqlite_open(
+            &self.db_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY,
+            false,
+            self.db_kind,
+        )?;
+        if self.unconfirmed() {
+            trie_sql::attach_unconfirmed_db(&db, &unconfirmed_db_path(&self.db_path), true)?;
+        }
         let cache = TrieCache::default();
         let blobs = if self.blobs.is_some() {
             Some(TrieFile::from_db_path(&self.db_path, true)?)
@@ -1546,8 +1655,11 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
             db: db,
             blobs,
             cache: cache,
+            pinned: PinnedTries::new(0),
             bench: TrieBenchmark::new(),
             hash_calculation_mode: self.hash_calculation_mode,
+            db_kind: self.db_kind,
+            write_buffering: false,
 
             data: TrieStorageTransientData {
                 uncommitted_writes: self.data.uncommitted_writes.clone(),
@@ -1578,6 +1690,67 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
         Ok(ret)
     }
 
+    /// Open an independent read-only view of this storage, with its own DB connection, trie blob
+    /// file descriptor and node cache (built with `cache_strategy`), pinned to a snapshot of the
+    /// currently-committed state.  See `begin_snapshot()` for what the snapshot covers.
+    /// Unlike `reopen_readonly()`, writes buffered in this storage's uncommitted trie are not
+    /// carried over.
+    pub fn reopen_snapshot(&self, cache_strategy: &str) -> Result<TrieFileStorage<T>, Error> {
+        let mut ret = self.reopen_readonly()?;
+        ret.cache = TrieCache::new(cache_strategy);
+        ret.data.uncommitted_writes = None;
+        ret.data.set_block(T::sentinel(), None);
+        ret.begin_snapshot()?;
+        Ok(ret)
+    }
+
+    /// Pin this read-only storage to the state of the MARF as of the last committed write.
+    /// Until `end_snapshot()` is called, blocks committed afterwards by a writer on another
+    /// connection are not visible here, and every read is consistent with the same set of tries.
+    /// Trie blobs are append-only, so a writer never moves the data a snapshot refers to (an
+    /// incremental blob migration copies tries, and the snapshot keeps seeing the DB copies).
+    ///
+    /// This holds a read transaction open on the underlying DB, which keeps the writer from
+    /// checkpointing the WAL past it, so snapshots should be short-lived or refreshed regularly.
+    pub fn begin_snapshot(&mut self) -> Result<(), Error> {
+        if !self.readonly() {
+            error!(
+                "Refusing to take a snapshot of writable storage {}",
+                &self.db_path
+            );
+            return Err(Error::ReadOnlyError);
+        }
+        if self.in_snapshot() {
+            return Err(Error::InProgressError);
+        }
+        self.db.execute_batch("BEGIN DEFERRED")?;
+        // the snapshot is only taken once the transaction first reads
+        let res: Result<i64, _> =
+            self.db
+                .query_row("SELECT EXISTS(SELECT 1 FROM marf_data)", NO_PARAMS, |row| {
+                    row.get(0)
+                });
+        if let Err(e) = res {
+            let _ = self.db.execute_batch("ROLLBACK");
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// Release the snapshot taken by `begin_snapshot()`.  Reads go back to seeing the latest
+    /// committed state.  Does nothing if there is no snapshot.
+    pub fn end_snapshot(&mut self) -> Result<(), Error> {
+        if self.in_snapshot() {
+            self.db.execute_batch("ROLLBACK")?;
+        }
+        Ok(())
+    }
+
+    /// Is this storage pinned to a snapshot?
+    pub fn in_snapshot(&self) -> bool {
+        !self.db.is_autocommit()
+    }
+
     pub fn get_benchmarks(&self) -> TrieBenchmark {
         self.bench.clone()
     }
@@ -1595,7 +1768,15 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
     /// reopen this transaction as a read-only marf.
     ///  _does not_ preserve the cur_block/open tip
     pub fn reopen_readonly(&self) -> Result<TrieFileStorage<T>, Error> {
-        let db = marf_sqlite_open(&self.db_path, OpenFlags::SQLITE_OPEN_READ_ONLY, false)?;
+        let db = marf_sqlite_open(
+            &self.db_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY,
+            false,
+            self.db_kind,
+        )?;
+        if self.unconfirmed() {
+            trie_sql::attach_unconfirmed_db(&db, &unconfirmed_db_path(&self.db_path), true)?;
+        }
         let blobs = if self.blobs.is_some() {
             Some(TrieFile::from_db_path(&self.db_path, true)?)
         } else {
@@ -1615,8 +1796,11 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
             db: db,
             blobs: blobs,
             cache: cache,
+            pinned: PinnedTries::new(0),
             bench: TrieBenchmark::new(),
             hash_calculation_mode: self.hash_calculation_mode,
+            db_kind: self.db_kind,
+            write_buffering: false,
 
             data: TrieStorageTransientData {
                 uncommitted_writes: None,
@@ -1735,6 +1919,16 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
             trie_sql::drop_lock(&self.db, &bhh)?;
 
             debug!("Flush: identifier of {} is {}", flush_options, block_id);
+
+            match flush_options {
+                FlushOptions::CurrentHeader | FlushOptions::NewHeader(_)
+                    if self.pinned.capacity() > 0 =>
+                {
+                    let nodes = PinnedTries::materialize(&buffer, self.root_disk_trieptr())?;
+                    self.pinned.stage(block_id, nodes);
+                }
+                _ => {}
+            }
         }
 
         Ok(())
@@ -1936,10 +2130,21 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
         }
     }
 
-    pub fn commit_tx(self) {
+    /// Commit the transaction.  If the tries buffered in it cannot be written out, the
+    /// transaction is rolled back instead and the error is returned.
+    pub fn commit_tx(mut self) -> Result<(), Error> {
+        if let Some(blobs) = self.0.blobs.as_mut() {
+            // the buffered tries must be on disk before the offsets that point to them are
+            if let Err(e) = blobs.flush_buffered() {
+                error!("Failed to write buffered MARF tries: {:?}", &e);
+                self.rollback();
+                return Err(e);
+            }
+        }
         match self.0.db {
             SqliteConnection::Tx(tx) => {
                 tx.commit().expect("CORRUPTION: Failed to commit MARF");
+                self.0.pinned.commit_staged();
             }
             SqliteConnection::ConnRef(_) => {
                 unreachable!(
@@ -1947,9 +2152,15 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
                 );
             }
         }
+        Ok(())
     }
 
-    pub fn rollback(self) {
+    pub fn rollback(mut self) {
+        if let Some(blobs) = self.0.blobs.as_mut() {
+            blobs.discard_buffered();
+        }
+        self.0.pinned.discard_staged();
+        self.0.cache.clear();
         match self.0.db {
             SqliteConnection::Tx(tx) => {
                 tx.rollback().expect("CORRUPTION: Failed to commit MARF");
@@ -2036,7 +2247,12 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
     /// Recover from partially-written state -- i.e. blow it away.
     /// Doesn't get called automatically.
     pub fn recover(db_path: &String) -> Result<(), Error> {
-        let conn = marf_sqlite_open(db_path, OpenFlags::SQLITE_OPEN_READ_WRITE, false)?;
+        let conn = marf_sqlite_open(
+            db_path,
+            OpenFlags::SQLITE_OPEN_READ_WRITE,
+            false,
+            SqliteDBKind::Marf,
+        )?;
         trie_sql::clear_lock_data(&conn)
     }
 
@@ -2114,6 +2330,10 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
 
     /// Is the given block in the marf_data DB table, and is it unconfirmed?
     pub fn has_unconfirmed_block(&self, bhh: &T) -> Result<bool, Error> {
+        if !self.data.unconfirmed {
+            // only unconfirmed storage has the unconfirmed trie database attached
+            return Ok(false);
+        }
         match trie_sql::get_unconfirmed_block_identifier(&self.db, bhh) {
             Ok(Some(_)) => Ok(true),
             Ok(None) => Ok(false),
@@ -2244,6 +2464,20 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
             e
         })?;
 
+        // a read-only storage never stores tries itself, so it pins newer ones as it sees them
+        if self.readonly()
+            && self.pinned.capacity() > 0
+            && self
+                .pinned
+                .newest()
+                .map(|newest| block_id > newest)
+                .unwrap_or(true)
+        {
+            if let Err(e) = self.pin_trie(block_id) {
+                warn!("Failed to pin trie {} ({}): {:?}", bhh, block_id, &e);
+            }
+        }
+
         self.data.set_block(bhh.clone(), Some(block_id));
         self.bench.open_block_finish(false);
         Ok(())
@@ -2312,6 +2546,53 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         (BLOCK_HEADER_HASH_ENCODED_SIZE as u32) + 4
     }
 
+    /// Get a TriePtr to a trie's root node if the trie is stored to disk.
+    fn root_disk_trieptr(&self) -> TriePtr {
+        TriePtr::new(
+            TrieNodeID::Node256 as u8,
+            0,
+            TrieStorageConnection::<T>::root_ptr_disk(),
+        )
+    }
+
+    /// Read a confirmed trie's nodes off of disk, and pin them.
+    fn pin_trie(&mut self, block_id: u32) -> Result<(), Error> {
+        let trie_blob = match self.blobs.as_mut() {
+            Some(blobs) => blobs.read_trie_blob(&self.db, block_id)?,
+            None => {
+                let mut fd = trie_sql::open_trie_blob_readonly(&self.db, block_id)?;
+                let mut trie_blob = vec![];
+                fd.read_to_end(&mut trie_blob)?;
+                trie_blob
+            }
+        };
+        let nodes = PinnedTries::materialize(&trie_blob, self.root_disk_trieptr())?;
+        self.pinned.pin(block_id, nodes);
+        Ok(())
+    }
+
+    /// Pin the most recently stored confirmed tries, up to the pinned tries' capacity.
+    pub fn pin_recent_tries(&mut self) -> Result<(), Error> {
+        let block_ids =
+            trie_sql::get_latest_confirmed_block_ids(&self.db, self.pinned.capacity() as u32)?;
+        for block_id in block_ids.into_iter() {
+            if !self.pinned.is_pinned(block_id) {
+                self.pin_trie(block_id)?;
+            }
+        }
+        debug!(
+            "Pinned {} tries of {} in RAM",
+            self.pinned.len(),
+            &self.db_path
+        );
+        Ok(())
+    }
+
+    /// Number of tries whose nodes are pinned in RAM
+    pub fn num_pinned_tries(&self) -> usize {
+        self.pinned.len()
+    }
+
     /// Read a node's children's hashes into the provided <Write> implementation.
     /// This only works for intermediate nodes and leafs (the latter of which have no children).
     ///
@@ -2503,7 +2784,10 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         match self.data.cur_block_id {
             Some(block_id) => {
                 self.bench.read_node_hash_start();
-                if let Some(node_hash) = self.cache.load_node_hash(block_id, ptr) {
+                if let Some(node_hash) = self.pinned.load_node_hash(block_id, ptr) {
+                    self.bench.read_node_hash_finish(true);
+                    Ok(node_hash)
+                } else if let Some(node_hash) = self.cache.load_node_hash(block_id, ptr) {
                     let res = node_hash;
                     self.bench.read_node_hash_finish(true);
                     Ok(res)
@@ -2616,6 +2900,15 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
         match self.data.cur_block_id {
             Some(id) => {
                 self.bench.read_nodetype_start();
+                if let Some((node_inst, node_hash)) = self.pinned.load_node_and_hash(id, &clear_ptr)
+                {
+                    self.bench.read_nodetype_finish(true);
+                    if read_hash {
+                        return Ok((node_inst, node_hash));
+                    } else {
+                        return Ok((node_inst, TrieHash([0u8; TRIEHASH_ENCODED_SIZE])));
+                    }
+                }
                 let (node_inst, node_hash) = if read_hash {
                     if let Some((node_inst, node_hash)) =
                         self.cache.load_node_and_hash(id, &clear_ptr)
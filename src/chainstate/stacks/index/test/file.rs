@@ -162,3 +162,442 @@ fn test_migrate_existing_trie_blobs() {
         }
     }
 }
+
+#[test]
+fn test_migrate_trie_blobs_incrementally() {
+    let test_file = "/tmp/test_migrate_trie_blobs_incrementally.sqlite";
+    let test_blobs_file = "/tmp/test_migrate_trie_blobs_incrementally.sqlite.blobs";
+    if fs::metadata(&test_file).is_ok() {
+        fs::remove_file(&test_file).unwrap();
+    }
+    if fs::metadata(&test_blobs_file).is_ok() {
+        fs::remove_file(&test_blobs_file).unwrap();
+    }
+
+    let data = make_test_insert_data(32, 32);
+    let mut last_block_header = BlockHeaderHash::sentinel();
+    {
+        let marf_opts = MARFOpenOpts::new(TrieHashCalculationMode::Deferred, "noop", false);
+        let f = TrieFileStorage::open(&test_file, marf_opts).unwrap();
+        let mut marf = MARF::from_storage(f);
+
+        for (i, block_data) in data.iter().enumerate() {
+            let mut block_hash_bytes = [0u8; 32];
+            block_hash_bytes[0..8].copy_from_slice(&(i as u64).to_be_bytes());
+
+            let block_header = BlockHeaderHash(block_hash_bytes);
+            marf.begin(&last_block_header, &block_header).unwrap();
+
+            for (key, value) in block_data.iter() {
+                let path = TriePath::from_key(key);
+                let leaf = TrieLeaf::from_value(&vec![], value.clone());
+                marf.insert_raw(path, leaf).unwrap();
+            }
+            marf.commit().unwrap();
+            last_block_header = block_header;
+        }
+    }
+
+    // nothing is migrated on open
+    let mut marf_opts = MARFOpenOpts::new(TrieHashCalculationMode::Deferred, "noop", true);
+    marf_opts.force_db_migrate = true;
+    marf_opts.blob_migration_batch = 10;
+
+    let f = TrieFileStorage::open(&test_file, marf_opts).unwrap();
+    let mut marf = MARF::from_storage(f);
+    assert_eq!(
+        trie_sql::get_blob_migration_progress(marf.sqlite_conn()).unwrap(),
+        Some((0, 0))
+    );
+    assert_eq!(
+        trie_sql::count_unmigrated_blocks(marf.sqlite_conn()).unwrap(),
+        data.len() as u64
+    );
+
+    let check_reads = |marf: &mut MARF<BlockHeaderHash>, tip: &BlockHeaderHash| {
+        for block_data in data.iter() {
+            for (key, value) in block_data.iter() {
+                let path = TriePath::from_key(key);
+                let marf_leaf = TrieLeaf::from_value(&vec![], value.clone());
+                let leaf = MARF::get_path(&mut marf.borrow_storage_backend(), tip, &path)
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(leaf.data.to_vec(), marf_leaf.data.to_vec());
+            }
+        }
+    };
+
+    // reads work with some tries in the DB and some in the blobs file
+    assert!(!marf.migrate_trie_blobs_step(10).unwrap());
+    assert_eq!(
+        trie_sql::count_unmigrated_blocks(marf.sqlite_conn()).unwrap(),
+        data.len() as u64 - 10
+    );
+    check_reads(&mut marf, &last_block_header);
+
+    // new tries go to the blobs file while the migration is underway (the block hash can't be
+    // all 0xff: that's the sentinel, and `extend_trie()` treats a trie opened at the sentinel as
+    // brand new storage)
+    let new_block_header = BlockHeaderHash([0xfe; 32]);
+    marf.begin(&last_block_header, &new_block_header).unwrap();
+    let new_path = TriePath::from_key("new-key");
+    let new_leaf = TrieLeaf::from_value(&vec![], MARFValue::from_value("new-value"));
+    marf.insert_raw(new_path.clone(), new_leaf.clone()).unwrap();
+    marf.commit().unwrap();
+
+    let mut steps = 1;
+    while !marf.migrate_trie_blobs_step(10).unwrap() {
+        steps += 1;
+        check_reads(&mut marf, &new_block_header);
+    }
+    // one step per batch, and then one more to clear the last batch's DB copies and finish
+    assert_eq!(steps, (data.len() + 9) / 10);
+
+    assert_eq!(
+        trie_sql::get_blob_migration_progress(marf.sqlite_conn()).unwrap(),
+        None
+    );
+    assert_eq!(
+        trie_sql::count_unmigrated_blocks(marf.sqlite_conn()).unwrap(),
+        0
+    );
+    assert!(!trie_sql::detect_partial_migration(marf.sqlite_conn()).unwrap());
+    let num_db_blobs = query_count(
+        marf.sqlite_conn(),
+        "SELECT COUNT(*) FROM marf_data WHERE length(data) > 0 AND unconfirmed = 0",
+        rusqlite::NO_PARAMS,
+    )
+    .unwrap();
+    assert_eq!(num_db_blobs, 0);
+
+    check_reads(&mut marf, &new_block_header);
+    let leaf = MARF::get_path(
+        &mut marf.borrow_storage_backend(),
+        &new_block_header,
+        &new_path,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(leaf.data.to_vec(), new_leaf.data.to_vec());
+}
+
+#[test]
+fn test_buffered_trie_blobs() {
+    let test_name = "test_buffered_trie_blobs";
+    let mut db = setup_db(test_name);
+    let blobs_path = format!("{}.blobs", &db_path(test_name));
+    if fs::metadata(&blobs_path).is_ok() {
+        fs::remove_file(&blobs_path).unwrap();
+    }
+    let mut blobs = TrieFile::from_db_path(&db_path(test_name), false).unwrap();
+    trie_sql::migrate_tables_if_needed::<BlockHeaderHash>(&mut db).unwrap();
+    let file_len = || fs::metadata(&blobs_path).unwrap().len();
+
+    let store = |blobs: &mut TrieFile, byte: u8, blob: &[u8]| {
+        blobs
+            .store_trie_blob::<BlockHeaderHash>(&db, &BlockHeaderHash([byte; 32]), blob)
+            .unwrap();
+        trie_sql::get_block_identifier(&db, &BlockHeaderHash([byte; 32])).unwrap()
+    };
+
+    let block_1 = store(&mut blobs, 0x01, &[1, 2, 3, 4, 5]);
+
+    // buffered tries can be read back before they reach the file
+    blobs.begin_buffering();
+    let block_2 = store(&mut blobs, 0x02, &[10, 20, 30, 40, 50]);
+    let block_3 = store(&mut blobs, 0x03, &[6, 7]);
+    assert_eq!(file_len(), 5);
+    assert_eq!(blobs.get_trie_offset(&db, block_3).unwrap(), 10);
+    assert_eq!(
+        blobs.read_trie_blob(&db, block_1).unwrap(),
+        vec![1, 2, 3, 4, 5]
+    );
+    assert_eq!(
+        blobs.read_trie_blob(&db, block_2).unwrap(),
+        vec![10, 20, 30, 40, 50]
+    );
+    assert_eq!(blobs.read_trie_blob(&db, block_3).unwrap(), vec![6, 7]);
+
+    blobs.flush_buffered().unwrap();
+    assert_eq!(file_len(), 12);
+    assert_eq!(blobs.read_trie_blob(&db, block_3).unwrap(), vec![6, 7]);
+    assert_eq!(
+        blobs.read_trie_blob(&db, block_1).unwrap(),
+        vec![1, 2, 3, 4, 5]
+    );
+
+    // discarded tries never reach the file
+    blobs.begin_buffering();
+    store(&mut blobs, 0x04, &[9, 9, 9]);
+    blobs.discard_buffered();
+    assert_eq!(file_len(), 12);
+    assert_eq!(
+        blobs.read_trie_blob(&db, block_2).unwrap(),
+        vec![10, 20, 30, 40, 50]
+    );
+}
+
+#[test]
+fn test_marf_write_buffering() {
+    let path = db_path("test_marf_write_buffering");
+    for path in [path.clone(), format!("{}.blobs", &path)] {
+        if fs::metadata(&path).is_ok() {
+            fs::remove_file(&path).unwrap();
+        }
+    }
+    let mut marf_opts = MARFOpenOpts::new(TrieHashCalculationMode::Deferred, "noop", true);
+    marf_opts.write_buffering = true;
+
+    let blocks: Vec<_> = (1..10).map(|i| StacksBlockId([i as u8; 32])).collect();
+    {
+        let mut marf = MARF::from_path(&path, marf_opts.clone()).unwrap();
+        let mut parent = StacksBlockId::sentinel();
+        for (i, block) in blocks.iter().enumerate() {
+            marf.begin(&parent, block).unwrap();
+            for j in 0..20 {
+                marf.insert(
+                    &format!("key-{}-{}", i, j),
+                    MARFValue::from_value(&format!("{}", j)),
+                )
+                .unwrap();
+            }
+            marf.commit().unwrap();
+            parent = block.clone();
+        }
+
+        // a trie flushed in a transaction stays buffered until commit, and leaves nothing
+        // behind in the blobs file if the transaction rolls back
+        let blobs_len = fs::metadata(format!("{}.blobs", &path)).unwrap().len();
+        let rolled_back = StacksBlockId([0xff; 32]);
+        let mut storage_tx = marf.borrow_storage_transaction();
+        storage_tx.open_block(&parent).unwrap();
+        MARF::extend_trie(&mut storage_tx, &rolled_back).unwrap();
+        storage_tx.flush().unwrap();
+        assert_eq!(
+            fs::metadata(format!("{}.blobs", &path)).unwrap().len(),
+            blobs_len
+        );
+        storage_tx.rollback();
+        assert_eq!(
+            fs::metadata(format!("{}.blobs", &path)).unwrap().len(),
+            blobs_len
+        );
+        assert!(marf.get_root_hash_at(&rolled_back).is_err());
+    }
+
+    let mut marf = MARF::<StacksBlockId>::from_path(&path, marf_opts).unwrap();
+    let tip = blocks.last().unwrap();
+    for i in 0..blocks.len() {
+        for j in 0..20 {
+            assert_eq!(
+                marf.get(tip, &format!("key-{}-{}", i, j)).unwrap(),
+                Some(MARFValue::from_value(&format!("{}", j)))
+            );
+        }
+    }
+}
+
+#[test]
+fn test_marf_pinned_tries() {
+    let path = db_path("test_marf_pinned_tries");
+    for path in [path.clone(), format!("{}.blobs", &path)] {
+        if fs::metadata(&path).is_ok() {
+            fs::remove_file(&path).unwrap();
+        }
+    }
+    let mut marf_opts = MARFOpenOpts::new(TrieHashCalculationMode::Deferred, "noop", true);
+    marf_opts.write_buffering = true;
+    marf_opts.pinned_tries = 3;
+
+    let blocks: Vec<_> = (1..10).map(|i| StacksBlockId([i as u8; 32])).collect();
+    {
+        let mut marf = MARF::from_path(&path, marf_opts.clone()).unwrap();
+        let mut parent = StacksBlockId::sentinel();
+        for (i, block) in blocks.iter().enumerate() {
+            marf.begin(&parent, block).unwrap();
+            for j in 0..20 {
+                marf.insert(
+                    &format!("key-{}-{}", i, j),
+                    MARFValue::from_value(&format!("{}", j)),
+                )
+                .unwrap();
+            }
+            marf.commit().unwrap();
+            parent = block.clone();
+
+            // each committed trie gets pinned
+            assert_eq!(
+                marf.borrow_storage_backend().num_pinned_tries(),
+                std::cmp::min(i + 1, 3)
+            );
+        }
+
+        // a trie flushed in a transaction that rolls back never gets pinned
+        let rolled_back = StacksBlockId([0xff; 32]);
+        let mut storage_tx = marf.borrow_storage_transaction();
+        storage_tx.open_block(&parent).unwrap();
+        MARF::extend_trie(&mut storage_tx, &rolled_back).unwrap();
+        storage_tx.flush().unwrap();
+        storage_tx.rollback();
+        assert_eq!(marf.borrow_storage_backend().num_pinned_tries(), 3);
+    }
+
+    // the most recent tries are pinned on open, so they can be read without the blobs file
+    let mut marf = MARF::<StacksBlockId>::from_path(&path, marf_opts).unwrap();
+    assert_eq!(marf.borrow_storage_backend().num_pinned_tries(), 3);
+    fs::OpenOptions::new()
+        .write(true)
+        .open(format!("{}.blobs", &path))
+        .unwrap()
+        .set_len(0)
+        .unwrap();
+
+    for (i, block) in blocks.iter().enumerate().skip(blocks.len() - 3) {
+        for j in 0..20 {
+            assert_eq!(
+                marf.get(block, &format!("key-{}-{}", i, j)).unwrap(),
+                Some(MARFValue::from_value(&format!("{}", j)))
+            );
+        }
+    }
+    assert!(marf.get(&blocks[0], "key-0-0").is_err());
+}
+
+#[test]
+fn test_marf_blobs_page_cache() {
+    let path = db_path("test_marf_blobs_page_cache");
+    for path in [path.clone(), format!("{}.blobs", &path)] {
+        if fs::metadata(&path).is_ok() {
+            fs::remove_file(&path).unwrap();
+        }
+    }
+    let mut marf_opts = MARFOpenOpts::new(TrieHashCalculationMode::Deferred, "noop", true);
+    marf_opts.blobs_page_cache_size = 1024 * 1024;
+
+    let blocks: Vec<_> = (1..10).map(|i| StacksBlockId([i as u8; 32])).collect();
+    let mut marf = MARF::from_path(&path, marf_opts.clone()).unwrap();
+    let mut parent = StacksBlockId::sentinel();
+    for (i, block) in blocks.iter().enumerate() {
+        marf.begin(&parent, block).unwrap();
+        for j in 0..20 {
+            marf.insert(
+                &format!("key-{}-{}", i, j),
+                MARFValue::from_value(&format!("{}", j)),
+            )
+            .unwrap();
+        }
+        marf.commit().unwrap();
+        parent = block.clone();
+    }
+
+    // a trie read in a transaction that rolls back doesn't leave its pages behind for the trie
+    // that gets its block ID next
+    let rolled_back = StacksBlockId([0xfd; 32]);
+    let mut storage_tx = marf.borrow_storage_transaction();
+    storage_tx.open_block(&parent).unwrap();
+    MARF::extend_trie(&mut storage_tx, &rolled_back).unwrap();
+    MARF::insert_leaf(
+        &mut storage_tx,
+        &rolled_back,
+        &TriePath::from_key("new-key"),
+        &TrieLeaf::from_value(&vec![], MARFValue::from_value("rolled back")),
+    )
+    .unwrap();
+    storage_tx.flush().unwrap();
+    assert!(MARF::get_path(
+        &mut storage_tx,
+        &rolled_back,
+        &TriePath::from_key("new-key")
+    )
+    .unwrap()
+    .is_some());
+    storage_tx.rollback();
+
+    let new_block = StacksBlockId([0xfe; 32]);
+    marf.begin(&parent, &new_block).unwrap();
+    marf.insert("new-key", MARFValue::from_value("committed"))
+        .unwrap();
+    marf.commit().unwrap();
+    assert_eq!(
+        marf.get(&new_block, "new-key").unwrap(),
+        Some(MARFValue::from_value("committed"))
+    );
+
+    // every trie is now cached, so it can be read without the blobs file
+    for (i, block) in blocks.iter().enumerate() {
+        for j in 0..20 {
+            assert_eq!(
+                marf.get(block, &format!("key-{}-{}", i, j)).unwrap(),
+                Some(MARFValue::from_value(&format!("{}", j)))
+            );
+        }
+    }
+    fs::OpenOptions::new()
+        .write(true)
+        .open(format!("{}.blobs", &path))
+        .unwrap()
+        .set_len(0)
+        .unwrap();
+    for (i, block) in blocks.iter().enumerate() {
+        for j in 0..20 {
+            assert_eq!(
+                marf.get(block, &format!("key-{}-{}", i, j)).unwrap(),
+                Some(MARFValue::from_value(&format!("{}", j)))
+            );
+        }
+    }
+
+    // without a page cache, the tries are gone
+    let mut marf_opts = MARFOpenOpts::new(TrieHashCalculationMode::Deferred, "noop", true);
+    marf_opts.blobs_page_cache_size = TRIE_FILE_PAGE_SIZE as usize - 1;
+    let mut marf = MARF::<StacksBlockId>::from_path(&path, marf_opts).unwrap();
+    assert!(marf.get(&blocks[0], "key-0-0").is_err());
+}
+
+#[test]
+fn test_marf_blobs_sync_modes() {
+    let sync_modes = [
+        TrieFileSyncMode::PerCommit,
+        TrieFileSyncMode::Periodic(std::time::Duration::from_millis(10)),
+        TrieFileSyncMode::DSync,
+        TrieFileSyncMode::NoSync,
+    ];
+    for (m, sync_mode) in sync_modes.iter().enumerate() {
+        for write_buffering in [false, true] {
+            let path = db_path(&format!(
+                "test_marf_blobs_sync_modes_{}_{}",
+                m, write_buffering
+            ));
+            for path in [path.clone(), format!("{}.blobs", &path)] {
+                if fs::metadata(&path).is_ok() {
+                    fs::remove_file(&path).unwrap();
+                }
+            }
+            let mut marf_opts = MARFOpenOpts::new(TrieHashCalculationMode::Deferred, "noop", true);
+            marf_opts.write_buffering = write_buffering;
+            marf_opts.blobs_sync_mode = *sync_mode;
+
+            let blocks: Vec<_> = (1..5).map(|i| StacksBlockId([i as u8; 32])).collect();
+            {
+                let mut marf = MARF::from_path(&path, marf_opts.clone()).unwrap();
+                let mut parent = StacksBlockId::sentinel();
+                for (i, block) in blocks.iter().enumerate() {
+                    marf.begin(&parent, block).unwrap();
+                    marf.insert(&format!("key-{}", i), MARFValue::from_value("value"))
+                        .unwrap();
+                    marf.commit().unwrap();
+                    parent = block.clone();
+                }
+            }
+
+            let mut marf = MARF::<StacksBlockId>::from_path(&path, marf_opts).unwrap();
+            let tip = blocks.last().unwrap();
+            for i in 0..blocks.len() {
+                assert_eq!(
+                    marf.get(tip, &format!("key-{}", i)).unwrap(),
+                    Some(MARFValue::from_value("value"))
+                );
+            }
+        }
+    }
+}
@@ -19,6 +19,7 @@
 
 use std::fs;
 use std::io::Cursor;
+use std::thread;
 
 use crate::chainstate::stacks::index::bits::*;
 use crate::chainstate::stacks::index::marf::*;
@@ -27,6 +28,7 @@ use crate::chainstate::stacks::index::proofs::*;
 use crate::chainstate::stacks::index::storage::*;
 use crate::chainstate::stacks::index::test::*;
 use crate::chainstate::stacks::index::trie::*;
+use crate::chainstate::stacks::index::trie_sql;
 use crate::chainstate::stacks::index::ClarityMarfTrieId;
 use crate::chainstate::stacks::index::Error;
 use crate::chainstate::stacks::index::MARFValue;
@@ -550,7 +552,7 @@ where
             f.test_genesis_block.replace(last_block_header.clone());
 
             let r = path_init(&mut f);
-            f.commit_tx();
+            f.commit_tx().unwrap();
             r
         };
 
@@ -830,7 +832,7 @@ fn marf_merkle_verify_backptrs() {
 
                 let r =
                     make_node_path(&mut f, node_id.to_u8(), &path_segments, [31u8; 40].to_vec());
-                f.commit_tx();
+                f.commit_tx().unwrap();
 
                 r
             };
@@ -2310,3 +2312,242 @@ fn test_marf_unconfirmed() {
         assert!(false);
     }
 }
+
+#[test]
+fn test_marf_unconfirmed_isolation() {
+    // unconfirmed tries are kept out of the main database, and vanish when dropped
+    let marf_path = "/tmp/test_marf_unconfirmed_isolation";
+    let unconfirmed_path = format!("{}.unconfirmed", marf_path);
+    for path in [marf_path, unconfirmed_path.as_str()].iter() {
+        if fs::metadata(path).is_ok() {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    let block_header = StacksBlockId([0x33u8; 32]);
+    {
+        let cf =
+            TrieFileStorage::<StacksBlockId>::open(marf_path, MARFOpenOpts::default()).unwrap();
+        let mut confirmed_marf = MARF::<StacksBlockId>::from_storage(cf);
+        confirmed_marf
+            .begin(&StacksBlockId::sentinel(), &StacksBlockId([0x11; 32]))
+            .unwrap();
+        confirmed_marf
+            .insert("confirmed", MARFValue::from_value("1"))
+            .unwrap();
+        confirmed_marf.commit_to(&block_header).unwrap();
+    }
+
+    let f = TrieFileStorage::<StacksBlockId>::open_unconfirmed(marf_path, MARFOpenOpts::default())
+        .unwrap();
+    let mut marf = MARF::<StacksBlockId>::from_storage(f);
+    let unconfirmed_tip = marf.begin_unconfirmed(&block_header).unwrap();
+    marf.insert("unconfirmed", MARFValue::from_value("2"))
+        .unwrap();
+    marf.commit().unwrap();
+
+    let main_db = rusqlite::Connection::open(marf_path).unwrap();
+    let unconfirmed_db = rusqlite::Connection::open(&unconfirmed_path).unwrap();
+    let count_unconfirmed = |db: &rusqlite::Connection| -> i64 {
+        db.query_row(
+            "SELECT COUNT(*) FROM marf_data WHERE unconfirmed = 1",
+            rusqlite::NO_PARAMS,
+            |row| row.get(0),
+        )
+        .unwrap()
+    };
+    assert_eq!(count_unconfirmed(&main_db), 0);
+    assert_eq!(count_unconfirmed(&unconfirmed_db), 1);
+
+    let block_id = marf
+        .borrow_storage_backend()
+        .get_block_identifier(&unconfirmed_tip)
+        .unwrap();
+    assert!(block_id >= trie_sql::UNCONFIRMED_BLOCK_ID_BASE);
+
+    // both the unconfirmed trie and its confirmed ancestor are readable, including from a
+    // read-only view
+    let mut ro_marf = marf.reopen_readonly().unwrap();
+    for m in [&mut marf, &mut ro_marf].iter_mut() {
+        assert_eq!(
+            m.get(&unconfirmed_tip, "unconfirmed").unwrap(),
+            Some(MARFValue::from_value("2"))
+        );
+        assert_eq!(
+            m.get(&unconfirmed_tip, "confirmed").unwrap(),
+            Some(MARFValue::from_value("1"))
+        );
+    }
+
+    marf.begin_unconfirmed(&block_header).unwrap();
+    marf.drop_unconfirmed();
+    assert_eq!(count_unconfirmed(&unconfirmed_db), 0);
+    assert_eq!(
+        marf.get(&unconfirmed_tip, "unconfirmed").unwrap_or(None),
+        None
+    );
+    assert_eq!(
+        marf.get(&block_header, "confirmed").unwrap(),
+        Some(MARFValue::from_value("1"))
+    );
+}
+
+#[test]
+fn marf_scan_prefix() {
+    // insert keys over a few blocks, overwriting some of them, and make sure a scan at each block
+    // finds exactly the keys set as of that block, across back-pointers to earlier tries.
+    let marf_opts = MARFOpenOpts::default();
+    let f = TrieFileStorage::new_memory(marf_opts).unwrap();
+    let mut marf = MARF::from_storage(f);
+
+    let blocks: Vec<_> = (1..4).map(|i| BlockHeaderHash([i as u8; 32])).collect();
+    let mut expected = HashMap::new();
+    let mut expected_at_block = vec![];
+    let mut parent = BlockHeaderHash::sentinel();
+    for (i, block) in blocks.iter().enumerate() {
+        marf.begin(&parent, block).unwrap();
+        for j in 0..200 {
+            // every block overwrites the first 50 keys and adds 150 new ones
+            let key = if j < 50 {
+                format!("key-{}", j)
+            } else {
+                format!("key-{}-{}", i, j)
+            };
+            let value = MARFValue::from_value(&format!("{}-{}", i, j));
+            marf.insert(&key, value.clone()).unwrap();
+            expected.insert(TriePath::from_key(&key), value);
+        }
+        marf.commit().unwrap();
+        expected_at_block.push(expected.clone());
+        parent = block.clone();
+    }
+
+    for (block, expected) in blocks.iter().zip(expected_at_block.iter()) {
+        let mut found = vec![];
+        marf.scan_prefix(block, &[], |path, value| {
+            found.push((path, value));
+            true
+        })
+        .unwrap();
+
+        // paths come back in order, and include the MARF's block height keys
+        let paths: Vec<_> = found.iter().map(|(path, _)| path.to_bytes()).collect();
+        let mut sorted_paths = paths.clone();
+        sorted_paths.sort();
+        assert_eq!(paths, sorted_paths);
+        for (path, value) in expected.iter() {
+            assert!(found.contains(&(path.clone(), value.clone())));
+        }
+
+        // a one-byte prefix only selects the matching paths
+        let prefix = [TriePath::from_key("key-0").as_bytes()[0]];
+        let mut found_with_prefix = vec![];
+        marf.scan_prefix(block, &prefix, |path, value| {
+            found_with_prefix.push((path, value));
+            true
+        })
+        .unwrap();
+        let expected_with_prefix: Vec<_> = found
+            .iter()
+            .filter(|(path, _)| path.as_bytes()[0] == prefix[0])
+            .cloned()
+            .collect();
+        assert!(!expected_with_prefix.is_empty());
+        assert_eq!(found_with_prefix, expected_with_prefix);
+
+        // the visitor can stop the scan
+        let mut visited = 0;
+        marf.scan_prefix(block, &[], |_, _| {
+            visited += 1;
+            visited < 10
+        })
+        .unwrap();
+        assert_eq!(visited, 10);
+    }
+
+    // a full-length prefix finds just that key
+    let path = TriePath::from_key("key-1-60");
+    let mut found = vec![];
+    marf.scan_prefix(&blocks[2], path.as_bytes(), |path, value| {
+        found.push((path, value));
+        true
+    })
+    .unwrap();
+    assert_eq!(found, vec![(path, MARFValue::from_value("1-60"))]);
+}
+
+#[test]
+fn test_marf_read_only_snapshots() {
+    for external_blobs in [false, true] {
+        let marf_path = format!("/tmp/test_marf_read_only_snapshots_{}", external_blobs);
+        for path in [marf_path.clone(), format!("{}.blobs", &marf_path)] {
+            if let Ok(_) = std::fs::metadata(&path) {
+                std::fs::remove_file(&path).unwrap();
+            }
+        }
+        let mut marf_opts = MARFOpenOpts::default();
+        marf_opts.external_blobs = external_blobs;
+
+        let block_1 = StacksBlockId([0x01; 32]);
+        let block_2 = StacksBlockId([0x02; 32]);
+        let block_3 = StacksBlockId([0x03; 32]);
+
+        let f = TrieFileStorage::<StacksBlockId>::open(&marf_path, marf_opts.clone()).unwrap();
+        let mut marf = MARF::from_storage(f);
+        marf.begin(&StacksBlockId::sentinel(), &block_1).unwrap();
+        marf.insert("foo", MARFValue::from_value("1")).unwrap();
+        marf.commit().unwrap();
+
+        // only read-only storage can be pinned to a snapshot
+        let mut rw_storage =
+            TrieFileStorage::<StacksBlockId>::open(&marf_path, marf_opts.clone()).unwrap();
+        match rw_storage.begin_snapshot() {
+            Err(Error::ReadOnlyError) => {}
+            x => panic!("Unexpected result {:?}", &x),
+        }
+
+        let mut snapshot = marf.reopen_snapshot("noop").unwrap();
+        let mut cached_snapshot = marf.reopen_snapshot("everything").unwrap();
+
+        marf.begin(&block_1, &block_2).unwrap();
+        marf.insert("foo", MARFValue::from_value("2")).unwrap();
+        marf.insert("bar", MARFValue::from_value("3")).unwrap();
+        marf.commit().unwrap();
+
+        // block 2 was committed after the snapshots were taken
+        assert_eq!(
+            snapshot.get(&block_1, "foo").unwrap(),
+            Some(MARFValue::from_value("1"))
+        );
+        assert_eq!(snapshot.get(&block_2, "foo").unwrap(), None);
+
+        // the writer can keep going while another thread reads its own snapshot
+        let reader = thread::spawn(move || {
+            for _ in 0..100 {
+                assert_eq!(
+                    cached_snapshot.get(&block_1, "foo").unwrap(),
+                    Some(MARFValue::from_value("1"))
+                );
+                assert_eq!(cached_snapshot.get(&block_2, "bar").unwrap(), None);
+            }
+        });
+        marf.begin(&block_2, &block_3).unwrap();
+        marf.insert("bar", MARFValue::from_value("4")).unwrap();
+        marf.commit().unwrap();
+        reader.join().unwrap();
+
+        snapshot.refresh_snapshot().unwrap();
+        assert_eq!(
+            snapshot.get(&block_2, "foo").unwrap(),
+            Some(MARFValue::from_value("2"))
+        );
+        assert_eq!(
+            snapshot.get(&block_3, "bar").unwrap(),
+            Some(MARFValue::from_value("4"))
+        );
+        assert_eq!(
+            snapshot.get(&block_2, "bar").unwrap(),
+            Some(MARFValue::from_value("3"))
+        );
+    }
+}
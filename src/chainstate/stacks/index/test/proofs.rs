@@ -17,6 +17,7 @@
 use crate::chainstate::stacks::index::marf::*;
 use crate::chainstate::stacks::index::test::*;
 use crate::chainstate::stacks::index::*;
+use crate::codec::StacksMessageCodec;
 
 use super::*;
 
@@ -198,3 +199,75 @@ fn ncc_verifier_catches_stale_proof() {
     println!("DEBUG: verify(old_v)");
     assert!(!proof_5.verify(&triepath_4, &marf_value_4, &root_hash_5, &root_to_block));
 }
+
+#[test]
+fn verify_serialized_proof() {
+    let marf_opts = MARFOpenOpts::default();
+    let mut m = MARF::from_path(":memory:", marf_opts).unwrap();
+
+    let sentinel_block = BlockHeaderHash::sentinel();
+    let blocks: Vec<_> = (0..4u8).map(|i| BlockHeaderHash([i; 32])).collect();
+
+    let k1 = "K1".to_string();
+    let v1 = "V1".to_string();
+    let k2 = "K2".to_string();
+    let v2 = "V2".to_string();
+
+    m.begin(&sentinel_block, &blocks[0]).unwrap();
+    m.commit().unwrap();
+
+    // K1 is written in block #1, and K2 in block #3
+    for i in 1..4 {
+        m.begin(&blocks[i - 1], &blocks[i]).unwrap();
+        if i == 1 {
+            m.insert(&k1, MARFValue::from_value(&v1)).unwrap();
+        }
+        if i == 3 {
+            m.insert(&k2, MARFValue::from_value(&v2)).unwrap();
+        }
+        m.commit().unwrap();
+    }
+
+    let root_hash = m.get_root_hash_at(&blocks[3]).unwrap();
+    let root_to_block = m
+        .borrow_storage_backend()
+        .read_root_to_block_table()
+        .unwrap();
+
+    // a value written in the queried block needs no ancestor roots
+    let (_, proof) = m.get_with_proof(&blocks[3], &k2).unwrap().unwrap();
+    let proof_bytes = proof.serialize_to_vec();
+    assert!(verify_proof(
+        &k2,
+        &v2,
+        &proof_bytes,
+        &root_hash,
+        &HashMap::<TrieHash, BlockHeaderHash>::new()
+    )
+    .unwrap());
+    assert!(!verify_proof(&k2, &v1, &proof_bytes, &root_hash, &root_to_block).unwrap());
+    assert!(!verify_proof(&k1, &v2, &proof_bytes, &root_hash, &root_to_block).unwrap());
+
+    // a value written in an ancestor needs that ancestor's root
+    let (_, proof) = m.get_with_proof(&blocks[3], &k1).unwrap().unwrap();
+    let proof_bytes = proof.serialize_to_vec();
+    assert!(verify_proof(&k1, &v1, &proof_bytes, &root_hash, &root_to_block).unwrap());
+    assert!(!verify_proof(
+        &k1,
+        &v1,
+        &proof_bytes,
+        &root_hash,
+        &HashMap::<TrieHash, BlockHeaderHash>::new()
+    )
+    .unwrap());
+
+    // the proof is only good for its own root
+    let other_root_hash = m.get_root_hash_at(&blocks[2]).unwrap();
+    assert!(!verify_proof(&k1, &v1, &proof_bytes, &other_root_hash, &root_to_block).unwrap());
+
+    // garbage is not a proof
+    assert!(verify_proof(&k1, &v1, &[0xff; 10], &root_hash, &root_to_block).is_err());
+    let mut trailing_bytes = proof_bytes.clone();
+    trailing_bytes.push(0);
+    assert!(verify_proof(&k1, &v1, &trailing_bytes, &root_hash, &root_to_block).is_err());
+}
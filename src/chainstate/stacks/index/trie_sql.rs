@@ -56,6 +56,7 @@ use crate::util_lib::db::query_count;
 use crate::util_lib::db::query_row;
 use crate::util_lib::db::query_rows;
 use crate::util_lib::db::sql_pragma;
+use crate::util_lib::db::table_exists;
 use crate::util_lib::db::tx_begin_immediate;
 use crate::util_lib::db::u64_to_sql;
 use stacks_common::util::log;
@@ -88,6 +89,21 @@ CREATE TABLE IF NOT EXISTS mined_blocks (
 CREATE INDEX IF NOT EXISTS block_hash_mined_blocks ON mined_blocks(block_hash);
 ";
 
+// unconfirmed tries live in their own database, attached to the MARF's connection as
+// `unconfirmed`, so their churn doesn't fragment the main database.
+static SQL_MARF_UNCONFIRMED_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS unconfirmed.marf_data (
+   block_id INTEGER PRIMARY KEY, 
+   block_hash TEXT UNIQUE NOT NULL,
+   data BLOB NOT NULL,
+   unconfirmed INTEGER NOT NULL,
+   external_offset INTEGER DEFAULT 0 NOT NULL,
+   external_length INTEGER DEFAULT 0 NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS unconfirmed.block_hash_marf_data ON marf_data(block_hash);
+";
+
 static SQL_EXTENSION_LOCKS_TABLE: &str = "
 CREATE TABLE IF NOT EXISTS block_extension_locks (block_hash TEXT PRIMARY KEY);
 ";
@@ -109,8 +125,74 @@ INSERT OR REPLACE INTO schema_version (version) VALUES (2);
 INSERT OR REPLACE INTO migrated_version (version) VALUES (1);
 ";
 
+// progress of an incremental migration of trie blobs out of marf_data and into the .blobs file.
+// the table only exists while such a migration is underway.
+static SQL_MARF_BLOB_MIGRATION_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS blob_migration (
+    -- every confirmed trie up to and including this block ID has been copied to the .blobs file
+    migrated_block_id INTEGER NOT NULL,
+    -- every copied trie up to and including this block ID has had its sqlite blob cleared
+    cleared_block_id INTEGER NOT NULL
+);
+";
+
 pub static SQL_MARF_SCHEMA_VERSION: u64 = 2;
 
+/// Name under which the unconfirmed trie database is attached
+pub static UNCONFIRMED_DB_NAME: &str = "unconfirmed";
+
+/// Block IDs of unconfirmed tries start here, so they never collide with the block IDs of
+/// confirmed tries (of which there can be at most 2**31 - 1).
+pub const UNCONFIRMED_BLOCK_ID_BASE: u32 = 0x8000_0000;
+
+/// Does this block ID belong to an unconfirmed trie?
+pub fn is_unconfirmed_block_id(block_id: u32) -> bool {
+    block_id >= UNCONFIRMED_BLOCK_ID_BASE
+}
+
+/// Which attached database holds the trie with this block ID
+fn trie_db_name(block_id: u32) -> rusqlite::DatabaseName<'static> {
+    if is_unconfirmed_block_id(block_id) {
+        rusqlite::DatabaseName::Attached(UNCONFIRMED_DB_NAME)
+    } else {
+        rusqlite::DatabaseName::Main
+    }
+}
+
+/// Which table holds the trie with this block ID
+fn trie_table_name(block_id: u32) -> &'static str {
+    if is_unconfirmed_block_id(block_id) {
+        "unconfirmed.marf_data"
+    } else {
+        "marf_data"
+    }
+}
+
+/// Attach the database that stores unconfirmed tries, creating its table if need be.
+/// Unconfirmed tries left in the main `marf_data` table by earlier versions of this code are
+/// dropped, since they are only ever scratch space.
+pub fn attach_unconfirmed_db(conn: &Connection, path: &str, readonly: bool) -> Result<(), Error> {
+    if !readonly && path != ":memory:" {
+        // the attached database inherits the main database's open flags, which may not permit
+        // creating it.  An empty file is an empty database.
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+    }
+    conn.execute("ATTACH DATABASE ?1 AS unconfirmed", &[path])?;
+    if !readonly {
+        conn.pragma_update(
+            Some(rusqlite::DatabaseName::Attached(UNCONFIRMED_DB_NAME)),
+            "journal_mode",
+            &"WAL",
+        )?;
+        conn.execute_batch(SQL_MARF_UNCONFIRMED_TABLE)?;
+        conn.execute("DELETE FROM marf_data WHERE unconfirmed = 1", NO_PARAMS)?;
+    }
+    Ok(())
+}
+
 pub fn create_tables_if_needed(conn: &mut Connection) -> Result<(), Error> {
     let tx = tx_begin_immediate(conn)?;
 
@@ -178,6 +260,7 @@ pub fn migrate_tables_if_needed<T: MarfTrieId>(conn: &mut Connection) -> Result<
     }
     if first_version == SQL_MARF_SCHEMA_VERSION
         && get_migrated_version(conn) != SQL_MARF_SCHEMA_VERSION
+        && get_blob_migration_progress(conn)?.is_none()
         && !trie_sql::detect_partial_migration(conn)?
     {
         // no migration will need to happen, so stop checking
@@ -196,6 +279,21 @@ pub fn get_block_identifier<T: MarfTrieId>(conn: &Connection, bhh: &T) -> Result
     .map_err(|e| e.into())
 }
 
+/// Get the block ID of a trie.  If `unconfirmed` is set, then the unconfirmed trie database is
+/// searched as well.
+pub fn get_any_block_identifier<T: MarfTrieId>(
+    conn: &Connection,
+    bhh: &T,
+    unconfirmed: bool,
+) -> Result<u32, Error> {
+    match get_block_identifier(conn, bhh) {
+        Err(Error::NotFoundError) if unconfirmed => {
+            get_unconfirmed_block_identifier(conn, bhh)?.ok_or(Error::NotFoundError)
+        }
+        x => x,
+    }
+}
+
 pub fn get_mined_block_identifier<T: MarfTrieId>(conn: &Connection, bhh: &T) -> Result<u32, Error> {
     conn.query_row(
         "SELECT block_id FROM mined_blocks WHERE block_hash = ?",
@@ -223,7 +321,7 @@ pub fn get_unconfirmed_block_identifier<T: MarfTrieId>(
     bhh: &T,
 ) -> Result<Option<u32>, Error> {
     conn.query_row(
-        "SELECT block_id FROM marf_data WHERE block_hash = ? AND unconfirmed = 1",
+        "SELECT block_id FROM unconfirmed.marf_data WHERE block_hash = ? AND unconfirmed = 1",
         &[bhh],
         |row| row.get("block_id"),
     )
@@ -234,7 +332,10 @@ pub fn get_unconfirmed_block_identifier<T: MarfTrieId>(
 pub fn get_block_hash<T: MarfTrieId>(conn: &Connection, local_id: u32) -> Result<T, Error> {
     let result = conn
         .query_row(
-            "SELECT block_hash FROM marf_data WHERE block_id = ?",
+            &format!(
+                "SELECT block_hash FROM {} WHERE block_id = ?",
+                trie_table_name(local_id)
+            ),
             &[local_id],
             |row| row.get("block_hash"),
         )
@@ -388,14 +489,15 @@ pub fn write_trie_blob_to_unconfirmed<T: MarfTrieId>(
     if let Ok(Some(block_id)) = get_unconfirmed_block_identifier(conn, block_hash) {
         // already exists; update
         let args: &[&dyn ToSql] = &[&data, &block_id];
-        let mut s = conn.prepare("UPDATE marf_data SET data = ? WHERE block_id = ?")?;
+        let mut s = conn.prepare("UPDATE unconfirmed.marf_data SET data = ? WHERE block_id = ?")?;
         s.execute(args)
             .expect("EXHAUSTION: MARF cannot track more than 2**31 - 1 blocks");
     } else {
         // doesn't exist yet; insert
-        let args: &[&dyn ToSql] = &[block_hash, &data, &1];
+        let args: &[&dyn ToSql] = &[&UNCONFIRMED_BLOCK_ID_BASE, block_hash, &data];
         let mut s =
-            conn.prepare("INSERT INTO marf_data (block_hash, data, unconfirmed, external_offset, external_length) VALUES (?, ?, ?, 0, 0)")?;
+            conn.prepare("INSERT INTO unconfirmed.marf_data (block_id, block_hash, data, unconfirmed, external_offset, external_length) \
+                          VALUES ((SELECT IFNULL(MAX(block_id) + 1, ?1) FROM unconfirmed.marf_data), ?2, ?3, 1, 0, 0)")?;
         s.execute(args)
             .expect("EXHAUSTION: MARF cannot track more than 2**31 - 1 blocks");
     };
@@ -413,7 +515,7 @@ pub fn write_trie_blob_to_unconfirmed<T: MarfTrieId>(
 /// Open a trie blob. Returns a Blob<'a> readable/writeable handle to it.
 pub fn open_trie_blob<'a>(conn: &'a Connection, block_id: u32) -> Result<Blob<'a>, Error> {
     let blob = conn.blob_open(
-        rusqlite::DatabaseName::Main,
+        trie_db_name(block_id),
         "marf_data",
         "data",
         block_id.into(),
@@ -425,7 +527,7 @@ pub fn open_trie_blob<'a>(conn: &'a Connection, block_id: u32) -> Result<Blob<'a
 /// Open a trie blob. Returns a Blob<'a> readable handle to it.
 pub fn open_trie_blob_readonly<'a>(conn: &'a Connection, block_id: u32) -> Result<Blob<'a>, Error> {
     let blob = conn.blob_open(
-        rusqlite::DatabaseName::Main,
+        trie_db_name(block_id),
         "marf_data",
         "data",
         block_id.into(),
@@ -462,7 +564,7 @@ pub fn read_node_hash_bytes<W: Write>(
     ptr: &TriePtr,
 ) -> Result<(), Error> {
     let mut blob = conn.blob_open(
-        rusqlite::DatabaseName::Main,
+        trie_db_name(block_id),
         "marf_data",
         "data",
         block_id.into(),
@@ -502,7 +604,7 @@ pub fn read_node_type(
     ptr: &TriePtr,
 ) -> Result<(TrieNodeType, TrieHash), Error> {
     let mut blob = conn.blob_open(
-        rusqlite::DatabaseName::Main,
+        trie_db_name(block_id),
         "marf_data",
         "data",
         block_id.into(),
@@ -518,7 +620,7 @@ pub fn read_node_type_nohash(
     ptr: &TriePtr,
 ) -> Result<TrieNodeType, Error> {
     let mut blob = conn.blob_open(
-        rusqlite::DatabaseName::Main,
+        trie_db_name(block_id),
         "marf_data",
         "data",
         block_id.into(),
@@ -552,20 +654,25 @@ pub fn get_external_trie_offset_length_by_bhh<T: MarfTrieId>(
 /// Determine the offset in the blobs file at which the last trie ends.  This is also the offset at
 /// which the next trie will be appended.
 pub fn get_external_blobs_length(conn: &Connection) -> Result<u64, Error> {
-    let qry = "SELECT (external_offset + external_length) AS blobs_length FROM marf_data ORDER BY external_offset DESC LIMIT 1";
+    // tries not yet moved out of sqlite by an incremental migration have offset 0 and length 0,
+    // so they must lose the tie with a trie stored at offset 0
+    let qry = "SELECT (external_offset + external_length) AS blobs_length FROM marf_data ORDER BY external_offset DESC, external_length DESC LIMIT 1";
     let max_len = query_row(conn, qry, NO_PARAMS)?.unwrap_or(0);
     Ok(max_len)
 }
 
 /// Do we have a partially-migrated database?
 /// Either all tries have offset and length 0, or they all don't.  If we have a mixture, then we're
-/// corrupted.
+/// corrupted -- unless an incremental migration is underway, in which case a mixture is expected.
 pub fn detect_partial_migration(conn: &Connection) -> Result<bool, Error> {
     let migrated_version = get_migrated_version(conn);
     let schema_version = get_schema_version(conn);
     if migrated_version == schema_version {
         return Ok(false);
     }
+    if get_blob_migration_progress(conn)?.is_some() {
+        return Ok(false);
+    }
 
     let num_migrated = query_count(
         conn,
@@ -590,13 +697,119 @@ pub fn set_migrated(conn: &Connection) -> Result<(), Error> {
     .and_then(|_| Ok(()))
 }
 
+/// Start an incremental migration of the confirmed tries' blobs into the .blobs file, unless one
+/// is already underway.
+pub fn begin_blob_migration(conn: &Connection) -> Result<(), Error> {
+    conn.execute_batch(SQL_MARF_BLOB_MIGRATION_TABLE)?;
+    conn.execute(
+        "INSERT INTO blob_migration (migrated_block_id, cleared_block_id) SELECT 0, 0 WHERE NOT EXISTS (SELECT 1 FROM blob_migration)",
+        NO_PARAMS,
+    )?;
+    Ok(())
+}
+
+/// Get the progress of the incremental blob migration, as the highest block ID whose trie has
+/// been copied to the .blobs file and the highest block ID whose sqlite blob has been cleared.
+/// Returns None if no incremental migration is underway.
+pub fn get_blob_migration_progress(conn: &Connection) -> Result<Option<(u32, u32)>, Error> {
+    if !table_exists(conn, "blob_migration")? {
+        return Ok(None);
+    }
+    let progress = conn
+        .query_row(
+            "SELECT migrated_block_id, cleared_block_id FROM blob_migration",
+            NO_PARAMS,
+            |row| Ok((row.get("migrated_block_id")?, row.get("cleared_block_id")?)),
+        )
+        .optional()?;
+    Ok(progress)
+}
+
+/// Record the progress of the incremental blob migration
+pub fn set_blob_migration_progress(
+    conn: &Connection,
+    migrated_block_id: u32,
+    cleared_block_id: u32,
+) -> Result<(), Error> {
+    conn.execute(
+        "UPDATE blob_migration SET migrated_block_id = ?1, cleared_block_id = ?2",
+        &[&migrated_block_id, &cleared_block_id],
+    )?;
+    Ok(())
+}
+
+/// Finish the incremental blob migration, and mark the DB as migrated
+pub fn finish_blob_migration(conn: &Connection) -> Result<(), Error> {
+    conn.execute_batch("DROP TABLE IF EXISTS blob_migration")?;
+    set_migrated(conn)
+}
+
+/// Get the block IDs of up to `count` confirmed tries after `after_block_id` that are still stored
+/// in sqlite, in ascending order.
+pub fn get_unmigrated_block_ids(
+    conn: &Connection,
+    after_block_id: u32,
+    count: u32,
+) -> Result<Vec<u32>, Error> {
+    let mut s = conn.prepare(
+        "SELECT block_id FROM marf_data WHERE block_id > ?1 AND unconfirmed = 0 AND external_offset = 0 AND external_length = 0 ORDER BY block_id LIMIT ?2",
+    )?;
+    let rows = s.query_and_then(&[&after_block_id, &count], |row| -> Result<u32, Error> {
+        Ok(row.get("block_id")?)
+    })?;
+    rows.collect()
+}
+
+/// Count the confirmed tries that are still stored in sqlite
+pub fn count_unmigrated_blocks(conn: &Connection) -> Result<u64, Error> {
+    let count = query_count(
+        conn,
+        "SELECT COUNT(*) FROM marf_data WHERE unconfirmed = 0 AND external_offset = 0 AND external_length = 0",
+        NO_PARAMS,
+    )?;
+    Ok(count as u64)
+}
+
+/// Record where a trie blob that was copied out of sqlite is stored in the .blobs file.  Unlike
+/// `update_external_trie_blob()`, this keeps the sqlite copy, for readers that already looked the
+/// trie up in sqlite.
+pub fn set_external_trie_blob_location(
+    conn: &Connection,
+    block_id: u32,
+    offset: u64,
+    length: u64,
+) -> Result<(), Error> {
+    let args: &[&dyn ToSql] = &[&u64_to_sql(offset)?, &u64_to_sql(length)?, &block_id];
+    conn.execute(
+        "UPDATE marf_data SET external_offset = ?1, external_length = ?2 WHERE block_id = ?3",
+        args,
+    )?;
+    Ok(())
+}
+
+/// Clear the sqlite copies of the trie blobs with block IDs in (`after_block_id`,
+/// `upto_block_id`] that have been copied to the .blobs file.
+pub fn clear_migrated_trie_blobs(
+    conn: &Connection,
+    after_block_id: u32,
+    upto_block_id: u32,
+) -> Result<(), Error> {
+    let empty_blob: &[u8] = &[];
+    let args: &[&dyn ToSql] = &[&empty_blob, &after_block_id, &upto_block_id];
+    conn.execute(
+        "UPDATE marf_data SET data = ?1 WHERE block_id > ?2 AND block_id <= ?3 AND unconfirmed = 0 AND external_length > 0",
+        args,
+    )?;
+    Ok(())
+}
+
 pub fn get_node_hash_bytes(
     conn: &Connection,
     block_id: u32,
     ptr: &TriePtr,
 ) -> Result<TrieHash, Error> {
     let mut blob = conn.blob_open(
-        rusqlite::DatabaseName::Main,
+        trie_db_name(block_id),
         "marf_data",
         "data",
         block_id.into(),
@@ -685,6 +898,17 @@ pub fn count_blocks(conn: &Connection) -> Result<u32, Error> {
     Ok(result)
 }
 
+/// Get the block IDs of the `count` most recently stored confirmed tries, newest first.
+pub fn get_latest_confirmed_block_ids(conn: &Connection, count: u32) -> Result<Vec<u32>, Error> {
+    let mut s = conn.prepare(
+        "SELECT block_id FROM marf_data WHERE unconfirmed = 0 ORDER BY block_id DESC LIMIT ?1",
+    )?;
+    let rows = s.query_and_then(&[&count], |row| -> Result<u32, Error> {
+        Ok(row.get("block_id")?)
+    })?;
+    rows.collect()
+}
+
 pub fn is_unconfirmed_block(conn: &Connection, block_id: u32) -> Result<bool, Error> {
     let res: i64 = conn.query_row(
         "SELECT unconfirmed FROM marf_data WHERE block_id = ?1",
@@ -705,7 +929,7 @@ pub fn drop_lock<T: MarfTrieId>(conn: &Connection, bhh: &T) -> Result<(), Error>
 pub fn drop_unconfirmed_trie<T: MarfTrieId>(conn: &Connection, bhh: &T) -> Result<(), Error> {
     debug!("Drop unconfirmed trie sqlite blob {}", bhh);
     conn.execute(
-        "DELETE FROM marf_data WHERE block_hash = ? AND unconfirmed = 1",
+        "DELETE FROM unconfirmed.marf_data WHERE block_hash = ? AND unconfirmed = 1",
         &[bhh],
     )?;
     debug!("Dropped unconfirmed trie sqlite blob {}", bhh);
@@ -18,7 +18,9 @@ use std::collections::HashMap;
 use std::collections::HashSet;
 use std::convert::From;
 use std::fs;
+use std::io;
 use std::mem;
+use std::path::{Path, PathBuf};
 
 use crate::burnchains::PrivateKey;
 use crate::burnchains::PublicKey;
@@ -31,7 +33,7 @@ use crate::chainstate::stacks::db::transactions::{
 use crate::chainstate::stacks::db::unconfirmed::UnconfirmedState;
 use crate::chainstate::stacks::db::{
     blocks::MemPoolRejection, ChainstateTx, ClarityTx, MinerRewardInfo, StacksChainState,
-    MINER_REWARD_MATURITY,
+    StacksEpochReceipt, StacksHeaderInfo, MINER_REWARD_MATURITY,
 };
 use crate::chainstate::stacks::events::{StacksTransactionEvent, StacksTransactionReceipt};
 use crate::chainstate::stacks::Error;
@@ -40,7 +42,7 @@ use crate::clarity_vm::clarity::{ClarityConnection, ClarityInstance};
 use crate::core::mempool::*;
 use crate::core::*;
 use crate::cost_estimates::metrics::CostMetric;
-use crate::cost_estimates::CostEstimator;
+use crate::cost_estimates::{CostEstimator, FeeEstimator};
 use crate::net::relay::Relayer;
 use crate::net::Error as net_error;
 use crate::types::StacksPublicKeyBuffer;
@@ -72,6 +74,15 @@ use clarity::vm::types::TypeSignature;
 pub struct BlockBuilderSettings {
     pub max_miner_time_ms: u64,
     pub mempool_settings: MemPoolWalkSettings,
+    /// If set, a `TenureCostReport` is written to this directory for every anchored block built.
+    pub tenure_report_dir: Option<PathBuf>,
+    /// Largest share of the block budget, in percent, that a microblock stream may consume on
+    /// top of its parent anchored block.  Only used when mining microblocks.
+    pub microblock_stream_budget_pct: u64,
+    /// If set, and the burnchain block after the one this anchored block is mined in starts a new
+    /// epoch or activates new AST rules, leave out transactions that the new AST rules would
+    /// reject.  No other rules of the new epoch are checked.
+    pub check_next_epoch_ast_rules: bool,
 }
 
 impl BlockBuilderSettings {
@@ -79,6 +90,9 @@ impl BlockBuilderSettings {
         BlockBuilderSettings {
             max_miner_time_ms: u64::max_value(),
             mempool_settings: MemPoolWalkSettings::default(),
+            tenure_report_dir: None,
+            microblock_stream_budget_pct: 100,
+            check_next_epoch_ast_rules: false,
         }
     }
 
@@ -86,6 +100,9 @@ impl BlockBuilderSettings {
         BlockBuilderSettings {
             max_miner_time_ms: u64::max_value(),
             mempool_settings: MemPoolWalkSettings::zero(),
+            tenure_report_dir: None,
+            microblock_stream_budget_pct: 100,
+            check_next_epoch_ast_rules: false,
         }
     }
 }
@@ -212,6 +229,157 @@ pub struct TransactionProblematicEvent {
     pub error: String,
 }
 
+/// Why a mempool transaction considered during block assembly was left out of the block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TenureTxSkipReason {
+    /// Including the transaction would have exceeded the block's size or execution budget.
+    CostExceeded,
+    /// The transaction's nonce did not follow the nonce the miner expected for its origin or
+    /// sponsor account.
+    NonceGap,
+    /// The transaction had no cost estimate when it was picked, and it exceeded the block budget.
+    EstimatorMiss,
+    /// The transaction failed to process.
+    ProcessingError,
+    /// The transaction is problematic and was dropped from the mempool.
+    Problematic,
+}
+
+impl TenureTxSkipReason {
+    /// Classify the error a transaction was skipped or rejected with.  `estimated` is false if
+    /// the transaction was picked before the cost estimator had an estimate for it.
+    pub fn from_error(error: &Error, estimated: bool) -> TenureTxSkipReason {
+        match error {
+            Error::BlockTooBigError if !estimated => TenureTxSkipReason::EstimatorMiss,
+            Error::BlockTooBigError | Error::TransactionTooBigError => {
+                TenureTxSkipReason::CostExceeded
+            }
+            Error::StacksTransactionSkipped(reason) => {
+                if reason.starts_with("BlockLimitFunction::") {
+                    TenureTxSkipReason::CostExceeded
+                } else if reason.starts_with("Bad origin nonce")
+                    || reason.starts_with("Bad sponsor nonce")
+                {
+                    TenureTxSkipReason::NonceGap
+                } else {
+                    TenureTxSkipReason::ProcessingError
+                }
+            }
+            Error::InvalidStacksTransaction(reason, _) if reason.starts_with("Bad nonce") => {
+                TenureTxSkipReason::NonceGap
+            }
+            _ => TenureTxSkipReason::ProcessingError,
+        }
+    }
+}
+
+/// A single transaction the miner looked at while assembling a block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TenureTxReport {
+    #[serde(deserialize_with = "hex_deserialize", serialize_with = "hex_serialize")]
+    pub txid: Txid,
+    pub payload: String,
+    pub size: u64,
+    pub fee: u64,
+    /// Execution cost of the transaction, if it was included in the block.
+    pub execution_cost: Option<ExecutionCost>,
+    pub included: bool,
+    pub skip_reason: Option<TenureTxSkipReason>,
+    /// The error message for a transaction that was not included.
+    pub error: Option<String>,
+}
+
+/// Report of how a miner filled (or failed to fill) the execution budget of a block it
+/// assembled: every transaction it considered, what it cost, and why it was left out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TenureCostReport {
+    pub block_hash: BlockHeaderHash,
+    pub parent_block_hash: BlockHeaderHash,
+    pub stacks_height: u64,
+    pub block_limit: ExecutionCost,
+    pub consumed: ExecutionCost,
+    pub block_size: u64,
+    pub assembly_time_ms: u64,
+    pub txs: Vec<TenureTxReport>,
+}
+
+impl TenureCostReport {
+    pub fn new(parent_block_hash: BlockHeaderHash, block_limit: ExecutionCost) -> TenureCostReport {
+        TenureCostReport {
+            block_hash: BlockHeaderHash([0u8; 32]),
+            parent_block_hash,
+            stacks_height: 0,
+            block_limit,
+            consumed: ExecutionCost::zero(),
+            block_size: 0,
+            assembly_time_ms: 0,
+            txs: vec![],
+        }
+    }
+
+    /// Record the outcome of mining `tx` (of encoded length `size`).  `estimated` is false if
+    /// the transaction was picked before the cost estimator had an estimate for it.
+    pub fn record_result(
+        &mut self,
+        tx: &StacksTransaction,
+        size: u64,
+        result: &TransactionResult,
+        estimated: bool,
+    ) {
+        let (execution_cost, fee, skip_reason, error) = match result {
+            TransactionResult::Success(TransactionSuccess { fee, receipt, .. }) => {
+                (Some(receipt.execution_cost.clone()), *fee, None, None)
+            }
+            TransactionResult::Skipped(TransactionSkipped { error, .. })
+            | TransactionResult::ProcessingError(TransactionError { error, .. }) => (
+                None,
+                tx.get_tx_fee(),
+                Some(TenureTxSkipReason::from_error(error, estimated)),
+                Some(error.to_string()),
+            ),
+            TransactionResult::Problematic(TransactionProblematic { error, .. }) => (
+                None,
+                tx.get_tx_fee(),
+                Some(TenureTxSkipReason::Problematic),
+                Some(error.to_string()),
+            ),
+        };
+        self.txs.push(TenureTxReport {
+            txid: tx.txid(),
+            payload: tx.payload.name().to_string(),
+            size,
+            fee,
+            execution_cost,
+            included: skip_reason.is_none(),
+            skip_reason,
+            error,
+        });
+    }
+
+    /// Number of considered transactions left out of the block for the given reason.
+    pub fn num_skipped(&self, reason: TenureTxSkipReason) -> usize {
+        self.txs
+            .iter()
+            .filter(|tx| tx.skip_reason == Some(reason))
+            .count()
+    }
+
+    /// Write this report as `tenure-<height>-<block hash>.json` into `dir`, creating it if
+    /// needed.  Returns the path written.
+    pub fn write_json(&self, dir: &Path) -> Result<PathBuf, Error> {
+        fs::create_dir_all(dir).map_err(Error::WriteError)?;
+        let path = dir.join(format!(
+            "tenure-{}-{}.json",
+            self.stacks_height, &self.block_hash
+        ));
+        let bytes = serde_json::to_vec_pretty(self)
+            .map_err(|e| Error::WriteError(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+        fs::write(&path, bytes).map_err(Error::WriteError)?;
+        Ok(path)
+    }
+}
+
 fn hex_serialize<S: serde::Serializer>(txid: &Txid, s: S) -> Result<S::Ok, S::Error> {
     let inst = txid.to_hex();
     s.serialize_str(inst.as_str())
@@ -495,6 +663,10 @@ pub struct StacksMicroblockBuilder<'a> {
     anchor_block: BlockHeaderHash,
     anchor_block_consensus_hash: ConsensusHash,
     anchor_block_height: u64,
+    /// Header of the anchored block this stream builds on
+    anchor_block_header: StacksHeaderInfo,
+    /// Execution cost of the anchored block this stream builds on
+    anchored_cost: ExecutionCost,
     header_reader: StacksChainState,
     clarity_tx: Option<ClarityTx<'a, 'a>>,
     unconfirmed: bool,
@@ -561,12 +733,14 @@ impl<'a> StacksMicroblockBuilder<'a> {
             &StacksBlockHeader::make_index_block_hash(&anchor_block_consensus_hash, &anchor_block),
             &cost_so_far
         );
-        clarity_tx.reset_cost(cost_so_far);
+        clarity_tx.reset_cost(cost_so_far.clone());
 
         Ok(StacksMicroblockBuilder {
             anchor_block,
             anchor_block_consensus_hash,
             anchor_block_height,
+            anchor_block_header,
+            anchored_cost: cost_so_far,
             runtime: runtime,
             clarity_tx: Some(clarity_tx),
             header_reader,
@@ -592,12 +766,9 @@ impl<'a> StacksMicroblockBuilder<'a> {
         };
 
         let (header_reader, _) = chainstate.reopen()?;
-        let (
-            anchored_consensus_hash,
-            anchored_block_hash,
-            anchored_block_height,
-            anchored_burn_height,
-        ) = if let Some(unconfirmed) = chainstate.unconfirmed_state.as_ref() {
+        let (anchor_block_header, anchored_cost) = if let Some(unconfirmed) =
+            chainstate.unconfirmed_state.as_ref()
+        {
             let header_info = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
                 chainstate.db(),
                 &unconfirmed.confirmed_chain_tip,
@@ -609,17 +780,21 @@ impl<'a> StacksMicroblockBuilder<'a> {
                 );
                 Error::NoSuchBlockError
             })?;
-            (
-                header_info.consensus_hash,
-                header_info.anchored_header.block_hash(),
-                header_info.stacks_block_height,
-                header_info.burn_header_height,
-            )
+            let anchored_cost = StacksChainState::get_stacks_block_anchored_cost(
+                chainstate.db(),
+                &unconfirmed.confirmed_chain_tip,
+            )?
+            .ok_or(Error::NoSuchBlockError)?;
+            (header_info, anchored_cost)
         } else {
             // unconfirmed state needs to be initialized
             debug!("Unconfirmed chainstate not initialized");
             return Err(Error::NoSuchBlockError)?;
         };
+        let anchored_consensus_hash = anchor_block_header.consensus_hash.clone();
+        let anchored_block_hash = anchor_block_header.anchored_header.block_hash();
+        let anchored_block_height = anchor_block_header.stacks_block_height;
+        let anchored_burn_height = anchor_block_header.burn_header_height;
 
         let ast_rules = burn_dbconn.get_ast_rules(anchored_burn_height);
 
@@ -645,6 +820,8 @@ impl<'a> StacksMicroblockBuilder<'a> {
             anchor_block: anchored_block_hash,
             anchor_block_consensus_hash: anchored_consensus_hash,
             anchor_block_height: anchored_block_height,
+            anchor_block_header,
+            anchored_cost,
             runtime: runtime,
             clarity_tx: Some(clarity_tx),
             header_reader,
@@ -980,11 +1157,27 @@ impl<'a> StacksMicroblockBuilder<'a> {
         return self.make_next_microblock(txs_included, miner_key, tx_events, None);
     }
 
+    /// Cost consumed by a microblock stream alone, given the total cost so far of the Clarity
+    /// transaction mining it (which also includes its parent anchored block's cost).
+    fn stream_cost(anchored_cost: &ExecutionCost, cost_so_far: &ExecutionCost) -> ExecutionCost {
+        let mut stream_cost = cost_so_far.clone();
+        if stream_cost.sub(anchored_cost).is_err() {
+            stream_cost = ExecutionCost::zero();
+        }
+        stream_cost
+    }
+
+    /// Mine the next microblock in the stream from the mempool.
+    /// Transactions are selected until the block budget, the stream's share of it
+    /// (`microblock_stream_budget_pct`), or the time budget is exhausted.  Transactions whose
+    /// estimated cost would exceed the stream budget are skipped.  If `fee_estimator` is given,
+    /// the fee rates paid by the mined transactions are reported to it.
     pub fn mine_next_microblock(
         &mut self,
         mem_pool: &mut MemPoolDB,
         miner_key: &Secp256k1PrivateKey,
         event_dispatcher: &dyn MemPoolEventDispatcher,
+        fee_estimator: Option<&mut dyn FeeEstimator>,
     ) -> Result<StacksMicroblock, Error> {
         let mut txs_included = vec![];
         let mempool_settings = self.settings.mempool_settings.clone();
@@ -1017,6 +1210,22 @@ impl<'a> StacksMicroblockBuilder<'a> {
             .expect("No block limit found for clarity_tx.");
         mem_pool.estimate_tx_rates(100, &block_limit, &stacks_epoch_id)?;
 
+        let stream_budget_pct = self.settings.microblock_stream_budget_pct;
+        let anchored_cost = self.anchored_cost.clone();
+        let mut receipts = vec![];
+        if stream_budget_pct < 100
+            && block_limit.proportion_largest_dimension(&StacksMicroblockBuilder::stream_cost(
+                &anchored_cost,
+                &clarity_tx.cost_so_far(),
+            )) >= stream_budget_pct
+        {
+            debug!(
+                "Microblock stream budget exhausted (child of {}); will not mine transactions",
+                &self.anchor_block
+            );
+            block_limit_hit = BlockLimitFunction::LIMIT_REACHED;
+        }
+
         debug!(
             "Microblock transaction selection begins (child of {}), bytes so far: {}",
             &self.anchor_block, bytes_so_far
@@ -1049,6 +1258,19 @@ impl<'a> StacksMicroblockBuilder<'a> {
                             considered.insert(mempool_tx.tx.txid());
                         }
 
+                        if stream_budget_pct < 100 && block_limit_hit != BlockLimitFunction::LIMIT_REACHED {
+                            if let Ok(estimated_cost) = estimator.estimate_cost(&mempool_tx.tx.payload, &stacks_epoch_id) {
+                                let mut projected_cost = StacksMicroblockBuilder::stream_cost(&anchored_cost, &clarity_tx.cost_so_far());
+                                if projected_cost.add(&estimated_cost).is_err()
+                                    || block_limit.proportion_largest_dimension(&projected_cost) > stream_budget_pct
+                                {
+                                    debug!("Estimated cost of tx {} exceeds microblock stream budget", &mempool_tx.tx.txid());
+                                    return Ok(Some(TransactionResult::skipped(
+                                        &mempool_tx.tx, "Estimated cost exceeds microblock stream budget".to_string()).convert_to_event()));
+                                }
+                            }
+                        }
+
                         match StacksMicroblockBuilder::mine_next_transaction(
                             clarity_tx,
                             mempool_tx.tx.clone(),
@@ -1085,9 +1307,18 @@ impl<'a> StacksMicroblockBuilder<'a> {
                                             mempool_tx.tx.payload.name()
                                         );
                                         txs_included.push(mempool_tx.tx.clone());
+                                        receipts.push(receipt);
                                         num_txs += 1;
                                         num_added += 1;
                                         num_selected += 1;
+
+                                        if stream_budget_pct < 100
+                                            && block_limit.proportion_largest_dimension(&StacksMicroblockBuilder::stream_cost(&anchored_cost, &clarity_tx.cost_so_far())) >= stream_budget_pct
+                                        {
+                                            debug!("Microblock stream budget exhausted";
+                                                "tx" => %mempool_tx.tx.txid(), "next_behavior" => "Stop mining microblock");
+                                            block_limit_hit = BlockLimitFunction::LIMIT_REACHED;
+                                        }
                                         Ok(Some(result_event))
                                     }
                                     TransactionResult::Skipped(TransactionSkipped {
@@ -1186,6 +1417,34 @@ impl<'a> StacksMicroblockBuilder<'a> {
             }
         }
 
+        if let Some(fee_estimator) = fee_estimator {
+            if receipts.len() > 0 {
+                let stream_cost = StacksMicroblockBuilder::stream_cost(
+                    &self.anchored_cost,
+                    &self
+                        .get_cost_so_far()
+                        .expect("BUG: microblock builder has no open Clarity transaction"),
+                );
+                let microblock_receipt = StacksEpochReceipt {
+                    header: self.anchor_block_header.clone(),
+                    tx_receipts: receipts,
+                    matured_rewards: vec![],
+                    matured_rewards_info: None,
+                    parent_microblocks_cost: stream_cost,
+                    anchored_block_cost: self.anchored_cost.clone(),
+                    parent_burn_block_hash: self.anchor_block_header.burn_header_hash.clone(),
+                    parent_burn_block_height: self.anchor_block_header.burn_header_height,
+                    parent_burn_block_timestamp: self.anchor_block_header.burn_header_timestamp,
+                    evaluated_epoch: stacks_epoch_id,
+                };
+                if let Err(e) = fee_estimator.notify_block(&microblock_receipt, &block_limit) {
+                    warn!("FeeEstimator failed to process microblock receipts";
+                          "anchor_block" => %self.anchor_block,
+                          "error" => %e);
+                }
+            }
+        }
+
         return self.make_next_microblock(
             txs_included,
             miner_key,
@@ -1924,6 +2183,26 @@ impl StacksBlockBuilder {
         })
     }
 
+    /// Determine whether or not the burnchain block after `burn_height` starts a new Stacks epoch
+    /// or activates new AST rules.  If it does, return the AST rules in effect from that
+    /// burnchain block onwards.
+    pub fn get_upcoming_epoch_ast_rules(
+        burn_conn: &DBConn,
+        burn_height: u64,
+    ) -> Result<Option<ASTRules>, Error> {
+        let cur_epoch = SortitionDB::get_stacks_epoch(burn_conn, burn_height)?.map(|e| e.epoch_id);
+        let next_epoch =
+            SortitionDB::get_stacks_epoch(burn_conn, burn_height + 1)?.map(|e| e.epoch_id);
+        let cur_ast_rules = SortitionDB::get_ast_rules(burn_conn, burn_height)?;
+        let next_ast_rules = SortitionDB::get_ast_rules(burn_conn, burn_height + 1)?;
+
+        if cur_epoch != next_epoch || cur_ast_rules != next_ast_rules {
+            Ok(Some(next_ast_rules))
+        } else {
+            Ok(None)
+        }
+    }
+
     /// Begin mining an epoch's transactions.
     /// Returns an open ClarityTx for mining the block, as well as the ExecutionCost of any confirmed
     ///  microblocks.
@@ -2187,6 +2466,26 @@ impl StacksBlockBuilder {
             builder.header.version = STACKS_BLOCK_VERSION_AST_PRECHECK_SIZE;
         }
 
+        // if the next burnchain block starts a new epoch, then transactions that only parse
+        // under this epoch's AST rules can be invalidated by a reorg or a late block, so leave
+        // them out.
+        let mainnet = miner_epoch_info.mainnet;
+        let upcoming_ast_rules = if settings.check_next_epoch_ast_rules {
+            let upcoming_ast_rules = StacksBlockBuilder::get_upcoming_epoch_ast_rules(
+                burn_dbconn.conn(),
+                miner_epoch_info.burn_tip_height.into(),
+            )?;
+            if upcoming_ast_rules.is_some() {
+                info!(
+                    "Next burnchain block after {} crosses an epoch boundary; skipping transactions that the next epoch's AST rules would reject",
+                    miner_epoch_info.burn_tip_height
+                );
+            }
+            upcoming_ast_rules
+        } else {
+            None
+        };
+
         let (mut epoch_tx, confirmed_mblock_cost) =
             builder.epoch_begin(burn_dbconn, &mut miner_epoch_info)?;
         let stacks_epoch_id = epoch_tx.get_epoch();
@@ -2194,12 +2493,17 @@ impl StacksBlockBuilder {
             .block_limit()
             .expect("Failed to obtain block limit from miner's block connection");
 
+        let mut tenure_report = TenureCostReport::new(tip_block_hash.clone(), block_limit.clone());
+
         let mut tx_events = Vec::new();
-        tx_events.push(
-            builder
-                .try_mine_tx(&mut epoch_tx, coinbase_tx, ast_rules.clone())?
-                .convert_to_event(),
+        let coinbase_result = builder.try_mine_tx(&mut epoch_tx, coinbase_tx, ast_rules.clone())?;
+        tenure_report.record_result(
+            coinbase_tx,
+            coinbase_tx.serialize_to_vec().len() as u64,
+            &coinbase_result,
+            true,
         );
+        tx_events.push(coinbase_result.convert_to_event());
 
         mempool.reset_last_known_nonces()?;
 
@@ -2243,6 +2547,7 @@ impl StacksBlockBuilder {
 
                         // skip transactions early if we can
                         if considered.contains(&txinfo.tx.txid()) {
+                            // already in the tenure report from when it was first considered
                             return Ok(Some(
                                 TransactionResult::skipped(
                                     &txinfo.tx,
@@ -2252,39 +2557,54 @@ impl StacksBlockBuilder {
                             ));
                         }
 
+                        let mut skip_early = None;
                         if let Some(nonce) = mined_origin_nonces.get(&txinfo.tx.origin_address()) {
                             if *nonce >= txinfo.tx.get_origin_nonce() {
-                                return Ok(Some(
-                                    TransactionResult::skipped(
-                                        &txinfo.tx,
-                                        format!(
-                                            "Bad origin nonce, tx nonce {} versus {}.",
-                                            txinfo.tx.get_origin_nonce(),
-                                            *nonce
-                                        ),
-                                    )
-                                    .convert_to_event(),
+                                skip_early = Some(format!(
+                                    "Bad origin nonce, tx nonce {} versus {}.",
+                                    txinfo.tx.get_origin_nonce(),
+                                    *nonce
                                 ));
                             }
                         }
-                        if let Some(sponsor_addr) = txinfo.tx.sponsor_address() {
-                            if let Some(nonce) = mined_sponsor_nonces.get(&sponsor_addr) {
-                                if let Some(sponsor_nonce) = txinfo.tx.get_sponsor_nonce() {
-                                    if *nonce >= sponsor_nonce {
-                                        return Ok(Some(
-                                            TransactionResult::skipped(
-                                                &txinfo.tx,
-                                                format!(
-                                                    "Bad sponsor nonce, tx nonce {} versus {}.",
-                                                    sponsor_nonce, *nonce
-                                                ),
-                                            )
-                                            .convert_to_event(),
-                                        ));
+                        if skip_early.is_none() {
+                            if let Some(sponsor_addr) = txinfo.tx.sponsor_address() {
+                                if let Some(nonce) = mined_sponsor_nonces.get(&sponsor_addr) {
+                                    if let Some(sponsor_nonce) = txinfo.tx.get_sponsor_nonce() {
+                                        if *nonce >= sponsor_nonce {
+                                            skip_early = Some(format!(
+                                                "Bad sponsor nonce, tx nonce {} versus {}.",
+                                                sponsor_nonce, *nonce
+                                            ));
+                                        }
                                     }
                                 }
                             }
                         }
+                        if skip_early.is_none() {
+                            if let Some(upcoming_ast_rules) = upcoming_ast_rules {
+                                if let Err(e) = Relayer::static_check_problematic_relayed_tx(
+                                    mainnet,
+                                    &txinfo.tx,
+                                    upcoming_ast_rules,
+                                ) {
+                                    skip_early = Some(format!(
+                                        "Rejected by the next epoch's AST rules: {:?}.",
+                                        &e
+                                    ));
+                                }
+                            }
+                        }
+                        if let Some(reason) = skip_early {
+                            let tx_result = TransactionResult::skipped(&txinfo.tx, reason);
+                            tenure_report.record_result(
+                                &txinfo.tx,
+                                txinfo.metadata.len,
+                                &tx_result,
+                                !update_estimator,
+                            );
+                            return Ok(Some(tx_result.convert_to_event()));
+                        }
 
                         considered.insert(txinfo.tx.txid());
                         num_considered += 1;
@@ -2297,6 +2617,12 @@ impl StacksBlockBuilder {
                             ast_rules,
                         );
 
+                        tenure_report.record_result(
+                            &txinfo.tx,
+                            txinfo.metadata.len,
+                            &tx_result,
+                            !update_estimator,
+                        );
                         let result_event = tx_result.convert_to_event();
                         match tx_result {
                             TransactionResult::Success(TransactionSuccess { receipt, .. }) => {
@@ -2414,6 +2740,23 @@ impl StacksBlockBuilder {
 
         let ts_end = get_epoch_time_ms();
 
+        tenure_report.block_hash = block.block_hash();
+        tenure_report.stacks_height = block.header.total_work.work;
+        tenure_report.consumed = consumed.clone();
+        tenure_report.block_size = size;
+        tenure_report.assembly_time_ms = ts_end.saturating_sub(ts_start) as u64;
+
+        if let Some(dir) = settings.tenure_report_dir.as_ref() {
+            match tenure_report.write_json(dir) {
+                Ok(path) => debug!("Wrote tenure cost report to {}", path.display()),
+                Err(e) => warn!(
+                    "Failed to write tenure cost report to {}: {:?}",
+                    dir.display(),
+                    &e
+                ),
+            }
+        }
+
         if let Some(observer) = event_observer {
             observer.mined_block_event(
                 SortitionDB::get_canonical_burn_chain_tip(burn_dbconn.conn())?.block_height + 1,
@@ -2422,6 +2765,7 @@ impl StacksBlockBuilder {
                 &consumed,
                 &confirmed_mblock_cost,
                 tx_events,
+                &tenure_report,
             );
         }
 
@@ -11024,6 +11368,138 @@ pub mod test {
         );
     }
 
+    #[test]
+    fn test_tenure_cost_report() {
+        let privk = StacksPrivateKey::new();
+        let recipient = PrincipalData::from(StacksAddress {
+            version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            bytes: Hash160([2; 20]),
+        });
+        let tx_ok = make_user_stacks_transfer(&privk, 0, 200, &recipient, 100);
+        let tx_big = make_user_stacks_transfer(&privk, 1, 300, &recipient, 100);
+        let tx_nonce = make_user_stacks_transfer(&privk, 0, 400, &recipient, 100);
+
+        assert_eq!(
+            TenureTxSkipReason::from_error(&ChainstateError::BlockTooBigError, true),
+            TenureTxSkipReason::CostExceeded
+        );
+        assert_eq!(
+            TenureTxSkipReason::from_error(&ChainstateError::BlockTooBigError, false),
+            TenureTxSkipReason::EstimatorMiss
+        );
+        assert_eq!(
+            TenureTxSkipReason::from_error(
+                &ChainstateError::StacksTransactionSkipped(
+                    "BlockLimitFunction::CONTRACT_LIMIT_HIT".to_string()
+                ),
+                true
+            ),
+            TenureTxSkipReason::CostExceeded
+        );
+        assert_eq!(
+            TenureTxSkipReason::from_error(
+                &ChainstateError::InvalidStacksTransaction(
+                    "Bad nonce: origin account ST000 nonce of tx 00 is 3 (expected 1)".to_string(),
+                    true
+                ),
+                true
+            ),
+            TenureTxSkipReason::NonceGap
+        );
+
+        let mut report =
+            TenureCostReport::new(BlockHeaderHash([1; 32]), ExecutionCost::max_value());
+        report.record_result(
+            &tx_ok,
+            100,
+            &TransactionResult::success(
+                &tx_ok,
+                200,
+                StacksTransactionReceipt::from_coinbase(tx_ok.clone()),
+            ),
+            true,
+        );
+        report.record_result(
+            &tx_big,
+            100,
+            &TransactionResult::skipped_due_to_error(&tx_big, ChainstateError::BlockTooBigError),
+            false,
+        );
+        report.record_result(
+            &tx_nonce,
+            100,
+            &TransactionResult::skipped(
+                &tx_nonce,
+                "Bad origin nonce, tx nonce 0 versus 0.".to_string(),
+            ),
+            true,
+        );
+
+        assert_eq!(report.txs.len(), 3);
+        assert!(report.txs[0].included);
+        assert_eq!(report.txs[0].fee, 200);
+        assert!(report.txs[0].execution_cost.is_some());
+        assert!(!report.txs[1].included);
+        assert_eq!(report.txs[1].fee, 300);
+        assert_eq!(report.num_skipped(TenureTxSkipReason::EstimatorMiss), 1);
+        assert_eq!(report.num_skipped(TenureTxSkipReason::NonceGap), 1);
+        assert_eq!(report.num_skipped(TenureTxSkipReason::CostExceeded), 0);
+
+        let dir = PathBuf::from("/tmp/test_tenure_cost_report");
+        if fs::metadata(&dir).is_ok() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        let path = report.write_json(&dir).unwrap();
+        let parsed: TenureCostReport = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(parsed, report);
+    }
+
+    #[test]
+    fn test_get_upcoming_epoch_ast_rules() {
+        let path = "/tmp/test_get_upcoming_epoch_ast_rules";
+        if fs::metadata(path).is_ok() {
+            fs::remove_dir_all(path).unwrap();
+        }
+        let mut sortdb = SortitionDB::connect(
+            path,
+            0,
+            &BurnchainHeaderHash([0u8; 32]),
+            0,
+            &StacksEpoch::unit_test_2_05(0),
+            true,
+        )
+        .unwrap();
+        {
+            let mut tx = sortdb.tx_begin().unwrap();
+            SortitionDB::override_ast_rule_height(&mut tx, ASTRules::PrecheckSize, 10).unwrap();
+            tx.commit().unwrap();
+        }
+
+        // no boundaries
+        assert_eq!(
+            StacksBlockBuilder::get_upcoming_epoch_ast_rules(sortdb.conn(), 1).unwrap(),
+            None
+        );
+        // epoch 2.0 to 2.05
+        assert_eq!(
+            StacksBlockBuilder::get_upcoming_epoch_ast_rules(sortdb.conn(), 3).unwrap(),
+            Some(ASTRules::Typical)
+        );
+        assert_eq!(
+            StacksBlockBuilder::get_upcoming_epoch_ast_rules(sortdb.conn(), 4).unwrap(),
+            None
+        );
+        // AST rules change
+        assert_eq!(
+            StacksBlockBuilder::get_upcoming_epoch_ast_rules(sortdb.conn(), 9).unwrap(),
+            Some(ASTRules::PrecheckSize)
+        );
+        assert_eq!(
+            StacksBlockBuilder::get_upcoming_epoch_ast_rules(sortdb.conn(), 10).unwrap(),
+            None
+        );
+    }
+
     // TODO: invalid block with duplicate microblock public key hash (okay between forks, but not
     // within the same fork)
     // TODO: (BLOCKED) build off of different points in the same microblock stream
@@ -233,6 +233,24 @@ impl ClarityInstance {
         self.mainnet
     }
 
+    /// Open a read-only Clarity instance over a snapshot of this instance's committed state,
+    /// with its own MARF connection and node cache.  Read-only connections can be opened on it
+    /// while this instance processes blocks, without contending with it.
+    pub fn reopen_snapshot(&self, cache_strategy: &str) -> Result<ClarityInstance, Error> {
+        let datastore = self.datastore.reopen_snapshot(cache_strategy)?;
+        Ok(ClarityInstance {
+            datastore,
+            mainnet: self.mainnet,
+        })
+    }
+
+    /// Move a snapshot instance opened with `reopen_snapshot()` up to the latest committed
+    /// state.
+    pub fn refresh_snapshot(&mut self) -> Result<(), Error> {
+        self.datastore.refresh_snapshot()?;
+        Ok(())
+    }
+
     /// Returns the Stacks epoch of the burn block that elected `stacks_block`
     fn get_epoch_of(
         stacks_block: &StacksBlockId,
@@ -542,6 +560,20 @@ impl<'a, 'b> ClarityConnection for ClarityBlockConnection<'a, 'b> {
     }
 }
 
+impl ClarityReadOnlyConnection<'_> {
+    /// Up to `limit` of the entries whose keys start with `prefix` as of this connection's block.
+    /// See `ReadOnlyMarfStore::get_entries_with_key_prefix()`.
+    pub fn get_entries_with_key_prefix(
+        &mut self,
+        prefix: &str,
+        start_after: Option<&str>,
+        limit: u32,
+    ) -> Vec<(String, String)> {
+        self.datastore
+            .get_entries_with_key_prefix(prefix, start_after, limit)
+    }
+}
+
 impl ClarityConnection for ClarityReadOnlyConnection<'_> {
     /// Do something with ownership of the underlying DB that involves only reading.
     fn with_clarity_db_readonly_owned<F, R>(&mut self, to_do: F) -> R
@@ -3,6 +3,8 @@ use std::path::PathBuf;
 use rusqlite::Connection;
 
 use crate::chainstate::stacks::index::marf::{MARFOpenOpts, MarfConnection, MarfTransaction, MARF};
+use crate::chainstate::stacks::index::node::TriePath;
+use crate::chainstate::stacks::index::storage::TrieFileStorage;
 use crate::chainstate::stacks::index::{Error, MarfTrieId};
 use crate::core::{FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH};
 use crate::util_lib::db::IndexDBConn;
@@ -65,7 +67,15 @@ impl MarfedKV {
         };
 
         if SqliteConnection::check_schema(&marf.sqlite_conn()).is_ok() {
-            // no need to initialize
+            // no need to initialize, unless this database predates key preimages
+            if !SqliteConnection::has_key_preimages(&marf.sqlite_conn())? {
+                let tx = marf
+                    .storage_tx()
+                    .map_err(|err| InterpreterError::DBError(err.to_string()))?;
+                SqliteConnection::initialize_key_preimages(&tx)?;
+                tx.commit()
+                    .map_err(|err| InterpreterError::SqliteError(IncomparableError { err }))?;
+            }
             return Ok(marf);
         }
 
@@ -94,6 +104,27 @@ impl MarfedKV {
         Ok(MarfedKV { marf, chain_tip })
     }
 
+    /// Open an existing store read-only, without creating or migrating anything (i.e. to query
+    /// the Clarity state of a node from another thread or process).
+    pub fn open_readonly(
+        path_str: &str,
+        marf_opts: Option<MARFOpenOpts>,
+    ) -> InterpreterResult<MarfedKV> {
+        let mut path = PathBuf::from(path_str);
+        path.push("marf.sqlite");
+        let marf_path = path.to_str().ok_or_else(|| InterpreterError::BadFileName)?;
+
+        let mut marf_opts = marf_opts.unwrap_or(MARFOpenOpts::default());
+        marf_opts.external_blobs = true;
+
+        let storage = TrieFileStorage::open_readonly(marf_path, marf_opts)
+            .map_err(|err| InterpreterError::MarfFailure(err.to_string()))?;
+        Ok(MarfedKV {
+            marf: MARF::from_storage(storage),
+            chain_tip: StacksBlockId::sentinel(),
+        })
+    }
+
     pub fn open_unconfirmed(
         path_str: &str,
         miner_tip: Option<&StacksBlockId>,
@@ -238,6 +269,27 @@ impl MarfedKV {
         self.chain_tip = bhh.clone();
     }
 
+    /// Open an independent read-only view of this store, pinned to a snapshot of the
+    /// committed Clarity state (see `MARF::reopen_snapshot()`).  It can be read from
+    /// while this store keeps processing blocks.
+    pub fn reopen_snapshot(&self, cache_strategy: &str) -> InterpreterResult<MarfedKV> {
+        let marf = self
+            .marf
+            .reopen_snapshot(cache_strategy)
+            .map_err(|err| InterpreterError::MarfFailure(err.to_string()))?;
+        Ok(MarfedKV {
+            marf,
+            chain_tip: self.chain_tip.clone(),
+        })
+    }
+
+    /// Move a snapshot store opened with `reopen_snapshot()` up to the latest committed state.
+    pub fn refresh_snapshot(&mut self) -> InterpreterResult<()> {
+        self.marf
+            .refresh_snapshot()
+            .map_err(|err| InterpreterError::MarfFailure(err.to_string()).into())
+    }
+
     pub fn get_marf(&mut self) -> &mut MARF<StacksBlockId> {
         &mut self.marf
     }
@@ -284,6 +336,49 @@ impl<'a> ReadOnlyMarfStore<'a> {
             Err(e) => Err(DatabaseError::IndexError(e)),
         })
     }
+
+    /// Resolve a key hash, such as one found by `MarfConnection::scan_prefix()`, to its key.
+    /// Keys written before preimages were recorded cannot be resolved.
+    pub fn get_key_preimage(&mut self, key_hash: &TriePath) -> Option<String> {
+        SqliteConnection::get_key_preimage(self.get_side_store(), &key_hash.to_hex())
+    }
+
+    /// Up to `limit` of the keys starting with `prefix` that are set at this store's chain tip,
+    /// with their values, in ascending key order and starting after `start_after` if given.  For
+    /// instance, `ClarityDatabase::make_key_prefix_for_data_map()` selects the entries of a
+    /// data map.  Keys written before preimages were recorded are not found.
+    pub fn get_entries_with_key_prefix(
+        &mut self,
+        prefix: &str,
+        start_after: Option<&str>,
+        limit: u32,
+    ) -> Vec<(String, String)> {
+        let mut entries = vec![];
+        let mut cursor = start_after.map(|key| key.to_string());
+        while (entries.len() as u32) < limit {
+            let keys = SqliteConnection::get_key_preimages_with_prefix(
+                self.get_side_store(),
+                prefix,
+                cursor.as_deref(),
+                limit,
+            );
+            let last_page = (keys.len() as u32) < limit;
+            for key in keys.into_iter() {
+                // the key may only have been set on another fork
+                if let Some(value) = self.get(&key) {
+                    entries.push((key.clone(), value));
+                    if entries.len() as u32 == limit {
+                        break;
+                    }
+                }
+                cursor = Some(key);
+            }
+            if last_page {
+                break;
+            }
+        }
+        entries
+    }
 }
 
 impl<'a> ClarityBackingStore for ReadOnlyMarfStore<'a> {
@@ -653,6 +748,11 @@ impl<'a> ClarityBackingStore for WritableMarfStore<'a> {
             trace!("MarfedKV put '{}' = '{}'", &key, &value);
             let marf_value = MARFValue::from_value(&value);
             SqliteConnection::put(self.get_side_store(), &marf_value.to_hex(), &value);
+            SqliteConnection::put_key_preimage(
+                self.get_side_store(),
+                &TriePath::from_key(&key).to_hex(),
+                &key,
+            );
             keys.push(key);
             values.push(marf_value);
         }
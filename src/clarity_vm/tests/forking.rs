@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use crate::chainstate::stacks::index::node::TriePath;
 use crate::chainstate::stacks::index::storage::TrieFileStorage;
 use crate::chainstate::stacks::index::ClarityMarfTrieId;
 use clarity::vm::analysis::errors::CheckErrors;
@@ -371,3 +372,108 @@ fn branched_execution(owned_env: &mut OwnedEnvironment, expect_success: bool) {
         assert!(is_err_code(&result, 30))
     }
 }
+
+#[test]
+fn test_data_map_entries_by_key_prefix() {
+    let p1_address = {
+        if let Value::Principal(PrincipalData::Standard(address)) = execute(p1_str) {
+            address
+        } else {
+            panic!();
+        }
+    };
+    let contract_identifier = QualifiedContractIdentifier::new(p1_address.clone(), "map".into());
+    let contract = "(define-map entries uint bool)
+                    (define-map entries-2 uint bool)
+                    (define-public (add (k uint))
+                      (ok (map-set entries k true)))
+                    (map-set entries u1 true)
+                    (map-set entries u2 true)
+                    (map-set entries-2 u1 true)";
+
+    let add_entry = |marf_kv: &mut MarfedKV, parent: u8, child: u8, k: u128| {
+        let mut store = marf_kv.begin(
+            &StacksBlockId([parent as u8; 32]),
+            &StacksBlockId([child as u8; 32]),
+        );
+        let mut owned_env =
+            OwnedEnvironment::new(store.as_clarity_db(&TEST_HEADER_DB, &TEST_BURN_STATE_DB));
+        let (result, _, _) = owned_env
+            .execute_transaction(
+                p1_address.clone().into(),
+                contract_identifier.clone(),
+                "add",
+                &symbols_from_values(vec![Value::UInt(k)]),
+            )
+            .unwrap();
+        assert!(is_committed(&result));
+        store.test_commit();
+    };
+
+    let mut marf_kv = MarfedKV::temporary();
+    {
+        let mut store = marf_kv.begin(&StacksBlockId::sentinel(), &StacksBlockId([0 as u8; 32]));
+        store
+            .as_clarity_db(&TEST_HEADER_DB, &TEST_BURN_STATE_DB)
+            .initialize();
+        store.test_commit();
+    }
+    {
+        let mut store = marf_kv.begin(&StacksBlockId([0 as u8; 32]), &StacksBlockId([1 as u8; 32]));
+        let mut owned_env =
+            OwnedEnvironment::new(store.as_clarity_db(&TEST_HEADER_DB, &TEST_BURN_STATE_DB));
+        owned_env
+            .initialize_contract(
+                contract_identifier.clone(),
+                contract,
+                ASTRules::PrecheckSize,
+            )
+            .unwrap();
+        store.test_commit();
+    }
+
+    // two forks, each adding its own entry
+    add_entry(&mut marf_kv, 1, 2, 3);
+    add_entry(&mut marf_kv, 1, 3, 4);
+
+    let prefix = ClarityDatabase::make_key_prefix_for_data_map(&contract_identifier, "entries");
+    let entry_key = |k: u128| {
+        ClarityDatabase::make_key_for_data_map_entry(
+            &contract_identifier,
+            "entries",
+            &Value::UInt(k),
+        )
+    };
+    let entry_keys = |entries: Vec<(String, String)>| {
+        entries
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect::<Vec<String>>()
+    };
+
+    let mut store = marf_kv.begin_read_only(Some(&StacksBlockId([2 as u8; 32])));
+    let mut expected = vec![entry_key(1), entry_key(2), entry_key(3)];
+    expected.sort();
+    assert_eq!(
+        entry_keys(store.get_entries_with_key_prefix(&prefix, None, 10)),
+        expected
+    );
+
+    // paging skips over the entry that is only set on the other fork
+    let first_page = entry_keys(store.get_entries_with_key_prefix(&prefix, None, 2));
+    assert_eq!(first_page, expected[..2].to_vec());
+    let second_page =
+        entry_keys(store.get_entries_with_key_prefix(&prefix, Some(first_page.last().unwrap()), 2));
+    assert_eq!(second_page, expected[2..].to_vec());
+
+    let entry_hash = TriePath::from_key(&entry_key(3));
+    assert_eq!(store.get_key_preimage(&entry_hash), Some(entry_key(3)));
+
+    let mut store = marf_kv.begin_read_only(Some(&StacksBlockId([3 as u8; 32])));
+    let mut expected = vec![entry_key(1), entry_key(2), entry_key(4)];
+    expected.sort();
+    assert_eq!(
+        entry_keys(store.get_entries_with_key_prefix(&prefix, None, 10)),
+        expected
+    );
+}
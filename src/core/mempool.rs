@@ -22,6 +22,7 @@ use std::io::{Read, Write};
 use std::ops::Deref;
 use std::ops::DerefMut;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use rand::distributions::Uniform;
 use rand::prelude::Distribution;
@@ -51,15 +52,19 @@ use crate::monitoring::increment_stx_mempool_gc;
 use crate::util_lib::db::query_int;
 use crate::util_lib::db::query_row_columns;
 use crate::util_lib::db::query_rows;
-use crate::util_lib::db::sqlite_open;
+use crate::util_lib::db::sqlite_open_kind;
 use crate::util_lib::db::tx_begin_immediate;
 use crate::util_lib::db::tx_busy_handler;
 use crate::util_lib::db::u64_to_sql;
 use crate::util_lib::db::Error as db_error;
 use crate::util_lib::db::FromColumn;
+use crate::util_lib::db::SqliteDBKind;
 use crate::util_lib::db::{query_row, Error};
 use crate::util_lib::db::{sql_pragma, DBConn, DBTx, FromRow};
+use clarity::vm::ast::ASTRules;
+use clarity::vm::database::BurnStateDB;
 use clarity::vm::types::PrincipalData;
+use clarity::vm::Value;
 use stacks_common::util::get_epoch_time_ms;
 use stacks_common::util::get_epoch_time_secs;
 use stacks_common::util::hash::to_hex;
@@ -73,7 +78,7 @@ use crate::util_lib::bloom::{BloomCounter, BloomFilter, BloomNodeHasher};
 use crate::clarity_vm::clarity::ClarityConnection;
 
 use crate::chainstate::stacks::events::StacksTransactionReceipt;
-use crate::chainstate::stacks::miner::TransactionEvent;
+use crate::chainstate::stacks::miner::{TenureCostReport, TransactionEvent};
 use crate::chainstate::stacks::StacksBlock;
 use crate::codec::Error as codec_error;
 use crate::codec::StacksMessageCodec;
@@ -180,6 +185,34 @@ impl MemPoolAdmitter {
     }
 }
 
+/// Whether the mempool pre-executes transactions that are submitted over the RPC interface before
+/// admitting them, and what it does with the ones that would abort
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemPoolAdmissionSimulation {
+    /// don't pre-execute transactions
+    Off,
+    /// admit transactions that would abort, but warn the submitter
+    Flag,
+    /// reject transactions that would abort
+    Reject,
+}
+
+impl FromStr for MemPoolAdmissionSimulation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<MemPoolAdmissionSimulation, String> {
+        match s {
+            "off" => Ok(MemPoolAdmissionSimulation::Off),
+            "flag" => Ok(MemPoolAdmissionSimulation::Flag),
+            "reject" => Ok(MemPoolAdmissionSimulation::Reject),
+            _ => Err(format!(
+                "Unknown mempool admission simulation '{}': expected 'off', 'flag', or 'reject'",
+                s
+            )),
+        }
+    }
+}
+
 pub enum MemPoolDropReason {
     REPLACE_ACROSS_FORK,
     REPLACE_BY_FEE,
@@ -225,6 +258,7 @@ pub trait MemPoolEventDispatcher {
         consumed: &ExecutionCost,
         confirmed_microblock_cost: &ExecutionCost,
         tx_results: Vec<TransactionEvent>,
+        tenure_report: &TenureCostReport,
     );
     fn mined_microblock_event(
         &self,
@@ -357,6 +391,9 @@ impl FromRow<(u64, u64)> for (u64, u64) {
     }
 }
 
+/// The schema version that `MemPoolDB::open` migrates the mempool database to.
+pub const MEMPOOL_SCHEMA_VERSION: i64 = 4;
+
 const MEMPOOL_INITIAL_SCHEMA: &'static [&'static str] = &[r#"
     CREATE TABLE mempool(
         txid TEXT NOT NULL,
@@ -484,6 +521,9 @@ pub struct MemPoolDB {
     metric: Box<dyn CostMetric>,
     pub blacklist_timeout: u64,
     pub blacklist_max_size: u64,
+    /// Transactions submitted with a fee rate (per unit of the cost metric) below this are
+    /// rejected.  0 admits any fee rate.
+    pub min_tx_fee_rate: f64,
 }
 
 pub struct MemPoolTx<'a> {
@@ -712,7 +752,7 @@ impl MemPoolDB {
 
     /// Load the schema version from the database, if it's new enough to have such a version.
     /// Returns Some(version) if a version can be loaded; None if not.
-    fn get_schema_version(conn: &DBConn) -> Result<Option<i64>, db_error> {
+    pub fn get_schema_version(conn: &DBConn) -> Result<Option<i64>, db_error> {
         let is_versioned = table_exists(conn, "schema_version")?;
         if !is_versioned {
             return Ok(None);
@@ -730,7 +770,7 @@ impl MemPoolDB {
     }
 
     /// Apply all schema migrations up to the latest schema.
-    fn apply_schema_migrations(tx: &mut DBTx) -> Result<(), db_error> {
+    pub fn apply_schema_migrations(tx: &mut DBTx) -> Result<(), db_error> {
         loop {
             let version = MemPoolDB::get_schema_version(&tx)?.unwrap_or(1);
             match version {
@@ -743,7 +783,7 @@ impl MemPoolDB {
                 3 => {
                     MemPoolDB::instantiate_tx_blacklist(tx)?;
                 }
-                4 => {
+                MEMPOOL_SCHEMA_VERSION => {
                     break;
                 }
                 _ => {
@@ -755,7 +795,7 @@ impl MemPoolDB {
     }
 
     /// Add indexes
-    fn add_indexes(tx: &mut DBTx) -> Result<(), db_error> {
+    pub fn add_indexes(tx: &mut DBTx) -> Result<(), db_error> {
         for cmd in MEMPOOL_INDEXES {
             tx.execute_batch(cmd).map_err(db_error::SqliteError)?;
         }
@@ -854,7 +894,7 @@ impl MemPoolDB {
             OpenFlags::SQLITE_OPEN_READ_WRITE
         };
 
-        let mut conn = sqlite_open(&db_path, open_flags, true)?;
+        let mut conn = sqlite_open_kind(&db_path, open_flags, true, SqliteDBKind::Mempool)?;
         if create_flag {
             // instantiate!
             MemPoolDB::instantiate_mempool_db(&mut conn)?;
@@ -878,6 +918,7 @@ impl MemPoolDB {
             metric,
             blacklist_timeout: DEFAULT_BLACKLIST_TIMEOUT,
             blacklist_max_size: DEFAULT_BLACKLIST_MAX_SIZE,
+            min_tx_fee_rate: 0f64,
         })
     }
 
@@ -1583,6 +1624,7 @@ impl MemPoolDB {
                 .admitter
                 .set_block(&block_hash, (*consensus_hash).clone());
             mempool_tx.admitter.will_admit_tx(chainstate, tx, len)?;
+            MemPoolDB::check_pending_spend(mempool_tx, chainstate, consensus_hash, block_hash, tx)?;
         }
 
         MemPoolDB::try_add_tx(
@@ -1615,6 +1657,108 @@ impl MemPoolDB {
         Ok(())
     }
 
+    /// The STX that `tx` commits `address` to spend: its fee, if `address` pays for it, and the
+    /// amount of its STX transfer, if `address` originates it.  Other payloads may spend STX too,
+    /// but how much can't be known without running them.
+    fn tx_spend_by(tx: &StacksTransaction, address: &StacksAddress) -> u128 {
+        let mut spend = 0u128;
+        if tx.sponsor_address().unwrap_or_else(|| tx.origin_address()) == *address {
+            spend += tx.get_tx_fee() as u128;
+        }
+        if let TransactionPayload::TokenTransfer(_, amount, _) = tx.payload {
+            if tx.origin_address() == *address {
+                spend += amount as u128;
+            }
+        }
+        spend
+    }
+
+    /// The STX that the transactions pending in the mempool commit `address` to spend, counting
+    /// only the ones whose nonce for `address` is at least its account nonce (the others were
+    /// already mined).  The transaction that `replacing` would replace, if any, is left out.
+    pub fn get_pending_spend(
+        conn: &DBConn,
+        address: &StacksAddress,
+        account_nonce: u64,
+        replacing: &StacksTransaction,
+    ) -> Result<u128, db_error> {
+        let sql = "SELECT * FROM mempool WHERE (origin_address = ?1 AND origin_nonce >= ?2) OR (sponsor_address = ?1 AND sponsor_nonce >= ?2)";
+        let args: &[&dyn ToSql] = &[&address.to_string(), &u64_to_sql(account_nonce)?];
+        let pending_txs: Vec<MemPoolTxInfo> = query_rows(conn, sql, args)?;
+
+        let replaced_origin = (replacing.origin_address(), replacing.get_origin_nonce());
+        let mut pending_spend = 0u128;
+        for pending_tx in pending_txs.into_iter() {
+            let md = &pending_tx.metadata;
+            if (md.origin_address.clone(), md.origin_nonce) == replaced_origin {
+                continue;
+            }
+            if md.sponsor_address == *address && md.sponsor_nonce >= account_nonce {
+                pending_spend = pending_spend.saturating_add(md.tx_fee as u128);
+            }
+            if md.origin_address == *address && md.origin_nonce >= account_nonce {
+                if let TransactionPayload::TokenTransfer(_, amount, _) = pending_tx.tx.payload {
+                    pending_spend = pending_spend.saturating_add(amount as u128);
+                }
+            }
+        }
+        Ok(pending_spend)
+    }
+
+    /// Reject `tx` if, together with the transactions they already have pending, it would commit
+    /// its origin or its sponsor to spend more STX than they have available at the given chain
+    /// tip.  Such a chain of transactions can never all be mined, and would only linger in the
+    /// mempool, blocking the sender's later transactions.
+    pub fn check_pending_spend(
+        mempool_tx: &MemPoolTx,
+        chainstate: &mut StacksChainState,
+        consensus_hash: &ConsensusHash,
+        block_hash: &BlockHeaderHash,
+        tx: &StacksTransaction,
+    ) -> Result<(), MemPoolRejection> {
+        let mut spenders = vec![tx.origin_address()];
+        if let Some(sponsor_address) = tx.sponsor_address() {
+            if sponsor_address != spenders[0] {
+                spenders.push(sponsor_address);
+            }
+        }
+        let principals: Vec<PrincipalData> = spenders
+            .iter()
+            .map(|address| address.clone().into())
+            .collect();
+        let accounts =
+            chainstate.get_mempool_admission_accounts(consensus_hash, block_hash, &principals)?;
+
+        for ((address, principal), (account_nonce, available)) in spenders
+            .iter()
+            .zip(principals.into_iter())
+            .zip(accounts.into_iter())
+        {
+            let spend = MemPoolDB::tx_spend_by(tx, address);
+            if spend == 0 {
+                continue;
+            }
+            let pending = MemPoolDB::get_pending_spend(mempool_tx, address, account_nonce, tx)?;
+            if pending.saturating_add(spend) > available {
+                test_debug!(
+                    "Transaction {} would have {} spend {} on top of {} pending, but only {} is available",
+                    &tx.txid(),
+                    address,
+                    spend,
+                    pending,
+                    available
+                );
+                return Err(MemPoolRejection::TooMuchPendingSpend {
+                    principal,
+                    pending,
+                    spend,
+                    available,
+                });
+            }
+        }
+        Ok(())
+    }
+
     /// One-shot submit
     pub fn submit(
         &mut self,
@@ -1640,8 +1784,6 @@ impl MemPoolDB {
             stacks_epoch_id,
         );
 
-        let mut mempool_tx = self.tx_begin().map_err(MemPoolRejection::DBError)?;
-
         let fee_rate = match estimator_result {
             Ok(x) => Some(x),
             Err(EstimatorError::NoEstimateAvailable) => None,
@@ -1653,6 +1795,24 @@ impl MemPoolDB {
             }
         };
 
+        if self.min_tx_fee_rate > 0f64 {
+            let admission_fee_rate = self.admission_fee_rate(tx, fee_rate);
+            if admission_fee_rate < self.min_tx_fee_rate {
+                test_debug!(
+                    "Transaction {} pays a fee rate of {}, below {}",
+                    &tx.txid(),
+                    admission_fee_rate,
+                    self.min_tx_fee_rate
+                );
+                return Err(MemPoolRejection::FeeRateTooLow(
+                    admission_fee_rate,
+                    self.min_tx_fee_rate,
+                ));
+            }
+        }
+
+        let mut mempool_tx = self.tx_begin().map_err(MemPoolRejection::DBError)?;
+
         MemPoolDB::tx_submit(
             &mut mempool_tx,
             chainstate,
@@ -1667,6 +1827,119 @@ impl MemPoolDB {
         Ok(())
     }
 
+    /// The fee rate of `tx` that `min_tx_fee_rate` applies to: its estimated fee rate, or, if the
+    /// cost estimator has no estimate for it, the fee rate it would pay if it had no execution
+    /// cost (which is at least its actual fee rate).
+    pub fn admission_fee_rate(
+        &self,
+        tx: &StacksTransaction,
+        fee_rate_estimate: Option<f64>,
+    ) -> f64 {
+        match fee_rate_estimate {
+            Some(fee_rate) => fee_rate,
+            None => tx.get_tx_fee() as f64 / cmp::max(1, self.metric.from_len(tx.tx_len())) as f64,
+        }
+    }
+
+    /// The fee rate of a transaction in the mempool that `min_tx_fee_rate` applies to, as it was
+    /// estimated when it was admitted.
+    pub fn get_admission_fee_rate(&self, tx: &StacksTransaction) -> Result<f64, db_error> {
+        let fee_rate_estimate: Option<f64> = self
+            .db
+            .query_row(
+                "SELECT fee_rate FROM fee_estimates WHERE txid = ?1",
+                &[&tx.txid()],
+                |row| row.get::<_, Option<f64>>(0),
+            )
+            .optional()?
+            .flatten();
+        Ok(self.admission_fee_rate(tx, fee_rate_estimate))
+    }
+
+    /// Pre-execute `tx` on top of the given chain tip, and explain why it would obviously abort if
+    /// it were mined there: its post-conditions would fail, it would return an `err`, or it could
+    /// not be mined at all (e.g. its payer can't cover the fee).  A transaction whose nonce is
+    /// ahead of its sender's account nonce can only run after the sender's pending transactions,
+    /// so it is not executed; it is only checked for a gap in the sender's nonces in the mempool.
+    /// Return `None` if nothing is obviously wrong.
+    pub fn simulate_tx(
+        &self,
+        chainstate: &mut StacksChainState,
+        burn_dbconn: &dyn BurnStateDB,
+        consensus_hash: &ConsensusHash,
+        block_hash: &BlockHeaderHash,
+        tx: &StacksTransaction,
+        ast_rules: ASTRules,
+    ) -> Result<Option<String>, MemPoolRejection> {
+        let tip = StacksBlockId::new(consensus_hash, block_hash);
+        let mut senders = vec![(tx.origin_address(), tx.get_origin_nonce())];
+        if let (Some(addr), Some(nonce)) = (tx.sponsor_address(), tx.get_sponsor_nonce()) {
+            senders.push((addr, nonce));
+        }
+        let account_nonces = chainstate
+            .with_read_only_clarity_tx(burn_dbconn, &tip, |conn| {
+                senders
+                    .iter()
+                    .map(|(addr, _)| {
+                        StacksChainState::get_account(conn, &addr.clone().into()).nonce
+                    })
+                    .collect::<Vec<u64>>()
+            })
+            .ok_or_else(|| {
+                MemPoolRejection::NoSuchChainTip(consensus_hash.clone(), block_hash.clone())
+            })?;
+
+        let mut chained = false;
+        for ((addr, nonce), account_nonce) in senders.iter().zip(account_nonces.into_iter()) {
+            if *nonce <= account_nonce {
+                continue;
+            }
+            chained = true;
+            // the previous nonce must be used by a pending transaction, either as its origin or
+            // as its sponsor
+            let has_predecessor =
+                MemPoolDB::get_tx_metadata_by_address(self.conn(), true, addr, nonce - 1)?
+                    .is_some()
+                    || MemPoolDB::get_tx_metadata_by_address(self.conn(), false, addr, nonce - 1)?
+                        .is_some();
+            if !has_predecessor {
+                return Ok(Some(format!(
+                    "nonce {} of {} leaves a gap: its account nonce is {}, and no pending transaction uses nonce {}",
+                    nonce,
+                    addr,
+                    account_nonce,
+                    nonce - 1
+                )));
+            }
+        }
+        if chained {
+            return Ok(None);
+        }
+
+        let receipt = match chainstate.simulate_transaction(
+            burn_dbconn,
+            consensus_hash,
+            block_hash,
+            tx,
+            ast_rules,
+        ) {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                return Ok(Some(format!("transaction cannot be mined: {}", e)));
+            }
+        };
+
+        if receipt.post_condition_aborted {
+            return Ok(Some("post-conditions would fail".to_string()));
+        }
+        if let Value::Response(ref data) = receipt.result {
+            if !data.committed {
+                return Ok(Some(format!("transaction would return {}", receipt.result)));
+            }
+        }
+        Ok(None)
+    }
+
     /// Directly submit to the mempool, and don't do any admissions checks.
     /// This method is only used during testing, but because it is used by the
     ///  integration tests, it cannot be marked #[cfg(test)].
@@ -25,13 +25,15 @@ use crate::chainstate::stacks::db::test::chainstate_path;
 use crate::chainstate::stacks::db::test::instantiate_chainstate;
 use crate::chainstate::stacks::db::test::instantiate_chainstate_with_balances;
 use crate::chainstate::stacks::db::StreamCursor;
+use crate::chainstate::stacks::miner::test::{make_user_contract_call, make_user_stacks_transfer};
 use crate::chainstate::stacks::miner::TransactionResult;
 use crate::chainstate::stacks::test::codec_all_transactions;
 use crate::chainstate::stacks::{
     db::blocks::MemPoolRejection, db::StacksChainState, index::MarfTrieId, CoinbasePayload,
-    Error as ChainstateError, SinglesigHashMode, SinglesigSpendingCondition, StacksPrivateKey,
-    StacksPublicKey, StacksTransaction, StacksTransactionSigner, TokenTransferMemo,
-    TransactionAnchorMode, TransactionAuth, TransactionContractCall, TransactionPayload,
+    Error as ChainstateError, FungibleConditionCode, PostConditionPrincipal, SinglesigHashMode,
+    SinglesigSpendingCondition, StacksPrivateKey, StacksPublicKey, StacksTransaction,
+    StacksTransactionSigner, TokenTransferMemo, TransactionAnchorMode, TransactionAuth,
+    TransactionContractCall, TransactionPayload, TransactionPostCondition,
     TransactionPostConditionMode, TransactionPublicKeyEncoding, TransactionSmartContract,
     TransactionSpendingCondition, TransactionVersion,
 };
@@ -48,9 +50,11 @@ use crate::net::HttpResponseType;
 use crate::net::MemPoolSyncData;
 use crate::util_lib::bloom::test::setup_bloom_counter;
 use crate::util_lib::bloom::*;
+use crate::util_lib::boot::boot_code_addr;
 use crate::util_lib::db::{tx_begin_immediate, DBConn, FromRow};
 use crate::util_lib::strings::StacksString;
 use clarity::vm::{
+    ast::ASTRules,
     database::HeadersDB,
     errors::Error as ClarityError,
     errors::RuntimeErrorType,
@@ -2284,3 +2288,320 @@ fn test_drop_and_blacklist_txs_by_size() {
 
     assert_eq!(num_blacklisted, 5);
 }
+
+#[test]
+fn mempool_simulate_tx() {
+    let privk = StacksPrivateKey::from_hex(SK_1).unwrap();
+    let addr = StacksAddress::from_public_keys(
+        C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+        &AddressHashMode::SerializeP2PKH,
+        1,
+        &vec![StacksPublicKey::from_private(&privk)],
+    )
+    .unwrap();
+    let recipient: PrincipalData = StacksAddress {
+        version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+        bytes: Hash160([0x11; 20]),
+    }
+    .into();
+
+    let mut chainstate = instantiate_chainstate_with_balances(
+        false,
+        0x80000000,
+        "mempool_simulate_tx",
+        vec![(addr.clone(), 1_000_000)],
+    );
+    let b_1 = make_block(
+        &mut chainstate,
+        ConsensusHash([0x1; 20]),
+        &(
+            FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+            FIRST_STACKS_BLOCK_HASH.clone(),
+        ),
+        1,
+        1,
+    );
+
+    let chainstate_path = chainstate_path("mempool_simulate_tx");
+    let mut mempool = MemPoolDB::open_test(false, 0x80000000, &chainstate_path).unwrap();
+
+    let simulate =
+        |mempool: &MemPoolDB, chainstate: &mut StacksChainState, tx: &StacksTransaction| {
+            mempool
+                .simulate_tx(
+                    chainstate,
+                    &TEST_BURN_STATE_DB,
+                    &b_1.0,
+                    &b_1.1,
+                    tx,
+                    ASTRules::PrecheckSize,
+                )
+                .unwrap()
+        };
+
+    // a transfer the sender can afford runs to completion
+    let tx = make_user_stacks_transfer(&privk, 0, 200, &recipient, 100);
+    assert_eq!(simulate(&mempool, &mut chainstate, &tx), None);
+
+    // a transfer the sender can't afford can't be mined
+    let tx = make_user_stacks_transfer(&privk, 0, 200, &recipient, 10_000_000);
+    assert!(simulate(&mempool, &mut chainstate, &tx)
+        .unwrap()
+        .starts_with("transaction cannot be mined"));
+
+    // a contract call that returns an error
+    let tx = make_user_contract_call(
+        &privk,
+        0,
+        200,
+        &boot_code_addr(false),
+        "cost-voting",
+        "veto",
+        vec![Value::UInt(99)],
+    );
+    assert!(simulate(&mempool, &mut chainstate, &tx)
+        .unwrap()
+        .starts_with("transaction would return (err"));
+
+    // a contract call that violates its post-conditions
+    let mut unsigned_tx = make_user_contract_call(
+        &privk,
+        0,
+        200,
+        &boot_code_addr(false),
+        "pox",
+        "disallow-contract-caller",
+        vec![Value::Principal(recipient.clone())],
+    );
+    unsigned_tx.auth = TransactionAuth::from_p2pkh(&privk).unwrap();
+    unsigned_tx.set_tx_fee(200);
+    unsigned_tx.post_condition_mode = TransactionPostConditionMode::Deny;
+    unsigned_tx
+        .post_conditions
+        .push(TransactionPostCondition::STX(
+            PostConditionPrincipal::Origin,
+            FungibleConditionCode::SentGe,
+            10,
+        ));
+    let mut signer = StacksTransactionSigner::new(&unsigned_tx);
+    signer.sign_origin(&privk).unwrap();
+    let tx = signer.get_tx().unwrap();
+    assert_eq!(
+        simulate(&mempool, &mut chainstate, &tx),
+        Some("post-conditions would fail".to_string())
+    );
+
+    // a transaction whose payer can't cover the fee can't be mined
+    let tx = make_user_stacks_transfer(&privk, 0, 2_000_000, &recipient, 100);
+    assert!(simulate(&mempool, &mut chainstate, &tx)
+        .unwrap()
+        .starts_with("transaction cannot be mined"));
+
+    // a chained transaction must follow a pending transaction
+    let tx = make_user_stacks_transfer(&privk, 1, 200, &recipient, 100);
+    assert!(simulate(&mempool, &mut chainstate, &tx)
+        .unwrap()
+        .starts_with("nonce 1 of"));
+
+    let pending = make_user_stacks_transfer(&privk, 0, 200, &recipient, 100);
+    let mut mempool_tx = mempool.tx_begin().unwrap();
+    MemPoolDB::try_add_tx(
+        &mut mempool_tx,
+        &mut chainstate,
+        &b_1.0,
+        &b_1.1,
+        pending.txid(),
+        pending.serialize_to_vec(),
+        pending.get_tx_fee(),
+        1,
+        &addr,
+        0,
+        &addr,
+        0,
+        None,
+    )
+    .unwrap();
+    mempool_tx.commit().unwrap();
+
+    assert_eq!(simulate(&mempool, &mut chainstate, &tx), None);
+}
+
+#[test]
+fn mempool_pending_spend() {
+    let privk = StacksPrivateKey::from_hex(SK_1).unwrap();
+    let addr = StacksAddress::from_public_keys(
+        C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+        &AddressHashMode::SerializeP2PKH,
+        1,
+        &vec![StacksPublicKey::from_private(&privk)],
+    )
+    .unwrap();
+    let recipient: PrincipalData = StacksAddress {
+        version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+        bytes: Hash160([0x11; 20]),
+    }
+    .into();
+
+    let mut chainstate = instantiate_chainstate_with_balances(
+        false,
+        0x80000000,
+        "mempool_pending_spend",
+        vec![(addr.clone(), 1_000_000)],
+    );
+    let b_1 = make_block(
+        &mut chainstate,
+        ConsensusHash([0x1; 20]),
+        &(
+            FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+            FIRST_STACKS_BLOCK_HASH.clone(),
+        ),
+        1,
+        1,
+    );
+
+    let chainstate_path = chainstate_path("mempool_pending_spend");
+    let mut mempool = MemPoolDB::open_test(false, 0x80000000, &chainstate_path).unwrap();
+
+    let add_tx =
+        |mempool: &mut MemPoolDB, chainstate: &mut StacksChainState, tx: &StacksTransaction| {
+            let mut mempool_tx = mempool.tx_begin().unwrap();
+            MemPoolDB::try_add_tx(
+                &mut mempool_tx,
+                chainstate,
+                &b_1.0,
+                &b_1.1,
+                tx.txid(),
+                tx.serialize_to_vec(),
+                tx.get_tx_fee(),
+                1,
+                &addr,
+                tx.get_origin_nonce(),
+                &addr,
+                tx.get_origin_nonce(),
+                None,
+            )
+            .unwrap();
+            mempool_tx.commit().unwrap();
+        };
+    let check =
+        |mempool: &mut MemPoolDB, chainstate: &mut StacksChainState, tx: &StacksTransaction| {
+            let mempool_tx = mempool.tx_begin().unwrap();
+            MemPoolDB::check_pending_spend(&mempool_tx, chainstate, &b_1.0, &b_1.1, tx)
+        };
+
+    let first = make_user_stacks_transfer(&privk, 0, 200, &recipient, 600_000);
+    check(&mut mempool, &mut chainstate, &first).unwrap();
+    add_tx(&mut mempool, &mut chainstate, &first);
+
+    // each transfer is affordable on its own, but not both of them
+    let second = make_user_stacks_transfer(&privk, 1, 200, &recipient, 600_000);
+    match check(&mut mempool, &mut chainstate, &second).unwrap_err() {
+        MemPoolRejection::TooMuchPendingSpend {
+            principal,
+            pending,
+            spend,
+            available,
+        } => {
+            assert_eq!(principal, addr.clone().into());
+            assert_eq!(pending, 600_200);
+            assert_eq!(spend, 600_200);
+            assert_eq!(available, 1_000_000);
+        }
+        e => panic!("Unexpected rejection: {:?}", &e),
+    }
+
+    let second = make_user_stacks_transfer(&privk, 1, 200, &recipient, 300_000);
+    check(&mut mempool, &mut chainstate, &second).unwrap();
+    add_tx(&mut mempool, &mut chainstate, &second);
+
+    // a replacement doesn't count the transaction it replaces
+    let replacement = make_user_stacks_transfer(&privk, 0, 400, &recipient, 600_000);
+    check(&mut mempool, &mut chainstate, &replacement).unwrap();
+    assert_eq!(
+        MemPoolDB::get_pending_spend(mempool.conn(), &addr, 0, &replacement).unwrap(),
+        300_200
+    );
+
+    // transactions below the account nonce were already mined
+    assert_eq!(
+        MemPoolDB::get_pending_spend(mempool.conn(), &addr, 1, &replacement).unwrap(),
+        300_200
+    );
+    assert_eq!(
+        MemPoolDB::get_pending_spend(mempool.conn(), &addr, 2, &replacement).unwrap(),
+        0
+    );
+}
+
+#[test]
+fn mempool_admission_fee_rate() {
+    let mut chainstate = instantiate_chainstate(false, 0x80000000, "mempool_admission_fee_rate");
+    let chainstate_path = chainstate_path("mempool_admission_fee_rate");
+    let mut mempool = MemPoolDB::open_test(false, 0x80000000, &chainstate_path).unwrap();
+
+    let b_1 = make_block(
+        &mut chainstate,
+        ConsensusHash([0x1; 20]),
+        &(
+            FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+            FIRST_STACKS_BLOCK_HASH.clone(),
+        ),
+        1,
+        1,
+    );
+
+    let mut txs = codec_all_transactions(
+        &TransactionVersion::Testnet,
+        0x80000000,
+        &TransactionAnchorMode::Any,
+        &TransactionPostConditionMode::Allow,
+    );
+    let mut tx = txs.pop().unwrap();
+    tx.set_tx_fee(123);
+
+    // the unit metric has a length of 1, so without an estimate the fee rate is the fee
+    assert_eq!(mempool.admission_fee_rate(&tx, None), 123f64);
+    assert_eq!(mempool.admission_fee_rate(&tx, Some(4.5)), 4.5);
+
+    let origin_address = StacksAddress {
+        version: 22,
+        bytes: Hash160::from_data(&[0; 32]),
+    };
+    let txid = tx.txid();
+    let mut mempool_tx = mempool.tx_begin().unwrap();
+    MemPoolDB::try_add_tx(
+        &mut mempool_tx,
+        &mut chainstate,
+        &b_1.0,
+        &b_1.1,
+        txid.clone(),
+        tx.serialize_to_vec(),
+        tx.get_tx_fee(),
+        1,
+        &origin_address,
+        tx.get_origin_nonce(),
+        &origin_address,
+        tx.get_origin_nonce(),
+        None,
+    )
+    .unwrap();
+    mempool_tx.commit().unwrap();
+    assert_eq!(mempool.get_admission_fee_rate(&tx).unwrap(), 123f64);
+
+    // once estimated, the estimate is used
+    mempool
+        .db
+        .execute(
+            "INSERT OR REPLACE INTO fee_estimates(txid, fee_rate) VALUES (?, ?)",
+            rusqlite::params![&txid, 4.5f64],
+        )
+        .unwrap();
+    assert_eq!(mempool.get_admission_fee_rate(&tx).unwrap(), 4.5);
+
+    let rejection = MemPoolRejection::FeeRateTooLow(4.5, 10.0).into_json(&txid);
+    assert_eq!(rejection["reason"], "FeeRateTooLow");
+    assert_eq!(
+        rejection["reason_data"],
+        json!({"expected": 10.0, "actual": 4.5})
+    );
+}
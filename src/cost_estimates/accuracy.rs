@@ -0,0 +1,290 @@
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+use clarity::vm::costs::ExecutionCost;
+
+use crate::chainstate::stacks::db::StacksEpochReceipt;
+use crate::chainstate::stacks::events::{StacksTransactionReceipt, TransactionOrigin};
+use crate::chainstate::stacks::TransactionPayload;
+use crate::core::StacksEpochId;
+use crate::monitoring;
+
+use super::fee_medians::{
+    fee_rate_and_weight_from_receipt, fee_rate_estimate_from_sorted_weighted_fees,
+};
+use super::metrics::CostMetric;
+use super::{CostEstimator, EstimatorError, FeeEstimator, FeeRateEstimate};
+
+/// Rolling statistics of an estimator's relative errors, `(predicted - realized) / realized`,
+/// over its last `window_size` scored predictions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EstimatorErrorStats {
+    window_size: usize,
+    errors: VecDeque<f64>,
+}
+
+impl EstimatorErrorStats {
+    pub fn new(window_size: usize) -> EstimatorErrorStats {
+        EstimatorErrorStats {
+            window_size: window_size.max(1),
+            errors: VecDeque::new(),
+        }
+    }
+
+    /// Score a prediction against the value that was realized.
+    pub fn record(&mut self, predicted: f64, realized: f64) {
+        let error = (predicted - realized) / realized.max(1f64);
+        if !error.is_finite() {
+            return;
+        }
+        if self.errors.len() == self.window_size {
+            self.errors.pop_front();
+        }
+        self.errors.push_back(error);
+    }
+
+    /// Number of predictions in the window.
+    pub fn samples(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Mean of the absolute relative errors in the window, or None if nothing was scored yet.
+    pub fn mean_absolute_error(&self) -> Option<f64> {
+        if self.errors.is_empty() {
+            return None;
+        }
+        Some(self.errors.iter().map(|error| error.abs()).sum::<f64>() / self.errors.len() as f64)
+    }
+
+    /// Mean of the relative errors in the window: positive if the estimator overestimates, and
+    /// negative if it underestimates.  None if nothing was scored yet.
+    pub fn mean_error(&self) -> Option<f64> {
+        if self.errors.is_empty() {
+            return None;
+        }
+        Some(self.errors.iter().sum::<f64>() / self.errors.len() as f64)
+    }
+
+    fn publish(&self, kind: &str, name: &str) {
+        if let (Some(mean_absolute_error), Some(mean_error)) =
+            (self.mean_absolute_error(), self.mean_error())
+        {
+            monitoring::update_estimator_accuracy(
+                kind,
+                name,
+                mean_absolute_error,
+                mean_error,
+                self.samples() as u64,
+            );
+        }
+    }
+}
+
+struct TrackedEstimator<E> {
+    name: String,
+    estimator: E,
+    stats: EstimatorErrorStats,
+}
+
+/// A `CostEstimator` that scores the estimators it wraps: before a block is fed to them, each
+/// one's estimate for every transaction in it is compared, as a scalar `metric`, with the cost
+/// the transaction actually had.  Estimates are served by the first estimator; the others only
+/// run in the shadow of it, to be compared with it.
+pub struct CostEstimatorAccuracy<CM: CostMetric> {
+    estimators: Vec<TrackedEstimator<Box<dyn CostEstimator>>>,
+    metric: CM,
+}
+
+impl<CM: CostMetric> CostEstimatorAccuracy<CM> {
+    /// `estimators` are (name, estimator) pairs, and must not be empty.
+    pub fn new(
+        estimators: Vec<(String, Box<dyn CostEstimator>)>,
+        metric: CM,
+        window_size: usize,
+    ) -> CostEstimatorAccuracy<CM> {
+        assert!(!estimators.is_empty());
+        CostEstimatorAccuracy {
+            estimators: estimators
+                .into_iter()
+                .map(|(name, estimator)| TrackedEstimator {
+                    name,
+                    estimator,
+                    stats: EstimatorErrorStats::new(window_size),
+                })
+                .collect(),
+            metric,
+        }
+    }
+
+    /// The error statistics of each estimator, by name.
+    pub fn get_accuracy(&self) -> Vec<(&str, &EstimatorErrorStats)> {
+        self.estimators
+            .iter()
+            .map(|tracked| (tracked.name.as_str(), &tracked.stats))
+            .collect()
+    }
+}
+
+impl<CM: CostMetric> CostEstimator for CostEstimatorAccuracy<CM> {
+    fn notify_event(
+        &mut self,
+        tx: &TransactionPayload,
+        actual_cost: &ExecutionCost,
+        block_limit: &ExecutionCost,
+        evaluated_epoch: &StacksEpochId,
+    ) -> Result<(), EstimatorError> {
+        let mut result = Ok(());
+        for (i, tracked) in self.estimators.iter_mut().enumerate() {
+            let tracked_result =
+                tracked
+                    .estimator
+                    .notify_event(tx, actual_cost, block_limit, evaluated_epoch);
+            if i == 0 {
+                result = tracked_result;
+            }
+        }
+        result
+    }
+
+    fn estimate_cost(
+        &self,
+        tx: &TransactionPayload,
+        evaluated_epoch: &StacksEpochId,
+    ) -> Result<ExecutionCost, EstimatorError> {
+        self.estimators[0]
+            .estimator
+            .estimate_cost(tx, evaluated_epoch)
+    }
+
+    fn notify_block(
+        &mut self,
+        receipts: &[StacksTransactionReceipt],
+        block_limit: &ExecutionCost,
+        stacks_epoch_id: &StacksEpochId,
+    ) {
+        for tracked in self.estimators.iter_mut() {
+            for receipt in receipts.iter() {
+                let tx = match receipt.transaction {
+                    TransactionOrigin::Stacks(ref tx) => tx,
+                    TransactionOrigin::Burn(_) => continue,
+                };
+                let estimate = match tracked
+                    .estimator
+                    .estimate_cost(&tx.payload, stacks_epoch_id)
+                {
+                    Ok(estimate) => estimate,
+                    Err(_) => continue,
+                };
+                let tx_len = tx.tx_len();
+                let predicted = self
+                    .metric
+                    .from_cost_and_len(&estimate, block_limit, tx_len);
+                let realized =
+                    self.metric
+                        .from_cost_and_len(&receipt.execution_cost, block_limit, tx_len);
+                tracked.stats.record(predicted as f64, realized as f64);
+            }
+            tracked
+                .estimator
+                .notify_block(receipts, block_limit, stacks_epoch_id);
+            tracked.stats.publish("cost", &tracked.name);
+        }
+    }
+}
+
+/// A `FeeEstimator` that scores the estimators it wraps: before a block is fed to them, each
+/// one's middle fee rate estimate is compared with the weighted median fee rate that the block's
+/// transactions paid.  Estimates are served by the first estimator; the others only run in the
+/// shadow of it, to be compared with it.
+pub struct FeeEstimatorAccuracy<CM: CostMetric> {
+    estimators: Vec<TrackedEstimator<Box<dyn FeeEstimator>>>,
+    metric: CM,
+}
+
+impl<CM: CostMetric> FeeEstimatorAccuracy<CM> {
+    /// `estimators` are (name, estimator) pairs, and must not be empty.
+    pub fn new(
+        estimators: Vec<(String, Box<dyn FeeEstimator>)>,
+        metric: CM,
+        window_size: usize,
+    ) -> FeeEstimatorAccuracy<CM> {
+        assert!(!estimators.is_empty());
+        FeeEstimatorAccuracy {
+            estimators: estimators
+                .into_iter()
+                .map(|(name, estimator)| TrackedEstimator {
+                    name,
+                    estimator,
+                    stats: EstimatorErrorStats::new(window_size),
+                })
+                .collect(),
+            metric,
+        }
+    }
+
+    /// The error statistics of each estimator, by name.
+    pub fn get_accuracy(&self) -> Vec<(&str, &EstimatorErrorStats)> {
+        self.estimators
+            .iter()
+            .map(|tracked| (tracked.name.as_str(), &tracked.stats))
+            .collect()
+    }
+
+    /// The weighted median fee rate paid by the transactions of a block, or None if none of
+    /// them pays a fee rate (e.g. a block with only a coinbase).
+    fn realized_fee_rate(
+        &self,
+        receipt: &StacksEpochReceipt,
+        block_limit: &ExecutionCost,
+    ) -> Option<f64> {
+        let mut fee_rates: Vec<_> = receipt
+            .tx_receipts
+            .iter()
+            .filter_map(|tx_receipt| {
+                fee_rate_and_weight_from_receipt(&self.metric, tx_receipt, block_limit)
+            })
+            .filter(|fee_rate| fee_rate.weight > 0)
+            .collect();
+        if fee_rates.is_empty() {
+            return None;
+        }
+        fee_rates.sort_by(|a, b| {
+            a.fee_rate
+                .partial_cmp(&b.fee_rate)
+                .unwrap_or(Ordering::Equal)
+        });
+        Some(fee_rate_estimate_from_sorted_weighted_fees(&fee_rates).middle)
+    }
+}
+
+impl<CM: CostMetric> FeeEstimator for FeeEstimatorAccuracy<CM> {
+    fn notify_block(
+        &mut self,
+        receipt: &StacksEpochReceipt,
+        block_limit: &ExecutionCost,
+    ) -> Result<(), EstimatorError> {
+        let realized_opt = self.realized_fee_rate(receipt, block_limit);
+        let mut result = Ok(());
+        for (i, tracked) in self.estimators.iter_mut().enumerate() {
+            if let (Some(realized), Ok(estimate)) =
+                (realized_opt, tracked.estimator.get_rate_estimates())
+            {
+                tracked.stats.record(estimate.middle, realized);
+            }
+            let tracked_result = tracked.estimator.notify_block(receipt, block_limit);
+            if i == 0 {
+                result = tracked_result;
+            } else if let Err(e) = tracked_result {
+                warn!("Shadow FeeEstimator failed to process block receipt";
+                      "estimator" => &tracked.name,
+                      "error" => %e);
+            }
+            tracked.stats.publish("fee", &tracked.name);
+        }
+        result
+    }
+
+    fn get_rate_estimates(&self) -> Result<FeeRateEstimate, EstimatorError> {
+        self.estimators[0].estimator.get_rate_estimates()
+    }
+}
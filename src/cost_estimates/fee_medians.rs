@@ -36,7 +36,8 @@ CREATE TABLE median_fee_estimator (
     low NUMBER NOT NULL
 )";
 
-const MINIMUM_TX_FEE_RATE: f64 = 1f64;
+/// The lowest fee rate that transactions are considered to pay
+pub const MINIMUM_TX_FEE_RATE: f64 = 1f64;
 
 /// FeeRateEstimator with the following properties:
 ///
@@ -310,7 +311,7 @@ fn maybe_add_minimum_fee_rate(working_rates: &mut Vec<FeeRateAndWeight>, full_bl
 /// Returns None if:
 ///   1) There is no fee rate for the tx.
 ///   2) Cacluated fee rate is infinite.
-fn fee_rate_and_weight_from_receipt(
+pub fn fee_rate_and_weight_from_receipt(
     metric: &dyn CostMetric,
     tx_receipt: &StacksTransactionReceipt,
     block_limit: &ExecutionCost,
@@ -22,29 +22,42 @@ use crate::chainstate::stacks::events::TransactionOrigin;
 use crate::util_lib::db::sql_pragma;
 use crate::util_lib::db::table_exists;
 
+use super::fee_medians::MINIMUM_TX_FEE_RATE;
+use super::fee_sketch::FeeRateSketch;
 use super::metrics::CostMetric;
 use super::FeeRateEstimate;
-use super::{EstimatorError, FeeEstimator};
+use super::{block_fullness, EstimatorError, FeeEstimator};
+
+/// Number of most recent blocks whose fee rates the estimates are drawn from
+pub const DEFAULT_WINDOW_SIZE: u32 = 20;
+/// How much each block weighs, relative to the block after it
+pub const DEFAULT_DECAY_RATE: f64 = 0.9;
+/// Compression of the sketches of blocks and of the merged window, which keep a few hundred
+/// centroids at most
+const SKETCH_COMPRESSION: f64 = 100f64;
 
-const SINGLETON_ROW_ID: i64 = 1;
 const CREATE_TABLE: &'static str = "
-CREATE TABLE scalar_fee_estimator (
-    estimate_key NUMBER PRIMARY KEY,
-    high NUMBER NOT NULL,
-    middle NUMBER NOT NULL,
-    low NUMBER NOT NULL
+CREATE TABLE scalar_fee_sketches (
+    block_seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    sketch TEXT NOT NULL
 )";
 
 /// This struct estimates fee rates by translating a transaction's `ExecutionCost`
 /// into a scalar using `ExecutionCost::proportion_dot_product` and computing
-/// the subsequent fee rate using the actual paid fee. The 5th, 50th and 95th
-/// percentile fee rates for each block are used as the low, middle, and high
-/// estimates. Estimates are updated via exponential decay windowing.
+/// the subsequent fee rate using the actual paid fee. The fee rates of each block
+/// are summarized in a quantile sketch (`FeeRateSketch`), and the sketches of the
+/// last `window_size` blocks are merged to serve estimates: every block weighs the
+/// same regardless of its number of transactions, discounted by `decay_rate` for
+/// each block since. The 5th, 50th and 95th percentiles of the merged sketch are
+/// used as the low, middle, and high estimates, so a single anomalous block only
+/// moves the estimates as far as its share of the window. Blocks are padded with
+/// the minimum fee rate in proportion to the capacity they left unused, so that the
+/// estimates fall toward it while blocks are mostly empty.
 pub struct ScalarFeeRateEstimator<M: CostMetric> {
     db: Connection,
-    /// how quickly does the current estimate decay
-    /// compared to the newly received block estimate
-    ///      new_estimate := (decay_rate) * old_estimate + (1 - decay_rate) * new_measure
+    window_size: u32,
+    /// how much a block's fee rates weigh compared to those of the block after it:
+    ///      weight(block) := decay_rate ^ (number of blocks since)
     decay_rate: f64,
     metric: M,
 }
@@ -52,6 +65,17 @@ pub struct ScalarFeeRateEstimator<M: CostMetric> {
 impl<M: CostMetric> ScalarFeeRateEstimator<M> {
     /// Open a fee rate estimator at the given db path. Creates if not existent.
     pub fn open(p: &Path, metric: M) -> Result<Self, SqliteError> {
+        Self::open_with_window(p, metric, DEFAULT_WINDOW_SIZE, DEFAULT_DECAY_RATE)
+    }
+
+    /// Open a fee rate estimator at the given db path, drawing its estimates from the last
+    /// `window_size` blocks weighed by `decay_rate`. Creates if not existent.
+    pub fn open_with_window(
+        p: &Path,
+        metric: M,
+        window_size: u32,
+        decay_rate: f64,
+    ) -> Result<Self, SqliteError> {
         let mut db = sqlite_open(
             p,
             rusqlite::OpenFlags::SQLITE_OPEN_CREATE | rusqlite::OpenFlags::SQLITE_OPEN_READ_WRITE,
@@ -68,87 +92,90 @@ impl<M: CostMetric> ScalarFeeRateEstimator<M> {
         Ok(Self {
             db,
             metric,
-            decay_rate: 0.5_f64,
+            window_size: cmp::max(window_size, 1),
+            decay_rate,
         })
     }
 
     /// Check if the SQL database was already created. Necessary to avoid races if
     ///  different threads open an estimator at the same time.
     fn db_already_instantiated(tx: &SqlTransaction) -> Result<bool, SqliteError> {
-        table_exists(tx, "scalar_fee_estimator")
+        table_exists(tx, "scalar_fee_sketches")
     }
 
     fn instantiate_db(tx: &SqlTransaction) -> Result<(), SqliteError> {
         if !Self::db_already_instantiated(tx)? {
             tx.execute(CREATE_TABLE, rusqlite::NO_PARAMS)?;
+            // the exponentially decayed estimate of earlier versions
+            tx.execute(
+                "DROP TABLE IF EXISTS scalar_fee_estimator",
+                rusqlite::NO_PARAMS,
+            )?;
         }
 
         Ok(())
     }
 
-    fn update_estimate(&mut self, new_measure: FeeRateEstimate) {
-        let next_estimate = match self.get_rate_estimates() {
-            Ok(old_estimate) => {
-                // compute the exponential windowing:
-                // estimate = (a/b * old_estimate) + ((1 - a/b) * new_estimate)
-                let prior_component = old_estimate.clone() * self.decay_rate;
-                let next_component = new_measure.clone() * (1_f64 - self.decay_rate);
-                let mut next_computed = prior_component + next_component;
-
-                // because of integer math, we can end up with some edge effects
-                // when the estimate is < decay_rate_fraction.1, so just saturate
-                // on the low end at a rate of "1"
-                next_computed.high = if next_computed.high >= 1f64 {
-                    next_computed.high
-                } else {
-                    1f64
-                };
-                next_computed.middle = if next_computed.middle >= 1f64 {
-                    next_computed.middle
-                } else {
-                    1f64
-                };
-                next_computed.low = if next_computed.low >= 1f64 {
-                    next_computed.low
-                } else {
-                    1f64
-                };
-
-                next_computed
-            }
-            Err(EstimatorError::NoEstimateAvailable) => new_measure.clone(),
-            Err(e) => {
-                warn!("Error in fee estimator fetching current estimates"; "err" => ?e);
-                return;
-            }
-        };
-
+    /// Store the sketch of a new block's fee rates, and forget the blocks that left the window.
+    fn add_block_sketch(&mut self, sketch: &FeeRateSketch) {
         debug!("Updating fee rate estimate for new block";
-               "new_measure_high" => new_measure.high,
-               "new_measure_middle" => new_measure.middle,
-               "new_measure_low" => new_measure.low,
-               "new_estimate_high" => next_estimate.high,
-               "new_estimate_middle" => next_estimate.middle,
-               "new_estimate_low" => next_estimate.low);
-
-        let sql = "INSERT OR REPLACE INTO scalar_fee_estimator
-                     (estimate_key, high, middle, low) VALUES (?, ?, ?, ?)";
+               "centroids" => sketch.centroids().len(),
+               "block_low" => sketch.quantile(0.05),
+               "block_middle" => sketch.quantile(0.5),
+               "block_high" => sketch.quantile(0.95));
 
+        let sketch_json = serde_json::to_string(sketch).expect("BUG: failed to serialize sketch");
         let tx = tx_begin_immediate_sqlite(&mut self.db).expect("SQLite failure");
-
         tx.execute(
-            sql,
-            rusqlite::params![
-                SINGLETON_ROW_ID,
-                next_estimate.high,
-                next_estimate.middle,
-                next_estimate.low,
-            ],
+            "INSERT INTO scalar_fee_sketches (sketch) VALUES (?)",
+            &[&sketch_json],
+        )
+        .expect("SQLite failure");
+        tx.execute(
+            "DELETE FROM scalar_fee_sketches WHERE block_seq <= last_insert_rowid() - ?",
+            &[self.window_size],
         )
         .expect("SQLite failure");
-
         tx.commit().expect("SQLite failure");
     }
+
+    /// The sketch of the fee rates in the window, with each block's weight decayed by its age.
+    fn get_window_sketch(&self) -> FeeRateSketch {
+        let sql = "SELECT sketch FROM scalar_fee_sketches ORDER BY block_seq DESC LIMIT ?";
+        let mut stmt = self.db.prepare(sql).expect("SQLite failure");
+        let sketches: Vec<FeeRateSketch> = stmt
+            .query_map(&[self.window_size], |row| {
+                let sketch_json: String = row.get(0)?;
+                Ok(sketch_json)
+            })
+            .expect("SQLite failure")
+            .filter_map(|sketch_json| {
+                let sketch_json = sketch_json.expect("SQLite failure");
+                match serde_json::from_str(&sketch_json) {
+                    Ok(sketch) => Some(sketch),
+                    Err(e) => {
+                        warn!("Failed to parse stored fee rate sketch"; "err" => %e);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        let mut weight = 1f64;
+        let mut weighted = Vec::with_capacity(sketches.len());
+        for sketch in sketches.iter() {
+            weighted.push((sketch, weight));
+            weight *= self.decay_rate;
+        }
+        FeeRateSketch::merge(weighted, SKETCH_COMPRESSION)
+    }
+
+    /// The fee rate at `percentile` (in [0, 1]) of the fee rates paid in the window.
+    pub fn get_rate_percentile(&self, percentile: f64) -> Result<f64, EstimatorError> {
+        self.get_window_sketch()
+            .quantile(percentile)
+            .ok_or(EstimatorError::NoEstimateAvailable)
+    }
 }
 
 impl<M: CostMetric> FeeEstimator for ScalarFeeRateEstimator<M> {
@@ -157,7 +184,7 @@ impl<M: CostMetric> FeeEstimator for ScalarFeeRateEstimator<M> {
         receipt: &StacksEpochReceipt,
         block_limit: &ExecutionCost,
     ) -> Result<(), EstimatorError> {
-        let mut all_fee_rates: Vec<_> = receipt
+        let all_fee_rates: Vec<_> = receipt
             .tx_receipts
             .iter()
             .filter_map(|tx_receipt| {
@@ -194,48 +221,48 @@ impl<M: CostMetric> FeeEstimator for ScalarFeeRateEstimator<M> {
                     } else {
                         1f64
                     };
-                if fee_rate >= 1f64 && fee_rate.is_finite() {
+                if fee_rate >= MINIMUM_TX_FEE_RATE && fee_rate.is_finite() {
                     Some(fee_rate)
                 } else {
-                    Some(1f64)
+                    Some(MINIMUM_TX_FEE_RATE)
                 }
             })
             .collect();
-        all_fee_rates.sort_by(|a, b| {
-            a.partial_cmp(b)
-                .expect("BUG: Fee rates should be orderable: NaN and infinite values are filtered")
-        });
-
-        let measures_len = all_fee_rates.len();
-        if measures_len > 0 {
-            // use 5th, 50th, and 95th percentiles from block
-            let highest_index = measures_len - cmp::max(1, measures_len / 20);
-            let median_index = measures_len / 2;
-            let lowest_index = measures_len / 20;
-            let block_estimate = FeeRateEstimate {
-                high: all_fee_rates[highest_index],
-                middle: all_fee_rates[median_index],
-                low: all_fee_rates[lowest_index],
-            };
-
-            self.update_estimate(block_estimate);
-        }
+
+        // the space left unused in the block could have been had at the minimum fee rate: it
+        // weighs as much, in the block's sketch, as the block's share of unused capacity
+        let fullness = if all_fee_rates.is_empty() {
+            0f64
+        } else {
+            block_fullness(receipt, block_limit)
+        };
+        let weighted_fee_rates: Vec<_> = all_fee_rates
+            .into_iter()
+            .map(|fee_rate| (fee_rate, 1f64))
+            .collect();
+        let tx_sketch =
+            FeeRateSketch::from_weighted_values(&weighted_fee_rates, SKETCH_COMPRESSION)
+                .normalized();
+        let unused_sketch =
+            FeeRateSketch::from_weighted_values(&[(MINIMUM_TX_FEE_RATE, 1f64)], SKETCH_COMPRESSION);
+        let block_sketch = FeeRateSketch::merge(
+            vec![(&tx_sketch, fullness), (&unused_sketch, 1f64 - fullness)],
+            SKETCH_COMPRESSION,
+        );
+        self.add_block_sketch(&block_sketch);
 
         Ok(())
     }
 
     fn get_rate_estimates(&self) -> Result<FeeRateEstimate, EstimatorError> {
-        let sql = "SELECT high, middle, low FROM scalar_fee_estimator WHERE estimate_key = ?";
-        self.db
-            .query_row(sql, &[SINGLETON_ROW_ID], |row| {
-                let high: f64 = row.get(0)?;
-                let middle: f64 = row.get(1)?;
-                let low: f64 = row.get(2)?;
-                Ok((high, middle, low))
-            })
-            .optional()
-            .expect("SQLite failure")
-            .map(|(high, middle, low)| FeeRateEstimate { high, middle, low })
-            .ok_or_else(|| EstimatorError::NoEstimateAvailable)
+        let sketch = self.get_window_sketch();
+        match (
+            sketch.quantile(0.05),
+            sketch.quantile(0.5),
+            sketch.quantile(0.95),
+        ) {
+            (Some(low), Some(middle), Some(high)) => Ok(FeeRateEstimate { high, middle, low }),
+            _ => Err(EstimatorError::NoEstimateAvailable),
+        }
     }
 }
@@ -0,0 +1,150 @@
+use std::cmp::Ordering;
+
+/// A cluster of nearby fee rates in a `FeeRateSketch`: their weighted mean, and their total
+/// weight.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Centroid {
+    pub mean: f64,
+    pub weight: f64,
+}
+
+/// A quantile sketch of weighted fee rates, in the manner of a merging t-digest: fee rates are
+/// clustered into a number of centroids proportional to `compression`, which are kept small near
+/// the tails so that extreme percentiles stay accurate.  Sketches can be scaled and merged, so a
+/// sketch can be kept for each block and the sketches of a window of blocks combined into one.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct FeeRateSketch {
+    /// Sorted by mean
+    centroids: Vec<Centroid>,
+}
+
+impl FeeRateSketch {
+    /// Sketch `values`, as (fee rate, weight) pairs.
+    pub fn from_weighted_values(values: &[(f64, f64)], compression: f64) -> FeeRateSketch {
+        let mut sketch = FeeRateSketch {
+            centroids: values
+                .iter()
+                .filter(|(fee_rate, weight)| fee_rate.is_finite() && *weight > 0f64)
+                .map(|(fee_rate, weight)| Centroid {
+                    mean: *fee_rate,
+                    weight: *weight,
+                })
+                .collect(),
+        };
+        sketch.compress(compression);
+        sketch
+    }
+
+    /// Merge `sketches`, each of whose weights are multiplied by its scale, into one sketch.
+    pub fn merge<'a, I>(sketches: I, compression: f64) -> FeeRateSketch
+    where
+        I: IntoIterator<Item = (&'a FeeRateSketch, f64)>,
+    {
+        let mut merged = FeeRateSketch::default();
+        for (sketch, scale) in sketches {
+            if scale <= 0f64 {
+                continue;
+            }
+            merged
+                .centroids
+                .extend(sketch.centroids.iter().map(|centroid| Centroid {
+                    mean: centroid.mean,
+                    weight: centroid.weight * scale,
+                }));
+        }
+        merged.compress(compression);
+        merged
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.centroids.is_empty()
+    }
+
+    pub fn centroids(&self) -> &[Centroid] {
+        &self.centroids
+    }
+
+    pub fn total_weight(&self) -> f64 {
+        self.centroids.iter().map(|centroid| centroid.weight).sum()
+    }
+
+    /// Returns a copy of this sketch whose total weight is 1, or an empty sketch if this one is.
+    pub fn normalized(&self) -> FeeRateSketch {
+        let total_weight = self.total_weight();
+        if total_weight <= 0f64 {
+            return FeeRateSketch::default();
+        }
+        FeeRateSketch {
+            centroids: self
+                .centroids
+                .iter()
+                .map(|centroid| Centroid {
+                    mean: centroid.mean,
+                    weight: centroid.weight / total_weight,
+                })
+                .collect(),
+        }
+    }
+
+    /// Sort the centroids, and merge neighbours while they stay within the t-digest size bound
+    /// `4 * total_weight * q * (1 - q) / compression` at their quantile `q`.
+    fn compress(&mut self, compression: f64) {
+        self.centroids
+            .sort_by(|a, b| a.mean.partial_cmp(&b.mean).unwrap_or(Ordering::Equal));
+        let total_weight = self.total_weight();
+        if total_weight <= 0f64 {
+            self.centroids.clear();
+            return;
+        }
+
+        let mut merged: Vec<Centroid> = Vec::with_capacity(self.centroids.len());
+        let mut weight_before_last = 0f64;
+        for centroid in self.centroids.drain(..) {
+            if let Some(last) = merged.last_mut() {
+                let combined_weight = last.weight + centroid.weight;
+                let q = (weight_before_last + combined_weight / 2f64) / total_weight;
+                let max_weight = 4f64 * total_weight * q * (1f64 - q) / compression;
+                if last.mean == centroid.mean {
+                    last.weight = combined_weight;
+                    continue;
+                }
+                if combined_weight <= max_weight {
+                    last.mean = (last.mean * last.weight + centroid.mean * centroid.weight)
+                        / combined_weight;
+                    last.weight = combined_weight;
+                    continue;
+                }
+                weight_before_last += last.weight;
+            }
+            merged.push(centroid);
+        }
+        self.centroids = merged;
+    }
+
+    /// The fee rate at `percentile` (in [0, 1]), interpolated between the centers of the
+    /// centroids around it.  None if the sketch is empty.
+    pub fn quantile(&self, percentile: f64) -> Option<f64> {
+        let first = self.centroids.first()?;
+        let target = percentile.max(0f64).min(1f64) * self.total_weight();
+
+        let mut cumulative_weight = 0f64;
+        let mut previous = (first.weight / 2f64, first.mean);
+        for centroid in self.centroids.iter() {
+            let center = cumulative_weight + centroid.weight / 2f64;
+            if target <= center {
+                let (previous_center, previous_mean) = previous;
+                if center <= previous_center {
+                    return Some(centroid.mean);
+                }
+                return Some(
+                    previous_mean
+                        + (target - previous_center) / (center - previous_center)
+                            * (centroid.mean - previous_mean),
+                );
+            }
+            previous = (center, centroid.mean);
+            cumulative_weight += centroid.weight;
+        }
+        Some(previous.1)
+    }
+}
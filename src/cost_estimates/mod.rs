@@ -6,16 +6,18 @@ use std::path::Path;
 use std::{error::Error, fmt::Display};
 
 use crate::chainstate::stacks::events::{StacksTransactionReceipt, TransactionOrigin};
-use crate::chainstate::stacks::{StacksBlock, TransactionPayload};
+use crate::chainstate::stacks::{StacksBlock, TransactionPayload, MAX_BLOCK_LEN};
 use clarity::vm::costs::ExecutionCost;
 use rusqlite::Error as SqliteError;
 
 use crate::burnchains::Txid;
 use crate::chainstate::stacks::db::StacksEpochReceipt;
 
+pub mod accuracy;
 pub mod fee_medians;
 pub mod fee_rate_fuzzer;
 pub mod fee_scalar;
+pub mod fee_sketch;
 pub mod metrics;
 pub mod pessimistic;
 
@@ -41,7 +43,9 @@ pub use self::pessimistic::PessimisticEstimator;
 ///  whereas miners only need to use a `CostEstimator`
 pub trait FeeEstimator {
     /// This method is invoked by the `stacks-node` to update the fee estimator with a new
-    ///  block receipt.
+    ///  block receipt. Implementations should account for how full the block was (see
+    ///  `block_fullness()`): space left unused in a block could have been had at the minimum
+    ///  fee rate, so mostly empty blocks should pull the estimates down toward it.
     fn notify_block(
         &mut self,
         receipt: &StacksEpochReceipt,
@@ -116,6 +120,42 @@ pub fn estimate_fee_rate<CE: CostEstimator + ?Sized, CM: CostMetric + ?Sized>(
     Ok(tx.get_tx_fee() as f64 / metric_estimate as f64)
 }
 
+/// How full a block was, in [0, 1]: the largest proportion of any dimension of `block_limit`
+///  consumed by the block together with the parent microblocks it confirmed, or of the maximum
+///  block length consumed by the anchored block.
+pub fn block_fullness(receipt: &StacksEpochReceipt, block_limit: &ExecutionCost) -> f64 {
+    let anchored = &receipt.anchored_block_cost;
+    let microblocks = &receipt.parent_microblocks_cost;
+    let proportion = |used: u64, limit: u64| 1f64.min(used as f64 / 1f64.max(limit as f64));
+    [
+        proportion(
+            anchored.runtime.saturating_add(microblocks.runtime),
+            block_limit.runtime,
+        ),
+        proportion(
+            anchored.read_count.saturating_add(microblocks.read_count),
+            block_limit.read_count,
+        ),
+        proportion(
+            anchored.write_count.saturating_add(microblocks.write_count),
+            block_limit.write_count,
+        ),
+        proportion(
+            anchored.read_length.saturating_add(microblocks.read_length),
+            block_limit.read_length,
+        ),
+        proportion(
+            anchored
+                .write_length
+                .saturating_add(microblocks.write_length),
+            block_limit.write_length,
+        ),
+        proportion(receipt.header.anchored_block_size, MAX_BLOCK_LEN as u64),
+    ]
+    .iter()
+    .fold(0f64, |fullness, dim| fullness.max(*dim))
+}
+
 /// This trait is for implementation of *execution cost* estimation. CostEstimators
 ///  provide the estimated `ExecutionCost` for a given `TransactionPayload`.
 ///
@@ -0,0 +1,242 @@
+use clarity::vm::costs::ExecutionCost;
+
+use crate::chainstate::stacks::db::StacksEpochReceipt;
+use crate::chainstate::stacks::events::{StacksTransactionReceipt, TransactionOrigin};
+use crate::chainstate::stacks::{
+    CoinbasePayload, StacksTransaction, TransactionAuth, TransactionContractCall,
+    TransactionPayload, TransactionSpendingCondition, TransactionVersion,
+};
+use crate::core::StacksEpochId;
+use crate::cost_estimates::accuracy::{
+    CostEstimatorAccuracy, EstimatorErrorStats, FeeEstimatorAccuracy,
+};
+use crate::cost_estimates::metrics::{CostMetric, ProportionalDotProduct};
+use crate::cost_estimates::tests::common::make_block_receipt;
+use crate::cost_estimates::{CostEstimator, EstimatorError, FeeEstimator, FeeRateEstimate};
+use crate::types::chainstate::StacksAddress;
+use crate::util::hash::Hash160;
+use crate::vm::Value;
+
+const BLOCK_LIMIT: ExecutionCost = ExecutionCost {
+    write_length: 100,
+    write_count: 100,
+    read_length: 100,
+    read_count: 100,
+    runtime: 100,
+};
+
+const TENTH_OPERATION_COST: ExecutionCost = ExecutionCost {
+    write_length: 0,
+    write_count: 0,
+    read_length: 0,
+    read_count: 0,
+    runtime: 10,
+};
+
+fn make_dummy_coinbase_receipt() -> StacksTransactionReceipt {
+    let tx = StacksTransaction::new(
+        TransactionVersion::Mainnet,
+        TransactionAuth::Standard(TransactionSpendingCondition::new_initial_sighash()),
+        TransactionPayload::Coinbase(CoinbasePayload([0; 32])),
+    );
+    StacksTransactionReceipt::from_coinbase(tx)
+}
+
+fn make_dummy_cc_receipt(fee: u64, execution_cost: &ExecutionCost) -> StacksTransactionReceipt {
+    let mut tx = StacksTransaction::new(
+        TransactionVersion::Mainnet,
+        TransactionAuth::Standard(TransactionSpendingCondition::new_initial_sighash()),
+        TransactionPayload::ContractCall(TransactionContractCall {
+            address: StacksAddress::new(0, Hash160([0; 20])),
+            contract_name: "cc-dummy".into(),
+            function_name: "func-name".into(),
+            function_args: vec![],
+        }),
+    );
+    tx.set_tx_fee(fee);
+    StacksTransactionReceipt::from_contract_call(
+        tx,
+        vec![],
+        Value::okay(Value::Bool(true)).unwrap(),
+        0,
+        execution_cost.clone(),
+    )
+}
+
+/// Estimates the same cost for every transaction.
+struct ConstantCostEstimator {
+    cost: ExecutionCost,
+}
+
+impl CostEstimator for ConstantCostEstimator {
+    fn notify_event(
+        &mut self,
+        _tx: &TransactionPayload,
+        _actual_cost: &ExecutionCost,
+        _block_limit: &ExecutionCost,
+        _evaluated_epoch: &StacksEpochId,
+    ) -> Result<(), EstimatorError> {
+        Ok(())
+    }
+
+    fn estimate_cost(
+        &self,
+        _tx: &TransactionPayload,
+        _evaluated_epoch: &StacksEpochId,
+    ) -> Result<ExecutionCost, EstimatorError> {
+        Ok(self.cost.clone())
+    }
+}
+
+/// Estimates the same middle fee rate for every block.
+struct ConstantFeeEstimator {
+    middle: f64,
+}
+
+impl FeeEstimator for ConstantFeeEstimator {
+    fn notify_block(
+        &mut self,
+        _receipt: &StacksEpochReceipt,
+        _block_limit: &ExecutionCost,
+    ) -> Result<(), EstimatorError> {
+        Ok(())
+    }
+
+    fn get_rate_estimates(&self) -> Result<FeeRateEstimate, EstimatorError> {
+        Ok(FeeRateEstimate {
+            high: self.middle * 2f64,
+            middle: self.middle,
+            low: self.middle / 2f64,
+        })
+    }
+}
+
+#[test]
+fn test_error_stats_window() {
+    let mut stats = EstimatorErrorStats::new(2);
+    assert_eq!(stats.samples(), 0);
+    assert_eq!(stats.mean_absolute_error(), None);
+    assert_eq!(stats.mean_error(), None);
+
+    stats.record(150f64, 100f64);
+    stats.record(50f64, 100f64);
+    assert_eq!(stats.samples(), 2);
+    assert_eq!(stats.mean_absolute_error(), Some(0.5));
+    assert_eq!(stats.mean_error(), Some(0.0));
+
+    // the oldest error leaves the window
+    stats.record(300f64, 100f64);
+    assert_eq!(stats.samples(), 2);
+    assert_eq!(stats.mean_absolute_error(), Some(1.25));
+    assert_eq!(stats.mean_error(), Some(0.75));
+}
+
+#[test]
+fn test_cost_estimator_accuracy() {
+    let metric = ProportionalDotProduct::new(10_000);
+    let mut estimator = CostEstimatorAccuracy::new(
+        vec![
+            (
+                "exact".to_string(),
+                Box::new(ConstantCostEstimator {
+                    cost: TENTH_OPERATION_COST,
+                }) as Box<dyn CostEstimator>,
+            ),
+            (
+                "empty".to_string(),
+                Box::new(ConstantCostEstimator {
+                    cost: ExecutionCost::zero(),
+                }) as Box<dyn CostEstimator>,
+            ),
+        ],
+        metric,
+        10,
+    );
+
+    let receipts = vec![
+        make_dummy_cc_receipt(10, &TENTH_OPERATION_COST),
+        make_dummy_cc_receipt(10, &TENTH_OPERATION_COST),
+    ];
+    let tx = match receipts[0].transaction {
+        TransactionOrigin::Stacks(ref tx) => tx.clone(),
+        TransactionOrigin::Burn(_) => unreachable!(),
+    };
+    estimator.notify_block(&receipts, &BLOCK_LIMIT, &StacksEpochId::Epoch2_05);
+
+    // estimates come from the first estimator
+    assert_eq!(
+        estimator
+            .estimate_cost(&tx.payload, &StacksEpochId::Epoch2_05)
+            .unwrap(),
+        TENTH_OPERATION_COST
+    );
+
+    let accuracy = estimator.get_accuracy();
+    assert_eq!(accuracy.len(), 2);
+    assert_eq!(accuracy[0].0, "exact");
+    assert_eq!(accuracy[0].1.samples(), 2);
+    assert_eq!(accuracy[0].1.mean_absolute_error(), Some(0.0));
+
+    // without the runtime, only the transaction length is estimated
+    let metric = ProportionalDotProduct::new(10_000);
+    let tx_len = tx.tx_len();
+    let realized = metric.from_cost_and_len(&TENTH_OPERATION_COST, &BLOCK_LIMIT, tx_len) as f64;
+    let predicted = metric.from_cost_and_len(&ExecutionCost::zero(), &BLOCK_LIMIT, tx_len) as f64;
+    assert_eq!(accuracy[1].0, "empty");
+    assert_eq!(accuracy[1].1.samples(), 2);
+    assert_eq!(
+        accuracy[1].1.mean_error(),
+        Some((predicted - realized) / realized)
+    );
+}
+
+#[test]
+fn test_fee_estimator_accuracy() {
+    let metric = ProportionalDotProduct::new(10_000);
+    let mut estimator = FeeEstimatorAccuracy::new(
+        vec![
+            (
+                "low".to_string(),
+                Box::new(ConstantFeeEstimator { middle: 50f64 }) as Box<dyn FeeEstimator>,
+            ),
+            (
+                "high".to_string(),
+                Box::new(ConstantFeeEstimator { middle: 100f64 }) as Box<dyn FeeEstimator>,
+            ),
+        ],
+        metric,
+        10,
+    );
+
+    // a block without fee-paying transactions scores nothing
+    estimator
+        .notify_block(
+            &make_block_receipt(vec![make_dummy_coinbase_receipt()]),
+            &BLOCK_LIMIT,
+        )
+        .unwrap();
+    assert_eq!(estimator.get_accuracy()[0].1.samples(), 0);
+
+    // the scalar cost of a contract call costing `TENTH_OPERATION_COST` is 1164, so this one
+    // pays a fee rate of 50
+    estimator
+        .notify_block(
+            &make_block_receipt(vec![
+                make_dummy_coinbase_receipt(),
+                make_dummy_cc_receipt(50 * 1164, &TENTH_OPERATION_COST),
+            ]),
+            &BLOCK_LIMIT,
+        )
+        .unwrap();
+
+    let accuracy = estimator.get_accuracy();
+    assert_eq!(accuracy[0].0, "low");
+    assert_eq!(accuracy[0].1.samples(), 1);
+    assert_eq!(accuracy[0].1.mean_error(), Some(0.0));
+    assert_eq!(accuracy[1].0, "high");
+    assert_eq!(accuracy[1].1.samples(), 1);
+    assert_eq!(accuracy[1].1.mean_error(), Some(1.0));
+
+    // estimates come from the first estimator
+    assert_eq!(estimator.get_rate_estimates().unwrap().middle, 50f64);
+}
@@ -22,7 +22,8 @@ use crate::chainstate::stacks::{
     TransactionContractCall, TransactionPayload, TransactionSpendingCondition, TransactionVersion,
 };
 use crate::core::StacksEpochId;
-use crate::cost_estimates::fee_scalar::ScalarFeeRateEstimator;
+use crate::cost_estimates::fee_scalar::{ScalarFeeRateEstimator, DEFAULT_WINDOW_SIZE};
+use crate::cost_estimates::fee_sketch::FeeRateSketch;
 use crate::cost_estimates::FeeRateEstimate;
 use crate::types::chainstate::StacksAddress;
 use crate::vm::types::{PrincipalData, StandardPrincipalData};
@@ -123,6 +124,13 @@ fn make_dummy_cc_tx(fee: u64) -> StacksTransactionReceipt {
     )
 }
 
+/// Make a block receipt from `tx_receipts` that fills a block limit of `ExecutionCost::max_value()`.
+fn make_full_block_receipt(tx_receipts: Vec<StacksTransactionReceipt>) -> StacksEpochReceipt {
+    let mut receipt = make_block_receipt(tx_receipts);
+    receipt.anchored_block_cost = ExecutionCost::max_value();
+    receipt
+}
+
 #[test]
 fn test_fee_estimator() {
     let metric = TestCostMetric;
@@ -136,37 +144,8 @@ fn test_fee_estimator() {
         "Empty rate estimator should return no estimate available"
     );
 
-    let empty_block_receipt = make_block_receipt(vec![]);
     let block_limit = ExecutionCost::max_value();
-    estimator
-        .notify_block(&empty_block_receipt, &block_limit)
-        .expect("Should be able to process an empty block");
-
-    assert_eq!(
-        estimator
-            .get_rate_estimates()
-            .expect_err("Empty rate estimator should error."),
-        EstimatorError::NoEstimateAvailable,
-        "Empty block should not update the estimator"
-    );
-
-    let coinbase_only_receipt = make_block_receipt(vec![StacksTransactionReceipt::from_coinbase(
-        make_dummy_coinbase_tx(),
-    )]);
-
-    estimator
-        .notify_block(&coinbase_only_receipt, &block_limit)
-        .expect("Should be able to process an empty block");
-
-    assert_eq!(
-        estimator
-            .get_rate_estimates()
-            .expect_err("Empty rate estimator should error."),
-        EstimatorError::NoEstimateAvailable,
-        "Coinbase-only block should not update the estimator"
-    );
-
-    let single_tx_receipt = make_block_receipt(vec![
+    let single_tx_receipt = make_full_block_receipt(vec![
         StacksTransactionReceipt::from_coinbase(make_dummy_coinbase_tx()),
         make_dummy_cc_tx(1),
     ]);
@@ -186,7 +165,7 @@ fn test_fee_estimator() {
         }
     );
 
-    let double_tx_receipt = make_block_receipt(vec![
+    let double_tx_receipt = make_full_block_receipt(vec![
         StacksTransactionReceipt::from_coinbase(make_dummy_coinbase_tx()),
         make_dummy_cc_tx(1),
         make_dummy_transfer_tx(10),
@@ -196,105 +175,206 @@ fn test_fee_estimator() {
         .notify_block(&double_tx_receipt, &block_limit)
         .expect("Should be able to process block receipt");
 
-    // estimate should increase for "high" and "middle":
-    // 10 * 1/2 + 1 * 1/2 = 5.5
-    assert_eq!(
+    // the window holds the single tx block, weighing 0.9, and the double tx block, weighing 1
+    // (0.5 for each transaction): 74% of the weight is at a fee rate of 1, centered on the 37th
+    // percentile, and the rest at 10, centered on the 87th percentile
+    let estimate = estimator
+        .get_rate_estimates()
+        .expect("Should be able to create estimate now");
+    assert_eq!(estimate.low, 1f64);
+    assert!(estimate.middle > 1f64 && estimate.middle < 10f64);
+    assert_eq!(estimate.high, 10f64);
+    assert_eq!(estimator.get_rate_percentile(0.3).unwrap(), 1f64);
+    assert_eq!(estimator.get_rate_percentile(1.0).unwrap(), 10f64);
+
+    // after as many blocks as the window holds, only the double tx block is left
+    for _ in 0..DEFAULT_WINDOW_SIZE {
         estimator
-            .get_rate_estimates()
-            .expect("Should be able to create estimate now"),
-        FeeRateEstimate {
-            high: 5.5f64,
-            middle: 5.5f64,
-            low: 1f64
-        }
-    );
+            .notify_block(&double_tx_receipt, &block_limit)
+            .expect("Should be able to process block receipt");
+    }
+    let estimate = estimator
+        .get_rate_estimates()
+        .expect("Should be able to create estimate now");
+    assert_eq!(estimate.low, 1f64);
+    assert!((estimate.high - 10f64).abs() < 1e-9);
+    assert!(estimate.middle > 1f64 && estimate.middle < 10f64);
+}
+
+/// Fill the window with blocks of fee rates from 1 to 100, then check that one block of
+/// anomalous fee rates barely moves the estimates, and leaves the window in time.
+#[test]
+fn test_fee_estimator_anomalous_block() {
+    let metric = TestCostMetric;
+    let mut estimator = instantiate_test_db(metric);
+    let block_limit = ExecutionCost::max_value();
+
+    let mut receipts: Vec<_> = (1..=100).map(|i| make_dummy_cc_tx(i)).collect();
+    let mut rng = rand::thread_rng();
+    receipts.shuffle(&mut rng);
+    let normal_block = make_full_block_receipt(receipts);
+    for _ in 0..DEFAULT_WINDOW_SIZE {
+        estimator
+            .notify_block(&normal_block, &block_limit)
+            .expect("Should be able to process block receipt");
+    }
+
+    let estimate = estimator
+        .get_rate_estimates()
+        .expect("Should be able to create estimate now");
+    assert!((estimate.low - 5.5f64).abs() < 1f64, "{:?}", &estimate);
+    assert!((estimate.middle - 50.5f64).abs() < 1f64, "{:?}", &estimate);
+    assert!((estimate.high - 95.5f64).abs() < 1f64, "{:?}", &estimate);
 
-    // estimate should increase for "high" and "middle":
-    // new value: 10 * 1/2 + 5.5 * 1/2 = 7.75
+    let anomalous_block =
+        make_full_block_receipt((0..100).map(|_| make_dummy_cc_tx(100_000)).collect());
     estimator
-        .notify_block(&double_tx_receipt, &block_limit)
+        .notify_block(&anomalous_block, &block_limit)
         .expect("Should be able to process block receipt");
-    assert_eq!(
+
+    // the anomalous block is only the top ~11% of the window
+    let estimate = estimator
+        .get_rate_estimates()
+        .expect("Should be able to create estimate now");
+    assert!(estimate.middle < 60f64, "{:?}", &estimate);
+    assert_eq!(estimator.get_rate_percentile(0.99).unwrap(), 100_000f64);
+
+    for _ in 0..DEFAULT_WINDOW_SIZE {
         estimator
-            .get_rate_estimates()
-            .expect("Should be able to create estimate now"),
-        FeeRateEstimate {
-            high: 7.75f64,
-            middle: 7.75f64,
-            low: 1f64
-        }
-    );
+            .notify_block(&normal_block, &block_limit)
+            .expect("Should be able to process block receipt");
+    }
+    assert!(estimator.get_rate_percentile(1.0).unwrap() <= 100f64);
+}
+
+/// Check that empty blocks are taken for blocks paying the minimum fee rate, and that the
+/// estimates fall toward it as blocks empty out.
+#[test]
+fn test_fee_estimator_block_fullness() {
+    let metric = TestCostMetric;
+    let mut estimator = instantiate_test_db(metric);
+    let block_limit = ExecutionCost {
+        write_length: 100,
+        write_count: 100,
+        read_length: 100,
+        read_count: 100,
+        runtime: 100,
+    };
+    let minimum_estimate = FeeRateEstimate {
+        high: 1f64,
+        middle: 1f64,
+        low: 1f64,
+    };
 
-    // estimate should increase for "high" and "middle":
-    // new value: 10 * 1/2 + 7.75 * 1/2 = 8.875
     estimator
-        .notify_block(&double_tx_receipt, &block_limit)
-        .expect("Should be able to process block receipt");
+        .notify_block(&make_block_receipt(vec![]), &block_limit)
+        .expect("Should be able to process an empty block");
     assert_eq!(
         estimator
             .get_rate_estimates()
-            .expect("Should be able to create estimate now"),
-        FeeRateEstimate {
-            high: 8.875f64,
-            middle: 8.875f64,
-            low: 1f64
-        }
+            .expect("Empty block should update the estimator"),
+        minimum_estimate
     );
 
-    // estimate should increase for "high" and "middle":
-    // new value: 10 * 1/2 + 8.875 * 1/2 = 9.4375
+    let coinbase_only_receipt = make_block_receipt(vec![StacksTransactionReceipt::from_coinbase(
+        make_dummy_coinbase_tx(),
+    )]);
     estimator
-        .notify_block(&double_tx_receipt, &block_limit)
-        .expect("Should be able to process block receipt");
+        .notify_block(&coinbase_only_receipt, &block_limit)
+        .expect("Should be able to process a coinbase-only block");
     assert_eq!(
         estimator
             .get_rate_estimates()
-            .expect("Should be able to create estimate now"),
-        FeeRateEstimate {
-            high: 9.4375f64,
-            middle: 9.4375f64,
-            low: 1f64
-        }
+            .expect("Coinbase-only block should update the estimator"),
+        minimum_estimate
     );
 
-    // estimate should increase for "high" and "middle":
-    // new value: 10 * 1/2 + 9.4375 * 1/2 = 9
-    estimator
-        .notify_block(&double_tx_receipt, &block_limit)
-        .expect("Should be able to process block receipt");
+    // full blocks paying a fee rate of 100, in any one dimension
+    let mut full_block = make_block_receipt(vec![make_dummy_cc_tx(100)]);
+    full_block.anchored_block_cost.runtime = 60;
+    full_block.parent_microblocks_cost.runtime = 40;
+    full_block.anchored_block_cost.read_count = 10;
+    for _ in 0..DEFAULT_WINDOW_SIZE {
+        estimator
+            .notify_block(&full_block, &block_limit)
+            .expect("Should be able to process block receipt");
+    }
     assert_eq!(
         estimator
             .get_rate_estimates()
             .expect("Should be able to create estimate now"),
         FeeRateEstimate {
-            high: 9.71875f64,
-            middle: 9.71875f64,
-            low: 1f64
+            high: 100f64,
+            middle: 100f64,
+            low: 100f64,
         }
     );
 
-    // make a large block receipt, and expect:
-    //  measured high = 950, middle = 500, low = 50
-    //  new high: 950/2 + 9.71875/2 = 479.859375
-    //  new middle: 500/2 + 9.71875/2 = 254.859375
-    //  new low: 50/2 + 1/2 = 25.5
-
-    let mut receipts: Vec<_> = (0..100).map(|i| make_dummy_cc_tx(i * 10)).collect();
-    let mut rng = rand::thread_rng();
-    receipts.shuffle(&mut rng);
-
-    estimator
-        .notify_block(&make_block_receipt(receipts), &block_limit)
-        .expect("Should be able to process block receipt");
-
+    // blocks filled to a tenth of their read count only pay 100 for that tenth
+    let mut sparse_block = make_block_receipt(vec![make_dummy_cc_tx(100)]);
+    sparse_block.anchored_block_cost.read_count = 10;
+    for _ in 0..DEFAULT_WINDOW_SIZE {
+        estimator
+            .notify_block(&sparse_block, &block_limit)
+            .expect("Should be able to process block receipt");
+    }
+    let estimate = estimator
+        .get_rate_estimates()
+        .expect("Should be able to create estimate now");
+    assert_eq!(estimate.low, 1f64);
+    assert!(estimate.middle < 20f64, "{:?}", &estimate);
+    assert!(estimate.high > 1f64 && estimate.high <= 100f64);
+
+    // and a window of empty blocks brings every estimate down to the minimum
+    for _ in 0..DEFAULT_WINDOW_SIZE {
+        estimator
+            .notify_block(&coinbase_only_receipt, &block_limit)
+            .expect("Should be able to process a coinbase-only block");
+    }
     assert_eq!(
         estimator
             .get_rate_estimates()
             .expect("Should be able to create estimate now"),
-        FeeRateEstimate {
-            high: 479.859375f64,
-            middle: 254.859375f64,
-            low: 25.5f64
-        }
+        minimum_estimate
     );
 }
+
+#[test]
+fn test_fee_rate_sketch() {
+    let empty = FeeRateSketch::from_weighted_values(&[], 100f64);
+    assert!(empty.is_empty());
+    assert_eq!(empty.quantile(0.5), None);
+
+    // 10,000 distinct fee rates fit in a small sketch that still has accurate percentiles
+    let values: Vec<_> = (1..=10_000).map(|i| (i as f64, 1f64)).collect();
+    let sketch = FeeRateSketch::from_weighted_values(&values, 100f64);
+    assert!(
+        sketch.centroids().len() <= 400,
+        "{}",
+        sketch.centroids().len()
+    );
+    assert_eq!(sketch.total_weight(), 10_000f64);
+    assert_eq!(sketch.quantile(0.0), Some(1f64));
+    assert_eq!(sketch.quantile(1.0), Some(10_000f64));
+    for percentile in [0.01, 0.05, 0.25, 0.5, 0.75, 0.95, 0.99].iter() {
+        let expected = percentile * 10_000f64;
+        let actual = sketch.quantile(*percentile).unwrap();
+        assert!(
+            (actual - expected).abs() / expected < 0.01,
+            "percentile {}: expected {}, got {}",
+            percentile,
+            expected,
+            actual
+        );
+    }
+
+    // merging scales each sketch's weights
+    let low = FeeRateSketch::from_weighted_values(&[(1f64, 1f64)], 100f64);
+    let high = FeeRateSketch::from_weighted_values(&[(10f64, 1f64)], 100f64);
+    let merged = FeeRateSketch::merge(vec![(&low, 3f64), (&high, 1f64)], 100f64);
+    assert_eq!(merged.total_weight(), 4f64);
+    assert_eq!(merged.quantile(0.25), Some(1f64));
+    assert_eq!(merged.quantile(0.5), Some(3.25f64));
+    assert_eq!(merged.quantile(1.0), Some(10f64));
+    assert_eq!(merged.normalized().total_weight(), 1f64);
+}
@@ -1,5 +1,6 @@
 use crate::cost_estimates::FeeRateEstimate;
 
+pub mod accuracy;
 pub mod common;
 pub mod cost_estimators;
 pub mod fee_medians;
@@ -24,6 +24,8 @@ pub enum SignalId {
     CtrlC = 0x00,
     Termination = 0x01,
     Bus = 0x02,
+    /// SIGUSR2: dump diagnostics, without terminating
+    DumpDiagnostics = 0x03,
     Other = 0xff,
 }
 
@@ -33,6 +35,7 @@ impl std::fmt::Display for SignalId {
             SignalId::CtrlC => write!(f, "CtrlC"),
             SignalId::Termination => write!(f, "Termination"),
             SignalId::Bus => write!(f, "Bus"),
+            SignalId::DumpDiagnostics => write!(f, "DumpDiagnostics"),
             SignalId::Other => write!(f, "Other"),
         }
     }
@@ -46,7 +49,7 @@ static INIT: AtomicBool = AtomicBool::new(false);
 /// typically at the start of your program.
 ///
 /// # Warning
-/// On Unix, any existing `SIGINT`, `SIGTERM`, `SIGHUP`, `SIGBUS`, or `SA_SIGINFO`
+/// On Unix, any existing `SIGINT`, `SIGTERM`, `SIGHUP`, `SIGBUS`, `SIGUSR2`, or `SA_SIGINFO`
 /// posix signal handlers will be overwritten. On Windows, multiple handler routines are allowed,
 /// but they are called on a last-registered, first-called basis until the signal is handled.
 ///
@@ -30,6 +30,7 @@ impl SignalId {
             }
             x if x == Signal::SIGINT as nix::libc::c_int => SignalId::CtrlC,
             x if x == Signal::SIGBUS as nix::libc::c_int => SignalId::Bus,
+            x if x == Signal::SIGUSR2 as nix::libc::c_int => SignalId::DumpDiagnostics,
             _ => SignalId::Other,
         }
     }
@@ -39,6 +40,7 @@ impl SignalId {
             x if x == SignalId::CtrlC as u8 => SignalId::CtrlC,
             x if x == SignalId::Termination as u8 => SignalId::Termination,
             x if x == SignalId::Bus as u8 => SignalId::Bus,
+            x if x == SignalId::DumpDiagnostics as u8 => SignalId::DumpDiagnostics,
             _ => SignalId::Other,
         }
     }
@@ -148,12 +150,24 @@ pub unsafe fn init_os_handler() -> Result<(), Error> {
         }
     };
 
-    match signal::sigaction(signal::Signal::SIGHUP, &new_action) {
+    let sighup_old = match signal::sigaction(signal::Signal::SIGHUP, &new_action) {
+        Ok(old) => old,
+        Err(e) => {
+            signal::sigaction(signal::Signal::SIGINT, &sigint_old).unwrap();
+            signal::sigaction(signal::Signal::SIGTERM, &sigterm_old).unwrap();
+            signal::sigaction(signal::Signal::SIGBUS, &sigbus_old).unwrap();
+            return Err(close_pipe(e));
+        }
+    };
+
+    // SIGUSR2 asks for a diagnostic dump
+    match signal::sigaction(signal::Signal::SIGUSR2, &new_action) {
         Ok(_) => {}
         Err(e) => {
             signal::sigaction(signal::Signal::SIGINT, &sigint_old).unwrap();
             signal::sigaction(signal::Signal::SIGTERM, &sigterm_old).unwrap();
             signal::sigaction(signal::Signal::SIGBUS, &sigbus_old).unwrap();
+            signal::sigaction(signal::Signal::SIGHUP, &sighup_old).unwrap();
             return Err(close_pipe(e));
         }
     }
@@ -80,6 +80,7 @@ use blockstack_lib::util::hash::{hex_bytes, to_hex};
 use blockstack_lib::util::log;
 use blockstack_lib::util::retry::LogReader;
 use blockstack_lib::util::sleep_ms;
+use blockstack_lib::util_lib::car;
 use blockstack_lib::util_lib::strings::UrlString;
 use blockstack_lib::{
     burnchains::{db::BurnchainBlockData, PoxConstants},
@@ -818,6 +819,127 @@ simulating a miner.
         process::exit(0);
     }
 
+    if argv[1] == "export-car" {
+        if argv.len() < 6 {
+            eprintln!(
+                "Usage: {} export-car <working-dir> <start-height> <end-height> <output.car>
+
+Given a <working-dir>, write the canonical Stacks chain's blocks from <start-height> to
+<end-height>, and the microblocks they confirm, to <output.car> as a CARv1 archive of
+content-addressed IPLD blocks. The archive's root is a manifest listing one index node per
+block, which links the block and its microblocks and records the block's consensus hash.
+",
+                argv[0]
+            );
+            process::exit(1);
+        }
+
+        let sort_db_path = format!("{}/mainnet/burnchain/sortition", &argv[2]);
+        let chain_state_path = format!("{}/mainnet/chainstate/", &argv[2]);
+        let start_height: u64 = argv[3].parse().expect("Could not parse start-height");
+        let end_height: u64 = argv[4].parse().expect("Could not parse end-height");
+        let output_path = &argv[5];
+
+        let sort_db = SortitionDB::open(&sort_db_path, false)
+            .expect(&format!("Failed to open {}", &sort_db_path));
+        let (chain_state, _) =
+            StacksChainState::open(true, CHAIN_ID_MAINNET, &chain_state_path, None)
+                .expect("Failed to open stacks chain state");
+
+        let (tip_consensus_hash, tip_block_hash) =
+            SortitionDB::get_canonical_stacks_chain_tip_hash(sort_db.conn())
+                .expect("Failed to get canonical Stacks chain tip");
+        let tip_header = StacksChainState::get_anchored_block_header_info(
+            chain_state.db(),
+            &tip_consensus_hash,
+            &tip_block_hash,
+        )
+        .expect("Failed to load chain tip header info")
+        .expect("Failed to load chain tip header info");
+        if tip_header.stacks_block_height < end_height {
+            eprintln!(
+                "Chain tip is at height {}, below end-height {}",
+                tip_header.stacks_block_height, end_height
+            );
+            process::exit(1);
+        }
+        let end_header =
+            StacksChainState::get_ancestors_headers(chain_state.db(), tip_header, end_height)
+                .expect("Failed to load ancestor headers")
+                .pop()
+                .expect("No block at end-height");
+
+        // the CAR header names the root, which is only known once every block is written
+        let body_path = format!("{}.body", output_path);
+        let root = {
+            let mut body = io::BufWriter::new(
+                fs::File::create(&body_path).expect(&format!("Failed to create {}", &body_path)),
+            );
+            let root = chain_state
+                .export_car_blocks(end_header, start_height, &mut body)
+                .expect("Failed to export blocks");
+            body.flush().expect("Failed to write archive");
+            root
+        };
+
+        let mut output = io::BufWriter::new(
+            fs::File::create(output_path).expect(&format!("Failed to create {}", output_path)),
+        );
+        car::write_car_header(&mut output, &[root.clone()]).expect("Failed to write archive");
+        let mut body = fs::File::open(&body_path).expect("Failed to reopen archive");
+        io::copy(&mut body, &mut output).expect("Failed to write archive");
+        output.flush().expect("Failed to write archive");
+        fs::remove_file(&body_path).expect(&format!("Failed to remove {}", &body_path));
+
+        println!("{}", &root);
+        return;
+    }
+
+    if argv[1] == "get-consistency-hash" {
+        if argv.len() < 4 {
+            eprintln!(
+                "Usage: {} get-consistency-hash <working-dir> <height>
+
+Given a <working-dir>, print the consistency hash of the chain state at <height> in the canonical
+Stacks chain, along with the chain metadata it digests. Two nodes that print the same hash at the
+same height agree on the chain state up to that height.
+",
+                argv[0]
+            );
+            process::exit(1);
+        }
+
+        let sort_db_path = format!("{}/mainnet/burnchain/sortition", &argv[2]);
+        let chain_state_path = format!("{}/mainnet/chainstate/", &argv[2]);
+        let height: u64 = argv[3].parse().expect("Could not parse height");
+
+        let sort_db = SortitionDB::open(&sort_db_path, false)
+            .expect(&format!("Failed to open {}", &sort_db_path));
+        let (chain_state, _) =
+            StacksChainState::open(true, CHAIN_ID_MAINNET, &chain_state_path, None)
+                .expect("Failed to open stacks chain state");
+
+        match chain_state
+            .get_canonical_consistency_hash(&sort_db, height)
+            .expect("Failed to compute consistency hash")
+        {
+            Some(consistency_hash) => {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&consistency_hash).unwrap()
+                );
+            }
+            None => {
+                eprintln!(
+                    "No block at height {} in the canonical Stacks chain",
+                    height
+                );
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
     if argv[1] == "decode-microblocks" {
         if argv.len() < 3 {
             eprintln!(
@@ -1069,6 +1191,9 @@ simulating a miner.
             first_block: 0,
             magic_bytes: BLOCKSTACK_MAGIC_MAINNET.clone(),
             epochs: None,
+            socks_proxy: None,
+            download_workers: 1,
+            parse_workers: 1,
         };
 
         let indexer = BitcoinIndexer::new(
@@ -14,11 +14,13 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::{HashMap, VecDeque};
 use std::{fmt, fs, path::PathBuf};
 
 use rusqlite::{OpenFlags, OptionalExtension};
 
 use crate::burnchains::BurnchainSigner;
+use crate::types::chainstate::{BlockHeaderHash, BurnchainHeaderHash, ConsensusHash};
 use crate::util_lib::db::sqlite_open;
 use crate::util_lib::db::Error as DatabaseError;
 use crate::{
@@ -29,6 +31,8 @@ use crate::{
     util_lib::db::{tx_busy_handler, DBConn},
 };
 use clarity::vm::costs::ExecutionCost;
+use stacks_common::util::get_epoch_time_ms;
+use stacks_common::util::hash::Sha512Trunc256Sum;
 use stacks_common::util::uint::{Uint256, Uint512};
 use std::convert::TryInto;
 use std::error::Error;
@@ -43,6 +47,25 @@ lazy_static! {
     static ref GLOBAL_BURNCHAIN_SIGNER: Mutex<Option<BurnchainSigner>> = Mutex::new(None);
 }
 
+lazy_static! {
+    static ref BLOCK_PROPAGATION: Mutex<BlockPropagationTracker> =
+        Mutex::new(BlockPropagationTracker::new(MAX_TRACKED_SORTITIONS));
+    static ref TENURES: Mutex<TenureTracker> = Mutex::new(TenureTracker::new(MAX_TRACKED_TENURES));
+    static ref SYNC_PROGRESS: Mutex<SyncProgressTracker> = Mutex::new(SyncProgressTracker::new());
+}
+
+/// How many of the most recent sortitions to keep block propagation times for
+const MAX_TRACKED_SORTITIONS: usize = 64;
+
+/// How many of the most recent burn blocks to keep this miner's tenure times for
+const MAX_TRACKED_TENURES: usize = 64;
+
+/// How far back to measure sync rates over
+const SYNC_RATE_WINDOW_MS: u64 = 300_000;
+
+/// Most heights to keep for measuring a sync rate
+const MAX_SYNC_RATE_SAMPLES: usize = 4096;
+
 pub fn increment_rpc_calls_counter() {
     #[cfg(feature = "monitoring_prom")]
     prometheus::RPC_CALL_COUNTER.inc();
@@ -269,6 +292,57 @@ pub fn update_burnchain_height(value: i64) {
     prometheus::BURNCHAIN_HEIGHT_GAUGE.set(value);
 }
 
+/// Record a burnchain reorg that orphaned `depth` burn blocks.
+#[allow(unused_variables)]
+pub fn log_burnchain_reorg(depth: u64, deep: bool) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        prometheus::BURNCHAIN_REORGS_COUNTER
+            .with_label_values(&[if deep { "true" } else { "false" }])
+            .inc();
+        prometheus::LAST_BURNCHAIN_REORG_DEPTH_GAUGE.set(depth as i64);
+    }
+}
+
+/// Record whether the node has stopped processing blocks of `chain` ("burnchain" or "stacks").
+#[allow(unused_variables)]
+pub fn update_node_stalled(chain: &str, stalled: bool) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        if stalled {
+            prometheus::NODE_STALLS_COUNTER
+                .with_label_values(&[chain])
+                .inc();
+        }
+        prometheus::NODE_STALLED_GAUGE
+            .with_label_values(&[chain])
+            .set(if stalled { 1 } else { 0 });
+    }
+}
+
+/// Record the rolling accuracy of the `kind` ("cost" or "fee") estimator `estimator`.
+#[allow(unused_variables)]
+pub fn update_estimator_accuracy(
+    kind: &str,
+    estimator: &str,
+    mean_absolute_error: f64,
+    mean_error: f64,
+    samples: u64,
+) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        prometheus::ESTIMATOR_MEAN_ABSOLUTE_ERROR_GAUGE
+            .with_label_values(&[kind, estimator])
+            .set(mean_absolute_error);
+        prometheus::ESTIMATOR_MEAN_ERROR_GAUGE
+            .with_label_values(&[kind, estimator])
+            .set(mean_error);
+        prometheus::ESTIMATOR_ACCURACY_SAMPLES_GAUGE
+            .with_label_values(&[kind, estimator])
+            .set(samples as i64);
+    }
+}
+
 #[allow(unused_variables)]
 pub fn update_inbound_neighbors(value: i64) {
     #[cfg(feature = "monitoring_prom")]
@@ -305,6 +379,14 @@ pub fn update_outbound_rpc_bandwidth(value: i64) {
     prometheus::OUTBOUND_RPC_BANDWIDTH_GAUGE.add(value);
 }
 
+#[allow(unused_variables)]
+pub fn increment_rpc_throttled_counter(path: &str, limit: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::RPC_THROTTLED_COUNTER_VEC
+        .with_label_values(&[path, limit])
+        .inc();
+}
+
 #[allow(unused_variables)]
 pub fn increment_msg_counter(name: String) {
     #[cfg(feature = "monitoring_prom")]
@@ -323,6 +405,620 @@ pub fn increment_contract_calls_processed() {
     prometheus::CONTRACT_CALLS_PROCESSED_COUNT.inc();
 }
 
+/// When the block elected by a sortition reached each stage of its propagation, in milliseconds
+/// since the epoch.  Each stage is recorded the first time it happens.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockPropagationTimes {
+    pub consensus_hash: ConsensusHash,
+    pub block_hash: Option<BlockHeaderHash>,
+    /// when this node processed the sortition
+    pub sortition_time_ms: u64,
+    /// when this node stored the block as a staging block
+    pub received_time_ms: Option<u64>,
+    /// when this node appended the block to its chainstate
+    pub validated_time_ms: Option<u64>,
+    /// when this node announced the block to its peers
+    pub announced_time_ms: Option<u64>,
+}
+
+/// A stage of a block's propagation
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BlockPropagationStage {
+    Received,
+    Validated,
+    Announced,
+}
+
+impl BlockPropagationStage {
+    #[allow(dead_code)]
+    fn as_str(&self) -> &'static str {
+        match *self {
+            BlockPropagationStage::Received => "received",
+            BlockPropagationStage::Validated => "validated",
+            BlockPropagationStage::Announced => "announced",
+        }
+    }
+}
+
+/// The propagation times of the blocks of the most recent sortitions
+struct BlockPropagationTracker {
+    max_sortitions: usize,
+    order: VecDeque<ConsensusHash>,
+    times: HashMap<ConsensusHash, BlockPropagationTimes>,
+}
+
+impl BlockPropagationTracker {
+    fn new(max_sortitions: usize) -> BlockPropagationTracker {
+        BlockPropagationTracker {
+            max_sortitions,
+            order: VecDeque::new(),
+            times: HashMap::new(),
+        }
+    }
+
+    fn sortition_processed(&mut self, consensus_hash: &ConsensusHash, now_ms: u64) {
+        if self.times.contains_key(consensus_hash) {
+            return;
+        }
+        self.order.push_back(consensus_hash.clone());
+        self.times.insert(
+            consensus_hash.clone(),
+            BlockPropagationTimes {
+                consensus_hash: consensus_hash.clone(),
+                block_hash: None,
+                sortition_time_ms: now_ms,
+                received_time_ms: None,
+                validated_time_ms: None,
+                announced_time_ms: None,
+            },
+        );
+        while self.order.len() > self.max_sortitions {
+            if let Some(oldest) = self.order.pop_front() {
+                self.times.remove(&oldest);
+            }
+        }
+    }
+
+    /// Record that the block of `consensus_hash` reached `stage` at `now_ms`.
+    /// Returns the seconds since the sortition if this is the first time it got there, and
+    /// None if it already had, or if we did not see its sortition.
+    fn block_reached(
+        &mut self,
+        consensus_hash: &ConsensusHash,
+        block_hash: Option<&BlockHeaderHash>,
+        stage: BlockPropagationStage,
+        now_ms: u64,
+    ) -> Option<f64> {
+        let times = self.times.get_mut(consensus_hash)?;
+        if times.block_hash.is_none() {
+            times.block_hash = block_hash.cloned();
+        }
+        let stage_time_ms = match stage {
+            BlockPropagationStage::Received => &mut times.received_time_ms,
+            BlockPropagationStage::Validated => &mut times.validated_time_ms,
+            BlockPropagationStage::Announced => &mut times.announced_time_ms,
+        };
+        if stage_time_ms.is_some() {
+            return None;
+        }
+        *stage_time_ms = Some(now_ms);
+        Some(now_ms.saturating_sub(times.sortition_time_ms) as f64 / 1000.0)
+    }
+
+    /// Seconds between the sortition of `consensus_hash` and `now_ms`
+    fn since_sortition(&self, consensus_hash: &ConsensusHash, now_ms: u64) -> Option<f64> {
+        self.times
+            .get(consensus_hash)
+            .map(|times| now_ms.saturating_sub(times.sortition_time_ms) as f64 / 1000.0)
+    }
+}
+
+#[allow(unused_variables)]
+fn log_block_stage(
+    consensus_hash: &ConsensusHash,
+    block_hash: Option<&BlockHeaderHash>,
+    stage: BlockPropagationStage,
+) {
+    let now_ms = get_epoch_time_ms() as u64;
+    let latency_opt =
+        BLOCK_PROPAGATION
+            .lock()
+            .unwrap()
+            .block_reached(consensus_hash, block_hash, stage, now_ms);
+
+    #[cfg(feature = "monitoring_prom")]
+    {
+        if let Some(latency) = latency_opt {
+            prometheus::BLOCK_PROPAGATION_LATENCIES_HISTOGRAM
+                .with_label_values(&[stage.as_str()])
+                .observe(latency);
+        }
+    }
+}
+
+/// Start tracking the propagation of the block elected by a sortition this node just processed.
+pub fn log_sortition_processed(consensus_hash: &ConsensusHash) {
+    BLOCK_PROPAGATION
+        .lock()
+        .unwrap()
+        .sortition_processed(consensus_hash, get_epoch_time_ms() as u64);
+}
+
+pub fn log_block_received(consensus_hash: &ConsensusHash, block_hash: &BlockHeaderHash) {
+    log_block_stage(
+        consensus_hash,
+        Some(block_hash),
+        BlockPropagationStage::Received,
+    );
+}
+
+pub fn log_block_validated(consensus_hash: &ConsensusHash, block_hash: &BlockHeaderHash) {
+    log_block_stage(
+        consensus_hash,
+        Some(block_hash),
+        BlockPropagationStage::Validated,
+    );
+}
+
+pub fn log_block_announced(consensus_hash: &ConsensusHash) {
+    log_block_stage(consensus_hash, None, BlockPropagationStage::Announced);
+}
+
+/// Log the receipt of a microblock streamed by the block elected at `consensus_hash`.
+#[allow(unused_variables)]
+pub fn log_microblock_received(consensus_hash: &ConsensusHash) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        let latency_opt = BLOCK_PROPAGATION
+            .lock()
+            .unwrap()
+            .since_sortition(consensus_hash, get_epoch_time_ms() as u64);
+        if let Some(latency) = latency_opt {
+            prometheus::BLOCK_PROPAGATION_LATENCIES_HISTOGRAM
+                .with_label_values(&["microblock_received"])
+                .observe(latency);
+        }
+    }
+}
+
+/// Get the propagation times of the block elected at `consensus_hash`, if this node saw its
+/// sortition recently enough.
+pub fn get_block_propagation_times(
+    consensus_hash: &ConsensusHash,
+) -> Option<BlockPropagationTimes> {
+    BLOCK_PROPAGATION
+        .lock()
+        .unwrap()
+        .times
+        .get(consensus_hash)
+        .cloned()
+}
+
+/// When a miner's tenure on a burn block reached each phase, in milliseconds since the epoch.
+/// The phases are listed in the order the miner goes through them: the block-commit's UTXOs
+/// are selected when the commit transaction is built, after the block is assembled.  Each phase
+/// is recorded the first time it happens; later attempts in the same tenure are only counted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TenureTimes {
+    /// the burn block the tenure built on
+    pub burn_block_height: u64,
+    pub burn_header_hash: BurnchainHeaderHash,
+    /// when this node processed the burn block
+    pub burn_block_time_ms: u64,
+    /// when the miner assembled its anchored block
+    pub block_assembled_time_ms: Option<u64>,
+    /// when the miner selected the UTXOs that fund its block-commit
+    pub utxos_selected_time_ms: Option<u64>,
+    /// when the miner broadcast its block-commit
+    pub commit_broadcast_time_ms: Option<u64>,
+    /// when this node processed the next burn block, which held the sortition
+    pub sortition_time_ms: Option<u64>,
+    /// number of block-commits broadcast during the tenure
+    pub commit_attempts: u64,
+    /// whether a block-commit from this miner was mined in the next burn block
+    pub commit_mined: Option<bool>,
+    /// whether this miner won the sortition
+    pub won_sortition: Option<bool>,
+    /// how long each phase took
+    pub durations: TenurePhaseDurations,
+}
+
+/// How long each phase of a tenure took, in milliseconds since the previous phase.  A phase is
+/// only timed if both it and the phase before it were reached.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct TenurePhaseDurations {
+    /// from the burn block's arrival to the assembled block
+    pub block_assembly_ms: Option<u64>,
+    /// from the assembled block to the selected UTXOs
+    pub utxo_selection_ms: Option<u64>,
+    /// from the selected UTXOs to the broadcast block-commit
+    pub commit_broadcast_ms: Option<u64>,
+    /// from the broadcast block-commit to the sortition
+    pub sortition_ms: Option<u64>,
+}
+
+impl TenureTimes {
+    fn phase_durations(&self) -> TenurePhaseDurations {
+        let between = |start: Option<u64>, end: Option<u64>| match (start, end) {
+            (Some(start), Some(end)) => Some(end.saturating_sub(start)),
+            _ => None,
+        };
+        TenurePhaseDurations {
+            block_assembly_ms: between(Some(self.burn_block_time_ms), self.block_assembled_time_ms),
+            utxo_selection_ms: between(self.block_assembled_time_ms, self.utxos_selected_time_ms),
+            commit_broadcast_ms: between(
+                self.utxos_selected_time_ms,
+                self.commit_broadcast_time_ms,
+            ),
+            sortition_ms: between(self.commit_broadcast_time_ms, self.sortition_time_ms),
+        }
+    }
+}
+
+/// The tenure times of the most recent burn blocks this node mined on
+struct TenureTracker {
+    max_burn_blocks: usize,
+    order: VecDeque<BurnchainHeaderHash>,
+    times: HashMap<BurnchainHeaderHash, TenureTimes>,
+}
+
+impl TenureTracker {
+    fn new(max_burn_blocks: usize) -> TenureTracker {
+        TenureTracker {
+            max_burn_blocks,
+            order: VecDeque::new(),
+            times: HashMap::new(),
+        }
+    }
+
+    fn burn_block_processed(
+        &mut self,
+        burn_header_hash: &BurnchainHeaderHash,
+        burn_block_height: u64,
+        now_ms: u64,
+    ) {
+        if self.times.contains_key(burn_header_hash) {
+            return;
+        }
+        self.order.push_back(burn_header_hash.clone());
+        self.times.insert(
+            burn_header_hash.clone(),
+            TenureTimes {
+                burn_block_height,
+                burn_header_hash: burn_header_hash.clone(),
+                burn_block_time_ms: now_ms,
+                block_assembled_time_ms: None,
+                utxos_selected_time_ms: None,
+                commit_broadcast_time_ms: None,
+                sortition_time_ms: None,
+                commit_attempts: 0,
+                commit_mined: None,
+                won_sortition: None,
+                durations: TenurePhaseDurations::default(),
+            },
+        );
+        while self.order.len() > self.max_burn_blocks {
+            if let Some(oldest) = self.order.pop_front() {
+                self.times.remove(&oldest);
+            }
+        }
+    }
+
+    fn block_assembled(&mut self, burn_header_hash: &BurnchainHeaderHash, now_ms: u64) {
+        if let Some(times) = self.times.get_mut(burn_header_hash) {
+            times.block_assembled_time_ms.get_or_insert(now_ms);
+            times.durations = times.phase_durations();
+        }
+    }
+
+    fn commit_broadcast(
+        &mut self,
+        burn_header_hash: &BurnchainHeaderHash,
+        utxos_selected_ms: u64,
+        now_ms: u64,
+    ) {
+        if let Some(times) = self.times.get_mut(burn_header_hash) {
+            times
+                .utxos_selected_time_ms
+                .get_or_insert(utxos_selected_ms);
+            times.commit_broadcast_time_ms.get_or_insert(now_ms);
+            times.commit_attempts += 1;
+            times.durations = times.phase_durations();
+        }
+    }
+
+    /// Record the outcome of the sortition that followed the tenure on `burn_header_hash`.
+    /// Returns the finished tenure times the first time this is called for a tenure in which the
+    /// miner assembled a block, and None otherwise.
+    fn sortition_processed(
+        &mut self,
+        burn_header_hash: &BurnchainHeaderHash,
+        commit_mined: bool,
+        won_sortition: bool,
+        now_ms: u64,
+    ) -> Option<TenureTimes> {
+        let times = self.times.get_mut(burn_header_hash)?;
+        if times.sortition_time_ms.is_some() || times.block_assembled_time_ms.is_none() {
+            return None;
+        }
+        times.sortition_time_ms = Some(now_ms);
+        times.commit_mined = Some(commit_mined);
+        times.won_sortition = Some(won_sortition);
+        times.durations = times.phase_durations();
+        Some(times.clone())
+    }
+}
+
+/// Start timing this miner's tenure on a burn block it just processed.
+pub fn log_tenure_burn_block(burn_header_hash: &BurnchainHeaderHash, burn_block_height: u64) {
+    TENURES.lock().unwrap().burn_block_processed(
+        burn_header_hash,
+        burn_block_height,
+        get_epoch_time_ms() as u64,
+    );
+}
+
+/// Record that the miner assembled an anchored block in its tenure on `burn_header_hash`.
+pub fn log_tenure_block_assembled(burn_header_hash: &BurnchainHeaderHash) {
+    TENURES
+        .lock()
+        .unwrap()
+        .block_assembled(burn_header_hash, get_epoch_time_ms() as u64);
+}
+
+/// Record that the miner broadcast a block-commit in its tenure on `burn_header_hash`, funded by
+/// UTXOs it selected at `utxos_selected_ms`.
+pub fn log_tenure_commit_broadcast(burn_header_hash: &BurnchainHeaderHash, utxos_selected_ms: u64) {
+    TENURES.lock().unwrap().commit_broadcast(
+        burn_header_hash,
+        utxos_selected_ms,
+        get_epoch_time_ms() as u64,
+    );
+}
+
+/// Record the sortition that followed the miner's tenure on `burn_header_hash`, and export how
+/// long each phase of the tenure took.
+#[allow(unused_variables)]
+pub fn log_tenure_sortition(
+    burn_header_hash: &BurnchainHeaderHash,
+    commit_mined: bool,
+    won_sortition: bool,
+) {
+    let times_opt = TENURES.lock().unwrap().sortition_processed(
+        burn_header_hash,
+        commit_mined,
+        won_sortition,
+        get_epoch_time_ms() as u64,
+    );
+
+    #[cfg(feature = "monitoring_prom")]
+    {
+        if let Some(times) = times_opt {
+            let durations = [
+                ("block_assembly", times.durations.block_assembly_ms),
+                ("utxo_selection", times.durations.utxo_selection_ms),
+                ("commit_broadcast", times.durations.commit_broadcast_ms),
+                ("sortition", times.durations.sortition_ms),
+            ];
+            for (phase, duration_ms) in durations.iter() {
+                if let Some(duration_ms) = duration_ms {
+                    prometheus::TENURE_PHASE_DURATIONS_HISTOGRAM
+                        .with_label_values(&[phase])
+                        .observe(*duration_ms as f64 / 1000.0);
+                }
+            }
+        }
+    }
+}
+
+/// Get the times of this miner's tenure on `burn_header_hash`, if it was recent enough.
+pub fn get_tenure_times(burn_header_hash: &BurnchainHeaderHash) -> Option<TenureTimes> {
+    TENURES.lock().unwrap().times.get(burn_header_hash).cloned()
+}
+
+/// How far one chain has synced
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainSyncProgress {
+    pub height: u64,
+    pub target_height: u64,
+    pub percent: f64,
+    /// blocks synced per second over the last few minutes
+    pub blocks_per_sec: f64,
+    /// seconds until `target_height` at that rate, or None if the chain is not advancing
+    pub eta_secs: Option<u64>,
+}
+
+/// How far this node has synced its chains, as reported by `/v2/info` while it syncs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncProgress {
+    /// burn blocks stored, out of the burnchain headers this node has
+    pub burnchain: ChainSyncProgress,
+    /// Stacks blocks processed, measured in burn block heights: the burn block that elected the
+    /// Stacks tip, out of the highest sortition this node has processed
+    pub stacks: ChainSyncProgress,
+    pub stacks_tip_height: u64,
+    pub stacks_blocks_per_sec: f64,
+}
+
+/// The heights a chain reached over the last `SYNC_RATE_WINDOW_MS`
+struct SyncRate {
+    first_height: u64,
+    target_height: u64,
+    samples: VecDeque<(u64, u64)>,
+}
+
+impl SyncRate {
+    fn new() -> SyncRate {
+        SyncRate {
+            first_height: 0,
+            target_height: 0,
+            samples: VecDeque::new(),
+        }
+    }
+
+    fn height(&self) -> u64 {
+        self.samples
+            .back()
+            .map(|(_, height)| *height)
+            .unwrap_or(self.first_height)
+    }
+
+    fn record(&mut self, height: u64, now_ms: u64) {
+        self.samples.push_back((now_ms, height));
+        while self.samples.len() > MAX_SYNC_RATE_SAMPLES
+            || (self.samples.len() > 1 && self.samples[0].0 + SYNC_RATE_WINDOW_MS < now_ms)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Blocks per second from the oldest sample until `now_ms`, so that the rate decays while
+    /// the chain does not advance
+    fn blocks_per_sec(&self, now_ms: u64) -> f64 {
+        match (self.samples.front(), self.samples.back()) {
+            (Some((first_ms, first_height)), Some((_, last_height))) if now_ms > *first_ms => {
+                last_height.saturating_sub(*first_height) as f64 * 1000.0
+                    / (now_ms - first_ms) as f64
+            }
+            _ => 0.0,
+        }
+    }
+
+    fn progress(&self, now_ms: u64) -> ChainSyncProgress {
+        let height = self.height();
+        let target_height = self.target_height.max(height);
+        let blocks_per_sec = self.blocks_per_sec(now_ms);
+        let percent = if target_height <= self.first_height {
+            100.0
+        } else {
+            height.saturating_sub(self.first_height) as f64 * 100.0
+                / (target_height - self.first_height) as f64
+        };
+        let eta_secs = if height >= target_height {
+            Some(0)
+        } else if blocks_per_sec > 0.0 {
+            Some(((target_height - height) as f64 / blocks_per_sec).ceil() as u64)
+        } else {
+            None
+        };
+        ChainSyncProgress {
+            height,
+            target_height,
+            percent,
+            blocks_per_sec,
+            eta_secs,
+        }
+    }
+}
+
+struct SyncProgressTracker {
+    burnchain: SyncRate,
+    /// in burn block heights of the sortitions that elected the Stacks tips
+    stacks: SyncRate,
+    /// in Stacks block heights
+    stacks_blocks: SyncRate,
+}
+
+impl SyncProgressTracker {
+    fn new() -> SyncProgressTracker {
+        SyncProgressTracker {
+            burnchain: SyncRate::new(),
+            stacks: SyncRate::new(),
+            stacks_blocks: SyncRate::new(),
+        }
+    }
+
+    fn progress(&self, now_ms: u64) -> Option<SyncProgress> {
+        if self.burnchain.target_height == 0 {
+            // no burnchain sync yet
+            return None;
+        }
+        Some(SyncProgress {
+            burnchain: self.burnchain.progress(now_ms),
+            stacks: self.stacks.progress(now_ms),
+            stacks_tip_height: self.stacks_blocks.height(),
+            stacks_blocks_per_sec: self.stacks_blocks.blocks_per_sec(now_ms),
+        })
+    }
+}
+
+#[allow(unused_variables)]
+fn export_sync_progress(progress: &SyncProgress) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        for (chain, chain_progress) in [
+            ("burnchain", &progress.burnchain),
+            ("stacks", &progress.stacks),
+        ]
+        .iter()
+        {
+            prometheus::SYNC_PROGRESS_PERCENT_GAUGE
+                .with_label_values(&[chain])
+                .set(chain_progress.percent);
+            prometheus::SYNC_BLOCKS_PER_SEC_GAUGE
+                .with_label_values(&[chain])
+                .set(chain_progress.blocks_per_sec);
+            prometheus::SYNC_ETA_SECONDS_GAUGE
+                .with_label_values(&[chain])
+                .set(chain_progress.eta_secs.map(|eta| eta as i64).unwrap_or(-1));
+        }
+    }
+}
+
+fn update_sync_progress<F>(update: F)
+where
+    F: FnOnce(&mut SyncProgressTracker, u64),
+{
+    let now_ms = get_epoch_time_ms() as u64;
+    let progress_opt = {
+        let mut tracker = SYNC_PROGRESS.lock().unwrap();
+        update(&mut tracker, now_ms);
+        tracker.progress(now_ms)
+    };
+    if let Some(progress) = progress_opt {
+        export_sync_progress(&progress);
+    }
+}
+
+/// Record the height of the burnchain headers this node has, which the burnchain sync aims for,
+/// and the burnchain's first block height.
+pub fn update_burnchain_sync_target(first_height: u64, target_height: u64) {
+    update_sync_progress(|tracker, _| {
+        tracker.burnchain.first_height = first_height;
+        tracker.burnchain.target_height = target_height;
+    });
+}
+
+/// Record that the burnchain is stored up to `height`.
+pub fn log_burnchain_block_synced(height: u64) {
+    update_sync_progress(|tracker, now_ms| tracker.burnchain.record(height, now_ms));
+}
+
+/// Record the height of the highest sortition processed, which the Stacks sync aims for, and the
+/// burnchain's first block height.
+pub fn update_stacks_sync_target(first_height: u64, sortition_height: u64) {
+    update_sync_progress(|tracker, _| {
+        tracker.stacks.first_height = first_height;
+        tracker.stacks.target_height = sortition_height;
+    });
+}
+
+/// Record that the Stacks tip is now at `stacks_height`, elected in burn block `burn_height`.
+pub fn log_stacks_block_synced(stacks_height: u64, burn_height: u64) {
+    update_sync_progress(|tracker, now_ms| {
+        tracker.stacks.record(burn_height, now_ms);
+        tracker.stacks_blocks.record(stacks_height, now_ms);
+    });
+}
+
+/// How far this node has synced its chains, or None if it has not started syncing the burnchain.
+pub fn get_sync_progress() -> Option<SyncProgress> {
+    SYNC_PROGRESS
+        .lock()
+        .unwrap()
+        .progress(get_epoch_time_ms() as u64)
+}
+
 /// Given a value (type uint256), return value/uint256::max() as an f64 value.
 /// The precision of the percentage is determined by the input `precision_points`, which is capped
 /// at a max of 15.
@@ -380,6 +1076,180 @@ pub fn test_convert_uint256_to_f64() {
     assert_approx_eq!(convert_uint256_to_f64_percentage(original, 1000), 12.234567);
 }
 
+#[test]
+pub fn test_block_propagation_tracker() {
+    let mut tracker = BlockPropagationTracker::new(2);
+    let ch1 = ConsensusHash([0x01; 20]);
+    let ch2 = ConsensusHash([0x02; 20]);
+    let ch3 = ConsensusHash([0x03; 20]);
+    let bhh = BlockHeaderHash([0x11; 32]);
+
+    // unknown sortitions are not tracked
+    assert_eq!(
+        tracker.block_reached(&ch1, Some(&bhh), BlockPropagationStage::Received, 1000),
+        None
+    );
+
+    tracker.sortition_processed(&ch1, 1000);
+    assert_eq!(
+        tracker.block_reached(&ch1, Some(&bhh), BlockPropagationStage::Received, 3500),
+        Some(2.5)
+    );
+    assert_eq!(
+        tracker.block_reached(&ch1, None, BlockPropagationStage::Announced, 4000),
+        Some(3.0)
+    );
+    assert_eq!(
+        tracker.block_reached(&ch1, Some(&bhh), BlockPropagationStage::Validated, 6000),
+        Some(5.0)
+    );
+    // only the first time counts
+    assert_eq!(
+        tracker.block_reached(&ch1, Some(&bhh), BlockPropagationStage::Received, 7000),
+        None
+    );
+    // re-processing the sortition does not reset it
+    tracker.sortition_processed(&ch1, 8000);
+    assert_eq!(tracker.since_sortition(&ch1, 9000), Some(8.0));
+
+    assert_eq!(
+        tracker.times.get(&ch1).unwrap(),
+        &BlockPropagationTimes {
+            consensus_hash: ch1.clone(),
+            block_hash: Some(bhh.clone()),
+            sortition_time_ms: 1000,
+            received_time_ms: Some(3500),
+            validated_time_ms: Some(6000),
+            announced_time_ms: Some(4000),
+        }
+    );
+
+    // oldest sortitions are forgotten
+    tracker.sortition_processed(&ch2, 10000);
+    tracker.sortition_processed(&ch3, 11000);
+    assert!(tracker.times.get(&ch1).is_none());
+    assert_eq!(tracker.since_sortition(&ch1, 12000), None);
+    assert_eq!(tracker.since_sortition(&ch2, 12000), Some(2.0));
+    assert_eq!(tracker.since_sortition(&ch3, 12000), Some(1.0));
+}
+
+#[test]
+pub fn test_tenure_tracker() {
+    let mut tracker = TenureTracker::new(2);
+    let bhh1 = BurnchainHeaderHash([0x01; 32]);
+    let bhh2 = BurnchainHeaderHash([0x02; 32]);
+    let bhh3 = BurnchainHeaderHash([0x03; 32]);
+
+    // unknown burn blocks are not tracked
+    tracker.block_assembled(&bhh1, 1000);
+    assert!(tracker.times.get(&bhh1).is_none());
+    assert_eq!(tracker.sortition_processed(&bhh1, true, true, 1000), None);
+
+    tracker.burn_block_processed(&bhh1, 100, 1000);
+    // no sortition record for a tenure that never assembled a block
+    assert_eq!(tracker.sortition_processed(&bhh1, false, false, 1500), None);
+
+    tracker.block_assembled(&bhh1, 3000);
+    tracker.commit_broadcast(&bhh1, 3200, 3500);
+    // a later attempt in the same tenure is only counted
+    tracker.block_assembled(&bhh1, 6000);
+    tracker.commit_broadcast(&bhh1, 6100, 6200);
+
+    let times = tracker
+        .sortition_processed(&bhh1, true, false, 10000)
+        .unwrap();
+    assert_eq!(
+        times,
+        TenureTimes {
+            burn_block_height: 100,
+            burn_header_hash: bhh1.clone(),
+            burn_block_time_ms: 1000,
+            block_assembled_time_ms: Some(3000),
+            utxos_selected_time_ms: Some(3200),
+            commit_broadcast_time_ms: Some(3500),
+            sortition_time_ms: Some(10000),
+            commit_attempts: 2,
+            commit_mined: Some(true),
+            won_sortition: Some(false),
+            durations: TenurePhaseDurations {
+                block_assembly_ms: Some(2000),
+                utxo_selection_ms: Some(200),
+                commit_broadcast_ms: Some(300),
+                sortition_ms: Some(6500),
+            },
+        }
+    );
+    // the sortition is only reported once
+    assert_eq!(tracker.sortition_processed(&bhh1, true, true, 11000), None);
+
+    // a tenure that never broadcast a commit only times the block assembly
+    tracker.burn_block_processed(&bhh2, 101, 20000);
+    tracker.block_assembled(&bhh2, 21000);
+    let times = tracker
+        .sortition_processed(&bhh2, false, false, 30000)
+        .unwrap();
+    assert_eq!(
+        times.durations,
+        TenurePhaseDurations {
+            block_assembly_ms: Some(1000),
+            utxo_selection_ms: None,
+            commit_broadcast_ms: None,
+            sortition_ms: None,
+        }
+    );
+
+    // oldest burn blocks are forgotten
+    tracker.burn_block_processed(&bhh3, 102, 40000);
+    assert!(tracker.times.get(&bhh1).is_none());
+    assert!(tracker.times.get(&bhh2).is_some());
+    assert!(tracker.times.get(&bhh3).is_some());
+}
+
+#[test]
+pub fn test_sync_progress_tracker() {
+    let mut tracker = SyncProgressTracker::new();
+    assert_eq!(tracker.progress(1000), None);
+
+    tracker.burnchain.first_height = 100;
+    tracker.burnchain.target_height = 1100;
+    tracker.burnchain.record(100, 0);
+    tracker.burnchain.record(300, 10_000);
+    let progress = tracker.progress(10_000).unwrap();
+    assert_eq!(progress.burnchain.height, 300);
+    assert_approx_eq!(progress.burnchain.percent, 20.0);
+    assert_approx_eq!(progress.burnchain.blocks_per_sec, 20.0);
+    assert_eq!(progress.burnchain.eta_secs, Some(40));
+
+    // the rate decays while the chain does not advance
+    let progress = tracker.progress(20_000).unwrap();
+    assert_approx_eq!(progress.burnchain.blocks_per_sec, 10.0);
+    assert_eq!(progress.burnchain.eta_secs, Some(80));
+
+    // samples older than the window are dropped
+    tracker.burnchain.record(400, SYNC_RATE_WINDOW_MS + 5_000);
+    assert_eq!(tracker.burnchain.samples.len(), 2);
+    let progress = tracker.progress(SYNC_RATE_WINDOW_MS + 5_000).unwrap();
+    assert_approx_eq!(
+        progress.burnchain.blocks_per_sec,
+        100.0 * 1000.0 / (SYNC_RATE_WINDOW_MS - 5_000) as f64
+    );
+
+    // caught up
+    tracker.burnchain.record(1100, SYNC_RATE_WINDOW_MS + 6_000);
+    let progress = tracker.progress(SYNC_RATE_WINDOW_MS + 6_000).unwrap();
+    assert_approx_eq!(progress.burnchain.percent, 100.0);
+    assert_eq!(progress.burnchain.eta_secs, Some(0));
+
+    // no Stacks blocks yet
+    assert_approx_eq!(progress.stacks.percent, 100.0);
+    assert_eq!(progress.stacks_tip_height, 0);
+    tracker.stacks.first_height = 100;
+    tracker.stacks.target_height = 1100;
+    let progress = tracker.progress(SYNC_RATE_WINDOW_MS + 6_000).unwrap();
+    assert_approx_eq!(progress.stacks.percent, 0.0);
+    assert_eq!(progress.stacks.eta_secs, None);
+}
+
 #[allow(unused_variables)]
 pub fn update_computed_relative_miner_score(value: Uint256) {
     #[cfg(feature = "monitoring_prom")]
@@ -411,6 +1281,19 @@ pub fn update_miner_current_median_commitment(value: u128) {
     }
 }
 
+/// Record the chainstate consistency hash computed at a Stacks block height.  Only the first 6
+/// bytes of the hash are exported, so that the gauge holds them exactly.
+#[allow(unused_variables)]
+pub fn update_consistency_hash(height: u64, hash: &Sha512Trunc256Sum) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        let mut prefix = [0u8; 8];
+        prefix[2..].copy_from_slice(&hash.as_bytes()[0..6]);
+        prometheus::CONSISTENCY_HASH_HEIGHT_GAUGE.set(height as i64);
+        prometheus::CONSISTENCY_HASH_PREFIX_GAUGE.set(u64::from_be_bytes(prefix) as i64);
+    }
+}
+
 /// Function sets the global variable `GLOBAL_BURNCHAIN_SIGNER`.
 /// Fails if there are multiple attempts to set this variable.
 #[allow(unused_variables)]
@@ -15,7 +15,8 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use prometheus::{
-    Gauge, Histogram, HistogramTimer, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    Gauge, GaugeVec, Histogram, HistogramTimer, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec,
 };
 
 lazy_static! {
@@ -66,6 +67,47 @@ lazy_static! {
         "Total number of blocks processed from the burnchain"
     )).unwrap();
 
+    pub static ref BURNCHAIN_REORGS_COUNTER: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_burnchain_reorgs_total",
+        "Total number of burnchain reorgs seen, labeled by whether they orphaned at least burnchain.deep_reorg_depth burn blocks",
+        &["deep"]
+    ).unwrap();
+
+    pub static ref LAST_BURNCHAIN_REORG_DEPTH_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_last_burnchain_reorg_depth",
+        "Number of burn blocks orphaned by the most recent burnchain reorg"
+    )).unwrap();
+
+    pub static ref NODE_STALLS_COUNTER: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_stalls_total",
+        "Total number of times the node stopped processing blocks for node.stall_watchdog.timeout_secs, labeled by chain",
+        &["chain"]
+    ).unwrap();
+
+    pub static ref NODE_STALLED_GAUGE: IntGaugeVec = register_int_gauge_vec!(
+        "stacks_node_stalled",
+        "1 while the node has not processed a block of the chain for node.stall_watchdog.timeout_secs, 0 otherwise",
+        &["chain"]
+    ).unwrap();
+
+    pub static ref ESTIMATOR_MEAN_ABSOLUTE_ERROR_GAUGE: GaugeVec = register_gauge_vec!(
+        "stacks_node_estimator_mean_absolute_error",
+        "Mean absolute relative error of an estimator's recent predictions, labeled by kind (cost or fee) and estimator",
+        &["kind", "estimator"]
+    ).unwrap();
+
+    pub static ref ESTIMATOR_MEAN_ERROR_GAUGE: GaugeVec = register_gauge_vec!(
+        "stacks_node_estimator_mean_error",
+        "Mean relative error (positive when overestimating) of an estimator's recent predictions, labeled by kind (cost or fee) and estimator",
+        &["kind", "estimator"]
+    ).unwrap();
+
+    pub static ref ESTIMATOR_ACCURACY_SAMPLES_GAUGE: IntGaugeVec = register_int_gauge_vec!(
+        "stacks_node_estimator_accuracy_samples",
+        "Number of recent predictions the estimator error gauges are computed over, labeled by kind (cost or fee) and estimator",
+        &["kind", "estimator"]
+    ).unwrap();
+
     pub static ref BTC_OPS_SENT_COUNTER: IntCounter = register_int_counter!(opts!(
         "stacks_node_btc_ops_sent_total",
         "Total number of ops (key registrations, block commits, user burn supports) submitted to the burnchain"
@@ -166,6 +208,12 @@ lazy_static! {
         "Total RPC outbound bandwidth in bytes"
     )).unwrap();
 
+    pub static ref RPC_THROTTLED_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_rpc_throttled_requests",
+        "Total count of RPC requests refused by rate limiting, by route and by the limit they hit",
+        &["path", "limit"]
+    ).unwrap();
+
     pub static ref MSG_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
         "stacks_node_message_count",
         "Stacks message count by type of message",
@@ -196,6 +244,18 @@ lazy_static! {
         labels! {"handler".to_string() => "all".to_string(),}
     )).unwrap();
 
+    pub static ref BLOCK_PROPAGATION_LATENCIES_HISTOGRAM: HistogramVec = register_histogram_vec!(histogram_opts!(
+        "stacks_node_block_propagation_latencies_histogram",
+        "Time (seconds) between when this node processed a sortition and when the elected block reached each stage: received, validated, or announced to peers (microblock_received is for each microblock of the elected block's stream)",
+        vec![1.0, 2.0, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1200.0]
+    ), &["stage"]).unwrap();
+
+    pub static ref TENURE_PHASE_DURATIONS_HISTOGRAM: HistogramVec = register_histogram_vec!(histogram_opts!(
+        "stacks_node_tenure_phase_durations_histogram",
+        "Time (seconds) this miner spent in each phase of a tenure, since the previous phase: block_assembly (since the burn block arrived), utxo_selection, commit_broadcast, or sortition (until the next burn block arrived)",
+        vec![0.1, 0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1200.0]
+    ), &["phase"]).unwrap();
+
     pub static ref COMPUTED_RELATIVE_MINER_SCORE: Gauge = register_gauge!(opts!(
         "stacks_node_computed_relative_miner_score",
         "Percentage of the u256 range that this miner is assigned in a particular round of sortition"
@@ -220,6 +280,34 @@ lazy_static! {
         "stacks_node_miner_current_median_commitment_low",
         "Low 64 bits of a miner's median commitment over the mining commitment window."
     )).unwrap();
+
+    pub static ref CONSISTENCY_HASH_HEIGHT_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_consistency_hash_height",
+        "Stacks block height of the most recently computed chainstate consistency hash"
+    )).unwrap();
+
+    pub static ref CONSISTENCY_HASH_PREFIX_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_consistency_hash_prefix",
+        "First 6 bytes of the most recently computed chainstate consistency hash, as a big-endian integer"
+    )).unwrap();
+
+    pub static ref SYNC_PROGRESS_PERCENT_GAUGE: GaugeVec = register_gauge_vec!(
+        "stacks_node_sync_progress_percent",
+        "Percent of the chain this node has synced, labeled by chain (burnchain or stacks)",
+        &["chain"]
+    ).unwrap();
+
+    pub static ref SYNC_BLOCKS_PER_SEC_GAUGE: GaugeVec = register_gauge_vec!(
+        "stacks_node_sync_blocks_per_second",
+        "Blocks synced per second over the last few minutes, labeled by chain (burnchain or stacks)",
+        &["chain"]
+    ).unwrap();
+
+    pub static ref SYNC_ETA_SECONDS_GAUGE: IntGaugeVec = register_int_gauge_vec!(
+        "stacks_node_sync_eta_seconds",
+        "Estimated seconds until the chain is synced, or -1 if it is not advancing, labeled by chain (burnchain or stacks)",
+        &["chain"]
+    ).unwrap();
 }
 
 pub fn new_rpc_call_timer(path: &str) -> HistogramTimer {
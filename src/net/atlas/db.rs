@@ -17,7 +17,7 @@
 use rusqlite::types::ToSql;
 use rusqlite::Row;
 use rusqlite::Transaction;
-use rusqlite::{Connection, OpenFlags, NO_PARAMS};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, NO_PARAMS};
 
 use std::collections::HashSet;
 use std::convert::From;
@@ -45,9 +45,10 @@ use crate::burnchains::Txid;
 use crate::codec::StacksMessageCodec;
 use crate::types::chainstate::StacksBlockId;
 
-use super::{AtlasConfig, Attachment, AttachmentInstance};
+use super::store::AttachmentStore;
+use super::{AtlasConfig, Attachment, AttachmentInstance, AttachmentStoreConfig};
 
-pub const ATLASDB_VERSION: &'static str = "1";
+pub const ATLASDB_VERSION: &'static str = "2";
 
 const ATLASDB_INITIAL_SCHEMA: &'static [&'static str] = &[
     r#"
@@ -73,6 +74,11 @@ const ATLASDB_INITIAL_SCHEMA: &'static [&'static str] = &[
     "CREATE TABLE db_config(version TEXT NOT NULL);",
 ];
 
+const ATLASDB_SCHEMA_2: &'static [&'static str] = &[
+    // whether an attachment's content lives in the attachment store instead of `content`
+    "ALTER TABLE attachments ADD COLUMN stored_externally INTEGER NOT NULL DEFAULT 0;",
+];
+
 const ATLASDB_INDEXES: &'static [&'static str] =
     &["CREATE INDEX IF NOT EXISTS index_was_instantiated ON attachments(was_instantiated);"];
 
@@ -109,6 +115,27 @@ impl FromRow<AttachmentInstance> for AttachmentInstance {
     }
 }
 
+/// An attachment row, whose content may be in the attachment store.
+struct StoredAttachment {
+    content_hash: Hash160,
+    content: Vec<u8>,
+    stored_externally: bool,
+}
+
+impl FromRow<StoredAttachment> for StoredAttachment {
+    fn from_row<'a>(row: &'a Row) -> Result<StoredAttachment, db_error> {
+        let hex_content_hash: String = row.get_unwrap("hash");
+        let content_hash = Hash160::from_hex(&hex_content_hash).map_err(|_| db_error::TypeError)?;
+        let content: Vec<u8> = row.get_unwrap("content");
+        let stored_externally: bool = row.get_unwrap("stored_externally");
+        Ok(StoredAttachment {
+            content_hash,
+            content,
+            stored_externally,
+        })
+    }
+}
+
 impl FromRow<(u32, u32)> for (u32, u32) {
     fn from_row<'a>(row: &'a Row) -> Result<(u32, u32), db_error> {
         let t1: u32 = row.get_unwrap(0);
@@ -122,6 +149,8 @@ pub struct AtlasDB {
     pub atlas_config: AtlasConfig,
     pub conn: Connection,
     pub readwrite: bool,
+    /// Where attachment contents are written, unless they are kept in the DB
+    content_store: Option<Box<dyn AttachmentStore>>,
 }
 
 impl AtlasDB {
@@ -136,6 +165,13 @@ impl AtlasDB {
 
     fn instantiate(&mut self) -> Result<(), db_error> {
         let genesis_attachments = self.atlas_config.genesis_attachments.take();
+        let genesis_contents = match genesis_attachments {
+            Some(ref attachments) => attachments
+                .iter()
+                .map(|attachment| self.store_content(attachment))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => vec![],
+        };
 
         let tx = self.tx_begin()?;
 
@@ -143,21 +179,23 @@ impl AtlasDB {
             tx.execute_batch(row_text).map_err(db_error::SqliteError)?;
         }
 
-        tx.execute(
-            "INSERT INTO db_config (version) VALUES (?1)",
-            &[&ATLASDB_VERSION],
-        )
-        .map_err(db_error::SqliteError)?;
+        tx.execute("INSERT INTO db_config (version) VALUES (?1)", &["1"])
+            .map_err(db_error::SqliteError)?;
+
+        AtlasDB::apply_schema_migrations(&tx)?;
 
         if let Some(attachments) = genesis_attachments {
             let now = util::get_epoch_time_secs() as i64;
-            for attachment in attachments {
+            for (attachment, (content, stored_externally)) in
+                attachments.iter().zip(genesis_contents.iter())
+            {
                 tx.execute(
-                    "INSERT INTO attachments (hash, content, was_instantiated, created_at) VALUES (?, ?, 1, ?)",
+                    "INSERT INTO attachments (hash, content, was_instantiated, created_at, stored_externally) VALUES (?, ?, 1, ?, ?)",
                     &[
                         &attachment.hash() as &dyn ToSql,
-                        &attachment.content as &dyn ToSql,
+                        content as &dyn ToSql,
                         &now as &dyn ToSql,
+                        stored_externally as &dyn ToSql,
                     ],
                 )
                 .map_err(db_error::SqliteError)?;
@@ -170,6 +208,163 @@ impl AtlasDB {
         Ok(())
     }
 
+    /// Get the database schema version, given a DB connection
+    pub fn get_schema_version(conn: &Connection) -> Result<Option<String>, db_error> {
+        let version = conn
+            .query_row("SELECT MAX(version) from db_config", NO_PARAMS, |row| {
+                row.get(0)
+            })
+            .optional()?;
+        Ok(version)
+    }
+
+    fn apply_schema_2(tx: &Transaction) -> Result<(), db_error> {
+        for sql_exec in ATLASDB_SCHEMA_2 {
+            tx.execute_batch(sql_exec)?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO db_config (version) VALUES (?1)",
+            &["2"],
+        )?;
+        Ok(())
+    }
+
+    /// Apply all schema migrations up to the latest schema.
+    fn apply_schema_migrations(tx: &Transaction) -> Result<(), db_error> {
+        loop {
+            match AtlasDB::get_schema_version(tx)? {
+                Some(version) if version == "1" => AtlasDB::apply_schema_2(tx)?,
+                Some(version) if version == ATLASDB_VERSION => return Ok(()),
+                Some(version) => {
+                    panic!("The schema version of the Atlas DB is invalid: {}", version)
+                }
+                None => panic!("The schema version of the Atlas DB is not recorded."),
+            }
+        }
+    }
+
+    fn check_schema_version_or_error(&self) -> Result<(), db_error> {
+        match AtlasDB::get_schema_version(&self.conn)? {
+            Some(version) if version == ATLASDB_VERSION => Ok(()),
+            Some(version) => Err(db_error::OldSchema(
+                version.parse::<u64>().map_err(|_| db_error::Corruption)?,
+            )),
+            None => panic!("The schema version of the Atlas DB is not recorded."),
+        }
+    }
+
+    /// Writes `attachment`'s content to the attachment store, if there is one.  Returns what to
+    /// keep in the `content` column, and whether the content went to the attachment store.
+    fn store_content(&mut self, attachment: &Attachment) -> Result<(Vec<u8>, bool), db_error> {
+        match self.content_store {
+            Some(ref mut store) => {
+                store.put(&attachment.hash(), &attachment.content)?;
+                Ok((vec![], true))
+            }
+            None => Ok((attachment.content.clone(), false)),
+        }
+    }
+
+    /// Reads a stored attachment's content, from the attachment store if it is not in the DB.
+    fn load_content(&self, stored: StoredAttachment) -> Result<Option<Attachment>, db_error> {
+        if !stored.stored_externally {
+            return Ok(Some(Attachment::new(stored.content)));
+        }
+        let store = self.content_store.as_ref().ok_or_else(|| {
+            db_error::Other(format!(
+                "Attachment {} is in an attachment store, but none is configured",
+                &stored.content_hash
+            ))
+        })?;
+        match store.get(&stored.content_hash)? {
+            Some(content) => Ok(Some(Attachment::new(content))),
+            None => {
+                warn!(
+                    "Atlas: attachment {} is missing from the attachment store",
+                    &stored.content_hash
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// Deletes from the attachment store the contents of the attachments that `qry` selects the
+    /// hash of, before these attachments are deleted.
+    fn delete_stored_contents(&mut self, qry: &str, args: &[&dyn ToSql]) -> Result<(), db_error> {
+        let mut stmt = self.conn.prepare(qry)?;
+        let hashes = stmt
+            .query_map(args, |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        drop(stmt);
+        if let Some(ref mut store) = self.content_store {
+            for hash in hashes {
+                let content_hash = Hash160::from_hex(&hash).map_err(|_| db_error::TypeError)?;
+                store.delete(&content_hash)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves attachment contents to the configured store, after `attachment_store` was changed
+    /// from `previous`.  Contents stored in the DB are moved to the configured attachment store,
+    /// if there is one, and contents in the previous attachment store are moved unless it is
+    /// still the configured one.  The previous store is left untouched, so this can be re-run
+    /// after a failure.  Returns how many contents were moved.
+    pub fn migrate_attachment_contents(
+        &mut self,
+        previous: &AttachmentStoreConfig,
+    ) -> Result<u64, db_error> {
+        let previous_store = previous.open()?;
+        let unchanged_store = *previous == self.atlas_config.attachment_store;
+        let rows = query_rows::<StoredAttachment, _>(
+            &self.conn,
+            "SELECT hash, content, stored_externally FROM attachments",
+            NO_PARAMS,
+        )?;
+
+        let mut moved = 0;
+        for stored in rows {
+            let content = if stored.stored_externally {
+                // with the DB as the previous store, externally stored contents were already
+                // moved to the configured store by an earlier migration
+                let store = match previous_store {
+                    Some(ref store) if !unchanged_store => store,
+                    _ => continue,
+                };
+                match store.get(&stored.content_hash)? {
+                    Some(content) => content,
+                    None => {
+                        warn!(
+                            "Atlas: attachment {} is missing from the previous attachment store",
+                            &stored.content_hash
+                        );
+                        continue;
+                    }
+                }
+            } else {
+                if self.content_store.is_none() {
+                    continue;
+                }
+                stored.content
+            };
+
+            let (content, stored_externally) = self.store_content(&Attachment::new(content))?;
+            let tx = self.tx_begin()?;
+            tx.execute(
+                "UPDATE attachments SET content = ?1, stored_externally = ?2 WHERE hash = ?3",
+                &[
+                    &content as &dyn ToSql,
+                    &stored_externally as &dyn ToSql,
+                    &to_hex(&stored.content_hash.0[..]) as &dyn ToSql,
+                ],
+            )
+            .map_err(db_error::SqliteError)?;
+            tx.commit().map_err(db_error::SqliteError)?;
+            moved += 1;
+        }
+        Ok(moved)
+    }
+
     pub fn should_keep_attachment(
         &self,
         contract_id: &QualifiedContractIdentifier,
@@ -215,16 +410,23 @@ impl AtlasDB {
         };
 
         let conn = sqlite_open(path, open_flags, false)?;
+        let content_store = atlas_config.attachment_store.open()?;
         let mut db = AtlasDB {
             atlas_config,
             conn,
             readwrite,
+            content_store,
         };
         if create_flag {
             db.instantiate()?;
         }
         if readwrite {
+            let tx = db.tx_begin()?;
+            AtlasDB::apply_schema_migrations(&tx)?;
+            tx.commit()?;
             db.add_indexes()?;
+        } else {
+            db.check_schema_version_or_error()?;
         }
         Ok(db)
     }
@@ -233,10 +435,12 @@ impl AtlasDB {
     #[cfg(test)]
     pub fn connect_memory(atlas_config: AtlasConfig) -> Result<AtlasDB, db_error> {
         let conn = Connection::open_in_memory().map_err(|e| db_error::SqliteError(e))?;
+        let content_store = atlas_config.attachment_store.open()?;
         let mut db = AtlasDB {
             atlas_config,
             conn,
             readwrite: true,
+            content_store,
         };
 
         db.instantiate()?;
@@ -326,14 +530,16 @@ impl AtlasDB {
             self.evict_k_oldest_uninstantiated_attachments(to_delete)?;
         }
 
+        let (content, stored_externally) = self.store_content(attachment)?;
         let tx = self.tx_begin()?;
         let now = util::get_epoch_time_secs() as i64;
         let res = tx.execute(
-            "INSERT OR REPLACE INTO attachments (hash, content, was_instantiated, created_at) VALUES (?, ?, 0, ?)",
+            "INSERT OR REPLACE INTO attachments (hash, content, was_instantiated, created_at, stored_externally) VALUES (?, ?, 0, ?, ?)",
             &[
                 &attachment.hash() as &dyn ToSql,
-                &attachment.content as &dyn ToSql,
+                &content as &dyn ToSql,
                 &now as &dyn ToSql,
+                &stored_externally as &dyn ToSql,
             ],
         );
         res.map_err(db_error::SqliteError)?;
@@ -342,6 +548,10 @@ impl AtlasDB {
     }
 
     pub fn evict_k_oldest_uninstantiated_attachments(&mut self, k: u32) -> Result<(), db_error> {
+        self.delete_stored_contents(
+            "SELECT hash FROM attachments WHERE was_instantiated = 0 AND stored_externally = 1 AND hash IN (SELECT hash FROM attachments WHERE was_instantiated = 0 ORDER BY created_at ASC LIMIT ?)",
+            &[&k as &dyn ToSql],
+        )?;
         let tx = self.tx_begin()?;
         let res = tx.execute(
             "DELETE FROM attachments WHERE hash IN (SELECT hash FROM attachments WHERE was_instantiated = 0 ORDER BY created_at ASC LIMIT ?)",
@@ -355,6 +565,10 @@ impl AtlasDB {
     pub fn evict_expired_uninstantiated_attachments(&mut self) -> Result<(), db_error> {
         let now = util::get_epoch_time_secs() as i64;
         let cut_off = now - self.atlas_config.uninstantiated_attachments_expire_after as i64;
+        self.delete_stored_contents(
+            "SELECT hash FROM attachments WHERE was_instantiated = 0 AND stored_externally = 1 AND created_at < ?",
+            &[&cut_off as &dyn ToSql],
+        )?;
         let tx = self.tx_begin()?;
         let res = tx.execute(
             "DELETE FROM attachments WHERE was_instantiated = 0 AND created_at < ?",
@@ -383,14 +597,16 @@ impl AtlasDB {
         &mut self,
         attachment: &Attachment,
     ) -> Result<(), db_error> {
+        let (content, stored_externally) = self.store_content(attachment)?;
         let now = util::get_epoch_time_secs() as i64;
         let tx = self.tx_begin()?;
         tx.execute(
-            "INSERT OR REPLACE INTO attachments (hash, content, was_instantiated, created_at) VALUES (?, ?, 1, ?)",
+            "INSERT OR REPLACE INTO attachments (hash, content, was_instantiated, created_at, stored_externally) VALUES (?, ?, 1, ?, ?)",
             &[
                 &attachment.hash() as &dyn ToSql,
-                &attachment.content as &dyn ToSql,
+                &content as &dyn ToSql,
                 &now as &dyn ToSql,
+                &stored_externally as &dyn ToSql,
             ],
         )
         .map_err(db_error::SqliteError)?;
@@ -408,11 +624,13 @@ impl AtlasDB {
         content_hash: &Hash160,
     ) -> Result<Option<Attachment>, db_error> {
         let hex_content_hash = to_hex(&content_hash.0[..]);
-        let qry = "SELECT content, hash FROM attachments WHERE hash = ?1 AND was_instantiated = 0"
+        let qry = "SELECT content, hash, stored_externally FROM attachments WHERE hash = ?1 AND was_instantiated = 0"
             .to_string();
         let args = [&hex_content_hash as &dyn ToSql];
-        let row = query_row::<Attachment, _>(&self.conn, &qry, &args)?;
-        Ok(row)
+        match query_row::<StoredAttachment, _>(&self.conn, &qry, &args)? {
+            Some(stored) => self.load_content(stored),
+            None => Ok(None),
+        }
     }
 
     pub fn evict_expired_unresolved_attachment_instances(&mut self) -> Result<(), db_error> {
@@ -455,11 +673,13 @@ impl AtlasDB {
         content_hash: &Hash160,
     ) -> Result<Option<Attachment>, db_error> {
         let hex_content_hash = to_hex(&content_hash.0[..]);
-        let qry = "SELECT content, hash FROM attachments WHERE hash = ?1 AND was_instantiated = 1"
+        let qry = "SELECT content, hash, stored_externally FROM attachments WHERE hash = ?1 AND was_instantiated = 1"
             .to_string();
         let args = [&hex_content_hash as &dyn ToSql];
-        let row = query_row::<Attachment, _>(&self.conn, &qry, &args)?;
-        Ok(row)
+        match query_row::<StoredAttachment, _>(&self.conn, &qry, &args)? {
+            Some(stored) => self.load_content(stored),
+            None => Ok(None),
+        }
     }
 
     pub fn insert_uninstantiated_attachment_instance(
@@ -57,6 +57,19 @@ pub struct AttachmentsDownloader {
     ongoing_batch: Option<AttachmentsBatchStateMachine>,
     processed_batches: Vec<AttachmentsBatch>,
     reliability_reports: HashMap<UrlString, ReliabilityReport>,
+    /// Peers whose latest inventories list each attachment that is still missing
+    attachments_sources: HashMap<Hash160, HashSet<UrlString>>,
+}
+
+/// Where the downloader is at with a missing attachment
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentFetchState {
+    /// How many times the batch with this attachment was attempted and fell short
+    pub retry_count: u64,
+    /// When the batch can be attempted next, in seconds since the epoch
+    pub retry_deadline: u64,
+    /// Whether the batch is being attempted right now
+    pub in_flight: bool,
 }
 
 impl AttachmentsDownloader {
@@ -66,10 +79,83 @@ impl AttachmentsDownloader {
             ongoing_batch: None,
             processed_batches: vec![],
             reliability_reports: HashMap::new(),
+            attachments_sources: HashMap::new(),
             initial_batch,
         }
     }
 
+    /// How reliable each peer the downloader has used so far has been
+    pub fn get_reliability_reports(&self) -> &HashMap<UrlString, ReliabilityReport> {
+        &self.reliability_reports
+    }
+
+    /// The peers whose latest inventories list the given attachment
+    pub fn get_attachment_sources(&self, content_hash: &Hash160) -> Option<&HashSet<UrlString>> {
+        self.attachments_sources.get(content_hash)
+    }
+
+    /// The fetch state of every attachment in a queued or in-flight batch.  Attachments that are
+    /// missing but not in here were given up on after `max_attachment_retry_count` attempts.
+    pub fn get_fetch_states(&self) -> HashMap<Hash160, AttachmentFetchState> {
+        let mut states = HashMap::new();
+        let mut track = |batch: &AttachmentsBatch, in_flight: bool| {
+            for missing_attachments in batch.attachments_instances.values() {
+                for content_hash in missing_attachments.values() {
+                    states.insert(
+                        content_hash.clone(),
+                        AttachmentFetchState {
+                            retry_count: batch.retry_count,
+                            retry_deadline: batch.retry_deadline,
+                            in_flight,
+                        },
+                    );
+                }
+            }
+        };
+        for batch in self.priority_queue.iter() {
+            track(batch, false);
+        }
+        for attachment_instance in self.initial_batch.iter() {
+            let mut batch = AttachmentsBatch::new();
+            batch.track_attachment(attachment_instance);
+            track(&batch, false);
+        }
+        if let Some(ref ongoing_batch) = self.ongoing_batch {
+            track(&ongoing_batch.context().attachments_batch, true);
+        }
+        states
+    }
+
+    /// Queues the attachment instances still waiting for `content_hash` for an immediate
+    /// download, such as after the downloader gave up on them.  Returns how many instances were
+    /// queued, which is 0 if no instance is missing this attachment.
+    pub fn refetch_attachment(
+        &mut self,
+        content_hash: &Hash160,
+        atlasdb: &mut AtlasDB,
+    ) -> Result<usize, DBError> {
+        let mut attachments_batches: HashMap<StacksBlockId, AttachmentsBatch> = HashMap::new();
+        let mut count = 0;
+        for attachment_instance in atlasdb.find_unresolved_attachment_instances()? {
+            if attachment_instance.content_hash != *content_hash {
+                continue;
+            }
+            attachments_batches
+                .entry(attachment_instance.index_block_hash)
+                .or_insert_with(AttachmentsBatch::new)
+                .track_attachment(&attachment_instance);
+            count += 1;
+        }
+        for (_, batch) in attachments_batches.into_iter() {
+            info!(
+                "Atlas: re-enqueuing batch {:?} for re-fetch of {}",
+                &batch, content_hash
+            );
+            self.priority_queue.push(batch);
+        }
+        Ok(count)
+    }
+
     /// Identify whether or not any AttachmentBatches in the priority queue are ready for
     /// (re-)consideration by the downloader, based on whether or not its re-try deadline
     /// has passed.
@@ -189,7 +275,8 @@ impl AttachmentsDownloader {
                     }
                     context
                         .attachments_batch
-                        .resolve_attachment(&attachment.hash())
+                        .resolve_attachment(&attachment.hash());
+                    self.attachments_sources.remove(&attachment.hash());
                 }
 
                 // Carrying events for centralized deregistration
@@ -203,6 +290,11 @@ impl AttachmentsDownloader {
                     .atlasdb
                     .evict_expired_unresolved_attachment_instances()?;
 
+                // Remember which peers have the attachments still missing
+                for (content_hash, sources) in context.get_attachments_sources().into_iter() {
+                    self.attachments_sources.insert(content_hash, sources);
+                }
+
                 // Update reliability reports
                 for (peer_url, report) in context.peers.drain() {
                     self.reliability_reports.insert(peer_url, report);
@@ -399,25 +491,7 @@ impl AttachmentsBatchStateContext {
                     attachment_index % AttachmentInstance::ATTACHMENTS_INV_PAGE_SIZE;
 
                 for (peer_url, response) in peers_responses.iter() {
-                    // Considering the response, look for the page with the index
-                    // we're looking for.
-                    let index = response
-                        .pages
-                        .iter()
-                        .position(|page| page.index == page_index);
-
-                    let has_attachment = match index {
-                        Some(index) => match response.pages[index]
-                            .inventory
-                            .get(position_in_page as usize)
-                        {
-                            Some(result) if *result == 1 => true,
-                            _ => false,
-                        },
-                        None => false,
-                    };
-
-                    if !has_attachment {
+                    if !inventory_has_attachment(response, *attachment_index) {
                         debug!(
                             "Atlas: peer does not have attachment ({}, {}) in its inventory {:?}",
                             page_index, position_in_page, response.pages
@@ -451,6 +525,34 @@ impl AttachmentsBatchStateContext {
         queue
     }
 
+    /// For each attachment of the batch, the peers whose inventories list it
+    pub fn get_attachments_sources(&self) -> HashMap<Hash160, HashSet<UrlString>> {
+        let mut sources: HashMap<Hash160, HashSet<UrlString>> = HashMap::new();
+        for ((contract_id, pages, _), peers_responses) in self.inventories.iter() {
+            let missing_attachments = match self
+                .attachments_batch
+                .attachments_instances
+                .get(&contract_id)
+            {
+                None => continue,
+                Some(missing_attachments) => missing_attachments,
+            };
+            for (attachment_index, content_hash) in missing_attachments.iter() {
+                let page_index = attachment_index / AttachmentInstance::ATTACHMENTS_INV_PAGE_SIZE;
+                if !pages.contains(&page_index) {
+                    continue;
+                }
+                let peers = sources.entry(content_hash.clone()).or_default();
+                for (peer_url, response) in peers_responses.iter() {
+                    if inventory_has_attachment(response, *attachment_index) {
+                        peers.insert(peer_url.clone());
+                    }
+                }
+            }
+        }
+        sources
+    }
+
     pub fn extend_with_dns_lookups(
         mut self,
         results: &mut BatchedDNSLookupsResults,
@@ -524,6 +626,17 @@ impl AttachmentsBatchStateContext {
     }
 }
 
+/// Whether the page of an inventory response that covers `attachment_index` says that the peer
+/// has it
+fn inventory_has_attachment(response: &GetAttachmentsInvResponse, attachment_index: u32) -> bool {
+    let page_index = attachment_index / AttachmentInstance::ATTACHMENTS_INV_PAGE_SIZE;
+    let position_in_page = attachment_index % AttachmentInstance::ATTACHMENTS_INV_PAGE_SIZE;
+    match response.pages.iter().find(|page| page.index == page_index) {
+        Some(page) => page.inventory.get(position_in_page as usize) == Some(&1),
+        None => false,
+    }
+}
+
 #[derive(Debug)]
 enum AttachmentsBatchStateMachine {
     Initialized(AttachmentsBatchStateContext),
@@ -548,6 +661,16 @@ impl AttachmentsBatchStateMachine {
         AttachmentsBatchStateMachine::Initialized(ctx)
     }
 
+    fn context(&self) -> &AttachmentsBatchStateContext {
+        match self {
+            AttachmentsBatchStateMachine::Initialized(context)
+            | AttachmentsBatchStateMachine::DNSLookup((_, context))
+            | AttachmentsBatchStateMachine::DownloadingAttachmentsInv((_, context))
+            | AttachmentsBatchStateMachine::DownloadingAttachment((_, context))
+            | AttachmentsBatchStateMachine::Done(context) => context,
+        }
+    }
+
     /// Runs the state machine one step. The machine transitions through the states sequentially:
     /// `Initialized`, `DNSLookup` (which invokes a sub state machine, `BatchedDNSLookupsState`),
     /// `DownloadingAttachmentsInv`, `DownloadingAttachment`, and `Done`.
@@ -35,6 +35,7 @@ use crate::types::chainstate::BlockHeaderHash;
 
 pub use self::db::AtlasDB;
 pub use self::download::AttachmentsDownloader;
+pub use self::store::{AttachmentStore, AttachmentStoreConfig, ObjectStoreConfig};
 
 /// Implements AtlasDB and associated API. Stores information about attachments and attachment
 /// instances.
@@ -42,6 +43,12 @@ pub mod db;
 /// Implements `AttachmentsDownloader`, which attempts to download the requested batch of
 /// attachment instances from peers.
 pub mod download;
+/// Implements the S3-compatible object store that attachment contents can be kept in.
+#[cfg(feature = "atlas_object_store")]
+pub mod object_store;
+/// Implements the stores that attachment contents can be kept in: the Atlas DB itself, flat
+/// files, or an S3-compatible object store.
+pub mod store;
 
 pub const MAX_ATTACHMENT_INV_PAGES_PER_REQUEST: usize = 8;
 pub const MAX_RETRY_DELAY: u64 = 600; // seconds
@@ -62,6 +69,7 @@ pub struct AtlasConfig {
     pub uninstantiated_attachments_expire_after: u32,
     pub unresolved_attachment_instances_expire_after: u32,
     pub genesis_attachments: Option<Vec<Attachment>>,
+    pub attachment_store: AttachmentStoreConfig,
 }
 
 impl AtlasConfig {
@@ -75,6 +83,7 @@ impl AtlasConfig {
             uninstantiated_attachments_expire_after: 3_600,
             unresolved_attachment_instances_expire_after: 172_800,
             genesis_attachments: None,
+            attachment_store: AttachmentStoreConfig::Sqlite,
         }
     }
 }
@@ -0,0 +1,559 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2021 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Keeps attachment contents in an S3-compatible object store.
+//!
+//! The Atlas DB is used from the p2p thread, which must never wait on the network, so the store
+//! is fronted by a local copy of every attachment this node wrote or read.  Writes and deletes
+//! are applied to the local copy and queued for a worker thread, which sends them to the object
+//! store.  A read is served from the local copy; on a miss, the worker thread downloads the
+//! attachment into it, and the read reports it as missing until the download completes.
+
+use std::collections::HashSet;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use aws_sigv4::http_request::{
+    sign, PayloadChecksumKind, PercentEncodingMode, SignableBody, SignableRequest, SigningParams,
+    SigningSettings, UriPathNormalizationMode,
+};
+use reqwest::blocking::Client;
+use reqwest::header::{HeaderValue, HOST};
+use reqwest::{Method, StatusCode};
+use url::Url;
+
+use super::store::{AttachmentStore, FlatFileStore, ObjectStoreConfig};
+use crate::util_lib::db::Error as db_error;
+use stacks_common::util::hash::{to_hex, Hash160};
+
+/// How long the object store may take to accept a connection or answer a request
+const OBJECT_STORE_TIMEOUT: Duration = Duration::from_secs(30);
+/// How many times the worker thread sends a request before giving up on it
+const OBJECT_STORE_ATTEMPTS: u64 = 3;
+
+/// Work for the object store's worker thread
+#[derive(Debug)]
+enum ObjectStoreRequest {
+    /// Upload the local copy of this attachment
+    Upload(Hash160),
+    /// Download this attachment into the local copy
+    Download(Hash160),
+    Delete(Hash160),
+}
+
+/// Keeps each attachment as the object `<prefix><hash>` of a bucket in an S3-compatible object
+/// store, addressed path-style.
+#[derive(Debug)]
+pub struct ObjectStore {
+    cache: FlatFileStore,
+    /// Attachments queued for download, so that repeated reads queue them once
+    downloading: Arc<Mutex<HashSet<Hash160>>>,
+    requests: Option<Sender<ObjectStoreRequest>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl ObjectStore {
+    pub fn open(config: ObjectStoreConfig) -> Result<ObjectStore, db_error> {
+        let client = ObjectStoreClient::new(config)?;
+        let cache = FlatFileStore::open(&client.config.cache_path)?;
+        let worker_cache = FlatFileStore::open(&client.config.cache_path)?;
+        let downloading = Arc::new(Mutex::new(HashSet::new()));
+        let worker_downloading = downloading.clone();
+        let (requests, requests_rx) = channel();
+        let worker = thread::Builder::new()
+            .name("atlas-object-store".to_string())
+            .spawn(move || client.run(worker_cache, requests_rx, worker_downloading))
+            .map_err(db_error::IOError)?;
+        Ok(ObjectStore {
+            cache,
+            downloading,
+            requests: Some(requests),
+            worker: Some(worker),
+        })
+    }
+
+    fn send(&self, request: ObjectStoreRequest) -> Result<(), db_error> {
+        self.requests
+            .as_ref()
+            .and_then(|requests| requests.send(request).ok())
+            .ok_or_else(|| db_error::Other("Object store worker thread exited".to_string()))
+    }
+}
+
+impl AttachmentStore for ObjectStore {
+    fn put(&mut self, content_hash: &Hash160, content: &[u8]) -> Result<(), db_error> {
+        self.cache.put(content_hash, content)?;
+        self.send(ObjectStoreRequest::Upload(content_hash.clone()))
+    }
+
+    fn get(&self, content_hash: &Hash160) -> Result<Option<Vec<u8>>, db_error> {
+        if let Some(content) = self.cache.get(content_hash)? {
+            return Ok(Some(content));
+        }
+        let queued = self
+            .downloading
+            .lock()
+            .expect("FATAL: object store download set lock poisoned")
+            .insert(content_hash.clone());
+        if queued {
+            self.send(ObjectStoreRequest::Download(content_hash.clone()))?;
+        }
+        Ok(None)
+    }
+
+    fn delete(&mut self, content_hash: &Hash160) -> Result<(), db_error> {
+        self.cache.delete(content_hash)?;
+        self.send(ObjectStoreRequest::Delete(content_hash.clone()))
+    }
+}
+
+impl Drop for ObjectStore {
+    /// Waits for the worker thread to send the queued requests
+    fn drop(&mut self) {
+        self.requests.take();
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                error!("Atlas: object store worker thread panicked");
+            }
+        }
+    }
+}
+
+/// Sends signed requests to the object store, over TLS if the endpoint is an https:// URL.
+struct ObjectStoreClient {
+    config: ObjectStoreConfig,
+    endpoint: Url,
+    /// The `Host` header, which is signed, so it must be exactly what is sent
+    host: HeaderValue,
+    http: Client,
+}
+
+impl ObjectStoreClient {
+    fn new(config: ObjectStoreConfig) -> Result<ObjectStoreClient, db_error> {
+        let endpoint = Url::parse(&config.endpoint).map_err(|e| {
+            db_error::Other(format!(
+                "Invalid object store endpoint '{}': {}",
+                &config.endpoint, e
+            ))
+        })?;
+        if !(endpoint.scheme() == "https" || endpoint.scheme() == "http")
+            || endpoint.path() != "/"
+            || endpoint.query().is_some()
+        {
+            return Err(db_error::Other(format!(
+                "Object store endpoint '{}' should be a bare https://host[:port] URL",
+                &config.endpoint
+            )));
+        }
+        let host = match (endpoint.host_str(), endpoint.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => {
+                return Err(db_error::Other(format!(
+                    "Object store endpoint '{}' has no host",
+                    &config.endpoint
+                )))
+            }
+        };
+        // object keys are not percent-encoded, so keep them to characters that need none
+        let is_plain_key = |key: &str| {
+            key.bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"-_.~/".contains(&b))
+        };
+        if config.bucket.is_empty() || config.bucket.contains('/') || !is_plain_key(&config.bucket)
+        {
+            return Err(db_error::Other(format!(
+                "Invalid object store bucket name '{}'",
+                &config.bucket
+            )));
+        }
+        if !is_plain_key(&config.prefix) {
+            return Err(db_error::Other(format!(
+                "Object store prefix '{}' may only contain letters, digits and -_.~/",
+                &config.prefix
+            )));
+        }
+
+        let http = Client::builder()
+            .connect_timeout(OBJECT_STORE_TIMEOUT)
+            .timeout(OBJECT_STORE_TIMEOUT)
+            .build()
+            .map_err(|e| db_error::Other(format!("Failed to build object store client: {}", e)))?;
+        Ok(ObjectStoreClient {
+            host: HeaderValue::from_str(&host)
+                .map_err(|_| db_error::Other(format!("Invalid object store host '{}'", host)))?,
+            config,
+            endpoint,
+            http,
+        })
+    }
+
+    fn url(&self, content_hash: &Hash160) -> String {
+        format!(
+            "{}://{}/{}/{}{}",
+            self.endpoint.scheme(),
+            self.host.to_str().expect("BUG: host header is not ASCII"),
+            &self.config.bucket,
+            &self.config.prefix,
+            to_hex(&content_hash.0[..])
+        )
+    }
+
+    /// Sends a request signed with AWS signature version 4, and returns the response's status
+    /// code and body.
+    fn request(
+        &self,
+        method: Method,
+        content_hash: &Hash160,
+        body: Vec<u8>,
+    ) -> Result<(StatusCode, Vec<u8>), String> {
+        let url = self.url(content_hash);
+        let mut request = http::Request::builder()
+            .method(method.clone())
+            .uri(&url)
+            .header(HOST, self.host.clone())
+            .body(())
+            .map_err(|e| format!("Invalid object store request {}: {}", &url, e))?;
+
+        let mut settings = SigningSettings::default();
+        settings.payload_checksum_kind = PayloadChecksumKind::XAmzSha256;
+        settings.percent_encoding_mode = PercentEncodingMode::Single;
+        settings.uri_path_normalization_mode = UriPathNormalizationMode::Disabled;
+        let params = SigningParams::builder()
+            .access_key(&self.config.access_key_id)
+            .secret_key(&self.config.secret_access_key)
+            .region(&self.config.region)
+            .service_name("s3")
+            .time(SystemTime::now())
+            .settings(settings)
+            .build()
+            .map_err(|e| format!("Failed to sign object store request: {}", e))?;
+        let signable = SignableRequest::new(
+            request.method(),
+            request.uri(),
+            request.headers(),
+            SignableBody::Bytes(&body),
+        );
+        let (instructions, _signature) = sign(signable, &params)
+            .map_err(|e| format!("Failed to sign object store request: {}", e))?
+            .into_parts();
+        instructions.apply_to_request(&mut request);
+
+        let response = self
+            .http
+            .request(method, &url)
+            .headers(request.headers().clone())
+            .body(body)
+            .send()
+            .map_err(|e| format!("Object store request {} failed: {}", &url, e))?;
+        let status = response.status();
+        let body = response
+            .bytes()
+            .map_err(|e| format!("Object store request {} failed: {}", &url, e))?;
+        Ok((status, body.to_vec()))
+    }
+
+    /// Sends a request until the object store gives an answer that `expected` accepts.
+    fn request_with_retries<T, F>(
+        &self,
+        method: Method,
+        content_hash: &Hash160,
+        body: &[u8],
+        expected: F,
+    ) -> Result<T, String>
+    where
+        F: Fn(StatusCode, Vec<u8>) -> Option<T>,
+    {
+        let mut last_error = String::new();
+        for attempt in 0..OBJECT_STORE_ATTEMPTS {
+            if attempt > 0 {
+                thread::sleep(Duration::from_secs(attempt));
+            }
+            match self.request(method.clone(), content_hash, body.to_vec()) {
+                Ok((status, response)) => {
+                    let error_body = String::from_utf8_lossy(&response).to_string();
+                    match expected(status, response) {
+                        Some(result) => return Ok(result),
+                        None => {
+                            last_error = format!(
+                                "Object store: {} {} returned status {}: {}",
+                                &method,
+                                self.url(content_hash),
+                                status,
+                                error_body
+                            );
+                        }
+                    }
+                }
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Handles requests until the `ObjectStore` is dropped.
+    fn run(
+        self,
+        mut cache: FlatFileStore,
+        requests: Receiver<ObjectStoreRequest>,
+        downloading: Arc<Mutex<HashSet<Hash160>>>,
+    ) {
+        for request in requests.iter() {
+            match request {
+                ObjectStoreRequest::Upload(content_hash) => {
+                    // it may have been deleted since
+                    let content = match cache.get(&content_hash) {
+                        Ok(Some(content)) => content,
+                        Ok(None) => continue,
+                        Err(e) => {
+                            warn!(
+                                "Atlas: failed to read attachment {} to upload: {:?}",
+                                &content_hash, &e
+                            );
+                            continue;
+                        }
+                    };
+                    if let Err(e) =
+                        self.request_with_retries(Method::PUT, &content_hash, &content, |s, _| {
+                            s.is_success().then(|| ())
+                        })
+                    {
+                        warn!(
+                            "Atlas: failed to upload attachment {}: {}",
+                            &content_hash, &e
+                        );
+                    }
+                }
+                ObjectStoreRequest::Download(content_hash) => {
+                    match self.request_with_retries(Method::GET, &content_hash, &[], |s, body| {
+                        if s == StatusCode::NOT_FOUND {
+                            Some(None)
+                        } else if s.is_success() {
+                            Some(Some(body))
+                        } else {
+                            None
+                        }
+                    }) {
+                        Ok(Some(content)) if Hash160::from_data(&content) == content_hash => {
+                            if let Err(e) = cache.put(&content_hash, &content) {
+                                warn!(
+                                    "Atlas: failed to save downloaded attachment {}: {:?}",
+                                    &content_hash, &e
+                                );
+                            }
+                        }
+                        Ok(Some(_)) => warn!(
+                            "Atlas: object store returned the wrong content for attachment {}",
+                            &content_hash
+                        ),
+                        Ok(None) => warn!(
+                            "Atlas: attachment {} is missing from the object store",
+                            &content_hash
+                        ),
+                        Err(e) => warn!(
+                            "Atlas: failed to download attachment {}: {}",
+                            &content_hash, &e
+                        ),
+                    }
+                    downloading
+                        .lock()
+                        .expect("FATAL: object store download set lock poisoned")
+                        .remove(&content_hash);
+                }
+                ObjectStoreRequest::Delete(content_hash) => {
+                    if let Err(e) =
+                        self.request_with_retries(Method::DELETE, &content_hash, &[], |s, _| {
+                            (s.is_success() || s == StatusCode::NOT_FOUND).then(|| ())
+                        })
+                    {
+                        warn!(
+                            "Atlas: failed to delete attachment {}: {}",
+                            &content_hash, &e
+                        );
+                    }
+                    // a download queued before the delete may have restored the local copy
+                    if let Err(e) = cache.delete(&content_hash) {
+                        warn!(
+                            "Atlas: failed to delete attachment {}: {:?}",
+                            &content_hash, &e
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::path::Path;
+    use std::time::Instant;
+
+    /// Serves a bucket out of memory, one request per connection.  Signatures are not checked,
+    /// but requests must carry one.
+    fn spawn_object_store(objects: Arc<Mutex<HashMap<String, Vec<u8>>>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = vec![];
+                let mut buf = [0u8; 4096];
+                let (head, content_length) = loop {
+                    let n = stream.read(&mut buf).unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                        let head = String::from_utf8_lossy(&request[..end]).to_lowercase();
+                        let content_length = head
+                            .lines()
+                            .find_map(|line| line.strip_prefix("content-length: "))
+                            .map(|len| len.parse::<usize>().unwrap())
+                            .unwrap_or(0);
+                        request.drain(..end + 4);
+                        break (head, content_length);
+                    }
+                };
+                while request.len() < content_length {
+                    let n = stream.read(&mut buf).unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                }
+                assert!(head.contains("authorization: aws4-hmac-sha256 credential=key/"));
+                assert!(head.contains("x-amz-content-sha256: "));
+                assert!(head.contains(&format!("host: {}", address)));
+
+                let mut request_line = head.lines().next().unwrap().split(' ');
+                let method = request_line.next().unwrap().to_string();
+                let path = request_line.next().unwrap().to_string();
+                let mut objects = objects.lock().unwrap();
+                let (status, body) = match method.as_str() {
+                    "put" => {
+                        objects.insert(path, request);
+                        ("200 OK", vec![])
+                    }
+                    "get" => match objects.get(&path) {
+                        Some(content) => ("200 OK", content.clone()),
+                        None => ("404 Not Found", b"NoSuchKey".to_vec()),
+                    },
+                    _ => {
+                        objects.remove(&path);
+                        ("204 No Content", vec![])
+                    }
+                };
+                let mut response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                )
+                .into_bytes();
+                response.extend_from_slice(&body);
+                stream.write_all(&response).unwrap();
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    fn object_store_config(endpoint: &str, cache_path: &str) -> ObjectStoreConfig {
+        ObjectStoreConfig {
+            endpoint: endpoint.to_string(),
+            bucket: "bucket".to_string(),
+            region: "us-east-1".to_string(),
+            prefix: "atlas/".to_string(),
+            access_key_id: "key".to_string(),
+            secret_access_key: "secret".to_string(),
+            cache_path: cache_path.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_object_store() {
+        let cache_path = "/tmp/test_object_store_cache";
+        let other_cache_path = "/tmp/test_object_store_other_cache";
+        let _ = fs::remove_dir_all(cache_path);
+        let _ = fs::remove_dir_all(other_cache_path);
+        let objects = Arc::new(Mutex::new(HashMap::new()));
+        let endpoint = spawn_object_store(objects.clone());
+        let content_hash = Hash160::from_data(b"hello");
+        let object_path = format!("/bucket/atlas/{}", content_hash);
+
+        // writes are served from the local copy right away, and uploaded by the worker thread,
+        // which is done once the store is dropped
+        let mut store = ObjectStore::open(object_store_config(&endpoint, cache_path)).unwrap();
+        store.put(&content_hash, b"hello").unwrap();
+        assert_eq!(store.get(&content_hash).unwrap(), Some(b"hello".to_vec()));
+        drop(store);
+        assert_eq!(
+            objects.lock().unwrap().get(&object_path),
+            Some(&b"hello".to_vec())
+        );
+
+        // a node without a local copy reports the attachment missing, without waiting on the
+        // object store, until the worker thread has downloaded it
+        let mut store =
+            ObjectStore::open(object_store_config(&endpoint, other_cache_path)).unwrap();
+        assert_eq!(store.get(&content_hash).unwrap(), None);
+        let deadline = Instant::now() + Duration::from_secs(30);
+        let content = loop {
+            if let Some(content) = store.get(&content_hash).unwrap() {
+                break content;
+            }
+            assert!(Instant::now() < deadline, "attachment was never downloaded");
+            thread::sleep(Duration::from_millis(100));
+        };
+        assert_eq!(content, b"hello".to_vec());
+
+        store.delete(&content_hash).unwrap();
+        assert!(!Path::new(other_cache_path)
+            .join(&to_hex(&content_hash.0[..])[0..2])
+            .join(to_hex(&content_hash.0[..]))
+            .exists());
+        // missing objects are reported missing
+        let missing_hash = Hash160::from_data(b"missing");
+        assert_eq!(store.get(&missing_hash).unwrap(), None);
+        drop(store);
+        assert!(objects.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_object_store_config() {
+        let open = |endpoint: &str, bucket: &str, prefix: &str| {
+            let mut config = object_store_config(endpoint, "/tmp/test_object_store_config");
+            config.bucket = bucket.to_string();
+            config.prefix = prefix.to_string();
+            ObjectStoreClient::new(config).map(|client| client.url(&Hash160([0u8; 20])))
+        };
+        assert_eq!(
+            open("https://s3.us-east-1.amazonaws.com", "bucket", "atlas/").unwrap(),
+            "https://s3.us-east-1.amazonaws.com/bucket/atlas/0000000000000000000000000000000000000000"
+        );
+        assert_eq!(
+            open("http://127.0.0.1:9000/", "bucket", "").unwrap(),
+            "http://127.0.0.1:9000/bucket/0000000000000000000000000000000000000000"
+        );
+        assert!(open("ftp://minio", "bucket", "").is_err());
+        assert!(open("https://minio/bucket", "bucket", "").is_err());
+        assert!(open("https://minio", "", "").is_err());
+        assert!(open("https://minio", "a/b", "").is_err());
+        assert!(open("https://minio", "bucket", "a b/").is_err());
+    }
+}
@@ -0,0 +1,154 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2021 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::util_lib::db::Error as db_error;
+use stacks_common::util::hash::{to_hex, Hash160};
+
+/// Where the Atlas DB keeps attachment contents.  Attachments and attachment instances are
+/// always tracked in the Atlas DB itself.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttachmentStoreConfig {
+    /// In the Atlas DB, next to the attachments' metadata
+    Sqlite,
+    /// One file per attachment, under this directory
+    FlatFiles(String),
+    /// One object per attachment, in an S3-compatible bucket.  Only available in builds with the
+    /// `atlas_object_store` feature.
+    ObjectStore(ObjectStoreConfig),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectStoreConfig {
+    /// Base URL of the object store, like `https://s3.us-east-1.amazonaws.com`
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    /// Prepended to the attachments' object keys
+    pub prefix: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Directory of the local copy of the attachments this node wrote or read, which is what the
+    /// node serves attachments from
+    pub cache_path: String,
+}
+
+/// Storage for attachment contents, keyed by their hash.
+pub trait AttachmentStore: fmt::Debug + Send {
+    /// Stores `content`, replacing whatever was stored under `content_hash`
+    fn put(&mut self, content_hash: &Hash160, content: &[u8]) -> Result<(), db_error>;
+    fn get(&self, content_hash: &Hash160) -> Result<Option<Vec<u8>>, db_error>;
+    /// Deletes the content stored under `content_hash`, if any
+    fn delete(&mut self, content_hash: &Hash160) -> Result<(), db_error>;
+}
+
+impl AttachmentStoreConfig {
+    /// Opens the configured store, or returns `None` if contents are kept in the Atlas DB.
+    pub fn open(&self) -> Result<Option<Box<dyn AttachmentStore>>, db_error> {
+        match self {
+            AttachmentStoreConfig::Sqlite => Ok(None),
+            AttachmentStoreConfig::FlatFiles(path) => {
+                Ok(Some(Box::new(FlatFileStore::open(path)?)))
+            }
+            #[cfg(feature = "atlas_object_store")]
+            AttachmentStoreConfig::ObjectStore(config) => Ok(Some(Box::new(
+                super::object_store::ObjectStore::open(config.clone())?,
+            ))),
+            #[cfg(not(feature = "atlas_object_store"))]
+            AttachmentStoreConfig::ObjectStore(_) => Err(db_error::Other(
+                "This build has no object store support; rebuild with the `atlas_object_store` feature"
+                    .to_string(),
+            )),
+        }
+    }
+}
+
+/// Keeps each attachment in `<root>/<first 2 hex digits of its hash>/<hash>`.
+#[derive(Debug)]
+pub struct FlatFileStore {
+    root: PathBuf,
+}
+
+impl FlatFileStore {
+    pub fn open(root: &str) -> Result<FlatFileStore, db_error> {
+        fs::create_dir_all(root).map_err(db_error::IOError)?;
+        Ok(FlatFileStore {
+            root: PathBuf::from(root),
+        })
+    }
+
+    fn path(&self, content_hash: &Hash160) -> PathBuf {
+        let hex_content_hash = to_hex(&content_hash.0[..]);
+        let mut path = self.root.clone();
+        path.push(&hex_content_hash[0..2]);
+        path.push(&hex_content_hash);
+        path
+    }
+}
+
+impl AttachmentStore for FlatFileStore {
+    fn put(&mut self, content_hash: &Hash160, content: &[u8]) -> Result<(), db_error> {
+        let path = self.path(content_hash);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(db_error::IOError)?;
+        }
+        // write then rename, so a crash never leaves a truncated attachment behind
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, content).map_err(db_error::IOError)?;
+        fs::rename(&tmp_path, &path).map_err(db_error::IOError)?;
+        Ok(())
+    }
+
+    fn get(&self, content_hash: &Hash160) -> Result<Option<Vec<u8>>, db_error> {
+        match fs::read(self.path(content_hash)) {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(db_error::IOError(e)),
+        }
+    }
+
+    fn delete(&mut self, content_hash: &Hash160) -> Result<(), db_error> {
+        match fs::remove_file(self.path(content_hash)) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(db_error::IOError(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flat_file_store() {
+        let root = "/tmp/test_flat_file_attachment_store";
+        let _ = fs::remove_dir_all(root);
+        let mut store = FlatFileStore::open(root).unwrap();
+        let content_hash = Hash160::from_data(b"hello");
+
+        assert_eq!(store.get(&content_hash).unwrap(), None);
+        store.put(&content_hash, b"hello").unwrap();
+        assert_eq!(store.get(&content_hash).unwrap(), Some(b"hello".to_vec()));
+        store.delete(&content_hash).unwrap();
+        assert_eq!(store.get(&content_hash).unwrap(), None);
+        store.delete(&content_hash).unwrap();
+    }
+}
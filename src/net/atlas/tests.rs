@@ -32,13 +32,13 @@ use crate::util_lib::strings::UrlString;
 use clarity::vm::types::QualifiedContractIdentifier;
 use stacks_common::types::chainstate::BlockHeaderHash;
 use stacks_common::types::chainstate::StacksBlockId;
-use stacks_common::util::hash::Hash160;
+use stacks_common::util::hash::{to_hex, Hash160};
 
 use super::download::{
-    AttachmentRequest, AttachmentsBatch, AttachmentsBatchStateContext, AttachmentsInventoryRequest,
-    BatchedRequestsResult, ReliabilityReport,
+    AttachmentRequest, AttachmentsBatch, AttachmentsBatchStateContext, AttachmentsDownloader,
+    AttachmentsInventoryRequest, BatchedRequestsResult, ReliabilityReport,
 };
-use super::{AtlasConfig, AtlasDB, Attachment, AttachmentInstance};
+use super::{AtlasConfig, AtlasDB, Attachment, AttachmentInstance, AttachmentStoreConfig};
 
 fn new_attachment_from(content: &str) -> Attachment {
     Attachment {
@@ -734,6 +734,83 @@ fn test_downloader_context_attachment_requests() {
     let request = attachments_requests.pop().unwrap();
     let request_type = request.make_request_type(localhost.clone());
     assert_eq!(request.get_url(), &peer_url_1);
+
+    let sources = context.get_attachments_sources();
+    assert_eq!(
+        sources[&attachment_4.hash()],
+        vec![peer_url_3.clone()].into_iter().collect::<HashSet<_>>()
+    );
+    assert_eq!(
+        sources[&attachment_1.hash()],
+        vec![peer_url_1.clone(), peer_url_2.clone()]
+            .into_iter()
+            .collect::<HashSet<_>>()
+    );
+    assert_eq!(
+        sources[&attachment_3.hash()],
+        vec![peer_url_1, peer_url_2, peer_url_3]
+            .into_iter()
+            .collect::<HashSet<_>>()
+    );
+}
+
+#[test]
+fn test_downloader_refetch_attachment() {
+    let atlas_config = AtlasConfig {
+        contracts: HashSet::new(),
+        attachments_max_size: 1024,
+        max_uninstantiated_attachments: 10,
+        uninstantiated_attachments_expire_after: 10,
+        unresolved_attachment_instances_expire_after: 10,
+        genesis_attachments: None,
+        attachment_store: AttachmentStoreConfig::Sqlite,
+    };
+    let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+
+    let attachment_1 = new_attachment_from("facade01");
+    let attachment_2 = new_attachment_from("facade02");
+    // the index block hashes and txids must not be all digits, which sqlite would turn into
+    // numbers in their `STRING` columns
+    for mut instance in vec![
+        new_attachment_instance_from(&attachment_1, 0, 10),
+        new_attachment_instance_from(&attachment_1, 3, 11),
+        new_attachment_instance_from(&attachment_2, 1, 10),
+    ] {
+        instance.tx_id = Txid([0xab; 32]);
+        atlas_db
+            .insert_uninstantiated_attachment_instance(&instance, false)
+            .unwrap();
+    }
+
+    // Nothing is queued, as if the downloader had given up
+    let mut downloader = AttachmentsDownloader::new(vec![]);
+    assert!(downloader.get_fetch_states().is_empty());
+
+    assert_eq!(
+        downloader
+            .refetch_attachment(&attachment_1.hash(), &mut atlas_db)
+            .unwrap(),
+        2
+    );
+    let fetch_states = downloader.get_fetch_states();
+    assert_eq!(fetch_states.len(), 1);
+    let fetch_state = &fetch_states[&attachment_1.hash()];
+    assert_eq!(fetch_state.retry_count, 0);
+    assert_eq!(fetch_state.retry_deadline, 0);
+    assert!(!fetch_state.in_flight);
+    // Both instances are ready to be fetched right away, in one batch per block
+    assert!(downloader.has_ready_batches());
+    assert!(downloader.pop_next_ready_batch().is_some());
+    assert!(downloader.pop_next_ready_batch().is_some());
+    assert!(downloader.pop_next_ready_batch().is_none());
+
+    // No instance is waiting for an unknown attachment
+    assert_eq!(
+        downloader
+            .refetch_attachment(&new_attachment_from("facade03").hash(), &mut atlas_db)
+            .unwrap(),
+        0
+    );
 }
 
 #[test]
@@ -751,6 +828,7 @@ fn test_keep_uninstantiated_attachments() {
         uninstantiated_attachments_expire_after: 10,
         unresolved_attachment_instances_expire_after: 10,
         genesis_attachments: None,
+        attachment_store: AttachmentStoreConfig::Sqlite,
     };
 
     let atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
@@ -783,6 +861,7 @@ fn test_evict_k_oldest_uninstantiated_attachments() {
         uninstantiated_attachments_expire_after: 0,
         unresolved_attachment_instances_expire_after: 10,
         genesis_attachments: None,
+        attachment_store: AttachmentStoreConfig::Sqlite,
     };
 
     let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
@@ -931,6 +1010,7 @@ fn test_evict_expired_uninstantiated_attachments() {
         uninstantiated_attachments_expire_after: 10,
         unresolved_attachment_instances_expire_after: 10,
         genesis_attachments: None,
+        attachment_store: AttachmentStoreConfig::Sqlite,
     };
 
     let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
@@ -1000,6 +1080,7 @@ fn test_evict_expired_unresolved_attachment_instances() {
         uninstantiated_attachments_expire_after: 200,
         unresolved_attachment_instances_expire_after: 10,
         genesis_attachments: None,
+        attachment_store: AttachmentStoreConfig::Sqlite,
     };
     let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
 
@@ -1059,6 +1140,147 @@ fn test_evict_expired_unresolved_attachment_instances() {
     assert_eq!(atlas_db.count_unresolved_attachment_instances().unwrap(), 3);
 }
 
+#[test]
+fn test_flat_file_attachment_store() {
+    let root = "/tmp/test_flat_file_attachment_store_atlasdb";
+    let _ = std::fs::remove_dir_all(root);
+    let atlas_config = AtlasConfig {
+        contracts: HashSet::new(),
+        attachments_max_size: 1024,
+        max_uninstantiated_attachments: 1,
+        uninstantiated_attachments_expire_after: 10,
+        unresolved_attachment_instances_expire_after: 10,
+        genesis_attachments: None,
+        attachment_store: AttachmentStoreConfig::FlatFiles(root.to_string()),
+    };
+
+    let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+
+    let instantiated = new_attachment_from("facade00");
+    atlas_db
+        .insert_instantiated_attachment(&instantiated)
+        .unwrap();
+    assert_eq!(
+        atlas_db.find_attachment(&instantiated.hash()).unwrap(),
+        Some(instantiated.clone())
+    );
+
+    let uninstantiated = new_attachment_from("facade01");
+    atlas_db
+        .insert_uninstantiated_attachment(&uninstantiated)
+        .unwrap();
+    assert_eq!(
+        atlas_db
+            .find_uninstantiated_attachment(&uninstantiated.hash())
+            .unwrap(),
+        Some(uninstantiated.clone())
+    );
+
+    // The contents are not in the DB
+    let contents: Vec<Vec<u8>> = atlas_db
+        .conn()
+        .prepare("SELECT content FROM attachments")
+        .unwrap()
+        .query_map(rusqlite::NO_PARAMS, |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(contents, vec![Vec::<u8>::new(), vec![]]);
+
+    // Evicting an attachment deletes its content from the store
+    let evicted_path = format!(
+        "{}/{}/{}",
+        root,
+        &to_hex(&uninstantiated.hash().0[..])[..2],
+        to_hex(&uninstantiated.hash().0[..])
+    );
+    assert!(std::path::Path::new(&evicted_path).exists());
+    atlas_db
+        .evict_k_oldest_uninstantiated_attachments(1)
+        .unwrap();
+    assert!(!std::path::Path::new(&evicted_path).exists());
+    assert_eq!(
+        atlas_db.find_attachment(&instantiated.hash()).unwrap(),
+        Some(instantiated)
+    );
+}
+
+#[test]
+fn test_migrate_attachment_contents() {
+    let path = "/tmp/test_migrate_attachment_contents.sqlite".to_string();
+    let root = "/tmp/test_migrate_attachment_contents";
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_dir_all(root);
+    let atlas_config = |attachment_store| AtlasConfig {
+        contracts: HashSet::new(),
+        attachments_max_size: 1024,
+        max_uninstantiated_attachments: 10,
+        uninstantiated_attachments_expire_after: 10,
+        unresolved_attachment_instances_expire_after: 10,
+        genesis_attachments: None,
+        attachment_store,
+    };
+    let flat_files = AttachmentStoreConfig::FlatFiles(root.to_string());
+    let attachments: Vec<_> = (0..3)
+        .map(|i| new_attachment_from(&format!("facade0{}", i)))
+        .collect();
+
+    let mut atlas_db =
+        AtlasDB::connect(atlas_config(AttachmentStoreConfig::Sqlite), &path, true).unwrap();
+    for attachment in attachments.iter() {
+        atlas_db.insert_instantiated_attachment(attachment).unwrap();
+    }
+    drop(atlas_db);
+
+    // sqlite -> flat files
+    let mut atlas_db = AtlasDB::connect(atlas_config(flat_files.clone()), &path, true).unwrap();
+    assert_eq!(
+        atlas_db
+            .migrate_attachment_contents(&AttachmentStoreConfig::Sqlite)
+            .unwrap(),
+        3
+    );
+    // Contents already in place are not moved again
+    assert_eq!(
+        atlas_db
+            .migrate_attachment_contents(&AttachmentStoreConfig::Sqlite)
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        atlas_db.migrate_attachment_contents(&flat_files).unwrap(),
+        0
+    );
+    for attachment in attachments.iter() {
+        assert_eq!(
+            atlas_db
+                .find_attachment(&attachment.hash())
+                .unwrap()
+                .as_ref(),
+            Some(attachment)
+        );
+    }
+    drop(atlas_db);
+
+    // flat files -> sqlite
+    let mut atlas_db =
+        AtlasDB::connect(atlas_config(AttachmentStoreConfig::Sqlite), &path, true).unwrap();
+    assert_eq!(
+        atlas_db.migrate_attachment_contents(&flat_files).unwrap(),
+        3
+    );
+    let _ = std::fs::remove_dir_all(root);
+    for attachment in attachments.iter() {
+        assert_eq!(
+            atlas_db
+                .find_attachment(&attachment.hash())
+                .unwrap()
+                .as_ref(),
+            Some(attachment)
+        );
+    }
+}
+
 #[test]
 fn test_get_minmax_heights_atlasdb() {
     let atlas_config = AtlasConfig {
@@ -1068,6 +1290,7 @@ fn test_get_minmax_heights_atlasdb() {
         uninstantiated_attachments_expire_after: 10,
         unresolved_attachment_instances_expire_after: 10,
         genesis_attachments: None,
+        attachment_store: AttachmentStoreConfig::Sqlite,
     };
 
     let atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
@@ -1088,6 +1311,7 @@ fn test_bit_vectors() {
         uninstantiated_attachments_expire_after: 10,
         unresolved_attachment_instances_expire_after: 10,
         genesis_attachments: None,
+        attachment_store: AttachmentStoreConfig::Sqlite,
     };
 
     let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
@@ -310,6 +310,9 @@ pub struct ConversationP2P {
 
     pub data_url: UrlString, // where does this peer's data live?  Set to a 0-length string if not known.
 
+    pub peer_min_tx_fee_rate: f64, // lowest fee rate the peer will accept transactions at.  0 if not known.
+    pub announced_min_tx_fee_rate: f64, // lowest fee rate we last told the peer we accept.
+
     // highest block height and consensus hash this peer has seen
     pub burnchain_tip_height: u64,
     pub burnchain_tip_burn_header_hash: BurnchainHeaderHash,
@@ -514,6 +517,9 @@ impl ConversationP2P {
 
             data_url: UrlString::try_from("".to_string()).unwrap(),
 
+            peer_min_tx_fee_rate: 0f64,
+            announced_min_tx_fee_rate: 0f64,
+
             burnchain_tip_height: 0,
             burnchain_tip_burn_header_hash: BurnchainHeaderHash::zero(),
             burnchain_stable_tip_height: 0,
@@ -635,6 +641,13 @@ impl ConversationP2P {
         (peer_services & expected_bits) == expected_bits
     }
 
+    /// Does this remote neighbor understand the minimum fee rate in handshakes?  It will if it
+    /// has the FEE_FILTER bit set.
+    pub fn supports_fee_filter(peer_services: u16) -> bool {
+        let expected_bits = ServiceFlags::FEE_FILTER as u16;
+        (peer_services & expected_bits) == expected_bits
+    }
+
     /// Determine whether or not a given (height, burn_header_hash) pair _disagrees_ with our
     /// burnchain view.  If it does, return true.  If it doesn't (including if the given pair is
     /// simply absent from the chain_view), then return False.
@@ -1050,6 +1063,9 @@ impl ConversationP2P {
         self.handshake_addrbytes = handshake_data.addrbytes.clone();
         self.handshake_port = handshake_data.port;
         self.data_url = handshake_data.data_url.clone();
+        if let Some(min_tx_fee_rate) = handshake_data.min_tx_fee_rate {
+            self.peer_min_tx_fee_rate = min_tx_fee_rate;
+        }
 
         let mut updated = false;
         let cur_pubk_opt = self.connection.get_public_key();
@@ -1180,7 +1196,12 @@ impl ConversationP2P {
             );
         }
 
-        let accept_data = HandshakeAcceptData::new(local_peer, self.heartbeat);
+        let mut accept_data = HandshakeAcceptData::new(local_peer, self.heartbeat);
+        if ConversationP2P::supports_fee_filter(self.peer_services) {
+            // only peers that understand it can parse the trailing fee rate
+            accept_data.handshake.min_tx_fee_rate = Some(self.connection.options.min_tx_fee_rate);
+            self.announced_min_tx_fee_rate = self.connection.options.min_tx_fee_rate;
+        }
         let accept = StacksMessage::from_chain_view(
             self.version,
             self.network_id,
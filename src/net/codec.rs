@@ -573,12 +573,13 @@ impl HandshakeData {
             ),
             expire_block_height: local_peer.private_key_expire,
             data_url: data_url,
+            min_tx_fee_rate: None,
         }
     }
-}
 
-impl StacksMessageCodec for HandshakeData {
-    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+    /// Serialize everything but the optional minimum fee rate, which goes at the end of the
+    /// message.
+    fn serialize_fields<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
         write_next(fd, &self.addrbytes)?;
         write_next(fd, &self.port)?;
         write_next(fd, &self.services)?;
@@ -588,7 +589,7 @@ impl StacksMessageCodec for HandshakeData {
         Ok(())
     }
 
-    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<HandshakeData, codec_error> {
+    fn deserialize_fields<R: Read>(fd: &mut R) -> Result<HandshakeData, codec_error> {
         let addrbytes: PeerAddress = read_next(fd)?;
         let port: u16 = read_next(fd)?;
         if port == 0 {
@@ -608,10 +609,68 @@ impl StacksMessageCodec for HandshakeData {
             node_public_key,
             expire_block_height,
             data_url,
+            min_tx_fee_rate: None,
         })
     }
 }
 
+/// Write the optional minimum fee rate that ends a handshake message, as the bits of an f64.
+fn write_min_tx_fee_rate<W: Write>(
+    fd: &mut W,
+    min_tx_fee_rate: &Option<f64>,
+) -> Result<(), codec_error> {
+    if let Some(min_tx_fee_rate) = min_tx_fee_rate {
+        write_next(fd, &min_tx_fee_rate.to_bits())?;
+    }
+    Ok(())
+}
+
+/// Read the optional minimum fee rate that ends a handshake message.  Messages are framed by
+/// their preamble's payload length, so it is present if and only if there are bytes left.
+fn read_min_tx_fee_rate<R: Read>(fd: &mut R) -> Result<Option<f64>, codec_error> {
+    let mut bytes = [0u8; 8];
+    let mut num_read = 0;
+    while num_read < bytes.len() {
+        let n = fd
+            .read(&mut bytes[num_read..])
+            .map_err(codec_error::ReadError)?;
+        if n == 0 {
+            break;
+        }
+        num_read += n;
+    }
+    if num_read == 0 {
+        return Ok(None);
+    }
+    if num_read < bytes.len() {
+        return Err(codec_error::DeserializeError(
+            "Invalid handshake data: truncated minimum fee rate".to_string(),
+        ));
+    }
+    let min_tx_fee_rate = f64::from_bits(u64::from_be_bytes(bytes));
+    if !min_tx_fee_rate.is_finite() || min_tx_fee_rate < 0f64 {
+        return Err(codec_error::DeserializeError(format!(
+            "Invalid handshake data: minimum fee rate is {}",
+            min_tx_fee_rate
+        )));
+    }
+    Ok(Some(min_tx_fee_rate))
+}
+
+impl StacksMessageCodec for HandshakeData {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        self.serialize_fields(fd)?;
+        write_min_tx_fee_rate(fd, &self.min_tx_fee_rate)?;
+        Ok(())
+    }
+
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<HandshakeData, codec_error> {
+        let mut handshake = HandshakeData::deserialize_fields(fd)?;
+        handshake.min_tx_fee_rate = read_min_tx_fee_rate(fd)?;
+        Ok(handshake)
+    }
+}
+
 impl HandshakeAcceptData {
     pub fn new(local_peer: &LocalPeer, heartbeat_interval: u32) -> HandshakeAcceptData {
         HandshakeAcceptData {
@@ -623,14 +682,16 @@ impl HandshakeAcceptData {
 
 impl StacksMessageCodec for HandshakeAcceptData {
     fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
-        write_next(fd, &self.handshake)?;
+        self.handshake.serialize_fields(fd)?;
         write_next(fd, &self.heartbeat_interval)?;
+        write_min_tx_fee_rate(fd, &self.handshake.min_tx_fee_rate)?;
         Ok(())
     }
 
     fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<HandshakeAcceptData, codec_error> {
-        let handshake: HandshakeData = read_next(fd)?;
+        let mut handshake = HandshakeData::deserialize_fields(fd)?;
         let heartbeat_interval: u32 = read_next(fd)?;
+        handshake.min_tx_fee_rate = read_min_tx_fee_rate(fd)?;
         Ok(HandshakeAcceptData {
             handshake,
             heartbeat_interval,
@@ -1785,6 +1846,7 @@ pub mod test {
             .unwrap(),
             expire_block_height: 0x0102030405060708,
             data_url: UrlString::try_from("https://the-new-interwebs.com/data").unwrap(),
+            min_tx_fee_rate: None,
         };
         let mut bytes = vec![
             // addrbytes
@@ -1823,6 +1885,7 @@ pub mod test {
                 .unwrap(),
                 expire_block_height: 0x0102030405060708,
                 data_url: UrlString::try_from("https://the-new-interwebs.com/data").unwrap(),
+                min_tx_fee_rate: None,
             },
             heartbeat_interval: 0x01020304,
         };
@@ -1849,6 +1912,59 @@ pub mod test {
         check_codec_and_corruption::<HandshakeAcceptData>(&data, &bytes);
     }
 
+    #[test]
+    fn codec_HandshakeData_min_tx_fee_rate() {
+        let mut data = HandshakeAcceptData {
+            handshake: HandshakeData {
+                addrbytes: PeerAddress([0x01; 16]),
+                port: 12345,
+                services: ServiceFlags::FEE_FILTER as u16,
+                node_public_key: StacksPublicKeyBuffer::from_bytes(
+                    &hex_bytes(
+                        "034e316be04870cef1795fba64d581cf64bad0c894b01a068fb9edf85321dcd9bb",
+                    )
+                    .unwrap(),
+                )
+                .unwrap(),
+                expire_block_height: 0x0102030405060708,
+                data_url: UrlString::try_from("https://the-new-interwebs.com/data").unwrap(),
+                min_tx_fee_rate: None,
+            },
+            heartbeat_interval: 0x01020304,
+        };
+        let handshake_bytes = data.handshake.serialize_to_vec();
+        let accept_bytes = data.serialize_to_vec();
+
+        // the minimum fee rate is appended to each message
+        data.handshake.min_tx_fee_rate = Some(2.5);
+        let mut expected_handshake_bytes = handshake_bytes.clone();
+        expected_handshake_bytes.extend_from_slice(&2.5f64.to_bits().to_be_bytes());
+        let mut expected_accept_bytes = accept_bytes.clone();
+        expected_accept_bytes.extend_from_slice(&2.5f64.to_bits().to_be_bytes());
+
+        assert_eq!(data.handshake.serialize_to_vec(), expected_handshake_bytes);
+        assert_eq!(data.serialize_to_vec(), expected_accept_bytes);
+        assert_eq!(
+            HandshakeData::consensus_deserialize(&mut &expected_handshake_bytes[..]).unwrap(),
+            data.handshake
+        );
+        assert_eq!(
+            HandshakeAcceptData::consensus_deserialize(&mut &expected_accept_bytes[..]).unwrap(),
+            data
+        );
+
+        // a truncated fee rate is rejected
+        assert!(HandshakeData::consensus_deserialize(
+            &mut &expected_handshake_bytes[..expected_handshake_bytes.len() - 1]
+        )
+        .is_err());
+
+        // so is a negative one
+        let mut negative_bytes = handshake_bytes.clone();
+        negative_bytes.extend_from_slice(&(-1f64).to_bits().to_be_bytes());
+        assert!(HandshakeData::consensus_deserialize(&mut &negative_bytes[..]).is_err());
+    }
+
     #[test]
     fn codec_NackData() {
         let data = NackData {
@@ -1953,6 +2069,7 @@ pub mod test {
                 expire_block_height: 0x0102030405060708,
                 data_url: UrlString::try_from("https://the-new-interwebs.com:4008/the-data")
                     .unwrap(),
+                min_tx_fee_rate: None,
             }),
             StacksMessageType::HandshakeAccept(HandshakeAcceptData {
                 heartbeat_interval: 0x01020304,
@@ -1973,6 +2090,7 @@ pub mod test {
                     expire_block_height: 0x0102030405060708,
                     data_url: UrlString::try_from("https://the-new-interwebs.com:4008/the-data")
                         .unwrap(),
+                    min_tx_fee_rate: None,
                 },
             }),
             StacksMessageType::HandshakeReject,
@@ -14,13 +14,16 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
 use std::collections::VecDeque;
 use std::convert::TryFrom;
 use std::io;
 use std::io::{Read, Write};
 use std::net;
+use std::net::SocketAddr;
 use std::ops::Deref;
 use std::ops::DerefMut;
+use std::str::FromStr;
 use std::time::Duration;
 
 use std::sync::mpsc::sync_channel;
@@ -36,6 +39,7 @@ use mio::net as mio_net;
 
 use crate::codec::StacksMessageCodec;
 use crate::codec::MAX_MESSAGE_LEN;
+use crate::core::mempool::MemPoolAdmissionSimulation;
 use crate::core::mempool::MAX_BLOOM_COUNTER_TXS;
 use crate::net::codec::*;
 use crate::net::Error as net_error;
@@ -317,6 +321,97 @@ struct ConnectionOutbox<P: ProtocolFamily> {
     inflight: VecDeque<ReceiverNotify<P>>,
 }
 
+/// Who may call an HTTP RPC route
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RPCAccess {
+    /// anyone
+    Public,
+    /// only clients that present one of the `rpc_api_keys`
+    Authenticated,
+    /// no one
+    Disabled,
+}
+
+impl FromStr for RPCAccess {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<RPCAccess, String> {
+        match s {
+            "public" => Ok(RPCAccess::Public),
+            "authenticated" => Ok(RPCAccess::Authenticated),
+            "disabled" => Ok(RPCAccess::Disabled),
+            _ => Err(format!(
+                "Unknown RPC access '{}': expected 'public', 'authenticated', or 'disabled'",
+                s
+            )),
+        }
+    }
+}
+
+/// Which IP address families the node talks to peers over.  IPv4-mapped IPv6 addresses count as
+/// IPv4.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddressFamily {
+    /// IPv4 and IPv6 alike
+    Any,
+    /// both, but try IPv4 peers first
+    PreferIPv4,
+    /// both, but try IPv6 peers first
+    PreferIPv6,
+    /// only IPv4
+    IPv4Only,
+    /// only IPv6
+    IPv6Only,
+}
+
+impl FromStr for AddressFamily {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<AddressFamily, String> {
+        match s {
+            "any" => Ok(AddressFamily::Any),
+            "prefer-ipv4" => Ok(AddressFamily::PreferIPv4),
+            "prefer-ipv6" => Ok(AddressFamily::PreferIPv6),
+            "ipv4-only" => Ok(AddressFamily::IPv4Only),
+            "ipv6-only" => Ok(AddressFamily::IPv6Only),
+            _ => Err(format!(
+                "Unknown address family '{}': expected 'any', 'prefer-ipv4', 'prefer-ipv6', 'ipv4-only', or 'ipv6-only'",
+                s
+            )),
+        }
+    }
+}
+
+impl AddressFamily {
+    /// May we talk to a peer at `addr`?
+    pub fn allows(&self, addr: &PeerAddress) -> bool {
+        match *self {
+            AddressFamily::IPv4Only => addr.is_ipv4(),
+            AddressFamily::IPv6Only => !addr.is_ipv4(),
+            _ => true,
+        }
+    }
+
+    /// 0 if we'd rather talk to `addr`, 1 if not
+    fn rank(&self, addr: &PeerAddress) -> u8 {
+        match *self {
+            AddressFamily::PreferIPv4 if !addr.is_ipv4() => 1,
+            AddressFamily::PreferIPv6 if addr.is_ipv4() => 1,
+            _ => 0,
+        }
+    }
+
+    /// Drop the items whose addresses we may not talk to, and move the ones whose addresses we'd
+    /// rather talk to to the front.  Items of the same rank keep their order.
+    pub fn filter<T, F>(&self, items: &mut Vec<T>, addr_of: F)
+    where
+        F: Fn(&T) -> PeerAddress,
+    {
+        items.retain(|item| self.allows(&addr_of(item)));
+        items.sort_by_key(|item| self.rank(&addr_of(item)));
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ConnectionOptions {
     pub inbox_maxlen: usize,
@@ -353,7 +448,14 @@ pub struct ConnectionOptions {
     pub download_interval: u64,
     pub pingback_timeout: u64,
     pub dns_timeout: u128,
+    /// how often to look up the DNS seeds among our bootstrap peers again, in seconds
+    pub dns_seed_refresh_interval: u64,
     pub max_inflight_blocks: u64,
+    /// how long, in milliseconds, to wait on a block or microblock request before asking
+    /// another neighbor for the same data
+    pub download_hedge_delay_ms: u64,
+    /// the most neighbors to ask at once for the same block or microblock stream
+    pub max_download_requests_per_block: u64,
     pub max_inflight_attachments: u64,
     pub max_attachment_retry_count: u64,
     pub read_only_call_limit: ExecutionCost,
@@ -361,6 +463,14 @@ pub struct ConnectionOptions {
     pub max_block_push_bandwidth: u64,
     pub max_microblocks_push_bandwidth: u64,
     pub max_transaction_push_bandwidth: u64,
+    /// ban neighbors whose reputation score falls to this or below
+    pub peer_ban_score: i64,
+    /// which IP address families to talk to peers over
+    pub address_family: AddressFamily,
+    /// send all outbound p2p and HTTP connections, and DNS lookups, through this SOCKS5 proxy
+    pub socks_proxy: Option<SocketAddr>,
+    /// the onion address to advertise to peers instead of an IP address
+    pub onion_address: Option<String>,
     pub max_sockets: usize,
     pub public_ip_address: Option<(PeerAddress, u16)>,
     pub public_ip_request_timeout: u64,
@@ -380,6 +490,34 @@ pub struct ConnectionOptions {
     pub mempool_max_tx_query: u64,
     /// how long a mempool sync is allowed to take, in total, before timing out
     pub mempool_sync_timeout: u64,
+    /// whether to pre-execute transactions submitted over the HTTP RPC before admitting them to
+    /// the mempool, and whether to reject or just flag the ones that would abort
+    pub mempool_admission_simulation: MemPoolAdmissionSimulation,
+    /// the minimum fee rate, per unit of the cost metric, of the transactions we admit to the
+    /// mempool.  It is announced to the neighbors that support fee filters, so they don't relay
+    /// cheaper transactions to us.
+    pub min_tx_fee_rate: f64,
+    /// how many blocks deep a Stacks block must be before it is listed on the finalized blocks feed
+    pub finalized_block_confirmations: u64,
+    /// API keys that HTTP RPC clients can authenticate with, as `Authorization: Bearer <key>`
+    pub rpc_api_keys: Vec<String>,
+    /// who may call the HTTP RPC routes that are not listed in `rpc_route_access`
+    pub rpc_default_access: RPCAccess,
+    /// who may call each HTTP RPC route, keyed by its path template (e.g. `/v2/transactions`)
+    pub rpc_route_access: HashMap<String, RPCAccess>,
+    /// how many request cost units each client IP address may spend per second on the HTTP RPC
+    /// (0 for no limit)
+    pub rpc_client_rate_limit: u64,
+    /// how many request cost units a client IP address may spend at once
+    pub rpc_client_burst: u64,
+    /// how many request cost units all clients together may spend per second on each HTTP RPC
+    /// route (0 for no limit)
+    pub rpc_route_rate_limit: u64,
+    /// how many request cost units all clients together may spend at once on each HTTP RPC route
+    pub rpc_route_burst: u64,
+    /// what a request to each HTTP RPC route costs, keyed by its path template.  Other routes
+    /// cost 1.
+    pub rpc_request_costs: HashMap<String, u64>,
 
     // fault injection
     pub disable_neighbor_walk: bool,
@@ -435,8 +573,11 @@ impl std::default::Default for ConnectionOptions {
             inv_reward_cycles: INV_REWARD_CYCLES, // how many reward cycles of blocks to sync in a non-full inventory sync
             download_interval: BLOCK_DOWNLOAD_INTERVAL, // how often to scan for blocks to download
             pingback_timeout: 60,
-            dns_timeout: 15_000,            // DNS timeout, in millis
-            max_inflight_blocks: 6,         // number of parallel block downloads
+            dns_timeout: 15_000,                // DNS timeout, in millis
+            dns_seed_refresh_interval: 3600,    // look up DNS seeds again every hour
+            max_inflight_blocks: 6,             // number of parallel block downloads
+            download_hedge_delay_ms: 5_000, // ask another neighbor if a block takes longer than this
+            max_download_requests_per_block: 3, // number of neighbors to race for the same block
             max_inflight_attachments: 6,    // number of parallel attachments downloads
             max_attachment_retry_count: 32, // how many attempt to get an attachment before giving up
             read_only_call_limit: ExecutionCost {
@@ -450,8 +591,12 @@ impl std::default::Default for ConnectionOptions {
             max_block_push_bandwidth: 0, // infinite upload bandwidth allowed
             max_microblocks_push_bandwidth: 0, // infinite upload bandwidth allowed
             max_transaction_push_bandwidth: 0, // infinite upload bandwidth allowed
-            max_sockets: 800,            // maximum number of client sockets we'll ever register
-            public_ip_address: None,     // resolve it at runtime by default
+            peer_ban_score: -100, // ban after e.g. 2 invalid messages or 20 stalls in a short time
+            address_family: AddressFamily::Any, // talk to IPv4 and IPv6 peers alike
+            socks_proxy: None,    // connect to peers directly
+            onion_address: None,  // advertise our IP address
+            max_sockets: 800,     // maximum number of client sockets we'll ever register
+            public_ip_address: None, // resolve it at runtime by default
             public_ip_request_timeout: 60, // how often we can attempt to look up our public IP address
             public_ip_timeout: 3600,       // re-learn the public IP ever hour, if it's not given
             public_ip_max_retries: 3, // maximum number of retries before self-throttling for $public_ip_timeout
@@ -466,6 +611,40 @@ impl std::default::Default for ConnectionOptions {
             mempool_sync_interval: 30, // number of seconds in-between mempool sync
             mempool_max_tx_query: 128, // maximum number of transactions to visit per mempool query
             mempool_sync_timeout: 180, // how long a mempool sync can go for (3 minutes)
+            mempool_admission_simulation: MemPoolAdmissionSimulation::Off, // costs CPU, so off by default
+            min_tx_fee_rate: 0f64, // admit transactions at any fee rate
+            finalized_block_confirmations: 6, // how deep a block must be before the finalized blocks feed lists it
+            rpc_api_keys: vec![],
+            rpc_default_access: RPCAccess::Public, // all RPC routes are open by default
+            rpc_route_access: [
+                ("/v2/neighbors/ban", RPCAccess::Authenticated), // admin routes need an API key
+                ("/v2/neighbors/unban", RPCAccess::Authenticated),
+                ("/v2/attachments/:hash/refetch", RPCAccess::Authenticated),
+            ]
+            .iter()
+            .map(|(route, access)| (route.to_string(), *access))
+            .collect(),
+            rpc_client_rate_limit: 0, // no RPC rate limits by default
+            rpc_client_burst: 100,
+            rpc_route_rate_limit: 0,
+            rpc_route_burst: 1000,
+            rpc_request_costs: [
+                (
+                    "/v2/contracts/call-read/:principal/:contract_name/:func_name",
+                    10,
+                ),
+                ("/v2/contracts/call-read-batch", 10), // per call in the batch
+                ("/v2/map_entry/:principal/:contract_name/:map_name", 2),
+                ("/v2/map_entries/:principal/:contract_name/:map_name", 10),
+                ("/v2/consistency_hash", 1),
+                ("/v2/pox/cycle/:n", 5),
+                ("/v2/fees/transaction", 5),
+                ("/v2/transactions/dry-run", 10),
+                ("/v2/mempool/query", 10),
+            ]
+            .iter()
+            .map(|(route, cost)| (route.to_string(), *cost))
+            .collect(),
 
             // no faults on by default
             disable_neighbor_walk: false,
@@ -487,6 +666,31 @@ impl std::default::Default for ConnectionOptions {
     }
 }
 
+impl ConnectionOptions {
+    /// Who may call the HTTP RPC route with the given path template
+    pub fn get_rpc_access(&self, route: &str) -> RPCAccess {
+        self.rpc_route_access
+            .get(route)
+            .cloned()
+            .unwrap_or(self.rpc_default_access)
+    }
+
+    /// Is `api_key` one of the keys that HTTP RPC clients can authenticate with?
+    /// Every key is compared in full, so the time this takes does not reveal how much of a key
+    /// the client guessed right.
+    pub fn is_rpc_api_key(&self, api_key: &str) -> bool {
+        self.rpc_api_keys.iter().fold(false, |found, key| {
+            let matches = key.len() == api_key.len()
+                && key
+                    .bytes()
+                    .zip(api_key.bytes())
+                    .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+                    == 0;
+            found | matches
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct NetworkConnection<P: ProtocolFamily> {
     pub options: ConnectionOptions,
@@ -1416,6 +1620,76 @@ mod test {
 
     use crate::util_lib::test::*;
 
+    #[test]
+    fn test_connection_options_rpc_access() {
+        let mut opts = ConnectionOptions::default();
+        assert_eq!(opts.get_rpc_access("/v2/transactions"), RPCAccess::Public);
+        assert_eq!(
+            opts.get_rpc_access("/v2/neighbors/ban"),
+            RPCAccess::Authenticated
+        );
+        assert_eq!(
+            opts.get_rpc_access("/v2/attachments/:hash/refetch"),
+            RPCAccess::Authenticated
+        );
+        assert!(!opts.is_rpc_api_key(""));
+
+        opts.rpc_api_keys = vec!["alpha".to_string(), "beta".to_string()];
+        opts.rpc_default_access = RPCAccess::Authenticated;
+        opts.rpc_route_access
+            .insert("/v2/info".to_string(), RPCAccess::Public);
+        opts.rpc_route_access
+            .insert("/v2/transactions".to_string(), RPCAccess::Disabled);
+
+        assert_eq!(opts.get_rpc_access("/v2/info"), RPCAccess::Public);
+        assert_eq!(opts.get_rpc_access("/v2/transactions"), RPCAccess::Disabled);
+        assert_eq!(
+            opts.get_rpc_access("/v2/accounts/:principal"),
+            RPCAccess::Authenticated
+        );
+
+        assert!(opts.is_rpc_api_key("alpha"));
+        assert!(opts.is_rpc_api_key("beta"));
+        assert!(!opts.is_rpc_api_key("alph"));
+        assert!(!opts.is_rpc_api_key("alphabet"));
+        assert!(!opts.is_rpc_api_key(""));
+
+        assert_eq!("disabled".parse::<RPCAccess>(), Ok(RPCAccess::Disabled));
+        assert!("Public".parse::<RPCAccess>().is_err());
+    }
+
+    #[test]
+    fn test_address_family() {
+        let v4 = PeerAddress::from_ipv4(1, 2, 3, 4);
+        let v4_mapped = PeerAddress::from_ip(&"::ffff:5.6.7.8".parse().unwrap());
+        let v6 = PeerAddress::from_ip(&"2001:db8::1".parse().unwrap());
+        let addrs = vec![v6.clone(), v4.clone(), v4_mapped.clone()];
+
+        let filtered = |family: AddressFamily| {
+            let mut items = addrs.clone();
+            family.filter(&mut items, |addr| addr.clone());
+            items
+        };
+
+        assert_eq!(filtered(AddressFamily::Any), addrs);
+        assert_eq!(
+            filtered(AddressFamily::PreferIPv4),
+            vec![v4.clone(), v4_mapped.clone(), v6.clone()]
+        );
+        assert_eq!(filtered(AddressFamily::PreferIPv6), addrs);
+        assert_eq!(
+            filtered(AddressFamily::IPv4Only),
+            vec![v4.clone(), v4_mapped.clone()]
+        );
+        assert_eq!(filtered(AddressFamily::IPv6Only), vec![v6.clone()]);
+
+        assert_eq!(
+            "prefer-ipv6".parse::<AddressFamily>(),
+            Ok(AddressFamily::PreferIPv6)
+        );
+        assert!("ipv6".parse::<AddressFamily>().is_err());
+    }
+
     fn test_connection_relay_producer_consumer<P, F>(
         mut protocol: P,
         mut conn: NetworkConnection<P>,
@@ -51,6 +51,7 @@ use rand::Rng;
 use rand::RngCore;
 
 use crate::net::asn::ASEntry4;
+use crate::net::reputation;
 use crate::net::Neighbor;
 use crate::net::NeighborAddress;
 use crate::net::NeighborKey;
@@ -68,6 +69,10 @@ pub const PEERDB_VERSION: &'static str = "1";
 
 const NUM_SLOTS: usize = 8;
 
+/// `allowed` value for peers that are always allowed only because a DNS seed resolved to them.
+/// Like -1, it means "always", but it lets us tell seed peers apart from operator-allowed ones.
+pub const SEED_PEER_ALLOWED: i64 = -2;
+
 impl PeerAddress {
     pub fn to_bin(&self) -> String {
         to_bin(&self.0)
@@ -360,6 +365,18 @@ const PEERDB_INITIAL_SCHEMA: &'static [&'static str] = &[
     );"#,
 ];
 
+// created on open, so that peer DBs from before scores existed get the table too
+const PEERDB_SCORES_SCHEMA: &'static [&'static str] = &[r#"
+    CREATE TABLE IF NOT EXISTS peer_scores(
+        network_id INTEGER NOT NULL,
+        addrbytes TEXT NOT NULL,
+        port INTEGER NOT NULL,
+        score INTEGER NOT NULL,
+        last_update INTEGER NOT NULL,
+
+        PRIMARY KEY(network_id,addrbytes,port)
+    );"#];
+
 const PEERDB_INDEXES: &'static [&'static str] =
     &["CREATE INDEX IF NOT EXISTS peer_address_index ON frontier(network_id,addrbytes,port);"];
 
@@ -443,10 +460,20 @@ impl PeerDB {
 
         tx.commit().map_err(db_error::SqliteError)?;
 
+        self.add_peer_scores()?;
         self.add_indexes()?;
         Ok(())
     }
 
+    fn add_peer_scores(&mut self) -> Result<(), db_error> {
+        let tx = self.tx_begin()?;
+        for row_text in PEERDB_SCORES_SCHEMA {
+            tx.execute_batch(row_text).map_err(db_error::SqliteError)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
     fn add_indexes(&mut self) -> Result<(), db_error> {
         let tx = self.tx_begin()?;
         for row_text in PEERDB_INDEXES {
@@ -606,6 +633,7 @@ impl PeerDB {
             }
         }
         if readwrite {
+            db.add_peer_scores()?;
             db.add_indexes()?;
         }
         Ok(db)
@@ -964,6 +992,145 @@ impl PeerDB {
         Ok(())
     }
 
+    /// Make a peer that a DNS seed resolved to into an always-allowed initial peer, inserting it
+    /// if we don't know it yet.  A new peer only evicts a peer in one of its slots if that peer
+    /// came from a DNS seed as well; if none of its slots hold one, it is not stored.  If
+    /// `public_key` is given, it replaces whatever key we have for the peer; otherwise, a new peer
+    /// gets a throwaway key until we learn its real one in a handshake.  A peer that the operator
+    /// already allows is left allowed as it is.
+    /// Returns whether or not the peer is in the DB.
+    pub fn add_seed_peer<'a>(
+        tx: &mut Transaction<'a>,
+        key: &NeighborKey,
+        public_key: Option<&Secp256k1PublicKey>,
+    ) -> Result<bool, db_error> {
+        let allowed = match PeerDB::get_peer(tx, key.network_id, &key.addrbytes, key.port)? {
+            Some(mut neighbor) => {
+                if let Some(public_key) = public_key {
+                    neighbor.public_key = public_key.clone();
+                    PeerDB::update_peer(tx, &neighbor)?;
+                }
+                neighbor.allowed
+            }
+            None => {
+                let public_key = public_key.cloned().unwrap_or_else(|| {
+                    Secp256k1PublicKey::from_private(&Secp256k1PrivateKey::new())
+                });
+                let neighbor = Neighbor::empty(key, &public_key, i64::MAX as u64);
+                if !PeerDB::try_insert_peer(tx, &neighbor)? {
+                    let mut seed_slot = None;
+                    for slot in PeerDB::peer_slots(tx, key.network_id, &key.addrbytes, key.port)? {
+                        if let Some(occupant) = PeerDB::get_peer_at(tx, key.network_id, slot)? {
+                            if occupant.allowed == SEED_PEER_ALLOWED {
+                                seed_slot = Some(slot);
+                                break;
+                            }
+                        }
+                    }
+                    match seed_slot {
+                        Some(slot) => {
+                            PeerDB::insert_or_replace_peer(tx, &neighbor, slot)?;
+                        }
+                        None => {
+                            debug!("No slot for DNS seed peer {:?}", key);
+                            return Ok(false);
+                        }
+                    }
+                }
+                neighbor.allowed
+            }
+        };
+        if allowed >= 0 {
+            PeerDB::set_initial_peer(tx, key.network_id, &key.addrbytes, key.port)?;
+            PeerDB::set_allow_peer(
+                tx,
+                key.network_id,
+                &key.addrbytes,
+                key.port,
+                SEED_PEER_ALLOWED,
+            )?;
+        } else if allowed == SEED_PEER_ALLOWED {
+            PeerDB::set_initial_peer(tx, key.network_id, &key.addrbytes, key.port)?;
+        }
+        Ok(true)
+    }
+
+    /// Stop treating a peer that a DNS seed no longer resolves to as an always-allowed initial
+    /// peer.  It stays in the DB like any other neighbor.  Peers that the operator allows (i.e.
+    /// were not made always-allowed by `add_seed_peer`) are left alone.
+    pub fn clear_seed_peer<'a>(
+        tx: &mut Transaction<'a>,
+        network_id: u32,
+        peer_addr: &PeerAddress,
+        peer_port: u16,
+    ) -> Result<(), db_error> {
+        tx.execute("UPDATE frontier SET initial = 0, allowed = 0 WHERE network_id = ?1 AND addrbytes = ?2 AND port = ?3 AND allowed = ?4",
+                    &[&network_id as &dyn ToSql, &peer_addr.to_bin(), &peer_port, &SEED_PEER_ALLOWED])
+            .map_err(db_error::SqliteError)?;
+
+        Ok(())
+    }
+
+    /// Get a peer's reputation score as of `now`.  Peers we have no score for have a score of 0.
+    pub fn get_peer_score(
+        conn: &DBConn,
+        network_id: u32,
+        peer_addr: &PeerAddress,
+        peer_port: u16,
+        now: u64,
+    ) -> Result<i64, db_error> {
+        let mut stmt = conn
+            .prepare("SELECT score, last_update FROM peer_scores WHERE network_id = ?1 AND addrbytes = ?2 AND port = ?3")
+            .map_err(db_error::SqliteError)?;
+        let mut rows = stmt
+            .query(&[&network_id as &dyn ToSql, &peer_addr.to_bin(), &peer_port])
+            .map_err(db_error::SqliteError)?;
+        match rows.next().map_err(db_error::SqliteError)? {
+            Some(row) => {
+                let score: i64 = row.get_unwrap("score");
+                let last_update = u64::from_column(row, "last_update")?;
+                Ok(reputation::recovered_score(score, last_update, now))
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Set a peer's reputation score as of `now`
+    pub fn set_peer_score<'a>(
+        tx: &mut Transaction<'a>,
+        network_id: u32,
+        peer_addr: &PeerAddress,
+        peer_port: u16,
+        score: i64,
+        now: u64,
+    ) -> Result<(), db_error> {
+        let args: &[&dyn ToSql] = &[
+            &network_id,
+            &peer_addr.to_bin(),
+            &peer_port,
+            &score,
+            &u64_to_sql(now)?,
+        ];
+        tx.execute("INSERT OR REPLACE INTO peer_scores (network_id, addrbytes, port, score, last_update) VALUES (?1, ?2, ?3, ?4, ?5)", args)
+            .map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    /// Add `delta` to a peer's reputation score as of `now`, and return the new score
+    pub fn adjust_peer_score<'a>(
+        tx: &mut Transaction<'a>,
+        network_id: u32,
+        peer_addr: &PeerAddress,
+        peer_port: u16,
+        delta: i64,
+        now: u64,
+    ) -> Result<i64, db_error> {
+        let score = PeerDB::get_peer_score(tx, network_id, peer_addr, peer_port, now)?
+            .saturating_add(delta);
+        PeerDB::set_peer_score(tx, network_id, peer_addr, peer_port, score, now)?;
+        Ok(score)
+    }
+
     /// Set/unset allow flag for a peer
     /// Pass -1 for "always"
     pub fn set_allow_peer<'a>(
@@ -2392,4 +2559,283 @@ mod test {
         let local_peer = PeerDB::get_local_peer(db.conn()).unwrap();
         assert_eq!(local_peer.private_key, key2);
     }
+
+    #[test]
+    fn test_peer_scores() {
+        let path = "/tmp/test-peer-scores.db".to_string();
+        if fs::metadata(&path).is_ok() {
+            fs::remove_file(&path).unwrap();
+        }
+
+        let connect = || {
+            PeerDB::connect(
+                &path,
+                true,
+                0x80000000,
+                0,
+                None,
+                i64::MAX as u64,
+                PeerAddress::from_ipv4(127, 0, 0, 1),
+                12345,
+                UrlString::try_from("http://foo.com").unwrap(),
+                &vec![],
+                None,
+            )
+            .unwrap()
+        };
+
+        let addr = PeerAddress::from_ipv4(1, 2, 3, 4);
+        let mut db = connect();
+        assert_eq!(
+            PeerDB::get_peer_score(db.conn(), 0x80000000, &addr, 20444, 1000).unwrap(),
+            0
+        );
+
+        {
+            let mut tx = db.tx_begin().unwrap();
+            assert_eq!(
+                PeerDB::adjust_peer_score(&mut tx, 0x80000000, &addr, 20444, -50, 1000).unwrap(),
+                -50
+            );
+            assert_eq!(
+                PeerDB::adjust_peer_score(&mut tx, 0x80000000, &addr, 20444, -5, 1000).unwrap(),
+                -55
+            );
+            tx.commit().unwrap();
+        }
+
+        // scores survive restarts, and recover over time
+        let mut db = connect();
+        assert_eq!(
+            PeerDB::get_peer_score(db.conn(), 0x80000000, &addr, 20444, 1000).unwrap(),
+            -55
+        );
+        assert_eq!(
+            PeerDB::get_peer_score(
+                db.conn(),
+                0x80000000,
+                &addr,
+                20444,
+                1000 + 5 * reputation::PEER_SCORE_RECOVERY_INTERVAL
+            )
+            .unwrap(),
+            -50
+        );
+
+        // scores are kept per address, port, and network
+        assert_eq!(
+            PeerDB::get_peer_score(db.conn(), 0x80000000, &addr, 20445, 1000).unwrap(),
+            0
+        );
+        assert_eq!(
+            PeerDB::get_peer_score(db.conn(), 0x80000001, &addr, 20444, 1000).unwrap(),
+            0
+        );
+
+        {
+            let mut tx = db.tx_begin().unwrap();
+            PeerDB::set_peer_score(&mut tx, 0x80000000, &addr, 20444, 0, 2000).unwrap();
+            tx.commit().unwrap();
+        }
+        assert_eq!(
+            PeerDB::get_peer_score(db.conn(), 0x80000000, &addr, 20444, 2000).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_seed_peers() {
+        let mut db = PeerDB::connect_memory(
+            0x9abcdef0,
+            12345,
+            0,
+            "http://foo.com".into(),
+            &vec![],
+            &vec![],
+        )
+        .unwrap();
+
+        let key = NeighborKey {
+            peer_version: 0x12345678,
+            network_id: 0x9abcdef0,
+            addrbytes: PeerAddress::from_ipv4(1, 2, 3, 4),
+            port: 20444,
+        };
+        let pinned_key = Secp256k1PublicKey::from_private(&Secp256k1PrivateKey::new());
+
+        {
+            let mut tx = db.tx_begin().unwrap();
+            PeerDB::add_seed_peer(&mut tx, &key, None).unwrap();
+            tx.commit().unwrap();
+        }
+
+        let neighbor = PeerDB::get_peer(db.conn(), key.network_id, &key.addrbytes, key.port)
+            .unwrap()
+            .unwrap();
+        assert_eq!(neighbor.allowed, SEED_PEER_ALLOWED);
+        assert!(neighbor.public_key != pinned_key);
+        assert!(
+            PeerDB::is_initial_peer(db.conn(), key.network_id, &key.addrbytes, key.port).unwrap()
+        );
+        assert_eq!(
+            PeerDB::get_bootstrap_peers(db.conn(), key.network_id)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // looking the seed up again does not forget the key we have, unless the seed pins one
+        {
+            let mut tx = db.tx_begin().unwrap();
+            PeerDB::add_seed_peer(&mut tx, &key, None).unwrap();
+            tx.commit().unwrap();
+        }
+        let again = PeerDB::get_peer(db.conn(), key.network_id, &key.addrbytes, key.port)
+            .unwrap()
+            .unwrap();
+        assert_eq!(again.public_key, neighbor.public_key);
+
+        {
+            let mut tx = db.tx_begin().unwrap();
+            PeerDB::add_seed_peer(&mut tx, &key, Some(&pinned_key)).unwrap();
+            tx.commit().unwrap();
+        }
+        let pinned = PeerDB::get_peer(db.conn(), key.network_id, &key.addrbytes, key.port)
+            .unwrap()
+            .unwrap();
+        assert_eq!(pinned.public_key, pinned_key);
+
+        // the seed moved away
+        {
+            let mut tx = db.tx_begin().unwrap();
+            PeerDB::clear_seed_peer(&mut tx, key.network_id, &key.addrbytes, key.port).unwrap();
+            tx.commit().unwrap();
+        }
+        let cleared = PeerDB::get_peer(db.conn(), key.network_id, &key.addrbytes, key.port)
+            .unwrap()
+            .unwrap();
+        assert_eq!(cleared.allowed, 0);
+        assert!(
+            !PeerDB::is_initial_peer(db.conn(), key.network_id, &key.addrbytes, key.port).unwrap()
+        );
+
+        // a peer the operator allows stays allowed when a seed stops resolving to it
+        let allowed_key = NeighborKey {
+            peer_version: 0x12345678,
+            network_id: 0x9abcdef0,
+            addrbytes: PeerAddress::from_ipv4(5, 6, 7, 8),
+            port: 20444,
+        };
+        {
+            let mut tx = db.tx_begin().unwrap();
+            PeerDB::set_allow_peer(
+                &mut tx,
+                allowed_key.network_id,
+                &allowed_key.addrbytes,
+                allowed_key.port,
+                -1,
+            )
+            .unwrap();
+            assert!(PeerDB::add_seed_peer(&mut tx, &allowed_key, None).unwrap());
+            PeerDB::clear_seed_peer(
+                &mut tx,
+                allowed_key.network_id,
+                &allowed_key.addrbytes,
+                allowed_key.port,
+            )
+            .unwrap();
+            tx.commit().unwrap();
+        }
+        let still_allowed = PeerDB::get_peer(
+            db.conn(),
+            allowed_key.network_id,
+            &allowed_key.addrbytes,
+            allowed_key.port,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(still_allowed.allowed, -1);
+    }
+
+    #[test]
+    fn test_seed_peers_evict_only_seed_peers() {
+        let mut db = PeerDB::connect_memory(
+            0x9abcdef0,
+            12345,
+            0,
+            "http://foo.com".into(),
+            &vec![],
+            &vec![],
+        )
+        .unwrap();
+
+        let key = NeighborKey {
+            peer_version: 0x12345678,
+            network_id: 0x9abcdef0,
+            addrbytes: PeerAddress::from_ipv4(1, 2, 3, 4),
+            port: 20444,
+        };
+        let slots =
+            PeerDB::peer_slots(db.conn(), key.network_id, &key.addrbytes, key.port).unwrap();
+
+        // fill all of the seed peer's slots with bootstrap peers
+        {
+            let mut tx = db.tx_begin().unwrap();
+            for (i, slot) in slots.iter().enumerate() {
+                let other_key = NeighborKey {
+                    addrbytes: PeerAddress::from_ipv4(10, 0, 0, i as u8),
+                    ..key.clone()
+                };
+                let pubkey = Secp256k1PublicKey::from_private(&Secp256k1PrivateKey::new());
+                let mut other = Neighbor::empty(&other_key, &pubkey, i64::MAX as u64);
+                other.allowed = -1;
+                PeerDB::insert_or_replace_peer(&mut tx, &other, *slot).unwrap();
+                PeerDB::set_initial_peer(
+                    &mut tx,
+                    other_key.network_id,
+                    &other_key.addrbytes,
+                    other_key.port,
+                )
+                .unwrap();
+            }
+            assert!(!PeerDB::add_seed_peer(&mut tx, &key, None).unwrap());
+            tx.commit().unwrap();
+        }
+        assert!(
+            PeerDB::get_peer(db.conn(), key.network_id, &key.addrbytes, key.port)
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(
+            PeerDB::get_bootstrap_peers(db.conn(), key.network_id)
+                .unwrap()
+                .len(),
+            slots.len()
+        );
+
+        // a peer from a DNS seed can be evicted
+        {
+            let mut tx = db.tx_begin().unwrap();
+            let occupant = PeerDB::get_peer_at(&tx, key.network_id, slots[3])
+                .unwrap()
+                .unwrap();
+            PeerDB::set_allow_peer(
+                &mut tx,
+                occupant.addr.network_id,
+                &occupant.addr.addrbytes,
+                occupant.addr.port,
+                SEED_PEER_ALLOWED,
+            )
+            .unwrap();
+            assert!(PeerDB::add_seed_peer(&mut tx, &key, None).unwrap());
+            tx.commit().unwrap();
+        }
+        assert_eq!(
+            PeerDB::get_peer_at(db.conn(), key.network_id, slots[3])
+                .unwrap()
+                .unwrap()
+                .addr,
+            key
+        );
+    }
 }
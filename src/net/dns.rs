@@ -35,12 +35,14 @@ use crate::net::NeighborKey;
 use crate::net::PeerAddress;
 
 use crate::net::codec::*;
+use crate::net::socks;
 use crate::net::*;
 
 use crate::util_lib::db::Error as db_error;
 use stacks_common::util::sleep_ms;
 
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
 
 use std::collections::HashMap;
 use std::collections::HashSet;
@@ -50,10 +52,23 @@ use stacks_common::util::get_epoch_time_ms;
 use stacks_common::util::get_epoch_time_secs;
 use stacks_common::util::hash::to_hex;
 use stacks_common::util::log;
+use stacks_common::util::secp256k1::Secp256k1PublicKey;
 
 /// In Rust, there's no easy way to do non-blocking DNS lookups (I blame getaddrinfo), so do it in
 /// a separate thread, and implement a way for the block downloader to periodically poll for
 /// resolved names.
+/// A bootstrap peer given by host name rather than by address.  The host is looked up when the
+/// node starts, and again every `ConnectionOptions::dns_seed_refresh_interval` seconds, and every
+/// address it resolves to becomes a bootstrap peer.  If the seed comes with a public key, all of
+/// its addresses are expected to use it; otherwise we learn each peer's key from its handshake.
+/// Behind a SOCKS proxy, the proxy resolves the seed, and only hands back one address per lookup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DNSSeed {
+    pub host: String,
+    pub port: u16,
+    pub public_key: Option<Secp256k1PublicKey>,
+}
+
 #[derive(Debug, Clone, Eq)]
 pub struct DNSRequest {
     pub host: String,
@@ -119,6 +134,8 @@ pub struct DNSResolver {
     inbound: Receiver<DNSRequest>,
     outbound: SyncSender<DNSResponse>,
     max_inflight: u64,
+    /// If set, names are resolved by this SOCKS5 proxy instead of by the system resolver
+    socks_proxy: Option<SocketAddr>,
 
     // used mainly for testing
     hardcoded: HashMap<(String, u16), Vec<SocketAddr>>,
@@ -144,6 +161,7 @@ impl DNSResolver {
             inbound: socket_chan_rx,
             outbound: dns_chan_tx,
             max_inflight: max_inflight,
+            socks_proxy: None,
             hardcoded: HashMap::new(),
         };
         (resolver, client)
@@ -153,11 +171,34 @@ impl DNSResolver {
         self.hardcoded.insert((host.to_string(), port), addrs);
     }
 
+    /// Resolve names through the SOCKS5 proxy at `proxy`, so lookups don't leak around it
+    pub fn set_socks_proxy(&mut self, proxy: Option<SocketAddr>) -> () {
+        self.socks_proxy = proxy;
+    }
+
     pub fn resolve(&self, req: DNSRequest) -> DNSResponse {
         if let Some(ref addrs) = self.hardcoded.get(&(req.host.clone(), req.port)) {
             return DNSResponse::new(req, Ok(addrs.to_vec()));
         }
 
+        if let Some(ref proxy) = self.socks_proxy {
+            if let Ok(ip) = req.host.parse::<IpAddr>() {
+                let addr = SocketAddr::new(ip, req.port);
+                return DNSResponse::new(req, Ok(vec![addr]));
+            }
+            let timeout = Duration::from_millis(
+                (req.timeout.saturating_sub(get_epoch_time_ms()) as u64).max(1),
+            );
+            test_debug!("Resolve {}:{} via {}", &req.host, req.port, proxy);
+            return match socks::resolve_blocking(proxy, &req.host, req.port, timeout) {
+                Ok(addr) => {
+                    test_debug!("{}:{} resolved to {}", &req.host, req.port, &addr);
+                    DNSResponse::new(req, Ok(vec![addr]))
+                }
+                Err(ioe) => DNSResponse::error(req, format!("DNS resolve error: {:?}", &ioe)),
+            };
+        }
+
         // TODO: this is a blocking operation, but there's not really a good solution here other
         // than to just do this in a separate thread :shrug:
         test_debug!("Resolve {}:{}", &req.host, req.port);
@@ -550,4 +591,39 @@ mod test {
             .is_some());
         dns_thread_shutdown(client, thread_handle);
     }
+
+    #[test]
+    fn dns_resolve_through_socks_proxy() {
+        use super::*;
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut sock, _) = listener.accept().unwrap();
+            let mut request = vec![0u8; 3 + 5 + "seed.example.com".len() + 2];
+            sock.read_exact(&mut request).unwrap();
+            sock.write_all(&[5, 0, 5, 0, 0, 1, 10, 0, 0, 1, 0, 0])
+                .unwrap();
+        });
+
+        let (mut resolver, _client) = DNSResolver::new(10);
+        resolver.set_socks_proxy(Some(proxy));
+
+        // IP addresses don't need the proxy
+        let resp = resolver.resolve(DNSRequest::new(
+            "1.2.3.4".to_string(),
+            20444,
+            get_epoch_time_ms() + 5_000,
+        ));
+        assert_eq!(resp.result, Ok(vec!["1.2.3.4:20444".parse().unwrap()]));
+
+        let resp = resolver.resolve(DNSRequest::new(
+            "seed.example.com".to_string(),
+            20444,
+            get_epoch_time_ms() + 5_000,
+        ));
+        assert_eq!(resp.result, Ok(vec!["10.0.0.1:20444".parse().unwrap()]));
+        server.join().unwrap();
+    }
 }
@@ -17,6 +17,7 @@
  along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
 */
 
+use std::cmp;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
@@ -101,6 +102,41 @@ pub const BLOCK_REREQUEST_INTERVAL: u64 = 30;
 
 /// This module is responsible for downloading blocks and microblocks from other peers, using block
 /// inventory state (see src/net/inv.rs)
+///
+/// A block or microblock stream is first requested from the fastest peer that has it.  If that
+/// peer does not answer within the hedge delay, the same data is requested from the next-fastest
+/// peer, and so on, up to a few requests at once.  The first valid answer wins, and the other
+/// requests for the same data are cancelled.  How long each peer takes to answer (or to lose a
+/// race) is remembered, so slow peers are asked last.
+
+/// How quickly a peer's data URL has answered our block and microblock requests
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadPeerStats {
+    /// moving average of how long requests took, in milliseconds
+    pub latency_ms: u64,
+    /// how many requests went into the average
+    pub num_requests: u64,
+}
+
+impl DownloadPeerStats {
+    fn new() -> DownloadPeerStats {
+        DownloadPeerStats {
+            latency_ms: 0,
+            num_requests: 0,
+        }
+    }
+
+    /// Fold a request's latency into the moving average, weighing recent requests more.
+    fn record(&mut self, latency_ms: u64) {
+        if self.num_requests == 0 {
+            self.latency_ms = latency_ms;
+        } else {
+            self.latency_ms =
+                (self.latency_ms.saturating_mul(3) / 4).saturating_add(latency_ms / 4);
+        }
+        self.num_requests += 1;
+    }
+}
 
 #[derive(Debug, PartialEq, Clone, Hash, Eq)]
 pub enum BlockRequestKeyKind {
@@ -255,6 +291,15 @@ pub struct BlockDownloader {
     /// when did we last request a given block hash
     requested_blocks: HashMap<StacksBlockId, u64>,
     requested_microblocks: HashMap<StacksBlockId, u64>,
+
+    /// when each in-flight request was sent, in milliseconds, by event ID
+    request_start_ms: HashMap<usize, u128>,
+    /// how quickly each data URL has answered our requests.  Kept across download passes.
+    peer_stats: HashMap<UrlString, DownloadPeerStats>,
+    /// how long to wait on a request before also asking another peer, in milliseconds
+    hedge_delay_ms: u64,
+    /// how many peers to ask for the same block or microblock stream at once
+    max_requests_per_block: u64,
 }
 
 impl BlockDownloader {
@@ -262,6 +307,8 @@ impl BlockDownloader {
         dns_timeout: u128,
         download_interval: u64,
         max_inflight_requests: u64,
+        hedge_delay_ms: u64,
+        max_requests_per_block: u64,
     ) -> BlockDownloader {
         BlockDownloader {
             state: BlockDownloaderState::DNSLookupBegin,
@@ -300,6 +347,11 @@ impl BlockDownloader {
             download_interval: download_interval,
             requested_blocks: HashMap::new(),
             requested_microblocks: HashMap::new(),
+
+            request_start_ms: HashMap::new(),
+            peer_stats: HashMap::new(),
+            hedge_delay_ms,
+            max_requests_per_block: cmp::max(max_requests_per_block, 1),
         }
     }
 
@@ -316,6 +368,7 @@ impl BlockDownloader {
         self.microblocks_to_try.clear();
         self.blocks.clear();
         self.microblocks.clear();
+        self.request_start_ms.clear();
 
         self.dead_peers.clear();
         self.broken_peers.clear();
@@ -323,6 +376,7 @@ impl BlockDownloader {
 
         // perserve sortition height
         // preserve download accounting
+        // preserve peer stats
     }
 
     pub fn restart_scan(&mut self, sortition_start: u64) -> () {
@@ -335,11 +389,14 @@ impl BlockDownloader {
         self.empty_microblock_download_passes = 0;
     }
 
+    /// Begin resolving the given data URLs.  If there is a SOCKS proxy, host names are left to
+    /// the proxy to resolve, and they "resolve" to the proxy's address here.
     pub fn dns_lookups_begin(
         &mut self,
         pox_id: &PoxId,
         dns_client: &mut DNSClient,
         mut urls: Vec<UrlString>,
+        socks_proxy: Option<&SocketAddr>,
     ) -> Result<(), net_error> {
         assert_eq!(self.state, BlockDownloaderState::DNSLookupBegin);
 
@@ -359,6 +416,10 @@ impl BlockDownloader {
                 }
             };
             match url.host() {
+                Some(url::Host::Domain(_)) if socks_proxy.is_some() => {
+                    self.dns_lookups
+                        .insert(url_str, Some(vec![socks_proxy.unwrap().clone()]));
+                }
                 Some(url::Host::Domain(domain)) => {
                     match dns_client.queue_lookup(
                         domain.clone(),
@@ -447,8 +508,16 @@ impl BlockDownloader {
         // requests that are still pending
         let mut pending_block_requests = HashMap::new();
 
+        // blocks that some peer already sent us
+        let mut won: HashSet<StacksBlockId> = self
+            .blocks
+            .keys()
+            .map(|block_key| block_key.index_block_hash.clone())
+            .collect();
+        let getblock_requests = std::mem::replace(&mut self.getblock_requests, HashMap::new());
+
         PeerNetwork::with_http(network, |ref mut network, ref mut http| {
-            for (block_key, event_id) in self.getblock_requests.drain() {
+            for (block_key, event_id) in getblock_requests.into_iter() {
                 match http.get_conversation(event_id) {
                     None => {
                         if http.is_connecting(event_id) {
@@ -462,6 +531,11 @@ impl BlockDownloader {
                             pending_block_requests.insert(block_key, event_id);
                         } else {
                             self.dead_peers.push(event_id);
+                            self.request_finished(
+                                event_id,
+                                &block_key.data_url,
+                                self.hedge_delay_ms.saturating_mul(2),
+                            );
 
                             // try again
                             self.requested_blocks.remove(&block_key.index_block_hash);
@@ -510,10 +584,28 @@ impl BlockDownloader {
                                     ) != block_key.index_block_hash
                                     {
                                         info!("Invalid block from {:?} ({:?}): did not ask for block {}/{}", &block_key.neighbor, &block_key.data_url, block_key.consensus_hash, block.block_hash());
+                                        self.request_finished(
+                                            event_id,
+                                            &block_key.data_url,
+                                            self.hedge_delay_ms.saturating_mul(2),
+                                        );
                                         self.broken_peers.push(event_id);
                                         self.broken_neighbors.push(block_key.neighbor.clone());
+                                    } else if won.contains(&block_key.index_block_hash) {
+                                        // another peer beat this one to it
+                                        debug!(
+                                            "Already got block {} from another peer; ignoring it from {:?}",
+                                            &block_key.index_block_hash, &block_key.data_url
+                                        );
+                                        self.request_finished(
+                                            event_id,
+                                            &block_key.data_url,
+                                            self.hedge_delay_ms,
+                                        );
                                     } else {
                                         // got the block
+                                        self.request_finished(event_id, &block_key.data_url, 0);
+                                        won.insert(block_key.index_block_hash.clone());
                                         debug!(
                                             "Got block {}: {}/{}",
                                             &block_key.sortition_height,
@@ -530,6 +622,11 @@ impl BlockDownloader {
 
                                     // the fact that we asked this peer means that it's block inv indicated
                                     // it was present, so the absence is the mark of a broken peer
+                                    self.request_finished(
+                                        event_id,
+                                        &block_key.data_url,
+                                        self.hedge_delay_ms.saturating_mul(2),
+                                    );
                                     self.broken_peers.push(event_id);
                                     self.broken_neighbors.push(block_key.neighbor.clone());
                                 }
@@ -539,6 +636,11 @@ impl BlockDownloader {
                                         "Got bad HTTP response from {:?}: {:?}",
                                         &block_key.data_url, &http_response
                                     );
+                                    self.request_finished(
+                                        event_id,
+                                        &block_key.data_url,
+                                        self.hedge_delay_ms.saturating_mul(2),
+                                    );
                                     self.broken_peers.push(event_id);
                                     self.broken_neighbors.push(block_key.neighbor.clone());
                                 }
@@ -549,6 +651,9 @@ impl BlockDownloader {
             }
         });
 
+        // the first peer to send a block wins
+        self.cancel_lost_races(&mut pending_block_requests, &won);
+
         // are we done?
         if pending_block_requests.len() == 0 {
             self.state = BlockDownloaderState::GetMicroblocksBegin;
@@ -579,8 +684,17 @@ impl BlockDownloader {
         // requests that are still pending
         let mut pending_microblock_requests = HashMap::new();
 
+        // microblock streams that some peer already sent us
+        let mut won: HashSet<StacksBlockId> = self
+            .microblocks
+            .keys()
+            .map(|block_key| block_key.index_block_hash.clone())
+            .collect();
+        let getmicroblocks_requests =
+            std::mem::replace(&mut self.getmicroblocks_requests, HashMap::new());
+
         PeerNetwork::with_http(network, |ref mut network, ref mut http| {
-            for (block_key, event_id) in self.getmicroblocks_requests.drain() {
+            for (block_key, event_id) in getmicroblocks_requests.into_iter() {
                 let rh_block_key = block_key.clone();
                 match http.get_conversation(event_id) {
                     None => {
@@ -589,6 +703,11 @@ impl BlockDownloader {
                             pending_microblock_requests.insert(block_key, event_id);
                         } else {
                             self.dead_peers.push(event_id);
+                            self.request_finished(
+                                event_id,
+                                &block_key.data_url,
+                                self.hedge_delay_ms.saturating_mul(2),
+                            );
 
                             // try again
                             self.requested_microblocks
@@ -633,10 +752,28 @@ impl BlockDownloader {
                                     if microblocks.len() == 0 {
                                         // we wouldn't have asked for a 0-length stream
                                         info!("Got unexpected zero-length microblock stream from {:?} ({:?})", &block_key.neighbor, &block_key.data_url);
+                                        self.request_finished(
+                                            event_id,
+                                            &block_key.data_url,
+                                            self.hedge_delay_ms.saturating_mul(2),
+                                        );
                                         self.broken_peers.push(event_id);
                                         self.broken_neighbors.push(block_key.neighbor.clone());
+                                    } else if won.contains(&block_key.index_block_hash) {
+                                        // another peer beat this one to it
+                                        debug!(
+                                            "Already got microblocks confirmed by {} from another peer; ignoring them from {:?}",
+                                            &block_key.index_block_hash, &block_key.data_url
+                                        );
+                                        self.request_finished(
+                                            event_id,
+                                            &block_key.data_url,
+                                            self.hedge_delay_ms,
+                                        );
                                     } else {
                                         // have microblocks (but we don't know yet if they're well-formed)
+                                        self.request_finished(event_id, &block_key.data_url, 0);
+                                        won.insert(block_key.index_block_hash.clone());
                                         debug!(
                                             "Got (tentative) microblocks {}: {}/{}-{}",
                                             block_key.sortition_height,
@@ -659,10 +796,20 @@ impl BlockDownloader {
                                     // invalid microblock streams as present, even though they are
                                     // truly absent.  Don't punish these peers with a ban; just don't
                                     // talk to them for a while.
+                                    self.request_finished(
+                                        event_id,
+                                        &block_key.data_url,
+                                        self.hedge_delay_ms.saturating_mul(2),
+                                    );
                                 }
                                 _ => {
                                     // wrong message response
                                     info!("Got bad HTTP response from {:?}", &block_key.data_url);
+                                    self.request_finished(
+                                        event_id,
+                                        &block_key.data_url,
+                                        self.hedge_delay_ms.saturating_mul(2),
+                                    );
                                     self.broken_peers.push(event_id);
                                     self.broken_neighbors.push(block_key.neighbor.clone());
                                 }
@@ -673,6 +820,9 @@ impl BlockDownloader {
             }
         });
 
+        // the first peer to send a microblock stream wins
+        self.cancel_lost_races(&mut pending_microblock_requests, &won);
+
         // are we done?
         if pending_microblock_requests.len() == 0 {
             self.state = BlockDownloaderState::Done;
@@ -1027,6 +1177,104 @@ impl BlockDownloader {
         }
         return false;
     }
+
+    /// Get how quickly a data URL has answered our requests, if we have asked it anything yet.
+    pub fn get_peer_stats(&self, data_url: &UrlString) -> Option<&DownloadPeerStats> {
+        self.peer_stats.get(data_url)
+    }
+
+    /// Order the requests for a block or microblock stream so that the peers that have answered
+    /// fastest are asked first.  Peers we have not asked anything yet go first, so we learn how
+    /// fast they are; the sort is stable, so they otherwise keep their order.
+    pub fn sort_requests_by_peer_speed(&self, requests: &mut VecDeque<BlockRequestKey>) {
+        requests.make_contiguous().sort_by_key(|request| {
+            self.peer_stats
+                .get(&request.data_url)
+                .map(|stats| stats.latency_ms)
+                .unwrap_or(0)
+        });
+    }
+
+    /// Remember that the request sent on `event_id` was sent now.
+    fn request_started(&mut self, event_id: usize) {
+        self.request_start_ms.insert(event_id, get_epoch_time_ms());
+    }
+
+    /// Count the time that the request sent on `event_id` took against its data URL.  Requests
+    /// that did not get us any data are charged at least `min_latency_ms`.
+    fn request_finished(&mut self, event_id: usize, data_url: &UrlString, min_latency_ms: u64) {
+        let latency_ms = match self.request_start_ms.remove(&event_id) {
+            Some(start_ms) => get_epoch_time_ms().saturating_sub(start_ms) as u64,
+            None => 0,
+        };
+        self.peer_stats
+            .entry(data_url.clone())
+            .or_insert_with(DownloadPeerStats::new)
+            .record(cmp::max(latency_ms, min_latency_ms));
+    }
+
+    /// Stop waiting on the requests for data that another peer already sent us.  Their
+    /// connections are closed, and their peers are charged for losing the race.
+    fn cancel_lost_races(
+        &mut self,
+        pending: &mut HashMap<BlockRequestKey, usize>,
+        won: &HashSet<StacksBlockId>,
+    ) {
+        let lost: Vec<_> = pending
+            .keys()
+            .filter(|request_key| won.contains(&request_key.index_block_hash))
+            .cloned()
+            .collect();
+        for request_key in lost.into_iter() {
+            if let Some(event_id) = pending.remove(&request_key) {
+                debug!(
+                    "Event {} ({:?}, {:?}) lost the race for {}; cancelling it",
+                    event_id, &request_key.neighbor, &request_key.data_url, &request_key
+                );
+                self.request_finished(event_id, &request_key.data_url, self.hedge_delay_ms);
+                self.dead_peers.push(event_id);
+            }
+        }
+    }
+
+    /// Find the sortition heights whose in-flight requests have all gone unanswered for at least
+    /// the hedge delay, and which still have other peers to ask.
+    fn find_stalled_requests(
+        &self,
+        inflight: &HashMap<BlockRequestKey, usize>,
+        to_try: &HashMap<u64, VecDeque<BlockRequestKey>>,
+        now_ms: u128,
+    ) -> Vec<u64> {
+        // sortition height --> (number of requests, when the newest one was sent)
+        let mut heights: HashMap<u64, (u64, u128)> = HashMap::new();
+        for (request_key, event_id) in inflight.iter() {
+            let start_ms = self
+                .request_start_ms
+                .get(event_id)
+                .cloned()
+                .unwrap_or(now_ms);
+            let entry = heights
+                .entry(request_key.sortition_height)
+                .or_insert((0, 0));
+            entry.0 += 1;
+            entry.1 = cmp::max(entry.1, start_ms);
+        }
+
+        let mut stalled: Vec<u64> = heights
+            .into_iter()
+            .filter(|(height, (num_requests, newest_start_ms))| {
+                *num_requests < self.max_requests_per_block
+                    && now_ms.saturating_sub(*newest_start_ms) >= self.hedge_delay_ms as u128
+                    && to_try
+                        .get(height)
+                        .map(|keys| keys.len() > 0)
+                        .unwrap_or(false)
+            })
+            .map(|(height, _)| height)
+            .collect();
+        stalled.sort();
+        stalled
+    }
 }
 
 impl PeerNetwork {
@@ -1528,6 +1776,7 @@ impl PeerNetwork {
                 requests.push_back(request);
             }
 
+            downloader.sort_requests_by_peer_speed(&mut requests);
             blocks_to_try.insert((i as u64) + start_sortition_height, requests);
         }
 
@@ -1897,7 +2146,12 @@ impl PeerNetwork {
                 urls.push(url);
             }
 
-            downloader.dns_lookups_begin(&network.pox_id, dns_client, urls)
+            downloader.dns_lookups_begin(
+                &network.pox_id,
+                dns_client,
+                urls,
+                network.connection_opts.socks_proxy.as_ref(),
+            )
         })
     }
 
@@ -1969,6 +2223,13 @@ impl PeerNetwork {
                 Some(requestable) => {
                     if let Some(Some(ref sockaddrs)) = dns_lookups.get(requestable.get_url()) {
                         assert!(sockaddrs.len() > 0);
+                        let mut sockaddrs = sockaddrs.clone();
+                        if network.connection_opts.socks_proxy.is_none() {
+                            network
+                                .connection_opts
+                                .address_family
+                                .filter(&mut sockaddrs, PeerAddress::from_socketaddr);
+                        }
 
                         let peerhost = match PeerHost::try_from_url(requestable.get_url()) {
                             Some(ph) => ph,
@@ -2044,6 +2305,7 @@ impl PeerNetwork {
                             chainstate,
                         ) {
                             Some((key, handle)) => {
+                                downloader.request_started(handle);
                                 requests.insert(key.clone(), handle);
                             }
                             None => {}
@@ -2063,11 +2325,71 @@ impl PeerNetwork {
         })
     }
 
+    /// Ask another peer for each block or microblock stream whose requests have gone unanswered
+    /// for longer than the hedge delay.  Whichever peer answers first wins; the others' requests
+    /// are cancelled once it does.
+    fn hedge_block_requests(
+        &mut self,
+        downloader: &mut BlockDownloader,
+        microblocks: bool,
+        mempool: &MemPoolDB,
+        chainstate: &mut StacksChainState,
+    ) {
+        let now_ms = get_epoch_time_ms();
+        let stalled = if microblocks {
+            downloader.find_stalled_requests(
+                &downloader.getmicroblocks_requests,
+                &downloader.microblocks_to_try,
+                now_ms,
+            )
+        } else {
+            downloader.find_stalled_requests(
+                &downloader.getblock_requests,
+                &downloader.blocks_to_try,
+                now_ms,
+            )
+        };
+
+        for sortition_height in stalled.into_iter() {
+            let keys_opt = if microblocks {
+                downloader.microblocks_to_try.get_mut(&sortition_height)
+            } else {
+                downloader.blocks_to_try.get_mut(&sortition_height)
+            };
+            let keys = match keys_opt {
+                Some(keys) => keys,
+                None => continue,
+            };
+            if let Some((key, handle)) =
+                PeerNetwork::begin_request(self, &downloader.dns_lookups, keys, mempool, chainstate)
+            {
+                debug!(
+                    "{:?}: Hedge request for {} at sortition height {} with event {}",
+                    &self.local_peer, &key, sortition_height, handle
+                );
+                downloader.request_started(handle);
+                if microblocks {
+                    downloader.getmicroblocks_requests.insert(key, handle);
+                } else {
+                    downloader.getblock_requests.insert(key, handle);
+                }
+            }
+        }
+    }
+
     /// Try to see if all blocks are finished downloading
-    pub fn block_getblocks_try_finish(&mut self) -> Result<bool, net_error> {
+    pub fn block_getblocks_try_finish(
+        &mut self,
+        mempool: &MemPoolDB,
+        chainstate: &mut StacksChainState,
+    ) -> Result<bool, net_error> {
         test_debug!("{:?}: block_getblocks_try_finish", &self.local_peer);
         PeerNetwork::with_downloader_state(self, |ref mut network, ref mut downloader| {
-            downloader.getblocks_try_finish(network)
+            let done = downloader.getblocks_try_finish(network)?;
+            if !done {
+                network.hedge_block_requests(downloader, false, mempool, chainstate);
+            }
+            Ok(done)
         })
     }
 
@@ -2092,6 +2414,7 @@ impl PeerNetwork {
                             chainstate,
                         ) {
                             Some((key, handle)) => {
+                                downloader.request_started(handle);
                                 requests.insert(key.clone(), handle);
                             }
                             None => {}
@@ -2112,10 +2435,18 @@ impl PeerNetwork {
     }
 
     /// Try to see if all microblocks are finished downloading
-    pub fn block_getmicroblocks_try_finish(&mut self) -> Result<bool, net_error> {
+    pub fn block_getmicroblocks_try_finish(
+        &mut self,
+        mempool: &MemPoolDB,
+        chainstate: &mut StacksChainState,
+    ) -> Result<bool, net_error> {
         test_debug!("{:?}: block_getmicroblocks_try_finish", &self.local_peer);
         PeerNetwork::with_downloader_state(self, |ref mut network, ref mut downloader| {
-            downloader.getmicroblocks_try_finish(network)
+            let done = downloader.getmicroblocks_try_finish(network)?;
+            if !done {
+                network.hedge_block_requests(downloader, true, mempool, chainstate);
+            }
+            Ok(done)
         })
     }
 
@@ -2378,6 +2709,8 @@ impl PeerNetwork {
             self.connection_opts.dns_timeout,
             self.connection_opts.download_interval,
             self.connection_opts.max_inflight_blocks,
+            self.connection_opts.download_hedge_delay_ms,
+            self.connection_opts.max_download_requests_per_block,
         ));
     }
 
@@ -2504,13 +2837,13 @@ impl PeerNetwork {
                     self.block_getblocks_begin(mempool, chainstate)?;
                 }
                 BlockDownloaderState::GetBlocksFinish => {
-                    self.block_getblocks_try_finish()?;
+                    self.block_getblocks_try_finish(mempool, chainstate)?;
                 }
                 BlockDownloaderState::GetMicroblocksBegin => {
                     self.block_getmicroblocks_begin(mempool, chainstate)?;
                 }
                 BlockDownloaderState::GetMicroblocksFinish => {
-                    self.block_getmicroblocks_try_finish()?;
+                    self.block_getmicroblocks_try_finish(mempool, chainstate)?;
                 }
                 BlockDownloaderState::Done => {
                     // did a pass.
@@ -3076,6 +3409,119 @@ pub mod test {
         peers
     }
 
+    fn make_test_request_key(port: u16, sortition_height: u64) -> BlockRequestKey {
+        let nk = NeighborKey {
+            peer_version: 0x18000000,
+            network_id: 0x80000000,
+            addrbytes: PeerAddress::from_ipv4(127, 0, 0, 1),
+            port,
+        };
+        BlockRequestKey::new(
+            nk,
+            UrlString::try_from(format!("http://127.0.0.1:{}", port).as_str()).unwrap(),
+            ConsensusHash([sortition_height as u8; 20]),
+            BlockHeaderHash([sortition_height as u8; 32]),
+            StacksBlockId([sortition_height as u8; 32]),
+            None,
+            None,
+            sortition_height,
+            BlockRequestKeyKind::Block,
+            0,
+        )
+    }
+
+    #[test]
+    fn test_download_peer_stats() {
+        let mut downloader = BlockDownloader::new(1000, 10, 6, 5000, 3);
+        let fast = make_test_request_key(20443, 1);
+        let slow = make_test_request_key(20444, 1);
+        let unknown = make_test_request_key(20445, 1);
+
+        downloader.request_finished(1, &slow.data_url, 2000);
+        assert_eq!(
+            downloader
+                .get_peer_stats(&slow.data_url)
+                .unwrap()
+                .latency_ms,
+            2000
+        );
+        downloader.request_finished(2, &slow.data_url, 6000);
+        let stats = downloader.get_peer_stats(&slow.data_url).unwrap();
+        assert_eq!(stats.latency_ms, 3000);
+        assert_eq!(stats.num_requests, 2);
+
+        downloader.request_finished(3, &fast.data_url, 100);
+
+        // unknown peers first, then fastest to slowest
+        let mut requests: VecDeque<_> = vec![slow.clone(), fast.clone(), unknown.clone()]
+            .into_iter()
+            .collect();
+        downloader.sort_requests_by_peer_speed(&mut requests);
+        assert_eq!(
+            requests.into_iter().collect::<Vec<_>>(),
+            vec![unknown, fast, slow]
+        );
+    }
+
+    #[test]
+    fn test_download_hedge_requests() {
+        let mut downloader = BlockDownloader::new(1000, 10, 6, 5000, 2);
+        let first = make_test_request_key(20443, 1);
+        let second = make_test_request_key(20444, 1);
+        let third = make_test_request_key(20445, 1);
+        let other = make_test_request_key(20443, 2);
+
+        let mut to_try = HashMap::new();
+        to_try.insert(
+            1,
+            vec![second.clone(), third.clone()]
+                .into_iter()
+                .collect::<VecDeque<_>>(),
+        );
+        to_try.insert(2, VecDeque::new());
+
+        let mut inflight = HashMap::new();
+        inflight.insert(first.clone(), 1);
+        inflight.insert(other.clone(), 2);
+        downloader.request_start_ms.insert(1, 10_000);
+        downloader.request_start_ms.insert(2, 10_000);
+
+        // not stalled yet
+        assert!(downloader
+            .find_stalled_requests(&inflight, &to_try, 14_999)
+            .is_empty());
+
+        // height 2 has no other peers to ask
+        assert_eq!(
+            downloader.find_stalled_requests(&inflight, &to_try, 15_000),
+            vec![1]
+        );
+
+        // the hedge delay restarts with each new request, and at most 2 are sent
+        inflight.insert(second.clone(), 3);
+        downloader.request_start_ms.insert(3, 15_000);
+        assert!(downloader
+            .find_stalled_requests(&inflight, &to_try, 30_000)
+            .is_empty());
+
+        // the first peer to answer wins, and the others are cancelled
+        let mut won = HashSet::new();
+        won.insert(first.index_block_hash.clone());
+        inflight.remove(&first);
+        downloader.request_started(3);
+        downloader.cancel_lost_races(&mut inflight, &won);
+        assert_eq!(downloader.dead_peers, vec![3]);
+        assert_eq!(inflight.len(), 1);
+        assert!(inflight.contains_key(&other));
+        assert_eq!(
+            downloader
+                .get_peer_stats(&second.data_url)
+                .unwrap()
+                .latency_ms,
+            5000
+        );
+    }
+
     #[test]
     #[ignore]
     pub fn test_get_blocks_and_microblocks_2_peers_download_plain() {
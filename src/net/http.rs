@@ -36,8 +36,11 @@ use serde_json;
 use time;
 use url::{form_urlencoded, Url};
 
+use crate::burnchains::bitcoin::address::BitcoinAddress;
 use crate::burnchains::{Address, Txid};
 use crate::chainstate::burn::ConsensusHash;
+use crate::chainstate::stacks::address::StacksAddressExtensions;
+use crate::chainstate::stacks::db::event_index::{AccountEventsQuery, ACCOUNT_EVENT_TYPES};
 use crate::chainstate::stacks::{
     StacksBlock, StacksMicroblock, StacksPublicKey, StacksTransaction,
 };
@@ -70,9 +73,16 @@ use crate::net::HTTP_PREAMBLE_MAX_NUM_HEADERS;
 use crate::net::HTTP_REQUEST_ID_RESERVED;
 use crate::net::MAX_HEADERS;
 use crate::net::MAX_MICROBLOCKS_UNCONFIRMED;
-use crate::net::{CallReadOnlyRequestBody, TipRequest};
+use crate::net::{
+    CallReadOnlyBatchEntry, CallReadOnlyRequestBody, ReadOnlyCall, TipRequest,
+    MAX_READ_ONLY_CALL_BATCH_SIZE,
+};
 use crate::net::{GetAttachmentResponse, GetAttachmentsInvResponse, PostTransactionRequestBody};
-use clarity::vm::types::{StandardPrincipalData, TraitIdentifier};
+use crate::net::{RPCPeerBanRequestBody, DENY_BAN_DURATION};
+use crate::net::{ACCOUNT_EVENTS_PAGE_SIZE, MAX_ACCOUNT_EVENTS_PAGE_SIZE};
+use crate::net::{MAP_ENTRIES_PAGE_SIZE, MAX_MAP_ENTRIES_PAGE_SIZE};
+use clarity::vm::database::ClaritySerializable;
+use clarity::vm::types::{QualifiedContractIdentifier, StandardPrincipalData, TraitIdentifier};
 use clarity::vm::{
     ast::parser::{
         CLARITY_NAME_REGEX, CONTRACT_NAME_REGEX, PRINCIPAL_DATA_REGEX, STANDARD_PRINCIPAL_REGEX,
@@ -95,13 +105,21 @@ use crate::codec::{
 };
 use crate::types::chainstate::{BlockHeaderHash, StacksAddress, StacksBlockId};
 
+use super::DryRunTransactionRequestBody;
 use super::FeeRateEstimateRequestBody;
 
 lazy_static! {
     static ref PATH_GETINFO: Regex = Regex::new(r#"^/v2/info$"#).unwrap();
     static ref PATH_GETPOXINFO: Regex = Regex::new(r#"^/v2/pox$"#).unwrap();
+    static ref PATH_GETPOXCYCLEINFO: Regex = Regex::new(r#"^/v2/pox/cycle/([0-9]+)$"#).unwrap();
     static ref PATH_GETNEIGHBORS: Regex = Regex::new(r#"^/v2/neighbors$"#).unwrap();
+    static ref PATH_POST_BAN_PEER: Regex = Regex::new(r#"^/v2/neighbors/ban$"#).unwrap();
+    static ref PATH_POST_UNBAN_PEER: Regex = Regex::new(r#"^/v2/neighbors/unban$"#).unwrap();
+    static ref PATH_GET_CLARITY_COVERAGE: Regex =
+        Regex::new(r#"^/v2/clarity/coverage$"#).unwrap();
     static ref PATH_GETHEADERS: Regex = Regex::new(r#"^/v2/headers/([0-9]+)$"#).unwrap();
+    static ref PATH_GET_LIGHT_HEADERS: Regex =
+        Regex::new(r#"^/v2/headers/light/([0-9]+)/([0-9]+)$"#).unwrap();
     static ref PATH_GETBLOCK: Regex = Regex::new(r#"^/v2/blocks/([0-9a-f]{64})$"#).unwrap();
     static ref PATH_GETMICROBLOCKS_INDEXED: Regex =
         Regex::new(r#"^/v2/microblocks/([0-9a-f]{64})$"#).unwrap();
@@ -109,10 +127,16 @@ lazy_static! {
         Regex::new(r#"^/v2/microblocks/confirmed/([0-9a-f]{64})$"#).unwrap();
     static ref PATH_GETMICROBLOCKS_UNCONFIRMED: Regex =
         Regex::new(r#"^/v2/microblocks/unconfirmed/([0-9a-f]{64})/([0-9]{1,5})$"#).unwrap();
+    static ref PATH_GETMICROBLOCKS_BY_PARENT: Regex =
+        Regex::new(r#"^/v2/microblocks/stream/([0-9a-f]{64})(/([0-9]{1,5}))?$"#).unwrap();
     static ref PATH_GETTRANSACTION_UNCONFIRMED: Regex =
         Regex::new(r#"^/v2/transactions/unconfirmed/([0-9a-f]{64})$"#).unwrap();
+    static ref PATH_GETTRANSACTION_STATUS: Regex =
+        Regex::new(r#"^/v2/transactions/([0-9a-f]{64})$"#).unwrap();
     static ref PATH_POSTTRANSACTION: Regex = Regex::new(r#"^/v2/transactions$"#).unwrap();
     static ref PATH_POST_FEE_RATE_ESIMATE: Regex = Regex::new(r#"^/v2/fees/transaction$"#).unwrap();
+    static ref PATH_POST_DRY_RUN_TRANSACTION: Regex =
+        Regex::new(r#"^/v2/transactions/dry-run$"#).unwrap();
     static ref PATH_POSTBLOCK: Regex = Regex::new(r#"^/v2/blocks/upload/([0-9a-f]{40})$"#).unwrap();
     static ref PATH_POSTMICROBLOCK: Regex = Regex::new(r#"^/v2/microblocks$"#).unwrap();
     static ref PATH_GET_ACCOUNT: Regex = Regex::new(&format!(
@@ -120,6 +144,11 @@ lazy_static! {
         *PRINCIPAL_DATA_REGEX
     ))
     .unwrap();
+    static ref PATH_GET_ACCOUNT_EVENTS: Regex = Regex::new(&format!(
+        "^/v2/accounts/(?P<principal>{})/events$",
+        *PRINCIPAL_DATA_REGEX
+    ))
+    .unwrap();
     static ref PATH_GET_DATA_VAR: Regex = Regex::new(&format!(
         "^/v2/data_var/(?P<address>{})/(?P<contract>{})/(?P<varname>{})$",
         *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX, *CLARITY_NAME_REGEX
@@ -130,16 +159,28 @@ lazy_static! {
         *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX, *CLARITY_NAME_REGEX
     ))
     .unwrap();
+    static ref PATH_GET_MAP_ENTRIES: Regex = Regex::new(&format!(
+        "^/v2/map_entries/(?P<address>{})/(?P<contract>{})/(?P<map>{})$",
+        *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX, *CLARITY_NAME_REGEX
+    ))
+    .unwrap();
     static ref PATH_POST_CALL_READ_ONLY: Regex = Regex::new(&format!(
         "^/v2/contracts/call-read/(?P<address>{})/(?P<contract>{})/(?P<function>{})$",
         *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX, *CLARITY_NAME_REGEX
     ))
     .unwrap();
+    static ref PATH_POST_CALL_READ_ONLY_BATCH: Regex =
+        Regex::new(r#"^/v2/contracts/call-read-batch$"#).unwrap();
     static ref PATH_GET_CONTRACT_SRC: Regex = Regex::new(&format!(
         "^/v2/contracts/source/(?P<address>{})/(?P<contract>{})$",
         *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX
     ))
     .unwrap();
+    static ref PATH_GET_CONTRACT_EVENTS: Regex = Regex::new(&format!(
+        "^/v2/contracts/events/(?P<address>{})/(?P<contract>{})$",
+        *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX
+    ))
+    .unwrap();
     static ref PATH_GET_IS_TRAIT_IMPLEMENTED: Regex = Regex::new(&format!(
         "^/v2/traits/(?P<address>{})/(?P<contract>{})/(?P<traitContractAddr>{})/(?P<traitContractName>{})/(?P<traitName>{})$",
         *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX, *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX, *CLARITY_NAME_REGEX
@@ -151,11 +192,21 @@ lazy_static! {
     ))
     .unwrap();
     static ref PATH_GET_TRANSFER_COST: Regex = Regex::new("^/v2/fees/transfer$").unwrap();
+    static ref PATH_GET_CONSISTENCY_HASH: Regex = Regex::new("^/v2/consistency_hash$").unwrap();
     static ref PATH_GET_ATTACHMENTS_INV: Regex = Regex::new("^/v2/attachments/inv$").unwrap();
     static ref PATH_GET_ATTACHMENT: Regex =
         Regex::new(r#"^/v2/attachments/([0-9a-f]{40})$"#).unwrap();
+    static ref PATH_GET_ATTACHMENTS_STATUS: Regex =
+        Regex::new("^/v2/attachments/status$").unwrap();
+    static ref PATH_POST_REFETCH_ATTACHMENT: Regex =
+        Regex::new(r#"^/v2/attachments/([0-9a-f]{40})/refetch$"#).unwrap();
     static ref PATH_POST_MEMPOOL_QUERY: Regex =
         Regex::new(r#"^/v2/mempool/query$"#).unwrap();
+    static ref PATH_GET_FINALIZED_BLOCKS: Regex =
+        Regex::new(r#"^/v2/feed/blocks(/([0-9]{1,10}))?$"#).unwrap();
+    static ref PATH_GET_BURN_OPS: Regex = Regex::new(r#"^/v2/burn_ops/([0-9]{1,20})$"#).unwrap();
+    static ref PATH_GET_SORTITION: Regex =
+        Regex::new(r#"^/v2/sortitions/([0-9a-f]{40})$"#).unwrap();
     static ref PATH_OPTIONS_WILDCARD: Regex = Regex::new("^/v2/.{0,4096}$").unwrap();
 }
 
@@ -335,7 +386,7 @@ impl HttpChunkedTransferReaderState {
 }
 
 /// read adapter for chunked transfer encoding
-struct HttpChunkedTransferReader<'a, R: Read> {
+pub(crate) struct HttpChunkedTransferReader<'a, R: Read> {
     fd: &'a mut R,
     state: HttpChunkedTransferReaderState,
 }
@@ -875,7 +926,7 @@ fn empty_headers<W: Write>(_fd: &mut W) -> Result<(), codec_error> {
     Ok(())
 }
 
-fn stacks_height_headers<W: Write>(
+fn request_metadata_headers<W: Write>(
     fd: &mut W,
     md: &HttpRequestMetadata,
 ) -> Result<(), codec_error> {
@@ -886,6 +937,14 @@ fn stacks_height_headers<W: Write>(
         }
         _ => {}
     }
+    if let Some(ref token) = md.auth_token {
+        fd.write_all(format!("Authorization: Bearer {}\r\n", token).as_bytes())
+            .map_err(codec_error::WriteError)?;
+    }
+    if let Some(ref etag) = md.if_none_match {
+        fd.write_all(format!("If-None-Match: {}\r\n", etag).as_bytes())
+            .map_err(codec_error::WriteError)?;
+    }
     Ok(())
 }
 
@@ -1497,12 +1556,37 @@ impl HttpRequestType {
         )] = &[
             ("GET", &PATH_GETINFO, &HttpRequestType::parse_getinfo),
             ("GET", &PATH_GETPOXINFO, &HttpRequestType::parse_getpoxinfo),
+            (
+                "GET",
+                &PATH_GETPOXCYCLEINFO,
+                &HttpRequestType::parse_getpoxcycleinfo,
+            ),
             (
                 "GET",
                 &PATH_GETNEIGHBORS,
                 &HttpRequestType::parse_getneighbors,
             ),
+            (
+                "POST",
+                &PATH_POST_BAN_PEER,
+                &HttpRequestType::parse_post_ban_peer,
+            ),
+            (
+                "POST",
+                &PATH_POST_UNBAN_PEER,
+                &HttpRequestType::parse_post_unban_peer,
+            ),
+            (
+                "GET",
+                &PATH_GET_CLARITY_COVERAGE,
+                &HttpRequestType::parse_get_clarity_coverage,
+            ),
             ("GET", &PATH_GETHEADERS, &HttpRequestType::parse_getheaders),
+            (
+                "GET",
+                &PATH_GET_LIGHT_HEADERS,
+                &HttpRequestType::parse_get_light_headers,
+            ),
             ("GET", &PATH_GETBLOCK, &HttpRequestType::parse_getblock),
             (
                 "GET",
@@ -1519,16 +1603,31 @@ impl HttpRequestType {
                 &PATH_GETMICROBLOCKS_UNCONFIRMED,
                 &HttpRequestType::parse_getmicroblocks_unconfirmed,
             ),
+            (
+                "GET",
+                &PATH_GETMICROBLOCKS_BY_PARENT,
+                &HttpRequestType::parse_getmicroblocks_by_parent,
+            ),
             (
                 "GET",
                 &PATH_GETTRANSACTION_UNCONFIRMED,
                 &HttpRequestType::parse_gettransaction_unconfirmed,
             ),
+            (
+                "GET",
+                &PATH_GETTRANSACTION_STATUS,
+                &HttpRequestType::parse_gettransaction_status,
+            ),
             (
                 "POST",
                 &PATH_POST_FEE_RATE_ESIMATE,
                 &HttpRequestType::parse_post_fee_rate_estimate,
             ),
+            (
+                "POST",
+                &PATH_POST_DRY_RUN_TRANSACTION,
+                &HttpRequestType::parse_post_dry_run_transaction,
+            ),
             (
                 "POST",
                 &PATH_POSTTRANSACTION,
@@ -1555,6 +1654,16 @@ impl HttpRequestType {
                 &PATH_GET_MAP_ENTRY,
                 &HttpRequestType::parse_get_map_entry,
             ),
+            (
+                "GET",
+                &PATH_GET_MAP_ENTRIES,
+                &HttpRequestType::parse_get_map_entries,
+            ),
+            (
+                "GET",
+                &PATH_GET_CONSISTENCY_HASH,
+                &HttpRequestType::parse_get_consistency_hash,
+            ),
             (
                 "GET",
                 &PATH_GET_TRANSFER_COST,
@@ -1580,6 +1689,11 @@ impl HttpRequestType {
                 &PATH_POST_CALL_READ_ONLY,
                 &HttpRequestType::parse_call_read_only,
             ),
+            (
+                "POST",
+                &PATH_POST_CALL_READ_ONLY_BATCH,
+                &HttpRequestType::parse_call_read_only_batch,
+            ),
             (
                 "OPTIONS",
                 &PATH_OPTIONS_WILDCARD,
@@ -1595,11 +1709,46 @@ impl HttpRequestType {
                 &PATH_GET_ATTACHMENTS_INV,
                 &HttpRequestType::parse_get_attachments_inv,
             ),
+            (
+                "GET",
+                &PATH_GET_ATTACHMENTS_STATUS,
+                &HttpRequestType::parse_get_attachments_status,
+            ),
+            (
+                "POST",
+                &PATH_POST_REFETCH_ATTACHMENT,
+                &HttpRequestType::parse_post_refetch_attachment,
+            ),
             (
                 "POST",
                 &PATH_POST_MEMPOOL_QUERY,
                 &HttpRequestType::parse_post_mempool_query,
             ),
+            (
+                "GET",
+                &PATH_GET_FINALIZED_BLOCKS,
+                &HttpRequestType::parse_get_finalized_blocks,
+            ),
+            (
+                "GET",
+                &PATH_GET_BURN_OPS,
+                &HttpRequestType::parse_get_burn_ops,
+            ),
+            (
+                "GET",
+                &PATH_GET_SORTITION,
+                &HttpRequestType::parse_get_sortition,
+            ),
+            (
+                "GET",
+                &PATH_GET_ACCOUNT_EVENTS,
+                &HttpRequestType::parse_get_account_events,
+            ),
+            (
+                "GET",
+                &PATH_GET_CONTRACT_EVENTS,
+                &HttpRequestType::parse_get_contract_events,
+            ),
         ];
 
         // use url::Url to parse path and query string
@@ -1686,6 +1835,53 @@ impl HttpRequestType {
         ))
     }
 
+    fn parse_getpoxcycleinfo<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetPoxCycleInfo".to_string(),
+            ));
+        }
+
+        let reward_cycle = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to reward cycle".to_string(),
+            ))?
+            .as_str()
+            .parse::<u64>()
+            .map_err(|_| {
+                net_error::DeserializeError("Failed to parse reward cycle as u64".to_string())
+            })?;
+
+        let mut pox_address = None;
+        if let Some(query_string) = query {
+            for (key, value) in form_urlencoded::parse(query_string.as_bytes()) {
+                if key != "pox_address" {
+                    continue;
+                }
+                let btc_addr = BitcoinAddress::from_b58(&value).map_err(|_| {
+                    net_error::DeserializeError(format!("Invalid PoX address '{}'", &value))
+                })?;
+                pox_address = Some(StacksAddress::from_bitcoin_address(&btc_addr));
+            }
+        }
+
+        let tip = HttpRequestType::get_chain_tip_query(query);
+
+        Ok(HttpRequestType::GetPoxCycleInfo(
+            HttpRequestMetadata::from_preamble(preamble),
+            reward_cycle,
+            pox_address,
+            tip,
+        ))
+    }
+
     fn parse_getneighbors<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -1704,6 +1900,87 @@ impl HttpRequestType {
         ))
     }
 
+    fn parse_get_clarity_coverage<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetClarityCoverage".to_string(),
+            ));
+        }
+
+        let reset = query
+            .map(|query_string| {
+                form_urlencoded::parse(query_string.as_bytes())
+                    .any(|(key, value)| key == "reset" && (value == "1" || value == "true"))
+            })
+            .unwrap_or(false);
+
+        Ok(HttpRequestType::GetClarityCoverage(
+            HttpRequestMetadata::from_preamble(preamble),
+            reset,
+        ))
+    }
+
+    /// Read the JSON body of a ban or unban request
+    fn parse_peer_ban_body<R: Read>(
+        preamble: &HttpRequestPreamble,
+        fd: &mut R,
+    ) -> Result<RPCPeerBanRequestBody, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_PAYLOAD_LEN) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for peer ban ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
+        }
+
+        let bound_fd = BoundReader::from_reader(fd, content_len as u64);
+        serde_json::from_reader(bound_fd)
+            .map_err(|e| net_error::DeserializeError(format!("Failed to parse JSON body: {}", e)))
+    }
+
+    fn parse_post_ban_peer<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let body = HttpRequestType::parse_peer_ban_body(preamble, fd)?;
+        Ok(HttpRequestType::BanPeer(
+            HttpRequestMetadata::from_preamble(preamble),
+            body.ip,
+            body.port,
+            body.duration.unwrap_or(DENY_BAN_DURATION),
+        ))
+    }
+
+    fn parse_post_unban_peer<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let body = HttpRequestType::parse_peer_ban_body(preamble, fd)?;
+        Ok(HttpRequestType::UnbanPeer(
+            HttpRequestMetadata::from_preamble(preamble),
+            body.ip,
+            body.port,
+        ))
+    }
+
     fn parse_get_transfer_cost<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -1722,6 +1999,25 @@ impl HttpRequestType {
         ))
     }
 
+    fn parse_get_consistency_hash<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetConsistencyHash".to_string(),
+            ));
+        }
+
+        Ok(HttpRequestType::GetConsistencyHash(
+            HttpRequestMetadata::from_preamble(preamble),
+            HttpRequestType::get_chain_tip_query(query),
+        ))
+    }
+
     /// Check whether the given option query string sets proof=0 (setting proof to false).
     /// Defaults to true.
     fn get_proof_query(query: Option<&str>) -> bool {
@@ -1753,6 +2049,9 @@ impl HttpRequestType {
                     if let Ok(tip) = StacksBlockId::from_hex(&value) {
                         return TipRequest::SpecificTip(tip);
                     }
+                    if let Ok(height) = value.parse::<u64>() {
+                        return TipRequest::SpecificHeight(height);
+                    }
                 }
                 return TipRequest::UseLatestAnchoredTip;
             }
@@ -1762,6 +2061,19 @@ impl HttpRequestType {
         }
     }
 
+    /// Check whether the given option query string sets trace=true (or trace=1).
+    /// Defaults to false.
+    fn get_trace_query(query: Option<&str>) -> bool {
+        if let Some(query_string) = query {
+            form_urlencoded::parse(query_string.as_bytes())
+                .find(|(key, _v)| key == "trace")
+                .map(|(_k, value)| value == "true" || value == "1")
+                .unwrap_or(false)
+        } else {
+            false
+        }
+    }
+
     /// get the mempool page ID optional query argument (`page_id`)
     /// Take the first value we can parse.
     fn get_mempool_page_id_query(query: Option<&str>) -> Option<Txid> {
@@ -1783,6 +2095,65 @@ impl HttpRequestType {
         }
     }
 
+    /// get the filter and pagination query arguments of a request for an account's events:
+    /// `after` and `before` (block heights), `types` (a comma-separated list of event types),
+    /// `cursor`, and `limit`.
+    fn get_account_events_query(query: Option<&str>) -> Result<AccountEventsQuery, net_error> {
+        let mut events_query = AccountEventsQuery {
+            after_height: None,
+            before_height: None,
+            event_types: vec![],
+            cursor: None,
+            limit: ACCOUNT_EVENTS_PAGE_SIZE,
+        };
+        let query_string = match query {
+            Some(query_string) => query_string,
+            None => {
+                return Ok(events_query);
+            }
+        };
+        let bad_request = |msg: String| net_error::ClientError(ClientError::Message(msg));
+        for (key, value) in form_urlencoded::parse(query_string.as_bytes()) {
+            match key.as_ref() {
+                "after" => {
+                    events_query.after_height = Some(value.parse().map_err(|_e| {
+                        bad_request(format!("Invalid block height for `after`: {}", &value))
+                    })?);
+                }
+                "before" => {
+                    events_query.before_height = Some(value.parse().map_err(|_e| {
+                        bad_request(format!("Invalid block height for `before`: {}", &value))
+                    })?);
+                }
+                "types" => {
+                    for event_type in value.split(',') {
+                        if !ACCOUNT_EVENT_TYPES.contains(&event_type) {
+                            return Err(bad_request(format!("Unknown event type: {}", event_type)));
+                        }
+                        events_query.event_types.push(event_type.to_string());
+                    }
+                }
+                "cursor" => {
+                    events_query.cursor = Some(value.parse().map_err(bad_request)?);
+                }
+                "limit" => {
+                    let limit: u64 = value
+                        .parse()
+                        .map_err(|_e| bad_request(format!("Invalid limit: {}", &value)))?;
+                    if limit == 0 || limit > MAX_ACCOUNT_EVENTS_PAGE_SIZE {
+                        return Err(bad_request(format!(
+                            "Limit must be between 1 and {}",
+                            MAX_ACCOUNT_EVENTS_PAGE_SIZE
+                        )));
+                    }
+                    events_query.limit = limit;
+                }
+                _ => {}
+            }
+        }
+        Ok(events_query)
+    }
+
     fn parse_get_account<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -1811,6 +2182,66 @@ impl HttpRequestType {
         ))
     }
 
+    fn parse_get_account_events<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetAccountEvents".to_string(),
+            ));
+        }
+
+        let principal = PrincipalData::parse(&captures["principal"]).map_err(|_e| {
+            net_error::DeserializeError("Failed to parse account principal".into())
+        })?;
+        let events_query = HttpRequestType::get_account_events_query(query)?;
+        let tip = HttpRequestType::get_chain_tip_query(query);
+
+        Ok(HttpRequestType::GetAccountEvents(
+            HttpRequestMetadata::from_preamble(preamble),
+            principal,
+            events_query,
+            tip,
+        ))
+    }
+
+    fn parse_get_contract_events<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetContractEvents".to_string(),
+            ));
+        }
+
+        let contract_addr = StacksAddress::from_string(&captures["address"]).ok_or_else(|| {
+            net_error::DeserializeError("Failed to parse contract address".into())
+        })?;
+        let contract_name = ContractName::try_from(captures["contract"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
+        let principal = PrincipalData::Contract(QualifiedContractIdentifier::new(
+            contract_addr.into(),
+            contract_name,
+        ));
+        let events_query = HttpRequestType::get_account_events_query(query)?;
+        let tip = HttpRequestType::get_chain_tip_query(query);
+
+        Ok(HttpRequestType::GetAccountEvents(
+            HttpRequestMetadata::from_preamble(preamble),
+            principal,
+            events_query,
+            tip,
+        ))
+    }
+
     fn parse_get_data_var<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -1896,12 +2327,83 @@ impl HttpRequestType {
         ))
     }
 
-    fn parse_call_read_only<R: Read>(
-        protocol: &mut StacksHttp,
+    /// get the pagination query arguments of a request for a data map's entries: `cursor` (the
+    /// hex-encoded key to list entries after) and `limit`.
+    fn get_map_entries_query(query: Option<&str>) -> Result<(Option<Value>, u64), net_error> {
+        let mut cursor = None;
+        let mut limit = MAP_ENTRIES_PAGE_SIZE;
+        let query_string = match query {
+            Some(query_string) => query_string,
+            None => {
+                return Ok((cursor, limit));
+            }
+        };
+        let bad_request = |msg: String| net_error::ClientError(ClientError::Message(msg));
+        for (key, value) in form_urlencoded::parse(query_string.as_bytes()) {
+            match key.as_ref() {
+                "cursor" => {
+                    cursor = Some(
+                        Value::try_deserialize_hex_untyped(&value)
+                            .map_err(|_e| bad_request(format!("Invalid cursor: {}", &value)))?,
+                    );
+                }
+                "limit" => {
+                    limit = value
+                        .parse()
+                        .map_err(|_e| bad_request(format!("Invalid limit: {}", &value)))?;
+                    if limit == 0 || limit > MAX_MAP_ENTRIES_PAGE_SIZE {
+                        return Err(bad_request(format!(
+                            "Limit must be between 1 and {}",
+                            MAX_MAP_ENTRIES_PAGE_SIZE
+                        )));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok((cursor, limit))
+    }
+
+    fn parse_get_map_entries<R: Read>(
+        _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
         captures: &Captures,
         query: Option<&str>,
-        fd: &mut R,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetMapEntries".to_string(),
+            ));
+        }
+
+        let contract_addr = StacksAddress::from_string(&captures["address"]).ok_or_else(|| {
+            net_error::DeserializeError("Failed to parse contract address".into())
+        })?;
+        let contract_name = ContractName::try_from(captures["contract"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
+        let map_name = ClarityName::try_from(captures["map"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse map name".into()))?;
+        let (cursor, limit) = HttpRequestType::get_map_entries_query(query)?;
+        let tip = HttpRequestType::get_chain_tip_query(query);
+
+        Ok(HttpRequestType::GetMapEntries(
+            HttpRequestMetadata::from_preamble(preamble),
+            contract_addr,
+            contract_name,
+            map_name,
+            cursor,
+            limit,
+            tip,
+        ))
+    }
+
+    fn parse_call_read_only<R: Read>(
+        protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
         let content_len = preamble.get_content_length();
         if !(content_len > 0 && content_len < protocol.maximum_call_argument_size) {
@@ -1941,6 +2443,7 @@ impl HttpRequestType {
             })?;
 
         let tip = HttpRequestType::get_chain_tip_query(query);
+        let trace = HttpRequestType::get_trace_query(query);
 
         Ok(HttpRequestType::CallReadOnlyFunction(
             HttpRequestMetadata::from_preamble(preamble),
@@ -1950,6 +2453,80 @@ impl HttpRequestType {
             func_name,
             arguments,
             tip,
+            trace,
+        ))
+    }
+
+    fn parse_call_read_only_batch<R: Read>(
+        protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < protocol.maximum_call_argument_size) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for CallReadOnlyBatch ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
+        }
+
+        let body: Vec<CallReadOnlyBatchEntry> = serde_json::from_reader(fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+
+        if body.is_empty() || body.len() > MAX_READ_ONLY_CALL_BATCH_SIZE {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: expected between 1 and {} read-only calls",
+                MAX_READ_ONLY_CALL_BATCH_SIZE
+            )));
+        }
+
+        let mut calls = Vec::with_capacity(body.len());
+        for entry in body.into_iter() {
+            let contract_addr =
+                StacksAddress::from_string(&entry.contract_address).ok_or_else(|| {
+                    net_error::DeserializeError("Failed to parse contract address".into())
+                })?;
+            let contract_name = ContractName::try_from(entry.contract_name).map_err(|_e| {
+                net_error::DeserializeError("Failed to parse contract name".into())
+            })?;
+            let function_name = ClarityName::try_from(entry.function_name).map_err(|_e| {
+                net_error::DeserializeError("Failed to parse function name".into())
+            })?;
+            let sender = PrincipalData::parse(&entry.sender).map_err(|_e| {
+                net_error::DeserializeError("Failed to parse sender principal".into())
+            })?;
+            let arguments = entry
+                .arguments
+                .iter()
+                .map(|hex| Value::try_deserialize_hex_untyped(hex).ok())
+                .collect::<Option<Vec<Value>>>()
+                .ok_or_else(|| {
+                    net_error::DeserializeError("Failed to deserialize argument value".into())
+                })?;
+
+            calls.push(ReadOnlyCall {
+                contract_addr,
+                contract_name,
+                sender,
+                function_name,
+                arguments,
+            });
+        }
+
+        let tip = HttpRequestType::get_chain_tip_query(query);
+
+        Ok(HttpRequestType::CallReadOnlyFunctionBatch(
+            HttpRequestMetadata::from_preamble(preamble),
+            calls,
+            tip,
         ))
     }
 
@@ -2077,6 +2654,47 @@ impl HttpRequestType {
         ))
     }
 
+    fn parse_get_light_headers<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetLightHeaders".to_string(),
+            ));
+        }
+
+        let start_height: u64 = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to start height group".to_string(),
+            ))?
+            .as_str()
+            .parse()
+            .map_err(|_| net_error::DeserializeError("Failed to parse start height".to_string()))?;
+
+        let count: u64 = captures
+            .get(2)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to count group".to_string(),
+            ))?
+            .as_str()
+            .parse()
+            .map_err(|_| net_error::DeserializeError("Failed to parse count".to_string()))?;
+
+        let tip = HttpRequestType::get_chain_tip_query(query);
+
+        Ok(HttpRequestType::GetLightHeaders(
+            HttpRequestMetadata::from_preamble(preamble),
+            start_height,
+            count,
+            tip,
+        ))
+    }
+
     fn parse_getblock<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -2210,6 +2828,45 @@ impl HttpRequestType {
         ))
     }
 
+    fn parse_getmicroblocks_by_parent<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetMicroblocksByParent"
+                    .to_string(),
+            ));
+        }
+
+        let block_hash_str = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to parent block hash group".to_string(),
+            ))?
+            .as_str();
+
+        let block_hash = BlockHeaderHash::from_hex(block_hash_str).map_err(|_e| {
+            net_error::DeserializeError("Failed to parse parent block hash".to_string())
+        })?;
+
+        let seq = match captures.get(3) {
+            Some(seq_str) => Some(seq_str.as_str().parse::<u16>().map_err(|_e| {
+                net_error::DeserializeError("Failed to parse microblock sequence".to_string())
+            })?),
+            None => None,
+        };
+
+        Ok(HttpRequestType::GetMicroblocksByParent(
+            HttpRequestMetadata::from_preamble(preamble),
+            block_hash,
+            seq,
+        ))
+    }
+
     fn parse_gettransaction_unconfirmed<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -2246,6 +2903,35 @@ impl HttpRequestType {
         ))
     }
 
+    fn parse_gettransaction_status<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetTransactionStatus".to_string(),
+            ));
+        }
+
+        let txid_hex = regex
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to txid group".to_string(),
+            ))?
+            .as_str();
+
+        let txid = Txid::from_hex(txid_hex)
+            .map_err(|_e| net_error::DeserializeError("Failed to decode txid hex".to_string()))?;
+
+        Ok(HttpRequestType::GetTransactionStatus(
+            HttpRequestMetadata::from_preamble(preamble),
+            txid,
+        ))
+    }
+
     fn parse_post_fee_rate_estimate<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -2301,6 +2987,74 @@ impl HttpRequestType {
         ))
     }
 
+    fn parse_post_dry_run_transaction<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_PAYLOAD_LEN) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for DryRunTransaction ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
+        }
+
+        let bound_fd = BoundReader::from_reader(fd, content_len as u64);
+
+        let body: DryRunTransactionRequestBody =
+            serde_json::from_reader(bound_fd).map_err(|e| {
+                net_error::DeserializeError(format!("Failed to parse JSON body: {}", e))
+            })?;
+
+        let sender = StacksAddress::from_string(&body.sender)
+            .ok_or_else(|| net_error::DeserializeError("Failed to parse sender address".into()))?;
+
+        let payload_hex = if body.transaction_payload.starts_with("0x") {
+            &body.transaction_payload[2..]
+        } else {
+            &body.transaction_payload
+        };
+
+        let payload_data = hex_bytes(payload_hex).map_err(|_e| {
+            net_error::DeserializeError("Bad hex string supplied for transaction payload".into())
+        })?;
+
+        let payload = TransactionPayload::consensus_deserialize(&mut payload_data.as_slice())
+            .map_err(|e| {
+                net_error::DeserializeError(format!(
+                    "Failed to deserialize transaction payload: {}",
+                    e
+                ))
+            })?;
+
+        match payload {
+            TransactionPayload::TokenTransfer(..)
+            | TransactionPayload::ContractCall(..)
+            | TransactionPayload::SmartContract(..) => {}
+            _ => {
+                return Err(net_error::DeserializeError(format!(
+                    "Cannot dry-run a {} transaction",
+                    payload.name()
+                )));
+            }
+        }
+
+        Ok(HttpRequestType::DryRunTransaction(
+            HttpRequestMetadata::from_preamble(preamble),
+            sender,
+            payload,
+        ))
+    }
+
     fn parse_posttransaction<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -2539,6 +3293,52 @@ impl HttpRequestType {
         ))
     }
 
+    fn parse_get_attachments_status<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+        Ok(HttpRequestType::GetAttachmentsStatus(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
+    }
+
+    fn parse_post_refetch_attachment<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+        let hex_content_hash = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to attachment hash group".to_string(),
+            ))?
+            .as_str();
+
+        let content_hash = Hash160::from_hex(&hex_content_hash).map_err(|_| {
+            net_error::DeserializeError("Failed to construct hash160 from inputs".to_string())
+        })?;
+
+        Ok(HttpRequestType::RefetchAttachment(
+            HttpRequestMetadata::from_preamble(preamble),
+            content_hash,
+        ))
+    }
+
     fn parse_get_attachments_inv<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -2653,46 +3453,149 @@ impl HttpRequestType {
         ))
     }
 
-    fn parse_options_preflight<R: Read>(
+    fn parse_get_finalized_blocks<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
-        _regex: &Captures,
+        captures: &Captures,
         _query: Option<&str>,
         _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
-        Ok(HttpRequestType::OptionsPreflight(
-            HttpRequestMetadata::from_preamble(preamble),
-            preamble.path.to_string(),
-        ))
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetFinalizedBlocks".to_string(),
+            ));
+        }
+
+        let page = match captures.get(2) {
+            Some(page_str) => Some(page_str.as_str().parse::<u64>().map_err(|_| {
+                net_error::DeserializeError("Failed to parse feed page".to_string())
+            })?),
+            None => None,
+        };
+
+        Ok(HttpRequestType::GetFinalizedBlocks(
+            HttpRequestMetadata::from_preamble(preamble),
+            page,
+        ))
+    }
+
+    fn parse_get_burn_ops<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetBurnOps".to_string(),
+            ));
+        }
+
+        let burn_height = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to burn block height".to_string(),
+            ))?
+            .as_str()
+            .parse::<u64>()
+            .map_err(|_| {
+                net_error::DeserializeError("Failed to parse burn block height".to_string())
+            })?;
+
+        Ok(HttpRequestType::GetBurnOps(
+            HttpRequestMetadata::from_preamble(preamble),
+            burn_height,
+        ))
+    }
+
+    fn parse_get_sortition<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetSortition".to_string(),
+            ));
+        }
+
+        let consensus_hash = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to consensus hash".to_string(),
+            ))
+            .and_then(|ch| {
+                ConsensusHash::from_hex(ch.as_str()).map_err(|_| {
+                    net_error::DeserializeError("Failed to parse consensus hash".to_string())
+                })
+            })?;
+
+        Ok(HttpRequestType::GetSortition(
+            HttpRequestMetadata::from_preamble(preamble),
+            consensus_hash,
+        ))
+    }
+
+    fn parse_options_preflight<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        Ok(HttpRequestType::OptionsPreflight(
+            HttpRequestMetadata::from_preamble(preamble),
+            preamble.path.to_string(),
+        ))
     }
 
     pub fn metadata(&self) -> &HttpRequestMetadata {
         match *self {
             HttpRequestType::GetInfo(ref md) => md,
             HttpRequestType::GetPoxInfo(ref md, ..) => md,
+            HttpRequestType::GetPoxCycleInfo(ref md, ..) => md,
             HttpRequestType::GetNeighbors(ref md) => md,
+            HttpRequestType::BanPeer(ref md, ..) => md,
+            HttpRequestType::UnbanPeer(ref md, ..) => md,
+            HttpRequestType::GetClarityCoverage(ref md, _) => md,
             HttpRequestType::GetHeaders(ref md, ..) => md,
             HttpRequestType::GetBlock(ref md, _) => md,
             HttpRequestType::GetMicroblocksIndexed(ref md, _) => md,
             HttpRequestType::GetMicroblocksConfirmed(ref md, _) => md,
             HttpRequestType::GetMicroblocksUnconfirmed(ref md, _, _) => md,
+            HttpRequestType::GetMicroblocksByParent(ref md, ..) => md,
             HttpRequestType::GetTransactionUnconfirmed(ref md, _) => md,
+            HttpRequestType::GetTransactionStatus(ref md, _) => md,
+            HttpRequestType::GetLightHeaders(ref md, ..) => md,
             HttpRequestType::PostTransaction(ref md, _, _) => md,
             HttpRequestType::PostBlock(ref md, ..) => md,
             HttpRequestType::PostMicroblock(ref md, ..) => md,
             HttpRequestType::GetAccount(ref md, ..) => md,
             HttpRequestType::GetDataVar(ref md, ..) => md,
             HttpRequestType::GetMapEntry(ref md, ..) => md,
+            HttpRequestType::GetMapEntries(ref md, ..) => md,
+            HttpRequestType::GetConsistencyHash(ref md, ..) => md,
             HttpRequestType::GetTransferCost(ref md) => md,
             HttpRequestType::GetContractABI(ref md, ..) => md,
             HttpRequestType::GetContractSrc(ref md, ..) => md,
             HttpRequestType::GetIsTraitImplemented(ref md, ..) => md,
             HttpRequestType::CallReadOnlyFunction(ref md, ..) => md,
+            HttpRequestType::CallReadOnlyFunctionBatch(ref md, ..) => md,
             HttpRequestType::OptionsPreflight(ref md, ..) => md,
             HttpRequestType::GetAttachmentsInv(ref md, ..) => md,
             HttpRequestType::GetAttachment(ref md, ..) => md,
+            HttpRequestType::GetAttachmentsStatus(ref md) => md,
+            HttpRequestType::RefetchAttachment(ref md, ..) => md,
             HttpRequestType::MemPoolQuery(ref md, ..) => md,
             HttpRequestType::FeeRateEstimate(ref md, _, _) => md,
+            HttpRequestType::DryRunTransaction(ref md, ..) => md,
+            HttpRequestType::GetFinalizedBlocks(ref md, _) => md,
+            HttpRequestType::GetBurnOps(ref md, _) => md,
+            HttpRequestType::GetSortition(ref md, _) => md,
+            HttpRequestType::GetAccountEvents(ref md, ..) => md,
             HttpRequestType::ClientError(ref md, ..) => md,
         }
     }
@@ -2701,29 +3604,46 @@ impl HttpRequestType {
         match *self {
             HttpRequestType::GetInfo(ref mut md) => md,
             HttpRequestType::GetPoxInfo(ref mut md, ..) => md,
+            HttpRequestType::GetPoxCycleInfo(ref mut md, ..) => md,
             HttpRequestType::GetNeighbors(ref mut md) => md,
+            HttpRequestType::BanPeer(ref mut md, ..) => md,
+            HttpRequestType::UnbanPeer(ref mut md, ..) => md,
+            HttpRequestType::GetClarityCoverage(ref mut md, _) => md,
             HttpRequestType::GetHeaders(ref mut md, ..) => md,
             HttpRequestType::GetBlock(ref mut md, _) => md,
             HttpRequestType::GetMicroblocksIndexed(ref mut md, _) => md,
             HttpRequestType::GetMicroblocksConfirmed(ref mut md, _) => md,
             HttpRequestType::GetMicroblocksUnconfirmed(ref mut md, _, _) => md,
+            HttpRequestType::GetMicroblocksByParent(ref mut md, ..) => md,
             HttpRequestType::GetTransactionUnconfirmed(ref mut md, _) => md,
+            HttpRequestType::GetTransactionStatus(ref mut md, _) => md,
+            HttpRequestType::GetLightHeaders(ref mut md, ..) => md,
             HttpRequestType::PostTransaction(ref mut md, _, _) => md,
             HttpRequestType::PostBlock(ref mut md, ..) => md,
             HttpRequestType::PostMicroblock(ref mut md, ..) => md,
             HttpRequestType::GetAccount(ref mut md, ..) => md,
             HttpRequestType::GetDataVar(ref mut md, ..) => md,
             HttpRequestType::GetMapEntry(ref mut md, ..) => md,
+            HttpRequestType::GetMapEntries(ref mut md, ..) => md,
+            HttpRequestType::GetConsistencyHash(ref mut md, ..) => md,
             HttpRequestType::GetTransferCost(ref mut md) => md,
             HttpRequestType::GetContractABI(ref mut md, ..) => md,
             HttpRequestType::GetContractSrc(ref mut md, ..) => md,
             HttpRequestType::GetIsTraitImplemented(ref mut md, ..) => md,
             HttpRequestType::CallReadOnlyFunction(ref mut md, ..) => md,
+            HttpRequestType::CallReadOnlyFunctionBatch(ref mut md, ..) => md,
             HttpRequestType::OptionsPreflight(ref mut md, ..) => md,
             HttpRequestType::GetAttachmentsInv(ref mut md, ..) => md,
             HttpRequestType::GetAttachment(ref mut md, ..) => md,
+            HttpRequestType::GetAttachmentsStatus(ref mut md) => md,
+            HttpRequestType::RefetchAttachment(ref mut md, ..) => md,
             HttpRequestType::MemPoolQuery(ref mut md, ..) => md,
             HttpRequestType::FeeRateEstimate(ref mut md, _, _) => md,
+            HttpRequestType::DryRunTransaction(ref mut md, ..) => md,
+            HttpRequestType::GetFinalizedBlocks(ref mut md, _) => md,
+            HttpRequestType::GetBurnOps(ref mut md, _) => md,
+            HttpRequestType::GetSortition(ref mut md, _) => md,
+            HttpRequestType::GetAccountEvents(ref mut md, ..) => md,
             HttpRequestType::ClientError(ref mut md, ..) => md,
         }
     }
@@ -2736,6 +3656,13 @@ impl HttpRequestType {
             TipRequest::SpecificTip(tip) => {
                 format!("?tip={}{}", tip, if with_proof { "" } else { "&proof=0" })
             }
+            TipRequest::SpecificHeight(height) => {
+                format!(
+                    "?tip={}{}",
+                    height,
+                    if with_proof { "" } else { "&proof=0" }
+                )
+            }
             TipRequest::UseLatestAnchoredTip => {
                 if !with_proof {
                     format!("?proof=0")
@@ -2746,6 +3673,88 @@ impl HttpRequestType {
         }
     }
 
+    fn make_map_entries_query_string(
+        cursor: &Option<Value>,
+        limit: u64,
+        tip_req: &TipRequest,
+    ) -> String {
+        let mut args = vec![];
+        match tip_req {
+            TipRequest::UseLatestUnconfirmedTip => args.push("tip=latest".to_string()),
+            TipRequest::SpecificTip(tip) => args.push(format!("tip={}", tip)),
+            TipRequest::SpecificHeight(height) => args.push(format!("tip={}", height)),
+            TipRequest::UseLatestAnchoredTip => {}
+        }
+        if let Some(cursor) = cursor {
+            args.push(format!(
+                "cursor=0x{}",
+                ClaritySerializable::serialize(cursor)
+            ));
+        }
+        if limit != MAP_ENTRIES_PAGE_SIZE {
+            args.push(format!("limit={}", limit));
+        }
+        if args.is_empty() {
+            "".to_string()
+        } else {
+            format!("?{}", args.join("&"))
+        }
+    }
+
+    fn make_account_events_query_string(
+        query: &AccountEventsQuery,
+        tip_req: &TipRequest,
+    ) -> String {
+        let mut args = vec![];
+        match tip_req {
+            TipRequest::UseLatestUnconfirmedTip => args.push("tip=latest".to_string()),
+            TipRequest::SpecificTip(tip) => args.push(format!("tip={}", tip)),
+            TipRequest::SpecificHeight(height) => args.push(format!("tip={}", height)),
+            TipRequest::UseLatestAnchoredTip => {}
+        }
+        if let Some(after_height) = query.after_height {
+            args.push(format!("after={}", after_height));
+        }
+        if let Some(before_height) = query.before_height {
+            args.push(format!("before={}", before_height));
+        }
+        if !query.event_types.is_empty() {
+            args.push(format!("types={}", query.event_types.join(",")));
+        }
+        if let Some(cursor) = query.cursor {
+            args.push(format!("cursor={}", cursor));
+        }
+        if query.limit != ACCOUNT_EVENTS_PAGE_SIZE {
+            args.push(format!("limit={}", query.limit));
+        }
+        if args.is_empty() {
+            "".to_string()
+        } else {
+            format!("?{}", args.join("&"))
+        }
+    }
+
+    fn make_pox_cycle_query_string(
+        pox_address: &Option<StacksAddress>,
+        tip_req: &TipRequest,
+    ) -> String {
+        let mut args = vec![];
+        match tip_req {
+            TipRequest::UseLatestUnconfirmedTip => args.push("tip=latest".to_string()),
+            TipRequest::SpecificTip(tip) => args.push(format!("tip={}", tip)),
+            TipRequest::SpecificHeight(height) => args.push(format!("tip={}", height)),
+            TipRequest::UseLatestAnchoredTip => {}
+        }
+        if let Some(pox_address) = pox_address {
+            args.push(format!("pox_address={}", pox_address.clone().to_b58()));
+        }
+        if args.is_empty() {
+            "".to_string()
+        } else {
+            format!("?{}", args.join("&"))
+        }
+    }
+
     pub fn request_path(&self) -> String {
         match self {
             HttpRequestType::GetInfo(_md) => "/v2/info".to_string(),
@@ -2753,12 +3762,30 @@ impl HttpRequestType {
                 "/v2/pox{}",
                 HttpRequestType::make_tip_query_string(tip_req, true)
             ),
+            HttpRequestType::GetPoxCycleInfo(_md, reward_cycle, pox_address, tip_req) => format!(
+                "/v2/pox/cycle/{}{}",
+                reward_cycle,
+                HttpRequestType::make_pox_cycle_query_string(pox_address, tip_req)
+            ),
             HttpRequestType::GetNeighbors(_md) => "/v2/neighbors".to_string(),
+            HttpRequestType::BanPeer(..) | HttpRequestType::UnbanPeer(..) => {
+                self.get_path().to_string()
+            }
+            HttpRequestType::GetClarityCoverage(_md, reset) => format!(
+                "/v2/clarity/coverage{}",
+                if *reset { "?reset=1" } else { "" }
+            ),
             HttpRequestType::GetHeaders(_md, quantity, tip_req) => format!(
                 "/v2/headers/{}{}",
                 quantity,
                 HttpRequestType::make_tip_query_string(tip_req, true)
             ),
+            HttpRequestType::GetLightHeaders(_md, start_height, count, tip_req) => format!(
+                "/v2/headers/light/{}/{}{}",
+                start_height,
+                count,
+                HttpRequestType::make_tip_query_string(tip_req, true)
+            ),
             HttpRequestType::GetBlock(_md, block_hash) => {
                 format!("/v2/blocks/{}", block_hash.to_hex())
             }
@@ -2773,9 +3800,16 @@ impl HttpRequestType {
                 block_hash.to_hex(),
                 min_seq
             ),
+            HttpRequestType::GetMicroblocksByParent(_md, block_hash, seq) => match seq {
+                Some(seq) => format!("/v2/microblocks/stream/{}/{}", block_hash.to_hex(), seq),
+                None => format!("/v2/microblocks/stream/{}", block_hash.to_hex()),
+            },
             HttpRequestType::GetTransactionUnconfirmed(_md, txid) => {
                 format!("/v2/transactions/unconfirmed/{}", txid)
             }
+            HttpRequestType::GetTransactionStatus(_md, txid) => {
+                format!("/v2/transactions/{}", txid)
+            }
             HttpRequestType::PostTransaction(_md, ..) => "/v2/transactions".to_string(),
             HttpRequestType::PostBlock(_md, ch, ..) => format!("/v2/blocks/upload/{}", &ch),
             HttpRequestType::PostMicroblock(_md, _, tip_req) => format!(
@@ -2818,6 +3852,25 @@ impl HttpRequestType {
                 map_name.as_str(),
                 HttpRequestType::make_tip_query_string(tip_req, *with_proof)
             ),
+            HttpRequestType::GetMapEntries(
+                _md,
+                contract_addr,
+                contract_name,
+                map_name,
+                cursor,
+                limit,
+                tip_req,
+            ) => format!(
+                "/v2/map_entries/{}/{}/{}{}",
+                &contract_addr.to_string(),
+                contract_name.as_str(),
+                map_name.as_str(),
+                HttpRequestType::make_map_entries_query_string(cursor, *limit, tip_req)
+            ),
+            HttpRequestType::GetConsistencyHash(_md, tip_req) => format!(
+                "/v2/consistency_hash{}",
+                HttpRequestType::make_tip_query_string(tip_req, true)
+            ),
             HttpRequestType::GetTransferCost(_md) => "/v2/fees/transfer".into(),
             HttpRequestType::GetContractABI(_, contract_addr, contract_name, tip_req) => format!(
                 "/v2/contracts/interface/{}/{}{}",
@@ -2860,11 +3913,25 @@ impl HttpRequestType {
                 func_name,
                 _,
                 tip_req,
-            ) => format!(
-                "/v2/contracts/call-read/{}/{}/{}{}",
-                contract_addr,
-                contract_name.as_str(),
-                func_name.as_str(),
+                trace,
+            ) => {
+                let query = HttpRequestType::make_tip_query_string(tip_req, true);
+                format!(
+                    "/v2/contracts/call-read/{}/{}/{}{}",
+                    contract_addr,
+                    contract_name.as_str(),
+                    func_name.as_str(),
+                    if !*trace {
+                        query
+                    } else if query.is_empty() {
+                        "?trace=true".to_string()
+                    } else {
+                        format!("{}&trace=true", query)
+                    }
+                )
+            }
+            HttpRequestType::CallReadOnlyFunctionBatch(_, _, tip_req) => format!(
+                "/v2/contracts/call-read-batch{}",
                 HttpRequestType::make_tip_query_string(tip_req, true)
             ),
             HttpRequestType::OptionsPreflight(_md, path) => path.to_string(),
@@ -2886,6 +3953,10 @@ impl HttpRequestType {
             HttpRequestType::GetAttachment(_, content_hash) => {
                 format!("/v2/attachments/{}", to_hex(&content_hash.0[..]))
             }
+            HttpRequestType::GetAttachmentsStatus(_) => "/v2/attachments/status".to_string(),
+            HttpRequestType::RefetchAttachment(_, content_hash) => {
+                format!("/v2/attachments/{}/refetch", to_hex(&content_hash.0[..]))
+            }
             HttpRequestType::MemPoolQuery(_, _, page_id_opt) => match page_id_opt {
                 Some(page_id) => {
                     format!("/v2/mempool/query?page_id={}", page_id)
@@ -2893,6 +3964,32 @@ impl HttpRequestType {
                 None => "/v2/mempool/query".to_string(),
             },
             HttpRequestType::FeeRateEstimate(_, _, _) => self.get_path().to_string(),
+            HttpRequestType::DryRunTransaction(..) => self.get_path().to_string(),
+            HttpRequestType::GetFinalizedBlocks(_md, page_opt) => match page_opt {
+                Some(page) => format!("/v2/feed/blocks/{}", page),
+                None => "/v2/feed/blocks".to_string(),
+            },
+            HttpRequestType::GetBurnOps(_md, burn_height) => {
+                format!("/v2/burn_ops/{}", burn_height)
+            }
+            HttpRequestType::GetSortition(_md, consensus_hash) => {
+                format!("/v2/sortitions/{}", consensus_hash)
+            }
+            HttpRequestType::GetAccountEvents(_md, principal, query, tip_req) => {
+                let path = match principal {
+                    PrincipalData::Contract(contract_id) => format!(
+                        "/v2/contracts/events/{}/{}",
+                        StacksAddress::from(contract_id.issuer.clone()),
+                        contract_id.name.as_str()
+                    ),
+                    PrincipalData::Standard(_) => format!("/v2/accounts/{}/events", principal),
+                };
+                format!(
+                    "{}{}",
+                    path,
+                    HttpRequestType::make_account_events_query_string(query, tip_req)
+                )
+            }
             HttpRequestType::ClientError(_md, e) => match e {
                 ClientError::NotFound(path) => path.to_string(),
                 _ => "error path unknown".into(),
@@ -2900,11 +3997,68 @@ impl HttpRequestType {
         }
     }
 
+    /// Every path template that `get_path()` can return for an RPC route
+    pub const PATH_TEMPLATES: &'static [&'static str] = &[
+        "/v2/info",
+        "/v2/pox",
+        "/v2/pox/cycle/:n",
+        "/v2/neighbors",
+        "/v2/neighbors/ban",
+        "/v2/neighbors/unban",
+        "/v2/clarity/coverage",
+        "/v2/headers/:height",
+        "/v2/blocks/:hash",
+        "/v2/microblocks/:hash",
+        "/v2/microblocks/confirmed/:hash",
+        "/v2/microblocks/unconfirmed/:hash/:seq",
+        "/v2/microblocks/stream/:hash",
+        "/v2/microblocks/stream/:hash/:seq",
+        "/v2/transactions/unconfirmed/:txid",
+        "/v2/transactions/:txid",
+        "/v2/headers/light/:start_height/:count",
+        "/v2/transactions",
+        "/v2/blocks/upload/:block",
+        "/v2/microblocks",
+        "/v2/accounts/:principal",
+        "/v2/data_var/:principal/:contract_name/:var_name",
+        "/v2/map_entry/:principal/:contract_name/:map_name",
+        "/v2/map_entries/:principal/:contract_name/:map_name",
+        "/v2/consistency_hash",
+        "/v2/fees/transfer",
+        "/v2/contracts/interface/:principal/:contract_name",
+        "/v2/contracts/source/:principal/:contract_name",
+        "/v2/contracts/call-read/:principal/:contract_name/:func_name",
+        "/v2/contracts/call-read-batch",
+        "/v2/attachments/inv",
+        "/v2/attachments/:hash",
+        "/v2/attachments/status",
+        "/v2/attachments/:hash/refetch",
+        "/v2/traits/:principal/:contract_name",
+        "/v2/mempool/query",
+        "/v2/fees/transaction",
+        "/v2/transactions/dry-run",
+        "/v2/feed/blocks",
+        "/v2/feed/blocks/:page",
+        "/v2/burn_ops/:burn_height",
+        "/v2/sortitions/:consensus_hash",
+        "/v2/accounts/:principal/events",
+        "/v2/contracts/events/:principal/:contract_name",
+    ];
+
+    /// Is `path` the path template of an RPC route, as `get_path()` reports it?
+    pub fn is_path_template(path: &str) -> bool {
+        HttpRequestType::PATH_TEMPLATES.contains(&path)
+    }
+
     pub fn get_path(&self) -> &'static str {
         match self {
             HttpRequestType::GetInfo(..) => "/v2/info",
             HttpRequestType::GetPoxInfo(..) => "/v2/pox",
+            HttpRequestType::GetPoxCycleInfo(..) => "/v2/pox/cycle/:n",
             HttpRequestType::GetNeighbors(..) => "/v2/neighbors",
+            HttpRequestType::BanPeer(..) => "/v2/neighbors/ban",
+            HttpRequestType::UnbanPeer(..) => "/v2/neighbors/unban",
+            HttpRequestType::GetClarityCoverage(..) => "/v2/clarity/coverage",
             HttpRequestType::GetHeaders(..) => "/v2/headers/:height",
             HttpRequestType::GetBlock(..) => "/v2/blocks/:hash",
             HttpRequestType::GetMicroblocksIndexed(..) => "/v2/microblocks/:hash",
@@ -2912,13 +4066,23 @@ impl HttpRequestType {
             HttpRequestType::GetMicroblocksUnconfirmed(..) => {
                 "/v2/microblocks/unconfirmed/:hash/:seq"
             }
+            HttpRequestType::GetMicroblocksByParent(_, _, None) => "/v2/microblocks/stream/:hash",
+            HttpRequestType::GetMicroblocksByParent(_, _, Some(_)) => {
+                "/v2/microblocks/stream/:hash/:seq"
+            }
             HttpRequestType::GetTransactionUnconfirmed(..) => "/v2/transactions/unconfirmed/:txid",
+            HttpRequestType::GetTransactionStatus(..) => "/v2/transactions/:txid",
+            HttpRequestType::GetLightHeaders(..) => "/v2/headers/light/:start_height/:count",
             HttpRequestType::PostTransaction(..) => "/v2/transactions",
             HttpRequestType::PostBlock(..) => "/v2/blocks/upload/:block",
             HttpRequestType::PostMicroblock(..) => "/v2/microblocks",
             HttpRequestType::GetAccount(..) => "/v2/accounts/:principal",
             HttpRequestType::GetDataVar(..) => "/v2/data_var/:principal/:contract_name/:var_name",
             HttpRequestType::GetMapEntry(..) => "/v2/map_entry/:principal/:contract_name/:map_name",
+            HttpRequestType::GetMapEntries(..) => {
+                "/v2/map_entries/:principal/:contract_name/:map_name"
+            }
+            HttpRequestType::GetConsistencyHash(..) => "/v2/consistency_hash",
             HttpRequestType::GetTransferCost(..) => "/v2/fees/transfer",
             HttpRequestType::GetContractABI(..) => {
                 "/v2/contracts/interface/:principal/:contract_name"
@@ -2927,11 +4091,25 @@ impl HttpRequestType {
             HttpRequestType::CallReadOnlyFunction(..) => {
                 "/v2/contracts/call-read/:principal/:contract_name/:func_name"
             }
+            HttpRequestType::CallReadOnlyFunctionBatch(..) => "/v2/contracts/call-read-batch",
             HttpRequestType::GetAttachmentsInv(..) => "/v2/attachments/inv",
             HttpRequestType::GetAttachment(..) => "/v2/attachments/:hash",
+            HttpRequestType::GetAttachmentsStatus(..) => "/v2/attachments/status",
+            HttpRequestType::RefetchAttachment(..) => "/v2/attachments/:hash/refetch",
             HttpRequestType::GetIsTraitImplemented(..) => "/v2/traits/:principal/:contract_name",
             HttpRequestType::MemPoolQuery(..) => "/v2/mempool/query",
             HttpRequestType::FeeRateEstimate(_, _, _) => "/v2/fees/transaction",
+            HttpRequestType::DryRunTransaction(..) => "/v2/transactions/dry-run",
+            HttpRequestType::GetFinalizedBlocks(_, None) => "/v2/feed/blocks",
+            HttpRequestType::GetFinalizedBlocks(_, Some(_)) => "/v2/feed/blocks/:page",
+            HttpRequestType::GetBurnOps(..) => "/v2/burn_ops/:burn_height",
+            HttpRequestType::GetSortition(..) => "/v2/sortitions/:consensus_hash",
+            HttpRequestType::GetAccountEvents(_, PrincipalData::Standard(_), ..) => {
+                "/v2/accounts/:principal/events"
+            }
+            HttpRequestType::GetAccountEvents(_, PrincipalData::Contract(_), ..) => {
+                "/v2/contracts/events/:principal/:contract_name"
+            }
             HttpRequestType::OptionsPreflight(..) | HttpRequestType::ClientError(..) => "/",
         }
     }
@@ -2977,7 +4155,7 @@ impl HttpRequestType {
                     md.keep_alive,
                     Some(request_body_bytes.len() as u32),
                     content_type,
-                    |fd| stacks_height_headers(fd, md),
+                    |fd| request_metadata_headers(fd, md),
                 )?;
                 fd.write_all(&request_body_bytes)
                     .map_err(net_error::WriteError)?;
@@ -2995,7 +4173,7 @@ impl HttpRequestType {
                     md.keep_alive,
                     Some(block_bytes.len() as u32),
                     Some(&HttpContentType::Bytes),
-                    |fd| stacks_height_headers(fd, md),
+                    |fd| request_metadata_headers(fd, md),
                 )?;
                 fd.write_all(&block_bytes).map_err(net_error::WriteError)?;
             }
@@ -3012,7 +4190,7 @@ impl HttpRequestType {
                     md.keep_alive,
                     Some(mb_bytes.len() as u32),
                     Some(&HttpContentType::Bytes),
-                    |fd| stacks_height_headers(fd, md),
+                    |fd| request_metadata_headers(fd, md),
                 )?;
                 fd.write_all(&mb_bytes).map_err(net_error::WriteError)?;
             }
@@ -3038,7 +4216,7 @@ impl HttpRequestType {
                     md.keep_alive,
                     Some(request_json.as_bytes().len() as u32),
                     Some(&HttpContentType::JSON),
-                    |fd| stacks_height_headers(fd, md),
+                    |fd| request_metadata_headers(fd, md),
                 )?;
                 fd.write_all(&request_json.as_bytes())
                     .map_err(net_error::WriteError)?;
@@ -3082,7 +4260,122 @@ impl HttpRequestType {
                     md.keep_alive,
                     Some(request_body_bytes.len() as u32),
                     Some(&HttpContentType::JSON),
-                    |fd| stacks_height_headers(fd, md),
+                    |fd| request_metadata_headers(fd, md),
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::CallReadOnlyFunctionBatch(md, calls, ..) => {
+                let mut request_body = vec![];
+                for call in calls.iter() {
+                    let mut args = vec![];
+                    for arg in call.arguments.iter() {
+                        let mut arg_bytes = vec![];
+                        arg.serialize_write(&mut arg_bytes)
+                            .map_err(net_error::WriteError)?;
+                        args.push(to_hex(&arg_bytes));
+                    }
+                    request_body.push(CallReadOnlyBatchEntry {
+                        contract_address: call.contract_addr.to_string(),
+                        contract_name: call.contract_name.to_string(),
+                        function_name: call.function_name.to_string(),
+                        sender: call.sender.to_string(),
+                        arguments: args,
+                    });
+                }
+
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize read-only call batch to JSON: {:?}",
+                        &e
+                    ))
+                })?;
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    |fd| request_metadata_headers(fd, md),
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::BanPeer(md, addr, port, ..)
+            | HttpRequestType::UnbanPeer(md, addr, port) => {
+                let request_body = RPCPeerBanRequestBody {
+                    ip: addr.clone(),
+                    port: *port,
+                    duration: match self {
+                        HttpRequestType::BanPeer(_, _, _, duration) => Some(*duration),
+                        _ => None,
+                    },
+                };
+
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize peer ban to JSON: {:?}",
+                        &e
+                    ))
+                })?;
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    |fd| request_metadata_headers(fd, md),
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::RefetchAttachment(md, ..) => {
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(0),
+                    None,
+                    |fd| request_metadata_headers(fd, md),
+                )?;
+            }
+            HttpRequestType::DryRunTransaction(md, sender, payload) => {
+                let request_body = DryRunTransactionRequestBody {
+                    sender: sender.to_string(),
+                    transaction_payload: to_hex(&payload.serialize_to_vec()),
+                };
+
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize dry run to JSON: {:?}",
+                        &e
+                    ))
+                })?;
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    |fd| request_metadata_headers(fd, md),
                 )?;
                 fd.write_all(&request_body_bytes)
                     .map_err(net_error::WriteError)?;
@@ -3115,7 +4408,7 @@ impl HttpRequestType {
                     md.keep_alive,
                     None,
                     None,
-                    |fd| stacks_height_headers(fd, md),
+                    |fd| request_metadata_headers(fd, md),
                 )?;
             }
         }
@@ -3336,6 +4629,13 @@ impl HttpResponseType {
         if preamble.status_code >= 400 {
             return HttpResponseType::parse_error(protocol, request_version, preamble, fd);
         }
+        if preamble.status_code == 304 {
+            let etag = preamble.headers.get("etag").cloned().unwrap_or_default();
+            return Ok(HttpResponseType::NotModified(
+                HttpResponseMetadata::from_preamble(request_version, preamble),
+                etag,
+            ));
+        }
 
         // TODO: make this static somehow
         let RESPONSE_METHODS: &[(
@@ -3350,11 +4650,27 @@ impl HttpResponseType {
         )] = &[
             (&PATH_GETINFO, &HttpResponseType::parse_peerinfo),
             (&PATH_GETPOXINFO, &HttpResponseType::parse_poxinfo),
+            (&PATH_GETPOXCYCLEINFO, &HttpResponseType::parse_poxcycleinfo),
             (&PATH_GETNEIGHBORS, &HttpResponseType::parse_neighbors),
+            (&PATH_POST_BAN_PEER, &HttpResponseType::parse_peer_ban),
+            (&PATH_POST_UNBAN_PEER, &HttpResponseType::parse_peer_ban),
+            (
+                &PATH_GET_CLARITY_COVERAGE,
+                &HttpResponseType::parse_clarity_coverage,
+            ),
             (&PATH_GETHEADERS, &HttpResponseType::parse_headers),
+            (
+                &PATH_GET_LIGHT_HEADERS,
+                &HttpResponseType::parse_light_headers,
+            ),
             (&PATH_GETBLOCK, &HttpResponseType::parse_block),
             (&PATH_GET_DATA_VAR, &HttpResponseType::parse_get_data_var),
             (&PATH_GET_MAP_ENTRY, &HttpResponseType::parse_get_map_entry),
+            (&PATH_GET_MAP_ENTRIES, &HttpResponseType::parse_map_entries),
+            (
+                &PATH_GET_CONSISTENCY_HASH,
+                &HttpResponseType::parse_consistency_hash,
+            ),
             (
                 &PATH_GETMICROBLOCKS_INDEXED,
                 &HttpResponseType::parse_microblocks,
@@ -3367,11 +4683,23 @@ impl HttpResponseType {
                 &PATH_GETMICROBLOCKS_UNCONFIRMED,
                 &HttpResponseType::parse_microblocks_unconfirmed,
             ),
+            (
+                &PATH_GETMICROBLOCKS_BY_PARENT,
+                &HttpResponseType::parse_microblocks,
+            ),
             (
                 &PATH_GETTRANSACTION_UNCONFIRMED,
                 &HttpResponseType::parse_transaction_unconfirmed,
             ),
+            (
+                &PATH_GETTRANSACTION_STATUS,
+                &HttpResponseType::parse_transaction_status,
+            ),
             (&PATH_POSTTRANSACTION, &HttpResponseType::parse_txid),
+            (
+                &PATH_POST_DRY_RUN_TRANSACTION,
+                &HttpResponseType::parse_dry_run_transaction,
+            ),
             (
                 &PATH_POSTBLOCK,
                 &HttpResponseType::parse_stacks_block_accepted,
@@ -3397,6 +4725,10 @@ impl HttpResponseType {
                 &PATH_POST_CALL_READ_ONLY,
                 &HttpResponseType::parse_call_read_only,
             ),
+            (
+                &PATH_POST_CALL_READ_ONLY_BATCH,
+                &HttpResponseType::parse_call_read_only_batch,
+            ),
             (
                 &PATH_GET_ATTACHMENT,
                 &HttpResponseType::parse_get_attachment,
@@ -3405,10 +4737,32 @@ impl HttpResponseType {
                 &PATH_GET_ATTACHMENTS_INV,
                 &HttpResponseType::parse_get_attachments_inv,
             ),
+            (
+                &PATH_GET_ATTACHMENTS_STATUS,
+                &HttpResponseType::parse_attachments_status,
+            ),
+            (
+                &PATH_POST_REFETCH_ATTACHMENT,
+                &HttpResponseType::parse_attachment_refetch,
+            ),
             (
                 &PATH_POST_MEMPOOL_QUERY,
                 &HttpResponseType::parse_post_mempool_query,
             ),
+            (
+                &PATH_GET_FINALIZED_BLOCKS,
+                &HttpResponseType::parse_finalized_blocks,
+            ),
+            (&PATH_GET_BURN_OPS, &HttpResponseType::parse_burn_ops),
+            (&PATH_GET_SORTITION, &HttpResponseType::parse_sortition),
+            (
+                &PATH_GET_ACCOUNT_EVENTS,
+                &HttpResponseType::parse_account_events,
+            ),
+            (
+                &PATH_GET_CONTRACT_EVENTS,
+                &HttpResponseType::parse_account_events,
+            ),
         ];
 
         // use url::Url to parse path and query string
@@ -3487,52 +4841,211 @@ impl HttpResponseType {
         ))
     }
 
-    fn parse_neighbors<R: Read>(
+    fn parse_poxcycleinfo<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
         preamble: &HttpResponsePreamble,
         fd: &mut R,
         len_hint: Option<usize>,
     ) -> Result<HttpResponseType, net_error> {
-        let neighbors_data =
+        let pox_cycle_info =
             HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::Neighbors(
+        Ok(HttpResponseType::PoxCycleInfo(
             HttpResponseMetadata::from_preamble(request_version, preamble),
-            neighbors_data,
+            pox_cycle_info,
         ))
     }
 
-    fn parse_headers<R: Read>(
+    fn parse_finalized_blocks<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
         preamble: &HttpResponsePreamble,
         fd: &mut R,
         len_hint: Option<usize>,
     ) -> Result<HttpResponseType, net_error> {
-        let headers: Vec<ExtendedStacksHeader> =
-            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::Headers(
+        let page = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::FinalizedBlocks(
             HttpResponseMetadata::from_preamble(request_version, preamble),
-            headers,
+            page,
         ))
     }
 
-    fn parse_block<R: Read>(
+    fn parse_burn_ops<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
         preamble: &HttpResponsePreamble,
         fd: &mut R,
         len_hint: Option<usize>,
     ) -> Result<HttpResponseType, net_error> {
-        let block: StacksBlock =
-            HttpResponseType::parse_bytestream(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::Block(
+        let burn_ops =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::BurnOps(
             HttpResponseMetadata::from_preamble(request_version, preamble),
-            block,
+            burn_ops,
         ))
     }
 
-    fn parse_microblocks<R: Read>(
+    fn parse_sortition<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let sortition =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::Sortition(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            sortition,
+        ))
+    }
+
+    fn parse_consistency_hash<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let consistency_hash =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::ConsistencyHash(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            consistency_hash,
+        ))
+    }
+
+    fn parse_map_entries<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let page = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::MapEntries(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            page,
+        ))
+    }
+
+    fn parse_account_events<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let page = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::AccountEvents(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            page,
+        ))
+    }
+
+    fn parse_peer_ban<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let ban = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::PeerBan(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            ban,
+        ))
+    }
+
+    fn parse_dry_run_transaction<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let dry_run = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::DryRunTransaction(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            dry_run,
+        ))
+    }
+
+    fn parse_clarity_coverage<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let coverage =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::ClarityCoverage(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            coverage,
+        ))
+    }
+
+    fn parse_neighbors<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let neighbors_data =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::Neighbors(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            neighbors_data,
+        ))
+    }
+
+    fn parse_headers<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let headers: Vec<ExtendedStacksHeader> =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::Headers(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            headers,
+        ))
+    }
+
+    fn parse_light_headers<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let batch = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::LightHeaders(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            batch,
+        ))
+    }
+
+    fn parse_block<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let block: StacksBlock =
+            HttpResponseType::parse_bytestream(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::Block(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            block,
+        ))
+    }
+
+    fn parse_microblocks<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
         preamble: &HttpResponsePreamble,
@@ -3651,6 +5164,21 @@ impl HttpResponseType {
         ))
     }
 
+    fn parse_call_read_only_batch<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let batch_data =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::CallReadOnlyFunctionBatch(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            batch_data,
+        ))
+    }
+
     fn parse_microblocks_unconfirmed<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
@@ -3715,6 +5243,20 @@ impl HttpResponseType {
         ))
     }
 
+    fn parse_transaction_status<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let status = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::TransactionStatus(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            status,
+        ))
+    }
+
     fn parse_txid<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
@@ -3731,10 +5273,15 @@ impl HttpResponseType {
 
         let txid = Txid::from_hex(&txid_hex)
             .map_err(|_e| net_error::DeserializeError("Failed to decode txid hex".to_string()))?;
-        Ok(HttpResponseType::TransactionID(
-            HttpResponseMetadata::from_preamble(request_version, preamble),
-            txid,
-        ))
+        let md = HttpResponseMetadata::from_preamble(request_version, preamble);
+        match preamble.headers.get("x-stacks-admission-warning") {
+            Some(warning) => Ok(HttpResponseType::TransactionIDWithWarning(
+                md,
+                txid,
+                warning.clone(),
+            )),
+            None => Ok(HttpResponseType::TransactionID(md, txid)),
+        }
     }
 
     fn parse_get_attachment<R: Read>(
@@ -3753,6 +5300,34 @@ impl HttpResponseType {
         ))
     }
 
+    fn parse_attachments_status<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let status = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::AttachmentsStatus(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            status,
+        ))
+    }
+
+    fn parse_attachment_refetch<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let refetch = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::AttachmentRefetch(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            refetch,
+        ))
+    }
+
     fn parse_get_attachments_inv<R: Read>(
         _protocol: &mut StacksHttp,
         request_version: HttpVersion,
@@ -3951,6 +5526,7 @@ impl HttpResponseType {
             402 => "Payment Required",
             403 => "Forbidden",
             404 => "Not Found",
+            429 => "Too Many Requests",
             500 => "Internal Server Error",
             503 => "Service Temporarily Unavailable",
             _ => "Error",
@@ -3982,7 +5558,10 @@ impl HttpResponseType {
         match *self {
             HttpResponseType::PeerInfo(ref md, _) => md,
             HttpResponseType::PoxInfo(ref md, _) => md,
+            HttpResponseType::PoxCycleInfo(ref md, _) => md,
             HttpResponseType::Neighbors(ref md, _) => md,
+            HttpResponseType::PeerBan(ref md, _) => md,
+            HttpResponseType::ClarityCoverage(ref md, _) => md,
             HttpResponseType::HeaderStream(ref md) => md,
             HttpResponseType::Headers(ref md, _) => md,
             HttpResponseType::Block(ref md, _) => md,
@@ -3990,23 +5569,37 @@ impl HttpResponseType {
             HttpResponseType::Microblocks(ref md, _) => md,
             HttpResponseType::MicroblockStream(ref md) => md,
             HttpResponseType::TransactionID(ref md, _) => md,
+            HttpResponseType::TransactionIDWithWarning(ref md, ..) => md,
             HttpResponseType::StacksBlockAccepted(ref md, ..) => md,
             HttpResponseType::MicroblockHash(ref md, _) => md,
             HttpResponseType::TokenTransferCost(ref md, _) => md,
             HttpResponseType::GetDataVar(ref md, _) => md,
             HttpResponseType::GetMapEntry(ref md, _) => md,
+            HttpResponseType::MapEntries(ref md, _) => md,
+            HttpResponseType::ConsistencyHash(ref md, _) => md,
             HttpResponseType::GetAccount(ref md, _) => md,
             HttpResponseType::GetContractABI(ref md, _) => md,
             HttpResponseType::GetContractSrc(ref md, _) => md,
             HttpResponseType::GetIsTraitImplemented(ref md, _) => md,
             HttpResponseType::CallReadOnlyFunction(ref md, _) => md,
+            HttpResponseType::CallReadOnlyFunctionBatch(ref md, _) => md,
             HttpResponseType::UnconfirmedTransaction(ref md, _) => md,
+            HttpResponseType::TransactionStatus(ref md, _) => md,
+            HttpResponseType::LightHeaders(ref md, _) => md,
             HttpResponseType::GetAttachment(ref md, _) => md,
             HttpResponseType::GetAttachmentsInv(ref md, _) => md,
+            HttpResponseType::AttachmentsStatus(ref md, _) => md,
+            HttpResponseType::AttachmentRefetch(ref md, _) => md,
             HttpResponseType::MemPoolTxStream(ref md) => md,
             HttpResponseType::MemPoolTxs(ref md, ..) => md,
             HttpResponseType::OptionsPreflight(ref md) => md,
             HttpResponseType::TransactionFeeEstimation(ref md, _) => md,
+            HttpResponseType::DryRunTransaction(ref md, _) => md,
+            HttpResponseType::FinalizedBlocks(ref md, _) => md,
+            HttpResponseType::BurnOps(ref md, _) => md,
+            HttpResponseType::Sortition(ref md, _) => md,
+            HttpResponseType::AccountEvents(ref md, _) => md,
+            HttpResponseType::NotModified(ref md, _) => md,
             // errors
             HttpResponseType::BadRequestJSON(ref md, _) => md,
             HttpResponseType::BadRequest(ref md, _) => md,
@@ -4090,10 +5683,61 @@ impl HttpResponseType {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, data)?;
             }
-            HttpResponseType::GetContractABI(ref md, ref data) => {
+            HttpResponseType::DryRunTransaction(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
+            HttpResponseType::FinalizedBlocks(ref md, ref page) => {
+                // a complete page never changes, so downstream caches can keep it forever
+                let cache_control = if page.complete {
+                    "Cache-Control: public, max-age=31536000, immutable\r\n"
+                } else {
+                    "Cache-Control: public, max-age=30\r\n"
+                };
+                HttpResponsePreamble::new_serialized(
+                    fd,
+                    200,
+                    "OK",
+                    md.content_length.clone(),
+                    &HttpContentType::JSON,
+                    md.request_id,
+                    |ref mut fd| {
+                        keep_alive_headers(fd, md)?;
+                        fd.write_all(cache_control.as_bytes())
+                            .map_err(codec_error::WriteError)
+                    },
+                )?;
+                HttpResponseType::send_json(protocol, md, fd, page)?;
+            }
+            HttpResponseType::BurnOps(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
+            HttpResponseType::Sortition(ref md, ref data) => {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, data)?;
             }
+            HttpResponseType::AccountEvents(ref md, ref page) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, page)?;
+            }
+            HttpResponseType::GetContractABI(ref md, ref data) => {
+                let etag = data.etag();
+                HttpResponsePreamble::new_serialized(
+                    fd,
+                    200,
+                    "OK",
+                    md.content_length.clone(),
+                    &HttpContentType::JSON,
+                    md.request_id,
+                    |ref mut fd| {
+                        keep_alive_headers(fd, md)?;
+                        fd.write_all(format!("ETag: {}\r\n", &etag).as_bytes())
+                            .map_err(codec_error::WriteError)
+                    },
+                )?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
             HttpResponseType::GetContractSrc(ref md, ref data) => {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, data)?;
@@ -4110,6 +5754,10 @@ impl HttpResponseType {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, data)?;
             }
+            HttpResponseType::CallReadOnlyFunctionBatch(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
             HttpResponseType::GetDataVar(ref md, ref var_data) => {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, var_data)?;
@@ -4118,6 +5766,14 @@ impl HttpResponseType {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, map_data)?;
             }
+            HttpResponseType::MapEntries(ref md, ref page) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, page)?;
+            }
+            HttpResponseType::ConsistencyHash(ref md, ref consistency_hash) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, consistency_hash)?;
+            }
             HttpResponseType::PeerInfo(ref md, ref peer_info) => {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, peer_info)?;
@@ -4126,10 +5782,22 @@ impl HttpResponseType {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, pox_info)?;
             }
+            HttpResponseType::PoxCycleInfo(ref md, ref pox_cycle_info) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, pox_cycle_info)?;
+            }
             HttpResponseType::Neighbors(ref md, ref neighbor_data) => {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, neighbor_data)?;
             }
+            HttpResponseType::PeerBan(ref md, ref ban) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, ban)?;
+            }
+            HttpResponseType::ClarityCoverage(ref md, ref coverage) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, coverage)?;
+            }
             HttpResponseType::GetAttachment(ref md, ref zonefile_data) => {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, zonefile_data)?;
@@ -4138,6 +5806,14 @@ impl HttpResponseType {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, zonefile_data)?;
             }
+            HttpResponseType::AttachmentsStatus(ref md, ref status) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, status)?;
+            }
+            HttpResponseType::AttachmentRefetch(ref md, ref refetch) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, refetch)?;
+            }
             HttpResponseType::Headers(ref md, ref headers) => {
                 HttpResponsePreamble::new_serialized(
                     fd,
@@ -4226,6 +5902,25 @@ impl HttpResponseType {
                 )?;
                 HttpResponseType::send_json(protocol, md, fd, &txid_bytes)?;
             }
+            HttpResponseType::TransactionIDWithWarning(ref md, ref txid, ref warning) => {
+                let txid_bytes = txid.to_hex();
+                HttpResponsePreamble::new_serialized(
+                    fd,
+                    200,
+                    "OK",
+                    md.content_length.clone(),
+                    &HttpContentType::JSON,
+                    md.request_id,
+                    |ref mut fd| {
+                        keep_alive_headers(fd, md)?;
+                        fd.write_all(
+                            format!("X-Stacks-Admission-Warning: {}\r\n", warning).as_bytes(),
+                        )
+                        .map_err(codec_error::WriteError)
+                    },
+                )?;
+                HttpResponseType::send_json(protocol, md, fd, &txid_bytes)?;
+            }
             HttpResponseType::StacksBlockAccepted(ref md, ref stacks_block_id, ref accepted) => {
                 let accepted_data = StacksBlockAcceptedData {
                     stacks_block_id: stacks_block_id.clone(),
@@ -4259,6 +5954,14 @@ impl HttpResponseType {
                 HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
                 HttpResponseType::send_json(protocol, md, fd, unconfirmed_status)?;
             }
+            HttpResponseType::TransactionStatus(ref md, ref status) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, status)?;
+            }
+            HttpResponseType::LightHeaders(ref md, ref batch) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, batch)?;
+            }
             HttpResponseType::MemPoolTxStream(ref md) => {
                 // only send the preamble.  The caller will need to figure out how to send along
                 // the tx data itself.
@@ -4328,6 +6031,21 @@ impl HttpResponseType {
                 )?;
                 HttpResponseType::send_json(protocol, md, fd, data)?;
             }
+            HttpResponseType::NotModified(ref md, ref etag) => {
+                HttpResponsePreamble::new_serialized(
+                    fd,
+                    304,
+                    "Not Modified",
+                    Some(0),
+                    &HttpContentType::JSON,
+                    md.request_id,
+                    |ref mut fd| {
+                        keep_alive_headers(fd, md)?;
+                        fd.write_all(format!("ETag: {}\r\n", etag).as_bytes())
+                            .map_err(codec_error::WriteError)
+                    },
+                )?;
+            }
             HttpResponseType::BadRequest(_, ref msg) => self.error_response(fd, 400, msg)?,
             HttpResponseType::Unauthorized(_, ref msg) => self.error_response(fd, 401, msg)?,
             HttpResponseType::PaymentRequired(_, ref msg) => self.error_response(fd, 402, msg)?,
@@ -4396,7 +6114,11 @@ impl MessageSequence for StacksHttpMessage {
             StacksHttpMessage::Request(ref req) => match req {
                 HttpRequestType::GetInfo(_) => "HTTP(GetInfo)",
                 HttpRequestType::GetPoxInfo(_, _) => "HTTP(GetPoxInfo)",
+                HttpRequestType::GetPoxCycleInfo(..) => "HTTP(GetPoxCycleInfo)",
                 HttpRequestType::GetNeighbors(_) => "HTTP(GetNeighbors)",
+                HttpRequestType::BanPeer(..) => "HTTP(BanPeer)",
+                HttpRequestType::UnbanPeer(..) => "HTTP(UnbanPeer)",
+                HttpRequestType::GetClarityCoverage(..) => "HTTP(GetClarityCoverage)",
                 HttpRequestType::GetHeaders(..) => "HTTP(GetHeaders)",
                 HttpRequestType::GetBlock(_, _) => "HTTP(GetBlock)",
                 HttpRequestType::GetMicroblocksIndexed(_, _) => "HTTP(GetMicroblocksIndexed)",
@@ -4404,41 +6126,64 @@ impl MessageSequence for StacksHttpMessage {
                 HttpRequestType::GetMicroblocksUnconfirmed(_, _, _) => {
                     "HTTP(GetMicroblocksUnconfirmed)"
                 }
+                HttpRequestType::GetMicroblocksByParent(..) => "HTTP(GetMicroblocksByParent)",
                 HttpRequestType::GetTransactionUnconfirmed(_, _) => {
                     "HTTP(GetTransactionUnconfirmed)"
                 }
+                HttpRequestType::GetTransactionStatus(_, _) => "HTTP(GetTransactionStatus)",
+                HttpRequestType::GetLightHeaders(..) => "HTTP(GetLightHeaders)",
                 HttpRequestType::PostTransaction(_, _, _) => "HTTP(PostTransaction)",
                 HttpRequestType::PostBlock(..) => "HTTP(PostBlock)",
                 HttpRequestType::PostMicroblock(..) => "HTTP(PostMicroblock)",
                 HttpRequestType::GetAccount(..) => "HTTP(GetAccount)",
                 HttpRequestType::GetDataVar(..) => "HTTP(GetDataVar)",
                 HttpRequestType::GetMapEntry(..) => "HTTP(GetMapEntry)",
+                HttpRequestType::GetMapEntries(..) => "HTTP(GetMapEntries)",
+                HttpRequestType::GetConsistencyHash(..) => "HTTP(GetConsistencyHash)",
                 HttpRequestType::GetTransferCost(_) => "HTTP(GetTransferCost)",
                 HttpRequestType::GetContractABI(..) => "HTTP(GetContractABI)",
                 HttpRequestType::GetContractSrc(..) => "HTTP(GetContractSrc)",
                 HttpRequestType::GetIsTraitImplemented(..) => "HTTP(GetIsTraitImplemented)",
                 HttpRequestType::CallReadOnlyFunction(..) => "HTTP(CallReadOnlyFunction)",
+                HttpRequestType::CallReadOnlyFunctionBatch(..) => "HTTP(CallReadOnlyFunctionBatch)",
                 HttpRequestType::GetAttachment(..) => "HTTP(GetAttachment)",
                 HttpRequestType::GetAttachmentsInv(..) => "HTTP(GetAttachmentsInv)",
+                HttpRequestType::GetAttachmentsStatus(..) => "HTTP(GetAttachmentsStatus)",
+                HttpRequestType::RefetchAttachment(..) => "HTTP(RefetchAttachment)",
                 HttpRequestType::MemPoolQuery(..) => "HTTP(MemPoolQuery)",
                 HttpRequestType::OptionsPreflight(..) => "HTTP(OptionsPreflight)",
                 HttpRequestType::ClientError(..) => "HTTP(ClientError)",
                 HttpRequestType::FeeRateEstimate(_, _, _) => "HTTP(FeeRateEstimate)",
+                HttpRequestType::DryRunTransaction(..) => "HTTP(DryRunTransaction)",
+                HttpRequestType::GetFinalizedBlocks(..) => "HTTP(GetFinalizedBlocks)",
+                HttpRequestType::GetBurnOps(..) => "HTTP(GetBurnOps)",
+                HttpRequestType::GetSortition(..) => "HTTP(GetSortition)",
+                HttpRequestType::GetAccountEvents(..) => "HTTP(GetAccountEvents)",
             },
             StacksHttpMessage::Response(ref res) => match res {
                 HttpResponseType::TokenTransferCost(_, _) => "HTTP(TokenTransferCost)",
                 HttpResponseType::GetDataVar(_, _) => "HTTP(GetDataVar)",
                 HttpResponseType::GetMapEntry(_, _) => "HTTP(GetMapEntry)",
+                HttpResponseType::MapEntries(..) => "HTTP(MapEntries)",
+                HttpResponseType::ConsistencyHash(..) => "HTTP(ConsistencyHash)",
                 HttpResponseType::GetAccount(_, _) => "HTTP(GetAccount)",
                 HttpResponseType::GetContractABI(..) => "HTTP(GetContractABI)",
                 HttpResponseType::GetContractSrc(..) => "HTTP(GetContractSrc)",
                 HttpResponseType::GetIsTraitImplemented(..) => "HTTP(GetIsTraitImplemented)",
                 HttpResponseType::CallReadOnlyFunction(..) => "HTTP(CallReadOnlyFunction)",
+                HttpResponseType::CallReadOnlyFunctionBatch(..) => {
+                    "HTTP(CallReadOnlyFunctionBatch)"
+                }
                 HttpResponseType::GetAttachment(_, _) => "HTTP(GetAttachment)",
                 HttpResponseType::GetAttachmentsInv(_, _) => "HTTP(GetAttachmentsInv)",
+                HttpResponseType::AttachmentsStatus(_, _) => "HTTP(AttachmentsStatus)",
+                HttpResponseType::AttachmentRefetch(_, _) => "HTTP(AttachmentRefetch)",
                 HttpResponseType::PeerInfo(_, _) => "HTTP(PeerInfo)",
                 HttpResponseType::PoxInfo(_, _) => "HTTP(PeerInfo)",
+                HttpResponseType::PoxCycleInfo(_, _) => "HTTP(PoxCycleInfo)",
                 HttpResponseType::Neighbors(_, _) => "HTTP(Neighbors)",
+                HttpResponseType::PeerBan(_, _) => "HTTP(PeerBan)",
+                HttpResponseType::ClarityCoverage(_, _) => "HTTP(ClarityCoverage)",
                 HttpResponseType::Headers(..) => "HTTP(Headers)",
                 HttpResponseType::HeaderStream(..) => "HTTP(HeaderStream)",
                 HttpResponseType::Block(_, _) => "HTTP(Block)",
@@ -4446,9 +6191,12 @@ impl MessageSequence for StacksHttpMessage {
                 HttpResponseType::Microblocks(_, _) => "HTTP(Microblocks)",
                 HttpResponseType::MicroblockStream(_) => "HTTP(MicroblockStream)",
                 HttpResponseType::TransactionID(_, _) => "HTTP(Transaction)",
+                HttpResponseType::TransactionIDWithWarning(..) => "HTTP(Transaction)",
                 HttpResponseType::StacksBlockAccepted(..) => "HTTP(StacksBlockAccepted)",
                 HttpResponseType::MicroblockHash(_, _) => "HTTP(MicroblockHash)",
                 HttpResponseType::UnconfirmedTransaction(_, _) => "HTTP(UnconfirmedTransaction)",
+                HttpResponseType::TransactionStatus(_, _) => "HTTP(TransactionStatus)",
+                HttpResponseType::LightHeaders(_, _) => "HTTP(LightHeaders)",
                 HttpResponseType::MemPoolTxStream(..) => "HTTP(MemPoolTxStream)",
                 HttpResponseType::MemPoolTxs(..) => "HTTP(MemPoolTxs)",
                 HttpResponseType::OptionsPreflight(_) => "HTTP(OptionsPreflight)",
@@ -4465,6 +6213,12 @@ impl MessageSequence for StacksHttpMessage {
                 HttpResponseType::TransactionFeeEstimation(_, _) => {
                     "HTTP(TransactionFeeEstimation)"
                 }
+                HttpResponseType::DryRunTransaction(_, _) => "HTTP(DryRunTransaction)",
+                HttpResponseType::FinalizedBlocks(..) => "HTTP(FinalizedBlocks)",
+                HttpResponseType::BurnOps(..) => "HTTP(BurnOps)",
+                HttpResponseType::Sortition(..) => "HTTP(Sortition)",
+                HttpResponseType::AccountEvents(..) => "HTTP(AccountEvents)",
+                HttpResponseType::NotModified(..) => "HTTP(304)",
             },
         }
     }
@@ -4984,7 +6738,9 @@ mod test {
 
     use crate::burnchains::Txid;
     use crate::chainstate::stacks::db::blocks::test::make_sample_microblock_stream;
+    use crate::chainstate::stacks::db::consistency::ChainstateConsistencyHash;
     use crate::chainstate::stacks::test::make_codec_test_block;
+    use crate::chainstate::stacks::CoinbasePayload;
     use crate::chainstate::stacks::StacksBlock;
     use crate::chainstate::stacks::StacksMicroblock;
     use crate::chainstate::stacks::StacksPrivateKey;
@@ -4998,6 +6754,14 @@ mod test {
     use crate::net::test::*;
     use crate::net::RPCNeighbor;
     use crate::net::RPCNeighborsInfo;
+    use crate::net::RPCPeerBanResponse;
+    use crate::net::{
+        RPCAttachmentRefetchResponse, RPCAttachmentsPeer, RPCAttachmentsStatus,
+        RPCMissingAttachment,
+    };
+    use crate::types::chainstate::{SortitionId, TrieHash};
+    use crate::util_lib::strings::UrlString;
+    use clarity::vm::coverage::CoverageDump;
     use stacks_common::util::hash::to_hex;
     use stacks_common::util::hash::Hash160;
     use stacks_common::util::hash::MerkleTree;
@@ -5942,12 +7706,16 @@ mod test {
             ),
             keep_alive: true,
             canonical_stacks_tip_height: None,
+            auth_token: None,
+            if_none_match: None,
         };
         let http_request_metadata_dns = HttpRequestMetadata {
             version: HttpVersion::Http11,
             peer: PeerHost::DNS("www.foo.com".to_string(), 80),
             keep_alive: true,
             canonical_stacks_tip_height: None,
+            auth_token: None,
+            if_none_match: None,
         };
 
         let tests = vec![
@@ -5962,10 +7730,27 @@ mod test {
                 make_test_transaction(),
                 None,
             ),
-            HttpRequestType::OptionsPreflight(http_request_metadata_ip.clone(), "/".to_string()),
-        ];
-
-        let mut tx_body = vec![];
+            HttpRequestType::GetClarityCoverage(http_request_metadata_ip.clone(), false),
+            HttpRequestType::GetPoxCycleInfo(
+                http_request_metadata_dns.clone(),
+                12,
+                Some(StacksAddress {
+                    version: 26,
+                    bytes: Hash160([0x11; 20]),
+                }),
+                TipRequest::SpecificHeight(5),
+            ),
+            HttpRequestType::GetPoxCycleInfo(
+                http_request_metadata_ip.clone(),
+                13,
+                None,
+                TipRequest::UseLatestAnchoredTip,
+            ),
+            HttpRequestType::GetClarityCoverage(http_request_metadata_dns.clone(), true),
+            HttpRequestType::OptionsPreflight(http_request_metadata_ip.clone(), "/".to_string()),
+        ];
+
+        let mut tx_body = vec![];
         make_test_transaction()
             .consensus_serialize(&mut tx_body)
             .unwrap();
@@ -6008,6 +7793,38 @@ mod test {
                 http_request_metadata_ip.keep_alive,
             ),
             post_transaction_preamble,
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/clarity/coverage".to_string(),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/pox/cycle/12?tip=5&pox_address=mh5CE8Nbj38iND267s4XnvhSmhDW7yWc6Q".to_string(),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/pox/cycle/13".to_string(),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/clarity/coverage?reset=1".to_string(),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
             HttpRequestPreamble::new(
                 HttpVersion::Http11,
                 "OPTIONS".to_string(),
@@ -6018,7 +7835,16 @@ mod test {
             ),
         ];
 
-        let expected_http_bodies = vec![vec![], vec![], vec![], tx_body];
+        let expected_http_bodies = vec![
+            vec![],
+            vec![],
+            vec![],
+            tx_body,
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+        ];
 
         for (test, (expected_http_preamble, expected_http_body)) in tests.iter().zip(
             expected_http_preambles
@@ -6053,6 +7879,9 @@ mod test {
             http.write_message(&mut bytes, &StacksHttpMessage::Request(test.clone()))
                 .unwrap();
 
+            if !matches!(test, HttpRequestType::OptionsPreflight(..)) {
+                assert!(HttpRequestType::is_path_template(test.get_path()));
+            }
             assert_eq!(bytes, expected_bytes);
         }
     }
@@ -6111,6 +7940,7 @@ mod test {
                     )
                     .unwrap(),
                     authenticated: true,
+                    score: 0,
                 },
                 RPCNeighbor {
                     network_id: 3,
@@ -6125,12 +7955,30 @@ mod test {
                     )
                     .unwrap(),
                     authenticated: false,
+                    score: -25,
                 },
             ],
             inbound: vec![],
             outbound: vec![],
         };
 
+        let test_peer_ban = RPCPeerBanResponse {
+            ip: PeerAddress::from_ipv4(1, 2, 3, 4),
+            port: 20444,
+            banned_until: 1655150400,
+            score: -25,
+        };
+
+        let test_clarity_coverage = CoverageDump {
+            run: 2,
+            coverage: vec![(
+                "ST2QKZ4FKHAH1NQKYKYAYZPY440FEPK7GZ1R5HBP2.hello-world".to_string(),
+                vec![(1, 3), (4, 1)],
+            )]
+            .into_iter()
+            .collect(),
+        };
+
         let privk = StacksPrivateKey::from_hex(
             "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
         )
@@ -6164,6 +8012,32 @@ mod test {
                 ),
                 "/v2/neighbors".to_string(),
             ),
+            (
+                HttpResponseType::PeerBan(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_peer_ban).unwrap().len() as u32),
+                        true,
+                        None,
+                    ),
+                    test_peer_ban.clone(),
+                ),
+                "/v2/neighbors/ban".to_string(),
+            ),
+            (
+                HttpResponseType::ClarityCoverage(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_clarity_coverage).unwrap().len() as u32),
+                        true,
+                        None,
+                    ),
+                    test_clarity_coverage.clone(),
+                ),
+                "/v2/clarity/coverage".to_string(),
+            ),
             (
                 HttpResponseType::Block(
                     HttpResponseMetadata::new(
@@ -6366,6 +8240,22 @@ mod test {
                 true,
                 123,
             ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_peer_ban).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_clarity_coverage).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
             HttpResponsePreamble::new(
                 200,
                 "OK".to_string(),
@@ -6450,6 +8340,14 @@ mod test {
                 .unwrap()
                 .as_bytes()
                 .to_vec(),
+            serde_json::to_string(&test_peer_ban)
+                .unwrap()
+                .as_bytes()
+                .to_vec(),
+            serde_json::to_string(&test_clarity_coverage)
+                .unwrap()
+                .as_bytes()
+                .to_vec(),
             test_block_info_bytes.clone(),
             test_microblock_info_bytes.clone(),
             Txid([0x1; 32]).to_hex().as_bytes().to_vec(),
@@ -6905,6 +8803,696 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_http_parse_account_events_query() {
+        let query = HttpRequestType::get_account_events_query(None).unwrap();
+        assert_eq!(query.limit, ACCOUNT_EVENTS_PAGE_SIZE);
+        assert!(query.event_types.is_empty());
+
+        let query_txt = "after=10&before=20&types=stx_transfer_event,ft_mint_event&cursor=15-2-0&limit=5&tip=latest";
+        let query = HttpRequestType::get_account_events_query(Some(query_txt)).unwrap();
+        assert_eq!(
+            query,
+            AccountEventsQuery {
+                after_height: Some(10),
+                before_height: Some(20),
+                event_types: vec!["stx_transfer_event".into(), "ft_mint_event".into()],
+                cursor: Some("15-2-0".parse().unwrap()),
+                limit: 5,
+            }
+        );
+
+        // the query string round-trips
+        let request = HttpRequestType::GetAccountEvents(
+            HttpRequestMetadata::new("127.0.0.1".to_string(), 20443, None),
+            PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+            query.clone(),
+            TipRequest::UseLatestUnconfirmedTip,
+        );
+        assert_eq!(
+            request.request_path(),
+            format!("/v2/accounts/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/events?{}", "tip=latest&after=10&before=20&types=stx_transfer_event,ft_mint_event&cursor=15-2-0&limit=5")
+        );
+
+        for bad_query in &[
+            "after=soon",
+            "before=-1",
+            "types=stx_transfer_event,bogus_event",
+            "cursor=15-2",
+            "limit=0",
+            "limit=100000",
+        ] {
+            assert!(
+                HttpRequestType::get_account_events_query(Some(bad_query)).is_err(),
+                "{} parsed",
+                bad_query
+            );
+        }
+    }
+
+    #[test]
+    fn test_http_parse_map_entries_query() {
+        assert_eq!(
+            HttpRequestType::get_map_entries_query(None).unwrap(),
+            (None, MAP_ENTRIES_PAGE_SIZE)
+        );
+
+        let cursor = Value::UInt(10);
+        let query_txt = format!(
+            "tip=latest&cursor=0x{}&limit=5",
+            ClaritySerializable::serialize(&cursor)
+        );
+        assert_eq!(
+            HttpRequestType::get_map_entries_query(Some(&query_txt)).unwrap(),
+            (Some(cursor.clone()), 5)
+        );
+
+        // the query string round-trips
+        let request = HttpRequestType::GetMapEntries(
+            HttpRequestMetadata::new("127.0.0.1".to_string(), 20443, None),
+            StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+            "hello-world".into(),
+            "unit-map".into(),
+            Some(cursor),
+            5,
+            TipRequest::UseLatestUnconfirmedTip,
+        );
+        assert_eq!(
+            request.request_path(),
+            format!(
+                "/v2/map_entries/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/hello-world/unit-map?{}",
+                query_txt
+            )
+        );
+
+        for bad_query in &["cursor=0xzz", "cursor=0x", "limit=0", "limit=100000"] {
+            assert!(
+                HttpRequestType::get_map_entries_query(Some(bad_query)).is_err(),
+                "{} parsed",
+                bad_query
+            );
+        }
+    }
+
+    #[test]
+    fn test_http_call_read_only_batch_codec() {
+        let calls = vec![
+            ReadOnlyCall {
+                contract_addr: StacksAddress::from_string(
+                    "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R",
+                )
+                .unwrap(),
+                contract_name: "hello-world".into(),
+                sender: PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+                function_name: "get-bar".into(),
+                arguments: vec![],
+            },
+            ReadOnlyCall {
+                contract_addr: StacksAddress::from_string(
+                    "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R",
+                )
+                .unwrap(),
+                contract_name: "hello-world".into(),
+                sender: PrincipalData::parse(
+                    "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world",
+                )
+                .unwrap(),
+                function_name: "set-bar".into(),
+                arguments: vec![Value::Int(1), Value::Int(2)],
+            },
+        ];
+        let request = HttpRequestType::CallReadOnlyFunctionBatch(
+            HttpRequestMetadata::new("127.0.0.1".to_string(), 20443, None),
+            calls.clone(),
+            TipRequest::UseLatestUnconfirmedTip,
+        );
+        assert_eq!(
+            request.request_path(),
+            "/v2/contracts/call-read-batch?tip=latest"
+        );
+
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let mut bytes = vec![];
+        http.write_message(&mut bytes, &StacksHttpMessage::Request(request))
+            .unwrap();
+        let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+        let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+        match message {
+            StacksHttpMessage::Request(HttpRequestType::CallReadOnlyFunctionBatch(
+                _,
+                parsed_calls,
+                TipRequest::UseLatestUnconfirmedTip,
+            )) => assert_eq!(parsed_calls, calls),
+            _ => panic!("Did not parse a read-only call batch: {:?}", &message),
+        }
+
+        // batches can't be empty or too big
+        let too_many_calls = vec![calls[0].clone(); MAX_READ_ONLY_CALL_BATCH_SIZE + 1];
+        for bad_calls in [vec![], too_many_calls] {
+            let request = HttpRequestType::CallReadOnlyFunctionBatch(
+                HttpRequestMetadata::new("127.0.0.1".to_string(), 20443, None),
+                bad_calls,
+                TipRequest::UseLatestAnchoredTip,
+            );
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let mut bytes = vec![];
+            http.write_message(&mut bytes, &StacksHttpMessage::Request(request))
+                .unwrap();
+            let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+            let e = http.read_payload(&preamble, &bytes[offset..]);
+            assert!(
+                e.as_ref()
+                    .unwrap_err()
+                    .to_string()
+                    .find("read-only calls")
+                    .is_some(),
+                "{:?}",
+                &e
+            );
+        }
+    }
+
+    #[test]
+    fn test_http_call_read_only_trace_codec() {
+        for (tip_req, trace, path) in [
+            (
+                TipRequest::UseLatestAnchoredTip,
+                true,
+                "/v2/contracts/call-read/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/hello-world/get-bar?trace=true",
+            ),
+            (
+                TipRequest::UseLatestUnconfirmedTip,
+                true,
+                "/v2/contracts/call-read/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/hello-world/get-bar?tip=latest&trace=true",
+            ),
+            (
+                TipRequest::UseLatestUnconfirmedTip,
+                false,
+                "/v2/contracts/call-read/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/hello-world/get-bar?tip=latest",
+            ),
+        ] {
+            let request = HttpRequestType::CallReadOnlyFunction(
+                HttpRequestMetadata::new("127.0.0.1".to_string(), 20443, None),
+                StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+                "hello-world".into(),
+                PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+                "get-bar".into(),
+                vec![],
+                tip_req.clone(),
+                trace,
+            );
+            assert_eq!(request.request_path(), path);
+
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let mut bytes = vec![];
+            http.write_message(&mut bytes, &StacksHttpMessage::Request(request))
+                .unwrap();
+            let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+            let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+            match message {
+                StacksHttpMessage::Request(HttpRequestType::CallReadOnlyFunction(
+                    _,
+                    _,
+                    _,
+                    _,
+                    _,
+                    _,
+                    parsed_tip_req,
+                    parsed_trace,
+                )) => {
+                    assert_eq!(parsed_tip_req, tip_req);
+                    assert_eq!(parsed_trace, trace);
+                }
+                _ => panic!("Did not parse a read-only call: {:?}", &message),
+            }
+        }
+    }
+
+    #[test]
+    fn test_http_dry_run_transaction_codec() {
+        let sender =
+            StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap();
+        let payload = TransactionPayload::new_contract_call(
+            sender.clone(),
+            "hello-world",
+            "set-bar",
+            vec![Value::Int(1), Value::Int(2)],
+        )
+        .unwrap();
+        let request = HttpRequestType::DryRunTransaction(
+            HttpRequestMetadata::new("127.0.0.1".to_string(), 20443, None),
+            sender.clone(),
+            payload.clone(),
+        );
+        assert_eq!(request.request_path(), "/v2/transactions/dry-run");
+
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let mut bytes = vec![];
+        http.write_message(&mut bytes, &StacksHttpMessage::Request(request))
+            .unwrap();
+        let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+        let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+        match message {
+            StacksHttpMessage::Request(HttpRequestType::DryRunTransaction(
+                _,
+                parsed_sender,
+                parsed_payload,
+            )) => {
+                assert_eq!(parsed_sender, sender);
+                assert_eq!(parsed_payload, payload);
+            }
+            _ => panic!("Did not parse a dry run: {:?}", &message),
+        }
+
+        // only transfers, contract calls and contract deployments can be dry-run
+        let request = HttpRequestType::DryRunTransaction(
+            HttpRequestMetadata::new("127.0.0.1".to_string(), 20443, None),
+            sender,
+            TransactionPayload::Coinbase(CoinbasePayload([0u8; 32])),
+        );
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let mut bytes = vec![];
+        http.write_message(&mut bytes, &StacksHttpMessage::Request(request))
+            .unwrap();
+        let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+        assert!(http.read_payload(&preamble, &bytes[offset..]).is_err());
+    }
+
+    #[test]
+    fn test_http_get_light_headers_codec() {
+        let request = HttpRequestType::GetLightHeaders(
+            HttpRequestMetadata::new("127.0.0.1".to_string(), 20443, None),
+            100,
+            50,
+            TipRequest::SpecificHeight(200),
+        );
+        assert_eq!(request.request_path(), "/v2/headers/light/100/50?tip=200");
+
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let mut bytes = vec![];
+        http.write_message(&mut bytes, &StacksHttpMessage::Request(request))
+            .unwrap();
+        let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+        let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+        match message {
+            StacksHttpMessage::Request(HttpRequestType::GetLightHeaders(
+                _,
+                100,
+                50,
+                TipRequest::SpecificHeight(200),
+            )) => {}
+            _ => panic!("Did not parse a light headers request: {:?}", &message),
+        }
+    }
+
+    #[test]
+    fn test_http_peer_ban_codec() {
+        let addr = PeerAddress::from_ipv4(1, 2, 3, 4);
+        let requests = vec![
+            HttpRequestType::BanPeer(
+                HttpRequestMetadata::new("127.0.0.1".to_string(), 20443, None),
+                addr.clone(),
+                20444,
+                3600,
+            ),
+            HttpRequestType::UnbanPeer(
+                HttpRequestMetadata::new("127.0.0.1".to_string(), 20443, None),
+                addr.clone(),
+                20444,
+            ),
+        ];
+
+        for request in requests.into_iter() {
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let mut bytes = vec![];
+            http.write_message(&mut bytes, &StacksHttpMessage::Request(request.clone()))
+                .unwrap();
+            let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+            let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+            match (message, request) {
+                (
+                    StacksHttpMessage::Request(HttpRequestType::BanPeer(_, addr, port, duration)),
+                    HttpRequestType::BanPeer(_, expected_addr, expected_port, expected_duration),
+                ) => {
+                    assert_eq!(addr, expected_addr);
+                    assert_eq!(port, expected_port);
+                    assert_eq!(duration, expected_duration);
+                }
+                (
+                    StacksHttpMessage::Request(HttpRequestType::UnbanPeer(_, addr, port)),
+                    HttpRequestType::UnbanPeer(_, expected_addr, expected_port),
+                ) => {
+                    assert_eq!(addr, expected_addr);
+                    assert_eq!(port, expected_port);
+                }
+                (message, request) => {
+                    panic!("Parsed {:?} from {:?}", &message, &request)
+                }
+            }
+        }
+
+        // the ban duration is optional
+        let body = r#"{"ip":"1.2.3.4","port":20444}"#;
+        let request_text = format!(
+            "POST /v2/neighbors/ban HTTP/1.1\r\nHost: 127.0.0.1:20443\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let (preamble, offset) = http.read_preamble(request_text.as_bytes()).unwrap();
+        let (message, _) = http
+            .read_payload(&preamble, &request_text.as_bytes()[offset..])
+            .unwrap();
+        match message {
+            StacksHttpMessage::Request(HttpRequestType::BanPeer(
+                _,
+                parsed_addr,
+                20444,
+                duration,
+            )) => {
+                assert_eq!(parsed_addr, addr);
+                assert_eq!(duration, DENY_BAN_DURATION);
+            }
+            _ => panic!("Did not parse a ban request: {:?}", &message),
+        }
+    }
+
+    #[test]
+    fn test_http_attachments_status_codec() {
+        let content_hash = Hash160([0x11; 20]);
+        let requests = vec![
+            HttpRequestType::GetAttachmentsStatus(HttpRequestMetadata::new(
+                "127.0.0.1".to_string(),
+                20443,
+                None,
+            )),
+            HttpRequestType::RefetchAttachment(
+                HttpRequestMetadata::new("127.0.0.1".to_string(), 20443, None),
+                content_hash.clone(),
+            ),
+        ];
+        for request in requests.into_iter() {
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let mut bytes = vec![];
+            http.write_message(&mut bytes, &StacksHttpMessage::Request(request.clone()))
+                .unwrap();
+            let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+            let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+            match message {
+                StacksHttpMessage::Request(mut parsed) => {
+                    parsed.metadata_mut().keep_alive = request.metadata().keep_alive;
+                    assert_eq!(parsed.request_path(), request.request_path());
+                    assert_eq!(parsed.get_path(), request.get_path());
+                    assert!(HttpRequestType::is_path_template(parsed.get_path()));
+                }
+                _ => panic!("Did not parse a request: {:?}", &message),
+            }
+        }
+
+        let status = RPCAttachmentsStatus {
+            missing_attachments: vec![RPCMissingAttachment {
+                content_hash: content_hash.clone(),
+                contract_id: "SP000000000000000000002Q6VF78.bns".to_string(),
+                attachment_index: 7,
+                block_height: 1542,
+                index_block_hash: StacksBlockId([0x22; 32]),
+                queued: true,
+                retry_count: 3,
+                next_attempt: 1655150400,
+                available_from: vec![UrlString::try_from("http://1.2.3.4:20443").unwrap()],
+            }],
+            peers: vec![RPCAttachmentsPeer {
+                data_url: UrlString::try_from("http://1.2.3.4:20443").unwrap(),
+                requests_sent: 12,
+                requests_succeeded: 9,
+                missing_attachments_available: 1,
+            }],
+        };
+        let refetch = RPCAttachmentRefetchResponse {
+            content_hash: content_hash.clone(),
+            queued_instances: 1,
+        };
+        let responses = vec![
+            (
+                HttpResponseType::AttachmentsStatus(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&status).unwrap().len() as u32),
+                        true,
+                        None,
+                    ),
+                    status,
+                ),
+                "/v2/attachments/status".to_string(),
+            ),
+            (
+                HttpResponseType::AttachmentRefetch(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&refetch).unwrap().len() as u32),
+                        true,
+                        None,
+                    ),
+                    refetch,
+                ),
+                format!("/v2/attachments/{}/refetch", &content_hash),
+            ),
+        ];
+        for (response, request_path) in responses.into_iter() {
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let mut bytes = vec![];
+            http.begin_request(HttpVersion::Http11, request_path);
+            http.write_message(&mut bytes, &StacksHttpMessage::Response(response.clone()))
+                .unwrap();
+            let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+            let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+            match (message, response) {
+                (
+                    StacksHttpMessage::Response(HttpResponseType::AttachmentsStatus(_, parsed)),
+                    HttpResponseType::AttachmentsStatus(_, expected),
+                ) => assert_eq!(parsed, expected),
+                (
+                    StacksHttpMessage::Response(HttpResponseType::AttachmentRefetch(_, parsed)),
+                    HttpResponseType::AttachmentRefetch(_, expected),
+                ) => assert_eq!(parsed, expected),
+                (message, response) => panic!("Parsed {:?} from {:?}", &message, &response),
+            }
+        }
+    }
+
+    #[test]
+    fn test_http_consistency_hash_codec() {
+        let request = HttpRequestType::GetConsistencyHash(
+            HttpRequestMetadata::new("127.0.0.1".to_string(), 20443, None),
+            TipRequest::SpecificHeight(1000),
+        );
+        assert_eq!(request.request_path(), "/v2/consistency_hash?tip=1000");
+
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let mut bytes = vec![];
+        http.write_message(&mut bytes, &StacksHttpMessage::Request(request.clone()))
+            .unwrap();
+        let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+        let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+        match message {
+            StacksHttpMessage::Request(HttpRequestType::GetConsistencyHash(_, tip_req)) => {
+                assert_eq!(tip_req, TipRequest::SpecificHeight(1000));
+            }
+            _ => panic!("Did not parse a request: {:?}", &message),
+        }
+
+        let consistency_hash = ChainstateConsistencyHash::new(
+            1000,
+            StacksBlockId([0x11; 32]),
+            ConsensusHash([0x22; 20]),
+            TrieHash([0x33; 32]),
+            SortitionId([0x44; 32]),
+            TrieHash([0x55; 32]),
+        );
+        let response = HttpResponseType::ConsistencyHash(
+            HttpResponseMetadata::new(
+                HttpVersion::Http11,
+                123,
+                Some(serde_json::to_string(&consistency_hash).unwrap().len() as u32),
+                true,
+                None,
+            ),
+            consistency_hash.clone(),
+        );
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let mut bytes = vec![];
+        http.begin_request(HttpVersion::Http11, request.request_path());
+        http.write_message(&mut bytes, &StacksHttpMessage::Response(response))
+            .unwrap();
+        let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+        let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+        match message {
+            StacksHttpMessage::Response(HttpResponseType::ConsistencyHash(_, parsed)) => {
+                assert_eq!(parsed, consistency_hash);
+            }
+            _ => panic!("Did not parse a response: {:?}", &message),
+        }
+    }
+
+    #[test]
+    fn test_http_request_auth_token() {
+        let mut md = HttpRequestMetadata::new("127.0.0.1".to_string(), 20443, None);
+        md.auth_token = Some("secret-key".to_string());
+        let request = HttpRequestType::GetInfo(md);
+
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let mut bytes = vec![];
+        http.write_message(&mut bytes, &StacksHttpMessage::Request(request))
+            .unwrap();
+        assert!(std::str::from_utf8(&bytes)
+            .unwrap()
+            .contains("Authorization: Bearer secret-key\r\n"));
+
+        let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+        let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+        match message {
+            StacksHttpMessage::Request(req) => {
+                assert_eq!(req.metadata().auth_token, Some("secret-key".to_string()));
+            }
+            _ => panic!("Did not parse a request: {:?}", &message),
+        }
+
+        // other schemes are not API keys
+        let mut preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "GET".to_string(),
+            "/v2/info".to_string(),
+            "127.0.0.1".to_string(),
+            20443,
+            true,
+        );
+        preamble.add_header(
+            "Authorization".to_string(),
+            "Basic Zm9vOmJhcg==".to_string(),
+        );
+        assert_eq!(
+            HttpRequestMetadata::from_preamble(&preamble).auth_token,
+            None
+        );
+    }
+
+    #[test]
+    fn test_http_contract_abi_not_modified() {
+        let etag = "\"0123456789abcdef\"".to_string();
+        let mut md = HttpRequestMetadata::new("127.0.0.1".to_string(), 20443, None);
+        md.if_none_match = Some(etag.clone());
+        let request = HttpRequestType::GetContractABI(
+            md,
+            StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+            "hello-world".try_into().unwrap(),
+            TipRequest::UseLatestAnchoredTip,
+        );
+        let request_path = request.request_path();
+
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let mut bytes = vec![];
+        http.write_message(&mut bytes, &StacksHttpMessage::Request(request))
+            .unwrap();
+        assert!(std::str::from_utf8(&bytes)
+            .unwrap()
+            .contains(&format!("If-None-Match: {}\r\n", &etag)));
+
+        let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+        let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+        match message {
+            StacksHttpMessage::Request(req) => {
+                assert_eq!(req.metadata().if_none_match, Some(etag.clone()));
+            }
+            _ => panic!("Did not parse a request: {:?}", &message),
+        }
+
+        let response = HttpResponseType::NotModified(
+            HttpResponseMetadata::new(HttpVersion::Http11, 123, Some(0), true, None),
+            etag.clone(),
+        );
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let mut bytes = vec![];
+        http.begin_request(HttpVersion::Http11, request_path);
+        http.write_message(&mut bytes, &StacksHttpMessage::Response(response.clone()))
+            .unwrap();
+        assert!(std::str::from_utf8(&bytes)
+            .unwrap()
+            .starts_with("HTTP/1.1 304 Not Modified\r\n"));
+
+        let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+        let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+        assert_eq!(message, StacksHttpMessage::Response(response));
+    }
+
+    #[test]
+    fn test_http_microblocks_by_parent_request() {
+        for seq in [None, Some(0), Some(65535)] {
+            let request = HttpRequestType::GetMicroblocksByParent(
+                HttpRequestMetadata::new("127.0.0.1".to_string(), 20443, None),
+                BlockHeaderHash([0x11; 32]),
+                seq,
+            );
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let mut bytes = vec![];
+            http.write_message(&mut bytes, &StacksHttpMessage::Request(request.clone()))
+                .unwrap();
+
+            let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+            let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+            assert_eq!(message, StacksHttpMessage::Request(request));
+        }
+
+        // sequence numbers are 16 bits
+        let request = format!(
+            "GET /v2/microblocks/stream/{}/65536 HTTP/1.1\r\nHost: 127.0.0.1:20443\r\n\r\n",
+            BlockHeaderHash([0x11; 32])
+        );
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+        assert!(http
+            .read_payload(&preamble, &request.as_bytes()[offset..])
+            .is_err());
+    }
+
+    #[test]
+    fn test_http_transaction_id_with_warning() {
+        let response = HttpResponseType::TransactionIDWithWarning(
+            HttpResponseMetadata::new(
+                HttpVersion::Http11,
+                123,
+                Some((Txid([0x1; 32]).to_hex().len() + 2) as u32),
+                true,
+                None,
+            ),
+            Txid([0x1; 32]),
+            "post-conditions would fail".to_string(),
+        );
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let mut bytes = vec![];
+        http.begin_request(HttpVersion::Http11, "/v2/transactions".to_string());
+        http.write_message(&mut bytes, &StacksHttpMessage::Response(response.clone()))
+            .unwrap();
+        assert!(std::str::from_utf8(&bytes)
+            .unwrap()
+            .contains("X-Stacks-Admission-Warning: post-conditions would fail\r\n"));
+
+        let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+        let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+        match message {
+            StacksHttpMessage::Response(HttpResponseType::TransactionIDWithWarning(
+                _,
+                txid,
+                warning,
+            )) => {
+                assert_eq!(txid, Txid([0x1; 32]));
+                assert_eq!(warning, "post-conditions would fail");
+            }
+            _ => panic!("Did not parse a flagged transaction ID: {:?}", &message),
+        }
+    }
+
     #[test]
     fn test_http_parse_proof_tip_query() {
         let query_txt = "tip=7070f213d719143d6045e08fd80f85014a161f8bbd3a42d1251576740826a392";
@@ -6948,6 +9536,15 @@ mod test {
             _ => panic!(),
         }
 
+        // tip can be a block height
+        let query_txt_height = "tip=1234&proof=0";
+        let tip_req = HttpRequestType::get_chain_tip_query(Some(query_txt_height));
+        assert_eq!(tip_req, TipRequest::SpecificHeight(1234));
+        assert_eq!(
+            HttpRequestType::make_tip_query_string(&tip_req, false),
+            "?tip=1234&proof=0"
+        );
+
         // tip can be skipped
         let query_txt_bad = "tip=bad";
         assert_eq!(
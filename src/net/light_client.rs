@@ -0,0 +1,355 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2022 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Header-only tracking of the Stacks chain, for clients that do not keep any chainstate.
+//!
+//! A `LightHeaderChain` starts from a header the client trusts (e.g. one it has checked against
+//! a node it operates), and extends it with the batches served by
+//! `GET /v2/headers/light/:start_height/:count`.  Each header must attach to the one before it,
+//! by index block hash and by block hash, and must agree with its own height, burn and state
+//! index root.  The chain keeps a bounded window of recent headers, so that it can follow forks
+//! that are no deeper than the window.
+//!
+//! A header chain does not decide which fork is canonical -- it follows the node that serves it.
+//! Nor does it check that each sortition actually happened on the burnchain: clients that need
+//! that should check `burn_header_hash` and `winning_block_txid` against a Bitcoin SPV client.
+//! Once the chain is validated, the `state_index_root` of any of its headers can be used to
+//! verify MARF proofs (see `chainstate::stacks::index::marf::verify_proof`).
+
+use std::collections::VecDeque;
+use std::error;
+use std::fmt;
+
+use crate::net::{RPCLightHeader, RPCLightHeaderBatch};
+use crate::types::chainstate::StacksBlockId;
+
+/// How many headers below the tip a `LightHeaderChain` keeps by default, and thus how deep a
+/// fork it can follow.
+pub const LIGHT_CLIENT_DEFAULT_REORG_DEPTH: usize = 144;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The first header of a batch does not attach to any header in the window
+    UnknownParent(StacksBlockId),
+    /// The batch forks off below the window, at the given height
+    ReorgTooDeep(u64),
+    /// A header in the batch is not valid, at the given height
+    InvalidHeader(u64, String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnknownParent(ref parent_block_id) => {
+                write!(f, "Unknown parent block {}", parent_block_id)
+            }
+            Error::ReorgTooDeep(height) => {
+                write!(
+                    f,
+                    "Fork at height {} is deeper than the header window",
+                    height
+                )
+            }
+            Error::InvalidHeader(height, ref msg) => {
+                write!(f, "Invalid header at height {}: {}", height, msg)
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        None
+    }
+}
+
+/// A validated chain of Stacks block headers, ending at the tip of the fork the client follows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightHeaderChain {
+    /// recent headers, in order of increasing height
+    headers: VecDeque<RPCLightHeader>,
+    max_reorg_depth: usize,
+}
+
+impl LightHeaderChain {
+    /// Start a header chain from a trusted header.
+    pub fn new(checkpoint: RPCLightHeader, max_reorg_depth: usize) -> LightHeaderChain {
+        let mut headers = VecDeque::new();
+        headers.push_back(checkpoint);
+        LightHeaderChain {
+            headers,
+            max_reorg_depth,
+        }
+    }
+
+    /// The highest validated header.
+    pub fn tip(&self) -> &RPCLightHeader {
+        self.headers
+            .back()
+            .expect("BUG: light header chain is empty")
+    }
+
+    /// The height to ask for next, to extend the chain.
+    pub fn next_height(&self) -> u64 {
+        self.tip().height + 1
+    }
+
+    /// Find a header in the window by its height.
+    pub fn get_header(&self, height: u64) -> Option<&RPCLightHeader> {
+        let lowest = self.headers.front()?.height;
+        if height < lowest {
+            return None;
+        }
+        self.headers.get((height - lowest) as usize)
+    }
+
+    /// Check that `header` can be the child of `parent`.
+    fn validate_header(parent: &RPCLightHeader, header: &RPCLightHeader) -> Result<(), Error> {
+        let invalid = |msg: String| Err(Error::InvalidHeader(header.height, msg));
+        if header.height != parent.height + 1 {
+            return invalid(format!("expected height {}", parent.height + 1));
+        }
+        if header.header.total_work.work != header.height {
+            return invalid(format!(
+                "header has total work {}",
+                header.header.total_work.work
+            ));
+        }
+        if header.parent_block_id != parent.index_block_hash() {
+            return invalid(format!(
+                "parent is {}, not {}",
+                header.parent_block_id,
+                parent.index_block_hash()
+            ));
+        }
+        if header.header.parent_block != parent.header.block_hash() {
+            return invalid(format!(
+                "header's parent block is {}, not {}",
+                header.header.parent_block,
+                parent.header.block_hash()
+            ));
+        }
+        if header.header.total_work.burn < parent.header.total_work.burn {
+            return invalid("total burn decreased".to_string());
+        }
+        if header.burn_header_height <= parent.burn_header_height {
+            return invalid(format!(
+                "burn height {} is not above its parent's",
+                header.burn_header_height
+            ));
+        }
+        if header.state_index_root != header.header.state_index_root {
+            return invalid("state index root does not match the header's".to_string());
+        }
+        Ok(())
+    }
+
+    /// Validate a batch of headers and add it to the chain.  If the batch forks off from the
+    /// chain, the headers it replaces are dropped.  Headers the chain already has are skipped.
+    /// Returns the number of headers dropped; on error, the chain is left unchanged.
+    pub fn apply_batch(&mut self, batch: &RPCLightHeaderBatch) -> Result<u64, Error> {
+        let first = match batch.headers.first() {
+            Some(first) => first,
+            None => return Ok(0),
+        };
+        if let Some(last) = batch.headers.last() {
+            if last.height == batch.tip_height && last.index_block_hash() != batch.tip {
+                return Err(Error::InvalidHeader(
+                    last.height,
+                    format!("expected the batch's tip {}", batch.tip),
+                ));
+            }
+        }
+
+        let parent_idx = match self
+            .headers
+            .iter()
+            .position(|header| header.index_block_hash() == first.parent_block_id)
+        {
+            Some(idx) => idx,
+            None => {
+                let lowest = self.headers.front().map(|h| h.height).unwrap_or(0);
+                if first.height <= lowest {
+                    return Err(Error::ReorgTooDeep(first.height));
+                }
+                return Err(Error::UnknownParent(first.parent_block_id));
+            }
+        };
+
+        let mut parent = &self.headers[parent_idx];
+        for header in batch.headers.iter() {
+            LightHeaderChain::validate_header(parent, header)?;
+            parent = header;
+        }
+
+        // skip the headers we already have, and drop the ones the batch replaces
+        let mut dropped = 0;
+        for (i, header) in batch.headers.iter().enumerate() {
+            let idx = parent_idx + 1 + i;
+            match self.headers.get(idx) {
+                Some(existing) if existing == header => continue,
+                Some(_) => {
+                    dropped = (self.headers.len() - idx) as u64;
+                    self.headers.truncate(idx);
+                    self.headers.push_back(header.clone());
+                }
+                None => self.headers.push_back(header.clone()),
+            }
+        }
+
+        while self.headers.len() > self.max_reorg_depth + 1 {
+            self.headers.pop_front();
+        }
+        Ok(dropped)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::burnchains::Txid;
+    use crate::chainstate::burn::ConsensusHash;
+    use crate::chainstate::stacks::StacksBlockHeader;
+    use crate::types::chainstate::StacksWorkScore;
+    use crate::types::chainstate::{BurnchainHeaderHash, SortitionId, TrieHash};
+
+    fn make_header(parent: Option<&RPCLightHeader>, fork: u8) -> RPCLightHeader {
+        let height = parent.map(|p| p.height + 1).unwrap_or(1);
+        let mut header = StacksBlockHeader::genesis_block_header();
+        header.total_work = StacksWorkScore {
+            burn: height * 100,
+            work: height,
+        };
+        header.state_index_root = TrieHash([fork; 32]);
+        header.tx_merkle_root.0[0] = height as u8;
+        header.tx_merkle_root.0[1] = fork;
+        let parent_block_id = match parent {
+            Some(p) => {
+                header.parent_block = p.header.block_hash();
+                p.index_block_hash()
+            }
+            None => StacksBlockId([0xff; 32]),
+        };
+        RPCLightHeader {
+            height,
+            consensus_hash: ConsensusHash([height as u8; 20]),
+            state_index_root: header.state_index_root,
+            header,
+            parent_block_id,
+            sortition_id: SortitionId([height as u8; 32]),
+            burn_header_hash: BurnchainHeaderHash([height as u8; 32]),
+            burn_header_height: 100 + height as u32,
+            winning_block_txid: Txid([height as u8; 32]),
+        }
+    }
+
+    fn make_batch(parent: &RPCLightHeader, count: usize, fork: u8) -> RPCLightHeaderBatch {
+        let mut headers: Vec<RPCLightHeader> = vec![];
+        for _ in 0..count {
+            let header = make_header(Some(headers.last().unwrap_or(parent)), fork);
+            headers.push(header);
+        }
+        let last = headers.last().unwrap();
+        RPCLightHeaderBatch {
+            tip: last.index_block_hash(),
+            tip_height: last.height,
+            headers,
+        }
+    }
+
+    #[test]
+    fn test_light_header_chain_extend() {
+        let checkpoint = make_header(None, 0);
+        let mut chain = LightHeaderChain::new(checkpoint.clone(), 3);
+        assert_eq!(chain.next_height(), 2);
+
+        let batch = make_batch(&checkpoint, 5, 0);
+        assert_eq!(chain.apply_batch(&batch), Ok(0));
+        assert_eq!(chain.tip(), batch.headers.last().unwrap());
+        assert_eq!(chain.next_height(), 7);
+
+        // only the last few headers are kept
+        assert_eq!(chain.get_header(2), None);
+        assert_eq!(chain.get_header(3), Some(&batch.headers[1]));
+
+        // a batch the chain already has changes nothing
+        let mut stale = batch.clone();
+        stale.headers.drain(..3);
+        assert_eq!(chain.apply_batch(&stale), Ok(0));
+        assert_eq!(chain.tip(), batch.headers.last().unwrap());
+    }
+
+    #[test]
+    fn test_light_header_chain_reorg() {
+        let checkpoint = make_header(None, 0);
+        let mut chain = LightHeaderChain::new(checkpoint.clone(), 3);
+        let batch = make_batch(&checkpoint, 4, 0);
+        chain.apply_batch(&batch).unwrap();
+
+        // fork off at height 3, dropping heights 4 and 5
+        let fork = make_batch(&batch.headers[1], 3, 1);
+        assert_eq!(chain.apply_batch(&fork), Ok(2));
+        assert_eq!(chain.tip(), fork.headers.last().unwrap());
+        assert_eq!(chain.get_header(4), Some(&fork.headers[0]));
+
+        // forks below the window cannot be followed
+        let deep_fork = make_batch(&checkpoint, 6, 2);
+        assert_eq!(chain.apply_batch(&deep_fork), Err(Error::ReorgTooDeep(2)));
+
+        // neither can batches that skip ahead of the chain
+        let orphan = make_batch(&fork.headers[2], 2, 1);
+        let mut skipped = orphan.clone();
+        skipped.headers.remove(0);
+        assert_eq!(
+            chain.apply_batch(&skipped),
+            Err(Error::UnknownParent(orphan.headers[0].index_block_hash()))
+        );
+    }
+
+    #[test]
+    fn test_light_header_chain_invalid() {
+        let checkpoint = make_header(None, 0);
+        let mut chain = LightHeaderChain::new(checkpoint.clone(), 3);
+        let good = make_batch(&checkpoint, 3, 0);
+
+        let mut bad_work = good.clone();
+        bad_work.headers[1].header.total_work.work = 10;
+        let mut bad_parent = good.clone();
+        bad_parent.headers[1].header.parent_block = checkpoint.header.block_hash();
+        let mut bad_burn = good.clone();
+        bad_burn.headers[1].burn_header_height = good.headers[0].burn_header_height;
+        let mut bad_root = good.clone();
+        bad_root.headers[1].state_index_root = TrieHash([0x11; 32]);
+        let mut bad_tip = good.clone();
+        bad_tip.tip = StacksBlockId([0x22; 32]);
+
+        for bad in [bad_work, bad_parent, bad_burn, bad_root].iter() {
+            match chain.apply_batch(bad) {
+                Err(Error::InvalidHeader(3, _)) => {}
+                res => panic!("expected an invalid header at height 3, got {:?}", res),
+            }
+        }
+        match chain.apply_batch(&bad_tip) {
+            Err(Error::InvalidHeader(4, _)) => {}
+            res => panic!("expected an invalid tip, got {:?}", res),
+        }
+
+        // failed batches leave the chain alone
+        assert_eq!(chain.tip(), &checkpoint);
+        assert_eq!(chain.apply_batch(&good), Ok(0));
+    }
+}
@@ -47,6 +47,8 @@ use crate::burnchains::Txid;
 use crate::chainstate::burn::ConsensusHash;
 use crate::chainstate::coordinator::Error as coordinator_error;
 use crate::chainstate::stacks::db::blocks::MemPoolRejection;
+use crate::chainstate::stacks::db::consistency::ChainstateConsistencyHash;
+use crate::chainstate::stacks::db::event_index::AccountEventsQuery;
 use crate::chainstate::stacks::index::Error as marf_error;
 use crate::chainstate::stacks::Error as chainstate_error;
 use crate::chainstate::stacks::{
@@ -56,6 +58,7 @@ use crate::chainstate::stacks::{
 use crate::clarity_vm::clarity::Error as clarity_error;
 use crate::core::mempool::*;
 use crate::core::POX_REWARD_CYCLE_LENGTH;
+use crate::monitoring::SyncProgress;
 use crate::net::atlas::{Attachment, AttachmentInstance};
 use crate::net::http::HttpReservedHeader;
 pub use crate::net::http::StacksBlockAcceptedData;
@@ -64,6 +67,8 @@ use crate::util_lib::boot::boot_code_tx_auth;
 use crate::util_lib::db::DBConn;
 use crate::util_lib::db::Error as db_error;
 use crate::util_lib::strings::UrlString;
+use clarity::vm::coverage::CoverageDump;
+use clarity::vm::tracing::ExecutionTrace;
 use clarity::vm::types::TraitIdentifier;
 use clarity::vm::{
     analysis::contract_interface_builder::ContractInterface, types::PrincipalData, ClarityName,
@@ -74,6 +79,7 @@ use stacks_common::codec::StacksMessageCodec;
 use stacks_common::codec::{read_next, write_next};
 use stacks_common::util::get_epoch_time_secs;
 use stacks_common::util::hash::Hash160;
+use stacks_common::util::hash::Sha512Trunc256Sum;
 use stacks_common::util::hash::DOUBLE_SHA256_ENCODED_SIZE;
 use stacks_common::util::hash::HASH160_ENCODED_SIZE;
 use stacks_common::util::hash::{hex_bytes, to_hex};
@@ -89,6 +95,7 @@ use crate::cost_estimates::FeeRateEstimate;
 use crate::types::chainstate::BlockHeaderHash;
 use crate::types::chainstate::PoxId;
 use crate::types::chainstate::{BurnchainHeaderHash, StacksAddress, StacksBlockId};
+use crate::types::chainstate::{SortitionId, TrieHash};
 use crate::types::StacksPublicKeyBuffer;
 use crate::util::hash::Sha256Sum;
 use crate::vm::costs::ExecutionCost;
@@ -122,6 +129,9 @@ pub mod dns;
 pub mod download;
 pub mod http;
 pub mod inv;
+/// Implements `LightHeaderChain`, which validates the header batches served by
+/// GET /v2/headers/light, so that clients can follow the canonical Stacks chain without its state.
+pub mod light_client;
 pub mod neighbors;
 pub mod p2p;
 /// Implements wrapper around `mio` crate, which itself is a wrapper around Linux's `epoll(2)` syscall.
@@ -130,9 +140,18 @@ pub mod p2p;
 /// p2p server and the http server.
 pub mod poll;
 pub mod prune;
+/// Implements `RPCRateLimiter`, which throttles HTTP RPC clients with token buckets per client
+/// IP address and per route.
+pub mod rate_limit;
 pub mod relay;
+/// Implements `PeerMisbehavior`, which says how much each kind of misbehavior costs a peer's
+/// score.  Scores are kept in the peer DB.
+pub mod reputation;
 pub mod rpc;
 pub mod server;
+/// Implements `SocksHandshake`, which asks a SOCKS5 proxy (such as Tor) to connect an outbound
+/// socket to its destination.
+pub mod socks;
 
 #[derive(Debug)]
 pub enum Error {
@@ -836,12 +855,17 @@ pub struct HandshakeData {
     pub node_public_key: StacksPublicKeyBuffer,
     pub expire_block_height: u64, // burn block height after which this node's key will be revoked,
     pub data_url: UrlString,
+    /// The minimum fee rate of the transactions this node admits to its mempool.  This is only
+    /// sent to peers that advertise `ServiceFlags::FEE_FILTER`, since it is an optional field at
+    /// the end of the message that older peers do not expect.
+    pub min_tx_fee_rate: Option<f64>,
 }
 
 #[repr(u8)]
 pub enum ServiceFlags {
     RELAY = 0x01,
     RPC = 0x02,
+    FEE_FILTER = 0x04, // understands the minimum fee rate in handshakes
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -1050,6 +1074,10 @@ pub struct RPCPeerInfoData {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub node_public_key_hash: Option<Hash160>,
+    /// How far the node has synced the burnchain and the Stacks chain, with ETAs
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync_progress: Option<SyncProgress>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -1096,6 +1124,164 @@ pub struct RPCPoxInfoData {
     pub next_reward_cycle_in: u64,
 }
 
+/// The status of the PoX anchor block for a reward cycle, as listed on GET /v2/pox/cycle/:n
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPoxAnchorBlockInfo {
+    /// `pending` until the cycle's prepare phase ends, then `selected_and_known`,
+    /// `selected_and_unknown`, or `not_selected`
+    pub status: String,
+    /// height of the last burnchain block of the prepare phase, in which the anchor block is chosen
+    pub prepare_phase_end_block_height: u64,
+    pub consensus_hash: Option<ConsensusHash>,
+    pub block_hash: Option<BlockHeaderHash>,
+}
+
+/// The data we return on GET /v2/pox/cycle/:n.  The reward set is projected from the PoX
+/// contract's state at the queried Stacks tip.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPoxCycleInfoData {
+    pub reward_cycle: u64,
+    pub reward_phase_start_block_height: u64,
+    pub is_pox_active: bool,
+    pub total_stacked_ustx: u64,
+    pub min_threshold_ustx: u64,
+    /// number of reward slots the stacked uSTX fill
+    pub reward_set_size: u64,
+    /// number of distinct PoX addresses in the reward set
+    pub reward_set_addresses: u64,
+    /// the `pox_address` query parameter, if given
+    pub pox_address: Option<String>,
+    /// uSTX stacked to `pox_address` in this cycle
+    pub pox_address_stacked_ustx: Option<u64>,
+    /// reward slots `pox_address` is projected to receive in this cycle
+    pub pox_address_reward_slots: Option<u64>,
+    pub anchor_block: RPCPoxAnchorBlockInfo,
+}
+
+/// A block listed on GET /v2/feed/blocks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCFinalizedBlock {
+    pub height: u64,
+    pub index_block_hash: StacksBlockId,
+    pub block_hash: BlockHeaderHash,
+    pub consensus_hash: ConsensusHash,
+    pub parent_block_id: StacksBlockId,
+    pub burn_header_hash: BurnchainHeaderHash,
+    pub burn_header_height: u32,
+    pub burn_header_timestamp: u64,
+}
+
+/// The data we return on GET /v2/feed/blocks.  Page `n` always covers the Stacks block heights
+/// `[n * page_size, (n + 1) * page_size)`, and only lists blocks that are at least
+/// `confirmations` blocks deep in the canonical chain.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCFinalizedBlocksPage {
+    pub page: u64,
+    pub page_size: u64,
+    pub confirmations: u64,
+    /// height of the newest finalized block
+    pub finalized_height: u64,
+    /// true if every block in this page's height range is finalized, so the page will not change
+    pub complete: bool,
+    /// path to the previous page, if there is one
+    pub prev: Option<String>,
+    /// path to the next page, once this page is complete
+    pub next: Option<String>,
+    /// in order of increasing height
+    pub blocks: Vec<RPCFinalizedBlock>,
+}
+
+/// A block-commit listed on GET /v2/burn_ops/:burn_height and GET /v2/sortitions/:consensus_hash
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCBlockCommit {
+    pub txid: String,
+    pub vtxindex: u32,
+    pub block_header_hash: BlockHeaderHash,
+    /// hex-encoded VRF seed
+    pub new_seed: String,
+    pub parent_block_ptr: u32,
+    pub parent_vtxindex: u16,
+    pub key_block_ptr: u32,
+    pub key_vtxindex: u16,
+    pub burn_fee: u64,
+    pub sunset_burn: u64,
+    /// the spent `txid:vout`
+    pub input: String,
+    /// hex-encoded public keys of the sender
+    pub apparent_sender: Vec<String>,
+    pub commit_outs: Vec<String>,
+    /// hex-encoded
+    pub memo: String,
+}
+
+/// A leader key registration listed on GET /v2/burn_ops/:burn_height
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCLeaderKeyRegister {
+    pub txid: String,
+    pub vtxindex: u32,
+    /// hex-encoded VRF public key
+    pub public_key: String,
+    pub address: String,
+    pub consensus_hash: ConsensusHash,
+    /// hex-encoded
+    pub memo: String,
+}
+
+/// The data we return on GET /v2/burn_ops/:burn_height: the block-commits and leader key
+/// registrations accepted in the canonical burnchain block at that height.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCBurnOps {
+    pub burn_block_height: u64,
+    pub burn_header_hash: String,
+    pub consensus_hash: ConsensusHash,
+    pub block_commits: Vec<RPCBlockCommit>,
+    pub leader_key_registers: Vec<RPCLeaderKeyRegister>,
+}
+
+/// The data we return on GET /v2/sortitions/:consensus_hash
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCSortitionInfo {
+    pub burn_block_height: u64,
+    pub burn_header_hash: String,
+    pub parent_burn_header_hash: String,
+    pub consensus_hash: ConsensusHash,
+    /// whether this burnchain block chose a Stacks block
+    pub sortition: bool,
+    pub total_burn: u64,
+    /// whether this sortition is on the canonical burnchain fork
+    pub canonical: bool,
+    /// the block-commit that won, if there was a sortition
+    pub winning_block_commit: Option<RPCBlockCommit>,
+}
+
+/// The data we return on GET /v2/accounts/:principal/events and
+/// GET /v2/contracts/events/:principal/:contract_name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCAccountEventsPage {
+    /// newest first
+    pub events: Vec<serde_json::Value>,
+    /// pass this as `cursor` to list the events that come before these, if there may be any
+    pub next_cursor: Option<String>,
+}
+
+/// An entry of a data map, as listed by GET /v2/map_entries/:principal/:contract_name/:map_name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMapEntry {
+    /// hex-encoded serialized Clarity value
+    pub key: String,
+    /// hex-encoded serialized Clarity value
+    pub value: String,
+}
+
+/// The data we return on GET /v2/map_entries/:principal/:contract_name/:map_name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMapEntriesPage {
+    /// in ascending order of their serialized keys
+    pub entries: Vec<RPCMapEntry>,
+    /// pass this as `cursor` to list the entries that come after these, if there may be any
+    pub next_cursor: Option<String>,
+}
+
 /// Headers response payload
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ExtendedStacksHeader {
@@ -1127,6 +1313,43 @@ fn ExtendedStacksHeader_StacksBlockHeader_deserialize<'de, D: serde::Deserialize
     StacksBlockHeader::consensus_deserialize(&mut &header_bytes[..]).map_err(de_Error::custom)
 }
 
+/// A Stacks block header as served to light clients: the header itself, how it attaches to its
+/// parent, and the sortition that selected it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCLightHeader {
+    pub height: u64,
+    pub consensus_hash: ConsensusHash,
+    #[serde(
+        serialize_with = "ExtendedStacksHeader_StacksBlockHeader_serialize",
+        deserialize_with = "ExtendedStacksHeader_StacksBlockHeader_deserialize"
+    )]
+    pub header: StacksBlockHeader,
+    pub parent_block_id: StacksBlockId,
+    /// root hash of the MARF as of this block, i.e. the header's `state_index_root`
+    pub state_index_root: TrieHash,
+    pub sortition_id: SortitionId,
+    pub burn_header_hash: BurnchainHeaderHash,
+    pub burn_header_height: u32,
+    /// txid of the block-commit that won the sortition, which committed to this block
+    pub winning_block_txid: Txid,
+}
+
+impl RPCLightHeader {
+    pub fn index_block_hash(&self) -> StacksBlockId {
+        StacksBlockHeader::make_index_block_hash(&self.consensus_hash, &self.header.block_hash())
+    }
+}
+
+/// The data we return on GET /v2/headers/light/:start_height/:count
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCLightHeaderBatch {
+    /// the chain tip the headers were read from
+    pub tip: StacksBlockId,
+    pub tip_height: u64,
+    /// in order of increasing height, starting at the requested height
+    pub headers: Vec<RPCLightHeader>,
+}
+
 impl StacksMessageCodec for ExtendedStacksHeader {
     fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
         write_next(fd, &self.consensus_hash)?;
@@ -1194,6 +1417,10 @@ pub struct HttpRequestMetadata {
     pub peer: PeerHost,
     pub keep_alive: bool,
     pub canonical_stacks_tip_height: Option<u64>,
+    /// API key sent as `Authorization: Bearer <key>`
+    pub auth_token: Option<String>,
+    /// Entity tag of a cached response, sent as `If-None-Match: <etag>`
+    pub if_none_match: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -1224,6 +1451,28 @@ pub struct ContractSrcResponse {
     pub marf_proof: Option<String>,
 }
 
+/// A contract's ABI, as served by GET /v2/contracts/interface.  The fields of the
+/// `ContractInterface` are kept at the top level so that older clients can still read it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractInterfaceResponse {
+    #[serde(flatten)]
+    pub interface: ContractInterface,
+    pub source: String,
+    pub publish_height: u32,
+    pub clarity_version: u32,
+    /// Traits this contract declares with `impl-trait`
+    pub implemented_traits: Vec<String>,
+}
+
+impl ContractInterfaceResponse {
+    /// Entity tag of this response.  A contract can't be changed once it's published, so this
+    /// stays the same at every tip that descends from the contract's block.
+    pub fn etag(&self) -> String {
+        let bytes = serde_json::to_vec(self).expect("FATAL: failed to serialize contract ABI");
+        format!("\"{}\"", Sha512Trunc256Sum::from_data(&bytes).to_hex())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GetIsTraitImplementedResponse {
     pub is_implemented: bool,
@@ -1238,6 +1487,18 @@ pub struct CallReadOnlyResponse {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cause: Option<String>,
+    /// Execution trace of the call, if it was requested with `trace=true`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<ExecutionTrace>,
+}
+
+/// The data we return on POST /v2/contracts/call-read-batch: each call's result, in order, and
+/// the chain tip they all ran against
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CallReadOnlyBatchResponse {
+    pub tip: StacksBlockId,
+    pub results: Vec<CallReadOnlyResponse>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -1269,6 +1530,63 @@ pub struct UnconfirmedTransactionResponse {
     pub status: UnconfirmedTransactionStatus,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TransactionStatus {
+    /// neither in the mempool nor confirmed in the canonical chain
+    #[serde(rename = "unknown")]
+    Unknown,
+    /// in the mempool, and not confirmed in the canonical chain
+    #[serde(rename = "pending")]
+    Pending,
+    /// confirmed in the canonical chain
+    #[serde(rename = "confirmed")]
+    Confirmed,
+}
+
+/// A transaction's mempool entry, as reported by GET /v2/transactions/:txid
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMempoolTransaction {
+    /// when the mempool admitted the transaction, in seconds since the epoch
+    pub accept_time: u64,
+    pub tx_fee: u64,
+    pub origin_address: StacksAddress,
+    pub origin_nonce: u64,
+    /// hex-encoded transaction
+    pub tx: String,
+}
+
+/// How a transaction was confirmed, as reported by GET /v2/transactions/:txid
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCConfirmedTransaction {
+    pub index_block_hash: StacksBlockId,
+    pub block_hash: BlockHeaderHash,
+    pub consensus_hash: ConsensusHash,
+    pub block_height: u64,
+    pub burn_block_height: u32,
+    /// the result, as a Clarity expression
+    pub result: String,
+    /// the hex-encoded consensus serialization of the result, if known
+    pub raw_result: Option<String>,
+    pub post_condition_aborted: Option<bool>,
+    /// the transaction's events, if known
+    pub events: Option<Vec<serde_json::Value>>,
+}
+
+/// The data we return on GET /v2/transactions/:txid
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCTransactionStatus {
+    pub txid: Txid,
+    pub status: TransactionStatus,
+    /// whether this node logs confirmed transactions.  If not, it can only report on transactions
+    /// that are still in its mempool, even if they were confirmed.
+    pub confirmations_logged: bool,
+    /// set if the transaction is in the mempool.  Confirmed transactions can remain in the
+    /// mempool for a while.
+    pub mempool: Option<RPCMempoolTransaction>,
+    /// set if the transaction is confirmed in the canonical chain
+    pub confirmed: Option<RPCConfirmedTransaction>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct PostTransactionRequestBody {
     pub tx: String,
@@ -1326,6 +1644,8 @@ impl HttpRequestMetadata {
             peer: PeerHost::from_host_port(host, port),
             keep_alive: true,
             canonical_stacks_tip_height,
+            auth_token: None,
+            if_none_match: None,
         }
     }
 
@@ -1338,6 +1658,8 @@ impl HttpRequestMetadata {
             peer: peer_host,
             keep_alive: true,
             canonical_stacks_tip_height,
+            auth_token: None,
+            if_none_match: None,
         }
     }
 
@@ -1351,11 +1673,22 @@ impl HttpRequestMetadata {
                 break;
             }
         }
+        let auth_token = preamble
+            .headers
+            .get("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|token| token.trim().to_string());
+        let if_none_match = preamble
+            .headers
+            .get("if-none-match")
+            .map(|etag| etag.trim().to_string());
         HttpRequestMetadata {
             version: preamble.version,
             peer: preamble.host.clone(),
             keep_alive: preamble.keep_alive,
             canonical_stacks_tip_height,
+            auth_token,
+            if_none_match,
         }
     }
 }
@@ -1366,6 +1699,26 @@ pub struct CallReadOnlyRequestBody {
     pub arguments: Vec<String>,
 }
 
+/// One of the calls in the body of a POST /v2/contracts/call-read-batch
+#[derive(Serialize, Deserialize)]
+pub struct CallReadOnlyBatchEntry {
+    pub contract_address: String,
+    pub contract_name: String,
+    pub function_name: String,
+    pub sender: String,
+    pub arguments: Vec<String>,
+}
+
+/// A read-only function call, as requested in a batch
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadOnlyCall {
+    pub contract_addr: StacksAddress,
+    pub contract_name: ContractName,
+    pub sender: PrincipalData,
+    pub function_name: ClarityName,
+    pub arguments: Vec<Value>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct FeeRateEstimateRequestBody {
     #[serde(default)]
@@ -1373,6 +1726,30 @@ pub struct FeeRateEstimateRequestBody {
     pub transaction_payload: String,
 }
 
+/// The body of a POST /v2/transactions/dry-run
+#[derive(Serialize, Deserialize)]
+pub struct DryRunTransactionRequestBody {
+    pub sender: String,
+    pub transaction_payload: String,
+}
+
+/// The data we return on POST /v2/transactions/dry-run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCDryRunResponse {
+    pub okay: bool,
+    /// Hex-serialized Clarity value the transaction returned, if it could be processed
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+    /// Why the transaction could not be processed
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cause: Option<String>,
+    pub execution_cost: ExecutionCost,
+    /// Events the transaction emitted, in the format sent to event observers
+    pub events: Vec<serde_json::Value>,
+}
+
 /// Items in the NeighborsInfo -- combines NeighborKey and NeighborAddress
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RPCNeighbor {
@@ -1383,10 +1760,18 @@ pub struct RPCNeighbor {
     pub port: u16,
     pub public_key_hash: Hash160,
     pub authenticated: bool,
+    /// the neighbor's reputation score (0 is best; it is banned once this gets low enough)
+    #[serde(default)]
+    pub score: i64,
 }
 
 impl RPCNeighbor {
-    pub fn from_neighbor_key_and_pubkh(nk: NeighborKey, pkh: Hash160, auth: bool) -> RPCNeighbor {
+    pub fn from_neighbor_key_and_pubkh(
+        nk: NeighborKey,
+        pkh: Hash160,
+        auth: bool,
+        score: i64,
+    ) -> RPCNeighbor {
         RPCNeighbor {
             network_id: nk.network_id,
             peer_version: nk.peer_version,
@@ -1394,6 +1779,7 @@ impl RPCNeighbor {
             port: nk.port,
             public_key_hash: pkh,
             authenticated: auth,
+            score: score,
         }
     }
 }
@@ -1406,11 +1792,78 @@ pub struct RPCNeighborsInfo {
     pub outbound: Vec<RPCNeighbor>,
 }
 
+/// Body of a POST to `/v2/neighbors/ban` or `/v2/neighbors/unban`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPeerBanRequestBody {
+    pub ip: PeerAddress,
+    pub port: u16,
+    /// how long to ban the peer for, in seconds.  Defaults to `DENY_BAN_DURATION`; ignored when
+    /// unbanning.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u64>,
+}
+
+/// Struct given back from a call to `/v2/neighbors/ban` or `/v2/neighbors/unban`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPeerBanResponse {
+    pub ip: PeerAddress,
+    pub port: u16,
+    /// when the ban ends, in seconds since the epoch (0 if the peer is not banned)
+    pub banned_until: u64,
+    pub score: i64,
+}
+
+/// An attachment instance whose attachment has not been fetched yet, in the answer to
+/// `/v2/attachments/status`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMissingAttachment {
+    pub content_hash: Hash160,
+    pub contract_id: String,
+    pub attachment_index: u32,
+    pub block_height: u64,
+    pub index_block_hash: StacksBlockId,
+    /// whether the attachments downloader is still trying to fetch it (false once it gave up)
+    pub queued: bool,
+    /// how many attempts to fetch it fell short
+    pub retry_count: u64,
+    /// when the next attempt may start, in seconds since the epoch (0 if not queued)
+    pub next_attempt: u64,
+    /// data URLs of the peers whose latest inventories list the attachment
+    pub available_from: Vec<UrlString>,
+}
+
+/// A peer the attachments downloader has used, in the answer to `/v2/attachments/status`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCAttachmentsPeer {
+    pub data_url: UrlString,
+    pub requests_sent: u32,
+    pub requests_succeeded: u32,
+    /// how many of the missing attachments its latest inventories list
+    pub missing_attachments_available: u64,
+}
+
+/// Struct given back from a call to `/v2/attachments/status`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCAttachmentsStatus {
+    pub missing_attachments: Vec<RPCMissingAttachment>,
+    pub peers: Vec<RPCAttachmentsPeer>,
+}
+
+/// Struct given back from a call to `/v2/attachments/{hash}/refetch`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCAttachmentRefetchResponse {
+    pub content_hash: Hash160,
+    /// how many attachment instances waiting for this attachment were queued
+    pub queued_instances: u64,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum TipRequest {
     UseLatestAnchoredTip,
     UseLatestUnconfirmedTip,
     SpecificTip(StacksBlockId),
+    /// The block at this height in the canonical Stacks chain
+    SpecificHeight(u64),
 }
 
 /// All HTTP request paths we support, and the arguments they carry in their paths
@@ -1418,13 +1871,24 @@ pub enum TipRequest {
 pub enum HttpRequestType {
     GetInfo(HttpRequestMetadata),
     GetPoxInfo(HttpRequestMetadata, TipRequest),
+    /// PoX information for this reward cycle, with the projected reward slots of this PoX address
+    GetPoxCycleInfo(HttpRequestMetadata, u64, Option<StacksAddress>, TipRequest),
     GetNeighbors(HttpRequestMetadata),
+    /// Ban the peer at this address and port for this many seconds
+    BanPeer(HttpRequestMetadata, PeerAddress, u16, u64),
+    /// Lift the ban on the peer at this address and port, and reset its score
+    UnbanPeer(HttpRequestMetadata, PeerAddress, u16),
+    /// Dump the Clarity code coverage of the current run, and start a new run if the bool is set
+    GetClarityCoverage(HttpRequestMetadata, bool),
     GetHeaders(HttpRequestMetadata, u64, TipRequest),
     GetBlock(HttpRequestMetadata, StacksBlockId),
     GetMicroblocksIndexed(HttpRequestMetadata, StacksBlockId),
     GetMicroblocksConfirmed(HttpRequestMetadata, StacksBlockId),
     GetMicroblocksUnconfirmed(HttpRequestMetadata, StacksBlockId, u16),
+    GetMicroblocksByParent(HttpRequestMetadata, BlockHeaderHash, Option<u16>),
     GetTransactionUnconfirmed(HttpRequestMetadata, Txid),
+    GetTransactionStatus(HttpRequestMetadata, Txid),
+    GetLightHeaders(HttpRequestMetadata, u64, u64, TipRequest),
     PostTransaction(HttpRequestMetadata, StacksTransaction, Option<Attachment>),
     PostBlock(HttpRequestMetadata, ConsensusHash, StacksBlock),
     PostMicroblock(HttpRequestMetadata, StacksMicroblock, TipRequest),
@@ -1446,7 +1910,22 @@ pub enum HttpRequestType {
         TipRequest,
         bool,
     ),
+    /// List a data map's entries, in pages of the given size that start after the given key
+    GetMapEntries(
+        HttpRequestMetadata,
+        StacksAddress,
+        ContractName,
+        ClarityName,
+        Option<Value>,
+        u64,
+        TipRequest,
+    ),
+    /// Get the chainstate consistency hash at a block
+    GetConsistencyHash(HttpRequestMetadata, TipRequest),
     FeeRateEstimate(HttpRequestMetadata, TransactionPayload, u64),
+    /// Execute this payload as if the sender sent it on top of the canonical tip, without
+    /// keeping any of its effects
+    DryRunTransaction(HttpRequestMetadata, StacksAddress, TransactionPayload),
     CallReadOnlyFunction(
         HttpRequestMetadata,
         StacksAddress,
@@ -1455,7 +1934,9 @@ pub enum HttpRequestType {
         ClarityName,
         Vec<Value>,
         TipRequest,
+        bool,
     ),
+    CallReadOnlyFunctionBatch(HttpRequestMetadata, Vec<ReadOnlyCall>, TipRequest),
     GetTransferCost(HttpRequestMetadata),
     GetContractSrc(
         HttpRequestMetadata,
@@ -1468,6 +1949,9 @@ pub enum HttpRequestType {
     OptionsPreflight(HttpRequestMetadata, String),
     GetAttachment(HttpRequestMetadata, Hash160),
     GetAttachmentsInv(HttpRequestMetadata, StacksBlockId, HashSet<u32>),
+    GetAttachmentsStatus(HttpRequestMetadata),
+    /// Queue the instances waiting for this attachment for an immediate download
+    RefetchAttachment(HttpRequestMetadata, Hash160),
     GetIsTraitImplemented(
         HttpRequestMetadata,
         StacksAddress,
@@ -1476,6 +1960,15 @@ pub enum HttpRequestType {
         TipRequest,
     ),
     MemPoolQuery(HttpRequestMetadata, MemPoolSyncData, Option<Txid>),
+    GetFinalizedBlocks(HttpRequestMetadata, Option<u64>),
+    GetBurnOps(HttpRequestMetadata, u64),
+    GetSortition(HttpRequestMetadata, ConsensusHash),
+    GetAccountEvents(
+        HttpRequestMetadata,
+        PrincipalData,
+        AccountEventsQuery,
+        TipRequest,
+    ),
     /// catch-all for any errors we should surface from parsing
     ClientError(HttpRequestMetadata, ClientError),
 }
@@ -1568,7 +2061,10 @@ impl HttpResponseMetadata {
 pub enum HttpResponseType {
     PeerInfo(HttpResponseMetadata, RPCPeerInfoData),
     PoxInfo(HttpResponseMetadata, RPCPoxInfoData),
+    PoxCycleInfo(HttpResponseMetadata, RPCPoxCycleInfoData),
     Neighbors(HttpResponseMetadata, RPCNeighborsInfo),
+    PeerBan(HttpResponseMetadata, RPCPeerBanResponse),
+    ClarityCoverage(HttpResponseMetadata, CoverageDump),
     Headers(HttpResponseMetadata, Vec<ExtendedStacksHeader>),
     HeaderStream(HttpResponseMetadata),
     Block(HttpResponseMetadata, StacksBlock),
@@ -1576,23 +2072,39 @@ pub enum HttpResponseType {
     Microblocks(HttpResponseMetadata, Vec<StacksMicroblock>),
     MicroblockStream(HttpResponseMetadata),
     TransactionID(HttpResponseMetadata, Txid),
+    /// The transaction was admitted, but pre-executing it found that it will abort
+    TransactionIDWithWarning(HttpResponseMetadata, Txid, String),
     StacksBlockAccepted(HttpResponseMetadata, StacksBlockId, bool),
     MicroblockHash(HttpResponseMetadata, BlockHeaderHash),
     TokenTransferCost(HttpResponseMetadata, u64),
     GetDataVar(HttpResponseMetadata, DataVarResponse),
     GetMapEntry(HttpResponseMetadata, MapEntryResponse),
+    MapEntries(HttpResponseMetadata, RPCMapEntriesPage),
+    ConsistencyHash(HttpResponseMetadata, ChainstateConsistencyHash),
     CallReadOnlyFunction(HttpResponseMetadata, CallReadOnlyResponse),
+    CallReadOnlyFunctionBatch(HttpResponseMetadata, CallReadOnlyBatchResponse),
     GetAccount(HttpResponseMetadata, AccountEntryResponse),
-    GetContractABI(HttpResponseMetadata, ContractInterface),
+    GetContractABI(HttpResponseMetadata, ContractInterfaceResponse),
     GetContractSrc(HttpResponseMetadata, ContractSrcResponse),
     GetIsTraitImplemented(HttpResponseMetadata, GetIsTraitImplementedResponse),
     UnconfirmedTransaction(HttpResponseMetadata, UnconfirmedTransactionResponse),
+    TransactionStatus(HttpResponseMetadata, RPCTransactionStatus),
+    LightHeaders(HttpResponseMetadata, RPCLightHeaderBatch),
     GetAttachment(HttpResponseMetadata, GetAttachmentResponse),
     GetAttachmentsInv(HttpResponseMetadata, GetAttachmentsInvResponse),
+    AttachmentsStatus(HttpResponseMetadata, RPCAttachmentsStatus),
+    AttachmentRefetch(HttpResponseMetadata, RPCAttachmentRefetchResponse),
     MemPoolTxStream(HttpResponseMetadata),
     MemPoolTxs(HttpResponseMetadata, Option<Txid>, Vec<StacksTransaction>),
     OptionsPreflight(HttpResponseMetadata),
     TransactionFeeEstimation(HttpResponseMetadata, RPCFeeEstimateResponse),
+    DryRunTransaction(HttpResponseMetadata, RPCDryRunResponse),
+    FinalizedBlocks(HttpResponseMetadata, RPCFinalizedBlocksPage),
+    BurnOps(HttpResponseMetadata, RPCBurnOps),
+    Sortition(HttpResponseMetadata, RPCSortitionInfo),
+    AccountEvents(HttpResponseMetadata, RPCAccountEventsPage),
+    /// The client's cached copy, identified by this entity tag, is still current
+    NotModified(HttpResponseMetadata, String),
     // peer-given error responses
     BadRequest(HttpResponseMetadata, String),
     BadRequestJSON(HttpResponseMetadata, serde_json::Value),
@@ -1876,6 +2388,22 @@ pub const MAX_MICROBLOCKS_UNCONFIRMED: usize = 1024;
 // maximum number of block headers we'll get streamed to us
 pub const MAX_HEADERS: usize = 2100;
 
+// number of Stacks block heights covered by each page of GET /v2/feed/blocks
+pub const FINALIZED_BLOCKS_PAGE_SIZE: u64 = 100;
+
+// number of events listed by GET /v2/accounts/:principal/events, unless the request asks for
+// fewer or more, and the most it can ask for
+pub const ACCOUNT_EVENTS_PAGE_SIZE: u64 = 50;
+pub const MAX_ACCOUNT_EVENTS_PAGE_SIZE: u64 = 200;
+
+// number of entries listed by GET /v2/map_entries/:principal/:contract_name/:map_name, unless
+// the request asks for fewer or more, and the most it can ask for
+pub const MAP_ENTRIES_PAGE_SIZE: u64 = 50;
+pub const MAX_MAP_ENTRIES_PAGE_SIZE: u64 = 200;
+
+// most read-only calls a single POST /v2/contracts/call-read-batch can make
+pub const MAX_READ_ONLY_CALL_BATCH_SIZE: usize = 32;
+
 // how long a peer will be denied for if it misbehaves
 #[cfg(test)]
 pub const DENY_BAN_DURATION: u64 = 30; // seconds
@@ -2073,6 +2601,7 @@ pub mod test {
     use crate::chainstate::coordinator::tests::*;
     use crate::chainstate::coordinator::*;
     use crate::chainstate::stacks::boot::*;
+    use crate::chainstate::stacks::db::blocks::BlockValidationTelemetry;
     use crate::chainstate::stacks::db::StacksChainState;
     use crate::chainstate::stacks::db::*;
     use crate::chainstate::stacks::miner::test::*;
@@ -2317,6 +2846,7 @@ pub mod test {
 
     pub struct TestEventObserver {
         blocks: Mutex<Vec<TestEventObserverBlock>>,
+        block_telemetry: Mutex<Vec<BlockValidationTelemetry>>,
     }
 
     impl TestEventObserver {
@@ -2324,9 +2854,14 @@ pub mod test {
             self.blocks.lock().unwrap().deref().to_vec()
         }
 
+        pub fn get_block_telemetry(&self) -> Vec<BlockValidationTelemetry> {
+            self.block_telemetry.lock().unwrap().deref().to_vec()
+        }
+
         pub fn new() -> TestEventObserver {
             TestEventObserver {
                 blocks: Mutex::new(vec![]),
+                block_telemetry: Mutex::new(vec![]),
             }
         }
     }
@@ -2372,6 +2907,10 @@ pub mod test {
         fn dispatch_boot_receipts(&mut self, _receipts: Vec<events::StacksTransactionReceipt>) {
             // pass
         }
+
+        fn announce_block_telemetry(&self, telemetry: &BlockValidationTelemetry) {
+            self.block_telemetry.lock().unwrap().push(telemetry.clone())
+        }
     }
 
     // describes a peer's initial configuration
@@ -26,6 +26,7 @@ use crate::net::PeerAddress;
 use crate::net::codec::*;
 use crate::net::*;
 
+use crate::net::connection::AddressFamily;
 use crate::net::connection::ConnectionOptions;
 use crate::net::connection::ReplyHandleP2P;
 
@@ -70,6 +71,7 @@ pub const WALK_MIN_DURATION: u64 = 20; // minimum number of steps a walk will ta
 pub const WALK_MAX_DURATION: u64 = 40; // maximum number of steps a walk will take before we do a hard reset
 pub const WALK_RESET_PROB: f64 = 0.05; // probability of a walk reset in-between the minimum and maximum duration
 pub const WALK_STATE_TIMEOUT: u64 = 60; // how long the walk can remain in a single state before being reset
+pub const ADDRESS_FAMILY_OVERSAMPLE: u64 = 4; // how many more walk neighbors to sample when some address families are filtered out or passed over
 
 #[cfg(test)]
 pub const WALK_RESET_INTERVAL: u64 = 60; // how long a walk can last
@@ -1717,14 +1719,24 @@ impl PeerNetwork {
         block_height: u64,
     ) -> Result<Vec<Neighbor>, net_error> {
         let cur_epoch = self.get_current_epoch();
-        let neighbors = PeerDB::get_random_walk_neighbors(
+        let address_family = self.connection_opts.address_family;
+
+        // sample extra neighbors if some of them may get filtered out or passed over
+        let num_samples = if address_family == AddressFamily::Any {
+            num_neighbors
+        } else {
+            num_neighbors.saturating_mul(ADDRESS_FAMILY_OVERSAMPLE)
+        };
+        let mut neighbors = PeerDB::get_random_walk_neighbors(
             &self.peerdb.conn(),
             self.local_peer.network_id,
             cur_epoch.network_epoch,
-            num_neighbors as u32,
+            num_samples as u32,
             block_height,
         )
         .map_err(net_error::DBError)?;
+        address_family.filter(&mut neighbors, |neighbor| neighbor.addr.addrbytes.clone());
+        neighbors.truncate(num_neighbors as usize);
 
         if neighbors.len() == 0 {
             debug!(
@@ -1893,13 +1905,18 @@ impl PeerNetwork {
     /// Instantiate the neighbor walk to an always-allowed node.
     /// If we're in the initial block download, then this must also be a *bootstrap* peer.
     fn instantiate_walk_to_always_allowed(&mut self, ibd: bool) -> Result<(), net_error> {
-        let allowed_peers = if ibd {
+        let mut allowed_peers = if ibd {
             // only get bootstrap peers
             PeerDB::get_bootstrap_peers(&self.peerdb.conn(), self.local_peer.network_id)?
         } else {
             // can be any peer marked 'always-allowed'
             PeerDB::get_always_allowed_peers(self.peerdb.conn(), self.local_peer.network_id)?
         };
+        self.connection_opts
+            .address_family
+            .filter(&mut allowed_peers, |neighbor| {
+                neighbor.addr.addrbytes.clone()
+            });
 
         let mut count = 0;
         for allowed in allowed_peers.iter() {
@@ -53,6 +53,7 @@ use crate::net::atlas::AtlasDB;
 use crate::net::atlas::{AttachmentInstance, AttachmentsDownloader};
 use crate::net::chat::ConversationP2P;
 use crate::net::chat::NeighborStats;
+use crate::net::connection::AddressFamily;
 use crate::net::connection::ConnectionOptions;
 use crate::net::connection::NetworkReplyHandle;
 use crate::net::connection::ReplyHandleP2P;
@@ -64,11 +65,14 @@ use crate::net::neighbors::*;
 use crate::net::poll::NetworkPollState;
 use crate::net::poll::NetworkState;
 use crate::net::prune::*;
+use crate::net::rate_limit::RPCRateLimiter;
 use crate::net::relay::RelayerStats;
 use crate::net::relay::*;
 use crate::net::relay::*;
+use crate::net::reputation::PeerMisbehavior;
 use crate::net::rpc::RPCHandlerArgs;
 use crate::net::server::*;
+use crate::net::socks::{SocksHandshake, SocksTarget};
 use crate::net::Error as net_error;
 use crate::net::Neighbor;
 use crate::net::NeighborKey;
@@ -99,6 +103,8 @@ pub enum NetworkRequest {
     ), // announce to all wanting neighbors that we have these confirmed microblock streams
     Relay(NeighborKey, StacksMessage),
     Broadcast(Vec<RelayData>, StacksMessageType),
+    BroadcastTransaction(Vec<RelayData>, StacksTransaction, f64), // broadcast a transaction paying this fee rate to neighbors whose minimum fee rate it meets
+    SetMinTxFeeRate(f64), // change the minimum fee rate we announce to neighbors
 }
 
 /// Handle for other threads to use to issue p2p network requests.
@@ -184,6 +190,24 @@ impl NetworkHandle {
         let req = NetworkRequest::Broadcast(relay_hints, msg);
         self.send_request(req)
     }
+
+    /// Broadcast a transaction to our neighbors via the p2p network thread, skipping the ones
+    /// that told us they won't accept its fee rate.
+    pub fn broadcast_transaction(
+        &mut self,
+        relay_hints: Vec<RelayData>,
+        tx: StacksTransaction,
+        fee_rate: f64,
+    ) -> Result<(), net_error> {
+        let req = NetworkRequest::BroadcastTransaction(relay_hints, tx, fee_rate);
+        self.send_request(req)
+    }
+
+    /// Change the minimum fee rate that the p2p network thread announces to our neighbors.
+    pub fn set_min_tx_fee_rate(&mut self, min_tx_fee_rate: f64) -> Result<(), net_error> {
+        let req = NetworkRequest::SetMinTxFeeRate(min_tx_fee_rate);
+        self.send_request(req)
+    }
 }
 
 impl NetworkHandleServer {
@@ -249,7 +273,10 @@ pub struct PeerNetwork {
     pub sockets: HashMap<usize, mio_net::TcpStream>,
     pub events: HashMap<NeighborKey, usize>,
     pub connecting: HashMap<usize, (mio_net::TcpStream, bool, u64)>, // (socket, outbound?, connection sent timestamp)
+    socks_handshakes: HashMap<usize, SocksHandshake>, // connecting sockets that go through the SOCKS proxy
     pub bans: HashSet<usize>,
+    // reputation score penalties not yet written to the peer DB
+    peer_penalties: HashMap<NeighborKey, i64>,
 
     // ongoing messages the network is sending via the p2p interface (not bound to a specific
     // conversation).
@@ -312,6 +339,15 @@ pub struct PeerNetwork {
     mempool_sync_completions: u64,
     mempool_sync_txs: u64,
 
+    // bootstrap peers given by host name, which we look up in DNS every
+    // `connection_opts.dns_seed_refresh_interval` seconds
+    // * dns_seed_pending are the seeds we're waiting on
+    // * dns_seed_addrs are the addresses each seed resolved to last time
+    pub dns_seeds: Vec<DNSSeed>,
+    dns_seed_pending: HashSet<usize>,
+    dns_seed_addrs: HashMap<usize, HashSet<SocketAddr>>,
+    dns_seed_last_refresh: u64,
+
     // how often we pruned a given inbound/outbound peer
     pub prune_outbound_counts: HashMap<NeighborKey, u64>,
     pub prune_inbound_counts: HashMap<NeighborKey, u64>,
@@ -319,6 +355,9 @@ pub struct PeerNetwork {
     // http endpoint, used for driving HTTP conversations (some of which we initiate)
     pub http: Option<HttpPeer>,
 
+    // how much of their allowance HTTP RPC clients have spent
+    pub rpc_rate_limiter: RPCRateLimiter,
+
     // our own neighbor address that we bind on
     bind_nk: NeighborKey,
 
@@ -368,7 +407,12 @@ impl PeerNetwork {
         epochs: Vec<StacksEpoch>,
     ) -> PeerNetwork {
         let http = HttpPeer::new(connection_opts.clone(), 0);
-        let pub_ip = connection_opts.public_ip_address.clone();
+        let pub_ip = if connection_opts.onion_address.is_some() {
+            // advertise no IP address at all; peers reach us through our onion data URL
+            Some((PeerAddress([0u8; 16]), local_peer.port))
+        } else {
+            connection_opts.public_ip_address.clone()
+        };
         let pub_ip_learned = pub_ip.is_none();
         local_peer.public_ip_address = pub_ip.clone();
 
@@ -404,7 +448,9 @@ impl PeerNetwork {
             sockets: HashMap::new(),
             events: HashMap::new(),
             connecting: HashMap::new(),
+            socks_handshakes: HashMap::new(),
             bans: HashSet::new(),
+            peer_penalties: HashMap::new(),
 
             relay_handles: HashMap::new(),
             relayer_stats: RelayerStats::new(),
@@ -444,10 +490,16 @@ impl PeerNetwork {
             mempool_sync_completions: 0,
             mempool_sync_txs: 0,
 
+            dns_seeds: vec![],
+            dns_seed_pending: HashSet::new(),
+            dns_seed_addrs: HashMap::new(),
+            dns_seed_last_refresh: 0,
+
             prune_outbound_counts: HashMap::new(),
             prune_inbound_counts: HashMap::new(),
 
             http: Some(http),
+            rpc_rate_limiter: RPCRateLimiter::new(),
             bind_nk: NeighborKey {
                 network_id: 0,
                 peer_version: 0,
@@ -513,8 +565,9 @@ impl PeerNetwork {
     pub fn bind(&mut self, my_addr: &SocketAddr, http_addr: &SocketAddr) -> Result<(), net_error> {
         let mut net = NetworkState::new(self.connection_opts.max_sockets)?;
 
-        let p2p_handle = net.bind(my_addr)?;
-        let http_handle = net.bind(http_addr)?;
+        let ipv6_only = self.connection_opts.address_family == AddressFamily::IPv6Only;
+        let p2p_handle = net.bind_with_options(my_addr, ipv6_only)?;
+        let http_handle = net.bind_with_options(http_addr, ipv6_only)?;
 
         test_debug!(
             "{:?}: bound on p2p {:?}, http {:?}",
@@ -849,6 +902,18 @@ impl PeerNetwork {
     ) -> Result<usize, net_error> {
         debug!("{:?}: connect to {:?}", &self.local_peer, neighbor);
 
+        if !self
+            .connection_opts
+            .address_family
+            .allows(&neighbor.addrbytes)
+        {
+            debug!(
+                "{:?}: will not connect to {:?} over its address family",
+                &self.local_peer, neighbor
+            );
+            return Err(net_error::Denied);
+        }
+
         if check_denied {
             // don't talk to our bind address
             if self.is_bound(neighbor) {
@@ -889,13 +954,23 @@ impl PeerNetwork {
                 return Err(net_error::NotConnected);
             }
             Some(ref mut network) => {
-                let sock = NetworkState::connect(&neighbor.addrbytes.to_socketaddr(neighbor.port))?;
+                let addr = neighbor.addrbytes.to_socketaddr(neighbor.port);
+                let (sock, handshake_opt) = match self.connection_opts.socks_proxy {
+                    Some(ref proxy) => (
+                        NetworkState::connect(proxy)?,
+                        Some(SocksHandshake::new(SocksTarget::Addr(addr))?),
+                    ),
+                    None => (NetworkState::connect(&addr)?, None),
+                };
                 let hint_event_id = network.next_event_id()?;
                 let registered_event_id =
                     network.register(self.p2p_network_handle, hint_event_id, &sock)?;
 
                 self.connecting
                     .insert(registered_event_id, (sock, true, get_epoch_time_secs()));
+                if let Some(handshake) = handshake_opt {
+                    self.socks_handshakes.insert(registered_event_id, handshake);
+                }
                 registered_event_id
             }
         };
@@ -1037,6 +1112,7 @@ impl PeerNetwork {
                         }
                         None => {}
                     }
+                    self.penalize_peer(neighbor_key, PeerMisbehavior::InvalidMessage);
                 }
                 Ok(())
             }
@@ -1092,6 +1168,48 @@ impl PeerNetwork {
                 self.broadcast_message(neighbor_keys, relay_hints, msg);
                 Ok(())
             }
+            NetworkRequest::BroadcastTransaction(relay_hints, tx, fee_rate) => {
+                let mut neighbor_keys = self.sample_broadcast_peers(&relay_hints, &tx)?;
+                neighbor_keys.retain(|nk| {
+                    let peer_min_tx_fee_rate = self
+                        .events
+                        .get(nk)
+                        .and_then(|event_id| self.peers.get(event_id))
+                        .map(|convo| convo.peer_min_tx_fee_rate)
+                        .unwrap_or(0f64);
+                    if fee_rate < peer_min_tx_fee_rate {
+                        debug!(
+                            "{:?}: Do not broadcast tx {} to {:?}: fee rate {} is below its minimum {}",
+                            &self.local_peer,
+                            &tx.txid(),
+                            nk,
+                            fee_rate,
+                            peer_min_tx_fee_rate
+                        );
+                        return false;
+                    }
+                    true
+                });
+                self.broadcast_message(
+                    neighbor_keys,
+                    relay_hints,
+                    StacksMessageType::Transaction(tx),
+                );
+                Ok(())
+            }
+            NetworkRequest::SetMinTxFeeRate(min_tx_fee_rate) => {
+                self.set_min_tx_fee_rate(min_tx_fee_rate);
+                Ok(())
+            }
+        }
+    }
+
+    /// Change the minimum fee rate at which we accept transactions.  Neighbors that understand
+    /// it will be told about it on the next pass of the network state machine.
+    pub fn set_min_tx_fee_rate(&mut self, min_tx_fee_rate: f64) {
+        self.connection_opts.min_tx_fee_rate = min_tx_fee_rate;
+        for (_, convo) in self.peers.iter_mut() {
+            convo.connection.options.min_tx_fee_rate = min_tx_fee_rate;
         }
     }
 
@@ -1315,6 +1433,19 @@ impl PeerNetwork {
             return Err(net_error::Denied);
         }
 
+        // wrong address family?
+        if !self
+            .connection_opts
+            .address_family
+            .allows(&neighbor_key.addrbytes)
+        {
+            debug!(
+                "{:?}: Peer {:?} is not in an allowed address family; dropping",
+                &self.local_peer, neighbor_key
+            );
+            return Err(net_error::Denied);
+        }
+
         // denied?
         if PeerDB::is_peer_denied(
             &self.peerdb.conn(),
@@ -1408,7 +1539,18 @@ impl PeerNetwork {
                 return Err(net_error::SocketError);
             }
         };
+        self.register_peer_at(event_id, socket, outbound, client_addr)
+    }
 
+    /// Register a socket/event pair whose remote peer is at client_addr.  This is not
+    /// necessarily the socket's peer address, such as when the socket goes through a proxy.
+    fn register_peer_at(
+        &mut self,
+        event_id: usize,
+        socket: mio_net::TcpStream,
+        outbound: bool,
+        client_addr: SocketAddr,
+    ) -> Result<(), net_error> {
         let neighbor_opt = match self.lookup_peer(self.chain_view.burn_block_height, &client_addr) {
             Ok(neighbor_opt) => neighbor_opt,
             Err(e) => {
@@ -1553,6 +1695,8 @@ impl PeerNetwork {
             }
         }
 
+        self.socks_handshakes.remove(&event_id);
+
         self.relay_handles.remove(&event_id);
         self.peers.remove(&event_id);
         self.pending_messages.remove(&event_id);
@@ -1570,6 +1714,65 @@ impl PeerNetwork {
         self.deregister_peer(event_id);
     }
 
+    /// Lower a neighbor's reputation score for misbehaving.  A neighbor that is already being
+    /// banned is left alone.  The penalty is written to the peer DB, along with all the others
+    /// incurred since, by the next call to `process_peer_penalties()`.
+    pub fn penalize_peer(&mut self, neighbor: &NeighborKey, misbehavior: PeerMisbehavior) -> () {
+        if let Some(event_id) = self.events.get(neighbor) {
+            if self.bans.contains(event_id) {
+                return;
+            }
+        }
+        debug!(
+            "{:?}: Neighbor {:?} misbehaved ({})",
+            &self.local_peer,
+            neighbor,
+            misbehavior.as_str()
+        );
+        let penalty = self.peer_penalties.entry(neighbor.clone()).or_insert(0);
+        *penalty = penalty.saturating_add(misbehavior.penalty());
+    }
+
+    /// Write the pending reputation score penalties to the peer DB in one transaction, and ban
+    /// the connected neighbors whose score drops to the ban score or below.
+    fn process_peer_penalties(&mut self) -> Result<(), net_error> {
+        if self.peer_penalties.is_empty() {
+            return Ok(());
+        }
+        let now = get_epoch_time_secs();
+        let mut tx = self.peerdb.tx_begin()?;
+        let mut scores = vec![];
+        for (neighbor, penalty) in self.peer_penalties.drain() {
+            let score = PeerDB::adjust_peer_score(
+                &mut tx,
+                neighbor.network_id,
+                &neighbor.addrbytes,
+                neighbor.port,
+                -penalty,
+                now,
+            )?;
+            scores.push((neighbor, score));
+        }
+        tx.commit()?;
+
+        for (neighbor, score) in scores.into_iter() {
+            debug!(
+                "{:?}: Neighbor {:?} score is now {}",
+                &self.local_peer, &neighbor, score
+            );
+            if score <= self.connection_opts.peer_ban_score {
+                if let Some(event_id) = self.events.get(&neighbor) {
+                    info!(
+                        "{:?}: Will ban {:?}: its score {} is at or below {}",
+                        &self.local_peer, &neighbor, score, self.connection_opts.peer_ban_score
+                    );
+                    self.bans.insert(*event_id);
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Deregister and ban a neighbor
     pub fn deregister_and_ban_neighbor(&mut self, neighbor: &NeighborKey) -> () {
         debug!("Disconnect from and ban {:?}", neighbor);
@@ -1579,6 +1782,7 @@ impl PeerNetwork {
             }
             None => {}
         }
+        self.penalize_peer(neighbor, PeerMisbehavior::ProtocolViolation);
 
         self.relayer_stats.process_neighbor_ban(neighbor);
         self.deregister_neighbor(neighbor);
@@ -1750,9 +1954,40 @@ impl PeerNetwork {
     fn process_connecting_sockets(&mut self, poll_state: &mut NetworkPollState) -> () {
         for event_id in poll_state.ready.iter() {
             if self.connecting.contains_key(event_id) {
+                let target_opt = match self.socks_handshakes.get_mut(event_id) {
+                    Some(handshake) => {
+                        let (socket, ..) = self.connecting.get_mut(event_id).unwrap();
+                        match handshake.step(socket) {
+                            Ok(true) => Some(handshake.target.clone()),
+                            Ok(false) => {
+                                // proxy is still working on it
+                                continue;
+                            }
+                            Err(_e) => {
+                                debug!(
+                                    "{:?}: SOCKS proxy failed to connect event {} to {}: {:?}",
+                                    &self.local_peer, event_id, &handshake.target, &_e
+                                );
+                                self.socks_handshakes.remove(event_id);
+                                let (socket, ..) = self.connecting.remove(event_id).unwrap();
+                                self.deregister_socket(*event_id, socket);
+                                continue;
+                            }
+                        }
+                    }
+                    None => None,
+                };
+                self.socks_handshakes.remove(event_id);
+
                 let (socket, outbound, _) = self.connecting.remove(event_id).unwrap();
                 let sock_str = format!("{:?}", &socket);
-                if let Err(_e) = self.register_peer(*event_id, socket, outbound) {
+                let res = match target_opt {
+                    Some(SocksTarget::Addr(addr)) => {
+                        self.register_peer_at(*event_id, socket, outbound, addr)
+                    }
+                    _ => self.register_peer(*event_id, socket, outbound),
+                };
+                if let Err(_e) = res {
                     debug!(
                         "{:?}: Failed to register connecting socket on event {} ({}): {:?}",
                         &self.local_peer, event_id, sock_str, &_e
@@ -1866,6 +2101,7 @@ impl PeerNetwork {
         }
 
         for dead in walk_result.dead_connections.iter() {
+            self.penalize_peer(dead, PeerMisbehavior::Stall);
             self.deregister_neighbor(dead);
         }
 
@@ -2073,6 +2309,47 @@ impl PeerNetwork {
         }
     }
 
+    /// Re-handshake with each authenticated neighbor that understands fee filters, but has not
+    /// yet been told our current minimum fee rate.
+    fn announce_fee_filters(&mut self) {
+        let mut msgs = HashMap::new();
+        for (event_id, convo) in self.peers.iter_mut() {
+            if !convo.is_authenticated()
+                || !ConversationP2P::supports_fee_filter(convo.peer_services)
+                || convo.announced_min_tx_fee_rate == self.connection_opts.min_tx_fee_rate
+            {
+                continue;
+            }
+            let nk = convo.to_neighbor_key();
+            let mut handshake_data = HandshakeData::from_local_peer(&self.local_peer);
+            handshake_data.min_tx_fee_rate = Some(self.connection_opts.min_tx_fee_rate);
+            let handshake = StacksMessageType::Handshake(handshake_data);
+
+            debug!(
+                "{:?}: send minimum fee rate {} to {:?}",
+                &self.local_peer, self.connection_opts.min_tx_fee_rate, &nk
+            );
+
+            if let Ok(msg) =
+                convo.sign_message(&self.chain_view, &self.local_peer.private_key, handshake)
+            {
+                convo.announced_min_tx_fee_rate = self.connection_opts.min_tx_fee_rate;
+                msgs.insert(nk, (*event_id, msg));
+            }
+        }
+
+        for (nk, (event_id, msg)) in msgs.drain() {
+            match self.send_message(&nk, msg, self.connection_opts.neighbor_request_timeout) {
+                Ok(handle) => {
+                    self.add_relay_handle(event_id, handle);
+                }
+                Err(e) => {
+                    info!("Failed to send minimum fee rate to {:?}: {:?}", &nk, &e);
+                }
+            }
+        }
+    }
+
     /// Flush relayed message handles, but don't block.
     /// Drop broken handles.
     /// Return the list of broken conversation event IDs
@@ -2284,7 +2561,7 @@ impl PeerNetwork {
     }
 
     /// Disconnect from all peers
-    fn disconnect_all(&mut self) -> () {
+    pub fn disconnect_all(&mut self) -> () {
         let mut all_event_ids = vec![];
         for (eid, _) in self.peers.iter() {
             all_event_ids.push(*eid);
@@ -2497,6 +2774,7 @@ impl PeerNetwork {
 
         // disconnect from dead connections
         for dead in dead_neighbors.into_iter() {
+            self.penalize_peer(&dead, PeerMisbehavior::Stall);
             self.deregister_neighbor(&dead);
         }
 
@@ -3221,6 +3499,157 @@ impl PeerNetwork {
         }
     }
 
+    /// Look up our DNS seeds again if it has been `dns_seed_refresh_interval` seconds since we
+    /// last did, and make the addresses they resolve to into bootstrap peers.  A seed that fails
+    /// to resolve keeps the bootstrap peers it resolved to last time.
+    fn do_dns_seed_refresh(&mut self, dns_client_opt: &mut Option<&mut DNSClient>) {
+        if self.dns_seeds.is_empty() {
+            return;
+        }
+        let dns_client = match dns_client_opt {
+            Some(ref mut dns_client) => dns_client,
+            None => {
+                return;
+            }
+        };
+
+        if self.dns_seed_pending.is_empty() {
+            let now = get_epoch_time_secs();
+            if self.dns_seed_last_refresh + self.connection_opts.dns_seed_refresh_interval > now {
+                return;
+            }
+            self.dns_seed_last_refresh = now;
+            for (i, seed) in self.dns_seeds.iter().enumerate() {
+                match dns_client.queue_lookup(
+                    &seed.host,
+                    seed.port,
+                    get_epoch_time_ms() + self.connection_opts.dns_timeout,
+                ) {
+                    Ok(_) => {
+                        self.dns_seed_pending.insert(i);
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Failed to queue DNS lookup on seed {}:{}: {:?}",
+                            &seed.host, seed.port, &e
+                        );
+                    }
+                }
+            }
+            return;
+        }
+
+        if let Err(e) = dns_client.try_recv() {
+            debug!("Failed to receive DNS responses: {:?}", &e);
+        }
+
+        let mut still_pending = HashSet::new();
+        let mut resolved = vec![];
+        for i in self.dns_seed_pending.drain() {
+            let seed = &self.dns_seeds[i];
+            match dns_client.poll_lookup(&seed.host, seed.port) {
+                Ok(Some(dns_response)) => match dns_response.result {
+                    Ok(mut addrs) => {
+                        self.connection_opts
+                            .address_family
+                            .filter(&mut addrs, PeerAddress::from_socketaddr);
+                        if addrs.is_empty() {
+                            warn!(
+                                "DNS seed {}:{} has no addresses in an allowed family; keeping its last-known peers",
+                                &seed.host, seed.port
+                            );
+                            continue;
+                        }
+                        resolved.push((i, addrs));
+                    }
+                    Err(msg) => {
+                        warn!(
+                            "DNS failed to look up seed {}:{}: {}; keeping its last-known peers",
+                            &seed.host, seed.port, msg
+                        );
+                    }
+                },
+                Ok(None) => {
+                    // still in-flight
+                    still_pending.insert(i);
+                }
+                Err(_) => {
+                    // someone else cleared the DNS client's lookups, so ask again
+                    if dns_client
+                        .queue_lookup(
+                            &seed.host,
+                            seed.port,
+                            get_epoch_time_ms() + self.connection_opts.dns_timeout,
+                        )
+                        .is_ok()
+                    {
+                        still_pending.insert(i);
+                    }
+                }
+            }
+        }
+        self.dns_seed_pending = still_pending;
+
+        for (i, addrs) in resolved.into_iter() {
+            if let Err(e) = self.update_dns_seed_peers(i, addrs.into_iter().collect()) {
+                warn!(
+                    "Failed to store peers of DNS seed {}:{}: {:?}",
+                    &self.dns_seeds[i].host, self.dns_seeds[i].port, &e
+                );
+            }
+        }
+    }
+
+    /// Make the addresses that the `i`th DNS seed resolved to into bootstrap peers, and demote
+    /// the ones it no longer resolves to (unless another seed still does).
+    fn update_dns_seed_peers(
+        &mut self,
+        i: usize,
+        addrs: HashSet<SocketAddr>,
+    ) -> Result<(), net_error> {
+        let seed = &self.dns_seeds[i];
+        let network_id = self.local_peer.network_id;
+        let mut tx = self.peerdb.tx_begin()?;
+        for addr in addrs.iter() {
+            let key = NeighborKey {
+                peer_version: self.peer_version,
+                network_id,
+                addrbytes: PeerAddress::from_socketaddr(addr),
+                port: addr.port(),
+            };
+            PeerDB::add_seed_peer(&mut tx, &key, seed.public_key.as_ref())?;
+        }
+        if let Some(old_addrs) = self.dns_seed_addrs.get(&i) {
+            for old_addr in old_addrs.difference(&addrs) {
+                if self
+                    .dns_seed_addrs
+                    .iter()
+                    .any(|(j, other_addrs)| *j != i && other_addrs.contains(old_addr))
+                {
+                    continue;
+                }
+                info!(
+                    "DNS seed {}:{} no longer resolves to {}",
+                    &seed.host, seed.port, old_addr
+                );
+                PeerDB::clear_seed_peer(
+                    &mut tx,
+                    network_id,
+                    &PeerAddress::from_socketaddr(old_addr),
+                    old_addr.port(),
+                )?;
+            }
+        }
+        tx.commit()?;
+
+        debug!(
+            "DNS seed {}:{} resolved to {:?}",
+            &seed.host, seed.port, &addrs
+        );
+        self.dns_seed_addrs.insert(i, addrs);
+        Ok(())
+    }
+
     /// Begin resolving the DNS host of a data URL for mempool sync.
     /// Returns Ok(None) if we're done syncing the mempool.
     /// Returns Ok(Some(..)) if we're not done, and can proceed
@@ -3250,7 +3679,14 @@ impl PeerNetwork {
                 page_id.clone(),
             )));
         } else if let Some(url::Host::Domain(domain)) = url.host() {
-            if let Some(ref mut dns_client) = dns_client_opt {
+            if let Some(proxy) = self.connection_opts.socks_proxy.clone() {
+                // let the proxy resolve it
+                return Ok(Some(MempoolSyncState::SendQuery(
+                    url_str,
+                    proxy,
+                    page_id.clone(),
+                )));
+            } else if let Some(ref mut dns_client) = dns_client_opt {
                 // begin DNS query
                 match dns_client.queue_lookup(
                     domain.clone(),
@@ -3296,7 +3732,10 @@ impl PeerNetwork {
             match dns_client.poll_lookup(&request.host, request.port) {
                 Ok(Some(dns_response)) => match dns_response.result {
                     Ok(mut addrs) => {
-                        if let Some(addr) = addrs.pop() {
+                        self.connection_opts
+                            .address_family
+                            .filter(&mut addrs, PeerAddress::from_socketaddr);
+                        if let Some(addr) = addrs.into_iter().next() {
                             // resolved!
                             return Ok((false, Some(addr)));
                         } else {
@@ -3340,7 +3779,7 @@ impl PeerNetwork {
         let sync_data = mempool.make_mempool_sync_data()?;
         let request = HttpRequestType::MemPoolQuery(
             HttpRequestMetadata::from_host(
-                PeerHost::from_socketaddr(addr),
+                PeerHost::try_from_url(url).unwrap_or_else(|| PeerHost::from_socketaddr(addr)),
                 Some(self.burnchain_tip.canonical_stacks_tip_height),
             ),
             sync_data,
@@ -4104,6 +4543,7 @@ impl PeerNetwork {
                         if let Some(outbound_event_id) = self.events.get(&outbound_neighbor_key) {
                             self.bans.insert(*outbound_event_id);
                         }
+                        self.penalize_peer(outbound_neighbor_key, PeerMisbehavior::InvalidMessage);
                         return Ok(None);
                     }
                     Err(e) => {
@@ -5039,6 +5479,12 @@ impl PeerNetwork {
             ibd,
             network_result,
         );
+        if let Err(e) = self.process_peer_penalties() {
+            warn!(
+                "{:?}: Failed to update reputation scores: {:?}",
+                &self.local_peer, &e
+            );
+        }
         if do_prune {
             // prune back our connections if it's been a while
             // (only do this if we're done with all other tasks).
@@ -5055,6 +5501,9 @@ impl PeerNetwork {
             self.prune_connections();
         }
 
+        // Keep the bootstrap peers behind our DNS seeds up-to-date
+        self.do_dns_seed_refresh(&mut dns_client_opt);
+
         // In parallel, do a neighbor walk
         self.do_network_neighbor_walk(ibd);
 
@@ -5114,6 +5563,9 @@ impl PeerNetwork {
         // do this after processing new sockets, so we don't accidentally re-use an event ID.
         self.dispatch_requests();
 
+        // tell neighbors about our minimum fee rate, if it is new to them
+        self.announce_fee_filters();
+
         let outbound_neighbors = PeerNetwork::count_outbound_conversations(&self.peers);
         let inbound_neighbors = self.peers.len() - outbound_neighbors as usize;
         update_outbound_neighbors(outbound_neighbors as i64);
@@ -5535,6 +5987,37 @@ mod test {
     }
 
     // tests relay_signed_message()
+    #[test]
+    fn test_peer_penalties() {
+        let neighbor = make_test_neighbor(2301);
+        let nk = neighbor.addr.clone();
+        let mut p2p = make_test_p2p_network(&vec![]);
+        let score = |p2p: &PeerNetwork| {
+            PeerDB::get_peer_score(
+                p2p.peerdb.conn(),
+                nk.network_id,
+                &nk.addrbytes,
+                nk.port,
+                get_epoch_time_secs(),
+            )
+            .unwrap()
+        };
+
+        // penalties are only written out when processed
+        p2p.penalize_peer(&nk, PeerMisbehavior::Stall);
+        p2p.penalize_peer(&nk, PeerMisbehavior::InvalidMessage);
+        assert_eq!(score(&p2p), 0);
+        p2p.process_peer_penalties().unwrap();
+        assert_eq!(score(&p2p), -55);
+        assert!(p2p.peer_penalties.is_empty());
+
+        // a neighbor that is being banned is not scored
+        p2p.events.insert(nk.clone(), 1);
+        p2p.bans.insert(1);
+        p2p.penalize_peer(&nk, PeerMisbehavior::InvalidMessage);
+        assert!(p2p.peer_penalties.is_empty());
+    }
+
     #[test]
     #[ignore]
     fn test_dispatch_requests_connect_and_message_relay() {
@@ -6391,4 +6874,75 @@ mod test {
             }
         });
     }
+
+    #[test]
+    fn test_dns_seed_refresh() {
+        let mut peer_config = TestPeerConfig::new("test_dns_seed_refresh", 2230, 2231);
+        peer_config.connection_opts.dns_seed_refresh_interval = 3600;
+        let mut peer = TestPeer::new(peer_config);
+
+        let seed_addrs: Vec<SocketAddr> = vec![
+            "1.2.3.4:20444".parse().unwrap(),
+            "5.6.7.8:20444".parse().unwrap(),
+        ];
+        let (mut resolver, mut dns_client) = DNSResolver::new(10);
+        resolver.add_hardcoded("seed.test", 20444, seed_addrs.clone());
+        let dns_thread_handle = thread::spawn(move || {
+            resolver.thread_main();
+        });
+
+        peer.network.dns_seeds = vec![DNSSeed {
+            host: "seed.test".to_string(),
+            port: 20444,
+            public_key: None,
+        }];
+
+        let network_id = peer.config.network_id;
+        let mut bootstrap_peers = vec![];
+        for _ in 0..100 {
+            let _ = peer.step_dns(&mut dns_client);
+            bootstrap_peers =
+                PeerDB::get_bootstrap_peers(peer.network.peerdb.conn(), network_id).unwrap();
+            if bootstrap_peers.len() == 2 {
+                break;
+            }
+            sleep_ms(100);
+        }
+        assert_eq!(bootstrap_peers.len(), 2);
+        for addr in seed_addrs.iter() {
+            assert!(bootstrap_peers
+                .iter()
+                .any(
+                    |neighbor| neighbor.addr.addrbytes == PeerAddress::from_socketaddr(addr)
+                        && neighbor.allowed < 0
+                ));
+        }
+
+        // not looked up again until the refresh interval passes
+        assert!(peer.network.dns_seed_pending.is_empty());
+        peer.step_dns(&mut dns_client).unwrap();
+        assert!(peer.network.dns_seed_pending.is_empty());
+
+        // the seed moves
+        let new_addr: SocketAddr = "9.10.11.12:20444".parse().unwrap();
+        peer.network
+            .update_dns_seed_peers(
+                0,
+                vec![seed_addrs[0].clone(), new_addr.clone()]
+                    .into_iter()
+                    .collect(),
+            )
+            .unwrap();
+        let bootstrap_peers =
+            PeerDB::get_bootstrap_peers(peer.network.peerdb.conn(), network_id).unwrap();
+        assert_eq!(bootstrap_peers.len(), 2);
+        assert!(!bootstrap_peers.iter().any(
+            |neighbor| neighbor.addr.addrbytes == PeerAddress::from_socketaddr(&seed_addrs[1])
+        ));
+        assert!(bootstrap_peers
+            .iter()
+            .any(|neighbor| neighbor.addr.addrbytes == PeerAddress::from_socketaddr(&new_addr)));
+
+        dns_thread_shutdown(dns_client, dns_thread_handle);
+    }
 }
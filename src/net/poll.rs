@@ -48,6 +48,8 @@ use std::net::Shutdown;
 use rand;
 use rand::RngCore;
 
+use socket2::{Domain, Protocol, Socket, Type};
+
 const SERVER: Token = mio::Token(0);
 
 pub struct NetworkPollState {
@@ -106,9 +108,28 @@ impl NetworkState {
         self.event_map.len()
     }
 
-    fn bind_address(addr: &SocketAddr) -> Result<mio_net::TcpListener, net_error> {
+    /// Make a listening socket on `addr`.  A socket on an IPv6 address also accepts IPv4
+    /// connections, which show up with IPv4-mapped addresses, unless `ipv6_only` is set.
+    fn listen(addr: &SocketAddr, ipv6_only: bool) -> Result<mio_net::TcpListener, io::Error> {
+        let socket = Socket::new(
+            Domain::for_address(*addr),
+            Type::STREAM,
+            Some(Protocol::TCP),
+        )?;
+        if addr.is_ipv6() {
+            socket.set_only_v6(ipv6_only)?;
+        }
+        if cfg!(unix) {
+            socket.set_reuse_address(true)?;
+        }
+        socket.bind(&(*addr).into())?;
+        socket.listen(1024)?;
+        mio_net::TcpListener::from_std(socket.into())
+    }
+
+    fn bind_address(addr: &SocketAddr, ipv6_only: bool) -> Result<mio_net::TcpListener, net_error> {
         if !cfg!(test) {
-            mio_net::TcpListener::bind(addr).map_err(|e| {
+            NetworkState::listen(addr, ipv6_only).map_err(|e| {
                 error!("Failed to bind to {:?}: {:?}", addr, e);
                 net_error::BindError
             })
@@ -117,7 +138,7 @@ impl NetworkState {
             let mut rng = rand::thread_rng();
             let mut count = 1000;
             loop {
-                match mio_net::TcpListener::bind(addr) {
+                match NetworkState::listen(addr, ipv6_only) {
                     Ok(server) => {
                         return Ok(server);
                     }
@@ -145,7 +166,18 @@ impl NetworkState {
     /// Bind to the given socket address.
     /// Returns the handle to the poll state, used to key network poll events.
     pub fn bind(&mut self, addr: &SocketAddr) -> Result<usize, net_error> {
-        let server = NetworkState::bind_address(addr)?;
+        self.bind_with_options(addr, false)
+    }
+
+    /// Bind to the given socket address, which, if it is an IPv6 address, only accepts IPv6
+    /// connections if `ipv6_only` is set.
+    /// Returns the handle to the poll state, used to key network poll events.
+    pub fn bind_with_options(
+        &mut self,
+        addr: &SocketAddr,
+        ipv6_only: bool,
+    ) -> Result<usize, net_error> {
+        let server = NetworkState::bind_address(addr, ipv6_only)?;
         let next_server_event = self.next_event_id()?;
 
         self.poll
@@ -488,6 +520,28 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_bind_dual_stack() {
+        if net::TcpListener::bind("[::1]:0").is_err() {
+            eprintln!("IPv6 is not available; skipping test_bind_dual_stack");
+            return;
+        }
+
+        let mut ns = NetworkState::new(100).unwrap();
+        let dual_stack_addr = "[::]:0".parse::<SocketAddr>().unwrap();
+        let ipv6_only_addr = "[::]:0".parse::<SocketAddr>().unwrap();
+        ns.bind_with_options(&dual_stack_addr, false).unwrap();
+        ns.bind_with_options(&ipv6_only_addr, true).unwrap();
+        let dual_stack_port = ns.servers[0].server_socket.local_addr().unwrap().port();
+        let ipv6_only_port = ns.servers[1].server_socket.local_addr().unwrap().port();
+
+        // both take IPv6 connections, but only the dual-stack socket takes IPv4 connections
+        assert!(net::TcpStream::connect(("::1", dual_stack_port)).is_ok());
+        assert!(net::TcpStream::connect(("::1", ipv6_only_port)).is_ok());
+        assert!(net::TcpStream::connect(("127.0.0.1", dual_stack_port)).is_ok());
+        assert!(net::TcpStream::connect(("127.0.0.1", ipv6_only_port)).is_err());
+    }
+
     #[test]
     #[ignore]
     fn test_register_deregister() {
@@ -0,0 +1,263 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2022 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Token-bucket rate limiting for the HTTP RPC server.
+//!
+//! Every request has a cost, which is 1 unless `ConnectionOptions::rpc_request_costs` says
+//! otherwise for its route (batched read-only calls cost their route's cost once per call).
+//! Each client IP address has a bucket that refills at `rpc_client_rate_limit` units per second,
+//! up to `rpc_client_burst` units, and each route has a bucket shared by all clients that
+//! refills at `rpc_route_rate_limit` units per second, up to `rpc_route_burst` units.  A request
+//! is only served if both of its buckets can pay for it.  A rate of 0 turns that limit off.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use crate::net::connection::ConnectionOptions;
+use crate::net::HttpRequestType;
+
+/// How often to forget the buckets of clients and routes that have been idle long enough to
+/// refill, in milliseconds.
+const RATE_LIMIT_PRUNE_INTERVAL_MS: u128 = 60_000;
+
+/// The limit that a throttled request ran into
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RPCRateLimit {
+    /// the client IP address has spent its allowance
+    Client,
+    /// all clients together have spent the route's allowance
+    Route,
+}
+
+impl RPCRateLimit {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            RPCRateLimit::Client => "client",
+            RPCRateLimit::Route => "route",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill_ms: u128,
+}
+
+impl TokenBucket {
+    fn new(burst: u64, now_ms: u128) -> TokenBucket {
+        TokenBucket {
+            tokens: burst as f64,
+            last_refill_ms: now_ms,
+        }
+    }
+
+    fn refill(&mut self, rate: u64, burst: u64, now_ms: u128) {
+        let elapsed_ms = now_ms.saturating_sub(self.last_refill_ms);
+        self.tokens =
+            (self.tokens + (rate as f64) * (elapsed_ms as f64) / 1000.0).min(burst as f64);
+        self.last_refill_ms = now_ms;
+    }
+
+    fn is_full(&self, burst: u64) -> bool {
+        self.tokens >= burst as f64
+    }
+}
+
+/// The buckets of all clients and routes of the HTTP RPC server
+#[derive(Debug, Clone, PartialEq)]
+pub struct RPCRateLimiter {
+    clients: HashMap<IpAddr, TokenBucket>,
+    routes: HashMap<&'static str, TokenBucket>,
+    last_prune_ms: u128,
+}
+
+impl RPCRateLimiter {
+    pub fn new() -> RPCRateLimiter {
+        RPCRateLimiter {
+            clients: HashMap::new(),
+            routes: HashMap::new(),
+            last_prune_ms: 0,
+        }
+    }
+
+    /// What it costs to handle `req`.
+    pub fn request_cost(opts: &ConnectionOptions, req: &HttpRequestType) -> u64 {
+        let route_cost = opts
+            .rpc_request_costs
+            .get(req.get_path())
+            .cloned()
+            .unwrap_or(1);
+        match req {
+            HttpRequestType::CallReadOnlyFunctionBatch(_, calls, _) => {
+                route_cost.saturating_mul(calls.len() as u64)
+            }
+            _ => route_cost,
+        }
+    }
+
+    /// Charge `cost` units to `client`'s bucket and to `route`'s bucket, as of `now_ms`.  If
+    /// either bucket cannot pay, nothing is charged and the limit that was hit is returned.
+    /// A cost above a bucket's burst is charged as the burst, so that expensive routes are
+    /// still usable by clients that have been idle.
+    pub fn charge(
+        &mut self,
+        opts: &ConnectionOptions,
+        client: IpAddr,
+        route: &'static str,
+        cost: u64,
+        now_ms: u128,
+    ) -> Result<(), RPCRateLimit> {
+        if now_ms.saturating_sub(self.last_prune_ms) >= RATE_LIMIT_PRUNE_INTERVAL_MS {
+            self.prune(opts, now_ms);
+        }
+
+        let client_cost = cost.min(opts.rpc_client_burst) as f64;
+        let route_cost = cost.min(opts.rpc_route_burst) as f64;
+
+        let client_bucket = if opts.rpc_client_rate_limit > 0 {
+            let bucket = self
+                .clients
+                .entry(client)
+                .or_insert_with(|| TokenBucket::new(opts.rpc_client_burst, now_ms));
+            bucket.refill(opts.rpc_client_rate_limit, opts.rpc_client_burst, now_ms);
+            if bucket.tokens < client_cost {
+                return Err(RPCRateLimit::Client);
+            }
+            Some(bucket)
+        } else {
+            None
+        };
+
+        if opts.rpc_route_rate_limit > 0 {
+            let bucket = self
+                .routes
+                .entry(route)
+                .or_insert_with(|| TokenBucket::new(opts.rpc_route_burst, now_ms));
+            bucket.refill(opts.rpc_route_rate_limit, opts.rpc_route_burst, now_ms);
+            if bucket.tokens < route_cost {
+                return Err(RPCRateLimit::Route);
+            }
+            bucket.tokens -= route_cost;
+        }
+
+        if let Some(bucket) = client_bucket {
+            bucket.tokens -= client_cost;
+        }
+        Ok(())
+    }
+
+    /// Forget the buckets that have refilled, since new buckets start out full anyway.
+    fn prune(&mut self, opts: &ConnectionOptions, now_ms: u128) {
+        self.clients.retain(|_, bucket| {
+            bucket.refill(opts.rpc_client_rate_limit, opts.rpc_client_burst, now_ms);
+            !bucket.is_full(opts.rpc_client_burst)
+        });
+        self.routes.retain(|_, bucket| {
+            bucket.refill(opts.rpc_route_rate_limit, opts.rpc_route_burst, now_ms);
+            !bucket.is_full(opts.rpc_route_burst)
+        });
+        self.last_prune_ms = now_ms;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn make_opts(client_rate: u64, route_rate: u64) -> ConnectionOptions {
+        let mut opts = ConnectionOptions::default();
+        opts.rpc_client_rate_limit = client_rate;
+        opts.rpc_client_burst = 10;
+        opts.rpc_route_rate_limit = route_rate;
+        opts.rpc_route_burst = 15;
+        opts
+    }
+
+    #[test]
+    fn test_rpc_rate_limit_client() {
+        let opts = make_opts(2, 0);
+        let mut limiter = RPCRateLimiter::new();
+        let alice: IpAddr = "1.2.3.4".parse().unwrap();
+        let bob: IpAddr = "5.6.7.8".parse().unwrap();
+
+        for _ in 0..10 {
+            assert_eq!(limiter.charge(&opts, alice, "/v2/info", 1, 1000), Ok(()));
+        }
+        assert_eq!(
+            limiter.charge(&opts, alice, "/v2/info", 1, 1000),
+            Err(RPCRateLimit::Client)
+        );
+        // other clients are not affected
+        assert_eq!(limiter.charge(&opts, bob, "/v2/info", 1, 1000), Ok(()));
+
+        // 2 units per second
+        assert_eq!(limiter.charge(&opts, alice, "/v2/info", 1, 1500), Ok(()));
+        assert_eq!(
+            limiter.charge(&opts, alice, "/v2/info", 1, 1500),
+            Err(RPCRateLimit::Client)
+        );
+
+        // costs above the burst need a full bucket
+        assert_eq!(
+            limiter.charge(&opts, alice, "/v2/info", 100, 5000),
+            Err(RPCRateLimit::Client)
+        );
+        assert_eq!(limiter.charge(&opts, alice, "/v2/info", 100, 6500), Ok(()));
+        assert_eq!(
+            limiter.charge(&opts, alice, "/v2/info", 1, 6500),
+            Err(RPCRateLimit::Client)
+        );
+    }
+
+    #[test]
+    fn test_rpc_rate_limit_route() {
+        let opts = make_opts(1, 1);
+        let mut limiter = RPCRateLimiter::new();
+
+        for i in 0..15u8 {
+            let client = IpAddr::from([10, 0, 0, i]);
+            assert_eq!(limiter.charge(&opts, client, "/v2/info", 1, 1000), Ok(()));
+        }
+        let client = IpAddr::from([10, 0, 0, 100]);
+        assert_eq!(
+            limiter.charge(&opts, client, "/v2/info", 1, 1000),
+            Err(RPCRateLimit::Route)
+        );
+        // the client was not charged for the throttled request
+        assert_eq!(limiter.clients.get(&client).unwrap().tokens, 10.0);
+        assert_eq!(limiter.charge(&opts, client, "/v2/pox", 1, 1000), Ok(()));
+
+        // idle buckets are forgotten
+        limiter
+            .charge(&opts, client, "/v2/pox", 1, 100_000)
+            .unwrap();
+        assert_eq!(limiter.clients.len(), 1);
+        assert_eq!(limiter.routes.len(), 1);
+    }
+
+    #[test]
+    fn test_rpc_rate_limit_disabled() {
+        let opts = make_opts(0, 0);
+        let mut limiter = RPCRateLimiter::new();
+        let client: IpAddr = "1.2.3.4".parse().unwrap();
+        for _ in 0..1000 {
+            assert_eq!(limiter.charge(&opts, client, "/v2/info", 5, 1000), Ok(()));
+        }
+        assert!(limiter.clients.is_empty());
+        assert!(limiter.routes.is_empty());
+    }
+}
@@ -60,7 +60,7 @@ use stacks_common::util::hash::Sha512Trunc256Sum;
 
 use crate::chainstate::coordinator::BlockEventDispatcher;
 use crate::chainstate::stacks::db::unconfirmed::ProcessedUnconfirmedState;
-use crate::monitoring::update_stacks_tip_height;
+use crate::monitoring::{log_block_announced, update_stacks_tip_height};
 use crate::types::chainstate::{PoxId, SortitionId};
 use stacks_common::codec::MAX_PAYLOAD_LEN;
 use stacks_common::types::chainstate::BurnchainHeaderHash;
@@ -454,6 +454,11 @@ impl Relayer {
         Relayer::new(handle)
     }
 
+    /// Have the p2p thread announce a new minimum fee rate to our neighbors.
+    pub fn set_min_tx_fee_rate(&mut self, min_tx_fee_rate: f64) -> Result<(), net_error> {
+        self.p2p.set_min_tx_fee_rate(min_tx_fee_rate)
+    }
+
     /// Given blocks pushed to us, verify that they correspond to expected block data.
     pub fn validate_blocks_push(
         conn: &SortitionDBConn,
@@ -1490,6 +1495,7 @@ impl Relayer {
         consensus_hash: ConsensusHash,
         block: StacksBlock,
     ) -> Result<(), net_error> {
+        log_block_announced(&consensus_hash);
         let blocks_data = BlocksData {
             blocks: vec![BlocksDatum(consensus_hash, block)],
         };
@@ -1704,8 +1710,18 @@ impl Relayer {
             for (relayers, tx) in new_txs.into_iter() {
                 debug!("{:?}: Broadcast tx {}", &_local_peer, &tx.txid());
                 mempool_txs_added.push(tx.clone());
-                let msg = StacksMessageType::Transaction(tx);
-                if let Err(e) = self.p2p.broadcast_message(relayers, msg) {
+                let fee_rate = match mempool.get_admission_fee_rate(&tx) {
+                    Ok(fee_rate) => fee_rate,
+                    Err(e) => {
+                        warn!(
+                            "Failed to load fee rate of tx {}, so not broadcasting it: {:?}",
+                            &tx.txid(),
+                            &e
+                        );
+                        continue;
+                    }
+                };
+                if let Err(e) = self.p2p.broadcast_transaction(relayers, tx, fee_rate) {
                     warn!("Failed to broadcast transaction: {:?}", &e);
                 }
             }
@@ -2040,6 +2056,12 @@ impl PeerNetwork {
         let num_inbound = inbound_recipients.len();
         let num_outbound = outbound_recipients.len();
 
+        if num_inbound + num_outbound > 0 {
+            for (_, consensus_hash) in availability_data.values() {
+                log_block_announced(consensus_hash);
+            }
+        }
+
         for recipient in outbound_recipients.drain(..) {
             debug!(
                 "{:?}: Advertize {} blocks to outbound peer {}",
@@ -0,0 +1,91 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2022 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Peer reputation.
+//!
+//! Every peer starts with a score of 0, which drops each time the peer misbehaves and recovers
+//! by one point every `PEER_SCORE_RECOVERY_INTERVAL` seconds.  Scores are kept in the peer DB,
+//! so they survive restarts.  A peer whose score falls to `ConnectionOptions::peer_ban_score`
+//! or below is banned, just like a peer that gets banned outright for a single offense.
+
+use std::cmp;
+
+/// How many seconds it takes for a peer's score to recover by one point.
+pub const PEER_SCORE_RECOVERY_INTERVAL: u64 = 60;
+
+/// Ways in which a peer can hurt its score
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PeerMisbehavior {
+    /// the peer sent us a message that failed validation
+    InvalidMessage,
+    /// the peer let a request go unanswered until it timed out or its connection died
+    Stall,
+    /// the peer broke the protocol, such as by claiming to have data that it then did not serve
+    ProtocolViolation,
+}
+
+impl PeerMisbehavior {
+    /// How many points the misbehavior costs
+    pub fn penalty(&self) -> i64 {
+        match *self {
+            PeerMisbehavior::InvalidMessage => 50,
+            PeerMisbehavior::Stall => 5,
+            PeerMisbehavior::ProtocolViolation => 25,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            PeerMisbehavior::InvalidMessage => "invalid-message",
+            PeerMisbehavior::Stall => "stall",
+            PeerMisbehavior::ProtocolViolation => "protocol-violation",
+        }
+    }
+}
+
+/// What a peer's score is at `now`, if it was `score` at `last_update`.
+pub fn recovered_score(score: i64, last_update: u64, now: u64) -> i64 {
+    if score >= 0 {
+        return score;
+    }
+    let recovered = now.saturating_sub(last_update) / PEER_SCORE_RECOVERY_INTERVAL;
+    cmp::min(0, score.saturating_add(recovered as i64))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_recovered_score() {
+        assert_eq!(recovered_score(0, 1000, 5000), 0);
+        assert_eq!(recovered_score(-10, 1000, 1000), -10);
+        assert_eq!(
+            recovered_score(-10, 1000, 1000 + PEER_SCORE_RECOVERY_INTERVAL - 1),
+            -10
+        );
+        assert_eq!(
+            recovered_score(-10, 1000, 1000 + 3 * PEER_SCORE_RECOVERY_INTERVAL),
+            -7
+        );
+        assert_eq!(
+            recovered_score(-10, 1000, 1000 + 100 * PEER_SCORE_RECOVERY_INTERVAL),
+            0
+        );
+        // clocks that go backwards do not hurt
+        assert_eq!(recovered_score(-10, 1000, 500), -10);
+    }
+}
@@ -25,7 +25,7 @@ use std::io::prelude::*;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::net::SocketAddr;
 use std::time::Instant;
-use std::{convert::TryFrom, fmt};
+use std::{cmp, convert::TryFrom, fmt};
 
 use rand::prelude::*;
 use rand::thread_rng;
@@ -35,14 +35,19 @@ use crate::burnchains::Burnchain;
 use crate::burnchains::BurnchainView;
 use crate::burnchains::*;
 use crate::chainstate::burn::db::sortdb::SortitionDB;
+use crate::chainstate::burn::operations::{LeaderBlockCommitOp, LeaderKeyRegisterOp};
+use crate::chainstate::burn::BlockSnapshot;
 use crate::chainstate::burn::ConsensusHash;
 use crate::chainstate::stacks::db::blocks::CheckError;
+use crate::chainstate::stacks::db::event_index::AccountEventsQuery;
 use crate::chainstate::stacks::db::{
-    blocks::MINIMUM_TX_FEE_RATE_PER_BYTE, StacksChainState, StreamCursor,
+    blocks::MemPoolRejection, blocks::MINIMUM_TX_FEE_RATE_PER_BYTE, StacksChainState, StreamCursor,
+    TRANSACTION_LOG,
 };
 use crate::chainstate::stacks::Error as chain_error;
 use crate::chainstate::stacks::*;
 use crate::clarity_vm::clarity::ClarityConnection;
+use crate::clarity_vm::clarity::ClarityReadOnlyConnection;
 use crate::codec::StacksMessageCodec;
 use crate::core::mempool::*;
 use crate::cost_estimates::metrics::CostMetric;
@@ -52,11 +57,13 @@ use crate::monitoring;
 use crate::net::atlas::{AtlasDB, Attachment, MAX_ATTACHMENT_INV_PAGES_PER_REQUEST};
 use crate::net::connection::ConnectionHttp;
 use crate::net::connection::ConnectionOptions;
+use crate::net::connection::RPCAccess;
 use crate::net::connection::ReplyHandleHttp;
 use crate::net::db::PeerDB;
 use crate::net::http::*;
 use crate::net::p2p::PeerMap;
 use crate::net::p2p::PeerNetwork;
+use crate::net::rate_limit::RPCRateLimiter;
 use crate::net::relay::Relayer;
 use crate::net::BlocksDatum;
 use crate::net::Error as net_error;
@@ -67,6 +74,7 @@ use crate::net::HttpResponseType;
 use crate::net::MemPoolSyncData;
 use crate::net::MicroblocksData;
 use crate::net::NeighborAddress;
+use crate::net::NeighborKey;
 use crate::net::NeighborsData;
 use crate::net::PeerAddress;
 use crate::net::PeerHost;
@@ -83,16 +91,33 @@ use crate::net::HTTP_REQUEST_ID_RESERVED;
 use crate::net::MAX_HEADERS;
 use crate::net::MAX_NEIGHBORS_DATA_LEN;
 use crate::net::{
-    AccountEntryResponse, AttachmentPage, CallReadOnlyResponse, ContractSrcResponse,
-    DataVarResponse, GetAttachmentResponse, GetAttachmentsInvResponse, MapEntryResponse,
+    AccountEntryResponse, AttachmentPage, CallReadOnlyBatchResponse, CallReadOnlyResponse,
+    ContractInterfaceResponse, ContractSrcResponse, DataVarResponse, GetAttachmentResponse,
+    GetAttachmentsInvResponse, MapEntryResponse, RPCDryRunResponse, RPCMapEntriesPage, RPCMapEntry,
+    ReadOnlyCall,
 };
 use crate::net::{BlocksData, GetIsTraitImplementedResponse};
 use crate::net::{ClientError, TipRequest};
-use crate::net::{RPCNeighbor, RPCNeighborsInfo};
+use crate::net::{
+    RPCAccountEventsPage, RPCFinalizedBlock, RPCFinalizedBlocksPage, FINALIZED_BLOCKS_PAGE_SIZE,
+};
+use crate::net::{
+    RPCAttachmentRefetchResponse, RPCAttachmentsPeer, RPCAttachmentsStatus, RPCMissingAttachment,
+};
+use crate::net::{RPCBlockCommit, RPCBurnOps, RPCLeaderKeyRegister, RPCSortitionInfo};
+use crate::net::{
+    RPCConfirmedTransaction, RPCMempoolTransaction, RPCTransactionStatus, TransactionStatus,
+};
+use crate::net::{RPCLightHeader, RPCLightHeaderBatch};
+use crate::net::{RPCNeighbor, RPCNeighborsInfo, RPCPeerBanResponse};
 use crate::net::{RPCPeerInfoData, RPCPoxInfoData};
+use crate::net::{RPCPoxAnchorBlockInfo, RPCPoxCycleInfoData};
 use crate::util_lib::db::DBConn;
 use crate::util_lib::db::Error as db_error;
+use clarity::vm::coverage::CoverageReporter;
 use clarity::vm::database::clarity_store::make_contract_hash_key;
+use clarity::vm::tracing::{ExecutionTrace, ExecutionTracer};
+use clarity::vm::types::OptionalData;
 use clarity::vm::types::TraitIdentifier;
 use clarity::vm::{
     analysis::errors::CheckErrors,
@@ -106,8 +131,9 @@ use clarity::vm::{
     errors::Error::Unchecked,
     errors::InterpreterError,
     types::{PrincipalData, QualifiedContractIdentifier, StandardPrincipalData},
-    ClarityName, ContractName, SymbolicExpression, Value,
+    ClarityName, ContractName, SymbolicExpression, Value, CLARITY_VERSION,
 };
+use stacks_common::util::get_epoch_time_ms;
 use stacks_common::util::get_epoch_time_secs;
 use stacks_common::util::hash::Hash160;
 use stacks_common::util::hash::{hex_bytes, to_hex};
@@ -129,6 +155,10 @@ use super::{RPCPoxCurrentCycleInfo, RPCPoxNextCycleInfo};
 
 pub const STREAM_CHUNK_SIZE: u64 = 4096;
 
+/// Maximum length of the warning sent with a transaction that was admitted to the mempool, but
+/// that will abort
+pub const MAX_ADMISSION_WARNING_LEN: usize = 512;
+
 #[derive(Default)]
 pub struct RPCHandlerArgs<'a> {
     pub exit_at_block_height: Option<&'a u64>,
@@ -255,6 +285,7 @@ impl RPCPeerInfoData {
             genesis_chainstate_hash: genesis_chainstate_hash.clone(),
             node_public_key: Some(public_key_buf),
             node_public_key_hash: Some(public_key_hash),
+            sync_progress: monitoring::get_sync_progress(),
         }
     }
 }
@@ -435,6 +466,159 @@ impl RPCPoxInfoData {
     }
 }
 
+impl RPCPoxCycleInfoData {
+    /// Project the reward set of `reward_cycle` from the PoX contract's state at `tip`, the same
+    /// way the coordinator computes it when the reward cycle begins, and look up the cycle's
+    /// anchor block in the canonical sortition history.
+    pub fn from_db(
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        burnchain: &Burnchain,
+        reward_cycle: u64,
+        pox_address: Option<&StacksAddress>,
+    ) -> Result<RPCPoxCycleInfoData, net_error> {
+        use crate::chainstate::stacks::address::StacksAddressExtensions;
+
+        let pox_consts = &burnchain.pox_constants;
+        let reward_phase_start_block_height = burnchain.reward_cycle_to_block_height(reward_cycle);
+        let prepare_phase_end_block_height = reward_phase_start_block_height - 1;
+
+        let liquid_ustx = chainstate
+            .maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                clarity_tx.with_clarity_db_readonly(|clarity_db| clarity_db.get_total_liquid_ustx())
+            })
+            .map_err(|_| net_error::NotFoundError)?
+            .ok_or(net_error::NotFoundError)?;
+
+        let is_pox_active = chainstate.is_pox_active(sortdb, tip, reward_cycle as u128)?;
+        let total_stacked_ustx =
+            chainstate.get_total_ustx_stacked(sortdb, tip, reward_cycle as u128)?;
+        let registered_addrs = chainstate.get_reward_addresses(
+            burnchain,
+            sortdb,
+            reward_phase_start_block_height,
+            tip,
+        )?;
+
+        let participation = registered_addrs
+            .iter()
+            .fold(0u128, |agg, (_, stacked_amt)| agg + stacked_amt);
+        let threshold = StacksChainState::get_threshold_from_participation(
+            liquid_ustx,
+            participation,
+            pox_consts.reward_slots() as u128,
+        );
+
+        // an address's slots are computed over the sum of everything stacked to it
+        let mut stacked_per_address: HashMap<StacksAddress, u128> = HashMap::new();
+        for (address, stacked_amt) in registered_addrs.into_iter() {
+            *stacked_per_address.entry(address).or_insert(0) += stacked_amt;
+        }
+
+        // without enough participation, the reward cycle defaults to burning
+        let enough_participation = pox_consts.enough_participation(participation, liquid_ustx);
+        let slots_for = |stacked_amt: u128| {
+            if enough_participation && threshold > 0 {
+                (stacked_amt / threshold) as u64
+            } else {
+                0
+            }
+        };
+        let reward_set_size: u64 = stacked_per_address
+            .values()
+            .map(|stacked_amt| slots_for(*stacked_amt))
+            .sum();
+        let reward_set_addresses = stacked_per_address
+            .values()
+            .filter(|stacked_amt| slots_for(**stacked_amt) > 0)
+            .count() as u64;
+
+        let pox_address_stacked_ustx =
+            pox_address.map(|address| stacked_per_address.get(address).cloned().unwrap_or(0));
+        let pox_address_reward_slots = pox_address_stacked_ustx.map(slots_for);
+
+        let anchor_block = RPCPoxCycleInfoData::get_anchor_block_info(
+            sortdb,
+            chainstate,
+            burnchain,
+            prepare_phase_end_block_height,
+        )?;
+
+        Ok(RPCPoxCycleInfoData {
+            reward_cycle,
+            reward_phase_start_block_height,
+            is_pox_active,
+            total_stacked_ustx: total_stacked_ustx as u64,
+            min_threshold_ustx: threshold as u64,
+            reward_set_size,
+            reward_set_addresses,
+            pox_address: pox_address.map(|address| address.clone().to_b58()),
+            pox_address_stacked_ustx: pox_address_stacked_ustx.map(|ustx| ustx as u64),
+            pox_address_reward_slots,
+            anchor_block,
+        })
+    }
+
+    /// Find the anchor block chosen in the prepare phase that ends at
+    /// `prepare_phase_end_block_height`, on the canonical burnchain fork.
+    fn get_anchor_block_info(
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        burnchain: &Burnchain,
+        prepare_phase_end_block_height: u64,
+    ) -> Result<RPCPoxAnchorBlockInfo, net_error> {
+        let mut anchor_block = RPCPoxAnchorBlockInfo {
+            status: "pending".to_string(),
+            prepare_phase_end_block_height,
+            consensus_hash: None,
+            block_hash: None,
+        };
+
+        let handle = sortdb.index_handle_at_tip();
+        let prepare_end =
+            match handle.get_block_snapshot_by_height(prepare_phase_end_block_height)? {
+                Some(sn) => sn,
+                None => {
+                    // the prepare phase has not ended yet
+                    return Ok(anchor_block);
+                }
+            };
+
+        if prepare_phase_end_block_height + 1 >= burnchain.pox_constants.sunset_end {
+            anchor_block.status = "not_selected".to_string();
+            return Ok(anchor_block);
+        }
+
+        let chosen = handle
+            .get_chosen_pox_anchor(&prepare_end.burn_header_hash, &burnchain.pox_constants)
+            .map_err(|e| {
+                net_error::ChainstateError(format!("Failed to find PoX anchor block: {:?}", &e))
+            })?;
+
+        match chosen {
+            Some((consensus_hash, block_hash)) => {
+                let known = StacksChainState::is_stacks_block_processed(
+                    chainstate.db(),
+                    &consensus_hash,
+                    &block_hash,
+                )?;
+                anchor_block.status = if known {
+                    "selected_and_known".to_string()
+                } else {
+                    "selected_and_unknown".to_string()
+                };
+                anchor_block.consensus_hash = Some(consensus_hash);
+                anchor_block.block_hash = Some(block_hash);
+            }
+            None => {
+                anchor_block.status = "not_selected".to_string();
+            }
+        }
+        Ok(anchor_block)
+    }
+}
+
 impl RPCNeighborsInfo {
     /// Load neighbor address information from the peer network
     pub fn from_p2p(
@@ -454,33 +638,42 @@ impl RPCNeighborsInfo {
         )
         .map_err(net_error::DBError)?;
 
-        let sample: Vec<RPCNeighbor> = neighbor_sample
-            .into_iter()
-            .map(|n| {
-                RPCNeighbor::from_neighbor_key_and_pubkh(
-                    n.addr.clone(),
-                    Hash160::from_node_public_key(&n.public_key),
-                    true,
-                )
-            })
-            .collect();
+        let now = get_epoch_time_secs();
+        let get_score = |nk: &NeighborKey| {
+            PeerDB::get_peer_score(peerdb.conn(), nk.network_id, &nk.addrbytes, nk.port, now)
+                .map_err(net_error::DBError)
+        };
+
+        let mut sample = vec![];
+        for n in neighbor_sample.into_iter() {
+            let score = get_score(&n.addr)?;
+            sample.push(RPCNeighbor::from_neighbor_key_and_pubkh(
+                n.addr.clone(),
+                Hash160::from_node_public_key(&n.public_key),
+                true,
+                score,
+            ));
+        }
 
         let mut inbound = vec![];
         let mut outbound = vec![];
         for (_, convo) in peers.iter() {
             let nk = convo.to_neighbor_key();
             let naddr = convo.to_neighbor_address();
+            let score = get_score(&nk)?;
             if convo.is_outbound() {
                 outbound.push(RPCNeighbor::from_neighbor_key_and_pubkh(
                     nk,
                     naddr.public_key_hash,
                     convo.is_authenticated(),
+                    score,
                 ));
             } else {
                 inbound.push(RPCNeighbor::from_neighbor_key_and_pubkh(
                     nk,
                     naddr.public_key_hash,
                     convo.is_authenticated(),
+                    score,
                 ));
             }
         }
@@ -493,6 +686,289 @@ impl RPCNeighborsInfo {
     }
 }
 
+impl RPCLightHeaderBatch {
+    /// Load up to `count` headers from the chain ending at `tip`, starting at `start_height`,
+    /// along with the sortitions that selected them.  The boot block is never included.
+    /// Returns Ok(None) if `start_height` is above the tip.
+    pub fn from_chainstate(
+        chainstate: &StacksChainState,
+        sortdb: &SortitionDB,
+        tip: &StacksBlockId,
+        start_height: u64,
+        count: u64,
+    ) -> Result<Option<RPCLightHeaderBatch>, chain_error> {
+        let tip_header = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            chainstate.db(),
+            tip,
+        )?
+        .ok_or(chain_error::NoSuchBlockError)?;
+        let tip_height = tip_header.stacks_block_height;
+        if start_height > tip_height {
+            return Ok(None);
+        }
+
+        let mut headers = vec![];
+        let lower_height = cmp::max(start_height, 1);
+        let upper_height = cmp::min(
+            start_height.saturating_add(count).saturating_sub(1),
+            tip_height,
+        );
+        if lower_height <= upper_height {
+            let upper_block_id = chainstate
+                .index_conn()?
+                .get_ancestor_block_hash(upper_height, tip)?
+                .ok_or(chain_error::NoSuchBlockError)?;
+            let upper_header = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                chainstate.db(),
+                &upper_block_id,
+            )?
+            .ok_or(chain_error::NoSuchBlockError)?;
+            let ancestors = StacksChainState::get_ancestors_headers(
+                chainstate.db(),
+                upper_header,
+                lower_height,
+            )?;
+            for header in ancestors.into_iter().rev() {
+                let index_block_hash = header.index_block_hash();
+                let parent_block_id =
+                    StacksChainState::get_parent_block_id(chainstate.db(), &index_block_hash)?
+                        .ok_or(chain_error::NoSuchBlockError)?;
+                let snapshot = SortitionDB::get_block_snapshot_consensus(
+                    sortdb.conn(),
+                    &header.consensus_hash,
+                )?
+                .ok_or(chain_error::NoSuchBlockError)?;
+                headers.push(RPCLightHeader {
+                    height: header.stacks_block_height,
+                    consensus_hash: header.consensus_hash,
+                    state_index_root: header.index_root,
+                    header: header.anchored_header,
+                    parent_block_id,
+                    sortition_id: snapshot.sortition_id,
+                    burn_header_hash: header.burn_header_hash,
+                    burn_header_height: header.burn_header_height,
+                    winning_block_txid: snapshot.winning_block_txid,
+                });
+            }
+        }
+
+        Ok(Some(RPCLightHeaderBatch {
+            tip: *tip,
+            tip_height,
+            headers,
+        }))
+    }
+}
+
+impl RPCFinalizedBlocksPage {
+    /// Load a page of the finalized blocks feed from the chain ending at `tip`.  If no page is
+    /// given, load the page that holds the most recently finalized height.
+    /// Returns Ok(None) if none of the page's heights are finalized yet.
+    pub fn from_chainstate(
+        chainstate: &StacksChainState,
+        tip: &StacksBlockId,
+        confirmations: u64,
+        page: Option<u64>,
+    ) -> Result<Option<RPCFinalizedBlocksPage>, chain_error> {
+        let tip_header = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            chainstate.db(),
+            tip,
+        )?
+        .ok_or(chain_error::NoSuchBlockError)?;
+        let finalized_height = tip_header.stacks_block_height.saturating_sub(confirmations);
+
+        let page_size = FINALIZED_BLOCKS_PAGE_SIZE;
+        let head_page = finalized_height / page_size;
+        let page = page.unwrap_or(head_page);
+        if page > head_page {
+            return Ok(None);
+        }
+        let start_height = page * page_size;
+        let end_height = start_height + page_size - 1;
+        let complete = end_height <= finalized_height;
+
+        let mut blocks = vec![];
+        // the boot block is not listed
+        let lower_height = cmp::max(start_height, 1);
+        let upper_height = cmp::min(end_height, finalized_height);
+        if lower_height <= upper_height {
+            let upper_block_id = chainstate
+                .index_conn()?
+                .get_ancestor_block_hash(upper_height, tip)?
+                .ok_or(chain_error::NoSuchBlockError)?;
+            let upper_header = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                chainstate.db(),
+                &upper_block_id,
+            )?
+            .ok_or(chain_error::NoSuchBlockError)?;
+            let headers = StacksChainState::get_ancestors_headers(
+                chainstate.db(),
+                upper_header,
+                lower_height,
+            )?;
+            for header in headers.into_iter().rev() {
+                let index_block_hash = header.index_block_hash();
+                let parent_block_id =
+                    StacksChainState::get_parent_block_id(chainstate.db(), &index_block_hash)?
+                        .ok_or(chain_error::NoSuchBlockError)?;
+                blocks.push(RPCFinalizedBlock {
+                    height: header.stacks_block_height,
+                    index_block_hash,
+                    block_hash: header.anchored_header.block_hash(),
+                    consensus_hash: header.consensus_hash,
+                    parent_block_id,
+                    burn_header_hash: header.burn_header_hash,
+                    burn_header_height: header.burn_header_height,
+                    burn_header_timestamp: header.burn_header_timestamp,
+                });
+            }
+        }
+
+        Ok(Some(RPCFinalizedBlocksPage {
+            page,
+            page_size,
+            confirmations,
+            finalized_height,
+            complete,
+            prev: if page > 0 {
+                Some(format!("/v2/feed/blocks/{}", page - 1))
+            } else {
+                None
+            },
+            next: if complete {
+                Some(format!("/v2/feed/blocks/{}", page + 1))
+            } else {
+                None
+            },
+            blocks,
+        }))
+    }
+}
+
+impl RPCBlockCommit {
+    pub fn from_op(commit: &LeaderBlockCommitOp) -> RPCBlockCommit {
+        RPCBlockCommit {
+            txid: commit.txid.to_hex(),
+            vtxindex: commit.vtxindex,
+            block_header_hash: commit.block_header_hash.clone(),
+            new_seed: commit.new_seed.to_hex(),
+            parent_block_ptr: commit.parent_block_ptr,
+            parent_vtxindex: commit.parent_vtxindex,
+            key_block_ptr: commit.key_block_ptr,
+            key_vtxindex: commit.key_vtxindex,
+            burn_fee: commit.burn_fee,
+            sunset_burn: commit.sunset_burn,
+            input: format!("{}:{}", commit.input.0, commit.input.1),
+            apparent_sender: commit
+                .apparent_sender
+                .public_keys
+                .iter()
+                .map(|public_key| public_key.to_hex())
+                .collect(),
+            commit_outs: commit
+                .commit_outs
+                .iter()
+                .map(|addr| addr.to_string())
+                .collect(),
+            memo: to_hex(&commit.memo),
+        }
+    }
+}
+
+impl RPCLeaderKeyRegister {
+    pub fn from_op(key: &LeaderKeyRegisterOp) -> RPCLeaderKeyRegister {
+        RPCLeaderKeyRegister {
+            txid: key.txid.to_hex(),
+            vtxindex: key.vtxindex,
+            public_key: key.public_key.to_hex(),
+            address: key.address.to_string(),
+            consensus_hash: key.consensus_hash.clone(),
+            memo: to_hex(&key.memo),
+        }
+    }
+}
+
+impl RPCBurnOps {
+    /// Load the block-commits and leader key registrations of the canonical burnchain block at
+    /// `burn_height`.  Returns Ok(None) if that height is above the canonical burnchain tip.
+    pub fn from_sortdb(
+        sortdb: &SortitionDB,
+        burn_height: u64,
+    ) -> Result<Option<RPCBurnOps>, db_error> {
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
+        if burn_height > tip.block_height {
+            return Ok(None);
+        }
+        let snapshot = match SortitionDB::get_ancestor_snapshot(
+            &sortdb.index_conn(),
+            burn_height,
+            &tip.sortition_id,
+        )? {
+            Some(snapshot) => snapshot,
+            None => return Ok(None),
+        };
+        let block_commits =
+            SortitionDB::get_block_commits_by_block(sortdb.conn(), &snapshot.sortition_id)?;
+        let leader_keys =
+            SortitionDB::get_leader_keys_by_block(sortdb.conn(), &snapshot.sortition_id)?;
+        Ok(Some(RPCBurnOps {
+            burn_block_height: snapshot.block_height,
+            burn_header_hash: snapshot.burn_header_hash.to_hex(),
+            consensus_hash: snapshot.consensus_hash,
+            block_commits: block_commits.iter().map(RPCBlockCommit::from_op).collect(),
+            leader_key_registers: leader_keys
+                .iter()
+                .map(RPCLeaderKeyRegister::from_op)
+                .collect(),
+        }))
+    }
+}
+
+impl RPCSortitionInfo {
+    /// Load the sortition identified by `consensus_hash`, on any burnchain fork.
+    /// Returns Ok(None) if there is no such sortition.
+    pub fn from_sortdb(
+        sortdb: &SortitionDB,
+        consensus_hash: &ConsensusHash,
+    ) -> Result<Option<RPCSortitionInfo>, db_error> {
+        let snapshot =
+            match SortitionDB::get_block_snapshot_consensus(sortdb.conn(), consensus_hash)? {
+                Some(snapshot) => snapshot,
+                None => return Ok(None),
+            };
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
+        let canonical = snapshot.block_height <= tip.block_height
+            && SortitionDB::get_ancestor_snapshot(
+                &sortdb.index_conn(),
+                snapshot.block_height,
+                &tip.sortition_id,
+            )?
+            .map(|ancestor| ancestor.sortition_id == snapshot.sortition_id)
+            .unwrap_or(false);
+        let winning_block_commit = if snapshot.sortition {
+            SortitionDB::get_block_commit(
+                sortdb.conn(),
+                &snapshot.winning_block_txid,
+                &snapshot.sortition_id,
+            )?
+            .as_ref()
+            .map(RPCBlockCommit::from_op)
+        } else {
+            None
+        };
+        Ok(Some(RPCSortitionInfo {
+            burn_block_height: snapshot.block_height,
+            burn_header_hash: snapshot.burn_header_hash.to_hex(),
+            parent_burn_header_hash: snapshot.parent_burn_header_hash.to_hex(),
+            consensus_hash: snapshot.consensus_hash,
+            sortition: snapshot.sortition,
+            total_burn: snapshot.total_burn,
+            canonical,
+            winning_block_commit,
+        }))
+    }
+}
+
 impl ConversationHttp {
     pub fn new(
         peer_addr: SocketAddr,
@@ -620,6 +1096,94 @@ impl ConversationHttp {
         Ok(())
     }
 
+    /// Charge the request to the client's and to the route's rate limits.  If either one has been
+    /// spent, reply with 429.  Returns whether or not the request may be handled.
+    fn handle_rate_limit<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        client: &SocketAddr,
+        rate_limiter: &mut RPCRateLimiter,
+        options: &ConnectionOptions,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<bool, net_error> {
+        let cost = RPCRateLimiter::request_cost(options, req);
+        let limit = match rate_limiter.charge(
+            options,
+            client.ip(),
+            req.get_path(),
+            cost,
+            get_epoch_time_ms(),
+        ) {
+            Ok(()) => {
+                return Ok(true);
+            }
+            Err(limit) => limit,
+        };
+        debug!(
+            "Throttling RPC request for {} from {:?}: {} rate limit reached",
+            req.get_path(),
+            client,
+            limit.as_str()
+        );
+        monitoring::increment_rpc_throttled_counter(req.get_path(), limit.as_str());
+
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let response = HttpResponseType::Error(
+            response_metadata,
+            429,
+            format!("Rate limit reached for {}", req.get_path()),
+        );
+        response.send(http, fd).map(|_| false)
+    }
+
+    /// Check that the client may call the route it requested, per the node's RPC access control.
+    /// If not, reply with 401 if the route needs an API key that the client did not present, or
+    /// 403 if the route is disabled.  CORS preflights and malformed requests are always answered.
+    /// Returns whether or not the request may be handled.
+    fn handle_check_rpc_access<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        options: &ConnectionOptions,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<bool, net_error> {
+        let access = match req {
+            HttpRequestType::OptionsPreflight(..) | HttpRequestType::ClientError(..) => {
+                return Ok(true);
+            }
+            _ => options.get_rpc_access(req.get_path()),
+        };
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let response = match access {
+            RPCAccess::Public => {
+                return Ok(true);
+            }
+            RPCAccess::Authenticated => match req.metadata().auth_token {
+                Some(ref api_key) if options.is_rpc_api_key(api_key) => {
+                    return Ok(true);
+                }
+                _ => HttpResponseType::Unauthorized(
+                    response_metadata,
+                    format!("{} requires an API key", req.get_path()),
+                ),
+            },
+            RPCAccess::Disabled => HttpResponseType::Forbidden(
+                response_metadata,
+                format!("{} is disabled on this node", req.get_path()),
+            ),
+        };
+        debug!(
+            "Refusing RPC request for {} from {:?}: {:?}",
+            req.get_path(),
+            &req.metadata().peer,
+            access
+        );
+        response.send(http, fd).map(|_| false)
+    }
+
     /// Handle a GET peer info.
     /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
     fn handle_getinfo<W: Write>(
@@ -682,86 +1246,293 @@ impl ConversationHttp {
         }
     }
 
-    fn handle_getattachmentsinv<W: Write>(
+    /// Handle a GET for the PoX information of one reward cycle.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_getpoxcycleinfo<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        atlasdb: &AtlasDB,
-        index_block_hash: &StacksBlockId,
-        pages_indexes: &HashSet<u32>,
-        _options: &ConnectionOptions,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        burnchain: &Burnchain,
+        reward_cycle: u64,
+        pox_address: Option<&StacksAddress>,
         canonical_stacks_tip_height: u64,
     ) -> Result<(), net_error> {
-        // We are receiving a list of page indexes with a chain tip hash.
-        // The amount of pages_indexes is capped by MAX_ATTACHMENT_INV_PAGES_PER_REQUEST (8)
-        // Pages sizes are controlled by the constant ATTACHMENTS_INV_PAGE_SIZE (8), which
-        // means that a `GET v2/attachments/inv` request can be requesting for a 64 bit vector
-        // at once.
-        // Since clients can be asking for non-consecutive pages indexes (1, 5_000, 10_000, ...),
-        // we will be handling each page index separately.
-        // We could also add the notion of "budget" so that a client could only get a limited number
-        // of pages when they are spanning over many blocks.
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-        if pages_indexes.len() > MAX_ATTACHMENT_INV_PAGES_PER_REQUEST {
-            let msg = format!(
-                "Number of attachment inv pages is limited by {} per request",
-                MAX_ATTACHMENT_INV_PAGES_PER_REQUEST
-            );
-            warn!("{}", msg);
+
+        let start_height = reward_cycle
+            .checked_mul(burnchain.pox_constants.reward_cycle_length as u64)
+            .and_then(|height| height.checked_add(burnchain.first_block_height + 1));
+        if start_height
+            .map(|height| height > i64::MAX as u64)
+            .unwrap_or(true)
+        {
+            let msg = format!("Reward cycle {} is out of range", reward_cycle);
             let response = HttpResponseType::BadRequest(response_metadata, msg);
-            response.send(http, fd)?;
-            return Ok(());
-        }
-        if pages_indexes.len() == 0 {
-            let msg = format!("Page indexes missing");
-            warn!("{}", msg);
-            let response = HttpResponseType::NotFound(response_metadata, msg.clone());
-            response.send(http, fd)?;
-            return Ok(());
+            return response.send(http, fd);
         }
 
-        let mut pages_indexes = pages_indexes.iter().map(|i| *i).collect::<Vec<u32>>();
-        pages_indexes.sort();
-
-        let mut pages = vec![];
-
-        for page_index in pages_indexes.iter() {
-            match atlasdb.get_attachments_available_at_page_index(*page_index, &index_block_hash) {
-                Ok(inventory) => {
-                    pages.push(AttachmentPage {
-                        inventory,
-                        index: *page_index,
-                    });
-                }
-                Err(e) => {
-                    let msg = format!("Unable to read Atlas DB - {}", e);
-                    warn!("{}", msg);
-                    let response = HttpResponseType::NotFound(response_metadata, msg);
-                    return response.send(http, fd);
-                }
+        match RPCPoxCycleInfoData::from_db(
+            sortdb,
+            chainstate,
+            tip,
+            burnchain,
+            reward_cycle,
+            pox_address,
+        ) {
+            Ok(info) => {
+                let response = HttpResponseType::PoxCycleInfo(response_metadata, info);
+                response.send(http, fd)
+            }
+            Err(net_error::NotFoundError) => {
+                debug!("Chain tip not found during get PoX cycle info: {:?}", req);
+                let response = HttpResponseType::NotFound(
+                    response_metadata,
+                    "Failed to find chain tip".to_string(),
+                );
+                response.send(http, fd)
+            }
+            Err(e) => {
+                warn!("Failed to get PoX cycle info {:?}: {:?}", req, &e);
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to query PoX cycle info".to_string(),
+                );
+                response.send(http, fd)
             }
         }
+    }
 
-        let content = GetAttachmentsInvResponse {
-            block_id: index_block_hash.clone(),
-            pages,
+    /// Handle a GET for the burnchain operations at a burnchain block height, on the canonical
+    /// burnchain fork.
+    fn handle_get_burn_ops<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        burn_height: u64,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+
+        let response = match RPCBurnOps::from_sortdb(sortdb, burn_height) {
+            Ok(Some(burn_ops)) => HttpResponseType::BurnOps(response_metadata, burn_ops),
+            Ok(None) => HttpResponseType::NotFound(
+                response_metadata,
+                format!("No burnchain block at height {}", burn_height),
+            ),
+            Err(e) => {
+                warn!("Failed to load burnchain operations {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to load burnchain operations".to_string(),
+                )
+            }
         };
-        let response = HttpResponseType::GetAttachmentsInv(response_metadata, content);
         response.send(http, fd)
     }
 
-    fn handle_getattachment<W: Write>(
+    /// Handle a GET for the sortition with a given consensus hash.
+    fn handle_get_sortition<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        atlasdb: &mut AtlasDB,
-        content_hash: Hash160,
+        sortdb: &SortitionDB,
+        consensus_hash: &ConsensusHash,
         canonical_stacks_tip_height: u64,
     ) -> Result<(), net_error> {
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
-        match atlasdb.find_attachment(&content_hash) {
+
+        let response = match RPCSortitionInfo::from_sortdb(sortdb, consensus_hash) {
+            Ok(Some(sortition)) => HttpResponseType::Sortition(response_metadata, sortition),
+            Ok(None) => HttpResponseType::NotFound(
+                response_metadata,
+                format!("No sortition with consensus hash {}", consensus_hash),
+            ),
+            Err(e) => {
+                warn!("Failed to load sortition {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to load sortition".to_string(),
+                )
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// Handle a GET on the finalized blocks feed, as of the canonical chain tip.
+    fn handle_get_finalized_blocks<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        chainstate: &StacksChainState,
+        tip: &StacksBlockId,
+        page: Option<u64>,
+        options: &ConnectionOptions,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+
+        let response = match RPCFinalizedBlocksPage::from_chainstate(
+            chainstate,
+            tip,
+            options.finalized_block_confirmations,
+            page,
+        ) {
+            Ok(Some(feed_page)) => HttpResponseType::FinalizedBlocks(response_metadata, feed_page),
+            Ok(None) => HttpResponseType::NotFound(
+                response_metadata,
+                format!(
+                    "Feed page {} has no finalized blocks yet",
+                    page.unwrap_or(0)
+                ),
+            ),
+            Err(e) => {
+                warn!("Failed to load finalized blocks {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to load finalized blocks".to_string(),
+                )
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// Handle a GET on an account's or a contract's events, as of the given chain tip.
+    fn handle_get_account_events<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        chainstate: &StacksChainState,
+        tip: &StacksBlockId,
+        principal: &PrincipalData,
+        query: &AccountEventsQuery,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+
+        let response = match chainstate.has_account_event_index() {
+            Ok(true) => match chainstate.get_account_events(principal, tip, query) {
+                Ok((events, next_cursor)) => HttpResponseType::AccountEvents(
+                    response_metadata,
+                    RPCAccountEventsPage {
+                        events,
+                        next_cursor: next_cursor.map(|cursor| cursor.to_string()),
+                    },
+                ),
+                Err(chain_error::NoSuchBlockError) => {
+                    HttpResponseType::NotFound(response_metadata, format!("No such block {}", tip))
+                }
+                Err(e) => {
+                    warn!("Failed to load account events {:?}: {:?}", req, &e);
+                    HttpResponseType::ServerError(
+                        response_metadata,
+                        "Failed to load account events".to_string(),
+                    )
+                }
+            },
+            Ok(false) => HttpResponseType::NotFound(
+                response_metadata,
+                "This node does not index account events".to_string(),
+            ),
+            Err(e) => {
+                warn!("Failed to check for the account event index: {:?}", &e);
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to load account events".to_string(),
+                )
+            }
+        };
+        response.send(http, fd)
+    }
+
+    fn handle_getattachmentsinv<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        atlasdb: &AtlasDB,
+        index_block_hash: &StacksBlockId,
+        pages_indexes: &HashSet<u32>,
+        _options: &ConnectionOptions,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        // We are receiving a list of page indexes with a chain tip hash.
+        // The amount of pages_indexes is capped by MAX_ATTACHMENT_INV_PAGES_PER_REQUEST (8)
+        // Pages sizes are controlled by the constant ATTACHMENTS_INV_PAGE_SIZE (8), which
+        // means that a `GET v2/attachments/inv` request can be requesting for a 64 bit vector
+        // at once.
+        // Since clients can be asking for non-consecutive pages indexes (1, 5_000, 10_000, ...),
+        // we will be handling each page index separately.
+        // We could also add the notion of "budget" so that a client could only get a limited number
+        // of pages when they are spanning over many blocks.
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        if pages_indexes.len() > MAX_ATTACHMENT_INV_PAGES_PER_REQUEST {
+            let msg = format!(
+                "Number of attachment inv pages is limited by {} per request",
+                MAX_ATTACHMENT_INV_PAGES_PER_REQUEST
+            );
+            warn!("{}", msg);
+            let response = HttpResponseType::BadRequest(response_metadata, msg);
+            response.send(http, fd)?;
+            return Ok(());
+        }
+        if pages_indexes.len() == 0 {
+            let msg = format!("Page indexes missing");
+            warn!("{}", msg);
+            let response = HttpResponseType::NotFound(response_metadata, msg.clone());
+            response.send(http, fd)?;
+            return Ok(());
+        }
+
+        let mut pages_indexes = pages_indexes.iter().map(|i| *i).collect::<Vec<u32>>();
+        pages_indexes.sort();
+
+        let mut pages = vec![];
+
+        for page_index in pages_indexes.iter() {
+            match atlasdb.get_attachments_available_at_page_index(*page_index, &index_block_hash) {
+                Ok(inventory) => {
+                    pages.push(AttachmentPage {
+                        inventory,
+                        index: *page_index,
+                    });
+                }
+                Err(e) => {
+                    let msg = format!("Unable to read Atlas DB - {}", e);
+                    warn!("{}", msg);
+                    let response = HttpResponseType::NotFound(response_metadata, msg);
+                    return response.send(http, fd);
+                }
+            }
+        }
+
+        let content = GetAttachmentsInvResponse {
+            block_id: index_block_hash.clone(),
+            pages,
+        };
+        let response = HttpResponseType::GetAttachmentsInv(response_metadata, content);
+        response.send(http, fd)
+    }
+
+    fn handle_getattachment<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        atlasdb: &mut AtlasDB,
+        content_hash: Hash160,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        match atlasdb.find_attachment(&content_hash) {
             Ok(Some(attachment)) => {
                 let content = GetAttachmentResponse { attachment };
                 let response = HttpResponseType::GetAttachment(response_metadata, content);
@@ -776,6 +1547,150 @@ impl ConversationHttp {
         }
     }
 
+    /// Handle a GET for the attachments that are still missing, how far the attachments
+    /// downloader got with each, and which peers have them.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_attachments_status<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        network: &mut PeerNetwork,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let mut attachment_instances = match network.atlasdb.find_unresolved_attachment_instances()
+        {
+            Ok(attachment_instances) => attachment_instances,
+            Err(e) => {
+                let msg = format!("Unable to read Atlas DB - {}", e);
+                warn!("{}", msg);
+                let response = HttpResponseType::ServerError(response_metadata, msg);
+                return response.send(http, fd);
+            }
+        };
+        attachment_instances.sort_by_key(|instance| {
+            (
+                instance.stacks_block_height,
+                instance.contract_id.to_string(),
+                instance.attachment_index,
+            )
+        });
+
+        let downloader = network.attachments_downloader.as_ref();
+        let fetch_states = downloader
+            .map(|downloader| downloader.get_fetch_states())
+            .unwrap_or_default();
+
+        let mut missing_attachments = vec![];
+        let mut peers: HashMap<UrlString, RPCAttachmentsPeer> = HashMap::new();
+        let mut counted = HashSet::new();
+        for instance in attachment_instances.into_iter() {
+            let fetch_state = fetch_states.get(&instance.content_hash);
+            let mut available_from: Vec<UrlString> = downloader
+                .and_then(|downloader| downloader.get_attachment_sources(&instance.content_hash))
+                .map(|sources| sources.iter().cloned().collect())
+                .unwrap_or_default();
+            available_from.sort();
+
+            if counted.insert(instance.content_hash.clone()) {
+                for data_url in available_from.iter() {
+                    peers
+                        .entry(data_url.clone())
+                        .or_insert_with(|| RPCAttachmentsPeer {
+                            data_url: data_url.clone(),
+                            requests_sent: 0,
+                            requests_succeeded: 0,
+                            missing_attachments_available: 0,
+                        })
+                        .missing_attachments_available += 1;
+                }
+            }
+
+            missing_attachments.push(RPCMissingAttachment {
+                content_hash: instance.content_hash,
+                contract_id: instance.contract_id.to_string(),
+                attachment_index: instance.attachment_index,
+                block_height: instance.stacks_block_height,
+                index_block_hash: instance.index_block_hash,
+                queued: fetch_state.is_some(),
+                retry_count: fetch_state.map(|state| state.retry_count).unwrap_or(0),
+                next_attempt: fetch_state.map(|state| state.retry_deadline).unwrap_or(0),
+                available_from,
+            });
+        }
+
+        if let Some(downloader) = downloader {
+            for (data_url, report) in downloader.get_reliability_reports().iter() {
+                let peer = peers
+                    .entry(data_url.clone())
+                    .or_insert_with(|| RPCAttachmentsPeer {
+                        data_url: data_url.clone(),
+                        requests_sent: 0,
+                        requests_succeeded: 0,
+                        missing_attachments_available: 0,
+                    });
+                peer.requests_sent = report.total_requests_sent;
+                peer.requests_succeeded = report.total_requests_success;
+            }
+        }
+        let mut peers: Vec<RPCAttachmentsPeer> = peers.into_iter().map(|(_, peer)| peer).collect();
+        peers.sort_by(|a, b| a.data_url.cmp(&b.data_url));
+
+        let response = HttpResponseType::AttachmentsStatus(
+            response_metadata,
+            RPCAttachmentsStatus {
+                missing_attachments,
+                peers,
+            },
+        );
+        response.send(http, fd)
+    }
+
+    /// Handle a POST to fetch an attachment again, right away.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_refetch_attachment<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        network: &mut PeerNetwork,
+        content_hash: &Hash160,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let res = PeerNetwork::with_attachments_downloader(network, |network, downloader| {
+            downloader
+                .refetch_attachment(content_hash, &mut network.atlasdb)
+                .map_err(net_error::DBError)
+        });
+        let response = match res {
+            Ok(0) => HttpResponseType::NotFound(
+                response_metadata,
+                format!(
+                    "No attachment instance is missing attachment {}",
+                    content_hash
+                ),
+            ),
+            Ok(queued_instances) => HttpResponseType::AttachmentRefetch(
+                response_metadata,
+                RPCAttachmentRefetchResponse {
+                    content_hash: content_hash.clone(),
+                    queued_instances: queued_instances as u64,
+                },
+            ),
+            Err(net_error::NotConnected) => HttpResponseType::ServiceUnavailable(
+                response_metadata,
+                "The attachments downloader is not running yet".to_string(),
+            ),
+            Err(e) => HttpResponseType::ServerError(
+                response_metadata,
+                format!("Failed to re-fetch attachment: {:?}", &e),
+            ),
+        };
+        response.send(http, fd)
+    }
+
     /// Handle a GET neighbors
     /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
     fn handle_getneighbors<W: Write>(
@@ -800,6 +1715,108 @@ impl ConversationHttp {
         response.send(http, fd)
     }
 
+    /// Handle a POST to ban or unban a peer.  Banning denies the peer in the peer DB until
+    /// `ban_duration` seconds from now and disconnects it; unbanning (`ban_duration` is `None`)
+    /// lifts any deny and resets its reputation score.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_set_peer_ban<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        network: &mut PeerNetwork,
+        addr: &PeerAddress,
+        port: u16,
+        ban_duration: Option<u64>,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let network_id = network.local_peer.network_id;
+        let now = get_epoch_time_secs();
+        let banned_until = ban_duration
+            .map(|duration| now.saturating_add(duration))
+            .unwrap_or(0);
+
+        let res = network.peerdb.tx_begin().and_then(|mut tx| {
+            if ban_duration.is_some() {
+                PeerDB::set_deny_peer(&mut tx, network_id, addr, port, banned_until)?;
+            } else {
+                if PeerDB::get_peer(&tx, network_id, addr, port)?.is_some() {
+                    PeerDB::set_deny_peer(&mut tx, network_id, addr, port, 0)?;
+                }
+                PeerDB::set_peer_score(&mut tx, network_id, addr, port, 0, now)?;
+            }
+            let score = PeerDB::get_peer_score(&tx, network_id, addr, port, now)?;
+            tx.commit()?;
+            Ok(score)
+        });
+
+        let response = match res {
+            Ok(score) => {
+                if ban_duration.is_some() {
+                    let event_ids: Vec<usize> = network
+                        .events
+                        .iter()
+                        .filter(|(nk, _)| nk.addrbytes == *addr && nk.port == port)
+                        .map(|(_, event_id)| *event_id)
+                        .collect();
+                    for event_id in event_ids.into_iter() {
+                        network.deregister_peer(event_id);
+                    }
+                }
+                info!(
+                    "{} peer {:?}",
+                    if ban_duration.is_some() {
+                        "Banned"
+                    } else {
+                        "Unbanned"
+                    },
+                    &addr.to_socketaddr(port)
+                );
+                HttpResponseType::PeerBan(
+                    response_metadata,
+                    RPCPeerBanResponse {
+                        ip: addr.clone(),
+                        port,
+                        banned_until,
+                        score,
+                    },
+                )
+            }
+            Err(e) => HttpResponseType::ServerError(
+                response_metadata,
+                format!("Failed to update ban of peer: {:?}", &e),
+            ),
+        };
+        response.send(http, fd)
+    }
+
+    /// Handle a GET for the Clarity code coverage collected so far in this run.  If `reset` is
+    /// set, the collected coverage is cleared and a new run begins.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_get_clarity_coverage<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        reset: bool,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let response = match CoverageReporter::dump_global(reset) {
+            Ok(Some(dump)) => HttpResponseType::ClarityCoverage(response_metadata, dump),
+            Ok(None) => HttpResponseType::NotFound(
+                response_metadata,
+                "Clarity coverage is not enabled".to_string(),
+            ),
+            Err(e) => HttpResponseType::ServerError(
+                response_metadata,
+                format!("Failed to dump Clarity coverage: {:?}", &e),
+            ),
+        };
+        response.send(http, fd)
+    }
+
     /// Handle a not-found
     fn handle_notfound<W: Write>(
         http: &mut StacksHttp,
@@ -876,20 +1893,68 @@ impl ConversationHttp {
         }
     }
 
-    /// Handle a GET block.  Start streaming the reply.
-    /// The response's preamble (but not the block data) will be synchronously written to the fd
-    /// (so use a fd that can buffer!)
-    /// Return a StreamCursor struct for the block that we're sending, so we can continue to
-    /// make progress sending it.
-    fn handle_getblock<W: Write>(
+    /// Handle a GET on a batch of headers for light clients, read from the chain ending at `tip`.
+    fn handle_get_light_headers<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        index_block_hash: &StacksBlockId,
         chainstate: &StacksChainState,
+        sortdb: &SortitionDB,
+        tip: &StacksBlockId,
+        start_height: u64,
+        count: u64,
         canonical_stacks_tip_height: u64,
-    ) -> Result<Option<StreamCursor>, net_error> {
-        monitoring::increment_stx_blocks_served_counter();
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        if count == 0 || count > (MAX_HEADERS as u64) {
+            let response = HttpResponseType::BadRequestJSON(
+                response_metadata,
+                serde_json::Value::String(format!(
+                    "Invalid request: expected between 1 and {} headers",
+                    MAX_HEADERS
+                )),
+            );
+            return response.send(http, fd);
+        }
+
+        let response = match RPCLightHeaderBatch::from_chainstate(
+            chainstate,
+            sortdb,
+            tip,
+            start_height,
+            count,
+        ) {
+            Ok(Some(batch)) => HttpResponseType::LightHeaders(response_metadata, batch),
+            Ok(None) => HttpResponseType::NotFound(
+                response_metadata,
+                format!("No block at height {} in chain {}", start_height, tip),
+            ),
+            Err(e) => {
+                warn!("Failed to load light client headers {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    format!("Failed to load headers from {}", tip),
+                )
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// Handle a GET block.  Start streaming the reply.
+    /// The response's preamble (but not the block data) will be synchronously written to the fd
+    /// (so use a fd that can buffer!)
+    /// Return a StreamCursor struct for the block that we're sending, so we can continue to
+    /// make progress sending it.
+    fn handle_getblock<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        index_block_hash: &StacksBlockId,
+        chainstate: &StacksChainState,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<Option<StreamCursor>, net_error> {
+        monitoring::increment_stx_blocks_served_counter();
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
 
@@ -1319,9 +2384,9 @@ impl ConversationHttp {
         response.send(http, fd).map(|_| ())
     }
 
-    /// Handle a POST to run a read-only function call with the given parameters on the given chain
-    /// tip.  Returns the result of the function call.  Returns a CallReadOnlyResponse on success.
-    fn handle_readonly_function_call<W: Write>(
+    /// Handle a GET on a page of a smart contract's data map entries, as of the given chain tip.
+    /// Entries are listed in the order of their serialized keys, starting after `cursor`.
+    fn handle_get_map_entries<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
@@ -1330,79 +2395,296 @@ impl ConversationHttp {
         tip: &StacksBlockId,
         contract_addr: &StacksAddress,
         contract_name: &ContractName,
-        function: &ClarityName,
-        sender: &PrincipalData,
-        args: &[Value],
-        options: &ConnectionOptions,
+        map_name: &ClarityName,
+        cursor: &Option<Value>,
+        limit: u64,
         canonical_stacks_tip_height: u64,
     ) -> Result<(), net_error> {
         let response_metadata =
             HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
         let contract_identifier =
             QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+        let prefix = ClarityDatabase::make_key_prefix_for_data_map(&contract_identifier, map_name);
+        let start_after = cursor.as_ref().map(|key| {
+            ClarityDatabase::make_key_for_data_map_entry(&contract_identifier, map_name, key)
+        });
+
+        let response =
+            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                let map_exists = clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                    clarity_db.load_map(&contract_identifier, map_name).is_ok()
+                });
+                if !map_exists {
+                    return None;
+                }
+
+                // ask for one more entry than we list, to tell whether there is another page
+                let mut entries = clarity_tx.get_entries_with_key_prefix(
+                    &prefix,
+                    start_after.as_deref(),
+                    (limit + 1) as u32,
+                );
+                let next_cursor = if entries.len() as u64 > limit {
+                    entries.truncate(limit as usize);
+                    entries
+                        .last()
+                        .map(|(key, _)| format!("0x{}", &key[prefix.len()..]))
+                } else {
+                    None
+                };
+
+                let entries = entries
+                    .into_iter()
+                    .filter_map(|(key, value)| {
+                        // entries are stored as `(some value)`, and deleted ones as `none`
+                        match Value::try_deserialize_hex_untyped(&value) {
+                            Ok(Value::Optional(OptionalData { data: Some(value) })) => {
+                                Some(RPCMapEntry {
+                                    key: format!("0x{}", &key[prefix.len()..]),
+                                    value: format!("0x{}", value.serialize()),
+                                })
+                            }
+                            _ => None,
+                        }
+                    })
+                    .collect();
+                Some(RPCMapEntriesPage {
+                    entries,
+                    next_cursor,
+                })
+            }) {
+                Ok(Some(Some(page))) => HttpResponseType::MapEntries(response_metadata, page),
+                Ok(Some(None)) => HttpResponseType::NotFound(
+                    response_metadata,
+                    format!("No such map {}.{}", &contract_identifier, map_name),
+                ),
+                Ok(None) | Err(_) => {
+                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
+                }
+            };
+
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET for the chainstate consistency hash at the given block
+    fn handle_get_consistency_hash<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        tip: &StacksBlockId,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let response = match chainstate.get_consistency_hash(sortdb, tip) {
+            Ok(Some(consistency_hash)) => {
+                HttpResponseType::ConsistencyHash(response_metadata, consistency_hash)
+            }
+            Ok(None) => {
+                HttpResponseType::NotFound(response_metadata, format!("No processed block {}", tip))
+            }
+            Err(e) => {
+                warn!("Failed to compute consistency hash at {}: {:?}", tip, &e);
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    format!("Failed to compute consistency hash at {}", tip),
+                )
+            }
+        };
+        response.send(http, fd).map(|_| ())
+    }
 
+    /// Run a read-only function call in `clarity_tx`, within the node's cost limit for read-only
+    /// calls.  If `trace` is set, also returns an execution trace of the call.
+    fn run_readonly_function_call(
+        clarity_tx: &mut ClarityReadOnlyConnection,
+        mainnet: bool,
+        options: &ConnectionOptions,
+        contract_identifier: &QualifiedContractIdentifier,
+        function: &ClarityName,
+        sender: &PrincipalData,
+        args: &[Value],
+        trace: bool,
+    ) -> (Result<Value, ClarityRuntimeError>, Option<ExecutionTrace>) {
         let args: Vec<_> = args
             .iter()
             .map(|x| SymbolicExpression::atom_value(x.clone()))
             .collect();
-        let mainnet = chainstate.mainnet;
         let mut cost_limit = options.read_only_call_limit.clone();
         cost_limit.write_length = 0;
         cost_limit.write_count = 0;
 
-        let data_opt_res =
-            chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
-                let epoch = clarity_tx.get_epoch();
-                let cost_track = clarity_tx
-                    .with_clarity_db_readonly(|clarity_db| {
-                        LimitedCostTracker::new_mid_block(mainnet, cost_limit, clarity_db, epoch)
-                    })
-                    .map_err(|_| {
-                        ClarityRuntimeError::from(InterpreterError::CostContractLoadFailure)
-                    })?;
+        let epoch = clarity_tx.get_epoch();
+        let cost_track = match clarity_tx.with_clarity_db_readonly(|clarity_db| {
+            LimitedCostTracker::new_mid_block(mainnet, cost_limit, clarity_db, epoch)
+        }) {
+            Ok(cost_track) => cost_track,
+            Err(_) => {
+                return (
+                    Err(ClarityRuntimeError::from(
+                        InterpreterError::CostContractLoadFailure,
+                    )),
+                    None,
+                );
+            }
+        };
 
-                clarity_tx.with_readonly_clarity_env(mainnet, sender.clone(), cost_track, |env| {
-                    // we want to execute any function as long as no actual writes are made as
-                    // opposed to be limited to purely calling `define-read-only` functions,
-                    // so use `read_only = false`.  This broadens the number of functions that
-                    // can be called, and also circumvents limitations on `define-read-only`
-                    // functions that can not use `contrac-call?`, even when calling other
-                    // read-only functions
-                    env.execute_contract(&contract_identifier, function.as_str(), &args, false)
-                })
+        let mut execution_trace = None;
+        let result =
+            clarity_tx.with_readonly_clarity_env(mainnet, sender.clone(), cost_track, |env| {
+                if trace {
+                    env.global_context.tracer = Some(ExecutionTracer::new());
+                }
+                // we want to execute any function as long as no actual writes are made as
+                // opposed to be limited to purely calling `define-read-only` functions,
+                // so use `read_only = false`.  This broadens the number of functions that
+                // can be called, and also circumvents limitations on `define-read-only`
+                // functions that can not use `contrac-call?`, even when calling other
+                // read-only functions
+                let result =
+                    env.execute_contract(contract_identifier, function.as_str(), &args, false);
+                execution_trace = env
+                    .global_context
+                    .tracer
+                    .take()
+                    .map(|tracer| tracer.into_trace());
+                result
             });
+        (result, execution_trace)
+    }
 
-        let response = match data_opt_res {
-            Ok(Some(Ok(data))) => HttpResponseType::CallReadOnlyFunction(
-                response_metadata,
-                CallReadOnlyResponse {
-                    okay: true,
-                    result: Some(format!("0x{}", data.serialize())),
-                    cause: None,
-                },
-            ),
-            Ok(Some(Err(e))) => match e {
+    /// Report the outcome of a read-only function call
+    fn make_readonly_call_response(
+        result: Result<Value, ClarityRuntimeError>,
+        trace: Option<ExecutionTrace>,
+    ) -> CallReadOnlyResponse {
+        match result {
+            Ok(data) => CallReadOnlyResponse {
+                okay: true,
+                result: Some(format!("0x{}", data.serialize())),
+                cause: None,
+                trace,
+            },
+            Err(e) => match e {
                 Unchecked(CheckErrors::CostBalanceExceeded(actual_cost, _))
                     if actual_cost.write_count > 0 =>
                 {
-                    HttpResponseType::CallReadOnlyFunction(
-                        response_metadata,
-                        CallReadOnlyResponse {
-                            okay: false,
-                            result: None,
-                            cause: Some("NotReadOnly".to_string()),
-                        },
-                    )
-                }
-                _ => HttpResponseType::CallReadOnlyFunction(
-                    response_metadata,
                     CallReadOnlyResponse {
                         okay: false,
                         result: None,
-                        cause: Some(e.to_string()),
-                    },
-                ),
+                        cause: Some("NotReadOnly".to_string()),
+                        trace,
+                    }
+                }
+                _ => CallReadOnlyResponse {
+                    okay: false,
+                    result: None,
+                    cause: Some(e.to_string()),
+                    trace,
+                },
             },
+        }
+    }
+
+    /// Handle a POST to run a read-only function call with the given parameters on the given chain
+    /// tip.  Returns the result of the function call.  Returns a CallReadOnlyResponse on success.
+    fn handle_readonly_function_call<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        contract_addr: &StacksAddress,
+        contract_name: &ContractName,
+        function: &ClarityName,
+        sender: &PrincipalData,
+        args: &[Value],
+        trace: bool,
+        options: &ConnectionOptions,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let contract_identifier =
+            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+        let mainnet = chainstate.mainnet;
+
+        let data_opt_res =
+            chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                ConversationHttp::run_readonly_function_call(
+                    clarity_tx,
+                    mainnet,
+                    options,
+                    &contract_identifier,
+                    function,
+                    sender,
+                    args,
+                    trace,
+                )
+            });
+
+        let response = match data_opt_res {
+            Ok(Some((result, execution_trace))) => HttpResponseType::CallReadOnlyFunction(
+                response_metadata,
+                ConversationHttp::make_readonly_call_response(result, execution_trace),
+            ),
+            Ok(None) | Err(_) => {
+                HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
+            }
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a POST to run a batch of read-only function calls on the given chain tip.  All calls
+    /// read the same chain state, and each is subject to the read-only call cost limit on its own.
+    /// Returns a CallReadOnlyBatchResponse with each call's result, in order.
+    fn handle_readonly_function_call_batch<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        calls: &[ReadOnlyCall],
+        options: &ConnectionOptions,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let mainnet = chainstate.mainnet;
+
+        let results_opt_res =
+            chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                calls
+                    .iter()
+                    .map(|call| {
+                        let contract_identifier = QualifiedContractIdentifier::new(
+                            call.contract_addr.into(),
+                            call.contract_name.clone(),
+                        );
+                        let (result, _) = ConversationHttp::run_readonly_function_call(
+                            clarity_tx,
+                            mainnet,
+                            options,
+                            &contract_identifier,
+                            &call.function_name,
+                            &call.sender,
+                            &call.arguments,
+                            false,
+                        );
+                        ConversationHttp::make_readonly_call_response(result, None)
+                    })
+                    .collect::<Vec<_>>()
+            });
+
+        let response = match results_opt_res {
+            Ok(Some(results)) => HttpResponseType::CallReadOnlyFunctionBatch(
+                response_metadata,
+                CallReadOnlyBatchResponse { tip: *tip, results },
+            ),
             Ok(None) | Err(_) => {
                 HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
             }
@@ -1523,6 +2805,8 @@ impl ConversationHttp {
     /// of Stacks -- callers must trust the Stacks node to return correct analysis data.
     /// Callers who don't trust the Stacks node should just fetch the contract source
     /// code and analyze it offline.
+    /// The response carries an ETag; if the request's If-None-Match already names it, the node
+    /// replies 304 Not Modified instead.
     fn handle_get_contract_abi<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
@@ -1541,12 +2825,37 @@ impl ConversationHttp {
 
         let response =
             match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
-                clarity_tx.with_analysis_db_readonly(|db| {
-                    let contract = db.load_contract(&contract_identifier)?;
-                    contract.contract_interface
+                let analysis = clarity_tx
+                    .with_analysis_db_readonly(|db| db.load_contract(&contract_identifier))?;
+                let interface = analysis.contract_interface?;
+                let (source, publish_height) = clarity_tx.with_clarity_db_readonly(|db| {
+                    let source = db.get_contract_src(&contract_identifier)?;
+                    let contract_commit = db
+                        .get::<ContractCommitment>(&make_contract_hash_key(&contract_identifier))
+                        .expect("BUG: obtained source, but couldn't get contract commit");
+                    Some((source, contract_commit.block_height))
+                })?;
+                Some(ContractInterfaceResponse {
+                    interface,
+                    source,
+                    publish_height,
+                    clarity_version: CLARITY_VERSION,
+                    implemented_traits: analysis
+                        .implemented_traits
+                        .iter()
+                        .map(|trait_id| trait_id.to_string())
+                        .collect(),
                 })
             }) {
-                Ok(Some(Some(data))) => HttpResponseType::GetContractABI(response_metadata, data),
+                Ok(Some(Some(data))) => {
+                    let etag = data.etag();
+                    if ConversationHttp::etag_matches(req.metadata().if_none_match.as_ref(), &etag)
+                    {
+                        HttpResponseType::NotModified(response_metadata, etag)
+                    } else {
+                        HttpResponseType::GetContractABI(response_metadata, data)
+                    }
+                }
                 Ok(Some(None)) => HttpResponseType::NotFound(
                     response_metadata,
                     "No contract interface data found".into(),
@@ -1559,11 +2868,72 @@ impl ConversationHttp {
         response.send(http, fd).map(|_| ())
     }
 
+    /// Does an If-None-Match header value name the given entity tag?
+    fn etag_matches(if_none_match: Option<&String>, etag: &str) -> bool {
+        match if_none_match {
+            Some(tags) => tags.split(',').any(|tag| {
+                let tag = tag.trim();
+                tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag
+            }),
+            None => false,
+        }
+    }
+
     /// Handle a GET unconfirmed microblock stream.  Start streaming the reply.
     /// The response's preamble (but not the block data) will be synchronously written to the fd
     /// (so use a fd that can buffer!)
     /// Return a StreamCursor struct for the block that we're sending, so we can continue to
     /// make progress sending it.
+    /// Handle a GET for the microblock stream built on an anchored block, or for one of its
+    /// microblocks, whether or not the stream was confirmed.
+    fn handle_getmicroblocks_by_parent<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        parent_block_hash: &BlockHeaderHash,
+        seq: Option<u16>,
+        chainstate: &StacksChainState,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+
+        let microblocks_res = match seq {
+            Some(seq) => StacksChainState::load_microblock_by_parent_and_sequence(
+                chainstate.db(),
+                parent_block_hash,
+                seq,
+            )
+            .map(|mblock_opt| mblock_opt.map(|mblock| vec![mblock])),
+            None => StacksChainState::load_microblock_stream_by_parent(
+                chainstate.db(),
+                parent_block_hash,
+            ),
+        };
+
+        let response = match microblocks_res {
+            Ok(Some(microblocks)) => HttpResponseType::Microblocks(response_metadata, microblocks),
+            Ok(None) => HttpResponseType::NotFound(
+                response_metadata,
+                match seq {
+                    Some(seq) => format!(
+                        "No microblock with sequence {} built on {}",
+                        seq, parent_block_hash
+                    ),
+                    None => format!("No microblock stream built on {}", parent_block_hash),
+                },
+            ),
+            Err(e) => {
+                warn!("Failed to load microblocks {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    format!("Failed to load microblocks built on {}", parent_block_hash),
+                )
+            }
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
     fn handle_getmicroblocks_unconfirmed<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
@@ -1702,6 +3072,75 @@ impl ConversationHttp {
         return response.send(http, fd).map(|_| ());
     }
 
+    /// Handle a GET on a transaction's status: whether it is in the mempool, and whether it is
+    /// confirmed in the chain ending at `tip`.
+    fn handle_gettransaction_status<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        chainstate: &StacksChainState,
+        mempool: &MemPoolDB,
+        tip: &StacksBlockId,
+        txid: &Txid,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+
+        let mempool_tx =
+            MemPoolDB::get_tx(mempool.conn(), txid)?.map(|txinfo| RPCMempoolTransaction {
+                accept_time: txinfo.metadata.accept_time,
+                tx_fee: txinfo.metadata.tx_fee,
+                origin_address: txinfo.metadata.origin_address,
+                origin_nonce: txinfo.metadata.origin_nonce,
+                tx: to_hex(&txinfo.tx.serialize_to_vec()),
+            });
+
+        let confirmed_tx = match chainstate.get_logged_transaction(txid, tip) {
+            Ok(logged_tx_opt) => logged_tx_opt.map(|(header, logged_tx)| {
+                let receipt = logged_tx.receipt;
+                RPCConfirmedTransaction {
+                    index_block_hash: logged_tx.index_block_hash,
+                    block_hash: header.anchored_header.block_hash(),
+                    consensus_hash: header.consensus_hash,
+                    block_height: header.stacks_block_height,
+                    burn_block_height: header.burn_header_height,
+                    result: logged_tx.result,
+                    raw_result: receipt.as_ref().map(|r| format!("0x{}", &r.raw_result)),
+                    post_condition_aborted: receipt.as_ref().map(|r| r.post_condition_aborted),
+                    events: receipt.map(|r| r.events),
+                }
+            }),
+            Err(e) => {
+                warn!("Failed to look up transaction {}: {:?}", txid, &e);
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    format!("Failed to look up transaction {}", txid),
+                );
+                return response.send(http, fd);
+            }
+        };
+
+        let status = if confirmed_tx.is_some() {
+            TransactionStatus::Confirmed
+        } else if mempool_tx.is_some() {
+            TransactionStatus::Pending
+        } else {
+            TransactionStatus::Unknown
+        };
+        let response = HttpResponseType::TransactionStatus(
+            response_metadata,
+            RPCTransactionStatus {
+                txid: *txid,
+                status,
+                confirmations_logged: *TRANSACTION_LOG,
+                mempool: mempool_tx,
+                confirmed: confirmed_tx,
+            },
+        );
+        response.send(http, fd)
+    }
+
     /// Load up the canonical Stacks chain tip.  Note that this is subject to both burn chain block
     /// Stacks block availability -- different nodes with different partial replicas of the Stacks chain state
     /// will return different values here.
@@ -1716,7 +3155,9 @@ impl ConversationHttp {
     /// # Inputs
     /// - `tip_req` is given by the HTTP request as the optional query parameter for the chain tip
     /// hash.  It will be UseLatestAnchoredTip if there was no parameter given. If it is set to
-    /// `latest`, the parameter will be set to UseLatestUnconfirmedTip.
+    /// `latest`, the parameter will be set to UseLatestUnconfirmedTip.  If it is a block height,
+    /// it will be SpecificHeight, which names the block at that height in the canonical Stacks
+    /// chain.
     fn handle_load_stacks_chain_tip<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
@@ -1770,9 +3211,40 @@ impl ConversationHttp {
                 }
             }
             TipRequest::SpecificTip(tip) => Ok(Some(*tip).clone()),
-            TipRequest::UseLatestAnchoredTip => match chainstate.get_stacks_chain_tip(sortdb)? {
-                Some(tip) => Ok(Some(StacksBlockHeader::make_index_block_hash(
-                    &tip.consensus_hash,
+            TipRequest::SpecificHeight(height) => {
+                let ancestor_opt = match chainstate.get_stacks_chain_tip(sortdb)? {
+                    Some(tip) => {
+                        let tip_id = StacksBlockHeader::make_index_block_hash(
+                            &tip.consensus_hash,
+                            &tip.anchored_block_hash,
+                        );
+                        chainstate
+                            .index_conn()?
+                            .get_ancestor_block_hash(*height, &tip_id)?
+                    }
+                    None => None,
+                };
+                match ancestor_opt {
+                    Some(ancestor) => Ok(Some(ancestor)),
+                    None => {
+                        let response_metadata = HttpResponseMetadata::from_http_request_type(
+                            req,
+                            Some(canonical_stacks_tip_height),
+                        );
+                        let response = HttpResponseType::NotFound(
+                            response_metadata,
+                            format!(
+                                "No block at height {} in the canonical Stacks chain",
+                                height
+                            ),
+                        );
+                        response.send(http, fd).and_then(|_| Ok(None))
+                    }
+                }
+            }
+            TipRequest::UseLatestAnchoredTip => match chainstate.get_stacks_chain_tip(sortdb)? {
+                Some(tip) => Ok(Some(StacksBlockHeader::make_index_block_hash(
+                    &tip.consensus_hash,
                     &tip.anchored_block_hash,
                 ))),
                 None => {
@@ -1815,6 +3287,57 @@ impl ConversationHttp {
         response.send(http, fd).and_then(|_| Ok(None))
     }
 
+    /// Handle a POST to dry-run a transaction payload from `sender` on top of the canonical Stacks
+    /// tip.  Nothing the transaction does is kept, and it is never admitted to the mempool.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_post_dry_run_transaction<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        burnchain_tip: &BlockSnapshot,
+        sender: &StacksAddress,
+        payload: &TransactionPayload,
+        canonical_stacks_tip_height: u64,
+    ) -> Result<(), net_error> {
+        let response_metadata =
+            HttpResponseMetadata::from_http_request_type(req, Some(canonical_stacks_tip_height));
+        let ast_rules = SortitionDB::get_ast_rules(sortdb.conn(), burnchain_tip.block_height + 1)?;
+        let response = match chainstate.dry_run_transaction(
+            &sortdb.index_conn(),
+            &burnchain_tip.canonical_stacks_tip_consensus_hash,
+            &burnchain_tip.canonical_stacks_tip_hash,
+            sender,
+            payload.clone(),
+            ast_rules,
+        ) {
+            Ok(receipt) => {
+                let txid = receipt.transaction.txid();
+                RPCDryRunResponse {
+                    okay: true,
+                    result: Some(format!("0x{}", receipt.result.serialize())),
+                    cause: None,
+                    execution_cost: receipt.execution_cost,
+                    events: receipt
+                        .events
+                        .iter()
+                        .enumerate()
+                        .map(|(event_index, event)| event.json_serialize(event_index, &txid, true))
+                        .collect(),
+                }
+            }
+            Err(e) => RPCDryRunResponse {
+                okay: false,
+                result: None,
+                cause: Some(e.to_string()),
+                execution_cost: ExecutionCost::zero(),
+                events: vec![],
+            },
+        };
+        HttpResponseType::DryRunTransaction(response_metadata, response).send(http, fd)
+    }
+
     fn handle_post_fee_rate_estimate<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
@@ -1915,6 +3438,7 @@ impl ConversationHttp {
         event_observer: Option<&dyn MemPoolEventDispatcher>,
         canonical_stacks_tip_height: u64,
         ast_rules: ASTRules,
+        admission_simulation: MemPoolAdmissionSimulation,
     ) -> Result<bool, net_error> {
         let txid = tx.txid();
         let response_metadata =
@@ -1952,29 +3476,96 @@ impl ConversationHttp {
                         net_error::ChainstateError("Could not load Stacks epoch for canonical burn height".into())
                     })?;
 
-                match mempool.submit(
-                    chainstate,
-                    &consensus_hash,
-                    &block_hash,
-                    &tx,
-                    event_observer,
-                    &stacks_epoch.block_limit,
-                    &stacks_epoch.epoch_id,
-                ) {
-                    Ok(_) => {
-                        debug!("Mempool accepted POSTed transaction {}", &txid);
-                        (
-                            HttpResponseType::TransactionID(response_metadata, txid),
-                            true,
-                        )
+                let warning = if admission_simulation == MemPoolAdmissionSimulation::Off {
+                    None
+                } else {
+                    match mempool.simulate_tx(
+                        chainstate,
+                        &sortdb.index_conn(),
+                        &consensus_hash,
+                        &block_hash,
+                        &tx,
+                        ast_rules,
+                    ) {
+                        Ok(warning) => warning,
+                        Err(e) => {
+                            debug!(
+                                "Failed to pre-execute POSTed transaction {}: {:?}",
+                                &txid, &e
+                            );
+                            None
+                        }
                     }
-                    Err(e) => {
-                        debug!("Mempool rejected POSTed transaction {}: {:?}", &txid, &e);
+                };
+
+                match warning {
+                    Some(warning) if admission_simulation == MemPoolAdmissionSimulation::Reject => {
+                        // if the mempool would have rejected it anyway, say why
+                        let tx_size = tx.serialize_to_vec().len() as u64;
+                        let rejection = match chainstate.will_admit_mempool_tx(
+                            &consensus_hash,
+                            &block_hash,
+                            &tx,
+                            tx_size,
+                        ) {
+                            Ok(()) => MemPoolRejection::WillAbort(warning),
+                            Err(e) => e,
+                        };
+                        debug!(
+                            "Mempool rejected POSTed transaction {}: {:?}",
+                            &txid, &rejection
+                        );
                         (
-                            HttpResponseType::BadRequestJSON(response_metadata, e.into_json(&txid)),
+                            HttpResponseType::BadRequestJSON(
+                                response_metadata,
+                                rejection.into_json(&txid),
+                            ),
                             false,
                         )
                     }
+                    warning => match mempool.submit(
+                        chainstate,
+                        &consensus_hash,
+                        &block_hash,
+                        &tx,
+                        event_observer,
+                        &stacks_epoch.block_limit,
+                        &stacks_epoch.epoch_id,
+                    ) {
+                        Ok(_) => {
+                            debug!("Mempool accepted POSTed transaction {}", &txid);
+                            let response = match warning {
+                                Some(warning) => {
+                                    debug!("POSTed transaction {} will abort: {}", &txid, &warning);
+                                    // the warning is sent as a header, so it must fit on one line
+                                    let warning: String = warning
+                                        .lines()
+                                        .collect::<Vec<_>>()
+                                        .join(" ")
+                                        .chars()
+                                        .take(MAX_ADMISSION_WARNING_LEN)
+                                        .collect();
+                                    HttpResponseType::TransactionIDWithWarning(
+                                        response_metadata,
+                                        txid,
+                                        warning,
+                                    )
+                                }
+                                None => HttpResponseType::TransactionID(response_metadata, txid),
+                            };
+                            (response, true)
+                        }
+                        Err(e) => {
+                            debug!("Mempool rejected POSTed transaction {}: {:?}", &txid, &e);
+                            (
+                                HttpResponseType::BadRequestJSON(
+                                    response_metadata,
+                                    e.into_json(&txid),
+                                ),
+                                false,
+                            )
+                        }
+                    },
                 }
             }
         };
@@ -2257,6 +3848,26 @@ impl ConversationHttp {
         let keep_alive = req.metadata().keep_alive;
         let mut ret = None;
 
+        // only requests the client may make count against its rate limits
+        if !ConversationHttp::handle_check_rpc_access(
+            &mut self.connection.protocol,
+            &mut reply,
+            &req,
+            &self.connection.options,
+            network.burnchain_tip.canonical_stacks_tip_height,
+        )? || !ConversationHttp::handle_rate_limit(
+            &mut self.connection.protocol,
+            &mut reply,
+            &req,
+            &self.peer_addr,
+            &mut network.rpc_rate_limiter,
+            &self.connection.options,
+            network.burnchain_tip.canonical_stacks_tip_height,
+        )? {
+            self.reply_streams.push_back((reply, None, keep_alive));
+            return Ok(ret);
+        }
+
         let stream_opt = match req {
             HttpRequestType::GetInfo(ref _md) => {
                 ConversationHttp::handle_getinfo(
@@ -2293,6 +3904,81 @@ impl ConversationHttp {
                 }
                 None
             }
+            HttpRequestType::GetPoxCycleInfo(
+                ref _md,
+                reward_cycle,
+                ref pox_address,
+                ref tip_req,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_getpoxcycleinfo(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        &network.burnchain,
+                        reward_cycle,
+                        pox_address.as_ref(),
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetFinalizedBlocks(ref _md, ref page) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    &TipRequest::UseLatestAnchoredTip,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_get_finalized_blocks(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        chainstate,
+                        &tip,
+                        *page,
+                        &self.connection.options,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetBurnOps(ref _md, burn_height) => {
+                ConversationHttp::handle_get_burn_ops(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    sortdb,
+                    burn_height,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::GetSortition(ref _md, ref consensus_hash) => {
+                ConversationHttp::handle_get_sortition(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    sortdb,
+                    consensus_hash,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
             HttpRequestType::GetNeighbors(ref _md) => {
                 ConversationHttp::handle_getneighbors(
                     &mut self.connection.protocol,
@@ -2303,6 +3989,44 @@ impl ConversationHttp {
                 )?;
                 None
             }
+            HttpRequestType::BanPeer(ref _md, ref addr, ref port, ref duration) => {
+                let canonical_stacks_tip_height = network.burnchain_tip.canonical_stacks_tip_height;
+                ConversationHttp::handle_set_peer_ban(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    network,
+                    addr,
+                    *port,
+                    Some(*duration),
+                    canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::UnbanPeer(ref _md, ref addr, ref port) => {
+                let canonical_stacks_tip_height = network.burnchain_tip.canonical_stacks_tip_height;
+                ConversationHttp::handle_set_peer_ban(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    network,
+                    addr,
+                    *port,
+                    None,
+                    canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::GetClarityCoverage(ref _md, ref reset) => {
+                ConversationHttp::handle_get_clarity_coverage(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    *reset,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
             HttpRequestType::GetHeaders(ref _md, ref quantity, ref tip_req) => {
                 if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
                     &mut self.connection.protocol,
@@ -2326,6 +4050,30 @@ impl ConversationHttp {
                     None
                 }
             }
+            HttpRequestType::GetLightHeaders(ref _md, ref start_height, ref count, ref tip_req) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_get_light_headers(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        chainstate,
+                        sortdb,
+                        &tip,
+                        *start_height,
+                        *count,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
             HttpRequestType::GetBlock(ref _md, ref index_block_hash) => {
                 ConversationHttp::handle_getblock(
                     &mut self.connection.protocol,
@@ -2369,6 +4117,18 @@ impl ConversationHttp {
                 chainstate,
                 network.burnchain_tip.canonical_stacks_tip_height,
             )?,
+            HttpRequestType::GetMicroblocksByParent(ref _md, ref parent_block_hash, ref seq) => {
+                ConversationHttp::handle_getmicroblocks_by_parent(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    parent_block_hash,
+                    *seq,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
             HttpRequestType::GetTransactionUnconfirmed(ref _md, ref txid) => {
                 ConversationHttp::handle_gettransaction_unconfirmed(
                     &mut self.connection.protocol,
@@ -2381,6 +4141,29 @@ impl ConversationHttp {
                 )?;
                 None
             }
+            HttpRequestType::GetTransactionStatus(ref _md, ref txid) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    &TipRequest::UseLatestAnchoredTip,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_gettransaction_status(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        chainstate,
+                        mempool,
+                        &tip,
+                        txid,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
             HttpRequestType::GetAccount(ref _md, ref principal, ref tip_req, ref with_proof) => {
                 if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
                     &mut self.connection.protocol,
@@ -2405,6 +4188,29 @@ impl ConversationHttp {
                 }
                 None
             }
+            HttpRequestType::GetAccountEvents(ref _md, ref principal, ref query, ref tip_req) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_get_account_events(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        chainstate,
+                        &tip,
+                        principal,
+                        query,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
             HttpRequestType::GetDataVar(
                 ref _md,
                 ref contract_addr,
@@ -2473,19 +4279,13 @@ impl ConversationHttp {
                 }
                 None
             }
-            HttpRequestType::GetTransferCost(ref _md) => {
-                ConversationHttp::handle_token_transfer_cost(
-                    &mut self.connection.protocol,
-                    &mut reply,
-                    &req,
-                    network.burnchain_tip.canonical_stacks_tip_height,
-                )?;
-                None
-            }
-            HttpRequestType::GetContractABI(
+            HttpRequestType::GetMapEntries(
                 ref _md,
                 ref contract_addr,
                 ref contract_name,
+                ref map_name,
+                ref cursor,
+                ref limit,
                 ref tip_req,
             ) => {
                 if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
@@ -2497,7 +4297,7 @@ impl ConversationHttp {
                     chainstate,
                     network.burnchain_tip.canonical_stacks_tip_height,
                 )? {
-                    ConversationHttp::handle_get_contract_abi(
+                    ConversationHttp::handle_get_map_entries(
                         &mut self.connection.protocol,
                         &mut reply,
                         &req,
@@ -2506,32 +4306,111 @@ impl ConversationHttp {
                         &tip,
                         contract_addr,
                         contract_name,
+                        map_name,
+                        cursor,
+                        *limit,
                         network.burnchain_tip.canonical_stacks_tip_height,
                     )?;
                 }
                 None
             }
-            HttpRequestType::FeeRateEstimate(ref _md, ref tx, estimated_len) => {
-                ConversationHttp::handle_post_fee_rate_estimate(
+            HttpRequestType::GetConsistencyHash(ref _md, ref tip_req) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
                     &mut self.connection.protocol,
                     &mut reply,
                     &req,
-                    handler_opts,
+                    tip_req,
                     sortdb,
-                    tx,
-                    estimated_len,
+                    chainstate,
                     network.burnchain_tip.canonical_stacks_tip_height,
-                )?;
-                None
-            }
-            HttpRequestType::CallReadOnlyFunction(
-                ref _md,
-                ref ctrct_addr,
+                )? {
+                    ConversationHttp::handle_get_consistency_hash(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetTransferCost(ref _md) => {
+                ConversationHttp::handle_token_transfer_cost(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::GetContractABI(
+                ref _md,
+                ref contract_addr,
+                ref contract_name,
+                ref tip_req,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_get_contract_abi(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        contract_addr,
+                        contract_name,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::FeeRateEstimate(ref _md, ref tx, estimated_len) => {
+                ConversationHttp::handle_post_fee_rate_estimate(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    handler_opts,
+                    sortdb,
+                    tx,
+                    estimated_len,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::DryRunTransaction(ref _md, ref sender, ref payload) => {
+                let burnchain_tip = network.burnchain_tip.clone();
+                ConversationHttp::handle_post_dry_run_transaction(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    sortdb,
+                    chainstate,
+                    &burnchain_tip,
+                    sender,
+                    payload,
+                    burnchain_tip.canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::CallReadOnlyFunction(
+                ref _md,
+                ref ctrct_addr,
                 ref ctrct_name,
                 ref as_sender,
                 ref func_name,
                 ref args,
                 ref tip_req,
+                ref trace,
             ) => {
                 if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
                     &mut self.connection.protocol,
@@ -2554,6 +4433,31 @@ impl ConversationHttp {
                         func_name,
                         as_sender,
                         args,
+                        *trace,
+                        &self.connection.options,
+                        network.burnchain_tip.canonical_stacks_tip_height,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::CallReadOnlyFunctionBatch(ref _md, ref calls, ref tip_req) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_req,
+                    sortdb,
+                    chainstate,
+                    network.burnchain_tip.canonical_stacks_tip_height,
+                )? {
+                    ConversationHttp::handle_readonly_function_call_batch(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        calls,
                         &self.connection.options,
                         network.burnchain_tip.canonical_stacks_tip_height,
                     )?;
@@ -2609,6 +4513,7 @@ impl ConversationHttp {
                             handler_opts.event_observer.as_deref(),
                             network.burnchain_tip.canonical_stacks_tip_height,
                             network.ast_rules,
+                            self.connection.options.mempool_admission_simulation,
                         )?;
                         if accepted {
                             // forward to peer network
@@ -2641,6 +4546,29 @@ impl ConversationHttp {
                 )?;
                 None
             }
+            HttpRequestType::GetAttachmentsStatus(ref _md) => {
+                let canonical_stacks_tip_height = network.burnchain_tip.canonical_stacks_tip_height;
+                ConversationHttp::handle_get_attachments_status(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    network,
+                    canonical_stacks_tip_height,
+                )?;
+                None
+            }
+            HttpRequestType::RefetchAttachment(ref _md, ref content_hash) => {
+                let canonical_stacks_tip_height = network.burnchain_tip.canonical_stacks_tip_height;
+                ConversationHttp::handle_refetch_attachment(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    network,
+                    content_hash,
+                    canonical_stacks_tip_height,
+                )?;
+                None
+            }
             HttpRequestType::GetAttachmentsInv(
                 ref _md,
                 ref index_block_hash,
@@ -3213,6 +5141,21 @@ impl ConversationHttp {
         )
     }
 
+    /// Make a new request for the PoX information of a reward cycle to this endpoint
+    pub fn new_getpoxcycleinfo(
+        &self,
+        reward_cycle: u64,
+        pox_address: Option<StacksAddress>,
+        tip_req: TipRequest,
+    ) -> HttpRequestType {
+        HttpRequestType::GetPoxCycleInfo(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            reward_cycle,
+            pox_address,
+            tip_req,
+        )
+    }
+
     /// Make a new getneighbors request to this endpoint
     pub fn new_getneighbors(&self) -> HttpRequestType {
         HttpRequestType::GetNeighbors(HttpRequestMetadata::from_host(self.peer_host.clone(), None))
@@ -3227,6 +5170,68 @@ impl ConversationHttp {
         )
     }
 
+    /// Make a new request for a batch of light client headers to this endpoint
+    pub fn new_get_light_headers(
+        &self,
+        start_height: u64,
+        count: u64,
+        tip_req: TipRequest,
+    ) -> HttpRequestType {
+        HttpRequestType::GetLightHeaders(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            start_height,
+            count,
+            tip_req,
+        )
+    }
+
+    /// Make a new request for a page of the finalized blocks feed to this endpoint
+    pub fn new_get_finalized_blocks(&self, page: Option<u64>) -> HttpRequestType {
+        HttpRequestType::GetFinalizedBlocks(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            page,
+        )
+    }
+
+    /// Make a new request for the burnchain operations at a burnchain block height to this endpoint
+    pub fn new_get_burn_ops(&self, burn_height: u64) -> HttpRequestType {
+        HttpRequestType::GetBurnOps(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            burn_height,
+        )
+    }
+
+    /// Make a new request for a sortition to this endpoint
+    pub fn new_get_sortition(&self, consensus_hash: ConsensusHash) -> HttpRequestType {
+        HttpRequestType::GetSortition(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            consensus_hash,
+        )
+    }
+
+    /// Make a new request for an account's or a contract's events to this endpoint
+    pub fn new_get_account_events(
+        &self,
+        principal: PrincipalData,
+        query: AccountEventsQuery,
+        tip_req: TipRequest,
+    ) -> HttpRequestType {
+        HttpRequestType::GetAccountEvents(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            principal,
+            query,
+            tip_req,
+        )
+    }
+
+    /// Make a new request for a transaction's status to this endpoint
+    pub fn new_gettransaction_status(&self, txid: Txid) -> HttpRequestType {
+        HttpRequestType::GetTransactionStatus(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            txid,
+        )
+    }
+
     /// Make a new getblock request to this endpoint
     pub fn new_getblock(&self, index_block_hash: StacksBlockId) -> HttpRequestType {
         HttpRequestType::GetBlock(
@@ -3270,6 +5275,20 @@ impl ConversationHttp {
         )
     }
 
+    /// Make a new request for the microblock stream built on an anchored block, or for the
+    /// microblock with the given sequence number in it
+    pub fn new_getmicroblocks_by_parent(
+        &self,
+        parent_block_hash: BlockHeaderHash,
+        seq: Option<u16>,
+    ) -> HttpRequestType {
+        HttpRequestType::GetMicroblocksByParent(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            parent_block_hash,
+            seq,
+        )
+    }
+
     /// Make a new get-unconfirmed-tx request
     pub fn new_gettransaction_unconfirmed(&self, txid: Txid) -> HttpRequestType {
         HttpRequestType::GetTransactionUnconfirmed(
@@ -3364,6 +5383,35 @@ impl ConversationHttp {
         )
     }
 
+    /// Make a new request for a page of a data map's entries
+    pub fn new_get_map_entries(
+        &self,
+        contract_addr: StacksAddress,
+        contract_name: ContractName,
+        map_name: ClarityName,
+        cursor: Option<Value>,
+        limit: u64,
+        tip_req: TipRequest,
+    ) -> HttpRequestType {
+        HttpRequestType::GetMapEntries(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            contract_addr,
+            contract_name,
+            map_name,
+            cursor,
+            limit,
+            tip_req,
+        )
+    }
+
+    /// Make a new request for the chainstate consistency hash
+    pub fn new_get_consistency_hash(&self, tip_req: TipRequest) -> HttpRequestType {
+        HttpRequestType::GetConsistencyHash(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            tip_req,
+        )
+    }
+
     /// Make a new request to get a contract's source
     pub fn new_getcontractsrc(
         &self,
@@ -3414,6 +5462,20 @@ impl ConversationHttp {
             function_name,
             function_args,
             tip_req,
+            false,
+        )
+    }
+
+    /// Make a new request to run a batch of read-only functions
+    pub fn new_callreadonlyfunction_batch(
+        &self,
+        calls: Vec<ReadOnlyCall>,
+        tip_req: TipRequest,
+    ) -> HttpRequestType {
+        HttpRequestType::CallReadOnlyFunctionBatch(
+            HttpRequestMetadata::from_host(self.peer_host.clone(), None),
+            calls,
+            tip_req,
         )
     }
 
@@ -3457,15 +5519,21 @@ mod test {
     use crate::chainstate::stacks::db::blocks::test::*;
     use crate::chainstate::stacks::db::StacksChainState;
     use crate::chainstate::stacks::db::StreamCursor;
+    use crate::chainstate::stacks::events::{StacksTransactionEvent, StacksTransactionReceipt};
     use crate::chainstate::stacks::miner::*;
     use crate::chainstate::stacks::test::*;
     use crate::chainstate::stacks::Error as chain_error;
     use crate::chainstate::stacks::*;
     use crate::net::codec::*;
     use crate::net::http::*;
+    use crate::net::light_client::LightHeaderChain;
     use crate::net::test::*;
     use crate::net::*;
+    use clarity::vm::costs::ExecutionCost;
+    use clarity::vm::events::{STXEventType, STXMintEventData, STXTransferEventData};
+    use clarity::vm::tracing::TraceEvent;
     use clarity::vm::types::*;
+    use rusqlite::types::ToSql;
     use stacks_common::address::*;
     use stacks_common::util::get_epoch_time_secs;
     use stacks_common::util::hash::hex_bytes;
@@ -4071,30 +6139,549 @@ mod test {
     /// stacks tip height, which is sent in HTTP headers as part of the request/response
     #[test]
     #[ignore]
-    fn test_rpc_getinfo() {
-        let peer_server_info = RefCell::new(None);
-        let client_stacks_height = 17;
+    fn test_rpc_getinfo() {
+        let peer_server_info = RefCell::new(None);
+        let client_stacks_height = 17;
+        test_rpc(
+            "test_rpc_getinfo",
+            40000,
+            40001,
+            50000,
+            50001,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let peer_info = RPCPeerInfoData::from_network(
+                    &peer_server.network,
+                    &peer_server.stacks_node.as_ref().unwrap().chainstate,
+                    &None,
+                    &Sha256Sum::zero(),
+                );
+
+                *peer_server_info.borrow_mut() = Some(peer_info);
+
+                convo_client.new_getinfo(Some(client_stacks_height))
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                assert_eq!(convo_client.canonical_stacks_tip_height, Some(1));
+                assert_eq!(
+                    convo_server.canonical_stacks_tip_height,
+                    Some(client_stacks_height)
+                );
+                match http_response {
+                    HttpResponseType::PeerInfo(response_md, peer_data) => {
+                        assert_eq!(Some((*peer_data).clone()), *peer_server_info.borrow());
+                        assert!(peer_data.node_public_key.is_some());
+                        assert!(peer_data.node_public_key_hash.is_some());
+                        assert_eq!(
+                            peer_data.node_public_key_hash,
+                            Some(Hash160::from_node_public_key(
+                                &peer_data
+                                    .node_public_key
+                                    .clone()
+                                    .unwrap()
+                                    .to_public_key()
+                                    .unwrap()
+                            ))
+                        );
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_getpoxinfo() {
+        // Test v2/pox (aka GetPoxInfo) endpoint.
+        // In this test, `tip_req` is set to UseLatestAnchoredTip.
+        // Thus, the query for pox info will be against the canonical Stacks tip, which we expect to succeed.
+        let pox_server_info = RefCell::new(None);
+        test_rpc(
+            "test_rpc_getpoxinfo",
+            40002,
+            40003,
+            50002,
+            50003,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let mut sortdb = peer_server.sortdb.as_mut().unwrap();
+                let chainstate = &mut peer_server.stacks_node.as_mut().unwrap().chainstate;
+                let stacks_block_id = {
+                    let tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
+                    StacksBlockHeader::make_index_block_hash(
+                        &tip.consensus_hash,
+                        &tip.anchored_block_hash,
+                    )
+                };
+                let pox_info = RPCPoxInfoData::from_db(
+                    &mut sortdb,
+                    chainstate,
+                    &stacks_block_id,
+                    &peer_client.config.burnchain,
+                )
+                .unwrap();
+                *pox_server_info.borrow_mut() = Some(pox_info);
+                convo_client.new_getpoxinfo(TipRequest::UseLatestAnchoredTip)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             convo_client,
+             convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::PoxInfo(response_md, pox_data) => {
+                        assert_eq!(Some((*pox_data).clone()), *pox_server_info.borrow());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_getpoxinfo_use_latest_tip() {
+        // Test v2/pox (aka GetPoxInfo) endpoint.
+        // In this test, we set `tip_req` to UseLatestUnconfirmedTip, and we expect that querying for pox
+        // info against the unconfirmed state will succeed.
+        let pox_server_info = RefCell::new(None);
+        test_rpc(
+            "test_rpc_getpoxinfo_use_latest_tip",
+            40004,
+            40005,
+            50004,
+            50005,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let mut sortdb = peer_server.sortdb.as_mut().unwrap();
+                let chainstate = &mut peer_server.stacks_node.as_mut().unwrap().chainstate;
+                let stacks_block_id = chainstate
+                    .unconfirmed_state
+                    .as_ref()
+                    .unwrap()
+                    .unconfirmed_chain_tip
+                    .clone();
+                let pox_info = RPCPoxInfoData::from_db(
+                    &mut sortdb,
+                    chainstate,
+                    &stacks_block_id,
+                    &peer_client.config.burnchain,
+                )
+                .unwrap();
+                *pox_server_info.borrow_mut() = Some(pox_info);
+                convo_client.new_getpoxinfo(TipRequest::UseLatestUnconfirmedTip)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::PoxInfo(response_md, pox_data) => {
+                        assert_eq!(Some((*pox_data).clone()), *pox_server_info.borrow());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_getpoxcycleinfo() {
+        // Test v2/pox/cycle/:n (aka GetPoxCycleInfo) endpoint, for the reward cycle after the
+        // current one, whose prepare phase has not ended yet.
+        let pox_server_info = RefCell::new(None);
+        let pox_address = StacksAddress {
+            version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            bytes: Hash160([0x11; 20]),
+        };
+        test_rpc(
+            "test_rpc_getpoxcycleinfo",
+            40006,
+            40007,
+            50006,
+            50007,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let mut sortdb = peer_server.sortdb.as_mut().unwrap();
+                let chainstate = &mut peer_server.stacks_node.as_mut().unwrap().chainstate;
+                let stacks_block_id = {
+                    let tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
+                    StacksBlockHeader::make_index_block_hash(
+                        &tip.consensus_hash,
+                        &tip.anchored_block_hash,
+                    )
+                };
+                let burnchain = &peer_client.config.burnchain;
+                let burn_tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn()).unwrap();
+                let next_cycle = burnchain
+                    .block_height_to_reward_cycle(burn_tip.block_height)
+                    .unwrap()
+                    + 1;
+                let pox_cycle_info = RPCPoxCycleInfoData::from_db(
+                    &mut sortdb,
+                    chainstate,
+                    &stacks_block_id,
+                    burnchain,
+                    next_cycle,
+                    Some(&pox_address),
+                )
+                .unwrap();
+                assert_eq!(pox_cycle_info.reward_cycle, next_cycle);
+                assert_eq!(pox_cycle_info.anchor_block.status, "pending");
+                assert_eq!(pox_cycle_info.pox_address_reward_slots, Some(0));
+                *pox_server_info.borrow_mut() = Some(pox_cycle_info);
+                convo_client.new_getpoxcycleinfo(
+                    next_cycle,
+                    Some(pox_address.clone()),
+                    TipRequest::UseLatestAnchoredTip,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::PoxCycleInfo(response_md, pox_cycle_data) => {
+                        assert_eq!(Some((*pox_cycle_data).clone()), *pox_server_info.borrow());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_getneighbors() {
+        test_rpc(
+            "test_rpc_getneighbors",
+            40010,
+            40011,
+            50010,
+            50011,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| { convo_client.new_getneighbors() },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::Neighbors(response_md, neighbor_info) => {
+                        assert_eq!(neighbor_info.sample.len(), 1);
+                        assert_eq!(neighbor_info.sample[0].port, peer_client.config.server_port); // we see ourselves as the neighbor
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_get_finalized_blocks() {
+        test_rpc(
+            "test_rpc_get_finalized_blocks",
+            40192,
+            40193,
+            50192,
+            50193,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                // the server's only block counts as final
+                convo_server
+                    .connection
+                    .options
+                    .finalized_block_confirmations = 0;
+                convo_client.new_get_finalized_blocks(None)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::FinalizedBlocks(_, page) => {
+                        let sortdb = peer_server.sortdb.as_ref().unwrap();
+                        let chainstate = &peer_server.stacks_node.as_ref().unwrap().chainstate;
+                        let tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
+                        assert_eq!(page.page, 0);
+                        assert_eq!(page.finalized_height, 1);
+                        assert!(!page.complete);
+                        assert_eq!(page.prev, None);
+                        assert_eq!(page.next, None);
+                        assert_eq!(page.blocks.len(), 1);
+                        assert_eq!(page.blocks[0].height, 1);
+                        assert_eq!(page.blocks[0].block_hash, tip.anchored_block_hash);
+                        assert_eq!(page.blocks[0].consensus_hash, tip.consensus_hash);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_get_finalized_blocks_unconfirmed() {
+        test_rpc(
+            "test_rpc_get_finalized_blocks_unconfirmed",
+            40194,
+            40195,
+            50194,
+            50195,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                // the server's only block is not deep enough to be listed
+                convo_client.new_get_finalized_blocks(Some(1))
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::NotFound(..) => true,
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_get_burn_ops() {
+        test_rpc(
+            "test_rpc_get_burn_ops",
+            40833,
+            40834,
+            50833,
+            50834,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let sortdb = peer_server.sortdb.as_ref().unwrap();
+                let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn()).unwrap();
+                convo_client.new_get_burn_ops(tip.block_height)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::BurnOps(_, burn_ops) => {
+                        let sortdb = peer_server.sortdb.as_ref().unwrap();
+                        let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn()).unwrap();
+                        assert_eq!(burn_ops.burn_block_height, tip.block_height);
+                        assert_eq!(burn_ops.burn_header_hash, tip.burn_header_hash.to_hex());
+                        assert_eq!(burn_ops.consensus_hash, tip.consensus_hash);
+                        assert!(burn_ops
+                            .block_commits
+                            .iter()
+                            .any(|commit| commit.txid == tip.winning_block_txid.to_hex()));
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_get_sortition() {
+        test_rpc(
+            "test_rpc_get_sortition",
+            40835,
+            40836,
+            50835,
+            50836,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let sortdb = peer_server.sortdb.as_ref().unwrap();
+                let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn()).unwrap();
+                convo_client.new_get_sortition(tip.consensus_hash)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::Sortition(_, sortition) => {
+                        let sortdb = peer_server.sortdb.as_ref().unwrap();
+                        let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn()).unwrap();
+                        assert_eq!(sortition.burn_block_height, tip.block_height);
+                        assert_eq!(sortition.consensus_hash, tip.consensus_hash);
+                        assert!(sortition.sortition);
+                        assert!(sortition.canonical);
+                        let winner = sortition.winning_block_commit.as_ref().unwrap();
+                        assert_eq!(winner.txid, tip.winning_block_txid.to_hex());
+                        assert_eq!(winner.block_header_hash, tip.winning_stacks_block_hash);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_get_account_events() {
+        let recipient = PrincipalData::from(StacksAddress {
+            version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            bytes: Hash160([0x11; 20]),
+        });
         test_rpc(
-            "test_rpc_getinfo",
-            40000,
-            40001,
-            50000,
-            50001,
+            "test_rpc_get_account_events",
+            40196,
+            40197,
+            50196,
+            50197,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let peer_info = RPCPeerInfoData::from_network(
-                    &peer_server.network,
-                    &peer_server.stacks_node.as_ref().unwrap().chainstate,
-                    &None,
-                    &Sha256Sum::zero(),
+                // index a transfer to and a mint for the recipient in the server's tip
+                let sortdb = peer_server.sortdb.as_ref().unwrap();
+                let chainstate = &mut peer_server.stacks_node.as_mut().unwrap().chainstate;
+                let tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
+                let header = StacksChainState::get_anchored_block_header_info(
+                    chainstate.db(),
+                    &tip.consensus_hash,
+                    &tip.anchored_block_hash,
+                )
+                .unwrap()
+                .unwrap();
+                let tx = StacksTransaction::new(
+                    TransactionVersion::Testnet,
+                    TransactionAuth::from_p2pkh(&StacksPrivateKey::new()).unwrap(),
+                    TransactionPayload::Coinbase(CoinbasePayload([0x22; 32])),
                 );
-
-                *peer_server_info.borrow_mut() = Some(peer_info);
-
-                convo_client.new_getinfo(Some(client_stacks_height))
+                let events = vec![
+                    StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(
+                        STXTransferEventData {
+                            sender: PrincipalData::from(StacksAddress {
+                                version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+                                bytes: Hash160([0x33; 20]),
+                            }),
+                            recipient: recipient.clone(),
+                            amount: 123,
+                        },
+                    )),
+                    StacksTransactionEvent::STXEvent(STXEventType::STXMintEvent(
+                        STXMintEventData {
+                            recipient: recipient.clone(),
+                            amount: 456,
+                        },
+                    )),
+                ];
+                let receipt = StacksTransactionReceipt::from_stx_transfer(
+                    tx,
+                    events,
+                    Value::okay_true(),
+                    ExecutionCost::zero(),
+                );
+                let db_tx = chainstate.db_tx_begin().unwrap();
+                StacksChainState::add_account_event_index(&db_tx).unwrap();
+                StacksChainState::insert_account_events(&db_tx, &header, &[receipt]).unwrap();
+                db_tx.commit().unwrap();
+
+                convo_client.new_get_account_events(
+                    recipient.clone(),
+                    AccountEventsQuery {
+                        after_height: Some(0),
+                        before_height: None,
+                        event_types: vec!["stx_mint_event".to_string()],
+                        cursor: None,
+                        limit: 10,
+                    },
+                    TipRequest::UseLatestAnchoredTip,
+                )
             },
             |ref http_request,
              ref http_response,
@@ -4102,28 +6689,13 @@ mod test {
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
-                assert_eq!(convo_client.canonical_stacks_tip_height, Some(1));
-                assert_eq!(
-                    convo_server.canonical_stacks_tip_height,
-                    Some(client_stacks_height)
-                );
                 match http_response {
-                    HttpResponseType::PeerInfo(response_md, peer_data) => {
-                        assert_eq!(Some((*peer_data).clone()), *peer_server_info.borrow());
-                        assert!(peer_data.node_public_key.is_some());
-                        assert!(peer_data.node_public_key_hash.is_some());
-                        assert_eq!(
-                            peer_data.node_public_key_hash,
-                            Some(Hash160::from_node_public_key(
-                                &peer_data
-                                    .node_public_key
-                                    .clone()
-                                    .unwrap()
-                                    .to_public_key()
-                                    .unwrap()
-                            ))
-                        );
+                    HttpResponseType::AccountEvents(_, page) => {
+                        assert_eq!(page.events.len(), 1);
+                        assert_eq!(page.events[0]["type"], "stx_mint_event");
+                        assert_eq!(page.events[0]["block_height"], 1);
+                        assert_eq!(page.events[0]["stx_mint_event"]["amount"], "456");
+                        assert_eq!(page.next_cursor, None);
                         true
                     }
                     _ => {
@@ -4137,53 +6709,44 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_getpoxinfo() {
-        // Test v2/pox (aka GetPoxInfo) endpoint.
-        // In this test, `tip_req` is set to UseLatestAnchoredTip.
-        // Thus, the query for pox info will be against the canonical Stacks tip, which we expect to succeed.
-        let pox_server_info = RefCell::new(None);
+    fn test_rpc_get_account_events_not_indexed() {
         test_rpc(
-            "test_rpc_getpoxinfo",
-            40002,
-            40003,
-            50002,
-            50003,
+            "test_rpc_get_account_events_not_indexed",
+            40198,
+            40199,
+            50198,
+            50199,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let mut sortdb = peer_server.sortdb.as_mut().unwrap();
-                let chainstate = &mut peer_server.stacks_node.as_mut().unwrap().chainstate;
-                let stacks_block_id = {
-                    let tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
-                    StacksBlockHeader::make_index_block_hash(
-                        &tip.consensus_hash,
-                        &tip.anchored_block_hash,
+                let contract = PrincipalData::Contract(
+                    QualifiedContractIdentifier::parse(
+                        "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world",
                     )
-                };
-                let pox_info = RPCPoxInfoData::from_db(
-                    &mut sortdb,
-                    chainstate,
-                    &stacks_block_id,
-                    &peer_client.config.burnchain,
+                    .unwrap(),
+                );
+                convo_client.new_get_account_events(
+                    contract,
+                    AccountEventsQuery {
+                        after_height: None,
+                        before_height: None,
+                        event_types: vec![],
+                        cursor: None,
+                        limit: ACCOUNT_EVENTS_PAGE_SIZE,
+                    },
+                    TipRequest::UseLatestAnchoredTip,
                 )
-                .unwrap();
-                *pox_server_info.borrow_mut() = Some(pox_info);
-                convo_client.new_getpoxinfo(TipRequest::UseLatestAnchoredTip)
             },
             |ref http_request,
              ref http_response,
              ref mut peer_client,
              ref mut peer_server,
-             convo_client,
-             convo_server| {
-                let req_md = http_request.metadata().clone();
+             ref convo_client,
+             ref convo_server| {
                 match http_response {
-                    HttpResponseType::PoxInfo(response_md, pox_data) => {
-                        assert_eq!(Some((*pox_data).clone()), *pox_server_info.borrow());
-                        true
-                    }
+                    HttpResponseType::NotFound(..) => true,
                     _ => {
                         error!("Invalid response: {:?}", &http_response);
                         false
@@ -4195,39 +6758,64 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_getpoxinfo_use_latest_tip() {
-        // Test v2/pox (aka GetPoxInfo) endpoint.
-        // In this test, we set `tip_req` to UseLatestUnconfirmedTip, and we expect that querying for pox
-        // info against the unconfirmed state will succeed.
-        let pox_server_info = RefCell::new(None);
+    fn test_rpc_gettransaction_status_confirmed() {
+        let tx = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(&StacksPrivateKey::new()).unwrap(),
+            TransactionPayload::Coinbase(CoinbasePayload([0x44; 32])),
+        );
+        let txid = tx.txid();
         test_rpc(
-            "test_rpc_getpoxinfo_use_latest_tip",
-            40004,
-            40005,
-            50004,
-            50005,
+            "test_rpc_gettransaction_status_confirmed",
+            40200,
+            40201,
+            50200,
+            50201,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let mut sortdb = peer_server.sortdb.as_mut().unwrap();
+                // log the transaction as confirmed in the server's tip
+                let sortdb = peer_server.sortdb.as_ref().unwrap();
                 let chainstate = &mut peer_server.stacks_node.as_mut().unwrap().chainstate;
-                let stacks_block_id = chainstate
-                    .unconfirmed_state
-                    .as_ref()
-                    .unwrap()
-                    .unconfirmed_chain_tip
-                    .clone();
-                let pox_info = RPCPoxInfoData::from_db(
-                    &mut sortdb,
-                    chainstate,
-                    &stacks_block_id,
-                    &peer_client.config.burnchain,
+                let tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
+                let index_block_hash = StacksBlockHeader::make_index_block_hash(
+                    &tip.consensus_hash,
+                    &tip.anchored_block_hash,
+                );
+                let receipt = StacksTransactionReceipt::from_stx_transfer(
+                    tx,
+                    vec![StacksTransactionEvent::STXEvent(
+                        STXEventType::STXMintEvent(STXMintEventData {
+                            recipient: PrincipalData::from(StacksAddress {
+                                version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+                                bytes: Hash160([0x11; 20]),
+                            }),
+                            amount: 456,
+                        }),
+                    )],
+                    Value::okay_true(),
+                    ExecutionCost::zero(),
+                );
+                let db_tx = chainstate.db_tx_begin().unwrap();
+                let args: &[&dyn ToSql] = &[&txid, &index_block_hash, &"00", &"(ok true)"];
+                db_tx
+                    .execute(
+                        "INSERT INTO transactions (txid, index_block_hash, tx_hex, result) VALUES (?, ?, ?, ?)",
+                        args,
+                    )
+                    .unwrap();
+                StacksChainState::add_transaction_log_receipts(&db_tx).unwrap();
+                StacksChainState::insert_transaction_log_receipts(
+                    &db_tx,
+                    &index_block_hash,
+                    &[receipt],
                 )
                 .unwrap();
-                *pox_server_info.borrow_mut() = Some(pox_info);
-                convo_client.new_getpoxinfo(TipRequest::UseLatestUnconfirmedTip)
+                db_tx.commit().unwrap();
+
+                convo_client.new_gettransaction_status(txid)
             },
             |ref http_request,
              ref http_response,
@@ -4235,10 +6823,17 @@ mod test {
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::PoxInfo(response_md, pox_data) => {
-                        assert_eq!(Some((*pox_data).clone()), *pox_server_info.borrow());
+                    HttpResponseType::TransactionStatus(_, status) => {
+                        assert_eq!(status.status, TransactionStatus::Confirmed);
+                        assert_eq!(status.mempool, None);
+                        let confirmed = status.confirmed.as_ref().unwrap();
+                        assert_eq!(confirmed.block_height, 1);
+                        assert_eq!(confirmed.result, "(ok true)");
+                        assert_eq!(confirmed.raw_result.as_deref(), Some("0x0703"));
+                        let events = confirmed.events.as_ref().unwrap();
+                        assert_eq!(events.len(), 1);
+                        assert_eq!(events[0]["type"], "stx_mint_event");
                         true
                     }
                     _ => {
@@ -4252,29 +6847,32 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_getneighbors() {
+    fn test_rpc_gettransaction_status_unknown() {
         test_rpc(
-            "test_rpc_getneighbors",
-            40010,
-            40011,
-            50010,
-            50011,
+            "test_rpc_gettransaction_status_unknown",
+            40202,
+            40203,
+            50202,
+            50203,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
-             ref mut convo_server| { convo_client.new_getneighbors() },
+             ref mut convo_server| {
+                convo_client.new_gettransaction_status(Txid([0x55; 32]))
+            },
             |ref http_request,
              ref http_response,
              ref mut peer_client,
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::Neighbors(response_md, neighbor_info) => {
-                        assert_eq!(neighbor_info.sample.len(), 1);
-                        assert_eq!(neighbor_info.sample[0].port, peer_client.config.server_port); // we see ourselves as the neighbor
+                    HttpResponseType::TransactionStatus(_, status) => {
+                        assert_eq!(status.txid, Txid([0x55; 32]));
+                        assert_eq!(status.status, TransactionStatus::Unknown);
+                        assert_eq!(status.mempool, None);
+                        assert_eq!(status.confirmed, None);
                         true
                     }
                     _ => {
@@ -4766,8 +7364,134 @@ mod test {
 
                 let consensus_hash = ConsensusHash([0x02; 20]);
                 let anchored_block_hash = BlockHeaderHash([0x03; 32]);
-                let index_block_hash =
-                    StacksBlockHeader::make_index_block_hash(&consensus_hash, &anchored_block_hash);
+                let index_block_hash =
+                    StacksBlockHeader::make_index_block_hash(&consensus_hash, &anchored_block_hash);
+
+                let mut mblocks = make_sample_microblock_stream(&privk, &anchored_block_hash);
+                mblocks.truncate(15);
+
+                for mblock in mblocks.iter() {
+                    store_staging_microblock(
+                        peer_server.chainstate(),
+                        &consensus_hash,
+                        &anchored_block_hash,
+                        &mblock,
+                    );
+                }
+
+                *server_microblocks_cell.borrow_mut() = mblocks;
+
+                // start at seq 5
+                convo_client.new_getmicroblocks_unconfirmed(index_block_hash, 5)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::Microblocks(response_md, microblocks) => {
+                        assert_eq!(microblocks.len(), 10);
+                        assert_eq!(
+                            *microblocks,
+                            (*server_microblocks_cell.borrow())[5..].to_vec()
+                        );
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_microblocks_by_parent() {
+        let server_microblocks_cell = RefCell::new(vec![]);
+
+        test_rpc(
+            "test_rpc_microblocks_by_parent",
+            40825,
+            40826,
+            50825,
+            50826,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let privk = StacksPrivateKey::from_hex(
+                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                )
+                .unwrap();
+
+                let consensus_hash = ConsensusHash([0x02; 20]);
+                let anchored_block_hash = BlockHeaderHash([0x03; 32]);
+
+                let mut mblocks = make_sample_microblock_stream(&privk, &anchored_block_hash);
+                mblocks.truncate(15);
+
+                for mblock in mblocks.iter() {
+                    store_staging_microblock(
+                        peer_server.chainstate(),
+                        &consensus_hash,
+                        &anchored_block_hash,
+                        &mblock,
+                    );
+                }
+
+                *server_microblocks_cell.borrow_mut() = mblocks;
+
+                convo_client.new_getmicroblocks_by_parent(anchored_block_hash, None)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::Microblocks(_, microblocks) => {
+                        assert_eq!(*microblocks, *server_microblocks_cell.borrow());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response: {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_microblock_by_parent_and_sequence() {
+        let server_microblocks_cell = RefCell::new(vec![]);
+
+        test_rpc(
+            "test_rpc_microblock_by_parent_and_sequence",
+            40827,
+            40828,
+            50827,
+            50828,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let privk = StacksPrivateKey::from_hex(
+                    "eb05c83546fdd2c79f10f5ad5434a90dd28f7e3acb7c092157aa1bc3656b012c01",
+                )
+                .unwrap();
+
+                let consensus_hash = ConsensusHash([0x02; 20]);
+                let anchored_block_hash = BlockHeaderHash([0x03; 32]);
 
                 let mut mblocks = make_sample_microblock_stream(&privk, &anchored_block_hash);
                 mblocks.truncate(15);
@@ -4783,8 +7507,7 @@ mod test {
 
                 *server_microblocks_cell.borrow_mut() = mblocks;
 
-                // start at seq 5
-                convo_client.new_getmicroblocks_unconfirmed(index_block_hash, 5)
+                convo_client.new_getmicroblocks_by_parent(anchored_block_hash, Some(7))
             },
             |ref http_request,
              ref http_response,
@@ -4792,13 +7515,11 @@ mod test {
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::Microblocks(response_md, microblocks) => {
-                        assert_eq!(microblocks.len(), 10);
+                    HttpResponseType::Microblocks(_, microblocks) => {
                         assert_eq!(
                             *microblocks,
-                            (*server_microblocks_cell.borrow())[5..].to_vec()
+                            vec![(*server_microblocks_cell.borrow())[7].clone()]
                         );
                         true
                     }
@@ -5517,6 +8238,149 @@ mod test {
         );
     }
 
+    #[test]
+    #[ignore]
+    fn test_rpc_get_data_var_at_height() {
+        test_rpc(
+            "test_rpc_get_data_var_at_height",
+            40206,
+            40207,
+            50206,
+            50207,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getdatavar(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    "bar".try_into().unwrap(),
+                    TipRequest::SpecificHeight(1),
+                    true,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::GetDataVar(response_md, data) => {
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
+                            Value::Int(0)
+                        );
+                        assert!(data.marf_proof.is_some());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_get_data_var_at_missing_height() {
+        test_rpc(
+            "test_rpc_get_data_var_at_missing_height",
+            40208,
+            40209,
+            50208,
+            50209,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getdatavar(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    "bar".try_into().unwrap(),
+                    TipRequest::SpecificHeight(1000),
+                    true,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::NotFound(_, msg) => {
+                        assert_eq!(msg, "No block at height 1000 in the canonical Stacks chain");
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_get_light_headers() {
+        test_rpc(
+            "test_rpc_get_light_headers",
+            40210,
+            40211,
+            50210,
+            50211,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_get_light_headers(0, 10, TipRequest::UseLatestAnchoredTip)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let sortdb = peer_server.sortdb.as_ref().unwrap();
+                let chainstate = &mut peer_server.stacks_node.as_mut().unwrap().chainstate;
+                let stacks_tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
+                let tip = StacksBlockHeader::make_index_block_hash(
+                    &stacks_tip.consensus_hash,
+                    &stacks_tip.anchored_block_hash,
+                );
+                match http_response {
+                    HttpResponseType::LightHeaders(_, batch) => {
+                        assert_eq!(batch.tip, tip);
+                        assert_eq!(batch.tip_height, stacks_tip.height);
+                        // the boot block is not served
+                        assert_eq!(batch.headers.len() as u64, stacks_tip.height);
+                        assert_eq!(batch.headers[0].height, 1);
+
+                        let mut light_chain = LightHeaderChain::new(batch.headers[0].clone(), 10);
+                        let mut rest = batch.clone();
+                        rest.headers.remove(0);
+                        assert_eq!(light_chain.apply_batch(&rest), Ok(0));
+                        assert_eq!(light_chain.tip().index_block_hash(), tip);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
     #[test]
     #[ignore]
     fn test_rpc_get_data_var_unconfirmed() {
@@ -5646,7 +8510,181 @@ mod test {
                             .unwrap(),
                     ),
                     TipRequest::UseLatestAnchoredTip,
-                    false,
+                    false,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::GetMapEntry(response_md, data) => {
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
+                            Value::some(Value::Tuple(
+                                TupleData::from_data(vec![("units".into(), Value::Int(123))])
+                                    .unwrap()
+                            ))
+                            .unwrap()
+                        );
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_get_map_entry_unconfirmed() {
+        // Test v2/map_entry (aka GetMapEntry) endpoint.
+        // In this test, we set `tip_req` to UseLatestUnconfirmedTip, and we expect that querying for map data
+        // against the unconfirmed state will succeed.
+        test_rpc(
+            "test_rpc_get_map_entry_unconfirmed",
+            40140,
+            40141,
+            50140,
+            50141,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let unconfirmed_tip = peer_client
+                    .chainstate()
+                    .unconfirmed_state
+                    .as_ref()
+                    .unwrap()
+                    .unconfirmed_chain_tip
+                    .clone();
+                let principal =
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal();
+                convo_client.new_getmapentry(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    "unit-map".try_into().unwrap(),
+                    Value::Tuple(
+                        TupleData::from_data(vec![("account".into(), Value::Principal(principal))])
+                            .unwrap(),
+                    ),
+                    TipRequest::SpecificTip(unconfirmed_tip),
+                    false,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::GetMapEntry(response_md, data) => {
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
+                            Value::some(Value::Tuple(
+                                TupleData::from_data(vec![("units".into(), Value::Int(1))])
+                                    .unwrap()
+                            ))
+                            .unwrap()
+                        );
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_get_consistency_hash() {
+        // Test v2/consistency_hash (aka GetConsistencyHash) endpoint.
+        // The hash is reported for the canonical Stacks tip, and commits to its state root.
+        test_rpc(
+            "test_rpc_get_consistency_hash",
+            40800,
+            40801,
+            50800,
+            50801,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_get_consistency_hash(TipRequest::UseLatestAnchoredTip)
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let sortdb = peer_server.sortdb.as_ref().unwrap();
+                let chainstate = &peer_server.stacks_node.as_ref().unwrap().chainstate;
+                let tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
+                let tip_id = StacksBlockHeader::make_index_block_hash(
+                    &tip.consensus_hash,
+                    &tip.anchored_block_hash,
+                );
+                let expected = chainstate
+                    .get_consistency_hash(sortdb, &tip_id)
+                    .unwrap()
+                    .unwrap();
+                match http_response {
+                    HttpResponseType::ConsistencyHash(response_md, consistency_hash) => {
+                        assert_eq!(consistency_hash, &expected);
+                        assert_eq!(consistency_hash.index_block_hash, tip_id);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_get_map_entries() {
+        // Test v2/map_entries (aka GetMapEntries) endpoint.
+        // At the canonical Stacks tip, the map has the single entry set when the contract was
+        // deployed.
+        test_rpc(
+            "test_rpc_get_map_entries",
+            40829,
+            40830,
+            50829,
+            50830,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_get_map_entries(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    "unit-map".try_into().unwrap(),
+                    None,
+                    MAP_ENTRIES_PAGE_SIZE,
+                    TipRequest::UseLatestAnchoredTip,
                 )
             },
             |ref http_request,
@@ -5655,17 +8693,31 @@ mod test {
              ref mut peer_server,
              ref convo_client,
              ref convo_server| {
-                let req_md = http_request.metadata().clone();
+                let principal =
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal();
                 match http_response {
-                    HttpResponseType::GetMapEntry(response_md, data) => {
+                    HttpResponseType::MapEntries(response_md, page) => {
+                        assert_eq!(page.entries.len(), 1);
                         assert_eq!(
-                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
-                            Value::some(Value::Tuple(
+                            Value::try_deserialize_hex_untyped(&page.entries[0].key).unwrap(),
+                            Value::Tuple(
+                                TupleData::from_data(vec![(
+                                    "account".into(),
+                                    Value::Principal(principal)
+                                )])
+                                .unwrap()
+                            )
+                        );
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&page.entries[0].value).unwrap(),
+                            Value::Tuple(
                                 TupleData::from_data(vec![("units".into(), Value::Int(123))])
                                     .unwrap()
-                            ))
-                            .unwrap()
+                            )
                         );
+                        assert_eq!(page.next_cursor, None);
                         true
                     }
                     _ => {
@@ -5679,43 +8731,26 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_map_entry_unconfirmed() {
-        // Test v2/map_entry (aka GetMapEntry) endpoint.
-        // In this test, we set `tip_req` to UseLatestUnconfirmedTip, and we expect that querying for map data
-        // against the unconfirmed state will succeed.
+    fn test_rpc_get_map_entries_no_such_map() {
         test_rpc(
-            "test_rpc_get_map_entry_unconfirmed",
-            40140,
-            40141,
-            50140,
-            50141,
+            "test_rpc_get_map_entries_no_such_map",
+            40831,
+            40832,
+            50831,
+            50832,
             true,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                let unconfirmed_tip = peer_client
-                    .chainstate()
-                    .unconfirmed_state
-                    .as_ref()
-                    .unwrap()
-                    .unconfirmed_chain_tip
-                    .clone();
-                let principal =
-                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap()
-                        .to_account_principal();
-                convo_client.new_getmapentry(
+                convo_client.new_get_map_entries(
                     StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
                         .unwrap(),
                     "hello-world".try_into().unwrap(),
-                    "unit-map".try_into().unwrap(),
-                    Value::Tuple(
-                        TupleData::from_data(vec![("account".into(), Value::Principal(principal))])
-                            .unwrap(),
-                    ),
-                    TipRequest::SpecificTip(unconfirmed_tip),
-                    false,
+                    "no-such-map".try_into().unwrap(),
+                    None,
+                    MAP_ENTRIES_PAGE_SIZE,
+                    TipRequest::UseLatestAnchoredTip,
                 )
             },
             |ref http_request,
@@ -5723,24 +8758,14 @@ mod test {
              ref mut peer_client,
              ref mut peer_server,
              ref convo_client,
-             ref convo_server| {
-                let req_md = http_request.metadata().clone();
-                match http_response {
-                    HttpResponseType::GetMapEntry(response_md, data) => {
-                        assert_eq!(
-                            Value::try_deserialize_hex_untyped(&data.data).unwrap(),
-                            Value::some(Value::Tuple(
-                                TupleData::from_data(vec![("units".into(), Value::Int(1))])
-                                    .unwrap()
-                            ))
-                            .unwrap()
-                        );
-                        true
-                    }
-                    _ => {
-                        error!("Invalid response; {:?}", &http_response);
-                        false
-                    }
+             ref convo_server| match http_response {
+                HttpResponseType::NotFound(_, msg) => {
+                    assert!(msg.contains("no-such-map"));
+                    true
+                }
+                _ => {
+                    error!("Invalid response; {:?}", &http_response);
+                    false
                 }
             },
         );
@@ -5938,6 +8963,175 @@ mod test {
         );
     }
 
+    #[test]
+    #[ignore]
+    fn test_rpc_get_contract_abi_with_source() {
+        test_rpc(
+            "test_rpc_get_contract_abi_with_source",
+            40819,
+            40820,
+            50819,
+            50820,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getcontractabi(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    TipRequest::UseLatestAnchoredTip,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::GetContractABI(_, data) => {
+                        assert!(data.source.contains("(define-data-var bar int 0)"));
+                        assert_eq!(data.clarity_version, CLARITY_VERSION);
+                        assert!(data.implemented_traits.is_empty());
+                        assert!(data
+                            .interface
+                            .functions
+                            .iter()
+                            .any(|function| function.name == "get-bar"));
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_get_contract_abi_not_modified() {
+        test_rpc(
+            "test_rpc_get_contract_abi_not_modified",
+            40821,
+            40822,
+            50821,
+            50822,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let mut request = convo_client.new_getcontractabi(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    TipRequest::UseLatestAnchoredTip,
+                );
+                request.metadata_mut().if_none_match = Some("*".to_string());
+                request
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::NotModified(_, etag) => {
+                        assert!(etag.starts_with('"') && etag.ends_with('"'));
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn test_etag_matches() {
+        let etag = "\"abcd\"";
+        assert!(!ConversationHttp::etag_matches(None, etag));
+        assert!(ConversationHttp::etag_matches(
+            Some(&"\"abcd\"".to_string()),
+            etag
+        ));
+        assert!(ConversationHttp::etag_matches(
+            Some(&"\"1234\", W/\"abcd\"".to_string()),
+            etag
+        ));
+        assert!(ConversationHttp::etag_matches(Some(&"*".to_string()), etag));
+        assert!(!ConversationHttp::etag_matches(
+            Some(&"\"1234\"".to_string()),
+            etag
+        ));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_dry_run_transaction() {
+        test_rpc(
+            "test_rpc_dry_run_transaction",
+            40823,
+            40824,
+            50823,
+            50824,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let sender =
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap();
+                HttpRequestType::DryRunTransaction(
+                    HttpRequestMetadata::from_host(convo_client.peer_host.clone(), None),
+                    sender.clone(),
+                    TransactionPayload::new_contract_call(
+                        sender,
+                        "hello-world",
+                        "set-bar",
+                        vec![Value::Int(10), Value::Int(2)],
+                    )
+                    .unwrap(),
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::DryRunTransaction(_, data) => {
+                        assert!(data.okay);
+                        assert_eq!(
+                            data.result,
+                            Some(format!(
+                                "0x{}",
+                                ClaritySerializable::serialize(
+                                    &Value::okay(Value::Int(5)).unwrap()
+                                )
+                            ))
+                        );
+                        assert!(data.execution_cost.runtime > 0);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
     #[test]
     #[ignore]
     fn test_rpc_call_read_only() {
@@ -6047,6 +9241,68 @@ mod test {
         );
     }
 
+    #[test]
+    #[ignore]
+    fn test_rpc_call_read_only_trace() {
+        // Test /v2/contracts/call-read (aka CallReadOnlyFunction) endpoint with trace=true.
+        // We expect the response to carry the entry and exit of the called function.
+        test_rpc(
+            "test_rpc_call_read_only_trace",
+            40817,
+            40818,
+            50817,
+            50818,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                HttpRequestType::CallReadOnlyFunction(
+                    HttpRequestMetadata::from_host(convo_client.peer_host.clone(), None),
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    "get-bar".try_into().unwrap(),
+                    vec![],
+                    TipRequest::UseLatestAnchoredTip,
+                    true,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                match http_response {
+                    HttpResponseType::CallReadOnlyFunction(_response_md, data) => {
+                        assert!(data.okay);
+                        let trace = data.trace.clone().unwrap();
+                        assert!(!trace.truncated);
+                        assert_eq!(trace.events.len(), 2);
+                        match &trace.events[1] {
+                            TraceEvent::Exit {
+                                function, result, ..
+                            } => {
+                                assert_eq!(function, "get-bar");
+                                assert!(result.as_ref().unwrap().starts_with("(ok "));
+                            }
+                            _ => panic!("expected an exit event"),
+                        }
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
     #[test]
     #[ignore]
     fn test_rpc_call_read_only_unconfirmed() {
@@ -6107,6 +9363,92 @@ mod test {
         );
     }
 
+    #[test]
+    #[ignore]
+    fn test_rpc_call_read_only_batch() {
+        // Test /v2/contracts/call-read-batch (aka CallReadOnlyFunctionBatch) endpoint.  Each
+        // call gets its own result, and the response names the tip they all ran against.
+        test_rpc(
+            "test_rpc_call_read_only_batch",
+            40204,
+            40205,
+            50204,
+            50205,
+            true,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                let contract_addr =
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap();
+                let call = |function_name: &str, arguments: Vec<Value>| ReadOnlyCall {
+                    contract_addr,
+                    contract_name: "hello-world".into(),
+                    sender: contract_addr.to_account_principal(),
+                    function_name: function_name.into(),
+                    arguments,
+                };
+                convo_client.new_callreadonlyfunction_batch(
+                    vec![
+                        call("get-bar", vec![]),
+                        call("set-bar", vec![Value::Int(4), Value::Int(2)]),
+                        call("no-such-function", vec![]),
+                    ],
+                    TipRequest::UseLatestAnchoredTip,
+                )
+            },
+            |ref http_request,
+             ref http_response,
+             ref mut peer_client,
+             ref mut peer_server,
+             ref convo_client,
+             ref convo_server| {
+                let sortdb = peer_server.sortdb.as_ref().unwrap();
+                let chainstate = &mut peer_server.stacks_node.as_mut().unwrap().chainstate;
+                let tip = chainstate.get_stacks_chain_tip(sortdb).unwrap().unwrap();
+                match http_response {
+                    HttpResponseType::CallReadOnlyFunctionBatch(_, data) => {
+                        assert_eq!(
+                            data.tip,
+                            StacksBlockHeader::make_index_block_hash(
+                                &tip.consensus_hash,
+                                &tip.anchored_block_hash
+                            )
+                        );
+                        assert_eq!(data.results.len(), 3);
+
+                        assert!(data.results[0].okay);
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(
+                                data.results[0].result.as_ref().unwrap()
+                            )
+                            .unwrap(),
+                            Value::okay(Value::Int(0)).unwrap()
+                        );
+
+                        // writes are not allowed
+                        assert!(!data.results[1].okay);
+                        assert_eq!(data.results[1].cause.as_deref(), Some("NotReadOnly"));
+
+                        assert!(!data.results[2].okay);
+                        assert!(data.results[2]
+                            .cause
+                            .as_ref()
+                            .unwrap()
+                            .find("UndefinedFunction")
+                            .is_some());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
     #[test]
     #[ignore]
     fn test_rpc_getattachmentsinv_limit_reached() {
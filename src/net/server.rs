@@ -38,6 +38,7 @@ use crate::net::http::*;
 use crate::net::p2p::{PeerMap, PeerNetwork};
 use crate::net::poll::*;
 use crate::net::rpc::*;
+use crate::net::socks::{SocksHandshake, SocksTarget};
 use crate::net::Error as net_error;
 use crate::net::*;
 
@@ -70,6 +71,9 @@ pub struct HttpPeer {
         ),
     >,
 
+    // outbound connections that go through the SOCKS proxy, and have yet to finish its handshake
+    socks_handshakes: HashMap<usize, SocksHandshake>,
+
     // server network handle
     pub http_server_handle: usize,
 
@@ -84,6 +88,7 @@ impl HttpPeer {
             sockets: HashMap::new(),
 
             connecting: HashMap::new(),
+            socks_handshakes: HashMap::new(),
             http_server_handle: server_handle,
 
             connection_opts: conn_opts,
@@ -147,7 +152,23 @@ impl HttpPeer {
             return Err(net_error::AlreadyConnected(event_id, http_nk));
         }
 
-        let sock = NetworkState::connect(&addr)?;
+        let (sock, handshake_opt) = match self.connection_opts.socks_proxy {
+            Some(ref proxy) => {
+                // have the proxy resolve the data URL's host name, if it has one
+                let target = match PeerHost::try_from_url(&data_url) {
+                    Some(PeerHost::DNS(host, port)) => SocksTarget::Domain(host, port),
+                    Some(PeerHost::IP(addrbytes, port)) => {
+                        SocksTarget::Addr(addrbytes.to_socketaddr(port))
+                    }
+                    None => SocksTarget::Addr(addr.clone()),
+                };
+                (
+                    NetworkState::connect(proxy)?,
+                    Some(SocksHandshake::new(target)?),
+                )
+            }
+            None => (NetworkState::connect(&addr)?, None),
+        };
         let hint_event_id = network_state.next_event_id()?;
         let next_event_id =
             network_state.register(self.http_server_handle, hint_event_id, &sock)?;
@@ -156,6 +177,9 @@ impl HttpPeer {
             next_event_id,
             (sock, Some(data_url), request, get_epoch_time_secs()),
         );
+        if let Some(handshake) = handshake_opt {
+            self.socks_handshakes.insert(next_event_id, handshake);
+        }
         Ok(next_event_id)
     }
 
@@ -218,7 +242,7 @@ impl HttpPeer {
         mempool: &MemPoolDB,
         chainstate: &mut StacksChainState,
         event_id: usize,
-        mut socket: mio_net::TcpStream,
+        socket: mio_net::TcpStream,
         outbound_url: Option<UrlString>,
         initial_request: Option<HttpRequestType>,
     ) -> Result<(), net_error> {
@@ -230,7 +254,31 @@ impl HttpPeer {
                 return Err(net_error::SocketError);
             }
         };
+        self.register_http_at(
+            network_state,
+            mempool,
+            chainstate,
+            event_id,
+            socket,
+            client_addr,
+            outbound_url,
+            initial_request,
+        )
+    }
 
+    /// Register a socket/event pair whose remote end is at client_addr.  This is not
+    /// necessarily the socket's peer address, such as when the socket goes through a proxy.
+    fn register_http_at(
+        &mut self,
+        network_state: &mut NetworkState,
+        mempool: &MemPoolDB,
+        chainstate: &mut StacksChainState,
+        event_id: usize,
+        mut socket: mio_net::TcpStream,
+        client_addr: SocketAddr,
+        outbound_url: Option<UrlString>,
+        initial_request: Option<HttpRequestType>,
+    ) -> Result<(), net_error> {
         match self.can_register_http(&client_addr, outbound_url.as_ref()) {
             Ok(_) => {}
             Err(e) => {
@@ -287,6 +335,7 @@ impl HttpPeer {
     /// Deregister a socket/event pair
     pub fn deregister_http(&mut self, network_state: &mut NetworkState, event_id: usize) -> () {
         self.peers.remove(&event_id);
+        self.socks_handshakes.remove(&event_id);
 
         match self.sockets.remove(&event_id) {
             None => {}
@@ -547,20 +596,60 @@ impl HttpPeer {
     ) -> () {
         for event_id in poll_state.ready.iter() {
             if self.connecting.contains_key(event_id) {
+                let proxied_addr_opt = match self.socks_handshakes.get_mut(event_id) {
+                    Some(handshake) => {
+                        let (socket, ..) = self.connecting.get_mut(event_id).unwrap();
+                        match handshake.step(socket) {
+                            Ok(true) => match handshake.target {
+                                SocksTarget::Addr(ref addr) => Some(addr.clone()),
+                                // all we know is the proxy's address
+                                SocksTarget::Domain(..) => None,
+                            },
+                            Ok(false) => {
+                                // proxy is still working on it
+                                continue;
+                            }
+                            Err(_e) => {
+                                debug!(
+                                    "SOCKS proxy failed to connect HTTP event {} to {}: {:?}",
+                                    event_id, &handshake.target, &_e
+                                );
+                                self.deregister_http(network_state, *event_id);
+                                continue;
+                            }
+                        }
+                    }
+                    None => None,
+                };
+                self.socks_handshakes.remove(event_id);
+
                 let (socket, data_url, initial_request_opt, _) =
                     self.connecting.remove(event_id).unwrap();
 
                 debug!("HTTP event {} connected ({:?})", event_id, &data_url);
 
-                if let Err(_e) = self.register_http(
-                    network_state,
-                    mempool,
-                    chainstate,
-                    *event_id,
-                    socket,
-                    data_url.clone(),
-                    initial_request_opt,
-                ) {
+                let res = match proxied_addr_opt {
+                    Some(client_addr) => self.register_http_at(
+                        network_state,
+                        mempool,
+                        chainstate,
+                        *event_id,
+                        socket,
+                        client_addr,
+                        data_url.clone(),
+                        initial_request_opt,
+                    ),
+                    None => self.register_http(
+                        network_state,
+                        mempool,
+                        chainstate,
+                        *event_id,
+                        socket,
+                        data_url.clone(),
+                        initial_request_opt,
+                    ),
+                };
+                if let Err(_e) = res {
                     debug!(
                         "Failed to register HTTP connection ({}, {:?})",
                         event_id, data_url
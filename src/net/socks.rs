@@ -0,0 +1,535 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2022 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! SOCKS5 client handshakes (RFC 1928), for sending outbound connections through a proxy such
+//! as Tor.
+//!
+//! Only the "no authentication" method, the CONNECT command, and Tor's RESOLVE extension
+//! command are supported.  The greeting and the request are sent together, so a handshake
+//! costs one round trip to the proxy.  Host names are handed to the proxy as-is, so that they are resolved on the far side
+//! of the proxy (which is what makes `.onion` addresses reachable, and keeps DNS queries from
+//! leaking).
+
+use std::fmt;
+use std::io;
+use std::io::{Read, Write};
+use std::net;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use crate::net::Error as net_error;
+
+const SOCKS_VERSION: u8 = 5;
+const SOCKS_METHOD_NO_AUTH: u8 = 0;
+const SOCKS_CMD_CONNECT: u8 = 1;
+/// Tor extension: resolve a host name, and reply with its address as the bound address
+const SOCKS_CMD_RESOLVE: u8 = 0xF0;
+const SOCKS_ATYP_IPV4: u8 = 1;
+const SOCKS_ATYP_DOMAIN: u8 = 3;
+const SOCKS_ATYP_IPV6: u8 = 4;
+const SOCKS_REPLY_SUCCEEDED: u8 = 0;
+
+/// Where the proxy should connect to
+#[derive(Debug, Clone, PartialEq)]
+pub enum SocksTarget {
+    Addr(SocketAddr),
+    Domain(String, u16),
+}
+
+impl fmt::Display for SocksTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SocksTarget::Addr(ref addr) => write!(f, "{}", addr),
+            SocksTarget::Domain(ref host, ref port) => write!(f, "{}:{}", host, port),
+        }
+    }
+}
+
+impl SocksTarget {
+    /// The target for a host that is either an IP address or a name for the proxy to resolve
+    pub fn from_host(host: &str, port: u16) -> SocksTarget {
+        match host.parse::<IpAddr>() {
+            Ok(ip) => SocksTarget::Addr(SocketAddr::new(ip, port)),
+            Err(_) => SocksTarget::Domain(host.to_string(), port),
+        }
+    }
+
+    /// Encode the greeting and the CONNECT request for this target
+    fn encode_request(&self) -> Result<Vec<u8>, net_error> {
+        self.encode_command(SOCKS_CMD_CONNECT)
+    }
+
+    /// Encode the greeting and a request with the given command for this target
+    fn encode_command(&self, cmd: u8) -> Result<Vec<u8>, net_error> {
+        let mut request = vec![SOCKS_VERSION, 1, SOCKS_METHOD_NO_AUTH];
+        request.extend_from_slice(&[SOCKS_VERSION, cmd, 0]);
+        let port = match *self {
+            SocksTarget::Addr(ref addr) => {
+                match addr.ip() {
+                    IpAddr::V4(ip) => {
+                        request.push(SOCKS_ATYP_IPV4);
+                        request.extend_from_slice(&ip.octets());
+                    }
+                    IpAddr::V6(ip) => {
+                        request.push(SOCKS_ATYP_IPV6);
+                        request.extend_from_slice(&ip.octets());
+                    }
+                }
+                addr.port()
+            }
+            SocksTarget::Domain(ref host, ref port) => {
+                if host.len() == 0 || host.len() > 255 {
+                    warn!("Cannot ask a SOCKS proxy to connect to '{}'", host);
+                    return Err(net_error::InvalidMessage);
+                }
+                request.push(SOCKS_ATYP_DOMAIN);
+                request.push(host.len() as u8);
+                request.extend_from_slice(host.as_bytes());
+                *port
+            }
+        };
+        request.extend_from_slice(&port.to_be_bytes());
+        Ok(request)
+    }
+}
+
+/// The state of a SOCKS5 handshake over a (possibly non-blocking) socket to the proxy
+#[derive(Debug, Clone, PartialEq)]
+pub struct SocksHandshake {
+    pub target: SocksTarget,
+    request: Vec<u8>,
+    num_sent: usize,
+    reply: Vec<u8>,
+}
+
+impl SocksHandshake {
+    pub fn new(target: SocksTarget) -> Result<SocksHandshake, net_error> {
+        let request = target.encode_request()?;
+        Ok(SocksHandshake {
+            target,
+            request,
+            num_sent: 0,
+            reply: vec![],
+        })
+    }
+
+    /// A handshake that asks the proxy to resolve `host` instead of connecting to it.  Once
+    /// it completes, `bound_addr()` is the address the proxy resolved `host` to.
+    pub fn new_resolve(host: &str, port: u16) -> Result<SocksHandshake, net_error> {
+        let target = SocksTarget::Domain(host.to_string(), port);
+        let request = target.encode_command(SOCKS_CMD_RESOLVE)?;
+        Ok(SocksHandshake {
+            target,
+            request,
+            num_sent: 0,
+            reply: vec![],
+        })
+    }
+
+    /// The IP address and port in the proxy's reply, once the handshake is done.
+    /// Returns None if the handshake isn't done, or if the proxy replied with a host name.
+    pub fn bound_addr(&self) -> Option<SocketAddr> {
+        match self.reply_len() {
+            Ok(len) if len == self.reply.len() => {}
+            _ => return None,
+        }
+        let addr = &self.reply[2 + 4..self.reply.len() - 2];
+        let port = u16::from_be_bytes([
+            self.reply[self.reply.len() - 2],
+            self.reply[self.reply.len() - 1],
+        ]);
+        let ip = match self.reply[2 + 3] {
+            SOCKS_ATYP_IPV4 => {
+                let mut octets = [0u8; 4];
+                octets.copy_from_slice(addr);
+                IpAddr::from(octets)
+            }
+            SOCKS_ATYP_IPV6 => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(addr);
+                IpAddr::from(octets)
+            }
+            _ => return None,
+        };
+        Some(SocketAddr::new(ip, port))
+    }
+
+    /// How many bytes of the proxy's replies we need, given what we have so far.
+    /// The method reply is 2 bytes, and the CONNECT reply is 4 bytes, then an address whose
+    /// length depends on its type, then a 2-byte port.
+    fn reply_len(&self) -> Result<usize, net_error> {
+        if self.reply.len() < 2 + 5 {
+            return Ok(2 + 5);
+        }
+        let addr_len = match self.reply[2 + 3] {
+            SOCKS_ATYP_IPV4 => 4,
+            SOCKS_ATYP_IPV6 => 16,
+            SOCKS_ATYP_DOMAIN => 1 + (self.reply[2 + 4] as usize),
+            atyp => {
+                warn!(
+                    "SOCKS proxy replied with unknown address type {} for {}",
+                    atyp, &self.target
+                );
+                return Err(net_error::ConnectionError);
+            }
+        };
+        Ok(2 + 4 + addr_len + 2)
+    }
+
+    /// Check the replies we have so far
+    fn check_reply(&self) -> Result<(), net_error> {
+        if self.reply.len() >= 2 {
+            if self.reply[0] != SOCKS_VERSION || self.reply[1] != SOCKS_METHOD_NO_AUTH {
+                warn!(
+                    "SOCKS proxy refused unauthenticated access (version {}, method {})",
+                    self.reply[0], self.reply[1]
+                );
+                return Err(net_error::ConnectionError);
+            }
+        }
+        if self.reply.len() >= 4 {
+            if self.reply[2] != SOCKS_VERSION || self.reply[3] != SOCKS_REPLY_SUCCEEDED {
+                warn!(
+                    "SOCKS proxy failed to connect to {} (reply {})",
+                    &self.target, self.reply[3]
+                );
+                return Err(net_error::ConnectionError);
+            }
+        }
+        Ok(())
+    }
+
+    /// Make as much progress on the handshake as the socket allows.
+    /// Returns Ok(true) once the proxy has connected us to the target, and Ok(false) if the
+    /// socket would block first.  Reads no more than the proxy's replies, so anything the
+    /// target sends afterwards stays in the socket.
+    pub fn step<S: Read + Write>(&mut self, sock: &mut S) -> Result<bool, net_error> {
+        while self.num_sent < self.request.len() {
+            match sock.write(&self.request[self.num_sent..]) {
+                Ok(0) => return Err(net_error::ConnectionBroken),
+                Ok(n) => self.num_sent += n,
+                Err(e) => {
+                    if e.kind() == io::ErrorKind::WouldBlock {
+                        return Ok(false);
+                    }
+                    debug!(
+                        "Failed to send SOCKS request for {}: {:?}",
+                        &self.target, &e
+                    );
+                    return Err(net_error::ConnectionError);
+                }
+            }
+        }
+
+        loop {
+            let reply_len = self.reply_len()?;
+            if self.reply.len() >= reply_len {
+                return Ok(true);
+            }
+
+            let mut buf = vec![0u8; reply_len - self.reply.len()];
+            match sock.read(&mut buf) {
+                Ok(0) => {
+                    debug!("SOCKS proxy hung up while connecting to {}", &self.target);
+                    return Err(net_error::ConnectionBroken);
+                }
+                Ok(n) => {
+                    self.reply.extend_from_slice(&buf[0..n]);
+                    self.check_reply()?;
+                }
+                Err(e) => {
+                    if e.kind() == io::ErrorKind::WouldBlock {
+                        return Ok(false);
+                    }
+                    debug!("Failed to read SOCKS reply for {}: {:?}", &self.target, &e);
+                    return Err(net_error::ConnectionError);
+                }
+            }
+        }
+    }
+}
+
+/// Open a blocking socket to `target` through the SOCKS5 proxy at `proxy`.
+/// `timeout` bounds connecting to the proxy and each read and write of the handshake.
+pub fn connect_blocking(
+    proxy: &SocketAddr,
+    target: &SocksTarget,
+    timeout: Duration,
+) -> io::Result<net::TcpStream> {
+    let mut sock = net::TcpStream::connect_timeout(proxy, timeout)?;
+    sock.set_read_timeout(Some(timeout))?;
+    sock.set_write_timeout(Some(timeout))?;
+
+    let mut handshake = SocksHandshake::new(target.clone())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("{:?}", &e)))?;
+    match handshake.step(&mut sock) {
+        Ok(true) => {}
+        Ok(false) => {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("SOCKS proxy {} timed out", proxy),
+            ));
+        }
+        Err(e) => {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!(
+                    "SOCKS proxy {} could not connect to {}: {:?}",
+                    proxy, target, &e
+                ),
+            ));
+        }
+    }
+
+    sock.set_read_timeout(None)?;
+    sock.set_write_timeout(None)?;
+    Ok(sock)
+}
+
+/// Resolve `host` through the SOCKS5 proxy at `proxy`, using Tor's RESOLVE extension, so that
+/// the lookup doesn't leak out of the proxy.  The proxy only hands back one address.
+/// `timeout` bounds connecting to the proxy and each read and write of the handshake.
+pub fn resolve_blocking(
+    proxy: &SocketAddr,
+    host: &str,
+    port: u16,
+    timeout: Duration,
+) -> io::Result<SocketAddr> {
+    let mut sock = net::TcpStream::connect_timeout(proxy, timeout)?;
+    sock.set_read_timeout(Some(timeout))?;
+    sock.set_write_timeout(Some(timeout))?;
+
+    let mut handshake = SocksHandshake::new_resolve(host, port)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("{:?}", &e)))?;
+    match handshake.step(&mut sock) {
+        Ok(true) => {}
+        Ok(false) => {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("SOCKS proxy {} timed out", proxy),
+            ));
+        }
+        Err(e) => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("SOCKS proxy {} could not resolve {}: {:?}", proxy, host, &e),
+            ));
+        }
+    }
+
+    // the proxy resolves the name, not the port
+    match handshake.bound_addr() {
+        Some(addr) => Ok(SocketAddr::new(addr.ip(), port)),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "SOCKS proxy {} did not resolve {} to an IP address",
+                proxy, host
+            ),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// A socket that hands out its scripted input a few bytes at a time, and would block
+    /// whenever it runs out
+    struct MockSocket {
+        input: VecDeque<u8>,
+        output: Vec<u8>,
+        chunk: usize,
+    }
+
+    impl Read for MockSocket {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.input.len() == 0 {
+                return Err(io::Error::from(io::ErrorKind::WouldBlock));
+            }
+            let n = buf.len().min(self.chunk).min(self.input.len());
+            for i in 0..n {
+                buf[i] = self.input.pop_front().unwrap();
+            }
+            Ok(n)
+        }
+    }
+
+    impl Write for MockSocket {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.chunk);
+            self.output.extend_from_slice(&buf[0..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_socks_encode_request() {
+        let target = SocksTarget::Addr("1.2.3.4:20444".parse().unwrap());
+        assert_eq!(
+            target.encode_request().unwrap(),
+            vec![5, 1, 0, 5, 1, 0, 1, 1, 2, 3, 4, 0x4f, 0xdc]
+        );
+
+        let target = SocksTarget::Addr("[::1]:80".parse().unwrap());
+        let mut expected = vec![5, 1, 0, 5, 1, 0, 4];
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        expected.extend_from_slice(&[0, 80]);
+        assert_eq!(target.encode_request().unwrap(), expected);
+
+        let target = SocksTarget::Domain("abc.onion".to_string(), 20443);
+        let mut expected = vec![5, 1, 0, 5, 1, 0, 3, 9];
+        expected.extend_from_slice("abc.onion".as_bytes());
+        expected.extend_from_slice(&[0x4f, 0xdb]);
+        assert_eq!(target.encode_request().unwrap(), expected);
+
+        assert!(SocksTarget::Domain("".to_string(), 80)
+            .encode_request()
+            .is_err());
+        assert!(SocksTarget::Domain("a".repeat(256), 80)
+            .encode_request()
+            .is_err());
+    }
+
+    #[test]
+    fn test_socks_handshake() {
+        let target = SocksTarget::Domain("abc.onion".to_string(), 20443);
+        let mut handshake = SocksHandshake::new(target.clone()).unwrap();
+        let mut sock = MockSocket {
+            input: VecDeque::new(),
+            output: vec![],
+            chunk: 3,
+        };
+
+        // nothing to read yet
+        assert_eq!(handshake.step(&mut sock), Ok(false));
+        assert_eq!(sock.output, target.encode_request().unwrap());
+
+        // method reply, then a CONNECT reply with a bound IPv6 address, then data from the
+        // target
+        sock.input.extend(&[5, 0, 5, 0, 0, 4]);
+        sock.input.extend(&[0u8; 16]);
+        sock.input.extend(&[0x12, 0x34]);
+        sock.input.extend(&[0xff, 0xfe]);
+
+        assert_eq!(handshake.step(&mut sock), Ok(true));
+        assert_eq!(sock.input, vec![0xff, 0xfe]);
+
+        // a domain-name bound address
+        let mut handshake = SocksHandshake::new(target.clone()).unwrap();
+        sock.input.clear();
+        sock.input
+            .extend(&[5, 0, 5, 0, 0, 3, 3, b'a', b'b', b'c', 0, 80]);
+        assert_eq!(handshake.step(&mut sock), Ok(true));
+        assert_eq!(sock.input.len(), 0);
+    }
+
+    #[test]
+    fn test_socks_handshake_refused() {
+        let target = SocksTarget::Addr("1.2.3.4:20444".parse().unwrap());
+        let mut sock = MockSocket {
+            input: VecDeque::new(),
+            output: vec![],
+            chunk: 64,
+        };
+
+        // proxy wants a password
+        let mut handshake = SocksHandshake::new(target.clone()).unwrap();
+        sock.input.extend(&[5, 2]);
+        assert_eq!(handshake.step(&mut sock), Err(net_error::ConnectionError));
+
+        // host unreachable
+        let mut handshake = SocksHandshake::new(target.clone()).unwrap();
+        sock.input.clear();
+        sock.input.extend(&[5, 0, 5, 4, 0, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(handshake.step(&mut sock), Err(net_error::ConnectionError));
+    }
+
+    #[test]
+    fn test_socks_connect_blocking() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy = listener.local_addr().unwrap();
+        let target = SocksTarget::Domain("example.onion".to_string(), 20443);
+        let expected = target.encode_request().unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (mut sock, _) = listener.accept().unwrap();
+            let mut request = vec![0u8; expected.len()];
+            sock.read_exact(&mut request).unwrap();
+            assert_eq!(request, expected);
+            sock.write_all(&[5, 0, 5, 0, 0, 1, 127, 0, 0, 1, 0x4f, 0xdb, b'!'])
+                .unwrap();
+        });
+
+        let mut sock = connect_blocking(&proxy, &target, Duration::from_secs(5)).unwrap();
+        let mut data = [0u8; 1];
+        sock.read_exact(&mut data).unwrap();
+        assert_eq!(data[0], b'!');
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_socks_resolve() {
+        let mut handshake = SocksHandshake::new_resolve("seed.example.com", 20444).unwrap();
+        let mut sock = MockSocket {
+            input: VecDeque::new(),
+            output: vec![],
+            chunk: 5,
+        };
+        assert_eq!(handshake.step(&mut sock), Ok(false));
+        assert_eq!(handshake.bound_addr(), None);
+
+        let mut expected = vec![5, 1, 0, 5, 0xF0, 0, 3, 16];
+        expected.extend_from_slice("seed.example.com".as_bytes());
+        expected.extend_from_slice(&[0x4f, 0xdc]);
+        assert_eq!(sock.output, expected);
+
+        sock.input.extend(&[5, 0, 5, 0, 0, 1, 1, 2, 3, 4, 0, 0]);
+        assert_eq!(handshake.step(&mut sock), Ok(true));
+        assert_eq!(handshake.bound_addr(), Some("1.2.3.4:0".parse().unwrap()));
+
+        // a proxy without RESOLVE support says the command is not supported
+        let mut handshake = SocksHandshake::new_resolve("seed.example.com", 20444).unwrap();
+        sock.input.clear();
+        sock.input.extend(&[5, 0, 5, 7, 0, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(handshake.step(&mut sock), Err(net_error::ConnectionError));
+    }
+
+    #[test]
+    fn test_socks_resolve_blocking() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy = listener.local_addr().unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (mut sock, _) = listener.accept().unwrap();
+            let mut request = vec![0u8; 3 + 5 + 16 + 2];
+            sock.read_exact(&mut request).unwrap();
+            assert_eq!(request[4], SOCKS_CMD_RESOLVE);
+            sock.write_all(&[5, 0, 5, 0, 0, 1, 10, 0, 0, 1, 0, 0])
+                .unwrap();
+        });
+
+        let addr =
+            resolve_blocking(&proxy, "seed.example.com", 20444, Duration::from_secs(5)).unwrap();
+        assert_eq!(addr, "10.0.0.1:20444".parse().unwrap());
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,364 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2022 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Minimal support for content-addressed archives in the IPLD CARv1 format.
+//!
+//! A CAR file is a DAG-CBOR header naming the archive's root CIDs, followed by a sequence of
+//! `(CID, data)` sections. Only what is needed to archive chain data is implemented here:
+//! CIDv1 with sha2-256 multihashes, the `raw` and `dag-cbor` codecs, and a DAG-CBOR encoder
+//! for maps, lists, byte strings, unsigned integers, text, and links.
+
+use std::fmt;
+use std::io;
+use std::io::{Read, Write};
+
+use stacks_common::util::hash::Sha256Sum;
+
+/// Multicodec for opaque bytes (used for consensus-serialized blocks and microblocks)
+pub const CODEC_RAW: u64 = 0x55;
+/// Multicodec for DAG-CBOR (used for index nodes that link blocks together)
+pub const CODEC_DAG_CBOR: u64 = 0x71;
+/// Multicodec for the sha2-256 multihash
+const MULTIHASH_SHA2_256: u64 = 0x12;
+const CID_VERSION_1: u64 = 1;
+/// CBOR tag for an IPLD link
+const CBOR_TAG_CID: u64 = 42;
+
+/// Largest section we are willing to read back. Stacks blocks are at most 2MB.
+const MAX_SECTION_LEN: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cid {
+    pub codec: u64,
+    pub digest: Sha256Sum,
+}
+
+impl Cid {
+    pub fn from_data(codec: u64, data: &[u8]) -> Cid {
+        Cid {
+            codec,
+            digest: Sha256Sum::from_data(data),
+        }
+    }
+
+    /// Binary CIDv1 encoding: version, codec, and multihash, each prefixed by an unsigned varint
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_varint(&mut bytes, CID_VERSION_1);
+        write_varint(&mut bytes, self.codec);
+        write_varint(&mut bytes, MULTIHASH_SHA2_256);
+        write_varint(&mut bytes, self.digest.as_bytes().len() as u64);
+        bytes.extend_from_slice(self.digest.as_bytes());
+        bytes
+    }
+
+    fn read_from<R: Read>(fd: &mut R) -> io::Result<Cid> {
+        let version = read_varint(fd)?;
+        let codec = read_varint(fd)?;
+        let hash_fn = read_varint(fd)?;
+        let hash_len = read_varint(fd)?;
+        if version != CID_VERSION_1 || hash_fn != MULTIHASH_SHA2_256 || hash_len != 32 {
+            return Err(invalid_data(format!(
+                "Unsupported CID (version {}, hash function {}, hash length {})",
+                version, hash_fn, hash_len
+            )));
+        }
+        let mut digest = [0u8; 32];
+        fd.read_exact(&mut digest)?;
+        Ok(Cid {
+            codec,
+            digest: Sha256Sum(digest),
+        })
+    }
+}
+
+impl fmt::Display for Cid {
+    /// The usual textual form of a CIDv1: multibase prefix `b` and lowercase base32
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "b{}", base32_lower(&self.to_bytes()))
+    }
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn base32_lower(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+    let mut out = String::with_capacity((bytes.len() * 8 + 4) / 5);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for byte in bytes.iter() {
+        buffer = (buffer << 8) | (*byte as u32);
+        bits += 8;
+        while bits >= 5 {
+            out.push(ALPHABET[((buffer >> (bits - 5)) & 0x1f) as usize] as char);
+            bits -= 5;
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Reads an unsigned LEB128 varint. Returns `UnexpectedEof` only if the stream ends before the
+/// first byte, so callers can tell a clean end of archive from a truncated one.
+fn read_varint<R: Read>(fd: &mut R) -> io::Result<u64> {
+    let mut value: u64 = 0;
+    for i in 0..10 {
+        let mut byte = [0u8; 1];
+        if let Err(e) = fd.read_exact(&mut byte) {
+            if i > 0 && e.kind() == io::ErrorKind::UnexpectedEof {
+                return Err(invalid_data("Truncated varint".to_string()));
+            }
+            return Err(e);
+        }
+        value |= ((byte[0] & 0x7f) as u64) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid_data("Varint is too long".to_string()))
+}
+
+/// The subset of the IPLD data model written to archives
+#[derive(Debug, Clone, PartialEq)]
+pub enum IpldValue {
+    Uint(u64),
+    Bytes(Vec<u8>),
+    Text(String),
+    List(Vec<IpldValue>),
+    Map(Vec<(String, IpldValue)>),
+    Link(Cid),
+    Null,
+}
+
+impl IpldValue {
+    /// Canonical DAG-CBOR encoding. Map keys are sorted shortest-first, then bytewise.
+    pub fn to_dag_cbor(&self) -> Vec<u8> {
+        let mut out = vec![];
+        self.write_dag_cbor(&mut out);
+        out
+    }
+
+    fn write_dag_cbor(&self, out: &mut Vec<u8>) {
+        match self {
+            IpldValue::Uint(value) => write_cbor_head(out, 0, *value),
+            IpldValue::Bytes(bytes) => {
+                write_cbor_head(out, 2, bytes.len() as u64);
+                out.extend_from_slice(bytes);
+            }
+            IpldValue::Text(text) => {
+                write_cbor_head(out, 3, text.len() as u64);
+                out.extend_from_slice(text.as_bytes());
+            }
+            IpldValue::List(items) => {
+                write_cbor_head(out, 4, items.len() as u64);
+                for item in items.iter() {
+                    item.write_dag_cbor(out);
+                }
+            }
+            IpldValue::Map(entries) => {
+                let mut entries: Vec<_> = entries.iter().collect();
+                entries
+                    .sort_by(|(k1, _), (k2, _)| k1.len().cmp(&k2.len()).then_with(|| k1.cmp(k2)));
+                write_cbor_head(out, 5, entries.len() as u64);
+                for (key, value) in entries.into_iter() {
+                    IpldValue::Text(key.clone()).write_dag_cbor(out);
+                    value.write_dag_cbor(out);
+                }
+            }
+            IpldValue::Link(cid) => {
+                write_cbor_head(out, 6, CBOR_TAG_CID);
+                // links carry the identity multibase prefix 0x00
+                let mut bytes = vec![0x00];
+                bytes.extend_from_slice(&cid.to_bytes());
+                IpldValue::Bytes(bytes).write_dag_cbor(out);
+            }
+            IpldValue::Null => out.push(0xf6),
+        }
+    }
+}
+
+fn write_cbor_head(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    if value < 24 {
+        out.push(major | value as u8);
+    } else if value <= u8::MAX as u64 {
+        out.push(major | 24);
+        out.push(value as u8);
+    } else if value <= u16::MAX as u64 {
+        out.push(major | 25);
+        out.extend_from_slice(&(value as u16).to_be_bytes());
+    } else if value <= u32::MAX as u64 {
+        out.push(major | 26);
+        out.extend_from_slice(&(value as u32).to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn write_section<W: Write>(fd: &mut W, payload: &[&[u8]]) -> io::Result<()> {
+    let len: usize = payload.iter().map(|part| part.len()).sum();
+    let mut prefix = vec![];
+    write_varint(&mut prefix, len as u64);
+    fd.write_all(&prefix)?;
+    for part in payload.iter() {
+        fd.write_all(part)?;
+    }
+    Ok(())
+}
+
+/// Writes the CARv1 header naming `roots`. Must be written before any block.
+pub fn write_car_header<W: Write>(fd: &mut W, roots: &[Cid]) -> io::Result<()> {
+    let header = IpldValue::Map(vec![
+        (
+            "roots".to_string(),
+            IpldValue::List(roots.iter().cloned().map(IpldValue::Link).collect()),
+        ),
+        ("version".to_string(), IpldValue::Uint(1)),
+    ]);
+    write_section(fd, &[&header.to_dag_cbor()])
+}
+
+/// Writes `data` as a block of the given codec, and returns its CID.
+pub fn write_car_block<W: Write>(fd: &mut W, codec: u64, data: &[u8]) -> io::Result<Cid> {
+    let cid = Cid::from_data(codec, data);
+    write_section(fd, &[&cid.to_bytes(), data])?;
+    Ok(cid)
+}
+
+/// Reads the next `(CID, data)` section of an archive, after its header has been read with
+/// `read_car_header`. Returns `None` at the end of the archive. Fails if the data does not hash
+/// to its CID, so a block fetched from an untrusted archive can be used as-is once read.
+pub fn read_car_block<R: Read>(fd: &mut R) -> io::Result<Option<(Cid, Vec<u8>)>> {
+    let len = match read_varint(fd) {
+        Ok(len) => len,
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if len > MAX_SECTION_LEN {
+        return Err(invalid_data(format!("Section of {} bytes is too big", len)));
+    }
+    let mut section = fd.take(len);
+    let cid = Cid::read_from(&mut section)?;
+    let mut data = vec![];
+    section.read_to_end(&mut data)?;
+    if (data.len() as u64) + (cid.to_bytes().len() as u64) != len {
+        return Err(invalid_data("Truncated section".to_string()));
+    }
+    if Sha256Sum::from_data(&data) != cid.digest {
+        return Err(invalid_data(format!(
+            "Block does not match its CID {}",
+            &cid
+        )));
+    }
+    Ok(Some((cid, data)))
+}
+
+/// Reads past the archive header, returning its raw DAG-CBOR bytes.
+pub fn read_car_header<R: Read>(fd: &mut R) -> io::Result<Vec<u8>> {
+    let len = read_varint(fd)?;
+    if len > MAX_SECTION_LEN {
+        return Err(invalid_data(format!("Header of {} bytes is too big", len)));
+    }
+    let mut header = vec![0u8; len as usize];
+    fd.read_exact(&mut header)?;
+    Ok(header)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use stacks_common::util::hash::to_hex;
+
+    #[test]
+    fn test_cid_encoding() {
+        // `echo -n hello | ipfs block put --cid-codec raw --mhtype sha2-256`
+        let cid = Cid::from_data(CODEC_RAW, b"hello");
+        assert_eq!(
+            cid.to_string(),
+            "bafkreibm6jg3ux5qumhcn2b3flc3tyu6dmlb4xa7u5bf44yegnrjhc4yeq"
+        );
+        assert_eq!(to_hex(&cid.to_bytes()[..4]), "01551220");
+    }
+
+    #[test]
+    fn test_dag_cbor_encoding() {
+        let value = IpldValue::Map(vec![
+            ("zz".to_string(), IpldValue::Uint(1000)),
+            ("b".to_string(), IpldValue::List(vec![IpldValue::Null])),
+            ("a".to_string(), IpldValue::Bytes(vec![0xff])),
+        ]);
+        // keys sort as "a", "b", "zz"
+        assert_eq!(
+            to_hex(&value.to_dag_cbor()),
+            "a3616141ff616281f6627a7a1903e8"
+        );
+
+        let link = IpldValue::Link(Cid::from_data(CODEC_RAW, b"hello")).to_dag_cbor();
+        assert_eq!(to_hex(&link[..5]), "d82a582500");
+        assert_eq!(link.len(), 5 + 36);
+    }
+
+    #[test]
+    fn test_car_roundtrip() {
+        let mut archive = vec![];
+        let root = Cid::from_data(CODEC_DAG_CBOR, &IpldValue::Null.to_dag_cbor());
+        write_car_header(&mut archive, &[root.clone()]).unwrap();
+        let cid_1 = write_car_block(&mut archive, CODEC_RAW, b"block one").unwrap();
+        let cid_2 = write_car_block(&mut archive, CODEC_RAW, &vec![7u8; 300]).unwrap();
+        write_car_block(&mut archive, CODEC_DAG_CBOR, &IpldValue::Null.to_dag_cbor()).unwrap();
+
+        let mut fd = &archive[..];
+        let header = read_car_header(&mut fd).unwrap();
+        let mut expected_header = vec![];
+        write_car_header(&mut expected_header, &[root.clone()]).unwrap();
+        assert_eq!(header, expected_header[1..].to_vec());
+
+        assert_eq!(
+            read_car_block(&mut fd).unwrap(),
+            Some((cid_1, b"block one".to_vec()))
+        );
+        assert_eq!(
+            read_car_block(&mut fd).unwrap(),
+            Some((cid_2, vec![7u8; 300]))
+        );
+        assert_eq!(read_car_block(&mut fd).unwrap().unwrap().0, root);
+        assert_eq!(read_car_block(&mut fd).unwrap(), None);
+
+        // corrupt the first block's data
+        let mut corrupted = archive.clone();
+        let pos = corrupted
+            .windows(9)
+            .position(|window| window == b"block one")
+            .unwrap();
+        corrupted[pos] ^= 0xff;
+        let mut fd = &corrupted[..];
+        read_car_header(&mut fd).unwrap();
+        assert!(read_car_block(&mut fd).is_err());
+    }
+}
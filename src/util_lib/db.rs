@@ -15,6 +15,7 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::chainstate::stacks::index::storage::TrieStorageConnection;
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::error;
 use std::fmt;
@@ -25,6 +26,8 @@ use std::ops::Deref;
 use std::ops::DerefMut;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::Duration;
 
 use stacks_common::util::hash::to_hex;
 use stacks_common::util::sleep_ms;
@@ -59,6 +62,7 @@ use crate::chainstate::stacks::index::Error as MARFError;
 use crate::chainstate::stacks::index::MARFValue;
 use crate::chainstate::stacks::index::MarfTrieId;
 use crate::types::chainstate::TrieHash;
+use crate::util_lib::db_locks;
 
 use rand::thread_rng;
 use rand::Rng;
@@ -75,6 +79,110 @@ pub const SQLITE_MMAP_SIZE: i64 = 256 * 1024 * 1024;
 // 32K
 pub const SQLITE_MARF_PAGE_SIZE: i64 = 32768;
 
+/// The node's databases, each of which can be tuned separately with `set_sqlite_opts()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SqliteDBKind {
+    /// The sortition DB's MARF
+    Sortition,
+    /// The chainstate's block index MARF, which also holds the staging blocks
+    Chainstate,
+    /// The mempool
+    Mempool,
+    /// The Clarity state MARF, and any other MARF
+    Marf,
+    /// Everything else (peer DB, Atlas DB, fee estimators, ...)
+    Other,
+}
+
+/// Connection settings applied to every sqlite DB of a given `SqliteDBKind` as it is opened.
+/// Journaling is always WAL.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqliteOpts {
+    /// Page size in bytes.  Only takes effect on a DB that has not been written to yet.
+    /// `None` leaves sqlite's default.
+    pub page_size: Option<i64>,
+    /// How many bytes of the DB to memory-map.  `None` leaves sqlite's default.
+    pub mmap_size: Option<i64>,
+    /// Checkpoint the WAL once it holds this many pages.  `None` leaves sqlite's default.
+    pub wal_autocheckpoint: Option<i64>,
+    /// Give up on a locked DB after this many milliseconds.  `None` retries with backoff for as
+    /// long as it takes.
+    pub busy_timeout_ms: Option<u64>,
+    /// The `synchronous` pragma: one of OFF, NORMAL, FULL or EXTRA
+    pub synchronous: String,
+}
+
+impl SqliteOpts {
+    /// The settings used for `kind` unless configured otherwise
+    pub fn default_for(kind: SqliteDBKind) -> SqliteOpts {
+        let (page_size, mmap_size) = match kind {
+            SqliteDBKind::Sortition | SqliteDBKind::Chainstate | SqliteDBKind::Marf => {
+                (Some(SQLITE_MARF_PAGE_SIZE), Some(SQLITE_MMAP_SIZE))
+            }
+            SqliteDBKind::Mempool | SqliteDBKind::Other => (None, None),
+        };
+        SqliteOpts {
+            page_size,
+            mmap_size,
+            wal_autocheckpoint: None,
+            busy_timeout_ms: None,
+            synchronous: "NORMAL".to_string(),
+        }
+    }
+
+    /// Check that the settings are ones sqlite will accept
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(page_size) = self.page_size {
+            if page_size < 512 || page_size > 65536 || page_size.count_ones() != 1 {
+                return Err(format!(
+                    "page_size must be a power of two between 512 and 65536 (got {})",
+                    page_size
+                ));
+            }
+        }
+        if self.mmap_size.map(|size| size < 0).unwrap_or(false) {
+            return Err("mmap_size must not be negative".to_string());
+        }
+        if self
+            .wal_autocheckpoint
+            .map(|pages| pages < 0)
+            .unwrap_or(false)
+        {
+            return Err("wal_autocheckpoint must not be negative".to_string());
+        }
+        match self.synchronous.to_uppercase().as_str() {
+            "OFF" | "NORMAL" | "FULL" | "EXTRA" => Ok(()),
+            _ => Err(format!(
+                "synchronous must be one of OFF, NORMAL, FULL or EXTRA (got {})",
+                &self.synchronous
+            )),
+        }
+    }
+}
+
+lazy_static! {
+    static ref SQLITE_OPTS: RwLock<HashMap<SqliteDBKind, SqliteOpts>> = RwLock::new(HashMap::new());
+}
+
+/// Set the connection settings for DBs of the given kind.  Only connections opened afterwards
+/// are affected, so this should be called at startup.
+pub fn set_sqlite_opts(kind: SqliteDBKind, opts: SqliteOpts) {
+    SQLITE_OPTS
+        .write()
+        .expect("FATAL: sqlite options lock is poisoned")
+        .insert(kind, opts);
+}
+
+/// Get the connection settings for DBs of the given kind
+pub fn get_sqlite_opts(kind: SqliteDBKind) -> SqliteOpts {
+    SQLITE_OPTS
+        .read()
+        .expect("FATAL: sqlite options lock is poisoned")
+        .get(&kind)
+        .cloned()
+        .unwrap_or_else(|| SqliteOpts::default_for(kind))
+}
+
 #[derive(Debug)]
 pub enum Error {
     /// Not implemented
@@ -530,6 +638,29 @@ pub fn sql_vacuum(conn: &Connection) -> Result<(), Error> {
         .and_then(|_| Ok(()))
 }
 
+/// How many bytes this connection has written to its database (or its write-ahead log) since
+/// it was opened, counted in whole pages.
+pub fn sqlite_bytes_written(conn: &Connection) -> u64 {
+    let mut pages: i32 = 0;
+    let mut highwater: i32 = 0;
+    let rc = unsafe {
+        rusqlite::ffi::sqlite3_db_status(
+            conn.handle(),
+            rusqlite::ffi::SQLITE_DBSTATUS_CACHE_WRITE,
+            &mut pages,
+            &mut highwater,
+            0,
+        )
+    };
+    if rc != rusqlite::ffi::SQLITE_OK {
+        return 0;
+    }
+    let page_size: i64 = conn
+        .query_row("PRAGMA page_size", NO_PARAMS, |row| row.get(0))
+        .unwrap_or(0);
+    (pages.max(0) as u64) * (page_size.max(0) as u64)
+}
+
 /// Returns true if the database table `table_name` exists in the active
 ///  database of the provided SQLite connection.
 pub fn table_exists(conn: &Connection, table_name: &str) -> Result<bool, sqlite_error> {
@@ -658,14 +789,45 @@ pub fn tx_begin_immediate<'a>(conn: &'a mut Connection) -> Result<DBTx<'a>, Erro
     tx_begin_immediate_sqlite(conn).map_err(Error::from)
 }
 
-/// Begin an immediate-mode transaction, and handle busy errors with exponential backoff.
+/// Begin an immediate-mode transaction, and handle busy errors with the connection's busy
+/// handling (see `sqlite_open()`).
 /// Handling busy errors when the tx begins is preferable to doing it when the tx commits, since
 /// then we don't have to worry about any extra rollback logic.
 /// Sames as `tx_begin_immediate` except that it returns a rusqlite error.
+/// The write lock the transaction takes is tracked in `db_locks`.
 pub fn tx_begin_immediate_sqlite<'a>(conn: &'a mut Connection) -> Result<DBTx<'a>, sqlite_error> {
-    conn.busy_handler(Some(tx_busy_handler))?;
-    let tx = Transaction::new(conn, TransactionBehavior::Immediate)?;
-    Ok(tx)
+    let lock_key = db_locks::lock_wait(conn);
+    match Transaction::new(conn, TransactionBehavior::Immediate) {
+        Ok(tx) => {
+            db_locks::lock_acquired(&tx);
+            Ok(tx)
+        }
+        Err(e) => {
+            db_locks::lock_failed(lock_key);
+            Err(e)
+        }
+    }
+}
+
+/// Apply the configured settings for `kind` to a newly-opened connection
+fn apply_sqlite_opts(conn: &Connection, kind: SqliteDBKind) -> Result<(), sqlite_error> {
+    let opts = get_sqlite_opts(kind);
+    match opts.busy_timeout_ms {
+        Some(timeout_ms) => conn.busy_timeout(Duration::from_millis(timeout_ms))?,
+        None => conn.busy_handler(Some(tx_busy_handler))?,
+    }
+    inner_sql_pragma(conn, "journal_mode", &"WAL")?;
+    inner_sql_pragma(conn, "synchronous", &opts.synchronous)?;
+    if let Some(page_size) = opts.page_size {
+        inner_sql_pragma(conn, "page_size", &page_size)?;
+    }
+    if let Some(mmap_size) = opts.mmap_size {
+        inner_sql_pragma(conn, "mmap_size", &mmap_size)?;
+    }
+    if let Some(pages) = opts.wal_autocheckpoint {
+        inner_sql_pragma(conn, "wal_autocheckpoint", &pages)?;
+    }
+    Ok(())
 }
 
 /// Open a database connection and set some typically-used pragmas
@@ -673,11 +835,19 @@ pub fn sqlite_open<P: AsRef<Path>>(
     path: P,
     flags: OpenFlags,
     foreign_keys: bool,
+) -> Result<Connection, sqlite_error> {
+    sqlite_open_kind(path, flags, foreign_keys, SqliteDBKind::Other)
+}
+
+/// Open a database connection, tuned with the settings for DBs of the given kind
+pub fn sqlite_open_kind<P: AsRef<Path>>(
+    path: P,
+    flags: OpenFlags,
+    foreign_keys: bool,
+    kind: SqliteDBKind,
 ) -> Result<Connection, sqlite_error> {
     let db = Connection::open_with_flags(path, flags)?;
-    db.busy_handler(Some(tx_busy_handler))?;
-    inner_sql_pragma(&db, "journal_mode", &"WAL")?;
-    inner_sql_pragma(&db, "synchronous", &"NORMAL")?;
+    apply_sqlite_opts(&db, kind)?;
     if foreign_keys {
         inner_sql_pragma(&db, "foreign_keys", &true)?;
     }
@@ -949,4 +1119,87 @@ mod tests {
         })
         .unwrap();
     }
+
+    #[test]
+    fn test_sqlite_bytes_written() {
+        let path = "/tmp/blockstack_db_test_sqlite_bytes_written.db";
+        if fs::metadata(path).is_ok() {
+            fs::remove_file(path).unwrap();
+        }
+        let mut db = sqlite_open(
+            path,
+            OpenFlags::SQLITE_OPEN_CREATE | OpenFlags::SQLITE_OPEN_READ_WRITE,
+            true,
+        )
+        .unwrap();
+        let before = sqlite_bytes_written(&db);
+
+        let tx = tx_begin_immediate(&mut db).unwrap();
+        tx.execute("CREATE TABLE t (x BLOB)", NO_PARAMS).unwrap();
+        tx.execute("INSERT INTO t VALUES (?1)", &[&vec![0u8; 100_000]])
+            .unwrap();
+        tx.commit().unwrap();
+
+        // the blob spans many pages, and they were all written to the WAL
+        assert!(sqlite_bytes_written(&db) >= before + 100_000);
+    }
+
+    #[test]
+    fn test_sqlite_opts() {
+        let path = "/tmp/blockstack_db_test_sqlite_opts.db";
+        if fs::metadata(path).is_ok() {
+            fs::remove_file(path).unwrap();
+        }
+
+        // other tests open mempools concurrently, so only change settings they don't depend on
+        let opts = SqliteOpts {
+            wal_autocheckpoint: Some(2000),
+            synchronous: "FULL".to_string(),
+            ..SqliteOpts::default_for(SqliteDBKind::Mempool)
+        };
+        set_sqlite_opts(SqliteDBKind::Mempool, opts.clone());
+        assert_eq!(get_sqlite_opts(SqliteDBKind::Mempool), opts);
+
+        let db = sqlite_open_kind(
+            path,
+            OpenFlags::SQLITE_OPEN_CREATE | OpenFlags::SQLITE_OPEN_READ_WRITE,
+            false,
+            SqliteDBKind::Mempool,
+        )
+        .unwrap();
+        set_sqlite_opts(
+            SqliteDBKind::Mempool,
+            SqliteOpts::default_for(SqliteDBKind::Mempool),
+        );
+
+        db.pragma_query(None, "wal_autocheckpoint", |row| {
+            let value: i64 = row.get(0)?;
+            assert_eq!(value, 2000);
+            Ok(())
+        })
+        .unwrap();
+        // FULL is 2
+        db.pragma_query(None, "synchronous", |row| {
+            let value: i64 = row.get(0)?;
+            assert_eq!(value, 2);
+            Ok(())
+        })
+        .unwrap();
+
+        assert!(SqliteOpts::default_for(SqliteDBKind::Marf)
+            .validate()
+            .is_ok());
+        assert!(SqliteOpts {
+            page_size: Some(3000),
+            ..SqliteOpts::default_for(SqliteDBKind::Marf)
+        }
+        .validate()
+        .is_err());
+        assert!(SqliteOpts {
+            synchronous: "sometimes".to_string(),
+            ..SqliteOpts::default_for(SqliteDBKind::Marf)
+        }
+        .validate()
+        .is_err());
+    }
 }
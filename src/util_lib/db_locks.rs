@@ -0,0 +1,319 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Tracks which threads hold, or wait for, the write lock of each sqlite database.
+//!
+//! Every write to the sortition DB, the chainstate, the MARFs and the mempool happens in an
+//! immediate-mode transaction (see `db::tx_begin_immediate()`), which takes the database's write
+//! lock when it begins and releases it when it commits or rolls back.  A thread that begins one
+//! while another thread holds the lock waits in the connection's busy handler.  This module
+//! records both, so that the locks of a wedged node can be inspected while it runs.
+
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::sync::Mutex;
+use std::thread;
+
+use rusqlite::Connection;
+
+use stacks_common::util::get_epoch_time_ms;
+
+/// What a thread is doing with a database's write lock
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DBLockState {
+    Waiting,
+    Holding,
+}
+
+/// One connection's use of its database's write lock
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DBLockUse {
+    /// Path of the database file
+    pub path: String,
+    /// Name of the thread using the connection
+    pub thread: String,
+    pub state: DBLockState,
+    /// When the thread started waiting for, or holding, the lock
+    pub since_ms: u64,
+    /// How long the thread has been waiting for, or holding, the lock
+    pub duration_ms: u64,
+}
+
+/// Totals for one database's write lock since the node started
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DBLockStats {
+    pub path: String,
+    /// Number of times the lock was taken
+    pub acquisitions: u64,
+    /// Number of times a thread had to wait for the lock, i.e. the lock was contended
+    pub contended: u64,
+    pub max_wait_ms: u64,
+    pub max_hold_ms: u64,
+}
+
+/// The write locks of all databases, as served by the admin API's `/v2/admin/debug/locks`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DBLocksReport {
+    pub now_ms: u64,
+    /// The connections that hold or wait for a lock, longest first
+    pub locks: Vec<DBLockUse>,
+    pub stats: Vec<DBLockStats>,
+}
+
+struct LockEntry {
+    path: String,
+    thread: String,
+    state: DBLockState,
+    since_ms: u64,
+}
+
+#[derive(Default)]
+struct LockRegistry {
+    /// Keyed by the address of the connection's sqlite handle, which, unlike the `Connection`,
+    /// does not move
+    connections: HashMap<usize, LockEntry>,
+    stats: HashMap<String, DBLockStats>,
+}
+
+lazy_static! {
+    static ref DB_LOCKS: Mutex<LockRegistry> = Mutex::new(LockRegistry::default());
+}
+
+fn conn_key(conn: &Connection) -> usize {
+    unsafe { conn.handle() as usize }
+}
+
+fn conn_path(conn: &Connection) -> String {
+    let path = unsafe {
+        let filename =
+            rusqlite::ffi::sqlite3_db_filename(conn.handle(), b"main\0".as_ptr() as *const c_char);
+        if filename.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(filename).to_string_lossy().into_owned()
+        }
+    };
+    if path.is_empty() {
+        ":memory:".to_string()
+    } else {
+        path
+    }
+}
+
+impl LockRegistry {
+    fn wait(&mut self, key: usize, path: String, thread: String, now_ms: u64) {
+        self.connections.insert(
+            key,
+            LockEntry {
+                path,
+                thread,
+                state: DBLockState::Waiting,
+                since_ms: now_ms,
+            },
+        );
+    }
+
+    /// The connection got the lock it was waiting for
+    fn acquire(&mut self, key: usize, now_ms: u64) {
+        let entry = match self.connections.get_mut(&key) {
+            Some(entry) => entry,
+            None => return,
+        };
+        let waited_ms = now_ms.saturating_sub(entry.since_ms);
+        entry.state = DBLockState::Holding;
+        entry.since_ms = now_ms;
+
+        let stats = self
+            .stats
+            .entry(entry.path.clone())
+            .or_insert_with(|| DBLockStats {
+                path: entry.path.clone(),
+                ..DBLockStats::default()
+            });
+        stats.acquisitions += 1;
+        // the busy handler sleeps at least 2ms, so anything faster did not wait for another
+        // connection
+        if waited_ms >= 2 {
+            stats.contended += 1;
+        }
+        stats.max_wait_ms = stats.max_wait_ms.max(waited_ms);
+    }
+
+    /// The connection released its lock, or gave up waiting for it
+    fn release(&mut self, key: usize, now_ms: u64) {
+        let entry = match self.connections.remove(&key) {
+            Some(entry) => entry,
+            None => return,
+        };
+        if entry.state == DBLockState::Holding {
+            if let Some(stats) = self.stats.get_mut(&entry.path) {
+                stats.max_hold_ms = stats.max_hold_ms.max(now_ms.saturating_sub(entry.since_ms));
+            }
+        }
+    }
+
+    fn report(&self, now_ms: u64) -> DBLocksReport {
+        let mut locks: Vec<_> = self
+            .connections
+            .values()
+            .map(|entry| DBLockUse {
+                path: entry.path.clone(),
+                thread: entry.thread.clone(),
+                state: entry.state,
+                since_ms: entry.since_ms,
+                duration_ms: now_ms.saturating_sub(entry.since_ms),
+            })
+            .collect();
+        locks.sort_by(|a, b| b.duration_ms.cmp(&a.duration_ms));
+        let mut stats: Vec<_> = self.stats.values().cloned().collect();
+        stats.sort_by(|a, b| a.path.cmp(&b.path));
+        DBLocksReport {
+            now_ms,
+            locks,
+            stats,
+        }
+    }
+}
+
+/// Record that the current thread is about to begin an immediate-mode transaction on `conn`.
+/// Returns the key to pass to `lock_failed()` if the transaction cannot begin.
+pub fn lock_wait(conn: &Connection) -> usize {
+    let key = conn_key(conn);
+    let thread = thread::current().name().unwrap_or("unnamed").to_string();
+    DB_LOCKS
+        .lock()
+        .unwrap()
+        .wait(key, conn_path(conn), thread, get_epoch_time_ms() as u64);
+    key
+}
+
+/// Record that the transaction begun on `conn` got the write lock, and have the lock released
+/// from the registry once the transaction commits or rolls back.
+pub fn lock_acquired(conn: &Connection) {
+    let key = conn_key(conn);
+    conn.commit_hook(Some(move || {
+        lock_released(key);
+        // let the commit proceed
+        false
+    }));
+    conn.rollback_hook(Some(move || lock_released(key)));
+    DB_LOCKS
+        .lock()
+        .unwrap()
+        .acquire(key, get_epoch_time_ms() as u64);
+}
+
+/// Record that the transaction whose `lock_wait()` returned `key` failed to get the write lock.
+pub fn lock_failed(key: usize) {
+    lock_released(key);
+}
+
+fn lock_released(key: usize) {
+    DB_LOCKS
+        .lock()
+        .unwrap()
+        .release(key, get_epoch_time_ms() as u64);
+}
+
+/// The write locks that are held or waited for right now, and their totals so far.
+pub fn get_db_locks_report() -> DBLocksReport {
+    DB_LOCKS.lock().unwrap().report(get_epoch_time_ms() as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util_lib::db::tx_begin_immediate;
+
+    #[test]
+    fn test_lock_registry() {
+        let mut registry = LockRegistry::default();
+        registry.wait(1, "/db/a".to_string(), "p2p".to_string(), 1_000);
+        registry.acquire(1, 1_001);
+        registry.wait(2, "/db/a".to_string(), "relayer".to_string(), 1_100);
+
+        let report = registry.report(1_500);
+        assert_eq!(
+            report.locks,
+            vec![
+                DBLockUse {
+                    path: "/db/a".to_string(),
+                    thread: "p2p".to_string(),
+                    state: DBLockState::Holding,
+                    since_ms: 1_001,
+                    duration_ms: 499,
+                },
+                DBLockUse {
+                    path: "/db/a".to_string(),
+                    thread: "relayer".to_string(),
+                    state: DBLockState::Waiting,
+                    since_ms: 1_100,
+                    duration_ms: 400,
+                },
+            ]
+        );
+
+        registry.release(1, 1_600);
+        registry.acquire(2, 1_600);
+        registry.release(2, 1_700);
+        // releasing a connection without a lock, e.g. on an autocommit write, does nothing
+        registry.release(3, 1_800);
+
+        let report = registry.report(2_000);
+        assert!(report.locks.is_empty());
+        assert_eq!(
+            report.stats,
+            vec![DBLockStats {
+                path: "/db/a".to_string(),
+                acquisitions: 2,
+                contended: 1,
+                max_wait_ms: 500,
+                max_hold_ms: 599,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_lock_tracking() {
+        let path = "/tmp/stacks-node-tests/db-locks.sqlite";
+        std::fs::create_dir_all("/tmp/stacks-node-tests").unwrap();
+        let _ = std::fs::remove_file(path);
+        let mut conn = Connection::open(path).unwrap();
+        let key = conn_key(&conn);
+        assert_eq!(conn_path(&conn), path);
+        let held = || {
+            get_db_locks_report()
+                .locks
+                .into_iter()
+                .any(|lock| lock.path == path && lock.state == DBLockState::Holding)
+        };
+
+        let tx = tx_begin_immediate(&mut conn).unwrap();
+        assert!(held());
+        tx.commit().unwrap();
+        assert!(!held());
+
+        let tx = tx_begin_immediate(&mut conn).unwrap();
+        tx.execute("CREATE TABLE t (x INTEGER)", rusqlite::NO_PARAMS)
+            .unwrap();
+        assert!(held());
+        drop(tx);
+        assert!(!held());
+        assert!(!DB_LOCKS.lock().unwrap().connections.contains_key(&key));
+    }
+}
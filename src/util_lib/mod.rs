@@ -2,6 +2,8 @@
 pub mod db;
 pub mod bloom;
 pub mod boot;
+pub mod car;
+pub mod db_locks;
 pub mod strings;
 
 #[cfg(test)]
@@ -104,6 +104,8 @@ pub enum NetworkMessage {
     Block(block::Block),
     /// `headers`
     Headers(Vec<block::LoneBlockHeader>),
+    /// `sendheaders`
+    SendHeaders,
     /// `getaddr`
     GetAddr,
     /// `ping`
@@ -130,6 +132,7 @@ impl RawNetworkMessage {
             NetworkMessage::Tx(_) => "tx",
             NetworkMessage::Block(_) => "block",
             NetworkMessage::Headers(_) => "headers",
+            NetworkMessage::SendHeaders => "sendheaders",
             NetworkMessage::GetAddr => "getaddr",
             NetworkMessage::Ping(_) => "ping",
             NetworkMessage::Pong(_) => "pong",
@@ -157,6 +160,7 @@ impl<S: SimpleEncoder> ConsensusEncodable<S> for RawNetworkMessage {
                 NetworkMessage::Tx(ref dat) => serialize(dat),
                 NetworkMessage::Block(ref dat) => serialize(dat),
                 NetworkMessage::Headers(ref dat) => serialize(dat),
+                NetworkMessage::SendHeaders => Ok(vec![]),
                 NetworkMessage::GetAddr => Ok(vec![]),
                 NetworkMessage::Ping(ref dat) => serialize(dat),
                 NetworkMessage::Pong(ref dat) => serialize(dat),
@@ -194,6 +198,7 @@ impl<D: SimpleDecoder> ConsensusDecodable<D> for RawNetworkMessage {
             "mempool" => NetworkMessage::MemPool,
             "block" => NetworkMessage::Block(ConsensusDecodable::consensus_decode(&mut mem_d)?),
             "headers" => NetworkMessage::Headers(ConsensusDecodable::consensus_decode(&mut mem_d)?),
+            "sendheaders" => NetworkMessage::SendHeaders,
             "getaddr" => NetworkMessage::GetAddr,
             "ping" => NetworkMessage::Ping(ConsensusDecodable::consensus_decode(&mut mem_d)?),
             "pong" => NetworkMessage::Pong(ConsensusDecodable::consensus_decode(&mut mem_d)?),
@@ -295,4 +300,22 @@ mod test {
             ])
         );
     }
+
+    #[test]
+    fn serialize_sendheaders_test() {
+        let bytes = vec![
+            0xf9, 0xbe, 0xb4, 0xd9, 0x73, 0x65, 0x6e, 0x64, 0x68, 0x65, 0x61, 0x64, 0x65, 0x72,
+            0x73, 0x00, 0x00, 0x00, 0x00, 0x00, 0x5d, 0xf6, 0xe0, 0xe2,
+        ];
+        assert_eq!(
+            serialize(&RawNetworkMessage {
+                magic: 0xd9b4bef9,
+                payload: NetworkMessage::SendHeaders
+            })
+            .ok(),
+            Some(bytes.clone())
+        );
+        let msg: RawNetworkMessage = deserialize(&bytes).unwrap();
+        assert_eq!(msg.payload, NetworkMessage::SendHeaders);
+    }
 }
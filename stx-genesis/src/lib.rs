@@ -1,5 +1,7 @@
+use std::fs;
 use std::io::{self, BufReader};
 use std::io::{prelude::*, Cursor, Lines};
+use std::sync::Arc;
 
 use libflate::deflate::{self, Decoder};
 
@@ -44,17 +46,46 @@ pub struct GenesisZonefile {
 pub static GENESIS_CHAINSTATE_HASH: &str =
     include_str!(concat!(env!("OUT_DIR"), "/chainstate.txt.sha256"));
 
+#[derive(Clone)]
 pub struct GenesisData {
     use_test_chainstate_data: bool,
+    custom_chainstate: Option<Arc<CustomChainstate>>,
+}
+
+/// The rows of a genesis chainstate file supplied at runtime, split by section.
+struct CustomChainstate {
+    balances: Vec<Vec<String>>,
+    lockups: Vec<Vec<String>>,
+    namespaces: Vec<Vec<String>>,
+    names: Vec<Vec<String>>,
 }
 
 impl GenesisData {
     pub fn new(use_test_chainstate_data: bool) -> GenesisData {
         GenesisData {
             use_test_chainstate_data,
+            custom_chainstate: None,
         }
     }
+
+    /// Genesis data read from a file in the same format as `chainstate.txt`, for private
+    /// chains with their own initial allocations. Sections that are absent are empty. Every row
+    /// is validated here, so that reading the data later cannot fail.
+    pub fn from_chainstate_file(path: &str) -> Result<GenesisData, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read genesis chainstate file {}: {}", path, e))?;
+        let custom_chainstate = CustomChainstate::parse(&content)
+            .map_err(|e| format!("Invalid genesis chainstate file {}: {}", path, e))?;
+        Ok(GenesisData {
+            use_test_chainstate_data: false,
+            custom_chainstate: Some(Arc::new(custom_chainstate)),
+        })
+    }
+
     pub fn read_balances(&self) -> Box<dyn Iterator<Item = GenesisAccountBalance>> {
+        if let Some(ref custom) = self.custom_chainstate {
+            return parse_balances(Box::new(custom.balances.clone().into_iter()));
+        }
         read_balances(if self.use_test_chainstate_data {
             include_bytes!(concat!(env!("OUT_DIR"), "/account_balances-test.gz"))
         } else {
@@ -62,6 +93,9 @@ impl GenesisData {
         })
     }
     pub fn read_lockups(&self) -> Box<dyn Iterator<Item = GenesisAccountLockup>> {
+        if let Some(ref custom) = self.custom_chainstate {
+            return parse_lockups(Box::new(custom.lockups.clone().into_iter()));
+        }
         read_lockups(if self.use_test_chainstate_data {
             include_bytes!(concat!(env!("OUT_DIR"), "/account_lockups-test.gz"))
         } else {
@@ -69,6 +103,9 @@ impl GenesisData {
         })
     }
     pub fn read_namespaces(&self) -> Box<dyn Iterator<Item = GenesisNamespace>> {
+        if let Some(ref custom) = self.custom_chainstate {
+            return parse_namespaces(Box::new(custom.namespaces.clone().into_iter()));
+        }
         read_namespaces(if self.use_test_chainstate_data {
             include_bytes!(concat!(env!("OUT_DIR"), "/namespaces-test.gz"))
         } else {
@@ -76,13 +113,20 @@ impl GenesisData {
         })
     }
     pub fn read_names(&self) -> Box<dyn Iterator<Item = GenesisName>> {
+        if let Some(ref custom) = self.custom_chainstate {
+            return parse_names(Box::new(custom.names.clone().into_iter()));
+        }
         read_names(if self.use_test_chainstate_data {
             include_bytes!(concat!(env!("OUT_DIR"), "/names-test.gz"))
         } else {
             include_bytes!(concat!(env!("OUT_DIR"), "/names.gz"))
         })
     }
+    /// A custom chainstate file carries no zonefiles.
     pub fn read_name_zonefiles(&self) -> Box<dyn Iterator<Item = GenesisZonefile>> {
+        if self.custom_chainstate.is_some() {
+            return Box::new(std::iter::empty());
+        }
         read_deflated_zonefiles(if self.use_test_chainstate_data {
             include_bytes!(concat!(env!("OUT_DIR"), "/name_zonefiles-test.gz"))
         } else {
@@ -91,6 +135,97 @@ impl GenesisData {
     }
 }
 
+impl CustomChainstate {
+    fn parse(content: &str) -> Result<CustomChainstate, String> {
+        let chainstate = CustomChainstate {
+            balances: read_section(content, "STX BALANCES", &[None, Some(ColumnType::U64)])?,
+            lockups: read_section(
+                content,
+                "STX VESTING",
+                &[None, Some(ColumnType::U64), Some(ColumnType::U64)],
+            )?,
+            namespaces: read_section(
+                content,
+                "NAMESPACES",
+                &[
+                    None,
+                    None,
+                    None,
+                    Some(ColumnType::I64),
+                    Some(ColumnType::I64),
+                    Some(ColumnType::I64),
+                    Some(ColumnType::I64),
+                    Some(ColumnType::I64),
+                ],
+            )?,
+            names: read_section(content, "NAMES", &[None, None, None])?,
+        };
+        Ok(chainstate)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ColumnType {
+    U64,
+    I64,
+}
+
+/// Rows of the named section, after its table header line. `columns` gives the expected
+/// column count, and the type of each column that must be numeric.
+fn read_section(
+    content: &str,
+    section_name: &str,
+    columns: &[Option<ColumnType>],
+) -> Result<Vec<Vec<String>>, String> {
+    let section_header = format!("-----BEGIN {}-----", section_name);
+    let section_footer = format!("-----END {}-----", section_name);
+
+    let mut lines = content.lines().enumerate();
+    if lines
+        .by_ref()
+        .find(|(_, line)| line.trim_end() == section_header)
+        .is_none()
+    {
+        return Ok(vec![]);
+    }
+    // skip table header line
+    lines.next();
+
+    let mut rows = vec![];
+    for (line_index, line) in lines {
+        let line = line.trim_end();
+        if line == section_footer {
+            return Ok(rows);
+        }
+        let cols: Vec<String> = line.split(",").map(String::from).collect();
+        if cols.len() != columns.len() {
+            return Err(format!(
+                "line {}: expected {} columns in {}, found {}",
+                line_index + 1,
+                columns.len(),
+                section_name,
+                cols.len()
+            ));
+        }
+        for (col, column_type) in cols.iter().zip(columns.iter()) {
+            let valid = match column_type {
+                Some(ColumnType::U64) => col.parse::<u64>().is_ok(),
+                Some(ColumnType::I64) => col.parse::<i64>().is_ok(),
+                None => true,
+            };
+            if !valid {
+                return Err(format!(
+                    "line {}: '{}' is not a valid number",
+                    line_index + 1,
+                    col
+                ));
+            }
+        }
+        rows.push(cols);
+    }
+    Err(format!("missing '{}'", section_footer))
+}
+
 struct LinePairReader {
     val: Lines<BufReader<Decoder<Cursor<&'static [u8]>>>>,
 }
@@ -134,7 +269,13 @@ fn iter_deflated_csv(deflate_bytes: &'static [u8]) -> Box<dyn Iterator<Item = Ve
 }
 
 fn read_balances(deflate_bytes: &'static [u8]) -> Box<dyn Iterator<Item = GenesisAccountBalance>> {
-    let balances = iter_deflated_csv(deflate_bytes).map(|cols| GenesisAccountBalance {
+    parse_balances(iter_deflated_csv(deflate_bytes))
+}
+
+fn parse_balances(
+    rows: Box<dyn Iterator<Item = Vec<String>>>,
+) -> Box<dyn Iterator<Item = GenesisAccountBalance>> {
+    let balances = rows.map(|cols| GenesisAccountBalance {
         address: cols[0].to_string(),
         amount: cols[1].parse::<u64>().unwrap(),
     });
@@ -142,7 +283,13 @@ fn read_balances(deflate_bytes: &'static [u8]) -> Box<dyn Iterator<Item = Genesi
 }
 
 fn read_lockups(deflate_bytes: &'static [u8]) -> Box<dyn Iterator<Item = GenesisAccountLockup>> {
-    let lockups = iter_deflated_csv(deflate_bytes).map(|cols| GenesisAccountLockup {
+    parse_lockups(iter_deflated_csv(deflate_bytes))
+}
+
+fn parse_lockups(
+    rows: Box<dyn Iterator<Item = Vec<String>>>,
+) -> Box<dyn Iterator<Item = GenesisAccountLockup>> {
+    let lockups = rows.map(|cols| GenesisAccountLockup {
         address: cols[0].to_string(),
         amount: cols[1].parse::<u64>().unwrap(),
         block_height: cols[2].parse::<u64>().unwrap(),
@@ -151,7 +298,13 @@ fn read_lockups(deflate_bytes: &'static [u8]) -> Box<dyn Iterator<Item = Genesis
 }
 
 fn read_namespaces(deflate_bytes: &'static [u8]) -> Box<dyn Iterator<Item = GenesisNamespace>> {
-    let namespaces = iter_deflated_csv(deflate_bytes).map(|cols| GenesisNamespace {
+    parse_namespaces(iter_deflated_csv(deflate_bytes))
+}
+
+fn parse_namespaces(
+    rows: Box<dyn Iterator<Item = Vec<String>>>,
+) -> Box<dyn Iterator<Item = GenesisNamespace>> {
+    let namespaces = rows.map(|cols| GenesisNamespace {
         namespace_id: cols[0].to_string(),
         importer: cols[1].to_string(),
         buckets: cols[2].to_string(),
@@ -165,7 +318,13 @@ fn read_namespaces(deflate_bytes: &'static [u8]) -> Box<dyn Iterator<Item = Gene
 }
 
 fn read_names(deflate_bytes: &'static [u8]) -> Box<dyn Iterator<Item = GenesisName>> {
-    let names = iter_deflated_csv(deflate_bytes).map(|cols| GenesisName {
+    parse_names(iter_deflated_csv(deflate_bytes))
+}
+
+fn parse_names(
+    rows: Box<dyn Iterator<Item = Vec<String>>>,
+) -> Box<dyn Iterator<Item = GenesisName>> {
+    let names = rows.map(|cols| GenesisName {
         fully_qualified_name: cols[0].to_string(),
         owner: cols[1].to_string(),
         zonefile_hash: cols[2].to_string(),
@@ -228,4 +387,65 @@ mod tests {
             assert_eq!(zonefile.zonefile_hash.len(), 40);
         }
     }
+
+    #[test]
+    fn test_custom_chainstate_read() {
+        let path = std::env::temp_dir().join("stx-genesis-test_custom_chainstate_read.txt");
+        let path = path.to_str().unwrap();
+
+        fs::write(
+            path,
+            "-----BEGIN STX BALANCES-----\n\
+             address,balance\n\
+             ST2QKZ4FKHAH1NQKYKYAYZPY440FEPK7GZ1R5HBP2,1000\n\
+             ST319CF5WV77KYR1H3GT0GZ7B8Q4AQPY42ETP1VPF,2000\n\
+             -----END STX BALANCES-----\n\
+             -----BEGIN STX VESTING-----\n\
+             address,value,blocks\n\
+             ST2QKZ4FKHAH1NQKYKYAYZPY440FEPK7GZ1R5HBP2,500,10\n\
+             -----END STX VESTING-----\n",
+        )
+        .unwrap();
+        let genesis_data = GenesisData::from_chainstate_file(path).unwrap();
+        let balances: Vec<_> = genesis_data.read_balances().collect();
+        assert_eq!(balances.len(), 2);
+        assert_eq!(
+            balances[1].address,
+            "ST319CF5WV77KYR1H3GT0GZ7B8Q4AQPY42ETP1VPF"
+        );
+        assert_eq!(balances[1].amount, 2000);
+        let lockups: Vec<_> = genesis_data.read_lockups().collect();
+        assert_eq!(lockups.len(), 1);
+        assert_eq!(lockups[0].block_height, 10);
+        assert_eq!(genesis_data.read_namespaces().count(), 0);
+        assert_eq!(genesis_data.read_names().count(), 0);
+        assert_eq!(genesis_data.read_name_zonefiles().count(), 0);
+
+        fs::write(
+            path,
+            "-----BEGIN STX BALANCES-----\n\
+             address,balance\n\
+             ST2QKZ4FKHAH1NQKYKYAYZPY440FEPK7GZ1R5HBP2,lots\n\
+             -----END STX BALANCES-----\n",
+        )
+        .unwrap();
+        assert!(GenesisData::from_chainstate_file(path)
+            .err()
+            .unwrap()
+            .ends_with("line 3: 'lots' is not a valid number"));
+
+        fs::write(
+            path,
+            "-----BEGIN STX BALANCES-----\n\
+             address,balance\n\
+             ST2QKZ4FKHAH1NQKYKYAYZPY440FEPK7GZ1R5HBP2,1000\n",
+        )
+        .unwrap();
+        assert!(GenesisData::from_chainstate_file(path)
+            .err()
+            .unwrap()
+            .ends_with("missing '-----END STX BALANCES-----'"));
+
+        fs::remove_file(path).unwrap();
+    }
 }
@@ -0,0 +1,5 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/stacks_node.proto")
+        .expect("failed to compile protobuf definitions");
+}
@@ -0,0 +1,193 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2022 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Derive macros for the stacks-node.
+//!
+//! `#[derive(DynConfigDiff)]` implements `config::DynConfigDiff` for a config section, so that it
+//! can be diffed against a newer copy of itself and have its reloadable fields changed while the
+//! node runs. Fields take these attributes:
+//!
+//! * `#[dyn_config(reloadable)]`: the field can be changed at runtime
+//! * `#[dyn_config(secret)]`: the field's values are redacted in diff reports
+//! * `#[dyn_config(skip)]`: the field is never compared
+//!
+//! Every field that isn't skipped must implement `Clone`, `Debug` and `PartialEq`, and reloadable
+//! fields must also implement `Deserialize`.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Field, Fields, Meta, NestedMeta};
+
+#[derive(Default)]
+struct FieldOpts {
+    reloadable: bool,
+    secret: bool,
+    skip: bool,
+}
+
+impl FieldOpts {
+    fn parse(field: &Field) -> syn::Result<FieldOpts> {
+        let mut opts = FieldOpts::default();
+        for attr in field.attrs.iter() {
+            if !attr.path.is_ident("dyn_config") {
+                continue;
+            }
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => {
+                    return Err(syn::Error::new_spanned(
+                        meta,
+                        "expected #[dyn_config(reloadable | secret | skip)]",
+                    ))
+                }
+            };
+            for nested in list.nested.iter() {
+                match nested {
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("reloadable") => {
+                        opts.reloadable = true;
+                    }
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("secret") => {
+                        opts.secret = true;
+                    }
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => {
+                        opts.skip = true;
+                    }
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            nested,
+                            "expected one of `reloadable`, `secret`, or `skip`",
+                        ))
+                    }
+                }
+            }
+        }
+        if opts.skip && opts.reloadable {
+            return Err(syn::Error::new_spanned(
+                field,
+                "a skipped field cannot be reloadable",
+            ));
+        }
+        Ok(opts)
+    }
+}
+
+#[proc_macro_derive(DynConfigDiff, attributes(dyn_config))]
+pub fn derive_dyn_config_diff(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_dyn_config_diff(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand_dyn_config_diff(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    input,
+                    "DynConfigDiff needs a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                input,
+                "DynConfigDiff can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut reloadable_names = vec![];
+    let mut diffs = vec![];
+    let mut setters = vec![];
+    for field in fields.iter() {
+        let opts = FieldOpts::parse(field)?;
+        if opts.skip {
+            continue;
+        }
+        let ident = field.ident.as_ref().expect("named field has no name");
+        let ty = &field.ty;
+        let field_name = ident.to_string();
+        let secret = opts.secret;
+        let reloadable = opts.reloadable;
+
+        let apply = if reloadable {
+            quote! { self.#ident = new.#ident.clone(); }
+        } else {
+            quote! {}
+        };
+        diffs.push(quote! {
+            if self.#ident != new.#ident {
+                report.record(section, #field_name, &self.#ident, &new.#ident, #secret, #reloadable);
+                #apply
+            }
+        });
+
+        if reloadable {
+            reloadable_names.push(field_name.clone());
+            setters.push(quote! {
+                #field_name => {
+                    let value: #ty = ::serde_json::from_value(value.clone()).map_err(|e| {
+                        format!("Invalid value for '{}.{}': {}", section, field, e)
+                    })?;
+                    let changed = self.#ident != value;
+                    self.#ident = value;
+                    Ok(changed)
+                }
+            });
+        }
+    }
+
+    Ok(quote! {
+        impl crate::config::DynConfigDiff for #name {
+            fn reloadable_fields() -> &'static [&'static str] {
+                &[#(#reloadable_names),*]
+            }
+
+            #[allow(unused_variables)]
+            fn apply_diff(
+                &mut self,
+                section: &str,
+                new: &Self,
+                report: &mut crate::config::ConfigDiffReport,
+            ) {
+                #(#diffs)*
+            }
+
+            #[allow(unused_variables)]
+            fn set_reloadable_field(
+                &mut self,
+                section: &str,
+                field: &str,
+                value: &::serde_json::Value,
+            ) -> Result<bool, String> {
+                match field {
+                    #(#setters)*
+                    _ => Err(format!(
+                        "Config key '{}.{}' cannot be changed at runtime",
+                        section, field
+                    )),
+                }
+            }
+        }
+    })
+}
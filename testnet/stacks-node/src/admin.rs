@@ -0,0 +1,1390 @@
+//! Operator admin API, served on `node.admin_bind`.
+//!
+//! Endpoints:
+//!
+//! * `GET /v2/admin/burnchain/ops`: the miner's leader key registration and block commit that
+//!   are not mined yet.
+//! * `POST /v2/admin/burnchain/ops/abandon`: stop tracking the in-flight block commit, so the
+//!   next one is built from fresh UTXOs instead of replacing it by fee.
+//! * `POST /v2/admin/burnchain/ops/fee-bump`: replace the in-flight block commit by fee now.
+//!   The optional JSON body `{"fee_rate": <sat/byte>}` sets the new fee rate; otherwise the
+//!   fee rate goes up by `burnchain.rbf_fee_increment`, or by 1 sat/byte with
+//!   `burnchain.auto_rbf`.
+//!
+//! * `POST /v2/admin/config`: change some of the node's settings without restarting it. The
+//!   body is a JSON patch of keys from `Config::hot_reloadable_keys()`, such as
+//!   `{"burnchain": {"satoshis_per_byte": 80}}`, and the answer lists the keys whose value
+//!   changed. A patch naming any other key is rejected as a whole. Changes are not written back
+//!   to the config file.
+//!
+//! * `POST /v2/admin/block/validate`: check a block built elsewhere, such as by an external
+//!   block producer, without storing or relaying it. The body is
+//!   `{"parent_consensus_hash": <hex>, "block": <hex>, "microblocks": [<hex>, ...]}`, where the
+//!   optional `microblocks` is the parent microblock stream the block confirms; by default the
+//!   node's own copy is used. The block is executed on its parent as a miner would build it, and
+//!   the answer is either `{"valid": true, ...}` with the block's costs, fees and transaction
+//!   results, or `{"valid": false, "reason": "..."}`.
+//!
+//! * `GET /v2/admin/miner`: whether the miner is running, and its addresses.
+//! * `POST /v2/admin/miner/stop`: stop mining, and drop the miner's in-progress blocks and
+//!   microblock stream. The p2p network, the relayer and the chains coordinator keep running.
+//! * `POST /v2/admin/miner/start`: start mining again. The optional JSON body
+//!   `{"seed": <hex>}` replaces the miner's keychain, such as to rotate its keys; the new miner
+//!   registers a fresh VRF key before its first tenure. Only a node started as a miner can be
+//!   started this way.
+//! * `POST /v2/admin/miner/rotate-key`: register a new VRF key, and keep mining with the current
+//!   one until the new key's registration is mined, so that no tenure is missed.  This is what
+//!   `stacks-node rotate-key` calls.
+//! * `GET /v2/admin/miner/stats?sortitions=<n>`: the miner's block-commits, wins, win rate and
+//!   burn over the last `n` sortitions (100 by default), next to the total burn, the median
+//!   burn of the other miners' block-commits, and the block-commits that were mined late or not
+//!   at all. See `miner_stats::MinerStats`.
+//!
+//! * `GET /v2/admin/backups`: the complete chainstate backups taken by `[node.backup]`, with
+//!   their manifests, oldest first. See `backup::BackupListing`.
+//! * `GET /v2/admin/backups/file?height=<height>&path=<path>&offset=<n>&length=<n>`: the bytes
+//!   of a file listed in a backup's manifest, from byte `offset` (0 by default) on, and at most
+//!   `length` of them (to the end of the file by default). Read replicas fetch their chainstate
+//!   this way, and only the chunks of a DB that changed since the backup they ingested last.
+//!
+//! * `GET /v2/admin/debug/locks`: which threads hold, or wait for, the write lock of each
+//!   database (the sortition DB, the chainstate, the MARFs, the mempool, ...), for how long, and
+//!   each lock's acquisitions, contention and longest wait and hold so far. See
+//!   `stacks::util_lib::db_locks::DBLocksReport`.
+//! * `GET /v2/admin/debug/pprof/profile?seconds=<n>&frequency=<hz>`: a CPU profile of the next
+//!   `n` seconds (30 by default), sampled `hz` times per second (99 by default), for
+//!   `go tool pprof`.
+//! * `GET /v2/admin/debug/pprof/heap?seconds=<n>&sample_bytes=<bytes>`: a heap profile of the
+//!   allocations made in the next `n` seconds, sampling one allocation per `bytes` allocated
+//!   (512 KiB by default). See `profiler`. The profile endpoints are only served with
+//!   `node.profiling` set, and only to loopback peers.
+//!
+//! If `node.admin_auth_token` is set, requests must carry it as `Authorization: Bearer <token>`;
+//! the config, block validation, miner and backup endpoints are disabled without it. Only loopback peers are served unless
+//! `node.admin_allow_remote` is set.
+//!
+//! On failure, endpoints answer with `{"error": "..."}`.
+
+use std::io::SeekFrom;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use async_std::channel;
+use async_std::fs::File;
+use async_std::io::BufReader;
+use async_std::net::{TcpListener, TcpStream};
+use async_std::prelude::*;
+use async_std::task;
+use http_types::{Body, Method, Request, Response, StatusCode, Url};
+
+use stacks::chainstate::burn::ConsensusHash;
+use stacks::chainstate::stacks::db::blocks::ValidatedBlockProposal;
+use stacks::chainstate::stacks::events::TransactionOrigin;
+use stacks::chainstate::stacks::Error as ChainstateError;
+use stacks::chainstate::stacks::{StacksBlock, StacksMicroblock};
+use stacks::codec::StacksMessageCodec;
+use stacks::util::hash::hex_bytes;
+use stacks::util_lib::db_locks::get_db_locks_report;
+
+use crate::backup;
+use crate::burnchains::bitcoin_regtest_controller::PendingBurnchainOp;
+use crate::miner_stats::{MinerStats, DEFAULT_MINER_STATS_SORTITIONS, MAX_MINER_STATS_SORTITIONS};
+use crate::profiler;
+use crate::Config;
+
+pub enum BurnchainOpsCommand {
+    List,
+    Abandon,
+    FeeBump(Option<u64>),
+}
+
+pub type BurnchainOpsHandler =
+    Arc<dyn Fn(BurnchainOpsCommand) -> Result<Vec<PendingBurnchainOp>, String> + Send + Sync>;
+
+/// Applies a config patch, returning the keys whose value changed.
+pub type ConfigPatchHandler =
+    Arc<dyn Fn(serde_json::Value) -> Result<Vec<String>, String> + Send + Sync>;
+
+/// A block posted to `/v2/admin/block/validate`.
+pub struct BlockProposal {
+    pub parent_consensus_hash: ConsensusHash,
+    pub block: StacksBlock,
+    pub microblocks: Option<Vec<StacksMicroblock>>,
+}
+
+/// Validates a proposed block against the node's chainstate.  Invalid blocks are reported as
+/// `ChainstateError::InvalidStacksBlock`.
+pub type BlockProposalHandler =
+    Arc<dyn Fn(BlockProposal) -> Result<ValidatedBlockProposal, ChainstateError> + Send + Sync>;
+
+pub enum MinerCommand {
+    Status,
+    Stop,
+    /// Start mining, with a new keychain seed if given
+    Start(Option<Vec<u8>>),
+    /// Register a new VRF key, and switch to it once its registration is mined
+    RotateKey,
+}
+
+/// The miner's state, as reported by the `/v2/admin/miner` endpoints.
+#[derive(Debug, Clone, Serialize)]
+pub struct MinerStatus {
+    pub running: bool,
+    /// Number of times the miner was restarted
+    pub generation: u64,
+    pub burnchain_address: String,
+    pub stacks_address: String,
+    /// Number of VRF key rotations requested since the miner last started
+    pub key_rotations: u64,
+}
+
+pub type MinerControlHandler =
+    Arc<dyn Fn(MinerCommand) -> Result<MinerStatus, String> + Send + Sync>;
+
+/// Sums up the miner's block-commits over the given number of sortitions.
+pub type MinerStatsHandler = Arc<dyn Fn(u64) -> Result<MinerStats, String> + Send + Sync>;
+
+/// The node components the admin API acts on.
+#[derive(Clone)]
+pub struct AdminApi {
+    pub auth_token: Option<String>,
+    pub allow_remote: bool,
+    /// Whether to serve CPU and heap profiles
+    pub profiling: bool,
+    pub burnchain_ops: BurnchainOpsHandler,
+    pub config_patch: ConfigPatchHandler,
+    pub block_proposal: BlockProposalHandler,
+    pub miner_control: MinerControlHandler,
+    pub miner_stats: MinerStatsHandler,
+    /// Where the node keeps its chainstate backups, if `[node.backup]` is set
+    pub backup_dir: Option<PathBuf>,
+}
+
+impl AdminApi {
+    fn is_authorized(&self, req: &Request) -> bool {
+        let expected = match self.auth_token {
+            Some(ref token) => format!("Bearer {}", token),
+            None => return true,
+        };
+        match req.header("Authorization") {
+            Some(values) => {
+                constant_time_eq(values.last().as_str().as_bytes(), expected.as_bytes())
+            }
+            None => false,
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter()
+        .zip(b.iter())
+        .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+        == 0
+}
+
+#[derive(Debug, Deserialize)]
+struct FeeBumpRequest {
+    fee_rate: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MinerStartRequest {
+    seed: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockProposalRequest {
+    parent_consensus_hash: String,
+    block: String,
+    microblocks: Option<Vec<String>>,
+}
+
+impl BlockProposalRequest {
+    fn decode(self) -> Result<BlockProposal, String> {
+        let parent_consensus_hash = ConsensusHash::from_hex(&self.parent_consensus_hash)
+            .map_err(|e| format!("Invalid parent consensus hash: {:?}", e))?;
+        let block = hex_bytes(&self.block)
+            .map_err(|e| format!("Invalid block hex: {:?}", e))
+            .and_then(|bytes| {
+                StacksBlock::consensus_deserialize(&mut &bytes[..])
+                    .map_err(|e| format!("Invalid block: {:?}", e))
+            })?;
+        let microblocks = match self.microblocks {
+            Some(microblocks) => Some(
+                microblocks
+                    .iter()
+                    .map(|microblock| {
+                        hex_bytes(microblock)
+                            .map_err(|e| format!("Invalid microblock hex: {:?}", e))
+                            .and_then(|bytes| {
+                                StacksMicroblock::consensus_deserialize(&mut &bytes[..])
+                                    .map_err(|e| format!("Invalid microblock: {:?}", e))
+                            })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            None => None,
+        };
+        Ok(BlockProposal {
+            parent_consensus_hash,
+            block,
+            microblocks,
+        })
+    }
+}
+
+fn validated_block_json(validated: &ValidatedBlockProposal) -> serde_json::Value {
+    let tx_receipts: Vec<_> = validated
+        .tx_receipts
+        .iter()
+        .map(|receipt| {
+            json!({
+                "txid": receipt.transaction.txid().to_string(),
+                "tx_index": receipt.tx_index,
+                "burnchain_op": matches!(receipt.transaction, TransactionOrigin::Burn(_)),
+                "microblock_hash": receipt
+                    .microblock_header
+                    .as_ref()
+                    .map(|header| header.block_hash().to_string()),
+                "result": receipt.result.to_string(),
+                "post_condition_aborted": receipt.post_condition_aborted,
+                "execution_cost": receipt.execution_cost,
+            })
+        })
+        .collect();
+    json!({
+        "valid": true,
+        "block_hash": validated.block_hash.to_string(),
+        "parent_block_id": validated.parent_block_id.to_string(),
+        "stacks_height": validated.stacks_height,
+        "block_size": validated.block_size,
+        "num_microblocks": validated.num_microblocks,
+        "microblocks_cost": validated.microblocks_cost,
+        "block_cost": validated.block_cost,
+        "microblock_fees": validated.microblock_fees as u64,
+        "anchored_fees": validated.anchored_fees as u64,
+        "tx_receipts": tx_receipts,
+    })
+}
+
+pub fn start_serving_admin_api(bind_address: String, api: AdminApi) {
+    async_std::task::block_on(async {
+        let listener = TcpListener::bind(bind_address)
+            .await
+            .expect("Admin API: unable to bind address");
+        info!(
+            "Admin API: server listening on http://{}",
+            listener
+                .local_addr()
+                .expect("Admin API: unable to get addr")
+        );
+
+        let mut incoming = listener.incoming();
+        while let Some(stream) = incoming.next().await {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    error!("Admin API: unable to open socket - {:?}", err);
+                    continue;
+                }
+            };
+            let api = api.clone();
+            task::spawn(async {
+                if let Err(err) = accept(stream, api).await {
+                    warn!("Admin API: failed to handle request - {}", err);
+                }
+            });
+        }
+    });
+}
+
+async fn accept(stream: TcpStream, api: AdminApi) -> http_types::Result<()> {
+    let peer_addr = stream.peer_addr()?;
+    if !api.allow_remote && !peer_addr.ip().is_loopback() {
+        warn!(
+            "Admin API: refusing connection from non-loopback peer {}",
+            peer_addr
+        );
+        return Ok(());
+    }
+    debug!("Handle admin API request ({})", peer_addr);
+    async_h1::accept(stream.clone(), |mut req| {
+        req.set_peer_addr(Some(peer_addr));
+        handle_request(api.clone(), req)
+    })
+    .await?;
+    Ok(())
+}
+
+fn json_response<T: serde::Serialize>(status: StatusCode, body: &T) -> Response {
+    let mut response = Response::new(status);
+    response.append_header("Content-Type", "application/json");
+    response.set_body(Body::from_json(body).expect("FATAL: failed to serialize response"));
+    response
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    json_response(status, &json!({ "error": message }))
+}
+
+async fn handle_request(api: AdminApi, mut req: Request) -> http_types::Result<Response> {
+    if !api.is_authorized(&req) {
+        return Ok(error_response(
+            StatusCode::Unauthorized,
+            "Missing or invalid admin auth token",
+        ));
+    }
+
+    let path = req.url().path().to_string();
+    let command = match (req.method(), path.as_str()) {
+        (Method::Post, "/v2/admin/config") => return handle_config_patch(api, req).await,
+        (Method::Post, "/v2/admin/block/validate") => return handle_block_proposal(api, req).await,
+        (Method::Get, "/v2/admin/miner/stats") => return handle_miner_stats(api, req).await,
+        (Method::Get, "/v2/admin/backups") => return handle_list_backups(api).await,
+        (Method::Get, "/v2/admin/backups/file") => return handle_backup_file(api, req).await,
+        (Method::Get, "/v2/admin/debug/locks") => {
+            return Ok(json_response(StatusCode::Ok, &get_db_locks_report()))
+        }
+        (Method::Get, "/v2/admin/debug/pprof/profile")
+        | (Method::Get, "/v2/admin/debug/pprof/heap") => return handle_profile(api, req).await,
+        (Method::Get, "/v2/admin/miner")
+        | (Method::Post, "/v2/admin/miner/stop")
+        | (Method::Post, "/v2/admin/miner/start")
+        | (Method::Post, "/v2/admin/miner/rotate-key") => {
+            return handle_miner_control(api, req).await
+        }
+        (Method::Get, "/v2/admin/burnchain/ops") => BurnchainOpsCommand::List,
+        (Method::Post, "/v2/admin/burnchain/ops/abandon") => BurnchainOpsCommand::Abandon,
+        (Method::Post, "/v2/admin/burnchain/ops/fee-bump") => {
+            let body = req.body_string().await?;
+            if body.trim().is_empty() {
+                BurnchainOpsCommand::FeeBump(None)
+            } else {
+                match serde_json::from_str::<FeeBumpRequest>(&body) {
+                    Ok(request) => BurnchainOpsCommand::FeeBump(request.fee_rate),
+                    Err(e) => {
+                        return Ok(error_response(
+                            StatusCode::BadRequest,
+                            &format!("Invalid fee-bump request: {}", e),
+                        ))
+                    }
+                }
+            }
+        }
+        _ => return Ok(error_response(StatusCode::NotFound, "No such endpoint")),
+    };
+
+    // the handlers block on the node's threads, which is fine for a handful of operators
+    match (api.burnchain_ops)(command) {
+        Ok(ops) => Ok(json_response(StatusCode::Ok, &ops)),
+        Err(e) => Ok(error_response(StatusCode::BadRequest, &e)),
+    }
+}
+
+async fn handle_config_patch(api: AdminApi, mut req: Request) -> http_types::Result<Response> {
+    if api.auth_token.is_none() {
+        return Ok(error_response(
+            StatusCode::Forbidden,
+            "The config endpoint is disabled because node.admin_auth_token is not set",
+        ));
+    }
+    let patch = match serde_json::from_str(&req.body_string().await?) {
+        Ok(patch) => patch,
+        Err(e) => {
+            return Ok(error_response(
+                StatusCode::BadRequest,
+                &format!("Invalid config patch: {}", e),
+            ))
+        }
+    };
+    match (api.config_patch)(patch) {
+        Ok(changed) => Ok(json_response(
+            StatusCode::Ok,
+            &json!({ "changed": changed }),
+        )),
+        Err(e) => Ok(error_response(StatusCode::BadRequest, &e)),
+    }
+}
+
+async fn handle_miner_control(api: AdminApi, mut req: Request) -> http_types::Result<Response> {
+    if api.auth_token.is_none() {
+        return Ok(error_response(
+            StatusCode::Forbidden,
+            "The miner endpoints are disabled because node.admin_auth_token is not set",
+        ));
+    }
+    let command = match req.url().path() {
+        "/v2/admin/miner" => MinerCommand::Status,
+        "/v2/admin/miner/stop" => MinerCommand::Stop,
+        "/v2/admin/miner/rotate-key" => MinerCommand::RotateKey,
+        _ => {
+            let body = req.body_string().await?;
+            if body.trim().is_empty() {
+                MinerCommand::Start(None)
+            } else {
+                match serde_json::from_str::<MinerStartRequest>(&body)
+                    .map_err(|e| format!("Invalid miner start request: {}", e))
+                    .and_then(|request| match request.seed {
+                        Some(seed) => hex_bytes(&seed)
+                            .map(Some)
+                            .map_err(|e| format!("Invalid seed hex: {:?}", e)),
+                        None => Ok(None),
+                    }) {
+                    Ok(seed) => MinerCommand::Start(seed),
+                    Err(e) => return Ok(error_response(StatusCode::BadRequest, &e)),
+                }
+            }
+        }
+    };
+    match (api.miner_control)(command) {
+        Ok(status) => Ok(json_response(StatusCode::Ok, &status)),
+        Err(e) => Ok(error_response(StatusCode::BadRequest, &e)),
+    }
+}
+
+async fn handle_miner_stats(api: AdminApi, req: Request) -> http_types::Result<Response> {
+    if api.auth_token.is_none() {
+        return Ok(error_response(
+            StatusCode::Forbidden,
+            "The miner endpoints are disabled because node.admin_auth_token is not set",
+        ));
+    }
+    let count = match req.url().query_pairs().find(|(key, _)| key == "sortitions") {
+        Some((_, value)) => match value.parse::<u64>() {
+            Ok(count) if count > 0 && count <= MAX_MINER_STATS_SORTITIONS => count,
+            _ => {
+                return Ok(error_response(
+                    StatusCode::BadRequest,
+                    &format!(
+                        "sortitions should be a number from 1 to {}",
+                        MAX_MINER_STATS_SORTITIONS
+                    ),
+                ))
+            }
+        },
+        None => DEFAULT_MINER_STATS_SORTITIONS,
+    };
+    match (api.miner_stats)(count) {
+        Ok(stats) => Ok(json_response(StatusCode::Ok, &stats)),
+        Err(e) => Ok(error_response(StatusCode::BadRequest, &e)),
+    }
+}
+
+async fn handle_profile(api: AdminApi, req: Request) -> http_types::Result<Response> {
+    if !api.profiling {
+        return Ok(error_response(
+            StatusCode::Forbidden,
+            "The profile endpoints are disabled because node.profiling is not set",
+        ));
+    }
+    let from_loopback = req
+        .peer_addr()
+        .and_then(|addr| addr.parse::<SocketAddr>().ok())
+        .map(|addr| addr.ip().is_loopback())
+        .unwrap_or(false);
+    if !from_loopback {
+        return Ok(error_response(
+            StatusCode::Forbidden,
+            "Profiles are only served to loopback peers",
+        ));
+    }
+
+    let query =
+        |name: &str, default: u64| match req.url().query_pairs().find(|(key, _)| key == name) {
+            Some((_, value)) => value.parse::<u64>().ok(),
+            None => Some(default),
+        };
+    let seconds = match query("seconds", profiler::DEFAULT_PROFILE_SECS) {
+        Some(seconds) if seconds > 0 && seconds <= profiler::MAX_PROFILE_SECS => seconds,
+        _ => {
+            return Ok(error_response(
+                StatusCode::BadRequest,
+                &format!(
+                    "seconds should be a number from 1 to {}",
+                    profiler::MAX_PROFILE_SECS
+                ),
+            ))
+        }
+    };
+    let duration = Duration::from_secs(seconds);
+    let take_profile: Box<dyn FnOnce() -> Result<Vec<u8>, String> + Send> =
+        if req.url().path().ends_with("/heap") {
+            match query("sample_bytes", profiler::DEFAULT_HEAP_SAMPLE_BYTES) {
+                Some(sample_bytes) => {
+                    Box::new(move || profiler::profile_heap(duration, sample_bytes))
+                }
+                None => {
+                    return Ok(error_response(
+                        StatusCode::BadRequest,
+                        "sample_bytes should be a number",
+                    ))
+                }
+            }
+        } else {
+            match query("frequency", profiler::DEFAULT_CPU_FREQUENCY) {
+                Some(frequency) => Box::new(move || profiler::profile_cpu(duration, frequency)),
+                None => {
+                    return Ok(error_response(
+                        StatusCode::BadRequest,
+                        "frequency should be a number",
+                    ))
+                }
+            }
+        };
+
+    // profiling blocks for the profile's duration, so it gets a thread of its own
+    let (profile_send, profile_recv) = channel::bounded(1);
+    thread::Builder::new()
+        .name("profiler".to_string())
+        .spawn(move || {
+            let _ = profile_send.try_send(take_profile());
+        })?;
+    let profile = profile_recv
+        .recv()
+        .await
+        .unwrap_or_else(|_| Err("The profiler thread exited".to_string()));
+    match profile {
+        Ok(profile) => {
+            let mut response = Response::new(StatusCode::Ok);
+            response.append_header("Content-Type", "application/octet-stream");
+            response.set_body(profile);
+            Ok(response)
+        }
+        Err(e) => Ok(error_response(StatusCode::BadRequest, &e)),
+    }
+}
+
+/// The backup directory, or the response to give if the backup endpoints are disabled
+fn get_backup_dir(api: &AdminApi) -> Result<PathBuf, Response> {
+    if api.auth_token.is_none() {
+        return Err(error_response(
+            StatusCode::Forbidden,
+            "The backup endpoints are disabled because node.admin_auth_token is not set",
+        ));
+    }
+    api.backup_dir.clone().ok_or_else(|| {
+        error_response(
+            StatusCode::NotFound,
+            "The node takes no backups because node.backup is not set",
+        )
+    })
+}
+
+async fn handle_list_backups(api: AdminApi) -> http_types::Result<Response> {
+    let backup_dir = match get_backup_dir(&api) {
+        Ok(dir) => dir,
+        Err(response) => return Ok(response),
+    };
+    match backup::list_backup_manifests(&backup_dir) {
+        Ok(listings) => Ok(json_response(StatusCode::Ok, &listings)),
+        Err(e) => Ok(error_response(StatusCode::InternalServerError, &e)),
+    }
+}
+
+async fn handle_backup_file(api: AdminApi, req: Request) -> http_types::Result<Response> {
+    let backup_dir = match get_backup_dir(&api) {
+        Ok(dir) => dir,
+        Err(response) => return Ok(response),
+    };
+    let query = |name: &str| {
+        req.url()
+            .query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.to_string())
+    };
+    let height = query("height").and_then(|height| height.parse::<u64>().ok());
+    let offset = query("offset").map(|offset| offset.parse::<u64>().ok());
+    let length = query("length").map(|length| length.parse::<u64>().ok().map(Some));
+    let (height, path, offset, length) = match (
+        height,
+        query("path"),
+        offset.unwrap_or(Some(0)),
+        length.unwrap_or(Some(None)),
+    ) {
+        (Some(height), Some(path), Some(offset), Some(length)) => (height, path, offset, length),
+        _ => {
+            return Ok(error_response(
+                StatusCode::BadRequest,
+                "Expected a backup height, a path, and optionally a numeric offset and length",
+            ))
+        }
+    };
+
+    // only serve files the manifest lists, so that nothing outside the backup can be named
+    let backup_path = backup_dir.join(height.to_string());
+    let file = match backup::read_manifest(&backup_path) {
+        Ok(manifest) => manifest.files.into_iter().find(|file| file.path == path),
+        Err(_) => {
+            return Ok(error_response(
+                StatusCode::NotFound,
+                &format!("No backup at height {}", height),
+            ))
+        }
+    };
+    let file = match file {
+        Some(file) => file,
+        None => {
+            return Ok(error_response(
+                StatusCode::NotFound,
+                &format!("Backup {} has no file {}", height, path),
+            ))
+        }
+    };
+    if offset > file.len {
+        return Ok(error_response(
+            StatusCode::RequestedRangeNotSatisfiable,
+            &format!("{} is only {} bytes", path, file.len),
+        ));
+    }
+
+    let length = length.map_or(file.len - offset, |length| length.min(file.len - offset));
+    let mut reader = File::open(backup_path.join(&file.path)).await?;
+    reader.seek(SeekFrom::Start(offset)).await?;
+    let mut response = Response::new(StatusCode::Ok);
+    response.append_header("Content-Type", "application/octet-stream");
+    response.set_body(Body::from_reader(
+        BufReader::new(reader.take(length)),
+        Some(length as usize),
+    ));
+    Ok(response)
+}
+
+async fn handle_block_proposal(api: AdminApi, mut req: Request) -> http_types::Result<Response> {
+    if api.auth_token.is_none() {
+        return Ok(error_response(
+            StatusCode::Forbidden,
+            "The block validation endpoint is disabled because node.admin_auth_token is not set",
+        ));
+    }
+    let proposal = match serde_json::from_str::<BlockProposalRequest>(&req.body_string().await?)
+        .map_err(|e| format!("Invalid block proposal: {}", e))
+        .and_then(|request| request.decode())
+    {
+        Ok(proposal) => proposal,
+        Err(e) => return Ok(error_response(StatusCode::BadRequest, &e)),
+    };
+    match (api.block_proposal)(proposal) {
+        Ok(validated) => Ok(json_response(
+            StatusCode::Ok,
+            &validated_block_json(&validated),
+        )),
+        Err(ChainstateError::InvalidStacksBlock(reason)) => Ok(json_response(
+            StatusCode::Ok,
+            &json!({ "valid": false, "reason": reason }),
+        )),
+        Err(e) => Ok(error_response(
+            StatusCode::InternalServerError,
+            &format!("Failed to validate block: {:?}", e),
+        )),
+    }
+}
+
+/// Sends a request to the admin API of the node configured by `config`, as the `stacks-node`
+/// subcommands that drive a running node do, and returns its JSON answer.
+pub fn admin_request(
+    config: &Config,
+    method: Method,
+    path: &str,
+) -> Result<serde_json::Value, String> {
+    let admin_address = config
+        .node
+        .admin_bind
+        .as_ref()
+        .ok_or("node.admin_bind is not set, so the node has no admin API".to_string())?
+        .replace("0.0.0.0", "127.0.0.1");
+    let url = Url::parse(&format!("http://{}{}", admin_address, path))
+        .map_err(|e| format!("Invalid admin API address '{}': {:?}", admin_address, e))?;
+    let mut request = Request::new(method, url);
+    request.append_header("Connection", "close");
+    if let Some(ref token) = config.node.admin_auth_token {
+        request.append_header("Authorization", format!("Bearer {}", token).as_str());
+    }
+
+    let (status, buffer) = task::block_on(async move {
+        let stream = TcpStream::connect(&admin_address).await.map_err(|e| {
+            format!(
+                "Failed to connect to the admin API at {}: {:?}",
+                admin_address, e
+            )
+        })?;
+        let mut response = async_h1::client::connect(stream, request)
+            .await
+            .map_err(|e| format!("Admin API request failed: {:?}", e))?;
+        let mut buffer = Vec::new();
+        response
+            .take_body()
+            .read_to_end(&mut buffer)
+            .await
+            .map_err(|e| format!("Failed to read admin API response: {:?}", e))?;
+        Ok::<_, String>((response.status(), buffer))
+    })?;
+
+    let body: serde_json::Value = serde_json::from_slice(&buffer)
+        .map_err(|e| format!("Failed to parse admin API response to {}: {:?}", path, e))?;
+    if !status.is_success() {
+        return Err(match body.get("error").and_then(|e| e.as_str()) {
+            Some(error) => error.to_string(),
+            None => format!("Admin API: status({}) for {}", status, path),
+        });
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    use stacks::chainstate::stacks::{
+        CoinbasePayload, StacksBlockHeader, StacksMicroblockHeader, StacksPrivateKey,
+        StacksTransaction, TransactionAuth, TransactionPayload, TransactionVersion,
+    };
+    use stacks::core::EMPTY_MICROBLOCK_PARENT_HASH;
+    use stacks::types::chainstate::{StacksWorkScore, TrieHash};
+    use stacks::util::hash::{to_hex, Hash160, Sha512Trunc256Sum};
+    use stacks::util::vrf::VRFProof;
+    use stacks::vm::costs::ExecutionCost;
+
+    use crate::config::Config;
+
+    fn make_op(txid: &str, fee_rate: u64) -> PendingBurnchainOp {
+        PendingBurnchainOp {
+            op: "leader_block_commit".to_string(),
+            txid: txid.to_string(),
+            fee_rate,
+            total_spent: 1000,
+            replaces: vec![],
+        }
+    }
+
+    fn make_request(method: Method, path: &str, body: Option<&str>) -> Request {
+        let url = Url::parse(&format!("http://127.0.0.1{}", path)).unwrap();
+        let mut req = Request::new(method, url);
+        if let Some(body) = body {
+            req.set_body(body);
+        }
+        req
+    }
+
+    fn make_authorized_request(method: Method, path: &str, body: Option<&str>) -> Request {
+        let mut req = make_request(method, path, body);
+        req.insert_header("Authorization", "Bearer secret");
+        req
+    }
+
+    fn make_api(burnchain_ops: BurnchainOpsHandler) -> AdminApi {
+        AdminApi {
+            auth_token: None,
+            allow_remote: false,
+            profiling: false,
+            burnchain_ops,
+            config_patch: Arc::new(|_| Err("unused".to_string())),
+            block_proposal: Arc::new(|_| Err(ChainstateError::NoSuchBlockError)),
+            miner_control: Arc::new(|_| Err("unused".to_string())),
+            miner_stats: Arc::new(|_| Err("unused".to_string())),
+            backup_dir: None,
+        }
+    }
+
+    fn call(api: &AdminApi, req: Request) -> (StatusCode, serde_json::Value) {
+        task::block_on(async {
+            let mut response = handle_request(api.clone(), req).await.unwrap();
+            let body: serde_json::Value = response.body_json().await.unwrap();
+            (response.status(), body)
+        })
+    }
+
+    #[test]
+    fn test_burnchain_ops_endpoints() {
+        let ops = Arc::new(Mutex::new(vec![make_op("aa", 10)]));
+        let handler_ops = ops.clone();
+        let api = make_api(Arc::new(move |command| {
+            let mut ops = handler_ops.lock().unwrap();
+            match command {
+                BurnchainOpsCommand::List => Ok(ops.clone()),
+                BurnchainOpsCommand::Abandon => Ok(ops.drain(..).collect()),
+                BurnchainOpsCommand::FeeBump(fee_rate) => {
+                    let op = ops
+                        .pop()
+                        .ok_or("No block commit is in flight".to_string())?;
+                    let mut replacement = make_op("bb", fee_rate.unwrap_or(op.fee_rate + 1));
+                    replacement.replaces = vec![op.txid];
+                    ops.push(replacement.clone());
+                    Ok(vec![replacement])
+                }
+            }
+        }));
+
+        let (status, body) = call(
+            &api,
+            make_request(Method::Get, "/v2/admin/burnchain/ops", None),
+        );
+        assert_eq!(status, StatusCode::Ok);
+        assert_eq!(body, json!([make_op("aa", 10)]));
+
+        let (status, body) = call(
+            &api,
+            make_request(
+                Method::Post,
+                "/v2/admin/burnchain/ops/fee-bump",
+                Some(r#"{"fee_rate": 25}"#),
+            ),
+        );
+        assert_eq!(status, StatusCode::Ok);
+        assert_eq!(body[0]["fee_rate"], json!(25));
+        assert_eq!(body[0]["replaces"], json!(["aa"]));
+
+        let (status, body) = call(
+            &api,
+            make_request(Method::Post, "/v2/admin/burnchain/ops/fee-bump", Some("{")),
+        );
+        assert_eq!(status, StatusCode::BadRequest);
+        assert!(body["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid fee-bump request"));
+
+        let (status, body) = call(
+            &api,
+            make_request(Method::Post, "/v2/admin/burnchain/ops/abandon", None),
+        );
+        assert_eq!(status, StatusCode::Ok);
+        assert_eq!(body[0]["txid"], json!("bb"));
+        assert!(ops.lock().unwrap().is_empty());
+
+        let (status, body) = call(
+            &api,
+            make_request(Method::Post, "/v2/admin/burnchain/ops/fee-bump", None),
+        );
+        assert_eq!(status, StatusCode::BadRequest);
+        assert_eq!(body["error"], json!("No block commit is in flight"));
+
+        let (status, _) = call(&api, make_request(Method::Get, "/v2/admin/nope", None));
+        assert_eq!(status, StatusCode::NotFound);
+    }
+
+    #[test]
+    fn test_config_endpoint() {
+        let config = Arc::new(Mutex::new(Config::default()));
+        let handler_config = config.clone();
+        let mut api = make_api(Arc::new(|_| Ok(vec![])));
+        api.config_patch =
+            Arc::new(move |patch| handler_config.lock().unwrap().apply_dynamic_patch(&patch));
+
+        // disabled without a token
+        let (status, _) = call(
+            &api,
+            make_request(Method::Post, "/v2/admin/config", Some("{}")),
+        );
+        assert_eq!(status, StatusCode::Forbidden);
+
+        api.auth_token = Some("secret".to_string());
+        let (status, _) = call(
+            &api,
+            make_request(Method::Post, "/v2/admin/config", Some("{}")),
+        );
+        assert_eq!(status, StatusCode::Unauthorized);
+        let (status, _) = call(
+            &api,
+            make_request(Method::Get, "/v2/admin/burnchain/ops", None),
+        );
+        assert_eq!(status, StatusCode::Unauthorized);
+
+        let default_fee_cap = config.lock().unwrap().burnchain.burn_fee_cap;
+        let (status, body) = call(
+            &api,
+            make_authorized_request(
+                Method::Post,
+                "/v2/admin/config",
+                Some(&format!(
+                    r#"{{"burnchain": {{"satoshis_per_byte": 80, "burn_fee_cap": {}}}, "miner": {{"min_tx_fee": 10}}}}"#,
+                    default_fee_cap
+                )),
+            ),
+        );
+        assert_eq!(status, StatusCode::Ok);
+        assert_eq!(
+            body,
+            json!({"changed": ["burnchain.satoshis_per_byte", "miner.min_tx_fee"]})
+        );
+        assert_eq!(config.lock().unwrap().burnchain.satoshis_per_byte, 80);
+        assert_eq!(config.lock().unwrap().miner.min_tx_fee, 10);
+
+        // non-reloadable keys reject the whole patch
+        let (status, body) = call(
+            &api,
+            make_authorized_request(
+                Method::Post,
+                "/v2/admin/config",
+                Some(r#"{"miner": {"min_tx_fee": 20}, "node": {"rpc_bind": "0.0.0.0:1"}}"#),
+            ),
+        );
+        assert_eq!(status, StatusCode::BadRequest);
+        assert_eq!(
+            body["error"],
+            json!("Config keys cannot be changed at runtime: node.rpc_bind")
+        );
+        assert_eq!(config.lock().unwrap().miner.min_tx_fee, 10);
+
+        let (status, body) = call(
+            &api,
+            make_authorized_request(
+                Method::Post,
+                "/v2/admin/config",
+                Some(r#"{"miner": {"probability_pick_no_estimate_tx": 256}}"#),
+            ),
+        );
+        assert_eq!(status, StatusCode::BadRequest);
+        assert_eq!(
+            body["error"],
+            json!("Invalid value for 'miner.probability_pick_no_estimate_tx': invalid number")
+        );
+    }
+
+    #[test]
+    fn test_block_proposal_endpoint() {
+        let mut api = make_api(Arc::new(|_| Ok(vec![])));
+        api.block_proposal = Arc::new(|proposal| {
+            if proposal.microblocks.is_some() {
+                return Err(ChainstateError::InvalidStacksBlock(
+                    "Parent microblock stream does not connect".to_string(),
+                ));
+            }
+            Ok(ValidatedBlockProposal {
+                block_hash: proposal.block.block_hash(),
+                parent_block_id: StacksBlockHeader::make_index_block_hash(
+                    &proposal.parent_consensus_hash,
+                    &proposal.block.header.parent_block,
+                ),
+                stacks_height: proposal.block.header.total_work.work,
+                block_size: 100,
+                num_microblocks: 0,
+                microblocks_cost: ExecutionCost::zero(),
+                block_cost: ExecutionCost::zero(),
+                microblock_fees: 0,
+                anchored_fees: 0,
+                tx_receipts: vec![],
+            })
+        });
+
+        let privk = StacksPrivateKey::new();
+        let coinbase = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(&privk).unwrap(),
+            TransactionPayload::Coinbase(CoinbasePayload([0u8; 32])),
+        );
+        let block = StacksBlock::from_parent(
+            &StacksBlockHeader::genesis_block_header(),
+            &StacksMicroblockHeader::first_unsigned(
+                &EMPTY_MICROBLOCK_PARENT_HASH,
+                &Sha512Trunc256Sum([0u8; 32]),
+            ),
+            vec![coinbase],
+            &StacksWorkScore::initial(),
+            &VRFProof::empty(),
+            &TrieHash([0u8; 32]),
+            &Hash160([0u8; 20]),
+        );
+        let body = format!(
+            r#"{{"parent_consensus_hash": "{}", "block": "{}"}}"#,
+            ConsensusHash([0x11; 20]),
+            to_hex(&block.serialize_to_vec())
+        );
+
+        // disabled without a token
+        let (status, _) = call(
+            &api,
+            make_request(Method::Post, "/v2/admin/block/validate", Some(&body)),
+        );
+        assert_eq!(status, StatusCode::Forbidden);
+
+        api.auth_token = Some("secret".to_string());
+        let (status, body_json) = call(
+            &api,
+            make_authorized_request(Method::Post, "/v2/admin/block/validate", Some(&body)),
+        );
+        assert_eq!(status, StatusCode::Ok);
+        assert_eq!(body_json["valid"], json!(true));
+        assert_eq!(
+            body_json["block_hash"],
+            json!(block.block_hash().to_string())
+        );
+        assert_eq!(body_json["tx_receipts"], json!([]));
+
+        let body = format!(
+            r#"{{"parent_consensus_hash": "{}", "block": "{}", "microblocks": []}}"#,
+            ConsensusHash([0x11; 20]),
+            to_hex(&block.serialize_to_vec())
+        );
+        let (status, body_json) = call(
+            &api,
+            make_authorized_request(Method::Post, "/v2/admin/block/validate", Some(&body)),
+        );
+        assert_eq!(status, StatusCode::Ok);
+        assert_eq!(
+            body_json,
+            json!({"valid": false, "reason": "Parent microblock stream does not connect"})
+        );
+
+        let (status, body_json) = call(
+            &api,
+            make_authorized_request(
+                Method::Post,
+                "/v2/admin/block/validate",
+                Some(r#"{"parent_consensus_hash": "11", "block": "00"}"#),
+            ),
+        );
+        assert_eq!(status, StatusCode::BadRequest);
+        assert!(body_json["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid parent consensus hash"));
+    }
+
+    #[test]
+    fn test_miner_control_endpoints() {
+        let status = Arc::new(Mutex::new(MinerStatus {
+            running: true,
+            generation: 0,
+            burnchain_address: "mzYBtAjNzuEvEMAp2ahx8oT9kWWvb5L2Rj".to_string(),
+            stacks_address: "ST2QKZ4FKHAH1NQKYKYAYZPY440FEPK7GZ1R5HBP2".to_string(),
+            key_rotations: 0,
+        }));
+        let handler_status = status.clone();
+        let mut api = make_api(Arc::new(|_| Ok(vec![])));
+        api.miner_control = Arc::new(move |command| {
+            let mut status = handler_status.lock().unwrap();
+            match command {
+                MinerCommand::Status => {}
+                MinerCommand::Stop => status.running = false,
+                MinerCommand::RotateKey => {
+                    if !status.running {
+                        return Err("not running".to_string());
+                    }
+                    status.key_rotations += 1;
+                }
+                MinerCommand::Start(seed) => {
+                    if seed == Some(vec![]) {
+                        return Err("empty seed".to_string());
+                    }
+                    status.running = true;
+                    status.generation += 1;
+                    status.key_rotations = 0;
+                    if let Some(seed) = seed {
+                        status.stacks_address = to_hex(&seed);
+                    }
+                }
+            }
+            Ok(status.clone())
+        });
+
+        // disabled without a token
+        let (status_code, _) = call(&api, make_request(Method::Get, "/v2/admin/miner", None));
+        assert_eq!(status_code, StatusCode::Forbidden);
+
+        api.auth_token = Some("secret".to_string());
+        let (status_code, _) = call(
+            &api,
+            make_request(Method::Post, "/v2/admin/miner/stop", None),
+        );
+        assert_eq!(status_code, StatusCode::Unauthorized);
+
+        let (status_code, body) = call(
+            &api,
+            make_authorized_request(Method::Get, "/v2/admin/miner", None),
+        );
+        assert_eq!(status_code, StatusCode::Ok);
+        assert_eq!(body["running"], json!(true));
+        assert_eq!(body["generation"], json!(0));
+
+        let (status_code, body) = call(
+            &api,
+            make_authorized_request(Method::Post, "/v2/admin/miner/stop", None),
+        );
+        assert_eq!(status_code, StatusCode::Ok);
+        assert_eq!(body["running"], json!(false));
+
+        let (status_code, body) = call(
+            &api,
+            make_authorized_request(Method::Post, "/v2/admin/miner/rotate-key", None),
+        );
+        assert_eq!(status_code, StatusCode::BadRequest);
+        assert_eq!(body["error"], json!("not running"));
+
+        // restart with the same keychain
+        let (status_code, body) = call(
+            &api,
+            make_authorized_request(Method::Post, "/v2/admin/miner/start", None),
+        );
+        assert_eq!(status_code, StatusCode::Ok);
+        assert_eq!(body["running"], json!(true));
+        assert_eq!(body["generation"], json!(1));
+
+        let (status_code, body) = call(
+            &api,
+            make_authorized_request(Method::Post, "/v2/admin/miner/rotate-key", None),
+        );
+        assert_eq!(status_code, StatusCode::Ok);
+        assert_eq!(body["key_rotations"], json!(1));
+        assert_eq!(body["generation"], json!(1));
+
+        // restart with a new seed
+        let (status_code, body) = call(
+            &api,
+            make_authorized_request(
+                Method::Post,
+                "/v2/admin/miner/start",
+                Some(r#"{"seed": "0102"}"#),
+            ),
+        );
+        assert_eq!(status_code, StatusCode::Ok);
+        assert_eq!(body["generation"], json!(2));
+        assert_eq!(body["stacks_address"], json!("0102"));
+
+        let (status_code, body) = call(
+            &api,
+            make_authorized_request(
+                Method::Post,
+                "/v2/admin/miner/start",
+                Some(r#"{"seed": "zz"}"#),
+            ),
+        );
+        assert_eq!(status_code, StatusCode::BadRequest);
+        assert!(body["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid seed hex"));
+
+        // handler errors are passed through
+        let (status_code, body) = call(
+            &api,
+            make_authorized_request(
+                Method::Post,
+                "/v2/admin/miner/start",
+                Some(r#"{"seed": ""}"#),
+            ),
+        );
+        assert_eq!(status_code, StatusCode::BadRequest);
+        assert_eq!(body["error"], json!("empty seed"));
+    }
+
+    #[test]
+    fn test_miner_stats_endpoint() {
+        let mut api = make_api(Arc::new(|_| Ok(vec![])));
+        api.miner_stats = Arc::new(|count| {
+            Ok(MinerStats {
+                sortitions: count,
+                first_burn_height: 1,
+                last_burn_height: count,
+                commits: 0,
+                wins: 0,
+                win_rate: None,
+                expected_win_rate: None,
+                burn: 0,
+                total_burn: 0,
+                median_competitor_burn: None,
+                late_commits: 0,
+                missed_commits: 0,
+            })
+        });
+
+        let (status_code, _) = call(
+            &api,
+            make_request(Method::Get, "/v2/admin/miner/stats", None),
+        );
+        assert_eq!(status_code, StatusCode::Forbidden);
+
+        api.auth_token = Some("secret".to_string());
+        let (status_code, body) = call(
+            &api,
+            make_authorized_request(Method::Get, "/v2/admin/miner/stats", None),
+        );
+        assert_eq!(status_code, StatusCode::Ok);
+        assert_eq!(body["sortitions"], DEFAULT_MINER_STATS_SORTITIONS);
+
+        let (status_code, body) = call(
+            &api,
+            make_authorized_request(Method::Get, "/v2/admin/miner/stats?sortitions=10", None),
+        );
+        assert_eq!(status_code, StatusCode::Ok);
+        assert_eq!(body["sortitions"], 10);
+        assert_eq!(body["win_rate"], serde_json::Value::Null);
+
+        for query in ["sortitions=0", "sortitions=nope", "sortitions=1000000"].iter() {
+            let (status_code, _) = call(
+                &api,
+                make_authorized_request(
+                    Method::Get,
+                    &format!("/v2/admin/miner/stats?{}", query),
+                    None,
+                ),
+            );
+            assert_eq!(status_code, StatusCode::BadRequest);
+        }
+    }
+
+    #[test]
+    fn test_debug_locks_endpoint() {
+        let api = make_api(Arc::new(|_| Ok(vec![])));
+        let (status_code, body) = call(
+            &api,
+            make_request(Method::Get, "/v2/admin/debug/locks", None),
+        );
+        assert_eq!(status_code, StatusCode::Ok);
+        assert!(body["now_ms"].as_u64().unwrap() > 0);
+        assert!(body["locks"].is_array());
+        assert!(body["stats"].is_array());
+    }
+
+    #[test]
+    fn test_profile_endpoints() {
+        let mut api = make_api(Arc::new(|_| Ok(vec![])));
+        let profile_request = |peer_addr: &str, query: &str| {
+            let mut req = make_request(
+                Method::Get,
+                &format!("/v2/admin/debug/pprof/profile?{}", query),
+                None,
+            );
+            req.set_peer_addr(Some(peer_addr));
+            req
+        };
+
+        let (status, _) = call(&api, profile_request("127.0.0.1:5000", "seconds=1"));
+        assert_eq!(status, StatusCode::Forbidden);
+
+        api.profiling = true;
+        let (status, body) = call(&api, profile_request("10.0.0.1:5000", "seconds=1"));
+        assert_eq!(status, StatusCode::Forbidden);
+        assert_eq!(body["error"], "Profiles are only served to loopback peers");
+
+        let (status, _) = call(&api, profile_request("127.0.0.1:5000", "seconds=0"));
+        assert_eq!(status, StatusCode::BadRequest);
+
+        let (status, profile) = task::block_on(async {
+            let mut response = handle_request(
+                api.clone(),
+                profile_request("127.0.0.1:5000", "seconds=1&frequency=100"),
+            )
+            .await
+            .unwrap();
+            (response.status(), response.body_bytes().await.unwrap())
+        });
+        assert_eq!(status, StatusCode::Ok);
+        assert!(!profile.is_empty());
+    }
+
+    #[test]
+    fn test_backup_endpoints() {
+        let backup_dir = PathBuf::from("/tmp/stacks-node-tests/admin/backups");
+        if backup_dir.exists() {
+            std::fs::remove_dir_all(&backup_dir).unwrap();
+        }
+        let backup = backup_dir.join("10");
+        std::fs::create_dir_all(&backup).unwrap();
+        std::fs::write(backup.join("headers.sqlite"), b"0123456789").unwrap();
+        std::fs::write(backup_dir.join("secret"), b"secret").unwrap();
+        let manifest = backup::BackupManifest {
+            mainnet: false,
+            chain_id: 0x80000000,
+            burn_block_height: 10,
+            burn_header_hash: "00".repeat(32),
+            consensus_hash: "00".repeat(20),
+            stacks_block_height: 5,
+            stacks_block_id: "00".repeat(32),
+            files: vec![backup::BackupFile {
+                path: "headers.sqlite".to_string(),
+                len: 10,
+                chunks: vec![],
+            }],
+            started_at: 0,
+            finished_at: 0,
+        };
+        std::fs::write(
+            backup.join(backup::BACKUP_MANIFEST_FILE),
+            serde_json::to_string(&manifest).unwrap(),
+        )
+        .unwrap();
+
+        let mut api = make_api(Arc::new(|_| Ok(vec![])));
+        api.backup_dir = Some(backup_dir);
+        let (status_code, _) = call(&api, make_request(Method::Get, "/v2/admin/backups", None));
+        assert_eq!(status_code, StatusCode::Forbidden);
+
+        api.auth_token = Some("secret".to_string());
+        let (status_code, body) = call(
+            &api,
+            make_authorized_request(Method::Get, "/v2/admin/backups", None),
+        );
+        assert_eq!(status_code, StatusCode::Ok);
+        let listings: Vec<backup::BackupListing> = serde_json::from_value(body).unwrap();
+        assert_eq!(
+            listings,
+            vec![backup::BackupListing {
+                height: 10,
+                manifest,
+            }]
+        );
+
+        let fetch = |query: &str| {
+            task::block_on(async {
+                let mut response = handle_request(
+                    api.clone(),
+                    make_authorized_request(
+                        Method::Get,
+                        &format!("/v2/admin/backups/file?{}", query),
+                        None,
+                    ),
+                )
+                .await
+                .unwrap();
+                (response.status(), response.body_bytes().await.unwrap())
+            })
+        };
+        assert_eq!(
+            fetch("height=10&path=headers.sqlite"),
+            (StatusCode::Ok, b"0123456789".to_vec())
+        );
+        assert_eq!(
+            fetch("height=10&path=headers.sqlite&offset=4"),
+            (StatusCode::Ok, b"456789".to_vec())
+        );
+        assert_eq!(
+            fetch("height=10&path=headers.sqlite&offset=4&length=3"),
+            (StatusCode::Ok, b"456".to_vec())
+        );
+        assert_eq!(
+            fetch("height=10&path=headers.sqlite&offset=8&length=5"),
+            (StatusCode::Ok, b"89".to_vec())
+        );
+        assert_eq!(
+            fetch("height=10&path=headers.sqlite&length=x").0,
+            StatusCode::BadRequest
+        );
+        assert_eq!(
+            fetch("height=10&path=headers.sqlite&offset=11").0,
+            StatusCode::RequestedRangeNotSatisfiable
+        );
+        // only files listed in the manifest are served
+        assert_eq!(fetch("height=10&path=..%2Fsecret").0, StatusCode::NotFound);
+        assert_eq!(
+            fetch("height=9&path=headers.sqlite").0,
+            StatusCode::NotFound
+        );
+        assert_eq!(fetch("path=headers.sqlite").0, StatusCode::BadRequest);
+
+        // a node that takes no backups
+        api.backup_dir = None;
+        let (status_code, _) = call(
+            &api,
+            make_authorized_request(Method::Get, "/v2/admin/backups", None),
+        );
+        assert_eq!(status_code, StatusCode::NotFound);
+    }
+}
@@ -0,0 +1,566 @@
+//! Periodic backups of the chainstate, configured by `[node.backup]`.
+//!
+//! Copying the working directory of a running node does not produce a usable chainstate: the
+//! sortition DB, the chainstate DBs and their MARF blob files are written to at different times,
+//! and a copy of a sqlite DB taken in the middle of a write is corrupt. A backup instead pauses
+//! the chains coordinator -- the only writer of processed sortitions and blocks -- and copies
+//! each sqlite DB with the sqlite backup API, which reads one consistent state of it. Each MARF
+//! blob file is append-only, so it is copied after its DB and cut at the last byte that the
+//! copied DB refers to. Every copy then agrees on the burnchain and Stacks tips recorded in the
+//! backup's `manifest.json`.
+//!
+//! Backups are written to `{dir}/{height}.tmp`, where `height` is the burn block height that
+//! triggered them, and renamed to `{dir}/{height}` once complete. Only the newest `keep` backups
+//! are kept. To restore one, stop the node and copy the contents of the backup directory into
+//! `{working_dir}/{mode}`.
+
+use std::fs;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use rusqlite::backup::{Backup, StepResult};
+use rusqlite::{Connection, OpenFlags, NO_PARAMS};
+
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::coordinator::comm::CoordinatorChannels;
+use stacks::types::chainstate::StacksBlockId;
+use stacks::util::get_epoch_time_secs;
+use stacks::util::hash::Sha256Sum;
+
+use crate::config::BackupConfig;
+use crate::Config;
+
+pub const BACKUP_MANIFEST_FILE: &str = "manifest.json";
+const BACKUP_TMP_SUFFIX: &str = ".tmp";
+/// The size of the sqlite DB chunks listed in a backup manifest
+pub const BACKUP_CHUNK_SIZE: u64 = 1024 * 1024;
+/// How many times to retry a sqlite backup that finds its source locked
+const BACKUP_BUSY_RETRIES: u32 = 100;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub mainnet: bool,
+    pub chain_id: u32,
+    /// The canonical burnchain tip of every DB in the backup
+    pub burn_block_height: u64,
+    pub burn_header_hash: String,
+    pub consensus_hash: String,
+    /// The canonical Stacks tip of every DB in the backup
+    pub stacks_block_height: u64,
+    pub stacks_block_id: String,
+    pub files: Vec<BackupFile>,
+    pub started_at: u64,
+    pub finished_at: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BackupFile {
+    /// Path relative to the backup directory
+    pub path: String,
+    pub len: u64,
+    /// SHA-256 of each `BACKUP_CHUNK_SIZE`-byte chunk of a sqlite DB, so that a read replica
+    /// fetches only the chunks that changed since the last backup it ingested.  Empty for
+    /// other files, and in backups taken before chunks were listed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<String>,
+}
+
+/// A sqlite DB to back up, relative to `{working_dir}/{mode}`, and whether it is a MARF that may
+/// keep its tries in a `.blobs` file.
+const BACKUP_DBS: &[(&str, bool)] = &[
+    ("chainstate/vm/index.sqlite", true),
+    ("chainstate/vm/clarity/marf.sqlite", true),
+    ("burnchain/sortition/marf.sqlite", true),
+    ("burnchain/burnchain.sqlite", false),
+    ("headers.sqlite", false),
+];
+/// Block and microblock files, relative to `{working_dir}/{mode}`
+pub const BACKUP_BLOCKS_DIR: &str = "chainstate/blocks";
+
+/// Copies the sqlite DB at `src` to `dest` with the sqlite backup API.  All pages are copied in
+/// one step, i.e. within a single read transaction, so that other connections can keep writing
+/// to `src` without the copy being restarted or catching half of a write.
+pub fn backup_sqlite_db(src: &Path, dest: &Path) -> Result<(), String> {
+    let src_conn = Connection::open_with_flags(src, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open {:?}: {}", src, e))?;
+    let mut dest_conn =
+        Connection::open(dest).map_err(|e| format!("Failed to open {:?}: {}", dest, e))?;
+    let backup = Backup::new(&src_conn, &mut dest_conn)
+        .map_err(|e| format!("Failed to back up {:?}: {}", src, e))?;
+    for _ in 0..BACKUP_BUSY_RETRIES {
+        match backup
+            .step(-1)
+            .map_err(|e| format!("Failed to back up {:?}: {}", src, e))?
+        {
+            StepResult::Done => return Ok(()),
+            _ => {
+                thread::sleep(Duration::from_millis(100));
+            }
+        }
+    }
+    Err(format!("Failed to back up {:?}: database is locked", src))
+}
+
+/// The hex SHA-256 of each `BACKUP_CHUNK_SIZE`-byte chunk of the file at `path`.  The last chunk
+/// may be shorter.
+pub fn chunk_hashes(path: &Path) -> Result<Vec<String>, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+    let mut chunks = vec![];
+    let mut buf = vec![0u8; BACKUP_CHUNK_SIZE as usize];
+    loop {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match file.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(format!("Failed to read {:?}: {}", path, e)),
+            }
+        }
+        if filled == 0 {
+            return Ok(chunks);
+        }
+        chunks.push(Sha256Sum::from_data(&buf[..filled]).to_hex());
+        if filled < buf.len() {
+            return Ok(chunks);
+        }
+    }
+}
+
+/// The length of the MARF blob file that the MARF DB at `db_path` refers to
+fn get_marf_blobs_len(db_path: &Path) -> Result<u64, String> {
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open {:?}: {}", db_path, e))?;
+    conn.query_row(
+        "SELECT IFNULL(MAX(external_offset + external_length), 0) FROM marf_data",
+        NO_PARAMS,
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|len| len as u64)
+    .map_err(|e| format!("Failed to read MARF blob offsets from {:?}: {}", db_path, e))
+}
+
+pub fn blobs_path(db_path: &Path) -> PathBuf {
+    let mut path = db_path.to_path_buf().into_os_string();
+    path.push(".blobs");
+    PathBuf::from(path)
+}
+
+/// Copies the first `len` bytes of the blob file `src` to `dest`.  Tries appended to `src` after
+/// the DB was copied are left out.
+fn copy_marf_blobs(src: &Path, dest: &Path, len: u64) -> Result<(), String> {
+    let src_file = File::open(src).map_err(|e| format!("Failed to open {:?}: {}", src, e))?;
+    let mut dest_file =
+        File::create(dest).map_err(|e| format!("Failed to create {:?}: {}", dest, e))?;
+    let copied = io::copy(&mut src_file.take(len), &mut dest_file)
+        .map_err(|e| format!("Failed to copy {:?} to {:?}: {}", src, dest, e))?;
+    if copied != len {
+        return Err(format!(
+            "MARF blob file {:?} is {} bytes, but its DB refers to {} bytes",
+            src, copied, len
+        ));
+    }
+    dest_file
+        .sync_all()
+        .map_err(|e| format!("Failed to sync {:?}: {}", dest, e))
+}
+
+/// Copies every file under `src` to `dest`, and returns their paths relative to `src`
+fn copy_dir(src: &Path, dest: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut copied = vec![];
+    fs::create_dir_all(dest).map_err(|e| format!("Failed to create {:?}: {}", dest, e))?;
+    let entries = fs::read_dir(src).map_err(|e| format!("Failed to list {:?}: {}", src, e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to list {:?}: {}", src, e))?;
+        let file_type = entry
+            .file_type()
+            .map_err(|e| format!("Failed to stat {:?}: {}", entry.path(), e))?;
+        let dest_path = dest.join(entry.file_name());
+        if file_type.is_dir() {
+            for path in copy_dir(&entry.path(), &dest_path)? {
+                copied.push(PathBuf::from(entry.file_name()).join(path));
+            }
+        } else if file_type.is_file() {
+            fs::copy(entry.path(), &dest_path).map_err(|e| {
+                format!(
+                    "Failed to copy {:?} to {:?}: {}",
+                    entry.path(),
+                    dest_path,
+                    e
+                )
+            })?;
+            copied.push(PathBuf::from(entry.file_name()));
+        }
+    }
+    Ok(copied)
+}
+
+/// Backs up the node's chainstate into `dest`, which must not exist.  The chains coordinator
+/// must be paused.
+fn write_backup(config: &Config, dest: &Path) -> Result<BackupManifest, String> {
+    let started_at = get_epoch_time_secs();
+    let mut src_root = PathBuf::from(&config.node.working_dir);
+    src_root.push(&config.burnchain.mode);
+
+    let sortdb = SortitionDB::open(&config.get_burn_db_file_path(), false)
+        .map_err(|e| format!("Failed to open sortition DB: {:?}", e))?;
+    let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())
+        .map_err(|e| format!("Failed to load canonical burnchain tip: {:?}", e))?;
+
+    let mut files = vec![];
+    fs::create_dir_all(dest).map_err(|e| format!("Failed to create {:?}: {}", dest, e))?;
+    for (db, is_marf) in BACKUP_DBS.iter() {
+        let src = src_root.join(db);
+        let dest_db = dest.join(db);
+        if !src.exists() {
+            continue;
+        }
+        if let Some(parent) = dest_db.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
+        }
+        backup_sqlite_db(&src, &dest_db)?;
+        files.push((db.to_string(), true));
+
+        let src_blobs = blobs_path(&src);
+        if *is_marf && src_blobs.exists() {
+            let len = get_marf_blobs_len(&dest_db)?;
+            copy_marf_blobs(&src_blobs, &blobs_path(&dest_db), len)?;
+            files.push((format!("{}.blobs", db), false));
+        }
+
+        // block files are stored before the index DB refers to them
+        if *db == "chainstate/vm/index.sqlite" {
+            let blocks = src_root.join(BACKUP_BLOCKS_DIR);
+            if blocks.exists() {
+                for path in copy_dir(&blocks, &dest.join(BACKUP_BLOCKS_DIR))? {
+                    files.push((
+                        Path::new(BACKUP_BLOCKS_DIR)
+                            .join(path)
+                            .to_string_lossy()
+                            .to_string(),
+                        false,
+                    ));
+                }
+            }
+        }
+    }
+
+    let mut backup_files = vec![];
+    for (path, is_db) in files {
+        let len = fs::metadata(dest.join(&path))
+            .map_err(|e| format!("Failed to stat {:?}: {}", dest.join(&path), e))?
+            .len();
+        let chunks = if is_db {
+            chunk_hashes(&dest.join(&path))?
+        } else {
+            vec![]
+        };
+        backup_files.push(BackupFile { path, len, chunks });
+    }
+
+    let manifest = BackupManifest {
+        mainnet: config.is_mainnet(),
+        chain_id: config.burnchain.chain_id,
+        burn_block_height: tip.block_height,
+        burn_header_hash: tip.burn_header_hash.to_hex(),
+        consensus_hash: tip.consensus_hash.to_hex(),
+        stacks_block_height: tip.canonical_stacks_tip_height,
+        stacks_block_id: StacksBlockId::new(
+            &tip.canonical_stacks_tip_consensus_hash,
+            &tip.canonical_stacks_tip_hash,
+        )
+        .to_hex(),
+        files: backup_files,
+        started_at,
+        finished_at: get_epoch_time_secs(),
+    };
+    let manifest_json = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    let mut manifest_file = File::create(dest.join(BACKUP_MANIFEST_FILE))
+        .map_err(|e| format!("Failed to write manifest: {}", e))?;
+    manifest_file
+        .write_all(manifest_json.as_bytes())
+        .and_then(|_| manifest_file.sync_all())
+        .map_err(|e| format!("Failed to write manifest: {}", e))?;
+    Ok(manifest)
+}
+
+/// A complete backup, as listed by the admin API's `GET /v2/admin/backups`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BackupListing {
+    pub height: u64,
+    pub manifest: BackupManifest,
+}
+
+/// The complete backups in `dir` with their manifests, in increasing order of height.  A backup
+/// whose manifest cannot be read, such as because it is being rotated out, is left out.
+pub fn list_backup_manifests(dir: &Path) -> Result<Vec<BackupListing>, String> {
+    let mut listings = vec![];
+    for height in list_backups(dir)? {
+        if let Ok(manifest) = read_manifest(&dir.join(height.to_string())) {
+            listings.push(BackupListing { height, manifest });
+        }
+    }
+    Ok(listings)
+}
+
+pub fn read_manifest(backup: &Path) -> Result<BackupManifest, String> {
+    let manifest_json = fs::read_to_string(backup.join(BACKUP_MANIFEST_FILE))
+        .map_err(|e| format!("Failed to read manifest: {}", e))?;
+    serde_json::from_str(&manifest_json).map_err(|e| format!("Invalid manifest: {}", e))
+}
+
+/// The heights of the complete backups in `dir`, in increasing order
+pub fn list_backups(dir: &Path) -> Result<Vec<u64>, String> {
+    let mut heights = vec![];
+    if !dir.exists() {
+        return Ok(heights);
+    }
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to list {:?}: {}", dir, e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to list {:?}: {}", dir, e))?;
+        if let Some(height) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<u64>().ok())
+        {
+            heights.push(height);
+        }
+    }
+    heights.sort_unstable();
+    Ok(heights)
+}
+
+/// Deletes all but the newest `keep` backups in `dir`, and any backup that was never finished
+fn rotate_backups(dir: &Path, keep: u64) -> Result<(), String> {
+    let heights = list_backups(dir)?;
+    let num_old = heights.len().saturating_sub(keep as usize);
+    let mut stale: Vec<PathBuf> = heights[..num_old]
+        .iter()
+        .map(|height| dir.join(height.to_string()))
+        .collect();
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to list {:?}: {}", dir, e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to list {:?}: {}", dir, e))?;
+        if entry
+            .file_name()
+            .to_str()
+            .map(|name| name.ends_with(BACKUP_TMP_SUFFIX))
+            .unwrap_or(false)
+        {
+            stale.push(entry.path());
+        }
+    }
+    for path in stale {
+        fs::remove_dir_all(&path).map_err(|e| format!("Failed to delete {:?}: {}", path, e))?;
+    }
+    Ok(())
+}
+
+/// Pauses the chains coordinator, backs up the chainstate into `{dir}/{height}`, and rotates
+/// out old backups.
+pub fn run_backup(
+    config: &Config,
+    coord_comms: &CoordinatorChannels,
+    height: u64,
+) -> Result<BackupManifest, String> {
+    let backup_config = config.node.backup.clone().unwrap_or_default();
+    let dir = config.get_backup_path();
+    let dest = dir.join(height.to_string());
+    let tmp = dir.join(format!("{}{}", height, BACKUP_TMP_SUFFIX));
+    if tmp.exists() {
+        fs::remove_dir_all(&tmp).map_err(|e| format!("Failed to delete {:?}: {}", tmp, e))?;
+    }
+
+    let manifest = {
+        let _paused = coord_comms.pause_chains_coordinator();
+        write_backup(config, &tmp)?
+    };
+
+    fs::rename(&tmp, &dest)
+        .map_err(|e| format!("Failed to rename {:?} to {:?}: {}", tmp, dest, e))?;
+    rotate_backups(&dir, backup_config.keep.max(1))?;
+    Ok(manifest)
+}
+
+/// Starts a backup in the background whenever the burnchain tip reaches a new multiple of
+/// `node.backup.interval`.  At most one backup runs at a time.
+pub struct BackupScheduler {
+    last_height: u64,
+    running: Option<JoinHandle<()>>,
+}
+
+impl BackupScheduler {
+    pub fn new() -> BackupScheduler {
+        BackupScheduler {
+            last_height: 0,
+            running: None,
+        }
+    }
+
+    /// The burn height to back up at, if a backup is due with the burnchain tip at `tip_height`
+    fn due_height(&self, backup: &BackupConfig, tip_height: u64) -> Option<u64> {
+        if backup.interval == 0 {
+            return None;
+        }
+        let height = tip_height - tip_height % backup.interval;
+        if height == 0 || height <= self.last_height {
+            return None;
+        }
+        Some(height)
+    }
+
+    pub fn update(
+        &mut self,
+        config: &Config,
+        sortdb: &SortitionDB,
+        coord_comms: &CoordinatorChannels,
+    ) {
+        let backup = match config.node.backup.as_ref() {
+            Some(backup) => backup,
+            None => return,
+        };
+        if let Some(running) = self.running.as_ref() {
+            if !running.is_finished() {
+                return;
+            }
+            self.running = None;
+        }
+        let tip_height = match SortitionDB::get_canonical_burn_chain_tip(sortdb.conn()) {
+            Ok(sn) => sn.block_height,
+            Err(e) => {
+                warn!("Failed to load canonical burn chain tip: {:?}", &e);
+                return;
+            }
+        };
+        let height = match self.due_height(backup, tip_height) {
+            Some(height) => height,
+            None => return,
+        };
+        self.last_height = height;
+        if config.get_backup_path().join(height.to_string()).exists() {
+            return;
+        }
+
+        let config = config.clone();
+        let coord_comms = coord_comms.clone();
+        let handle = thread::Builder::new()
+            .name(format!("chainstate-backup-{}", height))
+            .spawn(move || match run_backup(&config, &coord_comms, height) {
+                Ok(manifest) => info!(
+                    "Backed up chainstate at burn height {} to {:?}",
+                    manifest.burn_block_height,
+                    config.get_backup_path().join(height.to_string());
+                    "stacks_block_id" => %manifest.stacks_block_id,
+                    "duration_secs" => manifest.finished_at - manifest.started_at
+                ),
+                Err(e) => warn!(
+                    "Failed to back up chainstate at burn height {}: {}",
+                    height, e
+                ),
+            });
+        match handle {
+            Ok(handle) => self.running = Some(handle),
+            Err(e) => warn!("Failed to start chainstate backup thread: {:?}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_test_dir(name: &str) -> PathBuf {
+        let dir = PathBuf::from(format!("/tmp/stacks-node-tests/backup/{}", name));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_backup_marf_db() {
+        let dir = make_test_dir("marf");
+        let src = dir.join("marf.sqlite");
+        Connection::open(&src)
+            .unwrap()
+            .execute_batch(
+                "PRAGMA journal_mode = WAL; \
+                 CREATE TABLE marf_data (block_hash TEXT, external_offset INTEGER, external_length INTEGER); \
+                 INSERT INTO marf_data VALUES ('a', 0, 10), ('b', 10, 5);",
+            )
+            .unwrap();
+        // a trie that was appended after the DB was copied
+        fs::write(blobs_path(&src), vec![1u8; 20]).unwrap();
+
+        let dest = dir.join("backup.sqlite");
+        backup_sqlite_db(&src, &dest).unwrap();
+        let len = get_marf_blobs_len(&dest).unwrap();
+        assert_eq!(len, 15);
+        copy_marf_blobs(&blobs_path(&src), &blobs_path(&dest), len).unwrap();
+        assert_eq!(fs::read(blobs_path(&dest)).unwrap(), vec![1u8; 15]);
+
+        // the DB refers to tries the blob file does not have
+        fs::write(blobs_path(&src), vec![1u8; 10]).unwrap();
+        assert!(copy_marf_blobs(&blobs_path(&src), &blobs_path(&dest), len).is_err());
+    }
+
+    #[test]
+    fn test_chunk_hashes() {
+        let dir = make_test_dir("chunks");
+        let path = dir.join("db.sqlite");
+        let size = BACKUP_CHUNK_SIZE as usize;
+        let mut bytes = vec![1u8; size];
+        bytes.extend(vec![2u8; 10]);
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(
+            chunk_hashes(&path).unwrap(),
+            vec![
+                Sha256Sum::from_data(&bytes[..size]).to_hex(),
+                Sha256Sum::from_data(&bytes[size..]).to_hex()
+            ]
+        );
+
+        fs::write(&path, &bytes[..size]).unwrap();
+        assert_eq!(chunk_hashes(&path).unwrap().len(), 1);
+        fs::write(&path, b"").unwrap();
+        assert!(chunk_hashes(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rotate_backups() {
+        let dir = make_test_dir("rotate");
+        for name in ["10", "20", "30", "40", "50.tmp", "other"].iter() {
+            fs::create_dir_all(dir.join(name)).unwrap();
+        }
+        assert_eq!(list_backups(&dir).unwrap(), vec![10, 20, 30, 40]);
+
+        rotate_backups(&dir, 2).unwrap();
+        assert_eq!(list_backups(&dir).unwrap(), vec![30, 40]);
+        assert!(!dir.join("50.tmp").exists());
+        assert!(dir.join("other").exists());
+
+        rotate_backups(&dir, 3).unwrap();
+        assert_eq!(list_backups(&dir).unwrap(), vec![30, 40]);
+    }
+
+    #[test]
+    fn test_due_height() {
+        let backup = BackupConfig {
+            dir: None,
+            interval: 10,
+            keep: 3,
+        };
+        let mut scheduler = BackupScheduler::new();
+        assert_eq!(scheduler.due_height(&backup, 5), None);
+        assert_eq!(scheduler.due_height(&backup, 25), Some(20));
+        scheduler.last_height = 20;
+        assert_eq!(scheduler.due_height(&backup, 29), None);
+        assert_eq!(scheduler.due_height(&backup, 30), Some(30));
+    }
+}
@@ -0,0 +1,328 @@
+//! The `stacks-node btc-wallet` subcommands, which look after the miner's burnchain UTXOs
+//! through the configured bitcoind's RPC interface, using the keychain derived from
+//! `node.seed`.
+//!
+//! `pre-stx` and `transfer-stx` send the `PreStx` and `TransferStx` burnchain operations, which
+//! move STX through bitcoin for accounts that cannot get a Stacks transaction mined; they can be
+//! signed with another key than the miner's.  There is no `DelegateStx` burnchain operation in
+//! this version of the chain: delegation is a PoX contract call, made with `stack-stx`.
+//!
+//! The UTXOs are listed through bitcoind's wallet, so outside of regtest the miner's address has
+//! to be imported into it first, as it does for mining.  Only legacy (p2pkh and p2sh) recipient
+//! addresses are supported.
+
+use stacks::burnchains::bitcoin::address::BitcoinAddress;
+use stacks::burnchains::bitcoin::BitcoinNetworkType;
+use stacks::chainstate::stacks::address::StacksAddressExtensions;
+use stacks::types::chainstate::StacksAddress;
+use stacks::types::Address;
+use stacks::util::hash::to_hex;
+use stacks_common::deps_common::bitcoin::blockdata::transaction::Transaction;
+use stacks_common::deps_common::bitcoin::network::serialize::serialize;
+
+use crate::burnchains::bitcoin_regtest_controller::UTXO;
+use crate::operations::BurnchainOpSigner;
+use crate::{BitcoinRegtestController, Config, Keychain};
+
+/// What is left of bitcoin's 80-byte `OP_RETURN` limit after the magic bytes, opcode and amount
+/// of a `TransferStx` operation.
+const MAX_TRANSFER_STX_MEMO_LEN: usize = 80 - 3 - 16;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WalletUtxo {
+    pub txid: String,
+    pub vout: u32,
+    /// In satoshis
+    pub amount: u64,
+    pub confirmations: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WalletBalance {
+    pub address: String,
+    /// Satoshis in UTXOs with at least one confirmation
+    pub confirmed: u64,
+    /// Satoshis in unconfirmed UTXOs
+    pub unconfirmed: u64,
+    pub utxo_count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WalletTransaction {
+    pub txid: String,
+    /// The signed transaction, hex-encoded
+    pub hex: String,
+    /// Whether the transaction was sent to bitcoind, as opposed to a `--dry-run`
+    pub broadcast: bool,
+}
+
+fn miner_signer(config: &Config) -> BurnchainOpSigner {
+    Keychain::default(config.node.seed.clone()).generate_op_signer()
+}
+
+fn wallet_controller(config: &Config) -> Result<BitcoinRegtestController, String> {
+    if config.burnchain.mode == "mocknet" || config.burnchain.mode == "helium" {
+        return Err(format!(
+            "btc-wallet needs a bitcoind burnchain, not burnchain.mode = \"{}\"",
+            config.burnchain.mode
+        ));
+    }
+    Ok(BitcoinRegtestController::new_dummy(config.clone()))
+}
+
+fn summarize_balance(address: String, utxos: &[UTXO]) -> WalletBalance {
+    let (confirmed, unconfirmed): (Vec<&UTXO>, Vec<&UTXO>) =
+        utxos.iter().partition(|utxo| utxo.confirmations > 0);
+    WalletBalance {
+        address,
+        confirmed: confirmed.iter().map(|utxo| utxo.amount).sum(),
+        unconfirmed: unconfirmed.iter().map(|utxo| utxo.amount).sum(),
+        utxo_count: utxos.len(),
+    }
+}
+
+/// Parses a legacy base58 address, checking that it belongs to the configured bitcoin network.
+/// Regtest uses testnet addresses.
+pub fn parse_recipient(config: &Config, address: &str) -> Result<BitcoinAddress, String> {
+    let recipient = BitcoinAddress::from_b58(address)
+        .map_err(|_| format!("Invalid or unsupported bitcoin address '{}'", address))?;
+    let (_, network_id) = config.burnchain.get_bitcoin_network();
+    let mainnet = network_id == BitcoinNetworkType::Mainnet;
+    if mainnet != (recipient.network_id == BitcoinNetworkType::Mainnet) {
+        return Err(format!(
+            "Address '{}' is not on the configured bitcoin network ({:?})",
+            address, network_id
+        ));
+    }
+    Ok(recipient)
+}
+
+/// Parses a Stacks address, checking that it belongs to the configured network.
+pub fn parse_stacks_address(config: &Config, address: &str) -> Result<StacksAddress, String> {
+    let address = StacksAddress::from_string(address)
+        .ok_or(format!("Invalid Stacks address '{}'", address))?;
+    if address.is_mainnet() != config.is_mainnet() {
+        return Err(format!(
+            "Address '{}' is not on the configured Stacks network",
+            address
+        ));
+    }
+    Ok(address)
+}
+
+/// The miner's address and its confirmed and unconfirmed balance.
+pub fn wallet_balance(config: &Config) -> Result<WalletBalance, String> {
+    let controller = wallet_controller(config)?;
+    let public_key = miner_signer(config).get_public_key();
+    let utxos = controller.get_wallet_utxos(&public_key, true)?;
+    let address = controller.get_p2pkh_address(&public_key).to_b58();
+    Ok(summarize_balance(address, utxos.utxos()))
+}
+
+/// Every UTXO of the miner's address, confirmed or not.
+pub fn list_utxos(config: &Config) -> Result<Vec<WalletUtxo>, String> {
+    let controller = wallet_controller(config)?;
+    let public_key = miner_signer(config).get_public_key();
+    let utxos = controller.get_wallet_utxos(&public_key, true)?;
+    Ok(utxos
+        .utxos()
+        .iter()
+        .map(|utxo| WalletUtxo {
+            txid: utxo.txid.be_hex_string(),
+            vout: utxo.vout,
+            amount: utxo.amount,
+            confirmations: utxo.confirmations,
+        })
+        .collect())
+}
+
+fn finish_tx(
+    controller: &BitcoinRegtestController,
+    tx: Transaction,
+    broadcast: bool,
+) -> Result<WalletTransaction, String> {
+    let hex = to_hex(&serialize(&tx).expect("BUG: failed to serialize to a vec"));
+    let txid = if broadcast {
+        controller.send_wallet_tx(&tx)?
+    } else {
+        tx.txid().be_hex_string()
+    };
+    Ok(WalletTransaction {
+        txid,
+        hex,
+        broadcast,
+    })
+}
+
+/// Merges the miner's confirmed UTXOs into one, so that block-commits need fewer inputs.
+pub fn consolidate(
+    config: &Config,
+    fee_rate: u64,
+    broadcast: bool,
+) -> Result<WalletTransaction, String> {
+    let mut controller = wallet_controller(config)?;
+    let mut signer = miner_signer(config);
+    let tx = controller.build_wallet_consolidation_tx(&mut signer, fee_rate)?;
+    finish_tx(&controller, tx, broadcast)
+}
+
+/// Pays `amount` satoshis from the miner's confirmed UTXOs to `address`.
+pub fn send_to_address(
+    config: &Config,
+    address: &str,
+    amount: u64,
+    fee_rate: u64,
+    broadcast: bool,
+) -> Result<WalletTransaction, String> {
+    let recipient = parse_recipient(config, address)?;
+    let mut controller = wallet_controller(config)?;
+    let mut signer = miner_signer(config);
+    let tx = controller.build_wallet_send_tx(&mut signer, &recipient, amount, fee_rate)?;
+    finish_tx(&controller, tx, broadcast)
+}
+
+/// Sends a `PreStx` operation, funded by `signer`'s confirmed UTXOs, that makes `output` (by
+/// default the signer's own address) the sender of a following `transfer-stx`.
+pub fn pre_stx(
+    config: &Config,
+    signer: &mut BurnchainOpSigner,
+    output: Option<&str>,
+    fee_rate: u64,
+    broadcast: bool,
+) -> Result<WalletTransaction, String> {
+    let mut controller = wallet_controller(config)?;
+    let output = match output {
+        Some(output) => parse_stacks_address(config, output)?,
+        None => StacksAddress::from_bitcoin_address(
+            &controller.get_p2pkh_address(&signer.get_public_key()),
+        ),
+    };
+    let tx = controller.build_wallet_pre_stx_tx(signer, &output, fee_rate)?;
+    finish_tx(&controller, tx, broadcast)
+}
+
+/// Sends a `TransferStx` operation of `amount` uSTX to `recipient`, spending the output of the
+/// `PreStx` operation `pre_stx_txid`.  `signer` must hold that output.
+pub fn transfer_stx(
+    config: &Config,
+    signer: &mut BurnchainOpSigner,
+    pre_stx_txid: &str,
+    recipient: &str,
+    amount: u128,
+    memo: &[u8],
+    fee_rate: u64,
+    broadcast: bool,
+) -> Result<WalletTransaction, String> {
+    if memo.len() > MAX_TRANSFER_STX_MEMO_LEN {
+        return Err(format!(
+            "The memo can be at most {} bytes long",
+            MAX_TRANSFER_STX_MEMO_LEN
+        ));
+    }
+    let recipient = parse_stacks_address(config, recipient)?;
+    let mut controller = wallet_controller(config)?;
+    let tx = controller.build_wallet_transfer_stx_tx(
+        signer,
+        pre_stx_txid,
+        &recipient,
+        amount,
+        memo,
+        fee_rate,
+    )?;
+    finish_tx(&controller, tx, broadcast)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ConfigFile;
+    use stacks_common::deps_common::bitcoin::blockdata::script::Script;
+    use stacks_common::deps_common::bitcoin::util::hash::Sha256dHash;
+
+    #[test]
+    fn test_summarize_balance() {
+        let utxo = |amount, confirmations| UTXO {
+            txid: Sha256dHash::default(),
+            vout: 0,
+            script_pub_key: Script::new(),
+            amount,
+            confirmations,
+        };
+        let balance = summarize_balance(
+            "addr".to_string(),
+            &[utxo(10_000, 3), utxo(20_000, 0), utxo(5_000, 1)],
+        );
+        assert_eq!(balance.confirmed, 15_000);
+        assert_eq!(balance.unconfirmed, 20_000);
+        assert_eq!(balance.utxo_count, 3);
+    }
+
+    #[test]
+    fn test_parse_recipient() {
+        let mainnet = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                mode = "mainnet"
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let regtest = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                mode = "krypton"
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        let mainnet_p2pkh = "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2";
+        let mainnet_p2sh = "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy";
+        let testnet_p2pkh = "mxVFsFW5N4mu1HPkxPttorvocvzeZ7KZyk";
+
+        assert!(parse_recipient(&mainnet, mainnet_p2pkh).is_ok());
+        assert!(parse_recipient(&mainnet, mainnet_p2sh).is_ok());
+        assert!(parse_recipient(&mainnet, testnet_p2pkh).is_err());
+        assert!(parse_recipient(&regtest, testnet_p2pkh).is_ok());
+        assert!(parse_recipient(&regtest, mainnet_p2pkh).is_err());
+        assert!(parse_recipient(&regtest, "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq").is_err());
+    }
+
+    #[test]
+    fn test_parse_stacks_address() {
+        let mainnet = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                mode = "mainnet"
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let testnet = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                mode = "krypton"
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        let mainnet_address = "SP2J6ZY48GV1EZ5V2V5RB9MP66SW86PYKKNRV9EJ7";
+        let testnet_address = "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R";
+
+        assert!(parse_stacks_address(&mainnet, mainnet_address).is_ok());
+        assert!(parse_stacks_address(&mainnet, testnet_address).is_err());
+        assert!(parse_stacks_address(&testnet, testnet_address).is_ok());
+        assert!(parse_stacks_address(&testnet, mainnet_address).is_err());
+        assert!(parse_stacks_address(&testnet, "mxVFsFW5N4mu1HPkxPttorvocvzeZ7KZyk").is_err());
+    }
+}
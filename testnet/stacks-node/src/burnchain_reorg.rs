@@ -0,0 +1,145 @@
+//! Detection of burnchain reorgs between two passes of the run loop.
+//!
+//! The sortition DB keeps every burnchain fork it has seen, so sortitions and Stacks blocks on an
+//! orphaned fork simply stop being canonical once the burnchain reorgs. What the run loop has to
+//! do is notice that the burn blocks it already handed to the node are no longer on the canonical
+//! fork, and hand over the new fork's blocks from the fork point on, even if the new fork is no
+//! higher than the old one.
+
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::burn::BlockSnapshot;
+use stacks::types::chainstate::BurnchainHeaderHash;
+use stacks::util_lib::db::Error as DBError;
+
+/// A burnchain reorg, as reported to event observers on `POST /burnchain_reorg`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BurnchainReorg {
+    /// Height of the highest burn block that both forks share
+    pub fork_height: u64,
+    /// Number of burn blocks the reorg orphaned
+    pub depth: u64,
+    /// Whether `depth` is at least `burnchain.deep_reorg_depth`
+    pub deep: bool,
+    pub old_tip_height: u64,
+    pub old_tip_burn_block_hash: String,
+    pub new_tip_height: u64,
+    pub new_tip_burn_block_hash: String,
+    /// The orphaned burn blocks, from `fork_height + 1` up to the old tip
+    pub orphaned_burn_block_hashes: Vec<String>,
+}
+
+/// Finds the height of the highest burn block that two forks share, or `None` if the old tip is
+/// on the new fork.  `old_ancestor` and `new_ancestor` look up a fork's burn block at a height.
+pub fn find_fork_height<E, F, G>(
+    old_tip_height: u64,
+    new_tip_height: u64,
+    mut old_ancestor: F,
+    mut new_ancestor: G,
+) -> Result<Option<u64>, E>
+where
+    F: FnMut(u64) -> Result<Option<BurnchainHeaderHash>, E>,
+    G: FnMut(u64) -> Result<Option<BurnchainHeaderHash>, E>,
+{
+    let mut height = old_tip_height.min(new_tip_height);
+    while height > 0 && old_ancestor(height)? != new_ancestor(height)? {
+        height -= 1;
+    }
+    if height == old_tip_height {
+        return Ok(None);
+    }
+    Ok(Some(height))
+}
+
+/// Checks whether the burnchain reorged `old_tip` away on the way to `new_tip`.
+pub fn detect_burnchain_reorg(
+    sortdb: &SortitionDB,
+    old_tip: &BlockSnapshot,
+    new_tip: &BlockSnapshot,
+    deep_reorg_depth: u64,
+) -> Result<Option<BurnchainReorg>, DBError> {
+    let ic = sortdb.index_conn();
+    let ancestor_hash = |height: u64, tip: &BlockSnapshot| {
+        SortitionDB::get_ancestor_snapshot(&ic, height, &tip.sortition_id)
+            .map(|snapshot_opt| snapshot_opt.map(|snapshot| snapshot.burn_header_hash))
+    };
+    let fork_height = match find_fork_height(
+        old_tip.block_height,
+        new_tip.block_height,
+        |height| ancestor_hash(height, old_tip),
+        |height| ancestor_hash(height, new_tip),
+    )? {
+        Some(fork_height) => fork_height,
+        None => return Ok(None),
+    };
+
+    let mut orphaned_burn_block_hashes = vec![];
+    for height in (fork_height + 1)..=old_tip.block_height {
+        if let Some(burn_header_hash) = ancestor_hash(height, old_tip)? {
+            orphaned_burn_block_hashes.push(format!("0x{}", burn_header_hash));
+        }
+    }
+
+    let depth = old_tip.block_height - fork_height;
+    Ok(Some(BurnchainReorg {
+        fork_height,
+        depth,
+        deep: depth >= deep_reorg_depth,
+        old_tip_height: old_tip.block_height,
+        old_tip_burn_block_hash: format!("0x{}", old_tip.burn_header_hash),
+        new_tip_height: new_tip.block_height,
+        new_tip_burn_block_hash: format!("0x{}", new_tip.burn_header_hash),
+        orphaned_burn_block_hashes,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_fork(prefix: u8, shared: u64, height: u64) -> Vec<BurnchainHeaderHash> {
+        (0..=height)
+            .map(|h| {
+                let mut bytes = [0u8; 32];
+                bytes[0] = if h <= shared { 0 } else { prefix };
+                bytes[1] = h as u8;
+                BurnchainHeaderHash(bytes)
+            })
+            .collect()
+    }
+
+    fn fork_height(old: &[BurnchainHeaderHash], new: &[BurnchainHeaderHash]) -> Option<u64> {
+        find_fork_height::<(), _, _>(
+            old.len() as u64 - 1,
+            new.len() as u64 - 1,
+            |h| Ok(old.get(h as usize).cloned()),
+            |h| Ok(new.get(h as usize).cloned()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_find_fork_height() {
+        let old = make_fork(1, 10, 10);
+
+        // the new tip extends the old one
+        assert_eq!(fork_height(&old, &make_fork(1, 10, 12)), None);
+        assert_eq!(fork_height(&old, &old), None);
+
+        // a longer fork from height 7
+        assert_eq!(fork_height(&old, &make_fork(2, 7, 12)), Some(7));
+
+        // a same-height fork from height 9
+        let old = make_fork(1, 9, 10);
+        assert_eq!(fork_height(&old, &make_fork(2, 9, 10)), Some(9));
+
+        // a shorter canonical fork, with the old tip's ancestors intact
+        let old = make_fork(1, 10, 10);
+        assert_eq!(fork_height(&old, &make_fork(1, 10, 8)), Some(8));
+
+        // no shared block but the first
+        assert_eq!(
+            fork_height(&make_fork(1, 0, 5), &make_fork(2, 0, 5)),
+            Some(0)
+        );
+    }
+}
@@ -8,15 +8,18 @@ use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
 };
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use serde::Serialize;
 use serde_json::value::RawValue;
 
 use std::cmp;
+use std::collections::VecDeque;
 
 use super::super::operations::BurnchainOpSigner;
 use super::super::Config;
+use super::commit_journal::{CommitJournal, JournaledOp};
+use super::utxo_tracker::{BlockSource, UTXOTracker};
 use super::{BurnchainController, BurnchainTip, Error as BurnchainControllerError};
 
 use stacks::burnchains::bitcoin::indexer::{
@@ -44,29 +47,35 @@ use stacks::chainstate::coordinator::comm::CoordinatorChannels;
 use stacks::chainstate::stacks::address::StacksAddressExtensions;
 use stacks::codec::StacksMessageCodec;
 use stacks::core::StacksEpoch;
+use stacks::net::socks;
+use stacks::net::socks::SocksTarget;
 use stacks::util::hash::{hex_bytes, Hash160};
 use stacks::util::secp256k1::Secp256k1PublicKey;
-use stacks::util::sleep_ms;
+use stacks::util::{get_epoch_time_ms, get_epoch_time_secs, sleep_ms};
+use stacks_common::deps_common::bitcoin::blockdata::block::Block;
 use stacks_common::deps_common::bitcoin::blockdata::opcodes;
 use stacks_common::deps_common::bitcoin::blockdata::script::{Builder, Script};
 use stacks_common::deps_common::bitcoin::blockdata::transaction::{
     OutPoint, Transaction, TxIn, TxOut,
 };
 use stacks_common::deps_common::bitcoin::network::encodable::ConsensusEncodable;
-use stacks_common::deps_common::bitcoin::network::serialize::RawEncoder;
+use stacks_common::deps_common::bitcoin::network::serialize::{deserialize, RawEncoder};
 use stacks_common::deps_common::bitcoin::util::hash::Sha256dHash;
 
 use stacks::monitoring::{increment_btc_blocks_received_counter, increment_btc_ops_sent_counter};
 
-#[cfg(test)]
 use stacks::chainstate::burn::Opcodes;
-use stacks::types::chainstate::BurnchainHeaderHash;
+use stacks::types::chainstate::{BurnchainHeaderHash, StacksAddress};
 
 /// The number of bitcoin blocks that can have
 ///  passed since the UTXO cache was last refreshed before
 ///  the cache is force-reset.
 const UTXO_CACHE_STALENESS_LIMIT: u64 = 6;
 const DUST_UTXO_LIMIT: u64 = 5500;
+/// How many sent block-commits the submission log remembers
+const SUBMITTED_BLOCK_COMMITS_LOG_LEN: usize = 1000;
+/// Largest size, in bytes, of a `TransferStx` operation that spends a `PreStx` output
+const TRANSFER_STX_TX_SIZE: u64 = 230;
 
 pub struct BitcoinRegtestController {
     config: Config,
@@ -77,7 +86,18 @@ pub struct BitcoinRegtestController {
     use_coordinator: Option<CoordinatorChannels>,
     burnchain_config: Option<Burnchain>,
     ongoing_block_commit: Option<OngoingBlockCommit>,
+    ongoing_leader_key_register: Option<PendingBurnchainOp>,
     should_keep_running: Option<Arc<AtomicBool>>,
+    /// Opened on first use if `burnchain.utxo_scan_start_height` is set.
+    utxo_tracker: Option<UTXOTracker>,
+    /// When the UTXOs funding the last block-commit were selected, in milliseconds since the epoch
+    last_commit_utxos_selected_ms: Option<u128>,
+    /// The block-commits sent since the node started, oldest first
+    submitted_block_commits: VecDeque<SubmittedBlockCommit>,
+    /// Opened when the first block-commit or leader key is sent.
+    commit_journal: Option<CommitJournal>,
+    /// The block-commit or leader key transaction just built, to journal once it is sent
+    unsent_journal_op: Option<JournaledOp>,
 }
 
 struct OngoingBlockCommit {
@@ -91,6 +111,50 @@ impl OngoingBlockCommit {
     fn sum_utxos(&self) -> u64 {
         self.utxos.total_available()
     }
+
+    fn to_pending_op(&self) -> PendingBurnchainOp {
+        PendingBurnchainOp {
+            op: "leader_block_commit".to_string(),
+            txid: self
+                .txids
+                .last()
+                .expect("BUG: block commit without a txid")
+                .to_hex(),
+            fee_rate: self.fees.fee_rate,
+            total_spent: self.fees.total_spent(),
+            replaces: self.txids[..self.txids.len() - 1]
+                .iter()
+                .map(|txid| txid.to_hex())
+                .collect(),
+        }
+    }
+}
+
+/// A block-commit sent by this miner, as remembered by the submission log.  Replacing a
+/// block-commit by fee logs the replacement as another entry with the same intended height.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubmittedBlockCommit {
+    pub txid: String,
+    /// The burnchain block height the block-commit was sent for: one above the canonical
+    /// burnchain tip at the time
+    pub intended_burn_height: u64,
+    pub burn_fee: u64,
+    /// In seconds since the epoch
+    pub submitted_at: u64,
+}
+
+/// A burnchain operation sent by this miner that has not been mined yet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingBurnchainOp {
+    /// `leader_key_register` or `leader_block_commit`
+    pub op: String,
+    pub txid: String,
+    /// Fee rate, in satoshis per byte
+    pub fee_rate: u64,
+    /// Satoshis spent on fees and outputs, across all replaced attempts
+    pub total_spent: u64,
+    /// Earlier attempts that this transaction replaced by fee, oldest first
+    pub replaces: Vec<String>,
 }
 
 #[derive(Clone)]
@@ -114,7 +178,13 @@ impl LeaderBlockCommitFees {
         let mut fees = LeaderBlockCommitFees::estimated_fees_from_payload(payload, config);
         fees.spent_in_attempts = cmp::max(1, self.spent_in_attempts);
         fees.final_size = self.final_size;
-        fees.fee_rate = self.fee_rate + config.burnchain.rbf_fee_increment;
+        // with auto RBF, a replacement only pays the least that bitcoind accepts, and fee bumps
+        // are left to `auto_rbf_ongoing_block_commit`
+        fees.fee_rate = if config.burnchain.auto_rbf {
+            self.fee_rate + 1
+        } else {
+            self.fee_rate + config.burnchain.rbf_fee_increment
+        };
         fees.is_rbf_enabled = true;
         fees
     }
@@ -171,6 +241,11 @@ impl LeaderBlockCommitFees {
             + self.sortition_fee
     }
 
+    /// The transaction fees this commit and the ones it replaces are expected to spend.
+    pub fn estimated_tx_fees(&self) -> u64 {
+        self.fee_rate * self.min_tx_size() + self.spent_in_attempts
+    }
+
     pub fn amount_per_output(&self) -> u64 {
         self.sortition_fee / self.outputs_len
     }
@@ -192,6 +267,37 @@ impl LeaderBlockCommitFees {
     }
 }
 
+/// The fee rate to replace an in-flight block commit at, with `burnchain.auto_rbf`, given its
+/// `fee_rate`, the fee rate bitcoind's mempool holds it at (None if it is not in the mempool),
+/// and bitcoind's fee rate estimate for the next block (None if there is no estimate).  Returns
+/// None if the commit does not need replacing.
+pub fn next_auto_rbf_fee_rate(
+    fee_rate: u64,
+    mempool_fee_rate: Option<u64>,
+    next_block_fee_rate: Option<u64>,
+) -> Option<u64> {
+    if let Some(mempool_fee_rate) = mempool_fee_rate {
+        match next_block_fee_rate {
+            Some(next_block_fee_rate) if mempool_fee_rate < next_block_fee_rate => {}
+            _ => return None,
+        }
+    }
+    // BIP125 replacements have to pay a higher fee rate than the transaction they replace
+    Some(cmp::max(fee_rate + 1, next_block_fee_rate.unwrap_or(0)))
+}
+
+/// Converts a fee rate in BTC per kvB, as bitcoind reports it, to satoshis per vbyte.
+pub fn btc_per_kvb_to_sat_per_vb(fee_rate: f64) -> u64 {
+    (fee_rate * 100_000.0).ceil() as u64
+}
+
+/// The txid of `tx`, in the byte order used by the burnchain DB.
+fn bitcoin_txid(tx: &Transaction) -> Txid {
+    let mut txid = tx.txid().as_bytes().to_vec();
+    txid.reverse();
+    Txid::from_bytes(&txid[..]).expect("BUG: bitcoin txid is not 32 bytes")
+}
+
 impl BitcoinRegtestController {
     pub fn new(config: Config, coordinator_channel: Option<CoordinatorChannels>) -> Self {
         BitcoinRegtestController::with_burnchain(config, coordinator_channel, None, None)
@@ -242,6 +348,9 @@ impl BitcoinRegtestController {
                 first_block: burnchain_params.first_block_height,
                 magic_bytes: burnchain_config.magic_bytes,
                 epochs: burnchain_config.epochs,
+                socks_proxy: config.connection_options.socks_proxy.clone(),
+                download_workers: burnchain_config.download_workers as usize,
+                parse_workers: burnchain_config.parse_workers as usize,
             }
         };
 
@@ -261,12 +370,30 @@ impl BitcoinRegtestController {
             chain_tip: None,
             burnchain_config,
             ongoing_block_commit: None,
+            ongoing_leader_key_register: None,
             should_keep_running,
+            utxo_tracker: None,
+            last_commit_utxos_selected_ms: None,
+            submitted_block_commits: VecDeque::new(),
+            commit_journal: None,
+            unsent_journal_op: None,
         }
     }
 
     /// create a dummy bitcoin regtest controller.
     ///   used just for submitting bitcoin ops.
+    /// A second connection to the configured bitcoind peer, independent of the one this
+    /// controller syncs the burnchain with.
+    pub fn dup_indexer(&self) -> BitcoinIndexer {
+        self.indexer.dup()
+    }
+
+    /// Drop the connection to bitcoind that the burnchain is synced with.  The next sync opens a
+    /// new one.
+    pub fn reconnect_bitcoind(&mut self) {
+        self.indexer.disconnect();
+    }
+
     pub fn new_dummy(config: Config) -> Self {
         let (network, _) = config.burnchain.get_bitcoin_network();
         let burnchain_params = BurnchainParameters::from_params(&config.burnchain.chain, &network)
@@ -286,6 +413,9 @@ impl BitcoinRegtestController {
                 first_block: burnchain_params.first_block_height,
                 magic_bytes: burnchain_config.magic_bytes,
                 epochs: burnchain_config.epochs,
+                socks_proxy: config.connection_options.socks_proxy.clone(),
+                download_workers: burnchain_config.download_workers as usize,
+                parse_workers: burnchain_config.parse_workers as usize,
             }
         };
 
@@ -305,7 +435,13 @@ impl BitcoinRegtestController {
             chain_tip: None,
             burnchain_config: None,
             ongoing_block_commit: None,
+            ongoing_leader_key_register: None,
             should_keep_running: None,
+            utxo_tracker: None,
+            last_commit_utxos_selected_ms: None,
+            submitted_block_commits: VecDeque::new(),
+            commit_journal: None,
+            unsent_journal_op: None,
         }
     }
 
@@ -594,7 +730,7 @@ impl BitcoinRegtestController {
     }
 
     pub fn get_utxos(
-        &self,
+        &mut self,
         public_key: &Secp256k1PublicKey,
         total_required: u64,
         utxos_to_exclude: Option<UTXOSet>,
@@ -605,6 +741,16 @@ impl BitcoinRegtestController {
             return None;
         }
 
+        if let Some(start_height) = self.config.burnchain.utxo_scan_start_height {
+            return self.get_scanned_utxos(
+                start_height,
+                public_key,
+                total_required,
+                utxos_to_exclude,
+                block_height,
+            );
+        }
+
         // Configure UTXO filter
         let pkh = Hash160::from_data(&public_key.to_bytes())
             .to_bytes()
@@ -692,6 +838,78 @@ impl BitcoinRegtestController {
         Some(utxos)
     }
 
+    /// Like `get_utxos`, but finds the UTXOs by scanning blocks instead of through bitcoind's
+    /// wallet.
+    fn get_scanned_utxos(
+        &mut self,
+        start_height: u64,
+        public_key: &Secp256k1PublicKey,
+        total_required: u64,
+        utxos_to_exclude: Option<UTXOSet>,
+        block_height: u64,
+    ) -> Option<UTXOSet> {
+        let pkh = Hash160::from_data(&public_key.to_bytes());
+        let script_pub_key = BitcoinAddress::to_p2pkh_tx_out(&pkh, 0).script_pubkey;
+        let txids_to_exclude: Vec<_> = utxos_to_exclude
+            .map(|utxos| utxos.utxos.into_iter().map(|utxo| utxo.txid).collect())
+            .unwrap_or_default();
+
+        if self.utxo_tracker.is_none() {
+            let tracker = match UTXOTracker::open(self.config.get_utxo_tracker_path(), start_height)
+            {
+                Ok(tracker) => tracker,
+                Err(e) => {
+                    error!("Failed to open the UTXO tracker: {}", e);
+                    return None;
+                }
+            };
+            self.utxo_tracker = Some(tracker);
+        }
+        let source = BitcoinRPCBlockSource {
+            config: &self.config,
+        };
+        let tracker = self
+            .utxo_tracker
+            .as_mut()
+            .expect("BUG: UTXO tracker not opened");
+
+        let (bhh, utxos) = loop {
+            let result = tracker
+                .watch(&script_pub_key)
+                .and_then(|_| tracker.sync(&source))
+                .and_then(|_| source.get_block_hash(block_height))
+                .and_then(|bhh| {
+                    tracker
+                        .get_utxos(&script_pub_key, total_required, &txids_to_exclude)
+                        .map(|utxos| (bhh, utxos))
+                });
+            match result {
+                Ok(result) => break result,
+                Err(e) => {
+                    error!("Failed to scan for UTXOs: {}", e);
+                    sleep_ms(5000);
+                    continue;
+                }
+            }
+        };
+
+        let utxos = UTXOSet { bhh, utxos };
+        if utxos.is_empty() {
+            return None;
+        }
+        let total_unspent = utxos.total_available();
+        if total_unspent < total_required {
+            warn!(
+                "Total unspent {} < {} for {:?}",
+                total_unspent,
+                total_required,
+                &public_key.to_hex()
+            );
+            return None;
+        }
+        Some(utxos)
+    }
+
     fn build_leader_key_register_tx(
         &mut self,
         payload: LeaderKeyRegisterOp,
@@ -747,6 +965,15 @@ impl BitcoinRegtestController {
 
         increment_btc_ops_sent_counter();
 
+        self.stage_journal_op("leader_key_register", &tx, &utxos, fee_rate, 0, None);
+        self.ongoing_leader_key_register = Some(PendingBurnchainOp {
+            op: "leader_key_register".to_string(),
+            txid: bitcoin_txid(&tx).to_hex(),
+            fee_rate,
+            total_spent: budget_for_outputs + btc_miner_fee,
+            replaces: vec![],
+        });
+
         info!(
             "Miner node: submitting leader_key_register op - {}, waiting for its inclusion in the next Bitcoin block",
             public_key.to_hex()
@@ -787,9 +1014,7 @@ impl BitcoinRegtestController {
             }
         }?;
 
-        let ser_transaction = SerializedTx::new(transaction.clone());
-
-        if self.send_transaction(ser_transaction) {
+        if self.send_transaction(&transaction) {
             Some(transaction)
         } else {
             None
@@ -939,14 +1164,9 @@ impl BitcoinRegtestController {
         signer: &mut BurnchainOpSigner,
         utxos_to_include: Option<UTXOSet>,
         utxos_to_exclude: Option<UTXOSet>,
-        previous_fees: Option<LeaderBlockCommitFees>,
+        mut estimated_fees: LeaderBlockCommitFees,
         previous_txids: &Vec<Txid>,
     ) -> Option<Transaction> {
-        let mut estimated_fees = match previous_fees {
-            Some(fees) => fees.fees_from_previous_tx(&payload, &self.config),
-            None => LeaderBlockCommitFees::estimated_fees_from_payload(&payload, &self.config),
-        };
-
         let public_key = signer.get_public_key();
         let (mut tx, mut utxos) = self.prepare_tx(
             &public_key,
@@ -955,6 +1175,7 @@ impl BitcoinRegtestController {
             utxos_to_exclude,
             payload.parent_block_ptr as u64,
         )?;
+        self.last_commit_utxos_selected_ms = Some(get_epoch_time_ms());
 
         // Serialize the payload
         let op_bytes = {
@@ -997,11 +1218,17 @@ impl BitcoinRegtestController {
 
         let tx_size = serialized_tx.bytes.len() as u64;
         estimated_fees.register_replacement(tx_size);
-        let mut txid = tx.txid().as_bytes().to_vec();
-        txid.reverse();
 
         debug!("Transaction relying on UTXOs: {:?}", utxos);
-        let txid = Txid::from_bytes(&txid[..]).unwrap();
+        self.stage_journal_op(
+            "leader_block_commit",
+            &tx,
+            &utxos,
+            fee_rate,
+            payload.burn_fee,
+            previous_txids.last(),
+        );
+        let txid = bitcoin_txid(&tx);
         let mut txids = previous_txids.clone();
         txids.push(txid.clone());
         let ongoing_block_commit = OngoingBlockCommit {
@@ -1036,7 +1263,11 @@ impl BitcoinRegtestController {
         // Are we currently tracking an operation?
         if self.ongoing_block_commit.is_none() {
             // Good to go, let's build the transaction and send it.
-            let res = self.send_block_commit_operation(payload, signer, None, None, None, &vec![]);
+            let res = {
+                let fees =
+                    LeaderBlockCommitFees::estimated_fees_from_payload(&payload, &self.config);
+                self.send_block_commit_operation(payload, signer, None, None, fees, &vec![])
+            };
             return res;
         }
 
@@ -1050,8 +1281,11 @@ impl BitcoinRegtestController {
             if mined_op.is_some() {
                 // Good to go, the transaction in progress was mined
                 debug!("Was able to retrieve ongoing TXID - {}", txid);
-                let res =
-                    self.send_block_commit_operation(payload, signer, None, None, None, &vec![]);
+                let res = {
+                    let fees =
+                        LeaderBlockCommitFees::estimated_fees_from_payload(&payload, &self.config);
+                    self.send_block_commit_operation(payload, signer, None, None, fees, &vec![])
+                };
                 return res;
             } else {
                 debug!("Was unable to retrieve ongoing TXID - {}", txid);
@@ -1080,12 +1314,30 @@ impl BitcoinRegtestController {
                 "Possible presence of fork or stale UTXO cache, invalidating cached set of UTXOs.";
                 "cached_burn_block_hash" => %ongoing_op.utxos.bhh,
             );
-            let res = self.send_block_commit_operation(payload, signer, None, None, None, &vec![]);
+            let res = {
+                let fees =
+                    LeaderBlockCommitFees::estimated_fees_from_payload(&payload, &self.config);
+                self.send_block_commit_operation(payload, signer, None, None, fees, &vec![])
+            };
             return res;
         }
 
+        if self.config.burnchain.auto_rbf {
+            // Stop as soon as a replacement would spend more than the budget
+            let fees = ongoing_op
+                .fees
+                .fees_from_previous_tx(&payload, &self.config);
+            if fees.estimated_tx_fees() > self.config.burnchain.auto_rbf_budget {
+                warn!(
+                    "RBF'd block commits would spend more than the budget of {} sats in fees, not resubmitting",
+                    self.config.burnchain.auto_rbf_budget
+                );
+                self.ongoing_block_commit = Some(ongoing_op);
+                return None;
+            }
+        }
         // Stop as soon as the fee_rate is ${self.config.burnchain.max_rbf} percent higher, stop RBF
-        if ongoing_op.fees.fee_rate
+        else if ongoing_op.fees.fee_rate
             > (self.config.burnchain.satoshis_per_byte * self.config.burnchain.max_rbf / 100)
         {
             warn!(
@@ -1115,23 +1367,27 @@ impl BitcoinRegtestController {
         let res = if ongoing_op.fees.estimated_amount_required() > ongoing_op.sum_utxos() {
             // Try to build and submit op, excluding UTXOs currently used
             info!("Attempt to submit another leader_block_commit, despite an ongoing (outdated) commit");
+            let fees = LeaderBlockCommitFees::estimated_fees_from_payload(&payload, &self.config);
             self.send_block_commit_operation(
                 payload,
                 signer,
                 None,
                 Some(ongoing_op.utxos.clone()),
-                None,
+                fees,
                 &vec![],
             )
         } else {
             // Case 2) ii): Attempt to RBF
             info!("Attempt to replace by fee an outdated leader block commit");
+            let fees = ongoing_op
+                .fees
+                .fees_from_previous_tx(&payload, &self.config);
             self.send_block_commit_operation(
                 payload,
                 signer,
                 Some(ongoing_op.utxos.clone()),
                 None,
-                Some(ongoing_op.fees.clone()),
+                fees,
                 &ongoing_op.txids,
             )
         };
@@ -1143,6 +1399,311 @@ impl BitcoinRegtestController {
         res
     }
 
+    /// Replace the config this controller was made with, e.g. to pick up new fee settings.
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+
+    /// When the UTXOs funding the last block-commit were selected, in milliseconds since the epoch
+    pub fn get_last_commit_utxo_selection_time(&self) -> Option<u128> {
+        self.last_commit_utxos_selected_ms
+    }
+
+    /// The leader key registration and block commit sent by this miner that are not mined yet.
+    pub fn get_pending_ops(&mut self) -> Vec<PendingBurnchainOp> {
+        let _ = self.sortdb_mut();
+        let burnchain_db = self.burnchain_db.as_ref().expect("BurnchainDB not opened");
+        let is_mined = |txid: &Txid| burnchain_db.get_burnchain_op(txid).is_some();
+
+        let mut pending_ops = vec![];
+        if let Some(ref leader_key_register) = self.ongoing_leader_key_register {
+            let mined = Txid::from_hex(&leader_key_register.txid)
+                .map(|txid| is_mined(&txid))
+                .unwrap_or(false);
+            if !mined {
+                pending_ops.push(leader_key_register.clone());
+            }
+        }
+        if let Some(ref ongoing_op) = self.ongoing_block_commit {
+            if !ongoing_op.txids.iter().any(|txid| is_mined(txid)) {
+                pending_ops.push(ongoing_op.to_pending_op());
+            }
+        }
+        pending_ops
+    }
+
+    /// The burnchain block height an operation sent now is meant for: one above the canonical
+    /// burnchain tip.
+    fn get_intended_burn_height(&mut self) -> Option<u64> {
+        if self.db.is_none() {
+            let _ = self.sortdb_mut();
+        }
+        match SortitionDB::get_canonical_burn_chain_tip(self.sortdb_ref().conn()) {
+            Ok(tip) => Some(tip.block_height + 1),
+            Err(e) => {
+                warn!(
+                    "Failed to load the canonical burnchain tip to log a sent operation: {:?}",
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    fn log_submitted_block_commit(&mut self, tx: &Transaction) {
+        let burn_fee = match self.ongoing_block_commit {
+            Some(ref ongoing_op) => ongoing_op.payload.burn_fee,
+            None => return,
+        };
+        let intended_burn_height = match self.get_intended_burn_height() {
+            Some(height) => height,
+            None => return,
+        };
+        if self.submitted_block_commits.len() >= SUBMITTED_BLOCK_COMMITS_LOG_LEN {
+            self.submitted_block_commits.pop_front();
+        }
+        self.submitted_block_commits
+            .push_back(SubmittedBlockCommit {
+                txid: bitcoin_txid(tx).to_hex(),
+                intended_burn_height,
+                burn_fee,
+                submitted_at: get_epoch_time_secs(),
+            });
+    }
+
+    /// Keeps what the commit journal needs to know about `tx`, which was just built, until it is
+    /// sent.  `utxos` are the outputs it spends.
+    fn stage_journal_op(
+        &mut self,
+        op: &str,
+        tx: &Transaction,
+        utxos: &UTXOSet,
+        fee_rate: u64,
+        burn_fee: u64,
+        replaces: Option<&Txid>,
+    ) {
+        let spent: u64 = utxos.utxos.iter().map(|utxo| utxo.amount).sum();
+        let outputs: u64 = tx.output.iter().map(|output| output.value).sum();
+        self.unsent_journal_op = Some(JournaledOp {
+            txid: bitcoin_txid(tx).to_hex(),
+            op: op.to_string(),
+            inputs: utxos
+                .utxos
+                .iter()
+                .map(|utxo| format!("{}:{}", utxo.txid.be_hex_string(), utxo.vout))
+                .collect(),
+            fee: spent.saturating_sub(outputs),
+            fee_rate,
+            burn_fee,
+            target_burn_height: 0,
+            replaces: replaces.map(|txid| txid.to_hex()),
+            replaced_by: None,
+            sent_at: 0,
+        });
+    }
+
+    /// Writes the operation staged for `tx` to the commit journal, now that bitcoind has accepted
+    /// `tx`.  Failures are logged, since they must not keep the miner from mining.
+    fn journal_sent_op(&mut self, tx: &Transaction) {
+        let mut op = match self.unsent_journal_op.take() {
+            Some(op) if op.txid == bitcoin_txid(tx).to_hex() => op,
+            _ => return,
+        };
+        op.target_burn_height = match self.get_intended_burn_height() {
+            Some(height) => height,
+            None => return,
+        };
+        op.sent_at = get_epoch_time_secs();
+
+        if self.commit_journal.is_none() {
+            match CommitJournal::open(self.config.get_commit_journal_path()) {
+                Ok(journal) => self.commit_journal = Some(journal),
+                Err(e) => {
+                    warn!("Failed to open the commit journal: {}", e);
+                    return;
+                }
+            }
+        }
+        let journal = self
+            .commit_journal
+            .as_mut()
+            .expect("BUG: commit journal is not open");
+        if let Err(e) = journal.record(&op) {
+            warn!("Failed to journal {} {}: {}", &op.op, &op.txid, e);
+        }
+    }
+
+    /// The block-commits sent since the node started, oldest first.  Only the last
+    /// `SUBMITTED_BLOCK_COMMITS_LOG_LEN` are kept.
+    pub fn get_submitted_block_commits(&self) -> Vec<SubmittedBlockCommit> {
+        self.submitted_block_commits.iter().cloned().collect()
+    }
+
+    /// Whether the next block commit would replace the in-flight one by fee, i.e. there is one
+    /// and its UTXOs can pay for another attempt.  Otherwise, the next block commit is funded
+    /// from other UTXOs, and both could be mined.
+    pub fn can_replace_ongoing_block_commit(&self) -> bool {
+        match self.ongoing_block_commit {
+            Some(ref ongoing_op) => {
+                ongoing_op.fees.estimated_amount_required() <= ongoing_op.sum_utxos()
+            }
+            None => false,
+        }
+    }
+
+    /// Stop tracking the in-flight block commit, so that the next block commit is built from
+    /// fresh UTXOs instead of replacing it by fee. The abandoned transaction stays in the
+    /// Bitcoin mempool and may still be mined. Returns the abandoned operation, if any.
+    pub fn abandon_ongoing_block_commit(&mut self) -> Option<PendingBurnchainOp> {
+        let ongoing_op = self.ongoing_block_commit.take()?;
+        let pending_op = ongoing_op.to_pending_op();
+        info!(
+            "Miner node: abandoning leader_block_commit (txid: {})",
+            &pending_op.txid
+        );
+        Some(pending_op)
+    }
+
+    /// Replace the in-flight block commit by fee right away, at `fee_rate` satoshis per byte or,
+    /// if not given, at the next `burnchain.rbf_fee_increment`. Returns the replacement.
+    pub fn bump_ongoing_block_commit_fee(
+        &mut self,
+        fee_rate: Option<u64>,
+        signer: &mut BurnchainOpSigner,
+    ) -> Result<PendingBurnchainOp, String> {
+        if !self
+            .get_pending_ops()
+            .iter()
+            .any(|op| op.op == "leader_block_commit")
+        {
+            return Err("No block commit is in flight".to_string());
+        }
+        let ongoing_op = self
+            .ongoing_block_commit
+            .take()
+            .expect("BUG: pending block commit is not tracked");
+
+        let mut fees = ongoing_op
+            .fees
+            .fees_from_previous_tx(&ongoing_op.payload, &self.config);
+        if let Some(fee_rate) = fee_rate {
+            if fee_rate <= ongoing_op.fees.fee_rate {
+                let current_fee_rate = ongoing_op.fees.fee_rate;
+                self.ongoing_block_commit = Some(ongoing_op);
+                return Err(format!(
+                    "Fee rate must be higher than the current fee rate of {} sat/byte",
+                    current_fee_rate
+                ));
+            }
+            fees.fee_rate = fee_rate;
+        }
+        if fees.estimated_amount_required() > ongoing_op.sum_utxos() {
+            let available = ongoing_op.sum_utxos();
+            self.ongoing_block_commit = Some(ongoing_op);
+            return Err(format!(
+                "The UTXOs of the block commit ({} sats) cannot pay for a fee rate of {} sat/byte",
+                available, fees.fee_rate
+            ));
+        }
+
+        let tx = self.send_block_commit_operation(
+            ongoing_op.payload.clone(),
+            signer,
+            Some(ongoing_op.utxos.clone()),
+            None,
+            fees,
+            &ongoing_op.txids,
+        );
+        let sent = match tx {
+            Some(tx) => self.send_transaction(&tx),
+            None => false,
+        };
+        if !sent {
+            self.ongoing_block_commit = Some(ongoing_op);
+            return Err("Failed to send the replacement block commit".to_string());
+        }
+        Ok(self
+            .ongoing_block_commit
+            .as_ref()
+            .expect("BUG: replacement block commit is not tracked")
+            .to_pending_op())
+    }
+
+    /// With `burnchain.auto_rbf`, replace the in-flight block commit by fee if bitcoind's mempool
+    /// does not hold it at a fee rate that gets it into the next block, as long as the commit
+    /// and its replacements stay within `burnchain.auto_rbf_budget`. Returns the replacement, if
+    /// one was sent.
+    pub fn auto_rbf_ongoing_block_commit(
+        &mut self,
+        signer: &mut BurnchainOpSigner,
+    ) -> Option<PendingBurnchainOp> {
+        if !self.config.burnchain.auto_rbf {
+            return None;
+        }
+        if !self
+            .get_pending_ops()
+            .iter()
+            .any(|op| op.op == "leader_block_commit")
+        {
+            return None;
+        }
+        let txid = self.ongoing_block_commit.as_ref()?.txids.last()?.clone();
+
+        let mempool_fee_rate = match BitcoinRPCRequest::get_mempool_entry(&self.config, &txid) {
+            Ok(entry_opt) => entry_opt.map(|entry| entry.fee_rate()),
+            Err(e) => {
+                warn!(
+                    "Failed to look up block commit {} in bitcoind's mempool: {:?}",
+                    &txid, &e
+                );
+                return None;
+            }
+        };
+        let next_block_fee_rate = match BitcoinRPCRequest::estimate_smart_fee(&self.config, 1) {
+            Ok(fee_rate_opt) => fee_rate_opt,
+            Err(e) => {
+                warn!("Failed to get a fee rate estimate from bitcoind: {:?}", &e);
+                None
+            }
+        };
+        let ongoing_op = self
+            .ongoing_block_commit
+            .as_ref()
+            .expect("BUG: pending block commit is not tracked");
+        let fee_rate = next_auto_rbf_fee_rate(
+            ongoing_op.fees.fee_rate,
+            mempool_fee_rate,
+            next_block_fee_rate,
+        )?;
+
+        let mut fees = ongoing_op
+            .fees
+            .fees_from_previous_tx(&ongoing_op.payload, &self.config);
+        fees.fee_rate = fee_rate;
+        if fees.estimated_tx_fees() > self.config.burnchain.auto_rbf_budget {
+            warn!(
+                "Block commit {} needs a fee bump to {} sat/byte, but that would spend more than the budget of {} sats in fees",
+                &txid, fee_rate, self.config.burnchain.auto_rbf_budget;
+                "in_mempool" => mempool_fee_rate.is_some()
+            );
+            return None;
+        }
+
+        info!(
+            "Miner node: replacing block commit {} by fee at {} sat/byte",
+            &txid, fee_rate;
+            "mempool_fee_rate" => ?mempool_fee_rate,
+            "next_block_fee_rate" => ?next_block_fee_rate
+        );
+        match self.bump_ongoing_block_commit_fee(Some(fee_rate), signer) {
+            Ok(op) => Some(op),
+            Err(e) => {
+                warn!("Failed to replace block commit by fee: {}", e);
+                None
+            }
+        }
+    }
+
     fn prepare_tx(
         &mut self,
         public_key: &Secp256k1PublicKey,
@@ -1281,7 +1842,16 @@ impl BitcoinRegtestController {
             debug!("Not enough change to clear dust limit. Not adding change address.");
         }
 
-        for (i, utxo) in utxos_set.utxos.iter().enumerate() {
+        Self::sign_tx_inputs(tx, &utxos_set.utxos, signer);
+        true
+    }
+
+    /// Appends `utxos` to `tx`'s inputs and signs them.  `tx`'s outputs must be final, since
+    /// every signature commits to all of the inputs and outputs.
+    fn sign_tx_inputs(tx: &mut Transaction, utxos: &[UTXO], signer: &mut BurnchainOpSigner) {
+        let public_key = signer.get_public_key();
+        let first_input = tx.input.len();
+        for utxo in utxos.iter() {
             let input = TxIn {
                 previous_output: OutPoint {
                     txid: utxo.txid,
@@ -1292,7 +1862,10 @@ impl BitcoinRegtestController {
                 witness: vec![],
             };
             tx.input.push(input);
+        }
 
+        for (j, utxo) in utxos.iter().enumerate() {
+            let i = first_input + j;
             let script_pub_key = utxo.script_pub_key.clone();
             let sig_hash_all = 0x01;
             let sig_hash = tx.signature_hash(i, &script_pub_key, sig_hash_all);
@@ -1313,7 +1886,6 @@ impl BitcoinRegtestController {
                 .push_slice(&public_key.to_bytes())
                 .into_script();
         }
-        true
     }
 
     fn build_user_burn_support_tx(
@@ -1325,20 +1897,328 @@ impl BitcoinRegtestController {
         unimplemented!()
     }
 
-    fn send_transaction(&self, transaction: SerializedTx) -> bool {
-        let result = BitcoinRPCRequest::send_raw_transaction(&self.config, transaction.to_hex());
+    fn send_transaction(&mut self, transaction: &Transaction) -> bool {
+        let serialized_tx = SerializedTx::new(transaction.clone());
+        let result = BitcoinRPCRequest::send_raw_transaction(&self.config, serialized_tx.to_hex());
         match result {
-            Ok(_) => true,
+            Ok(_) => {
+                if let Some(ref mut tracker) = self.utxo_tracker {
+                    tracker.note_sent_transaction(transaction);
+                }
+                self.journal_sent_op(transaction);
+                true
+            }
             Err(e) => {
                 error!(
                     "Bitcoin RPC failure: transaction submission failed - {:?}",
                     e
                 );
+                self.unsent_journal_op = None;
                 false
             }
         }
     }
 
+    /// The legacy p2pkh address of `public_key` on the configured bitcoin network.
+    pub fn get_p2pkh_address(&self, public_key: &Secp256k1PublicKey) -> BitcoinAddress {
+        let pkh = Hash160::from_data(&public_key.to_bytes())
+            .to_bytes()
+            .to_vec();
+        let (_, network_id) = self.config.burnchain.get_bitcoin_network();
+        BitcoinAddress::from_bytes(network_id, BitcoinAddressType::PublicKeyHash, &pkh)
+            .expect("Public key incorrect")
+    }
+
+    /// Lists every UTXO of `public_key`'s address that bitcoind's wallet knows about, dust
+    /// included, for the `btc-wallet` subcommands.  Unconfirmed outputs sent by other wallets are
+    /// only listed if `include_unsafe` is set.  Unlike `get_utxos`, RPC errors are returned
+    /// instead of retried.
+    pub fn get_wallet_utxos(
+        &self,
+        public_key: &Secp256k1PublicKey,
+        include_unsafe: bool,
+    ) -> Result<UTXOSet, String> {
+        let filter_addresses = vec![self.get_p2pkh_address(public_key).to_b58()];
+        let list_unspent = || {
+            let block_height = BitcoinRPCRequest::get_block_count(&self.config)?;
+            BitcoinRPCRequest::list_unspent(
+                &self.config,
+                filter_addresses.clone(),
+                include_unsafe,
+                0,
+                &None,
+                block_height,
+            )
+        };
+
+        let mut utxos =
+            list_unspent().map_err(|e| format!("Bitcoin RPC failure listing UTXOs: {:?}", e))?;
+        let (_, network) = self.config.burnchain.get_bitcoin_network();
+        if utxos.is_empty() && network == BitcoinNetworkType::Regtest {
+            // see get_utxos(): only regtest addresses are imported on demand
+            BitcoinRPCRequest::import_public_key(&self.config, public_key)
+                .map_err(|e| format!("Bitcoin RPC failure importing address: {:?}", e))?;
+            utxos = list_unspent()
+                .map_err(|e| format!("Bitcoin RPC failure listing UTXOs: {:?}", e))?;
+        }
+        Ok(utxos)
+    }
+
+    /// Builds a signed transaction that pays `amount` satoshis from `signer`'s confirmed UTXOs to
+    /// `recipient`, at `fee_rate` satoshis per byte, with the change going back to `signer`.
+    pub fn build_wallet_send_tx(
+        &mut self,
+        signer: &mut BurnchainOpSigner,
+        recipient: &BitcoinAddress,
+        amount: u64,
+        fee_rate: u64,
+    ) -> Result<Transaction, String> {
+        if amount < DUST_UTXO_LIMIT {
+            return Err(format!(
+                "Amount {} is below the dust limit of {} satoshis",
+                amount, DUST_UTXO_LIMIT
+            ));
+        }
+
+        let public_key = signer.get_public_key();
+        let mut utxos = self.get_wallet_utxos(&public_key, false)?;
+        // one input, the payment and the change
+        let min_tx_size = 226;
+        let total_required = amount + min_tx_size * fee_rate;
+        if utxos.total_available() < total_required {
+            return Err(format!(
+                "Insufficient funds: {} satoshis available, at least {} required",
+                utxos.total_available(),
+                total_required
+            ));
+        }
+
+        let recipient_output = match recipient.addrtype {
+            BitcoinAddressType::PublicKeyHash => {
+                BitcoinAddress::to_p2pkh_tx_out(&recipient.bytes, amount)
+            }
+            BitcoinAddressType::ScriptHash => {
+                BitcoinAddress::to_p2sh_tx_out(&recipient.bytes, amount)
+            }
+        };
+        let mut tx = Transaction {
+            input: vec![],
+            output: vec![recipient_output],
+            version: 1,
+            lock_time: 0,
+        };
+        self.finalize_tx(
+            &mut tx,
+            amount,
+            0,
+            min_tx_size,
+            fee_rate,
+            &mut utxos,
+            signer,
+        );
+        if tx.input.is_empty() {
+            return Err(format!(
+                "Insufficient funds: {} satoshis available, not enough to pay {} plus fees",
+                utxos.total_available(),
+                amount
+            ));
+        }
+        Ok(tx)
+    }
+
+    /// Builds a signed transaction that spends all of `signer`'s confirmed UTXOs into a single
+    /// output back to `signer`, at `fee_rate` satoshis per byte.
+    pub fn build_wallet_consolidation_tx(
+        &mut self,
+        signer: &mut BurnchainOpSigner,
+        fee_rate: u64,
+    ) -> Result<Transaction, String> {
+        let public_key = signer.get_public_key();
+        let utxos = self.get_wallet_utxos(&public_key, false)?;
+        if utxos.utxos.len() < 2 {
+            return Err(format!(
+                "Nothing to consolidate: {} confirmed UTXO(s)",
+                utxos.utxos.len()
+            ));
+        }
+
+        let total = utxos.total_available();
+        let change_address_hash = Hash160::from_data(&public_key.to_bytes());
+        let build_tx = |value: u64, signer: &mut BurnchainOpSigner| {
+            let mut tx = Transaction {
+                input: vec![],
+                output: vec![BitcoinAddress::to_p2pkh_tx_out(&change_address_hash, value)],
+                version: 1,
+                lock_time: 0,
+            };
+            Self::sign_tx_inputs(&mut tx, &utxos.utxos, signer);
+            tx
+        };
+
+        // sign once to learn the transaction's size, then again with the fee taken out
+        let tx_size = SerializedTx::new(build_tx(total, signer)).bytes.len() as u64;
+        let fee = tx_size * fee_rate;
+        if total < fee + DUST_UTXO_LIMIT {
+            signer.dispose();
+            return Err(format!(
+                "Insufficient funds: {} satoshis available, {} needed for fees",
+                total, fee
+            ));
+        }
+        let tx = build_tx(total - fee, signer);
+        signer.dispose();
+        Ok(tx)
+    }
+
+    /// Builds a signed `PreStx` operation from `signer`'s confirmed UTXOs, at `fee_rate`
+    /// satoshis per byte.  Its second output goes to `output`, which becomes the sender of the
+    /// `TransferStx` operation that spends it, and carries enough to pay for that operation at
+    /// the same fee rate.
+    pub fn build_wallet_pre_stx_tx(
+        &mut self,
+        signer: &mut BurnchainOpSigner,
+        output: &StacksAddress,
+        fee_rate: u64,
+    ) -> Result<Transaction, String> {
+        let public_key = signer.get_public_key();
+        let mut utxos = self.get_wallet_utxos(&public_key, false)?;
+        let min_tx_size = 280;
+        let output_amt = DUST_UTXO_LIMIT + TRANSFER_STX_TX_SIZE * fee_rate;
+        let total_required = output_amt + min_tx_size * fee_rate;
+        if utxos.total_available() < total_required {
+            return Err(format!(
+                "Insufficient funds: {} satoshis available, at least {} required",
+                utxos.total_available(),
+                total_required
+            ));
+        }
+
+        let mut op_bytes = self.config.burnchain.magic_bytes.as_bytes().to_vec();
+        op_bytes.push(Opcodes::PreStx as u8);
+        let mut tx = Transaction {
+            input: vec![],
+            output: vec![
+                TxOut {
+                    value: 0,
+                    script_pubkey: Builder::new()
+                        .push_opcode(opcodes::All::OP_RETURN)
+                        .push_slice(&op_bytes)
+                        .into_script(),
+                },
+                output.to_bitcoin_tx_out(output_amt),
+            ],
+            version: 1,
+            lock_time: 0,
+        };
+        self.finalize_tx(
+            &mut tx,
+            output_amt,
+            0,
+            min_tx_size,
+            fee_rate,
+            &mut utxos,
+            signer,
+        );
+        if tx.input.is_empty() {
+            return Err(format!(
+                "Insufficient funds: {} satoshis available, not enough to pay {} plus fees",
+                utxos.total_available(),
+                output_amt
+            ));
+        }
+        Ok(tx)
+    }
+
+    /// Builds a signed `TransferStx` operation that sends `amount` uSTX to `recipient`.  Its
+    /// only input is the second output of the `PreStx` operation `pre_stx_txid`, which must pay
+    /// `signer`'s address: that address is the sender.  The `PreStx` may still be unconfirmed.
+    pub fn build_wallet_transfer_stx_tx(
+        &mut self,
+        signer: &mut BurnchainOpSigner,
+        pre_stx_txid: &str,
+        recipient: &StacksAddress,
+        amount: u128,
+        memo: &[u8],
+        fee_rate: u64,
+    ) -> Result<Transaction, String> {
+        let public_key = signer.get_public_key();
+        let sender_address = self.get_p2pkh_address(&public_key);
+        let payload = TransferStxOp {
+            sender: StacksAddress::from_bitcoin_address(&sender_address),
+            recipient: recipient.clone(),
+            transfered_ustx: amount,
+            memo: memo.to_vec(),
+            txid: Txid([0u8; 32]),
+            vtxindex: 0,
+            block_height: 0,
+            burn_header_hash: BurnchainHeaderHash::zero(),
+        };
+        payload
+            .check()
+            .map_err(|e| format!("Invalid transfer: {:?}", e))?;
+        let mut op_bytes = self.config.burnchain.magic_bytes.as_bytes().to_vec();
+        payload
+            .consensus_serialize(&mut op_bytes)
+            .map_err(|e| format!("Invalid transfer: {:?}", e))?;
+
+        let pre_stx_utxo = self
+            .get_wallet_utxos(&public_key, true)?
+            .utxos
+            .into_iter()
+            .find(|utxo| utxo.txid.be_hex_string() == pre_stx_txid && utxo.vout == 1)
+            .ok_or(format!(
+                "{}:1 is not an unspent output of {}; it must be the second output of a PreStx operation paying that address",
+                pre_stx_txid,
+                sender_address.to_b58()
+            ))?;
+        let mut utxos = UTXOSet {
+            bhh: BurnchainHeaderHash::zero(),
+            utxos: vec![pre_stx_utxo],
+        };
+
+        let mut tx = Transaction {
+            input: vec![],
+            output: vec![
+                TxOut {
+                    value: 0,
+                    script_pubkey: Builder::new()
+                        .push_opcode(opcodes::All::OP_RETURN)
+                        .push_slice(&op_bytes)
+                        .into_script(),
+                },
+                recipient.to_bitcoin_tx_out(DUST_UTXO_LIMIT),
+            ],
+            version: 1,
+            lock_time: 0,
+        };
+        self.finalize_tx(
+            &mut tx,
+            DUST_UTXO_LIMIT,
+            0,
+            TRANSFER_STX_TX_SIZE,
+            fee_rate,
+            &mut utxos,
+            signer,
+        );
+        if tx.input.is_empty() {
+            return Err(format!(
+                "The PreStx output holds {} satoshis, not enough to pay for the transfer at {} satoshis per byte",
+                utxos.total_available(),
+                fee_rate
+            ));
+        }
+        Ok(tx)
+    }
+
+    /// Broadcasts a transaction built by one of the `build_wallet_*` methods, returning its
+    /// bitcoin txid or bitcoind's reason for rejecting it.
+    pub fn send_wallet_tx(&self, tx: &Transaction) -> Result<String, String> {
+        let serialized_tx = SerializedTx::new(tx.clone());
+        BitcoinRPCRequest::send_raw_transaction(&self.config, serialized_tx.to_hex())
+            .map_err(|e| format!("Bitcoin RPC failure sending transaction: {:?}", e))?;
+        Ok(tx.txid().be_hex_string())
+    }
+
     /// wait until the ChainsCoordinator has processed sortitions up to the
     ///   canonical chain tip, or has processed up to height_to_wait
     pub fn wait_for_sortitions(
@@ -1554,6 +2434,7 @@ impl BurnchainController for BitcoinRegtestController {
         op_signer: &mut BurnchainOpSigner,
         attempt: u64,
     ) -> bool {
+        let is_block_commit = matches!(operation, BlockstackOperationType::LeaderBlockCommit(_));
         let transaction = match operation {
             BlockstackOperationType::LeaderBlockCommit(payload) => {
                 self.build_leader_block_commit_tx(payload, op_signer, attempt)
@@ -1573,12 +2454,16 @@ impl BurnchainController for BitcoinRegtestController {
             BlockstackOperationType::StackStx(_payload) => unimplemented!(),
         };
 
-        let transaction = match transaction {
-            Some(tx) => SerializedTx::new(tx),
-            _ => return false,
-        };
-
-        self.send_transaction(transaction)
+        match transaction {
+            Some(tx) => {
+                let sent = self.send_transaction(&tx);
+                if sent && is_block_commit {
+                    self.log_submitted_block_commit(&tx);
+                }
+                sent
+            }
+            _ => false,
+        }
     }
 
     #[cfg(test)]
@@ -1629,6 +2514,10 @@ impl UTXOSet {
     pub fn total_available(&self) -> u64 {
         self.utxos.iter().map(|o| o.amount).sum()
     }
+
+    pub fn utxos(&self) -> &[UTXO] {
+        &self.utxos
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -1761,6 +2650,43 @@ pub enum RPCError {
 
 type RPCResult<T> = Result<T, RPCError>;
 
+/// bitcoind's error code for a transaction that is not in its mempool
+const RPC_TX_NOT_IN_MEMPOOL: &str = "\"code\":-5";
+
+/// A transaction in bitcoind's mempool
+#[derive(Debug, Clone, PartialEq)]
+pub struct MempoolEntry {
+    pub vsize: u64,
+    /// fee paid by the transaction, in satoshis
+    pub fee: u64,
+}
+
+impl MempoolEntry {
+    /// The fee rate of the transaction, in satoshis per vbyte
+    pub fn fee_rate(&self) -> u64 {
+        self.fee / cmp::max(self.vsize, 1)
+    }
+}
+
+/// Reads blocks for the UTXO tracker from bitcoind's RPC interface.
+struct BitcoinRPCBlockSource<'a> {
+    config: &'a Config,
+}
+
+impl<'a> BlockSource for BitcoinRPCBlockSource<'a> {
+    fn get_block_count(&self) -> Result<u64, String> {
+        BitcoinRPCRequest::get_block_count(self.config).map_err(|e| format!("{:?}", e))
+    }
+
+    fn get_block_hash(&self, height: u64) -> Result<BurnchainHeaderHash, String> {
+        BitcoinRPCRequest::get_block_hash(self.config, height).map_err(|e| format!("{:?}", e))
+    }
+
+    fn get_block(&self, block_hash: &BurnchainHeaderHash) -> Result<Block, String> {
+        BitcoinRPCRequest::get_raw_block(self.config, block_hash).map_err(|e| format!("{:?}", e))
+    }
+}
+
 impl BitcoinRPCRequest {
     fn build_rpc_request(config: &Config) -> Request {
         let url = {
@@ -1915,6 +2841,62 @@ impl BitcoinRPCRequest {
         Ok(UTXOSet { bhh, utxos })
     }
 
+    pub fn get_block_count(config: &Config) -> RPCResult<u64> {
+        let payload = BitcoinRPCRequest {
+            method: "getblockcount".to_string(),
+            params: vec![],
+            id: "stacks".to_string(),
+            jsonrpc: "2.0".to_string(),
+        };
+
+        let res = BitcoinRPCRequest::send(&config, payload)?;
+        res.get("result")
+            .and_then(|result| result.as_u64())
+            .ok_or(RPCError::Parsing("Failed to get block count".to_string()))
+    }
+
+    pub fn get_block_hash(config: &Config, height: u64) -> RPCResult<BurnchainHeaderHash> {
+        let payload = BitcoinRPCRequest {
+            method: "getblockhash".to_string(),
+            params: vec![height.into()],
+            id: "stacks".to_string(),
+            jsonrpc: "2.0".to_string(),
+        };
+
+        let res = BitcoinRPCRequest::send(&config, payload)?;
+        res.get("result")
+            .and_then(|result| result.as_str())
+            .and_then(|hash| BurnchainHeaderHash::from_hex(hash).ok())
+            .ok_or(RPCError::Parsing(format!(
+                "Failed to get block hash at height {}",
+                height
+            )))
+    }
+
+    /// Fetches a block in its consensus serialization. Unlike `listunspent`, this does not need
+    /// bitcoind's wallet.
+    pub fn get_raw_block(config: &Config, block_hash: &BurnchainHeaderHash) -> RPCResult<Block> {
+        let payload = BitcoinRPCRequest {
+            method: "getblock".to_string(),
+            params: vec![block_hash.to_hex().into(), 0.into()],
+            id: "stacks".to_string(),
+            jsonrpc: "2.0".to_string(),
+        };
+
+        let res = BitcoinRPCRequest::send(&config, payload)?;
+        let block_bytes = res
+            .get("result")
+            .and_then(|result| result.as_str())
+            .and_then(|block_hex| hex_bytes(block_hex).ok())
+            .ok_or(RPCError::Parsing(format!(
+                "Failed to get block {}",
+                block_hash
+            )))?;
+        deserialize(&block_bytes).map_err(|e| {
+            RPCError::Parsing(format!("Failed to decode block {}: {:?}", block_hash, e))
+        })
+    }
+
     pub fn send_raw_transaction(config: &Config, tx: String) -> RPCResult<()> {
         let payload = BitcoinRPCRequest {
             method: "sendrawtransaction".to_string(),
@@ -1934,6 +2916,71 @@ impl BitcoinRPCRequest {
         Ok(())
     }
 
+    /// Looks up `txid` in bitcoind's mempool. Returns None if it is not there.
+    pub fn get_mempool_entry(config: &Config, txid: &Txid) -> RPCResult<Option<MempoolEntry>> {
+        let payload = BitcoinRPCRequest {
+            method: "getmempoolentry".to_string(),
+            params: vec![txid.to_hex().into()],
+            id: "stacks".to_string(),
+            jsonrpc: "2.0".to_string(),
+        };
+
+        let json_resp = match BitcoinRPCRequest::send(&config, payload) {
+            Ok(json_resp) => json_resp,
+            Err(RPCError::Bitcoind(e)) if e.contains(RPC_TX_NOT_IN_MEMPOOL) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if let Some(e) = json_resp.get("error") {
+            if !e.is_null() {
+                if e.to_string().contains(RPC_TX_NOT_IN_MEMPOOL) {
+                    return Ok(None);
+                }
+                return Err(RPCError::Bitcoind(e.to_string()));
+            }
+        }
+
+        let result = json_resp
+            .get("result")
+            .ok_or(RPCError::Parsing("Failed to get mempool entry".to_string()))?;
+        let vsize =
+            result
+                .get("vsize")
+                .and_then(|vsize| vsize.as_u64())
+                .ok_or(RPCError::Parsing(
+                    "Failed to get mempool entry vsize".to_string(),
+                ))?;
+        let fee = result
+            .get("fees")
+            .and_then(|fees| fees.get("base"))
+            .and_then(|fee| fee.as_f64())
+            .ok_or(RPCError::Parsing(
+                "Failed to get mempool entry fee".to_string(),
+            ))?;
+        Ok(Some(MempoolEntry {
+            vsize,
+            fee: (fee * 100_000_000.0).round() as u64,
+        }))
+    }
+
+    /// bitcoind's estimate of the fee rate, in satoshis per vbyte, that gets a transaction
+    /// confirmed within `conf_target` blocks. Returns None if bitcoind has no estimate, as is
+    /// usual in regtest.
+    pub fn estimate_smart_fee(config: &Config, conf_target: u64) -> RPCResult<Option<u64>> {
+        let payload = BitcoinRPCRequest {
+            method: "estimatesmartfee".to_string(),
+            params: vec![conf_target.into()],
+            id: "stacks".to_string(),
+            jsonrpc: "2.0".to_string(),
+        };
+
+        let json_resp = BitcoinRPCRequest::send(&config, payload)?;
+        Ok(json_resp
+            .get("result")
+            .and_then(|result| result.get("feerate"))
+            .and_then(|fee_rate| fee_rate.as_f64())
+            .map(btc_per_kvb_to_sat_per_vb))
+    }
+
     pub fn import_public_key(config: &Config, public_key: &Secp256k1PublicKey) -> RPCResult<()> {
         let rescan = true;
         let label = "";
@@ -2021,7 +3068,16 @@ impl BitcoinRPCRequest {
         request.set_body(body);
 
         let mut response = async_std::task::block_on(async move {
-            let stream = match TcpStream::connect(config.burnchain.get_rpc_socket_addr()).await {
+            let stream_res = match config.connection_options.socks_proxy {
+                Some(ref proxy) => socks::connect_blocking(
+                    proxy,
+                    &SocksTarget::from_host(&config.burnchain.peer_host, config.burnchain.rpc_port),
+                    Duration::from_secs(config.burnchain.timeout as u64),
+                )
+                .map(TcpStream::from),
+                None => TcpStream::connect(config.burnchain.get_rpc_socket_addr()).await,
+            };
+            let stream = match stream_res {
                 Ok(stream) => stream,
                 Err(err) => {
                     return Err(RPCError::Network(format!(
@@ -2052,6 +3108,14 @@ impl BitcoinRPCRequest {
         });
 
         if !status.is_success() {
+            // bitcoind answers failed calls with an error status and a JSON-RPC error
+            if let Ok(v) = serde_json::from_slice::<serde_json::Value>(&buffer[..]) {
+                if let Some(e) = v.get("error") {
+                    if !e.is_null() {
+                        return Err(RPCError::Bitcoind(e.to_string()));
+                    }
+                }
+            }
             return Err(RPCError::Network(format!(
                 "Bitcoin RPC: status({}) != success, body is '{:?}'",
                 status,
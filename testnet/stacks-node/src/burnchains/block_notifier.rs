@@ -0,0 +1,129 @@
+//! Listens for new-block announcements on bitcoind's P2P port, so the run loop syncs the
+//! burnchain as soon as a block arrives instead of at its next `burnchain.poll_time_secs` poll.
+//!
+//! The listener asks bitcoind to announce blocks with `sendheaders`, and also takes `inv`
+//! announcements from peers that ignore it.  It only wakes up the run loop; the burnchain is
+//! still downloaded and indexed by the burnchain controller as before.
+
+use std::thread;
+use std::thread::JoinHandle;
+
+use stacks::burnchains::bitcoin::indexer::BitcoinIndexer;
+use stacks::burnchains::bitcoin::Error as btc_error;
+use stacks::burnchains::bitcoin::PeerMessage;
+use stacks::util::sleep_ms;
+use stacks_common::deps_common::bitcoin::network::message::NetworkMessage;
+use stacks_common::deps_common::bitcoin::network::message_blockdata::InvType;
+use stacks_common::deps_common::bitcoin::network::serialize::Error as btc_serialize_error;
+
+use crate::syncctl::PoxSyncWatchdogComms;
+
+/// Does this message announce a new block?
+fn is_block_announcement(message: &PeerMessage) -> bool {
+    match message {
+        NetworkMessage::Headers(headers) => headers.len() > 0,
+        NetworkMessage::Inv(inventory) => inventory
+            .iter()
+            .any(|inv| inv.inv_type == InvType::Block || inv.inv_type == InvType::WitnessBlock),
+        _ => false,
+    }
+}
+
+/// Connect to bitcoind and forward its block announcements until the connection breaks.
+fn listen_for_blocks(
+    indexer: &mut BitcoinIndexer,
+    comms: &PoxSyncWatchdogComms,
+) -> Result<(), btc_error> {
+    indexer.connect_handshake_backoff()?;
+    indexer.send_sendheaders()?;
+
+    while comms.should_keep_running() {
+        let message = match indexer.recv_message() {
+            Ok(message) => message,
+            Err(btc_error::SerializationError(
+                btc_serialize_error::UnrecognizedNetworkCommand(command),
+            )) => {
+                // e.g. sendcmpct or feefilter, which we have no use for
+                debug!("Block notifier: ignoring '{}' message", &command);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+
+        if is_block_announcement(&message) {
+            debug!("Block notifier: bitcoind announced a new block");
+            comms.notify_burn_block();
+            continue;
+        }
+
+        match message {
+            NetworkMessage::Ping(..) => indexer.handle_ping(message)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Start the block notifier thread.  It reconnects to bitcoind whenever the connection breaks,
+/// and exits once the node shuts down.
+pub fn spawn_block_notifier(
+    mut indexer: BitcoinIndexer,
+    comms: PoxSyncWatchdogComms,
+) -> JoinHandle<()> {
+    thread::Builder::new()
+        .name("block-notifier".to_string())
+        .spawn(move || {
+            while comms.should_keep_running() {
+                if let Err(e) = listen_for_blocks(&mut indexer, &comms) {
+                    debug!("Block notifier: connection to bitcoind lost: {:?}", &e);
+                    sleep_ms(1000);
+                }
+            }
+        })
+        .expect("FATAL: failed to start block notifier thread")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use stacks_common::deps_common::bitcoin::blockdata::block::{BlockHeader, LoneBlockHeader};
+    use stacks_common::deps_common::bitcoin::network::encodable::VarInt;
+    use stacks_common::deps_common::bitcoin::network::message_blockdata::Inventory;
+    use stacks_common::deps_common::bitcoin::util::hash::Sha256dHash;
+
+    #[test]
+    fn test_is_block_announcement() {
+        let inv = |inv_type| Inventory {
+            inv_type,
+            hash: Sha256dHash::default(),
+        };
+        assert!(is_block_announcement(&NetworkMessage::Inv(vec![
+            inv(InvType::Transaction),
+            inv(InvType::Block),
+        ])));
+        assert!(is_block_announcement(&NetworkMessage::Inv(vec![inv(
+            InvType::WitnessBlock
+        )])));
+        assert!(!is_block_announcement(&NetworkMessage::Inv(vec![inv(
+            InvType::Transaction
+        )])));
+
+        let header = LoneBlockHeader {
+            header: BlockHeader {
+                version: 1,
+                prev_blockhash: Sha256dHash::default(),
+                merkle_root: Sha256dHash::default(),
+                time: 0,
+                bits: 0,
+                nonce: 0,
+            },
+            tx_count: VarInt(0),
+        };
+        assert!(is_block_announcement(&NetworkMessage::Headers(vec![
+            header
+        ])));
+        assert!(!is_block_announcement(&NetworkMessage::Headers(vec![])));
+        assert!(!is_block_announcement(&NetworkMessage::Ping(1)));
+    }
+}
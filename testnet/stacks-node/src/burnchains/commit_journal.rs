@@ -0,0 +1,270 @@
+//! Journals the block-commit and leader key transactions this miner broadcasts, so that an
+//! operator can reconcile what the miner spent and find out why a commit did not get mined.
+//!
+//! Each transaction is written to a sqlite database once bitcoind has accepted it, with the
+//! outputs it spends, the fees it pays, the burnchain block it was sent for, and the earlier
+//! transaction it replaced by fee, if any. `stacks-node commit-history` prints the journal.
+
+use std::path::Path;
+
+use rusqlite::types::ToSql;
+use rusqlite::{Connection, OpenFlags, Row, NO_PARAMS};
+
+use stacks::util_lib::db::{sqlite_open, tx_begin_immediate_sqlite};
+
+const COMMIT_JOURNAL_SCHEMA: &[&str] = &[
+    r#"
+    CREATE TABLE sent_ops(
+        txid TEXT PRIMARY KEY,
+        -- leader_key_register or leader_block_commit
+        op TEXT NOT NULL,
+        -- JSON list of the spent outputs, as txid:vout
+        inputs TEXT NOT NULL,
+        fee INTEGER NOT NULL,
+        fee_rate INTEGER NOT NULL,
+        burn_fee INTEGER NOT NULL,
+        target_burn_height INTEGER NOT NULL,
+        -- the transaction this one replaced by fee, if any
+        replaces TEXT,
+        sent_at INTEGER NOT NULL
+    );"#,
+    "CREATE INDEX index_sent_ops_by_target_height ON sent_ops(target_burn_height);",
+    "CREATE INDEX index_sent_ops_by_replaces ON sent_ops(replaces);",
+];
+
+/// A transaction this miner broadcast.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournaledOp {
+    pub txid: String,
+    /// `leader_key_register` or `leader_block_commit`
+    pub op: String,
+    /// The outputs the transaction spends, as `txid:vout`
+    pub inputs: Vec<String>,
+    /// Satoshis paid to the bitcoin miner: the spent outputs less the transaction's outputs
+    pub fee: u64,
+    /// Fee rate, in satoshis per byte
+    pub fee_rate: u64,
+    /// Satoshis sent to the block-commit's PoX or burn outputs. 0 for leader keys.
+    pub burn_fee: u64,
+    /// The burnchain block height the transaction was sent for: one above the canonical
+    /// burnchain tip at the time
+    pub target_burn_height: u64,
+    /// The transaction this one replaced by fee, if any
+    pub replaces: Option<String>,
+    /// The transaction that replaced this one by fee, if any. Not stored; filled in when the
+    /// journal is read.
+    pub replaced_by: Option<String>,
+    /// In seconds since the epoch
+    pub sent_at: u64,
+}
+
+impl JournaledOp {
+    fn from_row(row: &Row) -> Result<JournaledOp, rusqlite::Error> {
+        let inputs: String = row.get("inputs")?;
+        let inputs = serde_json::from_str(&inputs).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+        })?;
+        Ok(JournaledOp {
+            txid: row.get("txid")?,
+            op: row.get("op")?,
+            inputs,
+            fee: row.get::<_, i64>("fee")? as u64,
+            fee_rate: row.get::<_, i64>("fee_rate")? as u64,
+            burn_fee: row.get::<_, i64>("burn_fee")? as u64,
+            target_burn_height: row.get::<_, i64>("target_burn_height")? as u64,
+            replaces: row.get("replaces")?,
+            replaced_by: row.get("replaced_by")?,
+            sent_at: row.get::<_, i64>("sent_at")? as u64,
+        })
+    }
+}
+
+pub struct CommitJournal {
+    conn: Connection,
+}
+
+fn db_error(e: rusqlite::Error) -> String {
+    format!("Commit journal DB error: {}", e)
+}
+
+impl CommitJournal {
+    /// Opens the journal at `path`, creating it if it does not exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<CommitJournal, String> {
+        let create = !path.as_ref().exists();
+        let mut conn = sqlite_open(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
+            false,
+        )
+        .map_err(db_error)?;
+        if create {
+            let tx = tx_begin_immediate_sqlite(&mut conn).map_err(db_error)?;
+            for sql in COMMIT_JOURNAL_SCHEMA.iter() {
+                tx.execute_batch(sql).map_err(db_error)?;
+            }
+            tx.commit().map_err(db_error)?;
+        }
+        Ok(CommitJournal { conn })
+    }
+
+    /// Opens an existing journal for reading.
+    pub fn open_readonly<P: AsRef<Path>>(path: P) -> Result<CommitJournal, String> {
+        if !path.as_ref().exists() {
+            return Err(format!(
+                "No commit journal at {}: this node has not sent any block-commits or leader keys",
+                path.as_ref().display()
+            ));
+        }
+        let conn = sqlite_open(path, OpenFlags::SQLITE_OPEN_READ_ONLY, false).map_err(db_error)?;
+        Ok(CommitJournal { conn })
+    }
+
+    pub fn record(&mut self, op: &JournaledOp) -> Result<(), String> {
+        let inputs = serde_json::to_string(&op.inputs).expect("FATAL: failed to serialize inputs");
+        let args: &[&dyn ToSql] = &[
+            &op.txid,
+            &op.op,
+            &inputs,
+            &(op.fee as i64),
+            &(op.fee_rate as i64),
+            &(op.burn_fee as i64),
+            &(op.target_burn_height as i64),
+            &op.replaces,
+            &(op.sent_at as i64),
+        ];
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO sent_ops
+                 (txid, op, inputs, fee, fee_rate, burn_fee, target_burn_height, replaces, sent_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                args,
+            )
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// The transactions sent for burnchain heights from `from_height` on, oldest first. With a
+    /// `limit`, only the last `limit` of them.
+    pub fn get_history(
+        &self,
+        from_height: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<JournaledOp>, String> {
+        let limit = limit.map(|limit| limit as i64).unwrap_or(-1);
+        let args: &[&dyn ToSql] = &[&(from_height as i64), &limit];
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT * FROM (
+                     SELECT s.*, s.rowid AS seq, r.txid AS replaced_by FROM sent_ops s
+                     LEFT JOIN sent_ops r ON r.replaces = s.txid
+                     WHERE s.target_burn_height >= ?1
+                     ORDER BY s.sent_at DESC, s.rowid DESC LIMIT ?2
+                 ) ORDER BY sent_at ASC, seq ASC",
+            )
+            .map_err(db_error)?;
+        let history = stmt
+            .query_and_then(args, JournaledOp::from_row)
+            .map_err(db_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_error)?;
+        Ok(history)
+    }
+
+    /// Whether this miner sent the transaction `txid`
+    pub fn contains(&self, txid: &str) -> Result<bool, String> {
+        self.conn
+            .query_row(
+                "SELECT COUNT(*) FROM sent_ops WHERE txid = ?1",
+                &[txid],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|count| count > 0)
+            .map_err(db_error)
+    }
+
+    pub fn count(&self) -> Result<u64, String> {
+        self.conn
+            .query_row("SELECT COUNT(*) FROM sent_ops", NO_PARAMS, |row| {
+                row.get::<_, i64>(0)
+            })
+            .map(|count| count as u64)
+            .map_err(db_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_op(
+        txid: &str,
+        op: &str,
+        target_burn_height: u64,
+        replaces: Option<&str>,
+    ) -> JournaledOp {
+        JournaledOp {
+            txid: txid.to_string(),
+            op: op.to_string(),
+            inputs: vec![format!("{}:0", txid.replace("a", "f"))],
+            fee: 1_000,
+            fee_rate: 5,
+            burn_fee: if op == "leader_block_commit" {
+                20_000
+            } else {
+                0
+            },
+            target_burn_height,
+            replaces: replaces.map(|txid| txid.to_string()),
+            replaced_by: None,
+            sent_at: 1_600_000_000 + target_burn_height,
+        }
+    }
+
+    #[test]
+    fn test_commit_journal() {
+        let path = "/tmp/stacks-node-tests/test_commit_journal.sqlite";
+        if Path::new(path).exists() {
+            std::fs::remove_file(path).unwrap();
+        }
+        std::fs::create_dir_all("/tmp/stacks-node-tests").unwrap();
+        assert!(CommitJournal::open_readonly(path).is_err());
+
+        let mut journal = CommitJournal::open(path).unwrap();
+        let key = make_op("aa01", "leader_key_register", 100, None);
+        let commit = make_op("aa02", "leader_block_commit", 101, None);
+        let replacement = make_op("aa03", "leader_block_commit", 101, Some("aa02"));
+        let next_commit = make_op("aa04", "leader_block_commit", 102, None);
+        for op in [&key, &commit, &replacement, &next_commit].iter() {
+            journal.record(op).unwrap();
+        }
+        assert_eq!(journal.count().unwrap(), 4);
+        drop(journal);
+
+        // the journal survives a restart, and can be read while the node runs
+        let journal = CommitJournal::open_readonly(path).unwrap();
+        let history = journal.get_history(0, None).unwrap();
+        assert_eq!(
+            history
+                .iter()
+                .map(|op| op.txid.as_str())
+                .collect::<Vec<_>>(),
+            vec!["aa01", "aa02", "aa03", "aa04"]
+        );
+        assert_eq!(history[0], key);
+        assert_eq!(history[1].replaced_by, Some("aa03".to_string()));
+        assert_eq!(history[2].replaces, Some("aa02".to_string()));
+        assert_eq!(history[2].replaced_by, None);
+        assert!(journal.contains("aa02").unwrap());
+        assert!(!journal.contains("bb02").unwrap());
+
+        let history = journal.get_history(101, Some(2)).unwrap();
+        assert_eq!(
+            history
+                .iter()
+                .map(|op| op.txid.as_str())
+                .collect::<Vec<_>>(),
+            vec!["aa03", "aa04"]
+        );
+    }
+}
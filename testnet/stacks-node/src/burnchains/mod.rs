@@ -1,5 +1,8 @@
 pub mod bitcoin_regtest_controller;
+pub mod block_notifier;
+pub mod commit_journal;
 pub mod mocknet_controller;
+pub mod utxo_tracker;
 
 pub use self::bitcoin_regtest_controller::BitcoinRegtestController;
 pub use self::mocknet_controller::MocknetController;
@@ -0,0 +1,541 @@
+//! Tracks the miner's UTXOs by scanning bitcoin blocks, so the miner can run against a bitcoind
+//! with its wallet disabled, or one shared with other users.
+//!
+//! Every block from `burnchain.utxo_scan_start_height` on is fetched and searched for outputs
+//! paying to a watched scriptPubKey, and for inputs spending such outputs. The outputs and the
+//! hashes of the scanned blocks are kept in a sqlite database, so a restarted node resumes from
+//! the last scanned block. Scanned blocks that leave the best chain are rolled back.
+//!
+//! Only confirmed outputs are handed out. Outputs spent by transactions this node sent are held
+//! back until the spending transaction is mined, or until `PENDING_SPEND_EXPIRY` blocks pass
+//! without it being mined.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use rusqlite::{Connection, OpenFlags, NO_PARAMS};
+
+use stacks::types::chainstate::BurnchainHeaderHash;
+use stacks::util::hash::{hex_bytes, to_hex};
+use stacks::util_lib::db::{sqlite_open, tx_begin_immediate_sqlite};
+use stacks_common::deps_common::bitcoin::blockdata::block::Block;
+use stacks_common::deps_common::bitcoin::blockdata::script::Script;
+use stacks_common::deps_common::bitcoin::blockdata::transaction::{OutPoint, Transaction};
+use stacks_common::deps_common::bitcoin::util::hash::Sha256dHash;
+
+use super::bitcoin_regtest_controller::UTXO;
+
+/// Number of blocks after which an output spent by an unmined transaction of ours becomes
+/// spendable again.
+pub const PENDING_SPEND_EXPIRY: u64 = 6;
+
+const UTXO_TRACKER_SCHEMA: &[&str] = &[
+    r#"
+    CREATE TABLE watched_scripts(
+        script_pub_key TEXT PRIMARY KEY
+    );"#,
+    r#"
+    CREATE TABLE scanned_blocks(
+        block_height INTEGER PRIMARY KEY,
+        block_hash TEXT NOT NULL
+    );"#,
+    r#"
+    CREATE TABLE utxos(
+        txid TEXT NOT NULL,
+        vout INTEGER NOT NULL,
+        script_pub_key TEXT NOT NULL,
+        amount INTEGER NOT NULL,
+        block_height INTEGER NOT NULL,
+        -- height of the block that spends this output, if any
+        spent_height INTEGER,
+        PRIMARY KEY(txid, vout)
+    );"#,
+    "CREATE INDEX index_utxos_by_script ON utxos(script_pub_key, spent_height);",
+];
+
+/// Where the tracker gets blocks from.
+pub trait BlockSource {
+    /// Height of the best block.
+    fn get_block_count(&self) -> Result<u64, String>;
+    fn get_block_hash(&self, height: u64) -> Result<BurnchainHeaderHash, String>;
+    fn get_block(&self, block_hash: &BurnchainHeaderHash) -> Result<Block, String>;
+}
+
+pub struct UTXOTracker {
+    conn: Connection,
+    start_height: u64,
+    /// Outputs spent by transactions sent by this node that are not mined yet, and the height of
+    /// the best block when each was sent.
+    pending_spends: HashMap<OutPoint, u64>,
+    /// Height of the last block scanned.
+    tip_height: Option<u64>,
+}
+
+fn db_error(e: rusqlite::Error) -> String {
+    format!("UTXO tracker DB error: {}", e)
+}
+
+impl UTXOTracker {
+    pub fn open<P: AsRef<Path>>(path: P, start_height: u64) -> Result<UTXOTracker, String> {
+        let create = !path.as_ref().exists();
+        let mut conn = sqlite_open(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
+            false,
+        )
+        .map_err(db_error)?;
+        if create {
+            let tx = tx_begin_immediate_sqlite(&mut conn).map_err(db_error)?;
+            for sql in UTXO_TRACKER_SCHEMA.iter() {
+                tx.execute_batch(sql).map_err(db_error)?;
+            }
+            tx.commit().map_err(db_error)?;
+        }
+        let tip_height = conn
+            .query_row(
+                "SELECT MAX(block_height) FROM scanned_blocks",
+                NO_PARAMS,
+                |row| row.get::<_, Option<i64>>(0),
+            )
+            .map_err(db_error)?
+            .map(|height| height as u64);
+        Ok(UTXOTracker {
+            conn,
+            start_height,
+            pending_spends: HashMap::new(),
+            tip_height,
+        })
+    }
+
+    /// Starts tracking outputs to `script_pub_key`. A script that was not watched before causes
+    /// all blocks to be scanned again on the next `sync`.
+    pub fn watch(&mut self, script_pub_key: &Script) -> Result<(), String> {
+        let script_hex = to_hex(script_pub_key.as_bytes());
+        let watched: i64 = self
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM watched_scripts WHERE script_pub_key = ?1",
+                &[&script_hex],
+                |row| row.get(0),
+            )
+            .map_err(db_error)?;
+        if watched > 0 {
+            return Ok(());
+        }
+
+        info!(
+            "UTXO tracker: now watching scriptPubKey {}, will rescan from block {}",
+            &script_hex, self.start_height
+        );
+        let tx = tx_begin_immediate_sqlite(&mut self.conn).map_err(db_error)?;
+        tx.execute(
+            "INSERT INTO watched_scripts (script_pub_key) VALUES (?1)",
+            &[&script_hex],
+        )
+        .map_err(db_error)?;
+        tx.execute("DELETE FROM utxos", NO_PARAMS)
+            .map_err(db_error)?;
+        tx.execute("DELETE FROM scanned_blocks", NO_PARAMS)
+            .map_err(db_error)?;
+        tx.commit().map_err(db_error)?;
+        self.tip_height = None;
+        Ok(())
+    }
+
+    /// Scans the blocks `source` has that were not scanned yet, after rolling back the scanned
+    /// blocks that are no longer in its best chain. Returns the height of the best block.
+    pub fn sync<S: BlockSource>(&mut self, source: &S) -> Result<u64, String> {
+        let best_height = source.get_block_count()?;
+
+        // roll back to the last scanned block that is still in the best chain
+        while let Some(height) = self.tip_height {
+            let scanned_hash = self.get_scanned_block_hash(height)?;
+            if height <= best_height && source.get_block_hash(height)? == scanned_hash {
+                break;
+            }
+            info!(
+                "UTXO tracker: block {} at height {} left the best chain, rolling it back",
+                &scanned_hash, height
+            );
+            self.rollback_block(height)?;
+        }
+
+        let first_height = match self.tip_height {
+            Some(height) => height + 1,
+            None => self.start_height,
+        };
+        if first_height <= best_height {
+            debug!(
+                "UTXO tracker: scanning blocks {} to {}",
+                first_height, best_height
+            );
+        }
+        for height in first_height..=best_height {
+            let block_hash = source.get_block_hash(height)?;
+            let block = source.get_block(&block_hash)?;
+            self.scan_block(height, &block_hash, &block)?;
+            if (height - first_height + 1) % 1000 == 0 {
+                info!(
+                    "UTXO tracker: scanned blocks up to {} of {}",
+                    height, best_height
+                );
+            }
+        }
+
+        self.pending_spends
+            .retain(|_, sent_height| *sent_height + PENDING_SPEND_EXPIRY > best_height);
+        Ok(best_height)
+    }
+
+    /// Holds back the outputs spent by `tx`, which this node just sent.
+    pub fn note_sent_transaction(&mut self, tx: &Transaction) {
+        let sent_height = self.tip_height.unwrap_or(0);
+        for input in tx.input.iter() {
+            self.pending_spends
+                .insert(input.previous_output.clone(), sent_height);
+        }
+    }
+
+    /// The unspent outputs to `script_pub_key` worth at least `minimum_amount` each, excluding
+    /// outputs of the transactions in `txids_to_exclude` and outputs held back for transactions
+    /// this node sent.
+    pub fn get_utxos(
+        &self,
+        script_pub_key: &Script,
+        minimum_amount: u64,
+        txids_to_exclude: &[Sha256dHash],
+    ) -> Result<Vec<UTXO>, String> {
+        let tip_height = match self.tip_height {
+            Some(height) => height,
+            None => return Ok(vec![]),
+        };
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT txid, vout, amount, block_height FROM utxos
+                 WHERE script_pub_key = ?1 AND spent_height IS NULL AND amount >= ?2
+                 ORDER BY block_height, txid, vout",
+            )
+            .map_err(db_error)?;
+        let rows = stmt
+            .query_map(
+                rusqlite::params![to_hex(script_pub_key.as_bytes()), minimum_amount as i64],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, u32>(1)?,
+                        row.get::<_, i64>(2)?,
+                        row.get::<_, i64>(3)?,
+                    ))
+                },
+            )
+            .map_err(db_error)?;
+
+        let mut utxos = vec![];
+        for row in rows {
+            let (txid_hex, vout, amount, block_height) = row.map_err(db_error)?;
+            let txid = Sha256dHash::from_hex(&txid_hex)
+                .map_err(|e| format!("UTXO tracker: invalid txid {}: {:?}", &txid_hex, e))?;
+            if txids_to_exclude.contains(&txid) {
+                continue;
+            }
+            if self.pending_spends.contains_key(&OutPoint {
+                txid: txid.clone(),
+                vout,
+            }) {
+                continue;
+            }
+            utxos.push(UTXO {
+                txid,
+                vout,
+                script_pub_key: script_pub_key.clone(),
+                amount: amount as u64,
+                confirmations: (tip_height + 1 - block_height as u64) as u32,
+            });
+        }
+        Ok(utxos)
+    }
+
+    fn get_scanned_block_hash(&self, height: u64) -> Result<BurnchainHeaderHash, String> {
+        let block_hash: String = self
+            .conn
+            .query_row(
+                "SELECT block_hash FROM scanned_blocks WHERE block_height = ?1",
+                &[height as i64],
+                |row| row.get(0),
+            )
+            .map_err(db_error)?;
+        BurnchainHeaderHash::from_hex(&block_hash)
+            .map_err(|e| format!("UTXO tracker: invalid block hash {}: {:?}", &block_hash, e))
+    }
+
+    fn rollback_block(&mut self, height: u64) -> Result<(), String> {
+        let tx = tx_begin_immediate_sqlite(&mut self.conn).map_err(db_error)?;
+        tx.execute(
+            "DELETE FROM utxos WHERE block_height = ?1",
+            &[height as i64],
+        )
+        .map_err(db_error)?;
+        tx.execute(
+            "UPDATE utxos SET spent_height = NULL WHERE spent_height = ?1",
+            &[height as i64],
+        )
+        .map_err(db_error)?;
+        tx.execute(
+            "DELETE FROM scanned_blocks WHERE block_height = ?1",
+            &[height as i64],
+        )
+        .map_err(db_error)?;
+        tx.commit().map_err(db_error)?;
+
+        self.tip_height = if height > self.start_height {
+            Some(height - 1)
+        } else {
+            None
+        };
+        Ok(())
+    }
+
+    fn scan_block(
+        &mut self,
+        height: u64,
+        block_hash: &BurnchainHeaderHash,
+        block: &Block,
+    ) -> Result<(), String> {
+        let watched_scripts = {
+            let mut stmt = self
+                .conn
+                .prepare("SELECT script_pub_key FROM watched_scripts")
+                .map_err(db_error)?;
+            let rows = stmt
+                .query_map(NO_PARAMS, |row| row.get::<_, String>(0))
+                .map_err(db_error)?;
+            let mut scripts = vec![];
+            for row in rows {
+                let script_hex = row.map_err(db_error)?;
+                scripts.push(hex_bytes(&script_hex).map_err(|e| {
+                    format!("UTXO tracker: invalid script {}: {:?}", &script_hex, e)
+                })?);
+            }
+            scripts
+        };
+
+        let tx = tx_begin_immediate_sqlite(&mut self.conn).map_err(db_error)?;
+        for block_tx in block.txdata.iter() {
+            let txid = block_tx.txid();
+            for input in block_tx.input.iter() {
+                let spent = tx
+                    .execute(
+                        "UPDATE utxos SET spent_height = ?1 WHERE txid = ?2 AND vout = ?3 AND spent_height IS NULL",
+                        rusqlite::params![
+                            height as i64,
+                            input.previous_output.txid.be_hex_string(),
+                            input.previous_output.vout
+                        ],
+                    )
+                    .map_err(db_error)?;
+                if spent > 0 {
+                    self.pending_spends.remove(&input.previous_output);
+                }
+            }
+            for (vout, output) in block_tx.output.iter().enumerate() {
+                if !watched_scripts
+                    .iter()
+                    .any(|script| &script[..] == output.script_pubkey.as_bytes())
+                {
+                    continue;
+                }
+                tx.execute(
+                    "INSERT OR REPLACE INTO utxos (txid, vout, script_pub_key, amount, block_height, spent_height) VALUES (?1, ?2, ?3, ?4, ?5, NULL)",
+                    rusqlite::params![
+                        txid.be_hex_string(),
+                        vout as u32,
+                        to_hex(output.script_pubkey.as_bytes()),
+                        output.value as i64,
+                        height as i64
+                    ],
+                )
+                .map_err(db_error)?;
+            }
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO scanned_blocks (block_height, block_hash) VALUES (?1, ?2)",
+            rusqlite::params![height as i64, block_hash.to_hex()],
+        )
+        .map_err(db_error)?;
+        tx.commit().map_err(db_error)?;
+
+        self.tip_height = Some(height);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+
+    use stacks_common::deps_common::bitcoin::blockdata::block::BlockHeader;
+    use stacks_common::deps_common::bitcoin::blockdata::transaction::{TxIn, TxOut};
+
+    /// A chain of blocks, each holding the given transactions.
+    struct TestChain {
+        blocks: RefCell<Vec<(BurnchainHeaderHash, Block)>>,
+    }
+
+    impl TestChain {
+        fn push(&self, fork_id: u8, txdata: Vec<Transaction>) {
+            let mut blocks = self.blocks.borrow_mut();
+            let height = blocks.len() as u8;
+            let block = Block {
+                header: BlockHeader {
+                    version: 1,
+                    prev_blockhash: Sha256dHash([0; 32]),
+                    merkle_root: Sha256dHash([0; 32]),
+                    time: 0,
+                    bits: 0,
+                    nonce: 0,
+                },
+                txdata,
+            };
+            blocks.push((BurnchainHeaderHash([fork_id ^ height; 32]), block));
+        }
+    }
+
+    impl BlockSource for TestChain {
+        fn get_block_count(&self) -> Result<u64, String> {
+            Ok(self.blocks.borrow().len() as u64 - 1)
+        }
+
+        fn get_block_hash(&self, height: u64) -> Result<BurnchainHeaderHash, String> {
+            Ok(self.blocks.borrow()[height as usize].0.clone())
+        }
+
+        fn get_block(&self, block_hash: &BurnchainHeaderHash) -> Result<Block, String> {
+            self.blocks
+                .borrow()
+                .iter()
+                .find(|(hash, _)| hash == block_hash)
+                .map(|(_, block)| block.clone())
+                .ok_or("no such block".to_string())
+        }
+    }
+
+    fn make_tx(lock_time: u32, spends: Vec<OutPoint>, outputs: Vec<(&Script, u64)>) -> Transaction {
+        Transaction {
+            version: 1,
+            lock_time,
+            input: spends
+                .into_iter()
+                .map(|previous_output| TxIn {
+                    previous_output,
+                    script_sig: Script::new(),
+                    sequence: 0xffffffff,
+                    witness: vec![],
+                })
+                .collect(),
+            output: outputs
+                .into_iter()
+                .map(|(script, value)| TxOut {
+                    value,
+                    script_pubkey: script.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    fn outpoint(tx: &Transaction, vout: u32) -> OutPoint {
+        OutPoint {
+            txid: tx.txid(),
+            vout,
+        }
+    }
+
+    fn amounts(tracker: &UTXOTracker, script: &Script) -> Vec<u64> {
+        tracker
+            .get_utxos(script, 0, &[])
+            .unwrap()
+            .into_iter()
+            .map(|utxo| utxo.amount)
+            .collect()
+    }
+
+    #[test]
+    fn test_utxo_tracker() {
+        let path = "/tmp/stacks-node-tests/test_utxo_tracker.sqlite";
+        if Path::new(path).exists() {
+            std::fs::remove_file(path).unwrap();
+        }
+        std::fs::create_dir_all("/tmp/stacks-node-tests").unwrap();
+
+        let miner = Script::from(vec![0x76, 0xa9, 0x01]);
+        let other = Script::from(vec![0x76, 0xa9, 0x02]);
+
+        let funding = make_tx(0, vec![], vec![(&miner, 5000), (&other, 7000)]);
+        let spend = make_tx(1, vec![outpoint(&funding, 0)], vec![(&miner, 4000)]);
+        let chain = TestChain {
+            blocks: RefCell::new(vec![]),
+        };
+        // blocks before the start height are not scanned
+        chain.push(0, vec![make_tx(2, vec![], vec![(&miner, 100)])]);
+        chain.push(0, vec![funding.clone()]);
+        chain.push(0, vec![]);
+
+        let mut tracker = UTXOTracker::open(path, 1).unwrap();
+        tracker.watch(&miner).unwrap();
+        assert_eq!(tracker.sync(&chain).unwrap(), 2);
+        let utxos = tracker.get_utxos(&miner, 0, &[]).unwrap();
+        assert_eq!(utxos.len(), 1);
+        assert_eq!(utxos[0].amount, 5000);
+        assert_eq!(utxos[0].confirmations, 2);
+        assert_eq!(tracker.get_utxos(&miner, 5001, &[]).unwrap().len(), 0);
+        assert_eq!(
+            tracker
+                .get_utxos(&miner, 0, &[funding.txid()])
+                .unwrap()
+                .len(),
+            0
+        );
+
+        // an output spent by our unmined transaction is held back
+        tracker.note_sent_transaction(&spend);
+        assert_eq!(amounts(&tracker, &miner), Vec::<u64>::new());
+
+        chain.push(0, vec![spend.clone()]);
+        tracker.sync(&chain).unwrap();
+        assert_eq!(amounts(&tracker, &miner), vec![4000]);
+        assert!(tracker.pending_spends.is_empty());
+
+        // the scan resumes after a restart
+        drop(tracker);
+        let mut tracker = UTXOTracker::open(path, 1).unwrap();
+        tracker.watch(&miner).unwrap();
+        assert_eq!(amounts(&tracker, &miner), vec![4000]);
+
+        // a reorg drops the spend from the best chain
+        {
+            let mut blocks = chain.blocks.borrow_mut();
+            blocks.truncate(3);
+        }
+        chain.push(0xff, vec![]);
+        chain.push(0xff, vec![]);
+        assert_eq!(tracker.sync(&chain).unwrap(), 4);
+        assert_eq!(amounts(&tracker, &miner), vec![5000]);
+
+        // watching another script rescans the chain
+        tracker.watch(&other).unwrap();
+        tracker.sync(&chain).unwrap();
+        assert_eq!(amounts(&tracker, &other), vec![7000]);
+        assert_eq!(amounts(&tracker, &miner), vec![5000]);
+
+        // held-back outputs are released if the spending transaction is never mined
+        tracker.note_sent_transaction(&spend);
+        assert_eq!(amounts(&tracker, &miner), Vec::<u64>::new());
+        for _ in 0..PENDING_SPEND_EXPIRY {
+            chain.push(0xff, vec![]);
+        }
+        tracker.sync(&chain).unwrap();
+        assert_eq!(amounts(&tracker, &miner), vec![5000]);
+    }
+}
@@ -1,21 +1,31 @@
+use std::cmp;
+use std::collections::BTreeMap;
 use std::convert::TryInto;
+use std::fmt;
 use std::fs;
-use std::net::{SocketAddr, ToSocketAddrs};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
 use std::path::PathBuf;
+use std::time::Duration;
 
 use rand::RngCore;
+use serde::de;
+use serde::Deserialize;
 
 use stacks::burnchains::bitcoin::BitcoinNetworkType;
 use stacks::burnchains::{MagicBytes, BLOCKSTACK_MAGIC_MAINNET};
+use stacks::chainstate::stacks::index::file::TrieFileSyncMode;
 use stacks::chainstate::stacks::index::marf::MARFOpenOpts;
 use stacks::chainstate::stacks::index::storage::TrieHashCalculationMode;
 use stacks::chainstate::stacks::miner::BlockBuilderSettings;
 use stacks::chainstate::stacks::MAX_BLOCK_LEN;
-use stacks::core::mempool::MemPoolWalkSettings;
+use stacks::core::mempool::{MemPoolAdmissionSimulation, MemPoolWalkSettings};
 use stacks::core::StacksEpoch;
 use stacks::core::{
-    CHAIN_ID_MAINNET, CHAIN_ID_TESTNET, PEER_VERSION_MAINNET, PEER_VERSION_TESTNET,
+    BITCOIN_REGTEST_FIRST_BLOCK_HEIGHT, BITCOIN_TESTNET_FIRST_BLOCK_HEIGHT, CHAIN_ID_MAINNET,
+    CHAIN_ID_TESTNET, PEER_VERSION_MAINNET, PEER_VERSION_TESTNET, STACKS_EPOCHS_REGTEST,
+    STACKS_EPOCHS_TESTNET, STACKS_EPOCH_MAX,
 };
+use stacks::cost_estimates::accuracy::{CostEstimatorAccuracy, FeeEstimatorAccuracy};
 use stacks::cost_estimates::fee_medians::WeightedMedianFeeRateEstimator;
 use stacks::cost_estimates::fee_rate_fuzzer::FeeRateFuzzer;
 use stacks::cost_estimates::fee_scalar::ScalarFeeRateEstimator;
@@ -24,22 +34,37 @@ use stacks::cost_estimates::metrics::ProportionalDotProduct;
 use stacks::cost_estimates::CostEstimator;
 use stacks::cost_estimates::FeeEstimator;
 use stacks::cost_estimates::PessimisticEstimator;
-use stacks::net::connection::ConnectionOptions;
-use stacks::net::{Neighbor, NeighborKey, PeerAddress};
+use stacks::net::atlas::{AtlasConfig, AttachmentStoreConfig, ObjectStoreConfig};
+use stacks::net::connection::{AddressFamily, ConnectionOptions, RPCAccess};
+use stacks::net::dns::DNSSeed;
+use stacks::net::{HttpRequestType, Neighbor, NeighborKey, PeerAddress};
+use stacks::types::StacksEpochId;
 use stacks::util::get_epoch_time_ms;
-use stacks::util::hash::hex_bytes;
+use stacks::util::hash::Sha256Sum;
 use stacks::util::secp256k1::Secp256k1PrivateKey;
 use stacks::util::secp256k1::Secp256k1PublicKey;
+use stacks::util_lib::db::{set_sqlite_opts, SqliteDBKind, SqliteOpts};
 use stacks::vm::types::{AssetIdentifier, PrincipalData, QualifiedContractIdentifier};
+use stacks_node_derive::DynConfigDiff;
+use stx_genesis::GenesisData;
+
+use crate::event_dispatcher::unix_socket_path;
+use crate::replica;
+use crate::seed_encryption;
 
 const DEFAULT_SATS_PER_VB: u64 = 50;
 const DEFAULT_MAX_RBF_RATE: u64 = 150; // 1.5x
 const DEFAULT_RBF_FEE_RATE_INCREMENT: u64 = 5;
+const DEFAULT_AUTO_RBF_BUDGET: u64 = 100_000;
 const LEADER_KEY_TX_ESTIM_SIZE: u64 = 290;
 const BLOCK_COMMIT_TX_ESTIM_SIZE: u64 = 350;
 const INV_REWARD_CYCLES_TESTNET: u64 = 6;
+const DEFAULT_EVENT_OBSERVER_CONNECT_TIMEOUT_MS: u64 = 5_000;
+const DEFAULT_NATS_SUBJECT_PREFIX: &str = "stacks";
+const DEFAULT_ESTIMATOR_ACCURACY_WINDOW_SIZE: u64 = 100;
 
 #[derive(Clone, Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct ConfigFile {
     pub burnchain: Option<BurnchainConfigFile>,
     pub node: Option<NodeConfigFile>,
@@ -48,6 +73,10 @@ pub struct ConfigFile {
     pub connection_options: Option<ConnectionOptionsFile>,
     pub fee_estimation: Option<FeeEstimationConfigFile>,
     pub miner: Option<MinerConfigFile>,
+    pub mocknet: Option<MocknetConfigFile>,
+    pub mempool: Option<MempoolConfigFile>,
+    pub atlas: Option<AtlasConfigFile>,
+    pub sqlite: Option<SqliteConfigFile>,
 }
 
 #[derive(Clone, Deserialize, Default)]
@@ -55,71 +84,1890 @@ pub struct LegacyMstxConfigFile {
     pub mstx_balance: Option<Vec<InitialBalanceFile>>,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Deprecated top-level config keys, and the keys that replace them.
+const DEPRECATED_CONFIG_KEYS: &[(&str, &str)] = &[("mstx_balance", "ustx_balance")];
+
+/// A key that none of the config structs has, as reported by their deserializers.
+#[derive(Debug, PartialEq)]
+struct UnknownConfigKey {
+    /// Path of the table holding the key; empty for the top level.
+    table: Vec<String>,
+    key: String,
+    expected: Vec<String>,
+}
+
+impl UnknownConfigKey {
+    /// Removes the key from `value` (from every element, for an array of tables). Returns
+    /// false if it was not found.
+    fn remove_from(&self, value: &mut toml::Value) -> bool {
+        fn remove(value: &mut toml::Value, table: &[String], key: &str) -> bool {
+            match value {
+                toml::Value::Array(items) => items
+                    .iter_mut()
+                    .fold(false, |removed, item| remove(item, table, key) || removed),
+                toml::Value::Table(entries) => match table.split_first() {
+                    Some((first, rest)) => match entries.get_mut(first) {
+                        Some(child) => remove(child, rest, key),
+                        None => false,
+                    },
+                    None => entries.remove(key).is_some(),
+                },
+                _ => false,
+            }
+        }
+        remove(value, &self.table, &self.key)
+    }
+
+    /// The expected key closest to the unknown one, if it is close enough to be a typo.
+    fn suggestion(&self) -> Option<&str> {
+        self.expected
+            .iter()
+            .map(|name| (edit_distance(&self.key, name), name))
+            .filter(|(distance, name)| *distance <= cmp::max(2, name.len() / 3))
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, name)| name.as_str())
+    }
+}
+
+impl fmt::Display for UnknownConfigKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let qualify = |key: &str| {
+            let mut path = self.table.clone();
+            path.push(key.to_string());
+            path.join(".")
+        };
+        write!(f, "Unknown config key '{}'", qualify(&self.key))?;
+        if let Some(suggestion) = self.suggestion() {
+            write!(f, "; did you mean '{}'?", qualify(suggestion))?;
+        }
+        Ok(())
+    }
+}
+
+/// Error from deserializing a parsed config file. Unlike `toml`'s errors, an unknown key
+/// comes back as an `UnknownConfigKey` rather than as text.
+#[derive(Debug)]
+enum ConfigValueError {
+    UnknownKey(UnknownConfigKey),
+    /// Any other error, and the path of the key it happened at, once known.
+    Other(String, Option<Vec<String>>),
+}
+
+impl ConfigValueError {
+    /// Records where the error happened, unless a nested value already did. An unknown key
+    /// is reported by the table that holds it, so `path` is that table's.
+    fn at(self, path: &[String]) -> ConfigValueError {
+        match self {
+            ConfigValueError::Other(msg, None) => ConfigValueError::Other(msg, Some(path.to_vec())),
+            ConfigValueError::UnknownKey(mut unknown_key) => {
+                if unknown_key.table.is_empty() {
+                    unknown_key.table = path.to_vec();
+                }
+                ConfigValueError::UnknownKey(unknown_key)
+            }
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for ConfigValueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigValueError::UnknownKey(unknown_key) => unknown_key.fmt(f),
+            ConfigValueError::Other(msg, Some(path)) if !path.is_empty() => {
+                write!(f, "{} for key `{}`", msg, path.join("."))
+            }
+            ConfigValueError::Other(msg, _) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ConfigValueError {}
+
+impl de::Error for ConfigValueError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        ConfigValueError::Other(msg.to_string(), None)
+    }
+
+    fn unknown_field(field: &str, expected: &'static [&'static str]) -> Self {
+        ConfigValueError::UnknownKey(UnknownConfigKey {
+            table: vec![],
+            key: field.to_string(),
+            expected: expected.iter().map(|name| name.to_string()).collect(),
+        })
+    }
+}
+
+/// Deserializes the config structs from a parsed toml value, keeping track of the key path so
+/// that errors can say where they happened.
+struct ConfigValue {
+    value: toml::Value,
+    path: Vec<String>,
+}
+
+impl ConfigValue {
+    fn new(value: toml::Value) -> ConfigValue {
+        ConfigValue {
+            value,
+            path: vec![],
+        }
+    }
+}
+
+impl<'de> de::IntoDeserializer<'de, ConfigValueError> for ConfigValue {
+    type Deserializer = ConfigValue;
+
+    fn into_deserializer(self) -> ConfigValue {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ConfigValue {
+    type Error = ConfigValueError;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let path = self.path;
+        let result = match self.value {
+            toml::Value::String(s) => visitor.visit_string(s),
+            toml::Value::Integer(i) => visitor.visit_i64(i),
+            toml::Value::Float(f) => visitor.visit_f64(f),
+            toml::Value::Boolean(b) => visitor.visit_bool(b),
+            toml::Value::Datetime(d) => visitor.visit_string(d.to_string()),
+            toml::Value::Array(items) => {
+                de::value::SeqDeserializer::new(items.into_iter().map(|value| ConfigValue {
+                    value,
+                    path: path.clone(),
+                }))
+                .deserialize_any(visitor)
+            }
+            toml::Value::Table(entries) => {
+                de::value::MapDeserializer::new(entries.into_iter().map(|(key, value)| {
+                    let mut child_path = path.clone();
+                    child_path.push(key.clone());
+                    (
+                        key,
+                        ConfigValue {
+                            value,
+                            path: child_path,
+                        },
+                    )
+                }))
+                .deserialize_any(visitor)
+            }
+        };
+        result.map_err(|e| e.at(&path))
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.value {
+            toml::Value::String(s) => {
+                visitor.visit_enum(de::IntoDeserializer::into_deserializer(s))
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = diagonal + if a_char == *b_char { 0 } else { 1 };
+            diagonal = row[j + 1];
+            row[j + 1] = cmp::min(substitution, cmp::min(row[j], row[j + 1]) + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_file() {
+        assert_eq!(
+            format!("Invalid path: No such file or directory (os error 2)"),
+            ConfigFile::from_path("some_path").unwrap_err()
+        );
+        assert_eq!(
+            format!("Invalid toml: unexpected character found: `/` at line 1 column 1"),
+            ConfigFile::from_str("//[node]").unwrap_err()
+        );
+        assert!(ConfigFile::from_str("").is_ok());
+    }
+
+    #[test]
+    fn test_config() {
+        assert_eq!(
+            format!("node.seed should be a hex encoded string"),
+            Config::from_config_file(
+                ConfigFile::from_str(
+                    r#"
+                    [node]
+                    seed = "invalid-hex-value"
+                    "#,
+                )
+                .unwrap()
+            )
+            .unwrap_err()
+        );
+
+        assert_eq!(
+            format!("node.local_peer_seed should be a hex encoded string"),
+            Config::from_config_file(
+                ConfigFile::from_str(
+                    r#"
+                    [node]
+                    local_peer_seed = "invalid-hex-value"
+                    "#,
+                )
+                .unwrap()
+            )
+            .unwrap_err()
+        );
+
+        let expected_err_prefix =
+            "Invalid burnchain.peer_host: failed to lookup address information:";
+        let actual_err_msg = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                peer_host = "bitcoin2.blockstack.com"
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            expected_err_prefix,
+            &actual_err_msg[..expected_err_prefix.len()]
+        );
+
+        assert!(Config::from_config_file(ConfigFile::from_str("").unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_rpc_access_config() {
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [connection_options]
+                rpc_api_keys = ["key1", "key2"]
+                rpc_default_access = "authenticated"
+
+                [connection_options.rpc_route_access]
+                "/v2/info" = "public"
+                "/v2/blocks/upload/:block" = "disabled"
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let opts = &config.connection_options;
+        assert_eq!(
+            opts.rpc_api_keys,
+            vec!["key1".to_string(), "key2".to_string()]
+        );
+        assert_eq!(opts.get_rpc_access("/v2/info"), RPCAccess::Public);
+        assert_eq!(
+            opts.get_rpc_access("/v2/blocks/upload/:block"),
+            RPCAccess::Disabled
+        );
+        assert_eq!(
+            opts.get_rpc_access("/v2/transactions"),
+            RPCAccess::Authenticated
+        );
+
+        assert!(Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [connection_options.rpc_route_access]
+                "/v2/transactions" = "private"
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap_err()
+        .starts_with("Unknown RPC access 'private'"));
+
+        // routes are named by their path template, not by a concrete path
+        assert!(Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [connection_options.rpc_route_access]
+                "/v2/accounts/SP000000000000000000002Q6VF78" = "disabled"
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap_err()
+        .starts_with("Unknown RPC route '/v2/accounts/SP000000000000000000002Q6VF78'"));
+    }
+
+    #[test]
+    fn test_marf_blobs_sync_mode_config() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap()).unwrap();
+        assert_eq!(
+            config.node.get_marf_opts().blobs_sync_mode,
+            TrieFileSyncMode::PerCommit
+        );
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [node]
+                marf_blobs_sync_mode = "periodic:500"
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            config.node.get_marf_opts().blobs_sync_mode,
+            TrieFileSyncMode::Periodic(Duration::from_millis(500))
+        );
+
+        assert!(Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [node]
+                marf_blobs_sync_mode = "periodic"
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap_err()
+        .starts_with("Invalid trie blob sync mode 'periodic'"));
+    }
+
+    #[test]
+    fn test_grpc_bind_config() {
+        let load = |node: &str| {
+            ConfigFile::from_str(&format!("[node]\n{}", node)).and_then(Config::from_config_file)
+        };
+        assert_eq!(
+            load(r#"grpc_bind = "127.0.0.1:20445""#)
+                .unwrap()
+                .node
+                .grpc_bind,
+            Some("127.0.0.1:20445".to_string())
+        );
+        assert!(load(r#"grpc_bind = "localhost""#)
+            .unwrap_err()
+            .starts_with("Invalid node.grpc_bind 'localhost'"));
+    }
+
+    #[test]
+    fn test_marf_write_buffering_config() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap()).unwrap();
+        assert!(!config.node.get_marf_opts().write_buffering);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [node]
+                marf_write_buffering = true
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(config.node.get_marf_opts().write_buffering);
+    }
+
+    #[test]
+    fn test_mempool_admission_simulation_config() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap()).unwrap();
+        assert_eq!(
+            config.connection_options.mempool_admission_simulation,
+            MemPoolAdmissionSimulation::Off
+        );
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [connection_options]
+                mempool_admission_simulation = "reject"
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            config.connection_options.mempool_admission_simulation,
+            MemPoolAdmissionSimulation::Reject
+        );
+
+        assert!(Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [connection_options]
+                mempool_admission_simulation = "warn"
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap_err()
+        .starts_with("Unknown mempool admission simulation 'warn'"));
+    }
+
+    #[test]
+    fn test_microblock_seed_config() {
+        use crate::Keychain;
+
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap()).unwrap();
+        assert_eq!(config.node.microblock_seed, None);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [node]
+                seed = "0001"
+                local_peer_seed = "0002"
+                microblock_seed = "0003"
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(config.node.seed, vec![0x00, 0x01]);
+        assert_eq!(config.node.local_peer_seed, vec![0x00, 0x02]);
+        assert_eq!(config.node.microblock_seed, Some(vec![0x00, 0x03]));
+
+        // microblock keys no longer follow the mining key
+        let mut keychain = Keychain::default(config.node.seed.clone());
+        let mut other_keychain = Keychain::default(vec![0x00, 0x04]);
+        let default_microblock_key = keychain.rotate_microblock_keypair(1);
+        keychain.set_microblock_seed(&config.node.microblock_seed.clone().unwrap());
+        other_keychain.set_microblock_seed(&config.node.microblock_seed.clone().unwrap());
+        let microblock_key = keychain.rotate_microblock_keypair(1);
+        assert_ne!(microblock_key, default_microblock_key);
+        assert_eq!(microblock_key, other_keychain.rotate_microblock_keypair(1));
+        assert_eq!(keychain.get_microblock_key(), Some(microblock_key));
+
+        assert_eq!(
+            Config::from_config_file(
+                ConfigFile::from_str(
+                    r#"
+                    [node]
+                    microblock_seed = "zz"
+                    "#,
+                )
+                .unwrap(),
+            )
+            .unwrap_err(),
+            "node.microblock_seed should be a hex encoded string"
+        );
+
+        let config_file = ConfigFile::from_str(
+            r#"
+            [node]
+            microblock_seed = "0003"
+            "#,
+        )
+        .unwrap();
+        assert!(config_file.check_consistency().contains(
+            &"node.microblock_seed has no effect unless node.miner is enabled".to_string()
+        ));
+    }
+
+    #[test]
+    fn test_mempool_min_tx_fee_rate_config() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap()).unwrap();
+        assert_eq!(config.mempool.min_tx_fee_rate, 0f64);
+        assert_eq!(config.connection_options.min_tx_fee_rate, 0f64);
+
+        let mut config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [mempool]
+                min_tx_fee_rate = 2.5
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(config.mempool.min_tx_fee_rate, 2.5);
+        assert_eq!(config.connection_options.min_tx_fee_rate, 2.5);
+
+        assert!(Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [mempool]
+                min_tx_fee_rate = -1.0
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap_err()
+        .starts_with("Invalid mempool.min_tx_fee_rate -1"));
+
+        assert_eq!(
+            config
+                .apply_dynamic_patch(&serde_json::json!({"mempool": {"min_tx_fee_rate": 4.0}}))
+                .unwrap(),
+            vec!["mempool.min_tx_fee_rate".to_string()]
+        );
+        assert_eq!(config.mempool.min_tx_fee_rate, 4.0);
+        assert_eq!(config.connection_options.min_tx_fee_rate, 4.0);
+        assert!(config
+            .apply_dynamic_patch(&serde_json::json!({"mempool": {"min_tx_fee_rate": -4.0}}))
+            .is_err());
+        assert_eq!(config.mempool.min_tx_fee_rate, 4.0);
+    }
+
+    #[test]
+    fn test_microblock_stream_budget_config() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap()).unwrap();
+        assert_eq!(config.miner.microblock_stream_budget_pct, 100);
+        assert_eq!(config.miner.microblock_stream_time_ms, u64::MAX);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [miner]
+                microblock_stream_budget_pct = 40
+                microblock_stream_time_ms = 120000
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(config.miner.microblock_stream_time_ms, 120_000);
+        assert_eq!(
+            config
+                .make_block_builder_settings(0, true)
+                .microblock_stream_budget_pct,
+            40
+        );
+
+        assert!(Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [miner]
+                microblock_stream_budget_pct = 0
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap_err()
+        .starts_with("`miner.microblock_stream_budget_pct` must be between 1 and 100"));
+    }
+
+    #[test]
+    fn test_miner_standby_config() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap()).unwrap();
+        assert!(!config.miner.standby);
+        assert_eq!(config.miner.standby_takeover_burn_blocks, 3);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [miner]
+                standby = true
+                standby_takeover_burn_blocks = 6
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(config.miner.standby);
+        assert_eq!(config.miner.standby_takeover_burn_blocks, 6);
+
+        assert_eq!(
+            Config::from_config_file(
+                ConfigFile::from_str(
+                    r#"
+                    [miner]
+                    standby_takeover_burn_blocks = 0
+                    "#,
+                )
+                .unwrap(),
+            )
+            .unwrap_err(),
+            "`miner.standby_takeover_burn_blocks` must be at least 1"
+        );
+    }
+
+    #[test]
+    fn test_miner_recommit_config() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap()).unwrap();
+        assert!(!config.miner.recommit_on_new_parent);
+        assert_eq!(config.miner.recommit_deadline_ms, 60_000);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [miner]
+                recommit_on_new_parent = true
+                recommit_deadline_ms = 30000
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(config.miner.recommit_on_new_parent);
+        assert_eq!(config.miner.recommit_deadline_ms, 30_000);
+    }
+
+    #[test]
+    fn test_miner_check_next_epoch_ast_rules_config() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap()).unwrap();
+        assert!(!config.miner.check_next_epoch_ast_rules);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [miner]
+                check_next_epoch_ast_rules = true
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(config.miner.check_next_epoch_ast_rules);
+        assert!(
+            config
+                .make_block_builder_settings(1, false)
+                .check_next_epoch_ast_rules
+        );
+    }
+
+    #[test]
+    fn test_fee_estimation_accuracy_config() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap()).unwrap();
+        assert!(!config.estimation.track_accuracy);
+        assert!(!config.estimation.cross_validate);
+        assert_eq!(config.estimation.accuracy_window_size, 100);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [fee_estimation]
+                cross_validate = true
+                accuracy_window_size = 20
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(config.estimation.track_accuracy);
+        assert!(config.estimation.cross_validate);
+        assert_eq!(config.estimation.accuracy_window_size, 20);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [fee_estimation]
+                disabled = true
+                track_accuracy = true
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(!config.estimation.track_accuracy);
+        assert!(config.make_tracked_cost_estimator().is_none());
+        assert!(config.make_tracked_fee_estimator().is_none());
+    }
+
+    #[test]
+    fn test_deep_reorg_depth_config() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap()).unwrap();
+        assert_eq!(config.burnchain.deep_reorg_depth, 6);
+        assert!(!config.burnchain.p2p_block_notifications);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                deep_reorg_depth = 2
+                p2p_block_notifications = true
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(config.burnchain.deep_reorg_depth, 2);
+        assert!(config.burnchain.p2p_block_notifications);
+
+        assert_eq!(
+            Config::from_config_file(
+                ConfigFile::from_str(
+                    r#"
+                    [burnchain]
+                    deep_reorg_depth = 0
+                    "#,
+                )
+                .unwrap(),
+            )
+            .unwrap_err(),
+            "`burnchain.deep_reorg_depth` must be at least 1"
+        );
+    }
+
+    #[test]
+    fn test_burnchain_sync_workers_config() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap()).unwrap();
+        assert_eq!(config.burnchain.download_workers, 4);
+        assert_eq!(config.burnchain.parse_workers, 2);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                download_workers = 8
+                parse_workers = 1
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(config.burnchain.download_workers, 8);
+        assert_eq!(config.burnchain.parse_workers, 1);
+
+        for key in ["download_workers", "parse_workers"].iter() {
+            assert_eq!(
+                Config::from_config_file(
+                    ConfigFile::from_str(&format!("[burnchain]\n{} = 0", key)).unwrap(),
+                )
+                .unwrap_err(),
+                format!("`burnchain.{}` must be at least 1", key)
+            );
+        }
+    }
+
+    #[test]
+    fn test_sync_progress_log_interval_config() {
+        let config = Config::from_config_file(ConfigFile::from_str("").unwrap()).unwrap();
+        assert_eq!(config.node.sync_progress_log_interval_secs, 60);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str("[node]\nsync_progress_log_interval_secs = 0").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(config.node.sync_progress_log_interval_secs, 0);
+    }
+
+    #[test]
+    fn test_rpc_rate_limit_config() {
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [connection_options]
+                rpc_client_rate_limit = 20
+                rpc_client_burst = 200
+
+                [connection_options.rpc_request_costs]
+                "/v2/fees/transaction" = 3
+                "/v2/accounts/:principal" = 2
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let opts = &config.connection_options;
+        assert_eq!(opts.rpc_client_rate_limit, 20);
+        assert_eq!(opts.rpc_client_burst, 200);
+        assert_eq!(opts.rpc_route_rate_limit, 0);
+        assert_eq!(opts.rpc_request_costs.get("/v2/fees/transaction"), Some(&3));
+        assert_eq!(
+            opts.rpc_request_costs.get("/v2/accounts/:principal"),
+            Some(&2)
+        );
+        // built-in costs are kept
+        assert_eq!(
+            opts.rpc_request_costs.get("/v2/contracts/call-read-batch"),
+            Some(&10)
+        );
+    }
+
+    #[test]
+    fn test_bootstrap_node_dns_seeds() {
+        let pubkey_hex = "02fa66b66f8971a8cd4d20ffded09674e030f0f33883f337f34b95ad4935bac0e3";
+        let config = Config::from_config_file(
+            ConfigFile::from_str(&format!(
+                r#"
+                [node]
+                bootstrap_node = "{}@127.0.0.1:20444,localhost:20445,{}@localhost:20446,localhost:20445"
+
+                [connection_options]
+                dns_seed_refresh_interval = 600
+                "#,
+                pubkey_hex, pubkey_hex
+            ))
+            .unwrap(),
+        )
+        .unwrap();
+        let pubkey = Secp256k1PublicKey::from_hex(pubkey_hex).unwrap();
+
+        // literal addresses are not seeds
+        let literal = &config.node.bootstrap_node[0];
+        assert_eq!(literal.addr.port, 20444);
+        assert_eq!(literal.public_key, pubkey);
+
+        // seeds are looked up right away, and listed once
+        assert!(config
+            .node
+            .bootstrap_node
+            .iter()
+            .any(|neighbor| neighbor.addr.port == 20445));
+        assert_eq!(
+            config.node.dns_seeds,
+            vec![
+                DNSSeed {
+                    host: "localhost".to_string(),
+                    port: 20445,
+                    public_key: None,
+                },
+                DNSSeed {
+                    host: "localhost".to_string(),
+                    port: 20446,
+                    public_key: Some(pubkey.clone()),
+                },
+            ]
+        );
+        assert!(config
+            .node
+            .bootstrap_node
+            .iter()
+            .filter(|neighbor| neighbor.addr.port == 20446)
+            .all(|neighbor| neighbor.public_key == pubkey));
+        assert_eq!(config.connection_options.dns_seed_refresh_interval, 600);
+
+        // a seed that does not resolve yet is kept for later
+        let mut node = NodeConfig::default();
+        node.set_bootstrap_nodes("seed.invalid:20444".to_string(), 0x80000000, 0x18000000);
+        assert!(node.bootstrap_node.is_empty());
+        assert_eq!(node.dns_seeds.len(), 1);
+    }
+
+    #[test]
+    fn test_mocknet_block_time() {
+        let config = Config::from_config_file(ConfigFile::mocknet()).unwrap();
+        assert_eq!(config.get_block_time_ms(), 10_000);
+        assert!(!config.mocknet.mine_on_transaction);
+
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                mode = "mocknet"
+                commit_anchor_block_within = 10000
+
+                [mocknet]
+                block_time_ms = 500
+                mine_on_transaction = true
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(config.get_block_time_ms(), 500);
+        assert!(config.mocknet.mine_on_transaction);
+    }
+
+    #[test]
+    fn test_attachment_store() {
+        let load = |atlas: &str| {
+            ConfigFile::from_str(&format!(
+                "[burnchain]\nmode = \"mocknet\"\n[node]\nworking_dir = \"/data\"\n[atlas]\n{}",
+                atlas
+            ))
+            .and_then(Config::from_config_file)
+        };
+
+        let config = Config::from_config_file(ConfigFile::mocknet()).unwrap();
+        assert_eq!(config.attachment_store, AttachmentStoreConfig::Sqlite);
+        assert_eq!(
+            config.make_atlas_config().attachment_store,
+            AttachmentStoreConfig::Sqlite
+        );
+
+        assert_eq!(
+            load(r#"attachment_store = "flat_files""#)
+                .unwrap()
+                .attachment_store,
+            AttachmentStoreConfig::FlatFiles("/data/mocknet/attachments".to_string())
+        );
+
+        let object_store = load(
+            r#"
+            attachment_store = "object_store"
+            object_store_endpoint = "https://s3.us-east-1.amazonaws.com"
+            object_store_bucket = "attachments"
+            object_store_access_key_id = "key"
+            object_store_secret_access_key = "secret"
+            "#,
+        );
+        if cfg!(feature = "atlas_object_store") {
+            assert_eq!(
+                object_store.unwrap().attachment_store,
+                AttachmentStoreConfig::ObjectStore(ObjectStoreConfig {
+                    endpoint: "https://s3.us-east-1.amazonaws.com".to_string(),
+                    bucket: "attachments".to_string(),
+                    region: "us-east-1".to_string(),
+                    prefix: "".to_string(),
+                    access_key_id: "key".to_string(),
+                    secret_access_key: "secret".to_string(),
+                    cache_path: "/data/mocknet/attachments".to_string(),
+                })
+            );
+            assert!(load(r#"attachment_store = "object_store""#)
+                .unwrap_err()
+                .contains("atlas.object_store_endpoint"));
+            assert!(load(
+                r#"
+                attachment_store = "object_store"
+                object_store_endpoint = "s3.amazonaws.com"
+                object_store_bucket = "attachments"
+                object_store_access_key_id = "key"
+                object_store_secret_access_key = "secret"
+                "#
+            )
+            .is_err());
+        } else {
+            assert!(object_store
+                .unwrap_err()
+                .contains("the `atlas_object_store` feature"));
+        }
+        assert!(load(r#"attachment_store = "rocksdb""#).is_err());
+    }
+
+    #[test]
+    fn test_sqlite_opts() {
+        let load = |sqlite: &str| {
+            ConfigFile::from_str(&format!("[burnchain]\nmode = \"mocknet\"\n{}", sqlite))
+                .and_then(Config::from_config_file)
+        };
+
+        assert!(Config::from_config_file(ConfigFile::mocknet())
+            .unwrap()
+            .sqlite
+            .is_empty());
+
+        let config = load(
+            r#"
+            [sqlite.chainstate]
+            mmap_size = 0
+            busy_timeout_ms = 30000
+            [sqlite.mempool]
+            synchronous = "FULL"
+            wal_autocheckpoint = 10000
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.sqlite,
+            vec![
+                (
+                    SqliteDBKind::Chainstate,
+                    SqliteOpts {
+                        mmap_size: Some(0),
+                        busy_timeout_ms: Some(30000),
+                        ..SqliteOpts::default_for(SqliteDBKind::Chainstate)
+                    }
+                ),
+                (
+                    SqliteDBKind::Mempool,
+                    SqliteOpts {
+                        synchronous: "FULL".to_string(),
+                        wal_autocheckpoint: Some(10000),
+                        ..SqliteOpts::default_for(SqliteDBKind::Mempool)
+                    }
+                ),
+            ]
+        );
+
+        assert!(load("[sqlite.sortition]\npage_size = 1000")
+            .unwrap_err()
+            .contains("sqlite.sortition"));
+        assert!(load("[sqlite.marf]\nsynchronous = \"SOMETIMES\"").is_err());
+    }
+
+    #[test]
+    fn test_backup_config() {
+        let load = |node: &str| {
+            ConfigFile::from_str(&format!(
+                "[burnchain]\nmode = \"mocknet\"\n[node]\nworking_dir = \"/data\"\n{}",
+                node
+            ))
+            .and_then(Config::from_config_file)
+        };
+
+        let config = load("").unwrap();
+        assert_eq!(config.node.backup, None);
+        assert_eq!(
+            config.get_backup_path(),
+            PathBuf::from("/data/mocknet/backups")
+        );
+
+        let config = load("[node.backup]").unwrap();
+        assert_eq!(
+            config.node.backup,
+            Some(BackupConfig {
+                dir: None,
+                interval: BackupConfig::DEFAULT_INTERVAL,
+                keep: BackupConfig::DEFAULT_KEEP,
+            })
+        );
+
+        let config = load("[node.backup]\ndir = \"/backups\"\ninterval = 10\nkeep = 5").unwrap();
+        assert_eq!(
+            config.node.backup,
+            Some(BackupConfig {
+                dir: Some("/backups".to_string()),
+                interval: 10,
+                keep: 5,
+            })
+        );
+        assert_eq!(config.get_backup_path(), PathBuf::from("/backups"));
+
+        assert!(load("[node.backup]\ninterval = 0")
+            .unwrap_err()
+            .contains("node.backup.interval"));
+        assert!(load("[node.backup]\nkeep = 0")
+            .unwrap_err()
+            .contains("node.backup.keep"));
+    }
+
+    #[test]
+    fn test_replica_config() {
+        let load = |node: &str| {
+            ConfigFile::from_str(&format!(
+                "[burnchain]\nmode = \"mocknet\"\n[node]\n{}",
+                node
+            ))
+            .and_then(Config::from_config_file)
+        };
+
+        assert_eq!(load("").unwrap().node.replica, None);
+
+        let config = load(
+            r#"
+            [node.replica]
+            primary = "127.0.0.1:20445"
+            auth_token = "secret"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.node.replica,
+            Some(ReplicaConfig {
+                primary: "127.0.0.1:20445".to_string(),
+                auth_token: "secret".to_string(),
+                poll_interval_secs: ReplicaConfig::DEFAULT_POLL_INTERVAL_SECS,
+            })
+        );
+
+        assert!(load("[node.replica]\nauth_token = \"secret\"")
+            .unwrap_err()
+            .contains("node.replica.primary"));
+        assert!(load("[node.replica]\nprimary = \"127.0.0.1:20445\"")
+            .unwrap_err()
+            .contains("node.replica.auth_token"));
+        assert!(load(
+            "[node.replica]\nprimary = \"127.0.0.1:20445\"\nauth_token = \"secret\"\npoll_interval_secs = 0"
+        )
+        .unwrap_err()
+        .contains("node.replica.poll_interval_secs"));
+        assert!(load(
+            "miner = true\n[node.replica]\nprimary = \"127.0.0.1:20445\"\nauth_token = \"secret\""
+        )
+        .unwrap_err()
+        .contains("node.replica"));
+
+        // the auth token only crosses the network over TLS
+        let config = load(
+            "[node.replica]\nprimary = \"https://primary.example.com\"\nauth_token = \"secret\"",
+        )
+        .unwrap();
+        assert_eq!(
+            config.node.replica.unwrap().primary,
+            "https://primary.example.com"
+        );
+        assert!(
+            load("[node.replica]\nprimary = \"10.0.0.1:20445\"\nauth_token = \"secret\"")
+                .unwrap_err()
+                .contains("not a loopback address")
+        );
+    }
+
+    #[test]
+    fn test_stall_watchdog_config() {
+        let load = |node: &str| {
+            ConfigFile::from_str(&format!(
+                "[burnchain]\nmode = \"mocknet\"\n[node]\n{}",
+                node
+            ))
+            .and_then(Config::from_config_file)
+        };
+
+        assert_eq!(load("").unwrap().node.stall_watchdog, None);
+        assert_eq!(
+            load("[node.stall_watchdog]").unwrap().node.stall_watchdog,
+            Some(StallWatchdogConfig {
+                timeout_secs: StallWatchdogConfig::DEFAULT_TIMEOUT_SECS,
+                remediation: StallRemediation::None,
+            })
+        );
+        assert_eq!(
+            load("[node.stall_watchdog]\ntimeout_secs = 600\nremediation = \"reset_peers\"")
+                .unwrap()
+                .node
+                .stall_watchdog,
+            Some(StallWatchdogConfig {
+                timeout_secs: 600,
+                remediation: StallRemediation::ResetPeers,
+            })
+        );
+        for remediation in [
+            StallRemediation::None,
+            StallRemediation::ReconnectBitcoind,
+            StallRemediation::ResetPeers,
+            StallRemediation::Exit,
+        ] {
+            assert_eq!(
+                StallRemediation::parse(remediation.as_str()).unwrap(),
+                remediation
+            );
+        }
+
+        assert!(load("[node.stall_watchdog]\ntimeout_secs = 0")
+            .unwrap_err()
+            .contains("node.stall_watchdog.timeout_secs"));
+        assert!(load("[node.stall_watchdog]\nremediation = \"reboot\"")
+            .unwrap_err()
+            .contains("node.stall_watchdog.remediation"));
+    }
+
+    #[test]
+    fn test_epochs_override() {
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                mode = "mocknet"
+
+                [burnchain.epochs]
+                "2.05" = 150
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let epochs = config.burnchain.epochs.unwrap();
+        assert_eq!(epochs.len(), STACKS_EPOCHS_REGTEST.len());
+        assert_eq!(epochs[1].epoch_id, StacksEpochId::Epoch20);
+        assert_eq!(epochs[1].start_height, 0);
+        assert_eq!(epochs[1].end_height, 150);
+        assert_eq!(epochs[2].epoch_id, StacksEpochId::Epoch2_05);
+        assert_eq!(epochs[2].start_height, 150);
+        assert_eq!(epochs[2].end_height, STACKS_EPOCH_MAX);
+        assert_eq!(epochs[2].block_limit, STACKS_EPOCHS_REGTEST[2].block_limit);
+
+        // full epoch definitions are taken as they are, once they pass validation
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                mode = "mocknet"
+
+                [[burnchain.epochs]]
+                epoch_id = "Epoch20"
+                start_height = 0
+                end_height = 9223372036854775807
+                network_epoch = 0
+                block_limit = { write_length = 1, write_count = 2, read_length = 3, read_count = 4, runtime = 5 }
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let epochs = config.burnchain.epochs.unwrap();
+        assert_eq!(epochs.len(), 1);
+        assert_eq!(epochs[0].epoch_id, StacksEpochId::Epoch20);
+        assert_eq!(epochs[0].block_limit.runtime, 5);
+
+        let make_epochs = |epochs: &[(&str, u64)], mode: &str| {
+            let activation_heights = epochs
+                .iter()
+                .map(|(name, height)| (name.to_string(), *height))
+                .collect();
+            Config::make_epochs(&activation_heights, mode)
+        };
+        assert_eq!(
+            make_epochs(&[("2.05", 150)], "mainnet").unwrap_err(),
+            "burnchain.epochs cannot be overridden when running on mainnet"
+        );
+        assert_eq!(
+            make_epochs(&[("3.0", 150)], "mocknet").unwrap_err(),
+            "burnchain.epochs: unknown epoch '3.0'"
+        );
+        assert_eq!(
+            make_epochs(&[("2.0", 150)], "mocknet").unwrap_err(),
+            "burnchain.epochs: epoch 2.0 starts with the chain and cannot be overridden"
+        );
+        assert!(make_epochs(&[("2.05", 0)], "xenon")
+            .unwrap_err()
+            .starts_with("burnchain.epochs: epoch 2.05 would start at height 0, before epoch 2.0"));
+
+        // full epoch definitions must be validated too
+        let err = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                mode = "mocknet"
+
+                [[burnchain.epochs]]
+                epoch_id = "Epoch20"
+                start_height = 0
+                end_height = 100
+                network_epoch = 0
+                block_limit = { write_length = 1, write_count = 2, read_length = 3, read_count = 4, runtime = 5 }
+
+                [[burnchain.epochs]]
+                epoch_id = "Epoch2_05"
+                start_height = 101
+                end_height = 9223372036854775807
+                network_epoch = 5
+                block_limit = { write_length = 1, write_count = 2, read_length = 3, read_count = 4, runtime = 5 }
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            "burnchain.epochs: epoch 2.05 starts at height 101, but epoch 2.0 ends at height 100"
+        );
+
+        let mut gap = STACKS_EPOCHS_REGTEST.to_vec();
+        gap[2].start_height += 1;
+        assert_eq!(
+            Config::validate_epochs(&gap, 0).unwrap_err(),
+            "burnchain.epochs: epoch 2.05 starts at height 1001, but epoch 2.0 ends at height 1000"
+        );
+
+        let mut overlap = STACKS_EPOCHS_REGTEST.to_vec();
+        overlap[1].end_height += 1;
+        assert_eq!(
+            Config::validate_epochs(&overlap, 0).unwrap_err(),
+            "burnchain.epochs: epoch 2.05 starts at height 1000, but epoch 2.0 ends at height 1001"
+        );
+
+        let mut out_of_order = STACKS_EPOCHS_REGTEST.to_vec();
+        out_of_order.swap(1, 2);
+        assert_eq!(
+            Config::validate_epochs(&out_of_order, 0).unwrap_err(),
+            "burnchain.epochs: epoch 2.0 is listed after epoch 2.05"
+        );
+
+        assert_eq!(
+            Config::validate_epochs(&STACKS_EPOCHS_REGTEST[..], 10).unwrap_err(),
+            "burnchain.epochs: epoch 2.0 must start at the first burnchain block height 10, not 0"
+        );
+
+        let mut no_runtime = STACKS_EPOCHS_REGTEST.to_vec();
+        no_runtime[2].block_limit.runtime = 0;
+        assert_eq!(
+            Config::validate_epochs(&no_runtime, 0).unwrap_err(),
+            "burnchain.epochs: epoch 2.05 has a zero block limit, which no block can meet"
+        );
+
+        Config::validate_epochs(&STACKS_EPOCHS_REGTEST[..], 0).unwrap();
+        Config::validate_epochs(
+            &STACKS_EPOCHS_TESTNET[..],
+            BITCOIN_TESTNET_FIRST_BLOCK_HEIGHT,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_genesis_chainstate_path() {
+        let dir = "/tmp/stacks-node-tests/test_genesis_chainstate_path";
+        if fs::metadata(dir).is_ok() {
+            fs::remove_dir_all(dir).unwrap();
+        }
+        fs::create_dir_all(dir).unwrap();
+
+        let write_chainstate = |name: &str, balance: u64| {
+            let path = format!("{}/{}", dir, name);
+            fs::write(
+                &path,
+                format!(
+                    "-----BEGIN STX BALANCES-----\n\
+                     address,balance\n\
+                     ST2QKZ4FKHAH1NQKYKYAYZPY440FEPK7GZ1R5HBP2,{}\n\
+                     -----END STX BALANCES-----\n",
+                    balance
+                ),
+            )
+            .unwrap();
+            path
+        };
+        let path_a = write_chainstate("a.txt", 1000);
+        let path_b = write_chainstate("b.txt", 2000);
+
+        let load = |mode: &str, path: &str, chain_id: Option<u32>| {
+            Config::from_config_file(
+                ConfigFile::from_str(&format!(
+                    r#"
+                    [node]
+                    genesis_chainstate_path = "{}"
+
+                    [burnchain]
+                    mode = "{}"
+                    {}
+                    "#,
+                    path,
+                    mode,
+                    chain_id
+                        .map(|chain_id| format!("chain_id = {}", chain_id))
+                        .unwrap_or_default()
+                ))
+                .unwrap(),
+            )
+        };
+
+        let hash_a = Sha256Sum::from_data(&fs::read(&path_a).unwrap());
+        let hash_b = Sha256Sum::from_data(&fs::read(&path_b).unwrap());
+        let chain_id_a = Config::get_genesis_chain_id(&hash_a);
+        let chain_id_b = Config::get_genesis_chain_id(&hash_b);
+        assert_ne!(chain_id_a, chain_id_b);
+        assert_eq!(chain_id_a & CHAIN_ID_TESTNET, CHAIN_ID_TESTNET);
+
+        // the chain ID must be the one derived from the file
+        assert_eq!(
+            load("mocknet", &path_a, None).unwrap_err(),
+            format!(
+                "A node booted from genesis chainstate file {} must set burnchain.chain_id = {} (derived from the file's SHA256 {}), not {}",
+                &path_a, chain_id_a, &hash_a, CHAIN_ID_TESTNET
+            )
+        );
+        assert!(load("mocknet", &path_a, Some(chain_id_b)).is_err());
+
+        let config_a = load("mocknet", &path_a, Some(chain_id_a)).unwrap();
+        let config_b = load("mocknet", &path_b, Some(chain_id_b)).unwrap();
+        assert_eq!(config_a.burnchain.chain_id, chain_id_a);
+        assert_eq!(config_b.burnchain.chain_id, chain_id_b);
+        assert_eq!(config_a.get_genesis_chainstate_hash(), hash_a);
+        assert_eq!(config_b.get_genesis_chainstate_hash(), hash_b);
+        assert_eq!(
+            Config::from_config_file(ConfigFile::mocknet())
+                .unwrap()
+                .get_genesis_chainstate_hash(),
+            Sha256Sum::from_hex(stx_genesis::GENESIS_CHAINSTATE_HASH).unwrap()
+        );
+
+        assert_eq!(
+            load("mainnet", &path_a, None).unwrap_err(),
+            "Attempted to run mainnet node with `genesis_chainstate_path`"
+        );
+        assert_eq!(
+            load("mainnet", &path_a, Some(chain_id_a)).unwrap_err(),
+            format!("Attempted to run mainnet node with chain ID {}", chain_id_a)
+        );
+
+        // the hash is taken once, when the config is loaded
+        fs::remove_file(&path_b).unwrap();
+        assert_eq!(config_b.get_genesis_chainstate_hash(), hash_b);
+        assert!(load("mocknet", &format!("{}/missing.txt", dir), None).is_err());
+    }
+
+    #[test]
+    fn test_clarity_coverage_modes() {
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [node]
+                clarity_coverage = true
+                clarity_coverage_dir = "/tmp/coverage"
+
+                [burnchain]
+                mode = "mocknet"
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(config.node.clarity_coverage);
+        assert_eq!(
+            config.node.clarity_coverage_dir.as_deref(),
+            Some("/tmp/coverage")
+        );
+
+        assert!(Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [node]
+                clarity_coverage = true
+
+                [burnchain]
+                mode = "xenon"
+                "#,
+            )
+            .unwrap(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_unknown_config_keys() {
+        let (config, warnings) = ConfigFile::from_str_with_warnings(
+            r#"
+            foo = 1
+
+            [node]
+            minr = true
+            seed = "0000000000000000000000000000000000000000000000000000000000000000"
+
+            [[events_observer]]
+            endpoint = "localhost:3700"
+            events_keys = ["*"]
+
+            [[events_observer]]
+            endpoint = "localhost:3701"
+            events_keys = ["*"]
+            timout_ms = 100
+
+            [[mstx_balance]]
+            address = "ST2QKZ4FKHAH1NQKYKYAYZPY440FEPK7GZ1R5HBP2"
+            amount = 10000000000000000
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            warnings,
+            vec![
+                "'mstx_balance' inside toml config is deprecated, replace with 'ustx_balance'",
+                "Unknown config key 'events_observer.timout_ms'; did you mean 'events_observer.timeout_ms'?",
+                "Unknown config key 'foo'",
+                "Unknown config key 'node.minr'; did you mean 'node.miner'?",
+            ]
+        );
+        // everything else still applies
+        let node = config.node.as_ref().unwrap();
+        assert!(node.miner.is_none());
+        assert!(node.seed.is_some());
+        assert_eq!(config.events_observer.as_ref().unwrap().len(), 2);
+        assert_eq!(config.ustx_balance.as_ref().unwrap().len(), 1);
+
+        assert_eq!(
+            ConfigFile::from_str_with_warnings("[node]\nminer = 1\n").unwrap_err(),
+            "Invalid toml: invalid type: integer `1`, expected a boolean for key `node.miner`"
+        );
+    }
+
+    #[test]
+    fn test_events_observer_sink() {
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [[events_observer]]
+                endpoint = "localhost:3700"
+                events_keys = ["*"]
+
+                [[events_observer]]
+                endpoint = "localhost:4222"
+                events_keys = ["burn_blocks"]
+                sink = "nats"
+                nats_jetstream = true
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(config.events_observers[0].sink, EventObserverSink::Http);
+        assert_eq!(
+            config.events_observers[1].sink,
+            EventObserverSink::Nats {
+                subject_prefix: "stacks".to_string(),
+                jetstream: true,
+            }
+        );
+
+        let err = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [[events_observer]]
+                endpoint = "localhost:9092"
+                events_keys = ["*"]
+                sink = "kafka"
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap_err();
+        assert!(err.starts_with("Invalid events_observer sink 'kafka'"));
+    }
+
+    #[test]
+    fn test_events_observer_http2() {
+        let observer_config = |options: &str| {
+            Config::from_config_file(
+                ConfigFile::from_str(&format!(
+                    "[[events_observer]]\nevents_keys = [\"*\"]\n{}",
+                    options
+                ))
+                .unwrap(),
+            )
+        };
+        let config = observer_config("endpoint = \"localhost:3700\"\nhttp2 = true").unwrap();
+        assert!(config.events_observers[0].http2);
+        assert!(config.events_observers[0].keep_alive);
+        let config = observer_config("endpoint = \"localhost:3700\"").unwrap();
+        assert!(!config.events_observers[0].http2);
+
+        assert!(
+            observer_config("endpoint = \"localhost:3700\"\nhttp2 = true\nkeep_alive = false")
+                .unwrap_err()
+                .contains("keep_alive = false")
+        );
+        assert!(
+            observer_config("endpoint = \"unix:///tmp/observer.sock\"\nhttp2 = true")
+                .unwrap_err()
+                .contains("only supported for HTTP observers over TCP")
+        );
+        assert!(
+            observer_config("endpoint = \"localhost:4222\"\nsink = \"nats\"\nhttp2 = true")
+                .unwrap_err()
+                .contains("only supported for HTTP observers over TCP")
+        );
+    }
+
+    #[test]
+    fn test_check_consistency() {
+        let config = ConfigFile::from_str(
+            r#"
+            [node]
+            miner = true
+
+            [burnchain]
+            mode = "xenon"
+            "#,
+        )
+        .unwrap();
+        let warnings = config.check_consistency();
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].starts_with("node.miner is enabled, but node.seed is not set"));
+        assert!(warnings[1].starts_with("node.miner is enabled, but burnchain.username"));
+
+        let config = ConfigFile::from_str("[node]\nmock_mining = true\n").unwrap();
+        assert_eq!(
+            config.check_consistency(),
+            vec!["node.mock_mining has no effect unless node.miner is enabled"]
+        );
+        assert!(ConfigFile::mocknet().check_consistency().is_empty());
+
+        let config = ConfigFile::from_str(
+            r#"
+            [node]
+            p2p_address = "[2001:db8::1]:20444"
+
+            [connection_options]
+            address_family = "ipv4-only"
+            "#,
+        )
+        .unwrap();
+        let warnings = config.check_consistency();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("node.p2p_address is not in the address family"));
+    }
+
+    #[test]
+    fn test_address_family_config() {
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [node]
+                p2p_bind = "[::]:20444"
+                p2p_address = "[2001:db8::1]:20444"
+
+                [connection_options]
+                address_family = "prefer-ipv6"
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            config.connection_options.address_family,
+            AddressFamily::PreferIPv6
+        );
+        assert_eq!(config.node.p2p_bind, "[::]:20444");
+
+        assert!(Config::from_config_file(
+            ConfigFile::from_str("[connection_options]\naddress_family = \"ipv6\"\n").unwrap()
+        )
+        .unwrap_err()
+        .starts_with("Unknown address family 'ipv6'"));
+    }
+
+    #[test]
+    fn test_socks_proxy_config() {
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [node]
+                rpc_bind = "127.0.0.1:20443"
+
+                [connection_options]
+                socks_proxy = "127.0.0.1:9050"
+                onion_address = "abcdefghijklmnop.onion"
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            config.connection_options.socks_proxy,
+            Some("127.0.0.1:9050".parse().unwrap())
+        );
+        assert_eq!(
+            config.connection_options.onion_address,
+            Some("abcdefghijklmnop.onion".to_string())
+        );
+        assert_eq!(config.node.data_url, "http://abcdefghijklmnop.onion:20443");
+
+        assert!(Config::from_config_file(
+            ConfigFile::from_str("[connection_options]\nsocks_proxy = \"localhost\"\n").unwrap()
+        )
+        .unwrap_err()
+        .starts_with("Invalid connection_options.socks_proxy 'localhost'"));
+
+        assert_eq!(
+            Config::from_config_file(
+                ConfigFile::from_str(
+                    "[connection_options]\nonion_address = \"abcdefghijklmnop.onion\"\n"
+                )
+                .unwrap()
+            )
+            .unwrap_err(),
+            "connection_options.onion_address requires connection_options.socks_proxy"
+        );
+    }
+
+    #[test]
+    fn test_check_event_observers() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let reachable = listener.local_addr().unwrap().to_string();
+        let unreachable = {
+            let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            closed.local_addr().unwrap().to_string()
+        };
+
+        let mut config = Config::default();
+        config.events_observers = vec![
+            EventObserverConfig {
+                endpoint: reachable,
+                ..EventObserverConfig::default()
+            },
+            EventObserverConfig {
+                endpoint: unreachable.clone(),
+                ..EventObserverConfig::default()
+            },
+        ];
+        let warnings = config.check_event_observers();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with(&format!(
+            "Event observer endpoint {} is unreachable",
+            unreachable
+        )));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_unix_socket_event_observers() {
+        let path = "/tmp/test_check_unix_socket_event_observers.sock";
+        if fs::metadata(path).is_ok() {
+            fs::remove_file(path).unwrap();
+        }
+        let _listener = std::os::unix::net::UnixListener::bind(path).unwrap();
+
+        let mut config = Config::default();
+        config.events_observers = vec![
+            EventObserverConfig {
+                endpoint: format!("unix://{}", path),
+                ..EventObserverConfig::default()
+            },
+            EventObserverConfig {
+                endpoint: "unix:///tmp/no-such-observer.sock".to_string(),
+                ..EventObserverConfig::default()
+            },
+        ];
+        let warnings = config.check_event_observers();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with(
+            "Event observer endpoint unix:///tmp/no-such-observer.sock is unreachable"
+        ));
+    }
 
     #[test]
-    fn test_config_file() {
+    fn test_apply_dynamic_patch() {
+        let mut config = Config::default();
+        config.node.miner = true;
+        let changed = config
+            .apply_dynamic_patch(&serde_json::json!({
+                "miner": {
+                    "first_attempt_time_ms": 1_000,
+                    "probability_pick_no_estimate_tx": 20,
+                },
+                "node": { "miner": false },
+            }))
+            .unwrap();
         assert_eq!(
-            format!("Invalid path: No such file or directory (os error 2)"),
-            ConfigFile::from_path("some_path").unwrap_err()
+            changed,
+            vec![
+                "miner.first_attempt_time_ms",
+                "miner.probability_pick_no_estimate_tx",
+                "node.miner"
+            ]
         );
+        assert_eq!(config.miner.first_attempt_time_ms, 1_000);
+        assert_eq!(config.miner.probability_pick_no_estimate_tx, 20);
+        assert!(!config.node.miner);
+
+        // a bad value rejects the whole patch
+        let err = config
+            .apply_dynamic_patch(&serde_json::json!({
+                "miner": { "first_attempt_time_ms": 2_000 },
+                "node": { "miner": "yes" },
+            }))
+            .unwrap_err();
+        assert!(err.starts_with("Invalid value for 'node.miner'"), "{}", err);
+        assert_eq!(config.miner.first_attempt_time_ms, 1_000);
+
         assert_eq!(
-            format!("Invalid toml: unexpected character found: `/` at line 1 column 1"),
-            ConfigFile::from_str("//[node]").unwrap_err()
+            config
+                .apply_dynamic_patch(&serde_json::json!({ "node": { "seed": "00" } }))
+                .unwrap_err(),
+            "Config keys cannot be changed at runtime: node.seed"
         );
-        assert!(ConfigFile::from_str("").is_ok());
+
+        // the settings the miner reads for each block commit, tenure and microblock follow a patch
+        config
+            .apply_dynamic_patch(&serde_json::json!({
+                "burnchain": { "rbf_fee_increment": 7, "max_rbf": 300 },
+                "miner": { "microblock_attempt_time_ms": 2_500 },
+                "node": { "wait_time_for_microblocks": 15_000 },
+            }))
+            .unwrap();
+        assert_eq!(config.burnchain.rbf_fee_increment, 7);
+        assert_eq!(config.burnchain.max_rbf, 300);
+        assert_eq!(config.node.wait_time_for_microblocks, 15_000);
+        let settings = config.make_block_builder_settings(0, true);
+        assert_eq!(settings.max_miner_time_ms, 2_500);
+        assert_eq!(settings.mempool_settings.max_walk_time_ms, 2_500);
     }
 
     #[test]
-    fn test_config() {
+    fn test_config_profiles() {
+        for profile in ConfigProfile::ALL.iter() {
+            assert_eq!(ConfigProfile::parse(profile.as_str()).unwrap(), *profile);
+            let (config_file, warnings) =
+                ConfigFile::from_str_with_profile("", Some(*profile)).unwrap();
+            assert!(warnings.is_empty(), "{}: {:?}", profile.as_str(), warnings);
+            Config::from_config_file(config_file).unwrap();
+        }
+        assert!(ConfigProfile::parse("validator").is_err());
+
+        let (config_file, _) = ConfigFile::from_str_with_profile(
+            r#"
+            [node]
+            marf_pinned_tries = 4
+            seed = "0000000000000000000000000000000000000000000000000000000000000001"
+            "#,
+            Some(ConfigProfile::Miner),
+        )
+        .unwrap();
+        let config = Config::from_config_file(config_file).unwrap();
+        // the config file wins over the profile, key by key
+        assert_eq!(config.node.marf_pinned_tries, 4);
+        assert!(config.node.miner);
+        assert!(config.node.mine_microblocks);
+        assert!(config.miner.recommit_on_new_parent);
+        assert_eq!(config.mempool.min_tx_fee_rate, 1.0);
+
+        let (config_file, _) = ConfigFile::from_str_with_profile(
+            r#"
+            [node]
+            miner = false
+            "#,
+            Some(ConfigProfile::Miner),
+        )
+        .unwrap();
+        assert!(!Config::from_config_file(config_file).unwrap().node.miner);
+    }
+
+    #[test]
+    fn test_apply_config_diff() {
+        let mut config = Config::default();
+        assert!(Config::hot_reloadable_keys().contains(&"burnchain.satoshis_per_byte".to_string()));
+        assert!(!Config::hot_reloadable_keys().contains(&"node.seed".to_string()));
+
+        let mut new_config = config.clone();
+        new_config.burnchain.satoshis_per_byte = config.burnchain.satoshis_per_byte + 10;
+        new_config.burnchain.peer_port = config.burnchain.peer_port + 1;
+        new_config.node.seed = vec![0xab; 32];
+        new_config.mempool.min_tx_fee_rate = 3.0;
+
+        let report = config.apply_config_diff(&new_config);
         assert_eq!(
-            format!("node.seed should be a hex encoded string"),
-            Config::from_config_file(
-                ConfigFile::from_str(
-                    r#"
-                    [node]
-                    seed = "invalid-hex-value"
-                    "#,
-                )
-                .unwrap()
-            )
-            .unwrap_err()
+            report.applied_keys(),
+            vec!["burnchain.satoshis_per_byte", "mempool.min_tx_fee_rate"]
         );
-
         assert_eq!(
-            format!("node.local_peer_seed should be a hex encoded string"),
-            Config::from_config_file(
-                ConfigFile::from_str(
-                    r#"
-                    [node]
-                    local_peer_seed = "invalid-hex-value"
-                    "#,
-                )
-                .unwrap()
-            )
-            .unwrap_err()
+            report
+                .rejected
+                .iter()
+                .map(|diff| diff.key.as_str())
+                .collect::<Vec<_>>(),
+            vec!["burnchain.peer_port", "node.seed"]
         );
+        assert_eq!(report.rejected[1].old_value, "<redacted>");
+        assert_eq!(report.rejected[1].new_value, "<redacted>");
 
-        let expected_err_prefix =
-            "Invalid burnchain.peer_host: failed to lookup address information:";
-        let actual_err_msg = Config::from_config_file(
-            ConfigFile::from_str(
-                r#"
-                [burnchain]
-                peer_host = "bitcoin2.blockstack.com"
-                "#,
-            )
-            .unwrap(),
-        )
-        .unwrap_err();
         assert_eq!(
-            expected_err_prefix,
-            &actual_err_msg[..expected_err_prefix.len()]
+            config.burnchain.satoshis_per_byte,
+            new_config.burnchain.satoshis_per_byte
         );
-
-        assert!(Config::from_config_file(ConfigFile::from_str("").unwrap()).is_ok());
+        assert_eq!(config.connection_options.min_tx_fee_rate, 3.0);
+        assert_ne!(config.burnchain.peer_port, new_config.burnchain.peer_port);
+        assert_ne!(config.node.seed, new_config.node.seed);
+
+        // nothing left to apply, but the rejected changes are still reported
+        let report = config.apply_config_diff(&new_config);
+        assert!(report.applied.is_empty());
+        assert_eq!(report.rejected.len(), 2);
     }
 
     #[test]
@@ -174,18 +2022,137 @@ impl ConfigFile {
         Self::from_str(&content)
     }
 
+    /// Parses a config, warning about (and otherwise ignoring) unknown and deprecated keys.
     pub fn from_str(content: &str) -> Result<ConfigFile, String> {
-        let mut config: ConfigFile =
+        let (config, warnings) = Self::from_str_with_warnings(content)?;
+        for warning in warnings.iter() {
+            warn!("{}", warning);
+        }
+        Ok(config)
+    }
+
+    /// Loads the config at `path` on top of `profile`'s settings, if given.
+    pub fn from_path_with_profile(
+        path: &str,
+        profile: Option<ConfigProfile>,
+    ) -> Result<ConfigFile, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("Invalid path: {}", &e))?;
+        let (config, warnings) = Self::from_str_with_profile(&content, profile)?;
+        for warning in warnings.iter() {
+            warn!("{}", warning);
+        }
+        Ok(config)
+    }
+
+    /// Parses a config, returning it along with a warning for each unknown or deprecated key.
+    /// Unknown keys are dropped, so a misspelled key leaves its setting at the default.
+    pub fn from_str_with_warnings(content: &str) -> Result<(ConfigFile, Vec<String>), String> {
+        Self::from_str_with_profile(content, None)
+    }
+
+    /// Like `from_str_with_warnings`, but the config is laid over `profile`'s settings, so that
+    /// every key it sets takes precedence.
+    pub fn from_str_with_profile(
+        content: &str,
+        profile: Option<ConfigProfile>,
+    ) -> Result<(ConfigFile, Vec<String>), String> {
+        let mut value: toml::Value =
             toml::from_str(content).map_err(|e| format!("Invalid toml: {}", e))?;
-        let legacy_config: LegacyMstxConfigFile = toml::from_str(content).unwrap();
+        if let Some(profile) = profile {
+            let mut merged = profile.settings();
+            merge_toml(&mut merged, value);
+            value = merged;
+        }
+        let mut warnings = vec![];
+
+        let mut legacy_config = LegacyMstxConfigFile::default();
+        if let Some(table) = value.as_table_mut() {
+            for (deprecated, replacement) in DEPRECATED_CONFIG_KEYS.iter() {
+                if table.contains_key(*deprecated) {
+                    warnings.push(format!(
+                        "'{}' inside toml config is deprecated, replace with '{}'",
+                        deprecated, replacement
+                    ));
+                }
+            }
+            if let Some(mstx_balance) = table.remove("mstx_balance") {
+                let mut legacy_table = toml::value::Table::new();
+                legacy_table.insert("mstx_balance".to_string(), mstx_balance);
+                legacy_config = toml::Value::Table(legacy_table)
+                    .try_into()
+                    .map_err(|e| format!("Invalid toml: {}", e))?;
+            }
+        }
+
+        // every config struct denies unknown fields, so drop them one at a time until the
+        // config parses
+        let mut config: ConfigFile = loop {
+            let unknown_key = match ConfigFile::deserialize(ConfigValue::new(value.clone())) {
+                Ok(config) => break config,
+                Err(ConfigValueError::UnknownKey(unknown_key)) => unknown_key,
+                Err(e) => return Err(format!("Invalid toml: {}", e)),
+            };
+            if !unknown_key.remove_from(&mut value) {
+                return Err(format!("Invalid toml: {}", unknown_key));
+            }
+            warnings.push(unknown_key.to_string());
+        };
+
         if let Some(mstx_balance) = legacy_config.mstx_balance {
-            warn!("'mstx_balance' inside toml config is deprecated, replace with 'ustx_balance'");
             config.ustx_balance = match config.ustx_balance {
                 Some(balance) => Some([balance, mstx_balance].concat()),
                 None => Some(mstx_balance),
             };
         }
-        Ok(config)
+        Ok((config, warnings))
+    }
+
+    /// Settings that parse, but do not work together.
+    pub fn check_consistency(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        let node = self.node.clone().unwrap_or_default();
+        let burnchain = self.burnchain.clone().unwrap_or_default();
+        // mock miners never send burnchain operations
+        if node.miner == Some(true) && node.mock_mining != Some(true) {
+            if node.seed.is_none() {
+                warnings.push(
+                    "node.miner is enabled, but node.seed is not set: the miner will use a random key that is lost on restart".to_string(),
+                );
+            }
+            let mode = burnchain.mode.as_deref().unwrap_or("mocknet");
+            if mode != "mocknet" && (burnchain.username.is_none() || burnchain.password.is_none()) {
+                warnings.push(
+                    "node.miner is enabled, but burnchain.username or burnchain.password is not set: the miner cannot authenticate to bitcoind's RPC".to_string(),
+                );
+            }
+        }
+        if node.microblock_seed.is_some() && node.miner != Some(true) {
+            warnings.push(
+                "node.microblock_seed has no effect unless node.miner is enabled".to_string(),
+            );
+        }
+        if node.mock_mining == Some(true) && node.miner != Some(true) {
+            warnings
+                .push("node.mock_mining has no effect unless node.miner is enabled".to_string());
+        }
+        let address_family = self
+            .connection_options
+            .as_ref()
+            .and_then(|opts| opts.address_family.as_ref())
+            .and_then(|family| family.parse::<AddressFamily>().ok());
+        let p2p_address = node
+            .p2p_address
+            .as_ref()
+            .and_then(|addr| addr.parse::<SocketAddr>().ok())
+            .map(|addr| PeerAddress::from_socketaddr(&addr));
+        if let (Some(family), Some(p2p_address)) = (address_family, p2p_address) {
+            if !family.allows(&p2p_address) {
+                warnings.push(
+                    "node.p2p_address is not in the address family that connection_options.address_family allows: peers will not be able to reach this node".to_string(),
+                );
+            }
+        }
+        warnings
     }
 
     pub fn xenon() -> ConfigFile {
@@ -358,6 +2325,12 @@ pub struct Config {
     pub connection_options: ConnectionOptions,
     pub miner: MinerConfig,
     pub estimation: FeeEstimationConfig,
+    pub mocknet: MocknetConfig,
+    pub mempool: MempoolConfig,
+    /// Where the Atlas DB keeps attachment contents
+    pub attachment_store: AttachmentStoreConfig,
+    /// Connection settings for each kind of sqlite DB
+    pub sqlite: Vec<(SqliteDBKind, SqliteOpts)>,
 }
 
 lazy_static! {
@@ -391,7 +2364,405 @@ lazy_static! {
     };
 }
 
+/// A section of the config that can be diffed against a newer copy of itself, for applying
+/// configuration changes while the node runs. Derived with `#[derive(DynConfigDiff)]`, where
+/// `#[dyn_config(reloadable)]` marks the fields that can change at runtime, `#[dyn_config(secret)]`
+/// keeps a field's values out of the report, and `#[dyn_config(skip)]` leaves a field out.
+pub trait DynConfigDiff {
+    /// The fields that can be changed while the node runs.
+    fn reloadable_fields() -> &'static [&'static str];
+
+    /// Records each field that differs in `new` into `report`, and takes the new values of the
+    /// reloadable ones.
+    fn apply_diff(&mut self, section: &str, new: &Self, report: &mut ConfigDiffReport);
+
+    /// Sets the reloadable `field` to `value`, returning whether its value changed.
+    fn set_reloadable_field(
+        &mut self,
+        section: &str,
+        field: &str,
+        value: &serde_json::Value,
+    ) -> Result<bool, String>;
+}
+
+/// A config key whose value differs between two configs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigFieldDiff {
+    /// `section.field`
+    pub key: String,
+    pub old_value: String,
+    pub new_value: String,
+}
+
+/// The outcome of diffing a running config against a new one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigDiffReport {
+    /// Changes that were applied.
+    pub applied: Vec<ConfigFieldDiff>,
+    /// Changes to keys that cannot be changed at runtime, and were left alone.
+    pub rejected: Vec<ConfigFieldDiff>,
+}
+
+impl ConfigDiffReport {
+    pub fn record<T: fmt::Debug>(
+        &mut self,
+        section: &str,
+        field: &str,
+        old_value: &T,
+        new_value: &T,
+        secret: bool,
+        applied: bool,
+    ) {
+        let (old_value, new_value) = if secret {
+            ("<redacted>".to_string(), "<redacted>".to_string())
+        } else {
+            (format!("{:?}", old_value), format!("{:?}", new_value))
+        };
+        let diff = ConfigFieldDiff {
+            key: format!("{}.{}", section, field),
+            old_value,
+            new_value,
+        };
+        if applied {
+            self.applied.push(diff);
+        } else {
+            self.rejected.push(diff);
+        }
+    }
+
+    pub fn applied_keys(&self) -> Vec<String> {
+        self.applied.iter().map(|diff| diff.key.clone()).collect()
+    }
+}
+
 impl Config {
+    /// The config keys that can be changed while the node runs, as `section.field`. A change is
+    /// made to the relayer's copy of the config, which it also hands to its bitcoin controller:
+    /// the `burnchain` keys are read whenever a block commit is sent or replaced, and the `miner`
+    /// and `node` keys whenever the relayer runs a tenure or mines a microblock.
+    /// `mempool.min_tx_fee_rate` is passed on to the mempool and the p2p network when it changes.
+    pub fn hot_reloadable_keys() -> Vec<String> {
+        let sections: [(&str, &[&str]); 4] = [
+            ("burnchain", BurnchainConfig::reloadable_fields()),
+            ("miner", MinerConfig::reloadable_fields()),
+            ("mempool", MempoolConfig::reloadable_fields()),
+            ("node", NodeConfig::reloadable_fields()),
+        ];
+        sections
+            .iter()
+            .flat_map(|(section, fields)| {
+                fields
+                    .iter()
+                    .map(move |field| format!("{}.{}", section, field))
+            })
+            .collect()
+    }
+
+    /// Sets the hot-reloadable `section.field` to `value`, returning whether its value changed.
+    fn set_hot_reloadable_key(
+        &mut self,
+        section: &str,
+        field: &str,
+        value: &serde_json::Value,
+    ) -> Result<bool, String> {
+        match section {
+            "burnchain" => self.burnchain.set_reloadable_field(section, field, value),
+            "miner" => self.miner.set_reloadable_field(section, field, value),
+            "mempool" => self.mempool.set_reloadable_field(section, field, value),
+            "node" => self.node.set_reloadable_field(section, field, value),
+            _ => Err(format!(
+                "Config key '{}.{}' cannot be changed at runtime",
+                section, field
+            )),
+        }
+    }
+
+    /// Compares this config with `new`, takes the new values of the hot-reloadable keys, and
+    /// reports every key that differs. Changes to other keys are logged and left alone, since
+    /// they only take effect on restart.
+    pub fn apply_config_diff(&mut self, new: &Config) -> ConfigDiffReport {
+        let mut report = ConfigDiffReport::default();
+        self.burnchain
+            .apply_diff("burnchain", &new.burnchain, &mut report);
+        self.miner.apply_diff("miner", &new.miner, &mut report);
+        self.mempool
+            .apply_diff("mempool", &new.mempool, &mut report);
+        self.node.apply_diff("node", &new.node, &mut report);
+        self.connection_options.min_tx_fee_rate = self.mempool.min_tx_fee_rate;
+
+        for diff in report.rejected.iter() {
+            warn!(
+                "Config key '{}' cannot be changed at runtime; restart the node to change it from {} to {}",
+                diff.key, diff.old_value, diff.new_value
+            );
+        }
+        report
+    }
+
+    /// Applies a JSON patch of hot-reloadable keys, such as
+    /// `{"burnchain": {"satoshis_per_byte": 80}}`, and returns the keys whose value changed.
+    /// Nothing is applied if the patch names a key that is not in `Config::hot_reloadable_keys()`
+    /// or has an invalid value.
+    pub fn apply_dynamic_patch(
+        &mut self,
+        patch: &serde_json::Value,
+    ) -> Result<Vec<String>, String> {
+        let sections = patch
+            .as_object()
+            .ok_or("Config patch must be a JSON object".to_string())?;
+
+        let reloadable = Config::hot_reloadable_keys();
+        let mut entries = vec![];
+        let mut rejected = vec![];
+        for (section, fields) in sections.iter() {
+            match fields.as_object() {
+                Some(fields) => {
+                    for (field, value) in fields.iter() {
+                        let key = format!("{}.{}", section, field);
+                        if reloadable.contains(&key) {
+                            entries.push((section, field, value));
+                        } else {
+                            rejected.push(key);
+                        }
+                    }
+                }
+                None => rejected.push(section.clone()),
+            }
+        }
+        if rejected.len() > 0 {
+            return Err(format!(
+                "Config keys cannot be changed at runtime: {}",
+                rejected.join(", ")
+            ));
+        }
+
+        let mut patched = self.clone();
+        for (section, field, value) in entries.into_iter() {
+            patched.set_hot_reloadable_key(section, field, value)?;
+        }
+        if !patched.mempool.min_tx_fee_rate.is_finite() || patched.mempool.min_tx_fee_rate < 0f64 {
+            return Err(format!(
+                "Invalid mempool.min_tx_fee_rate {}: must be a non-negative number",
+                patched.mempool.min_tx_fee_rate
+            ));
+        }
+
+        let report = self.apply_config_diff(&patched);
+        Ok(report.applied_keys())
+    }
+
+    /// Tries to connect to each event observer, returning a warning for each one that cannot be
+    /// reached.
+    pub fn check_event_observers(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        for observer in self.events_observers.iter() {
+            if let Some(path) = unix_socket_path(&observer.endpoint) {
+                if let Err(e) = Self::check_unix_socket(path) {
+                    warnings.push(format!(
+                        "Event observer endpoint {} is unreachable: {}",
+                        observer.endpoint, e
+                    ));
+                }
+                continue;
+            }
+            let addrs = match observer.endpoint.to_socket_addrs() {
+                Ok(addrs) => addrs.collect::<Vec<_>>(),
+                Err(e) => {
+                    warnings.push(format!(
+                        "Event observer endpoint {} cannot be resolved: {}",
+                        observer.endpoint, e
+                    ));
+                    continue;
+                }
+            };
+            let timeout = observer.connect_timeout();
+            let mut last_error = None;
+            let reachable =
+                addrs
+                    .iter()
+                    .any(|addr| match TcpStream::connect_timeout(addr, timeout) {
+                        Ok(_) => true,
+                        Err(e) => {
+                            last_error = Some(e);
+                            false
+                        }
+                    });
+            if !reachable {
+                warnings.push(format!(
+                    "Event observer endpoint {} is unreachable: {}",
+                    observer.endpoint,
+                    last_error
+                        .map(|e| e.to_string())
+                        .unwrap_or("no addresses".to_string())
+                ));
+            }
+        }
+        warnings
+    }
+
+    #[cfg(unix)]
+    fn check_unix_socket(path: &str) -> Result<(), String> {
+        std::os::unix::net::UnixStream::connect(path)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    #[cfg(not(unix))]
+    fn check_unix_socket(_path: &str) -> Result<(), String> {
+        Err("Unix domain sockets are not supported on this platform".to_string())
+    }
+
+    /// Checks that the genesis chainstate file at `path` can be booted from, and returns its
+    /// SHA256.
+    pub fn hash_genesis_chainstate_file(path: &str) -> Result<Sha256Sum, String> {
+        GenesisData::from_chainstate_file(path)?;
+        let content = fs::read(path)
+            .map_err(|e| format!("Failed to read genesis chainstate file {}: {}", path, e))?;
+        Ok(Sha256Sum::from_data(&content))
+    }
+
+    /// The chain ID of a private chain booted from a genesis chainstate file with the given
+    /// SHA256: the first 4 bytes of the hash, with the testnet bit set.  Requiring this chain ID
+    /// means that nodes booted from different allocations never accept each other's
+    /// transactions or blocks.
+    pub fn get_genesis_chain_id(genesis_chainstate_hash: &Sha256Sum) -> u32 {
+        let mut chain_id_bytes = [0u8; 4];
+        chain_id_bytes.copy_from_slice(&genesis_chainstate_hash.as_bytes()[0..4]);
+        u32::from_be_bytes(chain_id_bytes) | CHAIN_ID_TESTNET
+    }
+
+    /// The SHA256 of the genesis chainstate this node boots from: the configured genesis
+    /// chainstate file (hashed when the config was loaded), or else the built-in one.
+    pub fn get_genesis_chainstate_hash(&self) -> Sha256Sum {
+        match self.node.genesis_chainstate_hash {
+            Some(ref hash) => hash.clone(),
+            None => Sha256Sum::from_hex(stx_genesis::GENESIS_CHAINSTATE_HASH)
+                .expect("FATAL: invalid built-in genesis chainstate hash"),
+        }
+    }
+
+    /// Builds the epoch list for a private network from the default epochs of its burnchain
+    /// mode, with the start heights of the epochs named in `activation_heights` moved to the
+    /// given burnchain heights. Each epoch ends where the next one starts.
+    pub fn make_epochs(
+        activation_heights: &BTreeMap<String, u64>,
+        burn_mode: &str,
+    ) -> Result<Vec<StacksEpoch>, String> {
+        let mut epochs = match burn_mode {
+            "mainnet" => {
+                return Err(format!(
+                    "burnchain.epochs cannot be overridden when running on mainnet"
+                ))
+            }
+            "xenon" => STACKS_EPOCHS_TESTNET.to_vec(),
+            _ => STACKS_EPOCHS_REGTEST.to_vec(),
+        };
+
+        for (epoch_name, start_height) in activation_heights.iter() {
+            let epoch = epochs
+                .iter_mut()
+                .find(|epoch| &epoch.epoch_id.to_string() == epoch_name)
+                .ok_or_else(|| format!("burnchain.epochs: unknown epoch '{}'", epoch_name))?;
+            if epoch.epoch_id <= StacksEpochId::Epoch20 {
+                return Err(format!(
+                    "burnchain.epochs: epoch {} starts with the chain and cannot be overridden",
+                    epoch_name
+                ));
+            }
+            epoch.start_height = *start_height;
+        }
+
+        for i in 1..epochs.len() {
+            if epochs[i].start_height < epochs[i - 1].start_height {
+                return Err(format!(
+                    "burnchain.epochs: epoch {} would start at height {}, before epoch {} at height {}",
+                    epochs[i].epoch_id,
+                    epochs[i].start_height,
+                    epochs[i - 1].epoch_id,
+                    epochs[i - 1].start_height
+                ));
+            }
+            epochs[i - 1].end_height = epochs[i].start_height;
+        }
+        if let Some(last_epoch) = epochs.last_mut() {
+            last_epoch.end_height = STACKS_EPOCH_MAX;
+        }
+
+        Ok(epochs)
+    }
+
+    /// Checks a full list of epoch definitions for a private network: the epochs must be listed
+    /// in order, with each one starting where the previous one ends, from height 0 up to
+    /// `STACKS_EPOCH_MAX`.  Epoch 2.0 must start at the burnchain's first block height, and the
+    /// block limits from epoch 2.0 on must allow some of each kind of cost.
+    pub fn validate_epochs(epochs: &[StacksEpoch], first_burn_height: u64) -> Result<(), String> {
+        let first_epoch = epochs
+            .first()
+            .ok_or_else(|| format!("burnchain.epochs: no epochs are defined"))?;
+        if first_epoch.start_height != 0 {
+            return Err(format!(
+                "burnchain.epochs: the first epoch ({}) must start at height 0, not {}",
+                first_epoch.epoch_id, first_epoch.start_height
+            ));
+        }
+        for pair in epochs.windows(2) {
+            if pair[1].epoch_id <= pair[0].epoch_id {
+                return Err(format!(
+                    "burnchain.epochs: epoch {} is listed after epoch {}",
+                    pair[1].epoch_id, pair[0].epoch_id
+                ));
+            }
+        }
+        for (i, epoch) in epochs.iter().enumerate() {
+            if epoch.start_height > epoch.end_height {
+                return Err(format!(
+                    "burnchain.epochs: epoch {} ends at height {}, before it starts at height {}",
+                    epoch.epoch_id, epoch.end_height, epoch.start_height
+                ));
+            }
+            if i > 0 {
+                let prev_epoch = &epochs[i - 1];
+                if epoch.start_height != prev_epoch.end_height {
+                    return Err(format!(
+                        "burnchain.epochs: epoch {} starts at height {}, but epoch {} ends at height {}",
+                        epoch.epoch_id, epoch.start_height, prev_epoch.epoch_id, prev_epoch.end_height
+                    ));
+                }
+            }
+            let limit = &epoch.block_limit;
+            if epoch.epoch_id >= StacksEpochId::Epoch20
+                && (limit.write_length == 0
+                    || limit.write_count == 0
+                    || limit.read_length == 0
+                    || limit.read_count == 0
+                    || limit.runtime == 0)
+            {
+                return Err(format!(
+                    "burnchain.epochs: epoch {} has a zero block limit, which no block can meet",
+                    epoch.epoch_id
+                ));
+            }
+        }
+        let last_epoch = &epochs[epochs.len() - 1];
+        if last_epoch.end_height != STACKS_EPOCH_MAX {
+            return Err(format!(
+                "burnchain.epochs: the last epoch ({}) must end at height {}, not {}",
+                last_epoch.epoch_id, STACKS_EPOCH_MAX, last_epoch.end_height
+            ));
+        }
+        match epochs
+            .iter()
+            .find(|epoch| epoch.epoch_id == StacksEpochId::Epoch20)
+        {
+            Some(epoch) if epoch.start_height != first_burn_height => Err(format!(
+                "burnchain.epochs: epoch 2.0 must start at the first burnchain block height {}, not {}",
+                first_burn_height, epoch.start_height
+            )),
+            Some(_) => Ok(()),
+            None => Err(format!("burnchain.epochs: epoch 2.0 is not defined")),
+        }
+    }
+
     pub fn from_config_file(config_file: ConfigFile) -> Result<Config, String> {
         let default_node_config = NodeConfig::default();
         let (mut node, bootstrap_node, deny_nodes) = match config_file.node {
@@ -400,8 +2771,7 @@ impl Config {
                 let node_config = NodeConfig {
                     name: node.name.unwrap_or(default_node_config.name),
                     seed: match node.seed {
-                        Some(seed) => hex_bytes(&seed)
-                            .map_err(|e| format!("node.seed should be a hex encoded string"))?,
+                        Some(seed) => seed_encryption::decode_seed("node.seed", &seed)?,
                         None => default_node_config.seed,
                     },
                     working_dir: node.working_dir.unwrap_or(default_node_config.working_dir),
@@ -409,17 +2779,22 @@ impl Config {
                     p2p_bind: node.p2p_bind.unwrap_or(default_node_config.p2p_bind),
                     p2p_address: node.p2p_address.unwrap_or(rpc_bind.clone()),
                     bootstrap_node: vec![],
+                    dns_seeds: vec![],
                     deny_nodes: vec![],
                     data_url: match node.data_url {
                         Some(data_url) => data_url,
                         None => format!("http://{}", rpc_bind),
                     },
                     local_peer_seed: match node.local_peer_seed {
-                        Some(seed) => hex_bytes(&seed).map_err(|e| {
-                            format!("node.local_peer_seed should be a hex encoded string")
-                        })?,
+                        Some(seed) => seed_encryption::decode_seed("node.local_peer_seed", &seed)?,
                         None => default_node_config.local_peer_seed,
                     },
+                    microblock_seed: match node.microblock_seed {
+                        Some(seed) => {
+                            Some(seed_encryption::decode_seed("node.microblock_seed", &seed)?)
+                        }
+                        None => default_node_config.microblock_seed,
+                    },
                     miner: node.miner.unwrap_or(default_node_config.miner),
                     mock_mining: node.mock_mining.unwrap_or(default_node_config.mock_mining),
                     mine_microblocks: node
@@ -435,14 +2810,63 @@ impl Config {
                         .wait_time_for_microblocks
                         .unwrap_or(default_node_config.wait_time_for_microblocks),
                     prometheus_bind: node.prometheus_bind,
+                    grpc_bind: node.grpc_bind,
+                    admin_bind: node.admin_bind,
+                    admin_auth_token: node.admin_auth_token,
+                    admin_allow_remote: node
+                        .admin_allow_remote
+                        .unwrap_or(default_node_config.admin_allow_remote),
+                    profiling: node.profiling.unwrap_or(default_node_config.profiling),
+                    supervisor_notify: node
+                        .supervisor_notify
+                        .unwrap_or(default_node_config.supervisor_notify),
                     marf_cache_strategy: node.marf_cache_strategy,
                     marf_defer_hashing: node
                         .marf_defer_hashing
                         .unwrap_or(default_node_config.marf_defer_hashing),
+                    marf_pinned_tries: node
+                        .marf_pinned_tries
+                        .unwrap_or(default_node_config.marf_pinned_tries),
+                    marf_blobs_sync_mode: match node.marf_blobs_sync_mode {
+                        Some(ref mode) => mode.parse()?,
+                        None => default_node_config.marf_blobs_sync_mode,
+                    },
+                    marf_blob_migration_batch: node
+                        .marf_blob_migration_batch
+                        .unwrap_or(default_node_config.marf_blob_migration_batch),
+                    marf_blobs_page_cache_size: node
+                        .marf_blobs_page_cache_size
+                        .unwrap_or(default_node_config.marf_blobs_page_cache_size),
+                    marf_write_buffering: node
+                        .marf_write_buffering
+                        .unwrap_or(default_node_config.marf_write_buffering),
                     pox_sync_sample_secs: node
                         .pox_sync_sample_secs
                         .unwrap_or(default_node_config.pox_sync_sample_secs),
+                    sync_progress_log_interval_secs: node
+                        .sync_progress_log_interval_secs
+                        .unwrap_or(default_node_config.sync_progress_log_interval_secs),
                     use_test_genesis_chainstate: node.use_test_genesis_chainstate,
+                    genesis_chainstate_path: node.genesis_chainstate_path,
+                    genesis_chainstate_hash: None,
+                    clarity_coverage: node
+                        .clarity_coverage
+                        .unwrap_or(default_node_config.clarity_coverage),
+                    clarity_coverage_dir: node.clarity_coverage_dir,
+                    consistency_hash_interval: node
+                        .consistency_hash_interval
+                        .unwrap_or(default_node_config.consistency_hash_interval),
+                    backup: node.backup.map(BackupConfig::from),
+                    replica: match node.replica {
+                        Some(replica) => Some(ReplicaConfig::from_config_file(replica)?),
+                        None => None,
+                    },
+                    stall_watchdog: match node.stall_watchdog {
+                        Some(stall_watchdog) => {
+                            Some(StallWatchdogConfig::from_config_file(stall_watchdog)?)
+                        }
+                        None => None,
+                    },
                 };
                 (node_config, node.bootstrap_node, node.deny_nodes)
             }
@@ -487,12 +2911,44 @@ impl Config {
                     }
                 }
 
+                let epochs = match burnchain.epochs {
+                    Some(EpochsConfigFile::ActivationHeights(ref activation_heights)) => {
+                        Some(Config::make_epochs(activation_heights, &burnchain_mode)?)
+                    }
+                    Some(EpochsConfigFile::Epochs(ref epochs)) => {
+                        if &burnchain_mode == "mainnet" {
+                            return Err(format!(
+                                "burnchain.epochs cannot be overridden when running on mainnet"
+                            ));
+                        }
+                        Some(epochs.clone())
+                    }
+                    None => default_burnchain_config.epochs,
+                };
+                if let Some(ref epochs) = epochs {
+                    let first_burn_height = if &burnchain_mode == "xenon" {
+                        BITCOIN_TESTNET_FIRST_BLOCK_HEIGHT
+                    } else {
+                        BITCOIN_REGTEST_FIRST_BLOCK_HEIGHT
+                    };
+                    Config::validate_epochs(epochs, first_burn_height)?;
+                }
+
                 BurnchainConfig {
                     chain: burnchain.chain.unwrap_or(default_burnchain_config.chain),
-                    chain_id: if &burnchain_mode == "mainnet" {
-                        CHAIN_ID_MAINNET
-                    } else {
-                        CHAIN_ID_TESTNET
+                    chain_id: match burnchain.chain_id {
+                        Some(chain_id) if &burnchain_mode == "mainnet" => {
+                            if chain_id != CHAIN_ID_MAINNET {
+                                return Err(format!(
+                                    "Attempted to run mainnet node with chain ID {}",
+                                    chain_id
+                                ));
+                            }
+                            chain_id
+                        }
+                        Some(chain_id) => chain_id,
+                        None if &burnchain_mode == "mainnet" => CHAIN_ID_MAINNET,
+                        None => CHAIN_ID_TESTNET,
                     },
                     peer_version: if &burnchain_mode == "mainnet" {
                         PEER_VERSION_MAINNET
@@ -569,16 +3025,62 @@ impl Config {
                     rbf_fee_increment: burnchain
                         .rbf_fee_increment
                         .unwrap_or(default_burnchain_config.rbf_fee_increment),
-                    epochs: match burnchain.epochs {
-                        Some(epochs) => Some(epochs),
-                        None => default_burnchain_config.epochs,
-                    },
+                    auto_rbf: burnchain
+                        .auto_rbf
+                        .unwrap_or(default_burnchain_config.auto_rbf),
+                    auto_rbf_budget: burnchain
+                        .auto_rbf_budget
+                        .unwrap_or(default_burnchain_config.auto_rbf_budget),
+                    epochs,
                     ast_precheck_size_height: burnchain.ast_precheck_size_height,
+                    utxo_scan_start_height: burnchain.utxo_scan_start_height,
+                    deep_reorg_depth: burnchain
+                        .deep_reorg_depth
+                        .unwrap_or(default_burnchain_config.deep_reorg_depth),
+                    p2p_block_notifications: burnchain
+                        .p2p_block_notifications
+                        .unwrap_or(default_burnchain_config.p2p_block_notifications),
+                    download_workers: burnchain
+                        .download_workers
+                        .unwrap_or(default_burnchain_config.download_workers),
+                    parse_workers: burnchain
+                        .parse_workers
+                        .unwrap_or(default_burnchain_config.parse_workers),
                 }
             }
             None => default_burnchain_config,
         };
 
+        if let Some(ref grpc_bind) = node.grpc_bind {
+            grpc_bind
+                .parse::<SocketAddr>()
+                .map_err(|e| format!("Invalid node.grpc_bind '{}': {}", grpc_bind, e))?;
+        }
+
+        if let Some(genesis_chainstate_path) = node.genesis_chainstate_path.clone() {
+            if burnchain.mode == "mainnet" {
+                return Err(format!(
+                    "Attempted to run mainnet node with `genesis_chainstate_path`"
+                ));
+            }
+            if node.use_test_genesis_chainstate == Some(true) {
+                return Err(format!(
+                    "`genesis_chainstate_path` and `use_test_genesis_chainstate` cannot both be set"
+                ));
+            }
+            // fail now, rather than at boot, if the file is not usable
+            let genesis_chainstate_hash =
+                Config::hash_genesis_chainstate_file(&genesis_chainstate_path)?;
+            let genesis_chain_id = Config::get_genesis_chain_id(&genesis_chainstate_hash);
+            if burnchain.chain_id != genesis_chain_id {
+                return Err(format!(
+                    "A node booted from genesis chainstate file {} must set burnchain.chain_id = {} (derived from the file's SHA256 {}), not {}",
+                    genesis_chainstate_path, genesis_chain_id, &genesis_chainstate_hash, burnchain.chain_id
+                ));
+            }
+            node.genesis_chainstate_hash = Some(genesis_chainstate_hash);
+        }
+
         let miner_default_config = MinerConfig::default();
         let miner = match config_file.miner {
             Some(ref miner) => MinerConfig {
@@ -595,18 +3097,114 @@ impl Config {
                 probability_pick_no_estimate_tx: miner
                     .probability_pick_no_estimate_tx
                     .unwrap_or(miner_default_config.probability_pick_no_estimate_tx),
+                adaptive_microblock_confirmation: miner
+                    .adaptive_microblock_confirmation
+                    .unwrap_or(miner_default_config.adaptive_microblock_confirmation),
+                microblock_ack_neighbors: miner
+                    .microblock_ack_neighbors
+                    .unwrap_or(miner_default_config.microblock_ack_neighbors),
+                max_microblock_confirmation_delay_ms: miner
+                    .max_microblock_confirmation_delay_ms
+                    .unwrap_or(miner_default_config.max_microblock_confirmation_delay_ms),
+                tenure_report_dir: miner.tenure_report_dir.clone(),
+                microblock_stream_budget_pct: miner
+                    .microblock_stream_budget_pct
+                    .unwrap_or(miner_default_config.microblock_stream_budget_pct),
+                microblock_stream_time_ms: miner
+                    .microblock_stream_time_ms
+                    .unwrap_or(miner_default_config.microblock_stream_time_ms),
+                standby: miner.standby.unwrap_or(miner_default_config.standby),
+                standby_takeover_burn_blocks: miner
+                    .standby_takeover_burn_blocks
+                    .unwrap_or(miner_default_config.standby_takeover_burn_blocks),
+                recommit_on_new_parent: miner
+                    .recommit_on_new_parent
+                    .unwrap_or(miner_default_config.recommit_on_new_parent),
+                recommit_deadline_ms: miner
+                    .recommit_deadline_ms
+                    .unwrap_or(miner_default_config.recommit_deadline_ms),
+                check_next_epoch_ast_rules: miner
+                    .check_next_epoch_ast_rules
+                    .unwrap_or(miner_default_config.check_next_epoch_ast_rules),
             },
             None => miner_default_config,
         };
 
-        let supported_modes = vec![
-            "mocknet", "helium", "neon", "argon", "krypton", "xenon", "mainnet",
-        ];
+        let mocknet = match config_file.mocknet {
+            Some(mocknet) => MocknetConfig {
+                block_time_ms: mocknet.block_time_ms,
+                mine_on_transaction: mocknet.mine_on_transaction.unwrap_or(false),
+            },
+            None => MocknetConfig::default(),
+        };
+
+        let mempool = match config_file.mempool {
+            Some(mempool) => MempoolConfig {
+                min_tx_fee_rate: mempool.min_tx_fee_rate.unwrap_or(0f64),
+            },
+            None => MempoolConfig::default(),
+        };
+        if !mempool.min_tx_fee_rate.is_finite() || mempool.min_tx_fee_rate < 0f64 {
+            return Err(format!(
+                "Invalid mempool.min_tx_fee_rate {}: must be a non-negative number",
+                mempool.min_tx_fee_rate
+            ));
+        }
+
+        let supported_modes = vec![
+            "mocknet", "helium", "neon", "argon", "krypton", "xenon", "mainnet",
+        ];
+
+        if !supported_modes.contains(&burnchain.mode.as_str()) {
+            return Err(format!(
+                "Setting burnchain.network not supported (should be: {})",
+                supported_modes.join(", ")
+            ));
+        }
+
+        if miner.microblock_stream_budget_pct == 0 || miner.microblock_stream_budget_pct > 100 {
+            return Err(format!(
+                "`miner.microblock_stream_budget_pct` must be between 1 and 100 (got {})",
+                miner.microblock_stream_budget_pct
+            ));
+        }
+
+        if miner.standby_takeover_burn_blocks == 0 {
+            return Err(format!(
+                "`miner.standby_takeover_burn_blocks` must be at least 1"
+            ));
+        }
 
-        if !supported_modes.contains(&burnchain.mode.as_str()) {
+        if burnchain.deep_reorg_depth == 0 {
+            return Err(format!("`burnchain.deep_reorg_depth` must be at least 1"));
+        }
+
+        if burnchain.download_workers == 0 {
+            return Err(format!("`burnchain.download_workers` must be at least 1"));
+        }
+
+        if burnchain.parse_workers == 0 {
+            return Err(format!("`burnchain.parse_workers` must be at least 1"));
+        }
+
+        if node.replica.is_some() && node.miner {
             return Err(format!(
-                "Setting burnchain.network not supported (should be: {})",
-                supported_modes.join(", ")
+                "`node.replica` cannot be set on a miner: a read replica does not process blocks"
+            ));
+        }
+
+        if let Some(backup) = node.backup.as_ref() {
+            if backup.interval == 0 {
+                return Err(format!("`node.backup.interval` must be at least 1"));
+            }
+            if backup.keep == 0 {
+                return Err(format!("`node.backup.keep` must be at least 1"));
+            }
+        }
+
+        if node.clarity_coverage && burnchain.mode != "mocknet" && burnchain.mode != "helium" {
+            return Err(format!(
+                "`node.clarity_coverage` is only supported in mocknet and helium modes"
             ));
         }
 
@@ -659,9 +3257,53 @@ impl Config {
 
                     let endpoint = format!("{}", observer.endpoint);
 
+                    let sink = match observer.sink.as_deref() {
+                        None | Some("http") => EventObserverSink::Http,
+                        Some("nats") if unix_socket_path(&endpoint).is_some() => {
+                            return Err(format!(
+                                "The NATS events_observer sink cannot publish to {}",
+                                &endpoint
+                            ))
+                        }
+                        Some("nats") => EventObserverSink::Nats {
+                            subject_prefix: observer
+                                .nats_subject_prefix
+                                .clone()
+                                .unwrap_or(DEFAULT_NATS_SUBJECT_PREFIX.to_string()),
+                            jetstream: observer.nats_jetstream.unwrap_or(false),
+                        },
+                        Some(sink) => {
+                            return Err(format!(
+                                "Invalid events_observer sink '{}' for {}; expected \"http\" or \"nats\"",
+                                sink, &endpoint
+                            ))
+                        }
+                    };
+
+                    let http2 = observer.http2.unwrap_or(false);
+                    if http2 {
+                        if sink != EventObserverSink::Http || unix_socket_path(&endpoint).is_some()
+                        {
+                            return Err(format!(
+                                "events_observer http2 is only supported for HTTP observers over TCP, not {}",
+                                &endpoint
+                            ));
+                        }
+                        if observer.keep_alive == Some(false) {
+                            return Err(format!(
+                                "events_observer http2 keeps a single connection to {}, so it cannot be combined with keep_alive = false",
+                                &endpoint
+                            ));
+                        }
+                    }
+
                     observers.push(EventObserverConfig {
                         endpoint,
                         events_keys,
+                        timeout_ms: observer.timeout_ms,
+                        keep_alive: observer.keep_alive.unwrap_or(true),
+                        http2,
+                        sink,
                     });
                 }
                 observers
@@ -674,11 +3316,12 @@ impl Config {
             Ok(val) => events_observers.push(EventObserverConfig {
                 endpoint: val,
                 events_keys: vec![EventKeyType::AnyEvent],
+                ..EventObserverConfig::default()
             }),
             _ => (),
         };
 
-        let connection_options = match config_file.connection_options {
+        let mut connection_options = match config_file.connection_options {
             Some(opts) => {
                 let ip_addr = match opts.public_ip_address {
                     Some(public_ip_address) => {
@@ -706,6 +3349,52 @@ impl Config {
                 opts.read_only_call_limit_runtime.map(|x| {
                     read_only_call_limit.runtime = x;
                 });
+                let address_family = match opts.address_family {
+                    Some(ref family) => family.parse::<AddressFamily>()?,
+                    None => HELIUM_DEFAULT_CONNECTION_OPTIONS.address_family,
+                };
+                let socks_proxy = match opts.socks_proxy {
+                    Some(ref proxy) => Some(proxy.parse::<SocketAddr>().map_err(|e| {
+                        format!("Invalid connection_options.socks_proxy '{}': {}", proxy, e)
+                    })?),
+                    None => None,
+                };
+                if opts.onion_address.is_some() && socks_proxy.is_none() {
+                    return Err(
+                        "connection_options.onion_address requires connection_options.socks_proxy"
+                            .to_string(),
+                    );
+                }
+                let mempool_admission_simulation = match opts.mempool_admission_simulation {
+                    Some(ref mode) => mode.parse::<MemPoolAdmissionSimulation>()?,
+                    None => HELIUM_DEFAULT_CONNECTION_OPTIONS.mempool_admission_simulation,
+                };
+                let rpc_default_access = match opts.rpc_default_access {
+                    Some(ref access) => access.parse::<RPCAccess>()?,
+                    None => HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_default_access,
+                };
+                let mut rpc_route_access =
+                    HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_route_access.clone();
+                for (route, access) in opts.rpc_route_access.iter().flatten() {
+                    if !HttpRequestType::is_path_template(route) {
+                        return Err(format!(
+                            "Unknown RPC route '{}' in connection_options.rpc_route_access: expected a path template such as '/v2/accounts/:principal'",
+                            route
+                        ));
+                    }
+                    rpc_route_access.insert(route.clone(), access.parse::<RPCAccess>()?);
+                }
+                let mut rpc_request_costs =
+                    HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_request_costs.clone();
+                for (route, cost) in opts.rpc_request_costs.iter().flatten() {
+                    if !HttpRequestType::is_path_template(route) {
+                        return Err(format!(
+                            "Unknown RPC route '{}' in connection_options.rpc_request_costs: expected a path template such as '/v2/accounts/:principal'",
+                            route
+                        ));
+                    }
+                    rpc_request_costs.insert(route.clone(), *cost);
+                }
                 ConnectionOptions {
                     read_only_call_limit,
                     inbox_maxlen: opts
@@ -777,11 +3466,25 @@ impl Config {
                     dns_timeout: opts.dns_timeout.unwrap_or_else(|| {
                         HELIUM_DEFAULT_CONNECTION_OPTIONS.dns_timeout.clone() as u64
                     }) as u128,
+                    dns_seed_refresh_interval: opts.dns_seed_refresh_interval.unwrap_or_else(
+                        || HELIUM_DEFAULT_CONNECTION_OPTIONS.dns_seed_refresh_interval,
+                    ),
                     max_inflight_blocks: opts.max_inflight_blocks.unwrap_or_else(|| {
                         HELIUM_DEFAULT_CONNECTION_OPTIONS
                             .max_inflight_blocks
                             .clone()
                     }),
+                    download_hedge_delay_ms: opts.download_hedge_delay_ms.unwrap_or_else(|| {
+                        HELIUM_DEFAULT_CONNECTION_OPTIONS.download_hedge_delay_ms
+                    }),
+                    peer_ban_score: opts
+                        .peer_ban_score
+                        .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.peer_ban_score),
+                    max_download_requests_per_block: opts
+                        .max_download_requests_per_block
+                        .unwrap_or_else(|| {
+                            HELIUM_DEFAULT_CONNECTION_OPTIONS.max_download_requests_per_block
+                        }),
                     max_inflight_attachments: opts.max_inflight_attachments.unwrap_or_else(|| {
                         HELIUM_DEFAULT_CONNECTION_OPTIONS
                             .max_inflight_attachments
@@ -822,17 +3525,63 @@ impl Config {
                     handshake_timeout: opts.connect_timeout.unwrap_or(5),
                     max_sockets: opts.max_sockets.unwrap_or(800) as usize,
                     antientropy_public: opts.antientropy_public.unwrap_or(true),
+                    finalized_block_confirmations: opts
+                        .finalized_block_confirmations
+                        .unwrap_or_else(|| {
+                            HELIUM_DEFAULT_CONNECTION_OPTIONS
+                                .finalized_block_confirmations
+                                .clone()
+                        }),
+                    mempool_admission_simulation,
+                    rpc_api_keys: opts.rpc_api_keys.unwrap_or_default(),
+                    rpc_default_access,
+                    rpc_route_access,
+                    rpc_client_rate_limit: opts
+                        .rpc_client_rate_limit
+                        .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_client_rate_limit),
+                    rpc_client_burst: opts
+                        .rpc_client_burst
+                        .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_client_burst),
+                    rpc_route_rate_limit: opts
+                        .rpc_route_rate_limit
+                        .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_route_rate_limit),
+                    rpc_route_burst: opts
+                        .rpc_route_burst
+                        .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_route_burst),
+                    rpc_request_costs,
+                    address_family,
+                    socks_proxy,
+                    onion_address: opts.onion_address.clone(),
                     ..ConnectionOptions::default()
                 }
             }
             None => HELIUM_DEFAULT_CONNECTION_OPTIONS.clone(),
         };
 
+        if let Some(ref onion_address) = connection_options.onion_address {
+            // only ever advertise the onion address, so peers never learn our IP
+            let rpc_port = node
+                .rpc_bind
+                .parse::<SocketAddr>()
+                .map_err(|e| format!("Invalid node.rpc_bind '{}': {}", &node.rpc_bind, e))?
+                .port();
+            node.data_url = format!("http://{}:{}", onion_address, rpc_port);
+        }
+
+        connection_options.min_tx_fee_rate = mempool.min_tx_fee_rate;
+
         let estimation = match config_file.fee_estimation {
             Some(f) => FeeEstimationConfig::from(f),
             None => FeeEstimationConfig::default(),
         };
 
+        let attachment_store = config_file
+            .atlas
+            .unwrap_or_default()
+            .into_attachment_store(&node.working_dir, &burnchain.mode)?;
+
+        let sqlite = config_file.sqlite.unwrap_or_default().into_sqlite_opts()?;
+
         Ok(Config {
             node,
             burnchain,
@@ -841,6 +3590,10 @@ impl Config {
             connection_options,
             estimation,
             miner,
+            mocknet,
+            mempool,
+            attachment_store,
+            sqlite,
         })
     }
 
@@ -876,6 +3629,23 @@ impl Config {
             .to_string()
     }
 
+    /// Where periodic chainstate backups go: `node.backup.dir`, or `{working_dir}/{mode}/backups`.
+    pub fn get_backup_path(&self) -> PathBuf {
+        match self
+            .node
+            .backup
+            .as_ref()
+            .and_then(|backup| backup.dir.as_ref())
+        {
+            Some(dir) => PathBuf::from(dir),
+            None => {
+                let mut path = self.get_chainstate_path();
+                path.set_file_name("backups");
+                path
+            }
+        }
+    }
+
     pub fn get_burnchain_path_str(&self) -> String {
         self.get_burnchain_path()
             .to_str()
@@ -883,6 +3653,20 @@ impl Config {
             .to_string()
     }
 
+    /// Where the miner keeps its UTXOs when `burnchain.utxo_scan_start_height` is set.
+    pub fn get_utxo_tracker_path(&self) -> String {
+        let mut path = self.get_burnchain_path();
+        path.push("miner_utxos.sqlite");
+        path.to_str().expect("Unable to produce path").to_string()
+    }
+
+    /// Where the miner journals the block-commits and leader keys it sends.
+    pub fn get_commit_journal_path(&self) -> String {
+        let mut path = self.get_burnchain_path();
+        path.push("miner_commits.sqlite");
+        path.to_str().expect("Unable to produce path").to_string()
+    }
+
     pub fn get_burn_db_path(&self) -> String {
         self.get_burnchain_path()
             .to_str()
@@ -914,6 +3698,21 @@ impl Config {
         path.to_str().expect("Unable to produce path").to_string()
     }
 
+    /// The Atlas settings, with the configured attachment store.
+    /// Make every sqlite DB opened from now on use the configured connection settings
+    pub fn apply_sqlite_opts(&self) {
+        for (kind, opts) in self.sqlite.iter() {
+            set_sqlite_opts(*kind, opts.clone());
+        }
+    }
+
+    pub fn make_atlas_config(&self) -> AtlasConfig {
+        AtlasConfig {
+            attachment_store: self.attachment_store.clone(),
+            ..AtlasConfig::default(self.is_mainnet())
+        }
+    }
+
     pub fn add_initial_balance(&mut self, address: String, amount: u64) {
         let new_balance = InitialBalance {
             address: PrincipalData::parse_standard_principal(&address)
@@ -939,6 +3738,13 @@ impl Config {
         }
     }
 
+    /// How long a helium or mocknet tenure waits before assembling its block.
+    pub fn get_block_time_ms(&self) -> u64 {
+        self.mocknet
+            .block_time_ms
+            .unwrap_or(self.burnchain.commit_anchor_block_within)
+    }
+
     pub fn is_node_event_driven(&self) -> bool {
         self.events_observers.len() > 0
     }
@@ -971,6 +3777,13 @@ impl Config {
                 },
                 consider_no_estimate_tx_prob: self.miner.probability_pick_no_estimate_tx,
             },
+            tenure_report_dir: if microblocks {
+                None
+            } else {
+                self.miner.tenure_report_dir.as_ref().map(PathBuf::from)
+            },
+            microblock_stream_budget_pct: self.miner.microblock_stream_budget_pct,
+            check_next_epoch_ast_rules: self.miner.check_next_epoch_ast_rules,
         }
     }
 }
@@ -997,38 +3810,71 @@ impl std::default::Default for Config {
             connection_options,
             estimation,
             miner: MinerConfig::default(),
+            mocknet: MocknetConfig::default(),
+            mempool: MempoolConfig::default(),
+            attachment_store: AttachmentStoreConfig::Sqlite,
+            sqlite: vec![],
         }
     }
 }
 
-#[derive(Clone, Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize, DynConfigDiff)]
 pub struct BurnchainConfig {
     pub chain: String,
     pub mode: String,
     pub chain_id: u32,
     pub peer_version: u32,
     pub commit_anchor_block_within: u64,
+    #[dyn_config(reloadable)]
     pub burn_fee_cap: u64,
     pub peer_host: String,
     pub peer_port: u16,
     pub rpc_port: u16,
     pub rpc_ssl: bool,
+    #[dyn_config(secret)]
     pub username: Option<String>,
+    #[dyn_config(secret)]
     pub password: Option<String>,
     pub timeout: u32,
     pub magic_bytes: MagicBytes,
     pub local_mining_public_key: Option<String>,
     pub process_exit_at_block_height: Option<u64>,
     pub poll_time_secs: u64,
+    #[dyn_config(reloadable)]
     pub satoshis_per_byte: u64,
+    #[dyn_config(reloadable)]
     pub max_rbf: u64,
     pub leader_key_tx_estimated_size: u64,
     pub block_commit_tx_estimated_size: u64,
+    #[dyn_config(reloadable)]
     pub rbf_fee_increment: u64,
+    /// If set, the in-flight block commit is only replaced by fee when it is missing from
+    /// bitcoind's mempool or pays less than bitcoind's fee estimate for the next block, and
+    /// `max_rbf` is not used.
+    #[dyn_config(reloadable)]
+    pub auto_rbf: bool,
+    /// With `auto_rbf`, the most satoshis of transaction fees a block commit and its replacements
+    /// may spend.
+    #[dyn_config(reloadable)]
+    pub auto_rbf_budget: u64,
     /// Custom override for the definitions of the epochs. This will only be applied for testnet and
     /// regtest nodes.
     pub epochs: Option<Vec<StacksEpoch>>,
     pub ast_precheck_size_height: Option<u64>,
+    /// If set, the miner finds its UTXOs by scanning the blocks from this height on, instead of
+    /// through bitcoind's wallet. It must not be above the block that first funded the miner.
+    pub utxo_scan_start_height: Option<u64>,
+    /// Burnchain reorgs that orphan at least this many burn blocks are logged as errors and
+    /// counted as deep reorgs.
+    pub deep_reorg_depth: u64,
+    /// If set, listen on bitcoind's P2P port (`peer_host`:`peer_port`) for new blocks, and sync
+    /// the burnchain as soon as one is announced instead of waiting out `poll_time_secs`.
+    pub p2p_block_notifications: bool,
+    /// Number of burnchain blocks to download at once while syncing, each over its own connection
+    /// to `peer_host`:`peer_port`.
+    pub download_workers: u64,
+    /// Number of downloaded burnchain blocks to parse at once while syncing.
+    pub parse_workers: u64,
 }
 
 impl BurnchainConfig {
@@ -1056,8 +3902,15 @@ impl BurnchainConfig {
             leader_key_tx_estimated_size: LEADER_KEY_TX_ESTIM_SIZE,
             block_commit_tx_estimated_size: BLOCK_COMMIT_TX_ESTIM_SIZE,
             rbf_fee_increment: DEFAULT_RBF_FEE_RATE_INCREMENT,
+            auto_rbf: false,
+            auto_rbf_budget: DEFAULT_AUTO_RBF_BUDGET,
             epochs: None,
             ast_precheck_size_height: None,
+            utxo_scan_start_height: None,
+            deep_reorg_depth: 6,
+            p2p_block_notifications: false,
+            download_workers: 4,
+            parse_workers: 2,
         }
     }
 
@@ -1090,8 +3943,11 @@ impl BurnchainConfig {
 }
 
 #[derive(Clone, Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct BurnchainConfigFile {
     pub chain: Option<String>,
+    /// Defaults to the mainnet or testnet chain ID, per `mode`
+    pub chain_id: Option<u32>,
     pub burn_fee_cap: Option<u64>,
     pub mode: Option<String>,
     pub commit_anchor_block_within: Option<u64>,
@@ -1111,41 +3967,327 @@ pub struct BurnchainConfigFile {
     pub block_commit_tx_estimated_size: Option<u64>,
     pub rbf_fee_increment: Option<u64>,
     pub max_rbf: Option<u64>,
-    pub epochs: Option<Vec<StacksEpoch>>,
+    pub auto_rbf: Option<bool>,
+    pub auto_rbf_budget: Option<u64>,
+    pub epochs: Option<EpochsConfigFile>,
     pub ast_precheck_size_height: Option<u64>,
+    pub utxo_scan_start_height: Option<u64>,
+    pub deep_reorg_depth: Option<u64>,
+    pub p2p_block_notifications: Option<bool>,
+    pub download_workers: Option<u64>,
+    pub parse_workers: Option<u64>,
 }
 
-#[derive(Clone, Debug, Default)]
+/// The `burnchain.epochs` setting, which comes in two forms
+#[derive(Clone, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum EpochsConfigFile {
+    /// Activation heights for epochs after 2.0, keyed by epoch name (e.g. `"2.05" = 150`)
+    ActivationHeights(BTreeMap<String, u64>),
+    /// Full epoch definitions, given as `[[burnchain.epochs]]` tables
+    Epochs(Vec<StacksEpoch>),
+}
+
+#[derive(Clone, Debug, Default, DynConfigDiff)]
 pub struct NodeConfig {
     pub name: String,
+    #[dyn_config(secret)]
     pub seed: Vec<u8>,
     pub working_dir: String,
     pub rpc_bind: String,
     pub p2p_bind: String,
     pub data_url: String,
     pub p2p_address: String,
+    /// Seed of the node's p2p identity, which is independent of `seed`: a fleet of nodes can share
+    /// it while only one of them holds the mining key.
+    #[dyn_config(secret)]
     pub local_peer_seed: Vec<u8>,
+    /// Seed of the miner's microblock signing keys. Derived from `seed` if not set.
+    #[dyn_config(secret)]
+    pub microblock_seed: Option<Vec<u8>>,
     pub bootstrap_node: Vec<Neighbor>,
+    /// bootstrap nodes given by host name, which the p2p thread looks up again periodically
+    pub dns_seeds: Vec<DNSSeed>,
     pub deny_nodes: Vec<Neighbor>,
+    #[dyn_config(reloadable)]
     pub miner: bool,
     pub mock_mining: bool,
     pub mine_microblocks: bool,
     pub microblock_frequency: u64,
     pub max_microblocks: u64,
+    #[dyn_config(reloadable)]
     pub wait_time_for_microblocks: u64,
     pub prometheus_bind: Option<String>,
+    /// Address to serve the gRPC API on. Disabled if not set, and requires the `grpc` feature.
+    pub grpc_bind: Option<String>,
+    /// Address to serve the operator admin API on. Disabled if not set.
+    pub admin_bind: Option<String>,
+    /// Bearer token that admin API requests must carry. The endpoints that change the node's
+    /// configuration are disabled if not set.
+    #[dyn_config(secret)]
+    pub admin_auth_token: Option<String>,
+    /// Whether the admin API answers peers other than the loopback interface.
+    pub admin_allow_remote: bool,
+    /// Whether the admin API serves CPU and heap profiles. They are only served to loopback
+    /// peers, even with `admin_allow_remote`.
+    pub profiling: bool,
+    /// Whether to report readiness and liveness to systemd over `$NOTIFY_SOCKET`, when it is set.
+    /// Turn this off if the socket is meant for a wrapper process that starts the node.
+    pub supervisor_notify: bool,
     pub marf_cache_strategy: Option<String>,
     pub marf_defer_hashing: bool,
+    /// How many of the most recent tries of each MARF to keep entirely in RAM. Not kept if 0.
+    pub marf_pinned_tries: usize,
+    /// When to fsync the tries appended to each MARF's blobs file.
+    pub marf_blobs_sync_mode: TrieFileSyncMode,
+    /// How many tries to move per burn block when migrating trie blobs out of the Clarity MARF's
+    /// DB. If 0, they are all migrated at startup.
+    pub marf_blob_migration_batch: u32,
+    /// How many bytes of the Clarity MARF's blobs file to cache in pages for reading tries. Not
+    /// cached if less than a page.
+    pub marf_blobs_page_cache_size: usize,
+    /// Whether to buffer the tries the Clarity MARF writes in a transaction, and write them to its
+    /// blobs file with a single sync when the transaction commits.
+    pub marf_write_buffering: bool,
     pub pox_sync_sample_secs: u64,
+    /// How often to log how far the node has synced while it is in initial block download. Not
+    /// logged if 0.
+    pub sync_progress_log_interval_secs: u64,
     pub use_test_genesis_chainstate: Option<bool>,
+    /// Genesis balances, lockups, and BNS state to boot a private chain with, in the format of
+    /// `stx-genesis/chainstate.txt`. `burnchain.chain_id` must be the chain ID derived from this
+    /// file (see `Config::get_genesis_chain_id()`).
+    pub genesis_chainstate_path: Option<String>,
+    /// SHA256 of the file at `genesis_chainstate_path`, taken when the config was loaded
+    pub genesis_chainstate_hash: Option<Sha256Sum>,
+    /// Whether to record which Clarity expressions are evaluated. Only supported in mocknet and
+    /// helium modes; the coverage is served at `/v2/clarity/coverage`.
+    pub clarity_coverage: bool,
+    /// Directory to write each coverage run to when it is dumped. Not persisted if not set.
+    pub clarity_coverage_dir: Option<String>,
+    /// Compute the chainstate consistency hash at every Stacks block height that is a multiple of
+    /// this, and export it to Prometheus. Disabled if 0.
+    pub consistency_hash_interval: u64,
+    /// Periodic backups of the chainstate. Disabled if not set.
+    pub backup: Option<BackupConfig>,
+    /// Run as a read replica of another node. Disabled if not set.
+    pub replica: Option<ReplicaConfig>,
+    /// Watch for the node to stop processing blocks. Disabled if not set.
+    pub stall_watchdog: Option<StallWatchdogConfig>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BackupConfig {
+    /// Directory to keep the backups in. `{working_dir}/{mode}/backups` if not set.
+    pub dir: Option<String>,
+    /// Back up at every burn block height that is a multiple of this.
+    pub interval: u64,
+    /// How many backups to keep. The oldest is deleted once a new one is complete.
+    pub keep: u64,
+}
+
+impl BackupConfig {
+    pub const DEFAULT_INTERVAL: u64 = 144;
+    pub const DEFAULT_KEEP: u64 = 3;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplicaConfig {
+    /// Admin API of the primary node, whose backups the replica ingests: an `https://` URL, or
+    /// a loopback `host:port` reached over plain HTTP
+    pub primary: String,
+    /// The primary's `node.admin_auth_token`
+    pub auth_token: String,
+    /// How often to check the primary for a newer backup, in seconds
+    pub poll_interval_secs: u64,
+}
+
+impl ReplicaConfig {
+    pub const DEFAULT_POLL_INTERVAL_SECS: u64 = 60;
+
+    fn from_config_file(f: ReplicaConfigFile) -> Result<ReplicaConfig, String> {
+        let primary = f
+            .primary
+            .ok_or("`node.replica.primary` must be set".to_string())?;
+        replica::primary_url(&primary)
+            .map_err(|e| format!("Invalid `node.replica.primary`: {}", e))?;
+        let auth_token = f
+            .auth_token
+            .ok_or("`node.replica.auth_token` must be set".to_string())?;
+        let poll_interval_secs = f
+            .poll_interval_secs
+            .unwrap_or(ReplicaConfig::DEFAULT_POLL_INTERVAL_SECS);
+        if poll_interval_secs == 0 {
+            return Err("`node.replica.poll_interval_secs` must be at least 1".to_string());
+        }
+        Ok(ReplicaConfig {
+            primary,
+            auth_token,
+            poll_interval_secs,
+        })
+    }
+}
+
+/// What the stall watchdog does once the node has stalled
+/// A built-in set of settings for a common kind of deployment, selected with `--profile`. The
+/// settings live in `conf/profiles`, and the node's config file overrides any of them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfigProfile {
+    /// Follows the chain without mining
+    Follower,
+    /// A follower that serves a busy RPC API
+    ApiNode,
+    /// Mines
+    Miner,
+    /// A follower that keeps and serves the whole chain history to its peers
+    Archival,
+}
+
+impl ConfigProfile {
+    pub const ALL: [ConfigProfile; 4] = [
+        ConfigProfile::Follower,
+        ConfigProfile::ApiNode,
+        ConfigProfile::Miner,
+        ConfigProfile::Archival,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConfigProfile::Follower => "follower",
+            ConfigProfile::ApiNode => "api-node",
+            ConfigProfile::Miner => "miner",
+            ConfigProfile::Archival => "archival",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<ConfigProfile, String> {
+        match s.to_lowercase().as_str() {
+            "follower" => Ok(ConfigProfile::Follower),
+            "api-node" => Ok(ConfigProfile::ApiNode),
+            "miner" => Ok(ConfigProfile::Miner),
+            "archival" => Ok(ConfigProfile::Archival),
+            _ => Err(format!(
+                "`--profile` must be one of `follower`, `api-node`, `miner`, or `archival`, not `{}`",
+                s
+            )),
+        }
+    }
+
+    fn toml(&self) -> &'static str {
+        match self {
+            ConfigProfile::Follower => include_str!("../conf/profiles/follower.toml"),
+            ConfigProfile::ApiNode => include_str!("../conf/profiles/api-node.toml"),
+            ConfigProfile::Miner => include_str!("../conf/profiles/miner.toml"),
+            ConfigProfile::Archival => include_str!("../conf/profiles/archival.toml"),
+        }
+    }
+
+    /// The profile's settings, as a config file table.
+    pub fn settings(&self) -> toml::Value {
+        toml::from_str(self.toml()).expect("FATAL: built-in config profile is not valid toml")
+    }
+}
+
+/// Lays `overrides` over `base`: tables are merged key by key, and any other value in
+/// `overrides` replaces the one in `base`.
+fn merge_toml(base: &mut toml::Value, overrides: toml::Value) {
+    match (base, overrides) {
+        (toml::Value::Table(base), toml::Value::Table(overrides)) => {
+            for (key, value) in overrides.into_iter() {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge_toml(base_value, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StallRemediation {
+    /// Only report the stall
+    None,
+    /// Drop the connection to bitcoind, so that the next burnchain sync opens a new one
+    ReconnectBitcoind,
+    /// Disconnect from all Stacks peers, so that the p2p thread finds new ones
+    ResetPeers,
+    /// Exit with a non-zero status, so that the process supervisor restarts the node
+    Exit,
+}
+
+impl StallRemediation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StallRemediation::None => "none",
+            StallRemediation::ReconnectBitcoind => "reconnect_bitcoind",
+            StallRemediation::ResetPeers => "reset_peers",
+            StallRemediation::Exit => "exit",
+        }
+    }
+
+    fn parse(s: &str) -> Result<StallRemediation, String> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(StallRemediation::None),
+            "reconnect_bitcoind" => Ok(StallRemediation::ReconnectBitcoind),
+            "reset_peers" => Ok(StallRemediation::ResetPeers),
+            "exit" => Ok(StallRemediation::Exit),
+            _ => Err(format!(
+                "`node.stall_watchdog.remediation` must be one of `none`, `reconnect_bitcoind`, `reset_peers`, or `exit`, not `{}`",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StallWatchdogConfig {
+    /// The node has stalled once it has not processed a new burn block, or a new Stacks block,
+    /// for this many seconds
+    pub timeout_secs: u64,
+    /// What to do about a stall, besides reporting it
+    pub remediation: StallRemediation,
+}
+
+impl StallWatchdogConfig {
+    pub const DEFAULT_TIMEOUT_SECS: u64 = 3600;
+
+    fn from_config_file(f: StallWatchdogConfigFile) -> Result<StallWatchdogConfig, String> {
+        let timeout_secs = f
+            .timeout_secs
+            .unwrap_or(StallWatchdogConfig::DEFAULT_TIMEOUT_SECS);
+        if timeout_secs == 0 {
+            return Err("`node.stall_watchdog.timeout_secs` must be at least 1".to_string());
+        }
+        let remediation = match f.remediation {
+            Some(remediation) => StallRemediation::parse(&remediation)?,
+            None => StallRemediation::None,
+        };
+        Ok(StallWatchdogConfig {
+            timeout_secs,
+            remediation,
+        })
+    }
+}
+
+impl From<BackupConfigFile> for BackupConfig {
+    fn from(f: BackupConfigFile) -> Self {
+        BackupConfig {
+            dir: f.dir,
+            interval: f.interval.unwrap_or(BackupConfig::DEFAULT_INTERVAL),
+            keep: f.keep.unwrap_or(BackupConfig::DEFAULT_KEEP),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum CostEstimatorName {
     NaivePessimistic,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum FeeEstimatorName {
     ScalarFeeRate,
     FuzzedWeightedMedianFeeRate,
@@ -1175,6 +4317,12 @@ impl Default for CostMetricName {
 }
 
 impl CostEstimatorName {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CostEstimatorName::NaivePessimistic => "naive_pessimistic",
+        }
+    }
+
     fn panic_parse(s: String) -> CostEstimatorName {
         if &s.to_lowercase() == "naive_pessimistic" {
             CostEstimatorName::NaivePessimistic
@@ -1188,6 +4336,18 @@ impl CostEstimatorName {
 }
 
 impl FeeEstimatorName {
+    pub const ALL: [FeeEstimatorName; 2] = [
+        FeeEstimatorName::ScalarFeeRate,
+        FeeEstimatorName::FuzzedWeightedMedianFeeRate,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FeeEstimatorName::ScalarFeeRate => "scalar_fee_rate",
+            FeeEstimatorName::FuzzedWeightedMedianFeeRate => "fuzzed_weighted_median_fee_rate",
+        }
+    }
+
     fn panic_parse(s: String) -> FeeEstimatorName {
         if &s.to_lowercase() == "scalar_fee_rate" {
             FeeEstimatorName::ScalarFeeRate
@@ -1224,6 +4384,14 @@ pub struct FeeEstimationConfig {
     /// If using WeightedMedianFeeRateEstimator, the window size to use. See comments on
     /// WeightedMedianFeeRateEstimator.
     pub fee_rate_window_size: u64,
+    /// Score the chains coordinator's estimators against the costs and fee rates of each
+    /// processed block, and export their rolling errors to Prometheus.
+    pub track_accuracy: bool,
+    /// Also run the fee estimators that are not configured, in the shadow of the configured one,
+    /// so that their accuracy can be compared with it.  Implies `track_accuracy`.
+    pub cross_validate: bool,
+    /// Number of most recent predictions that the rolling errors are computed over.
+    pub accuracy_window_size: u64,
 }
 
 impl Default for FeeEstimationConfig {
@@ -1235,6 +4403,9 @@ impl Default for FeeEstimationConfig {
             log_error: false,
             fee_rate_fuzzer_fraction: 0.1f64,
             fee_rate_window_size: 5u64,
+            track_accuracy: false,
+            cross_validate: false,
+            accuracy_window_size: DEFAULT_ESTIMATOR_ACCURACY_WINDOW_SIZE,
         }
     }
 }
@@ -1249,6 +4420,9 @@ impl From<FeeEstimationConfigFile> for FeeEstimationConfig {
                 log_error: false,
                 fee_rate_fuzzer_fraction: 0f64,
                 fee_rate_window_size: 0u64,
+                track_accuracy: false,
+                cross_validate: false,
+                accuracy_window_size: 0u64,
             };
         }
         let cost_estimator = f
@@ -1264,6 +4438,7 @@ impl From<FeeEstimationConfigFile> for FeeEstimationConfig {
             .map(CostMetricName::panic_parse)
             .unwrap_or_default();
         let log_error = f.log_error.unwrap_or(false);
+        let cross_validate = f.cross_validate.unwrap_or(false);
         Self {
             cost_estimator: Some(cost_estimator),
             fee_estimator: Some(fee_estimator),
@@ -1271,6 +4446,11 @@ impl From<FeeEstimationConfigFile> for FeeEstimationConfig {
             log_error,
             fee_rate_fuzzer_fraction: f.fee_rate_fuzzer_fraction.unwrap_or(0.1f64),
             fee_rate_window_size: f.fee_rate_window_size.unwrap_or(5u64),
+            track_accuracy: f.track_accuracy.unwrap_or(false) || cross_validate,
+            cross_validate,
+            accuracy_window_size: f
+                .accuracy_window_size
+                .unwrap_or(DEFAULT_ESTIMATOR_ACCURACY_WINDOW_SIZE),
         }
     }
 }
@@ -1299,18 +4479,76 @@ impl Config {
     }
 
     pub fn make_fee_estimator(&self) -> Option<Box<dyn FeeEstimator>> {
+        self.make_fee_estimator_with(&self.estimation)
+    }
+
+    fn make_fee_estimator_with(
+        &self,
+        estimation: &FeeEstimationConfig,
+    ) -> Option<Box<dyn FeeEstimator>> {
         let metric = self.make_cost_metric()?;
-        let fee_estimator: Box<dyn FeeEstimator> = match self.estimation.fee_estimator.as_ref()? {
-            FeeEstimatorName::ScalarFeeRate => self
-                .estimation
-                .make_scalar_fee_estimator(self.get_estimates_path(), metric),
-            FeeEstimatorName::FuzzedWeightedMedianFeeRate => self
-                .estimation
+        let fee_estimator: Box<dyn FeeEstimator> = match estimation.fee_estimator.as_ref()? {
+            FeeEstimatorName::ScalarFeeRate => {
+                estimation.make_scalar_fee_estimator(self.get_estimates_path(), metric)
+            }
+            FeeEstimatorName::FuzzedWeightedMedianFeeRate => estimation
                 .make_fuzzed_weighted_median_fee_estimator(self.get_estimates_path(), metric),
         };
 
         Some(fee_estimator)
     }
+
+    /// The cost estimator fed by the chains coordinator: `make_cost_estimator()`, scored against
+    /// the costs of processed blocks if `fee_estimation.track_accuracy` is set.
+    pub fn make_tracked_cost_estimator(&self) -> Option<Box<dyn CostEstimator>> {
+        let cost_estimator = self.make_cost_estimator()?;
+        if !self.estimation.track_accuracy {
+            return Some(cost_estimator);
+        }
+        let name = self
+            .estimation
+            .cost_estimator
+            .as_ref()?
+            .as_str()
+            .to_string();
+        Some(Box::new(CostEstimatorAccuracy::new(
+            vec![(name, cost_estimator)],
+            self.make_cost_metric()?,
+            self.estimation.accuracy_window_size as usize,
+        )))
+    }
+
+    /// The fee estimator fed by the chains coordinator: `make_fee_estimator()`, scored against
+    /// the fee rates of processed blocks if `fee_estimation.track_accuracy` is set, alongside
+    /// every other fee estimator if `fee_estimation.cross_validate` is set.
+    pub fn make_tracked_fee_estimator(&self) -> Option<Box<dyn FeeEstimator>> {
+        let fee_estimator = self.make_fee_estimator()?;
+        if !self.estimation.track_accuracy {
+            return Some(fee_estimator);
+        }
+        let configured = self.estimation.fee_estimator.as_ref()?;
+        let mut estimators = vec![(configured.as_str().to_string(), fee_estimator)];
+        if self.estimation.cross_validate {
+            for name in FeeEstimatorName::ALL.iter() {
+                if name == configured {
+                    continue;
+                }
+                let shadow_estimation = FeeEstimationConfig {
+                    fee_estimator: Some(name.clone()),
+                    ..self.estimation.clone()
+                };
+                estimators.push((
+                    name.as_str().to_string(),
+                    self.make_fee_estimator_with(&shadow_estimation)?,
+                ));
+            }
+        }
+        Some(Box::new(FeeEstimatorAccuracy::new(
+            estimators,
+            self.make_cost_metric()?,
+            self.estimation.accuracy_window_size as usize,
+        )))
+    }
 }
 
 impl FeeEstimationConfig {
@@ -1398,8 +4636,10 @@ impl NodeConfig {
             data_url: format!("http://127.0.0.1:{}", rpc_port),
             p2p_address: format!("127.0.0.1:{}", rpc_port),
             bootstrap_node: vec![],
+            dns_seeds: vec![],
             deny_nodes: vec![],
             local_peer_seed: local_peer_seed.to_vec(),
+            microblock_seed: None,
             miner: false,
             mock_mining: false,
             mine_microblocks: true,
@@ -1407,10 +4647,30 @@ impl NodeConfig {
             max_microblocks: u16::MAX as u64,
             wait_time_for_microblocks: 30_000,
             prometheus_bind: None,
+            grpc_bind: None,
+            admin_bind: None,
+            admin_auth_token: None,
+            admin_allow_remote: false,
+            profiling: false,
+            supervisor_notify: true,
             marf_cache_strategy: None,
             marf_defer_hashing: true,
+            marf_pinned_tries: 0,
+            marf_blobs_sync_mode: TrieFileSyncMode::PerCommit,
+            marf_blob_migration_batch: 0,
+            marf_blobs_page_cache_size: 0,
+            marf_write_buffering: false,
             pox_sync_sample_secs: 30,
+            sync_progress_log_interval_secs: 60,
             use_test_genesis_chainstate: None,
+            genesis_chainstate_path: None,
+            genesis_chainstate_hash: None,
+            clarity_coverage: false,
+            clarity_coverage_dir: None,
+            consistency_hash_interval: 100,
+            backup: None,
+            replica: None,
+            stall_watchdog: None,
         }
     }
 
@@ -1439,20 +4699,84 @@ impl NodeConfig {
         }
     }
 
+    /// Add a bootstrap node given as `PUBKEY@HOST:PORT` or `HOST:PORT`.  If `HOST` is a name
+    /// rather than an IP address, it is a DNS seed: every address it resolves to is a bootstrap
+    /// node, and the p2p thread looks it up again every so often in case it moves.  Without a
+    /// public key, the node's key is learned from its handshake.
     pub fn add_bootstrap_node(&mut self, bootstrap_node: &str, chain_id: u32, peer_version: u32) {
         let parts: Vec<&str> = bootstrap_node.split("@").collect();
-        if parts.len() != 2 {
-            panic!(
-                "Invalid bootstrap node '{}': expected PUBKEY@IP:PORT",
-                bootstrap_node
-            );
+        let (pubkey_opt, hostport) = match parts.len() {
+            1 => (None, parts[0]),
+            2 => {
+                let pubkey = Secp256k1PublicKey::from_hex(parts[0])
+                    .expect(&format!("Invalid public key '{}'", parts[0]));
+                (Some(pubkey), parts[1])
+            }
+            _ => {
+                panic!(
+                    "Invalid bootstrap node '{}': expected PUBKEY@HOST:PORT or HOST:PORT",
+                    bootstrap_node
+                );
+            }
+        };
+        let make_pubkey = || {
+            pubkey_opt
+                .clone()
+                .unwrap_or_else(|| Secp256k1PublicKey::from_private(&Secp256k1PrivateKey::new()))
+        };
+
+        if let Ok(sockaddr) = hostport.parse::<SocketAddr>() {
+            let neighbor =
+                NodeConfig::default_neighbor(sockaddr, make_pubkey(), chain_id, peer_version);
+            self.bootstrap_node.push(neighbor);
+            return;
+        }
+
+        let (host, port) = match hostport
+            .rsplit_once(":")
+            .map(|(h, p)| (h, p.parse::<u16>()))
+        {
+            Some((host, Ok(port))) if host.len() > 0 => (host, port),
+            _ => {
+                panic!(
+                    "Invalid bootstrap node '{}': expected PUBKEY@HOST:PORT or HOST:PORT",
+                    bootstrap_node
+                );
+            }
+        };
+
+        // the seed may be unreachable right now, but that's no reason not to start
+        match hostport.to_socket_addrs() {
+            Ok(sockaddrs) => {
+                for sockaddr in sockaddrs {
+                    let neighbor = NodeConfig::default_neighbor(
+                        sockaddr,
+                        make_pubkey(),
+                        chain_id,
+                        peer_version,
+                    );
+                    self.bootstrap_node.push(neighbor);
+                }
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to look up bootstrap node '{}': {:?}; will try again later",
+                    bootstrap_node, &e
+                );
+            }
+        }
+
+        if !self
+            .dns_seeds
+            .iter()
+            .any(|seed| seed.host == host && seed.port == port)
+        {
+            self.dns_seeds.push(DNSSeed {
+                host: host.to_string(),
+                port,
+                public_key: pubkey_opt,
+            });
         }
-        let (pubkey_str, hostport) = (parts[0], parts[1]);
-        let pubkey = Secp256k1PublicKey::from_hex(pubkey_str)
-            .expect(&format!("Invalid public key '{}'", pubkey_str));
-        let sockaddr = hostport.to_socket_addrs().unwrap().next().unwrap();
-        let neighbor = NodeConfig::default_neighbor(sockaddr, pubkey, chain_id, peer_version);
-        self.bootstrap_node.push(neighbor);
     }
 
     pub fn set_bootstrap_nodes(
@@ -1496,24 +4820,72 @@ impl NodeConfig {
             TrieHashCalculationMode::Immediate
         };
 
-        MARFOpenOpts::new(
+        let mut marf_opts = MARFOpenOpts::new(
             hash_mode,
             &self
                 .marf_cache_strategy
                 .as_ref()
                 .unwrap_or(&"noop".to_string()),
             false,
-        )
+        );
+        marf_opts.pinned_tries = self.marf_pinned_tries;
+        marf_opts.blobs_sync_mode = self.marf_blobs_sync_mode;
+        marf_opts.blob_migration_batch = self.marf_blob_migration_batch;
+        marf_opts.blobs_page_cache_size = self.marf_blobs_page_cache_size;
+        marf_opts.write_buffering = self.marf_write_buffering;
+        marf_opts
     }
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, DynConfigDiff)]
 pub struct MinerConfig {
+    #[dyn_config(reloadable)]
     pub min_tx_fee: u64,
+    #[dyn_config(reloadable)]
     pub first_attempt_time_ms: u64,
+    #[dyn_config(reloadable)]
     pub subsequent_attempt_time_ms: u64,
+    #[dyn_config(reloadable)]
     pub microblock_attempt_time_ms: u64,
+    #[dyn_config(reloadable)]
     pub probability_pick_no_estimate_tx: u8,
+    /// Only confirm parent microblocks that have had time to propagate, judging by how long
+    /// neighbors take to relay microblocks.
+    #[dyn_config(reloadable)]
+    pub adaptive_microblock_confirmation: bool,
+    /// Number of distinct neighbors that must relay a microblock for it to count as acknowledged.
+    #[dyn_config(reloadable)]
+    pub microblock_ack_neighbors: u64,
+    /// Upper bound on how long a parent microblock can be held back from confirmation.
+    #[dyn_config(reloadable)]
+    pub max_microblock_confirmation_delay_ms: u64,
+    /// Directory to write a JSON tenure cost report into for every block the miner assembles.
+    pub tenure_report_dir: Option<String>,
+    /// Largest share of the block budget, in percent, that a microblock stream may consume.
+    #[dyn_config(reloadable)]
+    pub microblock_stream_budget_pct: u64,
+    /// How long the miner keeps adding microblocks to a stream, in milliseconds.
+    #[dyn_config(reloadable)]
+    pub microblock_stream_time_ms: u64,
+    /// Start as the warm standby of another miner node with the same seed: follow the chain, but
+    /// send no burnchain operations until the active miner stops committing.
+    pub standby: bool,
+    /// Number of burn blocks in a row without a block-commit from the active miner after which a
+    /// standby takes over.
+    pub standby_takeover_burn_blocks: u64,
+    /// When a new Stacks chain tip arrives while this miner's block-commit for the current burn
+    /// block builds on another parent, mine on the new tip at the next tenure the run loop issues
+    /// and replace the commit by fee, instead of waiting out `node.wait_time_for_microblocks`.
+    #[dyn_config(reloadable)]
+    pub recommit_on_new_parent: bool,
+    /// How long after a burn block arrives, in milliseconds, `recommit_on_new_parent` may still
+    /// replace the block-commit.
+    #[dyn_config(reloadable)]
+    pub recommit_deadline_ms: u64,
+    /// When the next burn block begins a new epoch, leave out transactions that the new epoch's
+    /// AST rules would reject. No other rules of the new epoch are checked.
+    #[dyn_config(reloadable)]
+    pub check_next_epoch_ast_rules: bool,
 }
 
 impl MinerConfig {
@@ -1524,11 +4896,41 @@ impl MinerConfig {
             subsequent_attempt_time_ms: 30_000,
             microblock_attempt_time_ms: 30_000,
             probability_pick_no_estimate_tx: 5,
+            adaptive_microblock_confirmation: false,
+            microblock_ack_neighbors: 2,
+            max_microblock_confirmation_delay_ms: 10_000,
+            tenure_report_dir: None,
+            microblock_stream_budget_pct: 100,
+            microblock_stream_time_ms: u64::MAX,
+            standby: false,
+            standby_takeover_burn_blocks: 3,
+            recommit_on_new_parent: false,
+            recommit_deadline_ms: 60_000,
+            check_next_epoch_ast_rules: false,
         }
     }
 }
 
+#[derive(Clone, Debug, Default)]
+pub struct MocknetConfig {
+    /// Time between blocks, in milliseconds. Falls back to
+    /// `burnchain.commit_anchor_block_within` if unset.
+    pub block_time_ms: Option<u64>,
+    /// If set, a block is produced as soon as a transaction enters the mempool. If
+    /// `block_time_ms` is also set, an empty block is produced once it elapses.
+    pub mine_on_transaction: bool,
+}
+
+#[derive(Clone, Debug, Default, DynConfigDiff)]
+pub struct MempoolConfig {
+    /// Transactions paying less than this fee rate, in microSTX per unit of the cost metric, are
+    /// rejected from the mempool, and peers are told not to send them.  0 admits any fee rate.
+    #[dyn_config(reloadable)]
+    pub min_tx_fee_rate: f64,
+}
+
 #[derive(Clone, Default, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct ConnectionOptionsFile {
     pub inbox_maxlen: Option<usize>,
     pub outbox_maxlen: Option<usize>,
@@ -1551,7 +4953,14 @@ pub struct ConnectionOptionsFile {
     pub max_sockets: Option<u64>,
     pub walk_interval: Option<u64>,
     pub dns_timeout: Option<u64>,
+    pub dns_seed_refresh_interval: Option<u64>,
     pub max_inflight_blocks: Option<u64>,
+    pub download_hedge_delay_ms: Option<u64>,
+    pub max_download_requests_per_block: Option<u64>,
+    pub peer_ban_score: Option<i64>,
+    pub address_family: Option<String>,
+    pub socks_proxy: Option<String>,
+    pub onion_address: Option<String>,
     pub max_inflight_attachments: Option<u64>,
     pub read_only_call_limit_write_length: Option<u64>,
     pub read_only_call_limit_read_length: Option<u64>,
@@ -1569,9 +4978,20 @@ pub struct ConnectionOptionsFile {
     pub disable_block_download: Option<bool>,
     pub force_disconnect_interval: Option<u64>,
     pub antientropy_public: Option<bool>,
+    pub finalized_block_confirmations: Option<u64>,
+    pub mempool_admission_simulation: Option<String>,
+    pub rpc_api_keys: Option<Vec<String>>,
+    pub rpc_default_access: Option<String>,
+    pub rpc_route_access: Option<BTreeMap<String, String>>,
+    pub rpc_client_rate_limit: Option<u64>,
+    pub rpc_client_burst: Option<u64>,
+    pub rpc_route_rate_limit: Option<u64>,
+    pub rpc_route_burst: Option<u64>,
+    pub rpc_request_costs: Option<BTreeMap<String, u64>>,
 }
 
 #[derive(Clone, Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct NodeConfigFile {
     pub name: Option<String>,
     pub seed: Option<String>,
@@ -1583,6 +5003,7 @@ pub struct NodeConfigFile {
     pub data_url: Option<String>,
     pub bootstrap_node: Option<String>,
     pub local_peer_seed: Option<String>,
+    pub microblock_seed: Option<String>,
     pub miner: Option<bool>,
     pub mock_mining: Option<bool>,
     pub mine_microblocks: Option<bool>,
@@ -1590,13 +5011,57 @@ pub struct NodeConfigFile {
     pub max_microblocks: Option<u64>,
     pub wait_time_for_microblocks: Option<u64>,
     pub prometheus_bind: Option<String>,
+    pub grpc_bind: Option<String>,
+    pub admin_bind: Option<String>,
+    pub admin_auth_token: Option<String>,
+    pub admin_allow_remote: Option<bool>,
+    pub profiling: Option<bool>,
+    pub supervisor_notify: Option<bool>,
     pub marf_cache_strategy: Option<String>,
     pub marf_defer_hashing: Option<bool>,
+    pub marf_pinned_tries: Option<usize>,
+    /// One of `per-commit`, `periodic:<milliseconds>`, `dsync`, or `none`
+    pub marf_blobs_sync_mode: Option<String>,
+    pub marf_blob_migration_batch: Option<u32>,
+    pub marf_blobs_page_cache_size: Option<usize>,
+    pub marf_write_buffering: Option<bool>,
     pub pox_sync_sample_secs: Option<u64>,
+    pub sync_progress_log_interval_secs: Option<u64>,
     pub use_test_genesis_chainstate: Option<bool>,
+    pub genesis_chainstate_path: Option<String>,
+    pub clarity_coverage: Option<bool>,
+    pub clarity_coverage_dir: Option<String>,
+    pub consistency_hash_interval: Option<u64>,
+    pub backup: Option<BackupConfigFile>,
+    pub replica: Option<ReplicaConfigFile>,
+    pub stall_watchdog: Option<StallWatchdogConfigFile>,
+}
+
+#[derive(Clone, Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BackupConfigFile {
+    pub dir: Option<String>,
+    pub interval: Option<u64>,
+    pub keep: Option<u64>,
+}
+
+#[derive(Clone, Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ReplicaConfigFile {
+    pub primary: Option<String>,
+    pub auth_token: Option<String>,
+    pub poll_interval_secs: Option<u64>,
+}
+
+#[derive(Clone, Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct StallWatchdogConfigFile {
+    pub timeout_secs: Option<u64>,
+    pub remediation: Option<String>,
 }
 
 #[derive(Clone, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct FeeEstimationConfigFile {
     pub cost_estimator: Option<String>,
     pub fee_estimator: Option<String>,
@@ -1605,6 +5070,9 @@ pub struct FeeEstimationConfigFile {
     pub log_error: Option<bool>,
     pub fee_rate_fuzzer_fraction: Option<f64>,
     pub fee_rate_window_size: Option<u64>,
+    pub track_accuracy: Option<bool>,
+    pub cross_validate: Option<bool>,
+    pub accuracy_window_size: Option<u64>,
 }
 
 impl Default for FeeEstimationConfigFile {
@@ -1617,29 +5085,250 @@ impl Default for FeeEstimationConfigFile {
             log_error: None,
             fee_rate_fuzzer_fraction: None,
             fee_rate_window_size: None,
+            track_accuracy: None,
+            cross_validate: None,
+            accuracy_window_size: None,
         }
     }
 }
 
 #[derive(Clone, Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct MinerConfigFile {
     pub min_tx_fee: Option<u64>,
     pub first_attempt_time_ms: Option<u64>,
     pub subsequent_attempt_time_ms: Option<u64>,
     pub microblock_attempt_time_ms: Option<u64>,
     pub probability_pick_no_estimate_tx: Option<u8>,
+    pub adaptive_microblock_confirmation: Option<bool>,
+    pub microblock_ack_neighbors: Option<u64>,
+    pub max_microblock_confirmation_delay_ms: Option<u64>,
+    pub tenure_report_dir: Option<String>,
+    pub microblock_stream_budget_pct: Option<u64>,
+    pub microblock_stream_time_ms: Option<u64>,
+    pub standby: Option<bool>,
+    pub standby_takeover_burn_blocks: Option<u64>,
+    pub recommit_on_new_parent: Option<bool>,
+    pub recommit_deadline_ms: Option<u64>,
+    pub check_next_epoch_ast_rules: Option<bool>,
+}
+
+#[derive(Clone, Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MocknetConfigFile {
+    pub block_time_ms: Option<u64>,
+    pub mine_on_transaction: Option<bool>,
+}
+
+#[derive(Clone, Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MempoolConfigFile {
+    pub min_tx_fee_rate: Option<f64>,
+}
+
+#[derive(Clone, Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AtlasConfigFile {
+    /// One of "sqlite" (the default), "flat_files" or "object_store"
+    pub attachment_store: Option<String>,
+    /// Directory of the "flat_files" store, or of the "object_store" store's local copy. Defaults
+    /// to `attachments` next to the chainstate.
+    pub attachment_store_path: Option<String>,
+    pub object_store_endpoint: Option<String>,
+    pub object_store_bucket: Option<String>,
+    pub object_store_region: Option<String>,
+    pub object_store_prefix: Option<String>,
+    pub object_store_access_key_id: Option<String>,
+    pub object_store_secret_access_key: Option<String>,
+}
+
+impl AtlasConfigFile {
+    fn into_attachment_store(
+        self,
+        working_dir: &str,
+        mode: &str,
+    ) -> Result<AttachmentStoreConfig, String> {
+        let path = self.attachment_store_path.unwrap_or_else(|| {
+            let mut path = PathBuf::from(working_dir);
+            path.push(mode);
+            path.push("attachments");
+            path.to_str().expect("Unable to produce path").to_string()
+        });
+        match self.attachment_store.as_deref().unwrap_or("sqlite") {
+            "sqlite" => Ok(AttachmentStoreConfig::Sqlite),
+            "flat_files" => Ok(AttachmentStoreConfig::FlatFiles(path)),
+            "object_store" => {
+                if !cfg!(feature = "atlas_object_store") {
+                    return Err(
+                        "`atlas.attachment_store = \"object_store\"` needs a stacks-node built with the `atlas_object_store` feature"
+                            .to_string(),
+                    );
+                }
+                let required = |value: Option<String>, key: &str| {
+                    value.ok_or_else(|| {
+                        format!(
+                            "`atlas.{}` is required with `atlas.attachment_store = \"object_store\"`",
+                            key
+                        )
+                    })
+                };
+                let endpoint = required(self.object_store_endpoint, "object_store_endpoint")?;
+                if !endpoint.starts_with("https://") && !endpoint.starts_with("http://") {
+                    return Err(format!(
+                        "`atlas.object_store_endpoint` must be an https:// URL (got {})",
+                        endpoint
+                    ));
+                }
+                if endpoint.starts_with("http://") {
+                    warn!(
+                        "`atlas.object_store_endpoint` {} is plain http; object store credentials and attachments are sent unencrypted",
+                        &endpoint
+                    );
+                }
+                Ok(AttachmentStoreConfig::ObjectStore(ObjectStoreConfig {
+                    endpoint,
+                    bucket: required(self.object_store_bucket, "object_store_bucket")?,
+                    region: self
+                        .object_store_region
+                        .unwrap_or("us-east-1".to_string()),
+                    prefix: self.object_store_prefix.unwrap_or_default(),
+                    access_key_id: required(
+                        self.object_store_access_key_id,
+                        "object_store_access_key_id",
+                    )?,
+                    secret_access_key: required(
+                        self.object_store_secret_access_key,
+                        "object_store_secret_access_key",
+                    )?,
+                    cache_path: path,
+                }))
+            }
+            other => Err(format!(
+                "`atlas.attachment_store` must be one of sqlite, flat_files or object_store (got {})",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SqliteConfigFile {
+    pub sortition: Option<SqliteOptsFile>,
+    pub chainstate: Option<SqliteOptsFile>,
+    pub mempool: Option<SqliteOptsFile>,
+    /// The Clarity state MARF
+    pub marf: Option<SqliteOptsFile>,
+    /// Every other DB: peers, Atlas, fee estimation, ...
+    pub other: Option<SqliteOptsFile>,
+}
+
+#[derive(Clone, Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SqliteOptsFile {
+    pub page_size: Option<i64>,
+    pub mmap_size: Option<i64>,
+    pub wal_autocheckpoint: Option<i64>,
+    pub busy_timeout_ms: Option<u64>,
+    pub synchronous: Option<String>,
+}
+
+impl SqliteConfigFile {
+    fn into_sqlite_opts(self) -> Result<Vec<(SqliteDBKind, SqliteOpts)>, String> {
+        let sections = vec![
+            ("sortition", SqliteDBKind::Sortition, self.sortition),
+            ("chainstate", SqliteDBKind::Chainstate, self.chainstate),
+            ("mempool", SqliteDBKind::Mempool, self.mempool),
+            ("marf", SqliteDBKind::Marf, self.marf),
+            ("other", SqliteDBKind::Other, self.other),
+        ];
+        let mut all_opts = vec![];
+        for (name, kind, opts_file) in sections.into_iter() {
+            let opts_file = match opts_file {
+                Some(opts_file) => opts_file,
+                None => continue,
+            };
+            let defaults = SqliteOpts::default_for(kind);
+            let opts = SqliteOpts {
+                page_size: opts_file.page_size.or(defaults.page_size),
+                mmap_size: opts_file.mmap_size.or(defaults.mmap_size),
+                wal_autocheckpoint: opts_file.wal_autocheckpoint.or(defaults.wal_autocheckpoint),
+                busy_timeout_ms: opts_file.busy_timeout_ms.or(defaults.busy_timeout_ms),
+                synchronous: opts_file.synchronous.unwrap_or(defaults.synchronous),
+            };
+            opts.validate()
+                .map_err(|e| format!("Invalid `sqlite.{}`: {}", name, e))?;
+            all_opts.push((kind, opts));
+        }
+        Ok(all_opts)
+    }
 }
 
 #[derive(Clone, Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct EventObserverConfigFile {
     pub endpoint: String,
     pub events_keys: Vec<String>,
+    pub timeout_ms: Option<u64>,
+    pub keep_alive: Option<bool>,
+    pub http2: Option<bool>,
+    /// One of "http" (the default) or "nats"
+    pub sink: Option<String>,
+    pub nats_subject_prefix: Option<String>,
+    pub nats_jetstream: Option<bool>,
 }
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Debug)]
 pub struct EventObserverConfig {
     pub endpoint: String,
     pub events_keys: Vec<EventKeyType>,
+    /// How long a single delivery attempt (connect, send, and read the response) may take
+    /// before it is abandoned and retried. Without one, each attempt waits for the observer for
+    /// as long as it takes, so a slow observer is not sent the same event over and over.
+    pub timeout_ms: Option<u64>,
+    /// Whether to reuse the observer's HTTP connection across deliveries.
+    pub keep_alive: bool,
+    /// Whether to speak HTTP/2 to the observer without negotiating it first (h2c with prior
+    /// knowledge), so that concurrent deliveries are multiplexed over a single connection.
+    pub http2: bool,
+    /// How events are delivered to `endpoint`.
+    pub sink: EventObserverSink,
+}
+
+impl Default for EventObserverConfig {
+    fn default() -> EventObserverConfig {
+        EventObserverConfig {
+            endpoint: String::new(),
+            events_keys: vec![],
+            timeout_ms: None,
+            keep_alive: true,
+            http2: false,
+            sink: EventObserverSink::Http,
+        }
+    }
+}
+
+impl EventObserverConfig {
+    /// How long connecting to the observer may take: `timeout_ms`, or 5 seconds without one.
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_millis(
+            self.timeout_ms
+                .unwrap_or(DEFAULT_EVENT_OBSERVER_CONNECT_TIMEOUT_MS),
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EventObserverSink {
+    /// POST each event to the HTTP observer at `endpoint`.
+    Http,
+    /// Publish each event to the NATS server at `endpoint`, on the subject
+    /// `<subject_prefix>.<event path>`.  With `jetstream`, every publish waits for the stream
+    /// that captures the subject to acknowledge it.
+    Nats {
+        subject_prefix: String,
+        jetstream: bool,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -1653,6 +5342,9 @@ pub enum EventKeyType {
     BurnchainBlocks,
     MinedBlocks,
     MinedMicroblocks,
+    BlockPropagation,
+    TenureTimes,
+    BlockTelemetry,
 }
 
 impl EventKeyType {
@@ -1677,6 +5369,18 @@ impl EventKeyType {
             return Some(EventKeyType::Microblocks);
         }
 
+        if raw_key == "block_propagation" {
+            return Some(EventKeyType::BlockPropagation);
+        }
+
+        if raw_key == "tenure_times" {
+            return Some(EventKeyType::TenureTimes);
+        }
+
+        if raw_key == "block_telemetry" {
+            return Some(EventKeyType::BlockTelemetry);
+        }
+
         let comps: Vec<_> = raw_key.split("::").collect();
         if comps.len() == 1 {
             let split: Vec<_> = comps[0].split(".").collect();
@@ -1721,6 +5425,7 @@ pub struct InitialBalance {
 }
 
 #[derive(Clone, Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct InitialBalanceFile {
     pub address: String,
     pub amount: u64,
@@ -0,0 +1,322 @@
+//! On-demand diagnostic report. Sending the node `SIGUSR2` (e.g. `kill -USR2 <pid>`) writes
+//! `diagnostics-<unix time>.json` to `node.working_dir`, with:
+//!
+//! * `threads`: every thread of the process, with its state and the kernel function it is
+//!   blocked in (Linux only). In-process backtraces of other threads are not available; attach
+//!   a debugger (e.g. `gdb -p <pid> -batch -ex "thread apply all bt"`) for those.
+//! * `peers`: the p2p thread's conversations with its neighbors, as of the p2p thread's last
+//!   snapshot of them. An old `taken_at` means that the p2p thread has stopped making passes.
+//! * `mempool`: the summary that `stacks-node mempool-stats` prints.
+//! * `chain_tips` and `sync_progress`: the burnchain and Stacks tips the node has processed.
+//! * `db_locks`: the database transactions that are open or waiting, and for how long.
+//!
+//! Sections that cannot be collected are left out, and the reason is listed in `errors`. The
+//! report is collected on its own thread, and only reads from the node's databases, so it works
+//! on a node that is wedged.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::monitoring::{self, SyncProgress};
+use stacks::net::p2p::PeerMap;
+use stacks::util::get_epoch_time_secs;
+use stacks::util_lib::db_locks::{get_db_locks_report, DBLocksReport};
+
+use crate::mempool_stats::{mempool_stats, MempoolStats};
+use crate::snapshot::get_mempool_path;
+use crate::Config;
+
+/// How often the p2p thread snapshots its conversations, in seconds
+pub const PEER_SNAPSHOT_INTERVAL_SECS: u64 = 10;
+/// How many of the mempool's highest-fee transactions the report lists
+const MEMPOOL_TOP_N: usize = 20;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ThreadState {
+    pub tid: u64,
+    pub name: String,
+    /// `R` (running), `S` (sleeping), `D` (uninterruptible wait), ...; see `proc(5)`
+    pub state: String,
+    /// The kernel function the thread is blocked in, if any
+    pub wchan: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PeerState {
+    pub neighbor: String,
+    pub public_key_hash: String,
+    pub outbound: bool,
+    pub authenticated: bool,
+    pub burnchain_tip_height: u64,
+    pub first_contact_time: u64,
+    pub last_contact_time: u64,
+    pub last_send_time: u64,
+    pub last_recv_time: u64,
+    pub bytes_tx: u64,
+    pub bytes_rx: u64,
+    pub msgs_tx: u64,
+    pub msgs_rx: u64,
+    pub msgs_err: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PeersReport {
+    /// When the p2p thread took the snapshot
+    pub taken_at: u64,
+    pub peers: Vec<PeerState>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ChainTips {
+    pub burn_block_height: u64,
+    pub burn_header_hash: String,
+    pub consensus_hash: String,
+    pub stacks_tip_height: u64,
+    pub stacks_tip_block_hash: String,
+    pub stacks_tip_consensus_hash: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiagnosticReport {
+    pub time: u64,
+    pub pid: u32,
+    pub threads: Vec<ThreadState>,
+    pub peers: Option<PeersReport>,
+    pub mempool: Option<MempoolStats>,
+    pub chain_tips: Option<ChainTips>,
+    pub sync_progress: Option<SyncProgress>,
+    pub db_locks: DBLocksReport,
+    /// Why sections are missing
+    pub errors: Vec<String>,
+}
+
+/// The p2p thread's latest snapshot of its conversations, shared with the diagnostic dump.
+#[derive(Clone, Default)]
+pub struct PeerSnapshot(Arc<Mutex<Option<PeersReport>>>);
+
+impl PeerSnapshot {
+    pub fn new() -> PeerSnapshot {
+        PeerSnapshot::default()
+    }
+
+    /// Whether the last snapshot is older than `PEER_SNAPSHOT_INTERVAL_SECS`
+    pub fn is_stale(&self, now: u64) -> bool {
+        match self.0.lock().unwrap().as_ref() {
+            Some(report) => report.taken_at + PEER_SNAPSHOT_INTERVAL_SECS <= now,
+            None => true,
+        }
+    }
+
+    pub fn update(&self, peers: &PeerMap, now: u64) {
+        let peers = peers
+            .values()
+            .map(|convo| PeerState {
+                neighbor: convo.to_neighbor_key().to_string(),
+                public_key_hash: convo
+                    .get_public_key_hash()
+                    .map(|pubkh| pubkh.to_hex())
+                    .unwrap_or_default(),
+                outbound: convo.is_outbound(),
+                authenticated: convo.is_authenticated(),
+                burnchain_tip_height: convo.get_burnchain_tip_height(),
+                first_contact_time: convo.stats.first_contact_time,
+                last_contact_time: convo.stats.last_contact_time,
+                last_send_time: convo.stats.last_send_time,
+                last_recv_time: convo.stats.last_recv_time,
+                bytes_tx: convo.stats.bytes_tx,
+                bytes_rx: convo.stats.bytes_rx,
+                msgs_tx: convo.stats.msgs_tx,
+                msgs_rx: convo.stats.msgs_rx,
+                msgs_err: convo.stats.msgs_err,
+            })
+            .collect();
+        *self.0.lock().unwrap() = Some(PeersReport {
+            taken_at: now,
+            peers,
+        });
+    }
+
+    fn get(&self) -> Option<PeersReport> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Parses `/proc/<pid>/task/<tid>/stat` for the thread's state, which follows the parenthesized
+/// command name (which may itself contain spaces and parentheses).
+fn parse_thread_state(stat: &str) -> Option<String> {
+    let rest = &stat[stat.rfind(')')? + 1..];
+    rest.split_whitespace()
+        .next()
+        .map(|state| state.to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn collect_threads() -> Result<Vec<ThreadState>, String> {
+    let tasks = fs::read_dir("/proc/self/task")
+        .map_err(|e| format!("Failed to list /proc/self/task: {}", e))?;
+    let mut threads = vec![];
+    for task in tasks {
+        let task = task.map_err(|e| format!("Failed to list /proc/self/task: {}", e))?;
+        let tid = match task.file_name().to_string_lossy().parse::<u64>() {
+            Ok(tid) => tid,
+            Err(_) => continue,
+        };
+        // the thread may have exited since it was listed
+        let read = |file: &str| fs::read_to_string(task.path().join(file)).ok();
+        let name = match read("comm") {
+            Some(name) => name.trim().to_string(),
+            None => continue,
+        };
+        let state = read("stat")
+            .and_then(|stat| parse_thread_state(&stat))
+            .unwrap_or_default();
+        let wchan = read("wchan").filter(|wchan| !wchan.is_empty() && wchan != "0");
+        threads.push(ThreadState {
+            tid,
+            name,
+            state,
+            wchan,
+        });
+    }
+    threads.sort_by_key(|thread| thread.tid);
+    Ok(threads)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn collect_threads() -> Result<Vec<ThreadState>, String> {
+    Err("Thread states are only available on Linux".to_string())
+}
+
+fn collect_chain_tips(config: &Config) -> Result<ChainTips, String> {
+    let sortdb = SortitionDB::open(&config.get_burn_db_file_path(), false)
+        .map_err(|e| format!("Failed to open the sortition DB: {:?}", e))?;
+    let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())
+        .map_err(|e| format!("Failed to load the canonical burnchain tip: {:?}", e))?;
+    Ok(ChainTips {
+        burn_block_height: tip.block_height,
+        burn_header_hash: tip.burn_header_hash.to_hex(),
+        consensus_hash: tip.consensus_hash.to_hex(),
+        stacks_tip_height: tip.canonical_stacks_tip_height,
+        stacks_tip_block_hash: tip.canonical_stacks_tip_hash.to_hex(),
+        stacks_tip_consensus_hash: tip.canonical_stacks_tip_consensus_hash.to_hex(),
+    })
+}
+
+/// Collects the diagnostic report of the node configured by `config`.
+pub fn collect_report(config: &Config, peers: &PeerSnapshot) -> DiagnosticReport {
+    let mut errors = vec![];
+    let now = get_epoch_time_secs();
+    let threads = collect_threads().unwrap_or_else(|e| {
+        errors.push(e);
+        vec![]
+    });
+    let peers = peers.get();
+    if peers.is_none() {
+        errors.push("The p2p thread has not taken a snapshot of its peers".to_string());
+    }
+    let mempool = get_mempool_path(config)
+        .and_then(|path| mempool_stats(&path, MEMPOOL_TOP_N, now))
+        .map_err(|e| errors.push(e))
+        .ok();
+    let chain_tips = collect_chain_tips(config).map_err(|e| errors.push(e)).ok();
+
+    DiagnosticReport {
+        time: now,
+        pid: process::id(),
+        threads,
+        peers,
+        mempool,
+        chain_tips,
+        sync_progress: monitoring::get_sync_progress(),
+        db_locks: get_db_locks_report(),
+        errors,
+    }
+}
+
+/// Writes the diagnostic report to `node.working_dir`, on its own thread.
+#[derive(Clone)]
+pub struct DiagnosticDump {
+    config: Config,
+    peers: PeerSnapshot,
+}
+
+impl DiagnosticDump {
+    pub fn new(config: Config, peers: PeerSnapshot) -> DiagnosticDump {
+        DiagnosticDump { config, peers }
+    }
+
+    fn write(&self) -> Result<PathBuf, String> {
+        let report = collect_report(&self.config, &self.peers);
+        let mut path = PathBuf::from(&self.config.node.working_dir);
+        path.push(format!("diagnostics-{}.json", report.time));
+        let json = serde_json::to_vec_pretty(&report)
+            .map_err(|e| format!("Failed to serialize the diagnostic report: {}", e))?;
+        fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+
+    pub fn spawn(&self) {
+        let dump = self.clone();
+        let result = thread::Builder::new()
+            .name("diagnostics".to_string())
+            .spawn(move || match dump.write() {
+                Ok(path) => info!("Wrote diagnostic report to {}", path.display()),
+                Err(e) => error!("Failed to write diagnostic report: {}", e),
+            });
+        if let Err(e) = result {
+            error!("Failed to spawn diagnostics thread: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_thread_state() {
+        assert_eq!(
+            parse_thread_state("1234 (p2p) S 1 1234 1234 0 -1"),
+            Some("S".to_string())
+        );
+        assert_eq!(
+            parse_thread_state("1234 (a (b) c) D 1 1234"),
+            Some("D".to_string())
+        );
+        assert_eq!(parse_thread_state("garbage"), None);
+    }
+
+    #[test]
+    fn test_collect_report() {
+        let mut config = Config::default();
+        config.node.working_dir = "/tmp/stacks-node-tests/diagnostics".to_string();
+        let _ = fs::remove_dir_all(&config.node.working_dir);
+        fs::create_dir_all(&config.node.working_dir).unwrap();
+
+        let peers = PeerSnapshot::new();
+        assert!(peers.is_stale(get_epoch_time_secs()));
+        peers.update(&PeerMap::new(), get_epoch_time_secs());
+        assert!(!peers.is_stale(get_epoch_time_secs()));
+
+        let report = collect_report(&config, &peers);
+        assert_eq!(report.peers.as_ref().unwrap().peers, vec![]);
+        // there is no chainstate to read
+        assert!(report.chain_tips.is_none());
+        assert!(report.mempool.is_none());
+        assert_eq!(report.errors.len(), 2);
+        if cfg!(target_os = "linux") {
+            assert!(report
+                .threads
+                .iter()
+                .any(|thread| thread.tid == process::id() as u64));
+        }
+
+        let path = DiagnosticDump::new(config, peers).write().unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(json["pid"], process::id());
+    }
+}
@@ -1,4 +1,11 @@
+use std::borrow::Cow;
 use std::collections::hash_map::Entry;
+use std::convert::TryFrom;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{SyncSender, TrySendError};
+use std::task::{Context, Poll};
 use std::thread::sleep;
 use std::time::Duration;
 use std::{
@@ -7,13 +14,17 @@ use std::{
 };
 
 use async_h1::client;
+use async_std::io::{Read, Write};
 use async_std::net::TcpStream;
-use http_types::{Method, Request, Url};
+#[cfg(unix)]
+use async_std::os::unix::net::UnixStream;
+use http_types::{Method, Request, StatusCode, Url};
 use serde_json::json;
 
 use stacks::burnchains::Txid;
 use stacks::chainstate::coordinator::BlockEventDispatcher;
 use stacks::chainstate::stacks::address::StacksAddressExtensions;
+use stacks::chainstate::stacks::db::blocks::BlockValidationTelemetry;
 use stacks::chainstate::stacks::db::StacksHeaderInfo;
 use stacks::chainstate::stacks::events::{
     StacksTransactionEvent, StacksTransactionReceipt, TransactionOrigin,
@@ -24,6 +35,9 @@ use stacks::chainstate::stacks::{
 use stacks::chainstate::stacks::{StacksBlock, StacksMicroblock};
 use stacks::codec::StacksMessageCodec;
 use stacks::core::mempool::{MemPoolDropReason, MemPoolEventDispatcher};
+use stacks::monitoring::{
+    get_block_propagation_times, get_tenure_times, BlockPropagationTimes, TenureTimes,
+};
 use stacks::net::atlas::{Attachment, AttachmentInstance};
 use stacks::types::chainstate::{
     BlockHeaderHash, BurnchainHeaderHash, StacksAddress, StacksBlockId,
@@ -34,14 +48,112 @@ use stacks::vm::costs::ExecutionCost;
 use stacks::vm::events::{FTEventType, NFTEventType, STXEventType};
 use stacks::vm::types::{AssetIdentifier, QualifiedContractIdentifier, Value};
 
-use super::config::{EventKeyType, EventObserverConfig};
+use super::config::{EventKeyType, EventObserverConfig, EventObserverSink};
+use crate::burnchain_reorg::BurnchainReorg;
+use crate::nats::NatsPublisher;
+use crate::stall_watchdog::NodeStall;
 use stacks::chainstate::burn::ConsensusHash;
 use stacks::chainstate::stacks::db::unconfirmed::ProcessedUnconfirmedState;
-use stacks::chainstate::stacks::miner::TransactionEvent;
+use stacks::chainstate::stacks::miner::{TenureCostReport, TransactionEvent};
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 struct EventObserver {
     endpoint: String,
+    /// How long each request to the observer may take, if bounded at all.
+    timeout: Option<Duration>,
+    keep_alive: bool,
+    /// Idle connection to the observer, kept between deliveries when `keep_alive` is set.
+    /// Shared by all clones of the dispatcher, so deliveries to one observer are serialized.
+    connection: Arc<Mutex<Option<ObserverStream>>>,
+    /// HTTP/2 client for observers configured with `http2`, used instead of `connection`. It
+    /// keeps one connection to the observer, over which the deliveries of all clones of the
+    /// dispatcher are multiplexed.
+    http2_client: Option<reqwest::blocking::Client>,
+    /// In-process subscriber that receives the events instead of `endpoint`.
+    subscriber: Option<Arc<dyn ChainEventSubscriber>>,
+    /// Payload schema agreed on with the observer, once it has answered `GET /capabilities`.
+    schema_version: Arc<Mutex<Option<u32>>>,
+}
+
+/// Prefix of endpoints that are Unix domain sockets, as in `unix:///path/to.sock`.
+pub const UNIX_SOCKET_SCHEME: &str = "unix://";
+
+/// The socket path of a `unix://` observer endpoint.
+pub fn unix_socket_path(endpoint: &str) -> Option<&str> {
+    endpoint.strip_prefix(UNIX_SOCKET_SCHEME)
+}
+
+/// A connection to an HTTP observer, over TCP or over a Unix domain socket.
+#[derive(Clone)]
+enum ObserverStream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl ObserverStream {
+    async fn connect(endpoint: &str) -> io::Result<ObserverStream> {
+        match unix_socket_path(endpoint) {
+            #[cfg(unix)]
+            Some(path) => Ok(ObserverStream::Unix(UnixStream::connect(path).await?)),
+            #[cfg(not(unix))]
+            Some(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Unix domain sockets are not supported on this platform",
+            )),
+            None => Ok(ObserverStream::Tcp(TcpStream::connect(endpoint).await?)),
+        }
+    }
+}
+
+impl Read for ObserverStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            ObserverStream::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(unix)]
+            ObserverStream::Unix(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl Write for ObserverStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            ObserverStream::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(unix)]
+            ObserverStream::Unix(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            ObserverStream::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(unix)]
+            ObserverStream::Unix(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            ObserverStream::Tcp(stream) => Pin::new(stream).poll_close(cx),
+            #[cfg(unix)]
+            ObserverStream::Unix(stream) => Pin::new(stream).poll_close(cx),
+        }
+    }
+}
+
+/// What an observer answers to `GET /capabilities`.
+#[derive(Debug, Deserialize)]
+struct ObserverCapabilities {
+    schema_versions: Vec<u32>,
 }
 
 struct ReceiptPayloadInfo<'a> {
@@ -65,6 +177,21 @@ pub const PATH_MINED_MICROBLOCK: &str = "mined_microblock";
 pub const PATH_BURN_BLOCK_SUBMIT: &str = "new_burn_block";
 pub const PATH_BLOCK_PROCESSED: &str = "new_block";
 pub const PATH_ATTACHMENT_PROCESSED: &str = "attachments/new";
+pub const PATH_BLOCK_PROPAGATION: &str = "block_propagation";
+pub const PATH_BURNCHAIN_REORG: &str = "burnchain_reorg";
+pub const PATH_TENURE_TIMES: &str = "tenure_times";
+pub const PATH_NODE_STALL: &str = "node_stall";
+pub const PATH_BLOCK_TELEMETRY: &str = "block_telemetry";
+pub const PATH_CAPABILITIES: &str = "capabilities";
+
+/// Payload schema for observers that don't serve `GET /capabilities`, and for in-process
+/// subscribers. It never changes, so that existing observers keep working.
+pub const EVENT_SCHEMA_VERSION_LEGACY: u32 = 1;
+/// Newest payload schema the dispatcher emits. Version 2 posts `new_mempool_tx` transactions as
+/// `{"txid", "raw_tx"}` objects instead of bare raw transactions.
+pub const EVENT_SCHEMA_VERSION_LATEST: u32 = 2;
+/// Header carrying the schema version of every POSTed payload.
+pub const EVENT_SCHEMA_VERSION_HEADER: &str = "X-Stacks-Event-Schema-Version";
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MinedBlockEvent {
@@ -75,6 +202,7 @@ pub struct MinedBlockEvent {
     pub anchored_cost: ExecutionCost,
     pub confirmed_microblocks_cost: ExecutionCost,
     pub tx_events: Vec<TransactionEvent>,
+    pub tenure_report: TenureCostReport,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -86,63 +214,239 @@ pub struct MinedMicroblockEvent {
     pub anchor_block: BlockHeaderHash,
 }
 
+/// An event as delivered to in-process subscribers, carrying the payload that HTTP observers
+/// receive on the corresponding path in the legacy schema. Block, microblock, burn block,
+/// mempool and attachment events are not decoded: their variants hold the payload as JSON, to
+/// be read the same way an HTTP observer would. The other events arrive as typed structs.
+#[derive(Clone, Debug)]
+pub enum ChainEvent {
+    /// JSON payload of `/new_block`
+    Block(serde_json::Value),
+    /// JSON payload of `/new_microblocks`
+    Microblocks(serde_json::Value),
+    /// JSON payload of `/new_burn_block`
+    BurnBlock(serde_json::Value),
+    /// JSON payload of `/new_mempool_tx`
+    MempoolTxs(serde_json::Value),
+    /// JSON payload of `/drop_mempool_tx`
+    DroppedMempoolTxs(serde_json::Value),
+    MinedBlock(MinedBlockEvent),
+    MinedMicroblock(MinedMicroblockEvent),
+    /// JSON payload of `/attachments/new`
+    Attachments(serde_json::Value),
+    BlockPropagation(BlockPropagationTimes),
+    BurnchainReorg(BurnchainReorg),
+    TenureTimes(TenureTimes),
+    NodeStall(NodeStall),
+    BlockTelemetry(BlockValidationTelemetry),
+}
+
+impl ChainEvent {
+    fn from_payload(path: &str, payload: &serde_json::Value) -> Result<ChainEvent, String> {
+        let event = match path {
+            PATH_BLOCK_PROCESSED => ChainEvent::Block(payload.clone()),
+            PATH_MICROBLOCK_SUBMIT => ChainEvent::Microblocks(payload.clone()),
+            PATH_BURN_BLOCK_SUBMIT => ChainEvent::BurnBlock(payload.clone()),
+            PATH_MEMPOOL_TX_SUBMIT => ChainEvent::MempoolTxs(payload.clone()),
+            PATH_MEMPOOL_TX_DROP => ChainEvent::DroppedMempoolTxs(payload.clone()),
+            PATH_MINED_BLOCK => ChainEvent::MinedBlock(
+                serde_json::from_value(payload.clone()).map_err(|e| e.to_string())?,
+            ),
+            PATH_MINED_MICROBLOCK => ChainEvent::MinedMicroblock(
+                serde_json::from_value(payload.clone()).map_err(|e| e.to_string())?,
+            ),
+            PATH_ATTACHMENT_PROCESSED => ChainEvent::Attachments(payload.clone()),
+            PATH_BLOCK_PROPAGATION => ChainEvent::BlockPropagation(
+                serde_json::from_value(payload.clone()).map_err(|e| e.to_string())?,
+            ),
+            PATH_BURNCHAIN_REORG => ChainEvent::BurnchainReorg(
+                serde_json::from_value(payload.clone()).map_err(|e| e.to_string())?,
+            ),
+            PATH_TENURE_TIMES => ChainEvent::TenureTimes(
+                serde_json::from_value(payload.clone()).map_err(|e| e.to_string())?,
+            ),
+            PATH_NODE_STALL => ChainEvent::NodeStall(
+                serde_json::from_value(payload.clone()).map_err(|e| e.to_string())?,
+            ),
+            PATH_BLOCK_TELEMETRY => ChainEvent::BlockTelemetry(
+                serde_json::from_value(payload.clone()).map_err(|e| e.to_string())?,
+            ),
+            _ => return Err(format!("no event is posted to {}", path)),
+        };
+        Ok(event)
+    }
+
+    /// The payload, serialized as HTTP observers receive it in the legacy schema.
+    pub fn payload_json(&self) -> Result<String, serde_json::Error> {
+        match self {
+            ChainEvent::Block(payload)
+            | ChainEvent::Microblocks(payload)
+            | ChainEvent::BurnBlock(payload)
+            | ChainEvent::MempoolTxs(payload)
+            | ChainEvent::DroppedMempoolTxs(payload)
+            | ChainEvent::Attachments(payload) => serde_json::to_string(payload),
+            ChainEvent::MinedBlock(mined_block) => serde_json::to_string(mined_block),
+            ChainEvent::MinedMicroblock(mined_microblock) => {
+                serde_json::to_string(mined_microblock)
+            }
+            ChainEvent::BlockPropagation(times) => serde_json::to_string(times),
+            ChainEvent::BurnchainReorg(reorg) => serde_json::to_string(reorg),
+            ChainEvent::TenureTimes(times) => serde_json::to_string(times),
+            ChainEvent::NodeStall(stall) => serde_json::to_string(stall),
+            ChainEvent::BlockTelemetry(telemetry) => serde_json::to_string(telemetry),
+        }
+    }
+
+    /// The path HTTP observers receive this event on.
+    pub fn path(&self) -> &'static str {
+        match self {
+            ChainEvent::Block(_) => PATH_BLOCK_PROCESSED,
+            ChainEvent::Microblocks(_) => PATH_MICROBLOCK_SUBMIT,
+            ChainEvent::BurnBlock(_) => PATH_BURN_BLOCK_SUBMIT,
+            ChainEvent::MempoolTxs(_) => PATH_MEMPOOL_TX_SUBMIT,
+            ChainEvent::DroppedMempoolTxs(_) => PATH_MEMPOOL_TX_DROP,
+            ChainEvent::MinedBlock(_) => PATH_MINED_BLOCK,
+            ChainEvent::MinedMicroblock(_) => PATH_MINED_MICROBLOCK,
+            ChainEvent::Attachments(_) => PATH_ATTACHMENT_PROCESSED,
+            ChainEvent::BlockPropagation(_) => PATH_BLOCK_PROPAGATION,
+            ChainEvent::BurnchainReorg(_) => PATH_BURNCHAIN_REORG,
+            ChainEvent::TenureTimes(_) => PATH_TENURE_TIMES,
+            ChainEvent::NodeStall(_) => PATH_NODE_STALL,
+            ChainEvent::BlockTelemetry(_) => PATH_BLOCK_TELEMETRY,
+        }
+    }
+}
+
+/// Receives chain events in-process, filtered by event keys just like an HTTP event observer.
+/// `on_event` is called on the node's own threads as events happen, so it should hand any slow
+/// work off to another thread.
+pub trait ChainEventSubscriber: Send + Sync {
+    fn on_event(&self, event: ChainEvent);
+}
+
+/// Number of events dropped because a subscriber's channel was full.
+static SUBSCRIBER_EVENTS_DROPPED: AtomicU64 = AtomicU64::new(0);
+
+/// How many events have been dropped so far because a subscriber's channel was full.
+pub fn get_subscriber_events_dropped() -> u64 {
+    SUBSCRIBER_EVENTS_DROPPED.load(Ordering::SeqCst)
+}
+
+/// Forwards events into a channel, without ever blocking the node: events are dropped (and
+/// counted in `get_subscriber_events_dropped()`) while the channel is full, and once the
+/// receiver hangs up.
+impl ChainEventSubscriber for SyncSender<ChainEvent> {
+    fn on_event(&self, event: ChainEvent) {
+        match self.try_send(event) {
+            Ok(()) => {}
+            Err(TrySendError::Full(event)) => {
+                SUBSCRIBER_EVENTS_DROPPED.fetch_add(1, Ordering::SeqCst);
+                warn!(
+                    "Event dispatcher: subscriber channel full, dropping event";
+                    "path" => event.path()
+                );
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                debug!("Event dispatcher: subscriber channel disconnected");
+            }
+        }
+    }
+}
+
 impl EventObserver {
+    /// `payload` is built in the latest schema.
     fn send_payload(&self, payload: &serde_json::Value, path: &str) {
-        let body = match serde_json::to_vec(&payload) {
-            Ok(body) => body,
-            Err(err) => {
-                error!("Event dispatcher: serialization failed  - {:?}", err);
-                return;
+        if let Some(ref subscriber) = self.subscriber {
+            let payload = Self::payload_for_schema(path, payload, EVENT_SCHEMA_VERSION_LEGACY);
+            match ChainEvent::from_payload(path, &payload) {
+                Ok(event) => subscriber.on_event(event),
+                Err(e) => error!("Event dispatcher: failed to build event - {}", e),
             }
-        };
-
-        let url = {
-            let joined_components = match path.starts_with("/") {
-                true => format!("{}{}", &self.endpoint, path),
-                false => format!("{}/{}", &self.endpoint, path),
-            };
-            let url = format!("http://{}", joined_components);
-            Url::parse(&url).expect(&format!(
-                "Event dispatcher: unable to parse {} as a URL",
-                url
-            ))
-        };
+            return;
+        }
 
+        let url = self.url(path);
         let backoff = Duration::from_millis((1.0 * 1_000.0) as u64);
 
         loop {
-            let body = body.clone();
-            let mut req = Request::new(Method::Post, url.clone());
-            req.append_header("Content-Type", "application/json");
-            req.set_body(body);
-
-            let response = async_std::task::block_on(async {
-                let stream = match TcpStream::connect(self.endpoint.clone()).await {
-                    Ok(stream) => stream,
-                    Err(err) => {
-                        warn!("Event dispatcher: connection failed  - {:?}", err);
-                        return None;
-                    }
-                };
+            // until the observer has told us which schemas it accepts, there is nothing to send
+            let schema_version = match self.schema_version() {
+                Ok(schema_version) => schema_version,
+                Err(err) => {
+                    warn!("Event dispatcher: capabilities handshake failed - {}", err);
+                    sleep(backoff);
+                    continue;
+                }
+            };
 
-                match client::connect(stream, req).await {
-                    Ok(response) => Some(response),
-                    Err(err) => {
-                        warn!("Event dispatcher: rpc invocation failed  - {:?}", err);
-                        return None;
+            let body = match serde_json::to_vec(&Self::payload_for_schema(
+                path,
+                payload,
+                schema_version,
+            )) {
+                Ok(body) => body,
+                Err(err) => {
+                    error!("Event dispatcher: serialization failed  - {:?}", err);
+                    return;
+                }
+            };
+
+            let (result, reused_connection) = match self.http2_client {
+                Some(ref client) => {
+                    let req = client
+                        .post(url.as_str())
+                        .header("Content-Type", "application/json")
+                        .header(EVENT_SCHEMA_VERSION_HEADER, schema_version.to_string())
+                        .body(body);
+                    (Ok(Self::send_http2(req).map(|(status, _)| status)), false)
+                }
+                None => {
+                    let mut req = Request::new(Method::Post, url.clone());
+                    req.append_header("Content-Type", "application/json");
+                    req.append_header(EVENT_SCHEMA_VERSION_HEADER, schema_version.to_string());
+                    if !self.keep_alive {
+                        req.append_header("Connection", "close");
                     }
+                    req.set_body(body);
+
+                    // a connection left over from an earlier delivery may have been closed by
+                    // the observer in the meantime, so its failure is retried right away on a
+                    // new one
+                    let idle_connection = self
+                        .connection
+                        .lock()
+                        .expect("FATAL: event observer connection lock poisoned")
+                        .take();
+                    let reused_connection = idle_connection.is_some();
+                    (
+                        self.block_on(self.post(idle_connection, req)),
+                        reused_connection,
+                    )
                 }
-            });
+            };
 
-            if let Some(response) = response {
-                if response.status().is_success() {
+            match result {
+                Ok(Ok(status)) if status.is_success() => {
                     debug!(
                         "Event dispatcher: Successful POST"; "url" => %url
                     );
                     break;
-                } else {
+                }
+                Ok(Ok(status)) => {
                     error!(
-                        "Event dispatcher: Failed POST"; "url" => %url, "err" => ?response
+                        "Event dispatcher: Failed POST"; "url" => %url, "status" => %status
+                    );
+                }
+                Ok(Err(err)) => {
+                    if reused_connection {
+                        debug!("Event dispatcher: kept-alive connection failed - {}", err);
+                        continue;
+                    }
+                    warn!("Event dispatcher: rpc invocation failed  - {}", err);
+                }
+                Err(_) => {
+                    warn!(
+                        "Event dispatcher: POST timed out"; "url" => %url, "timeout_ms" => self.timeout.unwrap_or_default().as_millis()
                     );
                 }
             }
@@ -150,15 +454,186 @@ impl EventObserver {
         }
     }
 
+    /// Runs `request` to completion, giving up after the observer's timeout if it has one.
+    fn block_on<F: std::future::Future>(
+        &self,
+        request: F,
+    ) -> Result<F::Output, async_std::future::TimeoutError> {
+        match self.timeout {
+            Some(timeout) => {
+                async_std::task::block_on(async_std::future::timeout(timeout, request))
+            }
+            None => Ok(async_std::task::block_on(request)),
+        }
+    }
+
+    fn url(&self, path: &str) -> Url {
+        // requests over a Unix domain socket still need a host for their URL and `Host` header
+        let host = match unix_socket_path(&self.endpoint) {
+            Some(_) => "localhost",
+            None => &self.endpoint,
+        };
+        let joined_components = match path.starts_with("/") {
+            true => format!("{}{}", host, path),
+            false => format!("{}/{}", host, path),
+        };
+        let url = format!("http://{}", joined_components);
+        Url::parse(&url).expect(&format!(
+            "Event dispatcher: unable to parse {} as a URL",
+            url
+        ))
+    }
+
+    /// The payload schema to send to this observer, asking the observer with `GET /capabilities`
+    /// the first time. Fails if the observer could not be reached, so that the handshake is
+    /// tried again.
+    fn schema_version(&self) -> Result<u32, String> {
+        let mut schema_version = self
+            .schema_version
+            .lock()
+            .expect("FATAL: event observer schema version lock poisoned");
+        if let Some(schema_version) = *schema_version {
+            return Ok(schema_version);
+        }
+
+        let url = self.url(PATH_CAPABILITIES);
+        let (status, body) = match self.http2_client {
+            Some(ref client) => Self::send_http2(client.get(url.as_str()))?,
+            None => self
+                .block_on(self.get_capabilities(url.clone()))
+                .map_err(|_| format!("GET {} timed out", &url))??,
+        };
+
+        let negotiated = if status.is_success() {
+            Self::schema_version_from_capabilities(&body)
+        } else {
+            EVENT_SCHEMA_VERSION_LEGACY
+        };
+        info!(
+            "Event dispatcher: observer accepts schema version {}", negotiated;
+            "endpoint" => &self.endpoint, "capabilities_status" => %status
+        );
+        *schema_version = Some(negotiated);
+        Ok(negotiated)
+    }
+
+    async fn get_capabilities(&self, url: Url) -> Result<(StatusCode, Vec<u8>), String> {
+        let stream = ObserverStream::connect(&self.endpoint)
+            .await
+            .map_err(|e| format!("connection failed - {:?}", e))?;
+
+        let mut req = Request::new(Method::Get, url);
+        req.append_header("Connection", "close");
+        let mut response = client::connect(stream, req)
+            .await
+            .map_err(|e| format!("{:?}", e))?;
+        let body = response
+            .body_bytes()
+            .await
+            .map_err(|e| format!("failed to read response - {:?}", e))?;
+        Ok((response.status(), body))
+    }
+
+    /// Sends `req` over the observer's HTTP/2 connection, which the client opens the first
+    /// time, and reads the whole response. The client bounds the request by the observer's
+    /// timeout, if it has one.
+    fn send_http2(req: reqwest::blocking::RequestBuilder) -> Result<(StatusCode, Vec<u8>), String> {
+        let response = req.send().map_err(|e| format!("{}", e))?;
+        let status = StatusCode::try_from(response.status().as_u16())
+            .map_err(|e| format!("unexpected response status - {}", e))?;
+        let body = response
+            .bytes()
+            .map_err(|e| format!("failed to read response - {}", e))?;
+        Ok((status, body.to_vec()))
+    }
+
+    /// Picks the newest schema that both the dispatcher and the observer accept, falling back to
+    /// the legacy schema if the observer's capabilities can't be understood.
+    fn schema_version_from_capabilities(body: &[u8]) -> u32 {
+        match serde_json::from_slice::<ObserverCapabilities>(body) {
+            Ok(capabilities) => capabilities
+                .schema_versions
+                .into_iter()
+                .filter(|version| *version <= EVENT_SCHEMA_VERSION_LATEST)
+                .max()
+                .unwrap_or(EVENT_SCHEMA_VERSION_LEGACY),
+            Err(e) => {
+                warn!("Event dispatcher: unreadable observer capabilities - {}", e);
+                EVENT_SCHEMA_VERSION_LEGACY
+            }
+        }
+    }
+
+    /// Rewrites a payload built in the latest schema into `schema_version`.
+    fn payload_for_schema<'a>(
+        path: &str,
+        payload: &'a serde_json::Value,
+        schema_version: u32,
+    ) -> Cow<'a, serde_json::Value> {
+        match path {
+            PATH_MEMPOOL_TX_SUBMIT if schema_version < 2 => {
+                let raw_txs = payload
+                    .as_array()
+                    .map(|txs| txs.iter().map(|tx| tx["raw_tx"].clone()).collect())
+                    .unwrap_or_default();
+                Cow::Owned(serde_json::Value::Array(raw_txs))
+            }
+            _ => Cow::Borrowed(payload),
+        }
+    }
+
+    /// Sends `req` over `connection`, or over a new connection if there is none, and reads the
+    /// whole response. The connection is kept for the next delivery if both sides allow it.
+    async fn post(
+        &self,
+        connection: Option<ObserverStream>,
+        req: Request,
+    ) -> Result<StatusCode, String> {
+        let stream = match connection {
+            Some(stream) => stream,
+            None => ObserverStream::connect(&self.endpoint)
+                .await
+                .map_err(|e| format!("connection failed - {:?}", e))?,
+        };
+
+        let mut response = client::connect(stream.clone(), req)
+            .await
+            .map_err(|e| format!("{:?}", e))?;
+
+        // the response body has to be consumed before the connection can carry another request
+        response
+            .body_bytes()
+            .await
+            .map_err(|e| format!("failed to read response - {:?}", e))?;
+
+        let observer_closes = response
+            .header("Connection")
+            .map(|values| values.last().as_str().eq_ignore_ascii_case("close"))
+            .unwrap_or(false);
+        let delimited = response.header("Content-Length").is_some()
+            || response.header("Transfer-Encoding").is_some();
+        if self.keep_alive && !observer_closes && delimited {
+            *self
+                .connection
+                .lock()
+                .expect("FATAL: event observer connection lock poisoned") = Some(stream);
+        }
+
+        Ok(response.status())
+    }
+
     fn make_new_mempool_txs_payload(transactions: Vec<StacksTransaction>) -> serde_json::Value {
-        let raw_txs = transactions
+        let txs = transactions
             .into_iter()
             .map(|tx| {
-                serde_json::Value::String(format!("0x{}", &bytes_to_hex(&tx.serialize_to_vec())))
+                json!({
+                    "txid": format!("0x{}", &tx.txid()),
+                    "raw_tx": format!("0x{}", &bytes_to_hex(&tx.serialize_to_vec())),
+                })
             })
             .collect();
 
-        serde_json::Value::Array(raw_txs)
+        serde_json::Value::Array(txs)
     }
 
     fn make_new_burn_block_payload(
@@ -322,10 +797,30 @@ impl EventObserver {
         self.send_payload(payload, PATH_MINED_MICROBLOCK);
     }
 
+    fn send_block_propagation(&self, payload: &serde_json::Value) {
+        self.send_payload(payload, PATH_BLOCK_PROPAGATION);
+    }
+
+    fn send_tenure_times(&self, payload: &serde_json::Value) {
+        self.send_payload(payload, PATH_TENURE_TIMES);
+    }
+
     fn send_new_burn_block(&self, payload: &serde_json::Value) {
         self.send_payload(payload, PATH_BURN_BLOCK_SUBMIT);
     }
 
+    fn send_burnchain_reorg(&self, payload: &serde_json::Value) {
+        self.send_payload(payload, PATH_BURNCHAIN_REORG);
+    }
+
+    fn send_node_stall(&self, payload: &serde_json::Value) {
+        self.send_payload(payload, PATH_NODE_STALL);
+    }
+
+    fn send_block_telemetry(&self, payload: &serde_json::Value) {
+        self.send_payload(payload, PATH_BLOCK_TELEMETRY);
+    }
+
     fn send(
         &self,
         filtered_events: Vec<(usize, &(bool, Txid, &StacksTransactionEvent))>,
@@ -399,6 +894,9 @@ pub struct EventDispatcher {
     any_event_observers_lookup: HashSet<u16>,
     miner_observers_lookup: HashSet<u16>,
     mined_microblocks_observers_lookup: HashSet<u16>,
+    block_propagation_observers_lookup: HashSet<u16>,
+    tenure_times_observers_lookup: HashSet<u16>,
+    block_telemetry_observers_lookup: HashSet<u16>,
     boot_receipts: Arc<Mutex<Option<Vec<StacksTransactionReceipt>>>>,
 }
 
@@ -417,6 +915,7 @@ impl MemPoolEventDispatcher for EventDispatcher {
         consumed: &ExecutionCost,
         confirmed_microblock_cost: &ExecutionCost,
         tx_events: Vec<TransactionEvent>,
+        tenure_report: &TenureCostReport,
     ) {
         self.process_mined_block_event(
             target_burn_height,
@@ -425,6 +924,7 @@ impl MemPoolEventDispatcher for EventDispatcher {
             consumed,
             confirmed_microblock_cost,
             tx_events,
+            tenure_report,
         )
     }
 
@@ -473,7 +973,8 @@ impl BlockEventDispatcher for EventDispatcher {
             parent_burn_block_timestamp,
             anchored_consumed,
             mblock_confirmed_consumed,
-        )
+        );
+        self.process_block_propagation(&metadata.consensus_hash);
     }
 
     fn announce_burn_block(
@@ -496,6 +997,10 @@ impl BlockEventDispatcher for EventDispatcher {
     fn dispatch_boot_receipts(&mut self, receipts: Vec<StacksTransactionReceipt>) {
         self.process_boot_receipts(receipts)
     }
+
+    fn announce_block_telemetry(&self, telemetry: &BlockValidationTelemetry) {
+        self.process_block_telemetry(telemetry)
+    }
 }
 
 impl EventDispatcher {
@@ -512,6 +1017,9 @@ impl EventDispatcher {
             boot_receipts: Arc::new(Mutex::new(None)),
             miner_observers_lookup: HashSet::new(),
             mined_microblocks_observers_lookup: HashSet::new(),
+            block_propagation_observers_lookup: HashSet::new(),
+            tenure_times_observers_lookup: HashSet::new(),
+            block_telemetry_observers_lookup: HashSet::new(),
         }
     }
 
@@ -550,6 +1058,49 @@ impl EventDispatcher {
         }
     }
 
+    /// Announce a burnchain reorg to the observers of burn blocks.
+    pub fn process_burnchain_reorg(&self, reorg: &BurnchainReorg) {
+        let interested_observers: Vec<_> = self
+            .registered_observers
+            .iter()
+            .enumerate()
+            .filter(|(obs_id, _observer)| {
+                self.burn_block_observers_lookup.contains(&(*obs_id as u16))
+                    || self.any_event_observers_lookup.contains(&(*obs_id as u16))
+            })
+            .collect();
+        if interested_observers.len() < 1 {
+            return;
+        }
+
+        let payload = serde_json::to_value(reorg).unwrap();
+
+        for (_, observer) in interested_observers.iter() {
+            observer.send_burnchain_reorg(&payload);
+        }
+    }
+
+    pub fn process_node_stall(&self, stall: &NodeStall) {
+        let interested_observers: Vec<_> = self
+            .registered_observers
+            .iter()
+            .enumerate()
+            .filter(|(obs_id, _observer)| {
+                self.burn_block_observers_lookup.contains(&(*obs_id as u16))
+                    || self.any_event_observers_lookup.contains(&(*obs_id as u16))
+            })
+            .collect();
+        if interested_observers.len() < 1 {
+            return;
+        }
+
+        let payload = serde_json::to_value(stall).unwrap();
+
+        for (_, observer) in interested_observers.iter() {
+            observer.send_node_stall(&payload);
+        }
+    }
+
     /// Iterates through tx receipts, and then the events corresponding to each receipt to
     /// generate a dispatch matrix & event vector.
     ///
@@ -823,6 +1374,7 @@ impl EventDispatcher {
         consumed: &ExecutionCost,
         confirmed_microblock_cost: &ExecutionCost,
         tx_events: Vec<TransactionEvent>,
+        tenure_report: &TenureCostReport,
     ) {
         let interested_observers: Vec<_> = self
             .registered_observers
@@ -842,6 +1394,7 @@ impl EventDispatcher {
             anchored_cost: consumed.clone(),
             confirmed_microblocks_cost: confirmed_microblock_cost.clone(),
             tx_events,
+            tenure_report: tenure_report.clone(),
         })
         .unwrap();
 
@@ -884,6 +1437,80 @@ impl EventDispatcher {
         }
     }
 
+    /// Send the propagation times of a newly-processed block, if this node saw its sortition.
+    pub fn process_block_propagation(&self, consensus_hash: &ConsensusHash) {
+        let interested_observers: Vec<_> = self
+            .registered_observers
+            .iter()
+            .enumerate()
+            .filter(|(obs_id, _observer)| {
+                self.block_propagation_observers_lookup
+                    .contains(&(*obs_id as u16))
+            })
+            .collect();
+        if interested_observers.len() < 1 {
+            return;
+        }
+
+        let times = match get_block_propagation_times(consensus_hash) {
+            Some(times) => times,
+            None => return,
+        };
+        let payload = serde_json::to_value(times).unwrap();
+
+        for (_, observer) in interested_observers.iter() {
+            observer.send_block_propagation(&payload);
+        }
+    }
+
+    /// Send the times of this miner's tenure on `burn_header_hash`, once its sortition is known.
+    pub fn process_tenure_times(&self, burn_header_hash: &BurnchainHeaderHash) {
+        let interested_observers: Vec<_> = self
+            .registered_observers
+            .iter()
+            .enumerate()
+            .filter(|(obs_id, _observer)| {
+                self.tenure_times_observers_lookup
+                    .contains(&(*obs_id as u16))
+            })
+            .collect();
+        if interested_observers.len() < 1 {
+            return;
+        }
+
+        let times = match get_tenure_times(burn_header_hash) {
+            Some(times) if times.sortition_time_ms.is_some() => times,
+            _ => return,
+        };
+        let payload = serde_json::to_value(times).unwrap();
+
+        for (_, observer) in interested_observers.iter() {
+            observer.send_tenure_times(&payload);
+        }
+    }
+
+    /// Send how a newly-processed block's validation went.
+    pub fn process_block_telemetry(&self, telemetry: &BlockValidationTelemetry) {
+        let interested_observers: Vec<_> = self
+            .registered_observers
+            .iter()
+            .enumerate()
+            .filter(|(obs_id, _observer)| {
+                self.block_telemetry_observers_lookup
+                    .contains(&(*obs_id as u16))
+            })
+            .collect();
+        if interested_observers.len() < 1 {
+            return;
+        }
+
+        let payload = serde_json::to_value(telemetry).unwrap();
+
+        for (_, observer) in interested_observers.iter() {
+            observer.send_block_telemetry(&payload);
+        }
+    }
+
     pub fn process_dropped_mempool_txs(&self, txs: Vec<Txid>, reason: MemPoolDropReason) {
         // lazily assemble payload only if we have observers
         let interested_observers: Vec<_> = self
@@ -949,14 +1576,76 @@ impl EventDispatcher {
     }
 
     pub fn register_observer(&mut self, conf: &EventObserverConfig) {
-        info!("Registering event observer at: {}", conf.endpoint);
+        if let EventObserverSink::Nats {
+            subject_prefix,
+            jetstream,
+        } = &conf.sink
+        {
+            info!(
+                "Registering NATS event sink at: {}", conf.endpoint;
+                "subject_prefix" => subject_prefix, "jetstream" => jetstream
+            );
+            let publisher = NatsPublisher::spawn(
+                conf.endpoint.clone(),
+                subject_prefix.clone(),
+                *jetstream,
+                conf.connect_timeout(),
+            );
+            self.register_subscriber(Arc::new(publisher), &conf.events_keys);
+            return;
+        }
+
+        info!(
+            "Registering event observer at: {}", conf.endpoint;
+            "http2" => conf.http2
+        );
+        let timeout = conf.timeout_ms.map(Duration::from_millis);
+        let http2_client = if conf.http2 {
+            let client = reqwest::blocking::Client::builder()
+                .http2_prior_knowledge()
+                .timeout(timeout)
+                .connect_timeout(conf.connect_timeout())
+                .build()
+                .expect("FATAL: failed to create the HTTP/2 client for an event observer");
+            Some(client)
+        } else {
+            None
+        };
         let event_observer = EventObserver {
             endpoint: conf.endpoint.clone(),
+            timeout,
+            keep_alive: conf.keep_alive,
+            connection: Arc::new(Mutex::new(None)),
+            http2_client,
+            subscriber: None,
+            schema_version: Arc::new(Mutex::new(None)),
         };
+        self.add_observer(event_observer, &conf.events_keys);
+    }
 
+    /// Registers an in-process subscriber for the events selected by `events_keys`.
+    pub fn register_subscriber(
+        &mut self,
+        subscriber: Arc<dyn ChainEventSubscriber>,
+        events_keys: &[EventKeyType],
+    ) {
+        info!("Registering in-process event subscriber");
+        let event_observer = EventObserver {
+            endpoint: String::new(),
+            timeout: None,
+            keep_alive: false,
+            connection: Arc::new(Mutex::new(None)),
+            http2_client: None,
+            subscriber: Some(subscriber),
+            schema_version: Arc::new(Mutex::new(Some(EVENT_SCHEMA_VERSION_LEGACY))),
+        };
+        self.add_observer(event_observer, events_keys);
+    }
+
+    fn add_observer(&mut self, event_observer: EventObserver, events_keys: &[EventKeyType]) {
         let observer_index = self.registered_observers.len() as u16;
 
-        for event_key_type in conf.events_keys.iter() {
+        for event_key_type in events_keys.iter() {
             match event_key_type {
                 EventKeyType::SmartContractEvent(event_key) => {
                     match self
@@ -1007,9 +1696,515 @@ impl EventDispatcher {
                     self.mined_microblocks_observers_lookup
                         .insert(observer_index);
                 }
+                EventKeyType::BlockPropagation => {
+                    self.block_propagation_observers_lookup
+                        .insert(observer_index);
+                }
+                EventKeyType::TenureTimes => {
+                    self.tenure_times_observers_lookup.insert(observer_index);
+                }
+                EventKeyType::BlockTelemetry => {
+                    self.block_telemetry_observers_lookup.insert(observer_index);
+                }
             }
         }
 
         self.registered_observers.push(event_observer);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    #[cfg(unix)]
+    use std::os::unix::net::UnixListener;
+    use std::sync::mpsc::sync_channel;
+    use std::thread;
+
+    use stacks::types::chainstate::StacksPrivateKey;
+
+    use crate::tests::make_coinbase;
+
+    /// What a test observer received: the number of connections POSTs arrived on, and the
+    /// schema version header and body of each POST.
+    struct ObserverLog {
+        connections: usize,
+        posts: Vec<(String, serde_json::Value)>,
+    }
+
+    /// Where a test observer accepts connections.
+    enum TestListener {
+        Tcp(TcpListener),
+        #[cfg(unix)]
+        Unix(UnixListener),
+    }
+
+    impl From<TcpListener> for TestListener {
+        fn from(listener: TcpListener) -> TestListener {
+            TestListener::Tcp(listener)
+        }
+    }
+
+    #[cfg(unix)]
+    impl From<UnixListener> for TestListener {
+        fn from(listener: UnixListener) -> TestListener {
+            TestListener::Unix(listener)
+        }
+    }
+
+    impl TestListener {
+        fn accept(&self) -> (Box<dyn BufRead>, Box<dyn Write>) {
+            match self {
+                TestListener::Tcp(listener) => {
+                    let (stream, _) = listener.accept().unwrap();
+                    (
+                        Box::new(BufReader::new(stream.try_clone().unwrap())),
+                        Box::new(stream),
+                    )
+                }
+                #[cfg(unix)]
+                TestListener::Unix(listener) => {
+                    let (stream, _) = listener.accept().unwrap();
+                    (
+                        Box::new(BufReader::new(stream.try_clone().unwrap())),
+                        Box::new(stream),
+                    )
+                }
+            }
+        }
+    }
+
+    /// Serves `requests` POSTs, answering `GET /capabilities` with `capabilities` (or 404).
+    fn spawn_observer<L: Into<TestListener> + Send + 'static>(
+        listener: L,
+        requests: usize,
+        capabilities: Option<&'static str>,
+    ) -> thread::JoinHandle<ObserverLog> {
+        thread::spawn(move || {
+            let listener = listener.into();
+            let mut log = ObserverLog {
+                connections: 0,
+                posts: vec![],
+            };
+            while log.posts.len() < requests {
+                let (mut reader, mut writer) = listener.accept();
+                let mut counted = false;
+                while log.posts.len() < requests {
+                    let mut content_length = 0;
+                    let mut request_line = String::new();
+                    let mut schema_version = String::new();
+                    let mut line = String::new();
+                    loop {
+                        line.clear();
+                        if reader.read_line(&mut line).unwrap() == 0 {
+                            break;
+                        }
+                        if line == "\r\n" {
+                            break;
+                        }
+                        if request_line.is_empty() {
+                            request_line = line.clone();
+                        }
+                        let lower = line.to_ascii_lowercase();
+                        if lower.starts_with("content-length:") {
+                            content_length = lower[15..].trim().parse().unwrap();
+                        }
+                        let schema_header =
+                            format!("{}:", EVENT_SCHEMA_VERSION_HEADER.to_ascii_lowercase());
+                        if lower.starts_with(&schema_header) {
+                            schema_version = lower[schema_header.len()..].trim().to_string();
+                        }
+                    }
+                    if line.is_empty() {
+                        // the dispatcher hung up
+                        break;
+                    }
+                    if request_line.starts_with("GET /capabilities") {
+                        let response = match capabilities {
+                            Some(body) => format!(
+                                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                                body.len(),
+                                body
+                            ),
+                            None => {
+                                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string()
+                            }
+                        };
+                        writer.write_all(response.as_bytes()).unwrap();
+                        continue;
+                    }
+                    let mut body = vec![0u8; content_length];
+                    reader.read_exact(&mut body).unwrap();
+                    writer
+                        .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                        .unwrap();
+                    if !counted {
+                        log.connections += 1;
+                        counted = true;
+                    }
+                    log.posts
+                        .push((schema_version, serde_json::from_slice(&body).unwrap()));
+                }
+            }
+            log
+        })
+    }
+
+    fn make_observer(endpoint: String, keep_alive: bool) -> EventObserver {
+        EventObserver {
+            endpoint,
+            timeout: Some(Duration::from_millis(5_000)),
+            keep_alive,
+            connection: Arc::new(Mutex::new(None)),
+            http2_client: None,
+            subscriber: None,
+            schema_version: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    #[test]
+    fn test_send_payload_reuses_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("{}", listener.local_addr().unwrap());
+        let server = spawn_observer(listener, 3, None);
+
+        let observer = make_observer(endpoint, true);
+        for _ in 0..3 {
+            observer.send_payload(&json!({}), PATH_BURN_BLOCK_SUBMIT);
+        }
+        assert_eq!(server.join().unwrap().connections, 1);
+    }
+
+    #[test]
+    fn test_send_payload_without_keep_alive() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("{}", listener.local_addr().unwrap());
+        let server = spawn_observer(listener, 3, None);
+
+        let observer = make_observer(endpoint, false);
+        for _ in 0..3 {
+            observer.send_payload(&json!({}), PATH_BURN_BLOCK_SUBMIT);
+        }
+        assert!(observer.connection.lock().unwrap().is_none());
+        drop(observer);
+        assert_eq!(server.join().unwrap().connections, 3);
+    }
+
+    #[test]
+    fn test_send_payload_to_slow_observer() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("{}", listener.local_addr().unwrap());
+        // takes longer than the old default timeout to answer each POST
+        let server = thread::spawn(move || {
+            let mut posts = 0;
+            for stream in listener.incoming() {
+                let stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut writer = stream;
+                loop {
+                    let mut content_length = 0;
+                    let mut line = String::new();
+                    loop {
+                        line.clear();
+                        if reader.read_line(&mut line).unwrap() == 0 || line == "\r\n" {
+                            break;
+                        }
+                        let lower = line.to_ascii_lowercase();
+                        if lower.starts_with("content-length:") {
+                            content_length = lower[15..].trim().parse().unwrap();
+                        }
+                    }
+                    if line.is_empty() {
+                        break;
+                    }
+                    let mut body = vec![0u8; content_length];
+                    reader.read_exact(&mut body).unwrap();
+                    posts += 1;
+                    thread::sleep(Duration::from_millis(1_500));
+                    writer
+                        .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                        .unwrap();
+                }
+                if posts > 0 {
+                    return posts;
+                }
+            }
+            posts
+        });
+
+        let mut observer = make_observer(endpoint, true);
+        observer.timeout = None;
+        observer.schema_version = Arc::new(Mutex::new(Some(EVENT_SCHEMA_VERSION_LEGACY)));
+        observer.send_payload(&json!({}), PATH_BURN_BLOCK_SUBMIT);
+        drop(observer);
+        assert_eq!(server.join().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_send_payload_over_http2() {
+        use std::convert::Infallible;
+        use std::sync::atomic::AtomicUsize;
+        use warp::hyper::server::conn::AddrStream;
+        use warp::hyper::service::make_service_fn;
+        use warp::hyper::Server;
+        use warp::path::FullPath;
+        use warp::Filter;
+
+        // an observer that only speaks HTTP/2
+        let posts = Arc::new(Mutex::new(vec![]));
+        let capabilities = warp::get()
+            .and(warp::path(PATH_CAPABILITIES))
+            .map(|| warp::reply::json(&json!({"schema_versions": [2]})));
+        let post = {
+            let posts = posts.clone();
+            warp::post()
+                .and(warp::path::full())
+                .and(warp::header::<String>(EVENT_SCHEMA_VERSION_HEADER))
+                .and(warp::body::json())
+                .map(
+                    move |path: FullPath, version: String, body: serde_json::Value| {
+                        posts
+                            .lock()
+                            .unwrap()
+                            .push((path.as_str().to_string(), version, body));
+                        warp::reply()
+                    },
+                )
+        };
+        let routes = capabilities.or(post);
+        let connections = Arc::new(AtomicUsize::new(0));
+        let make_service = {
+            let connections = connections.clone();
+            make_service_fn(move |_: &AddrStream| {
+                connections.fetch_add(1, Ordering::SeqCst);
+                let service = warp::service(routes.clone());
+                async move { Ok::<_, Infallible>(service) }
+            })
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("{}", listener.local_addr().unwrap());
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        {
+            let _guard = runtime.enter();
+            let server = Server::from_tcp(listener)
+                .unwrap()
+                .http2_only(true)
+                .serve(make_service);
+            runtime.spawn(server);
+        }
+
+        let mut dispatcher = EventDispatcher::new();
+        dispatcher.register_observer(&EventObserverConfig {
+            endpoint,
+            events_keys: vec![EventKeyType::AnyEvent],
+            http2: true,
+            ..EventObserverConfig::default()
+        });
+        let observer = dispatcher.registered_observers[0].clone();
+
+        // deliveries from several threads share the one connection
+        let senders: Vec<_> = (0..4)
+            .map(|i| {
+                let observer = observer.clone();
+                thread::spawn(move || {
+                    observer
+                        .send_payload(&json!({ "burn_block_height": i }), PATH_BURN_BLOCK_SUBMIT)
+                })
+            })
+            .collect();
+        for sender in senders {
+            sender.join().unwrap();
+        }
+
+        let mut posts = posts.lock().unwrap().clone();
+        posts.sort_by_key(|(_, _, body)| body["burn_block_height"].as_u64());
+        assert_eq!(
+            posts,
+            (0..4)
+                .map(|i| (
+                    format!("/{}", PATH_BURN_BLOCK_SUBMIT),
+                    "2".to_string(),
+                    json!({ "burn_block_height": i })
+                ))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_send_payload_over_unix_socket() {
+        let path = "/tmp/test_send_payload_over_unix_socket.sock";
+        if fs::metadata(path).is_ok() {
+            fs::remove_file(path).unwrap();
+        }
+        let listener = UnixListener::bind(path).unwrap();
+        let server = spawn_observer(listener, 2, Some(r#"{"schema_versions": [2]}"#));
+
+        let observer = make_observer(format!("{}{}", UNIX_SOCKET_SCHEME, path), true);
+        observer.send_payload(&json!({}), PATH_BURN_BLOCK_SUBMIT);
+        observer.send_payload(&json!({"burn_block_height": 1}), PATH_BURN_BLOCK_SUBMIT);
+        let log = server.join().unwrap();
+        assert_eq!(log.connections, 1);
+        assert_eq!(
+            log.posts,
+            vec![
+                ("2".to_string(), json!({})),
+                ("2".to_string(), json!({"burn_block_height": 1}))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_schema_version_negotiation() {
+        let payload = json!([{"txid": "0x01", "raw_tx": "0x02"}]);
+
+        // observers without a capabilities endpoint get the legacy schema
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("{}", listener.local_addr().unwrap());
+        let server = spawn_observer(listener, 2, None);
+        let observer = make_observer(endpoint, true);
+        observer.send_payload(&payload, PATH_MEMPOOL_TX_SUBMIT);
+        observer.send_payload(&json!({}), PATH_BURN_BLOCK_SUBMIT);
+        let log = server.join().unwrap();
+        assert_eq!(log.posts[0], ("1".to_string(), json!(["0x02"])));
+        assert_eq!(log.posts[1], ("1".to_string(), json!({})));
+
+        // observers that accept the latest schema get it
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("{}", listener.local_addr().unwrap());
+        let server = spawn_observer(listener, 1, Some(r#"{"schema_versions": [1, 2, 99]}"#));
+        let observer = make_observer(endpoint, true);
+        observer.send_payload(&payload, PATH_MEMPOOL_TX_SUBMIT);
+        let log = server.join().unwrap();
+        assert_eq!(log.posts[0], ("2".to_string(), payload.clone()));
+        assert_eq!(*observer.schema_version.lock().unwrap(), Some(2));
+
+        assert_eq!(
+            EventObserver::schema_version_from_capabilities(br#"{"schema_versions": [1]}"#),
+            EVENT_SCHEMA_VERSION_LEGACY
+        );
+        assert_eq!(
+            EventObserver::schema_version_from_capabilities(br#"{"schema_versions": [3]}"#),
+            EVENT_SCHEMA_VERSION_LEGACY
+        );
+        assert_eq!(
+            EventObserver::schema_version_from_capabilities(b"not json"),
+            EVENT_SCHEMA_VERSION_LEGACY
+        );
+    }
+
+    #[test]
+    fn test_subscribers() {
+        let (burn_send, burn_recv) = sync_channel(10);
+        let (mempool_send, mempool_recv) = sync_channel(10);
+        let mut dispatcher = EventDispatcher::new();
+        dispatcher.register_subscriber(Arc::new(burn_send), &[EventKeyType::BurnchainBlocks]);
+        dispatcher
+            .register_subscriber(Arc::new(mempool_send), &[EventKeyType::MemPoolTransactions]);
+
+        dispatcher.process_burn_block(&BurnchainHeaderHash([1; 32]), 100, vec![], 5, vec![]);
+        dispatcher
+            .process_dropped_mempool_txs(vec![Txid([2; 32])], MemPoolDropReason::STALE_COLLECT);
+
+        match burn_recv.try_recv().unwrap() {
+            ChainEvent::BurnBlock(payload) => {
+                assert_eq!(payload["burn_block_height"], json!(100));
+                assert_eq!(payload["burn_amount"], json!(5));
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        assert!(burn_recv.try_recv().is_err());
+
+        let event = mempool_recv.try_recv().unwrap();
+        assert_eq!(event.path(), PATH_MEMPOOL_TX_DROP);
+        match event {
+            ChainEvent::DroppedMempoolTxs(payload) => {
+                assert_eq!(
+                    payload["dropped_txids"],
+                    json!([format!("0x{}", Txid([2; 32]))])
+                );
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        assert!(mempool_recv.try_recv().is_err());
+
+        // subscribers get the legacy schema
+        let raw_tx = make_coinbase(&StacksPrivateKey::new(), 0, 0);
+        let tx = StacksTransaction::consensus_deserialize(&mut &raw_tx[..]).unwrap();
+        dispatcher.process_new_mempool_txs(vec![tx]);
+        match mempool_recv.try_recv().unwrap() {
+            ChainEvent::MempoolTxs(payload) => {
+                assert_eq!(payload, json!([format!("0x{}", bytes_to_hex(&raw_tx))]));
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn test_subscriber_channel_full() {
+        let (burn_send, burn_recv) = sync_channel(1);
+        let mut dispatcher = EventDispatcher::new();
+        dispatcher.register_subscriber(Arc::new(burn_send), &[EventKeyType::BurnchainBlocks]);
+
+        let dropped = get_subscriber_events_dropped();
+        dispatcher.process_burn_block(&BurnchainHeaderHash([1; 32]), 100, vec![], 5, vec![]);
+        dispatcher.process_burn_block(&BurnchainHeaderHash([2; 32]), 101, vec![], 5, vec![]);
+        assert!(get_subscriber_events_dropped() >= dropped + 1);
+
+        match burn_recv.try_recv().unwrap() {
+            ChainEvent::BurnBlock(payload) => {
+                assert_eq!(payload["burn_block_height"], json!(100));
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        assert!(burn_recv.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_block_telemetry() {
+        let (any_send, any_recv) = sync_channel(10);
+        let (telemetry_send, telemetry_recv) = sync_channel(10);
+        let mut dispatcher = EventDispatcher::new();
+        dispatcher.register_subscriber(Arc::new(any_send), &[EventKeyType::AnyEvent]);
+        dispatcher.register_subscriber(Arc::new(telemetry_send), &[EventKeyType::BlockTelemetry]);
+
+        let telemetry = BlockValidationTelemetry {
+            block_hash: BlockHeaderHash([1; 32]),
+            consensus_hash: ConsensusHash([2; 20]),
+            index_block_hash: StacksBlockId([3; 32]),
+            block_height: 10,
+            block_size: 1000,
+            tx_count: 3,
+            microblock_count: 1,
+            validation_time_us: 5000,
+            tx_processing_time_us: 3000,
+            marf_commit_time_us: 1000,
+            event_generation_time_us: 500,
+            execution_cost: ExecutionCost::zero(),
+            sqlite_write_bytes: 65536,
+        };
+        dispatcher.announce_block_telemetry(&telemetry);
+
+        // only sent to observers that ask for it
+        assert!(any_recv.try_recv().is_err());
+        let event = telemetry_recv.try_recv().unwrap();
+        assert_eq!(event.path(), PATH_BLOCK_TELEMETRY);
+        let payload: serde_json::Value =
+            serde_json::from_str(&event.payload_json().unwrap()).unwrap();
+        assert_eq!(payload["validation_time_us"], json!(5000));
+        assert_eq!(
+            ChainEvent::from_payload(PATH_BLOCK_TELEMETRY, &payload)
+                .unwrap()
+                .path(),
+            PATH_BLOCK_TELEMETRY
+        );
+        match event {
+            ChainEvent::BlockTelemetry(received) => assert_eq!(received, telemetry),
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+}
@@ -0,0 +1,673 @@
+//! Export of the canonical Stacks chain to flat files for analytics tools. Blocks,
+//! transactions, events and account balances over a range of heights are read directly from
+//! the node's databases, so no node needs to be running.
+//!
+//! Transaction results and events only exist in the transaction log, so they are only exported
+//! for blocks processed while the node ran with `STACKS_TRANSACTION_LOG=1`.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use parquet::basic::{Compression, ConvertedType, Repetition, Type as PhysicalType};
+use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::Type as SchemaType;
+
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::chainstate::stacks::{StacksBlockHeader, StacksTransaction, TransactionPayload};
+use stacks::clarity_vm::clarity::ClarityConnection;
+use stacks::codec::StacksMessageCodec;
+use stacks::core::EMPTY_MICROBLOCK_PARENT_HASH;
+use stacks::types::chainstate::StacksBlockId;
+use stacks::util::hash::to_hex;
+use stacks::vm::database::{ClarityDatabase, STXBalance};
+use stacks::vm::types::PrincipalData;
+
+use crate::Config;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Parquet,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ExportFormat, String> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "parquet" => Ok(ExportFormat::Parquet),
+            _ => Err(format!(
+                "Unknown export format '{}': expected 'csv' or 'parquet'",
+                s
+            )),
+        }
+    }
+}
+
+impl ExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// How a column is typed in a Parquet file.  CSV files hold text either way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColumnType {
+    Text,
+    /// A `u64` that fits in an `i64`, such as a height, a nonce or a fee
+    Integer,
+}
+
+use self::ColumnType::{Integer, Text};
+
+/// Number of rows written to each file by an export.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportSummary {
+    pub blocks: u64,
+    pub transactions: u64,
+    pub events: u64,
+    pub balances: u64,
+}
+
+/// Quotes `field` for a CSV file, if it needs to be.
+fn csv_field(field: &str) -> String {
+    if field.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+struct CsvWriter {
+    writer: BufWriter<fs::File>,
+}
+
+impl CsvWriter {
+    fn create(path: &Path, header: &[&str]) -> Result<CsvWriter, String> {
+        let file =
+            fs::File::create(path).map_err(|e| format!("Failed to create {:?}: {}", path, e))?;
+        let mut writer = CsvWriter {
+            writer: BufWriter::new(file),
+        };
+        writer.write_row(header.iter().map(|column| column.to_string()).collect())?;
+        Ok(writer)
+    }
+
+    fn write_row(&mut self, fields: Vec<String>) -> Result<(), String> {
+        let line: Vec<_> = fields.iter().map(|field| csv_field(field)).collect();
+        writeln!(self.writer, "{}", line.join(","))
+            .map_err(|e| format!("Failed to write export file: {}", e))
+    }
+
+    fn finish(mut self) -> Result<(), String> {
+        self.writer
+            .flush()
+            .map_err(|e| format!("Failed to write export file: {}", e))
+    }
+}
+
+/// Rows buffered in memory before they are written out as a Parquet row group
+const PARQUET_ROW_GROUP_SIZE: usize = 64 * 1024;
+
+/// Writes a Parquet file with one optional column per header entry.  An empty field is
+/// written as a null.
+struct ParquetWriter {
+    writer: SerializedFileWriter<fs::File>,
+    column_types: Vec<ColumnType>,
+    rows: Vec<Vec<String>>,
+}
+
+fn parquet_error(e: parquet::errors::ParquetError) -> String {
+    format!("Failed to write export file: {}", e)
+}
+
+impl ParquetWriter {
+    fn create(path: &Path, header: &[(&str, ColumnType)]) -> Result<ParquetWriter, String> {
+        let mut fields = vec![];
+        for (name, column_type) in header.iter() {
+            let field = match column_type {
+                Text => SchemaType::primitive_type_builder(name, PhysicalType::BYTE_ARRAY)
+                    .with_converted_type(ConvertedType::UTF8),
+                Integer => SchemaType::primitive_type_builder(name, PhysicalType::INT64),
+            }
+            .with_repetition(Repetition::OPTIONAL)
+            .build()
+            .map_err(parquet_error)?;
+            fields.push(Arc::new(field));
+        }
+        let schema = SchemaType::group_type_builder("schema")
+            .with_fields(fields)
+            .build()
+            .map_err(parquet_error)?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+
+        let file =
+            fs::File::create(path).map_err(|e| format!("Failed to create {:?}: {}", path, e))?;
+        let writer = SerializedFileWriter::new(file, Arc::new(schema), Arc::new(properties))
+            .map_err(parquet_error)?;
+        Ok(ParquetWriter {
+            writer,
+            column_types: header.iter().map(|(_, column_type)| *column_type).collect(),
+            rows: vec![],
+        })
+    }
+
+    fn write_row(&mut self, fields: Vec<String>) -> Result<(), String> {
+        self.rows.push(fields);
+        if self.rows.len() >= PARQUET_ROW_GROUP_SIZE {
+            self.write_row_group()?;
+        }
+        Ok(())
+    }
+
+    fn write_row_group(&mut self) -> Result<(), String> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        let rows = std::mem::replace(&mut self.rows, vec![]);
+        let mut row_group = self.writer.next_row_group().map_err(parquet_error)?;
+        for (i, column_type) in self.column_types.iter().enumerate() {
+            let def_levels: Vec<i16> = rows
+                .iter()
+                .map(|row| if row[i].is_empty() { 0 } else { 1 })
+                .collect();
+            let fields = rows
+                .iter()
+                .map(|row| row[i].as_str())
+                .filter(|field| !field.is_empty());
+            let mut column = row_group
+                .next_column()
+                .map_err(parquet_error)?
+                .ok_or_else(|| "Parquet schema has too few columns".to_string())?;
+            match column_type {
+                Text => {
+                    let values: Vec<ByteArray> = fields.map(ByteArray::from).collect();
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(&values, Some(&def_levels), None)
+                        .map_err(parquet_error)?;
+                }
+                Integer => {
+                    let values = fields
+                        .map(|field| {
+                            field.parse::<i64>().map_err(|_| {
+                                format!("Failed to write export file: '{}' is not an i64", field)
+                            })
+                        })
+                        .collect::<Result<Vec<i64>, String>>()?;
+                    column
+                        .typed::<Int64Type>()
+                        .write_batch(&values, Some(&def_levels), None)
+                        .map_err(parquet_error)?;
+                }
+            }
+            column.close().map_err(parquet_error)?;
+        }
+        row_group.close().map_err(parquet_error)?;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), String> {
+        self.write_row_group()?;
+        self.writer.close().map_err(parquet_error)?;
+        Ok(())
+    }
+}
+
+/// One exported table, in whichever format was asked for
+enum TableWriter {
+    Csv(CsvWriter),
+    Parquet(ParquetWriter),
+}
+
+impl TableWriter {
+    /// Creates `<name>.csv` or `<name>.parquet` in `dest`
+    fn create(
+        dest: &Path,
+        name: &str,
+        format: ExportFormat,
+        header: &[(&str, ColumnType)],
+    ) -> Result<TableWriter, String> {
+        let path = dest.join(format!("{}.{}", name, format.extension()));
+        match format {
+            ExportFormat::Csv => {
+                let names: Vec<_> = header.iter().map(|(name, _)| *name).collect();
+                Ok(TableWriter::Csv(CsvWriter::create(&path, &names)?))
+            }
+            ExportFormat::Parquet => {
+                Ok(TableWriter::Parquet(ParquetWriter::create(&path, header)?))
+            }
+        }
+    }
+
+    fn write_row(&mut self, fields: Vec<String>) -> Result<(), String> {
+        match self {
+            TableWriter::Csv(writer) => writer.write_row(fields),
+            TableWriter::Parquet(writer) => writer.write_row(fields),
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        match self {
+            TableWriter::Csv(writer) => writer.finish(),
+            TableWriter::Parquet(writer) => writer.finish(),
+        }
+    }
+}
+
+/// Accounts whose STX balance a transaction or its events may have changed.
+fn touched_principals(
+    tx: &StacksTransaction,
+    events: &[serde_json::Value],
+    principals: &mut BTreeSet<String>,
+) {
+    principals.insert(PrincipalData::from(tx.origin_address()).to_string());
+    if let Some(sponsor) = tx.sponsor_address() {
+        principals.insert(PrincipalData::from(sponsor).to_string());
+    }
+    if let TransactionPayload::TokenTransfer(recipient, ..) = &tx.payload {
+        principals.insert(recipient.to_string());
+    }
+    for event in events.iter() {
+        let fields: &[(&str, &str)] = &[
+            ("stx_transfer_event", "sender"),
+            ("stx_transfer_event", "recipient"),
+            ("stx_mint_event", "recipient"),
+            ("stx_burn_event", "sender"),
+            ("stx_lock_event", "locked_address"),
+        ];
+        for (event_type, field) in fields.iter() {
+            if let Some(principal) = event[*event_type][*field].as_str() {
+                principals.insert(principal.to_string());
+            }
+        }
+    }
+}
+
+/// Writes `blocks`, `transactions`, `events` and `balances` files (`.csv` or `.parquet`) to `dest`
+/// for the canonical Stacks blocks from `start_height` to `end_height` (inclusive, and capped at
+/// the chain tip).  Balances are those, as of the last exported block, of every account touched
+/// in the range.
+pub fn export_chainstate(
+    config: &Config,
+    dest: &Path,
+    format: ExportFormat,
+    start_height: u64,
+    end_height: u64,
+) -> Result<ExportSummary, String> {
+    if start_height > end_height {
+        return Err(format!(
+            "Start height {} is above end height {}",
+            start_height, end_height
+        ));
+    }
+    if !StacksChainState::header_index_root_path(config.get_chainstate_path()).exists() {
+        return Err("The node has not booted yet".to_string());
+    }
+    let sortdb = SortitionDB::open(&config.get_burn_db_file_path(), false)
+        .map_err(|e| format!("Failed to open sortition DB: {:?}", e))?;
+    let (mut chainstate, _) = StacksChainState::open(
+        config.is_mainnet(),
+        config.burnchain.chain_id,
+        &config.get_chainstate_path_str(),
+        Some(config.node.get_marf_opts()),
+    )
+    .map_err(|e| format!("Failed to open chainstate: {:?}", e))?;
+
+    let (tip_consensus_hash, tip_block_hash) =
+        SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn())
+            .map_err(|e| format!("Failed to load chain tip: {:?}", e))?;
+    let tip = StacksBlockHeader::make_index_block_hash(&tip_consensus_hash, &tip_block_hash);
+
+    fs::create_dir_all(dest).map_err(|e| format!("Failed to create {:?}: {}", dest, e))?;
+    let mut blocks_file = TableWriter::create(
+        dest,
+        "blocks",
+        format,
+        &[
+            ("height", Integer),
+            ("index_block_hash", Text),
+            ("block_hash", Text),
+            ("consensus_hash", Text),
+            ("parent_block_id", Text),
+            ("burn_header_hash", Text),
+            ("burn_header_height", Integer),
+            ("burn_header_timestamp", Integer),
+            ("num_txs", Integer),
+            ("num_microblock_txs", Integer),
+        ],
+    )?;
+    let mut transactions_file = TableWriter::create(
+        dest,
+        "transactions",
+        format,
+        &[
+            ("block_height", Integer),
+            ("index_block_hash", Text),
+            ("microblock_hash", Text),
+            ("microblock_sequence", Integer),
+            ("tx_index", Integer),
+            ("txid", Text),
+            ("type", Text),
+            ("origin", Text),
+            ("sponsor", Text),
+            ("nonce", Integer),
+            ("fee", Integer),
+            ("result", Text),
+            ("post_condition_aborted", Text),
+            ("raw_tx", Text),
+        ],
+    )?;
+    let mut events_file = TableWriter::create(
+        dest,
+        "events",
+        format,
+        &[
+            ("block_height", Integer),
+            ("index_block_hash", Text),
+            ("txid", Text),
+            ("event_index", Integer),
+            ("type", Text),
+            ("committed", Text),
+            ("event", Text),
+        ],
+    )?;
+
+    let mut summary = ExportSummary::default();
+    let mut principals = BTreeSet::new();
+    let mut last_block_id: Option<StacksBlockId> = None;
+    for height in start_height..=end_height {
+        let block_id = match chainstate
+            .index_conn()
+            .and_then(|index_conn| Ok(index_conn.get_ancestor_block_hash(height, &tip)?))
+            .map_err(|e| format!("Failed to load block at height {}: {:?}", height, e))?
+        {
+            Some(block_id) => block_id,
+            // past the chain tip
+            None => break,
+        };
+        let header = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            chainstate.db(),
+            &block_id,
+        )
+        .map_err(|e| format!("Failed to load block header {}: {:?}", &block_id, e))?
+        .ok_or_else(|| format!("No header for block {}", &block_id))?;
+        last_block_id = Some(block_id.clone());
+
+        // the boot block has no parent and is never stored
+        let (parent_block_id, txs) = if header.stacks_block_height == 0 {
+            (None, vec![])
+        } else {
+            let parent_block_id = StacksChainState::get_parent_block_id(chainstate.db(), &block_id)
+                .map_err(|e| format!("Failed to load parent of {}: {:?}", &block_id, e))?
+                .ok_or_else(|| format!("No parent for block {}", &block_id))?;
+            let block_hash = header.anchored_header.block_hash();
+            let block = StacksChainState::load_block(
+                &chainstate.blocks_path,
+                &header.consensus_hash,
+                &block_hash,
+            )
+            .map_err(|e| format!("Failed to load block {}: {:?}", &block_id, e))?
+            .ok_or_else(|| format!("Block {} is not stored", &block_id))?;
+
+            // transactions in the parent's microblocks that this block confirmed
+            let mut txs = vec![];
+            if block.header.parent_microblock != EMPTY_MICROBLOCK_PARENT_HASH
+                || block.header.parent_microblock_sequence != 0
+            {
+                let parent_header =
+                    StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                        chainstate.db(),
+                        &parent_block_id,
+                    )
+                    .map_err(|e| format!("Failed to load parent of {}: {:?}", &block_id, e))?
+                    .ok_or_else(|| format!("No parent header for block {}", &block_id))?;
+                let microblocks = StacksChainState::load_processed_microblock_stream_fork(
+                    &chainstate.db(),
+                    &parent_header.consensus_hash,
+                    &parent_header.anchored_header.block_hash(),
+                    &block.header.parent_microblock,
+                )
+                .map_err(|e| format!("Failed to load microblocks of {}: {:?}", &block_id, e))?
+                .unwrap_or_default();
+                for microblock in microblocks.into_iter() {
+                    let microblock_hash = microblock.block_hash().to_string();
+                    let sequence = microblock.header.sequence.to_string();
+                    for tx in microblock.txs.into_iter() {
+                        txs.push((microblock_hash.clone(), sequence.clone(), tx));
+                    }
+                }
+            }
+            let num_microblock_txs = txs.len();
+            for tx in block.txs.into_iter() {
+                txs.push((String::new(), String::new(), tx));
+            }
+            (Some((parent_block_id, num_microblock_txs)), txs)
+        };
+
+        let logged_txs = chainstate
+            .get_logged_block_transactions(&block_id)
+            .map_err(|e| format!("Failed to read transaction log of {}: {:?}", &block_id, e))?;
+
+        let num_microblock_txs = parent_block_id.as_ref().map(|(_, n)| *n).unwrap_or(0);
+        blocks_file.write_row(vec![
+            header.stacks_block_height.to_string(),
+            block_id.to_string(),
+            header.anchored_header.block_hash().to_string(),
+            header.consensus_hash.to_string(),
+            parent_block_id
+                .map(|(parent_block_id, _)| parent_block_id.to_string())
+                .unwrap_or_default(),
+            header.burn_header_hash.to_string(),
+            header.burn_header_height.to_string(),
+            header.burn_header_timestamp.to_string(),
+            (txs.len() - num_microblock_txs).to_string(),
+            num_microblock_txs.to_string(),
+        ])?;
+        summary.blocks += 1;
+
+        for (tx_index, (microblock_hash, microblock_sequence, tx)) in txs.iter().enumerate() {
+            let txid = tx.txid();
+            let logged_tx = logged_txs.get(&txid);
+            let receipt = logged_tx.and_then(|logged_tx| logged_tx.receipt.as_ref());
+            transactions_file.write_row(vec![
+                header.stacks_block_height.to_string(),
+                block_id.to_string(),
+                microblock_hash.clone(),
+                microblock_sequence.clone(),
+                tx_index.to_string(),
+                txid.to_string(),
+                tx.payload.name().to_string(),
+                tx.origin_address().to_string(),
+                tx.sponsor_address()
+                    .map(|sponsor| sponsor.to_string())
+                    .unwrap_or_default(),
+                tx.get_origin_nonce().to_string(),
+                tx.get_tx_fee().to_string(),
+                logged_tx
+                    .map(|logged_tx| logged_tx.result.clone())
+                    .unwrap_or_default(),
+                receipt
+                    .map(|receipt| receipt.post_condition_aborted.to_string())
+                    .unwrap_or_default(),
+                to_hex(&tx.serialize_to_vec()),
+            ])?;
+            summary.transactions += 1;
+
+            let events = receipt
+                .map(|receipt| receipt.events.as_slice())
+                .unwrap_or(&[]);
+            for event in events.iter() {
+                events_file.write_row(vec![
+                    header.stacks_block_height.to_string(),
+                    block_id.to_string(),
+                    txid.to_string(),
+                    event["event_index"].to_string(),
+                    event["type"].as_str().unwrap_or_default().to_string(),
+                    event["committed"].to_string(),
+                    event.to_string(),
+                ])?;
+                summary.events += 1;
+            }
+            touched_principals(tx, events, &mut principals);
+        }
+    }
+    blocks_file.finish()?;
+    transactions_file.finish()?;
+    events_file.finish()?;
+
+    // balances are u128s, so they stay text in Parquet files too
+    let mut balances_file = TableWriter::create(
+        dest,
+        "balances",
+        format,
+        &[
+            ("index_block_hash", Text),
+            ("principal", Text),
+            ("balance", Text),
+            ("locked", Text),
+            ("unlock_height", Integer),
+            ("nonce", Integer),
+        ],
+    )?;
+    if let Some(last_block_id) = last_block_id {
+        let rows = chainstate
+            .maybe_read_only_clarity_tx(&sortdb.index_conn(), &last_block_id, |clarity_tx| {
+                clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                    let burn_block_height = clarity_db.get_current_burnchain_block_height() as u64;
+                    let mut rows = vec![];
+                    for principal_str in principals.iter() {
+                        let principal = match PrincipalData::parse(principal_str) {
+                            Ok(principal) => principal,
+                            Err(_) => continue,
+                        };
+                        let balance = clarity_db
+                            .get::<STXBalance>(&ClarityDatabase::make_key_for_account_balance(
+                                &principal,
+                            ))
+                            .unwrap_or_else(STXBalance::zero);
+                        let nonce: u64 = clarity_db
+                            .get(&ClarityDatabase::make_key_for_account_nonce(&principal))
+                            .unwrap_or(0);
+                        let (locked, unlock_height) =
+                            balance.get_locked_balance_at_burn_block(burn_block_height);
+                        rows.push(vec![
+                            last_block_id.to_string(),
+                            principal_str.clone(),
+                            balance
+                                .get_available_balance_at_burn_block(burn_block_height)
+                                .to_string(),
+                            locked.to_string(),
+                            unlock_height.to_string(),
+                            nonce.to_string(),
+                        ]);
+                    }
+                    rows
+                })
+            })
+            .map_err(|e| format!("Failed to read balances: {:?}", e))?
+            .ok_or_else(|| format!("The state of block {} is not available", &last_block_id))?;
+        for row in rows.into_iter() {
+            balances_file.write_row(row)?;
+            summary.balances += 1;
+        }
+    }
+    balances_file.finish()?;
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_format() {
+        assert_eq!(ExportFormat::from_str("csv"), Ok(ExportFormat::Csv));
+        assert_eq!(ExportFormat::from_str("parquet"), Ok(ExportFormat::Parquet));
+        assert!(ExportFormat::from_str("xlsx")
+            .unwrap_err()
+            .starts_with("Unknown export format 'xlsx'"));
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("SP000"), "SP000");
+        assert_eq!(csv_field("(ok true)"), "(ok true)");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(
+            csv_field(r#"{"type":"stx_transfer_event"}"#),
+            r#""{""type"":""stx_transfer_event""}""#
+        );
+    }
+
+    #[test]
+    fn test_parquet_writer() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use parquet::record::Field;
+
+        let dir = std::env::temp_dir().join(format!("test_parquet_writer_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut writer = TableWriter::create(
+            &dir,
+            "balances",
+            ExportFormat::Parquet,
+            &[("principal", Text), ("nonce", Integer)],
+        )
+        .unwrap();
+        writer
+            .write_row(vec!["SP000".to_string(), "3".to_string()])
+            .unwrap();
+        writer
+            .write_row(vec!["a,b".to_string(), "".to_string()])
+            .unwrap();
+        writer.finish().unwrap();
+
+        let reader =
+            SerializedFileReader::new(fs::File::open(dir.join("balances.parquet")).unwrap())
+                .unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        let rows: Vec<Vec<Field>> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| {
+                row.unwrap()
+                    .get_column_iter()
+                    .map(|(_, field)| field.clone())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                vec![Field::Str("SP000".to_string()), Field::Long(3)],
+                vec![Field::Str("a,b".to_string()), Field::Null],
+            ]
+        );
+
+        // an integer column rejects text instead of silently dropping it
+        let mut writer =
+            TableWriter::create(&dir, "bad", ExportFormat::Parquet, &[("nonce", Integer)]).unwrap();
+        writer.write_row(vec!["three".to_string()]).unwrap();
+        assert!(writer
+            .finish()
+            .unwrap_err()
+            .contains("'three' is not an i64"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
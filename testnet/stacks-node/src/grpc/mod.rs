@@ -0,0 +1,29 @@
+//! gRPC API, served alongside the HTTP RPC for clients that want a binary protocol and streamed
+//! events instead of polling. The service is defined in `proto/stacks_node.proto`, compiled by the
+//! build script when the node is built with the `grpc` feature.
+
+use crate::event_dispatcher::EventDispatcher;
+use crate::Config;
+
+#[cfg(feature = "grpc")]
+mod service;
+
+/// Starts serving the gRPC API on `bind_address` in a thread of its own. The server subscribes
+/// to `event_dispatcher`, so this must be called before the dispatcher is handed out.
+pub fn start_serving_grpc(
+    bind_address: String,
+    config: &Config,
+    event_dispatcher: &mut EventDispatcher,
+) {
+    #[cfg(feature = "grpc")]
+    service::start_serving_grpc(bind_address, config, event_dispatcher);
+
+    #[cfg(not(feature = "grpc"))]
+    {
+        let _ = (config, event_dispatcher);
+        warn!(
+            "gRPC: not serving on {}: this node was built without the `grpc` feature",
+            &bind_address
+        );
+    }
+}
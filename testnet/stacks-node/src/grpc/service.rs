@@ -0,0 +1,445 @@
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use rusqlite::{Connection, OpenFlags};
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status};
+
+use stacks::burnchains::Txid;
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::chainstate::stacks::StacksBlockHeader;
+use stacks::clarity_vm::clarity::ClarityConnection;
+use stacks::codec::StacksMessageCodec;
+use stacks::core::mempool::MemPoolDB;
+use stacks::types::chainstate::StacksBlockId;
+use stacks::vm::database::{ClarityDatabase, STXBalance};
+use stacks::vm::types::PrincipalData;
+
+use crate::config::EventKeyType;
+use crate::event_dispatcher::{ChainEvent, ChainEventSubscriber, EventDispatcher};
+use crate::snapshot::get_mempool_path;
+use crate::Config;
+
+use self::proto::stacks_node_server::{StacksNode, StacksNodeServer};
+use self::proto::{
+    get_block_request, Account, Block, Event, EventType, GetAccountRequest, GetBlockRequest,
+    GetTransactionRequest, SubscribeEventsRequest, Transaction,
+};
+
+pub mod proto {
+    tonic::include_proto!("stacks_node.v1");
+}
+
+/// Number of events buffered for each subscribed client before it is considered lagging.
+const EVENTS_CHANNEL_SIZE: usize = 1024;
+
+/// Forwards the node's events to the subscribed gRPC clients.
+struct EventBroadcaster(broadcast::Sender<ChainEvent>);
+
+impl ChainEventSubscriber for EventBroadcaster {
+    fn on_event(&self, event: ChainEvent) {
+        // fails only if no client is subscribed
+        let _ = self.0.send(event);
+    }
+}
+
+fn event_type(event: &ChainEvent) -> EventType {
+    match event {
+        ChainEvent::Block(_) => EventType::Block,
+        ChainEvent::Microblocks(_) => EventType::Microblocks,
+        ChainEvent::BurnBlock(_) => EventType::BurnBlock,
+        ChainEvent::MempoolTxs(_) => EventType::MempoolTxs,
+        ChainEvent::DroppedMempoolTxs(_) => EventType::DroppedMempoolTxs,
+        ChainEvent::MinedBlock(_) => EventType::MinedBlock,
+        ChainEvent::MinedMicroblock(_) => EventType::MinedMicroblock,
+        ChainEvent::Attachments(_) => EventType::Attachments,
+        ChainEvent::BlockPropagation(_) => EventType::BlockPropagation,
+        ChainEvent::BurnchainReorg(_) => EventType::BurnchainReorg,
+        ChainEvent::TenureTimes(_) => EventType::TenureTimes,
+        ChainEvent::NodeStall(_) => EventType::NodeStall,
+        ChainEvent::BlockTelemetry(_) => EventType::BlockTelemetry,
+    }
+}
+
+fn make_event(event: &ChainEvent) -> Result<Event, Status> {
+    let payload = event
+        .payload_json()
+        .map_err(|e| Status::internal(format!("Failed to serialize event: {}", e)))?;
+    Ok(Event {
+        r#type: event_type(event) as i32,
+        payload,
+    })
+}
+
+/// The node's databases, opened read-only on the first query.
+struct NodeDatabases {
+    sortdb: SortitionDB,
+    chainstate: StacksChainState,
+    mempool: Connection,
+}
+
+impl NodeDatabases {
+    fn open(config: &Config) -> Result<NodeDatabases, Status> {
+        // don't instantiate the chainstate if the node hasn't yet
+        if !StacksChainState::header_index_root_path(config.get_chainstate_path()).exists() {
+            return Err(Status::unavailable("The node has not booted yet"));
+        }
+        let sortdb = SortitionDB::open(&config.get_burn_db_file_path(), false)
+            .map_err(|e| Status::unavailable(format!("Failed to open sortition DB: {:?}", e)))?;
+        let chainstate = StacksChainState::open_readonly(
+            config.is_mainnet(),
+            config.burnchain.chain_id,
+            &config.get_chainstate_path_str(),
+            Some(config.node.get_marf_opts()),
+        )
+        .map_err(|e| Status::unavailable(format!("Failed to open chainstate: {:?}", e)))?;
+        let mempool_path = get_mempool_path(config).map_err(Status::internal)?;
+        let mempool = Connection::open_with_flags(&mempool_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| Status::unavailable(format!("Failed to open mempool: {}", e)))?;
+        Ok(NodeDatabases {
+            sortdb,
+            chainstate,
+            mempool,
+        })
+    }
+
+    fn canonical_tip(&self) -> Result<StacksBlockId, Status> {
+        let (consensus_hash, block_hash) =
+            SortitionDB::get_canonical_stacks_chain_tip_hash(self.sortdb.conn())
+                .map_err(|e| Status::internal(format!("Failed to load chain tip: {:?}", e)))?;
+        Ok(StacksBlockHeader::make_index_block_hash(
+            &consensus_hash,
+            &block_hash,
+        ))
+    }
+
+    fn get_block(&mut self, request: GetBlockRequest) -> Result<Block, Status> {
+        let index_block_hash = match request.id {
+            Some(get_block_request::Id::IndexBlockHash(bytes)) => StacksBlockId::from_bytes(&bytes)
+                .ok_or_else(|| Status::invalid_argument("Invalid index block hash"))?,
+            Some(get_block_request::Id::Height(height)) => {
+                let tip = self.canonical_tip()?;
+                self.chainstate
+                    .index_conn()
+                    .and_then(|index_conn| Ok(index_conn.get_ancestor_block_hash(height, &tip)?))
+                    .map_err(|e| Status::internal(format!("Failed to load block: {:?}", e)))?
+                    .ok_or_else(|| Status::not_found("No block at this height"))?
+            }
+            None => return Err(Status::invalid_argument("No block requested")),
+        };
+
+        let header = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            self.chainstate.db(),
+            &index_block_hash,
+        )
+        .map_err(|e| Status::internal(format!("Failed to load block header: {:?}", e)))?
+        .ok_or_else(|| Status::not_found("No such block"))?;
+        let block_hash = header.anchored_header.block_hash();
+
+        let mut block = Block {
+            height: header.stacks_block_height,
+            index_block_hash: index_block_hash.as_bytes().to_vec(),
+            block_hash: block_hash.as_bytes().to_vec(),
+            consensus_hash: header.consensus_hash.as_bytes().to_vec(),
+            parent_block_id: vec![],
+            burn_header_hash: header.burn_header_hash.as_bytes().to_vec(),
+            burn_header_height: header.burn_header_height,
+            burn_header_timestamp: header.burn_header_timestamp,
+            txids: vec![],
+            raw_block: vec![],
+        };
+        // the boot block has no parent and is never stored
+        if header.stacks_block_height == 0 {
+            return Ok(block);
+        }
+
+        block.parent_block_id =
+            StacksChainState::get_parent_block_id(self.chainstate.db(), &index_block_hash)
+                .map_err(|e| Status::internal(format!("Failed to load parent block: {:?}", e)))?
+                .ok_or_else(|| Status::not_found("No parent block"))?
+                .as_bytes()
+                .to_vec();
+        let stacks_block = StacksChainState::load_block(
+            &self.chainstate.blocks_path,
+            &header.consensus_hash,
+            &block_hash,
+        )
+        .map_err(|e| Status::internal(format!("Failed to load block: {:?}", e)))?
+        .ok_or_else(|| Status::not_found("Block data is not stored"))?;
+        block.txids = stacks_block
+            .txs
+            .iter()
+            .map(|tx| tx.txid().as_bytes().to_vec())
+            .collect();
+        block.raw_block = stacks_block.serialize_to_vec();
+        Ok(block)
+    }
+
+    fn get_transaction(&self, request: GetTransactionRequest) -> Result<Transaction, Status> {
+        let txid = Txid::from_bytes(&request.txid)
+            .ok_or_else(|| Status::invalid_argument("Invalid txid"))?;
+        let tx_info = MemPoolDB::get_tx(&self.mempool, &txid)
+            .map_err(|e| Status::internal(format!("Failed to query mempool: {:?}", e)))?
+            .ok_or_else(|| Status::not_found("No such transaction in the mempool"))?;
+        Ok(Transaction {
+            txid: txid.as_bytes().to_vec(),
+            raw_tx: tx_info.tx.serialize_to_vec(),
+            fee: tx_info.metadata.tx_fee,
+            accept_time: tx_info.metadata.accept_time,
+        })
+    }
+
+    fn get_account(&mut self, request: GetAccountRequest) -> Result<Account, Status> {
+        let principal = PrincipalData::parse(&request.principal)
+            .map_err(|e| Status::invalid_argument(format!("Invalid principal: {:?}", e)))?;
+        let tip = self.canonical_tip()?;
+        let account = self
+            .chainstate
+            .maybe_read_only_clarity_tx(&self.sortdb.index_conn(), &tip, |clarity_tx| {
+                clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                    let burn_block_height = clarity_db.get_current_burnchain_block_height() as u64;
+                    let balance = clarity_db
+                        .get::<STXBalance>(&ClarityDatabase::make_key_for_account_balance(
+                            &principal,
+                        ))
+                        .unwrap_or_else(STXBalance::zero);
+                    let nonce: u64 = clarity_db
+                        .get(&ClarityDatabase::make_key_for_account_nonce(&principal))
+                        .unwrap_or(0);
+                    let (locked, unlock_height) =
+                        balance.get_locked_balance_at_burn_block(burn_block_height);
+                    Account {
+                        balance: balance
+                            .get_available_balance_at_burn_block(burn_block_height)
+                            .to_string(),
+                        locked: locked.to_string(),
+                        unlock_height,
+                        nonce,
+                        tip: tip.as_bytes().to_vec(),
+                    }
+                })
+            })
+            .map_err(|e| Status::internal(format!("Failed to read account: {:?}", e)))?
+            .ok_or_else(|| Status::unavailable("The chain tip's state is not available"))?;
+        Ok(account)
+    }
+}
+
+struct StacksNodeService {
+    config: Config,
+    databases: Arc<Mutex<Option<NodeDatabases>>>,
+    events: broadcast::Sender<ChainEvent>,
+}
+
+impl StacksNodeService {
+    /// Runs `query` against the node's databases on a thread that may block.
+    async fn query<F, R>(&self, query: F) -> Result<Response<R>, Status>
+    where
+        F: FnOnce(&mut NodeDatabases) -> Result<R, Status> + Send + 'static,
+        R: Send + 'static,
+    {
+        let config = self.config.clone();
+        let databases = self.databases.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut databases = databases
+                .lock()
+                .map_err(|_| Status::internal("Database lock poisoned"))?;
+            if databases.is_none() {
+                *databases = Some(NodeDatabases::open(&config)?);
+            }
+            query(databases.as_mut().expect("BUG: databases not opened"))
+        })
+        .await
+        .map_err(|e| Status::internal(format!("Query failed: {}", e)))?
+        .map(Response::new)
+    }
+}
+
+#[tonic::async_trait]
+impl StacksNode for StacksNodeService {
+    async fn get_block(
+        &self,
+        request: Request<GetBlockRequest>,
+    ) -> Result<Response<Block>, Status> {
+        let request = request.into_inner();
+        self.query(move |databases| databases.get_block(request))
+            .await
+    }
+
+    async fn get_transaction(
+        &self,
+        request: Request<GetTransactionRequest>,
+    ) -> Result<Response<Transaction>, Status> {
+        let request = request.into_inner();
+        self.query(move |databases| databases.get_transaction(request))
+            .await
+    }
+
+    async fn get_account(
+        &self,
+        request: Request<GetAccountRequest>,
+    ) -> Result<Response<Account>, Status> {
+        let request = request.into_inner();
+        self.query(move |databases| databases.get_account(request))
+            .await
+    }
+
+    type SubscribeEventsStream = Pin<Box<dyn Stream<Item = Result<Event, Status>> + Send>>;
+
+    async fn subscribe_events(
+        &self,
+        request: Request<SubscribeEventsRequest>,
+    ) -> Result<Response<Self::SubscribeEventsStream>, Status> {
+        let types: HashSet<i32> = request.into_inner().types.into_iter().collect();
+        let mut events = self.events.subscribe();
+        let (send, recv) = mpsc::channel(16);
+        tokio::spawn(async move {
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        let _ = send
+                            .send(Err(Status::data_loss(format!(
+                                "Subscriber fell behind and missed {} events",
+                                missed
+                            ))))
+                            .await;
+                        return;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                };
+                if !types.is_empty() && !types.contains(&(event_type(&event) as i32)) {
+                    continue;
+                }
+                if send.send(make_event(&event)).await.is_err() {
+                    // client went away
+                    return;
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(recv))))
+    }
+}
+
+fn make_service(
+    config: &Config,
+    event_dispatcher: &mut EventDispatcher,
+) -> StacksNodeServer<StacksNodeService> {
+    let (events, _) = broadcast::channel(EVENTS_CHANNEL_SIZE);
+    event_dispatcher.register_subscriber(
+        Arc::new(EventBroadcaster(events.clone())),
+        &[
+            EventKeyType::AnyEvent,
+            EventKeyType::MinedBlocks,
+            EventKeyType::MinedMicroblocks,
+            EventKeyType::BlockPropagation,
+            EventKeyType::TenureTimes,
+            EventKeyType::BlockTelemetry,
+        ],
+    );
+    StacksNodeServer::new(StacksNodeService {
+        config: config.clone(),
+        databases: Arc::new(Mutex::new(None)),
+        events,
+    })
+}
+
+pub fn start_serving_grpc(
+    bind_address: String,
+    config: &Config,
+    event_dispatcher: &mut EventDispatcher,
+) {
+    let addr: SocketAddr = bind_address
+        .parse()
+        .expect("BUG: node.grpc_bind was not validated at config load");
+    let service = make_service(config, event_dispatcher);
+    thread::Builder::new()
+        .name("grpc".to_string())
+        .spawn(move || {
+            let runtime =
+                tokio::runtime::Runtime::new().expect("gRPC: failed to start the async runtime");
+            info!("gRPC: server listening on {}", addr);
+            if let Err(e) = runtime.block_on(
+                tonic::transport::Server::builder()
+                    .add_service(service)
+                    .serve(addr),
+            ) {
+                error!("gRPC: server failed: {}", e);
+            }
+        })
+        .expect("FATAL: failed to start gRPC thread");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use stacks::core::mempool::MemPoolDropReason;
+    use stacks::types::chainstate::BurnchainHeaderHash;
+
+    use super::proto::stacks_node_client::StacksNodeClient;
+
+    #[test]
+    fn test_grpc_service() {
+        let mut config = Config::default();
+        config.node.working_dir = "/tmp/stacks-node-tests/grpc/service".to_string();
+        let mut event_dispatcher = EventDispatcher::new();
+        start_serving_grpc(
+            "127.0.0.1:20448".to_string(),
+            &config,
+            &mut event_dispatcher,
+        );
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let mut client = loop {
+                match StacksNodeClient::connect("http://127.0.0.1:20448").await {
+                    Ok(client) => break client,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+                }
+            };
+
+            // no chainstate to query yet
+            let status = client
+                .get_block(GetBlockRequest {
+                    id: Some(get_block_request::Id::Height(1)),
+                })
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), tonic::Code::Unavailable);
+
+            let mut events = client
+                .subscribe_events(SubscribeEventsRequest {
+                    types: vec![EventType::BurnBlock as i32],
+                })
+                .await
+                .unwrap()
+                .into_inner();
+            // wait for the subscription to reach the server
+            tokio::time::sleep(Duration::from_millis(500)).await;
+
+            event_dispatcher
+                .process_dropped_mempool_txs(vec![Txid([2; 32])], MemPoolDropReason::STALE_COLLECT);
+            event_dispatcher.process_burn_block(
+                &BurnchainHeaderHash([1; 32]),
+                100,
+                vec![],
+                5,
+                vec![],
+            );
+
+            let event = events.message().await.unwrap().unwrap();
+            assert_eq!(event.r#type, EventType::BurnBlock as i32);
+            let payload: serde_json::Value = serde_json::from_str(&event.payload).unwrap();
+            assert_eq!(payload["burn_block_height"], serde_json::json!(100));
+        });
+    }
+}
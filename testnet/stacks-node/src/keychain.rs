@@ -17,6 +17,9 @@ pub struct Keychain {
     threshold: u16,
     hash_mode: AddressHashMode,
     pub hashed_secret_state: Sha256Sum,
+    /// What microblock signing keys derive from: `hashed_secret_state`, unless a separate
+    /// microblock seed is set
+    microblock_secret_state: Sha256Sum,
     microblocks_secret_keys: Vec<StacksPrivateKey>,
     vrf_secret_keys: Vec<VRFPrivateKey>,
     vrf_map: HashMap<VRFPublicKey, VRFPrivateKey>,
@@ -43,6 +46,7 @@ impl Keychain {
         Self {
             hash_mode,
             hashed_secret_state,
+            microblock_secret_state: hashed_secret_state,
             microblocks_secret_keys: vec![],
             secret_keys,
             threshold,
@@ -71,6 +75,13 @@ impl Keychain {
         Keychain::new(vec![secret_key], threshold, hash_mode)
     }
 
+    /// Derive microblock signing keys from `seed` (`node.microblock_seed`) instead of from the
+    /// keychain's secret keys.  Keys already rotated are forgotten.
+    pub fn set_microblock_seed(&mut self, seed: &[u8]) {
+        self.microblock_secret_state = Sha256Sum::from_data(seed);
+        self.microblocks_secret_keys.clear();
+    }
+
     pub fn rotate_vrf_keypair(&mut self, block_height: u64) -> VRFPublicKey {
         let mut seed = {
             let mut secret_state = self.hashed_secret_state.to_bytes().to_vec();
@@ -96,7 +107,7 @@ impl Keychain {
     pub fn rotate_microblock_keypair(&mut self, burn_block_height: u64) -> StacksPrivateKey {
         let mut secret_state = match self.microblocks_secret_keys.last() {
             // First key is the hash of the secret state
-            None => self.hashed_secret_state.to_bytes().to_vec(),
+            None => self.microblock_secret_state.to_bytes().to_vec(),
             // Next key is the hash of the last
             Some(last_sk) => last_sk.to_bytes().to_vec(),
         };
@@ -0,0 +1,651 @@
+//! Offline key generation for the `stacks-node generate-mnemonic`, `derive-keys` and
+//! `key-for-seed --vanity` subcommands: BIP39 mnemonics, and the Stacks and Bitcoin keys that
+//! BIP32 derives from them at the standard paths, `m/44'/5757'/0'/0/<index>` for Stacks and
+//! `m/44'/0'/0'/0/<index>` (`m/44'/1'/0'/0/<index>` on testnet) for Bitcoin p2pkh addresses.
+//!
+//! Nothing here touches the network or the node's databases.
+
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use secp256k1::SecretKey;
+use sha2::Sha512;
+use stacks::address::{
+    AddressHashMode, C32_ADDRESS_VERSION_MAINNET_SINGLESIG, C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+};
+use stacks::burnchains::bitcoin::address::{BitcoinAddress, BitcoinAddressType};
+use stacks::burnchains::bitcoin::BitcoinNetworkType;
+use stacks::types::chainstate::StacksAddress;
+use stacks::types::{PrivateKey, PublicKey};
+use stacks::util::hash::{to_hex, Hash160, Sha256Sum};
+use stacks::util::secp256k1::{Secp256k1PrivateKey, Secp256k1PublicKey};
+
+use crate::Keychain;
+
+/// The BIP39 English wordlist
+const BIP39_ENGLISH: &str = include_str!("bip39_english.txt");
+
+/// BIP39 stretches mnemonics with this many rounds of PBKDF2-HMAC-SHA512
+const BIP39_PBKDF2_ROUNDS: u32 = 2048;
+
+/// Child indexes at and above this are hardened
+const BIP32_HARDENED: u32 = 0x8000_0000;
+
+/// Characters that can follow the version prefix of a c32check address
+const C32_CHARACTERS: &str = "0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// The keys and addresses derived for one account index.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DerivedKeys {
+    pub stacks_path: String,
+    pub stacks_address: String,
+    /// Hex, with the `01` suffix of a compressed public key, as Stacks tooling expects
+    pub stacks_secret_key: String,
+    pub bitcoin_path: String,
+    pub bitcoin_address: String,
+    /// Wallet import format, for a compressed public key
+    pub bitcoin_wif: String,
+}
+
+/// A BIP32 extended private key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtendedPrivateKey {
+    pub secret_key: [u8; 32],
+    pub chain_code: [u8; 32],
+}
+
+fn bip39_words() -> Vec<&'static str> {
+    BIP39_ENGLISH.lines().collect()
+}
+
+fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
+    let mut mac =
+        Hmac::<Sha512>::new_from_slice(key).expect("BUG: HMAC accepts keys of any length");
+    mac.update(data);
+    let mut output = [0u8; 64];
+    output.copy_from_slice(&mac.finalize().into_bytes());
+    output
+}
+
+/// Encodes `entropy` (16 to 32 bytes, in steps of 4) as a BIP39 mnemonic.
+pub fn mnemonic_from_entropy(entropy: &[u8]) -> Result<String, String> {
+    if entropy.len() < 16 || entropy.len() > 32 || entropy.len() % 4 != 0 {
+        return Err(format!(
+            "Invalid entropy length {}: must be 16 to 32 bytes, in steps of 4",
+            entropy.len()
+        ));
+    }
+    let words = bip39_words();
+    let checksum_bits = entropy.len() * 8 / 32;
+    let mut bits = entropy.to_vec();
+    bits.push(Sha256Sum::from_data(entropy).as_bytes()[0]);
+
+    let num_words = (entropy.len() * 8 + checksum_bits) / 11;
+    let mnemonic: Vec<&str> = (0..num_words)
+        .map(|i| {
+            let index = (0..11).fold(0usize, |index, j| {
+                let bit = i * 11 + j;
+                (index << 1) | ((bits[bit / 8] >> (7 - bit % 8)) & 1) as usize
+            });
+            words[index]
+        })
+        .collect();
+    Ok(mnemonic.join(" "))
+}
+
+/// Generates a new BIP39 mnemonic of `num_words` words (12, 15, 18, 21 or 24).
+pub fn generate_mnemonic(num_words: usize) -> Result<String, String> {
+    if ![12, 15, 18, 21, 24].contains(&num_words) {
+        return Err(format!(
+            "Invalid mnemonic length {}: must be 12, 15, 18, 21 or 24 words",
+            num_words
+        ));
+    }
+    let mut entropy = vec![0u8; num_words * 4 / 3];
+    rand::thread_rng().fill_bytes(&mut entropy);
+    mnemonic_from_entropy(&entropy)
+}
+
+/// Checks that `mnemonic` is made of BIP39 English words and that its checksum is valid, and
+/// stretches it and `passphrase` into a 64-byte BIP32 seed.
+pub fn mnemonic_to_seed(mnemonic: &str, passphrase: &str) -> Result<[u8; 64], String> {
+    let words = bip39_words();
+    let mnemonic_words: Vec<&str> = mnemonic.split_whitespace().collect();
+    if ![12, 15, 18, 21, 24].contains(&mnemonic_words.len()) {
+        return Err(format!(
+            "Invalid mnemonic length {}: must be 12, 15, 18, 21 or 24 words",
+            mnemonic_words.len()
+        ));
+    }
+
+    let mut bits = vec![0u8; (mnemonic_words.len() * 11 + 7) / 8];
+    for (i, word) in mnemonic_words.iter().enumerate() {
+        let index = words
+            .binary_search(word)
+            .map_err(|_| format!("'{}' is not a BIP39 English word", word))?;
+        for j in 0..11 {
+            if (index >> (10 - j)) & 1 == 1 {
+                let bit = i * 11 + j;
+                bits[bit / 8] |= 1 << (7 - bit % 8);
+            }
+        }
+    }
+    let entropy_len = mnemonic_words.len() * 4 / 3;
+    if mnemonic_from_entropy(&bits[..entropy_len])? != mnemonic_words.join(" ") {
+        return Err("Invalid mnemonic: bad checksum".to_string());
+    }
+
+    // BIP39 normalizes the mnemonic and passphrase to NFKD, which is the identity on ASCII
+    if !passphrase.is_ascii() {
+        return Err("Only ASCII passphrases are supported".to_string());
+    }
+    let mut seed = [0u8; 64];
+    pbkdf2::pbkdf2::<Hmac<Sha512>>(
+        mnemonic_words.join(" ").as_bytes(),
+        format!("mnemonic{}", passphrase).as_bytes(),
+        BIP39_PBKDF2_ROUNDS,
+        &mut seed,
+    );
+    Ok(seed)
+}
+
+impl ExtendedPrivateKey {
+    /// The BIP32 master key of `seed`.
+    pub fn from_seed(seed: &[u8]) -> Result<ExtendedPrivateKey, String> {
+        ExtendedPrivateKey::from_hmac(hmac_sha512(b"Bitcoin seed", seed))
+    }
+
+    fn from_hmac(mac: [u8; 64]) -> Result<ExtendedPrivateKey, String> {
+        let mut secret_key = [0u8; 32];
+        let mut chain_code = [0u8; 32];
+        secret_key.copy_from_slice(&mac[..32]);
+        chain_code.copy_from_slice(&mac[32..]);
+        // rejects zero, and keys that are not less than the group order
+        SecretKey::from_slice(&secret_key)
+            .map_err(|_| "Derived an invalid secret key".to_string())?;
+        Ok(ExtendedPrivateKey {
+            secret_key,
+            chain_code,
+        })
+    }
+
+    pub fn private_key(&self) -> Secp256k1PrivateKey {
+        let mut private_key = Secp256k1PrivateKey::from_slice(&self.secret_key)
+            .expect("BUG: extended private key is not a valid secret key");
+        private_key.set_compress_public(true);
+        private_key
+    }
+
+    /// The child key at `index`, which is hardened if it is at least 2^31.  Fails, with
+    /// vanishing probability, if the child key is invalid; BIP32 then skips to the next index.
+    pub fn derive_child(&self, index: u32) -> Result<ExtendedPrivateKey, String> {
+        let mut data = if index >= BIP32_HARDENED {
+            let mut data = vec![0u8];
+            data.extend_from_slice(&self.secret_key);
+            data
+        } else {
+            Secp256k1PublicKey::from_private(&self.private_key()).to_bytes_compressed()
+        };
+        data.extend_from_slice(&index.to_be_bytes());
+
+        let child = ExtendedPrivateKey::from_hmac(hmac_sha512(&self.chain_code, &data))?;
+        let mut secret_key = SecretKey::from_slice(&self.secret_key)
+            .map_err(|_| "Invalid parent secret key".to_string())?;
+        secret_key
+            .add_assign(&child.secret_key)
+            .map_err(|_| "Derived an invalid secret key".to_string())?;
+        let mut child_secret_key = [0u8; 32];
+        child_secret_key.copy_from_slice(&secret_key[..]);
+        Ok(ExtendedPrivateKey {
+            secret_key: child_secret_key,
+            chain_code: child.chain_code,
+        })
+    }
+
+    /// The key at a derivation path like `m/44'/5757'/0'/0/0` (`h` also marks hardened indexes).
+    pub fn derive_path(&self, path: &str) -> Result<ExtendedPrivateKey, String> {
+        let mut components = path.split('/');
+        if components.next() != Some("m") {
+            return Err(format!("Invalid derivation path '{}'", path));
+        }
+        let mut key = self.clone();
+        for component in components {
+            let (index, hardened) = match component.strip_suffix(|c| c == '\'' || c == 'h') {
+                Some(index) => (index, true),
+                None => (component, false),
+            };
+            let index: u32 = index
+                .parse()
+                .ok()
+                .filter(|index| *index < BIP32_HARDENED)
+                .ok_or_else(|| format!("Invalid derivation path '{}'", path))?;
+            key = key.derive_child(if hardened {
+                index + BIP32_HARDENED
+            } else {
+                index
+            })?;
+        }
+        Ok(key)
+    }
+}
+
+/// The Stacks p2pkh address of `private_key`.
+pub fn stacks_address(private_key: &Secp256k1PrivateKey, mainnet: bool) -> StacksAddress {
+    let version = if mainnet {
+        C32_ADDRESS_VERSION_MAINNET_SINGLESIG
+    } else {
+        C32_ADDRESS_VERSION_TESTNET_SINGLESIG
+    };
+    StacksAddress::from_public_keys(
+        version,
+        &AddressHashMode::SerializeP2PKH,
+        1,
+        &vec![Secp256k1PublicKey::from_private(private_key)],
+    )
+    .expect("BUG: failed to make a p2pkh address")
+}
+
+/// The Bitcoin p2pkh address of `private_key`.
+pub fn bitcoin_address(private_key: &Secp256k1PrivateKey, mainnet: bool) -> String {
+    let network_id = if mainnet {
+        BitcoinNetworkType::Mainnet
+    } else {
+        BitcoinNetworkType::Testnet
+    };
+    let public_key = Secp256k1PublicKey::from_private(private_key);
+    let hash = Hash160::from_data(&public_key.to_bytes());
+    BitcoinAddress::from_bytes(
+        network_id,
+        BitcoinAddressType::PublicKeyHash,
+        hash.as_bytes(),
+    )
+    .expect("BUG: failed to make a p2pkh address")
+    .to_b58()
+}
+
+/// The wallet import format of `private_key`.
+pub fn bitcoin_wif(private_key: &Secp256k1PrivateKey, mainnet: bool) -> String {
+    let mut bytes = vec![if mainnet { 0x80 } else { 0xef }];
+    bytes.extend_from_slice(&private_key.to_bytes());
+    stacks::address::b58::check_encode_slice(&bytes)
+}
+
+/// Derives the Stacks and Bitcoin keys of account `index` from a BIP39 seed.
+pub fn derive_keys(seed: &[u8], index: u32, mainnet: bool) -> Result<DerivedKeys, String> {
+    let master = ExtendedPrivateKey::from_seed(seed)?;
+    let stacks_path = format!("m/44'/5757'/0'/0/{}", index);
+    let bitcoin_path = format!("m/44'/{}'/0'/0/{}", if mainnet { 0 } else { 1 }, index);
+    let stacks_key = master.derive_path(&stacks_path)?.private_key();
+    let bitcoin_key = master.derive_path(&bitcoin_path)?.private_key();
+    Ok(DerivedKeys {
+        stacks_address: stacks_address(&stacks_key, mainnet).to_string(),
+        stacks_secret_key: stacks_key.to_hex(),
+        stacks_path,
+        bitcoin_address: bitcoin_address(&bitcoin_key, mainnet),
+        bitcoin_wif: bitcoin_wif(&bitcoin_key, mainnet),
+        bitcoin_path,
+    })
+}
+
+/// Checks that `prefix` can begin a single-sig Stacks address of the given network.
+pub fn check_vanity_prefix(prefix: &str, mainnet: bool) -> Result<(), String> {
+    let network_prefix = if mainnet { "SP" } else { "ST" };
+    if !prefix.starts_with(network_prefix) {
+        return Err(format!(
+            "Vanity prefix '{}' must start with '{}'",
+            prefix, network_prefix
+        ));
+    }
+    if let Some(c) = prefix[2..].chars().find(|c| !C32_CHARACTERS.contains(*c)) {
+        return Err(format!(
+            "Vanity prefix '{}' contains '{}', which never appears in an address",
+            prefix, c
+        ));
+    }
+    Ok(())
+}
+
+/// Generates mnemonics of `num_words` words until the Stacks address of account 0 starts with
+/// `prefix`, giving up after `max_attempts`.  Every attempt stretches a new mnemonic, so each
+/// character of the prefix makes this about 32 times slower.
+pub fn grind_vanity_mnemonic(
+    prefix: &str,
+    num_words: usize,
+    mainnet: bool,
+    max_attempts: u64,
+) -> Result<(String, DerivedKeys), String> {
+    check_vanity_prefix(prefix, mainnet)?;
+    for _ in 0..max_attempts {
+        let mnemonic = generate_mnemonic(num_words)?;
+        let seed = mnemonic_to_seed(&mnemonic, "")?;
+        let keys = derive_keys(&seed, 0, mainnet)?;
+        if keys.stacks_address.starts_with(prefix) {
+            return Ok((mnemonic, keys));
+        }
+    }
+    Err(format!(
+        "No address starting with '{}' found in {} attempts",
+        prefix, max_attempts
+    ))
+}
+
+/// Generates node seeds until the Stacks address of the node's keychain, as derived from
+/// `node.seed`, starts with `prefix`, giving up after `max_attempts`.  Returns the seed.
+pub fn grind_vanity_seed(
+    prefix: &str,
+    mainnet: bool,
+    max_attempts: u64,
+) -> Result<Vec<u8>, String> {
+    check_vanity_prefix(prefix, mainnet)?;
+    let mut rng = rand::thread_rng();
+    for _ in 0..max_attempts {
+        let mut seed = vec![0u8; 32];
+        rng.fill_bytes(&mut seed);
+        let keychain = Keychain::default(seed.clone());
+        if keychain
+            .get_address(mainnet)
+            .to_string()
+            .starts_with(prefix)
+        {
+            return Ok(seed);
+        }
+    }
+    Err(format!(
+        "No address starting with '{}' found in {} attempts",
+        prefix, max_attempts
+    ))
+}
+
+/// Hex-encodes a node seed, as `node.seed` expects it.
+pub fn seed_to_hex(seed: &[u8]) -> String {
+    to_hex(seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use stacks::util::hash::hex_bytes;
+
+    /// The TREZOR BIP39 test vectors, https://github.com/trezor/python-mnemonic/blob/master/vectors.json:
+    /// entropy, mnemonic, seed with the passphrase "TREZOR", and the BIP32 master key of the seed
+    const BIP39_VECTORS: &[(&str, &str, &str, &str)] = &[
+        (
+            "00000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+            "xprv9s21ZrQH143K3h3fDYiay8mocZ3afhfULfb5GX8kCBdno77K4HiA15Tg23wpbeF1pLfs1c5SPmYHrEpTuuRhxMwvKDwqdKiGJS9XFKzUsAF",
+        ),
+        (
+            "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+            "legal winner thank year wave sausage worth useful legal winner thank yellow",
+            "2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6fa457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607",
+            "xprv9s21ZrQH143K2gA81bYFHqU68xz1cX2APaSq5tt6MFSLeXnCKV1RVUJt9FWNTbrrryem4ZckN8k4Ls1H6nwdvDTvnV7zEXs2HgPezuVccsq",
+        ),
+        (
+            "80808080808080808080808080808080",
+            "letter advice cage absurd amount doctor acoustic avoid letter advice cage above",
+            "d71de856f81a8acc65e6fc851a38d4d7ec216fd0796d0a6827a3ad6ed5511a30fa280f12eb2e47ed2ac03b5c462a0358d18d69fe4f985ec81778c1b370b652a8",
+            "xprv9s21ZrQH143K2shfP28KM3nr5Ap1SXjz8gc2rAqqMEynmjt6o1qboCDpxckqXavCwdnYds6yBHZGKHv7ef2eTXy461PXUjBFQg6PrwY4Gzq",
+        ),
+        (
+            "ffffffffffffffffffffffffffffffff",
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong",
+            "ac27495480225222079d7be181583751e86f571027b0497b5b5d11218e0a8a13332572917f0f8e5a589620c6f15b11c61dee327651a14c34e18231052e48c069",
+            "xprv9s21ZrQH143K2V4oox4M8Zmhi2Fjx5XK4Lf7GKRvPSgydU3mjZuKGCTg7UPiBUD7ydVPvSLtg9hjp7MQTYsW67rZHAXeccqYqrsx8LcXnyd",
+        ),
+        (
+            "000000000000000000000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon agent",
+            "035895f2f481b1b0f01fcf8c289c794660b289981a78f8106447707fdd9666ca06da5a9a565181599b79f53b844d8a71dd9f439c52a3d7b3e8a79c906ac845fa",
+            "xprv9s21ZrQH143K3mEDrypcZ2usWqFgzKB6jBBx9B6GfC7fu26X6hPRzVjzkqkPvDqp6g5eypdk6cyhGnBngbjeHTe4LsuLG1cCmKJka5SMkmU",
+        ),
+        (
+            "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+            "legal winner thank year wave sausage worth useful legal winner thank year wave sausage worth useful legal will",
+            "f2b94508732bcbacbcc020faefecfc89feafa6649a5491b8c952cede496c214a0c7b3c392d168748f2d4a612bada0753b52a1c7ac53c1e93abd5c6320b9e95dd",
+            "xprv9s21ZrQH143K3Lv9MZLj16np5GzLe7tDKQfVusBni7toqJGcnKRtHSxUwbKUyUWiwpK55g1DUSsw76TF1T93VT4gz4wt5RM23pkaQLnvBh7",
+        ),
+        (
+            "808080808080808080808080808080808080808080808080",
+            "letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic avoid letter always",
+            "107d7c02a5aa6f38c58083ff74f04c607c2d2c0ecc55501dadd72d025b751bc27fe913ffb796f841c49b1d33b610cf0e91d3aa239027f5e99fe4ce9e5088cd65",
+            "xprv9s21ZrQH143K3VPCbxbUtpkh9pRG371UCLDz3BjceqP1jz7XZsQ5EnNkYAEkfeZp62cDNj13ZTEVG1TEro9sZ9grfRmcYWLBhCocViKEJae",
+        ),
+        (
+            "ffffffffffffffffffffffffffffffffffffffffffffffff",
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo when",
+            "0cd6e5d827bb62eb8fc1e262254223817fd068a74b5b449cc2f667c3f1f985a76379b43348d952e2265b4cd129090758b3e3c2c49103b5051aac2eaeb890a528",
+            "xprv9s21ZrQH143K36Ao5jHRVhFGDbLP6FCx8BEEmpru77ef3bmA928BxsqvVM27WnvvyfWywiFN8K6yToqMaGYfzS6Db1EHAXT5TuyCLBXUfdm",
+        ),
+        (
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art",
+            "bda85446c68413707090a52022edd26a1c9462295029f2e60cd7c4f2bbd3097170af7a4d73245cafa9c3cca8d561a7c3de6f5d4a10be8ed2a5e608d68f92fcc8",
+            "xprv9s21ZrQH143K32qBagUJAMU2LsHg3ka7jqMcV98Y7gVeVyNStwYS3U7yVVoDZ4btbRNf4h6ibWpY22iRmXq35qgLs79f312g2kj5539ebPM",
+        ),
+        (
+            "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+            "legal winner thank year wave sausage worth useful legal winner thank year wave sausage worth useful legal winner thank year wave sausage worth title",
+            "bc09fca1804f7e69da93c2f2028eb238c227f2e9dda30cd63699232578480a4021b146ad717fbb7e451ce9eb835f43620bf5c514db0f8add49f5d121449d3e87",
+            "xprv9s21ZrQH143K3Y1sd2XVu9wtqxJRvybCfAetjUrMMco6r3v9qZTBeXiBZkS8JxWbcGJZyio8TrZtm6pkbzG8SYt1sxwNLh3Wx7to5pgiVFU",
+        ),
+        (
+            "8080808080808080808080808080808080808080808080808080808080808080",
+            "letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic bless",
+            "c0c519bd0e91a2ed54357d9d1ebef6f5af218a153624cf4f2da911a0ed8f7a09e2ef61af0aca007096df430022f7a2b6fb91661a9589097069720d015e4e982f",
+            "xprv9s21ZrQH143K3CSnQNYC3MqAAqHwxeTLhDbhF43A4ss4ciWNmCY9zQGvAKUSqVUf2vPHBTSE1rB2pg4avopqSiLVzXEU8KziNnVPauTqLRo",
+        ),
+        (
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo vote",
+            "dd48c104698c30cfe2b6142103248622fb7bb0ff692eebb00089b32d22484e1613912f0a5b694407be899ffd31ed3992c456cdf60f5d4564b8ba3f05a69890ad",
+            "xprv9s21ZrQH143K2WFF16X85T2QCpndrGwx6GueB72Zf3AHwHJaknRXNF37ZmDrtHrrLSHvbuRejXcnYxoZKvRquTPyp2JiNG3XcjQyzSEgqCB",
+        ),
+        (
+            "9e885d952ad362caeb4efe34a8e91bd2",
+            "ozone drill grab fiber curtain grace pudding thank cruise elder eight picnic",
+            "274ddc525802f7c828d8ef7ddbcdc5304e87ac3535913611fbbfa986d0c9e5476c91689f9c8a54fd55bd38606aa6a8595ad213d4c9c9f9aca3fb217069a41028",
+            "xprv9s21ZrQH143K2oZ9stBYpoaZ2ktHj7jLz7iMqpgg1En8kKFTXJHsjxry1JbKH19YrDTicVwKPehFKTbmaxgVEc5TpHdS1aYhB2s9aFJBeJH",
+        ),
+        (
+            "6610b25967cdcca9d59875f5cb50b0ea75433311869e930b",
+            "gravity machine north sort system female filter attitude volume fold club stay feature office ecology stable narrow fog",
+            "628c3827a8823298ee685db84f55caa34b5cc195a778e52d45f59bcf75aba68e4d7590e101dc414bc1bbd5737666fbbef35d1f1903953b66624f910feef245ac",
+            "xprv9s21ZrQH143K3uT8eQowUjsxrmsA9YUuQQK1RLqFufzybxD6DH6gPY7NjJ5G3EPHjsWDrs9iivSbmvjc9DQJbJGatfa9pv4MZ3wjr8qWPAK",
+        ),
+        (
+            "68a79eaca2324873eacc50cb9c6eca8cc68ea5d936f98787c60c7ebc74e6ce7c",
+            "hamster diagram private dutch cause delay private meat slide toddler razor book happy fancy gospel tennis maple dilemma loan word shrug inflict delay length",
+            "64c87cde7e12ecf6704ab95bb1408bef047c22db4cc7491c4271d170a1b213d20b385bc1588d9c7b38f1b39d415665b8a9030c9ec653d75e65f847d8fc1fc440",
+            "xprv9s21ZrQH143K2XTAhys3pMNcGn261Fi5Ta2Pw8PwaVPhg3D8DWkzWQwjTJfskj8ofb81i9NP2cUNKxwjueJHHMQAnxtivTA75uUFqPFeWzk",
+        ),
+        (
+            "c0ba5a8e914111210f2bd131f3d5e08d",
+            "scheme spot photo card baby mountain device kick cradle pact join borrow",
+            "ea725895aaae8d4c1cf682c1bfd2d358d52ed9f0f0591131b559e2724bb234fca05aa9c02c57407e04ee9dc3b454aa63fbff483a8b11de949624b9f1831a9612",
+            "xprv9s21ZrQH143K3FperxDp8vFsFycKCRcJGAFmcV7umQmcnMZaLtZRt13QJDsoS5F6oYT6BB4sS6zmTmyQAEkJKxJ7yByDNtRe5asP2jFGhT6",
+        ),
+        (
+            "6d9be1ee6ebd27a258115aad99b7317b9c8d28b6d76431c3",
+            "horn tenant knee talent sponsor spell gate clip pulse soap slush warm silver nephew swap uncle crack brave",
+            "fd579828af3da1d32544ce4db5c73d53fc8acc4ddb1e3b251a31179cdb71e853c56d2fcb11aed39898ce6c34b10b5382772db8796e52837b54468aeb312cfc3d",
+            "xprv9s21ZrQH143K3R1SfVZZLtVbXEB9ryVxmVtVMsMwmEyEvgXN6Q84LKkLRmf4ST6QrLeBm3jQsb9gx1uo23TS7vo3vAkZGZz71uuLCcywUkt",
+        ),
+        (
+            "9f6a2878b2520799a44ef18bc7df394e7061a224d2c33cd015b157d746869863",
+            "panda eyebrow bullet gorilla call smoke muffin taste mesh discover soft ostrich alcohol speed nation flash devote level hobby quick inner drive ghost inside",
+            "72be8e052fc4919d2adf28d5306b5474b0069df35b02303de8c1729c9538dbb6fc2d731d5f832193cd9fb6aeecbc469594a70e3dd50811b5067f3b88b28c3e8d",
+            "xprv9s21ZrQH143K2WNnKmssvZYM96VAr47iHUQUTUyUXH3sAGNjhJANddnhw3i3y3pBbRAVk5M5qUGFr4rHbEWwXgX4qrvrceifCYQJbbFDems",
+        ),
+        (
+            "23db8160a31d3e0dca3688ed941adbf3",
+            "cat swing flag economy stadium alone churn speed unique patch report train",
+            "deb5f45449e615feff5640f2e49f933ff51895de3b4381832b3139941c57b59205a42480c52175b6efcffaa58a2503887c1e8b363a707256bdd2b587b46541f5",
+            "xprv9s21ZrQH143K4G28omGMogEoYgDQuigBo8AFHAGDaJdqQ99QKMQ5J6fYTMfANTJy6xBmhvsNZ1CJzRZ64PWbnTFUn6CDV2FxoMDLXdk95DQ",
+        ),
+        (
+            "8197a4a47f0425faeaa69deebc05ca29c0a5b5cc76ceacc0",
+            "light rule cinnamon wrap drastic word pride squirrel upgrade then income fatal apart sustain crack supply proud access",
+            "4cbdff1ca2db800fd61cae72a57475fdc6bab03e441fd63f96dabd1f183ef5b782925f00105f318309a7e9c3ea6967c7801e46c8a58082674c860a37b93eda02",
+            "xprv9s21ZrQH143K3wtsvY8L2aZyxkiWULZH4vyQE5XkHTXkmx8gHo6RUEfH3Jyr6NwkJhvano7Xb2o6UqFKWHVo5scE31SGDCAUsgVhiUuUDyh",
+        ),
+        (
+            "066dca1a2bb7e8a1db2832148ce9933eea0f3ac9548d793112d9a95c9407efad",
+            "all hour make first leader extend hole alien behind guard gospel lava path output census museum junior mass reopen famous sing advance salt reform",
+            "26e975ec644423f4a4c4f4215ef09b4bd7ef924e85d1d17c4cf3f136c2863cf6df0a475045652c57eb5fb41513ca2a2d67722b77e954b4b3fc11f7590449191d",
+            "xprv9s21ZrQH143K3rEfqSM4QZRVmiMuSWY9wugscmaCjYja3SbUD3KPEB1a7QXJoajyR2T1SiXU7rFVRXMV9XdYVSZe7JoUXdP4SRHTxsT1nzm",
+        ),
+        (
+            "f30f8c1da665478f49b001d94c5fc452",
+            "vessel ladder alter error federal sibling chat ability sun glass valve picture",
+            "2aaa9242daafcee6aa9d7269f17d4efe271e1b9a529178d7dc139cd18747090bf9d60295d0ce74309a78852a9caadf0af48aae1c6253839624076224374bc63f",
+            "xprv9s21ZrQH143K2QWV9Wn8Vvs6jbqfF1YbTCdURQW9dLFKDovpKaKrqS3SEWsXCu6ZNky9PSAENg6c9AQYHcg4PjopRGGKmdD313ZHszymnps",
+        ),
+        (
+            "c10ec20dc3cd9f652c7fac2f1230f7a3c828389a14392f05",
+            "scissors invite lock maple supreme raw rapid void congress muscle digital elegant little brisk hair mango congress clump",
+            "7b4a10be9d98e6cba265566db7f136718e1398c71cb581e1b2f464cac1ceedf4f3e274dc270003c670ad8d02c4558b2f8e39edea2775c9e232c7cb798b069e88",
+            "xprv9s21ZrQH143K4aERa2bq7559eMCCEs2QmmqVjUuzfy5eAeDX4mqZffkYwpzGQRE2YEEeLVRoH4CSHxianrFaVnMN2RYaPUZJhJx8S5j6puX",
+        ),
+        (
+            "f585c11aec520db57dd353c69554b21a89b20fb0650966fa0a9d6f74fd989d8f",
+            "void come effort suffer camp survey warrior heavy shoot primary clutch crush open amazing screen patrol group space point ten exist slush involve unfold",
+            "01f5bced59dec48e362f2c45b5de68b9fd6c92c6634f44d6d40aab69056506f0e35524a518034ddc1192e1dacd32c1ed3eaa3c3b131c88ed8e7e54c49a5d0998",
+            "xprv9s21ZrQH143K39rnQJknpH1WEPFJrzmAqqasiDcVrNuk926oizzJDDQkdiTvNPr2FYDYzWgiMiC63YmfPAa2oPyNB23r2g7d1yiK6WpqaQS",
+        ),
+    ];
+
+    /// Decodes an `xprv` and checks that it holds `key`'s chain code and secret key.
+    fn assert_xprv(key: &ExtendedPrivateKey, xprv: &str) {
+        let bytes = stacks::address::b58::from_check(xprv).unwrap();
+        assert_eq!(bytes.len(), 78);
+        assert_eq!(to_hex(&bytes[13..45]), to_hex(&key.chain_code), "{}", xprv);
+        assert_eq!(bytes[45], 0);
+        assert_eq!(to_hex(&bytes[46..]), to_hex(&key.secret_key), "{}", xprv);
+    }
+
+    #[test]
+    fn test_bip39_wordlist() {
+        let words = bip39_words();
+        assert_eq!(words.len(), 2048);
+        assert_eq!(
+            Sha256Sum::from_data(BIP39_ENGLISH.as_bytes()).to_hex(),
+            "2f5eed53a4727b4bf8880d8f3f199efc90e58503646d9ff8eff3a2ed3b24dbda"
+        );
+    }
+
+    #[test]
+    fn test_bip39_vectors() {
+        for (entropy, mnemonic, seed, xprv) in BIP39_VECTORS.iter() {
+            assert_eq!(
+                mnemonic_from_entropy(&hex_bytes(entropy).unwrap()).unwrap(),
+                *mnemonic
+            );
+            let derived_seed = mnemonic_to_seed(mnemonic, "TREZOR").unwrap();
+            assert_eq!(to_hex(&derived_seed), *seed);
+            assert_xprv(&ExtendedPrivateKey::from_seed(&derived_seed).unwrap(), xprv);
+        }
+
+        // round trip
+        let mnemonic = generate_mnemonic(24).unwrap();
+        assert_eq!(mnemonic.split(' ').count(), 24);
+        mnemonic_to_seed(&mnemonic, "").unwrap();
+
+        assert!(mnemonic_to_seed(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon",
+            ""
+        )
+        .unwrap_err()
+        .contains("checksum"));
+        assert!(mnemonic_to_seed("abandon satoshis", "")
+            .unwrap_err()
+            .contains("length"));
+        assert!(generate_mnemonic(13).is_err());
+    }
+
+    #[test]
+    fn test_bip32_vectors() {
+        // BIP32 test vectors 1 and 2,
+        // https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki#test-vectors
+        let vectors: &[(&str, &[(&str, &str)])] = &[
+            (
+                "000102030405060708090a0b0c0d0e0f",
+                &[
+                    ("m", "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi"),
+                    ("m/0'", "xprv9uHRZZhk6KAJC1avXpDAp4MDc3sQKNxDiPvvkX8Br5ngLNv1TxvUxt4cV1rGL5hj6KCesnDYUhd7oWgT11eZG7XnxHrnYeSvkzY7d2bhkJ7"),
+                    ("m/0'/1", "xprv9wTYmMFdV23N2TdNG573QoEsfRrWKQgWeibmLntzniatZvR9BmLnvSxqu53Kw1UmYPxLgboyZQaXwTCg8MSY3H2EU4pWcQDnRnrVA1xe8fs"),
+                    ("m/0'/1/2'", "xprv9z4pot5VBttmtdRTWfWQmoH1taj2axGVzFqSb8C9xaxKymcFzXBDptWmT7FwuEzG3ryjH4ktypQSAewRiNMjANTtpgP4mLTj34bhnZX7UiM"),
+                    ("m/0'/1/2'/2", "xprvA2JDeKCSNNZky6uBCviVfJSKyQ1mDYahRjijr5idH2WwLsEd4Hsb2Tyh8RfQMuPh7f7RtyzTtdrbdqqsunu5Mm3wDvUAKRHSC34sJ7in334"),
+                    ("m/0'/1/2'/2/1000000000", "xprvA41z7zogVVwxVSgdKUHDy1SKmdb533PjDz7J6N6mV6uS3ze1ai8FHa8kmHScGpWmj4WggLyQjgPie1rFSruoUihUZREPSL39UNdE3BBDu76"),
+                ],
+            ),
+            (
+                "fffcf9f6f3f0edeae7e4e1dedbd8d5d2cfccc9c6c3c0bdbab7b4b1aeaba8a5a29f9c999693908d8a8784817e7b7875726f6c696663605d5a5754514e4b484542",
+                &[
+                    ("m", "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U"),
+                    ("m/0", "xprv9vHkqa6EV4sPZHYqZznhT2NPtPCjKuDKGY38FBWLvgaDx45zo9WQRUT3dKYnjwih2yJD9mkrocEZXo1ex8G81dwSM1fwqWpWkeS3v86pgKt"),
+                    ("m/0/2147483647'", "xprv9wSp6B7kry3Vj9m1zSnLvN3xH8RdsPP1Mh7fAaR7aRLcQMKTR2vidYEeEg2mUCTAwCd6vnxVrcjfy2kRgVsFawNzmjuHc2YmYRmagcEPdU9"),
+                    ("m/0/2147483647'/1", "xprv9zFnWC6h2cLgpmSA46vutJzBcfJ8yaJGg8cX1e5StJh45BBciYTRXSd25UEPVuesF9yog62tGAQtHjXajPPdbRCHuWS6T8XA2ECKADdw4Ef"),
+                    ("m/0/2147483647'/1/2147483646'", "xprvA1RpRA33e1JQ7ifknakTFpgNXPmW2YvmhqLQYMmrj4xJXXWYpDPS3xz7iAxn8L39njGVyuoseXzU6rcxFLJ8HFsTjSyQbLYnMpCqE2VbFWc"),
+                    ("m/0/2147483647'/1/2147483646'/2", "xprvA2nrNbFZABcdryreWet9Ea4LvTJcGsqrMzxHx98MMrotbir7yrKCEXw7nadnHM8Dq38EGfSh6dqA9QWTyefMLEcBYJUuekgW4BYPJcr9E7j"),
+                ],
+            ),
+        ];
+        for (seed, chains) in vectors.iter() {
+            let master = ExtendedPrivateKey::from_seed(&hex_bytes(seed).unwrap()).unwrap();
+            for (path, xprv) in chains.iter() {
+                assert_xprv(&master.derive_path(path).unwrap(), xprv);
+            }
+        }
+
+        let master =
+            ExtendedPrivateKey::from_seed(&hex_bytes("000102030405060708090a0b0c0d0e0f").unwrap())
+                .unwrap();
+        assert_eq!(
+            master.derive_path("m/0h/1").unwrap(),
+            master.derive_path("m/0'/1").unwrap()
+        );
+        assert!(master.derive_path("0/1").is_err());
+        assert!(master.derive_path("m/2147483648").is_err());
+    }
+
+    #[test]
+    fn test_derive_keys() {
+        let seed = mnemonic_to_seed(&mnemonic_from_entropy(&[0u8; 16]).unwrap(), "").unwrap();
+        let mainnet = derive_keys(&seed, 0, true).unwrap();
+        assert_eq!(mainnet.stacks_path, "m/44'/5757'/0'/0/0");
+        assert_eq!(mainnet.bitcoin_path, "m/44'/0'/0'/0/0");
+        assert!(mainnet.stacks_address.starts_with("SP"));
+        assert!(mainnet.stacks_secret_key.ends_with("01"));
+        assert_eq!(
+            mainnet.bitcoin_address,
+            "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA"
+        );
+        assert_eq!(
+            mainnet.bitcoin_wif,
+            "L4p2b9VAf8k5aUahF1JCJUzZkgNEAqLfq8DDdQiyAprQAKSbu8hf"
+        );
+
+        let testnet = derive_keys(&seed, 0, false).unwrap();
+        assert_eq!(testnet.bitcoin_path, "m/44'/1'/0'/0/0");
+        assert!(testnet.stacks_address.starts_with("ST"));
+        assert_eq!(testnet.stacks_secret_key, mainnet.stacks_secret_key);
+        assert_ne!(derive_keys(&seed, 1, true).unwrap(), mainnet);
+    }
+
+    #[test]
+    fn test_vanity() {
+        assert!(check_vanity_prefix("SP1", true).is_ok());
+        assert!(check_vanity_prefix("ST1", true).is_err());
+        assert!(check_vanity_prefix("SPO", true)
+            .unwrap_err()
+            .contains("never appears"));
+
+        let seed = grind_vanity_seed("SP1", true, 10_000).unwrap();
+        assert!(Keychain::default(seed)
+            .get_address(true)
+            .to_string()
+            .starts_with("SP1"));
+        assert!(grind_vanity_seed("SP00000000", true, 1).is_err());
+    }
+}
@@ -17,42 +17,80 @@ extern crate stacks;
 #[macro_use(o, slog_log, slog_trace, slog_debug, slog_info, slog_warn, slog_error)]
 extern crate slog;
 
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::stacks::index::marf::verify_proof;
+use stacks::net::atlas::{AtlasDB, AttachmentStoreConfig};
+use stacks::types::chainstate::{StacksBlockId, TrieHash};
 pub use stacks::util;
 use stacks::util::hash::hex_bytes;
+use stacks::vm::coverage::CoverageReporter;
 
 pub mod monitoring;
 
+pub mod admin;
+pub mod backup;
+pub mod btc_wallet;
+pub mod burnchain_reorg;
 pub mod burnchains;
 pub mod config;
+pub mod diagnostics;
 pub mod event_dispatcher;
+pub mod export;
 pub mod genesis_data;
+pub mod grpc;
 pub mod keychain;
+pub mod keygen;
+pub mod mempool_stats;
+pub mod microblock_propagation;
+pub mod miner_stats;
+pub mod nats;
 pub mod neon_node;
 pub mod node;
 pub mod operations;
+pub mod preflight;
+pub mod profiler;
+pub mod replay;
+pub mod replica;
 pub mod run_loop;
+pub mod seed_encryption;
+pub mod snapshot;
+pub mod sortition_info;
+pub mod stack_stx;
+pub mod stall_watchdog;
+pub mod standby;
+pub mod supervisor;
+pub mod sync_progress;
 pub mod syncctl;
 pub mod tenure;
 
 pub use self::burnchains::{
     BitcoinRegtestController, BurnchainController, BurnchainTip, MocknetController,
 };
-pub use self::config::{Config, ConfigFile};
+pub use self::config::{Config, ConfigFile, ConfigProfile};
 pub use self::event_dispatcher::EventDispatcher;
 pub use self::keychain::Keychain;
 pub use self::node::{ChainTip, Node};
-pub use self::run_loop::{helium, neon};
+pub use self::run_loop::{helium, neon, scenario::Scenario};
 pub use self::tenure::Tenure;
 
+use crate::burnchains::commit_journal::CommitJournal;
+
 use pico_args::Arguments;
+use std::collections::HashMap;
 use std::env;
 
 use std::convert::TryInto;
+use std::fs;
 use std::panic;
+use std::path::{Path, PathBuf};
 use std::process;
 
 use backtrace::Backtrace;
 
+/// Samples allocations while a heap profile is being taken; see `profiler`
+#[global_allocator]
+static GLOBAL_ALLOCATOR: profiler::ProfilingAllocator = profiler::ProfilingAllocator;
+
 fn main() {
     panic::set_hook(Box::new(|panic_info| {
         error!("Process abort due to thread panic: {}", panic_info);
@@ -92,6 +130,12 @@ fn main() {
         );
     }
 
+    let scenario_path: Option<String> = args
+        .opt_value_from_str("--scenario")
+        .expect("Failed to parse --scenario argument");
+
+    let mut windows_service = false;
+
     let config_file = match subcommand.as_str() {
         "mocknet" => {
             args.finish().unwrap();
@@ -111,31 +155,53 @@ fn main() {
         }
         "check-config" => {
             let config_path: String = args.value_from_str("--config").unwrap();
+            let strict = args.contains("--strict");
+            let profile = parse_profile_arg(&mut args);
             args.finish().unwrap();
             info!("Loading config at path {}", config_path);
-            let config_file = match ConfigFile::from_path(&config_path) {
-                Ok(config_file) => config_file,
+            let content = match fs::read_to_string(&config_path) {
+                Ok(content) => content,
                 Err(e) => {
-                    warn!("Invalid config file: {}", e);
+                    warn!("Invalid config file: Invalid path: {}", e);
                     process::exit(1);
                 }
             };
-            let conf = match Config::from_config_file(config_file) {
-                Ok(conf) => {
-                    info!("Loaded config!");
-                    process::exit(0);
-                }
+            let (config_file, mut warnings) =
+                match ConfigFile::from_str_with_profile(&content, profile) {
+                    Ok(parsed) => parsed,
+                    Err(e) => {
+                        warn!("Invalid config file: {}", e);
+                        process::exit(1);
+                    }
+                };
+            warnings.extend(config_file.check_consistency());
+            match Config::from_config_file(config_file) {
+                Ok(conf) => warnings.extend(conf.check_event_observers()),
                 Err(e) => {
                     warn!("Invalid config: {}", e);
                     process::exit(1);
                 }
             };
+            for warning in warnings.iter() {
+                warn!("{}", warning);
+            }
+            if strict && !warnings.is_empty() {
+                warn!("Config has {} warning(s) in strict mode", warnings.len());
+                process::exit(1);
+            }
+            info!("Loaded config!");
+            process::exit(0);
         }
         "start" => {
             let config_path: String = args.value_from_str("--config").unwrap();
+            windows_service = args.contains("--windows-service");
+            let profile = parse_profile_arg(&mut args);
             args.finish().unwrap();
             info!("Loading config at path {}", config_path);
-            match ConfigFile::from_path(&config_path) {
+            if let Some(profile) = profile {
+                info!("Using the built-in '{}' config profile", profile.as_str());
+            }
+            match ConfigFile::from_path_with_profile(&config_path, profile) {
                 Ok(config_file) => config_file,
                 Err(e) => {
                     warn!("Invalid config file: {}", e);
@@ -143,12 +209,511 @@ fn main() {
                 }
             }
         }
+        "export-snapshot" | "import-snapshot" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let include_mempool = args.contains("--include-mempool");
+            let overwrite = args.contains("--overwrite");
+            let dest: Option<String> = args.opt_value_from_str("--dest").unwrap();
+            let src: Option<String> = args.opt_value_from_str("--src").unwrap();
+            args.finish().unwrap();
+            info!("Loading config at path {}", config_path);
+            let conf = match ConfigFile::from_path(&config_path).and_then(Config::from_config_file)
+            {
+                Ok(conf) => conf,
+                Err(e) => {
+                    warn!("Invalid config: {}", e);
+                    process::exit(1);
+                }
+            };
+            let result = if subcommand == "export-snapshot" {
+                let dest = dest.expect("`export-snapshot` requires a `--dest` directory");
+                snapshot::export_snapshot(&conf, &version(), Path::new(&dest), include_mempool)
+            } else {
+                let src = src.expect("`import-snapshot` requires a `--src` directory");
+                snapshot::import_snapshot(&conf, Path::new(&src), overwrite)
+            };
+            if let Err(e) = result {
+                warn!("Failed to {}: {}", subcommand, e);
+                process::exit(1);
+            }
+            return;
+        }
+        "preflight-upgrade" | "migration-dry-run" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let new_binary: Option<String> = args.opt_value_from_str("--new-binary").unwrap();
+            args.finish().unwrap();
+            let report = if subcommand == "preflight-upgrade" {
+                let new_binary =
+                    new_binary.expect("`preflight-upgrade` requires a `--new-binary` path");
+                match preflight::run_preflight(&new_binary, &config_path) {
+                    Ok(report) => report,
+                    Err(e) => {
+                        warn!("Failed to run upgrade preflight: {}", e);
+                        process::exit(1);
+                    }
+                }
+            } else {
+                info!("Loading config at path {}", config_path);
+                let conf =
+                    match ConfigFile::from_path(&config_path).and_then(Config::from_config_file) {
+                        Ok(conf) => conf,
+                        Err(e) => {
+                            warn!("Invalid config: {}", e);
+                            process::exit(1);
+                        }
+                    };
+                let report = preflight::dry_run_migrations(&conf, &version());
+                println!(
+                    "{}",
+                    serde_json::to_string(&report).expect("Failed to serialize migration report")
+                );
+                return;
+            };
+            preflight::print_report(&report);
+            if !report.blockers.is_empty() {
+                process::exit(1);
+            }
+            return;
+        }
+        "mempool-stats" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let top_n: usize = args.opt_value_from_str("--top").unwrap().unwrap_or(20);
+            args.finish().unwrap();
+            info!("Loading config at path {}", config_path);
+            let conf = match ConfigFile::from_path(&config_path).and_then(Config::from_config_file)
+            {
+                Ok(conf) => conf,
+                Err(e) => {
+                    warn!("Invalid config: {}", e);
+                    process::exit(1);
+                }
+            };
+            let stats = snapshot::get_mempool_path(&conf).and_then(|path| {
+                mempool_stats::mempool_stats(&path, top_n, util::get_epoch_time_secs())
+            });
+            match stats {
+                Ok(stats) => {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&stats)
+                            .expect("Failed to serialize mempool stats")
+                    );
+                    return;
+                }
+                Err(e) => {
+                    warn!("Failed to read mempool stats: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        "btc-wallet" => {
+            let action = args.subcommand().unwrap().unwrap_or_default();
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let fee_rate: Option<u64> = args.opt_value_from_str("--fee-rate").unwrap();
+            let dry_run = args.contains("--dry-run");
+            let recipient: Option<String> = args.opt_value_from_str("--address").unwrap();
+            let amount: Option<u64> = args.opt_value_from_str("--amount").unwrap();
+            let pre_stx_txid: Option<String> = args.opt_value_from_str("--pre-stx-txid").unwrap();
+            let memo: Option<String> = args.opt_value_from_str("--memo").unwrap();
+            let private_key: Option<String> = args.opt_value_from_str("--private-key").unwrap();
+            let seed: Option<String> = args.opt_value_from_str("--seed").unwrap();
+            args.finish().unwrap();
+            info!("Loading config at path {}", config_path);
+            let conf = match ConfigFile::from_path(&config_path).and_then(Config::from_config_file)
+            {
+                Ok(conf) => conf,
+                Err(e) => {
+                    warn!("Invalid config: {}", e);
+                    process::exit(1);
+                }
+            };
+            let fee_rate = fee_rate.unwrap_or(conf.burnchain.satoshis_per_byte);
+            let result = match action.as_str() {
+                "balance" => btc_wallet::wallet_balance(&conf).map(|balance| json!(balance)),
+                "list-utxos" => btc_wallet::list_utxos(&conf).map(|utxos| json!(utxos)),
+                "consolidate" => {
+                    btc_wallet::consolidate(&conf, fee_rate, !dry_run).map(|tx| json!(tx))
+                }
+                "send-to-address" => match (recipient, amount) {
+                    (Some(recipient), Some(amount)) => {
+                        btc_wallet::send_to_address(&conf, &recipient, amount, fee_rate, !dry_run)
+                            .map(|tx| json!(tx))
+                    }
+                    _ => Err("send-to-address needs --address and --amount".to_string()),
+                },
+                "pre-stx" => {
+                    stack_stx::stacker_keychain(private_key.as_deref(), seed.as_deref(), &conf)
+                        .and_then(|keychain| {
+                            btc_wallet::pre_stx(
+                                &conf,
+                                &mut keychain.generate_op_signer(),
+                                recipient.as_deref(),
+                                fee_rate,
+                                !dry_run,
+                            )
+                        })
+                        .map(|tx| json!(tx))
+                }
+                "transfer-stx" => match (pre_stx_txid, recipient, amount) {
+                    (Some(pre_stx_txid), Some(recipient), Some(amount)) => {
+                        let memo = match memo {
+                            Some(memo) => stacks::util::hash::hex_bytes(&memo)
+                                .map_err(|_| "--memo should be a hex encoded string".to_string()),
+                            None => Ok(vec![]),
+                        };
+                        memo.and_then(|memo| {
+                            let keychain = stack_stx::stacker_keychain(
+                                private_key.as_deref(),
+                                seed.as_deref(),
+                                &conf,
+                            )?;
+                            btc_wallet::transfer_stx(
+                                &conf,
+                                &mut keychain.generate_op_signer(),
+                                &pre_stx_txid,
+                                &recipient,
+                                amount.into(),
+                                &memo,
+                                fee_rate,
+                                !dry_run,
+                            )
+                        })
+                        .map(|tx| json!(tx))
+                    }
+                    _ => {
+                        Err("transfer-stx needs --pre-stx-txid, --address and --amount".to_string())
+                    }
+                },
+                _ => {
+                    print_help();
+                    return;
+                }
+            };
+            match result {
+                Ok(result) => {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&result)
+                            .expect("Failed to serialize btc-wallet output")
+                    );
+                    return;
+                }
+                Err(e) => {
+                    warn!("btc-wallet {} failed: {}", action, e);
+                    process::exit(1);
+                }
+            }
+        }
+        "stack-stx" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let call: String = args
+                .opt_value_from_str("--call")
+                .unwrap()
+                .unwrap_or("stack-stx".to_string());
+            let amount_ustx: u128 = args.value_from_str("--amount").unwrap();
+            let lock_period: Option<u64> = args.opt_value_from_str("--cycles").unwrap();
+            let reward_address: Option<String> =
+                args.opt_value_from_str("--reward-address").unwrap();
+            let delegate_to: Option<String> = args.opt_value_from_str("--delegate-to").unwrap();
+            let until_burn_height: Option<u64> =
+                args.opt_value_from_str("--until-burn-height").unwrap();
+            let private_key: Option<String> = args.opt_value_from_str("--private-key").unwrap();
+            let seed: Option<String> = args.opt_value_from_str("--seed").unwrap();
+            let options = stack_stx::StackingOptions {
+                start_burn_height: args.opt_value_from_str("--start-burn-height").unwrap(),
+                nonce: args.opt_value_from_str("--nonce").unwrap(),
+                fee: args.value_from_str("--fee").unwrap(),
+                node: args.opt_value_from_str("--node").unwrap(),
+                broadcast: args.contains("--broadcast"),
+            };
+            args.finish().unwrap();
+            info!("Loading config at path {}", config_path);
+            let conf = match ConfigFile::from_path(&config_path).and_then(Config::from_config_file)
+            {
+                Ok(conf) => conf,
+                Err(e) => {
+                    warn!("Invalid config: {}", e);
+                    process::exit(1);
+                }
+            };
+            let result = stack_stx::PoxCall::from_str(&call)
+                .and_then(|call| {
+                    let reward_address = match reward_address {
+                        Some(address) => Some(btc_wallet::parse_recipient(&conf, &address)?),
+                        None => None,
+                    };
+                    let delegate_to = match delegate_to {
+                        Some(principal) => Some(
+                            clarity::vm::types::PrincipalData::parse(&principal)
+                                .map_err(|e| format!("Invalid --delegate-to: {:?}", e))?,
+                        ),
+                        None => None,
+                    };
+                    Ok(stack_stx::StackingRequest {
+                        call,
+                        amount_ustx,
+                        lock_period,
+                        reward_address,
+                        delegate_to,
+                        until_burn_height,
+                    })
+                })
+                .and_then(|request| {
+                    let keychain = stack_stx::stacker_keychain(
+                        private_key.as_deref(),
+                        seed.as_deref(),
+                        &conf,
+                    )?;
+                    stack_stx::stack_stx(&conf, &keychain, &request, &options)
+                });
+            match result {
+                Ok(tx) => {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&tx)
+                            .expect("Failed to serialize stacking transaction")
+                    );
+                    return;
+                }
+                Err(e) => {
+                    warn!("stack-stx failed: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        "migrate-attachments" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let from_config_path: Option<String> =
+                args.opt_value_from_str("--from-config").unwrap();
+            args.finish().unwrap();
+            info!("Loading config at path {}", config_path);
+            let load = |path: &str| ConfigFile::from_path(path).and_then(Config::from_config_file);
+            let result = load(&config_path).and_then(|conf| {
+                let previous = match from_config_path {
+                    Some(ref path) => load(path)?.attachment_store,
+                    None => AttachmentStoreConfig::Sqlite,
+                };
+                let mut atlas_db = AtlasDB::connect(
+                    conf.make_atlas_config(),
+                    &conf.get_atlas_db_file_path(),
+                    true,
+                )
+                .map_err(|e| format!("Failed to open the Atlas DB: {:?}", e))?;
+                atlas_db
+                    .migrate_attachment_contents(&previous)
+                    .map_err(|e| format!("Failed to migrate attachments: {:?}", e))
+            });
+            match result {
+                Ok(moved) => {
+                    println!("{}", serde_json::json!({ "migrated": moved }));
+                    return;
+                }
+                Err(e) => {
+                    warn!("migrate-attachments failed: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        "sortition-info" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let burn_height: u64 = args.value_from_str("--burn-height").unwrap();
+            args.finish().unwrap();
+            info!("Loading config at path {}", config_path);
+            let conf = match ConfigFile::from_path(&config_path).and_then(Config::from_config_file)
+            {
+                Ok(conf) => conf,
+                Err(e) => {
+                    warn!("Invalid config: {}", e);
+                    process::exit(1);
+                }
+            };
+            let info = SortitionDB::open(&conf.get_burn_db_file_path(), false)
+                .map_err(|e| format!("Failed to open sortition DB: {:?}", e))
+                .and_then(|sortdb| sortition_info::sortition_info(&sortdb, burn_height));
+            match info {
+                Ok(info) => {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&info)
+                            .expect("Failed to serialize sortition info")
+                    );
+                    return;
+                }
+                Err(e) => {
+                    warn!("Failed to read sortition info: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        "commit-history" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let from_height: u64 = args
+                .opt_value_from_str("--from-height")
+                .unwrap()
+                .unwrap_or(0);
+            let limit: Option<u64> = args.opt_value_from_str("--limit").unwrap();
+            args.finish().unwrap();
+            info!("Loading config at path {}", config_path);
+            let conf = match ConfigFile::from_path(&config_path).and_then(Config::from_config_file)
+            {
+                Ok(conf) => conf,
+                Err(e) => {
+                    warn!("Invalid config: {}", e);
+                    process::exit(1);
+                }
+            };
+            let history = CommitJournal::open_readonly(conf.get_commit_journal_path())
+                .and_then(|journal| journal.get_history(from_height, limit));
+            match history {
+                Ok(history) => {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&history)
+                            .expect("Failed to serialize commit history")
+                    );
+                    return;
+                }
+                Err(e) => {
+                    warn!("Failed to read commit history: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        "export" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let format: String = args
+                .opt_value_from_str("--format")
+                .unwrap()
+                .unwrap_or("csv".to_string());
+            let dest: String = args.value_from_str("--dest").unwrap();
+            let from_height: u64 = args
+                .opt_value_from_str("--from-height")
+                .unwrap()
+                .unwrap_or(0);
+            let to_height: u64 = args
+                .opt_value_from_str("--to-height")
+                .unwrap()
+                .unwrap_or(u64::MAX);
+            args.finish().unwrap();
+            info!("Loading config at path {}", config_path);
+            let conf = match ConfigFile::from_path(&config_path).and_then(Config::from_config_file)
+            {
+                Ok(conf) => conf,
+                Err(e) => {
+                    warn!("Invalid config: {}", e);
+                    process::exit(1);
+                }
+            };
+            let summary = format.parse().and_then(|format| {
+                export::export_chainstate(
+                    &conf,
+                    std::path::Path::new(&dest),
+                    format,
+                    from_height,
+                    to_height,
+                )
+            });
+            match summary {
+                Ok(summary) => {
+                    info!(
+                        "Exported {} blocks, {} transactions, {} events and {} balances to {}",
+                        summary.blocks,
+                        summary.transactions,
+                        summary.events,
+                        summary.balances,
+                        &dest
+                    );
+                    return;
+                }
+                Err(e) => {
+                    warn!("Failed to export chainstate: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        "replay-events" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            let endpoint: String = args.value_from_str("--endpoint").unwrap();
+            let from_height: u64 = args
+                .opt_value_from_str("--from-height")
+                .unwrap()
+                .unwrap_or(0);
+            let to_height: u64 = args
+                .opt_value_from_str("--to-height")
+                .unwrap()
+                .unwrap_or(u64::MAX);
+            args.finish().unwrap();
+            info!("Loading config at path {}", config_path);
+            let conf = match ConfigFile::from_path(&config_path).and_then(Config::from_config_file)
+            {
+                Ok(conf) => conf,
+                Err(e) => {
+                    warn!("Invalid config: {}", e);
+                    process::exit(1);
+                }
+            };
+            match replay::replay_events(&conf, &endpoint, from_height, to_height) {
+                Ok(replayed) => {
+                    info!(
+                        "Replayed the events of {} blocks to {}",
+                        replayed, &endpoint
+                    );
+                    return;
+                }
+                Err(e) => {
+                    warn!("Failed to replay events: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
         "version" => {
             println!("{}", &version());
             return;
         }
+        "verify-proof" => {
+            let key: String = args.value_from_str("--key").unwrap();
+            let value: String = args.value_from_str("--value").unwrap();
+            let proof: String = args.value_from_str("--proof").unwrap();
+            let root_hash: String = args.value_from_str("--root-hash").unwrap();
+            let root_to_block: Vec<String> = args.values_from_str("--root-to-block").unwrap();
+            args.finish().unwrap();
+            match verify_proof_args(&key, &value, &proof, &root_hash, &root_to_block) {
+                Ok(true) => {
+                    println!("Proof is valid");
+                    return;
+                }
+                Ok(false) => {
+                    println!("Proof is invalid");
+                    process::exit(1);
+                }
+                Err(e) => {
+                    warn!("Failed to verify proof: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
         "key-for-seed" => {
-            let seed = {
+            let vanity: Option<String> = args.opt_value_from_str("--vanity").unwrap();
+            let seed = if let Some(vanity) = vanity {
+                let mainnet = !args.contains("--testnet");
+                let max_attempts: u64 = args
+                    .opt_value_from_str("--max-attempts")
+                    .unwrap()
+                    .unwrap_or(10_000_000);
+                args.finish().unwrap();
+                match keygen::grind_vanity_seed(&vanity, mainnet, max_attempts) {
+                    Ok(seed) => {
+                        let keychain = Keychain::default(seed.clone());
+                        println!("Seed: {}", keygen::seed_to_hex(&seed));
+                        println!("Address: {}", keychain.get_address(mainnet));
+                        seed
+                    }
+                    Err(e) => {
+                        warn!("{}", e);
+                        process::exit(1);
+                    }
+                }
+            } else {
                 let config_path: Option<String> = args.opt_value_from_str("--config").unwrap();
                 if let Some(config_path) = config_path {
                     let conf =
@@ -175,6 +740,145 @@ fn main() {
             );
             return;
         }
+        "rotate-key" => {
+            let config_path: String = args.value_from_str("--config").unwrap();
+            args.finish().unwrap();
+            let conf = match ConfigFile::from_path(&config_path).and_then(Config::from_config_file)
+            {
+                Ok(conf) => conf,
+                Err(e) => {
+                    warn!("Invalid config: {}", e);
+                    process::exit(1);
+                }
+            };
+            match admin::admin_request(
+                &conf,
+                http_types::Method::Post,
+                "/v2/admin/miner/rotate-key",
+            ) {
+                Ok(status) => {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&status)
+                            .expect("Failed to serialize rotate-key output")
+                    );
+                    return;
+                }
+                Err(e) => {
+                    warn!("Failed to rotate the VRF key: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        "encrypt-seed" => {
+            let kdf_rounds: u32 = args
+                .opt_value_from_str("--kdf-rounds")
+                .unwrap()
+                .unwrap_or(seed_encryption::DEFAULT_KDF_ROUNDS);
+            let free_args = args.free().unwrap();
+            // keep the seed off the command line if it is piped in
+            let seed_hex = match free_args.first() {
+                Some(seed_hex) => seed_hex.clone(),
+                None => {
+                    let mut line = String::new();
+                    if let Err(e) = std::io::stdin().read_line(&mut line) {
+                        warn!("Failed to read the seed from stdin: {}", e);
+                        process::exit(1);
+                    }
+                    line.trim().to_string()
+                }
+            };
+            let seed = match hex_bytes(&seed_hex) {
+                Ok(seed) => seed,
+                Err(_) => {
+                    warn!("Seed should be a hex encoded string");
+                    process::exit(1);
+                }
+            };
+            if kdf_rounds == 0 {
+                warn!("--kdf-rounds must be positive");
+                process::exit(1);
+            }
+            match seed_encryption::new_seed_passphrase() {
+                Ok(passphrase) => {
+                    println!(
+                        "{}",
+                        seed_encryption::encrypt_seed(&seed, &passphrase, kdf_rounds)
+                    );
+                }
+                Err(e) => {
+                    warn!("{}", e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        "generate-mnemonic" => {
+            let num_words: usize = args.opt_value_from_str("--words").unwrap().unwrap_or(24);
+            let mainnet = !args.contains("--testnet");
+            let vanity: Option<String> = args.opt_value_from_str("--vanity").unwrap();
+            let max_attempts: u64 = args
+                .opt_value_from_str("--max-attempts")
+                .unwrap()
+                .unwrap_or(1_000_000);
+            args.finish().unwrap();
+            let result = match vanity {
+                Some(vanity) => {
+                    keygen::grind_vanity_mnemonic(&vanity, num_words, mainnet, max_attempts)
+                }
+                None => keygen::generate_mnemonic(num_words).and_then(|mnemonic| {
+                    let seed = keygen::mnemonic_to_seed(&mnemonic, "")?;
+                    Ok((mnemonic, keygen::derive_keys(&seed, 0, mainnet)?))
+                }),
+            };
+            match result {
+                Ok((mnemonic, keys)) => {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&json!({
+                            "mnemonic": mnemonic,
+                            "keys": keys,
+                        }))
+                        .expect("Failed to serialize generate-mnemonic output")
+                    );
+                }
+                Err(e) => {
+                    warn!("{}", e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        "derive-keys" => {
+            let mnemonic: String = args.value_from_str("--mnemonic").unwrap();
+            let passphrase: String = args
+                .opt_value_from_str("--passphrase")
+                .unwrap()
+                .unwrap_or_default();
+            let index: u32 = args.opt_value_from_str("--index").unwrap().unwrap_or(0);
+            let count: u32 = args.opt_value_from_str("--count").unwrap().unwrap_or(1);
+            let mainnet = !args.contains("--testnet");
+            args.finish().unwrap();
+            let result = keygen::mnemonic_to_seed(&mnemonic, &passphrase).and_then(|seed| {
+                (index..index.saturating_add(count))
+                    .map(|i| keygen::derive_keys(&seed, i, mainnet))
+                    .collect::<Result<Vec<_>, _>>()
+            });
+            match result {
+                Ok(keys) => {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&keys)
+                            .expect("Failed to serialize derive-keys output")
+                    );
+                }
+                Err(e) => {
+                    warn!("{}", e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
         _ => {
             print_help();
             return;
@@ -191,11 +895,31 @@ fn main() {
     debug!("node configuration {:?}", &conf.node);
     debug!("burnchain configuration {:?}", &conf.burnchain);
     debug!("connection configuration {:?}", &conf.connection_options);
+    debug!("sqlite configuration {:?}", &conf.sqlite);
+    conf.apply_sqlite_opts();
 
-    let num_round: u64 = 0; // Infinite number of rounds
+    let mut num_round: u64 = 0; // Infinite number of rounds
 
     if conf.burnchain.mode == "helium" || conf.burnchain.mode == "mocknet" {
+        if conf.node.clarity_coverage {
+            info!("Recording Clarity code coverage");
+            CoverageReporter::enable_global(
+                conf.node.clarity_coverage_dir.as_ref().map(PathBuf::from),
+            );
+        }
         let mut run_loop = helium::RunLoop::new(conf);
+        if let Some(scenario_path) = scenario_path {
+            info!("Loading scenario at path {}", scenario_path);
+            let scenario = match Scenario::from_path(&scenario_path) {
+                Ok(scenario) => scenario,
+                Err(e) => {
+                    warn!("Invalid scenario file: {}", e);
+                    process::exit(1);
+                }
+            };
+            num_round = scenario.rounds;
+            run_loop.set_scenario(scenario);
+        }
         if let Err(e) = run_loop.start(num_round) {
             warn!("Helium runloop exited: {}", e);
             return;
@@ -205,13 +929,85 @@ fn main() {
         || conf.burnchain.mode == "krypton"
         || conf.burnchain.mode == "mainnet"
     {
+        if conf.node.replica.is_some() {
+            let mut run_loop = run_loop::replica::RunLoop::new(conf);
+            run_loop.start();
+            return;
+        }
         let mut run_loop = neon::RunLoop::new(conf);
+        if windows_service {
+            #[cfg(windows)]
+            {
+                let should_keep_running = run_loop.get_termination_switch();
+                supervisor::run_windows_service(should_keep_running, move || {
+                    run_loop.start(None, mine_start.unwrap_or(0))
+                });
+                return;
+            }
+            #[cfg(not(windows))]
+            {
+                warn!("--windows-service is only supported on Windows");
+                process::exit(1);
+            }
+        }
         run_loop.start(None, mine_start.unwrap_or(0));
     } else {
         println!("Burnchain mode '{}' not supported", conf.burnchain.mode);
     }
 }
 
+/// Reads the optional `--profile` argument, exiting if it names no built-in profile.
+fn parse_profile_arg(args: &mut Arguments) -> Option<ConfigProfile> {
+    let profile: Option<String> = args
+        .opt_value_from_str("--profile")
+        .expect("Failed to parse --profile argument");
+    profile.map(|profile| match ConfigProfile::parse(&profile) {
+        Ok(profile) => profile,
+        Err(e) => {
+            warn!("Invalid config profile: {}", e);
+            process::exit(1);
+        }
+    })
+}
+
+/// Verify a MARF proof given on the command line.  Hex strings may have a `0x` prefix, as they do
+/// in RPC responses.  Each of `root_to_block` maps an ancestor trie's root hash to its block, as
+/// `<root hash>:<index block hash>`.
+fn verify_proof_args(
+    key: &str,
+    value: &str,
+    proof: &str,
+    root_hash: &str,
+    root_to_block: &[String],
+) -> Result<bool, String> {
+    let strip_hex_prefix = |s: &str| s.strip_prefix("0x").unwrap_or(s).to_string();
+    let proof = hex_bytes(&strip_hex_prefix(proof)).map_err(|_| "Proof is not hex".to_string())?;
+    let root_hash = TrieHash::from_hex(&strip_hex_prefix(root_hash))
+        .map_err(|_| "Root hash is not a 32-byte hex string".to_string())?;
+    let root_to_block = root_to_block
+        .iter()
+        .map(|entry| {
+            let (root, block) = entry
+                .split_once(':')
+                .ok_or_else(|| format!("Expected <root hash>:<block>, got {}", entry))?;
+            let root = TrieHash::from_hex(&strip_hex_prefix(root))
+                .map_err(|_| format!("Bad root hash {}", root))?;
+            let block = StacksBlockId::from_hex(&strip_hex_prefix(block))
+                .map_err(|_| format!("Bad index block hash {}", block))?;
+            Ok((root, block))
+        })
+        .collect::<Result<HashMap<_, _>, String>>()?;
+
+    verify_proof(
+        key,
+        &strip_hex_prefix(value),
+        &proof,
+        &root_hash,
+        &root_to_block,
+    )
+    .map_err(|e| e.to_string())
+}
+
 fn version() -> String {
     stacks::version_string(
         "stacks-node",
@@ -252,16 +1048,211 @@ testnet\t\tStart a node that will join and stream blocks from the public testnet
 start\t\tStart a node with a config of your own. Can be used for joining a network, starting new chain, etc.
 \t\tArguments:
 \t\t  --config: path of the config (such as https://github.com/blockstack/stacks-blockchain/blob/master/testnet/stacks-node/conf/testnet-follower-conf.toml).
+\t\t  --profile: optional built-in settings for a kind of deployment, which the config overrides:
+\t\t    `follower`, `api-node`, `miner`, or `archival`. See testnet/stacks-node/conf/profiles.
+\t\t  --windows-service: run under the Windows service control manager, which can then start and
+\t\t    stop the node. Set this in the service's command line.
 \t\tExample:
-\t\t  stacks-node start --config=/path/to/config.toml
+\t\t  stacks-node start --profile=miner --config=/path/to/config.toml
+\t\tWhen run by systemd as a `Type=notify` service, the node reports when it is ready and pings
+\t\tthe watchdog if the unit sets `WatchdogSec=`. Set `node.supervisor_notify = false` to turn this off.
 
 check-config\t\tValidates the config file without starting up the node. Uses same arguments as start subcommand.
+\t\tWarns about unknown (e.g. misspelled) and deprecated keys, settings that do not work together,
+\t\tand event observers that cannot be reached.
+\t\tArguments:
+\t\t  --strict: exit with an error if there are any warnings.
+
+export-snapshot\tCopy the fee/cost estimator databases (and, with --include-mempool, the mempool) of a node
+\t\tinto a snapshot directory, for seeding a replacement node.
+\t\tArguments:
+\t\t  --config: path of the node's config.
+\t\t  --dest: directory to write the snapshot to. Must be empty or not exist.
+\t\t  --include-mempool: also export the mempool database.
+\t\tExample:
+\t\t  stacks-node export-snapshot --config=/path/to/config.toml --dest=/path/to/snapshot
+
+import-snapshot\tInstall a snapshot written by export-snapshot into a stopped node. The snapshot must come from
+\t\ta node on the same network with the same estimators configured.
+\t\tArguments:
+\t\t  --config: path of the node's config.
+\t\t  --src: snapshot directory.
+\t\t  --overwrite: replace existing estimator and mempool databases.
+
+preflight-upgrade\tDry-run the database migrations that a new stacks-node binary would apply to this node's
+\t\tdatabases, and report the expected migration time, the disk space required, and anything
+\t\tthat would keep the new binary from starting. The node's databases are not modified, and
+\t\tthe node may keep running.
+\t\tArguments:
+\t\t  --config: path of the node's config.
+\t\t  --new-binary: path of the new stacks-node binary.
+\t\tExample:
+\t\t  stacks-node preflight-upgrade --config=/path/to/config.toml --new-binary=/path/to/stacks-node
+
+migration-dry-run\tThe part of preflight-upgrade that runs in the new binary: prints a JSON report of the
+\t\tmigrations this binary would apply to the databases of the node configured with --config.
+
+verify-proof\tCheck a MARF Merkle proof, such as the `proof` of a /v2/data_var or /v2/map_entry response,
+\t\tagainst the root hash of the block it was made at. Exits with an error if the proof is invalid.
+\t\tArguments:
+\t\t  --key: the MARF key, e.g. vm::<contract identifier>::1::<var name> for a data var.
+\t\t  --value: the value stored under the key, e.g. the `data` of a /v2/data_var response.
+\t\t  --proof: the hex-encoded proof.
+\t\t  --root-hash: the block's MARF root hash (the `state_index_root` of its header).
+\t\t  --root-to-block: <root hash>:<index block hash> of an ancestor block whose trie the proof
+\t\t    runs through. Values written before the block need these; repeat as needed.
 
-version\t\tDisplay information about the current version and our release cycle.
+mempool-stats\tPrint a JSON summary of the node's mempool: its size, fee rate and age histograms, the
+\t\tnumber of transactions from each origin address, and the transactions paying the highest fee
+\t\trates. The mempool is opened read-only, so the node may keep running.
+\t\tArguments:
+\t\t  --config: path of the node's config.
+\t\t  --top: how many transactions to list by fee rate. Defaults to 20.
+\t\tExample:
+\t\t  stacks-node mempool-stats --config=/path/to/config.toml --top=50
+
+btc-wallet\tManage the miner's bitcoin UTXOs through the configured bitcoind's RPC interface, using
+\t\tthe keychain derived from node.seed. Prints JSON. Outside of regtest, the miner's address must
+\t\talready be imported into bitcoind's wallet.
+\t\tSubcommands:
+\t\t  balance: the miner's address, and its confirmed and unconfirmed balance in satoshis.
+\t\t  list-utxos: every UTXO of the miner's address.
+\t\t  consolidate: spend all confirmed UTXOs into a single one back to the miner's address.
+\t\t  send-to-address: pay --amount satoshis to the legacy bitcoin address --address.
+\t\tArguments:
+\t\t  --config: path of the node's config.
+\t\t  --fee-rate: fee rate in satoshis per byte. Defaults to burnchain.satoshis_per_byte.
+\t\t  --dry-run: print the signed transaction without broadcasting it.
+\t\tExample:
+\t\t  stacks-node btc-wallet send-to-address --config=/path/to/config.toml --address=mxVFsFW5N4mu1HPkxPttorvocvzeZ7KZyk --amount=100000
+
+stack-stx\tBuild and sign a PoX contract call for a stacker, and print it as JSON. The PoX contract
+\t\tis the one in force, per the node's Stacks epochs, at the stacking start height.
+\t\tArguments:
+\t\t  --config: path of the node's config.
+\t\t  --call: stack-stx (the default), delegate-stx, or stack-extend where the PoX contract
+\t\t    defines it.
+\t\t  --amount: amount to stack or delegate, in micro-STX.
+\t\t  --cycles: number of reward cycles to lock for. Needed by stack-stx and stack-extend.
+\t\t  --reward-address: legacy bitcoin address to receive the rewards. Optional for delegate-stx.
+\t\t  --delegate-to: principal to delegate to. Needed by delegate-stx.
+\t\t  --until-burn-height: burnchain height at which a delegation expires. Optional.
+\t\t  --private-key: hex private key of the stacker. Otherwise, the key derives from --seed,
+\t\t    or from node.seed without it.
+\t\t  --fee: transaction fee, in micro-STX.
+\t\t  --nonce: stacker's nonce. Defaults to the next one, as reported by the node.
+\t\t  --start-burn-height: stacking start height. Defaults to the node's burnchain height.
+\t\t  --node: RPC address of the node to query and broadcast to. Defaults to node.rpc_bind.
+\t\t  --broadcast: post the transaction to the node.
+\t\tExample:
+\t\t  stacks-node stack-stx --config=/path/to/config.toml --amount=100000000000 --cycles=6 --reward-address=1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2 --fee=10000 --broadcast
+
+migrate-attachments\tMove the Atlas attachment contents into the attachment store of a config, after
+\t\tits `[atlas]` section was changed. Run it while the node is stopped. The previous store is
+\t\tleft as it was, so the command can be re-run if it fails.
+\t\tArguments:
+\t\t  --config: path of the node's config, with the new attachment store.
+\t\t  --from-config: path of a config with the previous attachment store. Defaults to the
+\t\t    Atlas DB itself, i.e. the sqlite attachment store.
+\t\tExample:
+\t\t  stacks-node migrate-attachments --config=/path/to/config.toml
+
+sortition-info\tPrint, as JSON, the sortition at a burnchain height on the canonical burnchain fork: its
+\t\twinner, every block-commit in it (burn amounts, leader keys, and parent pointers), and the
+\t\tblock-commits that were missed because they did not spend the right input.
+\t\tArguments:
+\t\t  --config: path of the node's config.
+\t\t  --burn-height: burnchain block height of the sortition.
+\t\tExample:
+\t\t  stacks-node sortition-info --config=/path/to/config.toml --burn-height=740000
+
+commit-history\tPrint, as JSON, the block-commit and leader key transactions this miner has broadcast,
+\t\toldest first: txid, spent outputs, fees, target burnchain height, and the transactions each one
+\t\treplaced by fee or was replaced by. Can be run while the node is running.
+\t\tArguments:
+\t\t  --config: path of the miner's config.
+\t\t  --from-height: only transactions sent for this burnchain height or later. Defaults to 0.
+\t\t  --limit: only the last this many transactions.
+\t\tExample:
+\t\t  stacks-node commit-history --config=/path/to/config.toml --limit=20
+
+export		Write the canonical Stacks chain to flat files for analytics: blocks, transactions and
+		events files for a range of block heights, and a balances file with the balances, as of the
+		last exported block, of every account touched in the range. Transaction results and events
+		are only available for blocks processed with STACKS_TRANSACTION_LOG=1.
+		Arguments:
+		  --config: path of the node's config.
+		  --dest: directory to write the files to.
+		  --format: `csv` (the default) or `parquet` (Snappy-compressed, with integer columns typed
+		    as INT64 and empty fields as nulls).
+		  --from-height: first Stacks block height to export. Defaults to 0.
+		  --to-height: last Stacks block height to export. Defaults to the chain tip.
+		Example:
+		  stacks-node export --config=/path/to/config.toml --dest=/tmp/export --from-height=1000
+
+replay-events	Re-execute the canonical Stacks blocks in a range of heights from the node's chainstate
+		and deliver their /new_block events (block, transaction receipts and events) to an event
+		observer, so that an indexer that lost data can rebuild it without re-syncing a node. The node
+		should be stopped while events are replayed.
+		Arguments:
+		  --config: path of the node's config.
+		  --endpoint: the observer to deliver events to, as in an [[events_observer]] section.
+		  --from-height: first Stacks block height to replay. Defaults to 1.
+		  --to-height: last Stacks block height to replay. Defaults to the chain tip.
+		Example:
+		  stacks-node replay-events --config=/path/to/config.toml --endpoint=localhost:3700 --from-height=1000
+
+version		Display information about the current version and our release cycle.
 
 key-for-seed\tOutput the associated secret key for a burnchain signer created with a given seed.
 \t\tCan be passed a config file for the seed via the `--config=<file>` option *or* by supplying the hex seed on
 \t\tthe command line directly.
+\t\tWith `--vanity=<prefix>`, instead generates random seeds until the node's Stacks address starts with
+\t\t<prefix>, and prints the seed to put in `node.seed`.
+\t\tArguments:
+\t\t  --vanity: address prefix to grind for, e.g. SP00. Each extra character takes about 32 times longer.
+\t\t  --testnet: grind a testnet (ST...) address.
+\t\t  --max-attempts: seeds to try before giving up. Defaults to 10000000.
+\t\tExample:
+\t\t  stacks-node key-for-seed --vanity=SP00
+
+rotate-key\tAsk a running miner to register a new VRF key. It keeps mining with its current key until the new
+\t\tkey's registration is mined, then switches to it, so no tenure is missed. Uses the admin API, so the
+\t\tconfig must set node.admin_bind and node.admin_auth_token.
+\t\tArguments:
+\t\t  --config: path of the running node's config.
+\t\tExample:
+\t\t  stacks-node rotate-key --config=/path/to/config.toml
+
+encrypt-seed\tEncrypt a hex seed for `node.seed`, `node.local_peer_seed` or `node.microblock_seed`, so it need not be
+\t\tstored as plaintext.
+\t\tThe seed is passed on the command line or, to keep it out of the process list, on stdin. The passphrase
+\t\tis taken from STACKS_NODE_SEED_PASSPHRASE, from the file named by STACKS_NODE_SEED_PASSPHRASE_FILE,
+\t\tor prompted for; the node reads it the same way at startup.
+\t\tArguments:
+\t\t  --kdf-rounds: PBKDF2 rounds used to stretch the passphrase. Defaults to 200000.
+\t\tExample:
+\t\t  echo <hex seed> | stacks-node encrypt-seed
+
+generate-mnemonic\tGenerate a new BIP39 mnemonic, and print it with the keys of its first account. Runs offline.
+\t\tArguments:
+\t\t  --words: mnemonic length, one of 12, 15, 18, 21 or 24. Defaults to 24.
+\t\t  --testnet: derive testnet addresses.
+\t\t  --vanity: generate mnemonics until the first Stacks address starts with this prefix.
+\t\t  --max-attempts: mnemonics to try before giving up on --vanity. Defaults to 1000000.
+\t\tExample:
+\t\t  stacks-node generate-mnemonic --words=12
+
+derive-keys\tDerive Stacks (m/44'/5757'/0'/0/<index>) and Bitcoin p2pkh (m/44'/0'/0'/0/<index>) keys from a
+\t\tBIP39 mnemonic. Runs offline.
+\t\tArguments:
+\t\t  --mnemonic: the mnemonic, quoted.
+\t\t  --passphrase: optional BIP39 passphrase.
+\t\t  --index: first account index to derive. Defaults to 0.
+\t\t  --count: number of accounts to derive. Defaults to 1.
+\t\t  --testnet: derive testnet addresses, with Bitcoin keys at m/44'/1'/0'/0/<index>.
+\t\tExample:
+\t\t  stacks-node derive-keys --mnemonic=\"<24 words>\" --index=0 --count=5
 
 help\t\tDisplay this help.
 
@@ -269,6 +1260,9 @@ OPTIONAL ARGUMENTS:
 
 \t\t--mine-at-height=<height>: optional argument for a miner to not attempt mining until Stacks block has sync'ed to <height>
 
+\t\t--scenario=<path>: optional argument for helium and mocknet nodes to run the per-round actions
+\t\t  (transactions to submit, sortitions to skip, forks to create) described in a scenario file
+
 ", argv[0]);
 }
 
@@ -0,0 +1,252 @@
+//! Offline summary of a node's mempool, for operators working out why a transaction has not
+//! been mined yet. The mempool database is opened read-only, so the node may keep running.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::Path;
+
+use rusqlite::{Connection, OpenFlags, NO_PARAMS};
+
+/// Upper bounds (exclusive) of the fee rate histogram buckets, in microSTX per byte.  The last
+/// bucket holds every fee rate at or above the last bound.
+const FEE_RATE_BUCKETS: &[u64] = &[1, 2, 5, 10, 20, 50, 100, 200, 500, 1_000, 10_000];
+
+/// Upper bounds (exclusive) of the age histogram buckets, in seconds.  The last bucket holds
+/// every transaction at least as old as the last bound.
+const AGE_BUCKETS: &[u64] = &[60, 300, 1_800, 3_600, 21_600, 86_400];
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct HistogramBucket {
+    /// Inclusive lower bound of the bucket
+    pub min: u64,
+    /// Exclusive upper bound of the bucket, or None for the last bucket
+    pub max: Option<u64>,
+    pub count: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct OriginCount {
+    pub origin_address: String,
+    pub count: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MempoolTxSummary {
+    pub txid: String,
+    pub origin_address: String,
+    pub origin_nonce: u64,
+    pub sponsor_address: String,
+    pub sponsor_nonce: u64,
+    pub tx_fee: u64,
+    pub length: u64,
+    /// Fee paid per byte of transaction
+    pub fee_rate: f64,
+    /// Fee rate computed by the node's cost estimator, if it has one for this transaction
+    pub estimated_fee_rate: Option<f64>,
+    pub accept_time: u64,
+    pub age_secs: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MempoolStats {
+    pub num_txs: u64,
+    pub total_bytes: u64,
+    pub total_fees: u64,
+    /// Transactions by fee paid per byte, in microSTX
+    pub fee_rate_histogram: Vec<HistogramBucket>,
+    /// Transactions by time since they were accepted into the mempool, in seconds
+    pub age_histogram: Vec<HistogramBucket>,
+    /// Number of transactions from each origin address, most first
+    pub origins: Vec<OriginCount>,
+    /// The transactions paying the highest fee rates, highest first
+    pub top_by_fee_rate: Vec<MempoolTxSummary>,
+}
+
+fn make_histogram(bounds: &[u64], values: impl Iterator<Item = u64>) -> Vec<HistogramBucket> {
+    let mut buckets: Vec<_> = std::iter::once(0)
+        .chain(bounds.iter().cloned())
+        .zip(
+            bounds
+                .iter()
+                .map(|bound| Some(*bound))
+                .chain(std::iter::once(None)),
+        )
+        .map(|(min, max)| HistogramBucket { min, max, count: 0 })
+        .collect();
+    for value in values {
+        let index = bounds
+            .iter()
+            .position(|bound| value < *bound)
+            .unwrap_or(bounds.len());
+        buckets[index].count += 1;
+    }
+    buckets
+}
+
+/// Summarizes the mempool database at `path`.  Ages are measured from `now`, in seconds since
+/// the epoch.  Up to `top_n` transactions are listed by fee rate.
+pub fn mempool_stats(path: &Path, top_n: usize, now: u64) -> Result<MempoolStats, String> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+
+    let has_fee_estimates: bool = conn
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'fee_estimates'",
+            NO_PARAMS,
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to read mempool schema: {}", e))?;
+    let sql = if has_fee_estimates {
+        "SELECT mempool.txid, origin_address, origin_nonce, sponsor_address, sponsor_nonce, tx_fee, length, accept_time, fee_estimates.fee_rate \
+         FROM mempool LEFT JOIN fee_estimates ON mempool.txid = fee_estimates.txid"
+    } else {
+        "SELECT txid, origin_address, origin_nonce, sponsor_address, sponsor_nonce, tx_fee, length, accept_time, NULL FROM mempool"
+    };
+
+    let mut stmt = conn
+        .prepare(sql)
+        .map_err(|e| format!("Failed to query mempool: {}", e))?;
+    let mut txs = stmt
+        .query_map(NO_PARAMS, |row| {
+            let tx_fee: i64 = row.get(5)?;
+            let length: i64 = row.get(6)?;
+            let accept_time: i64 = row.get(7)?;
+            let origin_nonce: i64 = row.get(2)?;
+            let sponsor_nonce: i64 = row.get(4)?;
+            Ok(MempoolTxSummary {
+                txid: row.get(0)?,
+                origin_address: row.get(1)?,
+                origin_nonce: origin_nonce as u64,
+                sponsor_address: row.get(3)?,
+                sponsor_nonce: sponsor_nonce as u64,
+                tx_fee: tx_fee as u64,
+                length: length as u64,
+                fee_rate: tx_fee as f64 / (length.max(1) as f64),
+                estimated_fee_rate: row.get(8)?,
+                accept_time: accept_time as u64,
+                age_secs: now.saturating_sub(accept_time as u64),
+            })
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to query mempool: {}", e))?;
+
+    let mut origin_counts: HashMap<&str, u64> = HashMap::new();
+    for tx in txs.iter() {
+        *origin_counts.entry(tx.origin_address.as_str()).or_insert(0) += 1;
+    }
+    let mut origins: Vec<_> = origin_counts
+        .into_iter()
+        .map(|(origin_address, count)| OriginCount {
+            origin_address: origin_address.to_string(),
+            count,
+        })
+        .collect();
+    origins.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.origin_address.cmp(&b.origin_address))
+    });
+
+    let fee_rate_histogram = make_histogram(
+        FEE_RATE_BUCKETS,
+        txs.iter().map(|tx| tx.fee_rate.floor() as u64),
+    );
+    let age_histogram = make_histogram(AGE_BUCKETS, txs.iter().map(|tx| tx.age_secs));
+
+    let num_txs = txs.len() as u64;
+    let total_bytes = txs.iter().map(|tx| tx.length).sum();
+    let total_fees = txs.iter().map(|tx| tx.tx_fee).sum();
+
+    txs.sort_by(|a, b| {
+        b.fee_rate
+            .partial_cmp(&a.fee_rate)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| a.txid.cmp(&b.txid))
+    });
+    txs.truncate(top_n);
+
+    Ok(MempoolStats {
+        num_txs,
+        total_bytes,
+        total_fees,
+        fee_rate_histogram,
+        age_histogram,
+        origins,
+        top_by_fee_rate: txs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    #[test]
+    fn test_mempool_stats() {
+        let dir = Path::new("/tmp/stacks-node-tests/mempool_stats");
+        if dir.exists() {
+            fs::remove_dir_all(dir).unwrap();
+        }
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join("mempool.sqlite");
+
+        // only the columns the summary reads are needed
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE mempool(txid TEXT NOT NULL, origin_address TEXT NOT NULL, origin_nonce INTEGER NOT NULL, \
+                 sponsor_address TEXT NOT NULL, sponsor_nonce INTEGER NOT NULL, tx_fee INTEGER NOT NULL, \
+                 length INTEGER NOT NULL, accept_time INTEGER NOT NULL); \
+             CREATE TABLE fee_estimates(txid TEXT NOT NULL, fee_rate NUMBER); \
+             INSERT INTO mempool VALUES ('aa', 'SP1', 0, 'SP1', 0, 1000, 100, 990); \
+             INSERT INTO mempool VALUES ('bb', 'SP1', 1, 'SP1', 1, 300, 100, 940); \
+             INSERT INTO mempool VALUES ('cc', 'SP2', 5, 'SP3', 2, 50000, 100, 0); \
+             INSERT INTO fee_estimates VALUES ('cc', 2.5);",
+        )
+        .unwrap();
+
+        let stats = mempool_stats(&path, 2, 1000).unwrap();
+        assert_eq!(stats.num_txs, 3);
+        assert_eq!(stats.total_bytes, 300);
+        assert_eq!(stats.total_fees, 51300);
+
+        assert_eq!(stats.fee_rate_histogram.len(), FEE_RATE_BUCKETS.len() + 1);
+        let count_in = |buckets: &[HistogramBucket], value: u64| {
+            buckets
+                .iter()
+                .find(|bucket| {
+                    bucket.min <= value && bucket.max.map(|max| value < max).unwrap_or(true)
+                })
+                .unwrap()
+                .count
+        };
+        assert_eq!(count_in(&stats.fee_rate_histogram, 3), 1);
+        assert_eq!(count_in(&stats.fee_rate_histogram, 10), 1);
+        assert_eq!(count_in(&stats.fee_rate_histogram, 500), 1);
+        assert_eq!(count_in(&stats.age_histogram, 10), 1);
+        assert_eq!(count_in(&stats.age_histogram, 60), 1);
+        assert_eq!(count_in(&stats.age_histogram, 1000), 1);
+
+        assert_eq!(
+            stats.origins,
+            vec![
+                OriginCount {
+                    origin_address: "SP1".into(),
+                    count: 2
+                },
+                OriginCount {
+                    origin_address: "SP2".into(),
+                    count: 1
+                }
+            ]
+        );
+
+        assert_eq!(stats.top_by_fee_rate.len(), 2);
+        assert_eq!(stats.top_by_fee_rate[0].txid, "cc");
+        assert_eq!(stats.top_by_fee_rate[0].fee_rate, 500.0);
+        assert_eq!(stats.top_by_fee_rate[0].estimated_fee_rate, Some(2.5));
+        assert_eq!(stats.top_by_fee_rate[0].age_secs, 1000);
+        assert_eq!(stats.top_by_fee_rate[1].txid, "aa");
+        assert_eq!(stats.top_by_fee_rate[1].estimated_fee_rate, None);
+    }
+}
@@ -0,0 +1,256 @@
+//! Tracks how quickly microblocks propagate through this node's neighbors.
+//!
+//! A microblock counts as acknowledged once enough distinct neighbors have relayed it to us. The
+//! time that takes is a sample of how long the network needs to see a new microblock. When
+//! assembling an anchored block, the miner can then leave out parent microblocks that are younger
+//! than that: other nodes would have to fetch them before they could validate the block, which
+//! slows down its propagation and makes it more likely to be orphaned.
+
+use std::collections::{HashMap, HashSet};
+
+use stacks::chainstate::stacks::StacksMicroblock;
+use stacks::net::{NeighborKey, NetworkResult};
+use stacks::types::chainstate::BlockHeaderHash;
+
+/// Microblocks first seen longer ago than this are forgotten.
+const MAX_TRACKED_AGE_MS: u128 = 3_600_000;
+
+/// Weight of a new latency sample in the moving average, as in TCP's RTT estimator.
+const LATENCY_SAMPLE_WEIGHT: f64 = 0.125;
+
+struct SeenMicroblock {
+    first_seen_ms: u128,
+    relayers: HashSet<NeighborKey>,
+    acked: bool,
+}
+
+/// What the miner based its choice of parent microblocks on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MicroblockConfirmation {
+    /// Number of parent microblocks available to confirm.
+    pub num_available: usize,
+    /// Number of parent microblocks, from the start of the stream, to confirm.
+    pub num_confirmed: usize,
+    /// Smoothed acknowledgment latency, if any microblock has been acknowledged yet.
+    pub ack_latency_ms: Option<u64>,
+    pub num_ack_samples: u64,
+    /// How long ago a microblock must have been first seen to be confirmed.
+    pub required_age_ms: u64,
+}
+
+pub struct MicroblockPropagation {
+    seen: HashMap<BlockHeaderHash, SeenMicroblock>,
+    ack_latency_ms: Option<f64>,
+    num_ack_samples: u64,
+}
+
+impl MicroblockPropagation {
+    pub fn new() -> MicroblockPropagation {
+        MicroblockPropagation {
+            seen: HashMap::new(),
+            ack_latency_ms: None,
+            num_ack_samples: 0,
+        }
+    }
+
+    fn first_seen(
+        &mut self,
+        microblock_hash: BlockHeaderHash,
+        now_ms: u128,
+    ) -> &mut SeenMicroblock {
+        self.seen
+            .entry(microblock_hash)
+            .or_insert_with(|| SeenMicroblock {
+                first_seen_ms: now_ms,
+                relayers: HashSet::new(),
+                acked: false,
+            })
+    }
+
+    /// Record a microblock this node mined.
+    pub fn note_mined(&mut self, microblock_hash: BlockHeaderHash, now_ms: u128) {
+        self.first_seen(microblock_hash, now_ms);
+    }
+
+    /// Record the microblocks that neighbors pushed to us or that were uploaded over HTTP. A
+    /// microblock is acknowledged once `ack_neighbors` distinct neighbors have pushed it.
+    pub fn note_network_result(
+        &mut self,
+        network_result: &NetworkResult,
+        ack_neighbors: u64,
+        now_ms: u128,
+    ) {
+        for microblocks_data in network_result.uploaded_microblocks.iter() {
+            for microblock in microblocks_data.microblocks.iter() {
+                self.first_seen(microblock.block_hash(), now_ms);
+            }
+        }
+        for (neighbor_key, pushed) in network_result.pushed_microblocks.iter() {
+            for (_, microblocks_data) in pushed.iter() {
+                for microblock in microblocks_data.microblocks.iter() {
+                    let seen = self.first_seen(microblock.block_hash(), now_ms);
+                    seen.relayers.insert(neighbor_key.clone());
+                    if seen.acked || (seen.relayers.len() as u64) < ack_neighbors {
+                        continue;
+                    }
+                    seen.acked = true;
+                    let sample = now_ms.saturating_sub(seen.first_seen_ms) as f64;
+                    self.add_latency_sample(sample);
+                }
+            }
+        }
+        self.seen
+            .retain(|_, seen| seen.first_seen_ms + MAX_TRACKED_AGE_MS > now_ms);
+    }
+
+    fn add_latency_sample(&mut self, sample_ms: f64) {
+        self.ack_latency_ms = Some(match self.ack_latency_ms {
+            Some(latency) => {
+                (1.0 - LATENCY_SAMPLE_WEIGHT) * latency + LATENCY_SAMPLE_WEIGHT * sample_ms
+            }
+            None => sample_ms,
+        });
+        self.num_ack_samples += 1;
+    }
+
+    pub fn ack_latency_ms(&self) -> Option<u64> {
+        self.ack_latency_ms.map(|latency| latency.round() as u64)
+    }
+
+    /// Decide how much of the parent's microblock stream to confirm: the longest prefix whose
+    /// microblocks were all first seen at least the acknowledgment latency (capped at
+    /// `max_delay_ms`) ago. Microblocks this node has no record of, e.g. because they arrived
+    /// before it started, are old enough.
+    pub fn choose_confirmed_microblocks(
+        &self,
+        microblocks: &[StacksMicroblock],
+        max_delay_ms: u64,
+        now_ms: u128,
+    ) -> MicroblockConfirmation {
+        let ack_latency_ms = self.ack_latency_ms();
+        let required_age_ms = ack_latency_ms.unwrap_or(0).min(max_delay_ms);
+        let num_confirmed = microblocks
+            .iter()
+            .take_while(|microblock| match self.seen.get(&microblock.block_hash()) {
+                Some(seen) => seen.first_seen_ms + (required_age_ms as u128) <= now_ms,
+                None => true,
+            })
+            .count();
+        MicroblockConfirmation {
+            num_available: microblocks.len(),
+            num_confirmed,
+            ack_latency_ms,
+            num_ack_samples: self.num_ack_samples,
+            required_age_ms,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use stacks::chainstate::stacks::{StacksMicroblockHeader, TransactionVersion};
+    use stacks::net::{MicroblocksData, PeerAddress};
+    use stacks::types::chainstate::StacksBlockId;
+    use stacks::util::hash::Sha512Trunc256Sum;
+    use stacks::util::secp256k1::MessageSignature;
+
+    fn make_microblock(sequence: u16) -> StacksMicroblock {
+        StacksMicroblock {
+            header: StacksMicroblockHeader {
+                version: TransactionVersion::Testnet as u8,
+                sequence,
+                prev_block: BlockHeaderHash([sequence as u8; 32]),
+                tx_merkle_root: Sha512Trunc256Sum([0u8; 32]),
+                signature: MessageSignature::empty(),
+            },
+            txs: vec![],
+        }
+    }
+
+    fn make_neighbor(port: u16) -> NeighborKey {
+        NeighborKey {
+            peer_version: 0,
+            network_id: 0,
+            addrbytes: PeerAddress::from_ipv4(127, 0, 0, 1),
+            port,
+        }
+    }
+
+    fn push(network_result: &mut NetworkResult, neighbor: u16, microblocks: &[StacksMicroblock]) {
+        network_result
+            .pushed_microblocks
+            .entry(make_neighbor(neighbor))
+            .or_insert_with(Vec::new)
+            .push((
+                vec![],
+                MicroblocksData {
+                    index_anchor_block: StacksBlockId([0u8; 32]),
+                    microblocks: microblocks.to_vec(),
+                },
+            ));
+    }
+
+    #[test]
+    fn test_microblock_propagation() {
+        let microblocks: Vec<_> = (0..3).map(make_microblock).collect();
+        let mut propagation = MicroblockPropagation::new();
+
+        // nothing is known, so everything can be confirmed
+        let confirmation = propagation.choose_confirmed_microblocks(&microblocks, 10_000, 1_000);
+        assert_eq!(confirmation.num_confirmed, 3);
+        assert_eq!(confirmation.ack_latency_ms, None);
+
+        // the first microblock is relayed by one neighbor, then by a second one 400ms later
+        let mut network_result = NetworkResult::new(0, 0, 0);
+        push(&mut network_result, 1, &microblocks[0..1]);
+        propagation.note_network_result(&network_result, 2, 1_000);
+        assert_eq!(propagation.ack_latency_ms(), None);
+
+        let mut network_result = NetworkResult::new(0, 0, 0);
+        push(&mut network_result, 2, &microblocks[0..1]);
+        propagation.note_network_result(&network_result, 2, 1_400);
+        assert_eq!(propagation.ack_latency_ms(), Some(400));
+
+        // a repeated push is not a new sample
+        propagation.note_network_result(&network_result, 2, 1_500);
+        assert_eq!(propagation.num_ack_samples, 1);
+
+        // we mine the second microblock and receive the third right after
+        propagation.note_mined(microblocks[1].block_hash(), 2_000);
+        let mut network_result = NetworkResult::new(0, 0, 0);
+        push(&mut network_result, 1, &microblocks[2..3]);
+        propagation.note_network_result(&network_result, 2, 2_300);
+
+        // too early to confirm either of them
+        let confirmation = propagation.choose_confirmed_microblocks(&microblocks, 10_000, 2_300);
+        assert_eq!(
+            confirmation,
+            MicroblockConfirmation {
+                num_available: 3,
+                num_confirmed: 1,
+                ack_latency_ms: Some(400),
+                num_ack_samples: 1,
+                required_age_ms: 400,
+            }
+        );
+
+        // the second has propagated, but the stream can only be confirmed up to the third
+        let confirmation = propagation.choose_confirmed_microblocks(&microblocks, 10_000, 2_500);
+        assert_eq!(confirmation.num_confirmed, 2);
+        let confirmation = propagation.choose_confirmed_microblocks(&microblocks, 10_000, 2_700);
+        assert_eq!(confirmation.num_confirmed, 3);
+
+        // the wait is capped
+        let confirmation = propagation.choose_confirmed_microblocks(&microblocks, 100, 2_400);
+        assert_eq!(confirmation.required_age_ms, 100);
+        assert_eq!(confirmation.num_confirmed, 3);
+
+        // a slower sample moves the average
+        let mut network_result = NetworkResult::new(0, 0, 0);
+        push(&mut network_result, 2, &microblocks[2..3]);
+        propagation.note_network_result(&network_result, 2, 4_300);
+        assert_eq!(propagation.ack_latency_ms(), Some(600));
+    }
+}
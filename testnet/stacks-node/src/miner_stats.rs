@@ -0,0 +1,332 @@
+//! What the miner's block-commits achieved over the last sortitions, as served on
+//! `GET /v2/admin/miner/stats`.  The sortition DB knows every block-commit that was mined; the
+//! bitcoin controller's submission log also knows the ones this node sent that never were, but
+//! only since the node started.
+
+use std::collections::HashSet;
+
+use stacks::burnchains::{BurnchainSigner, Txid};
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::burn::operations::leader_block_commit::BURN_BLOCK_MINED_AT_MODULUS;
+use stacks::chainstate::burn::operations::LeaderBlockCommitOp;
+
+use crate::burnchains::bitcoin_regtest_controller::SubmittedBlockCommit;
+
+/// How many sortitions `/v2/admin/miner/stats` looks at by default
+pub const DEFAULT_MINER_STATS_SORTITIONS: u64 = 100;
+/// The most sortitions `/v2/admin/miner/stats` will look at
+pub const MAX_MINER_STATS_SORTITIONS: u64 = 2016;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MinerStats {
+    /// Number of sortitions looked at, ending at the canonical burnchain tip
+    pub sortitions: u64,
+    pub first_burn_height: u64,
+    pub last_burn_height: u64,
+    /// This miner's block-commits that were mined in these sortitions
+    pub commits: u64,
+    pub wins: u64,
+    /// `wins / commits`
+    pub win_rate: Option<f64>,
+    /// This miner's average share of the burn in the sortitions it committed to, which is
+    /// roughly the win rate it should expect
+    pub expected_win_rate: Option<f64>,
+    /// Satoshis burnt by this miner's block-commits
+    pub burn: u64,
+    /// Satoshis burnt by every block-commit
+    pub total_burn: u64,
+    /// Median burn of the other miners' block-commits
+    pub median_competitor_burn: Option<u64>,
+    /// This miner's block-commits that were mined later than the block they were built for
+    pub late_commits: u64,
+    /// Block-commits this node sent for these sortitions, according to the submission log, that
+    /// were never mined in any of them
+    pub missed_commits: u64,
+}
+
+/// The block-commits mined in one burnchain block
+pub struct SortitionCommits {
+    pub burn_height: u64,
+    pub commits: Vec<LeaderBlockCommitOp>,
+    /// The block-commit that won the sortition, if there was one
+    pub winning_txid: Option<Txid>,
+}
+
+fn is_late(commit: &LeaderBlockCommitOp, burn_height: u64) -> bool {
+    let expected_modulus = (burn_height.saturating_sub(1) % BURN_BLOCK_MINED_AT_MODULUS) as u8;
+    commit.burn_parent_modulus != expected_modulus
+}
+
+fn median(mut values: Vec<u64>) -> Option<u64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    let mid = values.len() / 2;
+    if values.len() % 2 == 0 {
+        Some((values[mid - 1] + values[mid]) / 2)
+    } else {
+        Some(values[mid])
+    }
+}
+
+/// Sums up `sortitions`, in order of increasing burn height.  A block-commit is this miner's if
+/// `miner` sent it, or if the submission log has it.
+pub fn summarize(
+    sortitions: &[SortitionCommits],
+    miner: &BurnchainSigner,
+    submitted: &[SubmittedBlockCommit],
+) -> MinerStats {
+    let submitted_txids: HashSet<&str> = submitted
+        .iter()
+        .map(|commit| commit.txid.as_str())
+        .collect();
+    let is_ours = |commit: &LeaderBlockCommitOp| {
+        commit.apparent_sender == *miner || submitted_txids.contains(commit.txid.to_hex().as_str())
+    };
+
+    let mut stats = MinerStats {
+        sortitions: sortitions.len() as u64,
+        first_burn_height: sortitions.first().map(|s| s.burn_height).unwrap_or(0),
+        last_burn_height: sortitions.last().map(|s| s.burn_height).unwrap_or(0),
+        commits: 0,
+        wins: 0,
+        win_rate: None,
+        expected_win_rate: None,
+        burn: 0,
+        total_burn: 0,
+        median_competitor_burn: None,
+        late_commits: 0,
+        missed_commits: 0,
+    };
+    let mut competitor_burns = vec![];
+    let mut burn_shares = vec![];
+    let mut mined_txids = HashSet::new();
+
+    for sortition in sortitions.iter() {
+        let sortition_burn: u64 = sortition.commits.iter().map(|c| c.burn_fee).sum();
+        let mut our_burn = 0;
+        let mut committed = false;
+        for commit in sortition.commits.iter() {
+            if !is_ours(commit) {
+                competitor_burns.push(commit.burn_fee);
+                continue;
+            }
+            committed = true;
+            our_burn += commit.burn_fee;
+            stats.commits += 1;
+            if sortition.winning_txid.as_ref() == Some(&commit.txid) {
+                stats.wins += 1;
+            }
+            if is_late(commit, sortition.burn_height) {
+                stats.late_commits += 1;
+            }
+            mined_txids.insert(commit.txid.to_hex());
+        }
+        stats.total_burn += sortition_burn;
+        stats.burn += our_burn;
+        if committed && sortition_burn > 0 {
+            burn_shares.push(our_burn as f64 / sortition_burn as f64);
+        }
+    }
+
+    // a block-commit replaced by fee is logged once per attempt, so count intended heights
+    let mut sent_heights = HashSet::new();
+    let mut mined_heights = HashSet::new();
+    for commit in submitted.iter() {
+        if commit.intended_burn_height < stats.first_burn_height
+            || commit.intended_burn_height > stats.last_burn_height
+            || sortitions.is_empty()
+        {
+            continue;
+        }
+        sent_heights.insert(commit.intended_burn_height);
+        if mined_txids.contains(&commit.txid) {
+            mined_heights.insert(commit.intended_burn_height);
+        }
+    }
+    stats.missed_commits = sent_heights.difference(&mined_heights).count() as u64;
+
+    if stats.commits > 0 {
+        stats.win_rate = Some(stats.wins as f64 / stats.commits as f64);
+    }
+    if !burn_shares.is_empty() {
+        stats.expected_win_rate = Some(burn_shares.iter().sum::<f64>() / burn_shares.len() as f64);
+    }
+    stats.median_competitor_burn = median(competitor_burns);
+    stats
+}
+
+/// Loads the last `count` sortitions of the canonical burnchain fork and sums them up for
+/// `miner`.
+pub fn miner_stats(
+    sortdb: &SortitionDB,
+    miner: &BurnchainSigner,
+    submitted: &[SubmittedBlockCommit],
+    count: u64,
+) -> Result<MinerStats, String> {
+    let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())
+        .map_err(|e| format!("Failed to load canonical burnchain tip: {:?}", e))?;
+    let first_height = window_start(tip.block_height, count);
+
+    let mut sortitions = vec![];
+    for burn_height in first_height..=tip.block_height {
+        let snapshot = SortitionDB::get_ancestor_snapshot(
+            &sortdb.index_conn(),
+            burn_height,
+            &tip.sortition_id,
+        )
+        .map_err(|e| format!("Failed to load sortition: {:?}", e))?
+        .ok_or_else(|| format!("No sortition at burn height {}", burn_height))?;
+        let commits =
+            SortitionDB::get_block_commits_by_block(sortdb.conn(), &snapshot.sortition_id)
+                .map_err(|e| format!("Failed to load block-commits: {:?}", e))?;
+        sortitions.push(SortitionCommits {
+            burn_height,
+            commits,
+            winning_txid: if snapshot.sortition {
+                Some(snapshot.winning_block_txid)
+            } else {
+                None
+            },
+        });
+    }
+    Ok(summarize(&sortitions, miner, submitted))
+}
+
+/// The first of the last `count` burn heights up to `tip_height`
+fn window_start(tip_height: u64, count: u64) -> u64 {
+    (tip_height + 1).saturating_sub(count.max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use stacks::chainstate::stacks::{StacksPrivateKey, StacksPublicKey};
+    use stacks::types::chainstate::{BlockHeaderHash, BurnchainHeaderHash, VRFSeed};
+    use stacks_common::address::AddressHashMode;
+
+    fn signer() -> BurnchainSigner {
+        BurnchainSigner {
+            hash_mode: AddressHashMode::SerializeP2PKH,
+            num_sigs: 1,
+            public_keys: vec![StacksPublicKey::from_private(&StacksPrivateKey::new())],
+        }
+    }
+
+    fn commit(
+        txid: u8,
+        sender: &BurnchainSigner,
+        burn_fee: u64,
+        burn_height: u64,
+    ) -> LeaderBlockCommitOp {
+        LeaderBlockCommitOp {
+            block_header_hash: BlockHeaderHash([txid; 32]),
+            new_seed: VRFSeed([0; 32]),
+            parent_block_ptr: 0,
+            parent_vtxindex: 0,
+            key_block_ptr: 0,
+            key_vtxindex: 0,
+            memo: vec![],
+            burn_fee,
+            input: (Txid([0; 32]), 0),
+            burn_parent_modulus: ((burn_height - 1) % BURN_BLOCK_MINED_AT_MODULUS) as u8,
+            apparent_sender: sender.clone(),
+            commit_outs: vec![],
+            sunset_burn: 0,
+            txid: Txid([txid; 32]),
+            vtxindex: txid as u32,
+            block_height: burn_height,
+            burn_header_hash: BurnchainHeaderHash([0; 32]),
+        }
+    }
+
+    fn submitted(txid: u8, intended_burn_height: u64) -> SubmittedBlockCommit {
+        SubmittedBlockCommit {
+            txid: Txid([txid; 32]).to_hex(),
+            intended_burn_height,
+            burn_fee: 0,
+            submitted_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_summarize() {
+        let miner = signer();
+        let other = signer();
+
+        // won at 10, lost at 11, missed 12, and committed late at 13
+        let mut late = commit(5, &miner, 3000, 13);
+        late.burn_parent_modulus = ((11 - 1) % BURN_BLOCK_MINED_AT_MODULUS) as u8;
+        let sortitions = vec![
+            SortitionCommits {
+                burn_height: 10,
+                commits: vec![commit(1, &miner, 3000, 10), commit(2, &other, 1000, 10)],
+                winning_txid: Some(Txid([1; 32])),
+            },
+            SortitionCommits {
+                burn_height: 11,
+                commits: vec![commit(3, &miner, 1000, 11), commit(4, &other, 3000, 11)],
+                winning_txid: Some(Txid([4; 32])),
+            },
+            SortitionCommits {
+                burn_height: 12,
+                commits: vec![commit(6, &other, 2000, 12)],
+                winning_txid: Some(Txid([6; 32])),
+            },
+            SortitionCommits {
+                burn_height: 13,
+                commits: vec![late],
+                winning_txid: None,
+            },
+        ];
+        let log = vec![
+            submitted(1, 10),
+            submitted(3, 11),
+            submitted(7, 12),
+            // replaced by fee, then mined at 13
+            submitted(8, 12),
+            submitted(5, 12),
+            // not mined yet
+            submitted(9, 14),
+        ];
+
+        let stats = summarize(&sortitions, &miner, &log);
+        assert_eq!(stats.sortitions, 4);
+        assert_eq!(stats.first_burn_height, 10);
+        assert_eq!(stats.last_burn_height, 13);
+        assert_eq!(stats.commits, 3);
+        assert_eq!(stats.wins, 1);
+        assert_eq!(stats.win_rate, Some(1.0 / 3.0));
+        assert_eq!(stats.expected_win_rate, Some((0.75 + 0.25 + 1.0) / 3.0));
+        assert_eq!(stats.burn, 7000);
+        assert_eq!(stats.total_burn, 13000);
+        assert_eq!(stats.median_competitor_burn, Some(2000));
+        assert_eq!(stats.late_commits, 1);
+        // the height-12 commit was mined, if late
+        assert_eq!(stats.missed_commits, 0);
+
+        let log = vec![submitted(1, 10), submitted(7, 12)];
+        let stats = summarize(&sortitions, &miner, &log);
+        assert_eq!(stats.missed_commits, 1);
+    }
+
+    #[test]
+    fn test_summarize_without_commits() {
+        let stats = summarize(&[], &signer(), &[submitted(1, 0)]);
+        assert_eq!(stats.sortitions, 0);
+        assert_eq!(stats.commits, 0);
+        assert_eq!(stats.win_rate, None);
+        assert_eq!(stats.expected_win_rate, None);
+        assert_eq!(stats.median_competitor_burn, None);
+        assert_eq!(stats.missed_commits, 0);
+    }
+
+    #[test]
+    fn test_first_height() {
+        assert_eq!(window_start(100, 10), 91);
+        assert_eq!(window_start(5, 10), 0);
+        assert_eq!(window_start(5, 0), 5);
+    }
+}
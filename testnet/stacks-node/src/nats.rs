@@ -0,0 +1,435 @@
+//! Publishes the node's events to a NATS server, as an alternative to HTTP event observers for
+//! operators whose indexing pipelines already consume from NATS. Each event type goes to a
+//! subject of its own, `<prefix>.<path>`, where `<path>` is the path an HTTP observer receives
+//! the event on, with `/` replaced by `.` (e.g. `stacks.new_block`, `stacks.attachments.new`).
+//! Payloads are the legacy-schema JSON bodies that HTTP observers receive.
+//!
+//! Only the part of the NATS client protocol needed to publish is implemented. With JetStream,
+//! every publish waits for the stream that captures its subject to acknowledge it, so an event
+//! is persisted before the next one is sent.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread::{self, sleep};
+use std::time::Duration;
+
+use rand::{thread_rng, Rng};
+use serde_json::json;
+
+use stacks::util::hash::bytes_to_hex;
+
+use crate::event_dispatcher::{ChainEvent, ChainEventSubscriber};
+
+/// Number of events queued for publishing before the node's threads block on the publisher.
+const PUBLISH_QUEUE_SIZE: usize = 1024;
+
+/// Time to wait before trying again to publish an event that could not be published.
+const RETRY_BACKOFF: Duration = Duration::from_millis(1_000);
+
+/// Subscription ID of the inbox that JetStream acknowledgements arrive on.
+const INBOX_SID: &str = "1";
+
+/// Hands events to a thread that publishes them to a NATS server, in order. Like HTTP
+/// observers, an event that can't be published is retried until it is.
+pub struct NatsPublisher {
+    subject_prefix: String,
+    sender: SyncSender<(String, Vec<u8>)>,
+}
+
+impl NatsPublisher {
+    /// Starts publishing to the NATS server at `endpoint`. `timeout` bounds connecting to the
+    /// server and waiting on it for each publish.
+    pub fn spawn(
+        endpoint: String,
+        subject_prefix: String,
+        jetstream: bool,
+        timeout: Duration,
+    ) -> NatsPublisher {
+        let (sender, receiver) = sync_channel(PUBLISH_QUEUE_SIZE);
+        thread::Builder::new()
+            .name(format!("nats-publisher:{}", &endpoint))
+            .spawn(move || run_publisher(endpoint, jetstream, timeout, receiver))
+            .expect("FATAL: failed to start NATS publisher thread");
+        NatsPublisher {
+            subject_prefix,
+            sender,
+        }
+    }
+}
+
+impl ChainEventSubscriber for NatsPublisher {
+    fn on_event(&self, event: ChainEvent) {
+        let payload = match event.payload_json() {
+            Ok(payload) => payload,
+            Err(e) => {
+                error!("NATS: failed to serialize event - {:?}", e);
+                return;
+            }
+        };
+        let subject = make_subject(&self.subject_prefix, event.path());
+        if self.sender.send((subject, payload.into_bytes())).is_err() {
+            error!("NATS: publisher thread is no longer running");
+        }
+    }
+}
+
+fn make_subject(subject_prefix: &str, path: &str) -> String {
+    format!("{}.{}", subject_prefix, path.replace('/', "."))
+}
+
+fn run_publisher(
+    endpoint: String,
+    jetstream: bool,
+    timeout: Duration,
+    receiver: Receiver<(String, Vec<u8>)>,
+) {
+    let mut connection: Option<NatsConnection> = None;
+    while let Ok((subject, payload)) = receiver.recv() {
+        loop {
+            let conn = match connection.as_mut() {
+                Some(conn) => conn,
+                None => match NatsConnection::connect(&endpoint, jetstream, timeout) {
+                    Ok(conn) => connection.insert(conn),
+                    Err(e) => {
+                        warn!("NATS: failed to connect"; "endpoint" => &endpoint, "error" => %e);
+                        sleep(RETRY_BACKOFF);
+                        continue;
+                    }
+                },
+            };
+            match conn.publish(&subject, &payload) {
+                Ok(()) => {
+                    debug!("NATS: published"; "subject" => &subject);
+                    break;
+                }
+                Err(e) => {
+                    warn!("NATS: failed to publish"; "subject" => &subject, "error" => %e);
+                    connection = None;
+                    sleep(RETRY_BACKOFF);
+                }
+            }
+        }
+    }
+}
+
+enum ServerMessage {
+    Pong,
+    Msg {
+        subject: String,
+        /// Status code from the message's headers, such as 503 if no one is listening.
+        status: Option<u16>,
+        payload: Vec<u8>,
+    },
+}
+
+struct NatsConnection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    jetstream: bool,
+    /// Prefix of the subjects JetStream acknowledges publishes on.
+    inbox: String,
+    next_reply: u64,
+}
+
+impl NatsConnection {
+    fn connect(
+        endpoint: &str,
+        jetstream: bool,
+        timeout: Duration,
+    ) -> Result<NatsConnection, String> {
+        let addr = endpoint
+            .to_socket_addrs()
+            .map_err(|e| format!("cannot resolve {} - {}", endpoint, e))?
+            .next()
+            .ok_or_else(|| format!("cannot resolve {}", endpoint))?;
+        let stream = TcpStream::connect_timeout(&addr, timeout).map_err(|e| e.to_string())?;
+        stream
+            .set_read_timeout(Some(timeout))
+            .and_then(|_| stream.set_write_timeout(Some(timeout)))
+            .map_err(|e| e.to_string())?;
+        let reader = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?);
+
+        let mut conn = NatsConnection {
+            reader,
+            writer: stream,
+            jetstream,
+            inbox: format!(
+                "_INBOX.{}",
+                bytes_to_hex(&thread_rng().gen::<[u8; 8]>().to_vec())
+            ),
+            next_reply: 0,
+        };
+
+        let info = conn.read_line()?;
+        if !info.starts_with("INFO ") {
+            return Err(format!("expected INFO from server, got '{}'", info));
+        }
+        let connect = json!({
+            "verbose": false,
+            "pedantic": false,
+            "lang": "rust",
+            "name": "stacks-node",
+            "version": env!("CARGO_PKG_VERSION"),
+            "protocol": 1,
+            "headers": true,
+            "no_responders": true,
+        });
+        let mut handshake = format!("CONNECT {}\r\n", connect);
+        if jetstream {
+            handshake.push_str(&format!("SUB {}.* {}\r\n", &conn.inbox, INBOX_SID));
+        }
+        conn.write(handshake.as_bytes())?;
+        // the server answers the handshake with -ERR if it refuses it
+        conn.ping()?;
+        Ok(conn)
+    }
+
+    fn publish(&mut self, subject: &str, payload: &[u8]) -> Result<(), String> {
+        if !self.jetstream {
+            let mut msg = format!("PUB {} {}\r\n", subject, payload.len()).into_bytes();
+            msg.extend_from_slice(payload);
+            msg.extend_from_slice(b"\r\n");
+            self.write(&msg)?;
+            // the round trip surfaces any error with the publish
+            return self.ping();
+        }
+
+        self.next_reply += 1;
+        let reply = format!("{}.{}", &self.inbox, self.next_reply);
+        let mut msg = format!("PUB {} {} {}\r\n", subject, &reply, payload.len()).into_bytes();
+        msg.extend_from_slice(payload);
+        msg.extend_from_slice(b"\r\n");
+        self.write(&msg)?;
+
+        loop {
+            match self.next_message()? {
+                ServerMessage::Msg {
+                    subject,
+                    status,
+                    payload,
+                } if subject == reply => {
+                    if let Some(status) = status {
+                        return Err(format!(
+                            "no JetStream stream acknowledged the publish (status {})",
+                            status
+                        ));
+                    }
+                    let ack: serde_json::Value = serde_json::from_slice(&payload)
+                        .map_err(|e| format!("unreadable JetStream ack - {}", e))?;
+                    if let Some(error) = ack.get("error") {
+                        return Err(format!("JetStream rejected the publish: {}", error));
+                    }
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn ping(&mut self) -> Result<(), String> {
+        self.write(b"PING\r\n")?;
+        loop {
+            if let ServerMessage::Pong = self.next_message()? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Reads messages from the server until one that the caller has to handle arrives.
+    fn next_message(&mut self) -> Result<ServerMessage, String> {
+        loop {
+            let line = self.read_line()?;
+            let mut parts = line.split_whitespace();
+            match parts.next() {
+                Some("PING") => self.write(b"PONG\r\n")?,
+                Some("PONG") => return Ok(ServerMessage::Pong),
+                Some("+OK") | Some("INFO") => {}
+                Some("-ERR") => return Err(format!("server error: {}", &line[4..].trim())),
+                Some("MSG") => {
+                    // MSG <subject> <sid> [reply-to] <#bytes>
+                    let args: Vec<&str> = parts.collect();
+                    let len = parse_len(args.last())?;
+                    let subject = args.first().map(|s| s.to_string()).unwrap_or_default();
+                    let payload = self.read_payload(len)?;
+                    return Ok(ServerMessage::Msg {
+                        subject,
+                        status: None,
+                        payload,
+                    });
+                }
+                Some("HMSG") => {
+                    // HMSG <subject> <sid> [reply-to] <#header bytes> <#total bytes>
+                    let args: Vec<&str> = parts.collect();
+                    if args.len() < 2 {
+                        return Err(format!("malformed HMSG: '{}'", line));
+                    }
+                    let total_len = parse_len(args.last())?;
+                    let header_len = parse_len(args.get(args.len() - 2))?;
+                    if header_len > total_len {
+                        return Err(format!("malformed HMSG: '{}'", line));
+                    }
+                    let subject = args[0].to_string();
+                    let mut payload = self.read_payload(total_len)?;
+                    // the headers start with a version line, such as "NATS/1.0 503"
+                    let status = String::from_utf8_lossy(&payload[..header_len])
+                        .lines()
+                        .next()
+                        .and_then(|version_line| version_line.split_whitespace().nth(1))
+                        .and_then(|status| status.parse().ok());
+                    payload.drain(..header_len);
+                    return Ok(ServerMessage::Msg {
+                        subject,
+                        status,
+                        payload,
+                    });
+                }
+                _ => return Err(format!("unexpected message from server: '{}'", line)),
+            }
+        }
+    }
+
+    fn read_line(&mut self) -> Result<String, String> {
+        let mut line = String::new();
+        let read = self
+            .reader
+            .read_line(&mut line)
+            .map_err(|e| e.to_string())?;
+        if read == 0 {
+            return Err("server closed the connection".to_string());
+        }
+        Ok(line.trim_end().to_string())
+    }
+
+    fn read_payload(&mut self, len: usize) -> Result<Vec<u8>, String> {
+        let mut payload = vec![0u8; len + 2];
+        self.reader
+            .read_exact(&mut payload)
+            .map_err(|e| e.to_string())?;
+        payload.truncate(len);
+        Ok(payload)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.writer.write_all(bytes).map_err(|e| e.to_string())
+    }
+}
+
+fn parse_len(arg: Option<&&str>) -> Result<usize, String> {
+    arg.and_then(|len| len.parse().ok())
+        .ok_or_else(|| "malformed message length".to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::mpsc::channel;
+
+    use crate::event_dispatcher::PATH_ATTACHMENT_PROCESSED;
+
+    /// Acts as a NATS server for one connection, acknowledging JetStream publishes if
+    /// `jetstream` is set, and reporting each publish's subject and payload.
+    fn spawn_server(
+        listener: TcpListener,
+        jetstream: bool,
+    ) -> (thread::JoinHandle<()>, Receiver<(String, String)>) {
+        let (sender, receiver) = channel();
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            writer.write_all(b"INFO {\"headers\":true}\r\n").unwrap();
+            let mut seq = 0;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 {
+                    return;
+                }
+                let args: Vec<&str> = line.split_whitespace().collect();
+                match args[0] {
+                    "CONNECT" | "SUB" => {}
+                    "PING" => writer.write_all(b"PONG\r\n").unwrap(),
+                    "PUB" => {
+                        let len: usize = args.last().unwrap().parse().unwrap();
+                        let mut payload = vec![0u8; len + 2];
+                        reader.read_exact(&mut payload).unwrap();
+                        payload.truncate(len);
+                        if jetstream {
+                            assert_eq!(args.len(), 4);
+                            seq += 1;
+                            let ack = format!("{{\"stream\":\"STACKS\",\"seq\":{}}}", seq);
+                            // a server PING in between must be answered, not mistaken for the ack
+                            writer
+                                .write_all(
+                                    format!(
+                                        "PING\r\nMSG {} {} {}\r\n{}\r\n",
+                                        args[2],
+                                        INBOX_SID,
+                                        ack.len(),
+                                        ack
+                                    )
+                                    .as_bytes(),
+                                )
+                                .unwrap();
+                        }
+                        sender
+                            .send((args[1].to_string(), String::from_utf8(payload).unwrap()))
+                            .unwrap();
+                    }
+                    "PONG" => {}
+                    cmd => panic!("unexpected command {}", cmd),
+                }
+            }
+        });
+        (handle, receiver)
+    }
+
+    #[test]
+    fn test_make_subject() {
+        assert_eq!(make_subject("stacks", "new_block"), "stacks.new_block");
+        assert_eq!(
+            make_subject("stacks.testnet", PATH_ATTACHMENT_PROCESSED),
+            "stacks.testnet.attachments.new"
+        );
+    }
+
+    #[test]
+    fn test_publish() {
+        for jetstream in [false, true] {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let endpoint = format!("{}", listener.local_addr().unwrap());
+            let (server, published) = spawn_server(listener, jetstream);
+
+            let publisher = NatsPublisher::spawn(
+                endpoint,
+                "stacks".to_string(),
+                jetstream,
+                Duration::from_millis(5_000),
+            );
+            publisher.on_event(ChainEvent::BurnBlock(json!({"burn_block_height": 1})));
+            publisher.on_event(ChainEvent::MempoolTxs(json!(["0x00"])));
+
+            let recv = |published: &Receiver<(String, String)>| {
+                published.recv_timeout(Duration::from_secs(10)).unwrap()
+            };
+            assert_eq!(
+                recv(&published),
+                (
+                    "stacks.new_burn_block".to_string(),
+                    r#"{"burn_block_height":1}"#.to_string()
+                )
+            );
+            assert_eq!(
+                recv(&published),
+                (
+                    "stacks.new_mempool_tx".to_string(),
+                    r#"["0x00"]"#.to_string()
+                )
+            );
+
+            drop(publisher);
+            server.join().unwrap();
+        }
+    }
+}
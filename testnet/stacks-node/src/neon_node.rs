@@ -7,7 +7,7 @@ use std::fs;
 use std::io::Write;
 use std::net::SocketAddr;
 use std::path::Path;
-use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::mpsc::{channel, sync_channel, Receiver, Sender, SyncSender, TrySendError};
 use std::sync::{atomic::Ordering, Arc, Mutex};
 use std::time::Duration;
 use std::{thread, thread::JoinHandle};
@@ -22,6 +22,7 @@ use stacks::chainstate::burn::BlockSnapshot;
 use stacks::chainstate::burn::ConsensusHash;
 use stacks::chainstate::coordinator::comm::CoordinatorChannels;
 use stacks::chainstate::coordinator::{get_next_recipients, OnChainRewardSetProvider};
+use stacks::chainstate::stacks::db::blocks::ValidatedBlockProposal;
 use stacks::chainstate::stacks::db::unconfirmed::UnconfirmedTxMap;
 use stacks::chainstate::stacks::db::{StacksChainState, MINER_REWARD_MATURITY};
 use stacks::chainstate::stacks::Error as ChainstateError;
@@ -39,7 +40,7 @@ use stacks::core::mempool::MemPoolDB;
 use stacks::core::FIRST_BURNCHAIN_CONSENSUS_HASH;
 use stacks::core::STACKS_EPOCH_2_05_MARKER;
 use stacks::cost_estimates::metrics::UnitMetric;
-use stacks::cost_estimates::UnitEstimator;
+use stacks::cost_estimates::{FeeEstimator, UnitEstimator};
 use stacks::monitoring::{increment_stx_blocks_mined_counter, update_active_miners_count_gauge};
 use stacks::net::{
     atlas::{AtlasConfig, AtlasDB, AttachmentInstance},
@@ -62,10 +63,19 @@ use stacks::util_lib::strings::{UrlString, VecDisplay};
 use stacks::vm::costs::ExecutionCost;
 use stacks::{burnchains::BurnchainSigner, chainstate::stacks::db::StacksHeaderInfo};
 
-use crate::burnchains::bitcoin_regtest_controller::BitcoinRegtestController;
+use crate::admin::{
+    start_serving_admin_api, AdminApi, BlockProposal, BurnchainOpsCommand, MinerCommand,
+    MinerStatus,
+};
+use crate::backup::BackupScheduler;
+use crate::burnchains::bitcoin_regtest_controller::{BitcoinRegtestController, PendingBurnchainOp};
+use crate::burnchains::commit_journal::CommitJournal;
+use crate::microblock_propagation::MicroblockPropagation;
+use crate::miner_stats::{self, MinerStats};
 use crate::run_loop::neon::Counters;
 use crate::run_loop::neon::RunLoop;
 use crate::run_loop::RegisteredKey;
+use crate::standby::{FailoverEvent, MinerFailover, SignerCommit};
 use crate::ChainTip;
 
 use super::{BurnchainController, BurnchainTip, Config, EventDispatcher, Keychain};
@@ -83,6 +93,48 @@ struct AssembledAnchorBlock {
     attempt: u64,
 }
 
+/// The miner's run state and identity, shared between the relayer thread, which owns the miner,
+/// and the run loop thread, which schedules its tenures.
+#[derive(Clone)]
+struct MinerControl {
+    running: bool,
+    /// Bumped on every miner restart, so the run loop drops state tied to the previous miner
+    generation: u64,
+    burnchain_signer: BurnchainSigner,
+    /// Bumped on every VRF key rotation requested through the admin API, and reset on restart
+    key_rotations: u64,
+}
+
+/// Everything the relayer keeps for mining.  Restarting the miner replaces all of it, and leaves
+/// the relayer, the p2p network and the chains coordinator alone.
+struct MinerSubsystem {
+    keychain: Keychain,
+    last_mined_blocks:
+        HashMap<BurnchainHeaderHash, Vec<(AssembledAnchorBlock, Secp256k1PrivateKey)>>,
+    microblock_miner_state: Option<MicroblockMinerState>,
+    /// only set if we won the last sortition
+    miner_tip: Option<(ConsensusHash, BlockHeaderHash, Secp256k1PrivateKey)>,
+    last_microblock_tenure_time: u128,
+    last_tenure_issue_time: u128,
+    /// The burn block the miner's current tenure builds on, and when the relayer first heard of
+    /// it, in milliseconds since the epoch
+    burn_block_arrival: Option<(BurnchainHeaderHash, u128)>,
+}
+
+impl MinerSubsystem {
+    fn new(keychain: Keychain) -> MinerSubsystem {
+        MinerSubsystem {
+            keychain,
+            last_mined_blocks: HashMap::new(),
+            microblock_miner_state: None,
+            miner_tip: None,
+            last_microblock_tenure_time: 0,
+            last_tenure_issue_time: 0,
+            burn_block_arrival: None,
+        }
+    }
+}
+
 struct MicroblockMinerState {
     parent_consensus_hash: ConsensusHash,
     parent_block_hash: BlockHeaderHash,
@@ -92,6 +144,8 @@ struct MicroblockMinerState {
     quantity: u64,
     cost_so_far: ExecutionCost,
     settings: BlockBuilderSettings,
+    /// When mining of this stream began, in milliseconds since the epoch
+    stream_started: u128,
 }
 
 enum RelayerDirective {
@@ -100,6 +154,13 @@ enum RelayerDirective {
     RunTenure(RegisteredKey, BlockSnapshot, u128), // (vrf key, chain tip, time of issuance in ms)
     RegisterKey(BlockSnapshot),
     RunMicroblockTenure(BlockSnapshot, u128), // time of issuance in ms
+    BurnchainOps(
+        BurnchainOpsCommand,
+        Sender<Result<Vec<PendingBurnchainOp>, String>>,
+    ),
+    UpdateConfig(serde_json::Value, Sender<Result<Vec<String>, String>>),
+    ControlMiner(MinerCommand, Sender<Result<MinerStatus, String>>),
+    MinerStats(u64, Sender<Result<MinerStats, String>>),
     Exit,
 }
 
@@ -108,13 +169,41 @@ pub struct StacksNode {
     relay_channel: SyncSender<RelayerDirective>,
     last_sortition: Arc<Mutex<Option<BlockSnapshot>>>,
     burnchain_signer: BurnchainSigner,
+    miner_control: Arc<Mutex<MinerControl>>,
+    /// The `MinerControl` generation that `burnchain_signer` and the leader key belong to
+    miner_generation: u64,
+    /// The `MinerControl` key rotation count that the leader key reflects
+    miner_key_rotations: u64,
+    /// Set when the admin API asked for a VRF key rotation that has not been started yet
+    key_rotation_requested: bool,
     is_miner: bool,
+    /// Whether this miner is mining, or is the warm standby of another node with the same seed
+    miner_failover: MinerFailover,
     pub atlas_config: AtlasConfig,
     leader_key_registration_state: LeaderKeyRegistrationState,
     pub p2p_thread_handle: JoinHandle<()>,
     pub relayer_thread_handle: JoinHandle<()>,
 }
 
+/// Whether `miner.recommit_on_new_parent` should have the miner mine again right away: it has
+/// already mined on the current burn block, but none of those blocks (`mined_parents`) builds on
+/// the Stacks tip `tip`, and the burn block arrived at most `deadline_ms` before `now_ms`.
+///
+/// The new block's commit replaces the in-flight one by fee, so it reuses the tenure's VRF proof
+/// (over the same sortition hash) and microblock key: at most one of the commits can be mined.
+fn should_recommit_on_new_parent(
+    mined_parents: &[(ConsensusHash, BlockHeaderHash)],
+    tip: &(ConsensusHash, BlockHeaderHash),
+    burn_block_arrival_ms: u128,
+    deadline_ms: u64,
+    now_ms: u128,
+) -> bool {
+    if now_ms > burn_block_arrival_ms + (deadline_ms as u128) {
+        return false;
+    }
+    mined_parents.len() > 0 && !mined_parents.contains(tip)
+}
+
 #[cfg(test)]
 fn fault_injection_long_tenure() {
     // simulated slow block
@@ -400,6 +489,7 @@ fn mine_one_microblock(
     chainstate: &mut StacksChainState,
     mempool: &mut MemPoolDB,
     event_dispatcher: &EventDispatcher,
+    fee_estimator: Option<&mut dyn FeeEstimator>,
 ) -> Result<StacksMicroblock, ChainstateError> {
     debug!(
         "Try to mine one microblock off of {}/{} (total: {})",
@@ -458,6 +548,7 @@ fn mine_one_microblock(
             mempool,
             &microblock_state.miner_key,
             event_dispatcher,
+            fee_estimator,
         )?;
         let new_cost_so_far = microblock_miner.get_cost_so_far().expect("BUG: cannot read cost so far from miner -- indicates that the underlying Clarity Tx is somehow in use still.");
         let t2 = get_epoch_time_ms();
@@ -592,6 +683,7 @@ fn try_mine_microblock(
                     quantity: 0,
                     cost_so_far: cost_so_far,
                     settings: config.make_block_builder_settings(0, true),
+                    stream_started: get_epoch_time_ms(),
                 });
             }
             Ok(None) => {
@@ -612,7 +704,16 @@ fn try_mine_microblock(
     if let Some(mut microblock_miner) = microblock_miner_state.take() {
         if microblock_miner.parent_consensus_hash == ch && microblock_miner.parent_block_hash == bhh
         {
-            if microblock_miner.last_mined + (microblock_miner.frequency as u128)
+            let stream_time_remaining_ms = (config.miner.microblock_stream_time_ms as u128)
+                .saturating_sub(
+                    get_epoch_time_ms().saturating_sub(microblock_miner.stream_started),
+                );
+            if stream_time_remaining_ms == 0 {
+                debug!(
+                    "Will not mine more microblocks off of {}/{} -- stream time budget of {}ms exhausted",
+                    &ch, &bhh, config.miner.microblock_stream_time_ms
+                );
+            } else if microblock_miner.last_mined + (microblock_miner.frequency as u128)
                 < get_epoch_time_ms()
             {
                 // opportunistically try and mine, but only if there are no attachable blocks in
@@ -623,12 +724,25 @@ fn try_mine_microblock(
                     get_epoch_time_secs() - 600,
                 )?;
                 if num_attachable == 0 {
+                    // don't let this microblock run past the end of the stream's time budget
+                    let mut settings = config.make_block_builder_settings(0, true);
+                    let attempt_time_ms =
+                        cmp::min(settings.max_miner_time_ms as u128, stream_time_remaining_ms)
+                            as u64;
+                    settings.max_miner_time_ms = attempt_time_ms;
+                    settings.mempool_settings.max_walk_time_ms = attempt_time_ms;
+                    microblock_miner.settings = settings;
+
+                    let mut fee_estimator = config.make_fee_estimator();
                     match mine_one_microblock(
                         &mut microblock_miner,
                         sortdb,
                         chainstate,
                         mem_pool,
                         event_dispatcher,
+                        fee_estimator
+                            .as_mut()
+                            .map(|fe| fe.as_mut() as &mut dyn FeeEstimator),
                     ) {
                         Ok(microblock) => {
                             // will need to relay this
@@ -665,6 +779,7 @@ fn run_microblock_tenure(
     miner_tip: (ConsensusHash, BlockHeaderHash, Secp256k1PrivateKey),
     counters: &Counters,
     event_dispatcher: &EventDispatcher,
+    microblock_propagation: &mut MicroblockPropagation,
 ) {
     // TODO: this is sensitive to poll latency -- can we call this on a fixed
     // schedule, regardless of network activity?
@@ -697,6 +812,7 @@ fn run_microblock_tenure(
     if let Some(next_microblock) = next_microblock_opt {
         // apply it
         let microblock_hash = next_microblock.block_hash();
+        microblock_propagation.note_mined(microblock_hash.clone(), get_epoch_time_ms());
 
         let processed_unconfirmed_state = Relayer::refresh_unconfirmed(chainstate, sortdb);
         let num_mblocks = chainstate
@@ -750,6 +866,51 @@ fn send_unconfirmed_txs(
     }
 }
 
+/// Compute the chainstate consistency hash at the highest multiple of `interval` that the
+/// canonical Stacks chain has reached, if it is above `last_height`, and export it.  Nodes that
+/// use the same interval compute their hashes at the same heights, so they can be compared.
+fn update_consistency_hash(
+    chainstate: &StacksChainState,
+    sortdb: &SortitionDB,
+    interval: u64,
+    last_height: &mut u64,
+) {
+    if interval == 0 {
+        return;
+    }
+    let tip_height = match SortitionDB::get_canonical_burn_chain_tip(sortdb.conn()) {
+        Ok(sn) => sn.canonical_stacks_tip_height,
+        Err(e) => {
+            warn!("Failed to load canonical burn chain tip: {:?}", &e);
+            return;
+        }
+    };
+    let height = tip_height - tip_height % interval;
+    if height == 0 || height <= *last_height {
+        return;
+    }
+    match chainstate.get_canonical_consistency_hash(sortdb, height) {
+        Ok(Some(consistency_hash)) => {
+            info!(
+                "Chainstate consistency hash at height {} is {}",
+                height, &consistency_hash.hash;
+                "index_block_hash" => %consistency_hash.index_block_hash,
+                "index_root" => %consistency_hash.index_root,
+                "sortition_id" => %consistency_hash.sortition_id
+            );
+            monitoring::update_consistency_hash(height, &consistency_hash.hash);
+            *last_height = height;
+        }
+        Ok(None) => {}
+        Err(e) => {
+            warn!(
+                "Failed to compute consistency hash at height {}: {:?}",
+                height, &e
+            );
+        }
+    }
+}
+
 /// Have the p2p thread receive unconfirmed txs
 fn recv_unconfirmed_txs(
     chainstate: &mut StacksChainState,
@@ -770,6 +931,170 @@ fn recv_unconfirmed_txs(
     }
 }
 
+/// Opens the peer DB and the Atlas DB, and instantiates the p2p network, which also serves the
+/// node's RPC interface.  The mempool is created if it does not exist yet.
+pub fn make_peer_network(
+    config: &Config,
+    burnchain: &Burnchain,
+    sortdb: &SortitionDB,
+    atlas_config: &AtlasConfig,
+) -> PeerNetwork {
+    let epochs =
+        SortitionDB::get_stacks_epochs(sortdb.conn()).expect("Error while loading stacks epochs");
+
+    let view = {
+        let sortition_tip = SortitionDB::get_canonical_burn_chain_tip(&sortdb.conn())
+            .expect("Failed to get sortition tip");
+        SortitionDB::get_burnchain_view(&sortdb.conn(), &burnchain, &sortition_tip).unwrap()
+    };
+
+    // create a new peerdb
+    let data_url = UrlString::try_from(format!("{}", &config.node.data_url)).unwrap();
+    let initial_neighbors = config.node.bootstrap_node.clone();
+    if initial_neighbors.len() > 0 {
+        info!(
+            "Will bootstrap from peers {}",
+            VecDisplay(&initial_neighbors)
+        );
+    } else {
+        warn!("Without a peer to bootstrap from, the node will start mining a new chain");
+    }
+
+    let p2p_sock: SocketAddr = config.node.p2p_bind.parse().expect(&format!(
+        "Failed to parse socket: {}",
+        &config.node.p2p_bind
+    ));
+    let p2p_addr: SocketAddr = config.node.p2p_address.parse().expect(&format!(
+        "Failed to parse socket: {}",
+        &config.node.p2p_address
+    ));
+    let node_privkey = {
+        let mut re_hashed_seed = config.node.local_peer_seed.clone();
+        let my_private_key = loop {
+            match Secp256k1PrivateKey::from_slice(&re_hashed_seed[..]) {
+                Ok(sk) => break sk,
+                Err(_) => {
+                    re_hashed_seed = Sha256Sum::from_data(&re_hashed_seed[..])
+                        .as_bytes()
+                        .to_vec()
+                }
+            }
+        };
+        my_private_key
+    };
+
+    let mut peerdb = PeerDB::connect(
+        &config.get_peer_db_file_path(),
+        true,
+        config.burnchain.chain_id,
+        burnchain.network_id,
+        Some(node_privkey),
+        config.connection_options.private_key_lifetime.clone(),
+        PeerAddress::from_socketaddr(&p2p_addr),
+        p2p_sock.port(),
+        data_url,
+        &vec![],
+        Some(&initial_neighbors),
+    )
+    .map_err(|e| {
+        eprintln!(
+            "Failed to open {}: {:?}",
+            &config.get_peer_db_file_path(),
+            &e
+        );
+        panic!();
+    })
+    .unwrap();
+
+    {
+        // bootstrap nodes *always* allowed
+        let mut tx = peerdb.tx_begin().unwrap();
+        for initial_neighbor in initial_neighbors.iter() {
+            // update peer in case public key changed
+            PeerDB::update_peer(&mut tx, &initial_neighbor).unwrap();
+            PeerDB::set_allow_peer(
+                &mut tx,
+                initial_neighbor.addr.network_id,
+                &initial_neighbor.addr.addrbytes,
+                initial_neighbor.addr.port,
+                -1,
+            )
+            .unwrap();
+        }
+        tx.commit().unwrap();
+    }
+
+    if !config.node.deny_nodes.is_empty() {
+        warn!("Will ignore nodes {:?}", &config.node.deny_nodes);
+    }
+
+    {
+        let mut tx = peerdb.tx_begin().unwrap();
+        for denied in config.node.deny_nodes.iter() {
+            PeerDB::set_deny_peer(
+                &mut tx,
+                denied.addr.network_id,
+                &denied.addr.addrbytes,
+                denied.addr.port,
+                get_epoch_time_secs() + 24 * 365 * 3600,
+            )
+            .unwrap();
+        }
+        tx.commit().unwrap();
+    }
+
+    // update services to indicate we can support mempool sync and fee filters
+    {
+        let mut tx = peerdb.tx_begin().unwrap();
+        PeerDB::set_local_services(
+            &mut tx,
+            (ServiceFlags::RPC as u16)
+                | (ServiceFlags::RELAY as u16)
+                | (ServiceFlags::FEE_FILTER as u16),
+        )
+        .unwrap();
+        tx.commit().unwrap();
+    }
+
+    let atlasdb =
+        AtlasDB::connect(atlas_config.clone(), &config.get_atlas_db_file_path(), true).unwrap();
+
+    let local_peer = match PeerDB::get_local_peer(peerdb.conn()) {
+        Ok(local_peer) => local_peer,
+        _ => panic!("Unable to retrieve local peer"),
+    };
+
+    // force early mempool instantiation
+    let cost_estimator = config
+        .make_cost_estimator()
+        .unwrap_or_else(|| Box::new(UnitEstimator));
+    let metric = config
+        .make_cost_metric()
+        .unwrap_or_else(|| Box::new(UnitMetric));
+
+    let _ = MemPoolDB::open(
+        config.is_mainnet(),
+        config.burnchain.chain_id,
+        &config.get_chainstate_path_str(),
+        cost_estimator,
+        metric,
+    )
+    .expect("BUG: failed to instantiate mempool");
+
+    let mut p2p_net = PeerNetwork::new(
+        peerdb,
+        atlasdb,
+        local_peer,
+        config.burnchain.peer_version,
+        burnchain.clone(),
+        view,
+        config.connection_options.clone(),
+        epochs,
+    );
+    p2p_net.dns_seeds = config.node.dns_seeds.clone();
+    p2p_net
+}
+
 fn spawn_peer(
     runloop: &RunLoop,
     mut this: PeerNetwork,
@@ -784,6 +1109,9 @@ fn spawn_peer(
     let mut sync_comms = runloop.get_pox_sync_comms();
     let event_dispatcher = runloop.get_event_dispatcher();
     let should_keep_running = runloop.get_termination_switch();
+    let heartbeat = runloop.get_heartbeat();
+    let remediation_requests = runloop.get_remediation_requests();
+    let peer_snapshot = runloop.get_peer_snapshot();
 
     let is_mainnet = config.is_mainnet();
     let burn_db_path = config.get_burn_db_file_path();
@@ -792,6 +1120,7 @@ fn spawn_peer(
 
     this.bind(p2p_sock, rpc_sock).unwrap();
     let (mut dns_resolver, mut dns_client) = DNSResolver::new(10);
+    dns_resolver.set_socks_proxy(config.connection_options.socks_proxy.clone());
     let sortdb = SortitionDB::open(&burn_db_path, false).map_err(NetError::DBError)?;
 
     let (mut chainstate, _) = StacksChainState::open(
@@ -835,8 +1164,7 @@ fn spawn_peer(
 
             let handler_args = RPCHandlerArgs {
                 exit_at_block_height: exit_at_block_height.as_ref(),
-                genesis_chainstate_hash: Sha256Sum::from_hex(stx_genesis::GENESIS_CHAINSTATE_HASH)
-                    .unwrap(),
+                genesis_chainstate_hash: config.get_genesis_chainstate_hash(),
                 event_observer: Some(&event_dispatcher),
                 cost_estimator: Some(cost_estimator.as_ref()),
                 cost_metric: Some(metric.as_ref()),
@@ -850,6 +1178,8 @@ fn spawn_peer(
             let mut mblock_deadline = 0;
 
             while should_keep_running.load(Ordering::SeqCst) {
+                heartbeat.beat();
+
                 // initial block download?
                 let ibd = sync_comms.get_ibd();
                 let download_backpressure = results_with_data.len() > 0;
@@ -879,6 +1209,19 @@ fn spawn_peer(
                 let _ = Relayer::setup_unconfirmed_state_readonly(&mut chainstate, &sortdb);
                 recv_unconfirmed_txs(&mut chainstate, unconfirmed_txs.clone());
 
+                // the relayer may have changed the minimum fee rate
+                mem_pool.min_tx_fee_rate = this.connection_opts.min_tx_fee_rate;
+
+                if remediation_requests.take_reset_peers() {
+                    info!("Stall watchdog: disconnecting from all peers");
+                    this.disconnect_all();
+                }
+
+                let now = get_epoch_time_secs();
+                if peer_snapshot.is_stale(now) {
+                    peer_snapshot.update(&this.peers, now);
+                }
+
                 match this.run(
                     &sortdb,
                     &mut chainstate,
@@ -983,6 +1326,27 @@ fn spawn_peer(
     Ok(server_thread)
 }
 
+/// Validate a block posted to the admin API.  Fresh handles to the databases are opened, so the
+/// relayer and miner threads are not held up while the block executes.
+fn validate_block_proposal(
+    config: &Config,
+    proposal: BlockProposal,
+) -> Result<ValidatedBlockProposal, ChainstateError> {
+    let sortdb = SortitionDB::open(&config.get_burn_db_file_path(), false)?;
+    let (mut chainstate, _) = StacksChainState::open(
+        config.is_mainnet(),
+        config.burnchain.chain_id,
+        &config.get_chainstate_path_str(),
+        Some(config.node.get_marf_opts()),
+    )?;
+    chainstate.validate_block_proposal(
+        &sortdb.index_conn(),
+        &proposal.parent_consensus_hash,
+        &proposal.block,
+        proposal.microblocks,
+    )
+}
+
 fn get_last_sortition(last_sortition: &Arc<Mutex<Option<BlockSnapshot>>>) -> Option<BlockSnapshot> {
     match last_sortition.lock() {
         Ok(sort_opt) => sort_opt.clone(),
@@ -1008,17 +1372,26 @@ fn set_last_sortition(
     };
 }
 
+fn is_miner_running(miner_control: &Arc<Mutex<MinerControl>>) -> bool {
+    miner_control
+        .lock()
+        .expect("FATAL: miner control mutex poisoned")
+        .running
+}
+
 fn spawn_miner_relayer(
     runloop: &RunLoop,
     mut relayer: Relayer,
     local_peer: LocalPeer,
-    mut keychain: Keychain,
+    keychain: Keychain,
     relay_channel: Receiver<RelayerDirective>,
     last_sortition: Arc<Mutex<Option<BlockSnapshot>>>,
+    miner_control: Arc<Mutex<MinerControl>>,
     coord_comms: CoordinatorChannels,
     unconfirmed_txs: Arc<Mutex<UnconfirmedTxMap>>,
 ) -> Result<JoinHandle<()>, NetError> {
-    let config = runloop.config().clone();
+    let mut config = runloop.config().clone();
+    let started_as_miner = runloop.is_miner();
     let event_dispatcher = runloop.get_event_dispatcher();
     let counters = runloop.get_counters();
     let sync_comms = runloop.get_pox_sync_comms();
@@ -1045,17 +1418,10 @@ fn spawn_miner_relayer(
     )
     .map_err(|e| NetError::ChainstateError(e.to_string()))?;
 
-    let mut last_mined_blocks: HashMap<
-        BurnchainHeaderHash,
-        Vec<(AssembledAnchorBlock, Secp256k1PrivateKey)>,
-    > = HashMap::new();
-    let burn_fee_cap = config.burnchain.burn_fee_cap;
+    let mut miner = MinerSubsystem::new(keychain);
 
     let mut bitcoin_controller = BitcoinRegtestController::new_dummy(config.clone());
-    let mut microblock_miner_state: Option<MicroblockMinerState> = None;
-    let mut miner_tip = None; // only set if we won the last sortition
-    let mut last_microblock_tenure_time = 0;
-    let mut last_tenure_issue_time = 0;
+    let mut microblock_propagation = MicroblockPropagation::new();
 
     let relayer_handle = thread::Builder::new().name("relayer".to_string()).spawn(move || {
         let cost_estimator = config.make_cost_estimator()
@@ -1065,11 +1431,15 @@ fn spawn_miner_relayer(
 
         let mut mem_pool = MemPoolDB::open(is_mainnet, chain_id, &stacks_chainstate_path, cost_estimator, metric)
             .expect("Database failure opening mempool");
+        mem_pool.min_tx_fee_rate = config.mempool.min_tx_fee_rate;
+        let mut last_consistency_hash_height = 0;
+        let mut backup_scheduler = BackupScheduler::new();
 
         while let Ok(mut directive) = relay_channel.recv() {
             match directive {
                 RelayerDirective::HandleNetResult(ref mut net_result) => {
                     debug!("Relayer: Handle network result");
+                    microblock_propagation.note_network_result(net_result, config.miner.microblock_ack_neighbors, get_epoch_time_ms());
                     let net_receipts = relayer
                         .process_network_result(
                             &local_peer,
@@ -1105,6 +1475,9 @@ fn spawn_miner_relayer(
 
                     // synchronize unconfirmed tx index to p2p thread
                     send_unconfirmed_txs(&chainstate, unconfirmed_txs.clone());
+
+                    update_consistency_hash(&chainstate, &sortdb, config.node.consistency_hash_interval, &mut last_consistency_hash_height);
+                    backup_scheduler.update(&config, &sortdb, &coord_comms);
                 }
                 RelayerDirective::ProcessTenure(consensus_hash, burn_hash, block_header_hash) => {
                     debug!(
@@ -1112,7 +1485,7 @@ fn spawn_miner_relayer(
                         &consensus_hash, &block_header_hash, &burn_hash
                     );
                     if let Some(last_mined_blocks_at_burn_hash) =
-                        last_mined_blocks.remove(&burn_hash)
+                        miner.last_mined_blocks.remove(&burn_hash)
                     {
                         for (last_mined_block, microblock_privkey) in
                             last_mined_blocks_at_burn_hash.into_iter()
@@ -1194,7 +1567,7 @@ fn spawn_miner_relayer(
                                         &consensus_hash,
                                         &mined_block.block_hash()
                                     );
-                                    miner_tip = None;
+                                    miner.miner_tip = None;
 
                                 } else {
                                     let ch = snapshot.consensus_hash.clone();
@@ -1211,7 +1584,7 @@ fn spawn_miner_relayer(
                                         "Microblock miner tip is now {}/{} ({})",
                                         &consensus_hash, &block_header_hash, StacksBlockHeader::make_index_block_hash(&consensus_hash, &block_header_hash)
                                     );
-                                    miner_tip = Some((ch, bh, microblock_privkey));
+                                    miner.miner_tip = Some((ch, bh, microblock_privkey));
 
                                     Relayer::refresh_unconfirmed(&mut chainstate, &mut sortdb);
                                     send_unconfirmed_txs(&chainstate, unconfirmed_txs.clone());
@@ -1220,12 +1593,22 @@ fn spawn_miner_relayer(
                                 debug!("Did not win sortition, my blocks [burn_hash= {}, block_hash= {}], their blocks [parent_consenus_hash= {}, burn_hash= {}, block_hash ={}]",
                                   mined_burn_hash, mined_block.block_hash(), parent_consensus_hash, burn_hash, block_header_hash);
 
-                                miner_tip = None;
+                                miner.miner_tip = None;
                             }
                         }
                     }
                 }
                 RelayerDirective::RunTenure(registered_key, last_burn_block, issue_timestamp_ms) => {
+                    if !config.node.miner || !is_miner_running(&miner_control) {
+                        debug!("Relayer: will NOT run tenure since mining is switched off");
+                        continue;
+                    }
+                    if config.burnchain.auto_rbf {
+                        // the run loop asks for a tenure on every pass, so this is how often the
+                        // in-flight block commit gets checked against bitcoind's mempool
+                        let mut op_signer = miner.keychain.generate_op_signer();
+                        bitcoin_controller.auto_rbf_ongoing_block_commit(&mut op_signer);
+                    }
                     if let Some(cur_sortition) = get_last_sortition(&last_sortition) {
                         if last_burn_block.sortition_id != cur_sortition.sortition_id {
                             debug!("Drop stale RunTenure for {}: current sortition is for {}", &last_burn_block.burn_header_hash, &cur_sortition.burn_header_hash);
@@ -1242,14 +1625,34 @@ fn spawn_miner_relayer(
                         .burn_header_hash
                         .clone();
 
+                    if miner.burn_block_arrival.as_ref().map(|(bhh, _)| bhh != &burn_header_hash).unwrap_or(true) {
+                        miner.burn_block_arrival = Some((burn_header_hash.clone(), issue_timestamp_ms));
+                    }
+
                     let mut burn_tenure_snapshot = last_burn_block.clone();
                     if burn_chain_tip == burn_header_hash {
                         // no burnchain change, so only re-run block tenure every so often in order
-                        // to give microblocks a chance to collect
-                        if issue_timestamp_ms < last_tenure_issue_time + (config.node.wait_time_for_microblocks as u128) {
-                            debug!("Relayer: will NOT run tenure since issuance at {} is too fresh (wait until {} + {} = {})",
-                                    issue_timestamp_ms / 1000, last_tenure_issue_time / 1000, config.node.wait_time_for_microblocks / 1000, (last_tenure_issue_time + (config.node.wait_time_for_microblocks as u128)) / 1000);
-                            continue;
+                        // to give microblocks a chance to collect -- unless a better parent showed up
+                        if issue_timestamp_ms < miner.last_tenure_issue_time + (config.node.wait_time_for_microblocks as u128) {
+                            let recommit = config.miner.recommit_on_new_parent
+                                && bitcoin_controller.can_replace_ongoing_block_commit()
+                                && match chainstate.get_stacks_chain_tip(&sortdb) {
+                                    Ok(Some(tip)) => {
+                                        let mined_parents: Vec<_> = miner.last_mined_blocks
+                                            .get(&burn_header_hash)
+                                            .map(|blocks| blocks.iter().map(|(blk, _)| (blk.parent_consensus_hash.clone(), blk.anchored_block.header.parent_block.clone())).collect())
+                                            .unwrap_or_default();
+                                        let arrival_ms = miner.burn_block_arrival.as_ref().map(|(_, ms)| *ms).unwrap_or(issue_timestamp_ms);
+                                        should_recommit_on_new_parent(&mined_parents, &(tip.consensus_hash, tip.anchored_block_hash), arrival_ms, config.miner.recommit_deadline_ms, get_epoch_time_ms())
+                                    }
+                                    _ => false,
+                                };
+                            if !recommit {
+                                debug!("Relayer: will NOT run tenure since issuance at {} is too fresh (wait until {} + {} = {})",
+                                        issue_timestamp_ms / 1000, miner.last_tenure_issue_time / 1000, config.node.wait_time_for_microblocks / 1000, (miner.last_tenure_issue_time + (config.node.wait_time_for_microblocks as u128)) / 1000);
+                                continue;
+                            }
+                            info!("Relayer: Stacks tip changed since the last block-commit in burn block {}; mining on it and replacing the commit", &burn_header_hash);
                         }
                     }
                     else {
@@ -1273,7 +1676,7 @@ fn spawn_miner_relayer(
                     let tenure_begin = get_epoch_time_ms();
                     fault_injection_long_tenure();
 
-                    let mut last_mined_blocks_vec = last_mined_blocks
+                    let mut last_mined_blocks_vec = miner.last_mined_blocks
                         .remove(&burn_header_hash)
                         .unwrap_or_default();
 
@@ -1284,12 +1687,13 @@ fn spawn_miner_relayer(
                         &mut sortdb,
                         &burnchain,
                         burn_tenure_snapshot,
-                        &mut keychain,
+                        &mut miner.keychain,
                         &mut mem_pool,
-                        burn_fee_cap,
+                        config.burnchain.burn_fee_cap,
                         &mut bitcoin_controller,
                         &last_mined_blocks_vec.iter().map(|(blk, _)| blk).collect(),
                         &event_dispatcher,
+                        &microblock_propagation,
                     );
                     if let Some((last_mined_block, microblock_privkey)) = last_mined_block_opt {
                         if last_mined_blocks_vec.len() == 0 {
@@ -1297,22 +1701,28 @@ fn spawn_miner_relayer(
                         }
                         last_mined_blocks_vec.push((last_mined_block, microblock_privkey));
                     }
-                    last_mined_blocks.insert(burn_header_hash, last_mined_blocks_vec);
+                    miner.last_mined_blocks.insert(burn_header_hash, last_mined_blocks_vec);
 
-                    last_tenure_issue_time = get_epoch_time_ms();
-                    debug!("Relayer: RunTenure finished at {} (in {}ms)", last_tenure_issue_time, last_tenure_issue_time.saturating_sub(tenure_begin));
+                    miner.last_tenure_issue_time = get_epoch_time_ms();
+                    debug!("Relayer: RunTenure finished at {} (in {}ms)", miner.last_tenure_issue_time, miner.last_tenure_issue_time.saturating_sub(tenure_begin));
                 }
                 RelayerDirective::RegisterKey(ref last_burn_block) => {
+                    if !is_miner_running(&miner_control) {
+                        continue;
+                    }
                     rotate_vrf_and_register(
                         is_mainnet,
-                        &mut keychain,
+                        &mut miner.keychain,
                         last_burn_block,
                         &mut bitcoin_controller,
                     );
                     counters.bump_blocks_processed();
                 }
                 RelayerDirective::RunMicroblockTenure(burnchain_tip, tenure_issue_ms) => {
-                    if last_microblock_tenure_time > tenure_issue_ms {
+                    if !config.node.miner || !is_miner_running(&miner_control) {
+                        continue;
+                    }
+                    if miner.last_microblock_tenure_time > tenure_issue_ms {
                         // stale request
                         continue;
                     }
@@ -1327,22 +1737,22 @@ fn spawn_miner_relayer(
 
                     // unconfirmed state must be consistent with the chain tip, as must the
                     // microblock mining state.
-                    if let Some((ch, bh, mblock_pkey)) = miner_tip.clone() {
-                        if let Some(miner_state) = microblock_miner_state.take() {
+                    if let Some((ch, bh, mblock_pkey)) = miner.miner_tip.clone() {
+                        if let Some(miner_state) = miner.microblock_miner_state.take() {
                             if miner_state.parent_consensus_hash == ch || miner_state.parent_block_hash == bh {
                                 // preserve -- chaintip is unchanged
-                                microblock_miner_state = Some(miner_state);
+                                miner.microblock_miner_state = Some(miner_state);
                             }
                             else {
                                 debug!("Relayer: reset microblock miner state");
-                                microblock_miner_state = None;
+                                miner.microblock_miner_state = None;
                                 counters.set_microblocks_processed(0);
                             }
                         }
 
                         run_microblock_tenure(
                             &config,
-                            &mut microblock_miner_state,
+                            &mut miner.microblock_miner_state,
                             &mut chainstate,
                             &mut sortdb,
                             &mut mem_pool,
@@ -1350,18 +1760,139 @@ fn spawn_miner_relayer(
                             (ch, bh, mblock_pkey),
                             &counters,
                             &event_dispatcher,
+                            &mut microblock_propagation,
                         );
 
                         // synchronize unconfirmed tx index to p2p thread
                         send_unconfirmed_txs(&chainstate, unconfirmed_txs.clone());
-                        last_microblock_tenure_time = get_epoch_time_ms();
+                        miner.last_microblock_tenure_time = get_epoch_time_ms();
                     }
                     else {
                         debug!("Relayer: reset unconfirmed state to 0 microblocks");
                         counters.set_microblocks_processed(0);
-                        microblock_miner_state = None;
+                        miner.microblock_miner_state = None;
                     }
                 }
+                RelayerDirective::BurnchainOps(command, reply) => {
+                    let result = match command {
+                        BurnchainOpsCommand::List => Ok(bitcoin_controller.get_pending_ops()),
+                        BurnchainOpsCommand::Abandon => Ok(bitcoin_controller
+                            .abandon_ongoing_block_commit()
+                            .into_iter()
+                            .collect()),
+                        BurnchainOpsCommand::FeeBump(fee_rate) => {
+                            let mut op_signer = miner.keychain.generate_op_signer();
+                            bitcoin_controller
+                                .bump_ongoing_block_commit_fee(fee_rate, &mut op_signer)
+                                .map(|op| vec![op])
+                        }
+                    };
+                    let _ = reply.send(result);
+                }
+                RelayerDirective::UpdateConfig(patch, reply) => {
+                    let mut patched = config.clone();
+                    let result = patched.apply_dynamic_patch(&patch).and_then(|changed| {
+                        if patched.node.miner && !started_as_miner && changed.iter().any(|key| key == "node.miner") {
+                            return Err("node.miner cannot be switched on, since this node did not start as a miner".to_string());
+                        }
+                        config = patched;
+                        Ok(changed)
+                    });
+                    if let Ok(ref changed) = result {
+                        if changed.len() > 0 {
+                            info!("Relayer: config updated through the admin API"; "changed" => %changed.join(", "));
+                            bitcoin_controller.set_config(config.clone());
+                        }
+                        if changed.iter().any(|key| key == "mempool.min_tx_fee_rate") {
+                            mem_pool.min_tx_fee_rate = config.mempool.min_tx_fee_rate;
+                            if let Err(e) = relayer.set_min_tx_fee_rate(config.mempool.min_tx_fee_rate) {
+                                warn!("Relayer: failed to announce the new minimum fee rate: {:?}", &e);
+                            }
+                        }
+                    }
+                    let _ = reply.send(result);
+                }
+                RelayerDirective::MinerStats(count, reply) => {
+                    let result = if !started_as_miner {
+                        Err("This node did not start as a miner".to_string())
+                    } else {
+                        let miner = miner_control.lock().expect("FATAL: miner control mutex poisoned").burnchain_signer.clone();
+                        miner_stats::miner_stats(&sortdb, &miner, &bitcoin_controller.get_submitted_block_commits(), count)
+                    };
+                    let _ = reply.send(result);
+                }
+                RelayerDirective::ControlMiner(command, reply) => {
+                    let result = match command {
+                        MinerCommand::Status => Ok(()),
+                        MinerCommand::Stop => {
+                            info!("Relayer: miner stopped through the admin API");
+                            miner = MinerSubsystem::new(miner.keychain);
+                            counters.set_microblocks_processed(0);
+                            miner_control.lock().expect("FATAL: miner control mutex poisoned").running = false;
+                            Ok(())
+                        }
+                        MinerCommand::Start(_) if !started_as_miner => {
+                            Err("The miner cannot be started, since this node did not start as a miner".to_string())
+                        }
+                        MinerCommand::Start(Some(_)) if config.node.mock_mining => {
+                            Err("The miner's seed cannot be changed while node.mock_mining is set".to_string())
+                        }
+                        MinerCommand::Start(seed) => {
+                            let keychain = match seed {
+                                Some(seed) => {
+                                    let mut keychain = Keychain::default(seed);
+                                    if let Some(ref microblock_seed) = config.node.microblock_seed {
+                                        keychain.set_microblock_seed(microblock_seed);
+                                    }
+                                    keychain
+                                }
+                                None => miner.keychain,
+                            };
+                            miner = MinerSubsystem::new(keychain);
+                            counters.set_microblocks_processed(0);
+
+                            let mut control = miner_control.lock().expect("FATAL: miner control mutex poisoned");
+                            control.running = true;
+                            control.generation += 1;
+                            control.key_rotations = 0;
+                            control.burnchain_signer = miner.keychain.get_burnchain_signer();
+                            info!("Relayer: miner restarted through the admin API";
+                                  "generation" => control.generation,
+                                  "burnchain_address" => %control.burnchain_signer.to_bitcoin_address(config.burnchain.get_bitcoin_network().1));
+                            Ok(())
+                        }
+                        MinerCommand::RotateKey if !started_as_miner => {
+                            Err("The VRF key cannot be rotated, since this node did not start as a miner".to_string())
+                        }
+                        MinerCommand::RotateKey if config.node.mock_mining => {
+                            Err("A mock miner has no registered VRF key to rotate".to_string())
+                        }
+                        MinerCommand::RotateKey => {
+                            let mut control = miner_control.lock().expect("FATAL: miner control mutex poisoned");
+                            if control.running {
+                                // the run loop sends the registration, since it tracks the key in use
+                                control.key_rotations += 1;
+                                info!("Relayer: VRF key rotation requested through the admin API";
+                                      "key_rotations" => control.key_rotations);
+                                Ok(())
+                            } else {
+                                Err("The VRF key cannot be rotated while the miner is stopped".to_string())
+                            }
+                        }
+                    };
+                    let _ = reply.send(result.map(|_| {
+                        let control = miner_control.lock().expect("FATAL: miner control mutex poisoned");
+                        MinerStatus {
+                            running: control.running,
+                            generation: control.generation,
+                            burnchain_address: control
+                                .burnchain_signer
+                                .to_bitcoin_address(config.burnchain.get_bitcoin_network().1),
+                            stacks_address: miner.keychain.get_address(is_mainnet).to_string(),
+                            key_rotations: control.key_rotations,
+                        }
+                    }));
+                }
                 RelayerDirective::Exit => break
             }
         }
@@ -1375,6 +1906,8 @@ enum LeaderKeyRegistrationState {
     Inactive,
     Pending,
     Active(RegisteredKey),
+    /// Still mining with this key, while the registration of its replacement waits to be mined
+    Rotating(RegisteredKey),
 }
 
 impl StacksNode {
@@ -1387,23 +1920,17 @@ impl StacksNode {
         let config = runloop.config().clone();
         let miner = runloop.is_miner();
         let burnchain = runloop.get_burnchain();
-        let atlas_config = AtlasConfig::default(config.is_mainnet());
+        let atlas_config = config.make_atlas_config();
         let mut keychain = Keychain::default(config.node.seed.clone());
+        if let Some(ref microblock_seed) = config.node.microblock_seed {
+            keychain.set_microblock_seed(microblock_seed);
+        }
 
         // we can call _open_ here rather than _connect_, since connect is first called in
         //   make_genesis_block
         let mut sortdb = SortitionDB::open(&config.get_burn_db_file_path(), true)
             .expect("Error while instantiating sortition db");
 
-        let epochs = SortitionDB::get_stacks_epochs(sortdb.conn())
-            .expect("Error while loading stacks epochs");
-
-        let view = {
-            let sortition_tip = SortitionDB::get_canonical_burn_chain_tip(&sortdb.conn())
-                .expect("Failed to get sortition tip");
-            SortitionDB::get_burnchain_view(&sortdb.conn(), &burnchain, &sortition_tip).unwrap()
-        };
-
         if let Some(ast_precheck_size_height) = config.burnchain.ast_precheck_size_height {
             info!(
                 "Override burnchain height of {:?} to {}",
@@ -1423,18 +1950,6 @@ impl StacksNode {
                 .expect("FATAL: failed to commit sortition DB transaction");
         }
 
-        // create a new peerdb
-        let data_url = UrlString::try_from(format!("{}", &config.node.data_url)).unwrap();
-        let initial_neighbors = config.node.bootstrap_node.clone();
-        if initial_neighbors.len() > 0 {
-            info!(
-                "Will bootstrap from peers {}",
-                VecDisplay(&initial_neighbors)
-            );
-        } else {
-            warn!("Without a peer to bootstrap from, the node will start mining a new chain");
-        }
-
         let p2p_sock: SocketAddr = config.node.p2p_bind.parse().expect(&format!(
             "Failed to parse socket: {}",
             &config.node.p2p_bind
@@ -1443,138 +1958,14 @@ impl StacksNode {
             "Failed to parse socket: {}",
             &config.node.rpc_bind
         ));
-        let p2p_addr: SocketAddr = config.node.p2p_address.parse().expect(&format!(
-            "Failed to parse socket: {}",
-            &config.node.p2p_address
-        ));
-        let node_privkey = {
-            let mut re_hashed_seed = config.node.local_peer_seed.clone();
-            let my_private_key = loop {
-                match Secp256k1PrivateKey::from_slice(&re_hashed_seed[..]) {
-                    Ok(sk) => break sk,
-                    Err(_) => {
-                        re_hashed_seed = Sha256Sum::from_data(&re_hashed_seed[..])
-                            .as_bytes()
-                            .to_vec()
-                    }
-                }
-            };
-            my_private_key
-        };
-
-        let mut peerdb = PeerDB::connect(
-            &config.get_peer_db_file_path(),
-            true,
-            config.burnchain.chain_id,
-            burnchain.network_id,
-            Some(node_privkey),
-            config.connection_options.private_key_lifetime.clone(),
-            PeerAddress::from_socketaddr(&p2p_addr),
-            p2p_sock.port(),
-            data_url,
-            &vec![],
-            Some(&initial_neighbors),
-        )
-        .map_err(|e| {
-            eprintln!(
-                "Failed to open {}: {:?}",
-                &config.get_peer_db_file_path(),
-                &e
-            );
-            panic!();
-        })
-        .unwrap();
-
-        {
-            // bootstrap nodes *always* allowed
-            let mut tx = peerdb.tx_begin().unwrap();
-            for initial_neighbor in initial_neighbors.iter() {
-                // update peer in case public key changed
-                PeerDB::update_peer(&mut tx, &initial_neighbor).unwrap();
-                PeerDB::set_allow_peer(
-                    &mut tx,
-                    initial_neighbor.addr.network_id,
-                    &initial_neighbor.addr.addrbytes,
-                    initial_neighbor.addr.port,
-                    -1,
-                )
-                .unwrap();
-            }
-            tx.commit().unwrap();
-        }
-
-        if !config.node.deny_nodes.is_empty() {
-            warn!("Will ignore nodes {:?}", &config.node.deny_nodes);
-        }
-
-        {
-            let mut tx = peerdb.tx_begin().unwrap();
-            for denied in config.node.deny_nodes.iter() {
-                PeerDB::set_deny_peer(
-                    &mut tx,
-                    denied.addr.network_id,
-                    &denied.addr.addrbytes,
-                    denied.addr.port,
-                    get_epoch_time_secs() + 24 * 365 * 3600,
-                )
-                .unwrap();
-            }
-            tx.commit().unwrap();
-        }
-
-        // update services to indicate we can support mempool sync
-        {
-            let mut tx = peerdb.tx_begin().unwrap();
-            PeerDB::set_local_services(
-                &mut tx,
-                (ServiceFlags::RPC as u16) | (ServiceFlags::RELAY as u16),
-            )
-            .unwrap();
-            tx.commit().unwrap();
-        }
-
-        let atlasdb =
-            AtlasDB::connect(atlas_config.clone(), &config.get_atlas_db_file_path(), true).unwrap();
-
-        let local_peer = match PeerDB::get_local_peer(peerdb.conn()) {
-            Ok(local_peer) => local_peer,
-            _ => panic!("Unable to retrieve local peer"),
-        };
-
-        // force early mempool instantiation
-        let cost_estimator = config
-            .make_cost_estimator()
-            .unwrap_or_else(|| Box::new(UnitEstimator));
-        let metric = config
-            .make_cost_metric()
-            .unwrap_or_else(|| Box::new(UnitMetric));
-
-        let _ = MemPoolDB::open(
-            config.is_mainnet(),
-            config.burnchain.chain_id,
-            &config.get_chainstate_path_str(),
-            cost_estimator,
-            metric,
-        )
-        .expect("BUG: failed to instantiate mempool");
 
         // now we're ready to instantiate a p2p network object, the relayer, and the event dispatcher
-        let mut p2p_net = PeerNetwork::new(
-            peerdb,
-            atlasdb,
-            local_peer.clone(),
-            config.burnchain.peer_version,
-            burnchain.clone(),
-            view,
-            config.connection_options.clone(),
-            epochs,
-        );
+        let mut p2p_net = make_peer_network(&config, &burnchain, &sortdb, &atlas_config);
+        let local_peer = p2p_net.local_peer.clone();
 
         // setup the relayer channel
         let (relay_send, relay_recv) = sync_channel(RELAYER_MAX_BUFFER);
 
-        let last_sortition = Arc::new(Mutex::new(last_burn_block));
-
         let burnchain_signer = keychain.get_burnchain_signer();
         match monitoring::set_burnchain_signer(burnchain_signer.clone()) {
             Err(e) => {
@@ -1583,6 +1974,76 @@ impl StacksNode {
             _ => {}
         }
 
+        let miner_control = Arc::new(Mutex::new(MinerControl {
+            running: true,
+            generation: 0,
+            burnchain_signer: burnchain_signer.clone(),
+            key_rotations: 0,
+        }));
+
+        if let Some(admin_bind) = config.node.admin_bind.clone() {
+            let admin_relay_channel = relay_send.clone();
+            let miner_relay_channel = relay_send.clone();
+            let stats_relay_channel = relay_send.clone();
+            let config_relay_channel = relay_send.clone();
+            let proposal_config = config.clone();
+            let api = AdminApi {
+                auth_token: config.node.admin_auth_token.clone(),
+                allow_remote: config.node.admin_allow_remote,
+                profiling: config.node.profiling,
+                burnchain_ops: Arc::new(move |command| {
+                    let (reply_send, reply_recv) = channel();
+                    admin_relay_channel
+                        .send(RelayerDirective::BurnchainOps(command, reply_send))
+                        .map_err(|_| "The relayer thread has exited".to_string())?;
+                    reply_recv
+                        .recv()
+                        .map_err(|_| "The relayer thread has exited".to_string())?
+                }),
+                config_patch: Arc::new(move |patch| {
+                    let (reply_send, reply_recv) = channel();
+                    config_relay_channel
+                        .send(RelayerDirective::UpdateConfig(patch, reply_send))
+                        .map_err(|_| "The relayer thread has exited".to_string())?;
+                    reply_recv
+                        .recv()
+                        .map_err(|_| "The relayer thread has exited".to_string())?
+                }),
+                block_proposal: Arc::new(move |proposal| {
+                    validate_block_proposal(&proposal_config, proposal)
+                }),
+                miner_control: Arc::new(move |command| {
+                    let (reply_send, reply_recv) = channel();
+                    miner_relay_channel
+                        .send(RelayerDirective::ControlMiner(command, reply_send))
+                        .map_err(|_| "The relayer thread has exited".to_string())?;
+                    reply_recv
+                        .recv()
+                        .map_err(|_| "The relayer thread has exited".to_string())?
+                }),
+                miner_stats: Arc::new(move |count| {
+                    let (reply_send, reply_recv) = channel();
+                    stats_relay_channel
+                        .send(RelayerDirective::MinerStats(count, reply_send))
+                        .map_err(|_| "The relayer thread has exited".to_string())?;
+                    reply_recv
+                        .recv()
+                        .map_err(|_| "The relayer thread has exited".to_string())?
+                }),
+                backup_dir: config
+                    .node
+                    .backup
+                    .as_ref()
+                    .map(|_| config.get_backup_path()),
+            };
+            thread::Builder::new()
+                .name("admin".to_string())
+                .spawn(move || start_serving_admin_api(admin_bind, api))
+                .expect("FATAL: failed to start admin API thread");
+        }
+
+        let last_sortition = Arc::new(Mutex::new(last_burn_block));
+
         let relayer = Relayer::from_p2p(&mut p2p_net);
         let shared_unconfirmed_txs = Arc::new(Mutex::new(UnconfirmedTxMap::new()));
 
@@ -1605,6 +2066,7 @@ impl StacksNode {
             keychain,
             relay_recv,
             last_sortition.clone(),
+            miner_control.clone(),
             coord_comms,
             shared_unconfirmed_txs.clone(),
         )
@@ -1626,13 +2088,28 @@ impl StacksNode {
         info!("Start P2P server on: {}", &config.node.p2p_bind);
 
         let is_miner = miner;
+        if is_miner && config.miner.standby {
+            info!(
+                "Miner: starting as standby; will take over after {} burn blocks without a block-commit from the active miner",
+                config.miner.standby_takeover_burn_blocks
+            );
+        }
+        let miner_failover = MinerFailover::new(
+            is_miner && config.miner.standby,
+            config.miner.standby_takeover_burn_blocks,
+        );
 
         StacksNode {
             config,
             relay_channel: relay_send,
             last_sortition,
             burnchain_signer,
+            miner_control,
+            miner_generation: 0,
+            miner_key_rotations: 0,
+            key_rotation_requested: false,
             is_miner,
+            miner_failover,
             atlas_config,
             leader_key_registration_state,
             p2p_thread_handle,
@@ -1640,6 +2117,57 @@ impl StacksNode {
         }
     }
 
+    /// Pick up a miner restart from the admin API: a restarted miner may have a new keychain, so
+    /// it needs a new VRF key registered under its new burnchain signer.
+    /// Returns whether or not the miner is running.
+    fn sync_miner_control(&mut self) -> bool {
+        let control = self
+            .miner_control
+            .lock()
+            .expect("FATAL: miner control mutex poisoned")
+            .clone();
+        if control.generation != self.miner_generation {
+            self.miner_generation = control.generation;
+            if control.burnchain_signer != self.burnchain_signer {
+                // the global signer can only be set once, so monitoring keeps the old one
+                warn!("Miner: restarted with a new burnchain signer";
+                      "generation" => control.generation);
+            }
+            self.burnchain_signer = control.burnchain_signer;
+            self.miner_key_rotations = control.key_rotations;
+            self.key_rotation_requested = false;
+            if !self.config.node.mock_mining {
+                self.leader_key_registration_state = LeaderKeyRegistrationState::Inactive;
+            }
+        } else if control.key_rotations != self.miner_key_rotations {
+            self.miner_key_rotations = control.key_rotations;
+            self.key_rotation_requested = true;
+        }
+        control.running
+    }
+
+    /// Register a new VRF key, but keep mining with the current one until the registration is
+    /// mined, so that rotating the key costs no tenures.
+    fn start_key_rotation(&mut self, burnchain_tip: &BlockSnapshot) -> bool {
+        let current_key = match self.leader_key_registration_state {
+            LeaderKeyRegistrationState::Active(ref key)
+            | LeaderKeyRegistrationState::Rotating(ref key) => key.clone(),
+            LeaderKeyRegistrationState::Inactive | LeaderKeyRegistrationState::Pending => {
+                // a new key is on its way anyway
+                debug!("Tenure: VRF key rotation requested while no key is active");
+                return true;
+            }
+        };
+        info!(
+            "Tenure: registering a new VRF key; still mining with {}",
+            &current_key.vrf_public_key.to_hex()
+        );
+        self.leader_key_registration_state = LeaderKeyRegistrationState::Rotating(current_key);
+        self.relay_channel
+            .send(RelayerDirective::RegisterKey(burnchain_tip.clone()))
+            .is_ok()
+    }
+
     /// Tell the relayer to fire off a tenure and a block commit op,
     /// if it is time to do so.
     pub fn relayer_issue_tenure(&mut self) -> bool {
@@ -1648,9 +2176,26 @@ impl StacksNode {
             return true;
         }
 
+        if self.miner_failover.is_standby() {
+            // the active miner is still committing
+            return true;
+        }
+
+        if !self.sync_miner_control() {
+            // the miner was stopped through the admin API
+            return true;
+        }
+
         if let Some(burnchain_tip) = get_last_sortition(&self.last_sortition) {
+            if self.key_rotation_requested {
+                self.key_rotation_requested = false;
+                if !self.start_key_rotation(&burnchain_tip) {
+                    return false;
+                }
+            }
             match self.leader_key_registration_state {
-                LeaderKeyRegistrationState::Active(ref key) => {
+                LeaderKeyRegistrationState::Active(ref key)
+                | LeaderKeyRegistrationState::Rotating(ref key) => {
                     debug!(
                         "Tenure: Using key {:?} off of {}",
                         &key.vrf_public_key, &burnchain_tip.burn_header_hash
@@ -1831,6 +2376,7 @@ impl StacksNode {
         bitcoin_controller: &mut BitcoinRegtestController,
         last_mined_blocks: &Vec<&AssembledAnchorBlock>,
         event_dispatcher: &EventDispatcher,
+        microblock_propagation: &MicroblockPropagation,
     ) -> Option<(AssembledAnchorBlock, Secp256k1PrivateKey)> {
         let MiningTenureInformation {
             mut stacks_parent_header,
@@ -2083,6 +2629,29 @@ impl StacksNode {
             stacks_parent_header.microblock_tail =
                 microblocks.last().clone().map(|blk| blk.header.clone());
 
+            if config.miner.adaptive_microblock_confirmation {
+                // leave out the microblocks that the network has likely not seen yet
+                let confirmation = microblock_propagation.choose_confirmed_microblocks(
+                    microblocks,
+                    config.miner.max_microblock_confirmation_delay_ms,
+                    get_epoch_time_ms(),
+                );
+                info!(
+                    "Miner: decided how much of the parent microblock stream to confirm";
+                    "parent_consensus_hash" => %parent_consensus_hash,
+                    "parent_block_hash" => %stacks_parent_header.anchored_header.block_hash(),
+                    "num_available" => confirmation.num_available,
+                    "num_confirmed" => confirmation.num_confirmed,
+                    "ack_latency_ms" => ?confirmation.ack_latency_ms,
+                    "num_ack_samples" => confirmation.num_ack_samples,
+                    "required_age_ms" => confirmation.required_age_ms
+                );
+                stacks_parent_header.microblock_tail = confirmation
+                    .num_confirmed
+                    .checked_sub(1)
+                    .map(|i| microblocks[i].header.clone());
+            }
+
             if let Some(poison_payload) = poison_opt {
                 let poison_microblock_tx = inner_generate_poison_microblock_tx(
                     keychain,
@@ -2193,6 +2762,7 @@ impl StacksNode {
             anchored_block.txs.len(),
             attempt
         );
+        monitoring::log_tenure_block_assembled(&burn_block.burn_header_hash);
 
         // let's figure out the recipient set!
         let recipients = match get_next_recipients(
@@ -2212,6 +2782,12 @@ impl StacksNode {
         let sunset_burn = burnchain.expected_sunset_burn(burn_block.block_height + 1, burn_fee_cap);
         let rest_commit = burn_fee_cap - sunset_burn;
 
+        // The commit outputs are dictated by consensus, so they cannot be taken from the miner's
+        // config: `LeaderBlockCommitOp::check_pox` rejects any commit whose outputs are not
+        // exactly the next reward-set recipients (or burns, in the prepare phase and when
+        // there is no reward set).  Paying some other, configured, reward address would just
+        // throw the commit away.  The outputs are also `StacksAddress`es, which in this epoch
+        // can only encode legacy p2pkh and p2sh bitcoin addresses, not native segwit ones.
         let commit_outs = if burn_block.block_height + 1 < burnchain.pox_constants.sunset_end
             && !burnchain.is_in_prepare_phase(burn_block.block_height + 1)
         {
@@ -2296,6 +2872,13 @@ impl StacksNode {
             } else {
                 debug!("Mock-mining enabled; not sending Bitcoin transaction");
             }
+        } else if let Some(utxos_selected_ms) =
+            bitcoin_controller.get_last_commit_utxo_selection_time()
+        {
+            monitoring::log_tenure_commit_broadcast(
+                &burn_block.burn_header_hash,
+                utxos_selected_ms as u64,
+            );
         }
 
         Some((
@@ -2309,6 +2892,29 @@ impl StacksNode {
         ))
     }
 
+    /// Whether this node sent the block-commit `txid`, according to its commit journal.  If the
+    /// journal cannot be read, the block-commit is assumed to be this node's, so that a broken
+    /// journal does not stop the miner.
+    fn sent_block_commit(&self, txid: &Txid) -> bool {
+        let path = self.config.get_commit_journal_path();
+        if !Path::new(&path).exists() {
+            // this node has not sent anything
+            return false;
+        }
+        match CommitJournal::open_readonly(&path)
+            .and_then(|journal| journal.contains(&txid.to_hex()))
+        {
+            Ok(sent) => sent,
+            Err(e) => {
+                warn!(
+                    "Failed to look up block-commit {} in the commit journal: {}",
+                    txid, e
+                );
+                true
+            }
+        }
+    }
+
     /// Process a state coming from the burnchain, by extracting the validated KeyRegisterOp
     /// and inspecting if a sortition was won.
     /// `ibd`: boolean indicating whether or not we are in the initial block download
@@ -2335,6 +2941,52 @@ impl StacksNode {
 
         let (_, network) = self.config.burnchain.get_bitcoin_network();
 
+        self.sync_miner_control();
+
+        let active_miner_committed = block_commits
+            .iter()
+            .any(|op| op.apparent_sender == self.burnchain_signer);
+        if self.is_miner && !ibd {
+            // the commits in this burn block decide the sortition for the tenure on its parent
+            let won_sortition = block_commits.iter().any(|op| {
+                op.apparent_sender == self.burnchain_signer
+                    && op.txid == block_snapshot.winning_block_txid
+            });
+            monitoring::log_tenure_sortition(
+                &block_snapshot.parent_burn_header_hash,
+                active_miner_committed,
+                won_sortition,
+            );
+            monitoring::log_tenure_burn_block(&block_snapshot.burn_header_hash, block_height);
+        }
+        // while catching up, the other node's liveness is unknown
+        if self.is_miner && !ibd {
+            let signer_commits: Vec<_> = block_commits
+                .iter()
+                .filter(|op| op.apparent_sender == self.burnchain_signer)
+                .map(|op| SignerCommit {
+                    vtxindex: op.vtxindex,
+                    sent_by_us: self.sent_block_commit(&op.txid),
+                })
+                .collect();
+            match self.miner_failover.observe_burn_block(&signer_commits) {
+                Some(FailoverEvent::TookOver(missed_burn_blocks)) => {
+                    warn!(
+                        "Miner: no block-commit from the active miner for {} burn blocks; taking over as the active miner",
+                        missed_burn_blocks;
+                        "burn_block_height" => block_height
+                    );
+                }
+                Some(FailoverEvent::StoodDown) => {
+                    warn!(
+                        "Miner: another node sent a block-commit with this miner's key; standing down as its standby";
+                        "burn_block_height" => block_height
+                    );
+                }
+                None => {}
+            }
+        }
+
         for op in block_commits.into_iter() {
             if op.txid == block_snapshot.winning_block_txid {
                 info!(
@@ -2376,14 +3028,28 @@ impl StacksNode {
                 if !ibd {
                     // not in initial block download, so we're not just replaying an old key.
                     // Registered key has been mined
-                    if let LeaderKeyRegistrationState::Pending = self.leader_key_registration_state
-                    {
-                        self.leader_key_registration_state =
-                            LeaderKeyRegistrationState::Active(RegisteredKey {
-                                vrf_public_key: op.public_key,
-                                block_height: op.block_height as u64,
-                                op_vtxindex: op.vtxindex as u32,
-                            });
+                    let registered_key = RegisteredKey {
+                        vrf_public_key: op.public_key,
+                        block_height: op.block_height as u64,
+                        op_vtxindex: op.vtxindex as u32,
+                    };
+                    match self.leader_key_registration_state {
+                        LeaderKeyRegistrationState::Pending => {
+                            self.leader_key_registration_state =
+                                LeaderKeyRegistrationState::Active(registered_key);
+                        }
+                        LeaderKeyRegistrationState::Rotating(ref old_key)
+                            if old_key.vrf_public_key != registered_key.vrf_public_key =>
+                        {
+                            info!(
+                                "Tenure: rotated VRF key from {} to {}",
+                                &old_key.vrf_public_key.to_hex(),
+                                &registered_key.vrf_public_key.to_hex()
+                            );
+                            self.leader_key_registration_state =
+                                LeaderKeyRegistrationState::Active(registered_key);
+                        }
+                        _ => {}
                     }
                 }
             }
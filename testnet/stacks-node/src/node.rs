@@ -96,10 +96,10 @@ pub struct Node {
 }
 
 pub fn get_account_lockups(
-    use_test_chainstate_data: bool,
+    genesis_data: &stx_genesis::GenesisData,
 ) -> Box<dyn Iterator<Item = ChainstateAccountLockup>> {
     Box::new(
-        stx_genesis::GenesisData::new(use_test_chainstate_data)
+        genesis_data
             .read_lockups()
             .map(|item| ChainstateAccountLockup {
                 address: item.address,
@@ -110,10 +110,10 @@ pub fn get_account_lockups(
 }
 
 pub fn get_account_balances(
-    use_test_chainstate_data: bool,
+    genesis_data: &stx_genesis::GenesisData,
 ) -> Box<dyn Iterator<Item = ChainstateAccountBalance>> {
     Box::new(
-        stx_genesis::GenesisData::new(use_test_chainstate_data)
+        genesis_data
             .read_balances()
             .map(|item| ChainstateAccountBalance {
                 address: item.address,
@@ -123,10 +123,10 @@ pub fn get_account_balances(
 }
 
 pub fn get_namespaces(
-    use_test_chainstate_data: bool,
+    genesis_data: &stx_genesis::GenesisData,
 ) -> Box<dyn Iterator<Item = ChainstateBNSNamespace>> {
     Box::new(
-        stx_genesis::GenesisData::new(use_test_chainstate_data)
+        genesis_data
             .read_namespaces()
             .map(|item| ChainstateBNSNamespace {
                 namespace_id: item.namespace_id,
@@ -141,16 +141,14 @@ pub fn get_namespaces(
     )
 }
 
-pub fn get_names(use_test_chainstate_data: bool) -> Box<dyn Iterator<Item = ChainstateBNSName>> {
-    Box::new(
-        stx_genesis::GenesisData::new(use_test_chainstate_data)
-            .read_names()
-            .map(|item| ChainstateBNSName {
-                fully_qualified_name: item.fully_qualified_name,
-                owner: item.owner,
-                zonefile_hash: item.zonefile_hash,
-            }),
-    )
+pub fn get_names(
+    genesis_data: &stx_genesis::GenesisData,
+) -> Box<dyn Iterator<Item = ChainstateBNSName>> {
+    Box::new(genesis_data.read_names().map(|item| ChainstateBNSName {
+        fully_qualified_name: item.fully_qualified_name,
+        owner: item.owner,
+        zonefile_hash: item.zonefile_hash,
+    }))
 }
 
 fn spawn_peer(
@@ -274,6 +272,16 @@ pub fn use_test_genesis_chainstate(config: &Config) -> bool {
     }
 }
 
+/// The genesis data to boot the chain with: the configured genesis chainstate file if any, or
+/// else the built-in (test or production) data.
+pub fn get_genesis_data(config: &Config, use_test_genesis_data: bool) -> stx_genesis::GenesisData {
+    match config.node.genesis_chainstate_path {
+        Some(ref path) => stx_genesis::GenesisData::from_chainstate_file(path)
+            .expect("FATAL: failed to load genesis chainstate file"),
+        None => stx_genesis::GenesisData::new(use_test_genesis_data),
+    }
+}
+
 impl Node {
     /// Instantiate and initialize a new node, given a config
     pub fn new(
@@ -286,8 +294,12 @@ impl Node {
         } else {
             USE_TEST_GENESIS_CHAINSTATE
         };
+        let genesis_data = get_genesis_data(&config, use_test_genesis_data);
 
-        let keychain = Keychain::default(config.node.seed.clone());
+        let mut keychain = Keychain::default(config.node.seed.clone());
+        if let Some(ref microblock_seed) = config.node.microblock_seed {
+            keychain.set_microblock_seed(microblock_seed);
+        }
 
         let initial_balances = config
             .initial_balances
@@ -307,16 +319,19 @@ impl Node {
             first_burnchain_block_timestamp: 0,
             pox_constants,
             post_flight_callback: Some(boot_block_exec),
-            get_bulk_initial_lockups: Some(Box::new(move || {
-                get_account_lockups(use_test_genesis_data)
+            get_bulk_initial_lockups: Some(Box::new({
+                let genesis_data = genesis_data.clone();
+                move || get_account_lockups(&genesis_data)
             })),
-            get_bulk_initial_balances: Some(Box::new(move || {
-                get_account_balances(use_test_genesis_data)
+            get_bulk_initial_balances: Some(Box::new({
+                let genesis_data = genesis_data.clone();
+                move || get_account_balances(&genesis_data)
             })),
-            get_bulk_initial_namespaces: Some(Box::new(move || {
-                get_namespaces(use_test_genesis_data)
+            get_bulk_initial_namespaces: Some(Box::new({
+                let genesis_data = genesis_data.clone();
+                move || get_namespaces(&genesis_data)
             })),
-            get_bulk_initial_names: Some(Box::new(move || get_names(use_test_genesis_data))),
+            get_bulk_initial_names: Some(Box::new(move || get_names(&genesis_data))),
         };
 
         let chain_state_result = StacksChainState::open_and_exec(
@@ -378,7 +393,10 @@ impl Node {
     ) -> Node {
         let burnchain_tip = burnchain_controller.get_chain_tip();
 
-        let keychain = Keychain::default(config.node.seed.clone());
+        let mut keychain = Keychain::default(config.node.seed.clone());
+        if let Some(ref microblock_seed) = config.node.microblock_seed {
+            keychain.set_microblock_seed(microblock_seed);
+        }
 
         let mut event_dispatcher = EventDispatcher::new();
 
@@ -518,7 +536,7 @@ impl Node {
             }
             tx.commit().unwrap();
         }
-        let atlas_config = AtlasConfig::default(false);
+        let atlas_config = self.config.make_atlas_config();
         let atlasdb =
             AtlasDB::connect(atlas_config, &self.config.get_atlas_db_file_path(), true).unwrap();
 
@@ -550,7 +568,7 @@ impl Node {
             self.config.get_chainstate_path_str(),
             event_dispatcher,
             exit_at_block_height,
-            Sha256Sum::from_hex(stx_genesis::GENESIS_CHAINSTATE_HASH).unwrap(),
+            self.config.get_genesis_chainstate_hash(),
             1000,
             attachments_rx,
             self.config.clone(),
@@ -648,6 +666,49 @@ impl Node {
     /// on an isolated thread and discarded or canceled without corrupting the
     /// chain state of the node.
     pub fn initiate_new_tenure(&mut self) -> Option<Tenure> {
+        let block_to_build_upon = match &self.last_sortitioned_block {
+            None => unreachable!(),
+            Some(block) => block.clone(),
+        };
+
+        // Get the stack's chain tip
+        let chain_tip = match self.bootstraping_chain {
+            true => ChainTip::genesis(&BurnchainHeaderHash::zero(), 0, 0),
+            false => match &self.chain_tip {
+                Some(chain_tip) => chain_tip.clone(),
+                None => unreachable!(),
+            },
+        };
+
+        self.make_tenure(chain_tip, block_to_build_upon)
+    }
+
+    /// Constructs a tenure that builds on top of `parent`, a Stacks block this node processed
+    /// and that was selected in the sortition `parent_sortition`. If `parent` is not the
+    /// node's current chain tip, running this tenure creates a fork.
+    pub fn initiate_tenure_at(
+        &mut self,
+        parent: &ChainTip,
+        parent_sortition: &BurnchainTip,
+        burn_dbconn: &dyn BurnStateDB,
+    ) -> Option<Tenure> {
+        // The coinbase has to use the miner's nonce as of the parent block.
+        let principal = self.get_address().into();
+        let account = self.chain_state.with_read_only_clarity_tx(
+            burn_dbconn,
+            &parent.metadata.index_block_hash(),
+            |conn| StacksChainState::get_account(conn, &principal),
+        )?;
+        self.nonce = account.nonce;
+
+        self.make_tenure(parent.clone(), parent_sortition.clone())
+    }
+
+    fn make_tenure(
+        &mut self,
+        chain_tip: ChainTip,
+        parent_sortition: BurnchainTip,
+    ) -> Option<Tenure> {
         // Get the latest registered key
         let registered_key = match &self.active_registered_key {
             None => {
@@ -658,17 +719,18 @@ impl Node {
             Some(ref key) => key,
         };
 
-        let block_to_build_upon = match &self.last_sortitioned_block {
+        // The block-commit will be mined in the next burnchain block, so the proof is
+        // generated out of the sortition hash of the latest burnchain block.
+        let burnchain_tip = match &self.burnchain_tip {
             None => unreachable!(),
-            Some(block) => block.clone(),
+            Some(block) => block,
         };
 
-        // Generates a proof out of the sortition hash provided in the params.
         let vrf_proof = self
             .keychain
             .generate_proof(
                 &registered_key.vrf_public_key,
-                block_to_build_upon.block_snapshot.sortition_hash.as_bytes(),
+                burnchain_tip.block_snapshot.sortition_hash.as_bytes(),
             )
             .unwrap();
 
@@ -676,16 +738,7 @@ impl Node {
         // of the upcoming tenure.
         let microblock_secret_key = self
             .keychain
-            .rotate_microblock_keypair(block_to_build_upon.block_snapshot.block_height);
-
-        // Get the stack's chain tip
-        let chain_tip = match self.bootstraping_chain {
-            true => ChainTip::genesis(&BurnchainHeaderHash::zero(), 0, 0),
-            false => match &self.chain_tip {
-                Some(chain_tip) => chain_tip.clone(),
-                None => unreachable!(),
-            },
-        };
+            .rotate_microblock_keypair(burnchain_tip.block_snapshot.block_height);
 
         let estimator = self
             .config
@@ -718,7 +771,7 @@ impl Node {
             self.config.clone(),
             mem_pool,
             microblock_secret_key,
-            block_to_build_upon,
+            parent_sortition,
             vrf_proof,
             burn_fee_cap,
         );
@@ -736,20 +789,15 @@ impl Node {
         if self.active_registered_key.is_some() {
             let registered_key = self.active_registered_key.clone().unwrap();
 
-            let vrf_proof = self
-                .keychain
-                .generate_proof(
-                    &registered_key.vrf_public_key,
-                    burnchain_tip.block_snapshot.sortition_hash.as_bytes(),
-                )
-                .unwrap();
+            // The commit's seed must match the proof the tenure put in the block header.
+            let vrf_seed = VRFSeed::from_proof(&anchored_block_from_ongoing_tenure.header.proof);
 
             let op = self.generate_block_commit_op(
                 anchored_block_from_ongoing_tenure.header.block_hash(),
                 burn_fee,
                 &registered_key,
                 &burnchain_tip,
-                VRFSeed::from_proof(&vrf_proof),
+                vrf_seed,
             );
 
             let mut op_signer = self.keychain.generate_op_signer();
@@ -817,7 +865,7 @@ impl Node {
             parent_consensus_hash
         };
 
-        let atlas_config = AtlasConfig::default(false);
+        let atlas_config = self.config.make_atlas_config();
         let mut processed_blocks = vec![];
         loop {
             let mut process_blocks_at_tip = {
@@ -0,0 +1,483 @@
+//! Upgrade preflight: a dry run of the schema migrations a node's databases would undergo when
+//! the node is restarted with a new binary.
+//!
+//! `stacks-node preflight-upgrade --new-binary=<path>` runs the new binary's hidden
+//! `migration-dry-run` subcommand against the node's config. The new binary copies each of the
+//! node's databases into a scratch directory next to them (with `VACUUM INTO`, so the node can
+//! keep running), applies its own migrations to the copies, and prints a JSON
+//! [`MigrationReport`] on stdout. The original databases are never opened for writing.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Instant;
+
+use rusqlite::{Connection, OpenFlags};
+
+use stacks::burnchains::bitcoin::BitcoinNetworkType;
+use stacks::chainstate::burn::db::sortdb::{SortitionDB, SORTITION_DB_VERSION};
+use stacks::chainstate::coordinator::check_chainstate_db_versions;
+use stacks::chainstate::stacks::db::{StacksChainState, CHAINSTATE_VERSION};
+use stacks::core::mempool::{MemPoolDB, MEMPOOL_SCHEMA_VERSION};
+use stacks::core::{
+    StacksEpoch, STACKS_EPOCHS_MAINNET, STACKS_EPOCHS_REGTEST, STACKS_EPOCHS_TESTNET,
+};
+use stacks::util_lib::db::{tx_begin_immediate, Error as db_error};
+
+use crate::snapshot::{copy_sqlite_db, get_mempool_path};
+use crate::Config;
+
+const PREFLIGHT_SCRATCH_DIR: &str = "preflight-upgrade";
+
+/// What the dry run found for one database.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DatabaseMigration {
+    pub name: String,
+    pub path: String,
+    pub size_bytes: u64,
+    pub schema_version: String,
+    pub target_schema_version: String,
+    /// Time taken to migrate the scratch copy. Zero if no migration is needed.
+    pub migration_ms: u64,
+}
+
+impl DatabaseMigration {
+    pub fn needs_migration(&self) -> bool {
+        self.schema_version != self.target_schema_version
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MigrationReport {
+    /// Version of the binary that ran the dry run.
+    pub node_version: String,
+    pub databases: Vec<DatabaseMigration>,
+    /// Total time taken by the dry-run migrations.
+    pub migration_ms: u64,
+    /// Free disk space the migrations need: in the worst case, each migrated database's
+    /// rollback journal grows to the size of the database.
+    pub disk_required_bytes: u64,
+    /// Free disk space on the volume holding the databases, if it could be determined.
+    pub disk_available_bytes: Option<u64>,
+    /// Problems that would keep the new binary from starting on these databases.
+    pub blockers: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Database {
+    Sortition,
+    Chainstate,
+    Mempool,
+}
+
+impl Database {
+    const ALL: [Database; 3] = [Database::Sortition, Database::Chainstate, Database::Mempool];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Database::Sortition => "sortition",
+            Database::Chainstate => "chainstate",
+            Database::Mempool => "mempool",
+        }
+    }
+
+    /// Path to the sqlite file holding the database's schema.
+    fn path(&self, config: &Config) -> Result<PathBuf, String> {
+        match self {
+            Database::Sortition => {
+                Ok(Path::new(&config.get_burn_db_file_path()).join("marf.sqlite"))
+            }
+            Database::Chainstate => Ok(StacksChainState::header_index_root_path(
+                config.get_chainstate_path(),
+            )),
+            Database::Mempool => get_mempool_path(config),
+        }
+    }
+
+    fn target_schema_version(&self) -> String {
+        match self {
+            Database::Sortition => SORTITION_DB_VERSION.to_string(),
+            Database::Chainstate => CHAINSTATE_VERSION.to_string(),
+            Database::Mempool => MEMPOOL_SCHEMA_VERSION.to_string(),
+        }
+    }
+
+    fn schema_version(&self, conn: &Connection) -> Result<Option<String>, db_error> {
+        match self {
+            Database::Sortition => SortitionDB::get_schema_version(conn),
+            Database::Chainstate => Ok(Some(StacksChainState::load_db_config(conn)?.version)),
+            Database::Mempool => Ok(Some(
+                MemPoolDB::get_schema_version(conn)?
+                    .unwrap_or(1)
+                    .to_string(),
+            )),
+        }
+    }
+
+    /// Apply this binary's migrations to the database open in `conn`.
+    fn migrate(
+        &self,
+        conn: &mut Connection,
+        config: &Config,
+        epochs: &[StacksEpoch],
+    ) -> Result<(), String> {
+        let mut tx = tx_begin_immediate(conn).map_err(|e| format!("{:?}", e))?;
+        match self {
+            Database::Sortition => {
+                SortitionDB::apply_schema_migrations(&tx, epochs).map_err(|e| format!("{:?}", e))?
+            }
+            Database::Chainstate => {
+                StacksChainState::apply_schema_migrations(
+                    &tx,
+                    config.is_mainnet(),
+                    config.burnchain.chain_id,
+                )
+                .map_err(|e| format!("{:?}", e))?;
+                StacksChainState::add_indexes(&tx).map_err(|e| format!("{:?}", e))?;
+            }
+            Database::Mempool => {
+                MemPoolDB::apply_schema_migrations(&mut tx).map_err(|e| format!("{:?}", e))?;
+                MemPoolDB::add_indexes(&mut tx).map_err(|e| format!("{:?}", e))?;
+            }
+        }
+        tx.commit().map_err(|e| format!("{:?}", e))
+    }
+}
+
+/// The epochs the node would run with, which the sortition DB migrations need.
+fn get_epochs(config: &Config) -> Vec<StacksEpoch> {
+    if let Some(ref epochs) = config.burnchain.epochs {
+        return epochs.clone();
+    }
+    match config.burnchain.get_bitcoin_network().1 {
+        BitcoinNetworkType::Mainnet => STACKS_EPOCHS_MAINNET.to_vec(),
+        BitcoinNetworkType::Testnet => STACKS_EPOCHS_TESTNET.to_vec(),
+        BitcoinNetworkType::Regtest => STACKS_EPOCHS_REGTEST.to_vec(),
+    }
+}
+
+/// Scratch copies go next to the databases, so that they are measured against the same disk.
+fn get_scratch_dir(config: &Config) -> PathBuf {
+    let mut path = PathBuf::from(&config.node.working_dir);
+    path.push(&config.burnchain.mode);
+    path.push(PREFLIGHT_SCRATCH_DIR);
+    path
+}
+
+#[cfg(unix)]
+fn get_available_disk_space(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn get_available_disk_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Runs this binary's migrations against scratch copies of the databases of the node configured
+/// by `config`, and reports what they would do to the real ones.
+pub fn dry_run_migrations(config: &Config, node_version: &str) -> MigrationReport {
+    let epochs = get_epochs(config);
+    let mut report = MigrationReport {
+        node_version: node_version.to_string(),
+        databases: vec![],
+        migration_ms: 0,
+        disk_required_bytes: 0,
+        disk_available_bytes: None,
+        blockers: vec![],
+    };
+
+    // the databases must be usable in the current epoch before anything is migrated
+    let sortdb_path = config.get_burn_db_file_path();
+    let chainstate_path = config.get_chainstate_path_str();
+    let chainstate_index_path =
+        StacksChainState::header_index_root_path(config.get_chainstate_path());
+    match (
+        fs::metadata(&sortdb_path).is_ok(),
+        chainstate_index_path.exists(),
+    ) {
+        (true, true) => {
+            match check_chainstate_db_versions(&epochs, &sortdb_path, &chainstate_path) {
+                Ok(true) => {}
+                Ok(false) => report.blockers.push(
+                    "The chainstate databases are not compatible with the current epoch"
+                        .to_string(),
+                ),
+                Err(e) => report.blockers.push(format!(
+                    "Failed to check chainstate database versions: {:?}",
+                    e
+                )),
+            }
+        }
+        (false, true) => report.blockers.push(format!(
+            "The chainstate at {} exists without a sortition DB at {}",
+            chainstate_path, sortdb_path
+        )),
+        // the node has not booted yet
+        _ => {}
+    }
+
+    let scratch_dir = get_scratch_dir(config);
+    if let Err(e) = fs::create_dir_all(&scratch_dir) {
+        report.blockers.push(format!(
+            "Failed to create scratch directory {:?}: {}",
+            scratch_dir, e
+        ));
+        return report;
+    }
+    report.disk_available_bytes = get_available_disk_space(&scratch_dir);
+
+    for database in Database::ALL.iter() {
+        if let Err(e) = dry_run_migration(database, config, &epochs, &scratch_dir, &mut report) {
+            report
+                .blockers
+                .push(format!("{} database: {}", database.name(), e));
+        }
+    }
+
+    if let Err(e) = fs::remove_dir_all(&scratch_dir) {
+        warn!(
+            "Failed to remove scratch directory {:?}: {}",
+            scratch_dir, e
+        );
+    }
+
+    if let Some(available) = report.disk_available_bytes {
+        if available < report.disk_required_bytes {
+            report.blockers.push(format!(
+                "Migrations need {} bytes of free disk space, but only {} are available",
+                report.disk_required_bytes, available
+            ));
+        }
+    }
+    report
+}
+
+fn dry_run_migration(
+    database: &Database,
+    config: &Config,
+    epochs: &[StacksEpoch],
+    scratch_dir: &Path,
+    report: &mut MigrationReport,
+) -> Result<(), String> {
+    let path = database.path(config)?;
+    if !path.exists() {
+        // instantiated at the latest schema when the node boots
+        return Ok(());
+    }
+    let size_bytes = fs::metadata(&path)
+        .map_err(|e| format!("Failed to stat {:?}: {}", path, e))?
+        .len();
+
+    let conn = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+    let schema_version = database
+        .schema_version(&conn)
+        .map_err(|e| format!("Failed to read schema version: {:?}", e))?
+        .ok_or_else(|| "No schema version is recorded".to_string())?;
+    drop(conn);
+
+    let mut migration = DatabaseMigration {
+        name: database.name().to_string(),
+        path: path.to_string_lossy().to_string(),
+        size_bytes,
+        schema_version,
+        target_schema_version: database.target_schema_version(),
+        migration_ms: 0,
+    };
+    let needs_migration = migration.needs_migration();
+    let version_parts = (
+        migration.schema_version.parse::<u64>(),
+        migration.target_schema_version.parse::<u64>(),
+    );
+    report.databases.push(migration.clone());
+    match version_parts {
+        (Ok(version), Ok(target_version)) if version > target_version => {
+            return Err(format!(
+                "schema version {} is newer than this node's version {}; downgrades are not supported",
+                version, target_version
+            ));
+        }
+        (Ok(_), Ok(_)) => {}
+        _ => {
+            return Err(format!(
+                "unrecognized schema version {}",
+                &migration.schema_version
+            ))
+        }
+    }
+    if !needs_migration {
+        return Ok(());
+    }
+
+    report.disk_required_bytes += size_bytes;
+    if let Some(available) = report.disk_available_bytes {
+        if available < size_bytes {
+            return Err(format!(
+                "not enough free disk space to copy the database for a dry run ({} bytes needed, {} available)",
+                size_bytes, available
+            ));
+        }
+    }
+
+    let scratch_path = scratch_dir.join(format!("{}.sqlite", database.name()));
+    copy_sqlite_db(&path, &scratch_path)?;
+    let mut conn = Connection::open(&scratch_path)
+        .map_err(|e| format!("Failed to open {:?}: {}", scratch_path, e))?;
+    let start = Instant::now();
+    let result = database.migrate(&mut conn, config, epochs);
+    migration.migration_ms = start.elapsed().as_millis() as u64;
+    drop(conn);
+    let _ = fs::remove_file(&scratch_path);
+    result.map_err(|e| format!("migration failed: {}", e))?;
+
+    report.migration_ms += migration.migration_ms;
+    *report
+        .databases
+        .last_mut()
+        .expect("BUG: database report was not recorded") = migration;
+    Ok(())
+}
+
+/// Runs `new_binary`'s dry-run migrations against the node configured at `config_path`.
+pub fn run_preflight(new_binary: &str, config_path: &str) -> Result<MigrationReport, String> {
+    let output = process::Command::new(new_binary)
+        .arg("migration-dry-run")
+        .arg(format!("--config={}", config_path))
+        .stderr(process::Stdio::inherit())
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", new_binary, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} migration-dry-run exited with {}",
+            new_binary, output.status
+        ));
+    }
+    serde_json::from_slice(&output.stdout).map_err(|e| {
+        format!(
+            "Failed to parse the migration report of {} (does it support `migration-dry-run`?): {}",
+            new_binary, e
+        )
+    })
+}
+
+/// Prints `report` for an operator.
+pub fn print_report(report: &MigrationReport) {
+    println!("New binary: {}", report.node_version);
+    for database in report.databases.iter() {
+        if database.needs_migration() {
+            println!(
+                "  {}: schema {} -> {}, {} bytes, migrated in {} ms",
+                database.name,
+                database.schema_version,
+                database.target_schema_version,
+                database.size_bytes,
+                database.migration_ms
+            );
+        } else {
+            println!(
+                "  {}: schema {}, no migration needed",
+                database.name, database.schema_version
+            );
+        }
+    }
+    println!("Expected migration time: {} ms", report.migration_ms);
+    match report.disk_available_bytes {
+        Some(available) => println!(
+            "Disk space required: {} bytes ({} available)",
+            report.disk_required_bytes, available
+        ),
+        None => println!("Disk space required: {} bytes", report.disk_required_bytes),
+    }
+    if report.blockers.is_empty() {
+        println!("No blockers found");
+    } else {
+        println!("Blockers:");
+        for blocker in report.blockers.iter() {
+            println!("  {}", blocker);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_test_config(name: &str) -> Config {
+        let mut config = Config::default();
+        config.node.working_dir = format!("/tmp/stacks-node-tests/preflight/{}", name);
+        if fs::metadata(&config.node.working_dir).is_ok() {
+            fs::remove_dir_all(&config.node.working_dir).unwrap();
+        }
+        config
+    }
+
+    #[test]
+    fn test_dry_run_migrations() {
+        let config = make_test_config("dry_run");
+
+        // no databases yet: nothing to migrate
+        let report = dry_run_migrations(&config, "test");
+        assert!(report.databases.is_empty());
+        assert!(report.blockers.is_empty());
+
+        // a mempool one schema version behind
+        fs::create_dir_all(config.get_chainstate_path()).unwrap();
+        let mempool_path = get_mempool_path(&config).unwrap();
+        let old_version = MEMPOOL_SCHEMA_VERSION - 1;
+        Connection::open(&mempool_path)
+            .unwrap()
+            .execute_batch(&format!(
+                // opening a real mempool requires a booted chainstate, so only create the tables
+                // that the latest migration and the indexes touch
+                "CREATE TABLE schema_version (version INTEGER NOT NULL); \
+                 INSERT INTO schema_version (version) VALUES ({}); \
+                 CREATE TABLE mempool (txid TEXT, height INTEGER, sponsor_address TEXT, \
+                     sponsor_nonce INTEGER, origin_address TEXT, origin_nonce INTEGER, \
+                     accept_time INTEGER, consensus_hash TEXT, block_header_hash TEXT); \
+                 CREATE TABLE fee_estimates (txid TEXT); \
+                 CREATE TABLE randomized_txids (txid TEXT, hashed_txid TEXT);",
+                old_version
+            ))
+            .unwrap();
+
+        let report = dry_run_migrations(&config, "test");
+        assert!(report.blockers.is_empty(), "{:?}", report.blockers);
+        assert_eq!(report.databases.len(), 1);
+        let migration = &report.databases[0];
+        assert_eq!(migration.name, "mempool");
+        assert_eq!(migration.schema_version, old_version.to_string());
+        assert_eq!(
+            migration.target_schema_version,
+            MEMPOOL_SCHEMA_VERSION.to_string()
+        );
+        assert!(migration.needs_migration());
+        assert_eq!(report.disk_required_bytes, migration.size_bytes);
+        assert!(!get_scratch_dir(&config).exists());
+
+        // the original database is left alone
+        let conn = Connection::open(&mempool_path).unwrap();
+        assert_eq!(
+            MemPoolDB::get_schema_version(&conn).unwrap(),
+            Some(old_version)
+        );
+
+        // a database written by a newer node blocks the upgrade
+        conn.execute_batch(&format!(
+            "INSERT INTO schema_version (version) VALUES ({});",
+            MEMPOOL_SCHEMA_VERSION + 1
+        ))
+        .unwrap();
+        let report = dry_run_migrations(&config, "test");
+        assert_eq!(report.blockers.len(), 1);
+        assert!(report.blockers[0].starts_with("mempool database: schema version"));
+    }
+}
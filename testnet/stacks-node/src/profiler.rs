@@ -0,0 +1,814 @@
+//! CPU and heap profiles of the running node, in the protobuf format that `go tool pprof` and
+//! other pprof viewers read. With `node.profiling` set, the admin API serves them at
+//! `/v2/admin/debug/pprof/profile` and `/v2/admin/debug/pprof/heap`.
+//!
+//! * The CPU profiler samples the stack of whichever thread is running every `1/frequency`
+//!   seconds of CPU time, from a `SIGPROF` handler (Unix only).
+//! * The heap profiler samples about one allocation in every `sample_bytes` bytes allocated,
+//!   through `ProfilingAllocator`, and reports both the sampled allocations (`alloc_*`) and those
+//!   of them that are still live when the profile ends (`inuse_*`), scaled up to estimates of all
+//!   allocations. Only allocations made while the profile runs are seen.
+//!
+//! Neither profiler costs anything while no profile is being taken, beyond an atomic load per
+//! allocation. Taking a profile slows the node down somewhat, more so for the heap profile.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Longest profile that can be taken, in seconds
+pub const MAX_PROFILE_SECS: u64 = 300;
+/// Length of a profile, if not given
+pub const DEFAULT_PROFILE_SECS: u64 = 30;
+/// CPU samples per second, if not given
+pub const DEFAULT_CPU_FREQUENCY: u64 = 99;
+pub const MAX_CPU_FREQUENCY: u64 = 1000;
+/// Average number of bytes allocated per heap sample, if not given
+pub const DEFAULT_HEAP_SAMPLE_BYTES: u64 = 512 * 1024;
+/// Deepest stack that is recorded
+const MAX_DEPTH: usize = 64;
+/// How many leading frames may belong to the profiler itself
+const MAX_PROFILER_FRAMES: usize = 16;
+
+/// A recorded stack: instruction pointers, innermost first
+type Stack = Vec<usize>;
+
+/// Captures the calling thread's stack into `frames`, returning its depth.
+fn capture_stack(frames: &mut [usize; MAX_DEPTH]) -> usize {
+    let mut depth = 0;
+    // unsynchronized, because this runs in a signal handler or allocator that may have
+    // interrupted a thread holding the backtrace crate's lock
+    unsafe {
+        backtrace::trace_unsynchronized(|frame| {
+            frames[depth] = frame.ip() as usize;
+            depth += 1;
+            depth < MAX_DEPTH
+        });
+    }
+    depth
+}
+
+/// Writes protobuf messages.
+struct ProtoWriter(Vec<u8>);
+
+impl ProtoWriter {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+
+    fn uint(&mut self, field: u32, value: u64) {
+        if value != 0 {
+            self.varint(u64::from(field) << 3);
+            self.varint(value);
+        }
+    }
+
+    fn bytes(&mut self, field: u32, bytes: &[u8]) {
+        self.varint((u64::from(field) << 3) | 2);
+        self.varint(bytes.len() as u64);
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn packed(&mut self, field: u32, values: &[u64]) {
+        let mut packed = ProtoWriter(vec![]);
+        for value in values {
+            packed.varint(*value);
+        }
+        self.bytes(field, &packed.0);
+    }
+
+    fn message<F: FnOnce(&mut ProtoWriter)>(&mut self, field: u32, write: F) {
+        let mut message = ProtoWriter(vec![]);
+        write(&mut message);
+        self.bytes(field, &message.0);
+    }
+}
+
+/// A function a location resolved to
+struct FunctionLine {
+    name: String,
+    filename: String,
+    line: u64,
+}
+
+/// Builds a `perftools.profiles.Profile` message (see pprof's `profile.proto`), symbolizing
+/// the stacks as it goes.
+struct ProfileBuilder {
+    strings: Vec<String>,
+    string_ids: HashMap<String, u64>,
+    /// Function ID by name and file
+    functions: HashMap<(u64, u64), u64>,
+    /// Location ID by instruction pointer
+    locations: HashMap<usize, u64>,
+    samples: ProtoWriter,
+    /// Functions and locations, as encoded so far
+    body: ProtoWriter,
+    /// Leading frames whose function names contain any of these belong to the profiler, and are
+    /// left out
+    skip_through: &'static [&'static str],
+}
+
+impl ProfileBuilder {
+    fn new(skip_through: &'static [&'static str]) -> ProfileBuilder {
+        let mut builder = ProfileBuilder {
+            strings: vec![],
+            string_ids: HashMap::new(),
+            functions: HashMap::new(),
+            locations: HashMap::new(),
+            samples: ProtoWriter(vec![]),
+            body: ProtoWriter(vec![]),
+            skip_through,
+        };
+        // the string table must start with ""
+        builder.string_id("");
+        builder
+    }
+
+    fn string_id(&mut self, s: &str) -> u64 {
+        if let Some(id) = self.string_ids.get(s) {
+            return *id;
+        }
+        let id = self.strings.len() as u64;
+        self.strings.push(s.to_string());
+        self.string_ids.insert(s.to_string(), id);
+        id
+    }
+
+    /// The functions at `ip`, innermost (inlined) first
+    fn resolve(ip: usize) -> Vec<FunctionLine> {
+        let mut lines = vec![];
+        backtrace::resolve(ip as *mut _, |symbol| {
+            lines.push(FunctionLine {
+                name: symbol
+                    .name()
+                    .map(|name| format!("{:#}", name))
+                    .unwrap_or_else(|| format!("{:#x}", ip)),
+                filename: symbol
+                    .filename()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default(),
+                line: symbol.lineno().map(u64::from).unwrap_or(0),
+            })
+        });
+        if lines.is_empty() {
+            lines.push(FunctionLine {
+                name: format!("{:#x}", ip),
+                filename: String::new(),
+                line: 0,
+            });
+        }
+        lines
+    }
+
+    fn location_id(&mut self, ip: usize, lines: Vec<FunctionLine>) -> u64 {
+        if let Some(id) = self.locations.get(&ip) {
+            return *id;
+        }
+        let id = self.locations.len() as u64 + 1;
+        self.locations.insert(ip, id);
+
+        let mut line_ids = vec![];
+        for line in lines {
+            let name = self.string_id(&line.name);
+            let filename = self.string_id(&line.filename);
+            let next_id = self.functions.len() as u64 + 1;
+            let function_id = *self.functions.entry((name, filename)).or_insert(next_id);
+            if function_id == next_id {
+                // Function
+                self.body.message(5, |function| {
+                    function.uint(1, function_id);
+                    function.uint(2, name);
+                    function.uint(3, name);
+                    function.uint(4, filename);
+                });
+            }
+            line_ids.push((function_id, line.line));
+        }
+        // Location
+        self.body.message(4, |location| {
+            location.uint(1, id);
+            location.uint(3, ip as u64);
+            for (function_id, line) in line_ids {
+                location.message(4, |l| {
+                    l.uint(1, function_id);
+                    l.uint(2, line);
+                });
+            }
+        });
+        id
+    }
+
+    fn add_sample(&mut self, stack: &[usize], values: &[u64]) {
+        let resolved: Vec<_> = stack
+            .iter()
+            .enumerate()
+            .map(|(i, ip)| {
+                // other than the innermost frame, `ip` is a return address, which may belong to
+                // the next line or even function
+                let lookup = if i == 0 { *ip } else { ip.saturating_sub(1) };
+                (*ip, Self::resolve(lookup))
+            })
+            .collect();
+        let skip = resolved
+            .iter()
+            .take(MAX_PROFILER_FRAMES)
+            .rposition(|(_, lines)| {
+                lines.iter().any(|line| {
+                    self.skip_through
+                        .iter()
+                        .any(|marker| line.name.contains(marker))
+                })
+            })
+            .map(|i| i + 1)
+            .unwrap_or(0);
+
+        let mut location_ids = vec![];
+        for (ip, lines) in resolved.into_iter().skip(skip) {
+            location_ids.push(self.location_id(ip, lines));
+        }
+        // Sample
+        self.samples.message(2, |sample| {
+            sample.packed(1, &location_ids);
+            sample.packed(2, values);
+        });
+    }
+
+    /// Encodes the profile. `sample_types` are the (type, unit) of each sample value.
+    fn finish(
+        mut self,
+        sample_types: &[(&str, &str)],
+        period_type: (&str, &str),
+        period: u64,
+        started_at: SystemTime,
+        duration: Duration,
+    ) -> Vec<u8> {
+        let mut profile = ProtoWriter(vec![]);
+        let value_type = |builder: &mut ProfileBuilder, (kind, unit): (&str, &str)| {
+            (builder.string_id(kind), builder.string_id(unit))
+        };
+        let sample_types: Vec<_> = sample_types
+            .iter()
+            .map(|sample_type| value_type(&mut self, *sample_type))
+            .collect();
+        let period_type = value_type(&mut self, period_type);
+
+        for (kind, unit) in sample_types {
+            profile.message(1, |value_type| {
+                value_type.uint(1, kind);
+                value_type.uint(2, unit);
+            });
+        }
+        profile.0.extend_from_slice(&self.samples.0);
+        profile.0.extend_from_slice(&self.body.0);
+        for s in self.strings.iter() {
+            profile.bytes(6, s.as_bytes());
+        }
+        let time_nanos = started_at
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_nanos() as u64)
+            .unwrap_or(0);
+        profile.uint(9, time_nanos);
+        profile.uint(10, duration.as_nanos() as u64);
+        profile.message(11, |value_type| {
+            value_type.uint(1, period_type.0);
+            value_type.uint(2, period_type.1);
+        });
+        profile.uint(12, period);
+        profile.0
+    }
+}
+
+#[cfg(unix)]
+mod cpu {
+    use std::cell::UnsafeCell;
+    use std::ptr;
+    use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+    use std::sync::Once;
+
+    use super::{capture_stack, Stack, MAX_DEPTH};
+
+    struct Slot {
+        frames: UnsafeCell<[usize; MAX_DEPTH]>,
+        depth: AtomicUsize,
+        written: AtomicBool,
+    }
+
+    /// Where the `SIGPROF` handler stores samples, preallocated so that the handler does not
+    /// allocate
+    pub struct SampleBuffer {
+        slots: Vec<Slot>,
+        next: AtomicUsize,
+    }
+
+    // each slot is written by the one handler invocation that claimed it, and only read once
+    // its `written` flag is set
+    unsafe impl Sync for SampleBuffer {}
+
+    impl SampleBuffer {
+        pub fn new(capacity: usize) -> SampleBuffer {
+            SampleBuffer {
+                slots: (0..capacity)
+                    .map(|_| Slot {
+                        frames: UnsafeCell::new([0; MAX_DEPTH]),
+                        depth: AtomicUsize::new(0),
+                        written: AtomicBool::new(false),
+                    })
+                    .collect(),
+                next: AtomicUsize::new(0),
+            }
+        }
+
+        fn record(&self) {
+            let index = self.next.fetch_add(1, Ordering::Relaxed);
+            let slot = match self.slots.get(index) {
+                Some(slot) => slot,
+                // full; counted as dropped
+                None => return,
+            };
+            let depth = capture_stack(unsafe { &mut *slot.frames.get() });
+            slot.depth.store(depth, Ordering::Relaxed);
+            slot.written.store(true, Ordering::Release);
+        }
+
+        /// The recorded stacks, and how many samples did not fit
+        pub fn into_stacks(self) -> (Vec<Stack>, usize) {
+            let taken = self.next.load(Ordering::SeqCst);
+            let dropped = taken.saturating_sub(self.slots.len());
+            let stacks = self
+                .slots
+                .into_iter()
+                .filter(|slot| slot.written.load(Ordering::Acquire))
+                .map(|slot| {
+                    let depth = slot.depth.load(Ordering::Relaxed);
+                    slot.frames.into_inner()[..depth].to_vec()
+                })
+                .collect();
+            (stacks, dropped)
+        }
+    }
+
+    static SAMPLES: AtomicPtr<SampleBuffer> = AtomicPtr::new(ptr::null_mut());
+    /// Number of `SIGPROF` handlers running, which may be using `SAMPLES`
+    static HANDLERS_RUNNING: AtomicUsize = AtomicUsize::new(0);
+    static INSTALL_HANDLER: Once = Once::new();
+
+    extern "C" fn on_sigprof(_signal: libc::c_int) {
+        HANDLERS_RUNNING.fetch_add(1, Ordering::SeqCst);
+        let samples = SAMPLES.load(Ordering::SeqCst);
+        if !samples.is_null() {
+            unsafe { &*samples }.record();
+        }
+        HANDLERS_RUNNING.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// Installs the `SIGPROF` handler. It stays installed once the profile ends, so that a
+    /// signal still in flight does not kill the process.
+    fn install_handler() -> Result<(), String> {
+        let mut result = Ok(());
+        INSTALL_HANDLER.call_once(|| unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = on_sigprof as *const () as usize;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            if libc::sigaction(libc::SIGPROF, &action, ptr::null_mut()) != 0 {
+                result = Err(format!(
+                    "Failed to install the SIGPROF handler: {}",
+                    std::io::Error::last_os_error()
+                ));
+            }
+        });
+        result
+    }
+
+    fn set_timer(interval_us: u64) -> Result<(), String> {
+        let interval = libc::timeval {
+            tv_sec: (interval_us / 1_000_000) as libc::time_t,
+            tv_usec: (interval_us % 1_000_000) as libc::suseconds_t,
+        };
+        let timer = libc::itimerval {
+            it_interval: interval,
+            it_value: interval,
+        };
+        if unsafe { libc::setitimer(libc::ITIMER_PROF, &timer, ptr::null_mut()) } != 0 {
+            return Err(format!(
+                "Failed to set the profiling timer: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Starts sampling into `buffer` every `interval_us` microseconds of CPU time.
+    pub fn start(buffer: SampleBuffer, interval_us: u64) -> Result<(), String> {
+        install_handler()?;
+        SAMPLES.store(Box::into_raw(Box::new(buffer)), Ordering::SeqCst);
+        if let Err(e) = set_timer(interval_us) {
+            stop();
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Stops sampling, and returns the buffer once no handler uses it anymore.
+    pub fn stop() -> Option<SampleBuffer> {
+        let _ = set_timer(0);
+        let samples = SAMPLES.swap(ptr::null_mut(), Ordering::SeqCst);
+        while HANDLERS_RUNNING.load(Ordering::SeqCst) > 0 {
+            std::thread::yield_now();
+        }
+        if samples.is_null() {
+            None
+        } else {
+            Some(*unsafe { Box::from_raw(samples) })
+        }
+    }
+}
+
+/// Set while a CPU profile is being taken
+static CPU_PROFILING: AtomicBool = AtomicBool::new(false);
+
+/// Takes a CPU profile of the next `duration`, sampling `frequency` times per second of CPU
+/// time, and returns it encoded. Blocks for `duration`. Only one CPU profile can be taken at a
+/// time.
+#[cfg(unix)]
+pub fn profile_cpu(duration: Duration, frequency: u64) -> Result<Vec<u8>, String> {
+    if frequency == 0 || frequency > MAX_CPU_FREQUENCY {
+        return Err(format!(
+            "The sampling frequency must be from 1 to {}",
+            MAX_CPU_FREQUENCY
+        ));
+    }
+    if CPU_PROFILING.swap(true, Ordering::SeqCst) {
+        return Err("A CPU profile is already being taken".to_string());
+    }
+
+    // room for every thread to be busy on a 16-core machine
+    let capacity = (duration.as_secs() + 1) * frequency * 16;
+    let interval_us = 1_000_000 / frequency;
+    let started_at = SystemTime::now();
+    let result = cpu::start(cpu::SampleBuffer::new(capacity as usize), interval_us);
+    if result.is_ok() {
+        thread::sleep(duration);
+    }
+    let buffer = cpu::stop();
+    CPU_PROFILING.store(false, Ordering::SeqCst);
+    result?;
+
+    let (stacks, dropped) = buffer
+        .map(|buffer| buffer.into_stacks())
+        .unwrap_or_default();
+    if dropped > 0 {
+        warn!("CPU profile: dropped {} samples", dropped);
+    }
+    let mut counts: HashMap<Stack, u64> = HashMap::new();
+    for stack in stacks {
+        *counts.entry(stack).or_insert(0) += 1;
+    }
+    let mut builder = ProfileBuilder::new(&["on_sigprof", "__restore_rt"]);
+    for (stack, count) in counts {
+        builder.add_sample(&stack, &[count, count * interval_us * 1000]);
+    }
+    Ok(builder.finish(
+        &[("samples", "count"), ("cpu", "nanoseconds")],
+        ("cpu", "nanoseconds"),
+        interval_us * 1000,
+        started_at,
+        duration,
+    ))
+}
+
+#[cfg(not(unix))]
+pub fn profile_cpu(_duration: Duration, _frequency: u64) -> Result<Vec<u8>, String> {
+    Err("CPU profiles are only available on Unix".to_string())
+}
+
+/// Totals of the sampled allocations made at one stack, scaled to estimates of all allocations
+#[derive(Default)]
+struct HeapStackTotals {
+    alloc_objects: u64,
+    alloc_bytes: u64,
+    inuse_objects: u64,
+    inuse_bytes: u64,
+}
+
+#[derive(Default)]
+struct HeapSamples {
+    stacks: HashMap<Stack, HeapStackTotals>,
+    /// The live sampled allocations: their stack, and their scaled object count and bytes
+    live: HashMap<usize, (Stack, u64, u64)>,
+}
+
+/// Whether allocations are being sampled
+static HEAP_PROFILING: AtomicBool = AtomicBool::new(false);
+static HEAP_SAMPLE_BYTES: AtomicUsize = AtomicUsize::new(DEFAULT_HEAP_SAMPLE_BYTES as usize);
+static HEAP_SAMPLES: Mutex<Option<HeapSamples>> = Mutex::new(None);
+
+struct HeapThreadState {
+    /// Set while the thread is recording a sample, so that the allocations it makes for it are
+    /// not sampled in turn
+    busy: Cell<bool>,
+    /// Bytes left to allocate until the next sample
+    until_sample: Cell<usize>,
+}
+
+thread_local! {
+    static HEAP_THREAD_STATE: HeapThreadState = const {
+        HeapThreadState {
+            busy: Cell::new(false),
+            until_sample: Cell::new(0),
+        }
+    };
+}
+
+fn record_alloc(ptr: *mut u8, size: usize) {
+    let _ = HEAP_THREAD_STATE.try_with(|state| {
+        if state.busy.get() {
+            return;
+        }
+        let until_sample = state.until_sample.get();
+        if size < until_sample {
+            state.until_sample.set(until_sample - size);
+            return;
+        }
+        let sample_bytes = HEAP_SAMPLE_BYTES.load(Ordering::Relaxed);
+        state.until_sample.set(sample_bytes);
+        state.busy.set(true);
+
+        let mut frames = [0; MAX_DEPTH];
+        let depth = capture_stack(&mut frames);
+        // a sample stands for the `sample_bytes` allocated since the last one
+        let bytes = size.max(sample_bytes) as u64;
+        let objects = bytes / (size.max(1) as u64);
+        if let Ok(mut samples) = HEAP_SAMPLES.lock() {
+            if let Some(samples) = samples.as_mut() {
+                let stack = frames[..depth].to_vec();
+                let totals = samples.stacks.entry(stack.clone()).or_default();
+                totals.alloc_objects += objects;
+                totals.alloc_bytes += bytes;
+                totals.inuse_objects += objects;
+                totals.inuse_bytes += bytes;
+                samples.live.insert(ptr as usize, (stack, objects, bytes));
+            }
+        }
+        state.busy.set(false);
+    });
+}
+
+fn record_free(ptr: *mut u8) {
+    let _ = HEAP_THREAD_STATE.try_with(|state| {
+        // the thread is freeing memory of its own sample's
+        if state.busy.get() {
+            return;
+        }
+        state.busy.set(true);
+        if let Ok(mut samples) = HEAP_SAMPLES.lock() {
+            if let Some(samples) = samples.as_mut() {
+                if let Some((stack, objects, bytes)) = samples.live.remove(&(ptr as usize)) {
+                    if let Some(totals) = samples.stacks.get_mut(&stack) {
+                        totals.inuse_objects -= objects;
+                        totals.inuse_bytes -= bytes;
+                    }
+                }
+            }
+        }
+        state.busy.set(false);
+    });
+}
+
+/// The system allocator, which samples allocations while a heap profile is being taken.
+/// Installed as the node's global allocator.
+pub struct ProfilingAllocator;
+
+unsafe impl GlobalAlloc for ProfilingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if HEAP_PROFILING.load(Ordering::Relaxed) && !ptr.is_null() {
+            record_alloc(ptr, layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if HEAP_PROFILING.load(Ordering::Relaxed) && !ptr.is_null() {
+            record_alloc(ptr, layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if HEAP_PROFILING.load(Ordering::Relaxed) {
+            record_free(ptr);
+        }
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if HEAP_PROFILING.load(Ordering::Relaxed) && !new_ptr.is_null() {
+            record_free(ptr);
+            record_alloc(new_ptr, new_size);
+        }
+        new_ptr
+    }
+}
+
+/// Takes a heap profile of the next `duration`, sampling about one allocation in every
+/// `sample_bytes` bytes allocated, and returns it encoded. Blocks for `duration`. Only one heap
+/// profile can be taken at a time.
+pub fn profile_heap(duration: Duration, sample_bytes: u64) -> Result<Vec<u8>, String> {
+    if sample_bytes == 0 {
+        return Err("The sampling interval must be at least 1 byte".to_string());
+    }
+    {
+        let mut samples = HEAP_SAMPLES.lock().unwrap();
+        if samples.is_some() {
+            return Err("A heap profile is already being taken".to_string());
+        }
+        *samples = Some(HeapSamples::default());
+    }
+    HEAP_SAMPLE_BYTES.store(sample_bytes as usize, Ordering::SeqCst);
+    let started_at = SystemTime::now();
+    HEAP_PROFILING.store(true, Ordering::SeqCst);
+    thread::sleep(duration);
+    HEAP_PROFILING.store(false, Ordering::SeqCst);
+    let samples = HEAP_SAMPLES
+        .lock()
+        .unwrap()
+        .take()
+        .expect("FATAL: heap samples taken by another profile");
+
+    let mut builder = ProfileBuilder::new(&[
+        "ProfilingAllocator",
+        "__rust_alloc",
+        "__rust_alloc_zeroed",
+        "__rust_realloc",
+    ]);
+    for (stack, totals) in samples.stacks {
+        builder.add_sample(
+            &stack,
+            &[
+                totals.alloc_objects,
+                totals.alloc_bytes,
+                totals.inuse_objects,
+                totals.inuse_bytes,
+            ],
+        );
+    }
+    Ok(builder.finish(
+        &[
+            ("alloc_objects", "count"),
+            ("alloc_space", "bytes"),
+            ("inuse_objects", "count"),
+            ("inuse_space", "bytes"),
+        ],
+        ("space", "bytes"),
+        sample_bytes,
+        started_at,
+        duration,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads the fields of a protobuf message, as (field, varint value or bytes)
+    fn read_fields(mut buf: &[u8]) -> Vec<(u64, Result<u64, Vec<u8>>)> {
+        fn varint(buf: &mut &[u8]) -> u64 {
+            let mut value = 0;
+            let mut shift = 0;
+            loop {
+                let byte = buf[0];
+                *buf = &buf[1..];
+                value |= u64::from(byte & 0x7f) << shift;
+                if byte < 0x80 {
+                    return value;
+                }
+                shift += 7;
+            }
+        }
+        let mut fields = vec![];
+        while !buf.is_empty() {
+            let key = varint(&mut buf);
+            match key & 7 {
+                0 => fields.push((key >> 3, Ok(varint(&mut buf)))),
+                2 => {
+                    let len = varint(&mut buf) as usize;
+                    fields.push((key >> 3, Err(buf[..len].to_vec())));
+                    buf = &buf[len..];
+                }
+                wire_type => panic!("Unexpected wire type {}", wire_type),
+            }
+        }
+        fields
+    }
+
+    fn strings(profile: &[u8]) -> Vec<String> {
+        read_fields(profile)
+            .into_iter()
+            .filter(|(field, _)| *field == 6)
+            .map(|(_, value)| String::from_utf8(value.unwrap_err()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_proto_writer() {
+        let mut writer = ProtoWriter(vec![]);
+        writer.uint(1, 300);
+        writer.uint(2, 0);
+        writer.bytes(3, b"ab");
+        writer.packed(4, &[1, 128]);
+        assert_eq!(
+            writer.0,
+            vec![0x08, 0xac, 0x02, 0x1a, 2, b'a', b'b', 0x22, 3, 1, 0x80, 0x01]
+        );
+    }
+
+    #[inline(never)]
+    fn profiled_function() -> Vec<usize> {
+        let mut frames = [0; MAX_DEPTH];
+        let depth = capture_stack(&mut frames);
+        frames[..depth].to_vec()
+    }
+
+    #[test]
+    fn test_profile_builder() {
+        let stack = profiled_function();
+        let mut builder = ProfileBuilder::new(&["capture_stack"]);
+        builder.add_sample(&stack, &[3]);
+        builder.add_sample(&stack, &[4]);
+        let profile = builder.finish(
+            &[("samples", "count")],
+            ("cpu", "nanoseconds"),
+            10,
+            SystemTime::now(),
+            Duration::from_secs(1),
+        );
+
+        let fields = read_fields(&profile);
+        let count = |n| fields.iter().filter(|(field, _)| *field == n).count();
+        // two samples over the same locations, without the profiler's own frames
+        assert_eq!(count(2), 2);
+        assert!(count(4) > 0);
+        assert!(count(4) < stack.len());
+        let strings = strings(&profile);
+        assert_eq!(strings[0], "");
+        assert!(strings.iter().any(|s| s.contains("profiled_function")));
+        assert!(!strings.iter().any(|s| s.contains("capture_stack")));
+        assert!(strings.contains(&"samples".to_string()));
+    }
+
+    #[test]
+    fn test_heap_profile() {
+        let handle = thread::spawn(|| {
+            let started = std::time::Instant::now();
+            let mut kept = vec![];
+            while started.elapsed() < Duration::from_millis(500) {
+                kept.push(vec![0u8; 4096]);
+                thread::sleep(Duration::from_millis(1));
+            }
+            kept
+        });
+        let profile = profile_heap(Duration::from_millis(300), 4096).unwrap();
+        drop(handle.join().unwrap());
+
+        assert!(read_fields(&profile).iter().any(|(field, _)| *field == 2));
+        let strings = strings(&profile);
+        assert!(strings.contains(&"inuse_space".to_string()));
+        assert!(HEAP_SAMPLES.lock().unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cpu_profile() {
+        let handle = thread::spawn(|| {
+            let started = std::time::Instant::now();
+            let mut x = 0u64;
+            while started.elapsed() < Duration::from_millis(500) {
+                x = x.wrapping_mul(31).wrapping_add(7);
+            }
+            x
+        });
+        let profile = profile_cpu(Duration::from_millis(300), 100).unwrap();
+        handle.join().unwrap();
+
+        assert!(read_fields(&profile).iter().any(|(field, _)| *field == 2));
+        assert!(strings(&profile).contains(&"cpu".to_string()));
+        assert_eq!(
+            profile_cpu(Duration::from_millis(1), 0).unwrap_err(),
+            format!(
+                "The sampling frequency must be from 1 to {}",
+                MAX_CPU_FREQUENCY
+            )
+        );
+    }
+}
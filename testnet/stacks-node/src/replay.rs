@@ -0,0 +1,117 @@
+//! Replay of event observer payloads from the node's chainstate. Every canonical Stacks block in
+//! a range of heights is re-executed on top of its parent, without committing anything, and the
+//! resulting `/new_block` payload -- block, transaction receipts, events and matured rewards --
+//! is delivered to a single observer, exactly as it was when the block was first processed.
+//!
+//! The node should be stopped while events are replayed: re-executing a block takes the
+//! chainstate's write lock.
+
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::coordinator::BlockEventDispatcher;
+use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::chainstate::stacks::StacksBlockHeader;
+
+use crate::config::{EventKeyType, EventObserverConfig};
+use crate::event_dispatcher::EventDispatcher;
+use crate::Config;
+
+/// Re-executes the canonical Stacks blocks from `start_height` to `end_height` (inclusive, and
+/// capped at the chain tip) and delivers their `/new_block` events to the observer at
+/// `endpoint`.  The boot block has no transactions, so replay starts at height 1 at the
+/// earliest.  Returns the number of blocks replayed.
+pub fn replay_events(
+    config: &Config,
+    endpoint: &str,
+    start_height: u64,
+    end_height: u64,
+) -> Result<u64, String> {
+    if start_height > end_height {
+        return Err(format!(
+            "Start height {} is above end height {}",
+            start_height, end_height
+        ));
+    }
+    if !StacksChainState::header_index_root_path(config.get_chainstate_path()).exists() {
+        return Err("The node has not booted yet".to_string());
+    }
+    let sortdb = SortitionDB::open(&config.get_burn_db_file_path(), false)
+        .map_err(|e| format!("Failed to open sortition DB: {:?}", e))?;
+    let (mut chainstate, _) = StacksChainState::open(
+        config.is_mainnet(),
+        config.burnchain.chain_id,
+        &config.get_chainstate_path_str(),
+        Some(config.node.get_marf_opts()),
+    )
+    .map_err(|e| format!("Failed to open chainstate: {:?}", e))?;
+
+    let (tip_consensus_hash, tip_block_hash) =
+        SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn())
+            .map_err(|e| format!("Failed to load chain tip: {:?}", e))?;
+    let tip = StacksBlockHeader::make_index_block_hash(&tip_consensus_hash, &tip_block_hash);
+
+    let mut dispatcher = EventDispatcher::new();
+    dispatcher.register_observer(&EventObserverConfig {
+        endpoint: endpoint.to_string(),
+        events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
+    });
+
+    let mut replayed = 0;
+    for height in start_height.max(1)..=end_height {
+        let block_id = match chainstate
+            .index_conn()
+            .and_then(|index_conn| Ok(index_conn.get_ancestor_block_hash(height, &tip)?))
+            .map_err(|e| format!("Failed to load block at height {}: {:?}", height, e))?
+        {
+            Some(block_id) => block_id,
+            // past the chain tip
+            None => break,
+        };
+        let replayed_block = chainstate
+            .replay_block(&sortdb.index_conn(), &block_id)
+            .map_err(|e| format!("Failed to replay block {}: {:?}", &block_id, e))?;
+        let receipt = &replayed_block.epoch_receipt;
+
+        dispatcher.announce_block(
+            &replayed_block.block,
+            &receipt.header,
+            &receipt.tx_receipts,
+            &replayed_block.parent_block_id,
+            replayed_block.winning_block_txid,
+            &receipt.matured_rewards,
+            receipt.matured_rewards_info.as_ref(),
+            receipt.parent_burn_block_hash,
+            receipt.parent_burn_block_height,
+            receipt.parent_burn_block_timestamp,
+            &receipt.anchored_block_cost,
+            &receipt.parent_microblocks_cost,
+        );
+        info!(
+            "Replayed events of block {} at height {}",
+            &block_id, height;
+            "txs" => receipt.tx_receipts.len()
+        );
+        replayed += 1;
+    }
+    Ok(replayed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_events_checks_range() {
+        let mut config = Config::default();
+        config.node.working_dir = "/tmp/stacks-node-tests/replay_events".to_string();
+
+        assert_eq!(
+            replay_events(&config, "localhost:3700", 10, 9).unwrap_err(),
+            "Start height 10 is above end height 9"
+        );
+        assert_eq!(
+            replay_events(&config, "localhost:3700", 0, 10).unwrap_err(),
+            "The node has not booted yet"
+        );
+    }
+}
@@ -0,0 +1,792 @@
+//! Read replicas, configured by `[node.replica]`.
+//!
+//! A read replica serves the RPC interface from a chainstate it does not build itself: instead
+//! of syncing the burnchain and downloading and processing Stacks blocks, it ingests the backups
+//! that a primary node takes with `[node.backup]`, fetched from the primary's admin API with its
+//! auth token. The token would cross the network in the clear over plain HTTP, so a primary on
+//! another host has to be reached over TLS, through an `https://` URL (e.g. a TLS-terminating
+//! proxy in front of its admin API); a `host:port` primary must be a loopback address.
+//!
+//! Only what changed since the last ingested backup is transferred. The block files and the MARF
+//! blob files only grow, so the replica fetches the new block files and the new end of each blob
+//! file. The sqlite DBs, which come to several GB on mainnet, change in place: the replica keeps
+//! a mirror of the DBs of the last backup it ingested, fetches only the chunks whose hashes in
+//! the backup's manifest differ from the mirror's, and copies each DB that changed into the live
+//! DB with the sqlite backup API, so that the RPC interface keeps serving from them throughout.
+//! A DB that did not change is neither fetched nor copied.
+//!
+//! The DBs are put in place so that a DB only ever refers to data that is already there: new
+//! block files and tries first, then the chainstate DBs, and the sortition DB, which decides
+//! which chain tip the RPC interface serves, last.
+//!
+//! A replica does not relay the transactions posted to it, so a load balancer in front of
+//! replicas should send `POST /v2/transactions` to the primary.
+
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Seek, SeekFrom};
+use std::net::ToSocketAddrs;
+use std::path::{Component, Path, PathBuf};
+
+use reqwest::blocking::{Client, Response};
+use reqwest::Url;
+
+use crate::backup::{
+    self, BackupFile, BackupListing, BackupManifest, BACKUP_BLOCKS_DIR, BACKUP_CHUNK_SIZE,
+};
+use crate::config::ReplicaConfig;
+
+/// The last backup ingested, relative to `{working_dir}/{mode}`
+pub const REPLICA_STATE_FILE: &str = "replica.json";
+/// Where block files are downloaded to before they are put in place, relative to
+/// `{working_dir}/{mode}`
+const REPLICA_DOWNLOAD_DIR: &str = "replica-download";
+/// The sqlite DBs of the last backup ingested, byte for byte, relative to `{working_dir}/{mode}`.
+/// The DBs of the next backup are patched from these.
+const REPLICA_MIRROR_DIR: &str = "replica-mirror";
+
+/// The order to put the DBs of a backup in place in
+const REPLICA_DB_ORDER: &[&str] = &[
+    "chainstate/vm/clarity/marf.sqlite",
+    "chainstate/vm/index.sqlite",
+    "headers.sqlite",
+    "burnchain/burnchain.sqlite",
+    "burnchain/sortition/marf.sqlite",
+];
+
+/// Where a replica gets its backups from
+pub trait BackupSource {
+    /// The complete backups, oldest first
+    fn list_backups(&self) -> Result<Vec<BackupListing>, String>;
+    /// Writes the bytes of the file `path` of backup `height` from byte `offset` on, and at
+    /// most `length` of them if given, to `dest`, and returns how many there were.
+    fn fetch(
+        &self,
+        height: u64,
+        path: &str,
+        offset: u64,
+        length: Option<u64>,
+        dest: &mut File,
+    ) -> Result<u64, String>;
+}
+
+/// The base URL of the primary's admin API, given as an `https://` URL or as a loopback
+/// `host:port`, which is reached over plain HTTP.
+pub fn primary_url(primary: &str) -> Result<Url, String> {
+    if primary.starts_with("https://") {
+        let url =
+            Url::parse(primary).map_err(|e| format!("Invalid primary URL '{}': {}", primary, e))?;
+        if url.host().is_none() {
+            return Err(format!("Invalid primary URL '{}': no host", primary));
+        }
+        return Ok(url);
+    }
+    let addrs: Vec<_> = primary
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve primary '{}': {}", primary, e))?
+        .collect();
+    if addrs.is_empty() || addrs.iter().any(|addr| !addr.ip().is_loopback()) {
+        return Err(format!(
+            "Primary '{}' is not a loopback address; reach a remote primary over TLS with an https:// URL, so that the auth token is not sent in the clear",
+            primary
+        ));
+    }
+    Url::parse(&format!("http://{}", primary))
+        .map_err(|e| format!("Invalid primary address '{}': {}", primary, e))
+}
+
+/// A primary node, reached over its admin API
+pub struct PrimaryNode {
+    url: Url,
+    auth_token: String,
+    client: Client,
+}
+
+impl PrimaryNode {
+    pub fn new(config: &ReplicaConfig) -> Result<PrimaryNode, String> {
+        // backup files can take longer to download than any fixed timeout
+        let client = Client::builder()
+            .timeout(None)
+            .build()
+            .map_err(|e| format!("Failed to create the HTTP client: {}", e))?;
+        Ok(PrimaryNode {
+            url: primary_url(&config.primary)?,
+            auth_token: config.auth_token.clone(),
+            client,
+        })
+    }
+
+    /// Sends a GET request for `path` with `query`, and passes the response to `read_body`
+    fn get<F, T>(&self, path: &str, query: &[(&str, &str)], read_body: F) -> Result<T, String>
+    where
+        F: FnOnce(Response) -> Result<T, String>,
+    {
+        let mut url = self
+            .url
+            .join(path)
+            .map_err(|e| format!("Invalid primary URL '{}': {}", self.url, e))?;
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(&self.auth_token)
+            .send()
+            .map_err(|e| format!("Request to the primary at {} failed: {}", self.url, e))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            let error = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|body| body.get("error").and_then(|e| e.as_str()).map(String::from));
+            return Err(match error {
+                Some(error) => format!("Primary: {}", error),
+                None => format!("Primary: status({}) for {}", status, path),
+            });
+        }
+        read_body(response)
+    }
+}
+
+impl BackupSource for PrimaryNode {
+    fn list_backups(&self) -> Result<Vec<BackupListing>, String> {
+        self.get("/v2/admin/backups", &[], |response| {
+            response
+                .json()
+                .map_err(|e| format!("Failed to parse the primary's backups: {}", e))
+        })
+    }
+
+    fn fetch(
+        &self,
+        height: u64,
+        path: &str,
+        offset: u64,
+        length: Option<u64>,
+        dest: &mut File,
+    ) -> Result<u64, String> {
+        let mut query = vec![
+            ("height", height.to_string()),
+            ("path", path.to_string()),
+            ("offset", offset.to_string()),
+        ];
+        if let Some(length) = length {
+            query.push(("length", length.to_string()));
+        }
+        let query: Vec<(&str, &str)> = query.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        self.get("/v2/admin/backups/file", &query, |mut response| {
+            io::copy(&mut response, dest)
+                .map_err(|e| format!("Failed to fetch {} from the primary: {}", path, e))
+        })
+    }
+}
+
+/// The local path of `file`, making sure it stays inside `root`
+fn local_path(root: &Path, file: &BackupFile) -> Result<PathBuf, String> {
+    let path = Path::new(&file.path);
+    if path
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return Err(format!("Backup lists a file outside of it: {}", file.path));
+    }
+    Ok(root.join(path))
+}
+
+fn is_blobs_file(file: &BackupFile) -> bool {
+    file.path.ends_with(".blobs")
+}
+
+fn is_block_file(file: &BackupFile) -> bool {
+    Path::new(&file.path).starts_with(BACKUP_BLOCKS_DIR)
+}
+
+fn create_parent_dir(path: &Path) -> Result<(), String> {
+    match path.parent() {
+        Some(parent) => {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create {:?}: {}", parent, e))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Fetches all of `file` into `dest`
+fn fetch_file(
+    source: &dyn BackupSource,
+    height: u64,
+    file: &BackupFile,
+    dest: &Path,
+) -> Result<(), String> {
+    create_parent_dir(dest)?;
+    let mut dest_file =
+        File::create(dest).map_err(|e| format!("Failed to create {:?}: {}", dest, e))?;
+    let copied = source.fetch(height, &file.path, 0, None, &mut dest_file)?;
+    if copied != file.len {
+        return Err(format!(
+            "Fetched {} bytes of {}, which is {} bytes",
+            copied, file.path, file.len
+        ));
+    }
+    dest_file
+        .sync_all()
+        .map_err(|e| format!("Failed to sync {:?}: {}", dest, e))
+}
+
+/// Appends the tries that the local copy of the blob file `file` is missing
+fn fetch_blobs(
+    source: &dyn BackupSource,
+    height: u64,
+    file: &BackupFile,
+    local: &Path,
+) -> Result<(), String> {
+    let local_len = fs::metadata(local).map(|m| m.len()).unwrap_or(0);
+    if local_len > file.len {
+        return Err(format!(
+            "{:?} is longer than the primary's copy, so the replica's chainstate is not an earlier state of the primary's; start the replica from an empty working directory",
+            local
+        ));
+    }
+    if local_len == file.len {
+        return Ok(());
+    }
+    create_parent_dir(local)?;
+    let mut local_file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(local)
+        .map_err(|e| format!("Failed to open {:?}: {}", local, e))?;
+    let copied = source.fetch(height, &file.path, local_len, None, &mut local_file)?;
+    if local_len + copied != file.len {
+        return Err(format!(
+            "Fetched {} bytes of {} from byte {}, which is {} bytes",
+            copied, file.path, local_len, file.len
+        ));
+    }
+    local_file
+        .sync_all()
+        .map_err(|e| format!("Failed to sync {:?}: {}", local, e))
+}
+
+/// Brings `mirror`, the replica's copy of the sqlite DB `file` as of the last backup ingested, up
+/// to date with backup `height`.  Only the chunks whose hashes differ from the manifest's are
+/// fetched; without chunk hashes, or if the patched copy still does not match them, the DB is
+/// fetched whole.  A missing mirror is first seeded from `live`, most of whose pages match.
+fn sync_db_mirror(
+    source: &dyn BackupSource,
+    height: u64,
+    file: &BackupFile,
+    mirror: &Path,
+    live: &Path,
+) -> Result<(), String> {
+    if !file.chunks.is_empty() {
+        create_parent_dir(mirror)?;
+        if !mirror.exists() && live.exists() {
+            fs::copy(live, mirror)
+                .map_err(|e| format!("Failed to copy {:?} to {:?}: {}", live, mirror, e))?;
+        }
+        let mirror_chunks = if mirror.exists() {
+            backup::chunk_hashes(mirror)?
+        } else {
+            vec![]
+        };
+        let mut mirror_file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(mirror)
+            .map_err(|e| format!("Failed to open {:?}: {}", mirror, e))?;
+        for (i, chunk) in file.chunks.iter().enumerate() {
+            if mirror_chunks.get(i) == Some(chunk) {
+                continue;
+            }
+            let offset = i as u64 * BACKUP_CHUNK_SIZE;
+            let length = BACKUP_CHUNK_SIZE.min(file.len.saturating_sub(offset));
+            mirror_file
+                .seek(SeekFrom::Start(offset))
+                .map_err(|e| format!("Failed to seek in {:?}: {}", mirror, e))?;
+            let copied =
+                source.fetch(height, &file.path, offset, Some(length), &mut mirror_file)?;
+            if copied != length {
+                return Err(format!(
+                    "Fetched {} bytes of {} from byte {}, expected {}",
+                    copied, file.path, offset, length
+                ));
+            }
+        }
+        mirror_file
+            .set_len(file.len)
+            .and_then(|_| mirror_file.sync_all())
+            .map_err(|e| format!("Failed to write {:?}: {}", mirror, e))?;
+        if backup::chunk_hashes(mirror)? == file.chunks {
+            return Ok(());
+        }
+        warn!(
+            "Replica: patched copy of {} does not match the primary's; fetching it whole",
+            file.path
+        );
+    }
+
+    fetch_file(source, height, file, mirror)?;
+    if !file.chunks.is_empty() && backup::chunk_hashes(mirror)? != file.chunks {
+        return Err(format!(
+            "{} of backup {} does not match the chunk hashes in its manifest",
+            file.path, height
+        ));
+    }
+    Ok(())
+}
+
+/// The backup ingested last into the chainstate at `root`, if any
+pub fn read_replica_state(root: &Path) -> Option<BackupListing> {
+    let state = fs::read_to_string(root.join(REPLICA_STATE_FILE)).ok()?;
+    serde_json::from_str(&state).ok()
+}
+
+fn write_replica_state(root: &Path, listing: &BackupListing) -> Result<(), String> {
+    let state = serde_json::to_string_pretty(listing)
+        .map_err(|e| format!("Failed to serialize replica state: {}", e))?;
+    let tmp = root.join(format!("{}.tmp", REPLICA_STATE_FILE));
+    fs::write(&tmp, state).map_err(|e| format!("Failed to write {:?}: {}", tmp, e))?;
+    fs::rename(&tmp, root.join(REPLICA_STATE_FILE))
+        .map_err(|e| format!("Failed to write replica state: {}", e))
+}
+
+/// Puts backup `height`, described by `manifest`, in place in the chainstate at `root`, where
+/// the backup described by `previous` was ingested last, if any
+fn ingest_backup(
+    source: &dyn BackupSource,
+    root: &Path,
+    height: u64,
+    manifest: &BackupManifest,
+    previous: Option<&BackupManifest>,
+) -> Result<(), String> {
+    let download_dir = root.join(REPLICA_DOWNLOAD_DIR);
+    if download_dir.exists() {
+        fs::remove_dir_all(&download_dir)
+            .map_err(|e| format!("Failed to delete {:?}: {}", download_dir, e))?;
+    }
+
+    // new block files and tries, which nothing refers to until the DBs are in place
+    let mut dbs = vec![];
+    for file in manifest.files.iter() {
+        let local = local_path(root, file)?;
+        if is_blobs_file(file) {
+            fetch_blobs(source, height, file, &local)?;
+        } else if is_block_file(file) {
+            let local_len = fs::metadata(&local).map(|m| Some(m.len())).unwrap_or(None);
+            if local_len != Some(file.len) {
+                let download = local_path(&download_dir, file)?;
+                fetch_file(source, height, file, &download)?;
+                create_parent_dir(&local)?;
+                fs::rename(&download, &local)
+                    .map_err(|e| format!("Failed to move {:?} to {:?}: {}", download, local, e))?;
+            }
+        } else {
+            dbs.push(file);
+        }
+    }
+
+    dbs.sort_by_key(|file| {
+        REPLICA_DB_ORDER
+            .iter()
+            .position(|db| *db == file.path)
+            .unwrap_or(0)
+    });
+    let mirror_dir = root.join(REPLICA_MIRROR_DIR);
+    for file in dbs {
+        let local = local_path(root, file)?;
+        // the live DB already holds exactly these chunks
+        let unchanged = !file.chunks.is_empty()
+            && previous.map_or(false, |previous| previous.files.contains(file));
+        if unchanged && local.exists() {
+            debug!("Replica: {} is unchanged", file.path);
+            continue;
+        }
+        let mirror = local_path(&mirror_dir, file)?;
+        sync_db_mirror(source, height, file, &mirror, &local)?;
+        if local.exists() {
+            backup::backup_sqlite_db(&mirror, &local)?;
+        } else {
+            create_parent_dir(&local)?;
+            fs::copy(&mirror, &local)
+                .map_err(|e| format!("Failed to copy {:?} to {:?}: {}", mirror, local, e))?;
+        }
+    }
+
+    let _ = fs::remove_dir_all(&download_dir);
+    Ok(())
+}
+
+/// Ingests the newest backup of `source` into the chainstate at `root`, unless it was ingested
+/// already.  Returns the backup, if one was ingested.
+pub fn ingest_newest_backup(
+    source: &dyn BackupSource,
+    root: &Path,
+    mainnet: bool,
+    chain_id: u32,
+) -> Result<Option<BackupListing>, String> {
+    let newest = match source.list_backups()?.pop() {
+        Some(newest) => newest,
+        None => return Ok(None),
+    };
+    if newest.manifest.mainnet != mainnet || newest.manifest.chain_id != chain_id {
+        return Err(format!(
+            "The primary's backups are of chain ID {} (mainnet: {}), but this node is configured for chain ID {} (mainnet: {})",
+            newest.manifest.chain_id, newest.manifest.mainnet, chain_id, mainnet
+        ));
+    }
+    let current = read_replica_state(root);
+    if let Some(current) = current.as_ref() {
+        if current.height >= newest.height {
+            return Ok(None);
+        }
+    }
+
+    fs::create_dir_all(root).map_err(|e| format!("Failed to create {:?}: {}", root, e))?;
+    ingest_backup(
+        source,
+        root,
+        newest.height,
+        &newest.manifest,
+        current.as_ref().map(|current| &current.manifest),
+    )?;
+    write_replica_state(root, &newest)?;
+    Ok(Some(newest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+    use std::io::{Read, Write};
+
+    use rusqlite::{Connection, NO_PARAMS};
+
+    /// Backups in a local directory, laid out as the primary keeps them, and how many bytes
+    /// were fetched from them
+    struct LocalBackups(PathBuf, Cell<u64>);
+
+    impl LocalBackups {
+        fn new(dir: &Path) -> LocalBackups {
+            LocalBackups(dir.to_path_buf(), Cell::new(0))
+        }
+
+        /// The bytes fetched since the last call
+        fn take_fetched(&self) -> u64 {
+            self.1.replace(0)
+        }
+    }
+
+    impl BackupSource for LocalBackups {
+        fn list_backups(&self) -> Result<Vec<BackupListing>, String> {
+            backup::list_backup_manifests(&self.0)
+        }
+
+        fn fetch(
+            &self,
+            height: u64,
+            path: &str,
+            offset: u64,
+            length: Option<u64>,
+            dest: &mut File,
+        ) -> Result<u64, String> {
+            let mut file = File::open(self.0.join(height.to_string()).join(path)).unwrap();
+            file.seek(SeekFrom::Start(offset)).unwrap();
+            let mut bytes = vec![];
+            file.take(length.unwrap_or(u64::MAX))
+                .read_to_end(&mut bytes)
+                .unwrap();
+            dest.write_all(&bytes).unwrap();
+            self.1.set(self.1.get() + bytes.len() as u64);
+            Ok(bytes.len() as u64)
+        }
+    }
+
+    fn make_test_dir(name: &str) -> PathBuf {
+        let dir = PathBuf::from(format!("/tmp/stacks-node-tests/replica/{}", name));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Writes a backup at `height` with a sortition DB whose tip is `height`, a blob file of
+    /// `blobs_len` bytes, and a block file per height up to `height`
+    fn write_backup(dir: &Path, height: u64, blobs_len: usize) {
+        let backup = dir.join(height.to_string());
+        fs::create_dir_all(backup.join("burnchain/sortition")).unwrap();
+        fs::create_dir_all(backup.join("chainstate/blocks")).unwrap();
+
+        let sortdb = backup.join("burnchain/sortition/marf.sqlite");
+        Connection::open(&sortdb)
+            .unwrap()
+            .execute_batch(&format!(
+                "PRAGMA journal_mode = WAL; CREATE TABLE tip (height INTEGER); INSERT INTO tip VALUES ({});",
+                height
+            ))
+            .unwrap();
+        fs::write(backup::blobs_path(&sortdb), vec![7u8; blobs_len]).unwrap();
+        for block in 1..=height {
+            fs::write(
+                backup.join(format!("chainstate/blocks/{}", block)),
+                vec![block as u8; 10],
+            )
+            .unwrap();
+        }
+
+        write_manifest(
+            &backup,
+            height,
+            [
+                "burnchain/sortition/marf.sqlite",
+                "burnchain/sortition/marf.sqlite.blobs",
+            ]
+            .iter()
+            .map(|path| path.to_string())
+            .chain((1..=height).map(|block| format!("chainstate/blocks/{}", block))),
+        );
+    }
+
+    /// Writes a backup at `height` whose only file is a copy of the sqlite DB `db`, as its
+    /// sortition DB
+    fn write_db_backup(dir: &Path, height: u64, db: &Path) {
+        let backup = dir.join(height.to_string());
+        fs::create_dir_all(backup.join("burnchain/sortition")).unwrap();
+        fs::copy(db, backup.join("burnchain/sortition/marf.sqlite")).unwrap();
+        write_manifest(
+            &backup,
+            height,
+            vec!["burnchain/sortition/marf.sqlite".to_string()],
+        );
+    }
+
+    /// Writes the manifest of the backup at `height` in `backup`, listing `paths`
+    fn write_manifest<I: IntoIterator<Item = String>>(backup: &Path, height: u64, paths: I) {
+        let mut files = vec![];
+        for path in paths {
+            let len = fs::metadata(backup.join(&path)).unwrap().len();
+            let chunks = if path.ends_with(".sqlite") {
+                backup::chunk_hashes(&backup.join(&path)).unwrap()
+            } else {
+                vec![]
+            };
+            files.push(BackupFile { path, len, chunks });
+        }
+        let manifest = BackupManifest {
+            mainnet: false,
+            chain_id: 0x80000000,
+            burn_block_height: height,
+            burn_header_hash: "00".repeat(32),
+            consensus_hash: "00".repeat(20),
+            stacks_block_height: height,
+            stacks_block_id: "00".repeat(32),
+            files,
+            started_at: 0,
+            finished_at: 0,
+        };
+        fs::write(
+            backup.join(backup::BACKUP_MANIFEST_FILE),
+            serde_json::to_string(&manifest).unwrap(),
+        )
+        .unwrap();
+    }
+
+    fn tip_height(root: &Path) -> i64 {
+        Connection::open(root.join("burnchain/sortition/marf.sqlite"))
+            .unwrap()
+            .query_row("SELECT height FROM tip", NO_PARAMS, |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_ingest_backups() {
+        let primary = make_test_dir("primary");
+        let root = make_test_dir("root");
+        let source = LocalBackups::new(&primary);
+
+        assert_eq!(
+            ingest_newest_backup(&source, &root, false, 0x80000000).unwrap(),
+            None
+        );
+
+        write_backup(&primary, 2, 100);
+        let listing = ingest_newest_backup(&source, &root, false, 0x80000000)
+            .unwrap()
+            .unwrap();
+        assert_eq!(listing.height, 2);
+        assert_eq!(read_replica_state(&root).unwrap(), listing);
+        assert_eq!(tip_height(&root), 2);
+        assert!(root.join("chainstate/blocks/2").exists());
+        assert!(!root.join(REPLICA_DOWNLOAD_DIR).exists());
+
+        // nothing newer
+        assert_eq!(
+            ingest_newest_backup(&source, &root, false, 0x80000000).unwrap(),
+            None
+        );
+
+        // the DB is updated in place, so that open connections see the new backup
+        let conn = Connection::open(root.join("burnchain/sortition/marf.sqlite")).unwrap();
+        write_backup(&primary, 4, 150);
+        ingest_newest_backup(&source, &root, false, 0x80000000)
+            .unwrap()
+            .unwrap();
+        let height: i64 = conn
+            .query_row("SELECT height FROM tip", NO_PARAMS, |row| row.get(0))
+            .unwrap();
+        assert_eq!(height, 4);
+        assert_eq!(
+            fs::read(root.join("burnchain/sortition/marf.sqlite.blobs")).unwrap(),
+            vec![7u8; 150]
+        );
+        assert_eq!(
+            fs::read(root.join("chainstate/blocks/4")).unwrap(),
+            vec![4u8; 10]
+        );
+
+        // a different chain
+        assert!(ingest_newest_backup(&source, &root, true, 1)
+            .unwrap_err()
+            .contains("chain ID"));
+    }
+
+    #[test]
+    fn test_ingest_db_chunks() {
+        let primary = make_test_dir("chunks-primary");
+        let root = make_test_dir("chunks-root");
+        let db = make_test_dir("chunks-db").join("marf.sqlite");
+        let source = LocalBackups::new(&primary);
+        let sortdb_path = "burnchain/sortition/marf.sqlite";
+        let mirror = root.join(REPLICA_MIRROR_DIR).join(sortdb_path);
+
+        let conn = Connection::open(&db).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE tip (height INTEGER); INSERT INTO tip VALUES (1); \
+             CREATE TABLE data (bytes BLOB);",
+        )
+        .unwrap();
+        for _ in 0..4 {
+            conn.execute("INSERT INTO data VALUES (randomblob(1048576))", NO_PARAMS)
+                .unwrap();
+        }
+        let len = fs::metadata(&db).unwrap().len();
+        assert!(len > 4 * BACKUP_CHUNK_SIZE);
+
+        write_db_backup(&primary, 1, &db);
+        ingest_newest_backup(&source, &root, false, 0x80000000)
+            .unwrap()
+            .unwrap();
+        assert_eq!(source.take_fetched(), len);
+        assert_eq!(tip_height(&root), 1);
+
+        // only the chunks holding the changed pages are fetched
+        conn.execute("UPDATE tip SET height = 2", NO_PARAMS)
+            .unwrap();
+        write_db_backup(&primary, 2, &db);
+        ingest_newest_backup(&source, &root, false, 0x80000000)
+            .unwrap()
+            .unwrap();
+        assert_eq!(tip_height(&root), 2);
+        let fetched = source.take_fetched();
+        assert!(
+            fetched > 0 && fetched <= 2 * BACKUP_CHUNK_SIZE,
+            "{}",
+            fetched
+        );
+        assert_eq!(
+            fs::read(&mirror).unwrap(),
+            fs::read(primary.join("2").join(sortdb_path)).unwrap()
+        );
+
+        // an unchanged DB is neither fetched nor copied into the live DB
+        let modified = || {
+            fs::metadata(root.join(sortdb_path))
+                .unwrap()
+                .modified()
+                .unwrap()
+        };
+        let before = modified();
+        write_db_backup(&primary, 3, &db);
+        ingest_newest_backup(&source, &root, false, 0x80000000)
+            .unwrap()
+            .unwrap();
+        assert_eq!(source.take_fetched(), 0);
+        assert_eq!(modified(), before);
+        assert_eq!(read_replica_state(&root).unwrap().height, 3);
+
+        // a mirror that no longer matches the last backup is repaired
+        let mut mirror_file = OpenOptions::new().write(true).open(&mirror).unwrap();
+        mirror_file
+            .seek(SeekFrom::Start(3 * BACKUP_CHUNK_SIZE))
+            .unwrap();
+        mirror_file.write_all(&[0xff; 16]).unwrap();
+        drop(mirror_file);
+        conn.execute("UPDATE tip SET height = 4", NO_PARAMS)
+            .unwrap();
+        write_db_backup(&primary, 4, &db);
+        ingest_newest_backup(&source, &root, false, 0x80000000)
+            .unwrap()
+            .unwrap();
+        assert_eq!(tip_height(&root), 4);
+        assert!(source.take_fetched() <= 3 * BACKUP_CHUNK_SIZE);
+        assert_eq!(
+            fs::read(&mirror).unwrap(),
+            fs::read(primary.join("4").join(sortdb_path)).unwrap()
+        );
+
+        // a missing mirror is seeded from the live DB
+        fs::remove_dir_all(root.join(REPLICA_MIRROR_DIR)).unwrap();
+        conn.execute("UPDATE tip SET height = 5", NO_PARAMS)
+            .unwrap();
+        write_db_backup(&primary, 5, &db);
+        ingest_newest_backup(&source, &root, false, 0x80000000)
+            .unwrap()
+            .unwrap();
+        assert_eq!(tip_height(&root), 5);
+        assert!(source.take_fetched() < len);
+    }
+
+    #[test]
+    fn test_fetch_blobs() {
+        let primary = make_test_dir("blobs-primary");
+        let root = make_test_dir("blobs-root");
+        let source = LocalBackups::new(&primary);
+        write_backup(&primary, 1, 100);
+        let file = BackupFile {
+            path: "burnchain/sortition/marf.sqlite.blobs".to_string(),
+            len: 100,
+            chunks: vec![],
+        };
+        let local = root.join("marf.sqlite.blobs");
+
+        // only the missing bytes are fetched
+        fs::write(&local, vec![1u8; 60]).unwrap();
+        fetch_blobs(&source, 1, &file, &local).unwrap();
+        let blobs = fs::read(&local).unwrap();
+        assert_eq!(blobs.len(), 100);
+        assert_eq!(&blobs[..60], &vec![1u8; 60][..]);
+        assert_eq!(&blobs[60..], &vec![7u8; 40][..]);
+
+        // the replica is ahead of the primary
+        fs::write(&local, vec![1u8; 120]).unwrap();
+        assert!(fetch_blobs(&source, 1, &file, &local).is_err());
+    }
+
+    #[test]
+    fn test_local_path() {
+        let root = Path::new("/data");
+        let file = |path: &str| BackupFile {
+            path: path.to_string(),
+            len: 0,
+            chunks: vec![],
+        };
+        assert_eq!(
+            local_path(root, &file("chainstate/vm/index.sqlite")).unwrap(),
+            PathBuf::from("/data/chainstate/vm/index.sqlite")
+        );
+        assert!(local_path(root, &file("../etc/passwd")).is_err());
+        assert!(local_path(root, &file("/etc/passwd")).is_err());
+    }
+}
@@ -1,12 +1,15 @@
+use super::scenario::Scenario;
 use super::RunLoopCallbacks;
 use crate::burnchains::Error as BurnchainControllerError;
 use crate::{
-    BitcoinRegtestController, BurnchainController, ChainTip, Config, MocknetController, Node,
+    BitcoinRegtestController, BurnchainController, BurnchainTip, ChainTip, Config,
+    MocknetController, Node, Tenure,
 };
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
 use stacks::chainstate::stacks::db::ClarityTx;
 use stacks::net::atlas::AttachmentInstance;
 use stacks::types::chainstate::BurnchainHeaderHash;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::mpsc::{sync_channel, Receiver};
 
 /// RunLoop is coordinating a simulated burnchain and some simulated nodes
@@ -16,6 +19,7 @@ pub struct RunLoop {
     pub node: Node,
     pub callbacks: RunLoopCallbacks,
     attachments_rx: Option<Receiver<HashSet<AttachmentInstance>>>,
+    scenario: Option<Scenario>,
 }
 
 impl RunLoop {
@@ -38,6 +42,55 @@ impl RunLoop {
             node,
             callbacks: RunLoopCallbacks::new(),
             attachments_rx: Some(attachments_rx),
+            scenario: None,
+        }
+    }
+
+    /// Drives the run loop through the per-round actions described by `scenario`.
+    pub fn set_scenario(&mut self, scenario: Scenario) {
+        self.scenario = Some(scenario);
+    }
+
+    /// Submits the scenario's transactions for `round_index` to the tenure's mempool.
+    fn submit_scenario_transactions(
+        &mut self,
+        round_index: u64,
+        burnchain_tip: &BurnchainTip,
+        tenure: &mut Tenure,
+        sortdb: &SortitionDB,
+    ) {
+        let transactions = match self
+            .scenario
+            .as_ref()
+            .and_then(|scenario| scenario.get_round(round_index))
+        {
+            Some(round) => round.transactions.clone(),
+            None => return,
+        };
+
+        let stacks_epoch = SortitionDB::get_stacks_epoch(
+            sortdb.conn(),
+            burnchain_tip.block_snapshot.block_height + 1,
+        )
+        .expect("FATAL: failed to query sortition DB for epoch")
+        .expect("FATAL: no epoch defined for the next burnchain block");
+        let consensus_hash = tenure.parent_block.metadata.consensus_hash.clone();
+        let block_hash = tenure.parent_block.metadata.anchored_header.block_hash();
+
+        for tx in transactions.into_iter() {
+            if let Err(e) = tenure.mem_pool.submit_raw(
+                &mut self.node.chain_state,
+                &consensus_hash,
+                &block_hash,
+                tx,
+                &stacks_epoch.block_limit,
+                &stacks_epoch.epoch_id,
+            ) {
+                warn!(
+                    "Scenario: failed to submit transaction in round {}: {:?}",
+                    round_index, e
+                );
+            }
         }
     }
 
@@ -144,6 +197,14 @@ impl RunLoop {
             &burnchain.sortdb_ref().index_conn(),
         );
 
+        // Stacks blocks produced so far, and the sortitions that selected them, by round.
+        // Scenarios can fork off of any of them.
+        let mut produced_blocks = HashMap::new();
+        produced_blocks.insert(
+            round_index,
+            (chain_tip.clone(), last_sortitioned_block.clone()),
+        );
+
         // If the node we're looping on won the sortition, initialize and configure the next tenure
         if won_sortition {
             leader_tenure = self.node.initiate_new_tenure();
@@ -156,8 +217,42 @@ impl RunLoop {
                 return Ok(());
             }
 
+            let fork_from_round = self
+                .scenario
+                .as_ref()
+                .and_then(|scenario| scenario.get_fork_from_round(round_index));
+            if let Some(fork_from_round) = fork_from_round {
+                match produced_blocks.get(&fork_from_round) {
+                    Some((parent, parent_sortition)) => {
+                        info!(
+                            "Scenario: round {} forks off of the block produced in round {}",
+                            round_index, fork_from_round
+                        );
+                        leader_tenure = self.node.initiate_tenure_at(
+                            parent,
+                            parent_sortition,
+                            &burnchain.sortdb_ref().index_conn(),
+                        );
+                    }
+                    None => warn!(
+                        "Scenario: round {} did not produce a block to fork off of",
+                        fork_from_round
+                    ),
+                }
+            }
+
+            let skip_sortition = self
+                .scenario
+                .as_ref()
+                .map(|scenario| scenario.should_skip_sortition(round_index))
+                .unwrap_or(false);
+
             // Run the last initialized tenure
             let artifacts_from_tenure = match leader_tenure {
+                Some(_) if skip_sortition => {
+                    info!("Scenario: skipping sortition in round {}", round_index);
+                    None
+                }
                 Some(mut tenure) => {
                     self.callbacks.invoke_new_tenure(
                         round_index,
@@ -165,6 +260,12 @@ impl RunLoop {
                         &chain_tip,
                         &mut tenure,
                     );
+                    self.submit_scenario_transactions(
+                        round_index,
+                        &burnchain_tip,
+                        &mut tenure,
+                        burnchain.sortdb_ref(),
+                    );
                     tenure.run(&burnchain.sortdb_ref().index_conn())
                 }
                 None => None,
@@ -200,7 +301,7 @@ impl RunLoop {
 
             match artifacts_from_tenure {
                 // Pass if we're missing the artifacts from the current tenure.
-                None => continue,
+                None => {}
                 Some(ref artifacts) => {
                     // Have the node process its tenure.
                     // We should have some additional checks here, and ensure that the previous artifacts are legit.
@@ -218,12 +319,24 @@ impl RunLoop {
                         &mut self.node.chain_state,
                         &burnchain.sortdb_ref().index_conn(),
                     );
+
+                    produced_blocks.insert(
+                        round_index,
+                        (chain_tip.clone(), last_sortitioned_block.clone()),
+                    );
                 }
             };
 
             // If won sortition, initialize and configure the next tenure
             if won_sortition {
                 leader_tenure = self.node.initiate_new_tenure();
+            } else if skip_sortition {
+                // The skipped tenure's VRF proof is stale by now: start over on the same parent.
+                leader_tenure = self.node.initiate_tenure_at(
+                    &chain_tip,
+                    &last_sortitioned_block,
+                    &burnchain.sortdb_ref().index_conn(),
+                );
             }
 
             round_index += 1;
@@ -1,5 +1,7 @@
 pub mod helium;
 pub mod neon;
+pub mod replica;
+pub mod scenario;
 
 use crate::{BurnchainController, BurnchainTip, ChainTip, Tenure};
 
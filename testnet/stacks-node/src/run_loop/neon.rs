@@ -26,16 +26,27 @@ use stacks::chainstate::coordinator::{
     Error as coord_error,
 };
 use stacks::chainstate::stacks::db::{ChainStateBootData, StacksChainState};
-use stacks::net::atlas::{AtlasConfig, Attachment, AttachmentInstance, ATTACHMENTS_CHANNEL_SIZE};
+use stacks::monitoring;
+use stacks::net::atlas::{Attachment, AttachmentInstance, ATTACHMENTS_CHANNEL_SIZE};
 use stacks::util_lib::db::Error as db_error;
-use stx_genesis::GenesisData;
 
+use crate::burnchain_reorg::detect_burnchain_reorg;
+use crate::burnchains::block_notifier::spawn_block_notifier;
+use crate::config::EventKeyType;
+use crate::diagnostics::{DiagnosticDump, PeerSnapshot};
+use crate::event_dispatcher::ChainEventSubscriber;
+use crate::grpc::start_serving_grpc;
 use crate::monitoring::start_serving_monitoring_metrics;
 use crate::neon_node::StacksNode;
 use crate::node::use_test_genesis_chainstate;
+use crate::stall_watchdog::{spawn_stall_watchdog, RemediationRequests};
+use crate::supervisor::{self, Heartbeat};
+use crate::sync_progress::spawn_sync_progress_logger;
 use crate::syncctl::{PoxSyncWatchdog, PoxSyncWatchdogComms};
 use crate::{
-    node::{get_account_balances, get_account_lockups, get_names, get_namespaces},
+    node::{
+        get_account_balances, get_account_lockups, get_genesis_data, get_names, get_namespaces,
+    },
     BitcoinRegtestController, BurnchainController, Config, EventDispatcher, Keychain,
 };
 
@@ -134,6 +145,12 @@ pub struct RunLoop {
     is_miner: Option<bool>,                // not known until .start() is called
     burnchain: Option<Burnchain>,          // not known until .start() is called
     pox_watchdog_comms: PoxSyncWatchdogComms,
+    /// Beaten by the p2p thread on every pass; feeds the supervisor's watchdog.
+    heartbeat: Heartbeat,
+    /// Remediations the stall watchdog asked for
+    remediation_requests: RemediationRequests,
+    /// The p2p thread's conversations, for the diagnostic dump
+    peer_snapshot: PeerSnapshot,
 }
 
 /// Write to stderr in an async-safe manner.
@@ -154,6 +171,40 @@ fn async_safe_write_stderr(msg: &str) {
     }
 }
 
+/// Set up a termination handler that has a signal set `keep_running_writer` to false, and has
+/// SIGUSR2 write a diagnostic report with `diagnostics`.  Panics if called more than once.
+pub fn setup_termination_handler(
+    keep_running_writer: Arc<AtomicBool>,
+    diagnostics: DiagnosticDump,
+) {
+    let install = termination::set_handler(move |sig_id| match sig_id {
+        SignalId::DumpDiagnostics => {
+            info!("Caught SIGUSR2; writing diagnostic report");
+            diagnostics.spawn();
+        }
+        SignalId::Bus => {
+            let msg = "Caught SIGBUS; crashing immediately and dumping core\n";
+            async_safe_write_stderr(msg);
+            unsafe {
+                libc::abort();
+            }
+        }
+        _ => {
+            let msg = format!("Graceful termination request received (signal `{}`), will complete the ongoing runloop cycles and terminate\n", sig_id);
+            async_safe_write_stderr(&msg);
+            keep_running_writer.store(false, Ordering::SeqCst);
+        }
+    });
+
+    if let Err(e) = install {
+        // integration tests can do this
+        if cfg!(test) {
+        } else {
+            panic!("FATAL: error setting termination handler - {}", e);
+        }
+    }
+}
+
 impl RunLoop {
     /// Sets up a runloop and node, given a config.
     pub fn new(config: Config) -> Self {
@@ -177,6 +228,9 @@ impl RunLoop {
             is_miner: None,
             burnchain: None,
             pox_watchdog_comms,
+            heartbeat: Heartbeat::new(),
+            remediation_requests: RemediationRequests::new(),
+            peer_snapshot: PeerSnapshot::new(),
         }
     }
 
@@ -216,6 +270,17 @@ impl RunLoop {
         self.event_dispatcher.clone()
     }
 
+    /// Registers an in-process subscriber for the events selected by `events_keys`, as if it
+    /// were an event observer in the config. Must be called before `start()`.
+    pub fn register_subscriber(
+        &mut self,
+        subscriber: Arc<dyn ChainEventSubscriber>,
+        events_keys: &[EventKeyType],
+    ) {
+        self.event_dispatcher
+            .register_subscriber(subscriber, events_keys);
+    }
+
     pub fn is_miner(&self) -> bool {
         self.is_miner.unwrap_or(false)
     }
@@ -228,6 +293,18 @@ impl RunLoop {
         self.should_keep_running.clone()
     }
 
+    pub fn get_heartbeat(&self) -> Heartbeat {
+        self.heartbeat.clone()
+    }
+
+    pub fn get_remediation_requests(&self) -> RemediationRequests {
+        self.remediation_requests.clone()
+    }
+
+    pub fn get_peer_snapshot(&self) -> PeerSnapshot {
+        self.peer_snapshot.clone()
+    }
+
     pub fn get_burnchain(&self) -> Burnchain {
         self.burnchain
             .clone()
@@ -243,29 +320,10 @@ impl RunLoop {
     /// Set up termination handler.  Have a signal set the `should_keep_running` atomic bool to
     /// false.  Panics of called more than once.
     fn setup_termination_handler(&self) {
-        let keep_running_writer = self.should_keep_running.clone();
-        let install = termination::set_handler(move |sig_id| match sig_id {
-            SignalId::Bus => {
-                let msg = "Caught SIGBUS; crashing immediately and dumping core\n";
-                async_safe_write_stderr(msg);
-                unsafe {
-                    libc::abort();
-                }
-            }
-            _ => {
-                let msg = format!("Graceful termination request received (signal `{}`), will complete the ongoing runloop cycles and terminate\n", sig_id);
-                async_safe_write_stderr(&msg);
-                keep_running_writer.store(false, Ordering::SeqCst);
-            }
-        });
-
-        if let Err(e) = install {
-            // integration tests can do this
-            if cfg!(test) {
-            } else {
-                panic!("FATAL: error setting termination handler - {}", e);
-            }
-        }
+        setup_termination_handler(
+            self.should_keep_running.clone(),
+            DiagnosticDump::new(self.config.clone(), self.peer_snapshot.clone()),
+        );
     }
 
     /// Determine if we're the miner.
@@ -285,9 +343,11 @@ impl RunLoop {
             .expect("FATAL: unable to determine Bitcoin address for miner");
             info!("Miner node: checking UTXOs at address: {}", btc_addr);
 
-            match burnchain.create_wallet_if_dne() {
-                Err(e) => warn!("Error when creating wallet: {:?}", e),
-                _ => {}
+            if self.config.burnchain.utxo_scan_start_height.is_none() {
+                match burnchain.create_wallet_if_dne() {
+                    Err(e) => warn!("Error when creating wallet: {:?}", e),
+                    _ => {}
+                }
             }
 
             let utxos =
@@ -296,6 +356,9 @@ impl RunLoop {
                 if self.config.node.mock_mining {
                     info!("No UTXOs found, but configured to mock mine");
                     true
+                } else if let Some(start_height) = self.config.burnchain.utxo_scan_start_height {
+                    error!("UTXOs not found - switching off mining, will run as a Follower node. If this is unexpected, please ensure that the address {} was funded at or after burnchain.utxo_scan_start_height ({})", btc_addr, start_height);
+                    false
                 } else {
                     error!("UTXOs not found - switching off mining, will run as a Follower node. If this is unexpected, please ensure that your bitcoind instance is indexing transactions for the address {} (importaddress)", btc_addr);
                     false
@@ -395,6 +458,7 @@ impl RunLoop {
         coordinator_receivers: CoordinatorReceivers,
     ) -> (JoinHandle<()>, Receiver<HashSet<AttachmentInstance>>) {
         let use_test_genesis_data = use_test_genesis_chainstate(&self.config);
+        let genesis_data = get_genesis_data(&self.config, use_test_genesis_data);
 
         // load up genesis balances
         let initial_balances = self
@@ -405,8 +469,8 @@ impl RunLoop {
             .collect();
 
         // load up genesis Atlas attachments
-        let mut atlas_config = AtlasConfig::default(self.config.is_mainnet());
-        let genesis_attachments = GenesisData::new(use_test_genesis_data)
+        let mut atlas_config = self.config.make_atlas_config();
+        let genesis_attachments = genesis_data
             .read_name_zonefiles()
             .into_iter()
             .map(|z| Attachment::new(z.zonefile_content.as_bytes().to_vec()))
@@ -421,16 +485,19 @@ impl RunLoop {
             first_burnchain_block_height: burnchain_config.first_block_height as u32,
             first_burnchain_block_timestamp: burnchain_config.first_block_timestamp,
             pox_constants: burnchain_config.pox_constants.clone(),
-            get_bulk_initial_lockups: Some(Box::new(move || {
-                get_account_lockups(use_test_genesis_data)
+            get_bulk_initial_lockups: Some(Box::new({
+                let genesis_data = genesis_data.clone();
+                move || get_account_lockups(&genesis_data)
             })),
-            get_bulk_initial_balances: Some(Box::new(move || {
-                get_account_balances(use_test_genesis_data)
+            get_bulk_initial_balances: Some(Box::new({
+                let genesis_data = genesis_data.clone();
+                move || get_account_balances(&genesis_data)
             })),
-            get_bulk_initial_namespaces: Some(Box::new(move || {
-                get_namespaces(use_test_genesis_data)
+            get_bulk_initial_namespaces: Some(Box::new({
+                let genesis_data = genesis_data.clone();
+                move || get_namespaces(&genesis_data)
             })),
-            get_bulk_initial_names: Some(Box::new(move || get_names(use_test_genesis_data))),
+            get_bulk_initial_names: Some(Box::new(move || get_names(&genesis_data))),
         };
 
         let (chain_state_db, receipts) = StacksChainState::open_and_exec(
@@ -444,7 +511,7 @@ impl RunLoop {
         self.event_dispatcher.dispatch_boot_receipts(receipts);
 
         // NOTE: re-instantiate AtlasConfig so we don't have to keep the genesis attachments around
-        let moved_atlas_config = AtlasConfig::default(self.config.is_mainnet());
+        let moved_atlas_config = self.config.make_atlas_config();
         let moved_config = self.config.clone();
         let moved_burnchain_config = burnchain_config.clone();
         let mut coordinator_dispatcher = self.event_dispatcher.clone();
@@ -453,8 +520,8 @@ impl RunLoop {
         let coordinator_thread_handle = thread::Builder::new()
             .name("chains-coordinator".to_string())
             .spawn(move || {
-                let mut cost_estimator = moved_config.make_cost_estimator();
-                let mut fee_estimator = moved_config.make_fee_estimator();
+                let mut cost_estimator = moved_config.make_tracked_cost_estimator();
+                let mut fee_estimator = moved_config.make_tracked_fee_estimator();
 
                 ChainsCoordinator::run(
                     chain_state_db,
@@ -492,6 +559,14 @@ impl RunLoop {
         }
     }
 
+    /// Start the gRPC API. Must run before the event dispatcher is handed out, since the server
+    /// subscribes to it.
+    fn start_grpc(&mut self) {
+        if let Some(grpc_bind) = self.config.node.grpc_bind.clone() {
+            start_serving_grpc(grpc_bind, &self.config, &mut self.event_dispatcher);
+        }
+    }
+
     /// Get the sortition DB's highest block height, aligned to a reward cycle boundary, and the
     /// highest sortition.
     /// Returns (height at rc start, sortition)
@@ -537,20 +612,46 @@ impl RunLoop {
             .expect("Run loop already started, can only start once after initialization.");
 
         self.setup_termination_handler();
+        supervisor::set_notify_enabled(self.config.node.supervisor_notify);
+        supervisor::start_watchdog(self.heartbeat.clone(), self.should_keep_running.clone());
+        self.start_grpc();
+        supervisor::notify_status("Syncing burnchain headers");
         let mut burnchain =
             self.instantiate_burnchain_state(burnchain_opt, coordinator_senders.clone());
 
         let burnchain_config = burnchain.get_burnchain();
         self.burnchain = Some(burnchain_config.clone());
 
+        if self.config.burnchain.p2p_block_notifications {
+            spawn_block_notifier(burnchain.dup_indexer(), self.pox_watchdog_comms.clone());
+        }
+
+        if self.config.node.sync_progress_log_interval_secs > 0 {
+            spawn_sync_progress_logger(
+                self.config.node.sync_progress_log_interval_secs,
+                self.pox_watchdog_comms.clone(),
+                self.should_keep_running.clone(),
+            );
+        }
+
         let is_miner = self.check_is_miner(&mut burnchain);
         self.is_miner = Some(is_miner);
 
         // have headers; boot up the chains coordinator and instantiate the chain state
+        supervisor::notify_status("Booting chainstate");
         let (coordinator_thread_handle, attachments_rx) =
             self.spawn_chains_coordinator(&burnchain_config, coordinator_receivers);
         self.instantiate_pox_watchdog();
         self.start_prometheus();
+        if let Some(watchdog_config) = self.config.node.stall_watchdog.clone() {
+            spawn_stall_watchdog(
+                &self.config,
+                watchdog_config,
+                self.event_dispatcher.clone(),
+                self.remediation_requests.clone(),
+                self.should_keep_running.clone(),
+            );
+        }
 
         // We announce a new burn block so that the chains coordinator
         // can resume prior work and handle eventual unprocessed sortitions
@@ -580,6 +681,7 @@ impl RunLoop {
             coordinator_senders.clone(),
             attachments_rx,
         );
+        supervisor::notify_ready();
 
         // Wait for all pending sortitions to process
         let mut burnchain_tip = burnchain
@@ -608,8 +710,11 @@ impl RunLoop {
         );
 
         let mut last_tenure_sortition_height = 0;
+        // the burnchain tip whose blocks the node has processed, to detect reorgs
+        let mut last_processed_burnchain_tip: Option<BlockSnapshot> = None;
         loop {
             if !self.should_keep_running.load(Ordering::SeqCst) {
+                supervisor::notify_stopping();
                 // The p2p thread relies on the same atomic_bool, it will
                 // discontinue its execution after completing its ongoing runloop epoch.
                 info!("Terminating p2p process");
@@ -656,6 +761,11 @@ impl RunLoop {
                     break;
                 }
 
+                if self.remediation_requests.take_reconnect_bitcoind() {
+                    info!("Stall watchdog: reconnecting to bitcoind");
+                    burnchain.reconnect_bitcoind();
+                }
+
                 let (next_burnchain_tip, tip_burnchain_height) =
                     match burnchain.sync(Some(burnchain_height + 1)) {
                         Ok(x) => x,
@@ -672,8 +782,40 @@ impl RunLoop {
                 let sortition_tip = &burnchain_tip.block_snapshot.sortition_id;
                 let next_sortition_height = burnchain_tip.block_snapshot.block_height;
 
+                if let Some(last_tip) = last_processed_burnchain_tip.as_ref() {
+                    match detect_burnchain_reorg(
+                        burnchain.sortdb_ref(),
+                        last_tip,
+                        &burnchain_tip.block_snapshot,
+                        self.config.burnchain.deep_reorg_depth,
+                    ) {
+                        Ok(Some(reorg)) => {
+                            if reorg.deep {
+                                error!("Deep burnchain reorg: {} burn blocks orphaned", reorg.depth;
+                                       "fork_height" => reorg.fork_height,
+                                       "old_tip_height" => reorg.old_tip_height,
+                                       "new_tip_height" => reorg.new_tip_height);
+                            } else {
+                                warn!("Burnchain reorg: {} burn blocks orphaned", reorg.depth;
+                                      "fork_height" => reorg.fork_height,
+                                      "old_tip_height" => reorg.old_tip_height,
+                                      "new_tip_height" => reorg.new_tip_height);
+                            }
+                            monitoring::log_burnchain_reorg(reorg.depth, reorg.deep);
+                            self.event_dispatcher.process_burnchain_reorg(&reorg);
+
+                            // have the node process the new fork's blocks from the fork point on
+                            sortition_db_height = cmp::min(sortition_db_height, reorg.fork_height);
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            warn!("Failed to check for a burnchain reorg: {:?}", &e);
+                        }
+                    }
+                }
+
                 if next_sortition_height != last_tenure_sortition_height {
-                    info!(
+                    debug!(
                         "Downloaded burnchain blocks up to height {}; target height is {}; next_sortition_height = {}, sortition_db_height = {}",
                         burnchain_height, target_burnchain_block_height, next_sortition_height, sortition_db_height
                     );
@@ -705,6 +847,8 @@ impl RunLoop {
 
                         // Have the node process the new block, that can include, or not, a sortition.
                         node.process_burnchain_state(burnchain.sortdb_mut(), sortition_id, ibd);
+                        self.event_dispatcher
+                            .process_tenure_times(&block.parent_burn_header_hash);
 
                         // Now, tell the relayer to check if it won a sortition during this block,
                         // and, if so, to process and advertize the block.  This is basically a
@@ -725,6 +869,7 @@ impl RunLoop {
                     );
 
                     sortition_db_height = next_sortition_height;
+                    last_processed_burnchain_tip = Some(burnchain_tip.block_snapshot.clone());
                 } else if ibd {
                     // drive block processing after we reach the burnchain tip.
                     // we may have downloaded all the blocks already,
@@ -746,6 +891,12 @@ impl RunLoop {
                     .expect("BUG: block height is not in a reward cycle")
                     + 1,
             );
+            supervisor::notify_status(&format!(
+                "{} burnchain blocks up to height {} (remote height {})",
+                if ibd { "Syncing" } else { "Synced" },
+                sortition_db_height,
+                remote_chain_height
+            ));
 
             if sortition_db_height >= burnchain_height && !ibd {
                 let canonical_stacks_tip_height =
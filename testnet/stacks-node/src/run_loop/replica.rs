@@ -0,0 +1,299 @@
+use std::cmp;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use std::collections::HashSet;
+
+use stacks::burnchains::Burnchain;
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::core::mempool::MemPoolDB;
+use stacks::cost_estimates::metrics::UnitMetric;
+use stacks::cost_estimates::UnitEstimator;
+use stacks::net::dns::DNSResolver;
+use stacks::net::relay::Relayer;
+use stacks::net::rpc::RPCHandlerArgs;
+use stacks::util::get_epoch_time_secs;
+
+use crate::diagnostics::{DiagnosticDump, PeerSnapshot};
+use crate::neon_node::make_peer_network;
+use crate::replica::{self, PrimaryNode};
+use crate::run_loop::neon::setup_termination_handler;
+use crate::{BitcoinRegtestController, Config};
+
+/// How long the p2p loop waits for network events before checking for a new backup
+const POLL_TIMEOUT_MS: u64 = 1000;
+/// How often to check for the first backup, when the replica has none to serve yet
+const FIRST_BACKUP_POLL_INTERVAL_SECS: u64 = 60;
+
+/// Runs a read replica: ingests the backups of the primary node named by `[node.replica]`, and
+/// serves the RPC interface from them.
+pub struct RunLoop {
+    config: Config,
+    should_keep_running: Arc<AtomicBool>,
+    /// The height of the backup ingested last; the p2p loop reopens its DBs when it changes.
+    ingested_height: Arc<AtomicU64>,
+    /// The p2p loop's conversations, for the diagnostic dump
+    peer_snapshot: PeerSnapshot,
+}
+
+impl RunLoop {
+    pub fn new(config: Config) -> RunLoop {
+        assert!(
+            config.node.replica.is_some(),
+            "FATAL: replica run loop started without [node.replica]"
+        );
+        RunLoop {
+            config,
+            should_keep_running: Arc::new(AtomicBool::new(true)),
+            ingested_height: Arc::new(AtomicU64::new(0)),
+            peer_snapshot: PeerSnapshot::new(),
+        }
+    }
+
+    fn root_path(&self) -> PathBuf {
+        let mut root = PathBuf::from(&self.config.node.working_dir);
+        root.push(&self.config.burnchain.mode);
+        root
+    }
+
+    /// Ingests the newest backup of the primary, if it is newer than the one ingested last
+    fn ingest(config: &Config, root: &PathBuf, ingested_height: &AtomicU64) -> Result<(), String> {
+        let replica_config = config
+            .node
+            .replica
+            .as_ref()
+            .expect("FATAL: no [node.replica]");
+        let primary = PrimaryNode::new(replica_config)?;
+        if let Some(listing) = replica::ingest_newest_backup(
+            &primary,
+            root,
+            config.is_mainnet(),
+            config.burnchain.chain_id,
+        )? {
+            info!(
+                "Replica: ingested backup {} of {}: burn block {}, Stacks block {} ({})",
+                listing.height,
+                &replica_config.primary,
+                listing.manifest.burn_block_height,
+                listing.manifest.stacks_block_height,
+                &listing.manifest.stacks_block_id
+            );
+            ingested_height.store(listing.height, Ordering::SeqCst);
+        }
+        Ok(())
+    }
+
+    /// Spawns the thread that polls the primary for new backups
+    fn spawn_poller(&self) -> thread::JoinHandle<()> {
+        let config = self.config.clone();
+        let root = self.root_path();
+        let should_keep_running = self.should_keep_running.clone();
+        let ingested_height = self.ingested_height.clone();
+        let poll_interval_secs = config
+            .node
+            .replica
+            .as_ref()
+            .expect("FATAL: no [node.replica]")
+            .poll_interval_secs;
+
+        thread::Builder::new()
+            .name("replica".to_string())
+            .spawn(move || {
+                let mut next_poll = 0;
+                while should_keep_running.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_secs(1));
+                    next_poll += 1;
+                    if next_poll < poll_interval_secs {
+                        continue;
+                    }
+                    next_poll = 0;
+                    if let Err(e) = RunLoop::ingest(&config, &root, &ingested_height) {
+                        warn!(
+                            "Replica: failed to ingest the primary's newest backup: {}",
+                            e
+                        );
+                    }
+                }
+            })
+            .expect("FATAL: failed to spawn replica thread")
+    }
+
+    pub fn start(&mut self) {
+        setup_termination_handler(
+            self.should_keep_running.clone(),
+            DiagnosticDump::new(self.config.clone(), self.peer_snapshot.clone()),
+        );
+        let root = self.root_path();
+        let replica_config = self
+            .config
+            .node
+            .replica
+            .clone()
+            .expect("FATAL: no [node.replica]");
+
+        // the RPC interface has nothing to serve until a backup is in place
+        if let Some(current) = replica::read_replica_state(&root) {
+            info!(
+                "Replica: serving backup {} until the primary has a newer one",
+                current.height
+            );
+            self.ingested_height.store(current.height, Ordering::SeqCst);
+        }
+        while self.should_keep_running.load(Ordering::SeqCst) {
+            match RunLoop::ingest(&self.config, &root, &self.ingested_height) {
+                Ok(()) if self.ingested_height.load(Ordering::SeqCst) > 0 => break,
+                Ok(()) => info!(
+                    "Replica: waiting for {} to take its first backup",
+                    &replica_config.primary
+                ),
+                Err(e) => warn!(
+                    "Replica: failed to ingest the primary's newest backup: {}",
+                    e
+                ),
+            }
+            thread::sleep(Duration::from_secs(cmp::min(
+                replica_config.poll_interval_secs,
+                FIRST_BACKUP_POLL_INTERVAL_SECS,
+            )));
+        }
+        if !self.should_keep_running.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let poller = self.spawn_poller();
+        self.serve();
+        poller.join().expect("FATAL: replica thread panicked");
+    }
+
+    /// Serves the RPC interface from the ingested chainstate until the node is told to stop.  The
+    /// p2p network only answers requests: it does not sync, download, or relay anything.
+    fn serve(&self) {
+        let mut config = self.config.clone();
+        config.connection_options.disable_neighbor_walk = true;
+        config.connection_options.disable_inbound_walks = true;
+        config.connection_options.disable_inv_sync = true;
+        config.connection_options.disable_block_download = true;
+        config.connection_options.disable_block_advertisement = true;
+        config.connection_options.disable_block_push = true;
+        config.connection_options.disable_microblock_push = true;
+        config.connection_options.disable_natpunch = true;
+
+        let is_mainnet = config.is_mainnet();
+        let burnchain: Burnchain =
+            BitcoinRegtestController::new_dummy(config.clone()).get_burnchain();
+        let atlas_config = config.make_atlas_config();
+        let p2p_sock: SocketAddr = config.node.p2p_bind.parse().expect(&format!(
+            "Failed to parse socket: {}",
+            &config.node.p2p_bind
+        ));
+        let rpc_sock: SocketAddr = config.node.rpc_bind.parse().expect(&format!(
+            "Failed to parse socket: {}",
+            &config.node.rpc_bind
+        ));
+
+        let open_dbs = || {
+            let sortdb = SortitionDB::open(&config.get_burn_db_file_path(), false)
+                .expect("FATAL: failed to open the replica's sortition DB");
+            let (chainstate, _) = StacksChainState::open(
+                is_mainnet,
+                config.burnchain.chain_id,
+                &config.get_chainstate_path_str(),
+                Some(config.node.get_marf_opts()),
+            )
+            .expect("FATAL: failed to open the replica's chainstate");
+            let mempool = MemPoolDB::open(
+                is_mainnet,
+                config.burnchain.chain_id,
+                &config.get_chainstate_path_str(),
+                config
+                    .make_cost_estimator()
+                    .unwrap_or_else(|| Box::new(UnitEstimator)),
+                config
+                    .make_cost_metric()
+                    .unwrap_or_else(|| Box::new(UnitMetric)),
+            )
+            .expect("FATAL: failed to open the replica's mempool");
+            (sortdb, chainstate, mempool)
+        };
+
+        let (mut sortdb, mut chainstate, mut mem_pool) = open_dbs();
+        let mut opened_height = self.ingested_height.load(Ordering::SeqCst);
+
+        let mut net = make_peer_network(&config, &burnchain, &sortdb, &atlas_config);
+        net.bind(&p2p_sock, &rpc_sock)
+            .expect("FATAL: failed to bind the replica's p2p and RPC sockets");
+        let (mut dns_resolver, mut dns_client) = DNSResolver::new(10);
+        dns_resolver.set_socks_proxy(config.connection_options.socks_proxy.clone());
+        let dns_thread = thread::Builder::new()
+            .name("dns-resolver".to_string())
+            .spawn(move || dns_resolver.thread_main())
+            .expect("FATAL: failed to spawn DNS resolver thread");
+
+        let cost_estimator = config
+            .make_cost_estimator()
+            .unwrap_or_else(|| Box::new(UnitEstimator));
+        let metric = config
+            .make_cost_metric()
+            .unwrap_or_else(|| Box::new(UnitMetric));
+        let fee_estimator = config.make_fee_estimator();
+        let handler_args = RPCHandlerArgs {
+            genesis_chainstate_hash: config.get_genesis_chainstate_hash(),
+            cost_estimator: Some(cost_estimator.as_ref()),
+            cost_metric: Some(metric.as_ref()),
+            fee_estimator: fee_estimator.as_ref().map(|x| x.as_ref()),
+            ..RPCHandlerArgs::default()
+        };
+
+        info!(
+            "Replica: serving RPC on {} from backup {}",
+            &rpc_sock, opened_height
+        );
+        while self.should_keep_running.load(Ordering::SeqCst) {
+            let ingested_height = self.ingested_height.load(Ordering::SeqCst);
+            if ingested_height != opened_height {
+                // drop the connections to the old backup before opening the new one
+                drop((sortdb, chainstate, mem_pool));
+                let dbs = open_dbs();
+                sortdb = dbs.0;
+                chainstate = dbs.1;
+                mem_pool = dbs.2;
+                opened_height = ingested_height;
+                debug!(
+                    "Replica: reopened the chainstate at backup {}",
+                    opened_height
+                );
+            }
+
+            let now = get_epoch_time_secs();
+            if self.peer_snapshot.is_stale(now) {
+                self.peer_snapshot.update(&net.peers, now);
+            }
+
+            let _ = Relayer::setup_unconfirmed_state_readonly(&mut chainstate, &sortdb);
+            let mut expected_attachments = HashSet::new();
+            if let Err(e) = net.run(
+                &sortdb,
+                &mut chainstate,
+                &mut mem_pool,
+                Some(&mut dns_client),
+                false,
+                false,
+                POLL_TIMEOUT_MS,
+                &handler_args,
+                &mut expected_attachments,
+            ) {
+                error!("Replica: p2p loop error: {:?}", &e);
+                thread::sleep(Duration::from_millis(POLL_TIMEOUT_MS));
+            }
+        }
+
+        // the resolver exits once its client is gone
+        drop(dns_client);
+        let _ = dns_thread.join();
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::fs;
+
+use stacks::util::hash::hex_bytes;
+
+/// Per-round actions for the helium run loop, as read from a scenario file.
+///
+/// A scenario file is a TOML document listing the rounds that deviate from the
+/// default "mine one block per burn block" behavior:
+///
+/// ```toml
+/// rounds = 6
+///
+/// [[round]]
+/// index = 1
+/// transactions = ["80800000000400..."]
+///
+/// [[round]]
+/// index = 2
+/// skip_sortition = true
+///
+/// [[round]]
+/// index = 4
+/// fork_from_round = 1
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Scenario {
+    /// Number of rounds to run before the run loop exits. 0 runs forever.
+    pub rounds: u64,
+    actions: HashMap<u64, ScenarioRound>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScenarioRound {
+    /// Raw, serialized transactions to submit to the mempool before this round's tenure runs.
+    pub transactions: Vec<Vec<u8>>,
+    /// If set, the node does not submit its block-commit in this round, so the
+    /// burnchain block for this round carries no sortition.
+    pub skip_sortition: bool,
+    /// If set, this round's tenure builds on the Stacks block produced in the given round,
+    /// instead of on the current chain tip.
+    pub fork_from_round: Option<u64>,
+}
+
+#[derive(Clone, Deserialize, Default, Debug)]
+pub struct ScenarioFile {
+    pub rounds: Option<u64>,
+    pub round: Option<Vec<ScenarioRoundFile>>,
+}
+
+#[derive(Clone, Deserialize, Default, Debug)]
+pub struct ScenarioRoundFile {
+    pub index: u64,
+    pub transactions: Option<Vec<String>>,
+    pub skip_sortition: Option<bool>,
+    pub fork_from_round: Option<u64>,
+}
+
+impl Scenario {
+    pub fn from_path(path: &str) -> Result<Scenario, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("Invalid path: {}", &e))?;
+        Self::from_str(&content)
+    }
+
+    pub fn from_str(content: &str) -> Result<Scenario, String> {
+        let scenario_file: ScenarioFile =
+            toml::from_str(content).map_err(|e| format!("Invalid toml: {}", e))?;
+        Scenario::from_scenario_file(scenario_file)
+    }
+
+    pub fn from_scenario_file(scenario_file: ScenarioFile) -> Result<Scenario, String> {
+        let mut actions = HashMap::new();
+        for round in scenario_file.round.unwrap_or_default() {
+            if round.index == 0 {
+                return Err(format!(
+                    "round.index must be at least 1 (round 0 bootstraps the chain)"
+                ));
+            }
+            if let Some(fork_from_round) = round.fork_from_round {
+                if fork_from_round >= round.index {
+                    return Err(format!(
+                        "round {}: fork_from_round must refer to an earlier round",
+                        round.index
+                    ));
+                }
+            }
+
+            let mut transactions = vec![];
+            for tx_hex in round.transactions.unwrap_or_default().iter() {
+                let tx_bytes = hex_bytes(tx_hex).map_err(|_| {
+                    format!(
+                        "round {}: transactions should be hex encoded strings",
+                        round.index
+                    )
+                })?;
+                transactions.push(tx_bytes);
+            }
+
+            let action = ScenarioRound {
+                transactions,
+                skip_sortition: round.skip_sortition.unwrap_or(false),
+                fork_from_round: round.fork_from_round,
+            };
+
+            if actions.insert(round.index, action).is_some() {
+                return Err(format!("round {} is defined more than once", round.index));
+            }
+        }
+
+        Ok(Scenario {
+            rounds: scenario_file.rounds.unwrap_or(0),
+            actions,
+        })
+    }
+
+    /// Actions to take in the given round, if the scenario defines any.
+    pub fn get_round(&self, round_index: u64) -> Option<&ScenarioRound> {
+        self.actions.get(&round_index)
+    }
+
+    pub fn should_skip_sortition(&self, round_index: u64) -> bool {
+        self.get_round(round_index)
+            .map(|round| round.skip_sortition)
+            .unwrap_or(false)
+    }
+
+    pub fn get_fork_from_round(&self, round_index: u64) -> Option<u64> {
+        self.get_round(round_index)
+            .and_then(|round| round.fork_from_round)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scenario() {
+        let scenario = Scenario::from_str(
+            r#"
+            rounds = 5
+
+            [[round]]
+            index = 1
+            transactions = ["0001", "ff"]
+
+            [[round]]
+            index = 2
+            skip_sortition = true
+
+            [[round]]
+            index = 4
+            fork_from_round = 1
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(scenario.rounds, 5);
+        assert_eq!(
+            scenario.get_round(1).unwrap().transactions,
+            vec![vec![0x00, 0x01], vec![0xff]]
+        );
+        assert!(!scenario.should_skip_sortition(1));
+        assert!(scenario.should_skip_sortition(2));
+        assert!(scenario.get_round(3).is_none());
+        assert_eq!(scenario.get_fork_from_round(4), Some(1));
+        assert_eq!(scenario.get_fork_from_round(2), None);
+
+        assert!(Scenario::from_str("").is_ok());
+    }
+
+    #[test]
+    fn test_invalid_scenario() {
+        assert_eq!(
+            Scenario::from_str(
+                r#"
+                [[round]]
+                index = 2
+                fork_from_round = 2
+                "#
+            )
+            .unwrap_err(),
+            "round 2: fork_from_round must refer to an earlier round"
+        );
+        assert_eq!(
+            Scenario::from_str(
+                r#"
+                [[round]]
+                index = 0
+                "#
+            )
+            .unwrap_err(),
+            "round.index must be at least 1 (round 0 bootstraps the chain)"
+        );
+        assert_eq!(
+            Scenario::from_str(
+                r#"
+                [[round]]
+                index = 1
+                transactions = ["not-hex"]
+                "#
+            )
+            .unwrap_err(),
+            "round 1: transactions should be hex encoded strings"
+        );
+        assert_eq!(
+            Scenario::from_str(
+                r#"
+                [[round]]
+                index = 1
+                [[round]]
+                index = 1
+                "#
+            )
+            .unwrap_err(),
+            "round 1 is defined more than once"
+        );
+    }
+}
@@ -0,0 +1,302 @@
+//! Encrypted `node.seed`, `node.local_peer_seed` and `node.microblock_seed` values, so that the
+//! node's keys need not sit in the config file as plaintext hex.
+//!
+//! An encrypted value is `secretbox:` followed by the base64 of a version byte, the PBKDF2 round
+//! count, a salt, a nonce, and the seed sealed with NaCl's `crypto_secretbox`
+//! (XSalsa20-Poly1305) under a key stretched from a passphrase with PBKDF2-HMAC-SHA512.
+//! `stacks-node encrypt-seed` produces them.  The passphrase comes from, in order of preference:
+//!
+//! * the `STACKS_NODE_SEED_PASSPHRASE` environment variable,
+//! * the first line of the file named by `STACKS_NODE_SEED_PASSPHRASE_FILE`,
+//! * an interactive prompt on the controlling terminal.
+//!
+//! It is asked for at most once per process.
+
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::sync::Mutex;
+
+use crypto_secretbox::aead::{Aead, KeyInit};
+use crypto_secretbox::{Key, Nonce, XSalsa20Poly1305};
+use hmac::Hmac;
+use rand::RngCore;
+use sha2::Sha512;
+use stacks::util::hash::hex_bytes;
+
+/// Prefix of an encrypted seed value
+pub const ENCRYPTED_SEED_PREFIX: &str = "secretbox:";
+
+/// Environment variable holding the passphrase
+pub const PASSPHRASE_ENV: &str = "STACKS_NODE_SEED_PASSPHRASE";
+
+/// Environment variable naming a file to read the passphrase from
+pub const PASSPHRASE_FILE_ENV: &str = "STACKS_NODE_SEED_PASSPHRASE_FILE";
+
+/// PBKDF2-HMAC-SHA512 rounds used by `encrypt_seed`
+pub const DEFAULT_KDF_ROUNDS: u32 = 200_000;
+
+const ENCRYPTED_SEED_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+const HEADER_LEN: usize = 1 + 4 + SALT_LEN + NONCE_LEN;
+
+lazy_static! {
+    static ref SEED_PASSPHRASE: Mutex<Option<String>> = Mutex::new(None);
+}
+
+/// NaCl `crypto_secretbox`: the Poly1305 tag, followed by the XSalsa20 ciphertext.
+fn secretbox_seal(key: &[u8; 32], nonce: &[u8; NONCE_LEN], message: &[u8]) -> Vec<u8> {
+    XSalsa20Poly1305::new(&Key::from(*key))
+        .encrypt(&Nonce::from(*nonce), message)
+        .expect("BUG: failed to seal a seed")
+}
+
+/// NaCl `crypto_secretbox_open`: None if the tag does not match.
+fn secretbox_open(key: &[u8; 32], nonce: &[u8; NONCE_LEN], sealed: &[u8]) -> Option<Vec<u8>> {
+    XSalsa20Poly1305::new(&Key::from(*key))
+        .decrypt(&Nonce::from(*nonce), sealed)
+        .ok()
+}
+
+fn stretch_passphrase(passphrase: &str, salt: &[u8], rounds: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2::<Hmac<Sha512>>(passphrase.as_bytes(), salt, rounds, &mut key);
+    key
+}
+
+/// Whether a seed value from the config is encrypted.
+pub fn is_encrypted_seed(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_SEED_PREFIX)
+}
+
+/// Encrypts `seed` under `passphrase`, as a value for one of the node's seed keys.
+pub fn encrypt_seed(seed: &[u8], passphrase: &str, kdf_rounds: u32) -> String {
+    let mut rng = rand::thread_rng();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill_bytes(&mut salt);
+    rng.fill_bytes(&mut nonce);
+
+    let key = stretch_passphrase(passphrase, &salt, kdf_rounds);
+    let mut encoded = vec![ENCRYPTED_SEED_VERSION];
+    encoded.extend_from_slice(&kdf_rounds.to_be_bytes());
+    encoded.extend_from_slice(&salt);
+    encoded.extend_from_slice(&nonce);
+    encoded.extend_from_slice(&secretbox_seal(&key, &nonce, seed));
+    format!("{}{}", ENCRYPTED_SEED_PREFIX, base64::encode(&encoded))
+}
+
+/// Decrypts a value made by `encrypt_seed`.
+pub fn decrypt_seed(value: &str, passphrase: &str) -> Result<Vec<u8>, String> {
+    let encoded = value
+        .strip_prefix(ENCRYPTED_SEED_PREFIX)
+        .and_then(|encoded| base64::decode(encoded).ok())
+        .ok_or("Encrypted seed is not valid base64".to_string())?;
+    if encoded.len() < HEADER_LEN + TAG_LEN {
+        return Err("Encrypted seed is too short".to_string());
+    }
+    if encoded[0] != ENCRYPTED_SEED_VERSION {
+        return Err(format!("Unsupported encrypted seed version {}", encoded[0]));
+    }
+    let kdf_rounds = u32::from_be_bytes([encoded[1], encoded[2], encoded[3], encoded[4]]);
+    if kdf_rounds == 0 {
+        return Err("Encrypted seed has no key derivation rounds".to_string());
+    }
+    let salt = &encoded[5..5 + SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce.copy_from_slice(&encoded[5 + SALT_LEN..HEADER_LEN]);
+
+    let key = stretch_passphrase(passphrase, salt, kdf_rounds);
+    secretbox_open(&key, &nonce, &encoded[HEADER_LEN..])
+        .ok_or("Failed to decrypt seed: wrong passphrase, or the value is corrupt".to_string())
+}
+
+#[cfg(unix)]
+fn prompt_passphrase(prompt: &str) -> Result<String, String> {
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::os::unix::io::AsRawFd;
+
+    let mut tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .map_err(|e| {
+            format!(
+                "No terminal to prompt for the seed passphrase on ({}); set {} or {}",
+                e, PASSPHRASE_ENV, PASSPHRASE_FILE_ENV
+            )
+        })?;
+    let fd = tty.as_raw_fd();
+
+    let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+    if unsafe { libc::tcgetattr(fd, &mut termios) } != 0 {
+        return Err("Failed to read the terminal settings".to_string());
+    }
+    let saved = termios;
+    termios.c_lflag &= !libc::ECHO;
+    termios.c_lflag |= libc::ECHONL;
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) } != 0 {
+        return Err("Failed to turn off terminal echo".to_string());
+    }
+
+    let result = write!(tty, "{}", prompt)
+        .and_then(|_| tty.flush())
+        .and_then(|_| {
+            let mut line = String::new();
+            BufReader::new(&tty).read_line(&mut line).map(|_| line)
+        });
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &saved) };
+    result
+        .map(|line| line.trim_end_matches(&['\r', '\n'][..]).to_string())
+        .map_err(|e| format!("Failed to read the seed passphrase: {}", e))
+}
+
+#[cfg(not(unix))]
+fn prompt_passphrase(_prompt: &str) -> Result<String, String> {
+    Err(format!(
+        "Cannot prompt for the seed passphrase on this platform; set {} or {}",
+        PASSPHRASE_ENV, PASSPHRASE_FILE_ENV
+    ))
+}
+
+fn read_passphrase_file(path: &str) -> Result<String, String> {
+    let file = File::open(path).map_err(|e| {
+        format!(
+            "Failed to open the seed passphrase file {} named by {}: {}",
+            path, PASSPHRASE_FILE_ENV, e
+        )
+    })?;
+    let mut line = String::new();
+    BufReader::new(file)
+        .read_line(&mut line)
+        .map_err(|e| format!("Failed to read the seed passphrase from {}: {}", path, e))?;
+    Ok(line.trim_end_matches(&['\r', '\n'][..]).to_string())
+}
+
+/// The seed passphrase, from the environment, a file descriptor, or the terminal.
+pub fn seed_passphrase() -> Result<String, String> {
+    let mut cached = SEED_PASSPHRASE
+        .lock()
+        .expect("seed passphrase lock poisoned");
+    if let Some(passphrase) = cached.as_ref() {
+        return Ok(passphrase.clone());
+    }
+    let passphrase = if let Ok(passphrase) = env::var(PASSPHRASE_ENV) {
+        passphrase
+    } else if let Ok(path) = env::var(PASSPHRASE_FILE_ENV) {
+        read_passphrase_file(&path)?
+    } else {
+        prompt_passphrase("Seed passphrase: ")?
+    };
+    *cached = Some(passphrase.clone());
+    Ok(passphrase)
+}
+
+/// Asks for a new seed passphrase for `encrypt-seed`, twice if it comes from the terminal.
+pub fn new_seed_passphrase() -> Result<String, String> {
+    if env::var(PASSPHRASE_ENV).is_ok() || env::var(PASSPHRASE_FILE_ENV).is_ok() {
+        return seed_passphrase();
+    }
+    let passphrase = prompt_passphrase("New seed passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err("The seed passphrase must not be empty".to_string());
+    }
+    if prompt_passphrase("Repeat seed passphrase: ")? != passphrase {
+        return Err("The seed passphrases do not match".to_string());
+    }
+    Ok(passphrase)
+}
+
+/// Decodes the config value of `key` (`node.seed`, `node.local_peer_seed` or
+/// `node.microblock_seed`), which is either hex or encrypted.
+pub fn decode_seed(key: &str, value: &str) -> Result<Vec<u8>, String> {
+    if is_encrypted_seed(value) {
+        let passphrase = seed_passphrase()?;
+        decrypt_seed(value, &passphrase).map_err(|e| format!("{}: {}", key, e))
+    } else {
+        hex_bytes(value).map_err(|_| format!("{} should be a hex encoded string", key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use stacks::util::hash::to_hex;
+
+    #[test]
+    fn test_secretbox_vector() {
+        // NaCl's crypto_secretbox test vector, with the key crypto_box_beforenm derives
+        let mut key = [0u8; 32];
+        key.copy_from_slice(
+            &hex_bytes("1b27556473e985d462cd51197a9a46c76009549eac6474f206c4ee0844f68389").unwrap(),
+        );
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce.copy_from_slice(
+            &hex_bytes("69696ee955b62b73cd62bda875fc73d68219e0036b7a0b37").unwrap(),
+        );
+        let message = hex_bytes(
+            "be075fc53c81f2d5cf141316ebeb0c7b5228c52a4c62cbd44b66849b64244ffc\
+             e5ecbaaf33bd751a1ac728d45e6c61296cdc3c01233561f41db66cce314adb31\
+             0e3be8250c46f06dceea3a7fa1348057e2f6556ad6b1318a024a838f21af1fde\
+             048977eb48f59ffd4924ca1c60902e52f0a089bc76897040e082f93776384864\
+             5e0705",
+        )
+        .unwrap();
+        let sealed = secretbox_seal(&key, &nonce, &message);
+        assert_eq!(
+            to_hex(&sealed[..48]),
+            "f3ffc7703f9400e52a7dfb4b3d3305d98e993b9f48681273c29650ba32fc76ce48332ea7164d96a4476fb8c531a1186a"
+        );
+        assert_eq!(secretbox_open(&key, &nonce, &sealed).unwrap(), message);
+
+        let mut tampered = sealed.clone();
+        tampered[20] ^= 1;
+        assert!(secretbox_open(&key, &nonce, &tampered).is_none());
+    }
+
+    #[test]
+    fn test_stretch_passphrase_vector() {
+        // the first 32 bytes of the PBKDF2-HMAC-SHA512 test vectors for "password" and "salt"
+        assert_eq!(
+            to_hex(&stretch_passphrase("password", b"salt", 1)),
+            "867f70cf1ade02cff3752599a3a53dc4af34c7a669815ae5d513554e1c8cf252"
+        );
+        assert_eq!(
+            to_hex(&stretch_passphrase("password", b"salt", 4096)),
+            "d197b1b33db0143e018b12f3d1d1479e6cdebdcc97c5c0f87f6902e072f457b5"
+        );
+    }
+
+    #[test]
+    fn test_encrypt_seed() {
+        let seed =
+            hex_bytes("00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff").unwrap();
+        let encrypted = encrypt_seed(&seed, "hunter2", 10);
+        assert!(is_encrypted_seed(&encrypted));
+        assert_eq!(decrypt_seed(&encrypted, "hunter2").unwrap(), seed);
+        assert!(decrypt_seed(&encrypted, "hunter3")
+            .unwrap_err()
+            .contains("wrong passphrase"));
+        assert_ne!(encrypt_seed(&seed, "hunter2", 10), encrypted);
+
+        assert!(decrypt_seed("secretbox:not base64!", "hunter2").is_err());
+        assert!(decrypt_seed("secretbox:AAAA", "hunter2")
+            .unwrap_err()
+            .contains("too short"));
+        assert_eq!(decode_seed("node.seed", "0011").unwrap(), vec![0x00, 0x11]);
+
+        let path = "/tmp/stacks-node-tests/test_encrypt_seed.passphrase";
+        std::fs::create_dir_all("/tmp/stacks-node-tests").unwrap();
+        std::fs::write(path, "hunter2\nignored\n").unwrap();
+        assert_eq!(read_passphrase_file(path).unwrap(), "hunter2");
+        assert!(read_passphrase_file("/tmp/stacks-node-tests/no-such-file").is_err());
+        assert_eq!(
+            decode_seed("node.seed", "zz").unwrap_err(),
+            "node.seed should be a hex encoded string"
+        );
+    }
+}
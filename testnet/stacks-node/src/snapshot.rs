@@ -0,0 +1,335 @@
+//! Export and import of the node's auxiliary databases -- the fee/cost estimator state and,
+//! optionally, the mempool -- so that a replacement node resumes with the same fee behavior
+//! instead of starting from a cold estimator.
+//!
+//! A snapshot is a directory holding a consistent copy of each database (taken with
+//! `VACUUM INTO`, so it is safe to export from a running node) and a `manifest.json`
+//! describing the node that produced it. Importing checks the manifest against the
+//! importing node's config before any file is put in place.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OpenFlags, OptionalExtension, NO_PARAMS};
+
+use stacks::core::mempool::{MemPoolDB, MEMPOOL_SCHEMA_VERSION};
+
+use crate::Config;
+
+pub const SNAPSHOT_MANIFEST_FILE: &str = "manifest.json";
+const SNAPSHOT_ESTIMATES_DIR: &str = "estimates";
+const SNAPSHOT_MEMPOOL_FILE: &str = "mempool.sqlite";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub node_version: String,
+    pub mainnet: bool,
+    pub chain_id: u32,
+    pub cost_estimator: Option<String>,
+    pub fee_estimator: Option<String>,
+    pub cost_metric: Option<String>,
+    /// Schema version of the exported mempool, if the mempool was included.
+    pub mempool_schema_version: Option<i64>,
+    /// Estimator database files, relative to the snapshot's `estimates` directory.
+    pub estimator_files: Vec<String>,
+}
+
+impl SnapshotManifest {
+    fn from_config(config: &Config, node_version: &str) -> SnapshotManifest {
+        SnapshotManifest {
+            node_version: node_version.to_string(),
+            mainnet: config.is_mainnet(),
+            chain_id: config.burnchain.chain_id,
+            cost_estimator: config
+                .estimation
+                .cost_estimator
+                .as_ref()
+                .map(|name| format!("{:?}", name)),
+            fee_estimator: config
+                .estimation
+                .fee_estimator
+                .as_ref()
+                .map(|name| format!("{:?}", name)),
+            cost_metric: config
+                .estimation
+                .cost_metric
+                .as_ref()
+                .map(|name| format!("{:?}", name)),
+            mempool_schema_version: None,
+            estimator_files: vec![],
+        }
+    }
+
+    /// Checks that a node configured with `config` can use the databases in this snapshot.
+    pub fn check_compatible(&self, config: &Config) -> Result<(), String> {
+        let expected = SnapshotManifest::from_config(config, &self.node_version);
+        if self.mainnet != expected.mainnet || self.chain_id != expected.chain_id {
+            return Err(format!(
+                "Snapshot was taken on chain ID {} (mainnet: {}), but config is for chain ID {} (mainnet: {})",
+                self.chain_id, self.mainnet, expected.chain_id, expected.mainnet
+            ));
+        }
+        if self.cost_estimator != expected.cost_estimator
+            || self.fee_estimator != expected.fee_estimator
+            || self.cost_metric != expected.cost_metric
+        {
+            return Err(format!(
+                "Snapshot estimators ({:?}, {:?}, {:?}) do not match configured estimators ({:?}, {:?}, {:?})",
+                self.cost_estimator,
+                self.fee_estimator,
+                self.cost_metric,
+                expected.cost_estimator,
+                expected.fee_estimator,
+                expected.cost_metric
+            ));
+        }
+        if let Some(version) = self.mempool_schema_version {
+            if version > MEMPOOL_SCHEMA_VERSION {
+                return Err(format!(
+                    "Snapshot mempool schema version {} is newer than supported version {}",
+                    version, MEMPOOL_SCHEMA_VERSION
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes a consistent copy of the sqlite database at `src` to `dest`.
+pub fn copy_sqlite_db(src: &Path, dest: &Path) -> Result<(), String> {
+    let dest_str = dest
+        .to_str()
+        .ok_or_else(|| format!("Unable to produce path for {:?}", dest))?;
+    let conn = Connection::open_with_flags(src, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open {:?}: {}", src, e))?;
+    conn.execute_batch(&format!("VACUUM INTO '{}'", dest_str.replace("'", "''")))
+        .map_err(|e| format!("Failed to copy {:?} to {:?}: {}", src, dest, e))
+}
+
+fn get_mempool_schema_version(path: &Path) -> Result<Option<i64>, String> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+    conn.query_row(
+        "SELECT MAX(version) FROM schema_version",
+        NO_PARAMS,
+        |row| row.get(0),
+    )
+    .optional()
+    .map(|version| version.flatten())
+    .map_err(|e| format!("Failed to read mempool schema version: {}", e))
+}
+
+fn list_sqlite_files(dir: &Path) -> Result<Vec<String>, String> {
+    let mut files = vec![];
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to list {:?}: {}", dir, e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to list {:?}: {}", dir, e))?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if file_name.ends_with(".sqlite") {
+            files.push(file_name);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+pub fn get_mempool_path(config: &Config) -> Result<PathBuf, String> {
+    MemPoolDB::db_path(&config.get_chainstate_path_str())
+        .map(PathBuf::from)
+        .map_err(|e| format!("Unable to produce mempool path: {:?}", e))
+}
+
+/// Exports the estimator databases (and the mempool, if `include_mempool` is set) of the node
+/// configured by `config` into the empty or nonexistent directory `dest`.
+pub fn export_snapshot(
+    config: &Config,
+    node_version: &str,
+    dest: &Path,
+    include_mempool: bool,
+) -> Result<SnapshotManifest, String> {
+    if dest.exists()
+        && fs::read_dir(dest)
+            .map_err(|e| format!("Failed to list {:?}: {}", dest, e))?
+            .next()
+            .is_some()
+    {
+        return Err(format!("Snapshot directory {:?} is not empty", dest));
+    }
+
+    let mut manifest = SnapshotManifest::from_config(config, node_version);
+
+    let estimates_path = config.get_estimates_path();
+    let mut dest_estimates_path = dest.to_path_buf();
+    dest_estimates_path.push(SNAPSHOT_ESTIMATES_DIR);
+    fs::create_dir_all(&dest_estimates_path)
+        .map_err(|e| format!("Failed to create {:?}: {}", dest_estimates_path, e))?;
+
+    for file_name in list_sqlite_files(&estimates_path)? {
+        copy_sqlite_db(
+            &estimates_path.join(&file_name),
+            &dest_estimates_path.join(&file_name),
+        )?;
+        manifest.estimator_files.push(file_name);
+    }
+
+    if include_mempool {
+        let mempool_path = get_mempool_path(config)?;
+        if !mempool_path.exists() {
+            return Err(format!("No mempool database at {:?}", mempool_path));
+        }
+        manifest.mempool_schema_version = get_mempool_schema_version(&mempool_path)?;
+        copy_sqlite_db(&mempool_path, &dest.join(SNAPSHOT_MEMPOOL_FILE))?;
+    }
+
+    let manifest_json = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    fs::write(dest.join(SNAPSHOT_MANIFEST_FILE), manifest_json)
+        .map_err(|e| format!("Failed to write manifest: {}", e))?;
+
+    info!(
+        "Exported {} estimator database(s){} to {:?}",
+        manifest.estimator_files.len(),
+        if include_mempool {
+            " and the mempool"
+        } else {
+            ""
+        },
+        dest
+    );
+    Ok(manifest)
+}
+
+pub fn read_manifest(src: &Path) -> Result<SnapshotManifest, String> {
+    let manifest_json = fs::read_to_string(src.join(SNAPSHOT_MANIFEST_FILE))
+        .map_err(|e| format!("Failed to read manifest: {}", e))?;
+    serde_json::from_str(&manifest_json).map_err(|e| format!("Invalid manifest: {}", e))
+}
+
+/// Imports a snapshot produced by `export_snapshot` into the node configured by `config`.
+/// The node must not be running. Existing databases are only replaced if `overwrite` is set.
+pub fn import_snapshot(
+    config: &Config,
+    src: &Path,
+    overwrite: bool,
+) -> Result<SnapshotManifest, String> {
+    let manifest = read_manifest(src)?;
+    manifest.check_compatible(config)?;
+
+    let estimates_path = config.get_estimates_path();
+    let mempool_path = get_mempool_path(config)?;
+
+    let mut targets = vec![];
+    for file_name in manifest.estimator_files.iter() {
+        targets.push((
+            src.join(SNAPSHOT_ESTIMATES_DIR).join(file_name),
+            estimates_path.join(file_name),
+        ));
+    }
+    if manifest.mempool_schema_version.is_some() {
+        targets.push((src.join(SNAPSHOT_MEMPOOL_FILE), mempool_path));
+    }
+
+    for (from, to) in targets.iter() {
+        if !from.exists() {
+            return Err(format!("Snapshot is missing {:?}", from));
+        }
+        if to.exists() && !overwrite {
+            return Err(format!(
+                "Refusing to overwrite existing database {:?} without --overwrite",
+                to
+            ));
+        }
+    }
+
+    for (from, to) in targets.iter() {
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
+        }
+        // drop any stale write-ahead log that belongs to the database being replaced
+        for suffix in ["-wal", "-shm"].iter() {
+            let mut sidecar = to.clone().into_os_string();
+            sidecar.push(suffix);
+            let _ = fs::remove_file(sidecar);
+        }
+        fs::copy(from, to).map_err(|e| format!("Failed to copy {:?} to {:?}: {}", from, to, e))?;
+    }
+
+    info!(
+        "Imported snapshot taken by {} from {:?}",
+        &manifest.node_version, src
+    );
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use stacks::core::CHAIN_ID_MAINNET;
+
+    fn make_test_config(name: &str) -> Config {
+        let mut config = Config::default();
+        config.node.working_dir = format!("/tmp/stacks-node-tests/snapshot/{}", name);
+        if fs::metadata(&config.node.working_dir).is_ok() {
+            fs::remove_dir_all(&config.node.working_dir).unwrap();
+        }
+        config
+    }
+
+    #[test]
+    fn test_export_import_snapshot() {
+        let config = make_test_config("source");
+        assert!(config.make_cost_estimator().is_some());
+        assert!(config.make_fee_estimator().is_some());
+        // opening a real mempool requires a booted chainstate; only the schema version matters here
+        fs::create_dir_all(config.get_chainstate_path()).unwrap();
+        Connection::open(get_mempool_path(&config).unwrap())
+            .unwrap()
+            .execute_batch(&format!(
+                "CREATE TABLE schema_version (version INTEGER NOT NULL); \
+                 INSERT INTO schema_version (version) VALUES (1), ({});",
+                MEMPOOL_SCHEMA_VERSION
+            ))
+            .unwrap();
+
+        let snapshot_dir = PathBuf::from("/tmp/stacks-node-tests/snapshot/export");
+        if snapshot_dir.exists() {
+            fs::remove_dir_all(&snapshot_dir).unwrap();
+        }
+
+        let manifest = export_snapshot(&config, "test", &snapshot_dir, true).unwrap();
+        assert_eq!(manifest.estimator_files.len(), 2);
+        assert_eq!(
+            manifest.mempool_schema_version,
+            Some(MEMPOOL_SCHEMA_VERSION)
+        );
+        assert_eq!(read_manifest(&snapshot_dir).unwrap(), manifest);
+
+        // the snapshot directory must be empty
+        assert!(export_snapshot(&config, "test", &snapshot_dir, true).is_err());
+
+        let dest_config = make_test_config("dest");
+        import_snapshot(&dest_config, &snapshot_dir, false).unwrap();
+        for file_name in manifest.estimator_files.iter() {
+            assert!(dest_config.get_estimates_path().join(file_name).exists());
+        }
+        assert!(get_mempool_path(&dest_config).unwrap().exists());
+
+        // existing databases are only replaced on request
+        assert!(import_snapshot(&dest_config, &snapshot_dir, false).is_err());
+        import_snapshot(&dest_config, &snapshot_dir, true).unwrap();
+
+        let mut mainnet_config = make_test_config("mainnet");
+        mainnet_config.burnchain.chain_id = CHAIN_ID_MAINNET;
+        assert!(import_snapshot(&mainnet_config, &snapshot_dir, false)
+            .unwrap_err()
+            .starts_with("Snapshot was taken on chain ID"));
+
+        let mut no_estimator_config = make_test_config("no-estimator");
+        no_estimator_config.estimation.fee_estimator = None;
+        assert!(import_snapshot(&no_estimator_config, &snapshot_dir, false)
+            .unwrap_err()
+            .starts_with("Snapshot estimators"));
+    }
+}
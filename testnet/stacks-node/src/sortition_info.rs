@@ -0,0 +1,165 @@
+//! Offline view of what happened in a single sortition, for miners working out why their
+//! block-commit lost or was ignored. Reads only from the sortition DB, which is opened
+//! read-only, so the node may keep running.
+
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::burn::operations::LeaderBlockCommitOp;
+use stacks::chainstate::burn::BlockSnapshot;
+use stacks::util::hash::to_hex;
+
+fn block_commit_json(
+    sortdb: &SortitionDB,
+    snapshot: &BlockSnapshot,
+    commit: &LeaderBlockCommitOp,
+) -> Result<serde_json::Value, String> {
+    let ic = sortdb.index_conn();
+    let leader_key = SortitionDB::get_leader_key_at(
+        &ic,
+        commit.key_block_ptr as u64,
+        commit.key_vtxindex as u32,
+        &snapshot.sortition_id,
+    )
+    .map_err(|e| format!("Failed to load leader key: {:?}", e))?
+    .map(|key| {
+        json!({
+            "public_key": key.public_key.to_hex(),
+            "address": key.address.to_string(),
+        })
+    });
+
+    // a parent pointer of (0, 0) means the commit builds on the Stacks genesis block
+    let parent_stacks_block_hash = if commit.parent_block_ptr == 0 && commit.parent_vtxindex == 0 {
+        None
+    } else {
+        SortitionDB::get_block_commit_parent(
+            &ic,
+            commit.parent_block_ptr as u64,
+            commit.parent_vtxindex as u32,
+            &snapshot.sortition_id,
+        )
+        .map_err(|e| format!("Failed to load parent block-commit: {:?}", e))?
+        .map(|parent| parent.block_header_hash.to_string())
+    };
+
+    Ok(json!({
+        "txid": commit.txid.to_string(),
+        "vtxindex": commit.vtxindex,
+        "won": snapshot.sortition && commit.txid == snapshot.winning_block_txid,
+        "stacks_block_hash": commit.block_header_hash.to_string(),
+        "burn_fee": commit.burn_fee,
+        "sunset_burn": commit.sunset_burn,
+        "input": format!("{}:{}", commit.input.0, commit.input.1),
+        "burn_parent_modulus": commit.burn_parent_modulus,
+        "apparent_sender": commit
+            .apparent_sender
+            .public_keys
+            .iter()
+            .map(|public_key| public_key.to_hex())
+            .collect::<Vec<_>>(),
+        "commit_outs": commit
+            .commit_outs
+            .iter()
+            .map(|addr| addr.to_string())
+            .collect::<Vec<_>>(),
+        "memo": to_hex(&commit.memo),
+        "leader_key": {
+            "block_height": commit.key_block_ptr,
+            "vtxindex": commit.key_vtxindex,
+            "key": leader_key,
+        },
+        "parent": {
+            "block_height": commit.parent_block_ptr,
+            "vtxindex": commit.parent_vtxindex,
+            "stacks_block_hash": parent_stacks_block_hash,
+        },
+    }))
+}
+
+/// Describes the sortition at `burn_height` on the canonical burnchain fork: its winner, every
+/// block-commit it considered, and the block-commits that were intended for it but were missed
+/// because they did not spend the right input.  Leader keys and parents that can't be found in
+/// this fork are reported as `null`.
+pub fn sortition_info(sortdb: &SortitionDB, burn_height: u64) -> Result<serde_json::Value, String> {
+    let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())
+        .map_err(|e| format!("Failed to load canonical burnchain tip: {:?}", e))?;
+    if burn_height > tip.block_height {
+        return Err(format!(
+            "Burn height {} is above the canonical burnchain tip height {}",
+            burn_height, tip.block_height
+        ));
+    }
+    let snapshot =
+        SortitionDB::get_ancestor_snapshot(&sortdb.index_conn(), burn_height, &tip.sortition_id)
+            .map_err(|e| format!("Failed to load sortition: {:?}", e))?
+            .ok_or_else(|| format!("No sortition at burn height {}", burn_height))?;
+
+    let commits = SortitionDB::get_block_commits_by_block(sortdb.conn(), &snapshot.sortition_id)
+        .map_err(|e| format!("Failed to load block-commits: {:?}", e))?;
+    let missed_commits =
+        SortitionDB::get_missed_commits_by_intended(sortdb.conn(), &snapshot.sortition_id)
+            .map_err(|e| format!("Failed to load missed block-commits: {:?}", e))?;
+
+    let commits_json = commits
+        .iter()
+        .map(|commit| block_commit_json(sortdb, &snapshot, commit))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(json!({
+        "burn_block_height": snapshot.block_height,
+        "burn_header_hash": snapshot.burn_header_hash.to_string(),
+        "consensus_hash": snapshot.consensus_hash.to_string(),
+        "sortition_id": snapshot.sortition_id.to_string(),
+        "sortition": snapshot.sortition,
+        "winner": if snapshot.sortition {
+            json!({
+                "txid": snapshot.winning_block_txid.to_string(),
+                "stacks_block_hash": snapshot.winning_stacks_block_hash.to_string(),
+            })
+        } else {
+            serde_json::Value::Null
+        },
+        "total_burn_fee": commits.iter().map(|commit| commit.burn_fee).sum::<u64>(),
+        "block_commits": commits_json,
+        "missed_commits": missed_commits
+            .iter()
+            .map(|missed| {
+                json!({
+                    "txid": missed.txid.to_string(),
+                    "input": format!("{}:{}", missed.input.0, missed.input.1),
+                })
+            })
+            .collect::<Vec<_>>(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use stacks::core::STACKS_EPOCHS_REGTEST;
+    use stacks::types::chainstate::BurnchainHeaderHash;
+
+    #[test]
+    fn test_sortition_info_first_block() {
+        let path = "/tmp/stacks-node-tests/sortition_info/sortition.db";
+        if fs::metadata(path).is_ok() {
+            fs::remove_dir_all(path).unwrap();
+        }
+        let first_burn_hash = BurnchainHeaderHash([0x11; 32]);
+        SortitionDB::connect(path, 0, &first_burn_hash, 0, &*STACKS_EPOCHS_REGTEST, true).unwrap();
+
+        let sortdb = SortitionDB::open(path, false).unwrap();
+        let info = sortition_info(&sortdb, 0).unwrap();
+        assert_eq!(info["burn_block_height"], 0);
+        assert_eq!(info["burn_header_hash"], first_burn_hash.to_string());
+        assert_eq!(info["total_burn_fee"], 0);
+        assert_eq!(info["block_commits"].as_array().unwrap().len(), 0);
+        assert_eq!(info["missed_commits"].as_array().unwrap().len(), 0);
+
+        assert!(sortition_info(&sortdb, 1)
+            .unwrap_err()
+            .starts_with("Burn height 1 is above the canonical burnchain tip height 0"));
+    }
+}
@@ -0,0 +1,496 @@
+//! The `stacks-node stack-stx` subcommand, which builds and signs the PoX contract calls that
+//! stackers make, and optionally broadcasts them through a node's RPC interface.
+//!
+//! The PoX contract is picked from the Stacks epoch at the stacking start height, using the
+//! same epochs as the node, and only the calls that contract defines can be built.  Reward
+//! addresses are legacy bitcoin addresses, checked against the configured bitcoin network.
+
+use std::convert::TryFrom;
+use std::fmt;
+
+use async_std::io::ReadExt;
+use async_std::net::TcpStream;
+use http_types::{Method, Request, Url};
+
+use stacks::address::AddressHashMode;
+use stacks::burnchains::bitcoin::address::{BitcoinAddress, BitcoinAddressType};
+use stacks::chainstate::stacks::{
+    StacksPrivateKey, StacksTransaction, StacksTransactionSigner, TransactionAnchorMode,
+    TransactionContractCall, TransactionPayload, TransactionVersion,
+};
+use stacks::codec::StacksMessageCodec;
+use stacks::core::{StacksEpoch, StacksEpochId};
+use stacks::util::hash::to_hex;
+use stacks::util_lib::boot::boot_code_id;
+use stacks::vm::types::{PrincipalData, TupleData, Value};
+use stacks::vm::ClarityName;
+
+use crate::{BitcoinRegtestController, BurnchainController, Config, Keychain};
+
+/// The longest lock period, in reward cycles, that the PoX contract accepts
+pub const MAX_LOCK_PERIOD: u64 = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PoxCall {
+    StackStx,
+    DelegateStx,
+    StackExtend,
+}
+
+impl PoxCall {
+    pub fn from_str(call: &str) -> Result<PoxCall, String> {
+        match call {
+            "stack-stx" => Ok(PoxCall::StackStx),
+            "delegate-stx" => Ok(PoxCall::DelegateStx),
+            "stack-extend" => Ok(PoxCall::StackExtend),
+            _ => Err(format!(
+                "Unknown PoX call '{}': expected stack-stx, delegate-stx or stack-extend",
+                call
+            )),
+        }
+    }
+
+    pub fn function_name(&self) -> &'static str {
+        match self {
+            PoxCall::StackStx => "stack-stx",
+            PoxCall::DelegateStx => "delegate-stx",
+            PoxCall::StackExtend => "stack-extend",
+        }
+    }
+}
+
+impl fmt::Display for PoxCall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.function_name())
+    }
+}
+
+/// What to stack, as given on the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackingRequest {
+    pub call: PoxCall,
+    pub amount_ustx: u128,
+    /// Number of reward cycles to lock for, or to extend the lock by
+    pub lock_period: Option<u64>,
+    pub reward_address: Option<BitcoinAddress>,
+    pub delegate_to: Option<PrincipalData>,
+    pub until_burn_height: Option<u64>,
+}
+
+/// The signed contract call, as printed by `stack-stx`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StackingTransaction {
+    pub txid: String,
+    pub sender: String,
+    pub contract: String,
+    pub function: String,
+    /// The function arguments, as Clarity values
+    pub args: Vec<String>,
+    pub nonce: u64,
+    pub fee: u64,
+    /// The signed transaction, hex-encoded
+    pub hex: String,
+    /// Whether the transaction was posted to the node, as opposed to only printed
+    pub broadcast: bool,
+}
+
+/// The name of the PoX boot contract that stacking calls go to in `epoch`.
+pub fn pox_contract_for_epoch(epoch: StacksEpochId) -> Result<&'static str, String> {
+    match epoch {
+        StacksEpochId::Epoch10 => Err("PoX is not active before epoch 2.0".to_string()),
+        StacksEpochId::Epoch20 | StacksEpochId::Epoch2_05 => Ok("pox"),
+    }
+}
+
+/// The PoX contract in force at `burn_height`.
+pub fn pox_contract_at(epochs: &[StacksEpoch], burn_height: u64) -> Result<&'static str, String> {
+    let epoch_index = StacksEpoch::find_epoch(epochs, burn_height)
+        .ok_or_else(|| format!("No Stacks epoch at burnchain height {}", burn_height))?;
+    pox_contract_for_epoch(epochs[epoch_index].epoch_id)
+}
+
+/// The `{ version, hashbytes }` tuple that the PoX contract takes as a reward address.
+pub fn make_pox_addr(address: &BitcoinAddress) -> Value {
+    let hash_mode = match address.addrtype {
+        BitcoinAddressType::PublicKeyHash => AddressHashMode::SerializeP2PKH,
+        BitcoinAddressType::ScriptHash => AddressHashMode::SerializeP2SH,
+    };
+    Value::Tuple(
+        TupleData::from_data(vec![
+            (
+                ClarityName::try_from("version".to_string()).unwrap(),
+                Value::buff_from_byte(hash_mode as u8),
+            ),
+            (
+                ClarityName::try_from("hashbytes".to_string()).unwrap(),
+                Value::buff_from(address.bytes.as_bytes().to_vec()).unwrap(),
+            ),
+        ])
+        .expect("BUG: failed to build a PoX address tuple"),
+    )
+}
+
+/// The arguments of `request`'s call to `contract`, or an error if `contract` does not define
+/// that call or the request lacks one of its arguments.
+pub fn pox_call_args(
+    contract: &str,
+    request: &StackingRequest,
+    start_burn_height: u64,
+) -> Result<Vec<Value>, String> {
+    let lock_period = || match request.lock_period {
+        Some(lock_period) if lock_period >= 1 && lock_period <= MAX_LOCK_PERIOD => Ok(lock_period),
+        Some(lock_period) => Err(format!(
+            "--cycles must be between 1 and {}, not {}",
+            MAX_LOCK_PERIOD, lock_period
+        )),
+        None => Err(format!("{} needs --cycles", request.call)),
+    };
+    let pox_addr = || match request.reward_address {
+        Some(ref address) => Ok(make_pox_addr(address)),
+        None => Err(format!("{} needs --reward-address", request.call)),
+    };
+
+    match (contract, request.call) {
+        ("pox", PoxCall::StackStx) => Ok(vec![
+            Value::UInt(request.amount_ustx),
+            pox_addr()?,
+            Value::UInt(start_burn_height.into()),
+            Value::UInt(lock_period()?.into()),
+        ]),
+        ("pox", PoxCall::DelegateStx) => {
+            let delegate_to = request
+                .delegate_to
+                .clone()
+                .ok_or_else(|| format!("{} needs --delegate-to", request.call))?;
+            let until_burn_height = match request.until_burn_height {
+                Some(height) => Value::some(Value::UInt(height.into())).unwrap(),
+                None => Value::none(),
+            };
+            let pox_addr = match request.reward_address {
+                Some(ref address) => Value::some(make_pox_addr(address)).unwrap(),
+                None => Value::none(),
+            };
+            Ok(vec![
+                Value::UInt(request.amount_ustx),
+                Value::Principal(delegate_to),
+                until_burn_height,
+                pox_addr,
+            ])
+        }
+        (contract, call) => Err(format!(
+            "The `{}` contract has no `{}` function",
+            contract, call
+        )),
+    }
+}
+
+/// Signs a call of `function` in the PoX boot contract `contract`.
+pub fn make_pox_call_tx(
+    config: &Config,
+    keychain: &Keychain,
+    contract: &str,
+    function: &str,
+    args: Vec<Value>,
+    nonce: u64,
+    fee: u64,
+) -> StacksTransaction {
+    let mut tx_auth = keychain.get_transaction_auth().unwrap();
+    tx_auth.set_origin_nonce(nonce);
+    tx_auth.set_tx_fee(fee);
+
+    let version = if config.is_mainnet() {
+        TransactionVersion::Mainnet
+    } else {
+        TransactionVersion::Testnet
+    };
+    let contract_id = boot_code_id(contract, config.is_mainnet());
+    let payload = TransactionContractCall {
+        address: contract_id.issuer.into(),
+        contract_name: contract_id.name,
+        function_name: ClarityName::try_from(function.to_string())
+            .expect("BUG: invalid PoX function name"),
+        function_args: args,
+    };
+    let mut tx =
+        StacksTransaction::new(version, tx_auth, TransactionPayload::ContractCall(payload));
+    tx.chain_id = config.burnchain.chain_id;
+    tx.anchor_mode = TransactionAnchorMode::OnChainOnly;
+    let mut tx_signer = StacksTransactionSigner::new(&tx);
+    keychain.sign_as_origin(&mut tx_signer);
+
+    tx_signer.get_tx().unwrap()
+}
+
+/// Loads the stacker's keychain from a hex private key, or else from a hex seed the way the
+/// node derives its own keychain from `node.seed`.
+pub fn stacker_keychain(
+    private_key: Option<&str>,
+    seed: Option<&str>,
+    config: &Config,
+) -> Result<Keychain, String> {
+    if let Some(private_key) = private_key {
+        let private_key = StacksPrivateKey::from_hex(private_key)
+            .map_err(|_| "--private-key should be a hex encoded private key".to_string())?;
+        return Ok(Keychain::new(
+            vec![private_key],
+            1,
+            AddressHashMode::SerializeP2PKH,
+        ));
+    }
+    let seed = match seed {
+        Some(seed) => stacks::util::hash::hex_bytes(seed)
+            .map_err(|_| "--seed should be a hex encoded string".to_string())?,
+        None => config.node.seed.clone(),
+    };
+    Ok(Keychain::default(seed))
+}
+
+/// The node RPC address to talk to: `node.rpc_bind`, reached over loopback if it binds to all
+/// interfaces.
+fn node_rpc_address(config: &Config) -> String {
+    config.node.rpc_bind.replace("0.0.0.0", "127.0.0.1")
+}
+
+/// Sends a request to the node's RPC interface and returns its JSON response.
+fn node_rpc(
+    rpc_address: &str,
+    method: Method,
+    path: &str,
+    body: Option<Vec<u8>>,
+) -> Result<serde_json::Value, String> {
+    let url = Url::parse(&format!("http://{}{}", rpc_address, path))
+        .map_err(|e| format!("Invalid node RPC address '{}': {:?}", rpc_address, e))?;
+    let mut request = Request::new(method, url);
+    request.append_header("Connection", "close");
+    if let Some(body) = body {
+        request.append_header("Content-Type", "application/octet-stream");
+        request.set_body(body);
+    }
+
+    let (status, buffer) = async_std::task::block_on(async move {
+        let stream = TcpStream::connect(rpc_address)
+            .await
+            .map_err(|e| format!("Failed to connect to the node at {}: {:?}", rpc_address, e))?;
+        let mut response = async_h1::client::connect(stream, request)
+            .await
+            .map_err(|e| format!("Node RPC request failed: {:?}", e))?;
+        let mut buffer = Vec::new();
+        response
+            .take_body()
+            .read_to_end(&mut buffer)
+            .await
+            .map_err(|e| format!("Failed to read node RPC response: {:?}", e))?;
+        Ok::<_, String>((response.status(), buffer))
+    })?;
+
+    if !status.is_success() {
+        return Err(format!(
+            "Node RPC: status({}) for {}: {}",
+            status,
+            path,
+            String::from_utf8_lossy(&buffer)
+        ));
+    }
+    serde_json::from_slice(&buffer)
+        .map_err(|e| format!("Failed to parse node RPC response to {}: {:?}", path, e))
+}
+
+/// Options for `stack-stx` that are not part of the contract call itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackingOptions {
+    /// Defaults to the node's current burnchain height
+    pub start_burn_height: Option<u64>,
+    /// Defaults to the stacker's next nonce, as reported by the node
+    pub nonce: Option<u64>,
+    pub fee: u64,
+    /// Defaults to `node.rpc_bind`
+    pub node: Option<String>,
+    pub broadcast: bool,
+}
+
+/// Builds, signs and, if asked to, broadcasts `request`'s contract call.  The node is only
+/// queried for what `options` leaves out.
+pub fn stack_stx(
+    config: &Config,
+    keychain: &Keychain,
+    request: &StackingRequest,
+    options: &StackingOptions,
+) -> Result<StackingTransaction, String> {
+    let rpc_address = options
+        .node
+        .clone()
+        .unwrap_or_else(|| node_rpc_address(config));
+    let sender = keychain
+        .origin_address(config.is_mainnet())
+        .expect("BUG: keychain has no origin address");
+
+    let start_burn_height = match options.start_burn_height {
+        Some(height) => height,
+        None => node_rpc(&rpc_address, Method::Get, "/v2/info", None)?
+            .get("burn_block_height")
+            .and_then(|height| height.as_u64())
+            .ok_or_else(|| "Node RPC: /v2/info has no burn_block_height".to_string())?,
+    };
+    let nonce = match options.nonce {
+        Some(nonce) => nonce,
+        None => node_rpc(
+            &rpc_address,
+            Method::Get,
+            &format!("/v2/accounts/{}?proof=0", &sender),
+            None,
+        )?
+        .get("nonce")
+        .and_then(|nonce| nonce.as_u64())
+        .ok_or_else(|| "Node RPC: /v2/accounts has no nonce".to_string())?,
+    };
+
+    let epochs = BitcoinRegtestController::new_dummy(config.clone()).get_stacks_epochs();
+    let contract = pox_contract_at(&epochs, start_burn_height)?;
+    let args = pox_call_args(contract, request, start_burn_height)?;
+    let tx = make_pox_call_tx(
+        config,
+        keychain,
+        contract,
+        request.call.function_name(),
+        args.clone(),
+        nonce,
+        options.fee,
+    );
+    let tx_bytes = tx.serialize_to_vec();
+
+    if options.broadcast {
+        node_rpc(
+            &rpc_address,
+            Method::Post,
+            "/v2/transactions",
+            Some(tx_bytes.clone()),
+        )?;
+    }
+
+    Ok(StackingTransaction {
+        txid: tx.txid().to_string(),
+        sender: sender.to_string(),
+        contract: boot_code_id(contract, config.is_mainnet()).to_string(),
+        function: request.call.function_name().to_string(),
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+        nonce,
+        fee: options.fee,
+        hex: to_hex(&tx_bytes),
+        broadcast: options.broadcast,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::btc_wallet::parse_recipient;
+    use crate::ConfigFile;
+    use stacks::core::STACKS_EPOCHS_MAINNET;
+
+    fn regtest_config() -> Config {
+        Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [burnchain]
+                mode = "krypton"
+                "#,
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn stack_stx_request(config: &Config) -> StackingRequest {
+        StackingRequest {
+            call: PoxCall::StackStx,
+            amount_ustx: 100_000_000_000,
+            lock_period: Some(6),
+            reward_address: Some(
+                parse_recipient(config, "mxVFsFW5N4mu1HPkxPttorvocvzeZ7KZyk").unwrap(),
+            ),
+            delegate_to: None,
+            until_burn_height: None,
+        }
+    }
+
+    #[test]
+    fn test_pox_contract_selection() {
+        assert!(pox_contract_for_epoch(StacksEpochId::Epoch10).is_err());
+        assert_eq!(pox_contract_for_epoch(StacksEpochId::Epoch20), Ok("pox"));
+        assert_eq!(pox_contract_for_epoch(StacksEpochId::Epoch2_05), Ok("pox"));
+
+        let epochs = &*STACKS_EPOCHS_MAINNET;
+        assert!(pox_contract_at(epochs, epochs[0].start_height).is_err());
+        assert_eq!(pox_contract_at(epochs, epochs[1].start_height), Ok("pox"));
+        assert_eq!(pox_contract_at(epochs, epochs[2].start_height), Ok("pox"));
+    }
+
+    #[test]
+    fn test_pox_call_args() {
+        let config = regtest_config();
+        let request = stack_stx_request(&config);
+
+        let args = pox_call_args("pox", &request, 1000).unwrap();
+        assert_eq!(args.len(), 4);
+        assert_eq!(args[0], Value::UInt(100_000_000_000));
+        assert_eq!(
+            args[1],
+            make_pox_addr(&request.reward_address.clone().unwrap())
+        );
+        assert_eq!(args[2], Value::UInt(1000));
+        assert_eq!(args[3], Value::UInt(6));
+
+        // lock periods are bounded, and required
+        let mut bad_request = request.clone();
+        bad_request.lock_period = Some(MAX_LOCK_PERIOD + 1);
+        assert!(pox_call_args("pox", &bad_request, 1000).is_err());
+        bad_request.lock_period = None;
+        assert!(pox_call_args("pox", &bad_request, 1000).is_err());
+
+        // delegate-stx needs a delegate, but not a reward address
+        let mut delegate_request = request.clone();
+        delegate_request.call = PoxCall::DelegateStx;
+        assert!(pox_call_args("pox", &delegate_request, 1000).is_err());
+        delegate_request.delegate_to =
+            Some(PrincipalData::parse("ST2QKZ4FKHAH1NQKYKYAYZPY440FEPK7GZ1R5HBP2").unwrap());
+        delegate_request.reward_address = None;
+        let args = pox_call_args("pox", &delegate_request, 1000).unwrap();
+        assert_eq!(args.len(), 4);
+        assert_eq!(args[2], Value::none());
+        assert_eq!(args[3], Value::none());
+
+        // the original PoX contract cannot extend a lock
+        let mut extend_request = request.clone();
+        extend_request.call = PoxCall::StackExtend;
+        assert!(pox_call_args("pox", &extend_request, 1000).is_err());
+    }
+
+    #[test]
+    fn test_make_pox_call_tx() {
+        let config = regtest_config();
+        let keychain = stacker_keychain(None, Some("0123"), &config).unwrap();
+        let request = stack_stx_request(&config);
+        let args = pox_call_args("pox", &request, 1000).unwrap();
+
+        let tx = make_pox_call_tx(&config, &keychain, "pox", "stack-stx", args.clone(), 3, 400);
+        assert_eq!(tx.chain_id, config.burnchain.chain_id);
+        assert_eq!(tx.get_origin_nonce(), 3);
+        assert_eq!(tx.get_tx_fee(), 400);
+        tx.verify().unwrap();
+        match tx.payload {
+            TransactionPayload::ContractCall(ref call) => {
+                assert_eq!(call.contract_name.as_str(), "pox");
+                assert_eq!(call.function_name.as_str(), "stack-stx");
+                assert_eq!(call.function_args, args);
+            }
+            _ => panic!("not a contract call"),
+        }
+
+        // a hex private key signs as its own p2pkh address
+        let private_key = StacksPrivateKey::new();
+        let keychain = stacker_keychain(Some(&private_key.to_hex()), None, &config).unwrap();
+        let tx = make_pox_call_tx(&config, &keychain, "pox", "stack-stx", args, 0, 400);
+        tx.verify().unwrap();
+    }
+}
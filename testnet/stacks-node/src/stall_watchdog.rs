@@ -0,0 +1,287 @@
+//! Watches for the node to stop processing blocks, as configured by `[node.stall_watchdog]`.
+//!
+//! Every few seconds, the watchdog reads the canonical burnchain tip and the canonical Stacks tip
+//! from the sortition DB.  Once either of them has not moved for `timeout_secs`, the node has
+//! stalled: the watchdog logs it as an error, sets the `stacks_node_stalled` gauge, posts a
+//! `node_stall` event to burn block observers, and applies the configured remediation.  It does
+//! so again every `timeout_secs` for as long as the stall lasts.
+
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::monitoring;
+use stacks::util::get_epoch_time_ms;
+
+use crate::config::{StallRemediation, StallWatchdogConfig};
+use crate::supervisor;
+use crate::{Config, EventDispatcher};
+
+/// How often to check the chain tips, in seconds
+const CHECK_INTERVAL_SECS: u64 = 10;
+/// How long the `exit` remediation waits for the `node_stall` event to be delivered
+const EXIT_EVENT_TIMEOUT_SECS: u64 = 10;
+/// Exit status of a node that the `exit` remediation stopped
+pub const STALL_EXIT_CODE: i32 = 2;
+
+/// A stall, as reported to event observers on `POST /node_stall`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeStall {
+    /// The chain that stopped advancing: `burnchain` or `stacks`
+    pub chain: String,
+    /// Height of the chain's tip, which has not changed for `stalled_secs`
+    pub height: u64,
+    pub stalled_secs: u64,
+    /// `node.stall_watchdog.remediation`
+    pub remediation: String,
+}
+
+/// Remediations that the watchdog asks other threads to carry out: the run loop owns the
+/// connection to bitcoind, and the p2p thread owns the peer connections.
+#[derive(Clone, Default)]
+pub struct RemediationRequests {
+    reconnect_bitcoind: Arc<AtomicBool>,
+    reset_peers: Arc<AtomicBool>,
+}
+
+impl RemediationRequests {
+    pub fn new() -> RemediationRequests {
+        RemediationRequests::default()
+    }
+
+    fn request(&self, remediation: StallRemediation) {
+        match remediation {
+            StallRemediation::ReconnectBitcoind => {
+                self.reconnect_bitcoind.store(true, Ordering::SeqCst)
+            }
+            StallRemediation::ResetPeers => self.reset_peers.store(true, Ordering::SeqCst),
+            StallRemediation::None | StallRemediation::Exit => {}
+        }
+    }
+
+    /// Whether the connection to bitcoind should be reopened. Clears the request.
+    pub fn take_reconnect_bitcoind(&self) -> bool {
+        self.reconnect_bitcoind.swap(false, Ordering::SeqCst)
+    }
+
+    /// Whether all peers should be disconnected. Clears the request.
+    pub fn take_reset_peers(&self) -> bool {
+        self.reset_peers.swap(false, Ordering::SeqCst)
+    }
+}
+
+/// What happened to a watched chain since it was last observed
+#[derive(Debug, PartialEq)]
+enum StallChange {
+    /// The tip has not moved for this many milliseconds
+    Stalled(u64),
+    /// The tip moved after a stall
+    Recovered,
+}
+
+/// Tracks how long one chain's tip has not moved
+struct ChainWatch {
+    chain: &'static str,
+    height: Option<u64>,
+    /// When the tip last moved
+    progress_at_ms: u64,
+    /// When the stall was last reported, if the chain has stalled
+    reported_at_ms: Option<u64>,
+}
+
+impl ChainWatch {
+    fn new(chain: &'static str) -> ChainWatch {
+        ChainWatch {
+            chain,
+            height: None,
+            progress_at_ms: 0,
+            reported_at_ms: None,
+        }
+    }
+
+    /// Observe the chain's tip at `height`. A stall is reported once the tip has not moved for
+    /// `timeout_ms`, and again every `timeout_ms` until it moves.
+    fn observe(&mut self, height: u64, now_ms: u64, timeout_ms: u64) -> Option<StallChange> {
+        if self.height != Some(height) {
+            // a reorg to a lower tip counts as progress too
+            self.height = Some(height);
+            self.progress_at_ms = now_ms;
+            return self.reported_at_ms.take().map(|_| StallChange::Recovered);
+        }
+
+        let stalled_ms = now_ms.saturating_sub(self.progress_at_ms);
+        let last_report_ms = self.reported_at_ms.unwrap_or(self.progress_at_ms);
+        if stalled_ms >= timeout_ms && now_ms.saturating_sub(last_report_ms) >= timeout_ms {
+            self.reported_at_ms = Some(now_ms);
+            return Some(StallChange::Stalled(stalled_ms));
+        }
+        None
+    }
+}
+
+/// Report a stall of `chain` and apply `remediation`. Exits the process for the `exit`
+/// remediation.
+fn handle_stall(
+    stall: NodeStall,
+    remediation: StallRemediation,
+    event_dispatcher: &EventDispatcher,
+    requests: &RemediationRequests,
+) {
+    error!(
+        "Node stalled: no new {} block in {} seconds", stall.chain, stall.stalled_secs;
+        "height" => stall.height,
+        "remediation" => remediation.as_str()
+    );
+    monitoring::update_node_stalled(&stall.chain, true);
+
+    // an observer that is down would block the watchdog until it comes back
+    let (delivered_tx, delivered_rx) = channel();
+    let event_dispatcher = event_dispatcher.clone();
+    thread::Builder::new()
+        .name("stall-event".to_string())
+        .spawn(move || {
+            event_dispatcher.process_node_stall(&stall);
+            let _ = delivered_tx.send(());
+        })
+        .expect("FATAL: failed to spawn stall event thread");
+
+    match remediation {
+        StallRemediation::None => {}
+        StallRemediation::ReconnectBitcoind | StallRemediation::ResetPeers => {
+            requests.request(remediation)
+        }
+        StallRemediation::Exit => {
+            let _ = delivered_rx.recv_timeout(Duration::from_secs(EXIT_EVENT_TIMEOUT_SECS));
+            supervisor::notify_stopping();
+            error!("Node stalled: exiting so that the process supervisor restarts it");
+            process::exit(STALL_EXIT_CODE);
+        }
+    }
+}
+
+/// Spawns the stall watchdog thread. Must be called once the sortition DB exists.
+pub fn spawn_stall_watchdog(
+    config: &Config,
+    watchdog_config: StallWatchdogConfig,
+    event_dispatcher: EventDispatcher,
+    requests: RemediationRequests,
+    should_keep_running: Arc<AtomicBool>,
+) -> thread::JoinHandle<()> {
+    let burn_db_path = config.get_burn_db_file_path();
+    let timeout_ms = watchdog_config.timeout_secs * 1000;
+    info!(
+        "Stall watchdog enabled";
+        "timeout_secs" => watchdog_config.timeout_secs,
+        "remediation" => watchdog_config.remediation.as_str()
+    );
+
+    thread::Builder::new()
+        .name("stall-watchdog".to_string())
+        .spawn(move || {
+            let sortdb = SortitionDB::open(&burn_db_path, false)
+                .expect("FATAL: stall watchdog failed to open the sortition DB");
+            let mut burnchain = ChainWatch::new("burnchain");
+            let mut stacks = ChainWatch::new("stacks");
+            let mut elapsed_secs = CHECK_INTERVAL_SECS;
+            while should_keep_running.load(Ordering::SeqCst) {
+                if elapsed_secs < CHECK_INTERVAL_SECS {
+                    thread::sleep(Duration::from_secs(1));
+                    elapsed_secs += 1;
+                    continue;
+                }
+                elapsed_secs = 0;
+
+                let tip = match SortitionDB::get_canonical_burn_chain_tip(sortdb.conn()) {
+                    Ok(tip) => tip,
+                    Err(e) => {
+                        warn!("Stall watchdog: failed to load the chain tips: {:?}", &e);
+                        continue;
+                    }
+                };
+                let now_ms = get_epoch_time_ms() as u64;
+                for (watch, height) in [
+                    (&mut burnchain, tip.block_height),
+                    (&mut stacks, tip.canonical_stacks_tip_height),
+                ] {
+                    match watch.observe(height, now_ms, timeout_ms) {
+                        Some(StallChange::Stalled(stalled_ms)) => handle_stall(
+                            NodeStall {
+                                chain: watch.chain.to_string(),
+                                height,
+                                stalled_secs: stalled_ms / 1000,
+                                remediation: watchdog_config.remediation.as_str().to_string(),
+                            },
+                            watchdog_config.remediation,
+                            &event_dispatcher,
+                            &requests,
+                        ),
+                        Some(StallChange::Recovered) => {
+                            info!(
+                                "Node recovered from stall: new {} block", watch.chain;
+                                "height" => height
+                            );
+                            monitoring::update_node_stalled(watch.chain, false);
+                        }
+                        None => {}
+                    }
+                }
+            }
+        })
+        .expect("FATAL: failed to spawn stall watchdog thread")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_watch() {
+        let mut watch = ChainWatch::new("burnchain");
+        assert_eq!(watch.observe(100, 1_000, 60_000), None);
+        assert_eq!(watch.observe(100, 60_999, 60_000), None);
+        assert_eq!(
+            watch.observe(100, 61_000, 60_000),
+            Some(StallChange::Stalled(60_000))
+        );
+        // reported again only once another timeout has passed
+        assert_eq!(watch.observe(100, 90_000, 60_000), None);
+        assert_eq!(
+            watch.observe(100, 121_000, 60_000),
+            Some(StallChange::Stalled(120_000))
+        );
+        assert_eq!(
+            watch.observe(101, 122_000, 60_000),
+            Some(StallChange::Recovered)
+        );
+        assert_eq!(watch.observe(101, 150_000, 60_000), None);
+        // a reorg to a lower tip is progress
+        assert_eq!(watch.observe(99, 181_000, 60_000), None);
+        assert_eq!(
+            watch.observe(99, 241_000, 60_000),
+            Some(StallChange::Stalled(60_000))
+        );
+    }
+
+    #[test]
+    fn test_remediation_requests() {
+        let requests = RemediationRequests::new();
+        assert!(!requests.take_reconnect_bitcoind());
+        assert!(!requests.take_reset_peers());
+
+        requests.request(StallRemediation::None);
+        requests.request(StallRemediation::ResetPeers);
+        assert!(!requests.take_reconnect_bitcoind());
+        assert!(requests.take_reset_peers());
+        assert!(!requests.take_reset_peers());
+
+        requests
+            .clone()
+            .request(StallRemediation::ReconnectBitcoind);
+        assert!(requests.take_reconnect_bitcoind());
+        assert!(!requests.take_reconnect_bitcoind());
+    }
+}
@@ -0,0 +1,239 @@
+//! Active/standby failover for two miner nodes that share a seed, so a single host failure does
+//! not cost the miner its tenures.
+//!
+//! The two nodes coordinate through the burnchain itself: both have the same burnchain signer, so
+//! a standby, which sends no burnchain operations, knows that every block-commit from that signer
+//! was sent by the active miner. Once the active miner has sent no block-commit for
+//! `miner.standby_takeover_burn_blocks` burn blocks in a row, the standby takes over and mines
+//! from then on.
+//!
+//! The active miner is fenced the same way. A block-commit from its own signer that is not in its
+//! commit journal was sent by another node, such as a standby that took over while this node was
+//! cut off from the burnchain, so the active miner stands down and becomes the standby. If both
+//! nodes commit in the same burn block, the node whose block-commit comes first in the block keeps
+//! mining, and the other stands down; both see the same block, so they agree on which is which.
+
+/// A warm-standby miner's view of the active miner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinerStandby {
+    takeover_burn_blocks: u64,
+    missed_burn_blocks: u64,
+}
+
+impl MinerStandby {
+    pub fn new(takeover_burn_blocks: u64) -> MinerStandby {
+        MinerStandby {
+            takeover_burn_blocks,
+            missed_burn_blocks: 0,
+        }
+    }
+
+    /// Number of burn blocks in a row without a block-commit from the active miner.
+    pub fn missed_burn_blocks(&self) -> u64 {
+        self.missed_burn_blocks
+    }
+
+    /// Records a new burn block, and whether it has a block-commit from the active miner.
+    /// Returns true if the standby should take over.
+    pub fn observe_burn_block(&mut self, active_miner_committed: bool) -> bool {
+        if active_miner_committed {
+            self.missed_burn_blocks = 0;
+        } else {
+            self.missed_burn_blocks += 1;
+        }
+        self.missed_burn_blocks >= self.takeover_burn_blocks
+    }
+}
+
+/// A block-commit in a burn block from this miner's burnchain signer
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignerCommit {
+    pub vtxindex: u32,
+    /// Whether this node sent it, according to its commit journal
+    pub sent_by_us: bool,
+}
+
+/// What a burn block made a miner do
+#[derive(Debug, Clone, PartialEq)]
+pub enum FailoverEvent {
+    /// The standby saw no block-commit from the active miner for this many burn blocks
+    TookOver(u64),
+    /// Another node is committing with this miner's signer
+    StoodDown,
+}
+
+/// Whether a miner node is the active miner or the standby.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinerFailover {
+    takeover_burn_blocks: u64,
+    /// Set while this node is the standby
+    standby: Option<MinerStandby>,
+}
+
+impl MinerFailover {
+    pub fn new(standby: bool, takeover_burn_blocks: u64) -> MinerFailover {
+        MinerFailover {
+            takeover_burn_blocks,
+            standby: if standby {
+                Some(MinerStandby::new(takeover_burn_blocks))
+            } else {
+                None
+            },
+        }
+    }
+
+    pub fn is_standby(&self) -> bool {
+        self.standby.is_some()
+    }
+
+    /// Records a new burn block and the block-commits in it from this miner's signer.
+    pub fn observe_burn_block(&mut self, signer_commits: &[SignerCommit]) -> Option<FailoverEvent> {
+        match self.standby.as_mut() {
+            Some(standby) => {
+                if standby.observe_burn_block(!signer_commits.is_empty()) {
+                    let missed_burn_blocks = standby.missed_burn_blocks();
+                    self.standby = None;
+                    return Some(FailoverEvent::TookOver(missed_burn_blocks));
+                }
+                None
+            }
+            None => {
+                let first_commit = signer_commits.iter().min_by_key(|commit| commit.vtxindex)?;
+                if first_commit.sent_by_us {
+                    return None;
+                }
+                self.standby = Some(MinerStandby::new(self.takeover_burn_blocks));
+                Some(FailoverEvent::StoodDown)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_standby_takeover() {
+        let mut standby = MinerStandby::new(3);
+        assert!(!standby.observe_burn_block(false));
+        assert!(!standby.observe_burn_block(false));
+        // a commit restarts the count
+        assert!(!standby.observe_burn_block(true));
+        assert_eq!(standby.missed_burn_blocks(), 0);
+        assert!(!standby.observe_burn_block(false));
+        assert!(!standby.observe_burn_block(false));
+        assert!(standby.observe_burn_block(false));
+        assert_eq!(standby.missed_burn_blocks(), 3);
+    }
+
+    /// Two nodes with the same signer. Each burn block holds the block-commits of the nodes
+    /// that are up and active, in the given order, and each node sees it from its own side.
+    fn mine_burn_block(
+        nodes: &mut [&mut MinerFailover; 2],
+        up: [bool; 2],
+        order: [usize; 2],
+    ) -> [Option<FailoverEvent>; 2] {
+        let committers: Vec<usize> = order
+            .iter()
+            .cloned()
+            .filter(|i| up[*i] && !nodes[*i].is_standby())
+            .collect();
+        let mut events = [None, None];
+        for i in 0..2 {
+            if !up[i] {
+                continue;
+            }
+            let signer_commits: Vec<_> = committers
+                .iter()
+                .enumerate()
+                .map(|(vtxindex, committer)| SignerCommit {
+                    vtxindex: vtxindex as u32,
+                    sent_by_us: *committer == i,
+                })
+                .collect();
+            events[i] = nodes[i].observe_burn_block(&signer_commits);
+        }
+        events
+    }
+
+    #[test]
+    fn test_active_miner_recovers_after_takeover() {
+        let mut active = MinerFailover::new(false, 3);
+        let mut standby = MinerFailover::new(true, 3);
+        let mut nodes = [&mut active, &mut standby];
+
+        assert_eq!(
+            mine_burn_block(&mut nodes, [true, true], [0, 1]),
+            [None, None]
+        );
+        // the active miner loses its burnchain connection for three burn blocks
+        for _ in 0..2 {
+            assert_eq!(
+                mine_burn_block(&mut nodes, [false, true], [0, 1]),
+                [None, None]
+            );
+        }
+        assert_eq!(
+            mine_burn_block(&mut nodes, [false, true], [0, 1]),
+            [None, Some(FailoverEvent::TookOver(3))]
+        );
+        assert!(!nodes[1].is_standby());
+
+        // it comes back, still believing it is the active miner, and both commit in the same
+        // burn block: the standby's commit comes first, so the old active miner stands down
+        assert!(!nodes[0].is_standby());
+        assert_eq!(
+            mine_burn_block(&mut nodes, [true, true], [1, 0]),
+            [Some(FailoverEvent::StoodDown), None]
+        );
+        assert!(nodes[0].is_standby());
+        assert!(!nodes[1].is_standby());
+
+        // from then on only one node commits, and the other stays the standby
+        for _ in 0..10 {
+            assert_eq!(
+                mine_burn_block(&mut nodes, [true, true], [0, 1]),
+                [None, None]
+            );
+        }
+        assert!(nodes[0].is_standby());
+        assert!(!nodes[1].is_standby());
+    }
+
+    #[test]
+    fn test_first_commit_keeps_mining() {
+        // the old active miner's commit comes first in the burn block, so the node that just
+        // took over stands down instead
+        let mut active = MinerFailover::new(false, 2);
+        let mut standby = MinerFailover::new(true, 2);
+        let mut nodes = [&mut active, &mut standby];
+        for _ in 0..2 {
+            mine_burn_block(&mut nodes, [false, true], [0, 1]);
+        }
+        assert!(!nodes[1].is_standby());
+        assert_eq!(
+            mine_burn_block(&mut nodes, [true, true], [0, 1]),
+            [None, Some(FailoverEvent::StoodDown)]
+        );
+        assert!(!nodes[0].is_standby());
+        assert!(nodes[1].is_standby());
+
+        // a commit from the other node when this node did not commit also means standing down
+        let mut active = MinerFailover::new(false, 2);
+        assert_eq!(
+            active.observe_burn_block(&[SignerCommit {
+                vtxindex: 3,
+                sent_by_us: false,
+            }]),
+            Some(FailoverEvent::StoodDown)
+        );
+        assert_eq!(
+            MinerFailover::new(false, 2).observe_burn_block(&[SignerCommit {
+                vtxindex: 3,
+                sent_by_us: true,
+            }]),
+            None
+        );
+    }
+}
@@ -0,0 +1,331 @@
+//! Reports the node's lifecycle to the process supervisor running it, so that the supervisor
+//! knows when the node is ready and whether it is still healthy.
+//!
+//! On Linux, this speaks systemd's notification protocol (`sd_notify(3)`) to the socket named
+//! by `$NOTIFY_SOCKET`: `READY=1` once the node serves RPC requests, `STATUS=` lines while it
+//! syncs, and `STOPPING=1` on shutdown. If the unit sets `WatchdogSec=`, the node sends
+//! `WATCHDOG=1` for as long as its p2p thread keeps making passes, so a wedged node gets
+//! stopped. Setting `node.supervisor_notify = false` keeps the node off the socket.
+//!
+//! On Windows, `stacks-node start --windows-service` runs the node under the service control
+//! manager, which is told when the node is running and stopping, and can stop it like any other
+//! service.
+
+use std::env;
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use stacks::util::get_epoch_time_ms;
+
+/// Whether to speak to systemd's notification socket. See `node.supervisor_notify`.
+static NOTIFY_ENABLED: AtomicBool = AtomicBool::new(true);
+
+pub fn set_notify_enabled(enabled: bool) {
+    NOTIFY_ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// Records when a long-running thread last made progress.
+#[derive(Clone, Default)]
+pub struct Heartbeat(Arc<AtomicU64>);
+
+impl Heartbeat {
+    pub fn new() -> Heartbeat {
+        Heartbeat::default()
+    }
+
+    pub fn beat(&self) {
+        self.0.store(get_epoch_time_ms() as u64, Ordering::SeqCst);
+    }
+
+    /// Milliseconds since the last beat, or None if there has not been one yet.
+    fn age_ms(&self, now_ms: u64) -> Option<u64> {
+        match self.0.load(Ordering::SeqCst) {
+            0 => None,
+            last_beat_ms => Some(now_ms.saturating_sub(last_beat_ms)),
+        }
+    }
+}
+
+/// Tell the supervisor that the node is up and serving requests.
+pub fn notify_ready() {
+    notify("READY=1");
+    #[cfg(windows)]
+    windows::set_state(windows_service::service::ServiceState::Running);
+}
+
+/// Tell the supervisor that the node is shutting down.
+pub fn notify_stopping() {
+    notify("STOPPING=1");
+    #[cfg(windows)]
+    windows::set_state(windows_service::service::ServiceState::StopPending);
+}
+
+/// Describe what the node is doing, e.g. for `systemctl status`.
+pub fn notify_status(status: &str) {
+    notify(&format!("STATUS={}", status));
+}
+
+fn notify(state: &str) {
+    if !NOTIFY_ENABLED.load(Ordering::SeqCst) {
+        return;
+    }
+    #[cfg(target_os = "linux")]
+    if let Ok(socket) = env::var("NOTIFY_SOCKET") {
+        if let Err(e) = sd_notify(&socket, state) {
+            warn!("Failed to notify systemd at {}: {}", &socket, e);
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = state;
+}
+
+/// Send `state` to the systemd notification socket at `socket`. A leading `@` names a socket in
+/// the abstract namespace.
+#[cfg(target_os = "linux")]
+fn sd_notify(socket: &str, state: &str) -> std::io::Result<()> {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+    let addr = match socket.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name)?,
+        None => SocketAddr::from_pathname(socket)?,
+    };
+    UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &addr)?;
+    Ok(())
+}
+
+/// The watchdog interval the supervisor expects, from `$WATCHDOG_USEC`, if it is meant for this
+/// process.
+fn watchdog_interval(watchdog_usec: Option<&str>, watchdog_pid: Option<&str>) -> Option<Duration> {
+    if let Some(pid) = watchdog_pid {
+        if pid.parse::<u32>().ok()? != process::id() {
+            return None;
+        }
+    }
+    match watchdog_usec?.parse::<u64>().ok()? {
+        0 => None,
+        usec => Some(Duration::from_micros(usec)),
+    }
+}
+
+/// If the supervisor runs a watchdog, start a thread that pings it for as long as `heartbeat`
+/// beats at least once per watchdog interval. Before the first beat, the node is still booting,
+/// and the supervisor's startup timeout applies instead.
+pub fn start_watchdog(heartbeat: Heartbeat, should_keep_running: Arc<AtomicBool>) {
+    if !NOTIFY_ENABLED.load(Ordering::SeqCst) {
+        return;
+    }
+    let interval = match watchdog_interval(
+        env::var("WATCHDOG_USEC").ok().as_deref(),
+        env::var("WATCHDOG_PID").ok().as_deref(),
+    ) {
+        Some(interval) => interval,
+        None => return,
+    };
+    info!("Supervisor watchdog enabled"; "interval_ms" => interval.as_millis() as u64);
+    thread::Builder::new()
+        .name("watchdog".to_string())
+        .spawn(move || {
+            while should_keep_running.load(Ordering::SeqCst) {
+                let now_ms = get_epoch_time_ms() as u64;
+                match heartbeat.age_ms(now_ms) {
+                    Some(age_ms) if age_ms >= interval.as_millis() as u64 => {
+                        warn!(
+                            "P2P thread has not made progress in {} ms; not pinging the watchdog",
+                            age_ms
+                        );
+                    }
+                    _ => notify("WATCHDOG=1"),
+                }
+                thread::sleep(interval / 2);
+            }
+        })
+        .expect("FATAL: failed to start watchdog thread");
+}
+
+/// Run `run_node` under the Windows service control manager. A stop or shutdown request from
+/// the service control manager clears `should_keep_running`, which makes the node shut down
+/// gracefully.
+#[cfg(windows)]
+pub fn run_windows_service<F>(should_keep_running: Arc<AtomicBool>, run_node: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    windows::run(should_keep_running, Box::new(run_node));
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::ffi::OsString;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use windows_service::service::{
+        ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus,
+        ServiceType,
+    };
+    use windows_service::service_control_handler::{
+        self, ServiceControlHandlerResult, ServiceStatusHandle,
+    };
+    use windows_service::{define_windows_service, service_dispatcher};
+
+    const SERVICE_NAME: &str = "stacks-node";
+
+    struct Service {
+        should_keep_running: Arc<AtomicBool>,
+        run_node: Option<Box<dyn FnOnce() + Send>>,
+        status_handle: Option<ServiceStatusHandle>,
+    }
+
+    lazy_static! {
+        static ref SERVICE: Mutex<Option<Service>> = Mutex::new(None);
+    }
+
+    define_windows_service!(ffi_service_main, service_main);
+
+    pub fn run(should_keep_running: Arc<AtomicBool>, run_node: Box<dyn FnOnce() + Send>) {
+        *SERVICE.lock().unwrap() = Some(Service {
+            should_keep_running,
+            run_node: Some(run_node),
+            status_handle: None,
+        });
+        // blocks until the service has stopped
+        if let Err(e) = service_dispatcher::start(SERVICE_NAME, ffi_service_main) {
+            error!("Failed to start the Windows service dispatcher: {}", e);
+        }
+    }
+
+    fn service_main(_arguments: Vec<OsString>) {
+        let (stop_switch, run_node) = {
+            let mut service = SERVICE.lock().unwrap();
+            let service = service.as_mut().expect("BUG: Windows service not set up");
+            (
+                service.should_keep_running.clone(),
+                service
+                    .run_node
+                    .take()
+                    .expect("BUG: Windows service started twice"),
+            )
+        };
+
+        let status_handle =
+            match service_control_handler::register(SERVICE_NAME, move |control_event| {
+                match control_event {
+                    ServiceControl::Stop | ServiceControl::Shutdown => {
+                        info!("Graceful termination requested by the service control manager");
+                        stop_switch.store(false, Ordering::SeqCst);
+                        ServiceControlHandlerResult::NoError
+                    }
+                    ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+                    _ => ServiceControlHandlerResult::NotImplemented,
+                }
+            }) {
+                Ok(status_handle) => status_handle,
+                Err(e) => {
+                    error!(
+                        "Failed to register the Windows service control handler: {}",
+                        e
+                    );
+                    return;
+                }
+            };
+        SERVICE
+            .lock()
+            .unwrap()
+            .as_mut()
+            .expect("BUG: Windows service not set up")
+            .status_handle = Some(status_handle);
+
+        set_state(ServiceState::StartPending);
+        run_node();
+        set_state(ServiceState::Stopped);
+    }
+
+    /// Report `state` to the service control manager, if the node runs as a service.
+    pub fn set_state(state: ServiceState) {
+        let status_handle = match SERVICE.lock().unwrap().as_ref() {
+            Some(Service {
+                status_handle: Some(status_handle),
+                ..
+            }) => *status_handle,
+            _ => return,
+        };
+        let controls_accepted = match state {
+            ServiceState::Running => ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+            _ => ServiceControlAccept::empty(),
+        };
+        let result = status_handle.set_service_status(ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state: state,
+            controls_accepted,
+            exit_code: ServiceExitCode::Win32(0),
+            checkpoint: 0,
+            // booting and shutting down can take a while
+            wait_hint: Duration::from_secs(600),
+            process_id: None,
+        });
+        if let Err(e) = result {
+            warn!("Failed to report Windows service state {:?}: {}", state, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchdog_interval() {
+        let pid = process::id().to_string();
+        assert_eq!(watchdog_interval(None, None), None);
+        assert_eq!(
+            watchdog_interval(Some("30000000"), None),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            watchdog_interval(Some("30000000"), Some(&pid)),
+            Some(Duration::from_secs(30))
+        );
+        // meant for another process
+        assert_eq!(watchdog_interval(Some("30000000"), Some("1")), None);
+        assert_eq!(watchdog_interval(Some("0"), None), None);
+        assert_eq!(watchdog_interval(Some("soon"), None), None);
+    }
+
+    #[test]
+    fn test_heartbeat() {
+        let heartbeat = Heartbeat::new();
+        assert_eq!(heartbeat.age_ms(1_000), None);
+        heartbeat.beat();
+        let now_ms = get_epoch_time_ms() as u64;
+        assert!(heartbeat.age_ms(now_ms + 5_000).unwrap() >= 5_000);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_sd_notify() {
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+        let path = "/tmp/stacks-node-tests/supervisor-notify.sock";
+        std::fs::create_dir_all("/tmp/stacks-node-tests").unwrap();
+        let _ = std::fs::remove_file(path);
+        let receiver = UnixDatagram::bind(path).unwrap();
+        sd_notify(path, "READY=1").unwrap();
+
+        let mut buf = [0u8; 64];
+        let len = receiver.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"READY=1");
+
+        // abstract socket
+        let addr = SocketAddr::from_abstract_name("stacks-node-tests-supervisor").unwrap();
+        let receiver = UnixDatagram::bind_addr(&addr).unwrap();
+        sd_notify("@stacks-node-tests-supervisor", "WATCHDOG=1").unwrap();
+        let len = receiver.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"WATCHDOG=1");
+    }
+}
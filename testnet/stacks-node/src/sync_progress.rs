@@ -0,0 +1,113 @@
+//! Logs one line about how far the node has synced every `node.sync_progress_log_interval_secs`
+//! while it is in initial block download: the percent of each chain synced, how fast it is
+//! syncing, and when it should be done. The same numbers are served by `/v2/info` under
+//! `sync_progress`, and exported to Prometheus.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use stacks::monitoring::{self, ChainSyncProgress, SyncProgress};
+
+use crate::syncctl::PoxSyncWatchdogComms;
+
+/// `secs` as e.g. `2h05m`, `4m10s` or `30s`
+fn format_duration(secs: u64) -> String {
+    if secs >= 3600 {
+        format!("{}h{:02}m", secs / 3600, (secs % 3600) / 60)
+    } else if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+fn format_chain(progress: &ChainSyncProgress) -> String {
+    format!(
+        "{:.1}% ({}/{}, {:.1} blocks/s, ETA {})",
+        progress.percent,
+        progress.height,
+        progress.target_height,
+        progress.blocks_per_sec,
+        progress
+            .eta_secs
+            .map(format_duration)
+            .unwrap_or_else(|| "unknown".to_string())
+    )
+}
+
+pub fn format_sync_progress(progress: &SyncProgress) -> String {
+    format!(
+        "Sync progress: burnchain {}; Stacks {}, tip height {} ({:.1} blocks/s)",
+        format_chain(&progress.burnchain),
+        format_chain(&progress.stacks),
+        progress.stacks_tip_height,
+        progress.stacks_blocks_per_sec
+    )
+}
+
+/// Spawns the thread that logs the node's sync progress every `interval_secs` while it is in
+/// initial block download.
+pub fn spawn_sync_progress_logger(
+    interval_secs: u64,
+    sync_comms: PoxSyncWatchdogComms,
+    should_keep_running: Arc<AtomicBool>,
+) -> thread::JoinHandle<()> {
+    thread::Builder::new()
+        .name("sync-progress".to_string())
+        .spawn(move || {
+            let mut elapsed_secs = 0;
+            while should_keep_running.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_secs(1));
+                elapsed_secs += 1;
+                if elapsed_secs < interval_secs {
+                    continue;
+                }
+                elapsed_secs = 0;
+
+                if !sync_comms.get_ibd() {
+                    continue;
+                }
+                if let Some(progress) = monitoring::get_sync_progress() {
+                    info!("{}", format_sync_progress(&progress));
+                }
+            }
+        })
+        .expect("FATAL: failed to spawn sync progress thread")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_sync_progress() {
+        assert_eq!(format_duration(30), "30s");
+        assert_eq!(format_duration(250), "4m10s");
+        assert_eq!(format_duration(7500), "2h05m");
+
+        let progress = SyncProgress {
+            burnchain: ChainSyncProgress {
+                height: 700000,
+                target_height: 720000,
+                percent: 97.22,
+                blocks_per_sec: 12.5,
+                eta_secs: Some(1600),
+            },
+            stacks: ChainSyncProgress {
+                height: 680000,
+                target_height: 720000,
+                percent: 40.0,
+                blocks_per_sec: 0.0,
+                eta_secs: None,
+            },
+            stacks_tip_height: 30000,
+            stacks_blocks_per_sec: 0.0,
+        };
+        assert_eq!(
+            format_sync_progress(&progress),
+            "Sync progress: burnchain 97.2% (700000/720000, 12.5 blocks/s, ETA 26m40s); Stacks 40.0% (680000/720000, 0.0 blocks/s, ETA unknown), tip height 30000 (0.0 blocks/s)"
+        );
+    }
+}
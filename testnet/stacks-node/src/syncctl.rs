@@ -2,6 +2,7 @@ use std::collections::VecDeque;
 
 use stacks::burnchains::{Burnchain, Error as burnchain_error};
 use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::util::get_epoch_time_ms;
 use stacks::util::get_epoch_time_secs;
 use stacks::util::sleep_ms;
 
@@ -33,6 +34,8 @@ pub struct PoxSyncWatchdogComms {
     last_ibd: Arc<AtomicBool>,
     /// Should keep running?
     should_keep_running: Arc<AtomicBool>,
+    /// how many new burnchain blocks has bitcoind announced?
+    burn_block_notifications: Arc<AtomicU64>,
 }
 
 impl PoxSyncWatchdogComms {
@@ -44,6 +47,7 @@ impl PoxSyncWatchdogComms {
             burnchain_tip_height: Arc::new(AtomicU64::new(0)),
             last_ibd: Arc::new(AtomicBool::new(true)),
             should_keep_running,
+            burn_block_notifications: Arc::new(AtomicU64::new(0)),
         }
     }
 
@@ -59,6 +63,10 @@ impl PoxSyncWatchdogComms {
         self.download_passes.load(Ordering::SeqCst)
     }
 
+    pub fn get_burn_block_notifications(&self) -> u64 {
+        self.burn_block_notifications.load(Ordering::SeqCst)
+    }
+
     pub fn get_ibd(&self) -> bool {
         self.last_ibd.load(Ordering::SeqCst)
     }
@@ -79,13 +87,19 @@ impl PoxSyncWatchdogComms {
         return Ok(true);
     }
 
+    /// Sleep for `secs` seconds, or until bitcoind announces a new burnchain block.
     fn interruptable_sleep(&self, secs: u64) -> Result<(), burnchain_error> {
-        let deadline = secs + get_epoch_time_secs();
-        while get_epoch_time_secs() < deadline {
-            sleep_ms(1000);
+        let deadline = (secs as u128) * 1000 + get_epoch_time_ms();
+        let notifications = self.get_burn_block_notifications();
+        while get_epoch_time_ms() < deadline {
+            sleep_ms(100);
             if !self.should_keep_running() {
                 return Err(burnchain_error::CoordinatorClosed);
             }
+            if self.get_burn_block_notifications() != notifications {
+                debug!("PoX watchdog comms: woken up by a new burnchain block");
+                break;
+            }
         }
         Ok(())
     }
@@ -117,6 +131,10 @@ impl PoxSyncWatchdogComms {
         self.inv_sync_passes.fetch_add(1, Ordering::SeqCst);
     }
 
+    pub fn notify_burn_block(&self) {
+        self.burn_block_notifications.fetch_add(1, Ordering::SeqCst);
+    }
+
     pub fn notify_download_pass(&mut self) {
         self.download_passes.fetch_add(1, Ordering::SeqCst);
     }
@@ -1,6 +1,7 @@
 use super::node::ChainTip;
 use super::{BurnchainTip, Config};
 
+use std::cmp;
 use std::thread;
 use std::time::{Duration, Instant};
 
@@ -15,6 +16,9 @@ use stacks::types::chainstate::VRFSeed;
 use stacks::util::hash::Hash160;
 use stacks::util::vrf::VRFProof;
 
+/// How often a waiting tenure checks the clock and the mempool, in milliseconds.
+const TENURE_POLL_INTERVAL_MS: u128 = 100;
+
 pub struct TenureArtifacts {
     pub anchored_block: StacksBlock,
     pub microblocks: Vec<StacksMicroblock>,
@@ -69,12 +73,7 @@ impl<'a> Tenure {
     pub fn run(&mut self, burn_dbconn: &SortitionDBConn) -> Option<TenureArtifacts> {
         info!("Node starting new tenure with VRF {:?}", self.vrf_seed);
 
-        let duration_left: u128 = self.config.burnchain.commit_anchor_block_within as u128;
-        let mut elapsed = Instant::now().duration_since(self.burnchain_tip.received_at);
-        while duration_left.saturating_sub(elapsed.as_millis()) > 0 {
-            thread::sleep(Duration::from_millis(1000));
-            elapsed = Instant::now().duration_since(self.burnchain_tip.received_at);
-        }
+        self.wait_for_block_time();
 
         let (mut chain_state, _) = StacksChainState::open(
             self.config.is_mainnet(),
@@ -109,6 +108,51 @@ impl<'a> Tenure {
         Some(artifact)
     }
 
+    /// Blocks until it is time to assemble this tenure's block: once the configured block
+    /// time has elapsed, or, in `mine_on_transaction` mode, as soon as a transaction built
+    /// against the parent block shows up in the mempool.
+    fn wait_for_block_time(&self) {
+        let mine_on_transaction = self.config.mocknet.mine_on_transaction;
+        let duration_left: u128 = if mine_on_transaction {
+            match self.config.mocknet.block_time_ms {
+                Some(block_time_ms) => block_time_ms as u128,
+                None => u128::MAX,
+            }
+        } else {
+            self.config.get_block_time_ms() as u128
+        };
+
+        let mut elapsed = Instant::now().duration_since(self.burnchain_tip.received_at);
+        while duration_left.saturating_sub(elapsed.as_millis()) > 0 {
+            if mine_on_transaction && self.has_pending_transactions() {
+                info!("Transaction arrived in mempool, assembling block");
+                return;
+            }
+            let sleep_ms = cmp::min(
+                duration_left.saturating_sub(elapsed.as_millis()),
+                TENURE_POLL_INTERVAL_MS,
+            );
+            thread::sleep(Duration::from_millis(sleep_ms as u64));
+            elapsed = Instant::now().duration_since(self.burnchain_tip.received_at);
+        }
+    }
+
+    fn has_pending_transactions(&self) -> bool {
+        match MemPoolDB::get_txs_after(
+            self.mem_pool.conn(),
+            &self.parent_block.metadata.consensus_hash,
+            &self.parent_block.metadata.anchored_header.block_hash(),
+            0,
+            1,
+        ) {
+            Ok(txs) => !txs.is_empty(),
+            Err(e) => {
+                warn!("Failed to query mempool for pending transactions: {:?}", e);
+                false
+            }
+        }
+    }
+
     #[cfg(test)]
     pub fn open_chainstate(&self) -> StacksChainState {
         use stacks::core::CHAIN_ID_TESTNET;
@@ -33,6 +33,7 @@ use stacks::vm::{
 use crate::config::InitialBalance;
 use crate::helium::RunLoop;
 use crate::tests::make_sponsored_stacks_transfer_on_testnet;
+use crate::Scenario;
 use stacks::core::StacksEpoch;
 use stacks::core::StacksEpochId;
 use stacks::vm::costs::ExecutionCost;
@@ -2215,3 +2216,68 @@ fn mempool_errors() {
 
     run_loop.start(num_rounds).unwrap();
 }
+
+#[test]
+fn integration_test_scenario() {
+    let mut conf = super::new_test_conf();
+    let spender_sk = StacksPrivateKey::from_hex(SK_3).unwrap();
+    conf.initial_balances.push(InitialBalance {
+        address: to_addr(&spender_sk).into(),
+        amount: 100300,
+    });
+    conf.burnchain.commit_anchor_block_within = 1000;
+    conf.miner.min_tx_fee = 0;
+
+    let transfer_tx = make_stacks_transfer(
+        &spender_sk,
+        0,
+        300,
+        &StacksAddress::from_string(ADDR_4).unwrap().into(),
+        1000,
+    );
+
+    let scenario = Scenario::from_str(&format!(
+        r#"
+        rounds = 6
+
+        [[round]]
+        index = 1
+        transactions = ["{}"]
+
+        [[round]]
+        index = 2
+        skip_sortition = true
+
+        [[round]]
+        index = 4
+        fork_from_round = 1
+        "#,
+        to_hex(&transfer_tx)
+    ))
+    .unwrap();
+
+    let num_rounds = scenario.rounds;
+    let mut run_loop = RunLoop::new(conf);
+    run_loop.set_scenario(scenario);
+
+    run_loop.callbacks.on_new_stacks_chain_state(
+        |round, _burnchain_tip, chain_tip, _chain_state, _burn_dbconn| {
+            let expected_height = match round {
+                0 => 1,
+                1 => {
+                    // coinbase + the scenario's token transfer
+                    assert_eq!(chain_tip.block.txs.len(), 2);
+                    2
+                }
+                3 => 3,
+                // sibling of the block produced in round 3
+                4 => 3,
+                5 => 4,
+                _ => panic!("No Stacks block expected in round {}", round),
+            };
+            assert_eq!(chain_tip.metadata.stacks_block_height, expected_height);
+        },
+    );
+
+    run_loop.start(num_rounds).unwrap();
+}
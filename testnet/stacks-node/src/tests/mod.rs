@@ -28,7 +28,9 @@ use stacks::{address::AddressHashMode, util::hash::to_hex};
 use crate::helium::RunLoop;
 use stacks::core::StacksEpochId;
 
-use super::burnchains::bitcoin_regtest_controller::ParsedUTXO;
+use super::burnchains::bitcoin_regtest_controller::{
+    btc_per_kvb_to_sat_per_vb, next_auto_rbf_fee_rate, MempoolEntry, ParsedUTXO,
+};
 use super::Config;
 
 mod atlas;
@@ -937,3 +939,33 @@ fn test_btc_to_sat_errors() {
     assert!(ParsedUTXO::serialized_btc_to_sat("7.4e-7").is_none());
     assert!(ParsedUTXO::serialized_btc_to_sat("5.96e-6").is_none());
 }
+
+#[test]
+fn test_next_auto_rbf_fee_rate() {
+    // in the mempool at the next block's fee rate or better: leave it alone
+    assert_eq!(next_auto_rbf_fee_rate(50, Some(50), Some(50)), None);
+    assert_eq!(next_auto_rbf_fee_rate(50, Some(50), Some(20)), None);
+    // in the mempool, but no estimate to compare to
+    assert_eq!(next_auto_rbf_fee_rate(50, Some(50), None), None);
+    // in the mempool below the next block's fee rate: bump to it
+    assert_eq!(next_auto_rbf_fee_rate(50, Some(50), Some(80)), Some(80));
+    // the replacement always pays more than the original
+    assert_eq!(next_auto_rbf_fee_rate(50, Some(40), Some(45)), Some(51));
+    // missing from the mempool: resubmit, at the estimate if there is one
+    assert_eq!(next_auto_rbf_fee_rate(50, None, None), Some(51));
+    assert_eq!(next_auto_rbf_fee_rate(50, None, Some(20)), Some(51));
+    assert_eq!(next_auto_rbf_fee_rate(50, None, Some(70)), Some(70));
+}
+
+#[test]
+fn test_auto_rbf_fee_rate_units() {
+    assert_eq!(btc_per_kvb_to_sat_per_vb(0.00001), 1);
+    assert_eq!(btc_per_kvb_to_sat_per_vb(0.00012345), 13);
+    assert_eq!(btc_per_kvb_to_sat_per_vb(0.001), 100);
+
+    let entry = MempoolEntry {
+        vsize: 250,
+        fee: 12_500,
+    };
+    assert_eq!(entry.fee_rate(), 50);
+}
@@ -140,6 +140,8 @@ pub mod test_observer {
     use warp::Filter;
 
     use crate::event_dispatcher::{MinedBlockEvent, MinedMicroblockEvent};
+    use stacks::chainstate::stacks::db::blocks::BlockValidationTelemetry;
+    use stacks::monitoring::{BlockPropagationTimes, TenureTimes};
 
     pub const EVENT_OBSERVER_PORT: u16 = 50303;
 
@@ -152,6 +154,11 @@ pub mod test_observer {
         pub static ref MEMTXS: Mutex<Vec<String>> = Mutex::new(Vec::new());
         pub static ref MEMTXS_DROPPED: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
         pub static ref ATTACHMENTS: Mutex<Vec<serde_json::Value>> = Mutex::new(Vec::new());
+        pub static ref BLOCK_PROPAGATION: Mutex<Vec<BlockPropagationTimes>> =
+            Mutex::new(Vec::new());
+        pub static ref TENURE_TIMES: Mutex<Vec<TenureTimes>> = Mutex::new(Vec::new());
+        pub static ref BLOCK_TELEMETRY: Mutex<Vec<BlockValidationTelemetry>> =
+            Mutex::new(Vec::new());
     }
 
     async fn handle_burn_block(
@@ -221,6 +228,28 @@ pub mod test_observer {
         Ok(warp::http::StatusCode::OK)
     }
 
+    async fn handle_block_propagation(
+        times: serde_json::Value,
+    ) -> Result<impl warp::Reply, Infallible> {
+        let mut block_propagation = BLOCK_PROPAGATION.lock().unwrap();
+        block_propagation.push(serde_json::from_value(times).unwrap());
+        Ok(warp::http::StatusCode::OK)
+    }
+
+    async fn handle_tenure_times(times: serde_json::Value) -> Result<impl warp::Reply, Infallible> {
+        let mut tenure_times = TENURE_TIMES.lock().unwrap();
+        tenure_times.push(serde_json::from_value(times).unwrap());
+        Ok(warp::http::StatusCode::OK)
+    }
+
+    async fn handle_block_telemetry(
+        telemetry: serde_json::Value,
+    ) -> Result<impl warp::Reply, Infallible> {
+        let mut block_telemetry = BLOCK_TELEMETRY.lock().unwrap();
+        block_telemetry.push(serde_json::from_value(telemetry).unwrap());
+        Ok(warp::http::StatusCode::OK)
+    }
+
     async fn handle_mempool_txs(txs: serde_json::Value) -> Result<impl warp::Reply, Infallible> {
         let new_rawtxs = txs
             .as_array()
@@ -296,6 +325,18 @@ pub mod test_observer {
         MINED_MICROBLOCKS.lock().unwrap().clone()
     }
 
+    pub fn get_block_propagation() -> Vec<BlockPropagationTimes> {
+        BLOCK_PROPAGATION.lock().unwrap().clone()
+    }
+
+    pub fn get_tenure_times() -> Vec<TenureTimes> {
+        TENURE_TIMES.lock().unwrap().clone()
+    }
+
+    pub fn get_block_telemetry() -> Vec<BlockValidationTelemetry> {
+        BLOCK_TELEMETRY.lock().unwrap().clone()
+    }
+
     /// each path here should correspond to one of the paths listed in `event_dispatcher.rs`
     async fn serve() {
         let new_blocks = warp::path!("new_block")
@@ -330,6 +371,18 @@ pub mod test_observer {
             .and(warp::post())
             .and(warp::body::json())
             .and_then(handle_mined_microblock);
+        let block_propagation = warp::path!("block_propagation")
+            .and(warp::post())
+            .and(warp::body::json())
+            .and_then(handle_block_propagation);
+        let tenure_times = warp::path!("tenure_times")
+            .and(warp::post())
+            .and(warp::body::json())
+            .and_then(handle_tenure_times);
+        let block_telemetry = warp::path!("block_telemetry")
+            .and(warp::post())
+            .and(warp::body::json())
+            .and_then(handle_block_telemetry);
 
         info!("Spawning warp server");
         warp::serve(
@@ -340,7 +393,10 @@ pub mod test_observer {
                 .or(new_attachments)
                 .or(new_microblocks)
                 .or(mined_blocks)
-                .or(mined_microblocks),
+                .or(mined_microblocks)
+                .or(block_propagation)
+                .or(tenure_times)
+                .or(block_telemetry),
         )
         .run(([127, 0, 0, 1], EVENT_OBSERVER_PORT))
         .await
@@ -361,6 +417,9 @@ pub mod test_observer {
         MEMTXS.lock().unwrap().clear();
         MEMTXS_DROPPED.lock().unwrap().clear();
         MINED_BLOCKS.lock().unwrap().clear();
+        BLOCK_PROPAGATION.lock().unwrap().clear();
+        TENURE_TIMES.lock().unwrap().clear();
+        BLOCK_TELEMETRY.lock().unwrap().clear();
     }
 }
 
@@ -862,6 +921,7 @@ fn liquid_ustx_integration() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let spender_bal = 10_000_000_000 * (core::MICROSTACKS_PER_STACKS as u64);
@@ -990,6 +1050,7 @@ fn lockup_integration() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -1637,6 +1698,7 @@ fn microblock_integration_test() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -2414,6 +2476,7 @@ fn mining_transactions_is_fair() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -2736,6 +2799,7 @@ fn size_overflow_unconfirmed_microblocks_integration_test() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -2933,6 +2997,7 @@ fn size_overflow_unconfirmed_stream_microblocks_integration_test() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -3128,6 +3193,7 @@ fn size_overflow_unconfirmed_invalid_stream_microblocks_integration_test() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -3395,6 +3461,7 @@ fn runtime_overflow_unconfirmed_microblocks_integration_test() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -3568,6 +3635,7 @@ fn block_replay_integration_test() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -3692,6 +3760,7 @@ fn cost_voting_integration() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let spender_bal = 10_000_000_000 * (core::MICROSTACKS_PER_STACKS as u64);
@@ -4014,7 +4083,11 @@ fn mining_events_integration_test() {
             EventKeyType::AnyEvent,
             EventKeyType::MinedBlocks,
             EventKeyType::MinedMicroblocks,
+            EventKeyType::BlockPropagation,
+            EventKeyType::TenureTimes,
+            EventKeyType::BlockTelemetry,
         ],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -4064,6 +4137,46 @@ fn mining_events_integration_test() {
     let res = get_account(&http_origin, &addr_2);
     assert_eq!(res.nonce, 1);
 
+    // check block propagation records of our own blocks
+    let block_propagation = test_observer::get_block_propagation();
+    assert!(block_propagation.len() >= 1);
+    for times in block_propagation.iter() {
+        let received_time_ms = times.received_time_ms.unwrap();
+        let validated_time_ms = times.validated_time_ms.unwrap();
+        assert!(times.sortition_time_ms <= received_time_ms);
+        assert!(received_time_ms <= validated_time_ms);
+    }
+
+    // check the validation telemetry of every processed block
+    let block_telemetry = test_observer::get_block_telemetry();
+    assert!(block_telemetry.len() >= 1);
+    for telemetry in block_telemetry.iter() {
+        assert!(
+            telemetry.tx_processing_time_us + telemetry.marf_commit_time_us
+                <= telemetry.validation_time_us
+        );
+        assert!(telemetry.tx_count >= 1);
+        assert!(telemetry.sqlite_write_bytes > 0);
+    }
+    assert!(block_telemetry
+        .iter()
+        .any(|telemetry| telemetry.execution_cost.runtime > 0));
+
+    // check the timing of our own tenures
+    let tenure_times = test_observer::get_tenure_times();
+    assert!(tenure_times.len() >= 1);
+    assert!(tenure_times
+        .iter()
+        .any(|times| times.won_sortition == Some(true)));
+    for times in tenure_times.iter() {
+        let block_assembled_time_ms = times.block_assembled_time_ms.unwrap();
+        assert!(times.burn_block_time_ms <= block_assembled_time_ms);
+        if let Some(commit_broadcast_time_ms) = times.commit_broadcast_time_ms {
+            assert!(times.utxos_selected_time_ms.unwrap() <= commit_broadcast_time_ms);
+            assert!(commit_broadcast_time_ms <= times.sortition_time_ms.unwrap());
+        }
+    }
+
     // check mined microblock events
     let mined_microblock_events = test_observer::get_mined_microblocks();
     assert!(mined_microblock_events.len() >= 1);
@@ -4259,6 +4372,7 @@ fn block_limit_hit_integration_test() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -4486,6 +4600,7 @@ fn microblock_limit_hit_integration_test() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -4620,6 +4735,7 @@ fn block_large_tx_integration_test() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     conf.initial_balances.push(InitialBalance {
@@ -4754,6 +4870,7 @@ fn microblock_large_tx_integration_test_FLAKY() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     conf.initial_balances.push(InitialBalance {
@@ -4885,6 +5002,7 @@ fn pox_integration_test() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let first_bal = 6_000_000_000 * (core::MICROSTACKS_PER_STACKS as u64);
@@ -5412,6 +5530,7 @@ fn atlas_integration_test() {
         .push(EventObserverConfig {
             endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
             events_keys: vec![EventKeyType::AnyEvent],
+            ..EventObserverConfig::default()
         });
 
     // Our 2 nodes will share the bitcoind node
@@ -5939,6 +6058,7 @@ fn antientropy_integration_test() {
         .push(EventObserverConfig {
             endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
             events_keys: vec![EventKeyType::AnyEvent],
+            ..EventObserverConfig::default()
         });
 
     // Our 2 nodes will share the bitcoind node
@@ -6915,6 +7035,7 @@ fn fuzzed_median_fee_rate_estimation_test(window_size: u64, expected_final_value
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -7095,6 +7216,7 @@ fn use_latest_tip_integration_test() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());